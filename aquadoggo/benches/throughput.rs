@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Benchmarks publishing, ingesting and reducing operations of a synthetic schema.
+//!
+//! Runs against a temporary SQLite database by default. Set `DATABASE_URL` to benchmark against
+//! another database, for example PostgreSQL. Benchmarks write lots of data, use a dedicated
+//! database for them!
+use std::time::Duration;
+
+use aquadoggo::{BenchOptions, BenchSetup};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use tokio::runtime::Runtime;
+
+/// Returns a benchmark setup creating the given number of documents with a few updates each.
+async fn setup(documents: u64) -> BenchSetup {
+    let database_url = std::env::var("DATABASE_URL").ok();
+    let options = BenchOptions {
+        documents: documents as usize,
+        updates: 3,
+        ..BenchOptions::default()
+    };
+
+    BenchSetup::new(database_url.as_deref(), options)
+        .await
+        .expect("Could not set up benchmark")
+}
+
+fn throughput(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Could not start tokio runtime");
+
+    let mut group = c.benchmark_group("throughput");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(10));
+    group.throughput(Throughput::Elements(1));
+
+    // Every iteration creates and updates one document
+    group.bench_function("publish", |b| {
+        b.to_async(&runtime).iter_custom(|iters| async move {
+            let (result, _) = setup(iters).await.publish().await.unwrap();
+            result.total()
+        })
+    });
+
+    group.bench_function("ingest", |b| {
+        b.to_async(&runtime).iter_custom(|iters| async move {
+            let (result, _) = setup(iters).await.ingest().await.unwrap();
+            result.total()
+        })
+    });
+
+    // Every iteration reduces one document
+    group.bench_function("reduce", |b| {
+        b.to_async(&runtime).iter_custom(|iters| async move {
+            let setup = setup(iters).await;
+            let (_, document_ids) = setup.publish().await.unwrap();
+            let result = setup.reduce(&document_ids).await.unwrap();
+            result.total()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS document_activity (
+    document_id             TEXT            NOT NULL,
+    updated_at              BIGINT          NOT NULL,
+    PRIMARY KEY (document_id)
+);
+
+CREATE INDEX idx_document_activity ON document_activity (updated_at);
+
+-- We don't know when existing documents were active for the last time, they are considered
+-- inactive and get loaded back from the archive as soon as they are accessed again
+INSERT INTO document_activity (document_id, updated_at)
+    SELECT DISTINCT document_id, 0 FROM operations_v1;
+
+CREATE TABLE IF NOT EXISTS archived_documents (
+    document_id             TEXT            NOT NULL,
+    PRIMARY KEY (document_id)
+);
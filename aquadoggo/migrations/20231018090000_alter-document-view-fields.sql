@@ -0,0 +1,14 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+-- Remove duplicate rows which could occur when the same document view was inserted twice
+CREATE TABLE document_view_fields_deduplicated AS
+    SELECT DISTINCT document_view_id, operation_id, name FROM document_view_fields;
+
+DELETE FROM document_view_fields;
+
+INSERT INTO document_view_fields (document_view_id, operation_id, name)
+    SELECT document_view_id, operation_id, name FROM document_view_fields_deduplicated;
+
+DROP TABLE document_view_fields_deduplicated;
+
+CREATE UNIQUE INDEX ux_document_view_fields ON document_view_fields (document_view_id, name);
@@ -0,0 +1,10 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS bootstrap_peers (
+    address                 TEXT            NOT NULL,
+    peer_id                 TEXT            NOT NULL,
+    score                   BIGINT          NOT NULL,
+    PRIMARY KEY (address)
+);
+
+CREATE INDEX idx_bootstrap_peers ON bootstrap_peers (score);
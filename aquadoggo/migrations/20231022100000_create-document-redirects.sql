@@ -0,0 +1,9 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS document_redirects (
+    document_id             TEXT            NOT NULL,
+    canonical_document_id   TEXT            NOT NULL,
+    PRIMARY KEY (document_id)
+);
+
+CREATE INDEX idx_document_redirects ON document_redirects (canonical_document_id);
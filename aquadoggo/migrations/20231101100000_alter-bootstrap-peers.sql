@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+ALTER TABLE bootstrap_peers ADD COLUMN last_seen BIGINT NULL;
+ALTER TABLE bootstrap_peers ADD COLUMN successes BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE bootstrap_peers ADD COLUMN failures BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS document_annotations (
+    document_id     TEXT            NOT NULL,
+    key             TEXT            NOT NULL,
+    value           TEXT            NOT NULL,
+    PRIMARY KEY (document_id, key)
+);
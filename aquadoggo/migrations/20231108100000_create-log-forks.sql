@@ -0,0 +1,11 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS log_forks (
+    public_key              TEXT            NOT NULL,
+    log_id                  TEXT            NOT NULL,
+    seq_num                 TEXT            NOT NULL,
+    entry_hash              TEXT            NOT NULL,
+    conflicting_entry_hash  TEXT            NOT NULL,
+    detected_at             BIGINT          NOT NULL,
+    PRIMARY KEY (public_key, log_id, seq_num, conflicting_entry_hash)
+);
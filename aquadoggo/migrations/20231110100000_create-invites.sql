@@ -0,0 +1,11 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS invites (
+    code                    TEXT            NOT NULL,
+    schema_ids              TEXT            NOT NULL,
+    expires_at              BIGINT          NOT NULL,
+    public_key              TEXT            NULL,
+    PRIMARY KEY (code)
+);
+
+CREATE INDEX idx_invites_public_key ON invites (public_key);
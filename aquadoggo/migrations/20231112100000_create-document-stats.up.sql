@@ -0,0 +1,7 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS document_stats (
+    document_id     TEXT            NOT NULL PRIMARY KEY,
+    update_count    BIGINT          NOT NULL,
+    author_count    BIGINT          NOT NULL
+);
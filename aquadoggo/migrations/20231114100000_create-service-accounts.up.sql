@@ -0,0 +1,7 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS service_accounts (
+    name                    TEXT            NOT NULL,
+    private_key             TEXT            NOT NULL,
+    PRIMARY KEY (name)
+);
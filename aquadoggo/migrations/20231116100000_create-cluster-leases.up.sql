@@ -0,0 +1,8 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+CREATE TABLE IF NOT EXISTS cluster_leases (
+    name                    TEXT            NOT NULL,
+    instance                TEXT            NOT NULL,
+    expires_at              BIGINT          NOT NULL,
+    PRIMARY KEY (name)
+);
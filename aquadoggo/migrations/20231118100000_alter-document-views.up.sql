@@ -0,0 +1,4 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+-- Time a document view was last read, least recently used views get evicted first
+ALTER TABLE document_views ADD COLUMN accessed_at BIGINT NOT NULL DEFAULT 0;
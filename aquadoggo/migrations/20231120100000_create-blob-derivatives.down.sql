@@ -0,0 +1,4 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+DROP INDEX IF EXISTS idx_blob_derivatives_document_id;
+DROP TABLE IF EXISTS blob_derivatives;
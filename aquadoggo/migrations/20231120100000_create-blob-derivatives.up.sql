@@ -0,0 +1,13 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+-- Blobs derived from materialized blob views by transcoders of this node
+CREATE TABLE IF NOT EXISTS blob_derivatives (
+    document_id             TEXT            NOT NULL,
+    view_id                 TEXT            NOT NULL,
+    name                    TEXT            NOT NULL,
+    mime_type               TEXT            NOT NULL,
+    length                  BIGINT          NOT NULL,
+    PRIMARY KEY (view_id, name)
+);
+
+CREATE INDEX idx_blob_derivatives_document_id ON blob_derivatives (document_id);
@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+DROP INDEX IF EXISTS idx_document_history_previous_view_id;
+DROP INDEX IF EXISTS idx_document_history_changed_at;
+DROP TABLE IF EXISTS document_history;
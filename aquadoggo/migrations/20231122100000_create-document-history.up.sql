@@ -0,0 +1,15 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+-- Previous state of documents before they got created, updated or deleted, recorded to query
+-- collections as they were at an earlier point in time
+CREATE TABLE IF NOT EXISTS document_history (
+    document_id             TEXT            NOT NULL,
+    previous_view_id        TEXT            NULL,
+    previous_is_deleted     BOOLEAN         NULL,
+    schema_id               TEXT            NOT NULL,
+    changed_at              BIGINT          NOT NULL,
+    PRIMARY KEY (document_id, changed_at)
+);
+
+CREATE INDEX idx_document_history_changed_at ON document_history (changed_at);
+CREATE INDEX idx_document_history_previous_view_id ON document_history (previous_view_id);
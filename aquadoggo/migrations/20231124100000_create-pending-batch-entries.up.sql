@@ -0,0 +1,13 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+-- Entries of batches which are not completely published yet, they get removed again when
+-- publishing the batch fails half-way or the node stopped before it completed
+CREATE TABLE IF NOT EXISTS pending_batch_entries (
+    batch_id                TEXT            NOT NULL,
+    entry_hash              TEXT            NOT NULL,
+    public_key              TEXT            NOT NULL,
+    -- Store u64 integer as text
+    log_id                  TEXT            NOT NULL,
+    is_first_entry          BOOLEAN         NOT NULL,
+    PRIMARY KEY (batch_id, entry_hash)
+);
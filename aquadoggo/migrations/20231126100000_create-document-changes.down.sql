@@ -0,0 +1,5 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+DROP TABLE IF EXISTS document_change_sequence;
+DROP INDEX IF EXISTS idx_document_changes_document_id;
+DROP TABLE IF EXISTS document_changes;
@@ -0,0 +1,22 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+-- Ordered feed of created, updated and deleted documents, external indexers consume it by
+-- remembering the sequence number of the last change they processed
+CREATE TABLE IF NOT EXISTS document_changes (
+    sequence                BIGINT          NOT NULL PRIMARY KEY,
+    document_id             TEXT            NOT NULL,
+    document_view_id        TEXT            NOT NULL,
+    schema_id               TEXT            NOT NULL,
+    change_type             TEXT            NOT NULL,
+    changed_at              BIGINT          NOT NULL
+);
+
+CREATE INDEX idx_document_changes_document_id ON document_changes (document_id);
+
+-- Last sequence number handed out to a document change. Transactions recording a change lock
+-- this row until they commit, changes become visible in the order of their sequence numbers
+CREATE TABLE IF NOT EXISTS document_change_sequence (
+    sequence                BIGINT          NOT NULL
+);
+
+INSERT INTO document_change_sequence (sequence) VALUES (0);
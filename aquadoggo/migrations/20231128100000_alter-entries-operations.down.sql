@@ -0,0 +1,4 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+ALTER TABLE operations_v1 DROP COLUMN inserted_at;
+ALTER TABLE entries DROP COLUMN inserted_at;
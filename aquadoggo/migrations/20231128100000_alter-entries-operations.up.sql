@@ -0,0 +1,6 @@
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+
+-- Time entries and operations were inserted, the vacuum sweep leaves recently inserted rows alone
+-- as they might belong to an ingest which is still in progress
+ALTER TABLE entries ADD COLUMN inserted_at BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE operations_v1 ADD COLUMN inserted_at BIGINT NOT NULL DEFAULT 0;
@@ -1,20 +1,61 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::time::Duration;
+
 use anyhow::{bail, Result};
+use libp2p::PeerId;
+use log::warn;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::LogId;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::SchemaId;
 use tokio::sync::mpsc::Receiver;
 
-use crate::api::{migrate, LockFile};
+use crate::api::{
+    check_relations, export_document, import, migrate, peer_profiles, publish_node_profile,
+    purge_document, DocumentBundle, DocumentFilter, ImportCommit, ImportReport, LockFile,
+    PeerProfile, PurgeReport, RelationReport,
+};
 use crate::bus::{ServiceMessage, ServiceSender};
+use crate::capabilities::Invite;
 use crate::context::Context;
+use crate::materializer::{Task, TaskInput};
+use crate::network::{ConnectionDirection, ConnectionInfo, ConnectionTicket};
+use crate::startup::StartupPhase;
+use crate::vacuum::{vacuum, VacuumReport};
 
-/// Node events which can be interesting for clients, for example when peers connect or disconnect.
+/// Node events which can be interesting for clients, for example when peers connect or disconnect
+/// or when data received from other peers finished materializing.
 #[derive(Debug, Clone)]
 pub enum NodeEvent {
-    /// A peer connected to our node. This can be a direct or relayed connection.
-    PeerConnected,
+    /// A peer connected to our node. This can be a direct or relayed connection, a peer can hold
+    /// more than one connection with us.
+    PeerConnected(ConnectionInfo),
+
+    /// A connection with a peer was closed.
+    PeerDisconnected(ConnectionInfo),
+
+    /// A replication session over these schema ids was established with a peer. The direction is
+    /// outbound when we requested the session.
+    SessionEstablished(PeerId, ConnectionDirection, Vec<SchemaId>),
+
+    /// All data received from other peers got materialized, the node is up to date.
+    SyncComplete,
+
+    /// Conflicting entries of the same public key were detected in a log, the key pair is
+    /// probably used on more than one device. The log can not be extended on this node anymore.
+    LogForked(PublicKey, LogId),
+
+    /// A document got materialized into a new latest view.
+    DocumentUpdated(DocumentId, DocumentViewId),
 
-    /// A peer disconnected from our node.
-    PeerDisconnected,
+    /// The GraphQL API changed after new schemas got materialized, contains the new version of
+    /// the GraphQL schema. The SDL can be queried with `graphqlSchema`.
+    GraphQLSchemaChanged(u64),
+
+    /// A phase of starting the node completed after the given duration. These events are only
+    /// reported via `Readiness::subscribe`, the node is ready after the `Services` phase completed.
+    StartupPhaseCompleted(StartupPhase, Duration),
 }
 
 /// Interface to interact with the node in a programmatic, "low-level" way.
@@ -54,6 +95,137 @@ impl NodeInterface {
         Ok(did_migration_happen)
     }
 
+    pub async fn import(&self, commits: Vec<ImportCommit>) -> Result<ImportReport> {
+        let report = import(&self.context.store, &self.context.schema_provider, commits).await?;
+
+        // Send imported operations on service communication bus, this will arrive eventually at
+        // the materializer service
+        for operation_id in &report.imported {
+            if self
+                .tx
+                .send(ServiceMessage::NewOperation(operation_id.to_owned()))
+                .is_err()
+            {
+                bail!("Failed to inform materialization service about import");
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub async fn vacuum(&self) -> Result<VacuumReport> {
+        let report = vacuum(&self.context.store, &self.context.schema_provider).await?;
+
+        // Send restored operations on service communication bus, this will arrive eventually at
+        // the materializer service
+        for operation_id in &report.relinked_operations {
+            if self
+                .tx
+                .send(ServiceMessage::NewOperation(operation_id.to_owned()))
+                .is_err()
+            {
+                bail!("Failed to inform materialization service about restored operations");
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub async fn check_relations(&self) -> Result<RelationReport> {
+        check_relations(&self.context.store, &self.context.schema_provider).await
+    }
+
+    pub async fn export_document(&self, document_id: &DocumentId) -> Result<DocumentBundle> {
+        export_document(&self.context.store, &self.context.key_pair, document_id).await
+    }
+
+    pub async fn purge_document(
+        &self,
+        document_id: &DocumentId,
+        include_blobs: bool,
+    ) -> Result<PurgeReport> {
+        let report = purge_document(
+            &self.context.store,
+            &self.context.blob_store,
+            document_id,
+            include_blobs,
+        )
+        .await?;
+
+        // Garbage collect views of related documents which are not pinned anymore, this will
+        // arrive eventually at the materializer service
+        for affected_id in &report.affected_documents {
+            let task = Task::new(
+                "garbage_collection",
+                TaskInput::DocumentId(affected_id.to_owned()),
+            );
+            if self.tx.send(ServiceMessage::ScheduleTask(task)).is_err() {
+                bail!("Failed to inform materialization service about affected documents");
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub async fn create_invite(
+        &self,
+        schema_ids: Vec<SchemaId>,
+        valid_for: Duration,
+    ) -> Result<Invite> {
+        Ok(Invite::create(&self.context.store, schema_ids, valid_for).await?)
+    }
+
+    pub async fn publish_node_profile(&self) -> Result<bool> {
+        let schema_id = match &self.context.config.node_profile_schema_id {
+            Some(schema_id) => schema_id,
+            None => return Ok(false),
+        };
+
+        let operation_id = publish_node_profile(
+            &self.context.store,
+            &self.context.schema_provider,
+            &self.context.key_pair,
+            schema_id,
+            &self.context.config.node_profile,
+        )
+        .await?;
+
+        match operation_id {
+            Some(operation_id) => {
+                if self
+                    .tx
+                    .send(ServiceMessage::NewOperation(operation_id))
+                    .is_err()
+                {
+                    bail!("Failed to inform materialization service about node profile");
+                }
+
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub async fn peer_profiles(&self) -> Result<Vec<PeerProfile>> {
+        match &self.context.config.node_profile_schema_id {
+            Some(schema_id) => {
+                peer_profiles(
+                    &self.context.store,
+                    schema_id,
+                    &self.context.key_pair.public_key(),
+                )
+                .await
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    pub fn connection_ticket(&self) -> Option<ConnectionTicket> {
+        self.context
+            .local_addresses
+            .ticket(&self.context.config.network)
+    }
+
     pub async fn subscribe(&self) -> Receiver<NodeEvent> {
         let mut rx = self.tx.subscribe();
         let (events_tx, events_rx) = tokio::sync::mpsc::channel::<NodeEvent>(256);
@@ -61,11 +233,39 @@ impl NodeInterface {
         tokio::task::spawn(async move {
             loop {
                 match rx.recv().await {
-                    Ok(ServiceMessage::PeerConnected(_)) => {
-                        let _ = events_tx.send(NodeEvent::PeerConnected).await;
+                    Ok(ServiceMessage::PeerConnected(_, info)) => {
+                        let _ = events_tx.send(NodeEvent::PeerConnected(info)).await;
+                    }
+                    Ok(ServiceMessage::PeerDisconnected(_, info)) => {
+                        let _ = events_tx.send(NodeEvent::PeerDisconnected(info)).await;
+                    }
+                    Ok(ServiceMessage::SessionEstablished(peer, direction, target_set)) => {
+                        let schema_ids = target_set.iter().cloned().collect();
+                        let _ = events_tx
+                            .send(NodeEvent::SessionEstablished(
+                                peer.id(),
+                                direction,
+                                schema_ids,
+                            ))
+                            .await;
                     }
-                    Ok(ServiceMessage::PeerDisconnected(_)) => {
-                        let _ = events_tx.send(NodeEvent::PeerDisconnected).await;
+                    Ok(ServiceMessage::SyncComplete) => {
+                        let _ = events_tx.send(NodeEvent::SyncComplete).await;
+                    }
+                    Ok(ServiceMessage::LogForked(public_key, log_id)) => {
+                        let _ = events_tx
+                            .send(NodeEvent::LogForked(public_key, log_id))
+                            .await;
+                    }
+                    Ok(ServiceMessage::DocumentUpdated(_, document_id, view_id)) => {
+                        let _ = events_tx
+                            .send(NodeEvent::DocumentUpdated(document_id, view_id))
+                            .await;
+                    }
+                    Ok(ServiceMessage::GraphQLSchemaChanged(version)) => {
+                        let _ = events_tx
+                            .send(NodeEvent::GraphQLSchemaChanged(version))
+                            .await;
                     }
                     Ok(_) => continue,
                     Err(_) => break,
@@ -75,4 +275,53 @@ impl NodeInterface {
 
         events_rx
     }
+
+    pub async fn subscribe_documents(
+        &self,
+        schema_id: &SchemaId,
+        filter: DocumentFilter,
+    ) -> Result<Receiver<NodeEvent>> {
+        let schema = match self.context.schema_provider.get(schema_id).await {
+            Some(schema) => schema,
+            None => bail!("Schema {} is not supported by this node", schema_id),
+        };
+        filter.validate(&schema)?;
+
+        let store = self.context.store.clone();
+        let mut rx = self.tx.subscribe();
+        let (events_tx, events_rx) = tokio::sync::mpsc::channel::<NodeEvent>(256);
+
+        tokio::task::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(ServiceMessage::DocumentUpdated(
+                        updated_schema_id,
+                        document_id,
+                        view_id,
+                    )) if &updated_schema_id == schema.id() => {
+                        // Evaluate the filter against the updated document before sending
+                        // anything to the subscriber
+                        let is_match = filter.matches(&store, &schema, &document_id).await;
+
+                        match is_match {
+                            Ok(true) => {
+                                let event = NodeEvent::DocumentUpdated(document_id, view_id);
+                                if events_tx.send(event).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Ok(false) => continue,
+                            Err(err) => {
+                                warn!("Failed evaluating subscription filter: {}", err);
+                            }
+                        }
+                    }
+                    Ok(_) => continue,
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(events_rx)
+    }
 }
@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use anyhow::{anyhow, bail, Context as AnyhowContext, Result};
+use ed25519_dalek::Signature;
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
+use p2panda_rs::entry::validate::validate_payload;
+use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::Actionable;
+use p2panda_rs::operation::{OperationAction, OperationId};
+use p2panda_rs::storage_provider::traits::{DocumentStore, EntryStore, OperationStore};
+use p2panda_rs::WithId;
+use serde::{Deserialize, Serialize};
+
+use crate::api::ImportCommit;
+use crate::config::Configuration;
+use crate::db::{connection_pool, SqlStore};
+
+/// Version of the document bundle format.
+const BUNDLE_VERSION: u64 = 1;
+
+/// Document with all its entries and operations, signed by the exporting node.
+///
+/// Bundles allow sharing a document with a third party which can verify it offline, for example
+/// credentials or receipts. Every entry is signed by the author of its operation, the bundle
+/// itself is signed by the node which exported it, vouching for the included view of the document.
+///
+/// Bundles are encoded as CBOR. The node signs the message `bundle/<hash>`, where the hash is
+/// taken from the CBOR encoding of `[version, document_id, view_id, commits]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentBundle {
+    /// Version of the bundle format.
+    pub version: u64,
+
+    /// Id of the exported document.
+    pub document_id: DocumentId,
+
+    /// View of the document at the time of the export.
+    pub view_id: DocumentViewId,
+
+    /// Encoded entries and operations of the document, ordered by author, log and sequence number.
+    pub commits: Vec<ImportCommit>,
+
+    /// Public key of the node which exported the bundle.
+    pub public_key: PublicKey,
+
+    /// Signature of the exporting node.
+    #[serde(with = "serde_bytes")]
+    pub signature: Vec<u8>,
+}
+
+impl DocumentBundle {
+    /// Create a bundle and sign it with the given key pair.
+    pub fn new(
+        document_id: DocumentId,
+        view_id: DocumentViewId,
+        commits: Vec<ImportCommit>,
+        key_pair: &KeyPair,
+    ) -> Result<Self> {
+        let message = bundle_message(BUNDLE_VERSION, &document_id, &view_id, &commits)?;
+        let signature = key_pair.sign(&message).to_bytes().to_vec();
+
+        Ok(Self {
+            version: BUNDLE_VERSION,
+            document_id,
+            view_id,
+            commits,
+            public_key: key_pair.public_key(),
+            signature,
+        })
+    }
+
+    /// Decode a bundle from its CBOR encoding.
+    ///
+    /// This does not verify the bundle, use `verify` for it.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ciborium::de::from_reader(bytes)
+            .map_err(|err| anyhow!("Invalid encoding of document bundle: {}", err))
+    }
+
+    /// Returns the CBOR encoding of the bundle.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(self, &mut bytes).expect("Encode document bundle");
+        bytes
+    }
+
+    /// Verify the signature of the bundle and the integrity of all contained entries and
+    /// operations.
+    ///
+    /// Checks that every entry is correctly signed and matches its operation, that all operations
+    /// belong to the exported document and that the included view can be built from them. This
+    /// does not require access to a node, the schema of the document is not checked.
+    pub fn verify(&self) -> Result<()> {
+        if self.version != BUNDLE_VERSION {
+            bail!("Unsupported document bundle version {}", self.version);
+        }
+
+        let message = bundle_message(
+            self.version,
+            &self.document_id,
+            &self.view_id,
+            &self.commits,
+        )?;
+        let signature = Signature::try_from(self.signature.as_slice())
+            .map_err(|_| anyhow!("Invalid signature of document bundle"))?;
+        KeyPair::verify(&self.public_key, &message, &signature)
+            .map_err(|_| anyhow!("Invalid signature of document bundle"))?;
+
+        let mut operation_ids = HashSet::new();
+        let mut previous_ids = Vec::new();
+
+        for (encoded_entry, encoded_operation) in &self.commits {
+            let entry = decode_entry(encoded_entry)
+                .with_context(|| format!("Invalid entry {}", encoded_entry.hash()))?;
+            validate_payload(&entry, encoded_operation)
+                .with_context(|| format!("Invalid operation of entry {}", encoded_entry.hash()))?;
+
+            let operation = decode_operation(encoded_operation)
+                .with_context(|| format!("Invalid operation of entry {}", encoded_entry.hash()))?;
+            let operation_id: OperationId = encoded_entry.hash().into();
+
+            match operation.action() {
+                OperationAction::Create => {
+                    if operation_id.as_str() != self.document_id.as_str() {
+                        bail!("CREATE operation {} of another document", operation_id);
+                    }
+                }
+                _ => {
+                    let previous = operation
+                        .previous()
+                        .ok_or_else(|| anyhow!("Missing previous of operation {}", operation_id))?;
+                    previous_ids.extend(previous.iter().cloned());
+                }
+            }
+
+            operation_ids.insert(operation_id);
+        }
+
+        let create_id: OperationId = self.document_id.as_str().parse()?;
+        if !operation_ids.contains(&create_id) {
+            bail!("Missing CREATE operation of document {}", self.document_id);
+        }
+
+        // All operations need to be connected to the document's operation graph
+        for operation_id in previous_ids.iter().chain(self.view_id.iter()) {
+            if !operation_ids.contains(operation_id) {
+                bail!("Missing operation {} of document", operation_id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the bytes signed by the node exporting a document bundle.
+fn bundle_message(
+    version: u64,
+    document_id: &DocumentId,
+    view_id: &DocumentViewId,
+    commits: &[ImportCommit],
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(&(version, document_id, view_id, commits), &mut bytes)
+        .map_err(|err| anyhow!("Could not encode document bundle: {}", err))?;
+    Ok(format!("bundle/{}", Hash::new_from_bytes(&bytes)).into_bytes())
+}
+
+/// Export all entries and operations of a materialized document as a bundle signed by the given
+/// key pair.
+pub async fn export_document(
+    store: &SqlStore,
+    key_pair: &KeyPair,
+    document_id: &DocumentId,
+) -> Result<DocumentBundle> {
+    let document = store
+        .get_document(document_id)
+        .await
+        .context("Internal database error occurred while retrieving document")?
+        .ok_or_else(|| anyhow!("Document {} not found", document_id))?;
+
+    let operations = store
+        .get_operations_by_document_id(document_id)
+        .await
+        .context("Internal database error occurred while retrieving operations")?;
+
+    let mut entries = Vec::new();
+    for operation in operations {
+        let operation_id: &OperationId = operation.id();
+        let entry = store
+            .get_entry(&operation_id.to_owned().into())
+            .await
+            .context("Internal database error occurred while retrieving entry")?
+            .ok_or_else(|| anyhow!("Entry of operation {} not found", operation_id))?;
+        entries.push(entry);
+    }
+
+    entries.sort_by_cached_key(|entry| {
+        (
+            entry.public_key().to_string(),
+            entry.log_id().as_u64(),
+            entry.seq_num().as_u64(),
+        )
+    });
+
+    let commits = entries
+        .into_iter()
+        .map(|entry| {
+            let encoded_operation = entry
+                .payload()
+                .ok_or_else(|| anyhow!("Operation of entry {} not found", entry.hash()))?
+                .to_owned();
+            Ok((
+                EncodedEntry::from_bytes(&entry.into_bytes()),
+                encoded_operation,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    DocumentBundle::new(
+        document_id.to_owned(),
+        document.view_id().to_owned(),
+        commits,
+        key_pair,
+    )
+}
+
+/// Export a document from the node's database without starting the node.
+pub async fn export_document_bundle(
+    config: &Configuration,
+    key_pair: &KeyPair,
+    document_id: &DocumentId,
+) -> Result<DocumentBundle> {
+    let pool = connection_pool(&config.database_url, 1, config.database_key.as_deref()).await?;
+    let bundle = export_document(&SqlStore::new(pool.clone()), key_pair, document_id).await;
+    pool.close().await;
+
+    bundle
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::api::{decode_commits, import};
+    use crate::test_utils::{
+        add_document, add_schema, test_runner_with_manager, update_document, TestNodeManager,
+    };
+
+    use super::{export_document, DocumentBundle};
+
+    #[rstest]
+    fn export_and_import_bundle(key_pair: KeyPair) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let mut source = manager.create().await;
+            let target = manager.create().await;
+
+            let schema = add_schema(
+                &mut source,
+                "receipt",
+                vec![("amount", FieldType::Integer)],
+                &key_pair,
+            )
+            .await;
+            let create_view_id = add_document(
+                &mut source,
+                schema.id(),
+                vec![("amount", 5.into())],
+                &key_pair,
+            )
+            .await;
+            let document_id = create_view_id.to_string().parse().unwrap();
+            let view_id = update_document(
+                &mut source,
+                schema.id(),
+                vec![("amount", 10.into())],
+                &create_view_id,
+                &key_pair,
+            )
+            .await;
+
+            let node_key_pair = KeyPair::new();
+            let bundle = export_document(&source.context.store, &node_key_pair, &document_id)
+                .await
+                .unwrap();
+            assert_eq!(bundle.view_id, view_id);
+            assert_eq!(bundle.commits.len(), 2);
+            assert_eq!(bundle.public_key, node_key_pair.public_key());
+
+            // Bundle can be verified after decoding it
+            let bytes = bundle.to_bytes();
+            let decoded = DocumentBundle::from_bytes(&bytes).unwrap();
+            assert!(decoded.verify().is_ok());
+
+            // Tampering with the bundle is detected
+            let mut tampered = decoded.clone();
+            tampered.commits.pop();
+            assert!(tampered.verify().is_err());
+
+            let mut tampered = decoded.clone();
+            tampered.public_key = KeyPair::new().public_key();
+            assert!(tampered.verify().is_err());
+
+            // Bundles can be imported like a stream of entries and operations
+            target.context.schema_provider.update(schema).await.unwrap();
+            let commits = decode_commits(&bytes).unwrap();
+            let report = import(
+                &target.context.store,
+                &target.context.schema_provider,
+                commits,
+            )
+            .await
+            .unwrap();
+            assert_eq!(report.imported.len(), 2);
+            assert!(report.failed.is_empty());
+
+            // Invalid bundles are rejected
+            assert!(decode_commits(&tampered.to_bytes()).is_err());
+        });
+    }
+}
@@ -1,17 +1,33 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::OnceLock;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
+use libp2p::rendezvous::Namespace;
 use libp2p::{pnet::PreSharedKey, PeerId};
+use p2panda_rs::identity::{KeyPair, PublicKey};
 use p2panda_rs::schema::SchemaId;
+use regex::Regex;
+use rustls_acme::acme::LETS_ENCRYPT_PRODUCTION_DIRECTORY;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use tempfile::TempDir;
 
-use crate::{AllowList, Configuration, NetworkConfiguration, Transport};
+use crate::blobs::{essence, OUTPUT_PLACEHOLDER};
+use crate::config::{memory_database_url, temporary_blobs_base_path};
+use crate::materializer::DocumentChange;
+use crate::replication::{SUPPORTED_COMPRESSIONS, SUPPORTED_MODES};
+use crate::schema::MAX_DECIMAL_SCALE;
+use crate::{
+    AllowList, BlobTranscoder, Compression, Configuration, ConnectionTicket, DateTimeField,
+    DecimalField, Direction, DirectionPreference, FieldConstraint, IpVersion, IsolationLevel,
+    MetricsTarget, MimeTypeMismatch, Mode, ModePreference, NetworkConfiguration, NetworkSimulation,
+    NodeProfile, PrefetchProfile, RelationPath, SchemaPin, SchemaSettings, SchemaVersionPolicy,
+    ServiceAccount, SettingError, SettingValue, Transport, Webhook,
+};
 
 const WILDCARD: &str = "*";
 
@@ -19,15 +35,59 @@ const DEFAULT_LOG_LEVEL: &str = "off";
 
 const DEFAULT_MAX_DATABASE_CONNECTIONS: u32 = 32;
 
+const DEFAULT_DATABASE_MAX_RETRIES: u32 = 3;
+
+const DEFAULT_ARCHIVE_THRESHOLD: u64 = 60 * 60 * 24 * 30;
+
 const DEFAULT_HTTP_PORT: u16 = 2020;
 
+const DEFAULT_HTTPS_PORT: u16 = 443;
+
+const DEFAULT_HTTP_RATE_LIMIT: u32 = 1200;
+
+const DEFAULT_HTTP_MAX_CONCURRENT_REQUESTS: usize = 256;
+
+const DEFAULT_GRAPHQL_MAX_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+const DEFAULT_GRAPHQL_MAX_RELATION_DEPTH: usize = 8;
+
+const DEFAULT_GRAPHQL_MAX_RELATED_DOCUMENTS: usize = 10_000;
+
+const DEFAULT_BLOBS_MAX_BODY_SIZE: usize = 16 * 1024;
+
 const DEFAULT_NODE_PORT: u16 = 2022;
 
 const DEFAULT_WORKER_POOL_SIZE: u32 = 16;
 
+const DEFAULT_DEPENDENCY_FAN_OUT: usize = 256;
+
+const DEFAULT_TASK_DEDUP_WINDOW: u64 = 50;
+
 const DEFAULT_MDNS: bool = true;
 
-static TMP_DIR: OnceLock<TempDir> = OnceLock::new();
+const DEFAULT_BOOTSTRAP_TARGET_CONNECTIONS: usize = 8;
+
+const DEFAULT_BOOTSTRAP_PEER_EXPIRY: u64 = 60 * 60 * 24 * 30;
+
+const DEFAULT_METRICS_PUSH_INTERVAL: u64 = 60;
+
+const DEFAULT_CLUSTER_LEASE_DURATION: u64 = 15;
+
+const DEFAULT_IDEMPOTENCY_WINDOW: u64 = 60 * 5;
+
+const DEFAULT_DOCUMENT_VIEW_CACHE_SIZE: usize = 1000;
+
+const DEFAULT_LOG_STATE_CACHE_SIZE: usize = 1000;
+
+const DEFAULT_RENDEZVOUS_MIN_TTL: u64 = 60 * 60 * 2;
+
+const DEFAULT_RENDEZVOUS_MAX_TTL: u64 = 60 * 60 * 72;
+
+const DEFAULT_RENDEZVOUS_MAX_REGISTRATIONS: usize = 1024;
+
+fn default_schema_version_policy() -> String {
+    SchemaVersionPolicy::default().as_str().to_string()
+}
 
 fn default_log_level() -> String {
     DEFAULT_LOG_LEVEL.to_string()
@@ -37,37 +97,157 @@ fn default_max_database_connections() -> u32 {
     DEFAULT_MAX_DATABASE_CONNECTIONS
 }
 
+fn default_database_isolation_level() -> String {
+    IsolationLevel::default().as_str().to_string()
+}
+
+fn default_blob_mime_type_mismatch() -> String {
+    MimeTypeMismatch::default().as_str().to_string()
+}
+
+fn default_database_max_retries() -> u32 {
+    DEFAULT_DATABASE_MAX_RETRIES
+}
+
+fn default_archive_threshold() -> u64 {
+    DEFAULT_ARCHIVE_THRESHOLD
+}
+
 fn default_http_port() -> u16 {
     DEFAULT_HTTP_PORT
 }
 
+fn default_http_bind_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+}
+
+fn default_https_port() -> u16 {
+    DEFAULT_HTTPS_PORT
+}
+
+fn default_http_rate_limit() -> u32 {
+    DEFAULT_HTTP_RATE_LIMIT
+}
+
+fn default_http_max_concurrent_requests() -> usize {
+    DEFAULT_HTTP_MAX_CONCURRENT_REQUESTS
+}
+
+fn default_graphql_max_body_size() -> usize {
+    DEFAULT_GRAPHQL_MAX_BODY_SIZE
+}
+
+fn default_graphql_max_relation_depth() -> usize {
+    DEFAULT_GRAPHQL_MAX_RELATION_DEPTH
+}
+
+fn default_graphql_max_related_documents() -> usize {
+    DEFAULT_GRAPHQL_MAX_RELATED_DOCUMENTS
+}
+
+fn default_graphql_error_details() -> bool {
+    cfg!(debug_assertions)
+}
+
+fn default_graphql_snapshot_ttl() -> u64 {
+    0
+}
+
+fn default_graphql_ws_max_subscriptions() -> usize {
+    32
+}
+
+fn default_graphql_ws_idle_timeout() -> u64 {
+    300
+}
+
+fn default_blob_transcoder_timeout() -> u64 {
+    300
+}
+
+fn default_blobs_max_body_size() -> usize {
+    DEFAULT_BLOBS_MAX_BODY_SIZE
+}
+
+fn default_acme_directory_url() -> String {
+    LETS_ENCRYPT_PRODUCTION_DIRECTORY.to_string()
+}
+
 fn default_node_port() -> u16 {
     DEFAULT_NODE_PORT
 }
 
 fn default_database_url() -> String {
-    // Give each in-memory SQLite database an unique name as we're observing funny issues with
-    // SQLite sharing data between processes (!) and breaking each others databases
-    // potentially.
-    //
-    // See related issue: https://github.com/p2panda/aquadoggo/issues/568
-    let db_name = format!("dbmem{}", rand::random::<u32>());
-
-    // Set "mode=memory" to enable SQLite running in-memory and set "cache=shared", as
-    // setting it to "private" would break sqlx / SQLite.
-    //
-    // See related issue: https://github.com/launchbadge/sqlx/issues/2510
-    format!("sqlite://file:{db_name}?mode=memory&cache=shared")
+    memory_database_url()
 }
 
 fn default_worker_pool_size() -> u32 {
     DEFAULT_WORKER_POOL_SIZE
 }
 
+fn default_dependency_fan_out() -> usize {
+    DEFAULT_DEPENDENCY_FAN_OUT
+}
+
+fn default_task_dedup_window() -> u64 {
+    DEFAULT_TASK_DEDUP_WINDOW
+}
+
 fn default_mdns() -> bool {
     DEFAULT_MDNS
 }
 
+fn default_bootstrap_target_connections() -> usize {
+    DEFAULT_BOOTSTRAP_TARGET_CONNECTIONS
+}
+
+fn default_bootstrap_peer_expiry() -> u64 {
+    DEFAULT_BOOTSTRAP_PEER_EXPIRY
+}
+
+fn default_metrics_push_interval() -> u64 {
+    DEFAULT_METRICS_PUSH_INTERVAL
+}
+
+fn default_cluster_lease_duration() -> u64 {
+    DEFAULT_CLUSTER_LEASE_DURATION
+}
+
+fn default_idempotency_window() -> u64 {
+    DEFAULT_IDEMPOTENCY_WINDOW
+}
+
+fn default_document_view_cache_size() -> usize {
+    DEFAULT_DOCUMENT_VIEW_CACHE_SIZE
+}
+
+fn default_log_state_cache_size() -> usize {
+    DEFAULT_LOG_STATE_CACHE_SIZE
+}
+
+fn default_rendezvous_min_ttl() -> u64 {
+    DEFAULT_RENDEZVOUS_MIN_TTL
+}
+
+fn default_rendezvous_max_ttl() -> u64 {
+    DEFAULT_RENDEZVOUS_MAX_TTL
+}
+
+fn default_rendezvous_max_registrations() -> usize {
+    DEFAULT_RENDEZVOUS_MAX_REGISTRATIONS
+}
+
+fn default_replication_mode() -> String {
+    Mode::LogHeight.as_str().to_string()
+}
+
+fn default_compression() -> Vec<String> {
+    SUPPORTED_COMPRESSIONS
+        .iter()
+        .map(|compression| compression.to_string())
+        .collect()
+}
+
 /// Node configuration which can be de/serialized from a config file.
 ///
 /// See https://github.com/p2panda/aquadoggo/blob/main/aquadoggo_cli/config.toml for example
@@ -94,12 +274,24 @@ pub struct ConfigFile {
     /// It is recommended to set this list to all schema ids your own application should support,
     /// including all important system schemas.
     ///
+    /// Application schemas can also be pinned by their name and version, for example "venues@2"
+    /// for the schema named "venues" after its definition got updated once. Pins get resolved to
+    /// schema ids when the schema definitions arrive on the node. Schema names are not unique,
+    /// pin schemas by their id when replicating with untrusted nodes.
+    ///
     /// WARNING: When set to wildcard "*", your node will support _any_ schemas it will encounter
     /// on the network. This is useful for experimentation and local development but _not_
     /// recommended for production settings.
     #[serde(default)]
     pub allow_schema_ids: UncheckedAllowList,
 
+    /// Later versions of schemas pinned in "allow_schema_ids" which get allowed automatically,
+    /// either "exact" to only allow the pinned version, "compatible" to allow versions keeping all
+    /// fields of the pinned version unchanged or "latest" to allow all later versions. Defaults to
+    /// "exact".
+    #[serde(default = "default_schema_version_policy")]
+    pub schema_version_policy: String,
+
     /// URL / connection string to PostgreSQL or SQLite database. Defaults to an in-memory SQLite
     /// database.
     ///
@@ -112,10 +304,163 @@ pub struct ConfigFile {
     #[serde(default = "default_max_database_connections")]
     pub database_max_connections: u32,
 
+    /// Secret key to encrypt the SQLite main and archive databases at rest with SQLCipher.
+    /// Defaults to no encryption.
+    ///
+    /// Requires aquadoggo to be built with the "sqlcipher" feature. The key can also be set with
+    /// the `DATABASE_KEY` environment variable to avoid keeping it in a configuration file.
+    #[serde(default)]
+    pub database_key: Option<String>,
+
+    /// Read the database key from the keyring of the operating system instead, defaults to false.
+    ///
+    /// The key is looked up under the service name "aquadoggo" and user "database_key".
+    #[serde(default)]
+    pub database_key_keyring: bool,
+
+    /// Isolation level of transactions in PostgreSQL databases, either "read-committed",
+    /// "repeatable-read" or "serializable". Defaults to "read-committed".
+    #[serde(default = "default_database_isolation_level")]
+    pub database_isolation_level: String,
+
+    /// Maximum number of retries of transactions which failed due to serialization failures or
+    /// deadlocks in PostgreSQL databases, defaults to 3.
+    #[serde(default = "default_database_max_retries")]
+    pub database_max_retries: u32,
+
+    /// URL / connection string to an optional PostgreSQL or SQLite archive database. Defaults to
+    /// no archive.
+    ///
+    /// When set, historical operation data of documents which did not change for longer than
+    /// `archive_threshold` is moved into the archive and transparently loaded back on access.
+    #[serde(default)]
+    pub archive_database_url: Option<String>,
+
+    /// Duration in seconds after which inactive documents are moved into the archive database.
+    /// Defaults to 30 days.
+    #[serde(default = "default_archive_threshold")]
+    pub archive_threshold: u64,
+
     /// HTTP port for client-node communication, serving the GraphQL API. Defaults to 2020.
     #[serde(default = "default_http_port")]
     pub http_port: u16,
 
+    /// IP address the HTTP API is bound to. Defaults to all interfaces ("0.0.0.0").
+    #[serde(default = "default_http_bind_address")]
+    pub http_bind_address: IpAddr,
+
+    /// Address to serve blobs on instead of "http_port", GraphQL is not served on it. Defaults to
+    /// none, serving blobs together with the GraphQL API.
+    #[serde(default)]
+    pub blobs_http_address: Option<SocketAddr>,
+
+    /// Logical identity the GraphQL API reports instead of the peer id of this node, for example
+    /// its domain name. Defaults to none.
+    #[serde(default)]
+    pub http_identity: Option<String>,
+
+    /// Domain names to serve the GraphQL API for via HTTPS and HTTP/3. Defaults to none, serving
+    /// the API only via plain HTTP.
+    ///
+    /// TLS certificates for these domains are provisioned automatically via ACME (Let's Encrypt
+    /// by default). All domains need to resolve to this node and "https_port" needs to be
+    /// reachable as port 443.
+    #[serde(default)]
+    pub tls_domains: Vec<String>,
+
+    /// TCP and UDP port serving the GraphQL API via HTTPS and HTTP/3. Defaults to 443.
+    #[serde(default = "default_https_port")]
+    pub https_port: u16,
+
+    /// Email addresses the certificate authority can use to contact you, for example about
+    /// expiring certificates.
+    #[serde(default)]
+    pub acme_contacts: Vec<String>,
+
+    /// URL of the ACME directory to request certificates from. Defaults to Let's Encrypt.
+    #[serde(default = "default_acme_directory_url")]
+    pub acme_directory_url: String,
+
+    /// Path to folder where the ACME account key and certificates are persisted. Defaults to none,
+    /// requesting new certificates on every start.
+    #[serde(default)]
+    pub acme_cache_path: Option<PathBuf>,
+
+    /// Maximum number of GraphQL requests per minute from a single IP address. Defaults to 1200.
+    ///
+    /// Set to 0 to disable rate limiting.
+    #[serde(default = "default_http_rate_limit")]
+    pub graphql_rate_limit: u32,
+
+    /// Maximum number of GraphQL requests handled at the same time. Defaults to 256.
+    ///
+    /// Set to 0 to disable the limit.
+    #[serde(default = "default_http_max_concurrent_requests")]
+    pub graphql_max_concurrent_requests: usize,
+
+    /// Maximum size in bytes of GraphQL request bodies. Defaults to 4 MiB.
+    ///
+    /// Set to 0 to disable the limit.
+    #[serde(default = "default_graphql_max_body_size")]
+    pub graphql_max_body_size: usize,
+
+    /// Maximum number of relations a GraphQL query follows from the documents it selects at its
+    /// root. Defaults to 8.
+    ///
+    /// Set to 0 to disable the limit.
+    #[serde(default = "default_graphql_max_relation_depth")]
+    pub graphql_max_relation_depth: usize,
+
+    /// Maximum number of related documents a GraphQL query resolves in total. Defaults to 10000.
+    ///
+    /// Set to 0 to disable the limit.
+    #[serde(default = "default_graphql_max_related_documents")]
+    pub graphql_max_related_documents: usize,
+
+    /// Return details of internal errors like failed SQL queries or file paths in GraphQL
+    /// responses. Defaults to true in debug builds and false in release builds.
+    ///
+    /// When disabled, internal errors are only logged and clients receive a reference id instead.
+    #[serde(default = "default_graphql_error_details")]
+    pub graphql_error_details: bool,
+
+    /// Duration in seconds for which GraphQL collection snapshots can be paginated. Defaults to 0
+    /// which disables snapshots.
+    #[serde(default = "default_graphql_snapshot_ttl")]
+    pub graphql_snapshot_ttl: u64,
+
+    /// Maximum number of subscriptions a client runs at the same time over one GraphQL WebSocket
+    /// connection. Defaults to 32.
+    ///
+    /// Set to 0 to disable the limit.
+    #[serde(default = "default_graphql_ws_max_subscriptions")]
+    pub graphql_ws_max_subscriptions: usize,
+
+    /// Duration in seconds after which GraphQL WebSocket connections without any active
+    /// subscription are closed. Defaults to 300.
+    ///
+    /// Set to 0 to keep idle connections open.
+    #[serde(default = "default_graphql_ws_idle_timeout")]
+    pub graphql_ws_idle_timeout: u64,
+
+    /// Maximum number of blob requests per minute from a single IP address. Defaults to 1200.
+    ///
+    /// Set to 0 to disable rate limiting.
+    #[serde(default = "default_http_rate_limit")]
+    pub blobs_rate_limit: u32,
+
+    /// Maximum number of blob requests handled at the same time. Defaults to 256.
+    ///
+    /// Set to 0 to disable the limit.
+    #[serde(default = "default_http_max_concurrent_requests")]
+    pub blobs_max_concurrent_requests: usize,
+
+    /// Maximum size in bytes of blob request bodies. Defaults to 16 KiB.
+    ///
+    /// Set to 0 to disable the limit.
+    #[serde(default = "default_blobs_max_body_size")]
+    pub blobs_max_body_size: usize,
+
     /// Protocol (TCP/QUIC) used for node-node communication and data replication. Defaults to QUIC.
     #[serde(default)]
     pub transport: Transport,
@@ -124,6 +469,16 @@ pub struct ConfigFile {
     #[serde(default = "default_node_port")]
     pub node_port: u16,
 
+    /// IP versions (v4/v6/dual) the node listens on and uses for dialing other peers. Defaults to
+    /// dual-stack, preferring IPv6 when dialing if it is routable.
+    #[serde(default)]
+    pub ip_version: IpVersion,
+
+    /// TCP / QUIC port used for IPv6 node-node communication. Defaults to the same port as
+    /// `node_port`.
+    #[serde(default)]
+    pub node_port_v6: Option<u16>,
+
     /// Pre-shared key formatted as a 64 digit hexadecimal string.
     ///
     /// When provided a private network will be made with only peers knowing the psk being able
@@ -141,6 +496,51 @@ pub struct ConfigFile {
     #[serde(default)]
     pub blobs_base_path: Option<PathBuf>,
 
+    /// Blobs smaller than this number of bytes are aggregated in packfiles instead of being kept
+    /// in separate files. Disabled by default.
+    ///
+    /// Packing helps file systems which degrade when holding millions of small files.
+    #[serde(default)]
+    pub blobs_pack_threshold: Option<u64>,
+
+    /// Encrypt blobs on the file system with a key derived from `database_key`, defaults to
+    /// false.
+    ///
+    /// WARNING: The node refuses to start when encryption gets enabled for a blobs directory which
+    /// already contains unencrypted blobs or disabled for one with encrypted blobs.
+    #[serde(default)]
+    pub encrypt_blobs: bool,
+
+    /// Drop the data of blob pieces from the database as soon as their blob got materialized on
+    /// the file system, defaults to false.
+    ///
+    /// WARNING: Compacted pieces can not be replicated to other nodes anymore.
+    #[serde(default)]
+    pub compact_blob_pieces: bool,
+
+    /// List of mime types blobs are allowed to claim, for example "image/png". Defaults to
+    /// wildcard "*".
+    #[serde(default)]
+    pub allow_blob_mime_types: UncheckedAllowList,
+
+    /// Action taken when the mime type detected from the first piece of a blob does not match the
+    /// claimed one, either "flag" or "reject". Defaults to "flag".
+    #[serde(default = "default_blob_mime_type_mismatch")]
+    pub blob_mime_type_mismatch: String,
+
+    /// List of external commands transcoding materialized blobs into other formats, for example
+    /// web-friendly versions of audio and video files. Empty by default.
+    ///
+    /// Commands receive the paths of the original blob and the expected result via the
+    /// "{input}" and "{output}" placeholders in their arguments.
+    #[serde(default)]
+    pub blob_transcoders: Vec<UncheckedBlobTranscoder>,
+
+    /// Fetch blobs which are not materialized on this node from connected peers when clients
+    /// request them via HTTP, defaults to false.
+    #[serde(default)]
+    pub proxy_blobs: bool,
+
     /// Path to persist your ed25519 private key file. Defaults to an ephemeral key only for this
     /// current session.
     ///
@@ -168,6 +568,23 @@ pub struct ConfigFile {
     #[serde(default)]
     pub direct_node_addresses: Vec<String>,
 
+    /// List of bootstrap node addresses.
+    ///
+    /// The node dials bootstrap nodes in turns, ordered by how reliably they could be reached in
+    /// the past, until it holds "bootstrap_target_connections" connections. Peers we connected to
+    /// successfully are remembered in the database and dialed again after a restart.
+    #[serde(default)]
+    pub bootstrap_peers: Vec<String>,
+
+    /// Number of connections the node tries to keep up by dialing bootstrap peers, defaults to 8.
+    #[serde(default = "default_bootstrap_target_connections")]
+    pub bootstrap_target_connections: usize,
+
+    /// Duration in seconds after which learned peers we could not connect to anymore are
+    /// forgotten, defaults to 30 days.
+    #[serde(default = "default_bootstrap_peer_expiry")]
+    pub bootstrap_peer_expiry: u64,
+
     /// List of peers which are allowed to connect to your node.
     ///
     /// If set then only nodes (identified by their peer id) contained in this list will be able to
@@ -198,6 +615,25 @@ pub struct ConfigFile {
     #[serde(default)]
     pub block_peer_ids: Vec<PeerId>,
 
+    /// Hex-encoded ed25519 private key of the organization operating this node.
+    ///
+    /// If set, the node proves control of this operator key to every peer it connects to. The
+    /// operator key is independent from the node's own key pair, many nodes of one organization
+    /// can share it.
+    #[serde(default)]
+    pub operator_private_key: Option<String>,
+
+    /// List of operator public keys of peers we replicate with.
+    ///
+    /// If set then replication sessions are only initiated with and accepted from peers which
+    /// proved control of one of the listed operator keys. When not set we replicate with any
+    /// peer.
+    ///
+    /// Use this list for example to let the nodes of a federation of known organizations
+    /// replicate data only among themselves, without needing to share a pre-shared key.
+    #[serde(default)]
+    pub allow_operator_keys: UncheckedAllowList,
+
     /// List of relay addresses.
     ///
     /// A relay helps discover other nodes on the internet (also known as "rendesvouz" or
@@ -219,87 +655,1187 @@ pub struct ConfigFile {
     #[serde(default)]
     pub relay_mode: bool,
 
+    /// Name of the network this node participates in. Not set by default.
+    ///
+    /// Nodes register and discover each other at relays in a namespace derived from this name,
+    /// nodes of different networks do not find each other even when they use the same relays.
+    pub network_name: Option<String>,
+
+    /// List of connection tickets of nodes we want to connect to. Not set by default.
+    ///
+    /// Tickets are shown by other nodes when they start and contain their addresses, relays and
+    /// network name. These get added to the direct node and relay addresses of this node.
+    #[serde(default)]
+    pub join_tickets: Vec<String>,
+
+    /// Minimum time-to-live in seconds peers can request for their registration when this node
+    /// runs in relay mode, defaults to 2 hours.
+    #[serde(default = "default_rendezvous_min_ttl")]
+    pub rendezvous_min_ttl: u64,
+
+    /// Maximum time-to-live in seconds peers can request for their registration when this node
+    /// runs in relay mode, defaults to 72 hours.
+    #[serde(default = "default_rendezvous_max_ttl")]
+    pub rendezvous_max_ttl: u64,
+
+    /// Maximum number of peers which can hold registrations at the same time when this node runs
+    /// in relay mode, defaults to 1024.
+    ///
+    /// When this quota is reached, requests of peers without a registration are ignored.
+    #[serde(default = "default_rendezvous_max_registrations")]
+    pub rendezvous_max_registrations: usize,
+
+    /// Latency in milliseconds added to all data sent to other nodes, for testing replication
+    /// under poor connectivity. Defaults to 0.
+    ///
+    /// WARNING: Network simulation is only supported when using TCP for the transport layer.
+    #[serde(default)]
+    pub simulated_latency: u64,
+
+    /// Maximum random deviation in milliseconds from the simulated latency. Defaults to 0.
+    #[serde(default)]
+    pub simulated_jitter: u64,
+
+    /// Probability between 0.0 and 1.0 of data sent to other nodes getting lost and retransmitted.
+    /// Defaults to 0.0.
+    #[serde(default)]
+    pub simulated_packet_loss: f64,
+
     /// Worker pool size, defaults to 16.
     #[serde(default = "default_worker_pool_size")]
     pub worker_pool_size: u32,
-}
-
-impl Default for ConfigFile {
-    fn default() -> Self {
-        Self {
-            transport: Transport::default(),
-            psk: None,
-            log_level: default_log_level(),
-            allow_schema_ids: UncheckedAllowList::default(),
-            database_url: default_database_url(),
-            database_max_connections: default_max_database_connections(),
-            http_port: default_http_port(),
-            node_port: default_node_port(),
-            blobs_base_path: None,
-            mdns: default_mdns(),
-            private_key: None,
-            direct_node_addresses: vec![],
-            allow_peer_ids: UncheckedAllowList::default(),
-            block_peer_ids: vec![],
-            relay_addresses: vec![],
-            relay_mode: false,
-            worker_pool_size: default_worker_pool_size(),
-        }
-    }
-}
 
-impl TryFrom<ConfigFile> for Configuration {
-    type Error = anyhow::Error;
+    /// Maximum number of reduce tasks a single dependency task dispatches at once. Defaults to
+    /// 256.
+    ///
+    /// Documents with more pinned relations are processed in multiple runs.
+    #[serde(default = "default_dependency_fan_out")]
+    pub dependency_fan_out: usize,
 
-    fn try_from(value: ConfigFile) -> Result<Self, Self::Error> {
-        // Check if given schema ids are valid
-        let allow_schema_ids = match value.allow_schema_ids {
-            UncheckedAllowList::Wildcard => AllowList::<SchemaId>::Wildcard,
-            UncheckedAllowList::Set(str_values) => {
-                let schema_ids: Result<Vec<SchemaId>, anyhow::Error> = str_values
-                    .iter()
-                    .map(|str_value| {
-                        SchemaId::from_str(str_value).map_err(|_| {
-                            anyhow!(
-                                "Invalid schema id '{str_value}' found in 'allow_schema_ids' list"
-                            )
-                        })
-                    })
-                    .collect();
+    /// Milliseconds during which duplicate materialization tasks are coalesced after such a task
+    /// completed. Defaults to 50, set to 0 to disable.
+    #[serde(default = "default_task_dedup_window")]
+    pub task_dedup_window: u64,
 
-                AllowList::Set(schema_ids?)
-            }
-        };
+    /// Number of materialization tasks of a schema processed in a row before it is the next
+    /// schema's turn, mapped by schema id. Schemas not listed have a weight of 1.
+    #[serde(default)]
+    pub schema_task_weights: HashMap<String, u32>,
 
-        // Check if given peer ids are valid
-        let allow_peer_ids = match value.allow_peer_ids {
-            UncheckedAllowList::Wildcard => AllowList::<PeerId>::Wildcard,
-            UncheckedAllowList::Set(str_values) => {
-                let peer_ids: Result<Vec<PeerId>, anyhow::Error> = str_values
-                    .iter()
-                    .map(|str_value| {
-                        PeerId::from_str(str_value).map_err(|_| {
-                            anyhow!("Invalid peer id '{str_value}' found in 'allow_peer_ids' list")
-                        })
-                    })
-                    .collect();
+    /// Path of a UNIX socket streaming materializer task events as newline-delimited JSON.
+    /// Disabled by default.
+    #[serde(default)]
+    pub materializer_events_socket: Option<PathBuf>,
 
-                AllowList::Set(peer_ids?)
-            }
-        };
+    /// Schemas of which only the latest view of every document is kept, historic operation fields
+    /// are pruned after every update. Empty by default.
+    #[serde(default)]
+    pub latest_view_only_schemas: Vec<String>,
 
-        // Create a temporary blobs directory when none was given
-        let blobs_base_path = match value.blobs_base_path {
-            Some(path) => path,
-            None => TMP_DIR
-                .get_or_init(|| {
-                    // Initialise a `TempDir` instance globally to make sure it does not run out of
-                    // scope and gets deleted before the end of the application runtime
-                    TempDir::new().expect("Could not create temporary directory to store blobs")
-                })
-                .path()
-                .to_path_buf(),
-        };
+    /// Maximum number of historic views kept per document, mapped by schema id. Least recently
+    /// read views get evicted first. Unlimited by default.
+    #[serde(default)]
+    pub max_document_views: HashMap<String, usize>,
+
+    /// Node settings attached to application schemas, mapped by schema id. Empty by default.
+    ///
+    /// Every schema holds a table of arbitrary key-value pairs which subsystems look up. The
+    /// settings "task_weight", "latest_view_only" and "max_document_views" are merged with their
+    /// top-level counterparts.
+    #[serde(default)]
+    pub schemas: HashMap<String, HashMap<String, SettingValue>>,
+
+    /// Maximum number of recently requested document views kept in memory. Defaults to 1000.
+    ///
+    /// Set to 0 to disable caching of document views.
+    #[serde(default = "default_document_view_cache_size")]
+    pub document_view_cache_size: usize,
+
+    /// List of relations of application schemas which get loaded into the cache of document
+    /// views whenever one of their documents changes. Empty by default.
+    #[serde(default)]
+    pub prefetch_profiles: Vec<UncheckedPrefetchProfile>,
+
+    /// Maximum number of logs whose latest entries are kept in memory. Defaults to 1000.
+    ///
+    /// Set to 0 to disable caching of log state.
+    #[serde(default = "default_log_state_cache_size")]
+    pub log_state_cache_size: usize,
+
+    /// Maintain statistics about every document, like the number of updates and distinct
+    /// authors. Disabled by default.
+    #[serde(default)]
+    pub document_stats: bool,
+
+    /// List of validation constraints for fields of application schemas. Empty by default.
+    ///
+    /// Operations violating these constraints are rejected when they get published or arrive via
+    /// replication.
+    #[serde(default)]
+    pub field_constraints: Vec<UncheckedFieldConstraint>,
+
+    /// List of "int" fields of application schemas holding decimals with a fixed number of
+    /// fractional digits. Empty by default.
+    ///
+    /// Values are stored as integers scaled by the number of fractional digits and exposed as
+    /// `Decimal` strings on the GraphQL API.
+    #[serde(default)]
+    pub decimal_fields: Vec<UncheckedDecimalField>,
+
+    /// List of "int" and "str" fields of application schemas holding dates and times. Empty by
+    /// default.
+    ///
+    /// "int" fields hold UNIX timestamps in seconds, "str" fields UTC dates and times. Both are
+    /// exposed as `DateTime` strings on the GraphQL API.
+    #[serde(default)]
+    pub datetime_fields: Vec<UncheckedDateTimeField>,
+
+    /// Reject operations which are not encoded in canonical CBOR, both when they get published
+    /// and when they arrive via replication. Disabled by default.
+    #[serde(default)]
+    pub require_canonical_encoding: bool,
+
+    /// Schema id of capability documents which grant permissions to public keys. Disabled by
+    /// default.
+    ///
+    /// When set, documents of this schema are consulted when authorising requests, for example
+    /// when publishing operations via the GraphQL API. Capability documents need to contain a
+    /// `public_key` and `permission` string field. Possible permissions are "admin", "publish:*",
+    /// "publish:<schema_id>", "read:*" or "read:<document_id>".
+    #[serde(default)]
+    pub capability_schema_id: Option<String>,
+
+    /// List of public keys which hold the "admin" permission without requiring a capability
+    /// document.
+    ///
+    /// These keys form the root of trust for capability documents, they can grant permissions
+    /// (including "admin") to other public keys. Administrative GraphQL mutations, like purging
+    /// documents, are only accepted from admins and refused when this list is empty.
+    #[serde(default)]
+    pub admin_public_keys: Vec<String>,
+
+    /// Name of a relation field restricting read access of documents. Disabled by default.
+    ///
+    /// Documents of schemas containing a relation field with this name can only be queried by
+    /// clients authenticating with a public key holding the "read:<document_id>" permission for
+    /// the related ACL document. This has no effect when no capability schema is configured.
+    #[serde(default)]
+    pub read_acl_field: Option<String>,
+
+    /// Number of seconds the response of a "publish" request with an idempotency key is kept.
+    /// Defaults to 300.
+    ///
+    /// Set to 0 to disable caching of responses.
+    #[serde(default = "default_idempotency_window")]
+    pub idempotency_window: u64,
+
+    /// List of service accounts the node signs and publishes operations for on behalf of trusted
+    /// clients. Empty by default.
+    ///
+    /// Accounts without a private key get a key pair generated and persisted unencrypted in the
+    /// database, see "database_key" to encrypt SQLite databases at rest. Requires
+    /// "admin_public_keys" to be set, only admins can publish with service accounts.
+    #[serde(default)]
+    pub service_accounts: Vec<UncheckedServiceAccount>,
+
+    /// Schema of node profile documents. Disabled by default.
+    ///
+    /// When set, the node publishes "node_profile" as a document of this schema with its own key
+    /// pair. The schema needs the string fields "contact", "features" and "tos_url".
+    #[serde(default)]
+    pub node_profile_schema_id: Option<String>,
+
+    /// Profile of this node published to the node profile schema.
+    #[serde(default)]
+    pub node_profile: UncheckedNodeProfile,
+
+    /// List of compression algorithms offered to other nodes for replication, ordered by
+    /// preference. Defaults to ["zstd", "deflate"].
+    ///
+    /// Set to an empty list to disable compression of replication messages.
+    #[serde(default = "default_compression")]
+    pub compression: Vec<String>,
+
+    /// Replication mode used with other nodes, either "log-height" or "log-range". Defaults to
+    /// "log-height".
+    ///
+    /// With "log-range" missing entries are requested in ranges, ranges of the same log are
+    /// downloaded from all connected nodes holding it at once.
+    #[serde(default = "default_replication_mode")]
+    pub replication_mode: String,
+
+    /// List of preferred replication modes for certain peers and / or schema ids.
+    ///
+    /// The first matching entry determines the mode. When a mode is not supported by the remote
+    /// peer we fall back to "replication_mode" and finally to "log-height".
+    #[serde(default)]
+    pub replication_modes: Vec<UncheckedModePreference>,
+
+    /// List of directions in which entries are exchanged with certain peers, either "both",
+    /// "push" or "pull".
+    ///
+    /// The first matching entry determines the direction, otherwise entries are exchanged in both
+    /// directions.
+    #[serde(default)]
+    pub replication_directions: Vec<UncheckedDirectionPreference>,
+
+    /// URL of a remote endpoint receiving periodic snapshots of node metrics, for example
+    /// "http://localhost:9091/metrics", "statsd://localhost:8125" or "graphite://localhost:2003".
+    /// Defaults to no metrics push.
+    #[serde(default)]
+    pub metrics_push_target: Option<String>,
+
+    /// Interval in seconds between two pushed metric snapshots. Defaults to 60.
+    #[serde(default = "default_metrics_push_interval")]
+    pub metrics_push_interval: u64,
+
+    /// List of HTTP endpoints notified about created, updated or deleted documents of a schema.
+    /// Empty by default.
+    #[serde(default)]
+    pub webhooks: Vec<UncheckedWebhook>,
+
+    /// Interval in seconds between two sweeps removing or re-linking entries and operations which
+    /// belong to no known document. Disabled by default.
+    #[serde(default)]
+    pub vacuum_interval: Option<u64>,
+
+    /// Name of this instance when running several instances of the same node behind a load
+    /// balancer. Disabled by default.
+    ///
+    /// All instances need to share the same private key and PostgreSQL database.
+    #[serde(default)]
+    pub cluster_instance: Option<String>,
+
+    /// Duration in seconds of the leader lease of a cluster. Defaults to 15.
+    #[serde(default = "default_cluster_lease_duration")]
+    pub cluster_lease_duration: u64,
+}
+
+/// Preferred replication mode for a peer and / or schema ids as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedModePreference {
+    /// Peer this preference applies to, applies to all peers when not set.
+    #[serde(default)]
+    pub peer_id: Option<PeerId>,
+
+    /// Schema ids this preference applies to, applies to all schema ids when empty.
+    #[serde(default)]
+    pub schema_ids: Vec<String>,
+
+    /// Preferred replication mode.
+    pub mode: String,
+}
+
+/// Replication direction for a peer as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedDirectionPreference {
+    /// Peer this preference applies to, applies to all peers when not set.
+    #[serde(default)]
+    pub peer_id: Option<PeerId>,
+
+    /// Direction in which entries are exchanged with the peer.
+    pub direction: String,
+}
+
+/// External command transcoding blobs as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedBlobTranscoder {
+    /// Name of the derived blob, for example "webm".
+    pub name: String,
+
+    /// Mime types of blobs which get transcoded, for example "video/mp4" or "video/*".
+    pub mime_types: Vec<String>,
+
+    /// Mime type of the derived blob.
+    pub output_mime_type: String,
+
+    /// Program and its arguments.
+    pub command: Vec<String>,
+
+    /// Duration in seconds after which the command gets killed. Defaults to 300.
+    #[serde(default = "default_blob_transcoder_timeout")]
+    pub timeout: u64,
+}
+
+/// Validation constraint for a field of an application schema as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedFieldConstraint {
+    /// Schema of the constrained field.
+    pub schema_id: String,
+
+    /// Name of the constrained field.
+    pub field: String,
+
+    /// Smallest allowed number of "int" and "float" fields.
+    #[serde(default)]
+    pub min: Option<f64>,
+
+    /// Largest allowed number of "int" and "float" fields.
+    #[serde(default)]
+    pub max: Option<f64>,
+
+    /// Smallest allowed length of "str", "bytes" and relation list fields.
+    #[serde(default)]
+    pub min_length: Option<usize>,
+
+    /// Largest allowed length of "str", "bytes" and relation list fields.
+    #[serde(default)]
+    pub max_length: Option<usize>,
+
+    /// Regular expression "str" fields need to match.
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+/// Decimal field of an application schema as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedDecimalField {
+    /// Schema of the decimal field.
+    pub schema_id: String,
+
+    /// Name of the "int" field holding the decimal.
+    pub field: String,
+
+    /// Number of fractional digits.
+    pub scale: u32,
+}
+
+/// Date and time field of an application schema as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedDateTimeField {
+    /// Schema of the date and time field.
+    pub schema_id: String,
+
+    /// Name of the "int" or "str" field holding dates and times.
+    pub field: String,
+}
+
+/// Prefetched relations of an application schema as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedPrefetchProfile {
+    /// Schema of the documents whose relations get prefetched.
+    pub schema_id: String,
+
+    /// Relation paths, field names separated by dots, for example "venue.owner".
+    pub relations: Vec<String>,
+}
+
+/// Service account as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedServiceAccount {
+    /// Unique name of the account.
+    pub name: String,
+
+    /// Hex-encoded private key of the account, generated by the node when not set.
+    #[serde(default)]
+    pub private_key: Option<String>,
+}
+
+/// Webhook as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UncheckedWebhook {
+    /// URL of the endpoint receiving notifications, for example "http://localhost:8080/hooks".
+    pub url: String,
+
+    /// Schema of the documents the endpoint gets notified about.
+    pub schema_id: String,
+
+    /// Changes the endpoint gets notified about: "create", "update" or "delete". Defaults to all
+    /// changes.
+    #[serde(default)]
+    pub changes: Vec<String>,
+
+    /// Secret to sign payloads with. Payloads are not signed by default.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// Profile of this node as given in a config file.
+///
+/// These string values are not checked yet and need to be validated in a succeeding step.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UncheckedNodeProfile {
+    /// Contact of the node operator.
+    #[serde(default)]
+    pub contact: String,
+
+    /// Features supported by the node.
+    #[serde(default)]
+    pub features: Vec<String>,
+
+    /// URL of the terms of service of the node.
+    #[serde(default)]
+    pub tos_url: String,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            transport: Transport::default(),
+            psk: None,
+            log_level: default_log_level(),
+            allow_schema_ids: UncheckedAllowList::default(),
+            schema_version_policy: default_schema_version_policy(),
+            database_url: default_database_url(),
+            database_max_connections: default_max_database_connections(),
+            database_key: None,
+            database_key_keyring: false,
+            database_isolation_level: default_database_isolation_level(),
+            database_max_retries: default_database_max_retries(),
+            archive_database_url: None,
+            archive_threshold: default_archive_threshold(),
+            http_port: default_http_port(),
+            http_bind_address: default_http_bind_address(),
+            blobs_http_address: None,
+            http_identity: None,
+            tls_domains: vec![],
+            https_port: default_https_port(),
+            acme_contacts: vec![],
+            acme_directory_url: default_acme_directory_url(),
+            acme_cache_path: None,
+            graphql_rate_limit: default_http_rate_limit(),
+            graphql_max_concurrent_requests: default_http_max_concurrent_requests(),
+            graphql_max_body_size: default_graphql_max_body_size(),
+            graphql_max_relation_depth: default_graphql_max_relation_depth(),
+            graphql_max_related_documents: default_graphql_max_related_documents(),
+            graphql_error_details: default_graphql_error_details(),
+            graphql_snapshot_ttl: default_graphql_snapshot_ttl(),
+            graphql_ws_max_subscriptions: default_graphql_ws_max_subscriptions(),
+            graphql_ws_idle_timeout: default_graphql_ws_idle_timeout(),
+            blobs_rate_limit: default_http_rate_limit(),
+            blobs_max_concurrent_requests: default_http_max_concurrent_requests(),
+            blobs_max_body_size: default_blobs_max_body_size(),
+            node_port: default_node_port(),
+            ip_version: IpVersion::default(),
+            node_port_v6: None,
+            blobs_base_path: None,
+            blobs_pack_threshold: None,
+            encrypt_blobs: false,
+            compact_blob_pieces: false,
+            allow_blob_mime_types: UncheckedAllowList::default(),
+            blob_mime_type_mismatch: default_blob_mime_type_mismatch(),
+            blob_transcoders: Vec::new(),
+            proxy_blobs: false,
+            mdns: default_mdns(),
+            private_key: None,
+            direct_node_addresses: vec![],
+            bootstrap_peers: vec![],
+            bootstrap_target_connections: default_bootstrap_target_connections(),
+            bootstrap_peer_expiry: default_bootstrap_peer_expiry(),
+            allow_peer_ids: UncheckedAllowList::default(),
+            block_peer_ids: vec![],
+            operator_private_key: None,
+            allow_operator_keys: UncheckedAllowList::default(),
+            relay_addresses: vec![],
+            relay_mode: false,
+            network_name: None,
+            join_tickets: vec![],
+            rendezvous_min_ttl: default_rendezvous_min_ttl(),
+            rendezvous_max_ttl: default_rendezvous_max_ttl(),
+            rendezvous_max_registrations: default_rendezvous_max_registrations(),
+            simulated_latency: 0,
+            simulated_jitter: 0,
+            simulated_packet_loss: 0.0,
+            worker_pool_size: default_worker_pool_size(),
+            dependency_fan_out: default_dependency_fan_out(),
+            task_dedup_window: default_task_dedup_window(),
+            schema_task_weights: HashMap::new(),
+            materializer_events_socket: None,
+            latest_view_only_schemas: Vec::new(),
+            max_document_views: HashMap::new(),
+            schemas: HashMap::new(),
+            field_constraints: Vec::new(),
+            decimal_fields: Vec::new(),
+            datetime_fields: Vec::new(),
+            require_canonical_encoding: false,
+            document_view_cache_size: default_document_view_cache_size(),
+            prefetch_profiles: Vec::new(),
+            log_state_cache_size: default_log_state_cache_size(),
+            document_stats: false,
+            capability_schema_id: None,
+            admin_public_keys: vec![],
+            read_acl_field: None,
+            idempotency_window: default_idempotency_window(),
+            service_accounts: vec![],
+            node_profile_schema_id: None,
+            node_profile: UncheckedNodeProfile::default(),
+            compression: default_compression(),
+            replication_mode: default_replication_mode(),
+            replication_modes: vec![],
+            replication_directions: vec![],
+            metrics_push_target: None,
+            metrics_push_interval: default_metrics_push_interval(),
+            webhooks: vec![],
+            vacuum_interval: None,
+            cluster_instance: None,
+            cluster_lease_duration: default_cluster_lease_duration(),
+        }
+    }
+}
+
+impl TryFrom<ConfigFile> for Configuration {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ConfigFile) -> Result<Self, Self::Error> {
+        // Check if given schema ids and pins are valid
+        let mut allow_schema_pins = Vec::new();
+        let allow_schema_ids = match value.allow_schema_ids {
+            UncheckedAllowList::Wildcard => AllowList::<SchemaId>::Wildcard,
+            UncheckedAllowList::Set(str_values) => {
+                let (pin_values, str_values): (Vec<String>, Vec<String>) = str_values
+                    .into_iter()
+                    .partition(|str_value| SchemaPin::is_pin(str_value));
+
+                for pin_value in pin_values {
+                    let pin = SchemaPin::from_str(&pin_value).map_err(|err| {
+                        anyhow!("Invalid schema pin '{pin_value}' found in 'allow_schema_ids' list: {err}")
+                    })?;
+                    allow_schema_pins.push(pin);
+                }
+
+                let schema_ids: Result<Vec<SchemaId>, anyhow::Error> = str_values
+                    .iter()
+                    .map(|str_value| {
+                        SchemaId::from_str(str_value).map_err(|_| {
+                            anyhow!(
+                                "Invalid schema id '{str_value}' found in 'allow_schema_ids' list"
+                            )
+                        })
+                    })
+                    .collect();
+
+                AllowList::Set(schema_ids?)
+            }
+        };
+
+        // Check if given peer ids are valid
+        let allow_peer_ids = match value.allow_peer_ids {
+            UncheckedAllowList::Wildcard => AllowList::<PeerId>::Wildcard,
+            UncheckedAllowList::Set(str_values) => {
+                let peer_ids: Result<Vec<PeerId>, anyhow::Error> = str_values
+                    .iter()
+                    .map(|str_value| {
+                        PeerId::from_str(str_value).map_err(|_| {
+                            anyhow!("Invalid peer id '{str_value}' found in 'allow_peer_ids' list")
+                        })
+                    })
+                    .collect();
+
+                AllowList::Set(peer_ids?)
+            }
+        };
+
+        // Check if given operator keys are valid
+        if let Some(private_key) = &value.operator_private_key {
+            KeyPair::from_private_key_str(private_key)
+                .map_err(|_| anyhow!("Invalid private key found in 'operator_private_key'"))?;
+        }
+
+        let allow_operator_keys = match value.allow_operator_keys {
+            UncheckedAllowList::Wildcard => AllowList::<PublicKey>::Wildcard,
+            UncheckedAllowList::Set(str_values) => {
+                let public_keys: Result<Vec<PublicKey>, anyhow::Error> = str_values
+                    .iter()
+                    .map(|str_value| {
+                        PublicKey::from_str(str_value).map_err(|_| {
+                            anyhow!(
+                                "Invalid public key '{str_value}' found in 'allow_operator_keys' list"
+                            )
+                        })
+                    })
+                    .collect();
+
+                AllowList::Set(public_keys?)
+            }
+        };
+
+        // Check if given capability schema id is valid
+        let capability_schema_id = match value.capability_schema_id {
+            Some(str_value) => Some(SchemaId::from_str(&str_value).map_err(|_| {
+                anyhow!("Invalid schema id '{str_value}' found in 'capability_schema_id'")
+            })?),
+            None => None,
+        };
+
+        // Check if given schema task weights are valid
+        let schema_task_weights: Result<HashMap<SchemaId, u32>, anyhow::Error> = value
+            .schema_task_weights
+            .iter()
+            .map(|(str_value, weight)| {
+                let schema_id = SchemaId::from_str(str_value).map_err(|_| {
+                    anyhow!("Invalid schema id '{str_value}' found in 'schema_task_weights'")
+                })?;
+
+                if *weight == 0 {
+                    return Err(anyhow!(
+                        "Weight of '{str_value}' in 'schema_task_weights' needs to be larger than 0"
+                    ));
+                }
+
+                Ok((schema_id, *weight))
+            })
+            .collect();
+
+        // Check if given latest-view-only schema ids are valid
+        let latest_view_only_schemas: Result<Vec<SchemaId>, anyhow::Error> = value
+            .latest_view_only_schemas
+            .iter()
+            .map(|str_value| {
+                SchemaId::from_str(str_value).map_err(|_| {
+                    anyhow!("Invalid schema id '{str_value}' found in 'latest_view_only_schemas'")
+                })
+            })
+            .collect();
+
+        // Check if given schema ids limiting the number of document views are valid
+        let max_document_views: Result<HashMap<SchemaId, usize>, anyhow::Error> = value
+            .max_document_views
+            .iter()
+            .map(|(str_value, max_views)| {
+                let schema_id = SchemaId::from_str(str_value).map_err(|_| {
+                    anyhow!("Invalid schema id '{str_value}' found in 'max_document_views'")
+                })?;
+
+                Ok((schema_id, *max_views))
+            })
+            .collect();
+
+        // Check if given schema settings are valid, settings of existing top-level keys are merged
+        // with these
+        let mut schema_task_weights = schema_task_weights?;
+        let mut latest_view_only_schemas = latest_view_only_schemas?;
+        let mut max_document_views = max_document_views?;
+        let mut schema_settings = HashMap::new();
+        for (str_value, values) in value.schemas {
+            let schema_id = SchemaId::from_str(&str_value)
+                .map_err(|_| anyhow!("Invalid schema id '{str_value}' found in 'schemas'"))?;
+            let settings = SchemaSettings::new(values);
+            let invalid =
+                |err: SettingError| anyhow!("{err} for schema '{str_value}' in 'schemas'");
+
+            if let Some(weight) = settings.get::<u32>("task_weight").map_err(invalid)? {
+                if weight == 0 {
+                    return Err(anyhow!(
+                        "'task_weight' of '{str_value}' in 'schemas' needs to be larger than 0"
+                    ));
+                }
+
+                schema_task_weights.insert(schema_id.clone(), weight);
+            }
+
+            if settings.get::<bool>("latest_view_only").map_err(invalid)? == Some(true)
+                && !latest_view_only_schemas.contains(&schema_id)
+            {
+                latest_view_only_schemas.push(schema_id.clone());
+            }
+
+            if let Some(max_views) = settings
+                .get::<usize>("max_document_views")
+                .map_err(invalid)?
+            {
+                max_document_views.insert(schema_id.clone(), max_views);
+            }
+
+            // Moderation is looked up at runtime, check its value early
+            settings.get::<bool>("moderated").map_err(invalid)?;
+
+            schema_settings.insert(schema_id, settings);
+        }
+
+        // Check if given admin public keys are valid
+        let admin_public_keys: Result<Vec<PublicKey>, anyhow::Error> = value
+            .admin_public_keys
+            .iter()
+            .map(|str_value| {
+                PublicKey::from_str(str_value).map_err(|_| {
+                    anyhow!("Invalid public key '{str_value}' found in 'admin_public_keys' list")
+                })
+            })
+            .collect();
+
+        // Check if given service accounts are valid
+        let mut service_accounts: Vec<ServiceAccount> = Vec::new();
+        for account in value.service_accounts {
+            if account.name.is_empty() {
+                return Err(anyhow!("Empty name found in 'service_accounts' list"));
+            }
+
+            if service_accounts
+                .iter()
+                .any(|existing| existing.name == account.name)
+            {
+                return Err(anyhow!(
+                    "Duplicate name '{}' found in 'service_accounts' list",
+                    account.name
+                ));
+            }
+
+            if let Some(private_key) = &account.private_key {
+                KeyPair::from_private_key_str(private_key).map_err(|_| {
+                    anyhow!(
+                        "Invalid private key of '{}' found in 'service_accounts' list",
+                        account.name
+                    )
+                })?;
+            }
+
+            service_accounts.push(ServiceAccount {
+                name: account.name,
+                private_key: account.private_key,
+            });
+        }
+
+        // Nobody could use the service accounts without an admin
+        if !service_accounts.is_empty() && value.admin_public_keys.is_empty() {
+            return Err(anyhow!(
+                "'service_accounts' requires 'admin_public_keys' to be set"
+            ));
+        }
+
+        // Check if given node profile schema id is valid
+        let node_profile_schema_id = match value.node_profile_schema_id {
+            Some(str_value) => Some(SchemaId::from_str(&str_value).map_err(|_| {
+                anyhow!("Invalid schema id '{str_value}' found in 'node_profile_schema_id'")
+            })?),
+            None => None,
+        };
+
+        // Features are stored separated by commas in the profile document
+        for feature in &value.node_profile.features {
+            if feature.is_empty() || feature.contains(',') {
+                return Err(anyhow!(
+                    "Invalid feature '{feature}' found in 'node_profile', features can not be \
+                    empty or contain commas"
+                ));
+            }
+        }
+
+        // Check if given compression algorithms are valid
+        let compression: Result<Vec<Compression>, anyhow::Error> = value
+            .compression
+            .iter()
+            .map(|str_value| {
+                Compression::from_str(str_value).map_err(|_| {
+                    anyhow!("Invalid compression '{str_value}' found in 'compression' list")
+                })
+            })
+            .collect();
+
+        // Check if given isolation level is valid
+        let database_isolation_level = value.database_isolation_level;
+        let database_isolation_level = IsolationLevel::from_str(&database_isolation_level)
+            .map_err(|_| {
+                anyhow!(
+                    "Invalid isolation level '{database_isolation_level}' found in 'database_isolation_level'"
+                )
+            })?;
+
+        // Check if given blob mime types are valid
+        let allow_blob_mime_types = match value.allow_blob_mime_types {
+            UncheckedAllowList::Wildcard => AllowList::<String>::Wildcard,
+            UncheckedAllowList::Set(str_values) => {
+                let mime_types: Result<Vec<String>, anyhow::Error> = str_values
+                    .iter()
+                    .map(|str_value| {
+                        let mime_type = essence(str_value);
+                        match mime_type.split_once('/') {
+                            Some((kind, subtype)) if !kind.is_empty() && !subtype.is_empty() => {
+                                Ok(mime_type)
+                            }
+                            _ => Err(anyhow!(
+                                "Invalid mime type '{str_value}' found in 'allow_blob_mime_types' list"
+                            )),
+                        }
+                    })
+                    .collect();
+
+                AllowList::Set(mime_types?)
+            }
+        };
+
+        let blob_mime_type_mismatch = value.blob_mime_type_mismatch;
+        let blob_mime_type_mismatch = MimeTypeMismatch::from_str(&blob_mime_type_mismatch)
+            .map_err(|_| {
+                anyhow!(
+                    "Invalid action '{blob_mime_type_mismatch}' found in 'blob_mime_type_mismatch'"
+                )
+            })?;
+
+        // Check if given blob transcoders are valid
+        let mut blob_transcoders: Vec<BlobTranscoder> = Vec::new();
+        for transcoder in value.blob_transcoders {
+            if !BlobTranscoder::is_valid_name(&transcoder.name) {
+                return Err(anyhow!(
+                    "Invalid name '{}' found in 'blob_transcoders' list",
+                    transcoder.name
+                ));
+            }
+
+            if blob_transcoders
+                .iter()
+                .any(|existing| existing.name == transcoder.name)
+            {
+                return Err(anyhow!(
+                    "Duplicate name '{}' found in 'blob_transcoders' list",
+                    transcoder.name
+                ));
+            }
+
+            for mime_type in transcoder
+                .mime_types
+                .iter()
+                .chain([&transcoder.output_mime_type])
+            {
+                if !essence(mime_type).contains('/') {
+                    return Err(anyhow!(
+                        "Invalid mime type '{mime_type}' found in 'blob_transcoders' list"
+                    ));
+                }
+            }
+
+            if !transcoder
+                .command
+                .iter()
+                .any(|arg| arg.contains(OUTPUT_PLACEHOLDER))
+            {
+                return Err(anyhow!(
+                    "Command of transcoder '{}' in 'blob_transcoders' list is missing the \
+                    '{OUTPUT_PLACEHOLDER}' placeholder",
+                    transcoder.name
+                ));
+            }
+
+            blob_transcoders.push(BlobTranscoder {
+                name: transcoder.name,
+                mime_types: transcoder.mime_types,
+                output_mime_type: transcoder.output_mime_type,
+                command: transcoder.command,
+                timeout: transcoder.timeout,
+            });
+        }
+
+        // Check if given replication modes are valid and implemented by this node
+        let check_supported_mode = |mode: Mode, key: &str| {
+            if SUPPORTED_MODES.contains(&mode) {
+                Ok(mode)
+            } else {
+                Err(anyhow!(
+                    "Unsupported mode '{}' found in '{}', supported modes are {}",
+                    mode.as_str(),
+                    key,
+                    SUPPORTED_MODES
+                        .iter()
+                        .map(|mode| format!("'{}'", mode.as_str()))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))
+            }
+        };
+
+        let replication_mode = value.replication_mode;
+        let replication_mode = Mode::from_str(&replication_mode).map_err(|_| {
+            anyhow!("Invalid mode '{replication_mode}' found in 'replication_mode'")
+        })?;
+        let replication_mode = check_supported_mode(replication_mode, "replication_mode")?;
+
+        let replication_modes: Result<Vec<ModePreference>, anyhow::Error> = value
+            .replication_modes
+            .into_iter()
+            .map(|preference| {
+                let mode = Mode::from_str(&preference.mode).map_err(|_| {
+                    anyhow!(
+                        "Invalid mode '{}' found in 'replication_modes' list",
+                        preference.mode
+                    )
+                })?;
+                let mode = check_supported_mode(mode, "replication_modes")?;
+
+                let schema_ids: Result<Vec<SchemaId>, anyhow::Error> = preference
+                    .schema_ids
+                    .iter()
+                    .map(|str_value| {
+                        SchemaId::from_str(str_value).map_err(|_| {
+                            anyhow!(
+                                "Invalid schema id '{str_value}' found in 'replication_modes' list"
+                            )
+                        })
+                    })
+                    .collect();
+
+                Ok(ModePreference {
+                    peer_id: preference.peer_id,
+                    schema_ids: schema_ids?,
+                    mode,
+                })
+            })
+            .collect();
+
+        // Check if given replication directions are valid
+        let replication_directions: Result<Vec<DirectionPreference>, anyhow::Error> = value
+            .replication_directions
+            .into_iter()
+            .map(|preference| {
+                let direction = Direction::from_str(&preference.direction).map_err(|_| {
+                    anyhow!(
+                        "Invalid direction '{}' found in 'replication_directions' list",
+                        preference.direction
+                    )
+                })?;
+
+                Ok(DirectionPreference {
+                    peer_id: preference.peer_id,
+                    direction,
+                })
+            })
+            .collect();
+
+        // Check if given field constraints are valid
+        let field_constraints: Result<Vec<FieldConstraint>, anyhow::Error> = value
+            .field_constraints
+            .into_iter()
+            .map(|constraint| {
+                let schema_id = SchemaId::from_str(&constraint.schema_id).map_err(|_| {
+                    anyhow!(
+                        "Invalid schema id '{}' found in 'field_constraints' list",
+                        constraint.schema_id
+                    )
+                })?;
+
+                if let (Some(min), Some(max)) = (constraint.min, constraint.max) {
+                    if min > max {
+                        return Err(anyhow!(
+                            "Constraint of field '{}' in 'field_constraints' list has a 'min' \
+                            larger than 'max'",
+                            constraint.field
+                        ));
+                    }
+                }
+
+                if let (Some(min_length), Some(max_length)) =
+                    (constraint.min_length, constraint.max_length)
+                {
+                    if min_length > max_length {
+                        return Err(anyhow!(
+                            "Constraint of field '{}' in 'field_constraints' list has a \
+                            'min_length' larger than 'max_length'",
+                            constraint.field
+                        ));
+                    }
+                }
+
+                let pattern = match constraint.pattern {
+                    Some(pattern) => Some(Regex::new(&pattern).map_err(|_| {
+                        anyhow!("Invalid pattern '{pattern}' found in 'field_constraints' list")
+                    })?),
+                    None => None,
+                };
+
+                Ok(FieldConstraint {
+                    schema_id,
+                    field: constraint.field,
+                    min: constraint.min,
+                    max: constraint.max,
+                    min_length: constraint.min_length,
+                    max_length: constraint.max_length,
+                    pattern,
+                })
+            })
+            .collect();
+
+        // Check if given decimal fields are valid
+        let decimal_fields: Result<Vec<DecimalField>, anyhow::Error> = value
+            .decimal_fields
+            .into_iter()
+            .map(|decimal| {
+                let schema_id = SchemaId::from_str(&decimal.schema_id).map_err(|_| {
+                    anyhow!(
+                        "Invalid schema id '{}' found in 'decimal_fields' list",
+                        decimal.schema_id
+                    )
+                })?;
+
+                if decimal.scale > MAX_DECIMAL_SCALE {
+                    return Err(anyhow!(
+                        "Decimal field '{}' in 'decimal_fields' list has a 'scale' larger than {}",
+                        decimal.field,
+                        MAX_DECIMAL_SCALE
+                    ));
+                }
+
+                Ok(DecimalField::new(&schema_id, &decimal.field, decimal.scale))
+            })
+            .collect();
+        let decimal_fields = decimal_fields?;
+
+        // Check if given date and time fields are valid
+        let datetime_fields: Result<Vec<DateTimeField>, anyhow::Error> = value
+            .datetime_fields
+            .into_iter()
+            .map(|datetime| {
+                let schema_id = SchemaId::from_str(&datetime.schema_id).map_err(|_| {
+                    anyhow!(
+                        "Invalid schema id '{}' found in 'datetime_fields' list",
+                        datetime.schema_id
+                    )
+                })?;
+
+                // Fields can't hold decimals and dates at the same time
+                if decimal_fields.iter().any(|decimal| {
+                    decimal.schema_id == schema_id && decimal.field == datetime.field
+                }) {
+                    return Err(anyhow!(
+                        "Field '{}' in 'datetime_fields' list is also a decimal field",
+                        datetime.field
+                    ));
+                }
+
+                Ok(DateTimeField::new(&schema_id, &datetime.field))
+            })
+            .collect();
+
+        // Check if given prefetch profiles are valid
+        let prefetch_profiles: Result<Vec<PrefetchProfile>, anyhow::Error> = value
+            .prefetch_profiles
+            .into_iter()
+            .map(|profile| {
+                let schema_id = SchemaId::from_str(&profile.schema_id).map_err(|_| {
+                    anyhow!(
+                        "Invalid schema id '{}' found in 'prefetch_profiles' list",
+                        profile.schema_id
+                    )
+                })?;
+
+                let relations = profile
+                    .relations
+                    .iter()
+                    .map(|path| {
+                        RelationPath::from_str(path)
+                            .map_err(|err| anyhow!("{err} in 'prefetch_profiles' list"))
+                    })
+                    .collect::<Result<Vec<RelationPath>, anyhow::Error>>()?;
+
+                Ok(PrefetchProfile::new(&schema_id, &relations))
+            })
+            .collect();
+
+        // Check if given metrics push target is valid
+        let metrics_push_target = match value.metrics_push_target {
+            Some(str_value) => Some(
+                MetricsTarget::from_str(&str_value)
+                    .map_err(|err| anyhow!("{err} found in 'metrics_push_target'"))?,
+            ),
+            None => None,
+        };
+
+        // Check if given webhooks are valid
+        let webhooks: Result<Vec<Webhook>, anyhow::Error> = value
+            .webhooks
+            .into_iter()
+            .map(|webhook| {
+                let schema_id = SchemaId::from_str(&webhook.schema_id).map_err(|_| {
+                    anyhow!(
+                        "Invalid schema id '{}' found in 'webhooks' list",
+                        webhook.schema_id
+                    )
+                })?;
+
+                let mut checked = Webhook::new(&webhook.url, &schema_id)
+                    .map_err(|err| anyhow!("{err} found in 'webhooks' list"))?;
+
+                if !webhook.changes.is_empty() {
+                    let changes = webhook
+                        .changes
+                        .iter()
+                        .map(|change| match change.as_str() {
+                            "create" => Ok(DocumentChange::Create),
+                            "update" => Ok(DocumentChange::Update),
+                            "delete" => Ok(DocumentChange::Delete),
+                            _ => Err(anyhow!(
+                                "Invalid change '{}' found in 'webhooks' list",
+                                change
+                            )),
+                        })
+                        .collect::<Result<Vec<DocumentChange>, anyhow::Error>>()?;
+                    checked = checked.with_changes(&changes);
+                }
+
+                if let Some(secret) = &webhook.secret {
+                    checked = checked.with_secret(secret);
+                }
+
+                Ok(checked)
+            })
+            .collect();
+
+        // Check if given TLS domains and ACME settings are valid
+        if value.tls_domains.iter().any(|domain| domain.is_empty()) {
+            return Err(anyhow!("Invalid empty domain found in 'tls_domains' list"));
+        }
+
+        if let Some(contact) = value
+            .acme_contacts
+            .iter()
+            .find(|contact| !contact.contains('@'))
+        {
+            return Err(anyhow!(
+                "Invalid email address '{contact}' found in 'acme_contacts' list"
+            ));
+        }
+
+        if !value.acme_directory_url.starts_with("https://") {
+            return Err(anyhow!(
+                "Invalid URL '{}' found in 'acme_directory_url'",
+                value.acme_directory_url
+            ));
+        }
+
+        if value.rendezvous_min_ttl > value.rendezvous_max_ttl {
+            return Err(anyhow!(
+                "'rendezvous_min_ttl' needs to be smaller than 'rendezvous_max_ttl'"
+            ));
+        }
+
+        if value.bootstrap_peer_expiry == 0 {
+            return Err(anyhow!("'bootstrap_peer_expiry' needs to be larger than 0"));
+        }
+
+        if value.vacuum_interval == Some(0) {
+            return Err(anyhow!("'vacuum_interval' needs to be larger than 0"));
+        }
+
+        if value.blobs_pack_threshold == Some(0) {
+            return Err(anyhow!("'blobs_pack_threshold' needs to be larger than 0"));
+        }
+
+        if value.encrypt_blobs && value.database_key.is_none() {
+            return Err(anyhow!(
+                "'encrypt_blobs' requires a 'database_key' to be set"
+            ));
+        }
+
+        // Create a temporary blobs directory when none was given
+        let blobs_base_path = match value.blobs_base_path {
+            Some(path) => path,
+            None => temporary_blobs_base_path(),
+        };
 
         let relay_addresses = value.relay_addresses.into_iter().map(From::from).collect();
         let direct_node_addresses = value
@@ -307,6 +1843,13 @@ impl TryFrom<ConfigFile> for Configuration {
             .into_iter()
             .map(From::from)
             .collect();
+        let bootstrap_peers = value.bootstrap_peers.into_iter().map(From::from).collect();
+
+        if !(0.0..1.0).contains(&value.simulated_packet_loss) {
+            return Err(anyhow!(
+                "'simulated_packet_loss' needs to be at least 0.0 and less than 1.0"
+            ));
+        }
 
         // `PreSharedKey` expects to parse key string from a multi-line string in the following format.
         let psk = if let Some(psk) = value.psk {
@@ -316,25 +1859,140 @@ impl TryFrom<ConfigFile> for Configuration {
             None
         };
 
+        let mut network = NetworkConfiguration {
+            transport: value.transport,
+            psk,
+            port: value.node_port,
+            ip_version: value.ip_version,
+            port_v6: value.node_port_v6,
+            mdns: value.mdns,
+            direct_node_addresses,
+            bootstrap_peers,
+            bootstrap_target_connections: value.bootstrap_target_connections,
+            bootstrap_peer_expiry: value.bootstrap_peer_expiry,
+            allow_peer_ids,
+            block_peer_ids: value.block_peer_ids,
+            operator_private_key: value.operator_private_key,
+            allow_operator_keys,
+            relay_addresses,
+            relay_mode: value.relay_mode,
+            network_name: value.network_name,
+            rendezvous_min_ttl: value.rendezvous_min_ttl,
+            rendezvous_max_ttl: value.rendezvous_max_ttl,
+            rendezvous_max_registrations: value.rendezvous_max_registrations,
+            simulation: NetworkSimulation {
+                latency: Duration::from_millis(value.simulated_latency),
+                jitter: Duration::from_millis(value.simulated_jitter),
+                packet_loss: value.simulated_packet_loss,
+            },
+            ..Default::default()
+        };
+
+        // Add addresses of nodes we want to join
+        for ticket in &value.join_tickets {
+            ConnectionTicket::from_str(ticket)
+                .and_then(|ticket| ticket.apply(&mut network))
+                .map_err(|err| anyhow!("{err} found in 'join_tickets'"))?;
+        }
+
+        // Check if given network name results in a valid rendezvous namespace
+        if network.network_name.as_ref().is_some_and(String::is_empty)
+            || Namespace::new(network.rendezvous_namespace()).is_err()
+        {
+            return Err(anyhow!("Invalid network name given in 'network_name'"));
+        }
+
+        // Instances of a cluster need to reach the same database
+        if value.cluster_instance.is_some() && !value.database_url.starts_with("postgres") {
+            return Err(anyhow!(
+                "'cluster_instance' requires a PostgreSQL database in 'database_url'"
+            ));
+        }
+
+        let schema_version_policy = value.schema_version_policy;
+        let schema_version_policy =
+            SchemaVersionPolicy::from_str(&schema_version_policy).map_err(|_| {
+                anyhow!("Invalid policy '{schema_version_policy}' found in 'schema_version_policy'")
+            })?;
+
         Ok(Configuration {
             allow_schema_ids,
+            allow_schema_pins,
+            schema_version_policy,
             database_url: value.database_url,
             database_max_connections: value.database_max_connections,
+            database_key: value.database_key,
+            database_isolation_level,
+            database_max_retries: value.database_max_retries,
+            archive_database_url: value.archive_database_url,
+            archive_threshold: value.archive_threshold,
             http_port: value.http_port,
+            http_bind_address: value.http_bind_address,
+            blobs_http_address: value.blobs_http_address,
+            http_identity: value.http_identity,
+            tls_domains: value.tls_domains,
+            https_port: value.https_port,
+            acme_contacts: value.acme_contacts,
+            acme_directory_url: value.acme_directory_url,
+            acme_cache_path: value.acme_cache_path,
+            graphql_rate_limit: value.graphql_rate_limit,
+            graphql_max_concurrent_requests: value.graphql_max_concurrent_requests,
+            graphql_max_body_size: value.graphql_max_body_size,
+            graphql_max_relation_depth: value.graphql_max_relation_depth,
+            graphql_max_related_documents: value.graphql_max_related_documents,
+            graphql_error_details: value.graphql_error_details,
+            graphql_snapshot_ttl: value.graphql_snapshot_ttl,
+            graphql_ws_max_subscriptions: value.graphql_ws_max_subscriptions,
+            graphql_ws_idle_timeout: value.graphql_ws_idle_timeout,
+            blobs_rate_limit: value.blobs_rate_limit,
+            blobs_max_concurrent_requests: value.blobs_max_concurrent_requests,
+            blobs_max_body_size: value.blobs_max_body_size,
             blobs_base_path,
+            blobs_pack_threshold: value.blobs_pack_threshold,
+            encrypt_blobs: value.encrypt_blobs,
+            compact_blob_pieces: value.compact_blob_pieces,
+            allow_blob_mime_types,
+            blob_mime_type_mismatch,
+            blob_transcoders,
+            proxy_blobs: value.proxy_blobs,
             worker_pool_size: value.worker_pool_size,
-            network: NetworkConfiguration {
-                transport: value.transport,
-                psk,
-                port: value.node_port,
-                mdns: value.mdns,
-                direct_node_addresses,
-                allow_peer_ids,
-                block_peer_ids: value.block_peer_ids,
-                relay_addresses,
-                relay_mode: value.relay_mode,
-                ..Default::default()
+            dependency_fan_out: value.dependency_fan_out,
+            task_dedup_window: value.task_dedup_window,
+            schema_task_weights,
+            materializer_events_socket: value.materializer_events_socket,
+            latest_view_only_schemas,
+            max_document_views,
+            schema_settings,
+            document_view_cache_size: value.document_view_cache_size,
+            prefetch_profiles: prefetch_profiles?,
+            log_state_cache_size: value.log_state_cache_size,
+            document_stats: value.document_stats,
+            field_constraints: field_constraints?,
+            decimal_fields,
+            datetime_fields: datetime_fields?,
+            require_canonical_encoding: value.require_canonical_encoding,
+            capability_schema_id,
+            admin_public_keys: admin_public_keys?,
+            read_acl_field: value.read_acl_field,
+            idempotency_window: value.idempotency_window,
+            service_accounts,
+            node_profile_schema_id,
+            node_profile: NodeProfile {
+                contact: value.node_profile.contact,
+                features: value.node_profile.features,
+                tos_url: value.node_profile.tos_url,
             },
+            compression: compression?,
+            replication_mode,
+            replication_modes: replication_modes?,
+            replication_directions: replication_directions?,
+            metrics_push_target,
+            metrics_push_interval: value.metrics_push_interval,
+            webhooks: webhooks?,
+            vacuum_interval: value.vacuum_interval,
+            cluster_instance: value.cluster_instance,
+            cluster_lease_duration: value.cluster_lease_duration,
+            network,
         })
     }
 }
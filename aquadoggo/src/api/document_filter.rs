@@ -0,0 +1,254 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::OperationValue;
+use p2panda_rs::schema::Schema;
+use p2panda_rs::storage_provider::error::DocumentStorageError;
+
+use crate::db::query::errors::QueryError;
+use crate::db::query::{validate_query, Field, Filter, MetaField, Order, Select};
+use crate::db::SqlStore;
+
+/// Filter for document subscriptions, mirroring the `filter` and `meta` arguments of collection
+/// queries and `all_<SCHEMA_ID>` subscriptions in the GraphQL API.
+///
+/// Subscriptions via `Node::subscribe_documents` and the GraphQL API evaluate filters the same
+/// way. All conditions need to match. Deleted documents never match.
+///
+/// ```
+/// # use aquadoggo::DocumentFilter;
+/// let filter = DocumentFilter::new()
+///     .eq("room", "lobby")
+///     .gte("timestamp", 1699999999_i64);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentFilter(Filter);
+
+impl DocumentFilter {
+    /// Returns a filter matching all documents which are not deleted.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match documents where the field equals the value.
+    pub fn eq(mut self, field: &str, value: impl Into<OperationValue>) -> Self {
+        self.0.add(&field.into(), &value.into());
+        self
+    }
+
+    /// Match documents where the field does not equal the value.
+    pub fn not_eq(mut self, field: &str, value: impl Into<OperationValue>) -> Self {
+        self.0.add_not(&field.into(), &value.into());
+        self
+    }
+
+    /// Match documents where the field equals any of the values.
+    pub fn is_in(mut self, field: &str, values: Vec<OperationValue>) -> Self {
+        self.0.add_in(&field.into(), &values);
+        self
+    }
+
+    /// Match documents where the field equals none of the values.
+    pub fn not_in(mut self, field: &str, values: Vec<OperationValue>) -> Self {
+        self.0.add_not_in(&field.into(), &values);
+        self
+    }
+
+    /// Match documents where the field is greater than the value.
+    pub fn gt(mut self, field: &str, value: impl Into<OperationValue>) -> Self {
+        self.0.add_gt(&field.into(), &value.into());
+        self
+    }
+
+    /// Match documents where the field is greater than or equals the value.
+    pub fn gte(mut self, field: &str, value: impl Into<OperationValue>) -> Self {
+        self.0.add_gte(&field.into(), &value.into());
+        self
+    }
+
+    /// Match documents where the field is lower than the value.
+    pub fn lt(mut self, field: &str, value: impl Into<OperationValue>) -> Self {
+        self.0.add_lt(&field.into(), &value.into());
+        self
+    }
+
+    /// Match documents where the field is lower than or equals the value.
+    pub fn lte(mut self, field: &str, value: impl Into<OperationValue>) -> Self {
+        self.0.add_lte(&field.into(), &value.into());
+        self
+    }
+
+    /// Match documents where the string field contains the given text.
+    pub fn contains(mut self, field: &str, value: &str) -> Self {
+        self.0.add_contains(&field.into(), value);
+        self
+    }
+
+    /// Match documents where the string field does not contain the given text.
+    pub fn not_contains(mut self, field: &str, value: &str) -> Self {
+        self.0.add_not_contains(&field.into(), value);
+        self
+    }
+
+    /// Match documents created by the given public key.
+    pub fn owner(mut self, public_key: &PublicKey) -> Self {
+        self.0.add(
+            &Field::Meta(MetaField::Owner),
+            &OperationValue::String(public_key.to_string()),
+        );
+        self
+    }
+
+    /// Match the document with the given id.
+    pub fn document_id(mut self, document_id: &DocumentId) -> Self {
+        self.0.add(
+            &Field::Meta(MetaField::DocumentId),
+            &OperationValue::String(document_id.to_string()),
+        );
+        self
+    }
+
+    /// Match documents with the given view as their current view.
+    pub fn view_id(mut self, view_id: &DocumentViewId) -> Self {
+        self.0.add(
+            &Field::Meta(MetaField::DocumentViewId),
+            &OperationValue::String(view_id.to_string()),
+        );
+        self
+    }
+
+    /// Match documents which were updated after they got created, or the ones which were not.
+    pub fn edited(mut self, edited: bool) -> Self {
+        self.0.add(
+            &Field::Meta(MetaField::Edited),
+            &OperationValue::Boolean(edited),
+        );
+        self
+    }
+
+    /// Make sure the filtered fields exist in the schema and the values are of the right type.
+    pub(crate) fn validate(&self, schema: &Schema) -> Result<(), QueryError> {
+        validate_query(&Select::default(), &self.0, &Order::default(), schema)
+    }
+
+    /// Returns true if the current view of the given document matches the filter.
+    ///
+    /// The filter is evaluated by the database, exactly like it is when querying collections.
+    pub(crate) async fn matches(
+        &self,
+        store: &SqlStore,
+        schema: &Schema,
+        document_id: &DocumentId,
+    ) -> Result<bool, DocumentStorageError> {
+        store
+            .document_matches_filter(schema, document_id, &self.0)
+            .await
+    }
+}
+
+impl From<Filter> for DocumentFilter {
+    fn from(filter: Filter) -> Self {
+        Self(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::test_utils::{
+        add_document, add_schema, doggo_schema, test_runner, update_document, TestNode,
+    };
+
+    use super::DocumentFilter;
+
+    #[test]
+    fn validate_against_schema() {
+        let schema = doggo_schema();
+
+        assert!(DocumentFilter::new()
+            .eq("username", "bubu")
+            .gt("height", 2.5)
+            .lte("age", 30_i64)
+            .validate(&schema)
+            .is_ok());
+
+        // Unknown field
+        assert!(DocumentFilter::new()
+            .eq("colour", "brown")
+            .validate(&schema)
+            .is_err());
+
+        // Wrong value type
+        assert!(DocumentFilter::new()
+            .eq("age", OperationValue::String("old".into()))
+            .validate(&schema)
+            .is_err());
+    }
+
+    #[rstest]
+    fn matches_documents(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![
+                    ("name", FieldType::String),
+                    ("capacity", FieldType::Integer),
+                ],
+                &key_pair,
+            )
+            .await;
+            let create_view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Pub".into()), ("capacity", 40_i64.into())],
+                &key_pair,
+            )
+            .await;
+            let document_id = DocumentId::new(create_view_id.graph_tips().first().unwrap());
+            let view_id = update_document(
+                &mut node,
+                schema.id(),
+                vec![("capacity", 80_i64.into())],
+                &create_view_id,
+                &key_pair,
+            )
+            .await;
+
+            let store = &node.context.store;
+            let is_match = |filter: DocumentFilter| {
+                let schema = schema.clone();
+                let document_id = document_id.clone();
+                async move {
+                    filter.validate(&schema).unwrap();
+                    filter.matches(store, &schema, &document_id).await.unwrap()
+                }
+            };
+
+            // Fields and meta fields are filtered like in collection queries
+            assert!(is_match(DocumentFilter::new()).await);
+            assert!(
+                is_match(
+                    DocumentFilter::new()
+                        .eq("name", "Pub")
+                        .gt("capacity", 50_i64)
+                )
+                .await
+            );
+            assert!(!is_match(DocumentFilter::new().lt("capacity", 50_i64)).await);
+            assert!(is_match(DocumentFilter::new().owner(&key_pair.public_key())).await);
+            assert!(is_match(DocumentFilter::new().document_id(&document_id)).await);
+            assert!(is_match(DocumentFilter::new().view_id(&view_id)).await);
+            assert!(!is_match(DocumentFilter::new().view_id(&create_view_id)).await);
+            assert!(is_match(DocumentFilter::new().edited(true)).await);
+            assert!(!is_match(DocumentFilter::new().edited(false)).await);
+        });
+    }
+}
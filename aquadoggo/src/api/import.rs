@@ -0,0 +1,367 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context as AnyhowContext, Result};
+use p2panda_rs::api::publish;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
+use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::Schematic;
+use p2panda_rs::operation::{EncodedOperation, OperationId};
+use p2panda_rs::storage_provider::traits::{EntryStore, LogStore, OperationStore};
+
+use crate::api::DocumentBundle;
+use crate::schema::SchemaProvider;
+
+/// File extension of raw encoded entries in an import directory.
+const ENTRY_EXTENSION: &str = "entry";
+
+/// File extension of raw encoded operations in an import directory.
+const OPERATION_EXTENSION: &str = "operation";
+
+/// Encoded entry and the encoded operation it signs.
+pub type ImportCommit = (EncodedEntry, EncodedOperation);
+
+/// Result of importing entries and operations into the node database.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Operations which have been published during the import.
+    pub imported: Vec<OperationId>,
+
+    /// Number of entries which already existed in the database.
+    pub existing: usize,
+
+    /// Entries which could not be imported, with the reason why.
+    pub failed: Vec<(Hash, String)>,
+}
+
+/// Decode a stream of raw encoded entries and operations.
+///
+/// The stream is a sequence of CBOR arrays, each holding the bytes of an encoded entry and the
+/// bytes of its encoded operation: `[entry, operation][entry, operation]..`.
+///
+/// A signed document bundle is accepted as well, it gets verified before its entries and
+/// operations are returned.
+pub fn decode_commits(bytes: &[u8]) -> Result<Vec<ImportCommit>> {
+    if let Ok(bundle) = DocumentBundle::from_bytes(bytes) {
+        bundle.verify().context("Invalid document bundle")?;
+        return Ok(bundle.commits);
+    }
+
+    let mut reader = Cursor::new(bytes);
+    let mut commits = Vec::new();
+
+    while (reader.position() as usize) < bytes.len() {
+        let commit: ImportCommit = ciborium::de::from_reader(&mut reader)
+            .map_err(|err| anyhow!("Invalid encoding of entry and operation: {}", err))?;
+        commits.push(commit);
+    }
+
+    Ok(commits)
+}
+
+/// Read raw encoded entries and operations from a file or directory.
+///
+/// A file is decoded as a stream of entries and operations, see `decode_commits`. A directory
+/// contains pairs of files with the same name holding the raw bytes of an entry and its operation,
+/// for example `0001.entry` and `0001.operation`.
+pub fn read_commits(path: &Path) -> Result<Vec<ImportCommit>> {
+    if !path.is_dir() {
+        let bytes =
+            fs::read(path).with_context(|| format!("Could not read '{}'", path.display()))?;
+        return decode_commits(&bytes);
+    }
+
+    let mut entry_paths: Vec<_> = fs::read_dir(path)?
+        .map(|dir_entry| dir_entry.map(|dir_entry| dir_entry.path()))
+        .collect::<Result<_, _>>()?;
+    entry_paths.retain(|path| path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION));
+    entry_paths.sort();
+
+    entry_paths
+        .into_iter()
+        .map(|entry_path| {
+            let operation_path = entry_path.with_extension(OPERATION_EXTENSION);
+            if !operation_path.exists() {
+                bail!("Missing operation file for '{}'", entry_path.display());
+            }
+
+            let entry = EncodedEntry::from_bytes(&fs::read(&entry_path)?);
+            let operation = EncodedOperation::from_bytes(&fs::read(&operation_path)?);
+            Ok((entry, operation))
+        })
+        .collect()
+}
+
+/// Validate and publish raw entries and operations in the node database.
+///
+/// Entries are sorted by their log and sequence number first, so they can be given in any order.
+/// Every entry and operation runs through the same validation as published ones. Entries which
+/// are invalid or use a schema unknown to the node are reported as failed without aborting the
+/// import, schemas need to be materialized before their documents can be imported.
+pub async fn import<S>(
+    store: &S,
+    schema_provider: &SchemaProvider,
+    commits: Vec<ImportCommit>,
+) -> Result<ImportReport>
+where
+    S: OperationStore + EntryStore + LogStore,
+{
+    let mut report = ImportReport::default();
+
+    let mut decoded = Vec::new();
+    for (encoded_entry, encoded_operation) in commits {
+        match decode_entry(&encoded_entry) {
+            Ok(entry) => decoded.push((entry, encoded_entry, encoded_operation)),
+            Err(err) => report.failed.push((encoded_entry.hash(), err.to_string())),
+        }
+    }
+
+    // Entries need to be published in order as every one of them refers back to its predecessor
+    decoded.sort_by_cached_key(|(entry, _, _)| {
+        (
+            entry.public_key().to_string(),
+            entry.log_id().as_u64(),
+            entry.seq_num().as_u64(),
+        )
+    });
+
+    for (entry, encoded_entry, encoded_operation) in decoded {
+        let existing_entry = store
+            .get_entry_at_seq_num(entry.public_key(), entry.log_id(), entry.seq_num())
+            .await
+            .context("Internal database error occurred while retrieving entry")?;
+
+        // Check if node already knows about this entry
+        if let Some(existing_entry) = existing_entry {
+            if existing_entry.hash() == encoded_entry.hash() {
+                report.existing += 1;
+            } else {
+                report.failed.push((
+                    encoded_entry.hash(),
+                    "Entry conflicts with existing entry at same sequence number".into(),
+                ));
+            }
+
+            continue;
+        }
+
+        let operation = match decode_operation(&encoded_operation) {
+            Ok(operation) => operation,
+            Err(err) => {
+                report.failed.push((encoded_entry.hash(), err.to_string()));
+                continue;
+            }
+        };
+
+        let schema = match schema_provider.get(operation.schema_id()).await {
+            Some(schema) => schema,
+            None => {
+                report.failed.push((
+                    encoded_entry.hash(),
+                    format!("Schema {} not found", operation.schema_id()),
+                ));
+                continue;
+            }
+        };
+
+        if let Err(err) = schema_provider.check_constraints(&schema, &operation) {
+            report.failed.push((encoded_entry.hash(), err.to_string()));
+            continue;
+        }
+
+        match publish(
+            store,
+            &schema,
+            &encoded_entry,
+            &operation,
+            &encoded_operation,
+        )
+        .await
+        {
+            Ok(_) => report.imported.push(encoded_entry.hash().into()),
+            Err(err) => report.failed.push((encoded_entry.hash(), err.to_string())),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use p2panda_rs::entry::decode::decode_entry;
+    use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
+    use p2panda_rs::entry::{EncodedEntry, LogId, SeqNum};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::storage_provider::traits::OperationStore;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    use crate::config::AllowList;
+    use crate::db::SqlStore;
+    use crate::schema::SchemaProvider;
+    use crate::test_utils::{
+        populate_store, populate_store_config, test_runner_with_manager, PopulateStoreConfig,
+        TestNodeManager,
+    };
+
+    use super::{decode_commits, import, read_commits, ImportCommit};
+
+    /// Returns all entries and operations of a store populated with the given config.
+    async fn export_commits(store: &SqlStore, config: &PopulateStoreConfig) -> Vec<ImportCommit> {
+        populate_store(store, config).await;
+
+        let mut commits = Vec::new();
+        for key_pair in &config.authors {
+            for log_id in 0..config.no_of_logs {
+                let entries = store
+                    .get_entries_from(
+                        &key_pair.public_key(),
+                        &LogId::new(log_id as u64),
+                        &SeqNum::default(),
+                    )
+                    .await
+                    .unwrap();
+
+                for entry in entries {
+                    commits.push((
+                        EncodedEntry::from_bytes(&entry.into_bytes()),
+                        entry.payload().unwrap().to_owned(),
+                    ));
+                }
+            }
+        }
+
+        commits
+    }
+
+    #[rstest]
+    fn imports_entries_and_operations(
+        #[from(populate_store_config)]
+        #[with(4, 2, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let source = manager.create().await;
+            let node = manager.create().await;
+
+            let commits = export_commits(&source.context.store, &config).await;
+            let schema_provider =
+                SchemaProvider::new(vec![config.schema.clone()], AllowList::Wildcard);
+
+            // Entries can be imported in any order
+            let mut reversed = commits.clone();
+            reversed.reverse();
+
+            let report = import(&node.context.store, &schema_provider, reversed)
+                .await
+                .unwrap();
+            assert_eq!(report.imported.len(), 8);
+            assert!(report.failed.is_empty());
+
+            for operation_id in &report.imported {
+                assert!(node
+                    .context
+                    .store
+                    .get_operation(operation_id)
+                    .await
+                    .unwrap()
+                    .is_some());
+            }
+
+            // Importing the same data again does not change anything
+            let report = import(&node.context.store, &schema_provider, commits)
+                .await
+                .unwrap();
+            assert!(report.imported.is_empty());
+            assert_eq!(report.existing, 8);
+        });
+    }
+
+    #[rstest]
+    fn reports_invalid_entries(
+        #[from(populate_store_config)]
+        #[with(2, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let source = manager.create().await;
+            let node = manager.create().await;
+
+            let commits = export_commits(&source.context.store, &config).await;
+
+            // Node does not know about the schema
+            let schema_provider = SchemaProvider::new(vec![], AllowList::Wildcard);
+            let report = import(&node.context.store, &schema_provider, commits.clone())
+                .await
+                .unwrap();
+            assert!(report.imported.is_empty());
+            assert_eq!(report.failed.len(), 2);
+
+            // Second entry misses its predecessor
+            let schema_provider =
+                SchemaProvider::new(vec![config.schema.clone()], AllowList::Wildcard);
+            let second_entry = commits
+                .into_iter()
+                .filter(|(entry, _)| decode_entry(entry).unwrap().seq_num().as_u64() == 2)
+                .collect();
+            let report = import(&node.context.store, &schema_provider, second_entry)
+                .await
+                .unwrap();
+            assert!(report.imported.is_empty());
+            assert_eq!(report.failed.len(), 1);
+        });
+    }
+
+    #[rstest]
+    fn reads_commits_from_file_and_directory(
+        #[from(populate_store_config)]
+        #[with(2, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let source = manager.create().await;
+            let commits = export_commits(&source.context.store, &config).await;
+            let tmp_dir = TempDir::new().unwrap();
+
+            // Write all entries and operations as a stream into one file
+            let mut bytes = Vec::new();
+            for commit in &commits {
+                ciborium::ser::into_writer(commit, &mut bytes).unwrap();
+            }
+            let file_path = tmp_dir.path().join("export.cbor");
+            fs::write(&file_path, &bytes).unwrap();
+
+            assert_eq!(decode_commits(&bytes).unwrap(), commits);
+            assert_eq!(read_commits(&file_path).unwrap(), commits);
+
+            // Write entries and operations as pairs of files into a directory
+            let dir_path = tmp_dir.path().join("export");
+            fs::create_dir(&dir_path).unwrap();
+            for (index, (entry, operation)) in commits.iter().enumerate() {
+                fs::write(dir_path.join(format!("{index}.entry")), entry.into_bytes()).unwrap();
+                fs::write(
+                    dir_path.join(format!("{index}.operation")),
+                    operation.into_bytes(),
+                )
+                .unwrap();
+            }
+
+            assert_eq!(read_commits(&dir_path).unwrap(), commits);
+
+            // Invalid bytes can't be decoded
+            assert!(decode_commits(&[1, 2, 3]).is_err());
+
+            // Every entry needs an operation
+            fs::remove_file(dir_path.join("0.operation")).unwrap();
+            assert!(read_commits(&dir_path).is_err());
+        });
+    }
+}
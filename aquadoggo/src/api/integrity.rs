@@ -0,0 +1,258 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Report on relations pointing at documents which are missing, deleted or of an unexpected
+//! schema.
+use anyhow::Result;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::{FieldName, FieldType, SchemaId};
+
+use crate::db::SqlStore;
+use crate::schema::SchemaProvider;
+
+/// Reason a relation is dangling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DanglingReason {
+    /// The related document or view is not materialized on this node, for example because it was
+    /// not replicated (yet) or got garbage collected.
+    Missing,
+
+    /// The related document got deleted.
+    Deleted,
+
+    /// The related document is of another schema than the one expected by the field.
+    UnexpectedSchema(SchemaId),
+}
+
+/// Relation pointing at a document or document view which can not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingRelation {
+    /// Id of the document containing the relation.
+    pub document_id: DocumentId,
+
+    /// Name of the field containing the relation.
+    pub field: FieldName,
+
+    /// Document id or document view id the relation points at.
+    pub target: String,
+
+    /// Why the relation can not be resolved.
+    pub reason: DanglingReason,
+}
+
+/// Relations of the documents of one schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaRelations {
+    /// Id of the schema.
+    pub schema_id: SchemaId,
+
+    /// Number of relations in the latest views of all documents of this schema.
+    pub relations: usize,
+
+    /// Relations which can not be resolved.
+    pub dangling: Vec<DanglingRelation>,
+}
+
+/// Report on the integrity of relations across all schemas, see `check_relations`.
+#[derive(Debug, Default)]
+pub struct RelationReport {
+    /// Relations grouped by schema, schemas without any relations are omitted.
+    pub schemas: Vec<SchemaRelations>,
+}
+
+impl RelationReport {
+    /// Returns true if all relations can be resolved.
+    pub fn is_empty(&self) -> bool {
+        self.schemas
+            .iter()
+            .all(|schema_relations| schema_relations.dangling.is_empty())
+    }
+}
+
+/// Returns the schema a relation field is expected to point at.
+fn expected_schema_id(field_type: &FieldType) -> Option<&SchemaId> {
+    match field_type {
+        FieldType::Relation(schema_id)
+        | FieldType::RelationList(schema_id)
+        | FieldType::PinnedRelation(schema_id)
+        | FieldType::PinnedRelationList(schema_id) => Some(schema_id),
+        _ => None,
+    }
+}
+
+/// Check the relations in the latest views of all documents of all known schemas.
+///
+/// Relations are dangling when the related document or view is missing on this node, got deleted
+/// or is of another schema than the field expects. Deleted documents are not checked themselves.
+pub async fn check_relations(
+    store: &SqlStore,
+    schema_provider: &SchemaProvider,
+) -> Result<RelationReport> {
+    let mut schemas = schema_provider.all().await;
+    schemas.sort_by_key(|schema| schema.id().to_string());
+
+    let mut report = RelationReport::default();
+
+    for schema in schemas {
+        let relations = store.get_relations_by_schema(schema.id()).await?;
+        if relations.is_empty() {
+            continue;
+        }
+
+        let mut schema_relations = SchemaRelations {
+            schema_id: schema.id().to_owned(),
+            relations: relations.len(),
+            dangling: Vec::new(),
+        };
+
+        for relation in relations {
+            let expected_schema_id = schema
+                .fields()
+                .get(&relation.field)
+                .and_then(expected_schema_id);
+
+            let reason = match relation.target_state {
+                None => DanglingReason::Missing,
+                Some(target) if target.is_deleted => DanglingReason::Deleted,
+                Some(target) if expected_schema_id != Some(&target.schema_id) => {
+                    DanglingReason::UnexpectedSchema(target.schema_id)
+                }
+                Some(_) => continue,
+            };
+
+            schema_relations.dangling.push(DanglingRelation {
+                document_id: relation.document_id,
+                field: relation.field,
+                target: relation.target,
+                reason,
+            });
+        }
+
+        report.schemas.push(schema_relations);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::{DocumentId, DocumentViewId};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_id};
+    use rstest::rstest;
+
+    use crate::test_utils::{add_document, add_schema, delete_document, test_runner, TestNode};
+
+    use super::{check_relations, DanglingReason};
+
+    #[rstest]
+    fn reports_dangling_relations(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let venue_schema = add_schema(
+                &mut node,
+                "venue",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let person_schema = add_schema(
+                &mut node,
+                "person",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let event_schema = add_schema(
+                &mut node,
+                "event",
+                vec![
+                    (
+                        "pinned_venue",
+                        FieldType::PinnedRelation(venue_schema.id().to_owned()),
+                    ),
+                    ("venue", FieldType::Relation(venue_schema.id().to_owned())),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let mut view_ids = Vec::new();
+            for (schema_id, name) in [
+                (venue_schema.id(), "Panda Cafe"),
+                (venue_schema.id(), "Closed Cafe"),
+                (person_schema.id(), "Panda"),
+            ] {
+                let view_id =
+                    add_document(&mut node, schema_id, vec![("name", name.into())], &key_pair)
+                        .await;
+                view_ids.push(view_id);
+            }
+            let (venue_view_id, closed_venue_view_id, person_view_id) =
+                (&view_ids[0], &view_ids[1], &view_ids[2]);
+
+            let to_document_id =
+                |view_id: &DocumentViewId| -> DocumentId { view_id.to_string().parse().unwrap() };
+            let missing_document_id = random_document_id();
+
+            let mut events = Vec::new();
+            for venue_id in [
+                to_document_id(venue_view_id),
+                missing_document_id.clone(),
+                to_document_id(closed_venue_view_id),
+                to_document_id(person_view_id),
+            ] {
+                let event_view_id = add_document(
+                    &mut node,
+                    event_schema.id(),
+                    vec![
+                        ("pinned_venue", venue_view_id.clone().into()),
+                        ("venue", venue_id.into()),
+                    ],
+                    &key_pair,
+                )
+                .await;
+                events.push(to_document_id(&event_view_id));
+            }
+
+            delete_document(
+                &mut node,
+                venue_schema.id(),
+                closed_venue_view_id,
+                &key_pair,
+            )
+            .await;
+
+            let report = check_relations(&node.context.store, &node.context.schema_provider)
+                .await
+                .unwrap();
+            assert!(!report.is_empty());
+
+            // Only the events contain relations
+            assert_eq!(report.schemas.len(), 1);
+            let event_relations = &report.schemas[0];
+            assert_eq!(&event_relations.schema_id, event_schema.id());
+            assert_eq!(event_relations.relations, 8);
+            assert_eq!(event_relations.dangling.len(), 3);
+
+            let reason = |document_id: &DocumentId| {
+                event_relations
+                    .dangling
+                    .iter()
+                    .find(|relation| &relation.document_id == document_id)
+                    .map(|relation| {
+                        assert_eq!(relation.field, "venue");
+                        relation.reason.clone()
+                    })
+            };
+            assert_eq!(reason(&events[0]), None);
+            assert_eq!(reason(&events[1]), Some(DanglingReason::Missing));
+            assert_eq!(reason(&events[2]), Some(DanglingReason::Deleted));
+            assert_eq!(
+                reason(&events[3]),
+                Some(DanglingReason::UnexpectedSchema(
+                    person_schema.id().to_owned()
+                ))
+            );
+        });
+    }
+}
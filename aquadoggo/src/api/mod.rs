@@ -2,11 +2,25 @@
 
 #[allow(clippy::module_inception)]
 mod api;
+mod bundle;
 mod config_file;
+mod document_filter;
+mod import;
+mod integrity;
 mod lock_file;
 mod migration;
+mod profile;
+mod purge;
 
 pub use api::{NodeEvent, NodeInterface};
+pub use bundle::{export_document, export_document_bundle, DocumentBundle};
 pub use config_file::ConfigFile;
+pub use document_filter::DocumentFilter;
+pub use import::{decode_commits, import, read_commits, ImportCommit, ImportReport};
+pub use integrity::{
+    check_relations, DanglingReason, DanglingRelation, RelationReport, SchemaRelations,
+};
 pub use lock_file::LockFile;
 pub use migration::migrate;
+pub use profile::{peer_profiles, publish_node_profile, NodeProfile, PeerProfile};
+pub use purge::{purge_document, PurgeReport};
@@ -0,0 +1,296 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Self-describing profile documents nodes publish about themselves.
+use anyhow::{anyhow, Result};
+use libp2p::PeerId;
+use p2panda_rs::api::{next_args, publish};
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::entry::encode::sign_and_encode_entry;
+use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::encode::encode_operation;
+use p2panda_rs::operation::traits::AsOperation;
+use p2panda_rs::operation::{OperationAction, OperationBuilder, OperationId, OperationValue};
+use p2panda_rs::schema::SchemaId;
+use p2panda_rs::storage_provider::traits::DocumentStore;
+
+use crate::db::SqlStore;
+use crate::network::identity::to_libp2p_peer_id;
+use crate::schema::SchemaProvider;
+
+/// Name of the string field holding the contact of the node operator.
+pub const NODE_PROFILE_CONTACT_FIELD: &str = "contact";
+
+/// Name of the string field holding the comma-separated features supported by the node.
+pub const NODE_PROFILE_FEATURES_FIELD: &str = "features";
+
+/// Name of the string field holding the URL of the terms of service of the node.
+pub const NODE_PROFILE_TOS_URL_FIELD: &str = "tos_url";
+
+/// Separator between the features of a node in its profile document.
+const FEATURES_SEPARATOR: char = ',';
+
+/// Information about a node and its operator, published with the key pair of the node.
+///
+/// Profiles are documents of the configured node profile schema, they get replicated like any
+/// other document and help people in community networks to understand who runs which node. The
+/// schema needs the string fields "contact", "features" and "tos_url".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NodeProfile {
+    /// Contact of the node operator, for example an email address.
+    pub contact: String,
+
+    /// Features supported by the node, for example "blobs".
+    pub features: Vec<String>,
+
+    /// URL of the terms of service of the node.
+    pub tos_url: String,
+}
+
+impl NodeProfile {
+    /// Returns the fields of the profile document.
+    fn fields(&self) -> Vec<(&str, OperationValue)> {
+        vec![
+            (NODE_PROFILE_CONTACT_FIELD, self.contact.clone().into()),
+            (
+                NODE_PROFILE_FEATURES_FIELD,
+                self.features.join(&FEATURES_SEPARATOR.to_string()).into(),
+            ),
+            (NODE_PROFILE_TOS_URL_FIELD, self.tos_url.clone().into()),
+        ]
+    }
+
+    /// Reads the profile from a profile document, returns `None` if any field is missing.
+    fn from_document(document: &impl AsDocument) -> Option<Self> {
+        let get_string = |name: &str| match document.get(name) {
+            Some(OperationValue::String(value)) => Some(value.to_owned()),
+            _ => None,
+        };
+
+        let features = get_string(NODE_PROFILE_FEATURES_FIELD)?
+            .split(FEATURES_SEPARATOR)
+            .filter(|feature| !feature.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Some(Self {
+            contact: get_string(NODE_PROFILE_CONTACT_FIELD)?,
+            features,
+            tos_url: get_string(NODE_PROFILE_TOS_URL_FIELD)?,
+        })
+    }
+}
+
+/// Profile of another node which was discovered via replication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerProfile {
+    /// Public key of the node which published the profile.
+    pub public_key: PublicKey,
+
+    /// Peer id of the node, derived from its public key.
+    pub peer_id: PeerId,
+
+    /// Latest view of the profile document.
+    pub view_id: DocumentViewId,
+
+    /// Published profile.
+    pub profile: NodeProfile,
+}
+
+/// Publishes the profile of this node, updating a previously published profile document if it
+/// changed.
+///
+/// Returns the id of the published operation, `None` if the profile did not change.
+pub async fn publish_node_profile(
+    store: &SqlStore,
+    schema_provider: &SchemaProvider,
+    key_pair: &KeyPair,
+    schema_id: &SchemaId,
+    profile: &NodeProfile,
+) -> Result<Option<OperationId>> {
+    let schema = schema_provider
+        .get(schema_id)
+        .await
+        .ok_or_else(|| anyhow!("Node profile schema {} is not supported", schema_id))?;
+
+    let public_key = key_pair.public_key();
+    let previous = store
+        .get_documents_by_schema(schema_id)
+        .await?
+        .into_iter()
+        .find(|document| document.author() == &public_key);
+
+    let builder = match previous {
+        Some(document) => {
+            if NodeProfile::from_document(&document).as_ref() == Some(profile) {
+                return Ok(None);
+            }
+
+            OperationBuilder::new(schema_id)
+                .action(OperationAction::Update)
+                .previous(document.view_id())
+        }
+        None => OperationBuilder::new(schema_id),
+    };
+    let operation = builder.fields(&profile.fields()).build()?;
+    let encoded_operation = encode_operation(&operation)?;
+
+    let (backlink, skiplink, seq_num, log_id) =
+        next_args(store, &public_key, operation.previous()).await?;
+
+    let encoded_entry = sign_and_encode_entry(
+        &log_id,
+        &seq_num,
+        skiplink.as_ref(),
+        backlink.as_ref(),
+        &encoded_operation,
+        key_pair,
+    )?;
+
+    let plain_operation = decode_operation(&encoded_operation)?;
+    publish(
+        store,
+        &schema,
+        &encoded_entry,
+        &plain_operation,
+        &encoded_operation,
+    )
+    .await?;
+
+    Ok(Some(encoded_entry.hash().into()))
+}
+
+/// Returns the profiles of all other nodes known to this node.
+///
+/// Documents missing any of the profile fields are ignored.
+pub async fn peer_profiles(
+    store: &SqlStore,
+    schema_id: &SchemaId,
+    public_key: &PublicKey,
+) -> Result<Vec<PeerProfile>> {
+    let profiles = store
+        .get_documents_by_schema(schema_id)
+        .await?
+        .iter()
+        .filter(|document| document.author() != public_key)
+        .filter_map(|document| {
+            NodeProfile::from_document(document).map(|profile| PeerProfile {
+                public_key: document.author().to_owned(),
+                peer_id: to_libp2p_peer_id(document.author()),
+                view_id: document.view_id().to_owned(),
+                profile,
+            })
+        })
+        .collect();
+
+    Ok(profiles)
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::materializer::tasks::reduce_task;
+    use crate::materializer::TaskInput;
+    use crate::test_utils::{add_document, add_schema, test_runner, TestNode};
+
+    use super::{peer_profiles, publish_node_profile, NodeProfile};
+
+    #[rstest]
+    fn publish_and_discover_profiles(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "node_profile",
+                vec![
+                    ("contact", FieldType::String),
+                    ("features", FieldType::String),
+                    ("tos_url", FieldType::String),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let context = &node.context;
+            let mut profile = NodeProfile {
+                contact: "panda@example.org".into(),
+                features: vec!["blobs".into(), "proxy".into()],
+                tos_url: "https://example.org/tos".into(),
+            };
+
+            let operation_id = publish_node_profile(
+                &context.store,
+                &context.schema_provider,
+                &context.key_pair,
+                schema.id(),
+                &profile,
+            )
+            .await
+            .unwrap()
+            .expect("Profile got published");
+            let document_id = DocumentId::new(&operation_id);
+            reduce_task(context.clone(), TaskInput::DocumentId(document_id.clone()))
+                .await
+                .unwrap();
+
+            // Nothing gets published when the profile did not change
+            let result = publish_node_profile(
+                &context.store,
+                &context.schema_provider,
+                &context.key_pair,
+                schema.id(),
+                &profile,
+            )
+            .await
+            .unwrap();
+            assert!(result.is_none());
+
+            // Changed profiles update the existing document
+            profile.contact = "penguin@example.org".into();
+            let result = publish_node_profile(
+                &context.store,
+                &context.schema_provider,
+                &context.key_pair,
+                schema.id(),
+                &profile,
+            )
+            .await
+            .unwrap();
+            assert!(result.is_some());
+            reduce_task(context.clone(), TaskInput::DocumentId(document_id))
+                .await
+                .unwrap();
+
+            // Our own profile is not listed, profiles of other nodes are
+            let peer = KeyPair::new();
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![
+                    ("contact", OperationValue::String("".into())),
+                    ("features", OperationValue::String("blobs".into())),
+                    ("tos_url", OperationValue::String("".into())),
+                ],
+                &peer,
+            )
+            .await;
+
+            let context = &node.context;
+            let profiles =
+                peer_profiles(&context.store, schema.id(), &context.key_pair.public_key())
+                    .await
+                    .unwrap();
+            assert_eq!(profiles.len(), 1);
+            assert_eq!(profiles[0].public_key, peer.public_key());
+            assert_eq!(profiles[0].view_id, view_id);
+            assert_eq!(profiles[0].profile.features, vec!["blobs".to_string()]);
+        });
+    }
+}
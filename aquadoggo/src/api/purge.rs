@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Purge single documents on demand, with the same cleanup garbage collection does.
+use anyhow::Result;
+use log::debug;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::operation::traits::AsOperation;
+use p2panda_rs::operation::OperationId;
+use p2panda_rs::schema::SchemaId;
+use p2panda_rs::storage_provider::traits::OperationStore;
+use p2panda_rs::{Human, WithId};
+
+use crate::blobs::BlobStore;
+use crate::db::SqlStore;
+
+/// Report of what got removed when purging a document, see `purge_document`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PurgeReport {
+    /// Operations of the document which were removed together with their entries.
+    pub removed_operations: Vec<OperationId>,
+
+    /// Materialized views of the document which were removed.
+    pub removed_views: Vec<DocumentViewId>,
+
+    /// Blob documents which were purged together with the document, either because it is a blob
+    /// itself or because no other document relates to them anymore.
+    pub removed_blobs: Vec<DocumentId>,
+
+    /// Blob piece documents which were purged as no other blob uses them.
+    pub removed_blob_pieces: Vec<DocumentId>,
+
+    /// Blob views of which files were removed from the file system.
+    pub removed_blob_files: Vec<DocumentViewId>,
+
+    /// Documents the purged document related to, they might contain views which can be garbage
+    /// collected now.
+    pub affected_documents: Vec<DocumentId>,
+}
+
+impl PurgeReport {
+    /// Returns true if nothing was removed, for example because the document was purged before.
+    pub fn is_empty(&self) -> bool {
+        self.removed_operations.is_empty() && self.removed_views.is_empty()
+    }
+}
+
+/// Remove blob files of all views of a blob document from the file system.
+///
+/// Files which are already missing are skipped, this makes purging idempotent.
+async fn remove_blob_files(
+    blob_store: &BlobStore,
+    view_ids: &[DocumentViewId],
+    report: &mut PurgeReport,
+) -> Result<()> {
+    for view_id in view_ids {
+        if blob_store.remove(view_id).await? {
+            debug!("Deleted blob view from filesystem: {}", view_id);
+            report.removed_blob_files.push(view_id.to_owned());
+        }
+    }
+
+    Ok(())
+}
+
+/// Purge a document from the node right away: its entries, operations, materialized views, stats
+/// and history.
+///
+/// When `include_blobs` is set and the document is a blob, its pieces which are not used by other
+/// blobs and its files are removed as well. Blobs the document relates to are purged when no other
+/// document relates to them anymore. The logs of the document are kept to avoid collisions with
+/// log ids which were already used.
+///
+/// Purging a document which does not exist (anymore) returns an empty report.
+pub async fn purge_document(
+    store: &SqlStore,
+    blob_store: &BlobStore,
+    document_id: &DocumentId,
+    include_blobs: bool,
+) -> Result<PurgeReport> {
+    let mut report = PurgeReport::default();
+
+    let operations = store.get_operations_by_document_id(document_id).await?;
+    let schema_id = match operations.first() {
+        Some(operation) => operation.schema_id(),
+        None => return Ok(report),
+    };
+
+    report.removed_operations = operations
+        .iter()
+        .map(|operation| WithId::<OperationId>::id(operation).to_owned())
+        .collect();
+    report.removed_views = store.get_all_document_view_ids(document_id).await?;
+
+    // Collect the documents this one relates to before its views are gone
+    for view_id in &report.removed_views {
+        for child_id in store.get_child_document_ids(view_id).await? {
+            if !report.affected_documents.contains(&child_id) && &child_id != document_id {
+                report.affected_documents.push(child_id);
+            }
+        }
+    }
+
+    let related_blob_ids = if include_blobs {
+        store.get_blob_child_relations(document_id).await?
+    } else {
+        Vec::new()
+    };
+
+    debug!("Purge document: {}", document_id.display());
+
+    if include_blobs && schema_id == SchemaId::Blob(1) {
+        report.removed_blob_pieces = store.purge_blob_document(document_id).await?;
+        report.removed_blobs.push(document_id.to_owned());
+        remove_blob_files(blob_store, &report.removed_views, &mut report).await?;
+    } else {
+        store.purge_document(document_id).await?;
+    }
+
+    // Blobs are purged completely as soon as nothing relates to them anymore
+    for blob_id in related_blob_ids {
+        let view_ids = store.get_all_document_view_ids(&blob_id).await?;
+
+        if store.purge_blob(&blob_id).await? {
+            debug!("Purged blob from the database: {}", blob_id);
+            remove_blob_files(blob_store, &view_ids, &mut report).await?;
+            report
+                .affected_documents
+                .retain(|affected_id| affected_id != &blob_id);
+            report.removed_blobs.push(blob_id);
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_id};
+    use rstest::rstest;
+
+    use crate::materializer::tasks::blob_task;
+    use crate::materializer::TaskInput;
+    use crate::test_utils::{add_blob, add_document, add_schema, test_runner, TestNode};
+
+    use super::purge_document;
+
+    #[rstest]
+    fn purges_documents_idempotently(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Pub".into()))],
+                &key_pair,
+            )
+            .await;
+            let document_id = DocumentId::new(view_id.graph_tips().first().unwrap());
+
+            let context = &node.context;
+            let report = purge_document(&context.store, &context.blob_store, &document_id, true)
+                .await
+                .unwrap();
+            assert_eq!(report.removed_operations.len(), 1);
+            assert_eq!(report.removed_views, vec![view_id]);
+            assert!(context
+                .store
+                .get_document(&document_id)
+                .await
+                .unwrap()
+                .is_none());
+            assert!(context
+                .store
+                .get_operations_by_document_id(&document_id)
+                .await
+                .unwrap()
+                .is_empty());
+
+            // Purging again or purging unknown documents is not an error
+            let report = purge_document(&context.store, &context.blob_store, &document_id, true)
+                .await
+                .unwrap();
+            assert!(report.is_empty());
+
+            let report = purge_document(
+                &context.store,
+                &context.blob_store,
+                &random_document_id(),
+                false,
+            )
+            .await
+            .unwrap();
+            assert!(report.is_empty());
+        });
+    }
+
+    #[rstest]
+    fn purges_blobs(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_view_id = add_blob(
+                &mut node,
+                "Hello, Panda!".as_bytes(),
+                5,
+                "text/plain",
+                &key_pair,
+            )
+            .await;
+            let blob_id = DocumentId::new(blob_view_id.graph_tips().first().unwrap());
+            blob_task(
+                node.context.clone(),
+                TaskInput::DocumentViewId(blob_view_id.clone()),
+            )
+            .await
+            .unwrap();
+
+            let context = &node.context;
+            assert!(context
+                .blob_store
+                .len(&blob_view_id)
+                .await
+                .unwrap()
+                .is_some());
+
+            let report = purge_document(&context.store, &context.blob_store, &blob_id, true)
+                .await
+                .unwrap();
+            assert_eq!(report.removed_blobs, vec![blob_id.clone()]);
+            assert_eq!(report.removed_blob_pieces.len(), 3);
+            assert_eq!(report.removed_blob_files, vec![blob_view_id.clone()]);
+            assert!(context
+                .store
+                .get_document(&blob_id)
+                .await
+                .unwrap()
+                .is_none());
+            assert!(context
+                .blob_store
+                .len(&blob_view_id)
+                .await
+                .unwrap()
+                .is_none());
+
+            // Missing blob files are not an error
+            assert!(!context.blob_store.remove(&blob_view_id).await.unwrap());
+            let report = purge_document(&context.store, &context.blob_store, &blob_id, true)
+                .await
+                .unwrap();
+            assert!(report.is_empty());
+        });
+    }
+}
@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Tiering of storage into a main and an optional archive database.
+//!
+//! Historical data of documents which did not change for a while is moved into the archive
+//! database to keep the main database small and fast. Archived data is transparently loaded back
+//! by the store as soon as it is accessed again.
+mod service;
+
+pub use service::archive_service;
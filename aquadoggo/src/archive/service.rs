@@ -0,0 +1,52 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{debug, warn};
+use tokio::task;
+use tokio::time::interval;
+
+use crate::bus::ServiceSender;
+use crate::context::Context;
+use crate::manager::{ServiceReadySender, Shutdown};
+
+/// How often does the service check for inactive documents to archive.
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// The archive service periodically moves historical data of inactive documents from the main
+/// into the archive database.
+pub async fn archive_service(
+    context: Context,
+    shutdown: Shutdown,
+    _tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()> {
+    let store = context.store.clone();
+    let threshold = context.config.archive_threshold;
+
+    let handle = task::spawn(async move {
+        let mut interval = interval(ARCHIVE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match store.archive_inactive_documents(threshold).await {
+                Ok(0) => (),
+                Ok(count) => debug!("Moved {count} inactive documents into archive"),
+                Err(err) => warn!("Failed archiving inactive documents: {err}"),
+            }
+        }
+    });
+
+    if tx_ready.send(()).is_err() {
+        warn!("No subscriber informed about archive service being ready");
+    };
+
+    tokio::select! {
+        _ = handle => (),
+        _ = shutdown => (),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use log::info;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+
+use crate::authors::ServiceAccount;
+use crate::db::SqlStore;
+
+/// Key pairs of service accounts held by the node to sign entries on behalf of clients.
+#[derive(Clone, Default)]
+pub struct AuthorKeys {
+    /// Public keys of service accounts, indexed by their name.
+    accounts: Arc<HashMap<String, PublicKey>>,
+
+    /// Key pairs of all service accounts.
+    key_pairs: Arc<HashMap<PublicKey, KeyPair>>,
+}
+
+impl AuthorKeys {
+    /// Returns a new set of author keys holding the given named key pairs.
+    pub fn new(key_pairs: Vec<(String, KeyPair)>) -> Self {
+        let accounts = key_pairs
+            .iter()
+            .map(|(name, key_pair)| (name.to_owned(), key_pair.public_key()))
+            .collect();
+        let key_pairs = key_pairs
+            .into_iter()
+            .map(|(_, key_pair)| (key_pair.public_key(), key_pair))
+            .collect();
+
+        Self {
+            accounts: Arc::new(accounts),
+            key_pairs: Arc::new(key_pairs),
+        }
+    }
+
+    /// Loads the key pairs of the given service accounts.
+    ///
+    /// Accounts without a configured private key use the key pair persisted in the store, a new
+    /// one gets generated and persisted if the account is used for the first time.
+    pub async fn load(store: &SqlStore, service_accounts: &[ServiceAccount]) -> Result<Self> {
+        let mut key_pairs = Vec::new();
+
+        for account in service_accounts {
+            let private_key = match &account.private_key {
+                Some(private_key) => private_key.to_owned(),
+                None => match store.get_service_account_key(&account.name).await? {
+                    Some(private_key) => private_key,
+                    None => {
+                        let private_key = hex::encode(KeyPair::new().private_key().as_bytes());
+                        store
+                            .insert_service_account_key(&account.name, &private_key)
+                            .await?;
+                        private_key
+                    }
+                },
+            };
+
+            let key_pair = KeyPair::from_private_key_str(&private_key).map_err(|_| {
+                anyhow!("Invalid private key of service account '{}'", account.name)
+            })?;
+
+            info!(
+                "Service account '{}' signs with public key {}",
+                account.name,
+                key_pair.public_key()
+            );
+
+            key_pairs.push((account.name.to_owned(), key_pair));
+        }
+
+        Ok(Self::new(key_pairs))
+    }
+
+    /// Returns the key pair of a public key if it is held by the node.
+    pub fn get(&self, public_key: &PublicKey) -> Option<&KeyPair> {
+        self.key_pairs.get(public_key)
+    }
+
+    /// Returns the key pair of a service account.
+    pub fn get_by_name(&self, name: &str) -> Option<&KeyPair> {
+        self.accounts
+            .get(name)
+            .and_then(|public_key| self.get(public_key))
+    }
+}
+
+impl fmt::Debug for AuthorKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Do not print private keys
+        f.debug_struct("AuthorKeys")
+            .field("accounts", &self.accounts)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::identity::KeyPair;
+
+    use crate::authors::ServiceAccount;
+    use crate::test_utils::{test_runner, TestNode};
+
+    use super::AuthorKeys;
+
+    #[test]
+    fn holds_key_pairs() {
+        let key_pair = KeyPair::new();
+        let public_key = key_pair.public_key();
+        let author_keys = AuthorKeys::new(vec![("backend".into(), key_pair)]);
+
+        assert_eq!(
+            author_keys
+                .get(&public_key)
+                .map(|key_pair| key_pair.public_key()),
+            Some(public_key)
+        );
+        assert_eq!(
+            author_keys
+                .get_by_name("backend")
+                .map(|key_pair| key_pair.public_key()),
+            Some(public_key)
+        );
+        assert!(author_keys.get(&KeyPair::new().public_key()).is_none());
+        assert!(author_keys.get_by_name("frontend").is_none());
+        assert!(AuthorKeys::default().get(&public_key).is_none());
+    }
+
+    #[test]
+    fn loads_configured_and_generated_key_pairs() {
+        test_runner(|node: TestNode| async move {
+            let key_pair = KeyPair::new();
+            let service_accounts = vec![
+                ServiceAccount {
+                    name: "configured".into(),
+                    private_key: Some(hex::encode(key_pair.private_key().as_bytes())),
+                },
+                ServiceAccount::new("generated"),
+            ];
+
+            let author_keys = AuthorKeys::load(&node.context.store, &service_accounts)
+                .await
+                .unwrap();
+            assert_eq!(
+                author_keys
+                    .get_by_name("configured")
+                    .map(|key_pair| key_pair.public_key()),
+                Some(key_pair.public_key())
+            );
+            let generated = author_keys.get_by_name("generated").unwrap().public_key();
+
+            // Generated key pairs are kept across restarts
+            let author_keys = AuthorKeys::load(&node.context.store, &service_accounts)
+                .await
+                .unwrap();
+            assert_eq!(
+                author_keys
+                    .get_by_name("generated")
+                    .map(|key_pair| key_pair.public_key()),
+                Some(generated)
+            );
+        });
+    }
+}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Node-owned authors publishing operations on behalf of clients.
+//!
+//! Usually clients hold their own key pairs and sign entries before sending them to the node.
+//! Trusted clients, for example backend integrations, can instead let the node sign and publish
+//! operations with the key pairs of "service accounts", without having to construct bamboo
+//! entries themselves. Key pairs of service accounts are either configured or generated by the
+//! node and persisted in the database.
+mod author_keys;
+mod service_account;
+
+pub use author_keys::AuthorKeys;
+pub use service_account::ServiceAccount;
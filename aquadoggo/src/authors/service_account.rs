@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+/// Named key pair held by the node to sign entries on behalf of trusted clients, for example a
+/// backend integration which does not want to construct bamboo entries itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceAccount {
+    /// Unique name of the account, clients select the account by this name.
+    pub name: String,
+
+    /// Hex-encoded private key of the account.
+    ///
+    /// When not set, a new key pair is generated the first time the node starts with this account
+    /// and persisted in the database.
+    pub private_key: Option<String>,
+}
+
+impl ServiceAccount {
+    /// Returns a service account with a key pair generated by the node.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            private_key: None,
+        }
+    }
+}
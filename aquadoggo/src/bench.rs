@@ -0,0 +1,496 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Throughput benchmarks for publishing, replication ingest and reduce against a database.
+//!
+//! Benchmarks run with synthetic schemas and documents authored by fresh key pairs, this way
+//! they don't collide with data which is already in the database. Still, they should only run
+//! against dedicated databases as all generated data is kept.
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use log::info;
+use p2panda_rs::api::{next_args, publish};
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::encode::sign_and_encode_entry;
+use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::identity::KeyPair;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::encode::encode_operation;
+use p2panda_rs::operation::traits::AsOperation;
+use p2panda_rs::operation::{
+    EncodedOperation, Operation, OperationAction, OperationBuilder, OperationValue,
+    PinnedRelationList,
+};
+use p2panda_rs::schema::{FieldType, Schema, SchemaId, SchemaName};
+use tempfile::TempDir;
+use tokio::sync::broadcast;
+
+use crate::config::Configuration;
+use crate::context::Context;
+use crate::db::{connection_pool, create_database, run_pending_migrations, SqlStore};
+use crate::materializer::tasks::{reduce_task, schema_task};
+use crate::materializer::TaskInput;
+use crate::replication::SyncIngest;
+use crate::schema::SchemaProvider;
+
+/// Field types of the synthetic schema, fields cycle through them.
+const FIELD_TYPES: [FieldType; 4] = [
+    FieldType::String,
+    FieldType::Integer,
+    FieldType::Float,
+    FieldType::Boolean,
+];
+
+/// Options of a benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Number of documents created in every benchmark.
+    pub documents: usize,
+
+    /// Number of UPDATE operations published for every document.
+    pub updates: usize,
+
+    /// Number of fields of the synthetic schema.
+    pub fields: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            documents: 100,
+            updates: 10,
+            fields: 8,
+        }
+    }
+}
+
+/// Measurements of one benchmark.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    /// Name of the benchmark, for example "publish".
+    pub name: String,
+
+    /// Duration of every single measured step, for example publishing one operation.
+    pub latencies: Vec<Duration>,
+}
+
+impl BenchResult {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            latencies: Vec::new(),
+        }
+    }
+
+    /// Returns the sum of all measured steps.
+    pub fn total(&self) -> Duration {
+        self.latencies.iter().sum()
+    }
+
+    /// Returns the number of steps per second.
+    pub fn throughput(&self) -> f64 {
+        match self.total().as_secs_f64() {
+            secs if secs > 0.0 => self.latencies.len() as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the latency below which the given share of all steps stayed, for example 0.99.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::default();
+        }
+
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+
+        let index = ((latencies.len() as f64 * percentile).ceil() as usize).saturating_sub(1);
+        latencies[index.min(latencies.len() - 1)]
+    }
+}
+
+impl Display for BenchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} steps in {:.2?}, {:.1} steps/s (p50 {:.2?}, p99 {:.2?})",
+            self.name,
+            self.latencies.len(),
+            self.total(),
+            self.throughput(),
+            self.percentile(0.5),
+            self.percentile(0.99),
+        )
+    }
+}
+
+/// Entry and operation which were signed but not stored yet.
+type Commit = (EncodedEntry, EncodedOperation);
+
+/// Database and synthetic schema to run benchmarks against.
+#[derive(Debug)]
+pub struct BenchSetup {
+    context: Context,
+    schema: Schema,
+    options: BenchOptions,
+
+    /// Temporary directory holding the SQLite database when no database was given.
+    _tmp_dir: Option<TempDir>,
+}
+
+impl BenchSetup {
+    /// Connects to the database, runs migrations and creates a synthetic schema.
+    ///
+    /// A temporary SQLite database is used when no database URL is given.
+    pub async fn new(database_url: Option<&str>, options: BenchOptions) -> Result<Self> {
+        let (database_url, tmp_dir) = match database_url {
+            Some(database_url) => (database_url.to_string(), None),
+            None => {
+                let tmp_dir = TempDir::new()?;
+                let path = tmp_dir.path().join("bench.sqlite3");
+                (format!("sqlite:{}", path.display()), Some(tmp_dir))
+            }
+        };
+
+        let config = Configuration {
+            database_url: database_url.clone(),
+            ..Configuration::default()
+        };
+
+        create_database(&database_url).await?;
+        let pool = connection_pool(&database_url, config.database_max_connections, None).await?;
+        run_pending_migrations(&pool).await?;
+
+        let store = SqlStore::new(pool);
+        let schema_provider = SchemaProvider::new(
+            store.get_all_schema().await?,
+            config.allow_schema_ids.clone(),
+        );
+        let context = Context::new(store, KeyPair::new(), config, schema_provider);
+        let schema = create_schema(&context, options.fields).await?;
+
+        Ok(Self {
+            context,
+            schema,
+            options,
+            _tmp_dir: tmp_dir,
+        })
+    }
+
+    /// Measures publishing CREATE and UPDATE operations of new documents, in the same way as
+    /// clients publish them via GraphQL.
+    ///
+    /// Returns the measurements and the ids of the created documents.
+    pub async fn publish(&self) -> Result<(BenchResult, Vec<DocumentId>)> {
+        let mut result = BenchResult::new("publish");
+        let key_pair = KeyPair::new();
+        let mut document_ids = Vec::new();
+
+        for document_index in 0..self.options.documents {
+            let mut previous: Option<DocumentViewId> = None;
+
+            for operation_index in 0..=self.options.updates {
+                let operation = build_operation(
+                    &self.schema,
+                    previous.as_ref(),
+                    document_index + operation_index,
+                )?;
+                let (entry, operation) = sign(&self.context.store, &key_pair, &operation).await?;
+
+                let started_at = Instant::now();
+                self.publish_commit(&entry, &operation).await?;
+                result.latencies.push(started_at.elapsed());
+
+                if previous.is_none() {
+                    document_ids.push(entry.hash().into());
+                }
+                previous = Some(DocumentViewId::from(entry.hash()));
+            }
+        }
+
+        Ok((result, document_ids))
+    }
+
+    /// Measures ingesting entries and operations received from another node during replication.
+    ///
+    /// The entries are signed upfront in a separate in-memory database, only ingesting them is
+    /// measured. Returns the measurements and the ids of the ingested documents.
+    pub async fn ingest(&self) -> Result<(BenchResult, Vec<DocumentId>)> {
+        let (commits, document_ids) = self.remote_commits().await?;
+
+        let (tx, _rx) = broadcast::channel(1024);
+        let ingest = SyncIngest::new(self.context.schema_provider.clone(), tx);
+        let mut result = BenchResult::new("ingest");
+
+        for (entry, operation) in commits {
+            let started_at = Instant::now();
+            ingest
+                .handle_entry(&self.context.store, &entry, &operation)
+                .await?;
+            result.latencies.push(started_at.elapsed());
+        }
+
+        Ok((result, document_ids))
+    }
+
+    /// Measures materializing the given documents with reduce tasks.
+    pub async fn reduce(&self, document_ids: &[DocumentId]) -> Result<BenchResult> {
+        let mut result = BenchResult::new("reduce");
+
+        for document_id in document_ids {
+            let input = TaskInput::DocumentId(document_id.to_owned());
+
+            let started_at = Instant::now();
+            reduce_task(self.context.clone(), input)
+                .await
+                .map_err(|err| anyhow!("Reduce task failed: {:?}", err))?;
+            result.latencies.push(started_at.elapsed());
+        }
+
+        Ok(result)
+    }
+
+    /// Validate and store an entry with its operation.
+    async fn publish_commit(
+        &self,
+        entry: &EncodedEntry,
+        operation: &EncodedOperation,
+    ) -> Result<()> {
+        let plain_operation = decode_operation(operation)?;
+        publish(
+            &self.context.store,
+            &self.schema,
+            entry,
+            &plain_operation,
+            operation,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sign entries and operations of another author in a separate database.
+    async fn remote_commits(&self) -> Result<(Vec<Commit>, Vec<DocumentId>)> {
+        let pool = connection_pool("sqlite::memory:", 1, None).await?;
+        run_pending_migrations(&pool).await?;
+        let remote_store = SqlStore::new(pool.clone());
+
+        let key_pair = KeyPair::new();
+        let mut commits = Vec::new();
+        let mut document_ids = Vec::new();
+
+        for document_index in 0..self.options.documents {
+            let mut previous: Option<DocumentViewId> = None;
+
+            for operation_index in 0..=self.options.updates {
+                let operation = build_operation(
+                    &self.schema,
+                    previous.as_ref(),
+                    document_index + operation_index,
+                )?;
+                let (entry, operation) = sign(&remote_store, &key_pair, &operation).await?;
+
+                let plain_operation = decode_operation(&operation)?;
+                publish(
+                    &remote_store,
+                    &self.schema,
+                    &entry,
+                    &plain_operation,
+                    &operation,
+                )
+                .await?;
+
+                if previous.is_none() {
+                    document_ids.push(entry.hash().into());
+                }
+                previous = Some(DocumentViewId::from(entry.hash()));
+                commits.push((entry, operation));
+            }
+        }
+
+        pool.close().await;
+
+        Ok((commits, document_ids))
+    }
+}
+
+/// Run all benchmarks against the given database, a temporary SQLite database is used when no
+/// database URL is given.
+///
+/// Documents are first published and ingested, afterwards all of them get reduced.
+pub async fn run_benchmarks(
+    database_url: Option<&str>,
+    options: BenchOptions,
+) -> Result<Vec<BenchResult>> {
+    let setup = BenchSetup::new(database_url, options).await?;
+
+    info!("Benchmark publishing operations");
+    let (publish_result, mut document_ids) = setup.publish().await?;
+
+    info!("Benchmark ingesting replicated operations");
+    let (ingest_result, ingested_document_ids) = setup.ingest().await?;
+    document_ids.extend(ingested_document_ids);
+
+    info!("Benchmark reducing documents");
+    let reduce_result = setup.reduce(&document_ids).await?;
+
+    setup.context.store.pool.close().await;
+
+    Ok(vec![publish_result, ingest_result, reduce_result])
+}
+
+/// Returns the value of a field of the synthetic schema, varying with the given seed.
+fn field_value(field_type: &FieldType, seed: usize) -> OperationValue {
+    match field_type {
+        FieldType::Integer => OperationValue::Integer(seed as i64),
+        FieldType::Float => OperationValue::Float(seed as f64 / 3.0),
+        FieldType::Boolean => OperationValue::Boolean(seed % 2 == 0),
+        _ => OperationValue::String(format!("Value number {} of a benchmark document", seed)),
+    }
+}
+
+/// Build a CREATE operation with all fields or, when a previous view is given, an UPDATE
+/// operation changing one field.
+fn build_operation(
+    schema: &Schema,
+    previous: Option<&DocumentViewId>,
+    seed: usize,
+) -> Result<Operation> {
+    let fields: Vec<(&str, OperationValue)> = schema
+        .fields()
+        .iter()
+        .map(|(name, field_type)| (name.as_str(), field_value(field_type, seed)))
+        .collect();
+
+    let operation = match previous {
+        Some(previous) => OperationBuilder::new(schema.id())
+            .action(OperationAction::Update)
+            .fields(&fields[seed % fields.len()..=seed % fields.len()])
+            .previous(previous)
+            .build()?,
+        None => OperationBuilder::new(schema.id()).fields(&fields).build()?,
+    };
+
+    Ok(operation)
+}
+
+/// Sign an operation with the next entry of the given author.
+async fn sign(store: &SqlStore, key_pair: &KeyPair, operation: &Operation) -> Result<Commit> {
+    let encoded_operation = encode_operation(operation)?;
+
+    let (backlink, skiplink, seq_num, log_id) =
+        next_args(store, &key_pair.public_key(), operation.previous()).await?;
+
+    let encoded_entry = sign_and_encode_entry(
+        &log_id,
+        &seq_num,
+        skiplink.as_ref(),
+        backlink.as_ref(),
+        &encoded_operation,
+        key_pair,
+    )?;
+
+    Ok((encoded_entry, encoded_operation))
+}
+
+/// Publish and materialize a system document, returns its view id.
+async fn publish_system_document(
+    context: &Context,
+    key_pair: &KeyPair,
+    schema_id: SchemaId,
+    fields: &[(&str, OperationValue)],
+) -> Result<DocumentViewId> {
+    let schema = Schema::get_system(schema_id.clone())?;
+    let operation = OperationBuilder::new(&schema_id).fields(fields).build()?;
+    let (entry, operation) = sign(&context.store, key_pair, &operation).await?;
+
+    let plain_operation = decode_operation(&operation)?;
+    publish(&context.store, schema, &entry, &plain_operation, &operation).await?;
+
+    let document_id: DocumentId = entry.hash().into();
+    reduce_task(context.clone(), TaskInput::DocumentId(document_id))
+        .await
+        .map_err(|err| anyhow!("Could not materialize system document: {:?}", err))?;
+
+    Ok(DocumentViewId::from(entry.hash()))
+}
+
+/// Create a synthetic schema with the given number of fields.
+async fn create_schema(context: &Context, fields: usize) -> Result<Schema> {
+    let key_pair = KeyPair::new();
+    let mut field_ids = Vec::new();
+
+    for index in 0..fields.max(1) {
+        let field_type = &FIELD_TYPES[index % FIELD_TYPES.len()];
+        let field_id = publish_system_document(
+            context,
+            &key_pair,
+            SchemaId::SchemaFieldDefinition(1),
+            &[
+                ("name", OperationValue::String(format!("field_{}", index))),
+                ("type", field_type.to_owned().into()),
+            ],
+        )
+        .await?;
+        field_ids.push(field_id);
+    }
+
+    let view_id = publish_system_document(
+        context,
+        &key_pair,
+        SchemaId::SchemaDefinition(1),
+        &[
+            ("name", OperationValue::String("bench".to_string())),
+            (
+                "description",
+                OperationValue::String("Synthetic schema for benchmarks".to_string()),
+            ),
+            (
+                "fields",
+                OperationValue::PinnedRelationList(PinnedRelationList::new(field_ids)),
+            ),
+        ],
+    )
+    .await?;
+
+    schema_task(context.clone(), TaskInput::DocumentViewId(view_id.clone()))
+        .await
+        .map_err(|err| anyhow!("Could not materialize schema: {:?}", err))?;
+
+    let schema_id = SchemaId::Application(SchemaName::new("bench")?, view_id);
+    context
+        .schema_provider
+        .get(&schema_id)
+        .await
+        .ok_or_else(|| anyhow!("Synthetic schema was not materialized"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_benchmarks, BenchOptions};
+
+    #[tokio::test]
+    async fn runs_benchmarks() {
+        let options = BenchOptions {
+            documents: 3,
+            updates: 2,
+            fields: 5,
+        };
+
+        let results = run_benchmarks(None, options).await.unwrap();
+        assert_eq!(results.len(), 3);
+
+        // Every document consists of one CREATE and two UPDATE operations
+        assert_eq!(results[0].name, "publish");
+        assert_eq!(results[0].latencies.len(), 9);
+        assert_eq!(results[1].name, "ingest");
+        assert_eq!(results[1].latencies.len(), 9);
+
+        // Published and ingested documents are reduced
+        assert_eq!(results[2].name, "reduce");
+        assert_eq!(results[2].latencies.len(), 6);
+        assert!(results[2].percentile(0.99) >= results[2].percentile(0.5));
+    }
+}
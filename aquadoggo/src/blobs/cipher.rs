@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt;
+
+use argon2::Argon2;
+use chacha20::cipher::KeyIvInit;
+use chacha20::{ChaCha20, Key, Nonce};
+use p2panda_rs::document::DocumentViewId;
+
+/// Number of bytes of the salt used for deriving the blob encryption key from the secret.
+pub const SALT_LENGTH: usize = 16;
+
+/// Encrypts the bytes of blobs kept on the file system.
+///
+/// Blobs are encrypted with the ChaCha20 stream cipher, using a key derived from the node's
+/// database key with Argon2id and a nonce derived from the view id of the blob. As every view id is unique and
+/// blobs never change after materialization, a key-nonce pair never encrypts different data.
+///
+/// The encryption is not authenticated, it protects the confidentiality of blobs in case of
+/// device theft but does not detect tampering on the file system.
+#[derive(Clone)]
+pub struct BlobCipher {
+    key: [u8; 32],
+}
+
+impl BlobCipher {
+    /// Returns a new cipher with a key derived from the given secret and salt.
+    ///
+    /// Secrets are usually passphrases, the key is derived with the Argon2id password hashing
+    /// function to make guessing them expensive.
+    pub fn new(secret: &str, salt: &[u8; SALT_LENGTH]) -> Self {
+        let mut key = [0; 32];
+        Argon2::default()
+            .hash_password_into(secret.as_bytes(), salt, &mut key)
+            .expect("Valid key derivation parameters");
+
+        Self { key }
+    }
+
+    /// Returns the keystream to encrypt or decrypt the blob with the given view id.
+    pub(crate) fn keystream(&self, view_id: &DocumentViewId) -> ChaCha20 {
+        self.keystream_for(&view_id.to_string())
+    }
+
+    /// Returns the keystream to encrypt or decrypt a blob derived from the blob with the given
+    /// view id.
+    ///
+    /// View ids never contain a dot, the nonce therefore differs from the one of the original
+    /// blob. Derived blobs are only written once after their original got materialized.
+    pub(crate) fn derived_keystream(&self, view_id: &DocumentViewId, name: &str) -> ChaCha20 {
+        self.keystream_for(&format!("{}.{}", view_id, name))
+    }
+
+    fn keystream_for(&self, id: &str) -> ChaCha20 {
+        let hash = blake3::hash(id.as_bytes());
+        ChaCha20::new(
+            Key::from_slice(&self.key),
+            Nonce::from_slice(&hash.as_bytes()[..12]),
+        )
+    }
+}
+
+impl fmt::Debug for BlobCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobCipher").finish_non_exhaustive()
+    }
+}
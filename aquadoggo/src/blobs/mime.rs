@@ -0,0 +1,197 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Detection of the content type of blobs from their first bytes.
+//!
+//! Authors claim the mime type of a blob themselves. Browsers and other clients trusting a wrong
+//! claim can be tricked into interpreting blobs differently than intended, for example rendering
+//! an "image" as a HTML page.
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+
+/// Key of the annotation holding the detected mime type of blobs which claim a different one.
+pub const DETECTED_MIME_TYPE_ANNOTATION: &str = "detected_mime_type";
+
+/// Byte patterns at given offsets identifying a content type.
+const SIGNATURES: [(&[(usize, &[u8])], &str); 14] = [
+    (&[(0, b"\x89PNG\r\n\x1a\n")], "image/png"),
+    (&[(0, b"\xff\xd8\xff")], "image/jpeg"),
+    (&[(0, b"GIF87a")], "image/gif"),
+    (&[(0, b"GIF89a")], "image/gif"),
+    (&[(0, b"RIFF"), (8, b"WEBP")], "image/webp"),
+    (&[(0, b"RIFF"), (8, b"WAVE")], "audio/wav"),
+    (&[(0, b"OggS")], "audio/ogg"),
+    (&[(0, b"ID3")], "audio/mpeg"),
+    (&[(0, b"fLaC")], "audio/flac"),
+    (&[(0, b"\x1a\x45\xdf\xa3")], "video/webm"),
+    (&[(0, b"%PDF-")], "application/pdf"),
+    (&[(0, b"PK\x03\x04")], "application/zip"),
+    (&[(0, b"\x1f\x8b")], "application/gzip"),
+    (&[(0, b"\x7fELF")], "application/x-executable"),
+];
+
+/// Tags at the beginning of markup documents which browsers interpret as HTML.
+const HTML_TAGS: [&[u8]; 7] = [
+    b"<!doctype html",
+    b"<html",
+    b"<head",
+    b"<body",
+    b"<script",
+    b"<iframe",
+    b"<!--",
+];
+
+/// Action taken when the detected content type of a blob does not match the claimed one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MimeTypeMismatch {
+    /// Materialize the blob and annotate its document with the detected mime type.
+    #[default]
+    Flag,
+
+    /// Don't materialize the blob, it can't be downloaded from this node.
+    Reject,
+}
+
+impl MimeTypeMismatch {
+    /// Returns the action as used in configuration files.
+    pub fn as_str(&self) -> &str {
+        match self {
+            MimeTypeMismatch::Flag => "flag",
+            MimeTypeMismatch::Reject => "reject",
+        }
+    }
+}
+
+impl FromStr for MimeTypeMismatch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flag" => Ok(MimeTypeMismatch::Flag),
+            "reject" => Ok(MimeTypeMismatch::Reject),
+            _ => bail!("Unknown mime type mismatch action '{s}'"),
+        }
+    }
+}
+
+impl Display for MimeTypeMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Returns the mime type without parameters in lower case, for example "text/html" for
+/// "text/HTML; charset=utf-8".
+pub fn essence(mime_type: &str) -> String {
+    mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Detects the mime type of a blob from its first bytes.
+///
+/// Returns `None` when the content type could not be detected, for example for plain text.
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    let signature = SIGNATURES.iter().find(|(patterns, _)| {
+        patterns
+            .iter()
+            .all(|(offset, pattern)| data.get(*offset..offset + pattern.len()) == Some(*pattern))
+    });
+
+    if let Some((_, mime_type)) = signature {
+        return Some(*mime_type);
+    }
+
+    // Markup can start with a byte order mark and whitespace
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+    let start = data.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    let data = &data[start..];
+
+    if starts_with_tag(data, b"<svg") {
+        return Some("image/svg+xml");
+    }
+
+    if HTML_TAGS.iter().any(|tag| starts_with_tag(data, tag)) {
+        return Some("text/html");
+    }
+
+    None
+}
+
+/// Returns true if the data starts with the given tag, ignoring case.
+fn starts_with_tag(data: &[u8], tag: &[u8]) -> bool {
+    match data.get(..tag.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(tag) => {
+            // Comments don't need to be terminated, tags by a space or closing bracket
+            tag == b"<!--" || matches!(data.get(tag.len()), Some(b' ' | b'>' | b'\t' | b'\n'))
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if the claimed mime type of a blob is compatible with the detected one.
+///
+/// Some formats are known under more than one mime type, others are based on generic containers,
+/// for example office documents are zip files.
+pub fn is_compatible(claimed: &str, detected: &str) -> bool {
+    let claimed = essence(claimed);
+
+    // Clients don't interpret blobs of unknown content type
+    if claimed == detected || claimed == "application/octet-stream" {
+        return true;
+    }
+
+    match detected {
+        "image/jpeg" => claimed == "image/jpg",
+        "audio/wav" => matches!(claimed.as_str(), "audio/x-wav" | "audio/wave"),
+        "audio/ogg" => {
+            matches!(claimed.as_str(), "video/ogg" | "application/ogg")
+                || claimed.starts_with("audio/")
+        }
+        "audio/mpeg" => claimed == "audio/mp3",
+        "video/webm" => matches!(claimed.as_str(), "audio/webm" | "video/x-matroska"),
+        "application/zip" | "application/gzip" => claimed.starts_with("application/"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{is_compatible, sniff_mime_type};
+
+    #[rstest]
+    #[case::png(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR", Some("image/png"))]
+    #[case::webp(b"RIFF\x24\x00\x00\x00WEBPVP8 ", Some("image/webp"))]
+    #[case::html(b"\xef\xbb\xbf\n  <!DOCTYPE html><html>", Some("text/html"))]
+    #[case::script(b"<script>alert(1)</script>", Some("text/html"))]
+    #[case::svg(b"<svg xmlns=\"http://www.w3.org/2000/svg\">", Some("image/svg+xml"))]
+    #[case::text(b"Hello, World!", None)]
+    #[case::not_a_tag(b"<htmlish>", None)]
+    #[case::empty(b"", None)]
+    fn sniffs_mime_types(#[case] data: &[u8], #[case] expected: Option<&str>) {
+        assert_eq!(sniff_mime_type(data), expected);
+    }
+
+    #[rstest]
+    #[case("image/png", "image/png", true)]
+    #[case("Image/PNG; foo=bar", "image/png", true)]
+    #[case("image/jpg", "image/jpeg", true)]
+    #[case(
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "application/zip",
+        true
+    )]
+    #[case("application/octet-stream", "text/html", true)]
+    #[case("image/png", "text/html", false)]
+    #[case("text/plain", "text/html", false)]
+    #[case("image/png", "application/zip", false)]
+    fn checks_compatibility(#[case] claimed: &str, #[case] detected: &str, #[case] expected: bool) {
+        assert_eq!(is_compatible(claimed, detected), expected);
+    }
+}
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Storage of materialized blobs on the file system.
+//!
+//! Blob files are kept in sharded folders, small blobs can optionally be aggregated in packfiles.
+//! Materialized blobs can be transcoded into other formats, kept as derived blobs next to them.
+mod cipher;
+mod mime;
+mod pack;
+mod store;
+mod transcode;
+
+pub use mime::{
+    essence, is_compatible, sniff_mime_type, MimeTypeMismatch, DETECTED_MIME_TYPE_ANNOTATION,
+};
+pub use store::BlobStore;
+pub use transcode::{BlobTranscoder, INPUT_PLACEHOLDER, OUTPUT_PLACEHOLDER};
@@ -0,0 +1,275 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use log::warn;
+use tokio::fs::{self, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+/// Size in bytes after which a packfile is not appended to anymore and a new one gets started.
+const MAX_PACK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Name of the index file, kept in the same folder as the packfiles.
+const INDEX_FILE_NAME: &str = "index";
+
+/// File extension of packfiles.
+const PACK_FILE_EXTENSION: &str = "pack";
+
+/// Marker in the index recording that a blob got removed from its packfile.
+const TOMBSTONE: &str = "-";
+
+/// Position of a blob inside of a packfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackLocation {
+    /// Number of the packfile.
+    pub pack: u64,
+
+    /// Position of the first byte of the blob in the packfile.
+    pub offset: u64,
+
+    /// Length of the blob in bytes.
+    pub length: u64,
+}
+
+/// Packfiles aggregating many small blobs into few large files, with an index of their positions.
+///
+/// Blobs get appended to the current packfile until it reached its maximum size. Every write is
+/// recorded as a line in an append-only index file, removals are recorded as tombstones. The data
+/// of removed blobs stays in the packfile.
+///
+/// Index format, one line per record:
+///
+/// ```text
+/// <view id> <pack number> <offset> <length>
+/// <view id> -
+/// ```
+#[derive(Debug)]
+pub struct Packs {
+    /// Folder holding packfiles and their index.
+    path: PathBuf,
+
+    /// Positions of all blobs currently held in packfiles.
+    index: HashMap<String, PackLocation>,
+
+    /// Number of the packfile new blobs are appended to.
+    current_pack: u64,
+
+    /// Size of the current packfile in bytes.
+    current_size: u64,
+}
+
+impl Packs {
+    /// Load the index of packfiles in the given folder.
+    ///
+    /// Nothing gets created on the file system until the first blob is inserted.
+    pub async fn open(path: PathBuf) -> Result<Self> {
+        let mut packs = Self {
+            path,
+            index: HashMap::new(),
+            current_pack: 0,
+            current_size: 0,
+        };
+
+        if !fs::try_exists(&packs.path).await? {
+            return Ok(packs);
+        }
+
+        match fs::read_to_string(packs.index_path()).await {
+            Ok(index) => {
+                for line in index.lines() {
+                    // Skip malformed lines, for example when the node crashed while writing one
+                    if packs.apply(line).is_err() {
+                        warn!("Ignore malformed line in blob pack index: '{}'", line);
+                    }
+                }
+
+                // Terminate a partially written line so following records don't get appended to it
+                if !index.is_empty() && !index.ends_with('\n') {
+                    packs.append_to_index("").await?;
+                }
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => return Err(err.into()),
+        }
+
+        // Continue appending to the latest packfile
+        let mut entries = fs::read_dir(&packs.path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(pack) = parse_pack_file_name(&entry.path()) {
+                packs.current_pack = packs.current_pack.max(pack);
+            }
+        }
+
+        packs.current_size = match fs::metadata(packs.pack_path(packs.current_pack)).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+
+        Ok(packs)
+    }
+
+    /// Returns the path of a packfile.
+    pub fn pack_path(&self, pack: u64) -> PathBuf {
+        self.path.join(format!("{}.{}", pack, PACK_FILE_EXTENSION))
+    }
+
+    /// Returns the position of a blob when it is held in a packfile.
+    pub fn get(&self, view_id: &str) -> Option<PackLocation> {
+        self.index.get(view_id).copied()
+    }
+
+    /// Append a blob to the current packfile and record its position in the index.
+    pub async fn insert(&mut self, view_id: &str, data: &[u8]) -> Result<PackLocation> {
+        fs::create_dir_all(&self.path).await?;
+
+        let length = data.len() as u64;
+        if self.current_size > 0 && self.current_size + length > MAX_PACK_SIZE {
+            self.current_pack += 1;
+            self.current_size = 0;
+        }
+
+        let location = PackLocation {
+            pack: self.current_pack,
+            offset: self.current_size,
+            length,
+        };
+
+        // Write the data first, the blob only becomes visible with its index record
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.pack_path(location.pack))
+            .await?;
+        file.write_all(data).await?;
+        file.sync_data().await?;
+        self.current_size += length;
+
+        self.append_to_index(&format!(
+            "{} {} {} {}",
+            view_id, location.pack, location.offset, location.length
+        ))
+        .await?;
+        self.index.insert(view_id.to_owned(), location);
+
+        Ok(location)
+    }
+
+    /// Remove a blob from the index.
+    ///
+    /// Returns `true` if the blob was held in a packfile.
+    pub async fn remove(&mut self, view_id: &str) -> Result<bool> {
+        if !self.index.contains_key(view_id) {
+            return Ok(false);
+        }
+
+        self.append_to_index(&format!("{} {}", view_id, TOMBSTONE))
+            .await?;
+        self.index.remove(view_id);
+
+        Ok(true)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.path.join(INDEX_FILE_NAME)
+    }
+
+    async fn append_to_index(&self, line: &str) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.index_path())
+            .await?;
+        file.write_all(format!("{}\n", line).as_bytes()).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    /// Apply a record of the index file.
+    fn apply(&mut self, line: &str) -> Result<()> {
+        let parts: Vec<&str> = line.split(' ').collect();
+
+        match parts.as_slice() {
+            [view_id, TOMBSTONE] => {
+                self.index.remove(*view_id);
+            }
+            [view_id, pack, offset, length] => {
+                let location = PackLocation {
+                    pack: pack.parse()?,
+                    offset: offset.parse()?,
+                    length: length.parse()?,
+                };
+                self.index.insert(view_id.to_string(), location);
+            }
+            _ => return Err(anyhow!("Invalid number of fields")),
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the number of a packfile from its path.
+fn parse_pack_file_name(path: &Path) -> Option<u64> {
+    if path.extension()? != PACK_FILE_EXTENSION {
+        return None;
+    }
+
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use tokio::fs;
+    use tokio::io::AsyncWriteExt;
+
+    use super::{PackLocation, Packs};
+
+    #[tokio::test]
+    async fn inserts_and_removes_blobs() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path().join("packs");
+
+        let mut packs = Packs::open(path.clone()).await.unwrap();
+        assert_eq!(packs.get("a"), None);
+
+        packs.insert("a", b"Hello").await.unwrap();
+        packs.insert("b", b"Panda!").await.unwrap();
+        assert!(packs.remove("a").await.unwrap());
+        assert!(!packs.remove("a").await.unwrap());
+
+        let location = packs.get("b").unwrap();
+        assert_eq!(
+            location,
+            PackLocation {
+                pack: 0,
+                offset: 5,
+                length: 6,
+            }
+        );
+
+        let data = fs::read(packs.pack_path(0)).await.unwrap();
+        assert_eq!(&data[5..11], b"Panda!");
+
+        // Index survives a restart, also when the last record was not fully written
+        let mut index = fs::OpenOptions::new()
+            .append(true)
+            .open(path.join("index"))
+            .await
+            .unwrap();
+        index.write_all(b"c 0 11").await.unwrap();
+
+        let mut packs = Packs::open(path).await.unwrap();
+        assert_eq!(packs.get("a"), None);
+        assert_eq!(packs.get("b"), Some(location));
+        assert_eq!(packs.get("c"), None);
+
+        // New blobs are appended after the existing ones
+        let location = packs.insert("c", b"!").await.unwrap();
+        assert_eq!(location.offset, 11);
+
+        let packs = Packs::open(tmp_dir.path().join("packs")).await.unwrap();
+        assert_eq!(packs.get("c"), Some(location));
+    }
+}
@@ -0,0 +1,770 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt;
+use std::io::{self, SeekFrom};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use anyhow::{anyhow, bail, Result};
+use chacha20::cipher::StreamCipher;
+use chacha20::ChaCha20;
+use log::{debug, info};
+use p2panda_rs::document::DocumentViewId;
+use tempfile::TempDir;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, ReadBuf, Take};
+use tokio::sync::{Mutex, MutexGuard, OnceCell};
+
+use crate::blobs::cipher::{BlobCipher, SALT_LENGTH};
+use crate::blobs::pack::Packs;
+
+/// Name of the folder inside the blobs base path holding packfiles and their index.
+const PACKS_DIR: &str = "packs";
+
+/// Name of the folder inside the blobs base path holding temporary files, for example decrypted
+/// copies of blobs which get transcoded.
+const TMP_DIR: &str = "tmp";
+
+/// Name of the file inside the blobs base path holding the salt of the encryption key.
+const SALT_FILE: &str = "encryption-salt";
+
+/// Number of hex characters of the view id used for every level of shard folders.
+const SHARD_WIDTH: usize = 2;
+
+/// Number of shard folder levels, two levels of two hex characters give 65536 folders.
+const SHARD_LEVELS: usize = 2;
+
+/// Length of the multihash prefix of view ids, which is the same for all of them.
+const HASH_PREFIX_LEN: usize = 4;
+
+/// Reader over the bytes of a materialized blob.
+///
+/// Decrypts the bytes while reading when the blob store is encrypted.
+pub struct BlobReader {
+    inner: Take<File>,
+    keystream: Option<ChaCha20>,
+}
+
+impl fmt::Debug for BlobReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobReader")
+            .field("inner", &self.inner)
+            .field("encrypted", &self.keystream.is_some())
+            .finish()
+    }
+}
+
+impl AsyncRead for BlobReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+
+        if let Some(keystream) = &mut this.keystream {
+            keystream.apply_keystream(&mut buf.filled_mut()[filled..]);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Writer of the bytes of a blob into its file.
+///
+/// Encrypts the bytes while writing when the blob store is encrypted. Make sure to flush the
+/// writer after the last write.
+pub struct BlobWriter {
+    inner: File,
+    keystream: Option<ChaCha20>,
+    pending: Vec<u8>,
+}
+
+impl fmt::Debug for BlobWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobWriter")
+            .field("inner", &self.inner)
+            .field("encrypted", &self.keystream.is_some())
+            .finish()
+    }
+}
+
+impl BlobWriter {
+    /// Write all encrypted bytes which are still pending to the file.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for BlobWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.keystream.is_none() {
+            return Pin::new(&mut this.inner).poll_write(cx, buf);
+        }
+
+        ready!(this.poll_pending(cx))?;
+
+        // The bytes are accepted as soon as they got encrypted, as the keystream can not be
+        // rewound again
+        let mut data = buf.to_vec();
+        if let Some(keystream) = &mut this.keystream {
+            keystream.apply_keystream(&mut data);
+        }
+        this.pending = data;
+
+        if let Poll::Ready(Err(err)) = this.poll_pending(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Persists materialized blobs on the file system.
+///
+/// Blob files are kept in a sharded folder layout, fanning out over the first characters of their
+/// view id, for example `<base path>/a0/1f/<view id>`. This keeps the number of files per folder
+/// low, as many file systems degrade with millions of files in a single folder.
+///
+/// Blobs smaller than the optional pack threshold are aggregated in packfiles instead, see
+/// `Packs`. Blobs materialized in the previous flat layout, `<base path>/<view id>`, are still
+/// found until they got moved by `migrate`.
+///
+/// Blobs can optionally be encrypted at rest, see `with_encryption`.
+#[derive(Debug, Clone)]
+pub struct BlobStore {
+    base_path: PathBuf,
+    pack_threshold: Option<u64>,
+    packs: Arc<OnceCell<Mutex<Packs>>>,
+    cipher: Option<BlobCipher>,
+}
+
+impl BlobStore {
+    /// Returns a new blob store persisting blobs in the given folder.
+    ///
+    /// Blobs with fewer bytes than `pack_threshold` are aggregated in packfiles. When `None` every
+    /// blob is kept in its own file.
+    pub fn new(base_path: PathBuf, pack_threshold: Option<u64>) -> Self {
+        Self {
+            base_path,
+            pack_threshold,
+            packs: Arc::new(OnceCell::new()),
+            cipher: None,
+        }
+    }
+
+    /// Returns a blob store persisting blobs in the given folder, encrypted with a key derived
+    /// from the secret if one is given.
+    ///
+    /// The salt of the encryption key is kept in the folder, next to the blobs. Returns an error
+    /// when encryption gets enabled for a folder which already contains unencrypted blobs or
+    /// disabled for a folder with encrypted blobs, these blobs would not be readable anymore.
+    /// Blobs in the previous flat layout are encrypted when they get migrated.
+    pub fn initialize(
+        base_path: PathBuf,
+        pack_threshold: Option<u64>,
+        secret: Option<&str>,
+    ) -> Result<Self> {
+        let salt_path = base_path.join(SALT_FILE);
+        let blob_store = Self::new(base_path, pack_threshold);
+
+        let secret = match secret {
+            Some(secret) => secret,
+            None if salt_path.exists() => {
+                bail!(
+                    "Blobs in {} are encrypted, encryption can not be disabled",
+                    blob_store.base_path.display()
+                );
+            }
+            None => return Ok(blob_store),
+        };
+
+        let salt: [u8; SALT_LENGTH] = match std::fs::read(&salt_path) {
+            Ok(salt) => salt
+                .try_into()
+                .map_err(|_| anyhow!("Invalid encryption salt in {}", salt_path.display()))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                if blob_store.contains_blobs()? {
+                    bail!(
+                        "Blobs in {} are not encrypted, encryption can not be enabled for \
+                        existing blobs",
+                        blob_store.base_path.display()
+                    );
+                }
+
+                let salt = rand::random();
+                std::fs::create_dir_all(&blob_store.base_path)?;
+                std::fs::write(&salt_path, salt)?;
+                salt
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(blob_store.with_encryption(BlobCipher::new(secret, &salt)))
+    }
+
+    /// Returns true if the folder contains blobs in the sharded layout or packfiles.
+    ///
+    /// Blobs in the previous flat layout are not considered, they are still migrated.
+    fn contains_blobs(&self) -> Result<bool> {
+        let entries = match std::fs::read_dir(&self.base_path) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() && entry.file_name() != TMP_DIR {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Encrypt all blobs written from now on and decrypt them again when reading.
+    ///
+    /// Use `initialize` to derive the cipher from a secret and the salt kept next to the blobs.
+    pub fn with_encryption(mut self, cipher: BlobCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Returns the keystream to encrypt or decrypt a blob when encryption is enabled.
+    fn keystream(&self, view_id: &DocumentViewId) -> Option<ChaCha20> {
+        self.cipher.as_ref().map(|cipher| cipher.keystream(view_id))
+    }
+
+    /// Returns the path of a blob file in the sharded layout.
+    pub fn path(&self, view_id: &DocumentViewId) -> PathBuf {
+        let view_id = view_id.to_string();
+        let mut path = self.base_path.clone();
+
+        for level in 0..SHARD_LEVELS {
+            let start = HASH_PREFIX_LEN + level * SHARD_WIDTH;
+            path.push(&view_id[start..start + SHARD_WIDTH]);
+        }
+
+        path.join(view_id)
+    }
+
+    /// Returns the path of a blob derived from the blob with the given view id, for example a
+    /// transcoded video.
+    ///
+    /// Derived blobs are kept next to their original blob file, named after the original and
+    /// the name of the derivative, for example `<base path>/a0/1f/<view id>.webm`.
+    pub fn derived_path(&self, view_id: &DocumentViewId, name: &str) -> PathBuf {
+        self.path(view_id)
+            .with_file_name(format!("{}.{}", view_id, name))
+    }
+
+    /// Returns the path of a blob file in the previous flat layout.
+    fn legacy_path(&self, view_id: &DocumentViewId) -> PathBuf {
+        self.base_path.join(view_id.to_string())
+    }
+
+    /// Create a temporary folder inside the blobs base path which is removed again when dropped.
+    ///
+    /// Files in there can contain decrypted blobs, keeping them next to the blobs instead of the
+    /// system-wide temporary folder makes sure they don't end up on another, unprotected disk.
+    pub async fn temp_dir(&self) -> Result<TempDir> {
+        let path = self.base_path.join(TMP_DIR);
+        fs::create_dir_all(&path).await?;
+        Ok(TempDir::new_in(path)?)
+    }
+
+    /// Returns true if a blob with the given length should be aggregated in a packfile.
+    pub fn is_packed(&self, length: u64) -> bool {
+        self.pack_threshold
+            .is_some_and(|threshold| length < threshold)
+    }
+
+    /// Get access to the packfiles, loading their index on first use.
+    async fn packs(&self) -> Result<MutexGuard<'_, Packs>> {
+        let packs = self
+            .packs
+            .get_or_try_init(|| async {
+                Packs::open(self.base_path.join(PACKS_DIR))
+                    .await
+                    .map(Mutex::new)
+            })
+            .await?;
+
+        Ok(packs.lock().await)
+    }
+
+    /// Returns the length in bytes of a materialized blob or `None` if it does not exist.
+    pub async fn len(&self, view_id: &DocumentViewId) -> Result<Option<u64>> {
+        if let Some(location) = self.packs().await?.get(&view_id.to_string()) {
+            return Ok(Some(location.length));
+        }
+
+        for path in [self.path(view_id), self.legacy_path(view_id)] {
+            if let Ok(metadata) = fs::metadata(path).await {
+                return Ok(Some(metadata.len()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns a reader over the bytes of a materialized blob or `None` if it does not exist.
+    pub async fn open(&self, view_id: &DocumentViewId) -> Result<Option<BlobReader>> {
+        let packed = {
+            let packs = self.packs().await?;
+            packs
+                .get(&view_id.to_string())
+                .map(|location| (packs.pack_path(location.pack), location))
+        };
+
+        if let Some((path, location)) = packed {
+            let mut file = File::open(path).await?;
+            file.seek(SeekFrom::Start(location.offset)).await?;
+            return Ok(Some(BlobReader {
+                inner: file.take(location.length),
+                keystream: self.keystream(view_id),
+            }));
+        }
+
+        if let Ok(file) = File::open(self.path(view_id)).await {
+            let length = file.metadata().await?.len();
+            return Ok(Some(BlobReader {
+                inner: file.take(length),
+                keystream: self.keystream(view_id),
+            }));
+        }
+
+        // Blobs in the previous flat layout were never encrypted
+        if let Ok(file) = File::open(self.legacy_path(view_id)).await {
+            let length = file.metadata().await?.len();
+            return Ok(Some(BlobReader {
+                inner: file.take(length),
+                keystream: None,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Create or truncate the file of a blob in the sharded layout.
+    pub async fn create(&self, view_id: &DocumentViewId) -> Result<BlobWriter> {
+        create_file(self.path(view_id), self.keystream(view_id)).await
+    }
+
+    /// Returns a reader over the bytes of a derived blob or `None` if it does not exist.
+    pub async fn open_derived(
+        &self,
+        view_id: &DocumentViewId,
+        name: &str,
+    ) -> Result<Option<BlobReader>> {
+        match File::open(self.derived_path(view_id, name)).await {
+            Ok(file) => {
+                let length = file.metadata().await?.len();
+                Ok(Some(BlobReader {
+                    inner: file.take(length),
+                    keystream: self.derived_keystream(view_id, name),
+                }))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Create or truncate the file of a blob derived from the blob with the given view id.
+    pub async fn create_derived(&self, view_id: &DocumentViewId, name: &str) -> Result<BlobWriter> {
+        create_file(
+            self.derived_path(view_id, name),
+            self.derived_keystream(view_id, name),
+        )
+        .await
+    }
+
+    /// Returns the keystream to encrypt or decrypt a derived blob when encryption is enabled.
+    fn derived_keystream(&self, view_id: &DocumentViewId, name: &str) -> Option<ChaCha20> {
+        self.cipher
+            .as_ref()
+            .map(|cipher| cipher.derived_keystream(view_id, name))
+    }
+
+    /// Returns the bytes of a blob as they are kept on the file system.
+    fn encrypt(&self, view_id: &DocumentViewId, data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        if let Some(mut keystream) = self.keystream(view_id) {
+            keystream.apply_keystream(&mut data);
+        }
+        data
+    }
+
+    /// Append the bytes of a blob to a packfile.
+    pub async fn insert_packed(&self, view_id: &DocumentViewId, data: &[u8]) -> Result<()> {
+        let data = self.encrypt(view_id, data);
+        self.packs()
+            .await?
+            .insert(&view_id.to_string(), &data)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove a materialized blob from the file system, independent of where it is kept.
+    ///
+    /// Returns `true` if the blob existed. Files which are already missing, for example because
+    /// they got removed by a concurrent garbage collection task, are ignored.
+    pub async fn remove(&self, view_id: &DocumentViewId) -> Result<bool> {
+        // Hold the lock during removal to not race with a concurrent migration of this blob
+        let mut packs = self.packs().await?;
+        let mut removed = packs.remove(&view_id.to_string()).await?;
+
+        for path in [self.path(view_id), self.legacy_path(view_id)] {
+            match fs::remove_file(path).await {
+                Ok(()) => removed = true,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // Remove all blobs derived from this one as well
+        let path = self.path(view_id);
+        if let Some(parent) = path.parent() {
+            if fs::try_exists(parent).await? {
+                let prefix = format!("{}.", view_id);
+                let mut entries = fs::read_dir(parent).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    let is_derived = entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|file_name| file_name.starts_with(&prefix));
+                    if is_derived {
+                        match fs::remove_file(entry.path()).await {
+                            Ok(()) => (),
+                            Err(err) if err.kind() == io::ErrorKind::NotFound => (),
+                            Err(err) => return Err(err.into()),
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Move blobs from the previous flat layout into the sharded layout or packfiles.
+    ///
+    /// This can run while the node is online, blobs stay readable during the whole migration.
+    ///
+    /// Returns the number of migrated blobs.
+    pub async fn migrate(&self) -> Result<usize> {
+        if !fs::try_exists(&self.base_path).await? {
+            return Ok(0);
+        }
+
+        let mut migrated = 0;
+        let mut entries = fs::read_dir(&self.base_path).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+
+            // Only consider files named after a view id, ignore everything else
+            let view_id: DocumentViewId = match entry.file_name().to_str().map(str::parse) {
+                Some(Ok(view_id)) => view_id,
+                _ => continue,
+            };
+
+            let legacy_path = entry.path();
+            let length = entry.metadata().await?.len();
+
+            // Hold the lock during migration to not race with a concurrent removal of this blob
+            let mut packs = self.packs().await?;
+
+            let is_migrated = packs.get(&view_id.to_string()).is_some()
+                || fs::try_exists(self.path(&view_id)).await?;
+
+            if !is_migrated {
+                if self.is_packed(length) {
+                    let data = self.encrypt(&view_id, &fs::read(&legacy_path).await?);
+                    packs.insert(&view_id.to_string(), &data).await?;
+                } else if self.cipher.is_some() {
+                    let mut file = File::open(&legacy_path).await?;
+                    let mut writer = self.create(&view_id).await?;
+                    tokio::io::copy(&mut file, &mut writer).await?;
+                    writer.flush().await?;
+                } else {
+                    let path = self.path(&view_id);
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).await?;
+                    }
+                    fs::rename(&legacy_path, &path).await?;
+                }
+
+                debug!("Migrated blob {} to new storage layout", view_id);
+                migrated += 1;
+            }
+
+            if fs::try_exists(&legacy_path).await? {
+                fs::remove_file(&legacy_path).await?;
+            }
+        }
+
+        if migrated > 0 {
+            info!("Migrated {} blobs to new storage layout", migrated);
+        }
+
+        Ok(migrated)
+    }
+}
+
+/// Create or truncate a file, including its parent folders.
+async fn create_file(path: PathBuf, keystream: Option<ChaCha20>) -> Result<BlobWriter> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .await?;
+
+    Ok(BlobWriter {
+        inner: file,
+        keystream,
+        pending: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::test_utils::fixtures::random_document_view_id;
+    use rstest::rstest;
+    use tempfile::TempDir;
+    use tokio::fs;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{BlobStore, SALT_FILE};
+
+    async fn read(blob_store: &BlobStore, view_id: &DocumentViewId) -> Option<Vec<u8>> {
+        let mut reader = blob_store.open(view_id).await.unwrap()?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await.unwrap();
+        Some(data)
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn stores_blobs_in_shards(#[from(random_document_view_id)] view_id: DocumentViewId) {
+        let tmp_dir = TempDir::new().unwrap();
+        let blob_store = BlobStore::new(tmp_dir.path().to_path_buf(), None);
+
+        let path = blob_store.path(&view_id);
+        let view_id_str = view_id.to_string();
+        assert_eq!(
+            path,
+            tmp_dir
+                .path()
+                .join(&view_id_str[4..6])
+                .join(&view_id_str[6..8])
+                .join(&view_id_str)
+        );
+
+        let mut file = blob_store.create(&view_id).await.unwrap();
+        file.write_all(b"Hello, Panda!").await.unwrap();
+
+        assert_eq!(blob_store.len(&view_id).await.unwrap(), Some(13));
+        assert_eq!(
+            read(&blob_store, &view_id).await,
+            Some(b"Hello, Panda!".to_vec())
+        );
+
+        assert!(blob_store.remove(&view_id).await.unwrap());
+        assert!(!fs::try_exists(path).await.unwrap());
+        assert_eq!(blob_store.len(&view_id).await.unwrap(), None);
+        assert_eq!(read(&blob_store, &view_id).await, None);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn stores_small_blobs_in_packs(
+        #[from(random_document_view_id)] view_id_1: DocumentViewId,
+        #[from(random_document_view_id)] view_id_2: DocumentViewId,
+    ) {
+        let tmp_dir = TempDir::new().unwrap();
+        let blob_store = BlobStore::new(tmp_dir.path().to_path_buf(), Some(10));
+        assert!(blob_store.is_packed(9));
+        assert!(!blob_store.is_packed(10));
+
+        blob_store
+            .insert_packed(&view_id_1, b"Hello")
+            .await
+            .unwrap();
+        blob_store
+            .insert_packed(&view_id_2, b"Panda")
+            .await
+            .unwrap();
+        assert!(!fs::try_exists(blob_store.path(&view_id_1)).await.unwrap());
+
+        assert_eq!(blob_store.len(&view_id_2).await.unwrap(), Some(5));
+        assert_eq!(read(&blob_store, &view_id_1).await, Some(b"Hello".to_vec()));
+        assert_eq!(read(&blob_store, &view_id_2).await, Some(b"Panda".to_vec()));
+
+        assert!(blob_store.remove(&view_id_1).await.unwrap());
+        assert_eq!(read(&blob_store, &view_id_1).await, None);
+
+        // Packed blobs are still found after restarting the node, also when packing got disabled
+        let blob_store = BlobStore::new(tmp_dir.path().to_path_buf(), None);
+        assert_eq!(read(&blob_store, &view_id_1).await, None);
+        assert_eq!(read(&blob_store, &view_id_2).await, Some(b"Panda".to_vec()));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn migrates_flat_layout(
+        #[from(random_document_view_id)] small_view_id: DocumentViewId,
+        #[from(random_document_view_id)] large_view_id: DocumentViewId,
+    ) {
+        let tmp_dir = TempDir::new().unwrap();
+        let legacy_path = |view_id: &DocumentViewId| tmp_dir.path().join(view_id.to_string());
+
+        // Blobs materialized in the flat layout and some unrelated file
+        fs::write(legacy_path(&small_view_id), b"Hello")
+            .await
+            .unwrap();
+        fs::write(legacy_path(&large_view_id), b"Hello, Panda!")
+            .await
+            .unwrap();
+        fs::write(tmp_dir.path().join("README"), b"Hi!")
+            .await
+            .unwrap();
+
+        // Blobs are readable before migration
+        let blob_store = BlobStore::new(tmp_dir.path().to_path_buf(), Some(10));
+        assert_eq!(
+            read(&blob_store, &small_view_id).await,
+            Some(b"Hello".to_vec())
+        );
+
+        assert_eq!(blob_store.migrate().await.unwrap(), 2);
+
+        // Blobs moved into packfiles or shard folders and are still readable
+        assert!(!fs::try_exists(legacy_path(&small_view_id)).await.unwrap());
+        assert!(!fs::try_exists(legacy_path(&large_view_id)).await.unwrap());
+        assert!(!fs::try_exists(blob_store.path(&small_view_id))
+            .await
+            .unwrap());
+        assert!(fs::try_exists(blob_store.path(&large_view_id))
+            .await
+            .unwrap());
+        assert!(fs::try_exists(tmp_dir.path().join("README")).await.unwrap());
+
+        assert_eq!(
+            read(&blob_store, &small_view_id).await,
+            Some(b"Hello".to_vec())
+        );
+        assert_eq!(
+            read(&blob_store, &large_view_id).await,
+            Some(b"Hello, Panda!".to_vec())
+        );
+
+        // Nothing left to migrate
+        assert_eq!(blob_store.migrate().await.unwrap(), 0);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn encrypts_blobs(
+        #[from(random_document_view_id)] small_view_id: DocumentViewId,
+        #[from(random_document_view_id)] large_view_id: DocumentViewId,
+    ) {
+        let tmp_dir = TempDir::new().unwrap();
+        let blob_store =
+            BlobStore::initialize(tmp_dir.path().to_path_buf(), Some(10), Some("secret")).unwrap();
+
+        blob_store
+            .insert_packed(&small_view_id, b"Hello")
+            .await
+            .unwrap();
+        let mut file = blob_store.create(&large_view_id).await.unwrap();
+        file.write_all(b"Hello, Panda!").await.unwrap();
+        file.flush().await.unwrap();
+
+        // Bytes on the file system are encrypted
+        let on_disk = fs::read(blob_store.path(&large_view_id)).await.unwrap();
+        assert_eq!(on_disk.len(), 13);
+        assert_ne!(on_disk, b"Hello, Panda!".to_vec());
+
+        assert_eq!(
+            read(&blob_store, &small_view_id).await,
+            Some(b"Hello".to_vec())
+        );
+        assert_eq!(
+            read(&blob_store, &large_view_id).await,
+            Some(b"Hello, Panda!".to_vec())
+        );
+
+        // Blobs can not be read without the right key
+        let blob_store =
+            BlobStore::initialize(tmp_dir.path().to_path_buf(), Some(10), Some("wrong")).unwrap();
+        assert_ne!(
+            read(&blob_store, &large_view_id).await,
+            Some(b"Hello, Panda!".to_vec())
+        );
+
+        // Encryption can not be disabled again
+        assert!(BlobStore::initialize(tmp_dir.path().to_path_buf(), Some(10), None).is_err());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn refuses_encrypting_existing_blobs(
+        #[from(random_document_view_id)] view_id: DocumentViewId,
+    ) {
+        let tmp_dir = TempDir::new().unwrap();
+        let blob_store = BlobStore::initialize(tmp_dir.path().to_path_buf(), None, None).unwrap();
+
+        // Encryption can be enabled as long as there are no blobs
+        assert!(!blob_store.contains_blobs().unwrap());
+
+        let mut file = blob_store.create(&view_id).await.unwrap();
+        file.write_all(b"Hello, Panda!").await.unwrap();
+        file.flush().await.unwrap();
+        assert!(blob_store.contains_blobs().unwrap());
+
+        assert!(BlobStore::initialize(tmp_dir.path().to_path_buf(), None, Some("secret")).is_err());
+        assert!(!fs::try_exists(tmp_dir.path().join(SALT_FILE))
+            .await
+            .unwrap());
+    }
+}
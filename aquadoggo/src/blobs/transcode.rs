@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use p2panda_rs::document::DocumentViewId;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+
+use crate::blobs::mime::essence;
+use crate::blobs::BlobStore;
+
+/// Placeholder in transcoder commands which gets replaced with the path of the original blob.
+pub const INPUT_PLACEHOLDER: &str = "{input}";
+
+/// Placeholder in transcoder commands which gets replaced with the path the derived blob is
+/// expected at.
+pub const OUTPUT_PLACEHOLDER: &str = "{output}";
+
+/// Maximum length of transcoder names.
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Maximum number of bytes of the error output of a command which are kept for error messages.
+const MAX_STDERR_LENGTH: u64 = 4 * 1024;
+
+/// External command transcoding materialized blobs into another format, for example to offer
+/// web-friendly versions of audio and video files.
+///
+/// The result is kept as a blob derived from the original one, next to it on the file system.
+/// Derived blobs are node-local, they are never replicated to other nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobTranscoder {
+    /// Name of the derived blob, for example "webm".
+    pub name: String,
+
+    /// Mime types of blobs which get transcoded, for example "video/mp4". Wildcards like
+    /// "video/*" match all subtypes.
+    pub mime_types: Vec<String>,
+
+    /// Mime type of the derived blob.
+    pub output_mime_type: String,
+
+    /// Program and its arguments, containing the `{input}` and `{output}` placeholders.
+    pub command: Vec<String>,
+
+    /// Duration in seconds after which the command gets killed.
+    pub timeout: u64,
+}
+
+impl BlobTranscoder {
+    /// Returns true if the name can be used for derived blobs.
+    ///
+    /// Names are part of file names and URLs, only lowercase letters, digits, "-" and "_" are
+    /// allowed.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= MAX_NAME_LENGTH
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    }
+
+    /// Returns true if blobs with the given mime type get transcoded.
+    pub fn accepts(&self, mime_type: &str) -> bool {
+        let mime_type = essence(mime_type);
+
+        self.mime_types.iter().any(|pattern| {
+            let pattern = essence(pattern);
+            match pattern.strip_suffix("/*") {
+                Some(top_level_type) => mime_type.split('/').next() == Some(top_level_type),
+                None => pattern == mime_type,
+            }
+        })
+    }
+
+    /// Transcode a materialized blob and keep the result as derived blob.
+    ///
+    /// The command reads a decrypted copy of the original blob from a temporary folder inside the
+    /// blob store and writes its result into the same folder. Commands running longer than the
+    /// configured timeout are killed. Returns the length of the derived blob in bytes.
+    pub async fn transcode(&self, blob_store: &BlobStore, view_id: &DocumentViewId) -> Result<u64> {
+        let tmp_dir = blob_store.temp_dir().await?;
+        let input_path = tmp_dir.path().join("input");
+        let output_path = tmp_dir.path().join("output");
+
+        let mut reader = blob_store
+            .open(view_id)
+            .await?
+            .ok_or_else(|| anyhow!("Blob {} is not materialized", view_id))?;
+        let mut input = File::create(&input_path).await?;
+        tokio::io::copy(&mut reader, &mut input).await?;
+        input.flush().await?;
+
+        let args: Vec<String> = self
+            .command
+            .iter()
+            .map(|arg| {
+                arg.replace(INPUT_PLACEHOLDER, &input_path.to_string_lossy())
+                    .replace(OUTPUT_PLACEHOLDER, &output_path.to_string_lossy())
+            })
+            .collect();
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| anyhow!("Command of transcoder '{}' is empty", self.name))?;
+
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut stderr = child.stderr.take().expect("Error output is piped");
+
+        let run = async {
+            // Keep the beginning of the error output and discard the rest, the command would
+            // block otherwise when the pipe is full
+            let mut message = Vec::new();
+            (&mut stderr)
+                .take(MAX_STDERR_LENGTH)
+                .read_to_end(&mut message)
+                .await?;
+            tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await?;
+
+            let status = child.wait().await?;
+            Ok::<_, std::io::Error>((status, message))
+        };
+
+        // The command gets killed when the timeout drops it
+        let (status, message) = tokio::time::timeout(Duration::from_secs(self.timeout), run)
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "Transcoder '{}' timed out after {} seconds",
+                    self.name,
+                    self.timeout
+                )
+            })??;
+
+        if !status.success() {
+            bail!(
+                "Transcoder '{}' failed with {}: {}",
+                self.name,
+                status,
+                String::from_utf8_lossy(&message).trim()
+            );
+        }
+
+        let mut output_file = File::open(&output_path)
+            .await
+            .map_err(|_| anyhow!("Transcoder '{}' did not write any output", self.name))?;
+        let mut writer = blob_store.create_derived(view_id, &self.name).await?;
+        let length = tokio::io::copy(&mut output_file, &mut writer).await?;
+        writer.flush().await?;
+
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::test_utils::fixtures::random_document_view_id;
+    use rstest::rstest;
+    use tempfile::TempDir;
+    use tokio::fs;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::blobs::BlobStore;
+
+    use super::BlobTranscoder;
+
+    fn transcoder(command: &[&str]) -> BlobTranscoder {
+        BlobTranscoder {
+            name: "upper".into(),
+            mime_types: vec!["text/*".into()],
+            output_mime_type: "text/plain".into(),
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            timeout: 10,
+        }
+    }
+
+    #[test]
+    fn matches_mime_types() {
+        let transcoder = BlobTranscoder {
+            mime_types: vec!["video/*".into(), "audio/wav".into()],
+            ..transcoder(&[])
+        };
+
+        assert!(transcoder.accepts("video/mp4"));
+        assert!(transcoder.accepts("Audio/WAV; rate=44100"));
+        assert!(!transcoder.accepts("audio/mpeg"));
+        assert!(!transcoder.accepts("videos/mp4"));
+
+        assert!(BlobTranscoder::is_valid_name("webm_720p"));
+        assert!(!BlobTranscoder::is_valid_name(""));
+        assert!(!BlobTranscoder::is_valid_name("../webm"));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn transcodes_into_derived_blob(
+        #[from(random_document_view_id)] view_id: DocumentViewId,
+    ) {
+        let tmp_dir = TempDir::new().unwrap();
+        let blob_store =
+            BlobStore::initialize(tmp_dir.path().to_path_buf(), None, Some("secret")).unwrap();
+
+        let mut file = blob_store.create(&view_id).await.unwrap();
+        file.write_all(b"Hello, Panda!").await.unwrap();
+        file.flush().await.unwrap();
+
+        let transcoder = transcoder(&["sh", "-c", "tr a-z A-Z < {input} > {output}"]);
+        let length = transcoder.transcode(&blob_store, &view_id).await.unwrap();
+        assert_eq!(length, 13);
+
+        // Derived blobs are encrypted as well
+        let path = blob_store.derived_path(&view_id, "upper");
+        assert_ne!(fs::read(&path).await.unwrap(), b"HELLO, PANDA!");
+
+        let mut reader = blob_store
+            .open_derived(&view_id, "upper")
+            .await
+            .unwrap()
+            .unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, b"HELLO, PANDA!");
+
+        // Failing commands don't leave a derived blob behind
+        let failing = BlobTranscoder {
+            name: "failing".into(),
+            ..transcoder(&["sh", "-c", "exit 1"])
+        };
+        assert!(failing.transcode(&blob_store, &view_id).await.is_err());
+        assert!(blob_store
+            .open_derived(&view_id, "failing")
+            .await
+            .unwrap()
+            .is_none());
+
+        // Commands running for too long are killed
+        let slow = BlobTranscoder {
+            name: "slow".into(),
+            timeout: 1,
+            ..transcoder(&["sleep", "10"])
+        };
+        let err = slow.transcode(&blob_store, &view_id).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+
+        // Decrypted copies are only kept temporarily inside the blob store
+        let mut entries = fs::read_dir(tmp_dir.path().join("tmp")).await.unwrap();
+        assert!(entries.next_entry().await.unwrap().is_none());
+
+        // Derived blobs are removed together with their original
+        assert!(blob_store.remove(&view_id).await.unwrap());
+        assert!(!fs::try_exists(&path).await.unwrap());
+    }
+}
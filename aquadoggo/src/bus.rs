@@ -1,9 +1,15 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::LogId;
+use p2panda_rs::identity::PublicKey;
 use p2panda_rs::operation::OperationId;
+use p2panda_rs::schema::SchemaId;
 
 use crate::manager::Sender;
-use crate::network::{Peer, PeerMessage};
+use crate::materializer::{Task, TaskEvent, TaskInput};
+use crate::network::{ConnectionDirection, ConnectionInfo, Peer, PeerMessage};
+use crate::replication::{ReplicationPause, SchemaIdSet};
 
 /// Sender for cross-service communication bus.
 pub type ServiceSender = Sender<ServiceMessage>;
@@ -14,11 +20,37 @@ pub enum ServiceMessage {
     /// A new operation arrived at the node.
     NewOperation(OperationId),
 
+    /// A schema was added to the schema provider.
+    SchemaAdded(SchemaId),
+
+    /// A schema known to the schema provider was updated.
+    SchemaUpdated(SchemaId),
+
+    /// The GraphQL schema got rebuilt and changed, contains the new version of the schema.
+    GraphQLSchemaChanged(u64),
+
+    /// A document of this schema was materialized into a new latest view.
+    DocumentUpdated(SchemaId, DocumentId, DocumentViewId),
+
+    /// A task was scheduled manually and should be moved into the materializer task queue.
+    ScheduleTask(Task<TaskInput>),
+
+    /// A materializer task was started, finished or failed.
+    TaskEvent(TaskEvent<TaskInput>),
+
+    /// Entries of the same public key conflicting with each other arrived in this log, the key
+    /// pair is probably used on more than one device.
+    LogForked(PublicKey, LogId),
+
     /// Node established a bi-directional connection to another node.
-    PeerConnected(Peer),
+    PeerConnected(Peer, ConnectionInfo),
 
     /// Node closed a connection to another node.
-    PeerDisconnected(Peer),
+    PeerDisconnected(Peer, ConnectionInfo),
+
+    /// A replication session over these schema ids was established with another node, requested
+    /// by us when the direction is outbound.
+    SessionEstablished(Peer, ConnectionDirection, SchemaIdSet),
 
     /// Node sent a message to remote node.
     SentMessage(Peer, PeerMessage),
@@ -28,4 +60,21 @@ pub enum ServiceMessage {
 
     /// Replication protocol failed with an critical error.
     ReplicationFailed(Peer),
+
+    /// Replication within this scope should be paused until it gets resumed.
+    PauseReplication(ReplicationPause),
+
+    /// All pauses of replication lying within this scope should be lifted.
+    ResumeReplication(ReplicationPause),
+
+    /// All operations received via replication got materialized and no further tasks are
+    /// pending.
+    SyncComplete,
+
+    /// A client requested a blob which is not materialized on this node, it should be fetched
+    /// from a connected peer.
+    BlobRequested(DocumentId),
+
+    /// None of the connected peers could provide the requested blob.
+    BlobUnavailable(DocumentId),
 }
@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use ed25519_dalek::Signature;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use thiserror::Error;
+
+/// Maximum age of an auth token in seconds.
+const MAX_AGE: u64 = 60 * 5;
+
+/// Tolerated difference in seconds between the clocks of client and node.
+const MAX_CLOCK_SKEW: u64 = 30;
+
+/// Prefix of the signed message, separating auth tokens from other signed data.
+const MESSAGE_PREFIX: &str = "aquadoggo-auth:";
+
+/// Public key of an authenticated client, attached to incoming GraphQL requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authenticated(pub PublicKey);
+
+/// Short-lived token proving that a client holds the private key of a public key.
+///
+/// Tokens are formatted as `<public_key>.<timestamp>.<signature>` where the signature is created
+/// over the string `aquadoggo-auth:<audience>:<timestamp>`. The audience is the identity of the
+/// node the token is issued for, as returned by the `nodeInfo` query, tokens are not accepted by
+/// any other node. The timestamp is given in seconds since UNIX epoch, tokens are valid for five
+/// minutes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthToken {
+    public_key: PublicKey,
+    timestamp: u64,
+    signature: Signature,
+}
+
+impl AuthToken {
+    /// Returns a new auth token for the node with the given identity, signed by the given key
+    /// pair.
+    pub fn new(key_pair: &KeyPair, audience: &str, timestamp: u64) -> Self {
+        Self {
+            public_key: key_pair.public_key(),
+            timestamp,
+            signature: key_pair.sign(&Self::message(audience, timestamp)),
+        }
+    }
+
+    /// Checks signature and age of the token, returns the authenticated public key.
+    ///
+    /// Tokens issued for another audience than the given identity of the verifying node have an
+    /// invalid signature.
+    pub fn verify(&self, audience: &str, now: u64) -> Result<Authenticated, AuthTokenError> {
+        if self.timestamp > now + MAX_CLOCK_SKEW || self.timestamp + MAX_AGE < now {
+            return Err(AuthTokenError::Expired);
+        }
+
+        KeyPair::verify(
+            &self.public_key,
+            &Self::message(audience, self.timestamp),
+            &self.signature,
+        )
+        .map_err(|_| AuthTokenError::InvalidSignature)?;
+
+        Ok(Authenticated(self.public_key))
+    }
+
+    fn message(audience: &str, timestamp: u64) -> Vec<u8> {
+        format!("{MESSAGE_PREFIX}{audience}:{timestamp}").into_bytes()
+    }
+}
+
+impl Display for AuthToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}",
+            self.public_key,
+            self.timestamp,
+            hex::encode(self.signature.to_bytes())
+        )
+    }
+}
+
+impl FromStr for AuthToken {
+    type Err = AuthTokenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('.').collect();
+        if parts.len() != 3 {
+            return Err(AuthTokenError::InvalidFormat);
+        }
+
+        let public_key =
+            PublicKey::from_str(parts[0]).map_err(|_| AuthTokenError::InvalidFormat)?;
+        let timestamp = parts[1]
+            .parse()
+            .map_err(|_| AuthTokenError::InvalidFormat)?;
+        let signature_bytes = hex::decode(parts[2]).map_err(|_| AuthTokenError::InvalidFormat)?;
+        let signature = Signature::try_from(signature_bytes.as_slice())
+            .map_err(|_| AuthTokenError::InvalidFormat)?;
+
+        Ok(Self {
+            public_key,
+            timestamp,
+            signature,
+        })
+    }
+}
+
+/// Errors returned when parsing or verifying auth tokens.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AuthTokenError {
+    /// Token could not be decoded.
+    #[error("Invalid auth token format")]
+    InvalidFormat,
+
+    /// Signature does not match the public key of the token.
+    #[error("Invalid auth token signature")]
+    InvalidSignature,
+
+    /// Timestamp of the token is too old or too far in the future.
+    #[error("Auth token expired")]
+    Expired,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use super::{AuthToken, AuthTokenError, Authenticated};
+
+    const AUDIENCE: &str = "12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY";
+
+    #[rstest]
+    fn verify_auth_tokens(key_pair: KeyPair) {
+        let token = AuthToken::new(&key_pair, AUDIENCE, 1000);
+
+        // Tokens can be encoded and decoded again
+        let decoded = AuthToken::from_str(&token.to_string()).unwrap();
+        assert_eq!(decoded, token);
+
+        assert_eq!(
+            decoded.verify(AUDIENCE, 1010),
+            Ok(Authenticated(key_pair.public_key()))
+        );
+        assert_eq!(decoded.verify(AUDIENCE, 2000), Err(AuthTokenError::Expired));
+        assert_eq!(decoded.verify(AUDIENCE, 500), Err(AuthTokenError::Expired));
+
+        // Tokens can not be re-used for another node
+        assert_eq!(
+            decoded.verify("12D3KooWCqtLMJQLY3sm9rpDampJ2nPLswPPZto3mrRY7794QATF", 1010),
+            Err(AuthTokenError::InvalidSignature)
+        );
+
+        // Tokens can not be re-used for another public key
+        let forged = format!(
+            "{}.{}",
+            KeyPair::new().public_key(),
+            token.to_string().split_once('.').unwrap().1
+        );
+        assert_eq!(
+            AuthToken::from_str(&forged).unwrap().verify(AUDIENCE, 1000),
+            Err(AuthTokenError::InvalidSignature)
+        );
+
+        assert_eq!(
+            AuthToken::from_str("invalid"),
+            Err(AuthTokenError::InvalidFormat)
+        );
+    }
+}
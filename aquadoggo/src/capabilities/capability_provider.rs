@@ -0,0 +1,603 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use log::debug;
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::traits::WithPublicKey;
+use p2panda_rs::operation::OperationValue;
+use p2panda_rs::schema::{FieldType, Schema, SchemaId};
+use p2panda_rs::storage_provider::error::DocumentStorageError;
+use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
+
+use crate::capabilities::Permission;
+use crate::db::SqlStore;
+use crate::replication::now;
+
+/// Name of the string field holding the public key a capability document grants a permission to.
+pub const CAPABILITY_PUBLIC_KEY_FIELD: &str = "public_key";
+
+/// Name of the string field holding the granted permission of a capability document.
+pub const CAPABILITY_PERMISSION_FIELD: &str = "permission";
+
+/// Permission granted by a capability document.
+#[derive(Debug, Clone)]
+struct Grant {
+    /// Public keys of all authors who created or updated the capability document.
+    issuers: HashSet<PublicKey>,
+
+    /// Public key receiving the permission.
+    public_key: PublicKey,
+
+    /// The granted permission.
+    permission: Permission,
+}
+
+impl Grant {
+    /// Returns true if the capability document was only ever written by admins.
+    ///
+    /// Anyone can publish an UPDATE to a document, a capability created by an admin can not be
+    /// trusted anymore after someone else changed it.
+    fn is_issued_by(&self, admins: &HashSet<PublicKey>) -> bool {
+        self.issuers.iter().all(|issuer| admins.contains(issuer))
+    }
+}
+
+/// Grants loaded from capability documents, shared between all clones of a provider.
+#[derive(Debug, Default)]
+struct CachedGrants {
+    /// Incremented on every invalidation.
+    generation: u64,
+
+    grants: Option<Arc<Vec<Grant>>>,
+}
+
+/// Documents a public key is allowed to read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadScope {
+    /// All documents can be read.
+    All,
+
+    /// Only documents linked to one of these ACL documents can be read, next to all documents
+    /// which are not access controlled.
+    Only(Vec<DocumentId>),
+}
+
+/// Authorises requests based on materialized capability documents.
+///
+/// When no capability schema is configured every request is permitted. Otherwise a public key
+/// needs to be listed as an admin in the node configuration or hold a capability document which
+/// grants the requested permission.
+///
+/// Capability documents are only considered valid when all their operations were authored by
+/// admins. Admins are either configured directly or were granted the `admin` permission by another
+/// admin, this allows delegating access control to other peers in the network.
+///
+/// Grants are loaded from the database once and cached until `invalidate` is called, the GraphQL
+/// schema manager does this whenever a capability document changed.
+///
+/// Next to capability documents, public keys which redeemed an invite of the node operator may
+/// publish to the schemas of that invite until it expires, see `Invite`.
+///
+/// Read access can be restricted per document by configuring the name of an "ACL field". Documents
+/// of schemas with a relation field of that name can only be read by public keys holding the
+/// `read:<document_id>` permission for the related ACL document.
+#[derive(Clone, Debug, Default)]
+pub struct CapabilityProvider {
+    /// Schema of documents granting permissions, access control is disabled when not set.
+    schema_id: Option<SchemaId>,
+
+    /// Public keys which hold the admin permission without requiring a capability document.
+    admin_public_keys: Vec<PublicKey>,
+
+    /// Name of relation field linking documents to the ACL document controlling read access.
+    read_acl_field: Option<String>,
+
+    /// Cached grants of all capability documents.
+    cache: Arc<Mutex<CachedGrants>>,
+}
+
+impl CapabilityProvider {
+    /// Returns a new instance of `CapabilityProvider`.
+    pub fn new(schema_id: Option<SchemaId>, admin_public_keys: Vec<PublicKey>) -> Self {
+        Self {
+            schema_id,
+            admin_public_keys,
+            read_acl_field: None,
+            cache: Arc::new(Mutex::new(CachedGrants::default())),
+        }
+    }
+
+    /// Restrict read access of documents containing a relation field with this name.
+    pub fn with_read_acl_field(mut self, read_acl_field: Option<String>) -> Self {
+        self.read_acl_field = read_acl_field;
+        self
+    }
+
+    /// Returns the name of the ACL field if documents of this schema are access controlled.
+    pub fn read_acl_field(&self, schema: &Schema) -> Option<&str> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let field_name = self.read_acl_field.as_deref()?;
+        match schema.fields().get(field_name) {
+            Some(FieldType::Relation(_)) => Some(field_name),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the given public key is allowed to read this document.
+    ///
+    /// Documents without ACL field can be read by anyone, access controlled documents can only be
+    /// read by authenticated public keys holding the right permission.
+    pub async fn can_read(
+        &self,
+        store: &SqlStore,
+        public_key: Option<&PublicKey>,
+        schema: &Schema,
+        document: &impl AsDocument,
+    ) -> Result<bool, DocumentStorageError> {
+        let field_name = match self.read_acl_field(schema) {
+            Some(field_name) => field_name,
+            None => return Ok(true),
+        };
+
+        let acl_document_id = match document.get(field_name) {
+            Some(OperationValue::Relation(relation)) => relation.document_id().to_owned(),
+            _ => return Ok(true),
+        };
+
+        match public_key {
+            Some(public_key) => {
+                self.is_permitted(store, public_key, &Permission::Read(acl_document_id))
+                    .await
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the access controlled documents the given public key is allowed to read.
+    pub async fn read_scope(
+        &self,
+        store: &SqlStore,
+        public_key: Option<&PublicKey>,
+    ) -> Result<ReadScope, DocumentStorageError> {
+        let (schema_id, public_key) = match (&self.schema_id, public_key) {
+            (None, _) => return Ok(ReadScope::All),
+            (Some(_), None) => return Ok(ReadScope::Only(Vec::new())),
+            (Some(schema_id), Some(public_key)) => (schema_id, public_key),
+        };
+
+        let grants = self.grants(store, schema_id).await?;
+        let admins = self.admins(&grants);
+
+        if admins.contains(public_key) {
+            return Ok(ReadScope::All);
+        }
+
+        let mut acl_document_ids = Vec::new();
+        for grant in grants.iter() {
+            if &grant.public_key != public_key || !grant.is_issued_by(&admins) {
+                continue;
+            }
+
+            match &grant.permission {
+                Permission::Admin | Permission::ReadAny => return Ok(ReadScope::All),
+                Permission::Read(document_id) => acl_document_ids.push(document_id.to_owned()),
+                _ => (),
+            }
+        }
+
+        Ok(ReadScope::Only(acl_document_ids))
+    }
+
+    /// Returns true if access control via capability documents is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.schema_id.is_some()
+    }
+
+    /// Returns the schema of capability documents if access control is enabled.
+    pub fn schema_id(&self) -> Option<&SchemaId> {
+        self.schema_id.as_ref()
+    }
+
+    /// Drop cached grants, they are loaded from capability documents again with the next request.
+    pub fn invalidate(&self) {
+        let mut cache = self.cache.lock().expect("Capability cache lock poisoned");
+        cache.generation += 1;
+        cache.grants = None;
+    }
+
+    /// Returns true if admin public keys are configured for this node.
+    pub fn has_admins(&self) -> bool {
+        !self.admin_public_keys.is_empty()
+    }
+
+    /// Returns true if the given public key may run administrative requests on this node.
+    ///
+    /// Other than `is_permitted` this never permits a request by default: without configured
+    /// admin public keys nobody is an admin, also when access control via capability documents is
+    /// disabled.
+    pub async fn is_admin(
+        &self,
+        store: &SqlStore,
+        public_key: &PublicKey,
+    ) -> Result<bool, DocumentStorageError> {
+        if self.admin_public_keys.contains(public_key) {
+            return Ok(true);
+        }
+
+        if !self.is_enabled() {
+            return Ok(false);
+        }
+
+        self.is_permitted(store, public_key, &Permission::Admin)
+            .await
+    }
+
+    /// Returns true if the given public key holds the requested permission.
+    pub async fn is_permitted(
+        &self,
+        store: &SqlStore,
+        public_key: &PublicKey,
+        requested: &Permission,
+    ) -> Result<bool, DocumentStorageError> {
+        let schema_id = match &self.schema_id {
+            Some(schema_id) => schema_id,
+            None => return Ok(true),
+        };
+
+        let grants = self.grants(store, schema_id).await?;
+        let admins = self.admins(&grants);
+
+        if admins.contains(public_key) {
+            return Ok(true);
+        }
+
+        if grants.iter().any(|grant| {
+            &grant.public_key == public_key
+                && grant.is_issued_by(&admins)
+                && grant.permission.grants(requested)
+        }) {
+            return Ok(true);
+        }
+
+        // Public keys which redeemed an invite may publish to its schemas until it expires
+        match requested {
+            Permission::Publish(schema_id) => Ok(store
+                .get_invited_schema_ids(public_key, now() as i64)
+                .await
+                .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?
+                .contains(schema_id)),
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns the permission required to publish an operation for the given schema.
+    ///
+    /// Publishing to the capability schema itself requires admin access.
+    pub fn publish_permission(&self, schema_id: &SchemaId) -> Permission {
+        if self.schema_id.as_ref() == Some(schema_id) {
+            Permission::Admin
+        } else {
+            Permission::Publish(schema_id.to_owned())
+        }
+    }
+
+    /// Returns all grants of materialized capability documents, loads them if they are not
+    /// cached.
+    async fn grants(
+        &self,
+        store: &SqlStore,
+        schema_id: &SchemaId,
+    ) -> Result<Arc<Vec<Grant>>, DocumentStorageError> {
+        let generation = {
+            let cache = self.cache.lock().expect("Capability cache lock poisoned");
+            if let Some(grants) = &cache.grants {
+                return Ok(grants.clone());
+            }
+            cache.generation
+        };
+
+        let grants = Arc::new(self.load_grants(store, schema_id).await?);
+
+        // Grants might be outdated already when the cache got invalidated in the meantime
+        let mut cache = self.cache.lock().expect("Capability cache lock poisoned");
+        if cache.generation == generation {
+            cache.grants = Some(grants.clone());
+        }
+
+        Ok(grants)
+    }
+
+    /// Load all grants from materialized capability documents.
+    async fn load_grants(
+        &self,
+        store: &SqlStore,
+        schema_id: &SchemaId,
+    ) -> Result<Vec<Grant>, DocumentStorageError> {
+        let documents = store.get_documents_by_schema(schema_id).await?;
+
+        let mut grants = Vec::new();
+        for document in &documents {
+            let public_key = match document.get(CAPABILITY_PUBLIC_KEY_FIELD) {
+                Some(OperationValue::String(value)) => PublicKey::from_str(value).ok(),
+                _ => None,
+            };
+
+            let permission = match document.get(CAPABILITY_PERMISSION_FIELD) {
+                Some(OperationValue::String(value)) => Permission::from_str(value).ok(),
+                _ => None,
+            };
+
+            let (public_key, permission) = match (public_key, permission) {
+                (Some(public_key), Some(permission)) => (public_key, permission),
+                _ => {
+                    debug!("Ignore invalid capability document {}", document.id());
+                    continue;
+                }
+            };
+
+            let issuers = store
+                .get_operations_by_document_id(document.id())
+                .await
+                .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?
+                .iter()
+                .map(|operation| operation.public_key().to_owned())
+                .collect();
+
+            grants.push(Grant {
+                issuers,
+                public_key,
+                permission,
+            });
+        }
+
+        Ok(grants)
+    }
+
+    /// Derive the set of all admins, following delegations from configured admins.
+    fn admins(&self, grants: &[Grant]) -> HashSet<PublicKey> {
+        let mut admins: HashSet<PublicKey> = self.admin_public_keys.iter().cloned().collect();
+
+        // Repeat until no further admins were found, delegations can be chained
+        loop {
+            let mut changed = false;
+
+            for grant in grants {
+                if grant.permission == Permission::Admin
+                    && grant.is_issued_by(&admins)
+                    && admins.insert(grant.public_key)
+                {
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        admins
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::{FieldType, Schema, SchemaId};
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::capabilities::Permission;
+    use crate::test_utils::{add_document, add_schema, test_runner, update_document, TestNode};
+
+    use super::{CapabilityProvider, CAPABILITY_PERMISSION_FIELD, CAPABILITY_PUBLIC_KEY_FIELD};
+
+    async fn add_capability_schema(node: &mut TestNode, key_pair: &KeyPair) -> Schema {
+        add_schema(
+            node,
+            "capability",
+            vec![
+                (CAPABILITY_PUBLIC_KEY_FIELD, FieldType::String),
+                (CAPABILITY_PERMISSION_FIELD, FieldType::String),
+            ],
+            key_pair,
+        )
+        .await
+    }
+
+    async fn grant(
+        node: &mut TestNode,
+        schema_id: &SchemaId,
+        issuer: &KeyPair,
+        public_key: &KeyPair,
+        permission: &str,
+    ) -> DocumentViewId {
+        add_document(
+            node,
+            schema_id,
+            vec![
+                (
+                    CAPABILITY_PUBLIC_KEY_FIELD,
+                    public_key.public_key().to_string().into(),
+                ),
+                (CAPABILITY_PERMISSION_FIELD, permission.into()),
+            ],
+            issuer,
+        )
+        .await
+    }
+
+    #[rstest]
+    fn permits_everything_when_disabled(key_pair: KeyPair) {
+        test_runner(|node: TestNode| async move {
+            let provider = CapabilityProvider::default();
+
+            assert!(!provider.is_enabled());
+            assert!(provider
+                .is_permitted(
+                    &node.context.store,
+                    &key_pair.public_key(),
+                    &Permission::Admin
+                )
+                .await
+                .unwrap());
+        });
+    }
+
+    #[rstest]
+    fn requires_configured_admins(key_pair: KeyPair) {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+
+            // Nobody is an admin when no admin public keys are configured
+            let provider = CapabilityProvider::default();
+            assert!(!provider.has_admins());
+            assert!(!provider
+                .is_admin(store, &key_pair.public_key())
+                .await
+                .unwrap());
+
+            // Configured admins are recognised, also without capability schema
+            let provider = CapabilityProvider::new(None, vec![key_pair.public_key()]);
+            assert!(provider.has_admins());
+            assert!(provider
+                .is_admin(store, &key_pair.public_key())
+                .await
+                .unwrap());
+            assert!(!provider
+                .is_admin(store, &KeyPair::new().public_key())
+                .await
+                .unwrap());
+        });
+    }
+
+    #[rstest]
+    fn permits_granted_public_keys(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let admin = key_pair;
+            let delegated_admin = KeyPair::new();
+            let publisher = KeyPair::new();
+            let stranger = KeyPair::new();
+
+            let schema = add_capability_schema(&mut node, &admin).await;
+            let provider =
+                CapabilityProvider::new(Some(schema.id().to_owned()), vec![admin.public_key()]);
+            let store = node.context.store.clone();
+            let blob = Permission::Publish(SchemaId::Blob(1));
+
+            // Nobody except of the configured admin holds any permissions yet
+            assert!(provider
+                .is_permitted(&store, &admin.public_key(), &Permission::Admin)
+                .await
+                .unwrap());
+            assert!(!provider
+                .is_permitted(&store, &publisher.public_key(), &blob)
+                .await
+                .unwrap());
+
+            // Delegate admin permission and let the new admin grant publishing rights
+            grant(&mut node, schema.id(), &admin, &delegated_admin, "admin").await;
+            grant(
+                &mut node,
+                schema.id(),
+                &delegated_admin,
+                &publisher,
+                "publish:blob_v1",
+            )
+            .await;
+
+            // Capabilities issued by anyone else than an admin are ignored
+            grant(&mut node, schema.id(), &stranger, &stranger, "admin").await;
+
+            // Grants are cached until capability documents changed
+            assert!(!provider
+                .is_permitted(&store, &delegated_admin.public_key(), &Permission::Admin)
+                .await
+                .unwrap());
+            provider.invalidate();
+
+            assert!(provider
+                .is_permitted(&store, &delegated_admin.public_key(), &Permission::Admin)
+                .await
+                .unwrap());
+            assert!(provider
+                .is_permitted(&store, &publisher.public_key(), &blob)
+                .await
+                .unwrap());
+            assert!(!provider
+                .is_permitted(
+                    &store,
+                    &publisher.public_key(),
+                    &Permission::Publish(SchemaId::BlobPiece(1))
+                )
+                .await
+                .unwrap());
+            assert!(!provider
+                .is_permitted(&store, &stranger.public_key(), &blob)
+                .await
+                .unwrap());
+        });
+    }
+
+    #[rstest]
+    fn ignores_capabilities_updated_by_others(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let admin = key_pair;
+            let stranger = KeyPair::new();
+
+            let schema = add_capability_schema(&mut node, &admin).await;
+            let provider =
+                CapabilityProvider::new(Some(schema.id().to_owned()), vec![admin.public_key()]);
+            let store = node.context.store.clone();
+            let blob = Permission::Publish(SchemaId::Blob(1));
+
+            let view_id = grant(&mut node, schema.id(), &admin, &stranger, "publish:blob_v1").await;
+            assert!(provider
+                .is_permitted(&store, &stranger.public_key(), &blob)
+                .await
+                .unwrap());
+
+            // The stranger tries to escalate the capability created by the admin
+            update_document(
+                &mut node,
+                schema.id(),
+                vec![(CAPABILITY_PERMISSION_FIELD, "admin".into())],
+                &view_id,
+                &stranger,
+            )
+            .await;
+            provider.invalidate();
+
+            assert!(!provider
+                .is_permitted(&store, &stranger.public_key(), &Permission::Admin)
+                .await
+                .unwrap());
+            assert!(!provider
+                .is_permitted(&store, &stranger.public_key(), &blob)
+                .await
+                .unwrap());
+        });
+    }
+
+    #[test]
+    fn publishing_capabilities_requires_admin() {
+        let provider = CapabilityProvider::new(Some(SchemaId::Blob(1)), vec![]);
+
+        assert_eq!(
+            provider.publish_permission(&SchemaId::Blob(1)),
+            Permission::Admin
+        );
+        assert_eq!(
+            provider.publish_permission(&SchemaId::BlobPiece(1)),
+            Permission::Publish(SchemaId::BlobPiece(1))
+        );
+    }
+}
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use p2panda_rs::schema::SchemaId;
+use rand::RngCore;
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+use crate::replication::now;
+
+/// Number of random bytes of an invite code.
+const CODE_LENGTH: usize = 16;
+
+/// Invite granting the public key redeeming it the permission to publish to a set of schemas for
+/// a limited time.
+///
+/// Invites allow running semi-open communities: the node operator hands out codes to new members
+/// who redeem them with an auth token of their public key, without requiring an admin to create a
+/// capability document for every member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invite {
+    /// Random code to be shared with the invited person.
+    pub code: String,
+
+    /// Schemas the invite allows publishing to.
+    pub schema_ids: Vec<SchemaId>,
+
+    /// UNIX timestamp in seconds after which the invite can not be redeemed or used anymore.
+    pub expires_at: i64,
+}
+
+impl Invite {
+    /// Generates a new invite and persists it in the store.
+    pub async fn create(
+        store: &SqlStore,
+        schema_ids: Vec<SchemaId>,
+        valid_for: Duration,
+    ) -> Result<Self, SqlStoreError> {
+        let mut bytes = [0u8; CODE_LENGTH];
+        rand::thread_rng().fill_bytes(&mut bytes);
+
+        let invite = Self {
+            code: hex::encode(bytes),
+            schema_ids,
+            expires_at: (now() + valid_for.as_secs()) as i64,
+        };
+
+        store
+            .insert_invite(&invite.code, &invite.schema_ids, invite.expires_at)
+            .await?;
+
+        Ok(invite)
+    }
+}
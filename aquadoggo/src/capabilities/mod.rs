@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Access control based on materialized capability documents.
+//!
+//! A node can be configured with a "capability schema". Documents of that schema grant named
+//! permissions to public keys and are consulted by the API layers when authorising requests. As
+//! capability documents are regular p2panda documents, access control can be fully managed
+//! through the p2p network.
+//!
+//! Node operators can additionally hand out invites, granting new public keys the permission to
+//! publish to a set of schemas for a limited time.
+mod auth_token;
+mod capability_provider;
+mod invite;
+mod permission;
+
+pub use auth_token::{AuthToken, AuthTokenError, Authenticated};
+pub use capability_provider::{CapabilityProvider, ReadScope};
+pub use invite::Invite;
+pub use permission::Permission;
@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::SchemaId;
+use thiserror::Error;
+
+const ADMIN: &str = "admin";
+
+const PUBLISH_PREFIX: &str = "publish:";
+
+const READ_PREFIX: &str = "read:";
+
+const WILDCARD: &str = "*";
+
+/// Named permission which can be granted to a public key through a capability document.
+///
+/// Permissions are represented as strings inside of capability documents:
+///
+/// - `admin`: Grants all permissions, including issuing further capabilities
+/// - `publish:*`: Allows publishing operations for any schema
+/// - `publish:<schema_id>`: Allows publishing operations for the given schema
+/// - `read:*`: Allows reading any access controlled document
+/// - `read:<document_id>`: Allows reading documents linked to the given ACL document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Permission {
+    /// Access to all APIs, including publishing to the capability schema itself.
+    Admin,
+
+    /// Publish operations for any schema.
+    PublishAny,
+
+    /// Publish operations for the given schema.
+    Publish(SchemaId),
+
+    /// Read any access controlled document.
+    ReadAny,
+
+    /// Read documents which are linked to the given ACL document.
+    Read(DocumentId),
+}
+
+impl Permission {
+    /// Returns true if holding this permission also grants the requested one.
+    pub fn grants(&self, requested: &Permission) -> bool {
+        match (self, requested) {
+            (Permission::Admin, _) => true,
+            (Permission::PublishAny, Permission::PublishAny) => true,
+            (Permission::PublishAny, Permission::Publish(_)) => true,
+            (Permission::Publish(granted), Permission::Publish(requested)) => granted == requested,
+            (Permission::ReadAny, Permission::ReadAny) => true,
+            (Permission::ReadAny, Permission::Read(_)) => true,
+            (Permission::Read(granted), Permission::Read(requested)) => granted == requested,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Permission::Admin => write!(f, "{ADMIN}"),
+            Permission::PublishAny => write!(f, "{PUBLISH_PREFIX}{WILDCARD}"),
+            Permission::Publish(schema_id) => write!(f, "{PUBLISH_PREFIX}{schema_id}"),
+            Permission::ReadAny => write!(f, "{READ_PREFIX}{WILDCARD}"),
+            Permission::Read(document_id) => write!(f, "{READ_PREFIX}{document_id}"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid permission '{0}'")]
+pub struct PermissionParsingError(String);
+
+impl FromStr for Permission {
+    type Err = PermissionParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == ADMIN {
+            return Ok(Permission::Admin);
+        }
+
+        if let Some(value) = s.strip_prefix(READ_PREFIX) {
+            return match value {
+                WILDCARD => Ok(Permission::ReadAny),
+                document_id => DocumentId::from_str(document_id)
+                    .map(Permission::Read)
+                    .map_err(|_| PermissionParsingError(s.to_string())),
+            };
+        }
+
+        match s.strip_prefix(PUBLISH_PREFIX) {
+            Some(WILDCARD) => Ok(Permission::PublishAny),
+            Some(schema_id) => SchemaId::from_str(schema_id)
+                .map(Permission::Publish)
+                .map_err(|_| PermissionParsingError(s.to_string())),
+            None => Err(PermissionParsingError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::constants::HASH;
+    use rstest::rstest;
+
+    use super::Permission;
+
+    #[rstest]
+    #[case("admin", Permission::Admin)]
+    #[case("publish:*", Permission::PublishAny)]
+    #[case("publish:blob_v1", Permission::Publish(SchemaId::Blob(1)))]
+    #[case("read:*", Permission::ReadAny)]
+    #[case(&format!("read:{HASH}"), Permission::Read(HASH.parse().unwrap()))]
+    fn parse_permissions(#[case] value: &str, #[case] expected: Permission) {
+        let permission = Permission::from_str(value).unwrap();
+        assert_eq!(permission, expected);
+        assert_eq!(permission.to_string(), value);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("root")]
+    #[case("publish:")]
+    #[case("publish:not_a_schema")]
+    #[case("read:")]
+    #[case("read:not_a_document")]
+    fn invalid_permissions(#[case] value: &str) {
+        assert!(Permission::from_str(value).is_err());
+    }
+
+    #[test]
+    fn granted_permissions() {
+        let blob = Permission::Publish(SchemaId::Blob(1));
+        let blob_piece = Permission::Publish(SchemaId::BlobPiece(1));
+
+        assert!(Permission::Admin.grants(&Permission::Admin));
+        assert!(Permission::Admin.grants(&blob));
+        assert!(Permission::PublishAny.grants(&blob));
+        assert!(!Permission::PublishAny.grants(&Permission::Admin));
+        assert!(blob.grants(&blob));
+        assert!(!blob.grants(&blob_piece));
+        assert!(!blob.grants(&Permission::PublishAny));
+
+        let document_id: DocumentId = HASH.parse().unwrap();
+        let read = Permission::Read(document_id);
+
+        assert!(Permission::Admin.grants(&read));
+        assert!(Permission::ReadAny.grants(&read));
+        assert!(!Permission::ReadAny.grants(&blob));
+        assert!(!Permission::PublishAny.grants(&read));
+        assert!(read.grants(&read));
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Run several instances sharing one PostgreSQL database as one logical node.
+//!
+//! All instances use the same key pair and elect a leader via a lease in the database. Only the
+//! leader runs the materializer, network and replication services and presents the identity of
+//! the node to other peers, the other instances serve GraphQL reads behind a load balancer.
+mod service;
+mod state;
+
+pub use service::cluster_service;
+pub use state::ClusterState;
@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{error, info, warn};
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::{self, JoinHandle};
+use tokio::time::interval;
+
+use crate::archive::archive_service;
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::context::Context;
+use crate::manager::{Service, ServiceReadySender, Shutdown};
+use crate::materializer::materializer_service;
+use crate::network::network_service;
+use crate::replication::{now, replication_service};
+use crate::vacuum::vacuum_service;
+use crate::webhooks::webhook_service;
+
+/// Shortest lease, it gets renewed three times per lease duration.
+const MIN_LEASE_DURATION: u64 = 3;
+
+/// Services which only run on the leader of a cluster.
+struct LeaderServices {
+    shutdown: broadcast::Sender<()>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl LeaderServices {
+    /// Start materializer, network and replication services one after another, followed by the
+    /// optional archive, vacuum and webhook services.
+    async fn start(context: &Context, tx: &ServiceSender) -> Self {
+        let (shutdown, _) = broadcast::channel(1);
+        let mut services = Self {
+            shutdown,
+            handles: Vec::new(),
+        };
+
+        services
+            .add("materializer", materializer_service, context, tx)
+            .await;
+        services.add("network", network_service, context, tx).await;
+        services
+            .add("replication", replication_service, context, tx)
+            .await;
+
+        if context.config.archive_database_url.is_some() {
+            services.add("archive", archive_service, context, tx).await;
+        }
+
+        if context.config.vacuum_interval.is_some() {
+            services.add("vacuum", vacuum_service, context, tx).await;
+        }
+
+        if !context.config.webhooks.is_empty() {
+            services.add("webhooks", webhook_service, context, tx).await;
+        }
+
+        services
+    }
+
+    /// Start a service and wait until it is ready.
+    async fn add<F: Service<Context, ServiceMessage> + Send + Sync + 'static>(
+        &mut self,
+        name: &'static str,
+        service: F,
+        context: &Context,
+        tx: &ServiceSender,
+    ) {
+        let mut shutdown_rx = self.shutdown.subscribe();
+        let signal: Shutdown = task::spawn(async move {
+            let _ = shutdown_rx.recv().await;
+        });
+
+        let (tx_ready, rx_ready) = oneshot::channel();
+        let context = context.clone();
+        let tx = tx.clone();
+
+        self.handles.push(task::spawn(async move {
+            info!("Start {} service", name);
+            if let Err(err) = service.call(context, signal, tx, tx_ready).await {
+                error!("Error in {} service: {}", name, err);
+            }
+        }));
+
+        let _ = rx_ready.await;
+    }
+
+    /// Stop all services and wait until they are done.
+    async fn stop(self) {
+        drop(self.shutdown);
+
+        for handle in self.handles {
+            let _ = handle.await;
+        }
+    }
+}
+
+/// The cluster service elects the leader of a cluster and runs all services writing to the
+/// database or talking to other nodes while this instance is the leader.
+///
+/// Instances which are not the leader keep their schemas in sync with the database, so their
+/// GraphQL API serves newly materialized schemas as well.
+pub async fn cluster_service(
+    context: Context,
+    mut shutdown: Shutdown,
+    tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()> {
+    let instance = context
+        .config
+        .cluster_instance
+        .clone()
+        .expect("Cluster service requires an instance name");
+    let lease_duration = context
+        .config
+        .cluster_lease_duration
+        .max(MIN_LEASE_DURATION);
+
+    if tx_ready.send(()).is_err() {
+        warn!("No subscriber informed about cluster service being ready");
+    };
+
+    let mut leader_services: Option<LeaderServices> = None;
+    let mut interval = interval(Duration::from_secs(lease_duration) / 3);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => (),
+            _ = &mut shutdown => break,
+        }
+
+        let now = now() as i64;
+        let is_leader = match context
+            .store
+            .acquire_cluster_lease(&instance, now, now + lease_duration as i64)
+            .await
+        {
+            Ok(is_leader) => is_leader,
+            Err(err) => {
+                // Step down, another instance takes over as soon as our lease expired
+                warn!("Failed renewing cluster lease: {err}");
+                false
+            }
+        };
+
+        leader_services = match (is_leader, leader_services) {
+            (true, None) => {
+                info!("Instance {instance} became leader of the cluster");
+                Some(LeaderServices::start(&context, &tx).await)
+            }
+            (false, Some(services)) => {
+                info!("Instance {instance} is not leader of the cluster anymore");
+                services.stop().await;
+                None
+            }
+            (_, services) => services,
+        };
+
+        context.cluster.set_leader(leader_services.is_some());
+
+        if leader_services.is_none() {
+            // Pick up schemas materialized by the leader
+            match context.store.get_all_schema().await {
+                Ok(schemas) => {
+                    for schema in schemas {
+                        // Schemas which are not on the allow list get rejected here
+                        let _ = context.schema_provider.update(schema).await;
+                    }
+                }
+                Err(err) => warn!("Failed loading schemas: {err}"),
+            }
+        }
+    }
+
+    if let Some(services) = leader_services {
+        services.stop().await;
+
+        if let Err(err) = context.store.release_cluster_lease(&instance).await {
+            warn!("Failed releasing cluster lease: {err}");
+        }
+    }
+
+    Ok(())
+}
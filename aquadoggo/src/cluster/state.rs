@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Role of this instance in a cluster.
+///
+/// Nodes which are not part of a cluster are always the leader.
+#[derive(Clone, Debug)]
+pub struct ClusterState(Arc<AtomicBool>);
+
+impl ClusterState {
+    /// Returns the state of an instance which did not acquire the leader lease yet.
+    pub fn follower() -> Self {
+        Self(Arc::new(AtomicBool::new(false)))
+    }
+
+    /// Returns true if this instance runs the network and replication services and accepts
+    /// writes.
+    pub fn is_leader(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_leader(&self, is_leader: bool) {
+        self.0.store(is_leader, Ordering::SeqCst);
+    }
+}
+
+impl Default for ClusterState {
+    fn default() -> Self {
+        Self(Arc::new(AtomicBool::new(true)))
+    }
+}
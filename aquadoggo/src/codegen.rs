@@ -0,0 +1,437 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Generate typed client code for the documents of a schema known to the node.
+use std::collections::BTreeSet;
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use p2panda_rs::schema::{FieldType, Schema, SchemaId};
+use thiserror::Error;
+
+use crate::config::Configuration;
+use crate::db::{connection_pool, SqlStore};
+use crate::graphql::constants;
+
+/// Rust keywords which can't be used as field names without escaping them.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Keywords which can't be used as raw identifiers either.
+const RESERVED_IDENTIFIERS: &[&str] = &["crate", "self", "super"];
+
+/// Languages client code can be generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// Rust module with a struct of the document fields, GraphQL queries and operation builders
+    /// based on `p2panda-rs`.
+    Rust,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("Unsupported language '{0}', expected \"rust\"")]
+pub struct LanguageParsingError(String);
+
+impl FromStr for Language {
+    type Err = LanguageParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rust" | "rs" => Ok(Language::Rust),
+            _ => Err(LanguageParsingError(s.to_string())),
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Language::Rust => write!(f, "rust"),
+        }
+    }
+}
+
+/// Generate client code for the documents of a schema published to the node's database.
+pub async fn generate_schema_code(
+    config: &Configuration,
+    schema_id: &SchemaId,
+    language: Language,
+) -> Result<String> {
+    let pool = connection_pool(&config.database_url, 1, config.database_key.as_deref()).await?;
+    let schemas = SqlStore::new(pool.clone()).get_all_schema().await;
+    pool.close().await;
+
+    let schema = schemas?
+        .into_iter()
+        .find(|schema| schema.id() == schema_id)
+        .ok_or_else(|| anyhow!("Schema {} not found in database", schema_id))?;
+
+    Ok(generate_code(&schema, language))
+}
+
+/// Generate client code for the documents of a schema.
+pub fn generate_code(schema: &Schema, language: Language) -> String {
+    match language {
+        Language::Rust => rust_module(schema),
+    }
+}
+
+/// Returns a Rust module with a struct holding the fields of a document, GraphQL queries to fetch
+/// them and builders for CREATE, UPDATE and DELETE operations.
+fn rust_module(schema: &Schema) -> String {
+    let struct_name = pascal_case(&schema.id().name().to_string());
+    let fields: Vec<(&str, &FieldType)> = schema
+        .fields()
+        .iter()
+        .map(|(name, field_type)| (name.as_str(), field_type))
+        .collect();
+
+    let mut document_types: BTreeSet<&str> = BTreeSet::from(["DocumentViewId"]);
+    let mut operation_types: BTreeSet<&str> = BTreeSet::from([
+        "Operation",
+        "OperationAction",
+        "OperationBuilder",
+        "OperationValue",
+    ]);
+    for (_, field_type) in &fields {
+        match field_type {
+            FieldType::Relation(_) => {
+                document_types.insert("DocumentId");
+                operation_types.insert("Relation");
+            }
+            FieldType::RelationList(_) => {
+                document_types.insert("DocumentId");
+                operation_types.insert("RelationList");
+            }
+            FieldType::PinnedRelation(_) => {
+                operation_types.insert("PinnedRelation");
+            }
+            FieldType::PinnedRelationList(_) => {
+                operation_types.insert("PinnedRelationList");
+            }
+            _ => (),
+        }
+    }
+
+    let description: String = schema
+        .description()
+        .lines()
+        .map(|line| format!("/// {}", line).trim_end().to_string() + "\n")
+        .collect();
+
+    let struct_fields: String = fields
+        .iter()
+        .map(|(name, field_type)| {
+            format!(
+                "    pub {}: {},\n",
+                rust_identifier(name),
+                rust_type(field_type)
+            )
+        })
+        .collect();
+
+    let operation_values: String = fields
+        .iter()
+        .map(|(name, field_type)| {
+            let value = format!("self.{}", rust_identifier(name));
+            format!(
+                "            (\"{}\", {}),\n",
+                name,
+                operation_value(&value, field_type)
+            )
+        })
+        .collect();
+
+    format!(
+        r##"// Generated with `aquadoggo codegen` from schema "{schema_id}", don't edit by hand.
+
+use p2panda_rs::document::{document_types};
+use p2panda_rs::operation::error::OperationBuilderError;
+use p2panda_rs::operation::{operation_types};
+use p2panda_rs::schema::SchemaId;
+
+{description}#[derive(Debug, Clone, PartialEq)]
+pub struct {struct_name} {{
+{struct_fields}}}
+
+impl {struct_name} {{
+    /// Id of the schema of these documents.
+    pub const SCHEMA_ID: &'static str = "{schema_id}";
+
+    /// GraphQL query of a single document, takes the document id as `$id` variable.
+    pub const DOCUMENT_QUERY: &'static str = r#"{document_query}"#;
+
+    /// GraphQL query of a page of documents, takes the optional `$first` and `$after` pagination
+    /// variables.
+    pub const COLLECTION_QUERY: &'static str = r#"{collection_query}"#;
+
+    /// Returns the id of the schema of these documents.
+    pub fn schema_id() -> SchemaId {{
+        Self::SCHEMA_ID.parse().expect("Valid schema id")
+    }}
+
+    /// Returns all fields as operation values.
+    pub fn fields(&self) -> Vec<(&'static str, OperationValue)> {{
+        vec![
+{operation_values}        ]
+    }}
+
+    /// Returns an operation creating a document with these fields.
+    pub fn create(&self) -> Result<Operation, OperationBuilderError> {{
+        OperationBuilder::new(&Self::schema_id())
+            .action(OperationAction::Create)
+            .fields(&self.fields())
+            .build()
+    }}
+
+    /// Returns an operation updating all fields of the document at the given view.
+    pub fn update(
+        &self,
+        previous: &DocumentViewId,
+    ) -> Result<Operation, OperationBuilderError> {{
+        OperationBuilder::new(&Self::schema_id())
+            .action(OperationAction::Update)
+            .previous(previous)
+            .fields(&self.fields())
+            .build()
+    }}
+
+    /// Returns an operation deleting the document at the given view.
+    pub fn delete(previous: &DocumentViewId) -> Result<Operation, OperationBuilderError> {{
+        OperationBuilder::new(&Self::schema_id())
+            .action(OperationAction::Delete)
+            .previous(previous)
+            .build()
+    }}
+}}
+"##,
+        schema_id = schema.id(),
+        document_types = use_list(&document_types),
+        operation_types = use_list(&operation_types),
+        description = description,
+        struct_name = struct_name,
+        struct_fields = struct_fields,
+        document_query = document_query(schema.id(), &fields),
+        collection_query = collection_query(schema.id(), &fields),
+        operation_values = operation_values,
+    )
+}
+
+/// Returns a GraphQL query of a single document with all its fields.
+fn document_query(schema_id: &SchemaId, fields: &[(&str, &FieldType)]) -> String {
+    format!(
+        "query Document($id: {}!) {{ document: {}({}: $id) {{ {} }} }}",
+        constants::DOCUMENT_ID,
+        schema_id,
+        constants::DOCUMENT_ID_ARG,
+        document_selection(fields)
+    )
+}
+
+/// Returns a GraphQL query of a page of documents with all their fields.
+fn collection_query(schema_id: &SchemaId, fields: &[(&str, &FieldType)]) -> String {
+    format!(
+        "query Collection($first: Int, $after: Cursor) {{ collection: {}{}({}: $first, {}: $after) \
+        {{ {} {} {} {{ {} {} }} }} }}",
+        constants::QUERY_ALL_PREFIX,
+        schema_id,
+        constants::PAGINATION_FIRST_ARG,
+        constants::PAGINATION_AFTER_ARG,
+        constants::TOTAL_COUNT_FIELD,
+        constants::HAS_NEXT_PAGE_FIELD,
+        constants::DOCUMENTS_FIELD,
+        constants::CURSOR_FIELD,
+        document_selection(fields)
+    )
+}
+
+/// Returns the GraphQL selection of the meta data and all fields of a document.
+///
+/// Related documents are selected with their id or view id only.
+fn document_selection(fields: &[(&str, &FieldType)]) -> String {
+    let mut selection = String::new();
+
+    for (name, field_type) in fields {
+        let _ = match field_type {
+            FieldType::Relation(_) => write!(selection, " {} {{ meta {{ documentId }} }}", name),
+            FieldType::PinnedRelation(_) => write!(selection, " {} {{ meta {{ viewId }} }}", name),
+            FieldType::RelationList(_) => write!(
+                selection,
+                " {} {{ {} {{ meta {{ documentId }} }} }}",
+                name,
+                constants::DOCUMENTS_FIELD
+            ),
+            FieldType::PinnedRelationList(_) => write!(
+                selection,
+                " {} {{ {} {{ meta {{ viewId }} }} }}",
+                name,
+                constants::DOCUMENTS_FIELD
+            ),
+            _ => write!(selection, " {}", name),
+        };
+    }
+
+    format!(
+        "{} {{ documentId viewId }} {} {{{} }}",
+        constants::META_FIELD,
+        constants::FIELDS_FIELD,
+        selection
+    )
+}
+
+/// Returns the Rust type holding values of a field.
+fn rust_type(field_type: &FieldType) -> &'static str {
+    match field_type {
+        FieldType::Boolean => "bool",
+        FieldType::Integer => "i64",
+        FieldType::Float => "f64",
+        FieldType::String => "String",
+        FieldType::Bytes => "Vec<u8>",
+        FieldType::Relation(_) => "DocumentId",
+        FieldType::RelationList(_) => "Vec<DocumentId>",
+        FieldType::PinnedRelation(_) => "DocumentViewId",
+        FieldType::PinnedRelationList(_) => "Vec<DocumentViewId>",
+    }
+}
+
+/// Returns a Rust expression converting a field value into an operation value.
+fn operation_value(value: &str, field_type: &FieldType) -> String {
+    match field_type {
+        FieldType::Boolean => format!("OperationValue::Boolean({})", value),
+        FieldType::Integer => format!("OperationValue::Integer({})", value),
+        FieldType::Float => format!("OperationValue::Float({})", value),
+        FieldType::String => format!("OperationValue::String({}.clone())", value),
+        FieldType::Bytes => format!("OperationValue::Bytes({}.clone())", value),
+        FieldType::Relation(_) => {
+            format!("OperationValue::Relation(Relation::new({}.clone()))", value)
+        }
+        FieldType::RelationList(_) => format!(
+            "OperationValue::RelationList(RelationList::new({}.clone()))",
+            value
+        ),
+        FieldType::PinnedRelation(_) => format!(
+            "OperationValue::PinnedRelation(PinnedRelation::new({}.clone()))",
+            value
+        ),
+        FieldType::PinnedRelationList(_) => format!(
+            "OperationValue::PinnedRelationList(PinnedRelationList::new({}.clone()))",
+            value
+        ),
+    }
+}
+
+/// Returns the items of an `use` declaration, wrapped in braces when there are several.
+fn use_list(items: &BTreeSet<&str>) -> String {
+    let items: Vec<&str> = items.iter().copied().collect();
+    match items.as_slice() {
+        [item] => item.to_string(),
+        _ => format!("{{{}}}", items.join(", ")),
+    }
+}
+
+/// Turns a schema name like "cafe_menu" into a type name like "CafeMenu".
+fn pascal_case(name: &str) -> String {
+    let name: String = name
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+
+    // Type names can't start with a digit
+    match name.chars().next() {
+        Some(first) if first.is_ascii_digit() => format!("Schema{}", name),
+        _ => name,
+    }
+}
+
+/// Escapes field names which are Rust keywords.
+fn rust_identifier(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else if RESERVED_IDENTIFIERS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::schema::{FieldType, Schema, SchemaId, SchemaName};
+    use p2panda_rs::test_utils::fixtures::random_document_view_id;
+
+    use super::{generate_code, pascal_case, rust_identifier, Language};
+
+    #[test]
+    fn names() {
+        assert_eq!(pascal_case("cafe_menu"), "CafeMenu");
+        assert_eq!(pascal_case("venues"), "Venues");
+        assert_eq!(pascal_case("2023_events"), "Schema2023Events");
+        assert_eq!(rust_identifier("type"), "r#type");
+        assert_eq!(rust_identifier("self"), "self_");
+        assert_eq!(rust_identifier("name"), "name");
+        assert_eq!("Rust".parse::<Language>(), Ok(Language::Rust));
+        assert!("kotlin".parse::<Language>().is_err());
+    }
+
+    #[test]
+    fn rust_module() {
+        let schema_id: SchemaId =
+            "venues_0020c65567ae37efea293e34a9c7d13f8f2bf23dbdc3b5c7b9ab46293111c48fc78b"
+                .parse()
+                .unwrap();
+        let owner_schema_id = SchemaId::Application(
+            SchemaName::new("people").unwrap(),
+            random_document_view_id(),
+        );
+        let schema = Schema::new(
+            &schema_id,
+            "Places to meet",
+            &[
+                ("capacity", FieldType::Integer),
+                ("owner", FieldType::Relation(owner_schema_id)),
+                ("type", FieldType::String),
+            ],
+        )
+        .unwrap();
+
+        let code = generate_code(&schema, Language::Rust);
+
+        assert!(code.contains("use p2panda_rs::document::{DocumentId, DocumentViewId};"));
+        assert!(code.contains(
+            "use p2panda_rs::operation::{Operation, OperationAction, OperationBuilder, \
+            OperationValue, Relation};"
+        ));
+        assert!(code.contains(
+            "/// Places to meet\n\
+            #[derive(Debug, Clone, PartialEq)]\n\
+            pub struct Venues {\n    \
+                pub capacity: i64,\n    \
+                pub owner: DocumentId,\n    \
+                pub r#type: String,\n\
+            }"
+        ));
+        assert!(code.contains(&format!(
+            "query Document($id: DocumentId!) {{ document: {schema_id}(id: $id) \
+            {{ meta {{ documentId viewId }} fields {{ capacity owner {{ meta {{ documentId }} }} \
+            type }} }} }}"
+        )));
+        assert!(code.contains(&format!(
+            "query Collection($first: Int, $after: Cursor) {{ collection: all_{schema_id}(first: \
+            $first, after: $after) {{ totalCount hasNextPage documents {{ cursor meta"
+        )));
+        assert!(code
+            .contains("(\"owner\", OperationValue::Relation(Relation::new(self.owner.clone()))),"));
+        assert!(code.contains("(\"type\", OperationValue::String(self.r#type.clone())),"));
+    }
+}
@@ -1,10 +1,29 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, UdpSocket};
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
+use p2panda_rs::identity::PublicKey;
 use p2panda_rs::schema::SchemaId;
+use rustls_acme::acme::LETS_ENCRYPT_PRODUCTION_DIRECTORY;
+use tempfile::TempDir;
 
-use crate::network::NetworkConfiguration;
+use crate::api::NodeProfile;
+use crate::authors::ServiceAccount;
+use crate::blobs::{BlobTranscoder, MimeTypeMismatch};
+use crate::db::IsolationLevel;
+use crate::metrics::MetricsTarget;
+use crate::network::{NetworkConfiguration, Transport};
+use crate::replication::{
+    Compression, DirectionPreference, Mode, ModePreference, SUPPORTED_COMPRESSIONS,
+};
+use crate::schema::{
+    DateTimeField, DecimalField, FieldConstraint, PrefetchProfile, SchemaPin, SchemaSettings,
+    SchemaVersionPolicy,
+};
+use crate::webhooks::Webhook;
 
 /// Configuration object holding all important variables throughout the application.
 #[derive(Debug, Clone)]
@@ -23,6 +42,17 @@ pub struct Configuration {
     /// _not_ recommended for production settings.
     pub allow_schema_ids: AllowList<SchemaId>,
 
+    /// List of application schemas allowed by their name and version, besides the allowed schema
+    /// ids. Pins are resolved to schema ids when the schema definitions arrive on the node.
+    ///
+    /// Pins are only used when an allow-list of schema ids is set, with a wildcard all schemas are
+    /// supported anyhow.
+    pub allow_schema_pins: Vec<SchemaPin>,
+
+    /// Later versions of pinned schemas which get allowed automatically, either only the pinned
+    /// version, versions keeping all fields of the pinned version or all later versions.
+    pub schema_version_policy: SchemaVersionPolicy,
+
     /// URL / connection string to PostgreSQL or SQLite database.
     pub database_url: String,
 
@@ -33,17 +63,233 @@ pub struct Configuration {
     /// application in high-availability deployments).
     pub database_max_connections: u32,
 
+    /// Secret key to encrypt SQLite databases at rest using SQLCipher.
+    ///
+    /// When set, both the main and the archive database are opened with this key. This requires
+    /// aquadoggo to be built with the `sqlcipher` feature and is not supported for PostgreSQL
+    /// databases.
+    pub database_key: Option<String>,
+
+    /// Isolation level of transactions in PostgreSQL databases. Defaults to "read committed".
+    ///
+    /// Multi-statement store operations, like inserting a materialized document, run in explicit
+    /// transactions with this isolation level. Stricter levels avoid rare inconsistencies when
+    /// materializing documents with many concurrent workers, in exchange conflicting transactions
+    /// fail and need to be retried. Has no effect for SQLite databases.
+    pub database_isolation_level: IsolationLevel,
+
+    /// Maximum number of retries of transactions which failed due to serialization failures or
+    /// deadlocks in PostgreSQL databases. Defaults to 3.
+    pub database_max_retries: u32,
+
+    /// URL / connection string to an optional PostgreSQL or SQLite archive database.
+    ///
+    /// When set, historical operation data of documents which did not change for longer than
+    /// `archive_threshold` is moved from the main database into the archive. Archived data is
+    /// transparently loaded back as soon as it is accessed again, for example when a document
+    /// receives a new operation or gets replicated to another node.
+    ///
+    /// This keeps the main database small and fast on constrained devices while preserving the
+    /// full history of all documents.
+    pub archive_database_url: Option<String>,
+
+    /// Duration in seconds after which inactive documents are moved into the archive database.
+    /// Defaults to 30 days.
+    ///
+    /// This value has no effect when no `archive_database_url` is set.
+    pub archive_threshold: u64,
+
     /// HTTP port, serving the GraphQL API (for example hosted under
     /// http://localhost:2020/graphql). This API is used for client-node communication. Defaults to
     /// 2020.
     pub http_port: u16,
 
+    /// IP address the HTTP API is bound to. Defaults to all interfaces.
+    ///
+    /// Bind to "127.0.0.1" to only serve the GraphQL API locally, for example together with
+    /// `blobs_http_address` to make only blobs publicly reachable.
+    pub http_bind_address: IpAddr,
+
+    /// When set, blobs are served on this separate address instead of `http_port`, GraphQL is not
+    /// served on this address.
+    ///
+    /// Useful when only blobs should be publicly reachable. HTTPS and HTTP/3 serve the same routes
+    /// as `http_port`.
+    pub blobs_http_address: Option<SocketAddr>,
+
+    /// Logical identity the HTTP API is served under, for example the domain name of the node.
+    ///
+    /// When set, the GraphQL API reports this identity instead of the peer id of the node. This
+    /// allows rotating the identity HTTP clients know the node by, for example together with its
+    /// domain and TLS certificates, without touching the key pair used for node-node
+    /// communication.
+    pub http_identity: Option<String>,
+
+    /// Domain names the HTTP API is served for via HTTPS and HTTP/3.
+    ///
+    /// When set, TLS certificates for these domains are provisioned automatically from an ACME
+    /// certificate authority and the API is additionally served on `https_port`, via HTTP/1.1 and
+    /// HTTP/2 over TCP and via HTTP/3 over QUIC. All domains need to resolve to this node and
+    /// `https_port` needs to be reachable as port 443 to answer the TLS-ALPN-01 challenge of the
+    /// certificate authority. When empty, the API is only served via plain HTTP.
+    pub tls_domains: Vec<String>,
+
+    /// TCP and UDP port serving the HTTP API via HTTPS and HTTP/3. Defaults to 443.
+    ///
+    /// This value has no effect when no `tls_domains` are set.
+    pub https_port: u16,
+
+    /// Email addresses the certificate authority can use to contact the operator of this node, for
+    /// example about expiring certificates.
+    pub acme_contacts: Vec<String>,
+
+    /// URL of the directory of the ACME certificate authority. Defaults to Let's Encrypt.
+    pub acme_directory_url: String,
+
+    /// Path to folder where the ACME account key and certificates are kept.
+    ///
+    /// When not set, new certificates are requested on every start, which easily runs into rate
+    /// limits of the certificate authority.
+    pub acme_cache_path: Option<PathBuf>,
+
+    /// Maximum number of GraphQL requests per minute from a single IP address. Defaults to 1200.
+    ///
+    /// Clients can send this many requests in a burst, afterwards requests are granted evenly
+    /// spread over the minute. Requests exceeding the limit are answered with "429 Too Many
+    /// Requests". Set to 0 to disable rate limiting.
+    pub graphql_rate_limit: u32,
+
+    /// Maximum number of GraphQL requests handled at the same time. Defaults to 256.
+    ///
+    /// Further requests are answered with "503 Service Unavailable". Set to 0 to disable the
+    /// limit.
+    pub graphql_max_concurrent_requests: usize,
+
+    /// Maximum size in bytes of GraphQL request bodies. Defaults to 4 MiB.
+    ///
+    /// Larger requests are answered with "413 Payload Too Large". Make sure this is large enough
+    /// for the entries and operations your clients publish, for example blob pieces. Set to 0 to
+    /// disable the limit.
+    pub graphql_max_body_size: usize,
+
+    /// Maximum number of relations a GraphQL query follows from the documents it selects at its
+    /// root. Defaults to 8.
+    ///
+    /// Deeper relations resolve to `null`, the response contains a warning in its `relationLimits`
+    /// extension. Set to 0 to disable the limit.
+    pub graphql_max_relation_depth: usize,
+
+    /// Maximum number of related documents a GraphQL query resolves in total. Defaults to 10000.
+    ///
+    /// Further relations resolve to `null` and relation lists get truncated, the response contains
+    /// a warning in its `relationLimits` extension. This protects the node from nested queries
+    /// over densely linked documents. Set to 0 to disable the limit.
+    pub graphql_max_related_documents: usize,
+
+    /// Return details of internal errors in GraphQL responses, for example failed SQL queries or
+    /// file paths. Defaults to true in debug builds and false in release builds.
+    ///
+    /// When disabled, clients only receive a reference id for internal errors. The details are
+    /// logged together with the reference id on this node. Errors caused by invalid requests are
+    /// always returned in full.
+    pub graphql_error_details: bool,
+
+    /// Duration in seconds for which GraphQL collection snapshots can be paginated. Defaults to 0
+    /// which disables snapshots.
+    ///
+    /// Collection queries with the `snapshot` argument show all following pages as the collection
+    /// was at the time of the first page. The previous state of changed documents is kept for
+    /// this duration to make this possible, outdated document views are not garbage collected
+    /// before.
+    pub graphql_snapshot_ttl: u64,
+
+    /// Maximum number of subscriptions a client runs at the same time over one GraphQL WebSocket
+    /// connection. Defaults to 32.
+    ///
+    /// Further subscriptions on this connection are answered with an error. Set to 0 to disable
+    /// the limit.
+    pub graphql_ws_max_subscriptions: usize,
+
+    /// Duration in seconds after which GraphQL WebSocket connections without any active
+    /// subscription are closed. Defaults to 300.
+    ///
+    /// Set to 0 to keep idle connections open.
+    pub graphql_ws_idle_timeout: u64,
+
+    /// Maximum number of blob requests per minute from a single IP address. Defaults to 1200.
+    ///
+    /// Set to 0 to disable rate limiting.
+    pub blobs_rate_limit: u32,
+
+    /// Maximum number of blob requests handled at the same time. Defaults to 256.
+    ///
+    /// Set to 0 to disable the limit.
+    pub blobs_max_concurrent_requests: usize,
+
+    /// Maximum size in bytes of blob request bodies. Defaults to 16 KiB.
+    ///
+    /// Blobs are only read via the HTTP API, requests do not need to carry a body. Set to 0 to
+    /// disable the limit.
+    pub blobs_max_body_size: usize,
+
     /// Path to folder where blobs (binary files) are kept and served from.
     ///
     /// **Warning**: When set to a temporary directory, make sure that also the database itself is
     /// not persisted, otherwise you will run into data inconsistencies.
     pub blobs_base_path: PathBuf,
 
+    /// Blobs smaller than this number of bytes are aggregated in packfiles instead of being kept
+    /// in separate files.
+    ///
+    /// Packing reduces the number of files on file systems handling millions of small files
+    /// badly. When `None` every blob is kept in its own file.
+    pub blobs_pack_threshold: Option<u64>,
+
+    /// Encrypt blobs on the file system with a key derived from `database_key`.
+    ///
+    /// This value has no effect when no `database_key` is set. The node refuses to start when
+    /// encryption gets enabled for a blob directory which already contains unencrypted blobs or
+    /// disabled for one with encrypted blobs.
+    pub encrypt_blobs: bool,
+
+    /// Drop the data of blob pieces from the database as soon as their blob got materialized on
+    /// the file system. Defaults to false.
+    ///
+    /// This roughly halves the disk space blobs take. Entries of the pieces are kept without their
+    /// payloads, logs stay verifiable but the pieces can't be replicated to other nodes anymore.
+    /// Blobs can not be materialized again when their files get lost.
+    pub compact_blob_pieces: bool,
+
+    /// Mime types blobs are allowed to claim, other blobs are not materialized and can't be
+    /// downloaded from this node.
+    ///
+    /// Parameters like "charset" are ignored when comparing mime types.
+    pub allow_blob_mime_types: AllowList<String>,
+
+    /// Action taken when the mime type detected from the first piece of a blob does not match the
+    /// one claimed by its author.
+    ///
+    /// Clients trusting the claimed mime type could otherwise be tricked into interpreting a blob
+    /// differently than expected, for example rendering an "image" as a HTML page. Flagged blobs
+    /// get annotated with the detected mime type, rejected blobs are not materialized.
+    pub blob_mime_type_mismatch: MimeTypeMismatch,
+
+    /// External commands transcoding materialized blobs into other formats, for example
+    /// web-friendly versions of audio and video files.
+    ///
+    /// Results are kept as derived blobs next to their originals and served under
+    /// `/blobs/<document id>/derived/<name>`. Transcoding runs once after a blob got
+    /// materialized and occupies a worker of the materializer meanwhile, failures are only
+    /// logged.
+    pub blob_transcoders: Vec<BlobTranscoder>,
+
+    /// Fetch blobs which are not materialized on this node from connected peers when clients
+    /// request them via HTTP.
+    ///
+    /// Pieces are served to the client as soon as they arrive and get persisted at the same time,
+    /// like replicated ones. Peers need to support the blob schemas.
+    pub proxy_blobs: bool,
+
     /// Number of concurrent workers which defines the maximum of materialization tasks which can
     /// be worked on simultaneously.
     ///
@@ -51,6 +297,258 @@ pub struct Configuration {
     /// number for low-energy devices with limited resources.
     pub worker_pool_size: u32,
 
+    /// Maximum number of reduce tasks a single dependency task dispatches at once. Defaults to
+    /// 256.
+    ///
+    /// Documents with huge pinned relation lists are processed in multiple runs, this prevents a
+    /// single document from monopolizing the worker pool.
+    pub dependency_fan_out: usize,
+
+    /// Number of milliseconds during which duplicate materialization tasks with the same input
+    /// are coalesced after such a task completed. Defaults to 50.
+    ///
+    /// Bursts of operations for the same document, for example during a sync, then cause a
+    /// single reduce task instead of many redundant ones. Set to 0 to queue duplicates right
+    /// away.
+    pub task_dedup_window: u64,
+
+    /// Share of the worker pools for materialization tasks of certain schemas.
+    ///
+    /// Tasks of all schemas are processed in turns, every schema takes as many tasks in a row as
+    /// its weight defines before it is the next schema's turn. This keeps schemas with a large
+    /// backlog, for example blob pieces during a sync, from starving others. Schemas not listed
+    /// here have a weight of 1.
+    pub schema_task_weights: HashMap<SchemaId, u32>,
+
+    /// Path of a UNIX socket streaming materializer task events.
+    ///
+    /// Clients connecting to the socket receive an event whenever a task started, finished or
+    /// failed, as newline-delimited JSON objects containing the worker name, input id and
+    /// duration. External tools can use this to build dashboards. When not set, no socket is
+    /// opened.
+    pub materializer_events_socket: Option<PathBuf>,
+
+    /// Schemas of which only the latest view of every document is kept.
+    ///
+    /// After a document of these schemas got updated, the fields of all operations which are not
+    /// part of its current (or any pinned) view are removed from the database. Entries and their
+    /// payloads stay, logs can therefore still be verified and replicated to other nodes.
+    ///
+    /// Use this for schemas producing large histories whose intermediate states are not needed,
+    /// for example sensor readings. Earlier views of these documents are not materialized,
+    /// pinned relations to them do not resolve.
+    pub latest_view_only_schemas: Vec<SchemaId>,
+
+    /// Maximum number of historic views kept per document of the given schemas.
+    ///
+    /// Views pinned by other documents are usually kept until nothing refers to them anymore.
+    /// Documents which get pinned frequently can accumulate many views this way, when they exceed
+    /// this limit the least recently read historic views are evicted, even if they are pinned.
+    /// Pinned relations to evicted views don't resolve until the view got materialized again.
+    pub max_document_views: HashMap<SchemaId, usize>,
+
+    /// Node settings attached to application schemas.
+    ///
+    /// Subsystems with settings which only apply to documents of certain schemas, for example
+    /// retention periods, quotas or hooks, look up their keys here via the `SchemaProvider`
+    /// instead of introducing their own configuration.
+    pub schema_settings: HashMap<SchemaId, SchemaSettings>,
+
+    /// Maximum number of recently requested document views kept in memory. Defaults to 1000.
+    ///
+    /// GraphQL resolvers and the materializer's dependency task request the same views over and
+    /// over again when following relations, cached views are served without hitting the database.
+    /// Views are invalidated as soon as their document changes. Set to 0 to disable caching.
+    pub document_view_cache_size: usize,
+
+    /// Relations of application schemas which get loaded into the cache of document views
+    /// whenever one of their documents changes.
+    ///
+    /// This cuts the latency of the first GraphQL query following these relations after documents
+    /// got synced, at the cost of reading related documents which might never be queried.
+    /// Prefetching is skipped when caching of document views is disabled.
+    pub prefetch_profiles: Vec<PrefetchProfile>,
+
+    /// Maximum number of logs whose latest entries are kept in memory. Defaults to 1000.
+    ///
+    /// Calculating the arguments for the next entry and validating published entries look up the
+    /// latest entry and skiplinks of the same logs over and over again for authors publishing a
+    /// lot, cached entries are served without hitting the database. Set to 0 to disable caching.
+    pub log_state_cache_size: usize,
+
+    /// Maintain statistics about every document, like the number of updates and distinct
+    /// authors. Defaults to false.
+    ///
+    /// Statistics are updated whenever a document is materialized and can be queried via the
+    /// `stats` meta field of documents, sparing clients expensive aggregations over the document
+    /// history.
+    pub document_stats: bool,
+
+    /// Validation constraints for fields of application schemas, for example numeric ranges,
+    /// string lengths or regular expressions.
+    ///
+    /// Operations violating these constraints are rejected, both when they are published via the
+    /// GraphQL API and when they arrive via replication. This saves applications from validating
+    /// data in every client and keeps invalid data from other nodes out of the database.
+    pub field_constraints: Vec<FieldConstraint>,
+
+    /// `int` fields of application schemas holding decimals with a fixed number of fractional
+    /// digits.
+    ///
+    /// Values are stored as integers scaled by the number of fractional digits, this keeps
+    /// equality and range filters exact, unlike with `float` fields. The GraphQL API exposes these
+    /// fields as `Decimal` strings, for example "12.34", and accepts them as filter values.
+    pub decimal_fields: Vec<DecimalField>,
+
+    /// `int` and `str` fields of application schemas holding dates and times.
+    ///
+    /// `int` fields hold UNIX timestamps in seconds, `str` fields UTC dates and times formatted as
+    /// "2023-10-16T10:00:00Z". Both order chronologically in range filters and when ordering
+    /// collections. The GraphQL API exposes these fields as `DateTime` strings and accepts ISO 8601
+    /// strings or UNIX timestamps as values, operations holding other values are rejected.
+    pub datetime_fields: Vec<DateTimeField>,
+
+    /// Reject operations which are not encoded in canonical CBOR.
+    ///
+    /// CBOR allows encoding the same values in different ways, for example integers with more
+    /// bytes than needed. Lenient encodings of an operation result in different hashes for the
+    /// same content, which can be abused to publish seemingly different operations. When enabled,
+    /// operations published via the GraphQL API or arriving via replication are rejected unless
+    /// they are encoded exactly like p2panda encodes them. Disabled by default as older clients
+    /// might still emit lenient encodings.
+    pub require_canonical_encoding: bool,
+
+    /// Schema id of capability documents which grant permissions to public keys.
+    ///
+    /// When set, documents of this schema are consulted when authorising requests, for example
+    /// when publishing operations via the GraphQL API. Capability documents need to contain a
+    /// `public_key` and `permission` string field. Possible permissions are `admin`, `publish:*`,
+    /// `publish:<schema_id>`, `read:*` or `read:<document_id>`.
+    ///
+    /// Only capability documents which were created and updated by admins exclusively are
+    /// considered. When not set, any public key is allowed to publish data to the node.
+    pub capability_schema_id: Option<SchemaId>,
+
+    /// List of public keys which hold the `admin` permission without requiring a capability
+    /// document.
+    ///
+    /// These keys form the root of trust for capability documents, they can grant permissions
+    /// (including `admin`) to other public keys. Administrative GraphQL mutations, like purging
+    /// documents, are only accepted from admins and refused when this list is empty.
+    pub admin_public_keys: Vec<PublicKey>,
+
+    /// Name of a relation field restricting read access of documents.
+    ///
+    /// Documents of schemas containing a relation field with this name can only be queried by
+    /// clients authenticating with a public key holding the `read:<document_id>` permission for
+    /// the related ACL document. Documents of other schemas stay public.
+    ///
+    /// This has no effect when no capability schema is configured.
+    pub read_acl_field: Option<String>,
+
+    /// Number of seconds the response of a `publish` request with an idempotency key is kept.
+    /// Defaults to 300.
+    ///
+    /// Clients retrying a request with the same key within this window receive the cached
+    /// response instead of an error about an occupied sequence number. Set to 0 to disable
+    /// caching.
+    pub idempotency_window: u64,
+
+    /// Service accounts the node signs and publishes operations for on behalf of trusted
+    /// clients.
+    ///
+    /// Clients, for example backend integrations, can let the node build, sign and publish
+    /// entries with the key pair of a service account via the `createDocument`, `updateDocument`
+    /// and `deleteDocument` GraphQL mutations, requests need to be authenticated by one of the
+    /// `admin_public_keys`. Key pairs of accounts without a configured private key are generated
+    /// and persisted unencrypted in the database, unless the SQLite database is encrypted with
+    /// `database_key`.
+    pub service_accounts: Vec<ServiceAccount>,
+
+    /// Schema of node profile documents, disabled when not set.
+    ///
+    /// When set, the node publishes `node_profile` as a document of this schema with its own key
+    /// pair on start, updating it whenever the profile changed. Profiles of other nodes are
+    /// replicated like any other document and can be listed with `Node::peer_profiles`. The schema
+    /// needs the string fields "contact", "features" and "tos_url".
+    pub node_profile_schema_id: Option<SchemaId>,
+
+    /// Profile of this node published to the node profile schema.
+    pub node_profile: NodeProfile,
+
+    /// List of compression algorithms offered to other nodes for replication, ordered by
+    /// preference.
+    ///
+    /// Entries exchanged during replication are batched and compressed with the first algorithm
+    /// both nodes support. When empty, replication messages are never compressed. This can be
+    /// useful to stay compatible with nodes not supporting compression yet.
+    pub compression: Vec<Compression>,
+
+    /// Replication mode used with other nodes when no preference matches. Defaults to
+    /// `Mode::LogHeight`.
+    ///
+    /// Only modes listed in `SUPPORTED_MODES` are used, `Mode::SetReconciliation` is not
+    /// implemented yet.
+    pub replication_mode: Mode,
+
+    /// List of preferred replication modes for certain peers and / or schema ids.
+    ///
+    /// The first matching preference determines the mode used for a schema id during replication
+    /// with a peer, the target set gets split into multiple sessions when different modes apply.
+    /// When a mode is not supported by both nodes we fall back to `replication_mode` and finally
+    /// to `Mode::LogHeight`, which is supported by every node.
+    pub replication_modes: Vec<ModePreference>,
+
+    /// List of directions in which entries are exchanged with certain peers.
+    ///
+    /// The first matching preference determines the direction, entries are exchanged in both
+    /// directions when none matches. A node can for example push its entries to a server without
+    /// ever pulling third-party data from it. Both nodes need to allow a direction for entries to
+    /// flow in it.
+    pub replication_directions: Vec<DirectionPreference>,
+
+    /// Remote endpoint receiving periodic snapshots of node metrics.
+    ///
+    /// Useful for deployments behind a NAT where a metrics collector can not reach the node.
+    /// Snapshots are sent as JSON to an HTTP endpoint or as gauges to a statsd or graphite
+    /// server. When not set, no metrics are pushed.
+    pub metrics_push_target: Option<MetricsTarget>,
+
+    /// Interval in seconds between two pushed metric snapshots. Defaults to 60.
+    ///
+    /// This value has no effect when no `metrics_push_target` is set.
+    pub metrics_push_interval: u64,
+
+    /// HTTP endpoints notified whenever a document of their schema got created, updated or
+    /// deleted.
+    ///
+    /// Useful for external services which would otherwise need to poll the GraphQL API for
+    /// changes. Notifications are sent as JSON via POST requests. When empty, no webhooks are
+    /// notified.
+    pub webhooks: Vec<Webhook>,
+
+    /// Interval in seconds between two sweeps removing or re-linking entries and operations which
+    /// belong to no known document, for example after an ingest failed half-way.
+    ///
+    /// The first sweep runs right after the node started. When not set, sweeps only run on demand
+    /// via `Node::vacuum`. Entries and operations inserted in the last ten minutes are left alone
+    /// as their ingest might still be in progress.
+    pub vacuum_interval: Option<u64>,
+
+    /// Name of this instance when running several instances of the same node behind a load
+    /// balancer. When not set, clustering is disabled.
+    ///
+    /// All instances of a cluster share the same key pair and PostgreSQL database. They all serve
+    /// the GraphQL API for reading, while only the elected leader runs the materializer, takes
+    /// part in the p2p network and accepts mutations.
+    pub cluster_instance: Option<String>,
+
+    /// Duration in seconds of the leader lease of a cluster. Defaults to 15.
+    ///
+    /// Another instance takes over as the leader when the lease was not renewed in time. This
+    /// value has no effect when no `cluster_instance` is set.
+    pub cluster_lease_duration: u64,
+
     /// Network configuration.
     pub network: NetworkConfiguration,
 }
@@ -59,16 +557,152 @@ impl Default for Configuration {
     fn default() -> Self {
         Self {
             allow_schema_ids: AllowList::Wildcard,
+            allow_schema_pins: Vec::new(),
+            schema_version_policy: SchemaVersionPolicy::default(),
             database_url: "sqlite::memory:".into(),
             database_max_connections: 32,
+            database_key: None,
+            database_isolation_level: IsolationLevel::default(),
+            database_max_retries: 3,
+            archive_database_url: None,
+            archive_threshold: 60 * 60 * 24 * 30,
             http_port: 2020,
+            http_bind_address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            blobs_http_address: None,
+            http_identity: None,
+            tls_domains: Vec::new(),
+            https_port: 443,
+            acme_contacts: Vec::new(),
+            acme_directory_url: LETS_ENCRYPT_PRODUCTION_DIRECTORY.into(),
+            acme_cache_path: None,
+            graphql_rate_limit: 1200,
+            graphql_max_concurrent_requests: 256,
+            graphql_max_body_size: 4 * 1024 * 1024,
+            graphql_max_relation_depth: 8,
+            graphql_max_related_documents: 10_000,
+            graphql_error_details: cfg!(debug_assertions),
+            graphql_snapshot_ttl: 0,
+            graphql_ws_max_subscriptions: 32,
+            graphql_ws_idle_timeout: 300,
+            blobs_rate_limit: 1200,
+            blobs_max_concurrent_requests: 256,
+            blobs_max_body_size: 16 * 1024,
             blobs_base_path: PathBuf::new(),
+            blobs_pack_threshold: None,
+            encrypt_blobs: false,
+            compact_blob_pieces: false,
+            allow_blob_mime_types: AllowList::Wildcard,
+            blob_mime_type_mismatch: MimeTypeMismatch::default(),
+            blob_transcoders: Vec::new(),
+            proxy_blobs: false,
             worker_pool_size: 16,
+            dependency_fan_out: 256,
+            task_dedup_window: 50,
+            schema_task_weights: HashMap::new(),
+            materializer_events_socket: None,
+            latest_view_only_schemas: Vec::new(),
+            max_document_views: HashMap::new(),
+            schema_settings: HashMap::new(),
+            document_view_cache_size: 1000,
+            prefetch_profiles: Vec::new(),
+            log_state_cache_size: 1000,
+            document_stats: false,
+            field_constraints: Vec::new(),
+            decimal_fields: Vec::new(),
+            datetime_fields: Vec::new(),
+            require_canonical_encoding: false,
+            capability_schema_id: None,
+            admin_public_keys: Vec::new(),
+            read_acl_field: None,
+            idempotency_window: 300,
+            service_accounts: Vec::new(),
+            node_profile_schema_id: None,
+            node_profile: NodeProfile::default(),
+            compression: SUPPORTED_COMPRESSIONS.to_vec(),
+            replication_mode: Mode::LogHeight,
+            replication_modes: Vec::new(),
+            replication_directions: Vec::new(),
+            metrics_push_target: None,
+            metrics_push_interval: 60,
+            webhooks: Vec::new(),
+            vacuum_interval: None,
+            cluster_instance: None,
+            cluster_lease_duration: 15,
             network: NetworkConfiguration::default(),
         }
     }
 }
 
+impl Configuration {
+    /// Returns a configuration for short-lived nodes, for example to run integration tests of
+    /// applications.
+    ///
+    /// The node keeps all data in its own in-memory SQLite database, stores blobs in a temporary
+    /// directory and listens on random free ports for HTTP and node-node communication. Discovery
+    /// of other nodes on the local network via mDNS is disabled. All data is lost as soon as the
+    /// node shuts down.
+    ///
+    /// Chosen ports can be read from `http_port` and `network.port` to connect clients or other
+    /// nodes.
+    pub fn ephemeral() -> Self {
+        let network = NetworkConfiguration {
+            port: free_port(Transport::QUIC),
+            mdns: false,
+            ..NetworkConfiguration::default()
+        };
+
+        Self {
+            database_url: memory_database_url(),
+            database_max_connections: 8,
+            http_port: free_port(Transport::TCP),
+            blobs_base_path: temporary_blobs_base_path(),
+            network,
+            ..Configuration::default()
+        }
+    }
+}
+
+/// Returns an URL to an unique, in-memory SQLite database.
+pub(crate) fn memory_database_url() -> String {
+    // Give each in-memory SQLite database an unique name as we're observing funny issues with
+    // SQLite sharing data between processes (!) and breaking each others databases
+    // potentially.
+    //
+    // See related issue: https://github.com/p2panda/aquadoggo/issues/568
+    let db_name = format!("dbmem{}", rand::random::<u32>());
+
+    // Set "mode=memory" to enable SQLite running in-memory and set "cache=shared", as
+    // setting it to "private" would break sqlx / SQLite.
+    //
+    // See related issue: https://github.com/launchbadge/sqlx/issues/2510
+    format!("sqlite://file:{db_name}?mode=memory&cache=shared")
+}
+
+/// Returns path to a temporary directory which gets created once per process.
+pub(crate) fn temporary_blobs_base_path() -> PathBuf {
+    static TMP_DIR: OnceLock<TempDir> = OnceLock::new();
+
+    TMP_DIR
+        .get_or_init(|| {
+            // Initialise a `TempDir` instance globally to make sure it does not run out of scope
+            // and gets deleted before the end of the application runtime
+            TempDir::new().expect("Could not create temporary directory to store blobs")
+        })
+        .path()
+        .to_path_buf()
+}
+
+/// Returns a port which is currently not in use for the given transport protocol.
+fn free_port(transport: Transport) -> u16 {
+    let address = (Ipv4Addr::UNSPECIFIED, 0);
+    let local_address = match transport {
+        Transport::TCP => TcpListener::bind(address).and_then(|socket| socket.local_addr()),
+        Transport::QUIC => UdpSocket::bind(address).and_then(|socket| socket.local_addr()),
+    };
+
+    local_address.expect("Could not find free port").port()
+}
+
 /// Set a configuration value to either allow a defined set of elements or to a wildcard (*).
 #[derive(Debug, Clone)]
 pub enum AllowList<T> {
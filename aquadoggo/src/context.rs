@@ -6,9 +6,16 @@ use std::sync::Arc;
 use p2panda_rs::identity::KeyPair;
 use p2panda_rs::storage_provider::traits::{DocumentStore, EntryStore, LogStore, OperationStore};
 
+use crate::blobs::BlobStore;
+use crate::cluster::ClusterState;
 use crate::config::Configuration;
 use crate::db::SqlStore;
+use crate::materializer::tasks::DependencyCursors;
+use crate::materializer::DocumentEvents;
+use crate::network::identity::to_libp2p_key_pair;
+use crate::network::{LocalAddresses, NetworkMetrics};
 use crate::schema::SchemaProvider;
+use crate::startup::Readiness;
 
 /// Inner data shared across all services.
 #[derive(Debug)]
@@ -27,6 +34,27 @@ where
 
     /// Schema provider gives access to system and application schemas.
     pub schema_provider: SchemaProvider,
+
+    /// Blob store persisting materialized blobs on the file system.
+    pub blob_store: BlobStore,
+
+    /// Bandwidth and connection metrics of the network service.
+    pub network_metrics: NetworkMetrics,
+
+    /// Addresses of the node learned by the network service, used for issuing connection tickets.
+    pub local_addresses: LocalAddresses,
+
+    /// Informs about documents which got materialized into a new latest view.
+    pub document_events: DocumentEvents,
+
+    /// Positions of dependency tasks which continue processing pinned relations in a later run.
+    pub dependency_cursors: DependencyCursors,
+
+    /// Role of this instance in a cluster, always the leader when clustering is disabled.
+    pub cluster: ClusterState,
+
+    /// Startup progress of the node, reported by the readiness probe of the HTTP service.
+    pub readiness: Readiness,
 }
 
 impl<S> Data<S>
@@ -39,11 +67,48 @@ where
         config: Configuration,
         schema_provider: SchemaProvider,
     ) -> Self {
+        let secret = match config.encrypt_blobs {
+            true => config.database_key.as_deref(),
+            false => None,
+        };
+        let blob_store = BlobStore::initialize(
+            config.blobs_base_path.clone(),
+            config.blobs_pack_threshold,
+            secret,
+        )
+        .expect("Could not initialize blob store");
+
+        let cluster = match config.cluster_instance {
+            Some(_) => ClusterState::follower(),
+            None => ClusterState::default(),
+        };
+
         Self {
             key_pair,
             config,
             store,
             schema_provider,
+            blob_store,
+            network_metrics: NetworkMetrics::default(),
+            local_addresses: LocalAddresses::default(),
+            document_events: DocumentEvents::default(),
+            dependency_cursors: DependencyCursors::default(),
+            cluster,
+            readiness: Readiness::default(),
+        }
+    }
+
+    /// Returns the identity the HTTP API of this node is served under, the configured HTTP
+    /// identity or otherwise the peer id.
+    ///
+    /// Auth tokens are only accepted when they were issued for this identity.
+    pub fn http_identity(&self) -> String {
+        match &self.config.http_identity {
+            Some(identity) => identity.to_owned(),
+            None => to_libp2p_key_pair(&self.key_pair)
+                .public()
+                .to_peer_id()
+                .to_string(),
         }
     }
 }
@@ -72,6 +137,19 @@ where
             schema_provider,
         )))
     }
+
+    /// Returns a new instance of `Context` sharing the startup progress of the node.
+    pub fn with_readiness(
+        store: S,
+        key_pair: KeyPair,
+        config: Configuration,
+        schema_provider: SchemaProvider,
+        readiness: Readiness,
+    ) -> Self {
+        let mut data = Data::new(store, key_pair, config, schema_provider);
+        data.readiness = readiness;
+        Self(Arc::new(data))
+    }
 }
 
 impl<S> Clone for Context<S>
@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+
+use crate::db::types::StorageDocument;
+
+#[derive(Debug, Default)]
+struct CachedDocuments {
+    /// Incremented on every access, used to find the least recently used document.
+    clock: u64,
+
+    /// Incremented on every invalidation.
+    generation: u64,
+
+    documents: HashMap<DocumentViewId, (StorageDocument, u64)>,
+}
+
+/// In-memory cache of recently requested documents by their view id.
+///
+/// Documents are kept until the maximum number of cached documents is reached, the least recently
+/// used document is removed then. All cached views of a document are invalidated by the store
+/// whenever the document gets changed. Caching is disabled when the capacity is zero.
+#[derive(Clone, Debug, Default)]
+pub struct DocumentViewCache {
+    capacity: usize,
+    inner: Arc<Mutex<CachedDocuments>>,
+}
+
+impl DocumentViewCache {
+    /// Returns a new cache keeping at most the given number of documents.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Arc::new(Mutex::new(CachedDocuments::default())),
+        }
+    }
+
+    /// Returns `true` if documents are cached.
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Returns the cached document of this view.
+    pub fn get(&self, view_id: &DocumentViewId) -> Option<StorageDocument> {
+        let mut cache = self.inner.lock().expect("Document cache lock poisoned");
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        cache
+            .documents
+            .get_mut(view_id)
+            .map(|(document, last_used)| {
+                *last_used = clock;
+                document.clone()
+            })
+    }
+
+    /// Returns the current generation of the cache, this needs to be taken before a document is
+    /// read from the database and passed on when inserting it.
+    pub fn generation(&self) -> u64 {
+        self.inner
+            .lock()
+            .expect("Document cache lock poisoned")
+            .generation
+    }
+
+    /// Cache a document read from the database.
+    ///
+    /// The document is ignored when any document was invalidated since the given generation, it
+    /// might be outdated already.
+    pub fn insert(&self, document: &StorageDocument, generation: u64) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        let mut cache = self.inner.lock().expect("Document cache lock poisoned");
+        if cache.generation != generation {
+            return;
+        }
+
+        if cache.documents.len() >= self.capacity
+            && !cache.documents.contains_key(document.view_id())
+        {
+            let least_recently_used = cache
+                .documents
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(view_id, _)| view_id.to_owned());
+
+            if let Some(view_id) = least_recently_used {
+                cache.documents.remove(&view_id);
+            }
+        }
+
+        cache.clock += 1;
+        let clock = cache.clock;
+        cache
+            .documents
+            .insert(document.view_id().to_owned(), (document.to_owned(), clock));
+    }
+
+    /// Remove all cached views of a document.
+    pub fn invalidate(&self, document_id: &DocumentId) {
+        let mut cache = self.inner.lock().expect("Document cache lock poisoned");
+        cache.generation += 1;
+        cache
+            .documents
+            .retain(|_, (document, _)| document.id() != document_id);
+    }
+
+    /// Remove a cached document view.
+    pub fn invalidate_view(&self, view_id: &DocumentViewId) {
+        let mut cache = self.inner.lock().expect("Document cache lock poisoned");
+        cache.generation += 1;
+        cache.documents.remove(view_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::document::{DocumentId, DocumentViewFields, DocumentViewId};
+    use p2panda_rs::identity::PublicKey;
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::fixtures::{
+        public_key, random_document_id, random_document_view_id,
+    };
+    use rstest::rstest;
+
+    use crate::db::types::StorageDocument;
+
+    use super::DocumentViewCache;
+
+    fn document(id: &DocumentId, view_id: &DocumentViewId, author: PublicKey) -> StorageDocument {
+        StorageDocument {
+            id: id.to_owned(),
+            view_id: view_id.to_owned(),
+            schema_id: SchemaId::SchemaDefinition(1),
+            fields: Some(DocumentViewFields::new()),
+            author,
+            deleted: false,
+        }
+    }
+
+    #[rstest]
+    fn evicts_least_recently_used(
+        #[from(random_document_id)] document_id: DocumentId,
+        #[from(random_document_view_id)] view_id_1: DocumentViewId,
+        #[from(random_document_view_id)] view_id_2: DocumentViewId,
+        #[from(random_document_view_id)] view_id_3: DocumentViewId,
+        public_key: PublicKey,
+    ) {
+        let cache = DocumentViewCache::new(2);
+
+        for view_id in [&view_id_1, &view_id_2] {
+            cache.insert(
+                &document(&document_id, view_id, public_key),
+                cache.generation(),
+            );
+        }
+
+        // Using the first view makes the second one the least recently used
+        assert!(cache.get(&view_id_1).is_some());
+        cache.insert(
+            &document(&document_id, &view_id_3, public_key),
+            cache.generation(),
+        );
+
+        assert!(cache.get(&view_id_1).is_some());
+        assert!(cache.get(&view_id_2).is_none());
+        assert_eq!(cache.get(&view_id_3).unwrap().view_id(), &view_id_3);
+    }
+
+    #[rstest]
+    fn invalidates_documents(
+        #[from(random_document_id)] document_id: DocumentId,
+        #[from(random_document_id)] other_document_id: DocumentId,
+        #[from(random_document_view_id)] view_id_1: DocumentViewId,
+        #[from(random_document_view_id)] view_id_2: DocumentViewId,
+        public_key: PublicKey,
+    ) {
+        let cache = DocumentViewCache::new(8);
+        let generation = cache.generation();
+        cache.insert(&document(&document_id, &view_id_1, public_key), generation);
+        cache.insert(
+            &document(&other_document_id, &view_id_2, public_key),
+            generation,
+        );
+
+        cache.invalidate(&document_id);
+        assert!(cache.get(&view_id_1).is_none());
+        assert!(cache.get(&view_id_2).is_some());
+
+        // Documents read before an invalidation are not cached anymore
+        cache.insert(&document(&document_id, &view_id_1, public_key), generation);
+        assert!(cache.get(&view_id_1).is_none());
+
+        // Nothing gets cached when the cache is disabled
+        let cache = DocumentViewCache::new(0);
+        cache.insert(
+            &document(&document_id, &view_id_1, public_key),
+            cache.generation(),
+        );
+        assert!(cache.get(&view_id_1).is_none());
+    }
+}
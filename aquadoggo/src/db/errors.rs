@@ -2,7 +2,9 @@
 
 use p2panda_rs::schema::error::{SchemaError, SchemaIdError};
 use p2panda_rs::schema::system::SystemSchemaError;
-use p2panda_rs::storage_provider::error::{DocumentStorageError, OperationStorageError};
+use p2panda_rs::storage_provider::error::{
+    DocumentStorageError, EntryStorageError, OperationStorageError,
+};
 use thiserror::Error;
 
 /// `SQLStorage` errors.
@@ -14,6 +16,9 @@ pub enum SqlStoreError {
     #[error("Deletion of row from table {0} did not show any effect")]
     Deletion(String),
 
+    #[error("Document {0} can not be redirected to itself")]
+    CyclicRedirect(String),
+
     /// Error returned from BlobStore.
     #[error(transparent)]
     BlobStoreError(#[from] BlobStoreError),
@@ -64,4 +69,8 @@ pub enum BlobStoreError {
     /// Error returned from `DocumentStore` methods.
     #[error(transparent)]
     DocumentStorageError(#[from] DocumentStorageError),
+
+    /// Error returned from `EntryStore` methods.
+    #[error(transparent)]
+    EntryStorageError(#[from] EntryStorageError),
 }
@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::entry::{LogId, SeqNum};
+use p2panda_rs::identity::PublicKey;
+
+use crate::db::types::StorageEntry;
+
+/// Maximum number of entries besides the latest one kept per log, for example the skiplinks of
+/// upcoming entries.
+const MAX_ENTRIES_PER_LOG: usize = 8;
+
+/// Entries with larger payloads are not cached, blob pieces would quickly fill up the memory.
+const MAX_PAYLOAD_SIZE: u64 = 16 * 1024;
+
+#[derive(Debug)]
+struct CachedLog {
+    /// Latest entry of the log.
+    latest: StorageEntry,
+
+    /// Other entries of the log by their sequence number and when they were used last.
+    entries: HashMap<u64, (StorageEntry, u64)>,
+
+    /// When the log was used last.
+    last_used: u64,
+}
+
+impl CachedLog {
+    /// Keep an entry which is not the latest one, the least recently used entry is removed when
+    /// too many are kept already.
+    fn insert(&mut self, entry: StorageEntry, clock: u64) {
+        let seq_num = entry.seq_num().as_u64();
+
+        if self.entries.len() >= MAX_ENTRIES_PER_LOG && !self.entries.contains_key(&seq_num) {
+            let least_recently_used = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(seq_num, _)| *seq_num);
+
+            if let Some(seq_num) = least_recently_used {
+                self.entries.remove(&seq_num);
+            }
+        }
+
+        self.entries.insert(seq_num, (entry, clock));
+    }
+}
+
+#[derive(Debug, Default)]
+struct CachedLogs {
+    /// Incremented on every access, used to find the least recently used log.
+    clock: u64,
+
+    /// Incremented on every inserted entry and invalidation.
+    generation: u64,
+
+    logs: HashMap<(PublicKey, LogId), CachedLog>,
+}
+
+/// In-memory cache of the latest entries of recently used logs.
+///
+/// Calculating the arguments for the next entry and validating published entries look up the
+/// latest entry of a log and its skiplinks over and over again for the same authors. The latest
+/// entry is kept up-to-date by the store whenever an entry gets inserted, all logs are dropped
+/// when entries get removed or changed. Logs are kept until the maximum number of cached logs is
+/// reached, the least recently used log is removed then. Caching is disabled when the capacity
+/// is zero.
+#[derive(Clone, Debug, Default)]
+pub struct LogStateCache {
+    capacity: usize,
+    inner: Arc<Mutex<CachedLogs>>,
+}
+
+impl LogStateCache {
+    /// Returns a new cache keeping the entries of at most the given number of logs.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Arc::new(Mutex::new(CachedLogs::default())),
+        }
+    }
+
+    /// Returns `true` if entries are cached.
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Returns the cached latest entry of a log.
+    pub fn get_latest(&self, public_key: &PublicKey, log_id: &LogId) -> Option<StorageEntry> {
+        let mut cache = self.inner.lock().expect("Log cache lock poisoned");
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        cache
+            .logs
+            .get_mut(&(public_key.to_owned(), log_id.to_owned()))
+            .map(|log| {
+                log.last_used = clock;
+                log.latest.clone()
+            })
+    }
+
+    /// Returns the cached entry at a sequence number of a log.
+    pub fn get_at_seq_num(
+        &self,
+        public_key: &PublicKey,
+        log_id: &LogId,
+        seq_num: &SeqNum,
+    ) -> Option<StorageEntry> {
+        let mut cache = self.inner.lock().expect("Log cache lock poisoned");
+        cache.clock += 1;
+        let clock = cache.clock;
+
+        let log = cache
+            .logs
+            .get_mut(&(public_key.to_owned(), log_id.to_owned()))?;
+        log.last_used = clock;
+
+        if log.latest.seq_num() == seq_num {
+            return Some(log.latest.clone());
+        }
+
+        log.entries
+            .get_mut(&seq_num.as_u64())
+            .map(|(entry, last_used)| {
+                *last_used = clock;
+                entry.clone()
+            })
+    }
+
+    /// Returns the current generation of the cache, this needs to be taken before an entry is
+    /// read from the database and passed on when caching it.
+    pub fn generation(&self) -> u64 {
+        self.inner
+            .lock()
+            .expect("Log cache lock poisoned")
+            .generation
+    }
+
+    /// Cache the latest entry of a log read from the database.
+    ///
+    /// The entry is ignored when any entry was inserted or invalidated since the given
+    /// generation, it might not be the latest one anymore.
+    pub fn insert_latest(&self, entry: &StorageEntry, generation: u64) {
+        if !self.is_enabled() || entry.payload_size() > MAX_PAYLOAD_SIZE {
+            return;
+        }
+
+        let mut cache = self.inner.lock().expect("Log cache lock poisoned");
+        if cache.generation != generation {
+            return;
+        }
+
+        cache.clock += 1;
+        let clock = cache.clock;
+        let key = (entry.public_key().to_owned(), entry.log_id().to_owned());
+
+        match cache.logs.get_mut(&key) {
+            Some(log) => {
+                log.latest = entry.to_owned();
+                log.last_used = clock;
+            }
+            None => {
+                if cache.logs.len() >= self.capacity {
+                    let least_recently_used = cache
+                        .logs
+                        .iter()
+                        .min_by_key(|(_, log)| log.last_used)
+                        .map(|(key, _)| key.to_owned());
+
+                    if let Some(key) = least_recently_used {
+                        cache.logs.remove(&key);
+                    }
+                }
+
+                cache.logs.insert(
+                    key,
+                    CachedLog {
+                        latest: entry.to_owned(),
+                        entries: HashMap::new(),
+                        last_used: clock,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Cache an entry of a log read from the database.
+    ///
+    /// Only entries of logs with a cached latest entry are kept. The entry is ignored when any
+    /// entry was inserted or invalidated since the given generation.
+    pub fn insert(&self, entry: &StorageEntry, generation: u64) {
+        if !self.is_enabled() || entry.payload_size() > MAX_PAYLOAD_SIZE {
+            return;
+        }
+
+        let mut cache = self.inner.lock().expect("Log cache lock poisoned");
+        if cache.generation != generation {
+            return;
+        }
+
+        cache.clock += 1;
+        let clock = cache.clock;
+        let key = (entry.public_key().to_owned(), entry.log_id().to_owned());
+
+        if let Some(log) = cache.logs.get_mut(&key) {
+            if log.latest.seq_num() != entry.seq_num() {
+                log.insert(entry.to_owned(), clock);
+            }
+        }
+    }
+
+    /// Update the cached log of a newly inserted entry.
+    ///
+    /// The entry becomes the latest entry when it follows the cached latest entry of its log,
+    /// which remains cached as the backlink of the entry. Otherwise the log is dropped from the
+    /// cache. Entries read from the database before are not cached anymore.
+    pub fn on_inserted(&self, entry: &StorageEntry) {
+        let mut cache = self.inner.lock().expect("Log cache lock poisoned");
+        cache.generation += 1;
+        cache.clock += 1;
+        let clock = cache.clock;
+        let key = (entry.public_key().to_owned(), entry.log_id().to_owned());
+
+        let log = match cache.logs.get_mut(&key) {
+            Some(log) => log,
+            None => return,
+        };
+
+        let follows_latest = log.latest.seq_num().next().as_ref() == Some(entry.seq_num());
+        if !follows_latest || entry.payload_size() > MAX_PAYLOAD_SIZE {
+            cache.logs.remove(&key);
+            return;
+        }
+
+        let previous = std::mem::replace(&mut log.latest, entry.to_owned());
+        log.insert(previous, clock);
+        log.last_used = clock;
+    }
+
+    /// Remove all cached logs.
+    pub fn clear(&self) {
+        let mut cache = self.inner.lock().expect("Log cache lock poisoned");
+        cache.generation += 1;
+        cache.logs.clear();
+    }
+}
@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use sqlx::any::AnyConnection;
+use sqlx::migrate::{Migrate, Migration, MigrationType, Migrator};
+use sqlx::{migrate, query_scalar};
+
+use crate::db::Pool;
+
+/// Migrations of the main database, embedded at compile time.
+///
+/// Only the most recent migrations come with a down migration and can be reverted.
+static MIGRATOR: Migrator = migrate!();
+
+/// Number of rows of a table touched by a migration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSize {
+    /// Name of the table.
+    pub name: String,
+
+    /// Number of rows in the table, `None` if it does not exist yet.
+    pub rows: Option<i64>,
+}
+
+/// Migration which is pending or gets reverted, with the size of the tables it touches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStep {
+    /// Version of the migration, the timestamp in its file name.
+    pub version: i64,
+
+    /// Description of the migration, taken from its file name.
+    pub description: String,
+
+    /// Tables which get created, altered or written to by this migration.
+    pub tables: Vec<TableSize>,
+}
+
+impl Display for MigrationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.version, self.description)?;
+
+        let tables: Vec<String> = self
+            .tables
+            .iter()
+            .map(|table| match table.rows {
+                Some(rows) => format!("{}: {} rows", table.name, rows),
+                None => format!("{}: new table", table.name),
+            })
+            .collect();
+
+        if !tables.is_empty() {
+            write!(f, " ({})", tables.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Run any pending database migrations from inside the application.
+pub async fn run_pending_migrations(pool: &Pool) -> Result<()> {
+    MIGRATOR.run(pool).await?;
+    Ok(())
+}
+
+/// Returns the migrations which would be applied by `run_pending_migrations`, oldest first.
+///
+/// The database is not changed, except for creating the table keeping track of applied
+/// migrations if it does not exist yet.
+pub async fn pending_migrations(pool: &Pool) -> Result<Vec<MigrationStep>> {
+    let mut connection = pool.acquire().await?;
+    let applied = applied_versions(&mut connection).await?;
+
+    let mut steps = Vec::new();
+    for migration in MIGRATOR.iter() {
+        if migration.migration_type.is_down_migration() || applied.contains(&migration.version) {
+            continue;
+        }
+
+        steps.push(migration_step(&mut connection, migration).await);
+    }
+
+    Ok(steps)
+}
+
+/// Reverts the given number of most recently applied migrations, newest first.
+///
+/// Fails without changing the database if any of these migrations can not be reverted. When
+/// `dry_run` is set the migrations are only returned.
+pub async fn revert_migrations(
+    pool: &Pool,
+    count: usize,
+    dry_run: bool,
+) -> Result<Vec<MigrationStep>> {
+    let mut connection = pool.acquire().await?;
+    let mut applied = applied_versions(&mut connection).await?;
+    applied.sort_unstable_by(|a, b| b.cmp(a));
+
+    // Make sure all migrations can be reverted before touching the database, we don't want to
+    // stop half-way
+    let mut down_migrations = Vec::new();
+    for version in applied.into_iter().take(count) {
+        match MIGRATOR.iter().find(|migration| {
+            migration.version == version
+                && matches!(migration.migration_type, MigrationType::ReversibleDown)
+        }) {
+            Some(migration) => down_migrations.push(migration),
+            None => bail!("Migration {} can not be reverted", version),
+        }
+    }
+
+    let mut steps = Vec::new();
+    for migration in &down_migrations {
+        steps.push(migration_step(&mut connection, migration).await);
+    }
+
+    if !dry_run {
+        connection.lock().await?;
+        for migration in down_migrations {
+            if let Err(err) = connection.revert(migration).await {
+                connection.unlock().await?;
+                return Err(err.into());
+            }
+        }
+        connection.unlock().await?;
+    }
+
+    Ok(steps)
+}
+
+/// Returns the versions of all applied migrations.
+async fn applied_versions(connection: &mut AnyConnection) -> Result<Vec<i64>> {
+    connection.ensure_migrations_table().await?;
+
+    if let Some(version) = connection.dirty_version().await? {
+        bail!(
+            "Migration {} was only partially applied, the database needs to be fixed manually",
+            version
+        );
+    }
+
+    Ok(connection
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect())
+}
+
+/// Looks up the current size of all tables touched by a migration.
+async fn migration_step(connection: &mut AnyConnection, migration: &Migration) -> MigrationStep {
+    let mut tables = Vec::new();
+
+    for name in affected_tables(&migration.sql) {
+        // Counting fails for tables which don't exist yet
+        let rows: Option<i64> = query_scalar(&format!("SELECT COUNT(*) FROM {}", name))
+            .fetch_one(&mut *connection)
+            .await
+            .ok();
+
+        tables.push(TableSize { name, rows });
+    }
+
+    MigrationStep {
+        version: migration.version,
+        description: migration.description.to_string(),
+        tables,
+    }
+}
+
+/// Returns the names of all tables which get created, altered, dropped or written to by the
+/// statements of a migration, in order of their first appearance.
+fn affected_tables(sql: &str) -> Vec<String> {
+    let sql: String = sql
+        .lines()
+        .map(|line| line.split("--").next().unwrap_or_default())
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    let mut tables: Vec<String> = Vec::new();
+
+    for statement in sql.split(';') {
+        let tokens: Vec<&str> = statement
+            .split(|c: char| c.is_whitespace() || c == '(')
+            .filter(|token| !token.is_empty())
+            .collect();
+        let uppercase: Vec<String> = tokens.iter().map(|token| token.to_uppercase()).collect();
+        let keywords: Vec<&str> = uppercase.iter().map(String::as_str).collect();
+
+        // Position of the keyword which is followed by the table name
+        let position = match keywords.as_slice() {
+            ["CREATE" | "ALTER" | "DROP", "TABLE", ..] => Some(1),
+            ["INSERT", "INTO", ..] | ["DELETE", "FROM", ..] => Some(1),
+            ["UPDATE", ..] => Some(0),
+            ["CREATE", ..] if keywords.contains(&"INDEX") => {
+                keywords.iter().position(|keyword| *keyword == "ON")
+            }
+            _ => None,
+        };
+
+        let name = position.and_then(|position| {
+            tokens
+                .iter()
+                .zip(keywords.iter())
+                .skip(position + 1)
+                .find(|(_, keyword)| !matches!(**keyword, "IF" | "NOT" | "EXISTS" | "ONLY"))
+                .map(|(token, _)| token.trim_matches('"').to_owned())
+        });
+
+        if let Some(name) = name {
+            if !tables.contains(&name) {
+                tables.push(name);
+            }
+        }
+    }
+
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::test_utils::{test_runner, TestNode};
+
+    use super::{
+        affected_tables, pending_migrations, revert_migrations, run_pending_migrations, TableSize,
+    };
+
+    #[test]
+    fn tables_of_migration() {
+        let sql = "
+            -- Comments are ignored: UPDATE comments SET text = ''
+            CREATE TABLE IF NOT EXISTS cluster_leases (
+                name    TEXT    NOT NULL,
+                PRIMARY KEY (name)
+            );
+            CREATE UNIQUE INDEX idx_leases ON cluster_leases (name);
+            ALTER TABLE document_views ADD COLUMN accessed_at BIGINT NOT NULL DEFAULT 0;
+            INSERT INTO document_stats (document_id) SELECT document_id FROM documents;
+            UPDATE document_views SET accessed_at = 1;
+            DROP TABLE IF EXISTS \"service_accounts\";
+        ";
+
+        assert_eq!(
+            affected_tables(sql),
+            vec![
+                "cluster_leases",
+                "document_views",
+                "document_stats",
+                "service_accounts"
+            ]
+        );
+    }
+
+    #[rstest]
+    fn revert_recent_migrations() {
+        test_runner(|node: TestNode| async move {
+            let pool = &node.context.store.pool;
+            assert!(pending_migrations(pool).await.unwrap().is_empty());
+
+            // Dry runs don't change the database
+            let steps = revert_migrations(pool, 2, true).await.unwrap();
+            assert_eq!(steps.len(), 2);
+            assert!(steps[0].version > steps[1].version);
+            assert_eq!(steps[0].description, "create-document-changes");
+            assert_eq!(
+                steps[0].tables,
+                vec![
+                    TableSize {
+                        name: "document_change_sequence".into(),
+                        rows: Some(1)
+                    },
+                    TableSize {
+                        name: "document_changes".into(),
+                        rows: Some(0)
+                    }
+                ]
+            );
+            assert!(pending_migrations(pool).await.unwrap().is_empty());
+
+            let reverted = revert_migrations(pool, 2, false).await.unwrap();
+            assert_eq!(reverted, steps);
+
+            let pending = pending_migrations(pool).await.unwrap();
+            assert_eq!(pending.len(), 2);
+            assert_eq!(pending[0].version, steps[1].version);
+            assert_eq!(
+                pending[0].tables,
+                vec![TableSize {
+                    name: "pending_batch_entries".into(),
+                    rows: None
+                }]
+            );
+
+            run_pending_migrations(pool).await.unwrap();
+            assert!(pending_migrations(pool).await.unwrap().is_empty());
+
+            // Older migrations can not be reverted
+            assert!(revert_migrations(pool, 100, true).await.is_err());
+            assert!(pending_migrations(pool).await.unwrap().is_empty());
+        });
+    }
+}
@@ -4,27 +4,126 @@
 //!
 //! The main interface is [`SqlStore`] which offers an interface onto the database by implementing
 //! the storage traits defined in `p2panda-rs` as well as some implementation specific features.
-use anyhow::{Error, Result};
-use sqlx::any::{Any, AnyPool, AnyPoolOptions};
-use sqlx::migrate;
+#[cfg(feature = "sqlcipher")]
+use std::str::FromStr;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Error, Result};
+use p2panda_rs::schema::SchemaId;
+use sqlx::any::{Any, AnyConnectOptions, AnyPool, AnyPoolOptions};
 use sqlx::migrate::MigrateDatabase;
+#[cfg(feature = "sqlcipher")]
+use sqlx::sqlite::SqliteConnectOptions;
+
+use crate::faults::FaultInjector;
 
+mod document_cache;
 pub mod errors;
+mod log_cache;
+mod migrations;
 pub mod models;
 pub mod query;
 pub mod stores;
+mod transaction;
 pub mod types;
 
+pub use document_cache::DocumentViewCache;
+pub use log_cache::LogStateCache;
+pub use migrations::{
+    pending_migrations, revert_migrations, run_pending_migrations, MigrationStep, TableSize,
+};
+pub use transaction::{IsolationLevel, TransactionConfig};
+
 /// SQL based persistent storage that implements `EntryStore`, `OperationStore`, `LogStore` and `DocumentStore`.
 #[derive(Clone, Debug)]
 pub struct SqlStore {
     pub(crate) pool: Pool,
+
+    /// Optional connection pool to archive database holding historical data of inactive
+    /// documents.
+    pub(crate) archive: Option<Pool>,
+
+    /// Faults injected into store methods for testing.
+    pub(crate) faults: FaultInjector,
+
+    /// In-memory cache of recently requested document views.
+    pub(crate) document_cache: DocumentViewCache,
+
+    /// In-memory cache of the latest entries of recently used logs.
+    pub(crate) log_cache: LogStateCache,
+
+    /// Isolation level and retries of multi-statement store operations.
+    pub(crate) transactions: TransactionConfig,
+
+    /// Duration for which collections can be queried as they were at an earlier point in time,
+    /// `None` if snapshots are disabled.
+    pub(crate) snapshot_ttl: Option<Duration>,
+
+    /// Schemas with a limited number of document views, reads of their historic views are
+    /// recorded to evict the least recently used ones first.
+    pub(crate) evicted_view_schemas: Arc<HashSet<SchemaId>>,
 }
 
 impl SqlStore {
     /// Create a new `SqlStore` using the provided db `Pool`.
     pub fn new(pool: Pool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            archive: None,
+            faults: FaultInjector::default(),
+            document_cache: DocumentViewCache::default(),
+            log_cache: LogStateCache::default(),
+            transactions: TransactionConfig::default(),
+            snapshot_ttl: None,
+            evicted_view_schemas: Arc::new(HashSet::new()),
+        }
+    }
+
+    /// Use the provided db `Pool` as an archive for historical data of inactive documents.
+    pub fn with_archive(mut self, archive: Pool) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Keep up to the given number of recently requested document views in memory.
+    pub fn with_document_cache(mut self, capacity: usize) -> Self {
+        self.document_cache = DocumentViewCache::new(capacity);
+        self
+    }
+
+    /// Keep the latest entries of up to the given number of recently used logs in memory.
+    pub fn with_log_cache(mut self, capacity: usize) -> Self {
+        self.log_cache = LogStateCache::new(capacity);
+        self
+    }
+
+    /// Run multi-statement store operations with the given isolation level and retries.
+    pub fn with_transactions(mut self, transactions: TransactionConfig) -> Self {
+        self.transactions = transactions;
+        self
+    }
+
+    /// Record the previous state of changed documents to query collections as they were up to the
+    /// given duration ago.
+    pub fn with_snapshots(mut self, ttl: Duration) -> Self {
+        self.snapshot_ttl = Some(ttl);
+        self
+    }
+
+    /// Record when historic views of the given schemas were read, their least recently used views
+    /// get evicted first. Reads of other views don't cause any writes.
+    pub fn with_view_eviction(mut self, schema_ids: HashSet<SchemaId>) -> Self {
+        self.evicted_view_schemas = Arc::new(schema_ids);
+        self
+    }
+
+    /// Returns the faults injected into this store and all its clones.
+    #[cfg(feature = "fault-injection")]
+    pub fn faults(&self) -> &FaultInjector {
+        &self.faults
     }
 }
 
@@ -41,17 +140,93 @@ pub async fn create_database(url: &str) -> Result<()> {
 }
 
 /// Create a database agnostic connection pool.
-pub async fn connection_pool(url: &str, max_connections: u32) -> Result<Pool, Error> {
-    let pool: Pool = AnyPoolOptions::new()
-        .max_connections(max_connections)
-        .connect(url)
-        .await?;
+///
+/// When a key is given the SQLite database gets encrypted with SQLCipher. This requires
+/// `aquadoggo` to be built with the `sqlcipher` feature and is not supported for PostgreSQL.
+pub async fn connection_pool(
+    url: &str,
+    max_connections: u32,
+    key: Option<&str>,
+) -> Result<Pool, Error> {
+    let mut options = AnyPoolOptions::new().max_connections(max_connections);
+
+    // In-memory SQLite databases get dropped as soon as their last connection closes, keep at
+    // least one connection open for the whole lifetime of the pool
+    if is_memory_database(url) {
+        options = options
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None);
+    }
+
+    let pool: Pool = match key {
+        Some(key) => {
+            options
+                .connect_with(encrypted_connect_options(url, key)?)
+                .await?
+        }
+        None => options.connect(url).await?,
+    };
 
     Ok(pool)
 }
 
-/// Run any pending database migrations from inside the application.
-pub async fn run_pending_migrations(pool: &Pool) -> Result<()> {
-    migrate!().run(pool).await?;
+/// Returns connect options for a SQLite database encrypted with the given key.
+#[cfg(feature = "sqlcipher")]
+fn encrypted_connect_options(url: &str, key: &str) -> Result<AnyConnectOptions, Error> {
+    if !url.starts_with("sqlite") {
+        bail!("Database encryption is only supported for SQLite");
+    }
+
+    // SQLCipher requires the key to be the first statement on every new connection, sqlx makes
+    // sure that the "key" pragma is always applied before all other pragmas
+    let options = SqliteConnectOptions::from_str(url)?
+        .pragma("key", format!("'{}'", key.replace('\'', "''")));
+
+    Ok(AnyConnectOptions::from(options))
+}
+
+/// Returns connect options for a SQLite database encrypted with the given key.
+#[cfg(not(feature = "sqlcipher"))]
+fn encrypted_connect_options(_url: &str, _key: &str) -> Result<AnyConnectOptions, Error> {
+    bail!("Database encryption requires aquadoggo to be built with the \"sqlcipher\" feature");
+}
+
+/// Returns true if the URL points at an in-memory SQLite database.
+fn is_memory_database(url: &str) -> bool {
+    url.starts_with("sqlite") && (url.contains(":memory:") || url.contains("mode=memory"))
+}
+
+/// Create tables of archive database when not existing.
+///
+/// The archive only holds the fields of historical operations, all other data stays in the main
+/// database. This is why it does not share the migrations of the main database.
+pub async fn initialize_archive(pool: &Pool) -> Result<()> {
+    sqlx::query(
+        "
+        CREATE TABLE IF NOT EXISTS archived_operation_fields_v1 (
+            document_id             TEXT             NOT NULL,
+            operation_id            TEXT             NOT NULL,
+            name                    TEXT             NOT NULL,
+            field_type              TEXT             NOT NULL,
+            value                   TEXT             NULL,
+            list_index              INT              NOT NULL,
+            cursor                  TEXT             NOT NULL,
+            PRIMARY KEY (cursor)
+        )
+        ",
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query(
+        "
+        CREATE INDEX IF NOT EXISTS idx_archived_operation_fields_v1
+            ON archived_operation_fields_v1 (document_id)
+        ",
+    )
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
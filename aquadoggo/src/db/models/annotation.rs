@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::FromRow;
+
+/// Representation of a row from the `document_annotations` table as stored in the database.
+///
+/// Annotations are node-local metadata attached to documents, they are never replicated.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationRow {
+    /// Id of the annotated document.
+    pub document_id: String,
+
+    /// Key of the annotation, for example "label" or "moderation".
+    pub key: String,
+
+    /// Value of the annotation.
+    pub value: String,
+}
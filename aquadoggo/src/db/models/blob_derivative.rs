@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::FromRow;
+
+/// Representation of a row from the `blob_derivatives` table as stored in the database.
+///
+/// Derivatives are node-local transcodings of blob views, they are never replicated.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct BlobDerivativeRow {
+    /// Id of the blob document.
+    pub document_id: String,
+
+    /// Id of the blob view the derivative was transcoded from.
+    pub view_id: String,
+
+    /// Name of the transcoder which produced the derivative.
+    pub name: String,
+
+    /// Mime type of the derivative.
+    pub mime_type: String,
+
+    /// Length of the derivative in bytes.
+    pub length: i64,
+}
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::FromRow;
+
+/// Representation of a row from the `document_changes` table as stored in the database.
+///
+/// A change is recorded whenever a document gets created, updated or deleted. Sequence numbers
+/// are assigned in the order in which changes got committed to the database.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct DocumentChangeRow {
+    /// Sequence number of this change, increasing with every recorded change.
+    pub sequence: i64,
+
+    /// Id of the changed document.
+    pub document_id: String,
+
+    /// Id of the view the document changed to.
+    pub document_view_id: String,
+
+    /// Id of the schema of the document.
+    pub schema_id: String,
+
+    /// Kind of change, either "create", "update" or "delete".
+    pub change_type: String,
+
+    /// UNIX timestamp in milliseconds of when the change was recorded.
+    pub changed_at: i64,
+}
@@ -28,6 +28,53 @@ pub struct DocumentViewFieldRow {
     pub value: Option<String>,
 }
 
+/// A struct representing a single field row of a document's current view, joined with the
+/// document it belongs to.
+#[derive(FromRow, Debug, Clone)]
+pub struct DocumentFieldsJoinedRow {
+    /// Id of the document.
+    pub document_id: String,
+
+    /// Id of this documents most recent view.
+    pub document_view_id: String,
+
+    /// Id of this documents schema.
+    pub schema_id: String,
+
+    /// Author of this document.
+    pub public_key: String,
+
+    /// Id of operation which set that field value.
+    pub operation_id: String,
+
+    /// Name of field.
+    pub name: String,
+
+    /// Position index of value when it is in a relation list.
+    pub list_index: i32,
+
+    /// Type of field.
+    pub field_type: String,
+
+    /// Actual value contained in field.
+    /// This is optional as row representing an empty relation list has no value.
+    pub value: Option<String>,
+}
+
+impl From<DocumentFieldsJoinedRow> for DocumentViewFieldRow {
+    fn from(row: DocumentFieldsJoinedRow) -> Self {
+        Self {
+            document_id: row.document_id,
+            document_view_id: row.document_view_id,
+            operation_id: row.operation_id,
+            name: row.name,
+            list_index: row.list_index,
+            field_type: row.field_type,
+            value: row.value,
+        }
+    }
+}
+
 /// A struct representing a single row of a document table.
 #[derive(FromRow, Debug, Clone)]
 pub struct DocumentRow {
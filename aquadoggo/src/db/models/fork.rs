@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::FromRow;
+
+/// Representation of a row from the `log_forks` table as stored in the database.
+///
+/// A fork is recorded when an entry arrives which claims a position in a log we already hold a
+/// different entry for. This happens when the same key pair is used on more than one device.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct LogForkRow {
+    /// Public key of the author of both entries.
+    pub public_key: String,
+
+    /// Id of the forked log.
+    pub log_id: String,
+
+    /// Sequence number at which the log forked.
+    pub seq_num: String,
+
+    /// Hash of the entry we hold at this position.
+    pub entry_hash: String,
+
+    /// Hash of the conflicting entry which got rejected.
+    pub conflicting_entry_hash: String,
+
+    /// UNIX timestamp in seconds of when the fork was detected.
+    pub detected_at: i64,
+}
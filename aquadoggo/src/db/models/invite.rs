@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::FromRow;
+
+/// Representation of a row from the `invites` table as stored in the database.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct InviteRow {
+    /// Random code handed out by the node operator.
+    pub code: String,
+
+    /// Comma-separated ids of the schemas the invite allows publishing to.
+    pub schema_ids: String,
+
+    /// UNIX timestamp in seconds after which the invite can not be redeemed or used anymore.
+    pub expires_at: i64,
+
+    /// Public key which redeemed the invite, `None` if the invite was not redeemed yet.
+    pub public_key: Option<String>,
+}
@@ -2,18 +2,30 @@
 
 //! Structs representing rows in SQL tables. Needed when coercing results returned from a
 //! query using the `sqlx` library.
+mod annotation;
+mod blob_derivative;
+mod change;
 mod document;
 mod entry;
+mod fork;
+mod invite;
 mod log;
 mod operation;
+mod peer;
 mod query;
 mod task;
 pub mod utils;
 
 pub use self::log::LogHeightRow;
-pub use document::{DocumentRow, DocumentViewFieldRow};
+pub use annotation::AnnotationRow;
+pub use blob_derivative::BlobDerivativeRow;
+pub use change::DocumentChangeRow;
+pub use document::{DocumentFieldsJoinedRow, DocumentRow, DocumentViewFieldRow};
 pub use entry::EntryRow;
-pub use operation::OperationFieldsJoinedRow;
+pub use fork::LogForkRow;
+pub use invite::InviteRow;
+pub use operation::{ArchivedOperationFieldRow, OperationFieldsJoinedRow};
+pub use peer::BootstrapPeerRow;
 #[cfg(test)]
 pub use query::OptionalOwner;
 pub use query::QueryRow;
@@ -115,3 +115,29 @@ pub struct OperationFieldsJoinedRow {
     /// waiting for the `reduce` task to complete materialization.
     pub sorted_index: Option<i32>,
 }
+
+/// A struct representing a single operation field row which can be moved into the archive
+/// database.
+#[derive(FromRow, Debug, Clone)]
+pub struct ArchivedOperationFieldRow {
+    /// The id of the document the operation of this field is part of.
+    pub document_id: String,
+
+    /// The id of the operation this field was published on.
+    pub operation_id: String,
+
+    /// The name of this field.
+    pub name: String,
+
+    /// The type of this field.
+    pub field_type: String,
+
+    /// The actual value contained in this field.
+    pub value: Option<String>,
+
+    /// Index of document id or view id in (pinned) relation lists.
+    pub list_index: i32,
+
+    /// Unique cursor of this field row.
+    pub cursor: String,
+}
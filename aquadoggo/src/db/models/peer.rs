@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::FromRow;
+
+/// Representation of a row from the `bootstrap_peers` table as stored in the database.
+///
+/// This table holds peers we successfully connected to in the past, they are dialed first when
+/// the node starts again.
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapPeerRow {
+    /// Multiaddress we dialed to reach this peer.
+    pub address: String,
+
+    /// Peer id of this peer.
+    pub peer_id: String,
+
+    /// Health score of this peer.
+    pub score: i64,
+
+    /// UNIX timestamp in seconds of the last successful connection to this peer.
+    pub last_seen: Option<i64>,
+
+    /// Number of successful connection attempts.
+    pub successes: i64,
+
+    /// Number of failed connection attempts.
+    pub failures: i64,
+}
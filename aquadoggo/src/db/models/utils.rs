@@ -7,8 +7,8 @@ use p2panda_rs::document::{DocumentId, DocumentViewFields, DocumentViewId, Docum
 use p2panda_rs::identity::PublicKey;
 use p2panda_rs::operation::traits::AsOperation;
 use p2panda_rs::operation::{
-    OperationAction, OperationBuilder, OperationId, OperationValue, PinnedRelation,
-    PinnedRelationList, Relation, RelationList,
+    OperationAction, OperationBuilder, OperationFields, OperationId, OperationValue,
+    OperationVersion, PinnedRelation, PinnedRelationList, Relation, RelationList,
 };
 use p2panda_rs::schema::SchemaId;
 
@@ -48,106 +48,111 @@ pub fn parse_operation_rows(
     // * if it is a relation list value type: if the row.value is None then this list is empty and
     // we should create a relation list with no items, otherwise safely unwrap each item and parse
     // into a DocumentId/DocumentViewId then push to the suitable list vec
+    //
+    // Rows without a field type belong to operations whose fields were pruned.
     if first_row.action != "delete" {
-        operation_rows.iter().for_each(|row| {
-            let field_type = row.field_type.as_ref().unwrap().as_str();
-            let field_name = row.name.as_ref().unwrap();
-            // We don't unwrap the value as for empty relation lists this may be `None`
-            // Below we safely unwrap all values which are _not_ part of a relation list
-            let field_value = row.value.as_ref();
+        operation_rows
+            .iter()
+            .filter(|row| row.field_type.is_some())
+            .for_each(|row| {
+                let field_type = row.field_type.as_ref().unwrap().as_str();
+                let field_name = row.name.as_ref().unwrap();
+                // We don't unwrap the value as for empty relation lists this may be `None`
+                // Below we safely unwrap all values which are _not_ part of a relation list
+                let field_value = row.value.as_ref();
 
-            match field_type {
-                "bool" => {
-                    operation_fields.push((
-                        field_name.to_string(),
-                        OperationValue::Boolean(field_value.unwrap().parse::<bool>().unwrap()),
-                    ));
-                }
-                "int" => {
-                    operation_fields.push((
-                        field_name.to_string(),
-                        OperationValue::Integer(field_value.unwrap().parse::<i64>().unwrap()),
-                    ));
-                }
-                "float" => {
-                    operation_fields.push((
-                        field_name.to_string(),
-                        OperationValue::Float(field_value.unwrap().parse::<f64>().unwrap()),
-                    ));
-                }
-                "str" => {
-                    operation_fields.push((
-                        field_name.to_string(),
-                        OperationValue::String(field_value.unwrap().clone()),
-                    ));
-                }
-                "bytes" => {
-                    operation_fields.push((
-                        field_name.to_string(),
-                        OperationValue::Bytes(hex::decode(field_value.unwrap()).expect(
-                            "bytes coming from the store are encoded in valid hex strings",
-                        )),
-                    ));
-                }
-                "relation" => {
-                    operation_fields.push((
-                        field_name.to_string(),
-                        OperationValue::Relation(Relation::new(
-                            field_value.unwrap().parse::<DocumentId>().unwrap(),
-                        )),
-                    ));
-                }
-                // This is a list item, so we push it to a vec but _don't_ add it
-                // to the operation_fields yet.
-                "relation_list" => {
-                    match relation_lists.get_mut(field_name) {
-                        // We unwrap the field value here as if the list already exists then we can
-                        // assume this next item contains a value
-                        Some(list) => {
-                            list.push(field_value.unwrap().parse::<DocumentId>().unwrap())
-                        }
-                        None => {
-                            let list = match field_value {
-                                Some(document_id) => {
-                                    vec![document_id.parse::<DocumentId>().unwrap()]
-                                }
-                                None => vec![],
-                            };
-                            relation_lists.insert(field_name.to_string(), list);
-                        }
-                    };
-                }
-                "pinned_relation" => {
-                    operation_fields.push((
-                        field_name.to_string(),
-                        OperationValue::PinnedRelation(PinnedRelation::new(
-                            field_value.unwrap().parse::<DocumentViewId>().unwrap(),
-                        )),
-                    ));
-                }
-                // This is a list item, so we push it to a vec but _don't_ add it
-                // to the operation_fields yet.
-                "pinned_relation_list" => {
-                    match pinned_relation_lists.get_mut(field_name) {
-                        // We unwrap the field value here as if the list already exists then we can
-                        // assume this next item contains a value
-                        Some(list) => {
-                            list.push(field_value.unwrap().parse::<DocumentViewId>().unwrap())
-                        }
-                        None => {
-                            let list = match field_value {
-                                Some(document_view_id) => {
-                                    vec![document_view_id.parse::<DocumentViewId>().unwrap()]
-                                }
-                                None => vec![],
-                            };
-                            pinned_relation_lists.insert(field_name.to_string(), list);
-                        }
-                    };
-                }
-                _ => (),
-            };
-        })
+                match field_type {
+                    "bool" => {
+                        operation_fields.push((
+                            field_name.to_string(),
+                            OperationValue::Boolean(field_value.unwrap().parse::<bool>().unwrap()),
+                        ));
+                    }
+                    "int" => {
+                        operation_fields.push((
+                            field_name.to_string(),
+                            OperationValue::Integer(field_value.unwrap().parse::<i64>().unwrap()),
+                        ));
+                    }
+                    "float" => {
+                        operation_fields.push((
+                            field_name.to_string(),
+                            OperationValue::Float(field_value.unwrap().parse::<f64>().unwrap()),
+                        ));
+                    }
+                    "str" => {
+                        operation_fields.push((
+                            field_name.to_string(),
+                            OperationValue::String(field_value.unwrap().clone()),
+                        ));
+                    }
+                    "bytes" => {
+                        operation_fields.push((
+                            field_name.to_string(),
+                            OperationValue::Bytes(hex::decode(field_value.unwrap()).expect(
+                                "bytes coming from the store are encoded in valid hex strings",
+                            )),
+                        ));
+                    }
+                    "relation" => {
+                        operation_fields.push((
+                            field_name.to_string(),
+                            OperationValue::Relation(Relation::new(
+                                field_value.unwrap().parse::<DocumentId>().unwrap(),
+                            )),
+                        ));
+                    }
+                    // This is a list item, so we push it to a vec but _don't_ add it
+                    // to the operation_fields yet.
+                    "relation_list" => {
+                        match relation_lists.get_mut(field_name) {
+                            // We unwrap the field value here as if the list already exists then we can
+                            // assume this next item contains a value
+                            Some(list) => {
+                                list.push(field_value.unwrap().parse::<DocumentId>().unwrap())
+                            }
+                            None => {
+                                let list = match field_value {
+                                    Some(document_id) => {
+                                        vec![document_id.parse::<DocumentId>().unwrap()]
+                                    }
+                                    None => vec![],
+                                };
+                                relation_lists.insert(field_name.to_string(), list);
+                            }
+                        };
+                    }
+                    "pinned_relation" => {
+                        operation_fields.push((
+                            field_name.to_string(),
+                            OperationValue::PinnedRelation(PinnedRelation::new(
+                                field_value.unwrap().parse::<DocumentViewId>().unwrap(),
+                            )),
+                        ));
+                    }
+                    // This is a list item, so we push it to a vec but _don't_ add it
+                    // to the operation_fields yet.
+                    "pinned_relation_list" => {
+                        match pinned_relation_lists.get_mut(field_name) {
+                            // We unwrap the field value here as if the list already exists then we can
+                            // assume this next item contains a value
+                            Some(list) => {
+                                list.push(field_value.unwrap().parse::<DocumentViewId>().unwrap())
+                            }
+                            None => {
+                                let list = match field_value {
+                                    Some(document_view_id) => {
+                                        vec![document_view_id.parse::<DocumentViewId>().unwrap()]
+                                    }
+                                    None => vec![],
+                                };
+                                pinned_relation_lists.insert(field_name.to_string(), list);
+                            }
+                        };
+                    }
+                    _ => (),
+                };
+            })
     };
 
     for (field_name, relation_list) in relation_lists {
@@ -164,9 +169,31 @@ pub fn parse_operation_rows(
         ));
     }
 
-    let operation_builder = OperationBuilder::new(&schema_id);
     let previous = first_row.previous.clone();
-    let previous = previous.map(|previous| previous.parse().unwrap());
+    let previous: Option<DocumentViewId> = previous.map(|previous| previous.parse().unwrap());
+
+    // Operations whose fields were all pruned can not be built again, they are only kept to leave
+    // the operation graph of their document intact
+    if first_row.action != "delete" && operation_fields.is_empty() {
+        let action = match first_row.action.as_str() {
+            "create" => OperationAction::Create,
+            _ => OperationAction::Update,
+        };
+
+        return Some(StorageOperation {
+            document_id,
+            id: operation_id,
+            version: OperationVersion::V1,
+            action,
+            schema_id,
+            previous,
+            fields: Some(OperationFields::new()),
+            public_key,
+            sorted_index,
+        });
+    }
+
+    let operation_builder = OperationBuilder::new(&schema_id);
     let fields: Vec<(&str, OperationValue)> = operation_fields
         .iter()
         .map(|(name, value)| (name.as_str(), value.to_owned()))
@@ -17,6 +17,10 @@ pub enum QueryError {
     #[error("Can't apply ordering on unknown field '{0}'")]
     OrderFieldUnknown(String),
 
+    /// Ordering by a field of related documents requires a relation field.
+    #[error("Can't order by field of related documents as field '{0}' is not of type relation or pinned relation")]
+    OrderInvalidRelation(String),
+
     /// Filter can not be applied to a field of given type.
     #[error("Filter type '{0}' for field '{1}' is not matching schema type '{2}'")]
     FilterInvalidType(String, String, String),
@@ -32,4 +36,8 @@ pub enum QueryError {
     /// Search filters can only be applied on strings.
     #[error("Can't apply search filter as field '{0}' is not of type string")]
     FilterInvalidSearch(String),
+
+    /// Count filters can only be applied on relation lists.
+    #[error("Can't apply count filter as field '{0}' is not of type relation list")]
+    FilterInvalidCount(String),
 }
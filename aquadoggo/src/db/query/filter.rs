@@ -40,6 +40,9 @@ pub enum FilterBy {
 
     /// Filter elements containing the this search string.
     Contains(OperationValue),
+
+    /// Filter lists with a number of items inside the given interval.
+    Count(LowerBound, UpperBound),
 }
 
 /// An item representing a single filter setting.
@@ -190,14 +193,12 @@ impl Filter {
                 Some(FilterBy::Set(elements_a))
             }
             (FilterBy::Interval(lower_a, upper_a), FilterBy::Interval(lower_b, upper_b)) => {
-                match (lower_b.clone(), upper_b.clone()) {
-                    (LowerBound::Unbounded, UpperBound::Unbounded) => {
-                        Some(FilterBy::Interval(lower_a, upper_a))
-                    }
-                    (LowerBound::Unbounded, _) => Some(FilterBy::Interval(lower_a, upper_b)),
-                    (_, UpperBound::Unbounded) => Some(FilterBy::Interval(lower_b, upper_a)),
-                    _ => Some(FilterBy::Interval(lower_b, upper_b)),
-                }
+                let (lower, upper) = merge_bounds(lower_a, upper_a, lower_b, upper_b);
+                Some(FilterBy::Interval(lower, upper))
+            }
+            (FilterBy::Count(lower_a, upper_a), FilterBy::Count(lower_b, upper_b)) => {
+                let (lower, upper) = merge_bounds(lower_a, upper_a, lower_b, upper_b);
+                Some(FilterBy::Count(lower, upper))
             }
             _ => None,
         };
@@ -212,6 +213,30 @@ impl Filter {
         }
     }
 
+    /// Add a filter setting matching any of the given values, without merging it with other
+    /// filters of the same field.
+    ///
+    /// This is used to enforce restrictions which should not be widened by filters of the client.
+    pub fn restrict(&mut self, field: &Field, values: &[OperationValue]) {
+        self.0.push(FilterSetting::new(
+            field,
+            FilterBy::Set(values.to_vec()),
+            false,
+        ));
+    }
+
+    /// Add a filter setting matching none of the given values, without merging it with other
+    /// filters of the same field.
+    ///
+    /// Like `restrict` this is used to enforce restrictions the client can't lift.
+    pub fn exclude(&mut self, field: &Field, values: &[OperationValue]) {
+        self.0.push(FilterSetting::new(
+            field,
+            FilterBy::Set(values.to_vec()),
+            true,
+        ));
+    }
+
     /// Add an equality (eq) filter setting matching a value.
     pub fn add(&mut self, field: &Field, value: &OperationValue) {
         self.upsert_filter_item(FilterSetting::new(
@@ -309,6 +334,15 @@ impl Filter {
         ));
     }
 
+    /// Add a filter (count) to match all lists with a number of items inside the given interval.
+    pub fn add_count(&mut self, field: &Field, lower: LowerBound, upper: UpperBound) {
+        self.upsert_filter_item(FilterSetting::new(
+            field,
+            FilterBy::Count(lower, upper),
+            false,
+        ));
+    }
+
     /// Add a filter (contains) to match all items which contain the given search string.
     pub fn add_contains(&mut self, field: &Field, value: &str) {
         self.upsert_filter_item(FilterSetting::new(
@@ -343,6 +377,22 @@ impl Default for Filter {
     }
 }
 
+/// Helper method to merge the bounds of two intervals, bounds of the second interval overwrite the
+/// ones of the first interval when they are set.
+fn merge_bounds(
+    lower_a: LowerBound,
+    upper_a: UpperBound,
+    lower_b: LowerBound,
+    upper_b: UpperBound,
+) -> (LowerBound, UpperBound) {
+    match (lower_b, upper_b) {
+        (LowerBound::Unbounded, UpperBound::Unbounded) => (lower_a, upper_a),
+        (LowerBound::Unbounded, upper_b) => (lower_a, upper_b),
+        (lower_b, UpperBound::Unbounded) => (lower_b, upper_a),
+        (lower_b, upper_b) => (lower_b, upper_b),
+    }
+}
+
 #[cfg(test)]
 impl Filter {
     /// Helper method for tests to insert a range of filter settings.
@@ -595,4 +645,31 @@ mod tests {
         );
         assert!(filter.get(0).unwrap().exclusive);
     }
+
+    #[test]
+    fn merge_count_filters() {
+        let mut filter = Filter::new();
+        let field = Field::new("members");
+
+        filter.add_count(
+            &field,
+            LowerBound::GreaterEqual(5.into()),
+            UpperBound::Unbounded,
+        );
+        filter.add_count(&field, LowerBound::Unbounded, UpperBound::Lower(10.into()));
+
+        // Count filters are merged into one interval
+        assert_eq!(filter.len(), 1);
+        assert_eq!(
+            filter.get(0).unwrap().by,
+            FilterBy::Count(
+                LowerBound::GreaterEqual(5.into()),
+                UpperBound::Lower(10.into())
+            )
+        );
+
+        // .. but not with interval filters on the values of the same field
+        filter.add_gt(&field, &"0020".into());
+        assert_eq!(filter.len(), 2);
+    }
 }
@@ -12,6 +12,7 @@ mod validate;
 
 pub use field::{Field, MetaField};
 pub use filter::{Filter, FilterBy, FilterSetting, LowerBound, UpperBound};
-pub use order::{Direction, Order};
+pub use order::{Direction, Order, RelatedField};
 pub use pagination::{Cursor, Pagination, PaginationField};
 pub use select::{ApplicationFields, Select};
+pub use validate::validate_query;
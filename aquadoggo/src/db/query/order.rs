@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use p2panda_rs::schema::{FieldName, FieldType};
+
 use crate::db::query::Field;
 
 /// Options to determine the direction of the ordering.
@@ -12,12 +14,37 @@ pub enum Direction {
     Descending,
 }
 
+/// Field of a related document the results are ordered by.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelatedField {
+    /// Name of the field in the related document.
+    pub name: FieldName,
+
+    /// Type of the field in the related document, values get compared according to it.
+    pub field_type: FieldType,
+}
+
+impl RelatedField {
+    /// Returns a new field of a related document.
+    pub fn new(name: &str, field_type: &FieldType) -> Self {
+        Self {
+            name: name.to_string(),
+            field_type: field_type.clone(),
+        }
+    }
+}
+
 /// Ordering settings which can be used further to construct a database query.
 ///
 /// An ordering determines in which direction and based on what field the results are sorted.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Order {
     pub field: Option<Field>,
+
+    /// When set the results are ordered by this field of the document the (pinned) relation
+    /// `field` points at.
+    pub related_field: Option<RelatedField>,
+
     pub direction: Direction,
 }
 
@@ -27,6 +54,16 @@ impl Order {
     pub fn new(field: &Field, direction: &Direction) -> Self {
         Self {
             field: Some(field.clone()),
+            related_field: None,
+            direction: direction.clone(),
+        }
+    }
+
+    /// Returns a new instance of ordering settings based on a field of related documents.
+    pub fn new_related(field: &Field, related_field: &RelatedField, direction: &Direction) -> Self {
+        Self {
+            field: Some(field.clone()),
+            related_field: Some(related_field.clone()),
             direction: direction.clone(),
         }
     }
@@ -36,6 +73,7 @@ impl Default for Order {
     fn default() -> Self {
         Self {
             field: None,
+            related_field: None,
             direction: Direction::Ascending,
         }
     }
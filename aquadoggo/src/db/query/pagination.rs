@@ -73,6 +73,10 @@ where
     pub first: NonZeroU64,
     pub after: Option<C>,
     pub fields: Vec<PaginationField>,
+
+    /// Snapshot of the collection to paginate over, documents are shown in the state they had at
+    /// this point in time.
+    pub snapshot: Option<u64>,
 }
 
 impl<C> Pagination<C>
@@ -86,6 +90,7 @@ where
             first: *first,
             after: after.cloned(),
             fields: fields.to_owned(),
+            snapshot: None,
         }
     }
 }
@@ -100,6 +105,7 @@ where
             first: NonZeroU64::new(DEFAULT_PAGE_SIZE).unwrap(),
             after: None,
             fields: vec![],
+            snapshot: None,
         }
     }
 }
@@ -31,6 +31,23 @@ pub fn parse_str(key: &str, value: &[OperationValue]) -> Result<(String, FilterB
     // Unwrap since we know at least one element exists
     let element = value.first().unwrap();
 
+    if let Some((field_name, by)) = key.split_once("_count") {
+        let bound = element.to_owned();
+        let (lower, upper) = match by {
+            "" => (
+                LowerBound::GreaterEqual(bound.clone()),
+                UpperBound::LowerEqual(bound),
+            ),
+            "_gt" => (LowerBound::Greater(bound), UpperBound::Unbounded),
+            "_gte" => (LowerBound::GreaterEqual(bound), UpperBound::Unbounded),
+            "_lt" => (LowerBound::Unbounded, UpperBound::Lower(bound)),
+            "_lte" => (LowerBound::Unbounded, UpperBound::LowerEqual(bound)),
+            _ => bail!("Invalid query string"),
+        };
+
+        return Ok((field_name.into(), FilterBy::Count(lower, upper), false));
+    }
+
     if key.ends_with("_gt") {
         Ok((
             clean_key(key, "_gt"),
@@ -6,11 +6,10 @@ use p2panda_rs::operation::OperationValue;
 use p2panda_rs::schema::{FieldName, FieldType, Schema};
 
 use crate::db::query::errors::QueryError;
-use crate::db::query::{Field, Filter, FilterBy, MetaField, Order, Select};
+use crate::db::query::{Field, Filter, FilterBy, LowerBound, MetaField, Order, Select, UpperBound};
 
 /// Helper method to make sure that the chosen type in the query value matches the schema's field
 /// type.
-#[allow(dead_code)]
 fn validate_type(
     field_name: &str,
     query_field: &OperationValue,
@@ -31,7 +30,6 @@ fn validate_type(
 ///
 /// Not all fields in a schema can be equally filtered depending on their type. This method makes
 /// sure that these constraints are met and returns an error message.
-#[allow(dead_code)]
 pub fn validate_query(
     select: &Select,
     filter: &Filter,
@@ -59,11 +57,15 @@ pub fn validate_query(
         None | Some(Field::Meta(_)) => {
             // Ordering any meta field or none is always okay
         }
-        Some(Field::Field(field_name)) => {
-            if !schema_fields.contains_key(field_name) {
-                return Err(QueryError::OrderFieldUnknown(field_name.clone()));
+        Some(Field::Field(field_name)) => match schema_fields.get(field_name) {
+            None => return Err(QueryError::OrderFieldUnknown(field_name.clone())),
+            // Only documents of single relations can be ordered by their fields
+            Some(FieldType::Relation(_)) | Some(FieldType::PinnedRelation(_)) => (),
+            Some(_) if order.related_field.is_some() => {
+                return Err(QueryError::OrderInvalidRelation(field_name.clone()));
             }
-        }
+            Some(_) => (),
+        },
     };
 
     // Make sure field to filter exists and filtering value is of correct type
@@ -116,6 +118,11 @@ pub fn validate_query(
                     return Err(QueryError::FilterInvalidSearch(meta_field.to_string()))
                 }
 
+                // Counting meta fields is not permitted
+                (FilterBy::Count(_, _), _) => {
+                    return Err(QueryError::FilterInvalidCount(meta_field.to_string()))
+                }
+
                 _ => (),
             },
 
@@ -163,6 +170,34 @@ pub fn validate_query(
                         return Err(QueryError::FilterInvalidSearch(field_name.to_string()))
                     }
 
+                    // Check if count filter is applied on a list with integer bounds
+                    (FilterBy::Count(lower, upper), FieldType::RelationList(_))
+                    | (FilterBy::Count(lower, upper), FieldType::PinnedRelationList(_)) => {
+                        let bounds = [
+                            match lower {
+                                LowerBound::Unbounded => None,
+                                LowerBound::Greater(value) | LowerBound::GreaterEqual(value) => {
+                                    Some(value)
+                                }
+                            },
+                            match upper {
+                                UpperBound::Unbounded => None,
+                                UpperBound::Lower(value) | UpperBound::LowerEqual(value) => {
+                                    Some(value)
+                                }
+                            },
+                        ];
+
+                        for value in bounds.iter().flatten() {
+                            validate_type(field_name, value, &FieldType::Integer)?;
+                        }
+                    }
+
+                    // Disallow count filters for everything which is not a list
+                    (FilterBy::Count(_, _), _) => {
+                        return Err(QueryError::FilterInvalidCount(field_name.to_string()))
+                    }
+
                     _ => (),
                 }
             }
@@ -174,9 +209,10 @@ pub fn validate_query(
 
 #[cfg(test)]
 mod tests {
+    use p2panda_rs::schema::FieldType;
     use rstest::rstest;
 
-    use crate::db::query::{Direction, Filter, Order, Select};
+    use crate::db::query::{Direction, Filter, Order, RelatedField, Select};
     use crate::test_utils::doggo_schema;
 
     use super::validate_query;
@@ -199,6 +235,15 @@ mod tests {
             &Direction::Descending
         )
     )]
+    #[case::order_by_related_field(
+        Select::default(),
+        Filter::default(),
+        Order::new_related(
+            &"profile_picture".into(),
+            &RelatedField::new("blob_id", &FieldType::String),
+            &Direction::Ascending
+        )
+    )]
     fn valid_queries(#[case] select: Select, #[case] filter: Filter, #[case] order: Order) {
         if let Err(err) = validate_query(&select, &filter, &order, &doggo_schema()) {
             panic!("{}", err)
@@ -226,6 +271,16 @@ mod tests {
         Order::new(&"message".into(), &Direction::Ascending),
         "Can't apply ordering on unknown field 'message'"
     )]
+    #[case::order_related_field_of_non_relation(
+        Select::default(),
+        Filter::default(),
+        Order::new_related(
+            &"username".into(),
+            &RelatedField::new("name", &FieldType::String),
+            &Direction::Ascending
+        ),
+        "Can't order by field of related documents as field 'username' is not of type relation or pinned relation"
+    )]
     #[case::invalid_meta_field_type(
         Select::default(),
         Filter::new().meta_fields(&[
@@ -266,6 +321,14 @@ mod tests {
         Order::default(),
         "Filter type 'int' for field 'username' is not matching schema type 'str'"
     )]
+    #[case::invalid_count(
+        Select::default(),
+        Filter::new().fields(&[
+            ("age_count_gte", &[2.into()])
+        ]),
+        Order::default(),
+        "Can't apply count filter as field 'age' is not of type relation list"
+    )]
     fn invalid_queries(
         #[case] select: Select,
         #[case] filter: Filter,
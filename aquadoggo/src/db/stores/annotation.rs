@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::DocumentId;
+use sqlx::{query, query_as};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::models::AnnotationRow;
+use crate::db::SqlStore;
+
+/// Methods to interact with the `document_annotations` table in the database.
+///
+/// Annotations are node-local key-value pairs attached to documents, for example labels,
+/// moderation states or notes of the node operator. They are not part of any operation and never
+/// leave this node.
+impl SqlStore {
+    /// Sets the value of an annotation of a document, replacing the previous value of this key.
+    pub async fn set_document_annotation(
+        &self,
+        document_id: &DocumentId,
+        key: &str,
+        value: &str,
+    ) -> Result<(), SqlStoreError> {
+        query(
+            "
+            INSERT INTO
+                document_annotations (
+                    document_id,
+                    key,
+                    value
+                )
+            VALUES
+                ($1, $2, $3)
+            ON CONFLICT (document_id, key) DO UPDATE SET
+                value = excluded.value
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(key)
+        .bind(value)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Removes an annotation of a document.
+    ///
+    /// Returns `true` if an annotation with this key existed.
+    pub async fn delete_document_annotation(
+        &self,
+        document_id: &DocumentId,
+        key: &str,
+    ) -> Result<bool, SqlStoreError> {
+        let result = query(
+            "
+            DELETE FROM
+                document_annotations
+            WHERE
+                document_id = $1
+                AND key = $2
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(key)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns all annotations of a document, ordered by their key.
+    pub async fn get_document_annotations(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<Vec<AnnotationRow>, SqlStoreError> {
+        query_as::<_, AnnotationRow>(
+            "
+            SELECT
+                document_id,
+                key,
+                value
+            FROM
+                document_annotations
+            WHERE
+                document_id = $1
+            ORDER BY
+                key
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+
+    use crate::test_utils::{test_runner, TestNode};
+
+    #[rstest]
+    fn set_and_delete_annotations(
+        #[from(random_document_id)] document_id: DocumentId,
+        #[from(random_document_id)] other_document_id: DocumentId,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+
+            store
+                .set_document_annotation(&document_id, "moderation", "hidden")
+                .await
+                .unwrap();
+            store
+                .set_document_annotation(&document_id, "label", "spam")
+                .await
+                .unwrap();
+            store
+                .set_document_annotation(&other_document_id, "label", "news")
+                .await
+                .unwrap();
+
+            // Setting an existing key replaces its value
+            store
+                .set_document_annotation(&document_id, "moderation", "visible")
+                .await
+                .unwrap();
+
+            let annotations = store.get_document_annotations(&document_id).await.unwrap();
+            let pairs: Vec<(&str, &str)> = annotations
+                .iter()
+                .map(|row| (row.key.as_str(), row.value.as_str()))
+                .collect();
+            assert_eq!(pairs, vec![("label", "spam"), ("moderation", "visible")]);
+
+            assert!(store
+                .delete_document_annotation(&document_id, "label")
+                .await
+                .unwrap());
+            assert!(!store
+                .delete_document_annotation(&document_id, "label")
+                .await
+                .unwrap());
+            assert_eq!(
+                store
+                    .get_document_annotations(&document_id)
+                    .await
+                    .unwrap()
+                    .len(),
+                1
+            );
+            assert_eq!(
+                store
+                    .get_document_annotations(&other_document_id)
+                    .await
+                    .unwrap()
+                    .len(),
+                1
+            );
+        });
+    }
+}
@@ -0,0 +1,493 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::SchemaId;
+use sqlx::{query, query_as, query_scalar, Any, Transaction};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::models::ArchivedOperationFieldRow;
+use crate::db::SqlStore;
+use crate::replication::now;
+
+/// Mark a document as recently active.
+///
+/// Expects to be called inside of the transaction inserting new operations.
+pub(crate) async fn touch_document(
+    tx: &mut Transaction<'_, Any>,
+    document_id: &DocumentId,
+) -> Result<(), sqlx::Error> {
+    query(
+        "
+        INSERT INTO
+            document_activity (
+                document_id,
+                updated_at
+            )
+        VALUES
+            ($1, $2)
+        ON CONFLICT (document_id) DO UPDATE
+            SET updated_at = $2
+        ",
+    )
+    .bind(document_id.as_str())
+    .bind(now() as i64)
+    .execute(tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Methods to move historical data of inactive documents between the main and archive database.
+///
+/// Only the fields of operations which are not referenced by any materialized document view get
+/// archived. Everything else, including entries and the operations themselves, stays in the main
+/// database, this allows serving documents and replicating logs without touching the archive.
+impl SqlStore {
+    /// Returns true if an archive database is configured.
+    pub fn has_archive(&self) -> bool {
+        self.archive.is_some()
+    }
+
+    /// Move historical data of all documents which were not active for longer than the given
+    /// threshold (in seconds) into the archive database.
+    ///
+    /// Returns the number of archived documents.
+    pub async fn archive_inactive_documents(&self, threshold: u64) -> Result<usize, SqlStoreError> {
+        if self.archive.is_none() {
+            return Ok(0);
+        }
+
+        let document_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                document_activity.document_id
+            FROM
+                document_activity
+            WHERE
+                document_activity.updated_at < $1
+                AND document_activity.document_id NOT IN (
+                    SELECT archived_documents.document_id FROM archived_documents
+                )
+            ",
+        )
+        .bind(now() as i64 - threshold as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        for document_id in &document_ids {
+            let document_id: DocumentId = document_id.parse().unwrap();
+            self.archive_document(&document_id).await?;
+        }
+
+        Ok(document_ids.len())
+    }
+
+    /// Move historical data of a document into the archive database.
+    ///
+    /// Operation fields which are not referenced by any document view are moved. Does nothing when
+    /// no archive database is configured.
+    pub async fn archive_document(&self, document_id: &DocumentId) -> Result<(), SqlStoreError> {
+        let archive = match &self.archive {
+            Some(archive) => archive,
+            None => return Ok(()),
+        };
+
+        // Cached historic views might lose their fields
+        self.document_cache.invalidate(document_id);
+
+        // Select and remove the fields in the same transaction, fields which got referenced by a
+        // new document view in the meantime are not removed
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let rows = query_as::<_, ArchivedOperationFieldRow>(
+            "
+            SELECT
+                operations_v1.document_id,
+                operation_fields_v1.operation_id,
+                operation_fields_v1.name,
+                operation_fields_v1.field_type,
+                operation_fields_v1.value,
+                operation_fields_v1.list_index,
+                operation_fields_v1.cursor
+            FROM
+                operation_fields_v1
+                JOIN operations_v1
+                    ON operations_v1.operation_id = operation_fields_v1.operation_id
+            WHERE
+                operations_v1.document_id = $1
+                AND NOT EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        document_view_fields
+                    WHERE
+                        document_view_fields.operation_id = operation_fields_v1.operation_id
+                        AND document_view_fields.name = operation_fields_v1.name
+                )
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&mut tx)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        // Write the rows into the archive first, this way we never loose data when something
+        // fails in between. Left-overs from earlier, interrupted attempts are removed.
+        let mut archive_tx = archive
+            .begin()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        query("DELETE FROM archived_operation_fields_v1 WHERE document_id = $1")
+            .bind(document_id.as_str())
+            .execute(&mut archive_tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        for row in &rows {
+            query(
+                "
+                INSERT INTO
+                    archived_operation_fields_v1 (
+                        document_id,
+                        operation_id,
+                        name,
+                        field_type,
+                        value,
+                        list_index,
+                        cursor
+                    )
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7)
+                ",
+            )
+            .bind(&row.document_id)
+            .bind(&row.operation_id)
+            .bind(&row.name)
+            .bind(&row.field_type)
+            .bind(&row.value)
+            .bind(row.list_index)
+            .bind(&row.cursor)
+            .execute(&mut archive_tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+        }
+
+        archive_tx
+            .commit()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        // Remove the archived rows from the main database
+        for row in &rows {
+            query("DELETE FROM operation_fields_v1 WHERE cursor = $1")
+                .bind(&row.cursor)
+                .execute(&mut tx)
+                .await
+                .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+        }
+
+        query(
+            "
+            INSERT INTO
+                archived_documents (
+                    document_id
+                )
+            VALUES
+                ($1)
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(document_id.as_str())
+        .execute(&mut tx)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Load archived data of a document back into the main database.
+    ///
+    /// Does nothing when the document was not archived.
+    pub async fn restore_document(&self, document_id: &DocumentId) -> Result<(), SqlStoreError> {
+        let archive = match &self.archive {
+            Some(archive) => archive,
+            None => return Ok(()),
+        };
+
+        let is_archived: Option<String> = query_scalar(
+            "
+            SELECT
+                archived_documents.document_id
+            FROM
+                archived_documents
+            WHERE
+                archived_documents.document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        if is_archived.is_none() {
+            return Ok(());
+        }
+
+        let rows = query_as::<_, ArchivedOperationFieldRow>(
+            "
+            SELECT
+                document_id,
+                operation_id,
+                name,
+                field_type,
+                value,
+                list_index,
+                cursor
+            FROM
+                archived_operation_fields_v1
+            WHERE
+                document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(archive)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        for row in &rows {
+            query(
+                "
+                INSERT INTO
+                    operation_fields_v1 (
+                        operation_id,
+                        name,
+                        field_type,
+                        value,
+                        list_index,
+                        cursor
+                    )
+                VALUES
+                    ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT DO NOTHING
+                ",
+            )
+            .bind(&row.operation_id)
+            .bind(&row.name)
+            .bind(&row.field_type)
+            .bind(&row.value)
+            .bind(row.list_index)
+            .bind(&row.cursor)
+            .execute(&mut tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+        }
+
+        query("DELETE FROM archived_documents WHERE document_id = $1")
+            .bind(document_id.as_str())
+            .execute(&mut tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        // Reset activity to not move the document straight back into the archive
+        touch_document(&mut tx, document_id)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        // Finally remove the restored rows from the archive
+        query("DELETE FROM archived_operation_fields_v1 WHERE document_id = $1")
+            .bind(document_id.as_str())
+            .execute(archive)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Load archived data of all documents of the given schema back into the main database.
+    pub async fn restore_documents_by_schema(
+        &self,
+        schema_id: &SchemaId,
+    ) -> Result<(), SqlStoreError> {
+        if self.archive.is_none() {
+            return Ok(());
+        }
+
+        let document_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                archived_documents.document_id
+            FROM
+                archived_documents
+                JOIN operations_v1
+                    ON operations_v1.operation_id = archived_documents.document_id
+            WHERE
+                operations_v1.schema_id = $1
+            ",
+        )
+        .bind(schema_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        for document_id in document_ids {
+            self.restore_document(&document_id.parse().unwrap()).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove all archived data of a document.
+    pub(crate) async fn discard_archived_document(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<(), SqlStoreError> {
+        if let Some(archive) = &self.archive {
+            query("DELETE FROM archived_operation_fields_v1 WHERE document_id = $1")
+                .bind(document_id.as_str())
+                .execute(archive)
+                .await
+                .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
+    use rstest::rstest;
+    use sqlx::{query, query_scalar};
+
+    use crate::db::{connection_pool, initialize_archive, Pool, SqlStore};
+    use crate::test_utils::{
+        populate_and_materialize, populate_store_config, test_runner, PopulateStoreConfig,
+        TestConfiguration, TestNode,
+    };
+
+    async fn archive_pool() -> Pool {
+        let config = TestConfiguration::default();
+        let pool = connection_pool(&config.database_url, 1, None)
+            .await
+            .unwrap();
+        initialize_archive(&pool).await.unwrap();
+        pool
+    }
+
+    async fn count_operation_fields(store: &SqlStore) -> i64 {
+        query_scalar("SELECT COUNT(*) FROM operation_fields_v1")
+            .fetch_one(&store.pool)
+            .await
+            .unwrap()
+    }
+
+    #[rstest]
+    fn archive_and_restore_documents(
+        #[from(populate_store_config)]
+        #[with(5, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(|mut node: TestNode| async move {
+            let documents = populate_and_materialize(&mut node, &config).await;
+            let document_id = documents[0].id();
+
+            let store = node
+                .context
+                .store
+                .clone()
+                .with_archive(archive_pool().await);
+            let operations_before = store
+                .get_operations_by_document_id(document_id)
+                .await
+                .unwrap();
+            let fields_before = count_operation_fields(&store).await;
+
+            // Document was active recently
+            assert_eq!(store.archive_inactive_documents(60).await.unwrap(), 0);
+
+            // Pretend the document was inactive for a long time
+            query("UPDATE document_activity SET updated_at = 0")
+                .execute(&store.pool)
+                .await
+                .unwrap();
+            assert_eq!(store.archive_inactive_documents(60).await.unwrap(), 1);
+
+            // Historical fields were moved, but the document can still be served
+            assert!(count_operation_fields(&store).await < fields_before);
+            let document = store.get_document(document_id).await.unwrap().unwrap();
+            assert_eq!(document.fields(), documents[0].fields());
+
+            // Accessing the operations transparently loads them back
+            let operations_after = store
+                .get_operations_by_document_id(document_id)
+                .await
+                .unwrap();
+            assert_eq!(operations_after, operations_before);
+            assert_eq!(count_operation_fields(&store).await, fields_before);
+
+            // Restoring the document counts as activity
+            let updated_at: i64 =
+                query_scalar("SELECT updated_at FROM document_activity WHERE document_id = $1")
+                    .bind(document_id.as_str())
+                    .fetch_one(&store.pool)
+                    .await
+                    .unwrap();
+            assert!(updated_at > 0);
+            assert_eq!(store.archive_inactive_documents(60).await.unwrap(), 0);
+        });
+    }
+
+    #[rstest]
+    fn restore_single_operation(
+        #[from(populate_store_config)]
+        #[with(3, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(|mut node: TestNode| async move {
+            let documents = populate_and_materialize(&mut node, &config).await;
+            let document_id = documents[0].id();
+
+            let store = node
+                .context
+                .store
+                .clone()
+                .with_archive(archive_pool().await);
+            let operations = store
+                .get_operations_by_document_id(document_id)
+                .await
+                .unwrap();
+
+            store.archive_document(document_id).await.unwrap();
+
+            // The CREATE operation contained fields which got overwritten since
+            let create_operation = store
+                .get_operation(&operations[0].id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(create_operation, operations[0]);
+        });
+    }
+}
@@ -0,0 +1,255 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::hash::Hash;
+use sqlx::{query, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+
+/// Methods to publish batches of entries atomically.
+///
+/// Entries of a batch are recorded as pending before they get published. When publishing any
+/// entry of the batch fails, all entries published so far are removed again, together with their
+/// operations and the logs they created. Pending entries are neither replicated nor materialized,
+/// queries for log heights, entries of logs and operations of documents leave them out.
+impl SqlStore {
+    /// Record an entry as pending until its batch completes.
+    pub async fn insert_pending_batch_entry(
+        &self,
+        batch_id: &str,
+        entry: &impl AsEntry,
+        entry_hash: &Hash,
+    ) -> Result<(), SqlStoreError> {
+        query(
+            "
+            INSERT INTO
+                pending_batch_entries (
+                    batch_id,
+                    entry_hash,
+                    public_key,
+                    log_id,
+                    is_first_entry
+                )
+            VALUES
+                ($1, $2, $3, $4, $5)
+            ",
+        )
+        .bind(batch_id)
+        .bind(entry_hash.as_str())
+        .bind(entry.public_key().to_string())
+        .bind(entry.log_id().as_u64().to_string())
+        .bind(entry.seq_num().is_first())
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Forget a pending entry again, for example because it could not be published and should not
+    /// be removed when rolling back its batch.
+    pub async fn remove_pending_batch_entry(
+        &self,
+        batch_id: &str,
+        entry_hash: &Hash,
+    ) -> Result<(), SqlStoreError> {
+        query("DELETE FROM pending_batch_entries WHERE batch_id = $1 AND entry_hash = $2")
+            .bind(batch_id)
+            .bind(entry_hash.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Keep all entries of a completely published batch.
+    pub async fn complete_batch(&self, batch_id: &str) -> Result<(), SqlStoreError> {
+        query("DELETE FROM pending_batch_entries WHERE batch_id = $1")
+            .bind(batch_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Remove all entries of a batch which got published so far, with their operations and the
+    /// logs they created.
+    pub async fn rollback_batch(&self, batch_id: &str) -> Result<(), SqlStoreError> {
+        let mut tx = self
+            .begin()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let pending_entries = "
+            SELECT
+                pending_batch_entries.entry_hash
+            FROM
+                pending_batch_entries
+            WHERE
+                pending_batch_entries.batch_id = $1
+        ";
+
+        for sql in [
+            format!("DELETE FROM operation_fields_v1 WHERE operation_id IN ({pending_entries})"),
+            format!("DELETE FROM operations_v1 WHERE operation_id IN ({pending_entries})"),
+            format!("DELETE FROM entries WHERE entry_hash IN ({pending_entries})"),
+            "
+            DELETE FROM
+                logs
+            WHERE
+                EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        pending_batch_entries
+                    WHERE
+                        pending_batch_entries.batch_id = $1
+                        AND pending_batch_entries.is_first_entry
+                        AND pending_batch_entries.public_key = logs.public_key
+                        AND pending_batch_entries.log_id = logs.log_id
+                )
+            "
+            .to_string(),
+            "DELETE FROM pending_batch_entries WHERE batch_id = $1".to_string(),
+        ] {
+            query(&sql)
+                .bind(batch_id)
+                .execute(&mut tx)
+                .await
+                .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        // Cached logs might contain the removed entries
+        self.log_cache.clear();
+
+        Ok(())
+    }
+
+    /// Remove the entries of all batches which did not complete, for example because the node
+    /// stopped while publishing them.
+    ///
+    /// Returns the number of removed batches.
+    pub async fn rollback_pending_batches(&self) -> Result<usize, SqlStoreError> {
+        let batch_ids: Vec<String> =
+            query_scalar("SELECT DISTINCT batch_id FROM pending_batch_entries")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        for batch_id in &batch_ids {
+            self.rollback_batch(batch_id).await?;
+        }
+
+        Ok(batch_ids.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
+    use p2panda_rs::entry::SeqNum;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::storage_provider::traits::{EntryStore, LogStore, OperationStore};
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::test_utils::{add_document, add_schema, test_runner, update_document, TestNode};
+
+    #[rstest]
+    fn rollback_batches(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Pub".into()))],
+                &key_pair,
+            )
+            .await;
+            let update_view_id = update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Bar".into()))],
+                &view_id,
+                &key_pair,
+            )
+            .await;
+
+            let store = &node.context.store;
+            let create_hash = view_id.graph_tips()[0].as_hash().to_owned();
+            let update_hash = update_view_id.graph_tips()[0].as_hash().to_owned();
+            let create_entry = store.get_entry(&create_hash).await.unwrap().unwrap();
+            let update_entry = store.get_entry(&update_hash).await.unwrap().unwrap();
+            let document_id = DocumentId::new(&create_hash.clone().into());
+
+            // Completed batches keep their entries
+            store
+                .insert_pending_batch_entry("completed", &update_entry, &update_hash)
+                .await
+                .unwrap();
+            store.complete_batch("completed").await.unwrap();
+            assert_eq!(store.rollback_pending_batches().await.unwrap(), 0);
+            assert!(store.get_entry(&update_hash).await.unwrap().is_some());
+
+            // Entries of pending batches are not replicated or materialized
+            for entry in [&create_entry, &update_entry] {
+                store
+                    .insert_pending_batch_entry("pending", entry, &entry.hash())
+                    .await
+                    .unwrap();
+            }
+            assert!(store
+                .get_entries_from(
+                    &key_pair.public_key(),
+                    create_entry.log_id(),
+                    &SeqNum::default()
+                )
+                .await
+                .unwrap()
+                .is_empty());
+            assert!(store
+                .get_document_log_heights(&[document_id.clone()])
+                .await
+                .unwrap()
+                .is_empty());
+            assert!(store
+                .get_operations_by_document_id(&document_id)
+                .await
+                .unwrap()
+                .is_empty());
+
+            // Entries of pending batches get removed with their operations and logs
+            assert_eq!(store.rollback_pending_batches().await.unwrap(), 1);
+
+            assert!(store.get_entry(&create_hash).await.unwrap().is_none());
+            assert!(store.get_entry(&update_hash).await.unwrap().is_none());
+            assert!(store
+                .get_operation(&create_hash.clone().into())
+                .await
+                .unwrap()
+                .is_none());
+            assert!(store
+                .get_log_id(&key_pair.public_key(), &document_id)
+                .await
+                .unwrap()
+                .is_none());
+        });
+    }
+}
@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashSet;
 use std::num::NonZeroU64;
 
 use async_stream::try_stream;
@@ -7,15 +8,19 @@ use bytes::{BufMut, BytesMut};
 use futures::Stream;
 use p2panda_rs::document::traits::AsDocument;
 use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::SeqNum;
 use p2panda_rs::operation::OperationValue;
 use p2panda_rs::schema::validate::MAX_BLOB_PIECE_LENGTH;
 use p2panda_rs::schema::{Schema, SchemaId};
+use p2panda_rs::storage_provider::error::DocumentStorageError;
 use p2panda_rs::storage_provider::traits::DocumentStore;
-use sqlx::{query_scalar, AnyPool};
+use sqlx::{query, query_as, query_scalar, AnyPool};
 
 use crate::db::errors::{BlobStoreError, SqlStoreError};
+use crate::db::models::BlobDerivativeRow;
 use crate::db::query::{Filter, Order, Pagination, PaginationField, Select};
 use crate::db::stores::query::{PaginationCursor, Query, RelationList};
+use crate::db::types::StorageEntry;
 use crate::db::SqlStore;
 
 /// Number of blob pieces requested per database query iteration.
@@ -23,6 +28,33 @@ const BLOB_QUERY_PAGE_SIZE: u64 = 10;
 
 pub type BlobData = Vec<u8>;
 
+/// Completeness of the pieces of a blob document.
+///
+/// Blob documents can be published before all of their pieces arrived at the node. This allows
+/// clients to resume interrupted uploads by only publishing the pieces which are still missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlobStatus {
+    /// Latest view of the blob document.
+    pub view_id: DocumentViewId,
+
+    /// Claimed length of the blob in bytes.
+    pub length: u64,
+
+    /// Number of pieces the blob consists of.
+    pub num_pieces: usize,
+
+    /// View ids of pieces which are not materialized on this node yet, in the order they appear
+    /// in the blob.
+    pub missing_pieces: Vec<DocumentViewId>,
+}
+
+impl BlobStatus {
+    /// Returns true if all pieces of this blob are available.
+    pub fn is_complete(&self) -> bool {
+        self.missing_pieces.is_empty()
+    }
+}
+
 /// Gets blob data from the database in chunks (via pagination) and populates a readable stream
 /// with it.
 ///
@@ -175,62 +207,202 @@ impl SqlStore {
         }
     }
 
-    /// Purge blob data from the node _if_ it is not related to from another document.
-    pub async fn purge_blob(&self, document_id: &DocumentId) -> Result<bool, SqlStoreError> {
-        // Collect the view id of any existing document views which contain a relation to the blob
-        // which is the purge target.
-        let blob_reverse_relations = reverse_relations(&self.pool, document_id, None).await?;
+    /// Get the completeness of the pieces of a blob document, identified by its document id.
+    ///
+    /// Returns `None` if the blob document does not exist.
+    pub async fn get_blob_status(
+        &self,
+        id: &DocumentId,
+    ) -> Result<Option<BlobStatus>, BlobStoreError> {
+        let document = match self.get_document(id).await? {
+            Some(document) => document,
+            None => return Ok(None),
+        };
 
-        // If there are no documents referring to the blob then we continue with the purge.
-        let should_purge = blob_reverse_relations.is_empty();
-        if should_purge {
-            // Collect the document view ids of all pieces this blob has ever referred to in its
-            // `pieces`
-            let blob_piece_ids: Vec<String> = query_scalar(
-                "
+        if document.schema_id() != &SchemaId::Blob(1) {
+            return Err(BlobStoreError::NotBlobDocument);
+        }
+
+        let length = match document.get("length").unwrap() {
+            OperationValue::Integer(length) => *length as u64,
+            _ => unreachable!(), // We already validated that this is a blob document
+        };
+
+        let pieces = match document.get("pieces").unwrap() {
+            OperationValue::PinnedRelationList(list) => list.clone(),
+            _ => unreachable!(), // We already validated that this is a blob document
+        };
+
+        // Collect the view ids of all pieces of this blob view which are already materialized
+        let available_pieces: Vec<String> = query_scalar(
+            "
+            SELECT
+                document_views.document_view_id
+            FROM
+                document_views
+            WHERE
+                document_views.schema_id = 'blob_piece_v1'
+            AND
+                document_views.document_view_id
+            IN (
                 SELECT
                     operation_fields_v1.value
                 FROM
-                    operation_fields_v1
+                    document_view_fields
                 LEFT JOIN
-                    operations_v1
+                    operation_fields_v1
                 ON
-                    operations_v1.operation_id = operation_fields_v1.operation_id
+                    document_view_fields.operation_id = operation_fields_v1.operation_id
+                AND
+                    document_view_fields.name = operation_fields_v1.name
                 WHERE
-                    operations_v1.document_id = $1
+                    document_view_fields.document_view_id = $1
                 AND
                     operation_fields_v1.name = 'pieces'
-                ",
             )
-            .bind(document_id.to_string())
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| SqlStoreError::Transaction(e.to_string()))?;
+            ",
+        )
+        .bind(document.view_id().to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+        let available_pieces: HashSet<String> = available_pieces.into_iter().collect();
+        let missing_pieces = pieces
+            .iter()
+            .filter(|view_id| !available_pieces.contains(&view_id.to_string()))
+            .cloned()
+            .collect();
+
+        Ok(Some(BlobStatus {
+            view_id: document.view_id().to_owned(),
+            length,
+            num_pieces: pieces.len(),
+            missing_pieces,
+        }))
+    }
 
-            // Purge the blob document itself.
-            self.purge_document(document_id).await?;
+    /// Get all entries of a blob document and of the pieces of its latest view, identified by its
+    /// document id.
+    ///
+    /// Entries of the blob document come first, followed by the entries of its pieces in the order
+    /// they appear in the blob. Pieces which are not materialized on this node are left out.
+    /// Returns an empty list if the blob document does not exist.
+    pub async fn get_blob_entries(
+        &self,
+        id: &DocumentId,
+    ) -> Result<Vec<StorageEntry>, BlobStoreError> {
+        let document = match self.get_document(id).await? {
+            Some(document) => document,
+            None => return Ok(Vec::new()),
+        };
 
-            // Now iterate over each collected blob piece in order to check if they are still
-            // needed by any other blob document, and if not purge them as well.
-            for blob_piece_id in blob_piece_ids {
-                let blob_piece_id: DocumentId = blob_piece_id
-                    .parse()
-                    .expect("Document Id's from the store are valid");
+        if document.schema_id() != &SchemaId::Blob(1) {
+            return Err(BlobStoreError::NotBlobDocument);
+        }
+
+        let pieces = match document.get("pieces").unwrap() {
+            OperationValue::PinnedRelationList(list) => list.clone(),
+            _ => unreachable!(), // We already validated that this is a blob document
+        };
 
-                // Collect reverse relations for this blob piece.
-                let blob_piece_reverse_relations =
-                    reverse_relations(&self.pool, &blob_piece_id, Some(SchemaId::Blob(1))).await?;
+        let mut document_ids = vec![id.to_owned()];
+        for view_id in pieces.iter() {
+            if let Some(piece) = self.get_document_by_view_id(view_id).await? {
+                document_ids.push(piece.id().to_owned());
+            }
+        }
 
-                // If there are none then purge the blob piece.
-                if blob_piece_reverse_relations.is_empty() {
-                    self.purge_document(&blob_piece_id).await?;
+        let mut entries = Vec::new();
+        for document_id in document_ids {
+            for (public_key, log_heights) in self.get_document_log_heights(&[document_id]).await? {
+                for (log_id, _) in log_heights {
+                    entries.extend(
+                        self.get_entries_from(&public_key, &log_id, &SeqNum::default())
+                            .await?,
+                    );
                 }
             }
         }
 
+        Ok(entries)
+    }
+
+    /// Purge blob data from the node _if_ it is not related to from another document.
+    pub async fn purge_blob(&self, document_id: &DocumentId) -> Result<bool, SqlStoreError> {
+        // Collect the view id of any existing document views which contain a relation to the blob
+        // which is the purge target.
+        let blob_reverse_relations = reverse_relations(&self.pool, document_id, None).await?;
+
+        // If there are no documents referring to the blob then we continue with the purge.
+        let should_purge = blob_reverse_relations.is_empty();
+        if should_purge {
+            self.purge_blob_document(document_id).await?;
+        }
+
         Ok(should_purge)
     }
 
+    /// Purge a blob document and all its pieces which are not used by another blob, even if other
+    /// documents still relate to the blob.
+    ///
+    /// Returns the ids of the purged pieces.
+    pub async fn purge_blob_document(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<Vec<DocumentId>, SqlStoreError> {
+        // Make sure historical pieces are not hidden in the archive
+        self.restore_document(document_id).await?;
+
+        // Collect the document view ids of all pieces this blob has ever referred to in its
+        // `pieces`
+        let blob_piece_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                operation_fields_v1.value
+            FROM
+                operation_fields_v1
+            LEFT JOIN
+                operations_v1
+            ON
+                operations_v1.operation_id = operation_fields_v1.operation_id
+            WHERE
+                operations_v1.document_id = $1
+            AND
+                operation_fields_v1.name = 'pieces'
+            ",
+        )
+        .bind(document_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SqlStoreError::Transaction(e.to_string()))?;
+
+        // Purge the blob document itself.
+        self.purge_document(document_id).await?;
+
+        // Now iterate over each collected blob piece in order to check if they are still needed by
+        // any other blob document, and if not purge them as well.
+        let mut purged_piece_ids = Vec::new();
+        for blob_piece_id in blob_piece_ids {
+            let blob_piece_id: DocumentId = blob_piece_id
+                .parse()
+                .expect("Document Id's from the store are valid");
+
+            // Collect reverse relations for this blob piece.
+            let blob_piece_reverse_relations =
+                reverse_relations(&self.pool, &blob_piece_id, Some(SchemaId::Blob(1))).await?;
+
+            // If there are none then purge the blob piece.
+            if blob_piece_reverse_relations.is_empty() && !purged_piece_ids.contains(&blob_piece_id)
+            {
+                self.purge_document(&blob_piece_id).await?;
+                purged_piece_ids.push(blob_piece_id);
+            }
+        }
+
+        Ok(purged_piece_ids)
+    }
+
     /// Get ids for all blob documents which are related to from any view of the passed document.
     pub async fn get_blob_child_relations(
         &self,
@@ -282,6 +454,281 @@ impl SqlStore {
             })
             .collect())
     }
+
+    /// Get ids for all blob documents which are related to from views of the passed document
+    /// only and from no view of any other document.
+    pub async fn get_exclusive_blob_relations(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<Vec<DocumentId>, SqlStoreError> {
+        let document_view_ids: HashSet<String> = query_scalar(
+            "
+            SELECT
+                document_views.document_view_id
+            FROM
+                document_views
+            WHERE
+                document_views.document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?
+        .into_iter()
+        .collect();
+
+        let mut blob_ids = Vec::new();
+        for blob_id in self.get_blob_child_relations(document_id).await? {
+            let blob_reverse_relations = reverse_relations(&self.pool, &blob_id, None).await?;
+
+            if blob_reverse_relations
+                .iter()
+                .all(|view_id| document_view_ids.contains(view_id))
+            {
+                blob_ids.push(blob_id);
+            }
+        }
+
+        Ok(blob_ids)
+    }
+
+    /// Drop the data of the pieces of a blob view which got materialized on the file system.
+    ///
+    /// The `data` fields of all operations of the pieces are emptied and their entries lose their
+    /// payloads. Entries and their payload hashes stay in the store, logs can therefore still be
+    /// verified. Pieces which are part of other blob documents are left untouched.
+    ///
+    /// Returns the number of compacted pieces.
+    pub async fn compact_blob_pieces(
+        &self,
+        view_id: &DocumentViewId,
+    ) -> Result<usize, SqlStoreError> {
+        // Views of the blob document itself are allowed to refer to the pieces
+        let blob_view_ids: HashSet<String> = query_scalar(
+            "
+            SELECT
+                document_views.document_view_id
+            FROM
+                document_views
+            WHERE
+                document_views.document_id = (
+                    SELECT
+                        document_views.document_id
+                    FROM
+                        document_views
+                    WHERE
+                        document_views.document_view_id = $1
+                )
+            ",
+        )
+        .bind(view_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?
+        .into_iter()
+        .collect();
+
+        // Collect the document ids of all pieces of this blob view
+        let piece_document_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                document_views.document_id
+            FROM
+                document_views
+            WHERE
+                document_views.document_view_id
+            IN (
+                SELECT
+                    operation_fields_v1.value
+                FROM
+                    document_view_fields
+                LEFT JOIN
+                    operation_fields_v1
+                ON
+                    document_view_fields.operation_id = operation_fields_v1.operation_id
+                AND
+                    document_view_fields.name = operation_fields_v1.name
+                WHERE
+                    document_view_fields.document_view_id = $1
+                AND
+                    operation_fields_v1.name = 'pieces'
+            )
+            ",
+        )
+        .bind(view_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let mut compacted_count = 0;
+        for piece_document_id in piece_document_ids {
+            let piece_document_id: DocumentId = piece_document_id
+                .parse()
+                .expect("Document Id's from the store are valid");
+
+            let blob_reverse_relations =
+                reverse_relations(&self.pool, &piece_document_id, Some(SchemaId::Blob(1))).await?;
+            if !blob_reverse_relations
+                .iter()
+                .all(|view_id| blob_view_ids.contains(view_id))
+            {
+                continue;
+            }
+
+            let mut tx = self
+                .pool
+                .begin()
+                .await
+                .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+            query(
+                "
+                UPDATE
+                    operation_fields_v1
+                SET
+                    value = ''
+                WHERE
+                    operation_fields_v1.name = 'data'
+                AND
+                    operation_fields_v1.operation_id IN (
+                        SELECT
+                            operations_v1.operation_id
+                        FROM
+                            operations_v1
+                        WHERE
+                            operations_v1.document_id = $1
+                    )
+                ",
+            )
+            .bind(piece_document_id.as_str())
+            .execute(&mut tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+            query(
+                "
+                UPDATE
+                    entries
+                SET
+                    payload_bytes = NULL
+                WHERE
+                    entries.entry_hash IN (
+                        SELECT
+                            operations_v1.operation_id
+                        FROM
+                            operations_v1
+                        WHERE
+                            operations_v1.document_id = $1
+                    )
+                ",
+            )
+            .bind(piece_document_id.as_str())
+            .execute(&mut tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+            tx.commit()
+                .await
+                .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+            self.document_cache.invalidate(&piece_document_id);
+            self.log_cache.clear();
+            compacted_count += 1;
+        }
+
+        Ok(compacted_count)
+    }
+
+    /// Record a blob derived from a blob view, replacing a previous derivative with the same name.
+    pub async fn insert_blob_derivative(
+        &self,
+        document_id: &DocumentId,
+        view_id: &DocumentViewId,
+        name: &str,
+        mime_type: &str,
+        length: u64,
+    ) -> Result<(), SqlStoreError> {
+        query(
+            "
+            INSERT INTO
+                blob_derivatives (
+                    document_id,
+                    view_id,
+                    name,
+                    mime_type,
+                    length
+                )
+            VALUES
+                ($1, $2, $3, $4, $5)
+            ON CONFLICT (view_id, name) DO UPDATE SET
+                mime_type = excluded.mime_type,
+                length = excluded.length
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(view_id.to_string())
+        .bind(name)
+        .bind(mime_type)
+        .bind(length as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Returns all blobs derived from the latest view of a blob document, ordered by their name.
+    pub async fn get_blob_derivatives(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<Vec<BlobDerivativeRow>, SqlStoreError> {
+        query_as::<_, BlobDerivativeRow>(
+            "
+            SELECT
+                blob_derivatives.document_id,
+                blob_derivatives.view_id,
+                blob_derivatives.name,
+                blob_derivatives.mime_type,
+                blob_derivatives.length
+            FROM
+                blob_derivatives
+            JOIN
+                documents
+            ON
+                documents.document_view_id = blob_derivatives.view_id
+            WHERE
+                documents.document_id = $1
+            ORDER BY
+                blob_derivatives.name
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))
+    }
+
+    /// Remove the records of all blobs derived from a blob view.
+    pub async fn delete_blob_derivatives(
+        &self,
+        view_id: &DocumentViewId,
+    ) -> Result<(), SqlStoreError> {
+        query(
+            "
+            DELETE FROM
+                blob_derivatives
+            WHERE
+                view_id = $1
+            ",
+        )
+        .bind(view_id.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
 }
 
 /// Throws an error when database does not contain all related blob pieces yet.
@@ -374,6 +821,7 @@ mod tests {
     use bytes::{BufMut, BytesMut};
     use futures::{pin_mut, StreamExt};
     use p2panda_rs::document::DocumentId;
+    use p2panda_rs::entry::traits::AsEncodedEntry;
     use p2panda_rs::identity::KeyPair;
     use p2panda_rs::schema::SchemaId;
     use p2panda_rs::test_utils::fixtures::{key_pair, random_document_view_id};
@@ -432,6 +880,42 @@ mod tests {
         })
     }
 
+    #[rstest]
+    fn get_blob_entries(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_view_id = add_blob(
+                &mut node,
+                "Hello, World!".as_bytes(),
+                6,
+                "text/plain",
+                &key_pair,
+            )
+            .await;
+            let document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            let entries = node
+                .context
+                .store
+                .get_blob_entries(&document_id)
+                .await
+                .unwrap();
+
+            // Entry of the blob document comes first, followed by the ones of its three pieces
+            assert_eq!(entries.len(), 4);
+            assert_eq!(entries[0].hash().to_string(), blob_view_id.to_string());
+            assert!(entries.iter().all(|entry| entry.payload().is_some()));
+
+            // Unknown blobs don't have any entries
+            let entries = node
+                .context
+                .store
+                .get_blob_entries(&random_document_view_id().to_string().parse().unwrap())
+                .await
+                .unwrap();
+            assert!(entries.is_empty());
+        })
+    }
+
     #[rstest]
     fn get_blob_errors(key_pair: KeyPair) {
         test_runner(|mut node: TestNode| async move {
@@ -533,6 +1017,75 @@ mod tests {
         })
     }
 
+    #[rstest]
+    fn get_blob_status(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_data = generate_random_bytes(12);
+
+            let blob_piece_view_id = add_document(
+                &mut node,
+                &SchemaId::BlobPiece(1),
+                vec![("data", blob_data[..6].into())],
+                &key_pair,
+            )
+            .await;
+            let missing_piece_view_id = random_document_view_id();
+
+            // Publish a blob with one piece that is in the store and one that isn't.
+            let blob_view_id = add_document(
+                &mut node,
+                &SchemaId::Blob(1),
+                vec![
+                    ("length", { blob_data.len() as i64 }.into()),
+                    ("mime_type", "text/plain".into()),
+                    (
+                        "pieces",
+                        vec![blob_piece_view_id, missing_piece_view_id.clone()].into(),
+                    ),
+                ],
+                &key_pair,
+            )
+            .await;
+            let blob_document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            let status = node
+                .context
+                .store
+                .get_blob_status(&blob_document_id)
+                .await
+                .unwrap()
+                .expect("Blob document exists");
+            assert_eq!(status.view_id, blob_view_id);
+            assert_eq!(status.length, 12);
+            assert_eq!(status.num_pieces, 2);
+            assert_eq!(status.missing_pieces, vec![missing_piece_view_id]);
+            assert!(!status.is_complete());
+
+            // Publish a complete blob
+            let blob_view_id = add_blob(&mut node, &blob_data, 6, "text/plain", &key_pair).await;
+            let blob_document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            let status = node
+                .context
+                .store
+                .get_blob_status(&blob_document_id)
+                .await
+                .unwrap()
+                .expect("Blob document exists");
+            assert_eq!(status.num_pieces, 2);
+            assert!(status.is_complete());
+
+            // Unknown documents do not have a status
+            let status = node
+                .context
+                .store
+                .get_blob_status(&random_document_view_id().to_string().parse().unwrap())
+                .await
+                .unwrap();
+            assert!(status.is_none());
+        })
+    }
+
     #[rstest]
     fn purge_blob(key_pair: KeyPair) {
         test_runner(|mut node: TestNode| async move {
@@ -654,6 +1207,58 @@ mod tests {
         })
     }
 
+    #[rstest]
+    fn get_exclusive_blob_relations(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_data = "Hello, World!".as_bytes();
+            let blob_view_id = add_blob(&mut node, blob_data, 7, "text/plain", &key_pair).await;
+            let shared_blob_view_id =
+                add_blob(&mut node, blob_data, 7, "text/plain", &key_pair).await;
+
+            let (_, view_ids) = add_schema_and_documents(
+                &mut node,
+                "img",
+                vec![
+                    vec![("blob", blob_view_id.clone().into(), Some(SchemaId::Blob(1)))],
+                    vec![(
+                        "blob",
+                        shared_blob_view_id.clone().into(),
+                        Some(SchemaId::Blob(1)),
+                    )],
+                    vec![(
+                        "blob",
+                        shared_blob_view_id.clone().into(),
+                        Some(SchemaId::Blob(1)),
+                    )],
+                ],
+                &key_pair,
+            )
+            .await;
+
+            // Only the first document refers to its blob exclusively
+            let document_id: DocumentId = view_ids[0].to_string().parse().unwrap();
+            let blob_ids = node
+                .context
+                .store
+                .get_exclusive_blob_relations(&document_id)
+                .await
+                .unwrap();
+            assert_eq!(
+                blob_ids,
+                vec![blob_view_id.to_string().parse::<DocumentId>().unwrap()]
+            );
+
+            let document_id: DocumentId = view_ids[1].to_string().parse().unwrap();
+            let blob_ids = node
+                .context
+                .store
+                .get_exclusive_blob_relations(&document_id)
+                .await
+                .unwrap();
+            assert!(blob_ids.is_empty());
+        })
+    }
+
     #[rstest]
     fn purge_all_pieces_of_updated_blob(key_pair: KeyPair) {
         test_runner(|mut node: TestNode| async move {
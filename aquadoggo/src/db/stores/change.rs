@@ -0,0 +1,256 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::storage_provider::error::DocumentStorageError;
+use sqlx::{query, query_as, query_scalar, Any, Transaction};
+
+use crate::db::models::DocumentChangeRow;
+use crate::db::SqlStore;
+use crate::materializer::DocumentChange;
+use crate::replication::now_millis;
+
+/// Methods to consume the feed of created, updated and deleted documents.
+///
+/// Every change of a document is recorded with a sequence number in the `document_changes` table.
+/// Sequence numbers increase in the order in which changes got committed, external indexers can
+/// resume consuming the feed after the last change they processed, also across restarts of the
+/// node.
+impl SqlStore {
+    /// Returns up to `limit` document changes recorded after the change with the given sequence
+    /// number, oldest first.
+    pub async fn get_document_changes(
+        &self,
+        since: u64,
+        limit: u64,
+    ) -> Result<Vec<DocumentChangeRow>, DocumentStorageError> {
+        query_as::<_, DocumentChangeRow>(
+            "
+            SELECT
+                sequence,
+                document_id,
+                document_view_id,
+                schema_id,
+                change_type,
+                changed_at
+            FROM
+                document_changes
+            WHERE
+                document_changes.sequence > $1
+            ORDER BY
+                document_changes.sequence ASC
+            LIMIT
+                $2
+            ",
+        )
+        .bind(since as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))
+    }
+}
+
+/// Records the change of a document in the `document_changes` table before it gets replaced by
+/// the given one, nothing is recorded if the state does not change.
+///
+/// The sequence number is taken from the `document_change_sequence` table. Incrementing it locks
+/// the row until the transaction is committed, so no change with a lower sequence number can
+/// become visible after one with a higher one.
+pub(super) async fn record_document_change(
+    tx: &mut Transaction<'_, Any>,
+    document: &impl AsDocument,
+) -> Result<(), DocumentStorageError> {
+    let is_unchanged: bool = query_scalar::<_, i64>(
+        "
+        SELECT
+            COUNT(*)
+        FROM
+            documents
+        WHERE
+            documents.document_id = $1
+            AND documents.document_view_id = $2
+            AND documents.is_deleted = $3
+        ",
+    )
+    .bind(document.id().as_str())
+    .bind(document.view_id().to_string())
+    .bind(document.is_deleted())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?
+        > 0;
+
+    if is_unchanged {
+        return Ok(());
+    }
+
+    query(
+        "
+        UPDATE
+            document_change_sequence
+        SET
+            sequence = sequence + 1
+        ",
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+    let sequence: i64 = query_scalar(
+        "
+        SELECT
+            sequence
+        FROM
+            document_change_sequence
+        ",
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+    query(
+        "
+        INSERT INTO
+            document_changes (
+                sequence,
+                document_id,
+                document_view_id,
+                schema_id,
+                change_type,
+                changed_at
+            )
+        VALUES
+            ($1, $2, $3, $4, $5, $6)
+        ",
+    )
+    .bind(sequence)
+    .bind(document.id().as_str())
+    .bind(document.view_id().to_string())
+    .bind(document.schema_id().to_string())
+    .bind(DocumentChange::of(document).to_string())
+    .bind(now_millis() as i64)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::storage_provider::traits::DocumentStore;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::test_utils::{
+        add_document, add_schema, delete_document, test_runner, update_document, TestNode,
+    };
+
+    #[rstest]
+    fn records_document_changes(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            // Creating the schema recorded changes of its definition documents
+            let changes = node
+                .context
+                .store
+                .get_document_changes(0, 100)
+                .await
+                .unwrap();
+            assert!(!changes.is_empty());
+            let since = changes.last().unwrap().sequence as u64;
+
+            let create_view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Pub".into()))],
+                &key_pair,
+            )
+            .await;
+            let document_id = DocumentId::new(create_view_id.graph_tips().first().unwrap());
+
+            // Inserting a document again without changing it is not recorded
+            let document = node
+                .context
+                .store
+                .get_document(&document_id)
+                .await
+                .unwrap()
+                .unwrap();
+            node.context.store.insert_document(&document).await.unwrap();
+
+            let update_view_id = update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Bar".into()))],
+                &create_view_id,
+                &key_pair,
+            )
+            .await;
+            let delete_view_id =
+                delete_document(&mut node, schema.id(), &update_view_id, &key_pair).await;
+
+            let changes = node
+                .context
+                .store
+                .get_document_changes(since, 100)
+                .await
+                .unwrap();
+            let changes: Vec<(u64, String, String, String)> = changes
+                .into_iter()
+                .map(|row| {
+                    (
+                        row.sequence as u64,
+                        row.document_id,
+                        row.document_view_id,
+                        row.change_type,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                changes,
+                vec![
+                    (
+                        since + 1,
+                        document_id.to_string(),
+                        create_view_id.to_string(),
+                        "create".to_string()
+                    ),
+                    (
+                        since + 2,
+                        document_id.to_string(),
+                        update_view_id.to_string(),
+                        "update".to_string()
+                    ),
+                    (
+                        since + 3,
+                        document_id.to_string(),
+                        delete_view_id.to_string(),
+                        "delete".to_string()
+                    ),
+                ]
+            );
+
+            // Changes can be consumed in pages
+            let page = node
+                .context
+                .store
+                .get_document_changes(since + 1, 1)
+                .await
+                .unwrap();
+            assert_eq!(page.len(), 1);
+            assert_eq!(page[0].sequence as u64, since + 2);
+        });
+    }
+}
@@ -0,0 +1,132 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::{query, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+
+/// Name of the lease held by the leader of a cluster.
+const LEADER_LEASE: &str = "leader";
+
+/// Methods to interact with the `cluster_leases` table in the database.
+///
+/// Instances of a cluster share one database and elect their leader with a lease stored in it.
+/// The lease needs to be renewed before it expires, otherwise another instance takes it over.
+impl SqlStore {
+    /// Acquires or renews the leader lease for the given instance until `expires_at`.
+    ///
+    /// Returns `false` if another instance holds an unexpired lease.
+    pub async fn acquire_cluster_lease(
+        &self,
+        instance: &str,
+        now: i64,
+        expires_at: i64,
+    ) -> Result<bool, SqlStoreError> {
+        let result = query(
+            "
+            INSERT INTO
+                cluster_leases (
+                    name,
+                    instance,
+                    expires_at
+                )
+            VALUES
+                ($1, $2, $3)
+            ON CONFLICT(name) DO UPDATE SET
+                instance = $2,
+                expires_at = $3
+            WHERE
+                cluster_leases.instance = $2
+                OR cluster_leases.expires_at <= $4
+            ",
+        )
+        .bind(LEADER_LEASE)
+        .bind(instance)
+        .bind(expires_at)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Gives up the leader lease when it is held by the given instance.
+    pub async fn release_cluster_lease(&self, instance: &str) -> Result<(), SqlStoreError> {
+        query(
+            "
+            DELETE FROM
+                cluster_leases
+            WHERE
+                name = $1
+                AND instance = $2
+            ",
+        )
+        .bind(LEADER_LEASE)
+        .bind(instance)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Returns the instance holding an unexpired leader lease.
+    pub async fn get_cluster_leader(&self, now: i64) -> Result<Option<String>, SqlStoreError> {
+        query_scalar(
+            "
+            SELECT
+                instance
+            FROM
+                cluster_leases
+            WHERE
+                name = $1
+                AND expires_at > $2
+            ",
+        )
+        .bind(LEADER_LEASE)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{test_runner, TestNode};
+
+    #[test]
+    fn elect_leader() {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+            assert_eq!(store.get_cluster_leader(0).await.unwrap(), None);
+
+            // First instance becomes leader, second one has to wait
+            assert!(store.acquire_cluster_lease("a", 0, 15).await.unwrap());
+            assert!(!store.acquire_cluster_lease("b", 5, 20).await.unwrap());
+            assert_eq!(
+                store.get_cluster_leader(5).await.unwrap(),
+                Some("a".to_string())
+            );
+
+            // Leader renews its lease
+            assert!(store.acquire_cluster_lease("a", 10, 25).await.unwrap());
+            assert!(!store.acquire_cluster_lease("b", 20, 35).await.unwrap());
+
+            // Second instance takes over when the lease expired
+            assert!(store.acquire_cluster_lease("b", 25, 40).await.unwrap());
+            assert!(!store.acquire_cluster_lease("a", 30, 45).await.unwrap());
+            assert_eq!(
+                store.get_cluster_leader(30).await.unwrap(),
+                Some("b".to_string())
+            );
+
+            // Only the leader can release the lease
+            store.release_cluster_lease("a").await.unwrap();
+            assert!(store.get_cluster_leader(30).await.unwrap().is_some());
+            store.release_cluster_lease("b").await.unwrap();
+            assert_eq!(store.get_cluster_leader(30).await.unwrap(), None);
+        });
+    }
+}
@@ -0,0 +1,419 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::{BTreeMap, HashSet};
+
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use sqlx::{query_as, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+use crate::materializer::{Task, TaskInput};
+
+/// Relation of a materialized document view to another document or document view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewRelation {
+    /// Name of the relation field.
+    pub field: String,
+
+    /// Id of the related document or, for pinned relations, of the related document view.
+    pub target: String,
+
+    /// True if this is a pinned relation (list) pointing at a document view.
+    pub is_pinned: bool,
+
+    /// True if the related document or document view is materialized on this node.
+    pub is_materialized: bool,
+}
+
+/// Materialized view of a document and how it is connected to other views.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewDependencies {
+    /// Id of the materialized document view.
+    pub view_id: DocumentViewId,
+
+    /// True if this is the current view of the document.
+    pub is_current: bool,
+
+    /// Relations of this view to other documents and views, in the order of their fields.
+    pub relations: Vec<ViewRelation>,
+
+    /// Materialized views pinning exactly this view.
+    pub referenced_by: Vec<DocumentViewId>,
+}
+
+/// Dependency graph of all materialized views of a document, used for debugging the
+/// materializer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyGraph {
+    /// Id of the document.
+    pub document_id: DocumentId,
+
+    /// Current view of the document or `None` if it was not materialized yet.
+    pub current_view_id: Option<DocumentViewId>,
+
+    /// All materialized views of this document.
+    pub views: Vec<ViewDependencies>,
+
+    /// Materialized views relating to this document without pinning one of its views.
+    pub referenced_by: Vec<DocumentViewId>,
+
+    /// Pending materializer tasks for this document or any of its views.
+    pub pending_tasks: Vec<Task<TaskInput>>,
+}
+
+fn parse_view_id(view_id: &str) -> DocumentViewId {
+    view_id
+        .parse()
+        .unwrap_or_else(|_| panic!("Invalid document view id stored in database: {}", view_id))
+}
+
+impl SqlStore {
+    /// Assemble the dependency graph of a document from the materialized views and pending tasks
+    /// in the database.
+    ///
+    /// This shows which views reference which and which tasks are still pending for them, helping
+    /// to debug documents which are stuck waiting for their dependencies.
+    ///
+    /// Returns `None` if no operations or views of this document are known.
+    pub async fn get_dependency_graph(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<Option<DependencyGraph>, SqlStoreError> {
+        let operation_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                operations_v1.operation_id
+            FROM
+                operations_v1
+            WHERE
+                operations_v1.document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let current_view_id: Option<String> = query_scalar(
+            "
+            SELECT
+                documents.document_view_id
+            FROM
+                documents
+            WHERE
+                documents.document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let view_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                document_views.document_view_id
+            FROM
+                document_views
+            WHERE
+                document_views.document_id = $1
+            ORDER BY
+                document_views.document_view_id
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        if operation_ids.is_empty() && view_ids.is_empty() {
+            return Ok(None);
+        }
+
+        let mut views: BTreeMap<String, ViewDependencies> = view_ids
+            .iter()
+            .map(|view_id| {
+                let dependencies = ViewDependencies {
+                    view_id: parse_view_id(view_id),
+                    is_current: current_view_id.as_ref() == Some(view_id),
+                    relations: Vec::new(),
+                    referenced_by: Vec::new(),
+                };
+                (view_id.to_owned(), dependencies)
+            })
+            .collect();
+
+        // Collect all relations of all materialized views of this document
+        let relations: Vec<(String, String, String, String)> = query_as(
+            "
+            SELECT
+                document_view_fields.document_view_id,
+                operation_fields_v1.name,
+                operation_fields_v1.field_type,
+                operation_fields_v1.value
+            FROM
+                document_view_fields
+            JOIN
+                document_views
+            ON
+                document_view_fields.document_view_id = document_views.document_view_id
+            LEFT JOIN
+                operation_fields_v1
+            ON
+                document_view_fields.operation_id = operation_fields_v1.operation_id
+            AND
+                document_view_fields.name = operation_fields_v1.name
+            WHERE
+                document_views.document_id = $1
+            AND
+                operation_fields_v1.field_type IN (
+                    'pinned_relation',
+                    'pinned_relation_list',
+                    'relation',
+                    'relation_list'
+                )
+            AND
+                operation_fields_v1.value IS NOT NULL
+            ORDER BY
+                operation_fields_v1.name, operation_fields_v1.list_index
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        // Check which of the related documents and views are materialized
+        let targets = relations
+            .iter()
+            .map(|(_, _, _, target)| format!("'{}'", target))
+            .collect::<Vec<String>>()
+            .join(",");
+
+        let materialized_targets: HashSet<String> = if targets.is_empty() {
+            HashSet::new()
+        } else {
+            query_scalar::<_, String>(&format!(
+                "
+                SELECT
+                    documents.document_id
+                FROM
+                    documents
+                WHERE
+                    documents.document_id IN ({targets})
+                UNION
+                SELECT
+                    document_views.document_view_id
+                FROM
+                    document_views
+                WHERE
+                    document_views.document_view_id IN ({targets})
+                "
+            ))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?
+            .into_iter()
+            .collect()
+        };
+
+        for (view_id, field, field_type, target) in relations {
+            if let Some(view) = views.get_mut(&view_id) {
+                view.relations.push(ViewRelation {
+                    field,
+                    is_pinned: field_type.starts_with("pinned_"),
+                    is_materialized: materialized_targets.contains(&target),
+                    target,
+                });
+            }
+        }
+
+        // Collect all views of other documents relating to this document or one of its views
+        let reverse_relations: Vec<(String, String)> = query_as(
+            "
+            SELECT DISTINCT
+                document_view_fields.document_view_id,
+                operation_fields_v1.value
+            FROM
+                document_view_fields
+            LEFT JOIN
+                operation_fields_v1
+            ON
+                document_view_fields.operation_id = operation_fields_v1.operation_id
+            AND
+                document_view_fields.name = operation_fields_v1.name
+            WHERE
+                operation_fields_v1.field_type IN (
+                    'pinned_relation',
+                    'pinned_relation_list',
+                    'relation',
+                    'relation_list'
+                )
+            AND (
+                operation_fields_v1.value = $1
+                OR operation_fields_v1.value IN (
+                    SELECT
+                        document_views.document_view_id
+                    FROM
+                        document_views
+                    WHERE
+                        document_views.document_id = $1
+                )
+            )
+            ORDER BY
+                document_view_fields.document_view_id
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let mut referenced_by = Vec::new();
+        for (parent_view_id, target) in reverse_relations {
+            match views.get_mut(&target) {
+                Some(view) => view.referenced_by.push(parse_view_id(&parent_view_id)),
+                None => referenced_by.push(parse_view_id(&parent_view_id)),
+            }
+        }
+
+        // Pick all pending tasks concerning this document or one of its (not yet materialized)
+        // views
+        let operation_ids: HashSet<String> = operation_ids.into_iter().collect();
+        let pending_tasks = self
+            .get_tasks()
+            .await?
+            .into_iter()
+            .filter(|task| match task.input() {
+                TaskInput::DocumentId(id) => id == document_id,
+                TaskInput::DocumentViewId(view_id) => {
+                    views.contains_key(&view_id.to_string())
+                        || view_id
+                            .iter()
+                            .any(|operation_id| operation_ids.contains(operation_id.as_str()))
+                }
+            })
+            .collect();
+
+        Ok(Some(DependencyGraph {
+            document_id: document_id.to_owned(),
+            current_view_id: current_view_id.as_deref().map(parse_view_id),
+            views: views.into_values().collect(),
+            referenced_by,
+            pending_tasks,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_id};
+    use rstest::rstest;
+
+    use crate::materializer::{Task, TaskInput};
+    use crate::test_utils::{add_document, add_schema, test_runner, TestNode};
+
+    #[rstest]
+    fn dependency_graph(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let venue_schema = add_schema(
+                &mut node,
+                "venue",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let venue_view_id = add_document(
+                &mut node,
+                venue_schema.id(),
+                vec![("name", "Panda Cafe".into())],
+                &key_pair,
+            )
+            .await;
+            let venue_id: DocumentId = venue_view_id.to_string().parse().unwrap();
+
+            let event_schema = add_schema(
+                &mut node,
+                "event",
+                vec![
+                    (
+                        "venue",
+                        FieldType::PinnedRelation(venue_schema.id().to_owned()),
+                    ),
+                    (
+                        "organiser",
+                        FieldType::Relation(venue_schema.id().to_owned()),
+                    ),
+                ],
+                &key_pair,
+            )
+            .await;
+            let missing_document_id = random_document_id();
+            let event_view_id = add_document(
+                &mut node,
+                event_schema.id(),
+                vec![
+                    ("venue", venue_view_id.clone().into()),
+                    ("organiser", missing_document_id.clone().into()),
+                ],
+                &key_pair,
+            )
+            .await;
+            let event_id: DocumentId = event_view_id.to_string().parse().unwrap();
+
+            let task = Task::new(
+                "dependency",
+                TaskInput::DocumentViewId(event_view_id.clone()),
+            );
+            node.context.store.insert_task(&task).await.unwrap();
+
+            // The event pins the venue and relates to a document we don't know
+            let graph = node
+                .context
+                .store
+                .get_dependency_graph(&event_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(graph.current_view_id, Some(event_view_id.clone()));
+            assert_eq!(graph.views.len(), 1);
+            assert!(graph.views[0].is_current);
+            assert_eq!(graph.pending_tasks, vec![task]);
+
+            let relations = &graph.views[0].relations;
+            assert_eq!(relations.len(), 2);
+            assert_eq!(relations[0].field, "organiser");
+            assert_eq!(relations[0].target, missing_document_id.to_string());
+            assert!(!relations[0].is_pinned);
+            assert!(!relations[0].is_materialized);
+            assert_eq!(relations[1].field, "venue");
+            assert_eq!(relations[1].target, venue_view_id.to_string());
+            assert!(relations[1].is_pinned);
+            assert!(relations[1].is_materialized);
+
+            // The venue view is pinned by the event
+            let graph = node
+                .context
+                .store
+                .get_dependency_graph(&venue_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(graph.views[0].referenced_by, vec![event_view_id]);
+            assert!(graph.referenced_by.is_empty());
+            assert!(graph.pending_tasks.is_empty());
+
+            // Unknown documents don't have a graph
+            assert!(node
+                .context
+                .store
+                .get_dependency_graph(&random_document_id())
+                .await
+                .unwrap()
+                .is_none());
+        })
+    }
+}
@@ -28,6 +28,8 @@
 //! view if it has already been materialised and stored. Although it is possible to construct a
 //! document at any point in its history if all operations are retained, we use a system of "pinned
 //! relations" to identify and materialise only views we explicitly wish to keep.
+use std::time::Duration;
+
 use async_trait::async_trait;
 use log::debug;
 use p2panda_rs::document::traits::AsDocument;
@@ -39,10 +41,20 @@ use sqlx::any::AnyQueryResult;
 use sqlx::{query, query_as, query_scalar, Any, Transaction};
 
 use crate::db::models::utils::parse_document_view_field_rows;
-use crate::db::models::{DocumentRow, DocumentViewFieldRow};
+use crate::db::models::{DocumentFieldsJoinedRow, DocumentRow, DocumentViewFieldRow};
+use crate::db::stores::change::record_document_change;
+use crate::db::stores::snapshot::record_document_history;
 use crate::db::types::StorageDocument;
 use crate::db::Pool;
 use crate::db::SqlStore;
+use crate::faults::{FaultOutcome, FaultPoint};
+use crate::replication::now_millis;
+
+/// Maximum number of `document_view_fields` rows inserted with one statement.
+///
+/// Each row takes three bind arguments, this keeps us below the limit of 999 arguments of older
+/// SQLite versions.
+const MAX_FIELD_ROWS_PER_INSERT: usize = 333;
 
 #[async_trait]
 impl DocumentStore for SqlStore {
@@ -58,6 +70,10 @@ impl DocumentStore for SqlStore {
         &self,
         id: &DocumentId,
     ) -> Result<Option<Self::Document>, DocumentStorageError> {
+        if self.faults.check(FaultPoint::GetDocument).await? == FaultOutcome::Drop {
+            return Ok(None);
+        }
+
         // Retrieve one row from the document table matching on the passed id.
         let document_row = query_as::<_, DocumentRow>(
             "
@@ -177,6 +193,16 @@ impl DocumentStore for SqlStore {
             None => return Ok(None),
         };
 
+        let schema_id: SchemaId = document_row.schema_id.parse().unwrap();
+
+        // Remember when historic views of schemas with a limited number of views were read, the
+        // least recently used ones get evicted first
+        if document_row.document_view_id != view_id.to_string()
+            && self.evicted_view_schemas.contains(&schema_id)
+        {
+            touch_document_view(&self.pool, view_id).await?;
+        }
+
         // We now want to retrieve the view (current key-value map) for this document, as we
         // already filtered out deleted documents in the query above we can expect all documents
         // we handle here to have an associated view in the database.
@@ -190,7 +216,7 @@ impl DocumentStore for SqlStore {
         let document = StorageDocument {
             id: document_row.document_id.parse().unwrap(),
             view_id: view_id.to_owned(), // Set to requested document view id, not the current
-            schema_id: document_row.schema_id.parse().unwrap(),
+            schema_id,
             fields: document_view_fields,
             author: document_row.public_key.parse().unwrap(),
             deleted: document_row.is_deleted,
@@ -292,32 +318,44 @@ impl SqlStore {
         &self,
         document: &impl AsDocument,
     ) -> Result<(), DocumentStorageError> {
-        // Start a transaction, any db insertions after this point, and before the `commit()` can
-        // be rolled back in the event of an error.
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
-
-        // Insert the document and view to the database, in the case of an error all insertions
-        // since the tx was instantiated above will be rolled back.
-        let result = insert_document(&mut tx, document).await;
+        if self.faults.check(FaultPoint::InsertDocument).await? == FaultOutcome::Drop {
+            return Ok(());
+        }
 
-        match result {
-            // Commit the tx here if no error occurred.
-            Ok(_) => tx
-                .commit()
+        // Insert the document and view in a transaction, it is retried on serialization failures
+        self.retry(|| async move {
+            // Start a transaction, any db insertions after this point, and before the `commit()`
+            // can be rolled back in the event of an error.
+            let mut tx = self
+                .begin()
                 .await
-                .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string())),
-            // Rollback here if an error occurred.
-            Err(err) => {
-                tx.rollback()
+                .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+
+            // Insert the document and view to the database, in the case of an error all
+            // insertions since the tx was instantiated above will be rolled back.
+            let result = insert_document(&mut tx, document, self.snapshot_ttl).await;
+
+            match result {
+                // Commit the tx here if no error occurred.
+                Ok(_) => tx
+                    .commit()
                     .await
-                    .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
-                Err(err)
+                    .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string())),
+                // Rollback here if an error occurred.
+                Err(err) => {
+                    tx.rollback()
+                        .await
+                        .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+                    Err(err)
+                }
             }
-        }
+        })
+        .await?;
+
+        // Cached views might be outdated, for example when the document got deleted
+        self.document_cache.invalidate(document.id());
+
+        Ok(())
     }
 
     /// Insert a document view into the database.
@@ -333,41 +371,255 @@ impl SqlStore {
         document_id: &DocumentId,
         schema_id: &SchemaId,
     ) -> Result<(), DocumentStorageError> {
-        // Start a transaction, any db insertions after this point, and before the `commit()`
-        // will be rolled back in the event of an error.
-        let mut tx = self
-            .pool
-            .begin()
+        self.retry(|| async move {
+            // Start a transaction, any db insertions after this point, and before the `commit()`
+            // will be rolled back in the event of an error.
+            let mut tx = self
+                .begin()
+                .await
+                .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+
+            // Insert the document view into the `document_views` table. Rollback insertions if an
+            // error occurs.
+            match insert_document_view(&mut tx, document_view, document_id, schema_id).await {
+                Ok(_) => (),
+                Err(err) => {
+                    tx.rollback()
+                        .await
+                        .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+                    return Err(err);
+                }
+            };
+
+            // Insert the document view fields into the `document_view_fields` table. Rollback
+            // insertions if an error occurs.
+            match insert_document_fields(&mut tx, document_view).await {
+                Ok(_) => (),
+                Err(err) => {
+                    tx.rollback()
+                        .await
+                        .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+                    return Err(err);
+                }
+            };
+
+            // Commit the tx here as no errors occurred.
+            tx.commit()
+                .await
+                .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))
+        })
+        .await
+    }
+
+    /// Retrieves many documents of a schema, with their most current views, in a single query.
+    ///
+    /// Deleted documents and documents which are not known to the store or follow another schema
+    /// are not included. The returned documents are not ordered.
+    ///
+    /// An error is returned only if a fatal database error occurs.
+    pub async fn get_documents_by_ids(
+        &self,
+        schema_id: &SchemaId,
+        ids: &[DocumentId],
+    ) -> Result<Vec<StorageDocument>, DocumentStorageError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let placeholders = (0..ids.len())
+            .map(|index| format!("${}", index + 2))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        // Retrieve all field rows of the current views of the requested documents at once
+        let sql = format!(
+            "
+            SELECT
+                documents.document_id,
+                documents.document_view_id,
+                documents.schema_id,
+                operations_v1.public_key,
+                document_view_fields.operation_id,
+                document_view_fields.name,
+                operation_fields_v1.list_index,
+                operation_fields_v1.field_type,
+                operation_fields_v1.value
+            FROM
+                documents
+            JOIN operations_v1
+                ON
+                    operations_v1.operation_id = documents.document_id
+            JOIN document_view_fields
+                ON
+                    document_view_fields.document_view_id = documents.document_view_id
+            JOIN operation_fields_v1
+                ON
+                    document_view_fields.operation_id = operation_fields_v1.operation_id
+                AND
+                    document_view_fields.name = operation_fields_v1.name
+            WHERE
+                documents.schema_id = $1
+                AND documents.is_deleted = false
+                AND documents.document_id IN ({placeholders})
+            ORDER BY
+                documents.document_id ASC, operation_fields_v1.list_index ASC
+            "
+        );
+
+        let mut document_query =
+            query_as::<_, DocumentFieldsJoinedRow>(&sql).bind(schema_id.to_string());
+        for id in ids {
+            document_query = document_query.bind(id.as_str());
+        }
+
+        let rows = document_query
+            .fetch_all(&self.pool)
             .await
             .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
 
-        // Insert the document view into the `document_views` table. Rollback insertions if an error occurs.
-        match insert_document_view(&mut tx, document_view, document_id, schema_id).await {
-            Ok(_) => (),
-            Err(err) => {
-                tx.rollback()
-                    .await
-                    .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
-                return Err(err);
+        // Group the field rows by document
+        let mut grouped_rows: Vec<Vec<DocumentFieldsJoinedRow>> = Vec::new();
+        for row in rows {
+            match grouped_rows.last_mut() {
+                Some(group) if group[0].document_id == row.document_id => group.push(row),
+                _ => grouped_rows.push(vec![row]),
             }
-        };
+        }
 
-        // Insert the document view fields into the `document_view_fields` table. Rollback
-        // insertions if an error occurs.
-        match insert_document_fields(&mut tx, document_view).await {
-            Ok(_) => (),
-            Err(err) => {
-                tx.rollback()
-                    .await
-                    .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
-                return Err(err);
-            }
-        };
+        let documents: Vec<StorageDocument> = grouped_rows
+            .into_iter()
+            .map(|rows| {
+                let first_row = rows[0].clone();
 
-        // Commit the tx here as no errors occurred.
-        tx.commit()
+                // This method assumes all values coming from the db are already validated and so
+                // unwraps where errors might occur.
+                let document_view_fields = parse_document_view_field_rows(
+                    rows.into_iter().map(DocumentViewFieldRow::from).collect(),
+                );
+
+                StorageDocument {
+                    id: first_row.document_id.parse().unwrap(),
+                    view_id: first_row.document_view_id.parse().unwrap(),
+                    schema_id: first_row.schema_id.parse().unwrap(),
+                    fields: Some(document_view_fields),
+                    author: first_row.public_key.parse().unwrap(),
+                    deleted: false,
+                }
+            })
+            .collect();
+
+        // Remember when historic views of schemas with a limited number of views were read, the
+        // least recently used ones get evicted first
+        let evicted_view_ids: Vec<DocumentViewId> = documents
+            .iter()
+            .filter(|document| self.evicted_view_schemas.contains(&document.schema_id))
+            .map(|document| document.view_id.to_owned())
+            .collect();
+        if !evicted_view_ids.is_empty() {
+            touch_historic_document_views(&self.pool, &evicted_view_ids).await?;
+        }
+
+        Ok(documents)
+    }
+
+    /// Retrieves many document views in a single query.
+    ///
+    /// Views of deleted documents and views which are not materialized in the store are not
+    /// included. The returned documents are not ordered.
+    ///
+    /// An error is returned only if a fatal database error occurs.
+    pub async fn get_documents_by_view_ids(
+        &self,
+        view_ids: &[DocumentViewId],
+    ) -> Result<Vec<StorageDocument>, DocumentStorageError> {
+        if view_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let placeholders = (0..view_ids.len())
+            .map(|index| format!("${}", index + 1))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        // Retrieve all field rows of the requested views at once
+        let sql = format!(
+            "
+            SELECT
+                document_views.document_id,
+                document_views.document_view_id,
+                documents.schema_id,
+                operations_v1.public_key,
+                document_view_fields.operation_id,
+                document_view_fields.name,
+                operation_fields_v1.list_index,
+                operation_fields_v1.field_type,
+                operation_fields_v1.value
+            FROM
+                document_views
+            JOIN documents
+                ON
+                    documents.document_id = document_views.document_id
+            JOIN operations_v1
+                ON
+                    operations_v1.operation_id = document_views.document_id
+            JOIN document_view_fields
+                ON
+                    document_view_fields.document_view_id = document_views.document_view_id
+            JOIN operation_fields_v1
+                ON
+                    document_view_fields.operation_id = operation_fields_v1.operation_id
+                AND
+                    document_view_fields.name = operation_fields_v1.name
+            WHERE
+                documents.is_deleted = false
+                AND document_views.document_view_id IN ({placeholders})
+            ORDER BY
+                document_views.document_view_id ASC, operation_fields_v1.list_index ASC
+            "
+        );
+
+        let mut document_query = query_as::<_, DocumentFieldsJoinedRow>(&sql);
+        for view_id in view_ids {
+            document_query = document_query.bind(view_id.to_string());
+        }
+
+        let rows = document_query
+            .fetch_all(&self.pool)
             .await
-            .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))
+            .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+
+        // Group the field rows by view
+        let mut grouped_rows: Vec<Vec<DocumentFieldsJoinedRow>> = Vec::new();
+        for row in rows {
+            match grouped_rows.last_mut() {
+                Some(group) if group[0].document_view_id == row.document_view_id => group.push(row),
+                _ => grouped_rows.push(vec![row]),
+            }
+        }
+
+        let documents = grouped_rows
+            .into_iter()
+            .map(|rows| {
+                let first_row = rows[0].clone();
+
+                // This method assumes all values coming from the db are already validated and so
+                // unwraps where errors might occur.
+                let document_view_fields = parse_document_view_field_rows(
+                    rows.into_iter().map(DocumentViewFieldRow::from).collect(),
+                );
+
+                StorageDocument {
+                    id: first_row.document_id.parse().unwrap(),
+                    view_id: first_row.document_view_id.parse().unwrap(),
+                    schema_id: first_row.schema_id.parse().unwrap(),
+                    fields: Some(document_view_fields),
+                    author: first_row.public_key.parse().unwrap(),
+                    deleted: false,
+                }
+            })
+            .collect();
+
+        Ok(documents)
     }
 
     /// Get the ids for all document views for a document which are currently materialized to the store.
@@ -482,11 +734,120 @@ impl SqlStore {
             .collect())
     }
 
+    /// Get a document from the database by `DocumentViewId`, using the in-memory cache of
+    /// recently requested views when enabled.
+    ///
+    /// This behaves like `get_document_by_view_id` and is meant for read-heavy callers, like
+    /// GraphQL resolvers requesting the same views over and over again.
+    pub async fn get_cached_document_by_view_id(
+        &self,
+        view_id: &DocumentViewId,
+    ) -> Result<Option<StorageDocument>, DocumentStorageError> {
+        if !self.document_cache.is_enabled() {
+            return self.get_document_by_view_id(view_id).await;
+        }
+
+        if let Some(document) = self.document_cache.get(view_id) {
+            return Ok(Some(document));
+        }
+
+        let generation = self.document_cache.generation();
+        let document = self.get_document_by_view_id(view_id).await?;
+        if let Some(document) = &document {
+            self.document_cache.insert(document, generation);
+        }
+
+        Ok(document)
+    }
+
+    /// Retrieves many document views like `get_documents_by_view_ids`, using the in-memory cache
+    /// of recently requested views when enabled.
+    ///
+    /// Only views missing in the cache are retrieved from the database, in a single query.
+    pub async fn get_cached_documents_by_view_ids(
+        &self,
+        view_ids: &[DocumentViewId],
+    ) -> Result<Vec<StorageDocument>, DocumentStorageError> {
+        if !self.document_cache.is_enabled() {
+            return self.get_documents_by_view_ids(view_ids).await;
+        }
+
+        let mut documents = Vec::with_capacity(view_ids.len());
+        let mut missing_view_ids = Vec::new();
+        for view_id in view_ids {
+            match self.document_cache.get(view_id) {
+                Some(document) => documents.push(document),
+                None => missing_view_ids.push(view_id.to_owned()),
+            }
+        }
+
+        let generation = self.document_cache.generation();
+        for document in self.get_documents_by_view_ids(&missing_view_ids).await? {
+            self.document_cache.insert(&document, generation);
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+
+    /// Retrieves many documents of a schema like `get_documents_by_ids`, using the in-memory cache
+    /// of recently requested views when enabled.
+    ///
+    /// Only the ids of the current views are looked up in the database, their documents are taken
+    /// from the cache if they are present there.
+    pub async fn get_cached_documents_by_ids(
+        &self,
+        schema_id: &SchemaId,
+        ids: &[DocumentId],
+    ) -> Result<Vec<StorageDocument>, DocumentStorageError> {
+        if !self.document_cache.is_enabled() || ids.is_empty() {
+            return self.get_documents_by_ids(schema_id, ids).await;
+        }
+
+        let placeholders = (0..ids.len())
+            .map(|index| format!("${}", index + 2))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let sql = format!(
+            "
+            SELECT
+                documents.document_view_id
+            FROM
+                documents
+            WHERE
+                documents.schema_id = $1
+                AND documents.is_deleted = false
+                AND documents.document_id IN ({placeholders})
+            "
+        );
+
+        let mut view_id_query = query_scalar::<_, String>(&sql).bind(schema_id.to_string());
+        for id in ids {
+            view_id_query = view_id_query.bind(id.as_str());
+        }
+
+        let view_ids: Vec<DocumentViewId> = view_id_query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?
+            .iter()
+            .map(|view_id| {
+                view_id
+                    .parse()
+                    .expect("Document view id's coming from the store should be valid")
+            })
+            .collect();
+
+        self.get_cached_documents_by_view_ids(&view_ids).await
+    }
+
     /// Attempt to remove a document view from the store. Returns a boolean which indicates if the
     /// removal took place.
     ///
     /// This operations only succeeds if the view is "dangling", meaning no other document view
-    /// exists which relates to this view, AND it is not the current view of any document.
+    /// exists which relates to this view, AND it is not the current view of any document, AND it
+    /// is not the state of a document at a snapshot which can still be queried.
     pub async fn prune_document_view(
         &self,
         document_view_id: &DocumentViewId,
@@ -519,6 +880,10 @@ impl SqlStore {
                     SELECT documents.document_id FROM documents
                     WHERE documents.document_view_id = $1
                 )
+                AND NOT EXISTS (
+                    SELECT document_history.document_id FROM document_history
+                    WHERE document_history.previous_view_id = $1
+                )
                 "
             )
             .bind(document_view_id.to_string())
@@ -528,6 +893,7 @@ impl SqlStore {
 
         // If any rows were affected the deletion went ahead.
         if result.rows_affected() > 0 {
+            self.document_cache.invalidate_view(document_view_id);
             debug!("Deleted view: {}", document_view_id);
             Ok(true)
         } else {
@@ -536,6 +902,93 @@ impl SqlStore {
         }
     }
 
+    /// Get the ids of historic views of a document beyond the `keep` most recently read ones,
+    /// starting with the least recently read view.
+    ///
+    /// The current view of the document and views which are part of a snapshot are never
+    /// included.
+    pub async fn get_least_recently_used_views(
+        &self,
+        document_id: &DocumentId,
+        keep: usize,
+    ) -> Result<Vec<DocumentViewId>, DocumentStorageError> {
+        let document_view_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                document_views.document_view_id
+            FROM
+                document_views
+            WHERE
+                document_views.document_id = $1
+            AND NOT EXISTS (
+                SELECT documents.document_id FROM documents
+                WHERE documents.document_view_id = document_views.document_view_id
+            )
+            AND NOT EXISTS (
+                SELECT document_history.document_id FROM document_history
+                WHERE document_history.previous_view_id = document_views.document_view_id
+            )
+            ORDER BY
+                document_views.accessed_at DESC,
+                document_views.document_view_id
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+        Ok(document_view_ids
+            .iter()
+            .skip(keep)
+            .rev()
+            .map(|view_id_str| {
+                view_id_str
+                    .parse::<DocumentViewId>()
+                    .expect("Document view id's coming from the store should be valid")
+            })
+            .collect())
+    }
+
+    /// Remove a historic document view from the store, even if other document views pin it.
+    /// Returns a boolean which indicates if the removal took place.
+    ///
+    /// Evicted views can be materialized again from their operations. The current view of a
+    /// document and views which are part of a snapshot are never removed.
+    pub async fn evict_document_view(
+        &self,
+        document_view_id: &DocumentViewId,
+    ) -> Result<bool, DocumentStorageError> {
+        let result = query(
+            "
+            DELETE FROM
+                document_views
+            WHERE
+                document_views.document_view_id = $1
+            AND NOT EXISTS (
+                SELECT documents.document_id FROM documents
+                WHERE documents.document_view_id = $1
+            )
+            AND NOT EXISTS (
+                SELECT document_history.document_id FROM document_history
+                WHERE document_history.previous_view_id = $1
+            )
+            ",
+        )
+        .bind(document_view_id.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+        if result.rows_affected() > 0 {
+            self.document_cache.invalidate_view(document_view_id);
+            debug!("Evicted view: {}", document_view_id);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     /// Check if this view is the current view of its document.
     pub async fn is_current_view(
         &self,
@@ -559,6 +1012,57 @@ impl SqlStore {
         Ok(document_view_id.is_some())
     }
 
+    /// Returns true if a document with this id was materialized and not deleted.
+    ///
+    /// Only looks up the primary key of the `documents` table, no fields are loaded.
+    pub async fn document_exists(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<bool, DocumentStorageError> {
+        let document_id: Option<String> = query_scalar(
+            "
+            SELECT
+                documents.document_id
+            FROM
+                documents
+            WHERE
+                documents.document_id = $1
+                AND documents.is_deleted = false
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+        Ok(document_id.is_some())
+    }
+
+    /// Returns true if a document view with this id was materialized.
+    ///
+    /// Only looks up the primary key of the `document_views` table, no fields are loaded.
+    pub async fn document_view_exists(
+        &self,
+        document_view_id: &DocumentViewId,
+    ) -> Result<bool, DocumentStorageError> {
+        let document_view_id: Option<String> = query_scalar(
+            "
+            SELECT
+                document_views.document_view_id
+            FROM
+                document_views
+            WHERE
+                document_views.document_view_id = $1
+            ",
+        )
+        .bind(document_view_id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+        Ok(document_view_id.is_some())
+    }
+
     /// Purge a document from the store by its id.
     ///
     /// This removes entries, operations and any materialized documents which exist.
@@ -618,11 +1122,65 @@ impl SqlStore {
         .await
         .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
 
+        // Delete rows from `document_activity`, `archived_documents`, `document_stats`,
+        // `blob_derivatives`, `document_history` and `document_changes` tables.
+        for table in [
+            "document_activity",
+            "archived_documents",
+            "document_stats",
+            "blob_derivatives",
+            "document_history",
+            "document_changes",
+        ] {
+            query(&format!(
+                "DELETE FROM {table} WHERE {table}.document_id = $1"
+            ))
+            .bind(document_id.to_string())
+            .fetch_all(&mut tx)
+            .await
+            .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+        }
+
         // Commit the transaction if all queries succeeded.
         tx.commit()
             .await
             .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
 
+        self.document_cache.invalidate(document_id);
+        self.log_cache.clear();
+
+        // Remove historical data from the archive database as well.
+        self.discard_archived_document(document_id)
+            .await
+            .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Remove the materialized document and all its views from the store while keeping its
+    /// operations.
+    ///
+    /// Materializing the document again results in the same state, this is used to replay the
+    /// materialization of a document for debugging.
+    pub async fn discard_materialized_document(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<(), DocumentStorageError> {
+        // Delete rows from `documents` table, this cascades up to `document_views` and
+        // `document_view_fields` tables.
+        query(
+            "
+            DELETE FROM documents
+            WHERE documents.document_id = $1
+            ",
+        )
+        .bind(document_id.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+
+        self.document_cache.invalidate(document_id);
+
         Ok(())
     }
 }
@@ -680,10 +1238,26 @@ async fn insert_document_fields(
     tx: &mut Transaction<'_, Any>,
     document_view: &DocumentView,
 ) -> Result<Vec<AnyQueryResult>, DocumentStorageError> {
-    let mut results = Vec::with_capacity(document_view.len());
+    let document_view_id = document_view.id().to_string();
+    let rows: Vec<(&String, String)> = document_view
+        .iter()
+        .map(|(name, value)| (name, value.id().to_string()))
+        .collect();
+
+    let mut results = Vec::with_capacity(rows.len() / MAX_FIELD_ROWS_PER_INSERT + 1);
+
+    // Insert all field rows with as few statements as possible, only splitting them up when
+    // exceeding the maximum number of bind arguments the database allows per statement.
+    for batch in rows.chunks(MAX_FIELD_ROWS_PER_INSERT) {
+        let values = (0..batch.len())
+            .map(|index| {
+                let offset = index * 3;
+                format!("(${}, ${}, ${})", offset + 1, offset + 2, offset + 3)
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
 
-    for (name, value) in document_view.iter() {
-        let result = query(
+        let sql = format!(
             "
             INSERT INTO
                 document_view_fields (
@@ -692,15 +1266,24 @@ async fn insert_document_fields(
                     name
                 )
             VALUES
-                ($1, $2, $3)
-            ",
-        )
-        .bind(document_view.id().to_string())
-        .bind(value.id().as_str().to_owned())
-        .bind(name)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+                {values}
+            ON CONFLICT (document_view_id, name) DO UPDATE SET
+                operation_id = excluded.operation_id
+            "
+        );
+
+        let mut batch_query = query(&sql);
+        for (name, operation_id) in batch {
+            batch_query = batch_query
+                .bind(document_view_id.as_str())
+                .bind(operation_id.as_str())
+                .bind(name.as_str());
+        }
+
+        let result = batch_query
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
 
         results.push(result);
     }
@@ -708,6 +1291,72 @@ async fn insert_document_fields(
     Ok(results)
 }
 
+// Helper method for recording the time a document view was read.
+async fn touch_document_view(
+    pool: &Pool,
+    view_id: &DocumentViewId,
+) -> Result<(), DocumentStorageError> {
+    query(
+        "
+        UPDATE
+            document_views
+        SET
+            accessed_at = $2
+        WHERE
+            document_view_id = $1
+        ",
+    )
+    .bind(view_id.to_string())
+    .bind(now_millis() as i64)
+    .execute(pool)
+    .await
+    .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+
+    Ok(())
+}
+
+// Helper method for recording the time many document views were read, current views are skipped.
+async fn touch_historic_document_views(
+    pool: &Pool,
+    view_ids: &[DocumentViewId],
+) -> Result<(), DocumentStorageError> {
+    let placeholders = (0..view_ids.len())
+        .map(|index| format!("${}", index + 2))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let sql = format!(
+        "
+        UPDATE
+            document_views
+        SET
+            accessed_at = $1
+        WHERE
+            document_view_id IN ({placeholders})
+            AND NOT EXISTS (
+                SELECT
+                    1
+                FROM
+                    documents
+                WHERE
+                    documents.document_view_id = document_views.document_view_id
+            )
+        "
+    );
+
+    let mut touch_query = query(&sql).bind(now_millis() as i64);
+    for view_id in view_ids {
+        touch_query = touch_query.bind(view_id.to_string());
+    }
+
+    touch_query
+        .execute(pool)
+        .await
+        .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))?;
+
+    Ok(())
+}
+
 // Helper method for inserting document views into the `document_views` table.
 async fn insert_document_view(
     tx: &mut Transaction<'_, Any>,
@@ -721,27 +1370,38 @@ async fn insert_document_view(
             document_views (
                 document_view_id,
                 document_id,
-                schema_id
+                schema_id,
+                accessed_at
             )
         VALUES
-            ($1, $2, $3)
+            ($1, $2, $3, $4)
         ON CONFLICT DO NOTHING
         ",
     )
     .bind(document_view.id().to_string())
     .bind(document_id.to_string())
     .bind(schema_id.to_string())
+    .bind(now_millis() as i64)
     .execute(tx)
     .await
     .map_err(|e| DocumentStorageError::FatalStorageError(e.to_string()))
 }
 
 // Helper method for inserting documents into the database. For this, insertions are made in the
-// `documents`, `document_views` and `document_view_fields` tables.
+// `documents`, `document_views` and `document_view_fields` tables. The change is recorded in the
+// `document_changes` table and the previous state of the document in the `document_history`
+// table when snapshots are enabled.
 async fn insert_document(
     tx: &mut Transaction<'_, Any>,
     document: &impl AsDocument,
+    snapshot_ttl: Option<Duration>,
 ) -> Result<(), DocumentStorageError> {
+    if let Some(ttl) = snapshot_ttl {
+        record_document_history(&mut *tx, document, ttl).await?;
+    }
+
+    record_document_change(&mut *tx, document).await?;
+
     // Insert or update the document to the `documents` table.
     query(
         "
@@ -791,6 +1451,9 @@ async fn insert_document(
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
     use p2panda_rs::api::next_args;
     use p2panda_rs::document::traits::AsDocument;
     use p2panda_rs::document::{DocumentBuilder, DocumentId, DocumentViewFields, DocumentViewId};
@@ -805,15 +1468,26 @@ mod tests {
     };
     use p2panda_rs::WithId;
     use rstest::rstest;
+    use sqlx::{query, query_scalar};
 
     use crate::db::stores::document::DocumentView;
+    use crate::db::SqlStore;
     use crate::materializer::tasks::reduce_task;
     use crate::materializer::TaskInput;
     use crate::test_utils::{
         add_schema_and_documents, assert_query, doggo_schema, populate_and_materialize,
-        populate_store, populate_store_config, test_runner, PopulateStoreConfig, TestNode,
+        populate_store, populate_store_config, test_runner, update_document, PopulateStoreConfig,
+        TestNode,
     };
 
+    async fn accessed_at(store: &SqlStore, view_id: &DocumentViewId) -> i64 {
+        query_scalar("SELECT accessed_at FROM document_views WHERE document_view_id = $1")
+            .bind(view_id.to_string())
+            .fetch_one(&store.pool)
+            .await
+            .unwrap()
+    }
+
     #[rstest]
     fn insert_and_get_one_document_view(
         #[from(populate_store_config)]
@@ -913,6 +1587,182 @@ mod tests {
         });
     }
 
+    #[rstest]
+    fn insert_document_view_twice(
+        #[from(populate_store_config)]
+        #[with(2, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            let document = documents.first().expect("At least one document");
+
+            // Insert the document and its current view a second time
+            node.context.store.insert_document(document).await.unwrap();
+            node.context
+                .store
+                .insert_document_view(
+                    &document.view().unwrap(),
+                    document.id(),
+                    document.schema_id(),
+                )
+                .await
+                .unwrap();
+
+            // Field rows were not duplicated
+            let field_rows: i64 = query_scalar(
+                "SELECT COUNT(*) FROM document_view_fields WHERE document_view_id = $1",
+            )
+            .bind(document.view_id().to_string())
+            .fetch_one(&node.context.store.pool)
+            .await
+            .unwrap();
+            assert_eq!(field_rows as usize, document.fields().unwrap().len());
+
+            let retrieved_document = node
+                .context
+                .store
+                .get_document_by_view_id(document.view_id())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(retrieved_document.fields(), document.fields());
+        });
+    }
+
+    #[rstest]
+    fn caches_document_views(
+        #[from(populate_store_config)]
+        #[with(2, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let store = node.context.store.clone().with_document_cache(8);
+            let documents = populate_store(&store, &config).await;
+            let document = documents.first().expect("At least one document");
+            store.insert_document(document).await.unwrap();
+
+            let cached_document = store
+                .get_cached_document_by_view_id(document.view_id())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(cached_document.fields(), document.fields());
+
+            // Cached views are served without asking the database
+            query("DELETE FROM document_view_fields WHERE document_view_id = $1")
+                .bind(document.view_id().to_string())
+                .execute(&store.pool)
+                .await
+                .unwrap();
+            let cached_document = store
+                .get_cached_document_by_view_id(document.view_id())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(cached_document.fields(), document.fields());
+
+            // Changing the document invalidates its cached views
+            store.purge_document(document.id()).await.unwrap();
+            assert!(store
+                .get_cached_document_by_view_id(document.view_id())
+                .await
+                .unwrap()
+                .is_none());
+        });
+    }
+
+    #[rstest]
+    fn records_reads_of_evicted_views(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let (schema, view_ids) = add_schema_and_documents(
+                &mut node,
+                "profile",
+                vec![vec![("name", "panda".into(), None)]],
+                &key_pair,
+            )
+            .await;
+            let historic_view_id = view_ids[0].clone();
+            update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Panda".into())],
+                &historic_view_id,
+                &key_pair,
+            )
+            .await;
+
+            // Reads of views with an unlimited number of views are not recorded
+            let store = node.context.store.clone();
+            let inserted_at = accessed_at(&store, &historic_view_id).await;
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            store
+                .get_document_by_view_id(&historic_view_id)
+                .await
+                .unwrap();
+            store
+                .get_documents_by_view_ids(&[historic_view_id.clone()])
+                .await
+                .unwrap();
+            assert_eq!(accessed_at(&store, &historic_view_id).await, inserted_at);
+
+            // Reads of views which get evicted are recorded
+            let store = store.with_view_eviction(HashSet::from([schema.id().to_owned()]));
+            store
+                .get_document_by_view_id(&historic_view_id)
+                .await
+                .unwrap();
+            let read_at = accessed_at(&store, &historic_view_id).await;
+            assert!(read_at > inserted_at);
+
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            store
+                .get_documents_by_view_ids(&[historic_view_id.clone()])
+                .await
+                .unwrap();
+            assert!(accessed_at(&store, &historic_view_id).await > read_at);
+        });
+    }
+
+    #[rstest]
+    fn gets_many_document_views(
+        #[from(populate_store_config)]
+        #[with(2, 2, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        random_document_view_id: DocumentViewId,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            for document in &documents {
+                node.context.store.insert_document(document).await.unwrap();
+            }
+
+            // Views which are not in the store are not included
+            let mut view_ids: Vec<DocumentViewId> = documents
+                .iter()
+                .map(|document| document.view_id().to_owned())
+                .collect();
+            view_ids.push(random_document_view_id);
+
+            let retrieved_documents = node
+                .context
+                .store
+                .get_cached_documents_by_view_ids(&view_ids)
+                .await
+                .unwrap();
+            assert_eq!(retrieved_documents.len(), documents.len());
+
+            for document in &documents {
+                let retrieved_document = retrieved_documents
+                    .iter()
+                    .find(|retrieved| retrieved.view_id() == document.view_id())
+                    .expect("Document view should be retrieved");
+                assert_eq!(retrieved_document.id(), document.id());
+                assert_eq!(retrieved_document.fields(), document.fields());
+            }
+        });
+    }
+
     #[rstest]
     fn document_view_does_not_exist(random_document_view_id: DocumentViewId) {
         test_runner(|node: TestNode| async move {
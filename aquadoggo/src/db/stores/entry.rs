@@ -17,6 +17,8 @@ use sqlx::{query, query_as};
 use crate::db::models::{EntryRow, LogHeightRow};
 use crate::db::types::StorageEntry;
 use crate::db::SqlStore;
+use crate::faults::{FaultOutcome, FaultPoint};
+use crate::replication::now;
 
 /// Implementation of `EntryStore` trait which is required when constructing a `StorageProvider`.
 ///
@@ -36,6 +38,10 @@ impl EntryStore for SqlStore {
         encoded_entry: &EncodedEntry,
         encoded_operation: Option<&EncodedOperation>,
     ) -> Result<(), EntryStorageError> {
+        if self.faults.check(FaultPoint::InsertEntry).await? == FaultOutcome::Drop {
+            return Ok(());
+        }
+
         let insert_entry_result = query(
             "
             INSERT INTO
@@ -46,10 +52,11 @@ impl EntryStore for SqlStore {
                     log_id,
                     payload_bytes,
                     payload_hash,
-                    seq_num
+                    seq_num,
+                    inserted_at
                 )
             VALUES
-                ($1, $2, $3, $4, $5, $6, $7)
+                ($1, $2, $3, $4, $5, $6, $7, $8)
             ",
         )
         .bind(entry.public_key().to_string())
@@ -59,6 +66,7 @@ impl EntryStore for SqlStore {
         .bind(encoded_operation.map(|payload| payload.to_string()))
         .bind(entry.payload_hash().as_str())
         .bind(entry.seq_num().as_u64().to_string())
+        .bind(now() as i64)
         .execute(&self.pool)
         .await
         .map_err(|e| EntryStorageError::Custom(e.to_string()))?;
@@ -70,6 +78,9 @@ impl EntryStore for SqlStore {
             )));
         }
 
+        self.log_cache
+            .on_inserted(&StorageEntry::new(entry, encoded_entry, encoded_operation));
+
         Ok(())
     }
 
@@ -79,6 +90,10 @@ impl EntryStore for SqlStore {
     /// Returns `None` if the entry was not found in storage. Errors when a fatal storage error
     /// occured.
     async fn get_entry(&self, hash: &Hash) -> Result<Option<StorageEntry>, EntryStorageError> {
+        if self.faults.check(FaultPoint::GetEntry).await? == FaultOutcome::Drop {
+            return Ok(None);
+        }
+
         let entry_row = query_as::<_, EntryRow>(
             "
             SELECT
@@ -114,6 +129,15 @@ impl EntryStore for SqlStore {
         log_id: &LogId,
         seq_num: &SeqNum,
     ) -> Result<Option<StorageEntry>, EntryStorageError> {
+        if self.faults.check(FaultPoint::GetEntryAtSeqNum).await? == FaultOutcome::Drop {
+            return Ok(None);
+        }
+
+        if let Some(entry) = self.log_cache.get_at_seq_num(public_key, log_id, seq_num) {
+            return Ok(Some(entry));
+        }
+        let generation = self.log_cache.generation();
+
         let entry_row = query_as::<_, EntryRow>(
             "
             SELECT
@@ -139,7 +163,12 @@ impl EntryStore for SqlStore {
         .await
         .map_err(|e| EntryStorageError::Custom(e.to_string()))?;
 
-        Ok(entry_row.map(|row| row.into()))
+        let entry: Option<StorageEntry> = entry_row.map(|row| row.into());
+        if let Some(entry) = &entry {
+            self.log_cache.insert(entry, generation);
+        }
+
+        Ok(entry)
     }
 
     /// Get the latest entry in the log of a public key.
@@ -152,6 +181,15 @@ impl EntryStore for SqlStore {
         public_key: &PublicKey,
         log_id: &LogId,
     ) -> Result<Option<StorageEntry>, EntryStorageError> {
+        if self.faults.check(FaultPoint::GetLatestEntry).await? == FaultOutcome::Drop {
+            return Ok(None);
+        }
+
+        if let Some(entry) = self.log_cache.get_latest(public_key, log_id) {
+            return Ok(Some(entry));
+        }
+        let generation = self.log_cache.generation();
+
         let entry_row = query_as::<_, EntryRow>(
             "
             SELECT
@@ -179,11 +217,19 @@ impl EntryStore for SqlStore {
         .await
         .map_err(|e| EntryStorageError::Custom(e.to_string()))?;
 
-        Ok(entry_row.map(|row| row.into()))
+        let entry: Option<StorageEntry> = entry_row.map(|row| row.into());
+        if let Some(entry) = &entry {
+            self.log_cache.insert_latest(entry, generation);
+        }
+
+        Ok(entry)
     }
 }
 
 impl SqlStore {
+    /// Returns the heights of all logs of the given documents, grouped by public key.
+    ///
+    /// Entries of batches which are still being published are left out.
     pub async fn get_document_log_heights(
         &self,
         document_ids: &[DocumentId],
@@ -213,6 +259,15 @@ impl SqlStore {
                     AND entries.public_key = logs.public_key
             WHERE
                 logs.document IN ({document_ids_str})
+                -- entries of batches which are still being published are not shared yet
+                AND NOT EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        pending_batch_entries
+                    WHERE
+                        pending_batch_entries.entry_hash = entries.entry_hash
+                )
             GROUP BY
                 entries.public_key, entries.log_id
             ORDER BY
@@ -254,6 +309,10 @@ impl SqlStore {
         Ok(log_heights.into_iter().collect())
     }
 
+    /// Returns all entries of a log starting at the given sequence number.
+    ///
+    /// Entries of batches which are still being published are left out, they must not be
+    /// replicated before the whole batch got published.
     pub async fn get_entries_from(
         &self,
         public_key: &PublicKey,
@@ -276,6 +335,90 @@ impl SqlStore {
                 public_key = $1
                 AND log_id = $2
                 AND CAST(seq_num AS NUMERIC) >= CAST($3 AS NUMERIC)
+                -- entries of batches which are still being published are not shared yet
+                AND NOT EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        pending_batch_entries
+                    WHERE
+                        pending_batch_entries.entry_hash = entries.entry_hash
+                )
+            ORDER BY
+                CAST(seq_num AS NUMERIC)
+            ",
+        )
+        .bind(public_key.to_string())
+        .bind(log_id.as_u64().to_string())
+        .bind(seq_num.as_u64().to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EntryStorageError::Custom(e.to_string()))?;
+
+        Ok(entries.into_iter().map(|row| row.into()).collect())
+    }
+
+    /// Returns a page of entries of a log starting at the given sequence number.
+    ///
+    /// Pages are bounded by the size of the entries and their operations in bytes instead of their
+    /// number, they hold as many entries as fit into `max_bytes` but at least one, even when it is
+    /// larger than that. An empty page is returned when there are no more entries.
+    ///
+    /// Entries of batches which are still being published are left out.
+    pub async fn get_paginated_log_entries(
+        &self,
+        public_key: &PublicKey,
+        log_id: &LogId,
+        seq_num: &SeqNum,
+        max_bytes: usize,
+    ) -> Result<Vec<StorageEntry>, EntryStorageError> {
+        // Entries and operations are stored hex-encoded, every byte takes two characters
+        let max_length = max_bytes.saturating_mul(2).min(i64::MAX as usize) as i64;
+
+        let entries = query_as::<_, EntryRow>(
+            "
+            SELECT
+                public_key,
+                entry_bytes,
+                entry_hash,
+                log_id,
+                payload_bytes,
+                payload_hash,
+                seq_num
+            FROM (
+                SELECT
+                    public_key,
+                    entry_bytes,
+                    entry_hash,
+                    log_id,
+                    payload_bytes,
+                    payload_hash,
+                    seq_num,
+                    SUM(LENGTH(entry_bytes) + COALESCE(LENGTH(payload_bytes), 0)) OVER (
+                        ORDER BY CAST(seq_num AS NUMERIC)
+                    ) AS page_length,
+                    ROW_NUMBER() OVER (
+                        ORDER BY CAST(seq_num AS NUMERIC)
+                    ) AS page_position
+                FROM
+                    entries
+                WHERE
+                    public_key = $1
+                    AND log_id = $2
+                    AND CAST(seq_num AS NUMERIC) >= CAST($3 AS NUMERIC)
+                    -- entries of batches which are still being published are not shared yet
+                    AND NOT EXISTS (
+                        SELECT
+                            1
+                        FROM
+                            pending_batch_entries
+                        WHERE
+                            pending_batch_entries.entry_hash = entries.entry_hash
+                    )
+            ) AS page
+            WHERE
+                page_length <= $4
+                OR page_position = 1
             ORDER BY
                 CAST(seq_num AS NUMERIC)
             ",
@@ -283,6 +426,7 @@ impl SqlStore {
         .bind(public_key.to_string())
         .bind(log_id.as_u64().to_string())
         .bind(seq_num.as_u64().to_string())
+        .bind(max_length)
         .fetch_all(&self.pool)
         .await
         .map_err(|e| EntryStorageError::Custom(e.to_string()))?;
@@ -293,6 +437,7 @@ impl SqlStore {
 
 #[cfg(test)]
 mod tests {
+    use p2panda_rs::entry::encode::{encode_entry, sign_entry};
     use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
     use p2panda_rs::entry::{EncodedEntry, Entry, EntryBuilder, LogId, SeqNum};
     use p2panda_rs::identity::KeyPair;
@@ -301,6 +446,7 @@ mod tests {
     use p2panda_rs::test_utils::fixtures::{encoded_entry, encoded_operation, entry, random_hash};
     use rstest::rstest;
 
+    use crate::db::types::StorageEntry;
     use crate::test_utils::{
         populate_store, populate_store_config, test_runner, PopulateStoreConfig, TestNode,
     };
@@ -441,6 +587,91 @@ mod tests {
         });
     }
 
+    #[rstest]
+    fn cached_log_state(
+        #[from(populate_store_config)]
+        #[with(5, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        encoded_operation: EncodedOperation,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let store = node.context.store.clone().with_log_cache(8);
+            let _ = populate_store(&store, &config).await;
+
+            let key_pair = config.authors.first().expect("At least one key pair");
+            let public_key = key_pair.public_key();
+            let log_id = LogId::default();
+            let seq_num = |seq_num: u64| SeqNum::new(seq_num).unwrap();
+
+            // Entries are cached after they were looked up once
+            let latest_entry = store
+                .get_latest_entry(&public_key, &log_id)
+                .await
+                .expect("Get latest entry")
+                .expect("Unwrap entry");
+            assert_eq!(latest_entry.seq_num(), &seq_num(5));
+            assert_eq!(
+                store.log_cache.get_latest(&public_key, &log_id),
+                Some(latest_entry.clone())
+            );
+
+            let skiplink_entry = store
+                .get_entry_at_seq_num(&public_key, &log_id, &seq_num(1))
+                .await
+                .expect("Get entry")
+                .expect("Unwrap entry");
+            assert_eq!(
+                store
+                    .log_cache
+                    .get_at_seq_num(&public_key, &log_id, &seq_num(1)),
+                Some(skiplink_entry)
+            );
+
+            // Removing an entry invalidates the cache
+            store.remove_entry(&latest_entry.hash()).await.unwrap();
+            assert!(store.log_cache.get_latest(&public_key, &log_id).is_none());
+
+            let backlink = store
+                .get_latest_entry(&public_key, &log_id)
+                .await
+                .expect("Get latest entry")
+                .expect("Unwrap entry");
+            assert_eq!(backlink.seq_num(), &seq_num(4));
+
+            // Inserted entries following the cached latest entry of their log become the latest
+            // entry, the previous one stays cached
+            let entry = sign_entry(
+                &log_id,
+                &seq_num(5),
+                None,
+                Some(&backlink.hash()),
+                &encoded_operation,
+                key_pair,
+            )
+            .unwrap();
+            let encoded_entry = encode_entry(&entry).unwrap();
+            store
+                .insert_entry(&entry, &encoded_entry, Some(&encoded_operation))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                store
+                    .log_cache
+                    .get_latest(&public_key, &log_id)
+                    .map(|entry| entry.hash()),
+                Some(encoded_entry.hash())
+            );
+            assert_eq!(
+                store
+                    .get_entry_at_seq_num(&public_key, &log_id, &seq_num(4))
+                    .await
+                    .expect("Get entry"),
+                Some(backlink)
+            );
+        });
+    }
+
     #[rstest]
     fn entry_by_seq_number(
         #[from(populate_store_config)]
@@ -592,4 +823,79 @@ mod tests {
             assert_eq!(entries.len(), 11);
         });
     }
+
+    #[rstest]
+    fn get_paginated_log_entries(
+        #[from(populate_store_config)]
+        #[with(20, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let _ = populate_store(&node.context.store, &config).await;
+            let public_key = config.authors[0].public_key();
+            let entries = node
+                .context
+                .store
+                .get_entries_from(&public_key, &LogId::default(), &SeqNum::default())
+                .await
+                .unwrap();
+
+            let size = |entry: &StorageEntry| {
+                let operation_size = entry.payload().map_or(0, |payload| payload.size());
+                (entry.clone().encoded_entry.size() + operation_size) as usize
+            };
+
+            // Pages hold as many entries as fit into the byte budget
+            let max_bytes = entries[..3].iter().map(size).sum::<usize>();
+            let page = node
+                .context
+                .store
+                .get_paginated_log_entries(
+                    &public_key,
+                    &LogId::default(),
+                    &SeqNum::default(),
+                    max_bytes,
+                )
+                .await
+                .unwrap();
+            assert_eq!(page, entries[..3]);
+
+            // The next page continues where the previous one ended
+            let page = node
+                .context
+                .store
+                .get_paginated_log_entries(
+                    &public_key,
+                    &LogId::default(),
+                    &SeqNum::new(4).unwrap(),
+                    max_bytes + 1,
+                )
+                .await
+                .unwrap();
+            assert_eq!(page[0], entries[3]);
+
+            // Entries larger than the budget are returned on their own
+            let page = node
+                .context
+                .store
+                .get_paginated_log_entries(&public_key, &LogId::default(), &SeqNum::default(), 0)
+                .await
+                .unwrap();
+            assert_eq!(page, entries[..1]);
+
+            // Pages after the end of the log are empty
+            let page = node
+                .context
+                .store
+                .get_paginated_log_entries(
+                    &public_key,
+                    &LogId::default(),
+                    &SeqNum::new(21).unwrap(),
+                    max_bytes,
+                )
+                .await
+                .unwrap();
+            assert!(page.is_empty());
+        });
+    }
 }
@@ -0,0 +1,242 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::entry::LogId;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::storage_provider::traits::EntryStore;
+use sqlx::{query, query_as, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::models::LogForkRow;
+use crate::db::SqlStore;
+use crate::replication::now;
+
+/// Methods to interact with the `log_forks` table in the database.
+///
+/// Logs are append-only and only one device should ever write to them. When two devices use the
+/// same key pair they sooner or later publish different entries at the same position of a log.
+/// These forks are recorded here so node operators and clients can learn about them.
+impl SqlStore {
+    /// Checks if the given entry conflicts with the log we hold locally and records the fork.
+    ///
+    /// An entry conflicts when we already hold a different entry at its sequence number while
+    /// both extend the same log, that is its backlink points at the entry we hold right before
+    /// it. Entries with unknown backlinks are plain invalid entries and not considered here.
+    ///
+    /// Returns the recorded fork or `None` if the entry does not conflict with our log.
+    pub async fn detect_log_fork(
+        &self,
+        entry: &impl AsEntry,
+        entry_hash: &Hash,
+    ) -> Result<Option<LogForkRow>, SqlStoreError> {
+        let existing = match self
+            .get_entry_at_seq_num(entry.public_key(), entry.log_id(), entry.seq_num())
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?
+        {
+            Some(existing) if existing.hash() != *entry_hash => existing,
+            _ => return Ok(None),
+        };
+
+        let previous_hash = match entry.seq_num().prev() {
+            Some(previous_seq_num) => self
+                .get_entry_at_seq_num(entry.public_key(), entry.log_id(), &previous_seq_num)
+                .await
+                .map_err(|err| SqlStoreError::Transaction(err.to_string()))?
+                .map(|previous| previous.hash()),
+            None => None,
+        };
+
+        if entry.backlink() != previous_hash.as_ref() {
+            return Ok(None);
+        }
+
+        self.insert_log_fork(entry, &existing.hash(), entry_hash)
+            .await
+            .map(Some)
+    }
+
+    /// Returns true if a fork was recorded for this log.
+    pub async fn is_log_forked(
+        &self,
+        public_key: &PublicKey,
+        log_id: &LogId,
+    ) -> Result<bool, SqlStoreError> {
+        let count: i64 = query_scalar(
+            "
+            SELECT
+                COUNT(*)
+            FROM
+                log_forks
+            WHERE
+                public_key = $1
+                AND log_id = $2
+            ",
+        )
+        .bind(public_key.to_string())
+        .bind(log_id.as_u64().to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(count > 0)
+    }
+
+    /// Returns all recorded forks, latest first.
+    pub async fn get_log_forks(&self) -> Result<Vec<LogForkRow>, SqlStoreError> {
+        query_as::<_, LogForkRow>(
+            "
+            SELECT
+                public_key,
+                log_id,
+                seq_num,
+                entry_hash,
+                conflicting_entry_hash,
+                detected_at
+            FROM
+                log_forks
+            ORDER BY
+                detected_at DESC
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))
+    }
+
+    async fn insert_log_fork(
+        &self,
+        entry: &impl AsEntry,
+        entry_hash: &Hash,
+        conflicting_entry_hash: &Hash,
+    ) -> Result<LogForkRow, SqlStoreError> {
+        let row = LogForkRow {
+            public_key: entry.public_key().to_string(),
+            log_id: entry.log_id().as_u64().to_string(),
+            seq_num: entry.seq_num().as_u64().to_string(),
+            entry_hash: entry_hash.to_string(),
+            conflicting_entry_hash: conflicting_entry_hash.to_string(),
+            detected_at: now() as i64,
+        };
+
+        // The same conflicting entry might be sent to us more than once, we only keep the first
+        // record of it
+        query(
+            "
+            INSERT INTO
+                log_forks (
+                    public_key,
+                    log_id,
+                    seq_num,
+                    entry_hash,
+                    conflicting_entry_hash,
+                    detected_at
+                )
+            VALUES
+                ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(&row.public_key)
+        .bind(&row.log_id)
+        .bind(&row.seq_num)
+        .bind(&row.entry_hash)
+        .bind(&row.conflicting_entry_hash)
+        .bind(row.detected_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::entry::decode::decode_entry;
+    use p2panda_rs::entry::encode::sign_and_encode_entry;
+    use p2panda_rs::entry::traits::AsEncodedEntry;
+    use p2panda_rs::entry::{LogId, SeqNum};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::EncodedOperation;
+    use p2panda_rs::storage_provider::traits::EntryStore;
+    use p2panda_rs::test_utils::fixtures::encoded_operation;
+    use rstest::rstest;
+
+    use crate::test_utils::{
+        populate_store, populate_store_config, test_runner, PopulateStoreConfig, TestNode,
+    };
+
+    #[rstest]
+    fn detect_conflicting_entries(
+        #[from(populate_store_config)]
+        #[with(2, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        encoded_operation: EncodedOperation,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+            let _ = populate_store(store, &config).await;
+            let key_pair = config.authors.first().expect("At least one key pair");
+            let log_id = LogId::default();
+
+            let first_entry = store
+                .get_entry_at_seq_num(&key_pair.public_key(), &log_id, &SeqNum::new(1).unwrap())
+                .await
+                .unwrap()
+                .unwrap();
+
+            // Entries we already hold are not a fork
+            assert!(store
+                .detect_log_fork(&first_entry, &first_entry.hash())
+                .await
+                .unwrap()
+                .is_none());
+            assert!(!store
+                .is_log_forked(&key_pair.public_key(), &log_id)
+                .await
+                .unwrap());
+
+            // Another device signs a different first entry in the same log
+            let conflicting_entry = sign_and_encode_entry(
+                &log_id,
+                &SeqNum::new(1).unwrap(),
+                None,
+                None,
+                &encoded_operation,
+                key_pair,
+            )
+            .unwrap();
+            let fork = store
+                .detect_log_fork(
+                    &decode_entry(&conflicting_entry).unwrap(),
+                    &conflicting_entry.hash(),
+                )
+                .await
+                .unwrap()
+                .expect("Fork detected");
+
+            assert_eq!(fork.seq_num, "1");
+            assert_eq!(fork.entry_hash, first_entry.hash().to_string());
+            assert_eq!(
+                fork.conflicting_entry_hash,
+                conflicting_entry.hash().to_string()
+            );
+            assert!(store
+                .is_log_forked(&key_pair.public_key(), &log_id)
+                .await
+                .unwrap());
+
+            // Recording the same fork twice keeps one record
+            let _ = store
+                .detect_log_fork(
+                    &decode_entry(&conflicting_entry).unwrap(),
+                    &conflicting_entry.hash(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(store.get_log_forks().await.unwrap().len(), 1);
+        });
+    }
+}
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::str::FromStr;
+
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::SchemaId;
+use sqlx::{query, query_as};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::models::InviteRow;
+use crate::db::SqlStore;
+
+/// Separator of schema ids in the `schema_ids` column, schema ids never contain commas.
+const SCHEMA_IDS_SEPARATOR: char = ',';
+
+/// Methods to interact with the `invites` table in the database.
+///
+/// Invites are generated by the node operator and grant the public key redeeming them the
+/// permission to publish to a set of schemas until they expire.
+impl SqlStore {
+    /// Inserts a new invite which was not redeemed yet.
+    pub async fn insert_invite(
+        &self,
+        code: &str,
+        schema_ids: &[SchemaId],
+        expires_at: i64,
+    ) -> Result<(), SqlStoreError> {
+        let schema_ids: Vec<String> = schema_ids.iter().map(|id| id.to_string()).collect();
+
+        query(
+            "
+            INSERT INTO
+                invites (
+                    code,
+                    schema_ids,
+                    expires_at
+                )
+            VALUES
+                ($1, $2, $3)
+            ",
+        )
+        .bind(code)
+        .bind(schema_ids.join(&SCHEMA_IDS_SEPARATOR.to_string()))
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Binds an invite to the given public key.
+    ///
+    /// Returns `false` if the invite does not exist, expired or was already redeemed.
+    pub async fn redeem_invite(
+        &self,
+        code: &str,
+        public_key: &PublicKey,
+        now: i64,
+    ) -> Result<bool, SqlStoreError> {
+        let result = query(
+            "
+            UPDATE
+                invites
+            SET
+                public_key = $2
+            WHERE
+                code = $1
+                AND public_key IS NULL
+                AND expires_at > $3
+            ",
+        )
+        .bind(code)
+        .bind(public_key.to_string())
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns the ids of all schemas the public key may publish to via unexpired invites.
+    pub async fn get_invited_schema_ids(
+        &self,
+        public_key: &PublicKey,
+        now: i64,
+    ) -> Result<Vec<SchemaId>, SqlStoreError> {
+        let rows = query_as::<_, InviteRow>(
+            "
+            SELECT
+                code,
+                schema_ids,
+                expires_at,
+                public_key
+            FROM
+                invites
+            WHERE
+                public_key = $1
+                AND expires_at > $2
+            ",
+        )
+        .bind(public_key.to_string())
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let schema_ids = rows
+            .iter()
+            .flat_map(|row| row.schema_ids.split(SCHEMA_IDS_SEPARATOR))
+            .filter_map(|schema_id| SchemaId::from_str(schema_id).ok())
+            .collect();
+
+        Ok(schema_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::SchemaId;
+    use rstest::rstest;
+
+    use crate::test_utils::{test_runner, TestNode};
+
+    #[rstest]
+    fn redeem_invites() {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+            let invited = KeyPair::new().public_key();
+            let schema_ids = vec![SchemaId::Blob(1), SchemaId::BlobPiece(1)];
+
+            store.insert_invite("abc", &schema_ids, 100).await.unwrap();
+            store
+                .insert_invite("expired", &schema_ids, 10)
+                .await
+                .unwrap();
+
+            assert!(!store.redeem_invite("unknown", &invited, 50).await.unwrap());
+            assert!(!store.redeem_invite("expired", &invited, 50).await.unwrap());
+            assert!(store.redeem_invite("abc", &invited, 50).await.unwrap());
+
+            // Invites can only be redeemed once
+            let other = KeyPair::new().public_key();
+            assert!(!store.redeem_invite("abc", &other, 50).await.unwrap());
+
+            assert_eq!(
+                store.get_invited_schema_ids(&invited, 50).await.unwrap(),
+                schema_ids
+            );
+            assert!(store
+                .get_invited_schema_ids(&other, 50)
+                .await
+                .unwrap()
+                .is_empty());
+
+            // Permissions end when the invite expires
+            assert!(store
+                .get_invited_schema_ids(&invited, 100)
+                .await
+                .unwrap()
+                .is_empty());
+        });
+    }
+}
@@ -2,14 +2,36 @@
 
 //! Implementations of all `p2panda-rs` defined storage provider traits and additionally
 //! `aquadoggo` specific interfaces.
+mod annotation;
+mod archive;
+mod batch;
 mod blob;
+mod change;
+mod cluster;
+mod dependency;
 pub mod document;
 mod entry;
+mod fork;
+mod invite;
 mod log;
+mod moderation;
 mod operation;
+mod peer;
 mod query;
+mod redirect;
+mod relation;
 mod schema;
+mod search;
+mod service_account;
+mod snapshot;
+mod stats;
 mod task;
+mod vacuum;
 
+pub use blob::BlobStatus;
+pub use dependency::{DependencyGraph, ViewDependencies, ViewRelation};
 pub use operation::OperationCursor;
-pub use query::{PaginationCursor, PaginationData, Query, RelationList};
+pub use query::{PaginationCursor, PaginationData, Query, QueryPlan, RelationList};
+pub use relation::{RelationTarget, StoredRelation};
+pub use search::SearchMatch;
+pub use stats::DocumentStats;
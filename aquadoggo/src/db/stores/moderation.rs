@@ -0,0 +1,195 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::SchemaId;
+use sqlx::{query, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+
+/// Key of the annotation holding the moderation state of a document.
+const MODERATION_ANNOTATION: &str = "moderation";
+
+/// Moderation state of documents waiting for approval.
+const HELD: &str = "held";
+
+/// Moderation state of approved documents.
+const APPROVED: &str = "approved";
+
+/// Methods to manage the moderation queue of documents of moderated schemas.
+///
+/// Documents of moderated schemas which arrived from other nodes are held back until an admin
+/// approved them. The moderation state is kept as a node-local annotation of the document, the
+/// operations of held documents are stored and replicated as usual.
+impl SqlStore {
+    /// Holds back a document until it got approved.
+    ///
+    /// Documents which already have a moderation state keep it.
+    pub async fn hold_document(&self, document_id: &DocumentId) -> Result<(), SqlStoreError> {
+        query(
+            "
+            INSERT INTO
+                document_annotations (
+                    document_id,
+                    key,
+                    value
+                )
+            VALUES
+                ($1, $2, $3)
+            ON CONFLICT (document_id, key) DO NOTHING
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(MODERATION_ANNOTATION)
+        .bind(HELD)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Approves a held document.
+    ///
+    /// Returns `true` if the document was held.
+    pub async fn approve_document(&self, document_id: &DocumentId) -> Result<bool, SqlStoreError> {
+        let result = query(
+            "
+            UPDATE
+                document_annotations
+            SET
+                value = $3
+            WHERE
+                document_id = $1
+                AND key = $2
+                AND value = $4
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(MODERATION_ANNOTATION)
+        .bind(APPROVED)
+        .bind(HELD)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns `true` if the document is waiting for approval.
+    pub async fn is_document_held(&self, document_id: &DocumentId) -> Result<bool, SqlStoreError> {
+        let held: Option<String> = query_scalar(
+            "
+            SELECT
+                document_id
+            FROM
+                document_annotations
+            WHERE
+                document_id = $1
+                AND key = $2
+                AND value = $3
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(MODERATION_ANNOTATION)
+        .bind(HELD)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(held.is_some())
+    }
+
+    /// Returns the ids of all documents of a schema waiting for approval, ordered by id.
+    pub async fn get_held_document_ids(
+        &self,
+        schema_id: &SchemaId,
+    ) -> Result<Vec<DocumentId>, SqlStoreError> {
+        // The CREATE operation of a document tells us its schema, even if the document was not
+        // materialized yet
+        let document_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                document_annotations.document_id
+            FROM
+                document_annotations
+                JOIN operations_v1
+                    ON operations_v1.operation_id = document_annotations.document_id
+            WHERE
+                document_annotations.key = $1
+                AND document_annotations.value = $2
+                AND operations_v1.schema_id = $3
+            ORDER BY
+                document_annotations.document_id
+            ",
+        )
+        .bind(MODERATION_ANNOTATION)
+        .bind(HELD)
+        .bind(schema_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(document_ids
+            .into_iter()
+            .map(|document_id| {
+                document_id
+                    .parse::<DocumentId>()
+                    .expect("Valid document id in database")
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+
+    use crate::test_utils::{
+        populate_store, populate_store_config, test_runner, PopulateStoreConfig, TestNode,
+    };
+
+    #[rstest]
+    fn hold_and_approve_documents(
+        #[from(populate_store_config)]
+        #[with(1, 2, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        #[from(random_document_id)] unknown_document_id: DocumentId,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+            let documents = populate_store(store, &config).await;
+            let held_id = documents[0].id();
+            let other_id = documents[1].id();
+
+            store.hold_document(held_id).await.unwrap();
+            assert!(store.is_document_held(held_id).await.unwrap());
+            assert!(!store.is_document_held(other_id).await.unwrap());
+            assert_eq!(
+                store
+                    .get_held_document_ids(config.schema.id())
+                    .await
+                    .unwrap(),
+                vec![held_id.to_owned()]
+            );
+
+            assert!(store.approve_document(held_id).await.unwrap());
+            assert!(!store.approve_document(held_id).await.unwrap());
+            assert!(!store.approve_document(&unknown_document_id).await.unwrap());
+            assert!(!store.is_document_held(held_id).await.unwrap());
+
+            // Approved documents are not held again
+            store.hold_document(held_id).await.unwrap();
+            assert!(!store.is_document_held(held_id).await.unwrap());
+            assert!(store
+                .get_held_document_ids(config.schema.id())
+                .await
+                .unwrap()
+                .is_empty());
+        });
+    }
+}
@@ -15,8 +15,11 @@ use sqlx::{query, query_as, query_scalar, Any};
 
 use crate::db::models::utils::{parse_operation_rows, parse_value_to_string_vec};
 use crate::db::models::{DocumentViewFieldRow, OperationFieldsJoinedRow};
+use crate::db::stores::archive::touch_document;
 use crate::db::types::StorageOperation;
 use crate::db::SqlStore;
+use crate::faults::{FaultOutcome, FaultPoint};
+use crate::replication::now;
 
 /// Implementation of `OperationStore` trait which is required when constructing a
 /// `StorageProvider`.
@@ -85,6 +88,20 @@ impl OperationStore for SqlStore {
         &self,
         id: &OperationId,
     ) -> Result<Option<StorageOperation>, OperationStorageError> {
+        if self.faults.check(FaultPoint::GetOperation).await? == FaultOutcome::Drop {
+            return Ok(None);
+        }
+
+        // Load archived fields back into the store if the operation belongs to an archived
+        // document
+        if self.has_archive() {
+            if let Some(document_id) = self.get_document_id_by_operation_id(id).await? {
+                self.restore_document(&document_id)
+                    .await
+                    .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
+            }
+        }
+
         let operation_rows = query_as::<_, OperationFieldsJoinedRow>(
             "
             SELECT
@@ -124,6 +141,10 @@ impl OperationStore for SqlStore {
         &self,
         id: &DocumentId,
     ) -> Result<Vec<StorageOperation>, OperationStorageError> {
+        self.restore_document(id)
+            .await
+            .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
+
         let operation_rows = query_as::<_, OperationFieldsJoinedRow>(
             "
             SELECT
@@ -144,6 +165,15 @@ impl OperationStore for SqlStore {
                     ON operation_fields_v1.operation_id = operations_v1.operation_id
             WHERE
                 operations_v1.document_id = $1
+                -- operations of batches which are still being published are not materialized yet
+                AND NOT EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        pending_batch_entries
+                    WHERE
+                        pending_batch_entries.entry_hash = operations_v1.operation_id
+                )
             ORDER BY
                 -- order the operations by their index when topologically sorted, in the case where
                 -- this may not be set yet we fall back to ordering by operation id. In both cases
@@ -168,6 +198,10 @@ impl OperationStore for SqlStore {
         &self,
         id: &SchemaId,
     ) -> Result<Vec<StorageOperation>, OperationStorageError> {
+        self.restore_documents_by_schema(id)
+            .await
+            .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
+
         let operation_rows = query_as::<_, OperationFieldsJoinedRow>(
             "
                 SELECT
@@ -267,6 +301,89 @@ impl SqlStore {
             .collect())
     }
 
+    /// Returns the schema id of an operation.
+    pub async fn get_schema_id_by_operation_id(
+        &self,
+        id: &OperationId,
+    ) -> Result<Option<SchemaId>, OperationStorageError> {
+        let schema_id: Option<String> = query_scalar(
+            "
+            SELECT
+                schema_id
+            FROM
+                operations_v1
+            WHERE
+                operation_id = $1
+            ",
+        )
+        .bind(id.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
+
+        Ok(schema_id.map(|id| id.parse().expect("invalid schema id in database")))
+    }
+
+    /// Returns the number of documents with operations which have not been materialized yet.
+    pub async fn count_unindexed_documents(&self) -> Result<u64, OperationStorageError> {
+        let count: i64 = query_scalar(
+            "
+            SELECT
+                COUNT(DISTINCT operations_v1.document_id)
+            FROM
+                operations_v1
+            WHERE
+                operations_v1.sorted_index IS NULL
+            ",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
+
+        Ok(count as u64)
+    }
+
+    /// Remove the fields of all operations of a document which are not referenced by any of its
+    /// materialized views.
+    ///
+    /// The operations themselves and their entries are kept, this way the document's operation
+    /// graph and logs stay intact. Returns the number of removed field rows.
+    pub async fn prune_operation_history(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<u64, OperationStorageError> {
+        let result = query(
+            "
+            DELETE FROM
+                operation_fields_v1
+            WHERE
+                operation_fields_v1.operation_id IN (
+                    SELECT
+                        operations_v1.operation_id
+                    FROM
+                        operations_v1
+                    WHERE
+                        operations_v1.document_id = $1
+                )
+                AND NOT EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        document_view_fields
+                    WHERE
+                        document_view_fields.operation_id = operation_fields_v1.operation_id
+                        AND document_view_fields.name = operation_fields_v1.name
+                )
+            ",
+        )
+        .bind(document_id.as_str())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Update the sorted index of an operation. This method is used in `reduce` tasks as each
     /// operation is processed.
     pub async fn update_operation_index(
@@ -302,11 +419,35 @@ impl SqlStore {
         operation: &Operation,
         document_id: &DocumentId,
         sorted_index: Option<i32>,
+    ) -> Result<(), OperationStorageError> {
+        if self.faults.check(FaultPoint::InsertOperation).await? == FaultOutcome::Drop {
+            return Ok(());
+        }
+
+        self.retry(|| {
+            self.try_insert_operation_with_index(
+                id,
+                public_key,
+                operation,
+                document_id,
+                sorted_index,
+            )
+        })
+        .await
+    }
+
+    /// Insert an operation with its index in a single transaction.
+    async fn try_insert_operation_with_index(
+        &self,
+        id: &OperationId,
+        public_key: &PublicKey,
+        operation: &Operation,
+        document_id: &DocumentId,
+        sorted_index: Option<i32>,
     ) -> Result<(), OperationStorageError> {
         // Start a transaction, any db insertions after this point, and before the `commit()` will
         // be rolled back in the event of an error.
         let mut tx = self
-            .pool
             .begin()
             .await
             .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
@@ -323,10 +464,11 @@ impl SqlStore {
                     action,
                     schema_id,
                     previous,
-                    sorted_index
+                    sorted_index,
+                    inserted_at
                 )
             VALUES
-                ($1, $2, $3, $4, $5, $6, $7)
+                ($1, $2, $3, $4, $5, $6, $7, $8)
             ",
         )
         .bind(public_key.to_string())
@@ -340,6 +482,7 @@ impl SqlStore {
                 .map(|document_view_id| document_view_id.to_string()),
         )
         .bind(sorted_index)
+        .bind(now() as i64)
         .execute(&mut tx)
         .await
         .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
@@ -386,6 +529,11 @@ impl SqlStore {
             }
         };
 
+        // Keep track of when this document changed the last time.
+        touch_document(&mut tx, document_id)
+            .await
+            .map_err(|e| OperationStorageError::FatalStorageError(e.to_string()))?;
+
         // Commit the transaction.
         tx.commit()
             .await
@@ -532,6 +680,62 @@ mod tests {
         });
     }
 
+    #[rstest]
+    fn count_unindexed_documents(
+        operation: Operation,
+        #[from(random_operation_id)] operation_id_a: OperationId,
+        #[from(random_operation_id)] operation_id_b: OperationId,
+        public_key: PublicKey,
+        document_id: DocumentId,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            assert_eq!(
+                node.context
+                    .store
+                    .count_unindexed_documents()
+                    .await
+                    .unwrap(),
+                0
+            );
+
+            // Insert two operations for the same document
+            for operation_id in [&operation_id_a, &operation_id_b] {
+                node.context
+                    .store
+                    .insert_operation(operation_id, &public_key, &operation, &document_id)
+                    .await
+                    .unwrap();
+            }
+
+            assert_eq!(
+                node.context
+                    .store
+                    .count_unindexed_documents()
+                    .await
+                    .unwrap(),
+                1
+            );
+
+            // Indexed operations are not counted anymore
+            for operation_id in [&operation_id_a, &operation_id_b] {
+                node.context
+                    .store
+                    .update_operation_index(operation_id, 0)
+                    .await
+                    .unwrap();
+            }
+
+            assert_eq!(
+                node.context
+                    .store
+                    .count_unindexed_documents()
+                    .await
+                    .unwrap(),
+                0
+            );
+        });
+    }
+
     #[rstest]
     fn gets_document_by_operation_id(
         operation: Operation,
@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use libp2p::Multiaddr;
+use sqlx::{query, query_as};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::models::BootstrapPeerRow;
+use crate::db::SqlStore;
+use crate::network::BootstrapPeer;
+
+/// Methods to interact with the `bootstrap_peers` table in the database.
+impl SqlStore {
+    /// Inserts or updates a peer we successfully connected to.
+    ///
+    /// Peers without a known peer id are ignored.
+    pub async fn insert_bootstrap_peer(&self, peer: &BootstrapPeer) -> Result<(), SqlStoreError> {
+        let peer_id = match peer.peer_id {
+            Some(peer_id) => peer_id,
+            None => return Ok(()),
+        };
+
+        query(
+            "
+            INSERT INTO
+                bootstrap_peers (
+                    address,
+                    peer_id,
+                    score,
+                    last_seen,
+                    successes,
+                    failures
+                )
+            VALUES
+                ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (address) DO UPDATE SET
+                peer_id = excluded.peer_id,
+                score = excluded.score,
+                last_seen = excluded.last_seen,
+                successes = excluded.successes,
+                failures = excluded.failures
+            ",
+        )
+        .bind(peer.address.to_string())
+        .bind(peer_id.to_string())
+        .bind(peer.score)
+        .bind(peer.last_seen.map(|last_seen| last_seen as i64))
+        .bind(peer.successes as i64)
+        .bind(peer.failures as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Removes a peer from the database.
+    pub async fn remove_bootstrap_peer(&self, address: &Multiaddr) -> Result<(), SqlStoreError> {
+        query(
+            "
+            DELETE FROM
+                bootstrap_peers
+            WHERE
+                address = $1
+            ",
+        )
+        .bind(address.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Removes all peers we did not connect to since the given UNIX timestamp in seconds.
+    ///
+    /// Returns the number of removed peers.
+    pub async fn remove_expired_bootstrap_peers(
+        &self,
+        last_seen_before: u64,
+    ) -> Result<u64, SqlStoreError> {
+        let result = query(
+            "
+            DELETE FROM
+                bootstrap_peers
+            WHERE
+                last_seen < $1
+            ",
+        )
+        .bind(last_seen_before as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Returns the healthiest peers we connected to in the past.
+    pub async fn get_bootstrap_peers(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<BootstrapPeer>, SqlStoreError> {
+        let peer_rows = query_as::<_, BootstrapPeerRow>(
+            "
+            SELECT
+                address,
+                peer_id,
+                score,
+                last_seen,
+                successes,
+                failures
+            FROM
+                bootstrap_peers
+            ORDER BY
+                score DESC
+            LIMIT
+                $1
+            ",
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        // Convert database rows into peers, ignoring invalid entries
+        let peers = peer_rows
+            .into_iter()
+            .filter_map(|row| {
+                let address = row.address.parse().ok()?;
+                let peer_id = row.peer_id.parse().ok()?;
+
+                let mut peer = BootstrapPeer::new(address, Some(peer_id), false);
+                peer.score = row.score;
+                peer.last_seen = row.last_seen.map(|last_seen| last_seen as u64);
+                peer.successes = row.successes as u64;
+                peer.failures = row.failures as u64;
+                Some(peer)
+            })
+            .collect();
+
+        Ok(peers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::{Multiaddr, PeerId};
+    use rstest::rstest;
+
+    use crate::network::BootstrapPeer;
+    use crate::test_utils::{test_runner, TestNode};
+
+    #[rstest]
+    fn insert_and_remove_bootstrap_peers() {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+
+            let address: Multiaddr = "/ip4/192.0.2.1/udp/2022/quic-v1".parse().unwrap();
+            let mut peer = BootstrapPeer::new(address.clone(), Some(PeerId::random()), true);
+            peer.score = 3;
+
+            // Peers without peer id are ignored
+            let unknown = BootstrapPeer::new(
+                "/ip4/192.0.2.2/udp/2022/quic-v1".parse().unwrap(),
+                None,
+                true,
+            );
+
+            store.insert_bootstrap_peer(&peer).await.unwrap();
+            store.insert_bootstrap_peer(&unknown).await.unwrap();
+
+            // Update score and stats of the same address
+            peer.score = 5;
+            peer.last_seen = Some(1_700_000_000);
+            peer.successes = 4;
+            peer.failures = 2;
+            store.insert_bootstrap_peer(&peer).await.unwrap();
+
+            let peers = store.get_bootstrap_peers(10).await.unwrap();
+            assert_eq!(peers.len(), 1);
+            assert_eq!(peers[0].address, address);
+            assert_eq!(peers[0].peer_id, peer.peer_id);
+            assert_eq!(peers[0].score, 5);
+            assert_eq!(peers[0].last_seen, Some(1_700_000_000));
+            assert_eq!(peers[0].successes, 4);
+            assert_eq!(peers[0].failures, 2);
+            assert!(!peers[0].configured);
+
+            store.remove_bootstrap_peer(&address).await.unwrap();
+            assert!(store.get_bootstrap_peers(10).await.unwrap().is_empty());
+        });
+    }
+
+    #[rstest]
+    fn remove_expired_bootstrap_peers() {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+
+            let mut recent = BootstrapPeer::new(
+                "/ip4/192.0.2.1/udp/2022/quic-v1".parse().unwrap(),
+                Some(PeerId::random()),
+                false,
+            );
+            recent.last_seen = Some(2000);
+
+            let mut expired = BootstrapPeer::new(
+                "/ip4/192.0.2.2/udp/2022/quic-v1".parse().unwrap(),
+                Some(PeerId::random()),
+                false,
+            );
+            expired.last_seen = Some(1000);
+
+            // Peers persisted before we kept track of their last connection do not expire
+            let unknown = BootstrapPeer::new(
+                "/ip4/192.0.2.3/udp/2022/quic-v1".parse().unwrap(),
+                Some(PeerId::random()),
+                false,
+            );
+
+            for peer in [&recent, &expired, &unknown] {
+                store.insert_bootstrap_peer(peer).await.unwrap();
+            }
+
+            assert_eq!(store.remove_expired_bootstrap_peers(1500).await.unwrap(), 1);
+
+            let addresses: Vec<Multiaddr> = store
+                .get_bootstrap_peers(10)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|peer| peer.address)
+                .collect();
+            assert_eq!(addresses.len(), 2);
+            assert!(addresses.contains(&recent.address));
+            assert!(addresses.contains(&unknown.address));
+        });
+    }
+}
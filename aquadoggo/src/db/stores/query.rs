@@ -4,12 +4,13 @@
 //! parameters. The results are batched via cursor-based pagination.
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::num::NonZeroU64;
 use std::str::FromStr;
 
 use anyhow::bail;
-use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
 use p2panda_rs::operation::OperationValue;
-use p2panda_rs::schema::{FieldName, Schema, SchemaId};
+use p2panda_rs::schema::{FieldName, FieldType, Schema, SchemaId};
 use p2panda_rs::storage_provider::error::DocumentStorageError;
 use sqlx::query::QueryAs;
 use sqlx::query_as;
@@ -18,8 +19,9 @@ use crate::db::models::utils::parse_document_view_field_rows;
 use crate::db::models::{DocumentViewFieldRow, QueryRow};
 use crate::db::query::{
     ApplicationFields, Cursor, Direction, Field, Filter, FilterBy, FilterSetting, LowerBound,
-    MetaField, Order, Pagination, PaginationField, Select, UpperBound,
+    MetaField, Order, Pagination, PaginationField, RelatedField, Select, UpperBound,
 };
+use crate::db::stores::snapshot::documents_sql;
 use crate::db::stores::OperationCursor;
 use crate::db::types::StorageDocument;
 use crate::db::{Pool, SqlStore};
@@ -77,6 +79,10 @@ pub struct PaginationCursor {
     /// In relation list queries we use this field to represent the parent document holding that
     /// list.
     pub root_view_id: Option<DocumentViewId>,
+
+    /// Snapshot of the collection the cursor was created for, following pages are queried at the
+    /// same point in time.
+    pub snapshot: Option<u64>,
 }
 
 impl PaginationCursor {
@@ -91,8 +97,15 @@ impl PaginationCursor {
             operation_cursor,
             root_operation_cursor,
             root_view_id,
+            snapshot: None,
         }
     }
+
+    /// Returns this cursor for paginating over the given snapshot of the collection.
+    pub fn with_snapshot(mut self, snapshot: Option<u64>) -> Self {
+        self.snapshot = snapshot;
+        self
+    }
 }
 
 impl Display for PaginationCursor {
@@ -112,24 +125,33 @@ impl Cursor for PaginationCursor {
         let bytes = bs58::decode(encoded).into_vec()?;
         let decoded = std::str::from_utf8(&bytes)?;
 
-        let parts: Vec<&str> = decoded.split(CURSOR_SEPARATOR).collect();
-        match parts.len() {
-            1 => Ok(Self::new(OperationCursor::from(parts[0]), None, None)),
-            3 => Ok(Self::new(
+        let mut parts: Vec<&str> = decoded.split(CURSOR_SEPARATOR).collect();
+
+        // Cursors of snapshots carry the point in time as an additional last part
+        let snapshot = match parts.len() {
+            2 | 4 => Some(u64::from_str(parts.pop().expect("Cursor has parts"))?),
+            _ => None,
+        };
+
+        let cursor = match parts.len() {
+            1 => Self::new(OperationCursor::from(parts[0]), None, None),
+            3 => Self::new(
                 OperationCursor::from(parts[0]),
                 Some(OperationCursor::from(parts[1])),
                 Some(DocumentViewId::from_str(parts[2])?),
-            )),
+            ),
             _ => {
                 bail!("Invalid amount of cursor parts");
             }
-        }
+        };
+
+        Ok(cursor.with_snapshot(snapshot))
     }
 
     fn encode(&self) -> String {
         bs58::encode(
             format!(
-                "{}{}",
+                "{}{}{}",
                 self.operation_cursor,
                 self.root_view_id
                     .as_ref()
@@ -141,7 +163,11 @@ impl Cursor for PaginationCursor {
                             .expect("Expect root_operation to be set when root_view_id is as well"),
                         CURSOR_SEPARATOR,
                         view_id
-                    ))
+                    )),
+                self.snapshot.map_or("".to_string(), |snapshot| format!(
+                    "{}{}",
+                    CURSOR_SEPARATOR, snapshot
+                ))
             )
             .as_bytes(),
         )
@@ -175,6 +201,17 @@ pub type QueryResponse = (
     Vec<(PaginationCursor, StorageDocument)>,
 );
 
+/// SQL query of a collection and the plan the database uses to execute it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPlan {
+    /// Generated SQL query.
+    pub sql: String,
+
+    /// Lines of the query plan, as returned by `EXPLAIN ANALYZE` on PostgreSQL or `EXPLAIN QUERY
+    /// PLAN` on SQLite.
+    pub plan: Vec<String>,
+}
+
 /// Query configuration to determine pagination cursor, selected fields, filters and order of
 /// results.
 #[derive(Debug, Clone)]
@@ -229,11 +266,16 @@ fn typecast_field_sql(
         // everything has been validated before
         .unwrap_or_else(|| panic!("Field '{}' not given in Schema", field_name));
 
+    typecast_sql(sql_field, field_type, case_sensitive)
+}
+
+/// Helper method to derive a SQL type cast function from a field type.
+fn typecast_sql(sql_field: &str, field_type: &FieldType, case_sensitive: bool) -> String {
     match field_type {
-        p2panda_rs::schema::FieldType::Integer => {
+        FieldType::Integer => {
             format!("CAST ({sql_field} AS INTEGER)")
         }
-        p2panda_rs::schema::FieldType::Float => {
+        FieldType::Float => {
             format!("CAST ({sql_field} AS REAL)")
         }
         // All other types (booleans, relations, etc.) we keep as strings. We can not convert
@@ -248,6 +290,92 @@ fn typecast_field_sql(
     }
 }
 
+/// Returns SQL selecting the raw value of a field of the document related in a (pinned) relation
+/// field. The relation field is read from the document view with the given id.
+///
+/// Unpinned relations point at the current view of the related document, or of its canonical
+/// document when it was merged into another one. The value is NULL when the related document was
+/// not materialized yet.
+fn related_field_sql(
+    relation_field: &str,
+    related_field: &RelatedField,
+    schema: &Schema,
+    view_id_sql: &str,
+) -> String {
+    let related_field_name = &related_field.name;
+
+    let related_view_sql = match schema.fields().get(relation_field) {
+        Some(FieldType::PinnedRelation(_)) => r#"
+            JOIN document_view_fields related_view_fields
+                ON related_view_fields.document_view_id = relation_fields.value
+            "#
+        .to_string(),
+        _ => r#"
+            LEFT JOIN document_redirects related_redirects
+                ON relation_fields.value = related_redirects.document_id
+            JOIN documents related_documents
+                ON related_documents.document_id = COALESCE(
+                    related_redirects.canonical_document_id,
+                    relation_fields.value
+                )
+            JOIN document_view_fields related_view_fields
+                ON related_view_fields.document_view_id = related_documents.document_view_id
+            "#
+        .to_string(),
+    };
+
+    format!(
+        r#"
+        (
+            SELECT
+                related_fields.value
+            FROM
+                -- Relation field of the document ..
+                operation_fields_v1 relation_fields
+                JOIN document_view_fields relation_view_fields
+                    ON
+                        relation_fields.operation_id = relation_view_fields.operation_id
+                        AND relation_fields.name = relation_view_fields.name
+
+                -- .. pointing at the view of the related document ..
+                {related_view_sql}
+
+                -- .. and the field of the related document we're interested in
+                JOIN operation_fields_v1 related_fields
+                    ON
+                        related_fields.operation_id = related_view_fields.operation_id
+                        AND related_fields.name = related_view_fields.name
+            WHERE
+                relation_view_fields.document_view_id = {view_id_sql}
+                AND relation_fields.name = '{relation_field}'
+                AND related_fields.name = '{related_field_name}'
+            LIMIT 1
+        )
+        "#
+    )
+}
+
+/// Returns SQL comparing values of a field of related documents.
+///
+/// Documents without a materialized related document are treated as if the field was empty (zero
+/// for numbers). Like this they are ordered the same way in SQLite and PostgreSQL, which sort NULL
+/// values differently, and we can paginate over them.
+fn typecast_related_field_sql(sql_field: &str, related_field: &RelatedField) -> String {
+    format!(
+        "COALESCE({}, {})",
+        typecast_sql(sql_field, &related_field.field_type, false),
+        empty_value_sql(&related_field.field_type)
+    )
+}
+
+/// Returns SQL of the value missing fields of related documents are treated as.
+fn empty_value_sql(field_type: &FieldType) -> &'static str {
+    match field_type {
+        FieldType::Integer | FieldType::Float => "0",
+        _ => "''",
+    }
+}
+
 /// Values to bind to SQL query.
 #[derive(Debug)]
 enum BindArgument {
@@ -340,34 +468,9 @@ fn cmp_sql(
                 format!("{sql_field} NOT IN ({})", args_sql)
             }
         }
-        FilterBy::Interval(lower_value, upper_value) => {
-            let mut values: Vec<String> = Vec::new();
-
-            match lower_value {
-                LowerBound::Unbounded => (),
-                LowerBound::Greater(value) => {
-                    args.append(&mut bind_arg(value));
-                    values.push(format!("{sql_field} > ${}", args.len()));
-                }
-                LowerBound::GreaterEqual(value) => {
-                    args.append(&mut bind_arg(value));
-                    values.push(format!("{sql_field} >= ${}", args.len()));
-                }
-            }
-
-            match upper_value {
-                UpperBound::Unbounded => (),
-                UpperBound::Lower(value) => {
-                    args.append(&mut bind_arg(value));
-                    values.push(format!("{sql_field} < ${}", args.len()));
-                }
-                UpperBound::LowerEqual(value) => {
-                    args.append(&mut bind_arg(value));
-                    values.push(format!("{sql_field} <= ${}", args.len()));
-                }
-            }
-
-            values.join(" AND ")
+        FilterBy::Interval(lower_value, upper_value)
+        | FilterBy::Count(lower_value, upper_value) => {
+            interval_sql(sql_field, lower_value, upper_value, args)
         }
         FilterBy::Contains(OperationValue::String(value)) => {
             args.push(BindArgument::String(format!("%{value}%")));
@@ -382,6 +485,42 @@ fn cmp_sql(
     }
 }
 
+/// Helper method to convert interval bounds into SQL comparison operations.
+fn interval_sql(
+    sql_field: &str,
+    lower_value: &LowerBound,
+    upper_value: &UpperBound,
+    args: &mut Vec<BindArgument>,
+) -> String {
+    let mut values: Vec<String> = Vec::new();
+
+    match lower_value {
+        LowerBound::Unbounded => (),
+        LowerBound::Greater(value) => {
+            args.append(&mut bind_arg(value));
+            values.push(format!("{sql_field} > ${}", args.len()));
+        }
+        LowerBound::GreaterEqual(value) => {
+            args.append(&mut bind_arg(value));
+            values.push(format!("{sql_field} >= ${}", args.len()));
+        }
+    }
+
+    match upper_value {
+        UpperBound::Unbounded => (),
+        UpperBound::Lower(value) => {
+            args.append(&mut bind_arg(value));
+            values.push(format!("{sql_field} < ${}", args.len()));
+        }
+        UpperBound::LowerEqual(value) => {
+            args.append(&mut bind_arg(value));
+            values.push(format!("{sql_field} <= ${}", args.len()));
+        }
+    }
+
+    values.join(" AND ")
+}
+
 /// Helper method to join optional SQL strings into one, separated by a comma.
 fn concatenate_sql(items: &[Option<String>]) -> String {
     items
@@ -453,6 +592,30 @@ fn where_filter_sql(filter: &Filter, schema: &Schema) -> (String, Vec<BindArgume
                     "AND {}",
                     cmp_sql("documents.document_view_id", filter_setting, &mut args)
                 )),
+                Field::Field(field_name) if matches!(filter_setting.by, FilterBy::Count(_, _)) => {
+                    // Empty lists are stored as one row with a NULL value which is not counted
+                    let count_sql = format!(
+                        r#"
+                        (
+                            SELECT
+                                COUNT(operation_fields_v1.value)
+                            FROM
+                                document_view_fields AS document_view_fields_subquery
+                                JOIN operation_fields_v1
+                                    ON
+                                        document_view_fields_subquery.operation_id = operation_fields_v1.operation_id
+                                    AND
+                                        document_view_fields_subquery.name = operation_fields_v1.name
+                            WHERE
+                                -- Match document_view_fields of this subquery with the parent one
+                                document_view_fields.document_view_id = document_view_fields_subquery.document_view_id
+                                AND operation_fields_v1.name = '{field_name}'
+                        )
+                        "#
+                    );
+
+                    Some(format!("AND {}", cmp_sql(&count_sql, filter_setting, &mut args)))
+                }
                 Field::Field(field_name) => {
                     let field_sql = typecast_field_sql("operation_fields_v1.value", field_name, schema, true);
                     let filter_cmp = cmp_sql(&field_sql, filter_setting, &mut args);
@@ -752,7 +915,106 @@ async fn where_pagination_sql(
             }
         }
 
-        // 3. Ordering over an application field
+        // 3. Ordering over a field of a related document
+        // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+        //
+        // Similar to ordering over an application field, but the compared value is the one of the
+        // document which the relation field of the document the cursor points at is related to.
+        //
+        // Documents with equal values are ordered by their view id (if more than one field was
+        // selected) and cursor, we continue right after the document the cursor points at.
+        Some(Field::Field(order_field_name)) if order.related_field.is_some() => {
+            let related_field = order
+                .related_field
+                .as_ref()
+                .expect("Related field is set at this point");
+
+            // Select the view id of the document the cursor points at
+            let view_id_pre = format!(
+                r#"
+                SELECT
+                    document_view_fields.document_view_id
+                FROM
+                    operation_fields_v1
+                    JOIN document_view_fields
+                        ON operation_fields_v1.operation_id = document_view_fields.operation_id
+                WHERE
+                    operation_fields_v1.cursor = '{operation_cursor}'
+                LIMIT 1
+                "#
+            );
+
+            let document_view_id: (String,) = query_as(&view_id_pre)
+                .fetch_one(pool)
+                .await
+                .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+            let view_id_sql = format!("'{}'", document_view_id.0);
+
+            // .. and the value of the related document we want to compare with
+            let cmp_value_pre = format!(
+                "SELECT {}",
+                related_field_sql(order_field_name, related_field, schema, &view_id_sql)
+            );
+
+            let related_value: (Option<String>,) =
+                query_as(&cmp_value_pre)
+                    .fetch_one(pool)
+                    .await
+                    .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+            // The returned value is added to the bindable arguments array since this is untrusted
+            // user content
+            let cmp_value = match related_value.0 {
+                Some(value) => {
+                    bind_args.push(BindArgument::String(value));
+                    typecast_related_field_sql(&format!("${}", bind_args.len()), related_field)
+                }
+                None => empty_value_sql(&related_field.field_type).to_string(),
+            };
+
+            let cmp_field = typecast_related_field_sql(
+                &related_field_sql(
+                    order_field_name,
+                    related_field,
+                    schema,
+                    "documents.document_view_id",
+                ),
+                related_field,
+            );
+
+            let tie_breaker_sql = if fields.len() > 1 {
+                format!(
+                    r#"
+                    documents.document_view_id > {view_id_sql}
+                    OR
+                    (
+                        documents.document_view_id = {view_id_sql}
+                        AND
+                            {cursor_sql}
+                    )
+                    "#
+                )
+            } else {
+                cursor_sql
+            };
+
+            // Cursor-based pagination
+            Ok(format!(
+                r#"
+                AND (
+                    {cmp_field} {cmp_direction} {cmp_value}
+                    OR
+                    (
+                        {cmp_field} = {cmp_value}
+                        AND
+                            ({tie_breaker_sql})
+                    )
+                )
+                "#
+            ))
+        }
+
+        // 4. Ordering over an application field
         // ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
         //
         // Cursors are always pointing at the last field of a document. In the following example
@@ -881,6 +1143,23 @@ fn order_sql(
             Field::Meta(MetaField::Owner) => "owner".to_string(),
             Field::Meta(MetaField::Edited) => "is_edited".to_string(),
             Field::Meta(MetaField::Deleted) => "is_deleted".to_string(),
+            // Ordering by a field of the related document
+            Field::Field(field_name) if order.related_field.is_some() => {
+                let related_field = order
+                    .related_field
+                    .as_ref()
+                    .expect("Related field is set at this point");
+
+                typecast_related_field_sql(
+                    &related_field_sql(
+                        field_name,
+                        related_field,
+                        schema,
+                        "documents.document_view_id",
+                    ),
+                    related_field,
+                )
+            }
             Field::Field(field_name) => {
                 format!(
                     r#"
@@ -1066,10 +1345,15 @@ fn where_sql(schema: &Schema, fields: &ApplicationFields, list: Option<&Relation
 
     match list {
         None => {
-            // Filter by the queried schema of that collection
+            // Filter by the queried schema of that collection, documents which were merged into
+            // another one are represented by their canonical document
             format!(
                 r#"
                 documents.schema_id = '{schema_id}'
+                AND
+                    documents.document_id NOT IN (
+                        SELECT document_redirects.document_id FROM document_redirects
+                    )
                 {list_index_sql}
                 {extra_field_select}
                 "#
@@ -1099,12 +1383,26 @@ fn where_sql(schema: &Schema, fields: &ApplicationFields, list: Option<&Relation
     }
 }
 
-fn from_sql(list: Option<&RelationList>) -> String {
+fn from_sql(list: Option<&RelationList>, snapshot: Option<u64>) -> String {
+    // Documents are taken from the current `documents` table or from how it was at the snapshot
+    let documents = documents_sql(snapshot);
+
     match list {
         Some(relation_list) => {
-            let filter_sql = match relation_list.list_type {
-                RelationListType::Pinned => "documents.document_view_id",
-                RelationListType::Unpinned => "documents.document_id",
+            // Unpinned relations to merged documents resolve to their canonical document
+            let (redirect_sql, filter_sql) = match relation_list.list_type {
+                RelationListType::Pinned => (
+                    "",
+                    "operation_fields_v1_list.value = documents.document_view_id",
+                ),
+                RelationListType::Unpinned => (
+                    r#"
+                    LEFT JOIN document_redirects
+                        ON
+                            operation_fields_v1_list.value = document_redirects.document_id
+                    "#,
+                    "COALESCE(document_redirects.canonical_document_id, operation_fields_v1_list.value) = documents.document_id",
+                ),
             };
 
             format!(
@@ -1116,23 +1414,25 @@ fn from_sql(list: Option<&RelationList>) -> String {
                         document_view_fields_list.operation_id = operation_fields_v1_list.operation_id
                     AND
                         document_view_fields_list.name = operation_fields_v1_list.name
+                {redirect_sql}
 
                 -- .. and join the related documents afterwards
-                JOIN documents
+                JOIN {documents}
                     ON
-                        operation_fields_v1_list.value = {filter_sql}
+                        {filter_sql}
                 JOIN document_view_fields
                     ON documents.document_view_id = document_view_fields.document_view_id
                 "#
             )
         }
         // Otherwise just query the documents directly
-        None => r#"
-            documents
+        None => format!(
+            r#"
+            {documents}
             JOIN document_view_fields
                 ON documents.document_view_id = document_view_fields.document_view_id
-        "#
-        .to_string(),
+            "#
+        ),
     }
 }
 
@@ -1148,6 +1448,88 @@ impl SqlStore {
         args: &Query<PaginationCursor>,
         list: Option<&RelationList>,
     ) -> Result<QueryResponse, DocumentStorageError> {
+        let application_fields = args.select.application_fields();
+        let (sea_quel, bind_args, page_size) = self.query_sql(schema, args, list).await?;
+
+        let mut query = query_as::<_, QueryRow>(&sea_quel);
+
+        // Bind untrusted user arguments to query
+        query = bind_to_query(query, &bind_args);
+
+        let mut rows: Vec<QueryRow> = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+        // We always query one more row than needed to find out if there's more data. This
+        // information aids the user during pagination
+        let has_next_page = if rows.len() as u64 > page_size {
+            // Remove that last row from final results if it exists
+            rows.pop();
+            true
+        } else {
+            false
+        };
+
+        // Calculate the total number of (filtered) documents in this query
+        let total_count = if args
+            .pagination
+            .fields
+            .contains(&PaginationField::TotalCount)
+        {
+            Some(self.count(schema, args, list).await?)
+        } else {
+            None
+        };
+
+        // Finally convert everything into the right format, cursors of snapshots point at the
+        // same snapshot to query the following pages at the same point in time
+        let documents: Vec<(PaginationCursor, StorageDocument)> =
+            convert_rows(rows, list, &application_fields, schema.id())
+                .into_iter()
+                .map(|(cursor, document)| {
+                    (cursor.with_snapshot(args.pagination.snapshot), document)
+                })
+                .collect();
+
+        // Determine cursors for pagination by looking at beginning and end of results
+        let start_cursor = if args
+            .pagination
+            .fields
+            .contains(&PaginationField::StartCursor)
+        {
+            documents.first().map(|(cursor, _)| cursor.to_owned())
+        } else {
+            None
+        };
+
+        let end_cursor = if args.pagination.fields.contains(&PaginationField::EndCursor) {
+            documents.last().map(|(cursor, _)| cursor.to_owned())
+        } else {
+            None
+        };
+
+        let pagination_data = PaginationData {
+            total_count,
+            has_next_page,
+            // @TODO: Implement backwards pagination, see related issue:
+            // https://github.com/p2panda/aquadoggo/issues/325
+            has_previous_page: false,
+            start_cursor,
+            end_cursor,
+        };
+
+        Ok((pagination_data, documents))
+    }
+
+    /// Returns the SQL query with its arguments and the page size to query a paginated collection
+    /// of documents.
+    async fn query_sql(
+        &self,
+        schema: &Schema,
+        args: &Query<PaginationCursor>,
+        list: Option<&RelationList>,
+    ) -> Result<(String, Vec<BindArgument>, u64), DocumentStorageError> {
         // Get all selected application fields from query
         let application_fields = args.select.application_fields();
 
@@ -1174,7 +1556,7 @@ impl SqlStore {
 
         let select = concatenate_sql(&select_vec);
 
-        let from = from_sql(list);
+        let from = from_sql(list, args.pagination.snapshot);
 
         let where_ = where_sql(schema, &application_fields, list);
         let and_fields = where_fields_sql(&application_fields);
@@ -1252,68 +1634,47 @@ impl SqlStore {
         "#
         );
 
-        let mut query = query_as::<_, QueryRow>(&sea_quel);
-
-        // Bind untrusted user arguments to query
-        query = bind_to_query(query, &bind_args);
-
-        let mut rows: Vec<QueryRow> = query
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
-
-        // We always query one more row than needed to find out if there's more data. This
-        // information aids the user during pagination
-        let has_next_page = if rows.len() as u64 > page_size {
-            // Remove that last row from final results if it exists
-            rows.pop();
-            true
-        } else {
-            false
-        };
-
-        // Calculate the total number of (filtered) documents in this query
-        let total_count = if args
-            .pagination
-            .fields
-            .contains(&PaginationField::TotalCount)
-        {
-            Some(self.count(schema, args, list).await?)
-        } else {
-            None
-        };
-
-        // Finally convert everything into the right format
-        let documents = convert_rows(rows, list, &application_fields, schema.id());
-
-        // Determine cursors for pagination by looking at beginning and end of results
-        let start_cursor = if args
-            .pagination
-            .fields
-            .contains(&PaginationField::StartCursor)
-        {
-            documents.first().map(|(cursor, _)| cursor.to_owned())
-        } else {
-            None
-        };
+        Ok((sea_quel, bind_args, page_size))
+    }
 
-        let end_cursor = if args.pagination.fields.contains(&PaginationField::EndCursor) {
-            documents.last().map(|(cursor, _)| cursor.to_owned())
+    /// Returns the SQL query used to query a paginated collection of documents, together with
+    /// its query plan.
+    ///
+    /// On PostgreSQL the query gets executed to analyze it, SQLite only estimates the plan.
+    pub async fn explain_query(
+        &self,
+        schema: &Schema,
+        args: &Query<PaginationCursor>,
+        list: Option<&RelationList>,
+    ) -> Result<QueryPlan, DocumentStorageError> {
+        let (sea_quel, bind_args, _) = self.query_sql(schema, args, list).await?;
+
+        let plan = if self.is_postgres() {
+            let explain_sql = format!("EXPLAIN ANALYZE {sea_quel}");
+            let query = query_as::<_, (String,)>(&explain_sql);
+            bind_to_query(query, &bind_args)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?
+                .into_iter()
+                .map(|(line,)| line)
+                .collect()
         } else {
-            None
-        };
-
-        let pagination_data = PaginationData {
-            total_count,
-            has_next_page,
-            // @TODO: Implement backwards pagination, see related issue:
-            // https://github.com/p2panda/aquadoggo/issues/325
-            has_previous_page: false,
-            start_cursor,
-            end_cursor,
+            let explain_sql = format!("EXPLAIN QUERY PLAN {sea_quel}");
+            let query = query_as::<_, (i64, i64, i64, String)>(&explain_sql);
+            bind_to_query(query, &bind_args)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?
+                .into_iter()
+                .map(|(_, _, _, detail)| detail)
+                .collect()
         };
 
-        Ok((pagination_data, documents))
+        Ok(QueryPlan {
+            sql: sea_quel,
+            plan,
+        })
     }
 
     /// Query number of documents in filtered collection.
@@ -1325,7 +1686,7 @@ impl SqlStore {
     ) -> Result<u64, DocumentStorageError> {
         let application_fields = args.select.application_fields();
 
-        let from = from_sql(list);
+        let from = from_sql(list, args.pagination.snapshot);
         let where_ = where_sql(schema, &application_fields, list);
         let (and_filters, bind_args) = where_filter_sql(&args.filter, schema);
 
@@ -1369,6 +1730,32 @@ impl SqlStore {
 
         Ok(count)
     }
+
+    /// Returns true if the current view of the given document matches the filter.
+    ///
+    /// The filter gets evaluated by the database, exactly like it is when querying collections.
+    pub async fn document_matches_filter(
+        &self,
+        schema: &Schema,
+        document_id: &DocumentId,
+        filter: &Filter,
+    ) -> Result<bool, DocumentStorageError> {
+        let mut filter = filter.clone();
+        filter.add(
+            &Field::Meta(MetaField::DocumentId),
+            &OperationValue::String(document_id.to_string()),
+        );
+
+        let args = Query::new(
+            &Pagination::new(&NonZeroU64::new(1).unwrap(), None, &vec![]),
+            &Select::default(),
+            &filter,
+            &Order::default(),
+        );
+
+        let (_, documents) = self.query(schema, &args, None).await?;
+        Ok(!documents.is_empty())
+    }
 }
 
 /// Merges all operation fields from the database into documents.
@@ -1502,18 +1889,19 @@ mod tests {
     use std::num::NonZeroU64;
 
     use p2panda_rs::document::traits::AsDocument;
-    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::document::{DocumentId, DocumentViewId};
     use p2panda_rs::hash::Hash;
     use p2panda_rs::identity::KeyPair;
-    use p2panda_rs::operation::{OperationValue, PinnedRelationList};
+    use p2panda_rs::operation::{OperationValue, PinnedRelationList, Relation};
     use p2panda_rs::schema::{FieldType, Schema, SchemaId};
     use p2panda_rs::storage_provider::traits::DocumentStore;
-    use p2panda_rs::test_utils::fixtures::{key_pair, schema_id};
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_id, schema_id};
     use rstest::rstest;
 
     use crate::db::models::{OptionalOwner, QueryRow};
     use crate::db::query::{
-        Direction, Field, Filter, MetaField, Order, Pagination, PaginationField, Select,
+        Cursor, Direction, Field, Filter, MetaField, Order, Pagination, PaginationField,
+        RelatedField, Select,
     };
     use crate::db::stores::{OperationCursor, RelationList};
     use crate::db::types::StorageDocument;
@@ -1895,6 +2283,117 @@ mod tests {
         });
     }
 
+    #[rstest]
+    #[case::ascending(Direction::Ascending, vec!["text".into(), "author".into()])]
+    #[case::ascending_one_field(Direction::Ascending, vec!["text".into()])]
+    #[case::descending(Direction::Descending, vec!["text".into(), "author".into()])]
+    fn pagination_over_related_fields(
+        key_pair: KeyPair,
+        #[from(random_document_id)] missing_author_id: DocumentId,
+        #[case] direction: Direction,
+        #[case] selected_fields: Vec<Field>,
+    ) {
+        test_runner(|mut node: TestNode| async move {
+            let (authors_schema, author_view_ids) = add_schema_and_documents(
+                &mut node,
+                "authors",
+                vec![
+                    vec![("name", "Zoe".into(), None)],
+                    vec![("name", "alice".into(), None)],
+                    vec![("name", "Panda".into(), None)],
+                ],
+                &key_pair,
+            )
+            .await;
+
+            // The view id of a document with only one operation is its document id
+            let author_ids: Vec<DocumentId> = author_view_ids
+                .iter()
+                .map(|view_id| view_id.to_string().parse().unwrap())
+                .collect();
+
+            let comment = |text: &str, author_id: &DocumentId| {
+                vec![
+                    ("text", text.into(), None),
+                    (
+                        "author",
+                        OperationValue::Relation(Relation::new(author_id.to_owned())),
+                        Some(authors_schema.id().to_owned()),
+                    ),
+                ]
+            };
+
+            let (schema, _) = add_schema_and_documents(
+                &mut node,
+                "comments",
+                vec![
+                    comment("first", &author_ids[0]),
+                    comment("second", &author_ids[1]),
+                    comment("third", &author_ids[2]),
+                    comment("fourth", &author_ids[1]),
+                    comment("fifth", &missing_author_id),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            // Comments are ordered case-insensitive by the name of their author, missing authors
+            // have an empty name
+            let author_rank = |text: &OperationValue| match text {
+                OperationValue::String(text) => match text.as_str() {
+                    "fifth" => 0,
+                    "second" | "fourth" => 1,
+                    "third" => 2,
+                    "first" => 3,
+                    _ => panic!("Unexpected comment"),
+                },
+                _ => panic!("Unexpected field value"),
+            };
+
+            let order = Order::new_related(
+                &"author".into(),
+                &RelatedField::new("name", &FieldType::String),
+                &direction,
+            );
+
+            let mut args = Query::new(
+                &Pagination::new(
+                    &NonZeroU64::new(1).unwrap(),
+                    None,
+                    &vec![PaginationField::EndCursor],
+                ),
+                &Select::new(&selected_fields),
+                &Filter::default(),
+                &order,
+            );
+
+            // Go through all pages, one document at a time
+            let mut ranks = Vec::new();
+            loop {
+                let (pagination_data, documents) = node
+                    .context
+                    .store
+                    .query(&schema, &args, None)
+                    .await
+                    .expect("Query failed");
+
+                match documents.first() {
+                    Some((_, document)) => ranks.push(author_rank(document.get("text").unwrap())),
+                    None => break,
+                }
+
+                args.pagination.after = pagination_data.end_cursor;
+            }
+
+            // Every comment shows up exactly once, in the right order
+            let mut expected = vec![0, 1, 1, 2, 3];
+            if direction == Direction::Descending {
+                expected.reverse();
+            }
+            assert_eq!(ranks, expected);
+        });
+    }
+
     #[rstest]
     fn pagination_over_ordered_view_ids(key_pair: KeyPair) {
         test_runner(|mut node: TestNode| async move {
@@ -2472,6 +2971,62 @@ mod tests {
         });
     }
 
+    #[rstest]
+    #[case::count_gte(Filter::new().fields(&[("venues_count_gte", &[5.into()])]), 1)]
+    #[case::count_lt(Filter::new().fields(&[("venues_count_lt", &[5.into()])]), 1)]
+    #[case::count_eq(Filter::new().fields(&[("venues_count", &[3.into()])]), 1)]
+    #[case::count_interval(
+        Filter::new().fields(&[
+            ("venues_count_gt", &[3.into()]),
+            ("venues_count_lte", &[7.into()])
+        ]),
+        1
+    )]
+    #[case::count_gte_all(Filter::new().fields(&[("venues_count_gte", &[3.into()])]), 2)]
+    #[case::no_results(Filter::new().fields(&[("venues_count_gt", &[7.into()])]), 0)]
+    fn filter_by_relation_list_count(
+        #[case] filter: Filter,
+        #[case] expected_result: u64,
+        key_pair: KeyPair,
+    ) {
+        test_runner(move |mut node: TestNode| async move {
+            let (venues_schema, venues_view_ids) =
+                create_venues_test_data(&mut node, &key_pair).await;
+
+            // Visited documents contain 7 and 3 venues
+            let (visited_schema, _) = create_visited_test_data(
+                &mut node,
+                venues_view_ids.clone(),
+                venues_schema.clone(),
+                &key_pair,
+            )
+            .await;
+
+            let args = Query::new(
+                &Pagination::default(),
+                &Select::new(&["user".into()]),
+                &filter,
+                &Order::default(),
+            );
+
+            let result = node
+                .context
+                .store
+                .count(&visited_schema, &args, None)
+                .await
+                .unwrap();
+            assert_eq!(result, expected_result);
+
+            let (_, documents) = node
+                .context
+                .store
+                .query(&visited_schema, &args, None)
+                .await
+                .unwrap();
+            assert_eq!(documents.len() as u64, expected_result);
+        });
+    }
+
     #[rstest]
     fn total_count_of_document_with_relation_list_field(key_pair: KeyPair) {
         test_runner(|mut node: TestNode| async move {
@@ -2709,4 +3264,59 @@ mod tests {
             }
         });
     }
+    #[rstest]
+    fn document_matches_filter(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let (schema, view_ids) = add_schema_and_documents(
+                &mut node,
+                "messages",
+                vec![
+                    vec![("room", "lobby".into(), None)],
+                    vec![("room", "kitchen".into(), None)],
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let mut filter = Filter::new();
+            filter.add(&Field::new("room"), &"lobby".into());
+
+            let lobby_document_id = DocumentId::new(view_ids[0].iter().next().unwrap());
+            let kitchen_document_id = DocumentId::new(view_ids[1].iter().next().unwrap());
+
+            assert!(node
+                .context
+                .store
+                .document_matches_filter(&schema, &lobby_document_id, &filter)
+                .await
+                .unwrap());
+            assert!(!node
+                .context
+                .store
+                .document_matches_filter(&schema, &kitchen_document_id, &filter)
+                .await
+                .unwrap());
+        });
+    }
+
+    #[rstest]
+    fn encode_snapshot_cursors(#[from(random_document_id)] document_id: DocumentId) {
+        let view_id: DocumentViewId = document_id.as_str().parse().unwrap();
+
+        for cursor in [
+            PaginationCursor::new(OperationCursor::from("cursor"), None, None),
+            PaginationCursor::new(
+                OperationCursor::from("cursor"),
+                Some(OperationCursor::from("root_cursor")),
+                Some(view_id),
+            ),
+        ] {
+            assert_eq!(PaginationCursor::decode(&cursor.encode()).unwrap(), cursor);
+
+            let snapshot_cursor = cursor.with_snapshot(Some(1700000000000));
+            let decoded = PaginationCursor::decode(&snapshot_cursor.encode()).unwrap();
+            assert_eq!(decoded.snapshot, Some(1700000000000));
+            assert_eq!(decoded, snapshot_cursor);
+        }
+    }
 }
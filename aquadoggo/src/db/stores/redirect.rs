@@ -0,0 +1,234 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use p2panda_rs::document::DocumentId;
+use sqlx::{query, query_as, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+
+/// Methods to interact with the `document_redirects` table in the database.
+///
+/// Redirects link a merged document to its canonical document, for example after the same author
+/// accidentally created identical documents from two devices. Queries and relations targeting the
+/// merged document resolve to the canonical one.
+impl SqlStore {
+    /// Redirects a document to a canonical document.
+    ///
+    /// If the canonical document is itself redirected, the final target is used instead. Existing
+    /// redirects pointing at the merged document are moved to the canonical document as well,
+    /// this way we never need to follow more than one redirect.
+    ///
+    /// Returns the id of the document the merged document is now redirected to.
+    pub async fn insert_document_redirect(
+        &self,
+        document_id: &DocumentId,
+        canonical_document_id: &DocumentId,
+    ) -> Result<DocumentId, SqlStoreError> {
+        let canonical_document_id = self
+            .resolve_document_redirect(canonical_document_id)
+            .await?;
+
+        if &canonical_document_id == document_id {
+            return Err(SqlStoreError::CyclicRedirect(document_id.to_string()));
+        }
+
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        query(
+            "
+            UPDATE
+                document_redirects
+            SET
+                canonical_document_id = $2
+            WHERE
+                canonical_document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(canonical_document_id.as_str())
+        .execute(&mut tx)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        query(
+            "
+            INSERT INTO
+                document_redirects (
+                    document_id,
+                    canonical_document_id
+                )
+            VALUES
+                ($1, $2)
+            ON CONFLICT (document_id) DO UPDATE SET
+                canonical_document_id = excluded.canonical_document_id
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(canonical_document_id.as_str())
+        .execute(&mut tx)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(canonical_document_id)
+    }
+
+    /// Returns the id of the canonical document if the given document was merged into another
+    /// one, otherwise the given document id.
+    pub async fn resolve_document_redirect(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<DocumentId, SqlStoreError> {
+        let canonical_document_id: Option<String> = query_scalar(
+            "
+            SELECT
+                canonical_document_id
+            FROM
+                document_redirects
+            WHERE
+                document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        match canonical_document_id {
+            Some(canonical_document_id) => Ok(DocumentId::from_str(&canonical_document_id)
+                .expect("Document id from database is valid")),
+            None => Ok(document_id.to_owned()),
+        }
+    }
+
+    /// Returns the ids of the canonical documents of all given documents which were merged into
+    /// another one, documents without a redirect are not included.
+    pub async fn resolve_document_redirects(
+        &self,
+        document_ids: &[DocumentId],
+    ) -> Result<HashMap<DocumentId, DocumentId>, SqlStoreError> {
+        if document_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let placeholders = (0..document_ids.len())
+            .map(|index| format!("${}", index + 1))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let sql = format!(
+            "
+            SELECT
+                document_id,
+                canonical_document_id
+            FROM
+                document_redirects
+            WHERE
+                document_id IN ({placeholders})
+            "
+        );
+
+        let mut redirects_query = query_as::<_, (String, String)>(&sql);
+        for document_id in document_ids {
+            redirects_query = redirects_query.bind(document_id.as_str());
+        }
+
+        let redirects = redirects_query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(redirects
+            .into_iter()
+            .map(|(document_id, canonical_document_id)| {
+                (
+                    DocumentId::from_str(&document_id).expect("Document id from database is valid"),
+                    DocumentId::from_str(&canonical_document_id)
+                        .expect("Document id from database is valid"),
+                )
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+
+    use crate::test_utils::{test_runner, TestNode};
+
+    #[rstest]
+    fn insert_and_resolve_redirects(
+        #[from(random_document_id)] document_a: DocumentId,
+        #[from(random_document_id)] document_b: DocumentId,
+        #[from(random_document_id)] document_c: DocumentId,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+
+            // Documents without redirect resolve to themselves
+            assert_eq!(
+                store.resolve_document_redirect(&document_a).await.unwrap(),
+                document_a
+            );
+
+            // Merge A into B
+            assert_eq!(
+                store
+                    .insert_document_redirect(&document_a, &document_b)
+                    .await
+                    .unwrap(),
+                document_b
+            );
+            assert_eq!(
+                store.resolve_document_redirect(&document_a).await.unwrap(),
+                document_b
+            );
+
+            // Merge B into C, A follows along
+            store
+                .insert_document_redirect(&document_b, &document_c)
+                .await
+                .unwrap();
+            assert_eq!(
+                store.resolve_document_redirect(&document_a).await.unwrap(),
+                document_c
+            );
+            assert_eq!(
+                store.resolve_document_redirect(&document_b).await.unwrap(),
+                document_c
+            );
+
+            // Many redirects are resolved at once, documents without redirect are not included
+            let redirects = store
+                .resolve_document_redirects(&[
+                    document_a.clone(),
+                    document_b.clone(),
+                    document_c.clone(),
+                ])
+                .await
+                .unwrap();
+            assert_eq!(redirects.len(), 2);
+            assert_eq!(redirects.get(&document_a), Some(&document_c));
+            assert_eq!(redirects.get(&document_b), Some(&document_c));
+
+            // Redirects can't form cycles
+            assert!(store
+                .insert_document_redirect(&document_c, &document_a)
+                .await
+                .is_err());
+        });
+    }
+}
@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::{FieldName, SchemaId};
+use sqlx::query_as;
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+
+/// Relation with the schema ids and deletion states of the related document and view.
+type RelationRow = (
+    String,
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<bool>,
+    Option<String>,
+    Option<bool>,
+);
+
+/// Document or document view a relation points at, as found in the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationTarget {
+    /// Schema of the related document.
+    pub schema_id: SchemaId,
+
+    /// True if the related document got deleted.
+    pub is_deleted: bool,
+}
+
+/// Relation in the latest view of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredRelation {
+    /// Id of the document containing the relation.
+    pub document_id: DocumentId,
+
+    /// Name of the field containing the relation.
+    pub field: FieldName,
+
+    /// True if the relation points at a document view instead of a document.
+    pub is_pinned: bool,
+
+    /// Document id or document view id the relation points at.
+    pub target: String,
+
+    /// Related document or `None` if it is not materialized on this node.
+    pub target_state: Option<RelationTarget>,
+}
+
+/// Methods to inspect relations between documents.
+impl SqlStore {
+    /// Returns all relations in the latest views of documents of a schema, together with the
+    /// state of the documents and views they point at.
+    ///
+    /// Deleted documents are not included. Relations are ordered by document id and field name,
+    /// items of relation lists keep their order.
+    pub async fn get_relations_by_schema(
+        &self,
+        schema_id: &SchemaId,
+    ) -> Result<Vec<StoredRelation>, SqlStoreError> {
+        let rows: Vec<RelationRow> = query_as(
+            "
+            SELECT
+                documents.document_id,
+                operation_fields_v1.name,
+                operation_fields_v1.field_type,
+                operation_fields_v1.value,
+                target_documents.schema_id,
+                target_documents.is_deleted,
+                target_views.schema_id,
+                target_view_documents.is_deleted
+            FROM
+                documents
+            JOIN
+                document_view_fields
+            ON
+                document_view_fields.document_view_id = documents.document_view_id
+            LEFT JOIN
+                operation_fields_v1
+            ON
+                document_view_fields.operation_id = operation_fields_v1.operation_id
+            AND
+                document_view_fields.name = operation_fields_v1.name
+            LEFT JOIN
+                documents AS target_documents
+            ON
+                target_documents.document_id = operation_fields_v1.value
+            LEFT JOIN
+                document_views AS target_views
+            ON
+                target_views.document_view_id = operation_fields_v1.value
+            LEFT JOIN
+                documents AS target_view_documents
+            ON
+                target_view_documents.document_id = target_views.document_id
+            WHERE
+                documents.schema_id = $1
+            AND
+                documents.is_deleted = false
+            AND
+                operation_fields_v1.field_type IN (
+                    'pinned_relation',
+                    'pinned_relation_list',
+                    'relation',
+                    'relation_list'
+                )
+            AND
+                operation_fields_v1.value IS NOT NULL
+            ORDER BY
+                documents.document_id,
+                operation_fields_v1.name,
+                operation_fields_v1.list_index
+            ",
+        )
+        .bind(schema_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        let relations = rows
+            .into_iter()
+            .map(
+                |(
+                    document_id,
+                    field,
+                    field_type,
+                    target,
+                    document_schema_id,
+                    is_document_deleted,
+                    view_schema_id,
+                    is_view_document_deleted,
+                )| {
+                    let is_pinned = field_type.starts_with("pinned_");
+
+                    let target_state = if is_pinned {
+                        view_schema_id.map(|schema_id| (schema_id, is_view_document_deleted))
+                    } else {
+                        document_schema_id.map(|schema_id| (schema_id, is_document_deleted))
+                    }
+                    .map(|(schema_id, is_deleted)| RelationTarget {
+                        schema_id: schema_id
+                            .parse()
+                            .expect("Invalid schema id stored in database"),
+                        is_deleted: is_deleted.unwrap_or(false),
+                    });
+
+                    StoredRelation {
+                        document_id: document_id
+                            .parse()
+                            .expect("Invalid document id stored in database"),
+                        field,
+                        is_pinned,
+                        target,
+                        target_state,
+                    }
+                },
+            )
+            .collect();
+
+        Ok(relations)
+    }
+}
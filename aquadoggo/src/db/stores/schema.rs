@@ -3,11 +3,12 @@
 use std::convert::{TryFrom, TryInto};
 
 use p2panda_rs::document::traits::AsDocument;
-use p2panda_rs::document::DocumentViewId;
+use p2panda_rs::document::{DocumentBuilder, DocumentViewId};
+use p2panda_rs::operation::traits::WithId;
 use p2panda_rs::schema::system::{SchemaFieldView, SchemaView};
 use p2panda_rs::schema::{Schema, SchemaId};
 use p2panda_rs::storage_provider::error::OperationStorageError;
-use p2panda_rs::storage_provider::traits::DocumentStore;
+use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
 use sqlx::query_scalar;
 
 use crate::db::errors::SchemaStoreError;
@@ -96,6 +97,62 @@ impl SqlStore {
         Ok(all_schema.into_iter().flatten().collect())
     }
 
+    /// Get all versions of a schema definition up to the given document view, starting with its
+    /// first version.
+    ///
+    /// Every operation of the schema definition document is a new version, operations are
+    /// counted in the order they get applied when materializing the document. The given view is
+    /// the last version in the returned list.
+    ///
+    /// Returns `None` if the schema definition view is not materialized or not all of its
+    /// operations are known.
+    pub async fn get_schema_versions(
+        &self,
+        view_id: &DocumentViewId,
+    ) -> Result<Option<Vec<SchemaView>>, SchemaStoreError> {
+        let document_id = match self.get_document_by_view_id(view_id).await? {
+            Some(document) => document.id().to_owned(),
+            None => return Ok(None),
+        };
+
+        let operations = self.get_operations_by_document_id(&document_id).await?;
+
+        let sorted_operation_ids: Vec<_> =
+            match DocumentBuilder::from(&operations).build_to_view_id(view_id.to_owned()) {
+                Ok((_, sorted_operations)) => sorted_operations
+                    .into_iter()
+                    .map(|(operation_id, _, _)| operation_id)
+                    .collect(),
+                Err(_) => return Ok(None),
+            };
+
+        let mut versions = Vec::new();
+        let mut history = Vec::new();
+
+        for operation_id in sorted_operation_ids {
+            let operation = match operations
+                .iter()
+                .find(|operation| operation.id() == &operation_id)
+            {
+                Some(operation) => operation.to_owned(),
+                None => return Ok(None),
+            };
+            history.push(operation);
+
+            let version: SchemaView = match DocumentBuilder::from(&history).build() {
+                Ok((document, _)) => match document.view() {
+                    Some(view) => view.try_into()?,
+                    // Deleted schema definitions have no versions
+                    None => return Ok(None),
+                },
+                Err(_) => return Ok(None),
+            };
+            versions.push(version);
+        }
+
+        Ok(Some(versions))
+    }
+
     /// Returns the schema id for a document view.
     ///
     /// Returns `None` if this document view is not found.
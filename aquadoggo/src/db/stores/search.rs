@@ -0,0 +1,268 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Text search over documents of many schemas at once.
+use std::collections::HashMap;
+
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::schema::{FieldName, SchemaId};
+use p2panda_rs::storage_provider::error::DocumentStorageError;
+use sqlx::query_as;
+
+use crate::db::SqlStore;
+
+/// Document containing the searched text in at least one of its string fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// Id of the matching document.
+    pub document_id: DocumentId,
+
+    /// Latest view id of the matching document.
+    pub view_id: DocumentViewId,
+
+    /// Schema of the matching document.
+    pub schema_id: SchemaId,
+
+    /// Names and values of all fields containing the searched text, ordered by field name.
+    pub fields: Vec<(FieldName, String)>,
+}
+
+/// Escape wildcard characters of a `LIKE` pattern, so the text is matched literally.
+fn escape_like_pattern(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Returns a SQL placeholder list, for example `$2, $3, $4`, starting at the given index.
+fn placeholders(start: usize, len: usize) -> String {
+    (start..start + len)
+        .map(|index| format!("${}", index))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl SqlStore {
+    /// Search all documents of the given schemas for string fields containing the text.
+    ///
+    /// Matching is case-insensitive, in the same way as the `contains` filter of collection
+    /// queries. Results of all schemas are combined and ranked by the number of matching fields,
+    /// documents with the same rank are ordered by their id.
+    ///
+    /// Deleted documents are not included.
+    pub async fn search(
+        &self,
+        text: &str,
+        schema_ids: &[SchemaId],
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<SearchMatch>, DocumentStorageError> {
+        if schema_ids.is_empty() || text.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pattern = format!("%{}%", escape_like_pattern(text));
+        let schema_placeholders = placeholders(2, schema_ids.len());
+
+        // Rank matching documents of all schemas
+        let sql = format!(
+            "
+            SELECT
+                documents.document_id,
+                documents.document_view_id,
+                documents.schema_id,
+                COUNT(DISTINCT operation_fields_v1.name) AS matches
+            FROM
+                documents
+                JOIN document_view_fields
+                    ON document_view_fields.document_view_id = documents.document_view_id
+                JOIN operation_fields_v1
+                    ON operation_fields_v1.operation_id = document_view_fields.operation_id
+                    AND operation_fields_v1.name = document_view_fields.name
+            WHERE
+                documents.is_deleted = false
+                AND documents.schema_id IN ({schema_placeholders})
+                AND operation_fields_v1.field_type = 'str'
+                AND LOWER(operation_fields_v1.value) LIKE LOWER($1) ESCAPE '\\'
+            GROUP BY
+                documents.document_id,
+                documents.document_view_id,
+                documents.schema_id
+            ORDER BY
+                matches DESC,
+                documents.document_id ASC
+            LIMIT {limit}
+            OFFSET {offset}
+            "
+        );
+
+        let mut query = query_as::<_, (String, String, String, i64)>(&sql).bind(pattern.clone());
+        for schema_id in schema_ids {
+            query = query.bind(schema_id.to_string());
+        }
+
+        let documents = query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Collect the matching field values of all ranked documents
+        let view_placeholders = placeholders(2, documents.len());
+        let sql = format!(
+            "
+            SELECT
+                document_view_fields.document_view_id,
+                operation_fields_v1.name,
+                operation_fields_v1.value
+            FROM
+                document_view_fields
+                JOIN operation_fields_v1
+                    ON operation_fields_v1.operation_id = document_view_fields.operation_id
+                    AND operation_fields_v1.name = document_view_fields.name
+            WHERE
+                document_view_fields.document_view_id IN ({view_placeholders})
+                AND operation_fields_v1.field_type = 'str'
+                AND LOWER(operation_fields_v1.value) LIKE LOWER($1) ESCAPE '\\'
+            ORDER BY
+                operation_fields_v1.name ASC
+            "
+        );
+
+        let mut query = query_as::<_, (String, String, String)>(&sql).bind(pattern);
+        for (_, view_id, _, _) in &documents {
+            query = query.bind(view_id);
+        }
+
+        let mut fields: HashMap<String, Vec<(FieldName, String)>> = HashMap::new();
+        for (view_id, name, value) in query
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?
+        {
+            fields.entry(view_id).or_default().push((name, value));
+        }
+
+        let matches = documents
+            .into_iter()
+            .map(|(document_id, view_id, schema_id, _)| SearchMatch {
+                document_id: document_id.parse().unwrap(),
+                schema_id: schema_id.parse().unwrap(),
+                fields: fields.remove(&view_id).unwrap_or_default(),
+                view_id: view_id.parse().unwrap(),
+            })
+            .collect();
+
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::test_utils::{add_document, add_schema, test_runner, TestNode};
+
+    #[rstest]
+    fn ranks_matches_across_schemas(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let songs = add_schema(
+                &mut node,
+                "songs",
+                vec![("title", FieldType::String), ("lyrics", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let artists = add_schema(
+                &mut node,
+                "artists",
+                vec![("name", FieldType::String), ("albums", FieldType::Integer)],
+                &key_pair,
+            )
+            .await;
+
+            let song_view_id = add_document(
+                &mut node,
+                songs.id(),
+                vec![
+                    ("title", "Panda Party".into()),
+                    ("lyrics", "Dance like a panda!".into()),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let artist_view_id = add_document(
+                &mut node,
+                artists.id(),
+                vec![("name", "The Pandas".into()), ("albums", 3.into())],
+                &key_pair,
+            )
+            .await;
+
+            add_document(
+                &mut node,
+                artists.id(),
+                vec![("name", "The Bears".into()), ("albums", 1.into())],
+                &key_pair,
+            )
+            .await;
+
+            let schema_ids = vec![songs.id().to_owned(), artists.id().to_owned()];
+
+            // Documents of both schemas match, ranked by number of matching fields
+            let matches = node
+                .context
+                .store
+                .search("PANDA", &schema_ids, 10, 0)
+                .await
+                .unwrap();
+            assert_eq!(matches.len(), 2);
+            assert_eq!(matches[0].view_id, song_view_id);
+            assert_eq!(matches[0].schema_id, *songs.id());
+            assert_eq!(
+                matches[0].fields,
+                vec![
+                    ("lyrics".to_string(), "Dance like a panda!".to_string()),
+                    ("title".to_string(), "Panda Party".to_string())
+                ]
+            );
+            assert_eq!(matches[1].view_id, artist_view_id);
+
+            // Only requested schemas are searched
+            let matches = node
+                .context
+                .store
+                .search("panda", &[artists.id().to_owned()], 10, 0)
+                .await
+                .unwrap();
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].view_id, artist_view_id);
+
+            // Results are paginated
+            let matches = node
+                .context
+                .store
+                .search("panda", &schema_ids, 10, 1)
+                .await
+                .unwrap();
+            assert_eq!(matches.len(), 1);
+            assert_eq!(matches[0].view_id, artist_view_id);
+
+            // Wildcard characters are matched literally
+            let matches = node
+                .context
+                .store
+                .search("%", &schema_ids, 10, 0)
+                .await
+                .unwrap();
+            assert!(matches.is_empty());
+        });
+    }
+}
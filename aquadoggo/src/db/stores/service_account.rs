@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use sqlx::{query, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+
+/// Methods to interact with the `service_accounts` table in the database.
+///
+/// Service accounts hold key pairs the node generated to sign entries on behalf of trusted
+/// clients, their private keys are persisted to keep the same identity across restarts.
+///
+/// Private keys are stored unencrypted, they are only protected at rest when the SQLite database
+/// is encrypted with a `database_key`.
+impl SqlStore {
+    /// Returns the hex-encoded private key of a service account or `None` if it does not exist.
+    pub async fn get_service_account_key(
+        &self,
+        name: &str,
+    ) -> Result<Option<String>, SqlStoreError> {
+        query_scalar(
+            "
+            SELECT
+                private_key
+            FROM
+                service_accounts
+            WHERE
+                name = $1
+            ",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))
+    }
+
+    /// Persists the hex-encoded private key of a new service account.
+    pub async fn insert_service_account_key(
+        &self,
+        name: &str,
+        private_key: &str,
+    ) -> Result<(), SqlStoreError> {
+        query(
+            "
+            INSERT INTO
+                service_accounts (
+                    name,
+                    private_key
+                )
+            VALUES
+                ($1, $2)
+            ",
+        )
+        .bind(name)
+        .bind(private_key)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{test_runner, TestNode};
+
+    #[test]
+    fn insert_and_get_service_account_key() {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+            assert_eq!(
+                store.get_service_account_key("backend").await.unwrap(),
+                None
+            );
+
+            store
+                .insert_service_account_key("backend", "abcd")
+                .await
+                .unwrap();
+            assert_eq!(
+                store.get_service_account_key("backend").await.unwrap(),
+                Some("abcd".to_string())
+            );
+
+            // Names are unique
+            assert!(store
+                .insert_service_account_key("backend", "ef01")
+                .await
+                .is_err());
+        });
+    }
+}
@@ -0,0 +1,266 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::storage_provider::error::DocumentStorageError;
+use sqlx::{query, Any, Transaction};
+
+use crate::db::SqlStore;
+use crate::replication::now_millis;
+
+/// Duration for which the previous state of documents is kept in addition to the lifetime of
+/// snapshots, queries which started right before their snapshot expired can still finish.
+const HISTORY_GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Methods to query collections as they were at an earlier point in time.
+///
+/// Snapshots are UNIX timestamps in milliseconds. When snapshots are enabled the previous state of
+/// every document is recorded in the `document_history` table whenever the document gets
+/// created, updated or deleted. This allows us to reconstruct the `documents` table for any
+/// snapshot within the configured lifetime.
+impl SqlStore {
+    /// Returns a snapshot of the current state of the store, `None` if snapshots are disabled.
+    pub fn acquire_snapshot(&self) -> Option<u64> {
+        self.snapshot_ttl.map(|_| now_millis())
+    }
+
+    /// Returns true if collections can still be queried as they were at the given snapshot.
+    pub fn is_valid_snapshot(&self, snapshot: u64) -> bool {
+        match self.snapshot_ttl {
+            Some(ttl) => {
+                let now = now_millis();
+                snapshot <= now && now - snapshot <= ttl.as_millis() as u64
+            }
+            None => false,
+        }
+    }
+}
+
+/// Records the current state of a document in the `document_history` table before it gets
+/// replaced by the given one, nothing is recorded if the state does not change.
+///
+/// Documents which did not exist before are recorded without a previous view. Records which are
+/// not needed by any valid snapshot anymore are removed at the same time.
+pub(super) async fn record_document_history(
+    tx: &mut Transaction<'_, Any>,
+    document: &impl AsDocument,
+    ttl: Duration,
+) -> Result<(), DocumentStorageError> {
+    let changed_at = now_millis() as i64;
+
+    query(
+        "
+        INSERT INTO
+            document_history (
+                document_id,
+                previous_view_id,
+                previous_is_deleted,
+                schema_id,
+                changed_at
+            )
+        SELECT
+            $1,
+            previous.document_view_id,
+            previous.is_deleted,
+            $4,
+            $5
+        FROM
+            (SELECT 1 AS document) changed
+            LEFT JOIN documents previous
+                ON previous.document_id = $1
+        WHERE
+            previous.document_id IS NULL
+            OR previous.document_view_id != $2
+            OR previous.is_deleted != $3
+        ON CONFLICT DO NOTHING
+        ",
+    )
+    .bind(document.id().as_str())
+    .bind(document.view_id().to_string())
+    .bind(document.is_deleted())
+    .bind(document.schema_id().to_string())
+    .bind(changed_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+    let expired_at = changed_at - (ttl + HISTORY_GRACE_PERIOD).as_millis() as i64;
+
+    query(
+        "
+        DELETE FROM
+            document_history
+        WHERE
+            document_history.changed_at < $1
+        ",
+    )
+    .bind(expired_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+
+    Ok(())
+}
+
+/// Returns SQL selecting the `documents` table as it was at the given snapshot, or the current
+/// `documents` table if no snapshot is given.
+///
+/// Documents which did not change since the snapshot are taken as they are, for all others we
+/// take the previous state of their first change after the snapshot. Documents created after the
+/// snapshot are left out.
+pub(super) fn documents_sql(snapshot: Option<u64>) -> String {
+    let snapshot = match snapshot {
+        Some(snapshot) => snapshot,
+        None => return "documents".to_string(),
+    };
+
+    format!(
+        r#"
+        (
+            SELECT
+                documents.document_id,
+                documents.document_view_id,
+                documents.is_deleted,
+                documents.schema_id
+            FROM
+                documents
+            WHERE
+                NOT EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        document_history
+                    WHERE
+                        document_history.document_id = documents.document_id
+                        AND document_history.changed_at > {snapshot}
+                )
+
+            UNION ALL
+
+            SELECT
+                document_history.document_id,
+                document_history.previous_view_id,
+                document_history.previous_is_deleted,
+                document_history.schema_id
+            FROM
+                document_history
+            WHERE
+                document_history.previous_view_id IS NOT NULL
+                AND document_history.changed_at = (
+                    SELECT
+                        MIN(first_change.changed_at)
+                    FROM
+                        document_history first_change
+                    WHERE
+                        first_change.document_id = document_history.document_id
+                        AND first_change.changed_at > {snapshot}
+                )
+        ) documents
+        "#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::{FieldType, SchemaId};
+    use sqlx::query_scalar;
+
+    use crate::config::Configuration;
+    use crate::test_utils::{
+        add_document, add_schema, test_runner_with_manager, update_document, TestNode,
+        TestNodeManager,
+    };
+
+    use super::documents_sql;
+
+    /// Returns the view ids of all documents of a schema at the given snapshot.
+    async fn view_ids_at(node: &TestNode, schema_id: &SchemaId, snapshot: u64) -> Vec<String> {
+        query_scalar(&format!(
+            "SELECT documents.document_view_id FROM {} WHERE documents.schema_id = $1",
+            documents_sql(Some(snapshot))
+        ))
+        .bind(schema_id.to_string())
+        .fetch_all(&node.context.store.pool)
+        .await
+        .unwrap()
+    }
+
+    /// Acquires a snapshot which is distinct from the points in time of changes before and after.
+    async fn acquire_snapshot(node: &TestNode) -> u64 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let snapshot = node.context.store.acquire_snapshot().unwrap();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        snapshot
+    }
+
+    #[test]
+    fn documents_at_snapshot() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let config = Configuration {
+                graphql_snapshot_ttl: 300,
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+            let key_pair = KeyPair::new();
+
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let before_create = acquire_snapshot(&node).await;
+
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Pub".into()))],
+                &key_pair,
+            )
+            .await;
+
+            let before_update = acquire_snapshot(&node).await;
+
+            let updated_view_id = update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Bar".into()))],
+                &view_id,
+                &key_pair,
+            )
+            .await;
+
+            let after_update = acquire_snapshot(&node).await;
+
+            // Documents created after the snapshot are left out
+            assert!(view_ids_at(&node, schema.id(), before_create)
+                .await
+                .is_empty());
+
+            // Documents updated after the snapshot are shown in their previous state
+            assert_eq!(
+                view_ids_at(&node, schema.id(), before_update).await,
+                vec![view_id.to_string()]
+            );
+
+            // Documents which did not change since the snapshot are shown as they are
+            assert_eq!(
+                view_ids_at(&node, schema.id(), after_update).await,
+                vec![updated_view_id.to_string()]
+            );
+
+            // Snapshots expire after the configured duration
+            let store = &node.context.store;
+            assert!(store.is_valid_snapshot(before_create));
+            assert!(!store.is_valid_snapshot(before_create - 301_000));
+            assert!(!store.is_valid_snapshot(after_update + 60_000));
+        });
+    }
+}
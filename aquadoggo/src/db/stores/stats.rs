@@ -0,0 +1,112 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::DocumentId;
+use sqlx::{query, query_as};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::SqlStore;
+
+/// Statistics about the history of a document, maintained while it is materialized.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentStats {
+    /// Number of UPDATE operations applied to the document.
+    pub update_count: u64,
+
+    /// Number of distinct public keys which authored operations of the document.
+    pub author_count: u64,
+}
+
+/// Methods to interact with the `document_stats` table in the database.
+impl SqlStore {
+    /// Sets the statistics of a document, replacing the previous ones.
+    pub async fn set_document_stats(
+        &self,
+        document_id: &DocumentId,
+        stats: &DocumentStats,
+    ) -> Result<(), SqlStoreError> {
+        query(
+            "
+            INSERT INTO
+                document_stats (
+                    document_id,
+                    update_count,
+                    author_count
+                )
+            VALUES
+                ($1, $2, $3)
+            ON CONFLICT (document_id) DO UPDATE SET
+                update_count = excluded.update_count,
+                author_count = excluded.author_count
+            ",
+        )
+        .bind(document_id.as_str())
+        .bind(stats.update_count as i64)
+        .bind(stats.author_count as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Returns the statistics of a document or `None` if they were not recorded.
+    pub async fn get_document_stats(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<Option<DocumentStats>, SqlStoreError> {
+        let row: Option<(i64, i64)> = query_as(
+            "
+            SELECT
+                update_count,
+                author_count
+            FROM
+                document_stats
+            WHERE
+                document_id = $1
+            ",
+        )
+        .bind(document_id.as_str())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(row.map(|(update_count, author_count)| DocumentStats {
+            update_count: update_count as u64,
+            author_count: author_count as u64,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+
+    use crate::test_utils::{test_runner, TestNode};
+
+    use super::DocumentStats;
+
+    #[rstest]
+    fn set_and_get_stats(#[from(random_document_id)] document_id: DocumentId) {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+            assert_eq!(store.get_document_stats(&document_id).await.unwrap(), None);
+
+            for update_count in [1, 2] {
+                let stats = DocumentStats {
+                    update_count,
+                    author_count: 1,
+                };
+                store
+                    .set_document_stats(&document_id, &stats)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    store.get_document_stats(&document_id).await.unwrap(),
+                    Some(stats)
+                );
+            }
+        });
+    }
+}
@@ -131,6 +131,33 @@ impl SqlStore {
 
         Ok(tasks)
     }
+
+    /// Returns the number of "pending" tasks for each materialization service worker.
+    ///
+    /// Workers without any pending tasks are not included.
+    pub async fn get_task_counts(&self) -> Result<Vec<(String, u64)>, SqlStoreError> {
+        let counts = query_as::<_, (String, i64)>(
+            "
+            SELECT
+                name,
+                COUNT(*)
+            FROM
+                tasks
+            GROUP BY
+                name
+            ORDER BY
+                name
+            ",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(counts
+            .into_iter()
+            .map(|(name, count)| (name, count as u64))
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -166,6 +193,31 @@ mod tests {
         });
     }
 
+    #[rstest]
+    fn count_tasks(document_id: DocumentId, document_view_id: DocumentViewId) {
+        test_runner(|node: TestNode| async move {
+            let result = node.context.store.get_task_counts().await;
+            assert_eq!(result.unwrap(), vec![]);
+
+            for task in [
+                Task::new("reduce", TaskInput::DocumentId(document_id.clone())),
+                Task::new(
+                    "reduce",
+                    TaskInput::DocumentViewId(document_view_id.clone()),
+                ),
+                Task::new("dependency", TaskInput::DocumentViewId(document_view_id)),
+            ] {
+                node.context.store.insert_task(&task).await.unwrap();
+            }
+
+            let result = node.context.store.get_task_counts().await;
+            assert_eq!(
+                result.unwrap(),
+                vec![("dependency".to_string(), 1), ("reduce".to_string(), 2)]
+            );
+        });
+    }
+
     #[rstest]
     fn avoid_duplicates(document_id: DocumentId) {
         test_runner(|node: TestNode| async move {
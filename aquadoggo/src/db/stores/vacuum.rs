@@ -0,0 +1,210 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::entry::LogId;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::OperationId;
+use p2panda_rs::schema::SchemaId;
+use sqlx::{query, query_as, query_scalar};
+
+use crate::db::errors::SqlStoreError;
+use crate::db::models::EntryRow;
+use crate::db::types::StorageEntry;
+use crate::db::SqlStore;
+
+/// Methods to find and remove data which is not connected to any known document anymore, for
+/// example after an ingest failed half-way.
+impl SqlStore {
+    /// Returns the ids of all operations inserted before the given UNIX timestamp which are stored
+    /// without the entry signing them.
+    pub async fn get_operations_without_entry(
+        &self,
+        inserted_before: u64,
+    ) -> Result<Vec<OperationId>, SqlStoreError> {
+        let operation_ids: Vec<String> = query_scalar(
+            "
+            SELECT
+                operations_v1.operation_id
+            FROM
+                operations_v1
+            LEFT JOIN
+                entries
+            ON
+                entries.entry_hash = operations_v1.operation_id
+            WHERE
+                entries.entry_hash IS NULL
+            AND
+                operations_v1.inserted_at < $1
+            ",
+        )
+        .bind(inserted_before as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(operation_ids
+            .iter()
+            .map(|id| id.parse().expect("Invalid operation id stored in database"))
+            .collect())
+    }
+
+    /// Returns all entries inserted before the given UNIX timestamp which are stored without their
+    /// operation.
+    pub async fn get_entries_without_operation(
+        &self,
+        inserted_before: u64,
+    ) -> Result<Vec<StorageEntry>, SqlStoreError> {
+        let entries = query_as::<_, EntryRow>(
+            "
+            SELECT
+                entries.public_key,
+                entries.entry_bytes,
+                entries.entry_hash,
+                entries.log_id,
+                entries.payload_bytes,
+                entries.payload_hash,
+                entries.seq_num
+            FROM
+                entries
+            LEFT JOIN
+                operations_v1
+            ON
+                operations_v1.operation_id = entries.entry_hash
+            WHERE
+                operations_v1.operation_id IS NULL
+            AND
+                entries.inserted_at < $1
+            ",
+        )
+        .bind(inserted_before as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(entries.into_iter().map(|row| row.into()).collect())
+    }
+
+    /// Returns logs which contain entries and operations inserted before the given UNIX timestamp
+    /// but are not registered, together with the schema and document derived from their
+    /// operations.
+    pub async fn get_unregistered_logs(
+        &self,
+        inserted_before: u64,
+    ) -> Result<Vec<(PublicKey, LogId, SchemaId, DocumentId)>, SqlStoreError> {
+        let rows: Vec<(String, String, String, String)> = query_as(
+            "
+            SELECT DISTINCT
+                entries.public_key,
+                entries.log_id,
+                operations_v1.schema_id,
+                operations_v1.document_id
+            FROM
+                entries
+            JOIN
+                operations_v1
+            ON
+                operations_v1.operation_id = entries.entry_hash
+            LEFT JOIN
+                logs
+            ON
+                logs.public_key = entries.public_key
+            AND
+                logs.log_id = entries.log_id
+            WHERE
+                logs.log_id IS NULL
+            AND
+                entries.inserted_at < $1
+            ",
+        )
+        .bind(inserted_before as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(public_key, log_id, schema_id, document_id)| {
+                (
+                    public_key
+                        .parse()
+                        .expect("Invalid public key stored in database"),
+                    log_id.parse().expect("Invalid log id stored in database"),
+                    schema_id
+                        .parse()
+                        .expect("Invalid schema id stored in database"),
+                    document_id
+                        .parse()
+                        .expect("Invalid document id stored in database"),
+                )
+            })
+            .collect())
+    }
+
+    /// Returns the id of the document a registered log belongs to.
+    pub async fn get_document_id_by_log(
+        &self,
+        public_key: &PublicKey,
+        log_id: &LogId,
+    ) -> Result<Option<DocumentId>, SqlStoreError> {
+        let document_id: Option<String> = query_scalar(
+            "
+            SELECT
+                logs.document
+            FROM
+                logs
+            WHERE
+                logs.public_key = $1
+            AND
+                logs.log_id = $2
+            ",
+        )
+        .bind(public_key.to_string())
+        .bind(log_id.as_u64().to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(document_id.map(|id| id.parse().expect("Invalid document id stored in database")))
+    }
+
+    /// Remove an operation and its fields from the database.
+    pub async fn remove_operation(&self, operation_id: &OperationId) -> Result<(), SqlStoreError> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        query("DELETE FROM operation_fields_v1 WHERE operation_id = $1")
+            .bind(operation_id.as_str())
+            .execute(&mut tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        query("DELETE FROM operations_v1 WHERE operation_id = $1")
+            .bind(operation_id.as_str())
+            .execute(&mut tx)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        tx.commit()
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Remove an entry from the database.
+    pub async fn remove_entry(&self, entry_hash: &Hash) -> Result<(), SqlStoreError> {
+        query("DELETE FROM entries WHERE entry_hash = $1")
+            .bind(entry_hash.as_str())
+            .execute(&self.pool)
+            .await
+            .map_err(|err| SqlStoreError::Transaction(err.to_string()))?;
+
+        self.log_cache.clear();
+
+        Ok(())
+    }
+}
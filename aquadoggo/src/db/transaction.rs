@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Explicit transactions with configurable isolation level and retries on serialization failures.
+use std::fmt::Display;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{bail, Error};
+use log::debug;
+use sqlx::any::AnyKind;
+use sqlx::{query, Any, Transaction};
+
+use crate::db::SqlStore;
+
+/// Delay before the first retry of a failed transaction, doubles with every further retry.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(10);
+
+/// Error messages of PostgreSQL for serialization failures (40001) and deadlocks (40P01).
+///
+/// Storage errors of `p2panda-rs` only carry the message of the original database error, we
+/// detect the failures by their message.
+const RETRYABLE_ERRORS: [&str; 2] = ["could not serialize access", "deadlock detected"];
+
+/// Isolation level of transactions in PostgreSQL databases.
+///
+/// SQLite always runs transactions serializable, the isolation level has no effect there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// Default isolation level of PostgreSQL.
+    #[default]
+    ReadCommitted,
+
+    /// All statements of a transaction see the same snapshot of the database.
+    RepeatableRead,
+
+    /// Transactions behave as if they ran one after another, concurrent transactions touching
+    /// the same rows fail and get retried.
+    Serializable,
+}
+
+impl IsolationLevel {
+    /// Returns the isolation level as used in configuration files.
+    pub fn as_str(&self) -> &str {
+        match self {
+            IsolationLevel::ReadCommitted => "read-committed",
+            IsolationLevel::RepeatableRead => "repeatable-read",
+            IsolationLevel::Serializable => "serializable",
+        }
+    }
+
+    fn as_sql(&self) -> &str {
+        match self {
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+impl FromStr for IsolationLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read-committed" => Ok(IsolationLevel::ReadCommitted),
+            "repeatable-read" => Ok(IsolationLevel::RepeatableRead),
+            "serializable" => Ok(IsolationLevel::Serializable),
+            _ => bail!("Unknown isolation level '{s}'"),
+        }
+    }
+}
+
+impl Display for IsolationLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Isolation level and retry behaviour of multi-statement store operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransactionConfig {
+    /// Isolation level of transactions in PostgreSQL databases.
+    pub isolation_level: IsolationLevel,
+
+    /// Maximum number of retries of a transaction which failed due to a serialization failure or
+    /// deadlock in PostgreSQL databases.
+    pub max_retries: u32,
+}
+
+impl Default for TransactionConfig {
+    fn default() -> Self {
+        Self {
+            isolation_level: IsolationLevel::default(),
+            max_retries: 3,
+        }
+    }
+}
+
+/// Returns true if the error message belongs to a transaction which can be retried.
+fn is_retryable(message: &str) -> bool {
+    RETRYABLE_ERRORS
+        .iter()
+        .any(|retryable| message.contains(retryable))
+}
+
+impl SqlStore {
+    /// Returns true if the store is backed by a PostgreSQL database.
+    pub(crate) fn is_postgres(&self) -> bool {
+        self.pool.any_kind() == AnyKind::Postgres
+    }
+
+    /// Start a transaction with the configured isolation level.
+    pub(crate) async fn begin(&self) -> Result<Transaction<'static, Any>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        if self.is_postgres() && self.transactions.isolation_level != IsolationLevel::default() {
+            query(&format!(
+                "SET TRANSACTION ISOLATION LEVEL {}",
+                self.transactions.isolation_level.as_sql()
+            ))
+            .execute(&mut tx)
+            .await?;
+        }
+
+        Ok(tx)
+    }
+
+    /// Run a transaction again when it failed due to a serialization failure or deadlock.
+    ///
+    /// The given function needs to start, run and commit the whole transaction. Retries only
+    /// happen for PostgreSQL databases, with an increasing delay between them.
+    pub(crate) async fn retry<T, E, F, Fut>(&self, mut transaction: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Display,
+    {
+        let mut retries = 0;
+
+        loop {
+            match transaction().await {
+                Err(err)
+                    if self.is_postgres()
+                        && retries < self.transactions.max_retries
+                        && is_retryable(&err.to_string()) =>
+                {
+                    retries += 1;
+                    debug!("Retry transaction ({}) after error: {}", retries, err);
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(retries - 1)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use crate::test_utils::{test_runner, TestNode};
+
+    use super::{is_retryable, IsolationLevel};
+
+    #[test]
+    fn parses_isolation_levels() {
+        for level in [
+            IsolationLevel::ReadCommitted,
+            IsolationLevel::RepeatableRead,
+            IsolationLevel::Serializable,
+        ] {
+            assert_eq!(level.as_str().parse::<IsolationLevel>().unwrap(), level);
+        }
+
+        assert!("snapshot".parse::<IsolationLevel>().is_err());
+    }
+
+    #[test]
+    fn detects_retryable_errors() {
+        assert!(is_retryable(
+            "error returned from database: could not serialize access due to concurrent update"
+        ));
+        assert!(is_retryable(
+            "error returned from database: deadlock detected"
+        ));
+        assert!(!is_retryable(
+            "error returned from database: UNIQUE constraint failed: documents.document_id"
+        ));
+    }
+
+    #[test]
+    fn runs_transactions() {
+        test_runner(|node: TestNode| async move {
+            let store = &node.context.store;
+            let attempts = &AtomicU32::new(0);
+
+            // Only transactions in PostgreSQL databases are retried
+            let result: Result<(), String> = store
+                .retry(|| async move {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err("could not serialize access due to concurrent update".to_string())
+                })
+                .await;
+            assert!(result.is_err());
+
+            let expected_attempts = if store.is_postgres() {
+                store.transactions.max_retries + 1
+            } else {
+                1
+            };
+            assert_eq!(attempts.load(Ordering::SeqCst), expected_attempts);
+
+            // Transactions can be started and committed
+            let tx = store.begin().await.unwrap();
+            tx.commit().await.unwrap();
+        });
+    }
+}
@@ -46,8 +46,27 @@ pub struct StorageEntry {
 }
 
 impl StorageEntry {
-    // We will need this again as soon as we implement a new replication protocol
-    #[allow(dead_code)]
+    /// Returns a new entry as it gets stored in the database.
+    pub fn new(
+        entry: &impl AsEntry,
+        encoded_entry: &EncodedEntry,
+        payload: Option<&EncodedOperation>,
+    ) -> Self {
+        Self {
+            public_key: entry.public_key().to_owned(),
+            log_id: entry.log_id().to_owned(),
+            seq_num: entry.seq_num().to_owned(),
+            skiplink: entry.skiplink().cloned(),
+            backlink: entry.backlink().cloned(),
+            payload_size: entry.payload_size(),
+            payload_hash: entry.payload_hash().to_owned(),
+            signature: entry.signature().to_owned(),
+            encoded_entry: encoded_entry.to_owned(),
+            payload: payload.cloned(),
+        }
+    }
+
+    /// Returns the encoded operation signed by this entry, if it is stored.
     pub fn payload(&self) -> Option<&EncodedOperation> {
         self.payload.as_ref()
     }
@@ -122,21 +141,12 @@ impl From<EntryRow> for StorageEntry {
                 .expect("Decode entry hex entry bytes from database"),
         );
         let entry = decode_entry(&encoded_entry).expect("Decoding encoded entry from database");
-        StorageEntry {
-            public_key: entry.public_key().to_owned(),
-            log_id: entry.log_id().to_owned(),
-            seq_num: entry.seq_num().to_owned(),
-            skiplink: entry.skiplink().cloned(),
-            backlink: entry.backlink().cloned(),
-            payload_size: entry.payload_size(),
-            payload_hash: entry.payload_hash().to_owned(),
-            signature: entry.signature().to_owned(),
-            encoded_entry,
-            payload: entry_row.payload_bytes.map(|payload| {
-                EncodedOperation::from_bytes(
-                    &hex::decode(payload).expect("Decode entry payload from database"),
-                )
-            }),
-        }
+        let payload = entry_row.payload_bytes.map(|payload| {
+            EncodedOperation::from_bytes(
+                &hex::decode(payload).expect("Decode entry payload from database"),
+            )
+        });
+
+        StorageEntry::new(&entry, &encoded_entry, payload.as_ref())
     }
 }
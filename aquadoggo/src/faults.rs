@@ -0,0 +1,263 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Injectable faults in the store and network layers for testing crash and retry paths.
+//!
+//! Faults are only injected when the `fault-injection` feature is enabled, otherwise
+//! `FaultInjector` is an empty type and all checks compile to nothing.
+#[cfg(feature = "fault-injection")]
+use std::collections::HashMap;
+#[cfg(feature = "fault-injection")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use p2panda_rs::storage_provider::error::{
+    DocumentStorageError, EntryStorageError, OperationStorageError,
+};
+
+/// Place in the code where a fault can be injected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultPoint {
+    /// `EntryStore::insert_entry` of `SqlStore`.
+    InsertEntry,
+
+    /// `EntryStore::get_entry` of `SqlStore`.
+    GetEntry,
+
+    /// `EntryStore::get_entry_at_seq_num` of `SqlStore`, used for looking up backlinks and
+    /// skiplinks.
+    GetEntryAtSeqNum,
+
+    /// `EntryStore::get_latest_entry` of `SqlStore`.
+    GetLatestEntry,
+
+    /// `OperationStore::insert_operation` of `SqlStore`.
+    InsertOperation,
+
+    /// `OperationStore::get_operation` of `SqlStore`.
+    GetOperation,
+
+    /// `SqlStore::insert_document`.
+    InsertDocument,
+
+    /// `DocumentStore::get_document` of `SqlStore`.
+    GetDocument,
+
+    /// Encoding of a replication message sent to another peer.
+    EncodeMessage,
+
+    /// Decoding of a replication message received from another peer.
+    DecodeMessage,
+}
+
+/// Fault which can be injected at a `FaultPoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "fault-injection"), allow(dead_code))]
+pub enum Fault {
+    /// Wait before continuing, for example to simulate lock contention.
+    ///
+    /// Codecs can not wait asynchronously, the delay blocks the thread of the connection.
+    Delay(Duration),
+
+    /// Fail with an error.
+    Error,
+
+    /// Silently skip the operation: writes are not persisted, reads return nothing and messages
+    /// are dropped.
+    Drop,
+}
+
+/// Outcome of checking a fault point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultOutcome {
+    /// No fault was injected or a delay passed, continue as usual.
+    Continue,
+
+    /// Skip the operation.
+    Drop,
+}
+
+/// Error returned at fault points where an error was injected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("Injected fault at {0:?}")]
+pub struct FaultError(pub FaultPoint);
+
+impl From<FaultError> for EntryStorageError {
+    fn from(err: FaultError) -> Self {
+        EntryStorageError::Custom(err.to_string())
+    }
+}
+
+impl From<FaultError> for OperationStorageError {
+    fn from(err: FaultError) -> Self {
+        OperationStorageError::FatalStorageError(err.to_string())
+    }
+}
+
+impl From<FaultError> for DocumentStorageError {
+    fn from(err: FaultError) -> Self {
+        DocumentStorageError::FatalStorageError(err.to_string())
+    }
+}
+
+#[cfg(feature = "fault-injection")]
+#[derive(Debug, Default)]
+struct FaultRules {
+    /// Injected faults with the number of times they still trigger, `None` for always.
+    faults: HashMap<FaultPoint, (Fault, Option<usize>)>,
+
+    /// Number of times each fault point was reached.
+    hits: HashMap<FaultPoint, usize>,
+}
+
+/// Injects faults at configured points, shared by all clones.
+#[cfg(feature = "fault-injection")]
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector(Arc<Mutex<FaultRules>>);
+
+#[cfg(feature = "fault-injection")]
+impl FaultInjector {
+    fn rules(&self) -> std::sync::MutexGuard<'_, FaultRules> {
+        self.0.lock().expect("Fault rules lock poisoned")
+    }
+
+    /// Inject a fault at the given point, triggering for the given number of times or always when
+    /// `None`.
+    pub fn inject(&self, point: FaultPoint, fault: Fault, times: Option<usize>) {
+        self.rules().faults.insert(point, (fault, times));
+    }
+
+    /// Remove the fault of the given point.
+    pub fn clear(&self, point: FaultPoint) {
+        self.rules().faults.remove(&point);
+    }
+
+    /// Remove all faults and reset hit counters.
+    pub fn reset(&self) {
+        let mut rules = self.rules();
+        rules.faults.clear();
+        rules.hits.clear();
+    }
+
+    /// Returns how often the given point was reached.
+    pub fn hits(&self, point: FaultPoint) -> usize {
+        self.rules().hits.get(&point).copied().unwrap_or_default()
+    }
+
+    /// Count a hit of the point and return the fault which should be applied.
+    pub fn trigger(&self, point: FaultPoint) -> Option<Fault> {
+        let mut rules = self.rules();
+        *rules.hits.entry(point).or_default() += 1;
+
+        let (fault, remaining) = rules.faults.get_mut(&point)?;
+        let fault = *fault;
+        match remaining {
+            Some(0) => return None,
+            Some(remaining) => *remaining -= 1,
+            None => (),
+        }
+
+        Some(fault)
+    }
+}
+
+/// Injects faults at configured points, shared by all clones.
+#[cfg(not(feature = "fault-injection"))]
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {}
+
+#[cfg(not(feature = "fault-injection"))]
+impl FaultInjector {
+    /// Count a hit of the point and return the fault which should be applied.
+    #[inline]
+    pub fn trigger(&self, _point: FaultPoint) -> Option<Fault> {
+        None
+    }
+}
+
+impl FaultInjector {
+    /// Apply a fault injected at the given point, waiting asynchronously for delays.
+    #[inline]
+    pub async fn check(&self, point: FaultPoint) -> Result<FaultOutcome, FaultError> {
+        match self.trigger(point) {
+            None => Ok(FaultOutcome::Continue),
+            Some(Fault::Delay(duration)) => {
+                tokio::time::sleep(duration).await;
+                Ok(FaultOutcome::Continue)
+            }
+            Some(Fault::Error) => Err(FaultError(point)),
+            Some(Fault::Drop) => Ok(FaultOutcome::Drop),
+        }
+    }
+
+    /// Apply a fault injected at the given point, blocking the thread for delays.
+    #[inline]
+    pub fn check_blocking(&self, point: FaultPoint) -> Result<FaultOutcome, FaultError> {
+        match self.trigger(point) {
+            None => Ok(FaultOutcome::Continue),
+            Some(Fault::Delay(duration)) => {
+                std::thread::sleep(duration);
+                Ok(FaultOutcome::Continue)
+            }
+            Some(Fault::Error) => Err(FaultError(point)),
+            Some(Fault::Drop) => Ok(FaultOutcome::Drop),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fault-injection"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Fault, FaultError, FaultInjector, FaultOutcome, FaultPoint};
+
+    #[tokio::test]
+    async fn trigger_faults() {
+        let faults = FaultInjector::default();
+        assert_eq!(
+            faults.check(FaultPoint::InsertEntry).await,
+            Ok(FaultOutcome::Continue)
+        );
+
+        // Faults trigger the given number of times
+        faults.inject(FaultPoint::InsertEntry, Fault::Error, Some(2));
+        for _ in 0..2 {
+            assert_eq!(
+                faults.check(FaultPoint::InsertEntry).await,
+                Err(FaultError(FaultPoint::InsertEntry))
+            );
+        }
+        assert_eq!(
+            faults.check(FaultPoint::InsertEntry).await,
+            Ok(FaultOutcome::Continue)
+        );
+        assert_eq!(faults.hits(FaultPoint::InsertEntry), 4);
+
+        // Faults are shared between clones
+        faults
+            .clone()
+            .inject(FaultPoint::GetEntry, Fault::Drop, None);
+        for _ in 0..3 {
+            assert_eq!(
+                faults.check_blocking(FaultPoint::GetEntry),
+                Ok(FaultOutcome::Drop)
+            );
+        }
+
+        faults.inject(
+            FaultPoint::GetDocument,
+            Fault::Delay(Duration::from_millis(1)),
+            None,
+        );
+        assert_eq!(
+            faults.check(FaultPoint::GetDocument).await,
+            Ok(FaultOutcome::Continue)
+        );
+
+        faults.reset();
+        assert_eq!(faults.hits(FaultPoint::InsertEntry), 0);
+        assert_eq!(
+            faults.check(FaultPoint::GetEntry).await,
+            Ok(FaultOutcome::Continue)
+        );
+    }
+}
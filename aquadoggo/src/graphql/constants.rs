@@ -11,6 +11,39 @@ pub const DOCUMENT_META: &str = "DocumentMeta";
 /// GraphQL object representing next arguments data.
 pub const NEXT_ARGS: &str = "NextArguments";
 
+/// GraphQL object representing materialization progress.
+pub const MATERIALIZER_PROGRESS: &str = "MaterializerProgress";
+
+/// GraphQL object representing bandwidth and connection metrics of a transport.
+pub const NETWORK_TRAFFIC: &str = "NetworkTraffic";
+
+/// GraphQL object representing information about the local node.
+pub const NODE_INFO: &str = "NodeInfo";
+
+/// GraphQL object representing a node-local annotation of a document.
+pub const ANNOTATION: &str = "Annotation";
+
+/// GraphQL object representing conflicting entries of the same author in a log.
+pub const LOG_FORK: &str = "LogFork";
+
+/// GraphQL object representing the completeness of the pieces of a blob.
+pub const BLOB_STATUS: &str = "BlobStatus";
+
+/// GraphQL object representing a blob derived from another blob by a transcoder.
+pub const BLOB_DERIVATIVE: &str = "BlobDerivative";
+
+/// GraphQL object representing the dependency graph of a document's views.
+pub const DEPENDENCY_GRAPH: &str = "DependencyGraph";
+
+/// GraphQL object representing a document matching a search.
+pub const SEARCH_RESULT: &str = "SearchResult";
+
+/// GraphQL object representing a page of the feed of document changes.
+pub const DOCUMENT_CHANGE_FEED: &str = "DocumentChangeFeed";
+
+/// GraphQL object representing the SDL and version of the served GraphQL schema.
+pub const GRAPHQL_SCHEMA_INFO: &str = "GraphQLSchemaInfo";
+
 /// GraphQL scalar type representing a public key.
 pub const PUBLIC_KEY: &str = "PublicKey";
 
@@ -25,12 +58,75 @@ pub const DOCUMENT_VIEW_ID: &str = "DocumentViewId";
 /// Prefix for query name where all documents of a particular schema can be retrieved.
 pub const QUERY_ALL_PREFIX: &str = "all_";
 
+/// Prefix for query name where many documents of a particular schema can be retrieved by their ids.
+pub const QUERY_BY_IDS_PREFIX: &str = "byIds_";
+
 /// Name of query to fetch next entry arguments.
 pub const NEXT_ARGS_QUERY: &str = "nextArgs";
 
+/// Name of query to fetch materialization progress.
+pub const MATERIALIZER_PROGRESS_QUERY: &str = "materializerProgress";
+
+/// Name of query to fetch bandwidth and connection metrics.
+pub const NETWORK_METRICS_QUERY: &str = "networkMetrics";
+
+/// Name of query to fetch information about the local node.
+pub const NODE_INFO_QUERY: &str = "nodeInfo";
+
+/// Name of query to fetch node-local annotations of a document.
+pub const ANNOTATIONS_QUERY: &str = "annotations";
+
+/// Argument string used for passing the id of the annotated document into a query.
+pub const ANNOTATIONS_DOCUMENT_ID_ARG: &str = "documentId";
+
+/// Name of query to fetch documents of a moderated schema waiting for approval.
+pub const HELD_DOCUMENTS_QUERY: &str = "heldDocuments";
+
+/// Argument string used for passing the id of a moderated schema into a query.
+pub const HELD_DOCUMENTS_SCHEMA_ID_ARG: &str = "schemaId";
+
+/// Name of query to check if a document exists.
+pub const DOCUMENT_EXISTS_QUERY: &str = "documentExists";
+
+/// Name of query to check if a document view exists.
+pub const VIEW_EXISTS_QUERY: &str = "viewExists";
+
+/// Name of query to fetch detected forks of logs.
+pub const LOG_FORKS_QUERY: &str = "logForks";
+
+/// Name of query to fetch the completeness of the pieces of a blob.
+pub const BLOB_STATUS_QUERY: &str = "blobStatus";
+
+/// Name of query to fetch blobs derived from a blob by transcoders.
+pub const BLOB_DERIVATIVES_QUERY: &str = "blobDerivatives";
+
+/// Name of query to fetch the dependency graph of a document's views.
+pub const DEPENDENCY_GRAPH_QUERY: &str = "dependencyGraph";
+
+/// Name of query to search documents across schemas.
+pub const SEARCH_QUERY: &str = "search";
+
+/// Name of query to fetch the feed of created, updated and deleted documents.
+pub const DOCUMENT_CHANGES_QUERY: &str = "documentChanges";
+
+/// Argument string used for passing the token of the last processed change into a query.
+pub const DOCUMENT_CHANGES_SINCE_ARG: &str = "since";
+
+/// Name of query to fetch the SDL and version of the served GraphQL schema.
+pub const GRAPHQL_SCHEMA_QUERY: &str = "graphqlSchema";
+
+/// Argument string used for passing the searched text into a query.
+pub const SEARCH_TEXT_ARG: &str = "text";
+
+/// Argument string used for passing the schemas to search into a query.
+pub const SEARCH_SCHEMAS_ARG: &str = "schemas";
+
 /// Argument string used for passing a document id into a query.
 pub const DOCUMENT_ID_ARG: &str = "id";
 
+/// Argument string used for passing a list of document ids into a query.
+pub const DOCUMENT_IDS_ARG: &str = "ids";
+
 /// Argument string used for passing a public key into a query.
 pub const PUBLIC_KEY_ARG: &str = "publicKey";
 
@@ -49,6 +145,9 @@ pub const PAGINATION_AFTER_ARG: &str = "after";
 /// Argument string used for passing number of paginated items requested to query.
 pub const PAGINATION_FIRST_ARG: &str = "first";
 
+/// Argument string used for requesting a snapshot of the collection to paginate over.
+pub const SNAPSHOT_ARG: &str = "snapshot";
+
 /// Argument string used for passing field to order by to query.
 pub const ORDER_BY_ARG: &str = "orderBy";
 
@@ -58,6 +157,9 @@ pub const ORDER_DIRECTION_ARG: &str = "orderDirection";
 /// Name of field where a collection of documents can be accessed.
 pub const DOCUMENTS_FIELD: &str = "documents";
 
+/// Name of field on a document lookup where the found document can be accessed.
+pub const DOCUMENT_FIELD: &str = "document";
+
 /// Name of field on a document where its fields can be accessed.
 pub const FIELDS_FIELD: &str = "fields";
 
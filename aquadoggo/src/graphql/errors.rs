@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::error::Error as StdError;
+
+use async_graphql::ServerError;
+use log::warn;
+use p2panda_rs::storage_provider::error::{
+    DocumentStorageError, EntryStorageError, LogStorageError, OperationStorageError,
+};
+
+use crate::db::errors::{BlobStoreError, SchemaStoreError, SqlStoreError};
+
+/// Message returned to clients instead of the details of an internal error.
+const MASKED_ERROR_MESSAGE: &str = "Internal server error";
+
+/// Returns true if the given error is raised by the database, the file system or another
+/// component of the node, its message might contain SQL queries or file paths.
+fn is_internal_error(error: &(dyn StdError + 'static)) -> bool {
+    error.is::<SqlStoreError>()
+        || error.is::<SchemaStoreError>()
+        || error.is::<BlobStoreError>()
+        || error.is::<DocumentStorageError>()
+        || error.is::<EntryStorageError>()
+        || error.is::<LogStorageError>()
+        || error.is::<OperationStorageError>()
+        || error.is::<sqlx::Error>()
+        || error.is::<std::io::Error>()
+}
+
+/// Returns true if a GraphQL error got caused by an internal error instead of the request.
+///
+/// Errors created with a message for the client don't have a source and are never considered
+/// internal.
+fn is_internal(error: &ServerError) -> bool {
+    if let Some(err) = error.source::<anyhow::Error>() {
+        return err.chain().any(is_internal_error);
+    }
+
+    error.source::<SqlStoreError>().is_some()
+        || error.source::<SchemaStoreError>().is_some()
+        || error.source::<BlobStoreError>().is_some()
+        || error.source::<DocumentStorageError>().is_some()
+        || error.source::<EntryStorageError>().is_some()
+        || error.source::<LogStorageError>().is_some()
+        || error.source::<OperationStorageError>().is_some()
+        || error.source::<sqlx::Error>().is_some()
+        || error.source::<std::io::Error>().is_some()
+}
+
+/// Replace the messages of internal errors with a reference id.
+///
+/// The details are logged together with the reference id instead, operators can look them up
+/// when users report the error.
+pub fn mask_internal_errors(errors: &mut [ServerError]) {
+    for error in errors.iter_mut().filter(|error| is_internal(error)) {
+        let reference = hex::encode(rand::random::<[u8; 8]>());
+        warn!(
+            "GraphQL request failed with error {}: {}",
+            reference, error.message
+        );
+        error.message = format!("{} (reference {})", MASKED_ERROR_MESSAGE, reference);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{Error, Pos};
+    use p2panda_rs::storage_provider::error::DocumentStorageError;
+
+    use crate::db::errors::SqlStoreError;
+
+    use super::mask_internal_errors;
+
+    #[test]
+    fn masks_internal_errors() {
+        let mut errors = vec![
+            Error::from(SqlStoreError::Transaction(
+                "no such table: document_views".into(),
+            ))
+            .into_server_error(Pos::default()),
+            Error::from(anyhow::Error::from(
+                DocumentStorageError::FatalStorageError(
+                    "/var/lib/aquadoggo/db.sqlite3 is locked".into(),
+                ),
+            ))
+            .into_server_error(Pos::default()),
+            Error::new("Invalid schema id 'venues'").into_server_error(Pos::default()),
+            Error::from(anyhow::anyhow!("Invalid signature")).into_server_error(Pos::default()),
+        ];
+
+        mask_internal_errors(&mut errors);
+
+        assert!(errors[0]
+            .message
+            .starts_with("Internal server error (reference "));
+        assert!(errors[1]
+            .message
+            .starts_with("Internal server error (reference "));
+        assert_ne!(errors[0].message, errors[1].message);
+        assert_eq!(errors[2].message, "Invalid schema id 'venues'");
+        assert_eq!(errors[3].message, "Invalid signature");
+    }
+}
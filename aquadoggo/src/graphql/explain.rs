@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Query plans of the SQL generated for GraphQL collection queries, to find out why they are slow.
+use std::sync::{Arc, Mutex};
+
+use async_graphql::{value, Request, Value};
+use log::debug;
+
+use crate::db::stores::QueryPlan;
+
+/// Key of the request extension which asks for the query plans of a request.
+pub const EXPLAIN_EXTENSION: &str = "explain";
+
+/// Key of the response extension listing the SQL queries and their plans.
+pub const QUERY_PLANS_EXTENSION: &str = "queryPlans";
+
+/// Collects the query plans of all collections queried during one GraphQL request.
+///
+/// Clients tag a request by sending `"extensions": { "explain": true }` with it. Every queried
+/// collection then runs its query a second time with `EXPLAIN`, the generated SQL and the plans
+/// are logged and returned in the `queryPlans` extension of the response. This is only available
+/// in debug builds, release builds ignore the tag.
+#[derive(Clone, Debug, Default)]
+pub struct QueryPlans(Arc<Mutex<Vec<QueryPlan>>>);
+
+impl QueryPlans {
+    /// Returns true if the request asks for query plans and this is a debug build.
+    pub fn is_requested(request: &Request) -> bool {
+        cfg!(debug_assertions)
+            && request.extensions.get(EXPLAIN_EXTENSION) == Some(&Value::Boolean(true))
+    }
+
+    /// Keep the plan of a query.
+    pub fn push(&self, plan: QueryPlan) {
+        debug!("Query plan of {}\n{}", plan.sql, plan.plan.join("\n"));
+
+        self.0
+            .lock()
+            .expect("Could not acquire lock for query plans")
+            .push(plan);
+    }
+
+    /// Returns all collected plans for the response extensions.
+    pub fn to_value(&self) -> Value {
+        let plans = self
+            .0
+            .lock()
+            .expect("Could not acquire lock for query plans");
+
+        Value::List(
+            plans
+                .iter()
+                .map(|plan| value!({ "sql": plan.sql.trim(), "plan": plan.plan }))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Request, Value};
+
+    use crate::db::stores::QueryPlan;
+
+    use super::{QueryPlans, EXPLAIN_EXTENSION};
+
+    #[test]
+    fn tagged_requests() {
+        assert!(!QueryPlans::is_requested(&Request::new("{ a }")));
+
+        let mut request = Request::new("{ a }");
+        request
+            .extensions
+            .insert(EXPLAIN_EXTENSION.to_string(), Value::Boolean(true));
+        assert!(QueryPlans::is_requested(&request));
+    }
+
+    #[test]
+    fn collects_plans() {
+        let plans = QueryPlans::default();
+        plans.push(QueryPlan {
+            sql: "\n SELECT 1\n".into(),
+            plan: vec!["SCAN documents".into()],
+        });
+
+        assert_eq!(
+            plans.to_value(),
+            value!([{ "sql": "SELECT 1", "plan": ["SCAN documents"] }])
+        );
+    }
+}
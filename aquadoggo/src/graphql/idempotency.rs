@@ -0,0 +1,336 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Cache of publish responses, allowing clients to safely retry requests with an idempotency key.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
+
+use crate::graphql::responses::NextArguments;
+
+/// Maximum length of an idempotency key in bytes.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// Maximum number of requests which are remembered at the same time.
+const DEFAULT_MAX_REQUESTS: usize = 10_000;
+
+/// State of a request which was sent with an idempotency key.
+#[derive(Debug, Clone)]
+enum RequestState {
+    /// Request is still being processed.
+    Pending,
+
+    /// Request succeeded with this response.
+    Completed(NextArguments),
+}
+
+#[derive(Debug, Clone)]
+struct CachedRequest {
+    /// Hash of the entry which was published with this key.
+    entry_hash: Hash,
+
+    /// Time when the request was received first.
+    received_at: Instant,
+
+    state: RequestState,
+}
+
+/// Remembers responses of publish requests for a configurable window.
+///
+/// Clients retrying a request after a timeout would otherwise receive an error as the entry got
+/// already published and its sequence number is occupied. When a request carries an idempotency
+/// key the response of the first successful attempt is returned again instead.
+///
+/// Keys are scoped by the public key of the entry author. Failed requests are not cached, they can
+/// be retried with the same key.
+///
+/// Public keys are not verified before requests get registered, the oldest requests are
+/// forgotten when the cache is full.
+#[derive(Debug, Clone)]
+pub struct IdempotencyCache {
+    /// Duration for which responses are kept, caching is disabled when zero.
+    window: Duration,
+
+    /// Maximum number of remembered requests.
+    max_requests: usize,
+
+    requests: Arc<Mutex<HashMap<(PublicKey, String), CachedRequest>>>,
+}
+
+impl IdempotencyCache {
+    /// Returns a new cache keeping responses for the given window.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            max_requests: DEFAULT_MAX_REQUESTS,
+            requests: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Remember at most the given number of requests, defaults to 10.000.
+    pub fn with_max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
+    /// Returns `true` if responses are cached.
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero()
+    }
+
+    /// Register an incoming request with an idempotency key.
+    ///
+    /// Returns the cached response when a request with the same key and entry succeeded before.
+    /// Otherwise the request is marked as pending and the returned `PendingRequest` needs to be
+    /// completed with the response. Requests which are dropped before are forgotten again, so
+    /// they can be retried with the same key.
+    ///
+    /// Fails when the key is already used for another entry or when a request with the same key
+    /// is still being processed.
+    pub fn begin(&self, public_key: &PublicKey, key: &str, entry_hash: &Hash) -> Result<Begin> {
+        if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+            return Err(anyhow!(
+                "Idempotency key needs to be between 1 and {} bytes long",
+                MAX_IDEMPOTENCY_KEY_LENGTH
+            ));
+        }
+
+        if !self.is_enabled() {
+            return Ok(Begin::Pending(self.pending_request(public_key, key)));
+        }
+
+        let mut requests = self
+            .requests
+            .lock()
+            .expect("Idempotency cache lock poisoned");
+
+        // Forget requests which are older than the configured window
+        requests.retain(|_, request| request.received_at.elapsed() < self.window);
+
+        let cache_key = (*public_key, key.to_owned());
+        match requests.get(&cache_key) {
+            Some(request) if &request.entry_hash != entry_hash => Err(anyhow!(
+                "Idempotency key '{}' was already used for a different entry",
+                key
+            )),
+            Some(CachedRequest {
+                state: RequestState::Pending,
+                ..
+            }) => Err(anyhow!(
+                "Request with idempotency key '{}' is still being processed",
+                key
+            )),
+            Some(CachedRequest {
+                state: RequestState::Completed(response),
+                ..
+            }) => Ok(Begin::Cached(response.clone())),
+            None => {
+                // Make room for the new request by forgetting the oldest one
+                if requests.len() >= self.max_requests {
+                    let oldest = requests
+                        .iter()
+                        .min_by_key(|(_, request)| request.received_at)
+                        .map(|(cache_key, _)| cache_key.to_owned());
+                    if let Some(oldest) = oldest {
+                        requests.remove(&oldest);
+                    }
+                }
+
+                requests.insert(
+                    cache_key,
+                    CachedRequest {
+                        entry_hash: entry_hash.to_owned(),
+                        received_at: Instant::now(),
+                        state: RequestState::Pending,
+                    },
+                );
+                Ok(Begin::Pending(self.pending_request(public_key, key)))
+            }
+        }
+    }
+
+    fn pending_request(&self, public_key: &PublicKey, key: &str) -> PendingRequest {
+        PendingRequest {
+            cache: self.clone(),
+            public_key: *public_key,
+            key: key.to_owned(),
+            completed: false,
+        }
+    }
+
+    /// Remember the response of a successful request.
+    fn complete(&self, public_key: &PublicKey, key: &str, response: &NextArguments) {
+        let mut requests = self
+            .requests
+            .lock()
+            .expect("Idempotency cache lock poisoned");
+        if let Some(request) = requests.get_mut(&(*public_key, key.to_owned())) {
+            request.state = RequestState::Completed(response.clone());
+        }
+    }
+
+    /// Forget a failed request, so it can be retried with the same key.
+    fn abort(&self, public_key: &PublicKey, key: &str) {
+        let mut requests = self
+            .requests
+            .lock()
+            .expect("Idempotency cache lock poisoned");
+        requests.remove(&(*public_key, key.to_owned()));
+    }
+}
+
+/// Outcome of registering a request with an idempotency key.
+#[derive(Debug)]
+pub enum Begin {
+    /// Request succeeded before with this response.
+    Cached(NextArguments),
+
+    /// Request needs to be processed.
+    Pending(PendingRequest),
+}
+
+/// Request with an idempotency key which is being processed.
+///
+/// The request is forgotten when this gets dropped without being completed, for example when it
+/// failed or the client disconnected, so it can be retried with the same key.
+#[derive(Debug)]
+pub struct PendingRequest {
+    cache: IdempotencyCache,
+    public_key: PublicKey,
+    key: String,
+    completed: bool,
+}
+
+impl PendingRequest {
+    /// Remember the response of the successful request.
+    pub fn complete(mut self, response: &NextArguments) {
+        self.cache.complete(&self.public_key, &self.key, response);
+        self.completed = true;
+    }
+}
+
+impl Drop for PendingRequest {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.cache.abort(&self.public_key, &self.key);
+        }
+    }
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use p2panda_rs::entry::{LogId, SeqNum};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::random_hash;
+
+    use crate::graphql::responses::NextArguments;
+
+    use super::{Begin, IdempotencyCache, PendingRequest};
+
+    fn next_arguments() -> NextArguments {
+        NextArguments {
+            log_id: LogId::default().into(),
+            seq_num: SeqNum::new(2).unwrap().into(),
+            backlink: Some(random_hash().into()),
+            skiplink: None,
+        }
+    }
+
+    fn pending(begin: Begin) -> PendingRequest {
+        match begin {
+            Begin::Pending(pending_request) => pending_request,
+            Begin::Cached(_) => panic!("Expected pending request"),
+        }
+    }
+
+    #[test]
+    fn returns_cached_responses() {
+        let cache = IdempotencyCache::default();
+        let public_key = KeyPair::new().public_key();
+        let entry_hash = random_hash();
+
+        let pending_request = pending(cache.begin(&public_key, "abc", &entry_hash).unwrap());
+
+        // Concurrent requests with the same key are rejected
+        assert!(cache.begin(&public_key, "abc", &entry_hash).is_err());
+
+        let response = next_arguments();
+        pending_request.complete(&response);
+
+        match cache.begin(&public_key, "abc", &entry_hash).unwrap() {
+            Begin::Cached(cached) => assert_eq!(cached.seq_num, response.seq_num),
+            Begin::Pending(_) => panic!("Expected cached response"),
+        }
+
+        // Keys can not be reused for other entries
+        assert!(cache.begin(&public_key, "abc", &random_hash()).is_err());
+
+        // Keys are scoped by public key
+        let other_public_key = KeyPair::new().public_key();
+        let pending_request = pending(
+            cache
+                .begin(&other_public_key, "abc", &random_hash())
+                .unwrap(),
+        );
+
+        // Failed or cancelled requests can be retried
+        drop(pending_request);
+        pending(
+            cache
+                .begin(&other_public_key, "abc", &random_hash())
+                .unwrap(),
+        );
+
+        // Invalid keys are rejected
+        assert!(cache.begin(&public_key, "", &entry_hash).is_err());
+        assert!(cache
+            .begin(&public_key, &"a".repeat(256), &entry_hash)
+            .is_err());
+    }
+
+    #[test]
+    fn expires_responses() {
+        let cache = IdempotencyCache::new(Duration::from_millis(10));
+        let public_key = KeyPair::new().public_key();
+        let entry_hash = random_hash();
+
+        pending(cache.begin(&public_key, "abc", &entry_hash).unwrap()).complete(&next_arguments());
+        std::thread::sleep(Duration::from_millis(20));
+
+        // Key can be used again for another entry after the window passed
+        pending(cache.begin(&public_key, "abc", &random_hash()).unwrap());
+
+        // Nothing is cached when disabled
+        let cache = IdempotencyCache::new(Duration::ZERO);
+        pending(cache.begin(&public_key, "abc", &entry_hash).unwrap()).complete(&next_arguments());
+        pending(cache.begin(&public_key, "abc", &entry_hash).unwrap());
+    }
+
+    #[test]
+    fn forgets_oldest_requests_when_full() {
+        let cache = IdempotencyCache::default().with_max_requests(2);
+        let public_key = KeyPair::new().public_key();
+        let entry_hash = random_hash();
+
+        pending(cache.begin(&public_key, "a", &entry_hash).unwrap()).complete(&next_arguments());
+        std::thread::sleep(Duration::from_millis(1));
+        for key in ["b", "c"] {
+            pending(cache.begin(&public_key, key, &random_hash()).unwrap())
+                .complete(&next_arguments());
+        }
+
+        // First request got forgotten to make room for the last one
+        pending(cache.begin(&public_key, "a", &random_hash()).unwrap());
+    }
+}
@@ -11,64 +11,104 @@ use dynamic_graphql::InputObject;
 use p2panda_rs::schema::{FieldType, Schema};
 
 use crate::graphql::scalars::{
-    DocumentIdScalar, DocumentViewIdScalar, HexBytesScalar, PublicKeyScalar,
+    DateTimeScalar, DecimalScalar, DocumentIdScalar, DocumentViewIdScalar, HexBytesScalar,
+    PublicKeyScalar,
 };
 use crate::graphql::utils::filter_name;
+use crate::schema::SchemaProvider;
+
+/// Filter operators of fields holding a boolean or bytes.
+const EQUALITY_OPERATORS: [&str; 2] = ["eq", "notEq"];
+
+/// Filter operators of fields holding a relation.
+const RELATION_OPERATORS: [&str; 4] = ["eq", "notEq", "in", "notIn"];
+
+/// Filter operators of fields holding a number.
+const NUMBER_OPERATORS: [&str; 8] = ["in", "notIn", "eq", "notEq", "gte", "gt", "lte", "lt"];
+
+/// Filter operators of fields holding a string.
+const STRING_OPERATORS: [&str; 10] = [
+    "in",
+    "notIn",
+    "eq",
+    "notEq",
+    "gte",
+    "gt",
+    "lte",
+    "lt",
+    "contains",
+    "notContains",
+];
+
+/// Filter operators of fields holding a relation list.
+const RELATION_LIST_OPERATORS: [&str; 7] = [
+    "in", "notIn", "countEq", "countGte", "countGt", "countLte", "countLt",
+];
+
+/// Returns the name of the filter input type and the filter operators supported by a field.
+fn filter_capabilities(
+    field_type: &FieldType,
+    is_decimal: bool,
+    is_datetime: bool,
+) -> (&'static str, &'static [&'static str]) {
+    match field_type {
+        FieldType::Boolean => ("BooleanFilter", &EQUALITY_OPERATORS),
+        FieldType::Integer | FieldType::String if is_datetime => {
+            ("DateTimeFilter", &NUMBER_OPERATORS)
+        }
+        FieldType::Integer if is_decimal => ("DecimalFilter", &NUMBER_OPERATORS),
+        FieldType::Integer => ("IntegerFilter", &NUMBER_OPERATORS),
+        FieldType::Float => ("FloatFilter", &NUMBER_OPERATORS),
+        FieldType::String => ("StringFilter", &STRING_OPERATORS),
+        FieldType::Bytes => ("HexBytesFilter", &EQUALITY_OPERATORS),
+        FieldType::Relation(_) => ("RelationFilter", &RELATION_OPERATORS),
+        FieldType::RelationList(_) => ("RelationListFilter", &RELATION_LIST_OPERATORS),
+        FieldType::PinnedRelation(_) => ("PinnedRelationFilter", &RELATION_OPERATORS),
+        FieldType::PinnedRelationList(_) => ("PinnedRelationListFilter", &RELATION_LIST_OPERATORS),
+    }
+}
+
+/// Returns a description of a filter field ending with a `@filterOperators` directive which
+/// lists the supported operators.
+///
+/// Applied directives are not supported for dynamic schemas, we add the directive to the
+/// description where clients can parse it from the introspection result.
+fn filter_description(name: &str, operators: &[&str]) -> String {
+    let operators = operators
+        .iter()
+        .map(|operator| format!("\"{operator}\""))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    format!(
+        "Filter by values of the `{name}` field.\n\n\
+        @filterOperators(operators: [{operators}])"
+    )
+}
 
 /// Build a filter input object for a p2panda schema. It can be used to filter collection queries
 /// based on the values each document contains.
 ///
 /// The resulting input objects are used passed to the `filter` argument on a document collection
-/// query or list relation fields.
-pub fn build_filter_input_object(schema: &Schema) -> InputObject {
+/// query or list relation fields. Fields holding decimals are filtered with `Decimal` values,
+/// fields holding dates and times with `DateTime` values. The description of every field lists
+/// the operators it supports.
+pub fn build_filter_input_object(schema: &Schema, schema_provider: &SchemaProvider) -> InputObject {
     // Construct the document fields object which will be named `<schema_id>Filter`
     let schema_field_name = filter_name(schema.id());
-    let mut filter_input = InputObject::new(schema_field_name);
+    let mut filter_input = InputObject::new(schema_field_name)
+        .description(format!("Filter for `{}` documents.", schema.id().name()));
 
     // For every field in the schema we create a type with a resolver
     for (name, field_type) in schema.fields().iter() {
-        match field_type {
-            FieldType::Boolean => {
-                filter_input =
-                    filter_input.field(InputValue::new(name, TypeRef::named("BooleanFilter")));
-            }
-            FieldType::Integer => {
-                filter_input =
-                    filter_input.field(InputValue::new(name, TypeRef::named("IntegerFilter")));
-            }
-            FieldType::Float => {
-                filter_input =
-                    filter_input.field(InputValue::new(name, TypeRef::named("FloatFilter")));
-            }
-            FieldType::String => {
-                filter_input =
-                    filter_input.field(InputValue::new(name, TypeRef::named("StringFilter")));
-            }
-            FieldType::Bytes => {
-                filter_input =
-                    filter_input.field(InputValue::new(name, TypeRef::named("HexBytesFilter")));
-            }
-            FieldType::Relation(_) => {
-                filter_input =
-                    filter_input.field(InputValue::new(name, TypeRef::named("RelationFilter")));
-            }
-            FieldType::RelationList(_) => {
-                filter_input =
-                    filter_input.field(InputValue::new(name, TypeRef::named("RelationListFilter")));
-            }
-            FieldType::PinnedRelation(_) => {
-                filter_input = filter_input.field(InputValue::new(
-                    name,
-                    TypeRef::named("PinnedRelationFilter"),
-                ));
-            }
-            FieldType::PinnedRelationList(_) => {
-                filter_input = filter_input.field(InputValue::new(
-                    name,
-                    TypeRef::named("PinnedRelationListFilter"),
-                ));
-            }
-        };
+        let is_decimal = schema_provider.decimal_scale(schema, name).is_some();
+        let is_datetime = schema_provider.is_datetime_field(schema, name);
+        let (type_name, operators) = filter_capabilities(field_type, is_decimal, is_datetime);
+
+        filter_input = filter_input.field(
+            InputValue::new(name, TypeRef::named(type_name))
+                .description(filter_description(name, operators)),
+        );
     }
 
     filter_input
@@ -223,6 +263,72 @@ pub struct IntegerFilter {
     lt: Option<u64>,
 }
 
+/// A filter input type for decimal field values.
+#[derive(InputObject)]
+#[allow(dead_code)]
+pub struct DecimalFilter {
+    /// Filter by values in set.
+    #[graphql(name = "in")]
+    is_in: Option<Vec<DecimalScalar>>,
+
+    /// Filter by values not in set.
+    #[graphql(name = "notIn")]
+    is_not_in: Option<Vec<DecimalScalar>>,
+
+    /// Filter by equal to.
+    #[graphql(name = "eq")]
+    eq: Option<DecimalScalar>,
+
+    /// Filter by not equal to.
+    #[graphql(name = "notEq")]
+    not_eq: Option<DecimalScalar>,
+
+    /// Filter by greater than or equal to.
+    gte: Option<DecimalScalar>,
+
+    /// Filter by greater than.
+    gt: Option<DecimalScalar>,
+
+    /// Filter by less than or equal to.
+    lte: Option<DecimalScalar>,
+
+    /// Filter by less than.
+    lt: Option<DecimalScalar>,
+}
+
+/// A filter input type for date and time field values.
+#[derive(InputObject)]
+#[allow(dead_code)]
+pub struct DateTimeFilter {
+    /// Filter by values in set.
+    #[graphql(name = "in")]
+    is_in: Option<Vec<DateTimeScalar>>,
+
+    /// Filter by values not in set.
+    #[graphql(name = "notIn")]
+    is_not_in: Option<Vec<DateTimeScalar>>,
+
+    /// Filter by equal to.
+    #[graphql(name = "eq")]
+    eq: Option<DateTimeScalar>,
+
+    /// Filter by not equal to.
+    #[graphql(name = "notEq")]
+    not_eq: Option<DateTimeScalar>,
+
+    /// Filter by later than or equal to.
+    gte: Option<DateTimeScalar>,
+
+    /// Filter by later than.
+    gt: Option<DateTimeScalar>,
+
+    /// Filter by earlier than or equal to.
+    lte: Option<DateTimeScalar>,
+
+    /// Filter by earlier than.
+    lt: Option<DateTimeScalar>,
+}
+
 /// A filter input type for float field values.
 #[derive(InputObject)]
 #[allow(dead_code)]
@@ -322,6 +428,25 @@ pub struct RelationListFilter {
     /// Filter by values not in set.
     #[graphql(name = "notIn")]
     not_in: Option<Vec<DocumentIdScalar>>,
+    /// Filter by number of items equal to.
+    #[graphql(name = "countEq")]
+    count_eq: Option<u64>,
+
+    /// Filter by number of items greater than or equal to.
+    #[graphql(name = "countGte")]
+    count_gte: Option<u64>,
+
+    /// Filter by number of items greater than.
+    #[graphql(name = "countGt")]
+    count_gt: Option<u64>,
+
+    /// Filter by number of items less than or equal to.
+    #[graphql(name = "countLte")]
+    count_lte: Option<u64>,
+
+    /// Filter by number of items less than.
+    #[graphql(name = "countLt")]
+    count_lt: Option<u64>,
 }
 
 /// A filter input type for pinned relation list field values.
@@ -335,4 +460,23 @@ pub struct PinnedRelationListFilter {
     /// Filter by values not in set.
     #[graphql(name = "notIn")]
     not_in: Option<Vec<DocumentViewIdScalar>>,
+    /// Filter by number of items equal to.
+    #[graphql(name = "countEq")]
+    count_eq: Option<u64>,
+
+    /// Filter by number of items greater than or equal to.
+    #[graphql(name = "countGte")]
+    count_gte: Option<u64>,
+
+    /// Filter by number of items greater than.
+    #[graphql(name = "countGt")]
+    count_gt: Option<u64>,
+
+    /// Filter by number of items less than or equal to.
+    #[graphql(name = "countLte")]
+    count_lte: Option<u64>,
+
+    /// Filter by number of items less than.
+    #[graphql(name = "countLt")]
+    count_lt: Option<u64>,
 }
@@ -5,9 +5,10 @@ mod meta_filter;
 mod order;
 
 pub use fields_filter::{
-    build_filter_input_object, BooleanFilter, DocumentIdFilter, DocumentViewIdFilter, FloatFilter,
-    HexBytesFilter, IntegerFilter, OwnerFilter, PinnedRelationFilter, PinnedRelationListFilter,
-    RelationFilter, RelationListFilter, StringFilter,
+    build_filter_input_object, BooleanFilter, DateTimeFilter, DecimalFilter, DocumentIdFilter,
+    DocumentViewIdFilter, FloatFilter, HexBytesFilter, IntegerFilter, OwnerFilter,
+    PinnedRelationFilter, PinnedRelationListFilter, RelationFilter, RelationListFilter,
+    StringFilter,
 };
 pub use meta_filter::MetaFilterInputObject;
 pub use order::{build_order_enum_value, OrderDirection};
@@ -1,11 +1,11 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 //! Input value types used when specifying ordering parameters on collection queries.
-use async_graphql::dynamic::Enum;
+use async_graphql::dynamic::{Enum, EnumItem};
 use dynamic_graphql::Enum;
-use p2panda_rs::schema::Schema;
+use p2panda_rs::schema::{FieldType, Schema};
 
-use crate::graphql::utils::order_by_name;
+use crate::graphql::utils::{order_by_name, related_order_name};
 
 /// Meta fields by which a collection of documents can be sorted.
 // @TODO: Add more fields, see related issue: https://github.com/p2panda/aquadoggo/issues/326
@@ -21,25 +21,90 @@ pub enum OrderDirection {
     Descending,
 }
 
+/// Returns a description of an ordering field ending with an `@orderBy` directive which lists the
+/// supported ordering directions, like `@filterOperators` does for filter fields.
+fn order_description(description: &str) -> String {
+    format!("{description}\n\n@orderBy(directions: [\"ASC\", \"DESC\"])")
+}
+
 /// Dynamically build an enum input value which can be set to meta or application fields which a
 /// collection of documents can be ordered by.
+///
+/// Lists can not be ordered by, their items are marked as deprecated.
+///
+/// Collections can also be ordered by fields of documents related in (pinned) relation fields,
+/// these items are formatted as `<relation field>__<field of related document>`. The related
+/// schemas are looked up in `all_schema`.
 // @TODO: Distinct between enum and application field values. See related issue:
 // https://github.com/p2panda/aquadoggo/issues/333
-pub fn build_order_enum_value(schema: &Schema) -> Enum {
-    let mut input_values = Enum::new(order_by_name(schema.id()));
+pub fn build_order_enum_value(schema: &Schema, all_schema: &[Schema]) -> Enum {
+    let mut input_values = Enum::new(order_by_name(schema.id())).description(format!(
+        "Field by which a collection of `{}` documents can be ordered.",
+        schema.id().name()
+    ));
 
     // Add meta fields to ordering enum.
     //
     // Meta fields are uppercase formatted strings.
     for name in META_ORDER_FIELDS {
-        input_values = input_values.item(name)
+        let description = format!("Order by {}.", name.to_lowercase().replace('_', " "));
+        input_values =
+            input_values.item(EnumItem::new(name).description(order_description(&description)))
     }
 
     // Add document fields to ordering enum.
     //
     // Application fields are lowercase formatted strings.
-    for (name, _) in schema.fields().iter() {
-        input_values = input_values.item(name)
+    for (name, field_type) in schema.fields().iter() {
+        let item = match field_type {
+            FieldType::RelationList(_) | FieldType::PinnedRelationList(_) => EnumItem::new(name)
+                .description(format!(
+                    "The `{name}` field holds a list and can't be ordered by."
+                ))
+                .deprecation(Some("Ordering by relation lists is not supported")),
+            _ => EnumItem::new(name).description(order_description(&format!(
+                "Order by values of the `{name}` field."
+            ))),
+        };
+
+        input_values = input_values.item(item)
+    }
+
+    // Add fields of related documents to ordering enum.
+    for (name, field_type) in schema.fields().iter() {
+        let related_schema_id = match field_type {
+            FieldType::Relation(schema_id) | FieldType::PinnedRelation(schema_id) => schema_id,
+            _ => continue,
+        };
+
+        let related_schema = match all_schema
+            .iter()
+            .find(|related_schema| related_schema.id() == related_schema_id)
+        {
+            Some(related_schema) => related_schema,
+            None => continue,
+        };
+
+        for (related_name, related_type) in related_schema.fields().iter() {
+            let item_name = related_order_name(name, related_name);
+
+            // Skip lists and names which are already taken by application fields
+            if matches!(
+                related_type,
+                FieldType::RelationList(_) | FieldType::PinnedRelationList(_)
+            ) || schema.fields().contains_key(&item_name)
+            {
+                continue;
+            }
+
+            input_values = input_values.item(EnumItem::new(item_name).description(
+                order_description(&format!(
+                    "Order by values of the `{related_name}` field of the documents related \
+                    in the `{name}` field."
+                )),
+            ));
+        }
     }
+
     input_values
 }
@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Batched lookups of related documents during a single GraphQL query.
+//!
+//! Resolvers of relation fields of all documents in a list run concurrently. Instead of looking
+//! up every related document on its own, lookups requested at the same time are collected and
+//! combined into one query per schema.
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use async_graphql::Error;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::schema::SchemaId;
+
+use crate::db::types::StorageDocument;
+use crate::db::SqlStore;
+
+/// Document requested by a relation field.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum DocumentKey {
+    /// Current view of a document following the given schema.
+    Document(SchemaId, DocumentId),
+
+    /// Pinned view of a document.
+    View(DocumentViewId),
+}
+
+/// Lookup of all documents which were requested while it was waiting to run.
+type Batch = Shared<BoxFuture<'static, Result<(), Error>>>;
+
+#[derive(Default)]
+struct LoaderState {
+    /// Documents looked up during this query, `None` if they were not found.
+    loaded: HashMap<DocumentKey, Option<StorageDocument>>,
+
+    /// Documents waiting for the next batch.
+    pending: HashSet<DocumentKey>,
+
+    /// Next batch, it has not started yet.
+    batch: Option<Batch>,
+}
+
+/// Loads related documents in batches, scoped to a single GraphQL query.
+///
+/// Every document is looked up at most once per query, even when many documents relate to it.
+#[derive(Clone)]
+pub struct DocumentLoader {
+    store: SqlStore,
+    state: Arc<Mutex<LoaderState>>,
+}
+
+impl DocumentLoader {
+    pub fn new(store: SqlStore) -> Self {
+        Self {
+            store,
+            state: Arc::default(),
+        }
+    }
+
+    /// Returns the current view of a document following the given schema.
+    ///
+    /// Redirects are followed when the document was merged into another one. Deleted documents
+    /// are not returned.
+    pub async fn load_document(
+        &self,
+        schema_id: &SchemaId,
+        document_id: &DocumentId,
+    ) -> Result<Option<StorageDocument>, Error> {
+        self.load(DocumentKey::Document(
+            schema_id.to_owned(),
+            document_id.to_owned(),
+        ))
+        .await
+    }
+
+    /// Returns a pinned view of a document.
+    pub async fn load_view(
+        &self,
+        view_id: &DocumentViewId,
+    ) -> Result<Option<StorageDocument>, Error> {
+        self.load(DocumentKey::View(view_id.to_owned())).await
+    }
+
+    async fn load(&self, key: DocumentKey) -> Result<Option<StorageDocument>, Error> {
+        let batch = {
+            let mut state = self.state.lock().expect("Lock of loader state poisoned");
+            if let Some(document) = state.loaded.get(&key) {
+                return Ok(document.clone());
+            }
+
+            state.pending.insert(key.clone());
+            match &state.batch {
+                Some(batch) => batch.clone(),
+                None => {
+                    let batch = self.next_batch();
+                    state.batch = Some(batch.clone());
+                    batch
+                }
+            }
+        };
+
+        batch.await?;
+
+        let state = self.state.lock().expect("Lock of loader state poisoned");
+        Ok(state.loaded.get(&key).cloned().flatten())
+    }
+
+    /// Returns a batch looking up all pending documents once it runs.
+    fn next_batch(&self) -> Batch {
+        let store = self.store.clone();
+        let state = self.state.clone();
+
+        async move {
+            // Give resolvers of the other documents in a list the chance to join this batch
+            tokio::task::yield_now().await;
+
+            let keys: Vec<DocumentKey> = {
+                let mut state = state.lock().expect("Lock of loader state poisoned");
+                state.batch = None;
+                state.pending.drain().collect()
+            };
+
+            let loaded = load_batch(&store, keys).await?;
+            state
+                .lock()
+                .expect("Lock of loader state poisoned")
+                .loaded
+                .extend(loaded);
+
+            Ok(())
+        }
+        .boxed()
+        .shared()
+    }
+}
+
+/// Looks up all given documents with one query per schema and one for all pinned views.
+async fn load_batch(
+    store: &SqlStore,
+    keys: Vec<DocumentKey>,
+) -> Result<HashMap<DocumentKey, Option<StorageDocument>>, Error> {
+    let mut loaded = HashMap::new();
+    let mut document_ids: HashMap<SchemaId, Vec<DocumentId>> = HashMap::new();
+    let mut view_ids = Vec::new();
+
+    for key in keys {
+        match &key {
+            DocumentKey::Document(schema_id, document_id) => document_ids
+                .entry(schema_id.to_owned())
+                .or_default()
+                .push(document_id.to_owned()),
+            DocumentKey::View(view_id) => view_ids.push(view_id.to_owned()),
+        }
+
+        loaded.insert(key, None);
+    }
+
+    for (schema_id, document_ids) in document_ids {
+        // Follow redirects when related documents were merged into other ones
+        let redirects = store.resolve_document_redirects(&document_ids).await?;
+        let canonical_ids: Vec<DocumentId> = document_ids
+            .iter()
+            .map(|document_id| redirects.get(document_id).unwrap_or(document_id).to_owned())
+            .collect();
+
+        let documents: HashMap<DocumentId, StorageDocument> = store
+            .get_cached_documents_by_ids(&schema_id, &canonical_ids)
+            .await?
+            .into_iter()
+            .map(|document| (document.id().to_owned(), document))
+            .collect();
+
+        for (document_id, canonical_id) in document_ids.into_iter().zip(canonical_ids) {
+            loaded.insert(
+                DocumentKey::Document(schema_id.clone(), document_id),
+                documents.get(&canonical_id).cloned(),
+            );
+        }
+    }
+
+    for document in store.get_cached_documents_by_view_ids(&view_ids).await? {
+        loaded.insert(
+            DocumentKey::View(document.view_id().to_owned()),
+            Some(document),
+        );
+    }
+
+    Ok(loaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::try_join_all;
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+
+    use crate::test_utils::{
+        populate_store, populate_store_config, test_runner, PopulateStoreConfig, TestNode,
+    };
+
+    use super::DocumentLoader;
+
+    #[rstest]
+    fn loads_documents_in_batches(
+        #[from(populate_store_config)]
+        #[with(1, 3, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        #[from(random_document_id)] unknown_document_id: DocumentId,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            for document in &documents {
+                node.context.store.insert_document(document).await.unwrap();
+            }
+
+            let schema_id = config.schema.id().to_owned();
+            let loader = DocumentLoader::new(node.context.store.clone());
+
+            // Documents requested at the same time are looked up together
+            let mut document_ids: Vec<DocumentId> = documents
+                .iter()
+                .map(|document| document.id().to_owned())
+                .collect();
+            document_ids.push(unknown_document_id);
+            let loaded = try_join_all(
+                document_ids
+                    .iter()
+                    .map(|document_id| loader.load_document(&schema_id, document_id)),
+            )
+            .await
+            .unwrap();
+
+            for (document, loaded) in documents.iter().zip(&loaded) {
+                assert_eq!(
+                    loaded.as_ref().map(|loaded| loaded.id()),
+                    Some(document.id())
+                );
+            }
+            assert!(loaded.last().unwrap().is_none());
+            assert!(loader.state.lock().unwrap().pending.is_empty());
+
+            // Pinned views are loaded as well
+            let document = documents.first().unwrap();
+            let view = loader.load_view(document.view_id()).await.unwrap().unwrap();
+            assert_eq!(view.fields(), document.fields());
+        });
+    }
+}
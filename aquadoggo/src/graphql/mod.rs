@@ -1,7 +1,11 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 pub mod constants;
+mod errors;
+mod explain;
+mod idempotency;
 pub mod input_values;
+mod loader;
 pub mod mutations;
 pub mod objects;
 pub mod queries;
@@ -9,8 +13,16 @@ pub mod resolvers;
 pub mod responses;
 pub mod scalars;
 mod schema;
+mod sdl;
+pub mod subscriptions;
 #[cfg(test)]
 mod tests;
+mod traversal;
 pub mod utils;
 
-pub use schema::GraphQLSchemaManager;
+pub use explain::QueryPlans;
+pub use idempotency::{Begin, IdempotencyCache};
+pub use loader::DocumentLoader;
+pub use schema::{GraphQLSchemaManager, GraphQLSharedData};
+pub use sdl::GraphQLSdl;
+pub use traversal::{RelationLimits, RelationTraversal};
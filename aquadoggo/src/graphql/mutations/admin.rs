@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Result};
+use log::warn;
+use p2panda_rs::identity::PublicKey;
+
+use crate::capabilities::{Authenticated, CapabilityProvider};
+use crate::db::SqlStore;
+
+/// Check if the request was authenticated by an admin of this node, returns their public key.
+///
+/// Administrative mutations are refused when no admin public keys are configured, as the GraphQL
+/// endpoint is usually reachable by anyone in the network. The action is used in error messages,
+/// for example "purge documents".
+pub async fn check_admin(ctx: &Context<'_>, action: &str) -> Result<PublicKey> {
+    let store = ctx.data::<SqlStore>()?;
+    let capability_provider = ctx.data::<CapabilityProvider>()?;
+
+    if !capability_provider.has_admins() {
+        return Err(anyhow!(
+            "Not allowed to {} as no admin public keys are configured on this node",
+            action
+        )
+        .into());
+    }
+
+    let public_key = match ctx.data_opt::<Authenticated>() {
+        Some(authenticated) => authenticated.0,
+        None => {
+            return Err(anyhow!("Request to {} requires an auth token", action).into());
+        }
+    };
+
+    if !capability_provider.is_admin(store, &public_key).await? {
+        warn!(
+            "Rejected request to {} by unauthorised public key {}",
+            action, public_key
+        );
+        return Err(anyhow!("Public key {} is not permitted to {}", public_key, action).into());
+    }
+
+    Ok(public_key)
+}
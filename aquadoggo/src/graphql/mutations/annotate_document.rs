@@ -0,0 +1,233 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::info;
+use p2panda_rs::document::DocumentId;
+
+use crate::db::SqlStore;
+use crate::graphql::mutations::{check_admin, MutationRoot};
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// GraphQL "annotateDocument" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct AnnotateDocument(MutationRoot);
+
+#[MutationFields]
+impl AnnotateDocument {
+    /// Set or remove a node-local annotation of a document, for example to hide it locally.
+    ///
+    /// Annotations are stored next to the document and never replicated, no operation is
+    /// created. The request needs to be authenticated with an auth token of an admin, it is
+    /// refused when no admin public keys are configured on this node.
+    ///
+    /// Returns true when an annotation was set or removed.
+    async fn annotate_document(
+        ctx: &Context<'_>,
+        // Id of the annotated document.
+        document_id: DocumentIdScalar,
+        // Key of the annotation, for example "label" or "moderation".
+        key: String,
+        // Value of the annotation, the annotation is removed when not given.
+        value: Option<String>,
+    ) -> Result<bool> {
+        let store = ctx.data::<SqlStore>()?;
+
+        let document_id = DocumentId::from(&document_id);
+
+        if key.trim().is_empty() {
+            return Err(anyhow!("Annotation key can not be empty").into());
+        }
+
+        ///////////////////////////////////////
+        // CHECK CAPABILITIES OF THE REQUEST //
+        ///////////////////////////////////////
+
+        check_admin(ctx, "annotate documents").await?;
+
+        //////////////////////////////
+        // SET OR REMOVE ANNOTATION //
+        //////////////////////////////
+
+        match value {
+            Some(value) => {
+                store
+                    .set_document_annotation(&document_id, &key, &value)
+                    .await?;
+                info!("Annotated {} with '{}'", document_id, key);
+                Ok(true)
+            }
+            None => {
+                let is_removed = store.delete_document_annotation(&document_id, &key).await?;
+                if is_removed {
+                    info!("Removed annotation '{}' of {}", key, document_id);
+                }
+                Ok(is_removed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use async_graphql::{value, Request, Response, Variables};
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+    use serde_json::{json, Value as JsonValue};
+    use tokio::sync::broadcast;
+
+    use crate::capabilities::{Authenticated, CapabilityProvider};
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::test_utils::{
+        auth_token, http_test_client, test_runner, test_runner_with_manager, TestNode,
+        TestNodeManager,
+    };
+    use crate::Configuration;
+
+    const ANNOTATE_DOCUMENT_QUERY: &str = r#"
+        mutation TestAnnotateDocument($documentId: String!, $key: String!, $value: String) {
+            annotateDocument(documentId: $documentId, key: $key, value: $value)
+        }"#;
+
+    #[rstest]
+    fn annotates_documents(#[from(random_document_id)] document_id: DocumentId) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = manager
+                .create_with_config(Configuration {
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+
+            let client = http_test_client(&node).await;
+            let authorization = format!("Bearer {}", auth_token(&node, &admin));
+            let query = |request: JsonValue| {
+                let client = &client;
+                let authorization = &authorization;
+                async move {
+                    let response = client
+                        .post("/graphql")
+                        .header("Authorization", authorization)
+                        .json(&request)
+                        .send()
+                        .await;
+                    response.json::<Response>().await
+                }
+            };
+            let annotations_query = json!({
+                "query": format!(
+                    r#"{{ annotations(documentId: "{}") {{ key value }} }}"#,
+                    document_id
+                )
+            });
+
+            for (key, value) in [("moderation", "hidden"), ("label", "spam")] {
+                let response = query(json!({
+                    "query": ANNOTATE_DOCUMENT_QUERY,
+                    "variables": {
+                        "documentId": document_id.to_string(),
+                        "key": key,
+                        "value": value,
+                    }
+                }))
+                .await;
+                assert_eq!(
+                    response.data,
+                    value!({ "annotateDocument": true }),
+                    "{:?}",
+                    response.errors
+                );
+            }
+
+            let response = query(annotations_query.clone()).await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "annotations": [
+                        { "key": "label", "value": "spam" },
+                        { "key": "moderation", "value": "hidden" },
+                    ]
+                })
+            );
+
+            // Annotations without value get removed
+            let response = query(json!({
+                "query": ANNOTATE_DOCUMENT_QUERY,
+                "variables": {
+                    "documentId": document_id.to_string(),
+                    "key": "label",
+                }
+            }))
+            .await;
+            assert_eq!(response.data, value!({ "annotateDocument": true }));
+
+            let response = query(annotations_query).await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "annotations": [{ "key": "moderation", "value": "hidden" }]
+                })
+            );
+        });
+    }
+
+    #[rstest]
+    fn requires_admin_when_access_control_is_enabled(
+        #[from(random_document_id)] document_id: DocumentId,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let admin = KeyPair::new();
+
+            // Enable access control, only configured admins are allowed to annotate documents
+            let capability_schema_id = SchemaId::from_str(
+                "capability_0020c65567ae37efea293e34a9c7d13f8f2bf23dbdc3b5c7b9ab46293111c48fc78b",
+            )
+            .unwrap();
+
+            let (tx, _) = broadcast::channel(16);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(CapabilityProvider::new(
+                    Some(capability_schema_id),
+                    vec![admin.public_key()],
+                )),
+            )
+            .await;
+
+            let request = || {
+                Request::new(ANNOTATE_DOCUMENT_QUERY).variables(Variables::from_value(value!({
+                    "documentId": document_id.to_string(),
+                    "key": "moderation",
+                    "value": "hidden",
+                })))
+            };
+
+            // Requests without auth token are rejected
+            let response = manager.execute(request()).await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            // Public keys without admin permission are rejected
+            let response = manager
+                .execute(request().data(Authenticated(KeyPair::new().public_key())))
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+
+            let response = manager
+                .execute(request().data(Authenticated(admin.public_key())))
+                .await;
+            assert_eq!(response.data, value!({ "annotateDocument": true }));
+        });
+    }
+}
@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::info;
+use p2panda_rs::document::DocumentId;
+
+use crate::db::SqlStore;
+use crate::graphql::mutations::{check_admin, MutationRoot};
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// GraphQL "approveDocument" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct ApproveDocument(MutationRoot);
+
+#[MutationFields]
+impl ApproveDocument {
+    /// Approve a document of a moderated schema which arrived from another node, it shows up in
+    /// queries afterwards.
+    ///
+    /// The request needs to be authenticated with an auth token of an admin, it is refused when no
+    /// admin public keys are configured on this node.
+    ///
+    /// Returns true when the document was waiting for approval.
+    async fn approve_document(
+        ctx: &Context<'_>,
+        // Id of the held document.
+        document_id: DocumentIdScalar,
+    ) -> Result<bool> {
+        let store = ctx.data::<SqlStore>()?;
+
+        let document_id = DocumentId::from(&document_id);
+
+        ///////////////////////////////////////
+        // CHECK CAPABILITIES OF THE REQUEST //
+        ///////////////////////////////////////
+
+        check_admin(ctx, "approve documents").await?;
+
+        //////////////////////////
+        // APPROVE THE DOCUMENT //
+        //////////////////////////
+
+        let is_approved = store.approve_document(&document_id).await?;
+        if is_approved {
+            info!("Approved held document {}", document_id);
+        }
+
+        Ok(is_approved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use async_graphql::{value, Response};
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::identity::KeyPair;
+    use rstest::rstest;
+    use serde_json::{json, Value as JsonValue};
+
+    use crate::test_utils::{
+        auth_token, http_test_client, populate_and_materialize, populate_store_config,
+        test_runner_with_manager, PopulateStoreConfig, TestNodeManager,
+    };
+    use crate::{Configuration, SchemaSettings, SettingValue};
+
+    #[rstest]
+    fn hides_held_documents_until_approved(
+        #[from(populate_store_config)]
+        #[with(1, 2, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let schema_id = config.schema.id().to_owned();
+
+            let mut settings = SchemaSettings::default();
+            settings.insert("moderated", SettingValue::Boolean(true));
+            let admin = KeyPair::new();
+            let mut node = manager
+                .create_with_config(Configuration {
+                    schema_settings: HashMap::from([(schema_id.clone(), settings)]),
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+
+            let documents = populate_and_materialize(&mut node, &config).await;
+            let held_id = documents[0].id().to_owned();
+            node.context.store.hold_document(&held_id).await.unwrap();
+
+            let client = http_test_client(&node).await;
+            let authorization = format!("Bearer {}", auth_token(&node, &admin));
+            let query = |request: JsonValue| {
+                let client = &client;
+                let authorization = &authorization;
+                async move {
+                    let response = client
+                        .post("/graphql")
+                        .header("Authorization", authorization)
+                        .json(&request)
+                        .send()
+                        .await;
+                    response.json::<Response>().await
+                }
+            };
+            let documents_query = json!({
+                "query": format!(
+                    r#"{{
+                        collection: all_{schema_id} {{ totalCount }}
+                        held: {schema_id}(id: "{held_id}") {{ meta {{ documentId }} }}
+                        queue: heldDocuments(schemaId: "{schema_id}")
+                    }}"#,
+                )
+            });
+
+            // Held documents don't show up in queries
+            let response = query(documents_query.clone()).await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "collection": { "totalCount": 1 },
+                    "held": null,
+                    "queue": [held_id.to_string()],
+                }),
+                "{:?}",
+                response.errors
+            );
+
+            let approve = json!({
+                "query": format!(r#"mutation {{ approveDocument(documentId: "{held_id}") }}"#)
+            });
+
+            // Only admins can approve documents
+            let response = client
+                .post("/graphql")
+                .json(&approve)
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = query(approve.clone()).await;
+            assert_eq!(response.data, value!({ "approveDocument": true }));
+
+            // Approving a document twice has no effect
+            let response = query(approve).await;
+            assert_eq!(response.data, value!({ "approveDocument": false }));
+
+            let response = query(documents_query).await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "collection": { "totalCount": 2 },
+                    "held": { "meta": { "documentId": held_id.to_string() } },
+                    "queue": [],
+                })
+            );
+        });
+    }
+}
@@ -0,0 +1,692 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use async_graphql::Value;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::{debug, info};
+use p2panda_rs::api::next_args;
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::entry::encode::sign_and_encode_entry;
+use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use p2panda_rs::operation::encode::encode_operation;
+use p2panda_rs::operation::traits::AsOperation;
+use p2panda_rs::operation::{
+    Operation, OperationAction, OperationBuilder, OperationValue, RelationList,
+};
+use p2panda_rs::schema::{FieldType, Schema, SchemaId};
+use p2panda_rs::storage_provider::traits::DocumentStore;
+
+use crate::authors::AuthorKeys;
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::db::types::StorageDocument;
+use crate::db::SqlStore;
+use crate::graphql::mutations::publish::publish_entry;
+use crate::graphql::mutations::{check_admin, MutationRoot};
+use crate::graphql::scalars::{DocumentViewIdScalar, OperationFieldsScalar};
+use crate::materializer::{Task, TaskInput};
+use crate::schema::{datetime_value, parse_datetime, parse_decimal, SchemaProvider};
+
+/// Returns the key pair of the given service account or, if no account was given, the key pair
+/// of the document author.
+fn select_key_pair<'a>(
+    author_keys: &'a AuthorKeys,
+    account: Option<&str>,
+    author: Option<&PublicKey>,
+) -> Result<&'a KeyPair> {
+    let key_pair = match (account, author) {
+        (Some(account), _) => author_keys
+            .get_by_name(account)
+            .ok_or_else(|| anyhow!("Service account '{}' not found", account))?,
+        (None, Some(author)) => author_keys
+            .get(author)
+            .ok_or_else(|| anyhow!("Author {} is not held by this node", author))?,
+        (None, None) => return Err(anyhow!("Expected 'account' argument").into()),
+    };
+
+    Ok(key_pair)
+}
+
+/// Convert a GraphQL value into an operation value of the given field type.
+fn parse_field_value(field_type: &FieldType, value: &Value) -> Result<OperationValue> {
+    let parse_list = |values: &Vec<Value>| {
+        values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => Ok(value.to_owned()),
+                _ => Err(anyhow!("Expected list of strings")),
+            })
+            .collect::<Result<Vec<String>, _>>()
+    };
+
+    let operation_value = match (field_type, value) {
+        (FieldType::Boolean, Value::Boolean(value)) => (*value).into(),
+        (FieldType::Integer, Value::Number(number)) => number
+            .as_i64()
+            .ok_or_else(|| anyhow!("Expected integer"))?
+            .into(),
+        (FieldType::Float, Value::Number(number)) => number
+            .as_f64()
+            .ok_or_else(|| anyhow!("Expected float"))?
+            .into(),
+        (FieldType::String, Value::String(value)) => value.to_owned().into(),
+        (FieldType::Bytes, Value::String(value)) => {
+            let bytes = hex::decode(value)?;
+            bytes[..].into()
+        }
+        (FieldType::Relation(_), Value::String(value)) => value.parse::<DocumentId>()?.into(),
+        (FieldType::RelationList(_), Value::List(values)) => {
+            let document_ids = parse_list(values)?
+                .iter()
+                .map(|value| value.parse::<DocumentId>())
+                .collect::<Result<Vec<DocumentId>, _>>()?;
+            OperationValue::RelationList(RelationList::new(document_ids))
+        }
+        (FieldType::PinnedRelation(_), Value::String(value)) => {
+            value.parse::<DocumentViewId>()?.into()
+        }
+        (FieldType::PinnedRelationList(_), Value::List(values)) => parse_list(values)?
+            .iter()
+            .map(|value| value.parse::<DocumentViewId>())
+            .collect::<Result<Vec<DocumentViewId>, _>>()?
+            .into(),
+        _ => return Err(anyhow!("Unexpected value {}", value).into()),
+    };
+
+    Ok(operation_value)
+}
+
+/// Convert GraphQL field values into operation fields according to the field types of the
+/// schema.
+///
+/// Fields holding decimals are given as strings and converted into scaled integers, fields
+/// holding dates and times are given as ISO 8601 strings or UNIX timestamps.
+fn parse_fields(
+    schema: &Schema,
+    schema_provider: &SchemaProvider,
+    fields: &OperationFieldsScalar,
+) -> Result<Vec<(String, OperationValue)>> {
+    fields
+        .iter()
+        .map(|(name, value)| {
+            let field_type = schema.fields().get(name).ok_or_else(|| {
+                anyhow!("Field '{}' does not exist in schema {}", name, schema.id())
+            })?;
+
+            let is_datetime = schema_provider.is_datetime_field(schema, name);
+            let operation_value = match (schema_provider.decimal_scale(schema, name), value) {
+                (Some(scale), Value::String(value)) => parse_decimal(value, scale)
+                    .map(OperationValue::from)
+                    .map_err(|err| anyhow!("Invalid value of field '{}': {}", name, err))?,
+                (_, Value::String(value)) if is_datetime => parse_datetime(value)
+                    .and_then(|timestamp| datetime_value(timestamp, field_type))
+                    .map_err(|err| anyhow!("Invalid value of field '{}': {}", name, err))?,
+                (_, Value::Number(number)) if is_datetime => {
+                    let timestamp = number
+                        .as_i64()
+                        .ok_or_else(|| anyhow!("Invalid value of field '{}'", name))?;
+                    datetime_value(timestamp, field_type)
+                        .map_err(|err| anyhow!("Invalid value of field '{}': {}", name, err))?
+                }
+                _ => parse_field_value(field_type, value)
+                    .map_err(|err| anyhow!("Invalid value of field '{}': {}", name, err.message))?,
+            };
+
+            Ok((name.to_owned(), operation_value))
+        })
+        .collect()
+}
+
+/// Build an operation for the given schema and fields.
+fn build_operation(
+    schema_id: &SchemaId,
+    action: OperationAction,
+    fields: Option<&[(String, OperationValue)]>,
+    previous: Option<&DocumentViewId>,
+) -> Result<Operation> {
+    let mut builder = OperationBuilder::new(schema_id).action(action);
+
+    if let Some(fields) = fields {
+        let fields: Vec<(&str, OperationValue)> = fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_owned()))
+            .collect();
+        builder = builder.fields(&fields);
+    }
+
+    if let Some(previous) = previous {
+        builder = builder.previous(previous);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Sign an operation with the given key pair and publish it.
+///
+/// Returns the id of the published operation as a document view id.
+async fn sign_and_publish(
+    ctx: &Context<'_>,
+    key_pair: &KeyPair,
+    operation: &Operation,
+) -> Result<DocumentViewId> {
+    let store = ctx.data::<SqlStore>()?;
+
+    let encoded_operation = encode_operation(operation)?;
+
+    let (backlink, skiplink, seq_num, log_id) =
+        next_args(store, &key_pair.public_key(), operation.previous()).await?;
+
+    let encoded_entry = sign_and_encode_entry(
+        &log_id,
+        &seq_num,
+        skiplink.as_ref(),
+        backlink.as_ref(),
+        &encoded_operation,
+        key_pair,
+    )?;
+
+    publish_entry(ctx, &encoded_entry, &encoded_operation).await?;
+
+    Ok(DocumentViewId::from(encoded_entry.hash()))
+}
+
+/// Returns the document of the given view.
+async fn get_document(store: &SqlStore, view_id: &DocumentViewId) -> Result<StorageDocument> {
+    let document = store
+        .get_document_by_view_id(view_id)
+        .await?
+        .ok_or_else(|| anyhow!("Document view {} not found", view_id))?;
+
+    Ok(document)
+}
+
+/// GraphQL "createDocument" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct CreateDocument(MutationRoot);
+
+#[MutationFields]
+impl CreateDocument {
+    /// Create a document with the key pair of a service account held by this node.
+    ///
+    /// The node builds, signs and publishes the CREATE operation on behalf of the client. Field
+    /// values are given as an object and converted according to the field types of the schema,
+    /// bytes are hex-encoded, decimals given as strings and relations as (lists of) document
+    /// (view) ids. The request needs to be authenticated with an auth token of an admin, it is
+    /// refused when no admin public keys are configured on this node.
+    ///
+    /// Returns the id of the created document.
+    async fn create_document(
+        ctx: &Context<'_>,
+        // Id of the schema of the document.
+        schema_id: String,
+        // Field values of the document.
+        fields: OperationFieldsScalar,
+        // Name of the service account signing the operation.
+        account: String,
+    ) -> Result<DocumentViewIdScalar> {
+        let schema_provider = ctx.data::<SchemaProvider>()?;
+        let author_keys = ctx.data::<AuthorKeys>()?;
+
+        check_admin(ctx, "publish with service accounts").await?;
+
+        let schema_id = schema_id
+            .parse::<SchemaId>()
+            .map_err(|_| anyhow!("Invalid schema id '{}'", schema_id))?;
+        let schema = schema_provider
+            .get(&schema_id)
+            .await
+            .ok_or_else(|| anyhow!("Schema not found"))?;
+
+        let key_pair = select_key_pair(author_keys, Some(account.as_str()), None)?;
+
+        let fields = parse_fields(&schema, schema_provider, &fields)?;
+        let operation = build_operation(schema.id(), OperationAction::Create, Some(&fields), None)?;
+
+        let view_id = sign_and_publish(ctx, key_pair, &operation).await?;
+
+        info!(
+            "Created document {} with service account '{}'",
+            view_id, account
+        );
+
+        Ok(DocumentViewIdScalar::from(&view_id))
+    }
+}
+
+/// GraphQL "updateDocument" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct UpdateDocument(MutationRoot);
+
+#[MutationFields]
+impl UpdateDocument {
+    /// Update a document with the key pair of a service account held by this node.
+    ///
+    /// The node builds, signs and publishes the UPDATE operation on behalf of the client. When no
+    /// account is given, the key pair of the document author is used if it is held by this node.
+    /// The request needs to be authenticated with an auth token of an admin, it is refused when no
+    /// admin public keys are configured on this node.
+    ///
+    /// Returns the id of the published UPDATE operation.
+    async fn update_document(
+        ctx: &Context<'_>,
+        // Id of the document view the UPDATE operation follows.
+        view_id: DocumentViewIdScalar,
+        // Changed field values of the document.
+        fields: OperationFieldsScalar,
+        // Name of the service account signing the operation, the document author when not given.
+        account: Option<String>,
+    ) -> Result<DocumentViewIdScalar> {
+        let store = ctx.data::<SqlStore>()?;
+        let schema_provider = ctx.data::<SchemaProvider>()?;
+        let author_keys = ctx.data::<AuthorKeys>()?;
+
+        check_admin(ctx, "publish with service accounts").await?;
+
+        let view_id = DocumentViewId::from(view_id);
+        let document = get_document(store, &view_id).await?;
+        let schema = schema_provider
+            .get(document.schema_id())
+            .await
+            .ok_or_else(|| anyhow!("Schema not found"))?;
+
+        let key_pair = select_key_pair(author_keys, account.as_deref(), Some(document.author()))?;
+
+        let fields = parse_fields(&schema, schema_provider, &fields)?;
+        let operation = build_operation(
+            schema.id(),
+            OperationAction::Update,
+            Some(&fields),
+            Some(&view_id),
+        )?;
+
+        let update_view_id = sign_and_publish(ctx, key_pair, &operation).await?;
+
+        info!(
+            "Updated document {} on behalf of {}",
+            document.id(),
+            key_pair.public_key()
+        );
+
+        Ok(DocumentViewIdScalar::from(&update_view_id))
+    }
+}
+
+/// GraphQL "deleteDocument" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct DeleteDocument(MutationRoot);
+
+#[MutationFields]
+impl DeleteDocument {
+    /// Delete a document with the key pair of a service account held by this node.
+    ///
+    /// The node builds, signs and publishes the DELETE operation on behalf of the client. When no
+    /// account is given, the key pair of the document author is used if it is held by this node.
+    /// Blobs which are only referred to by the deleted document get scheduled for garbage
+    /// collection. The request needs to be authenticated with an auth token of an admin, it is
+    /// refused when no admin public keys are configured on this node.
+    ///
+    /// Returns the id of the published DELETE operation.
+    async fn delete_document(
+        ctx: &Context<'_>,
+        // Id of the document view the DELETE operation follows.
+        view_id: DocumentViewIdScalar,
+        // Name of the service account signing the operation, the document author when not given.
+        account: Option<String>,
+    ) -> Result<DocumentViewIdScalar> {
+        let store = ctx.data::<SqlStore>()?;
+        let tx = ctx.data::<ServiceSender>()?;
+        let author_keys = ctx.data::<AuthorKeys>()?;
+
+        check_admin(ctx, "publish with service accounts").await?;
+
+        let view_id = DocumentViewId::from(view_id);
+        let document = get_document(store, &view_id).await?;
+
+        let key_pair = select_key_pair(author_keys, account.as_deref(), Some(document.author()))?;
+
+        // Collect blobs before the document gets deleted, afterwards its views are gone
+        let blob_ids = store.get_exclusive_blob_relations(document.id()).await?;
+
+        let operation = build_operation(
+            document.schema_id(),
+            OperationAction::Delete,
+            None,
+            Some(&view_id),
+        )?;
+
+        let delete_view_id = sign_and_publish(ctx, key_pair, &operation).await?;
+
+        info!(
+            "Deleted document {} on behalf of {}",
+            document.id(),
+            key_pair.public_key()
+        );
+
+        // Purge blobs now as nothing else refers to them anymore
+        for blob_id in blob_ids {
+            debug!("Schedule garbage collection of blob {}", blob_id);
+
+            let task = Task::new("garbage_collection", TaskInput::DocumentId(blob_id));
+            if tx.send(ServiceMessage::ScheduleTask(task)).is_err() {
+                // Silently fail here as we don't mind if there are no subscribers
+            }
+        }
+
+        Ok(DocumentViewIdScalar::from(&delete_view_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use async_graphql::{value, Request, Response, Variables};
+    use p2panda_rs::document::{DocumentId, DocumentViewId};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::traits::{AsOperation, WithPublicKey};
+    use p2panda_rs::operation::{OperationAction, OperationId, OperationValue};
+    use p2panda_rs::schema::{FieldType, SchemaId};
+    use p2panda_rs::storage_provider::traits::OperationStore;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use serde_json::json;
+    use tokio::sync::broadcast;
+
+    use crate::authors::AuthorKeys;
+    use crate::bus::ServiceMessage;
+    use crate::capabilities::{Authenticated, CapabilityProvider};
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::materializer::{Task, TaskInput};
+    use crate::test_utils::{
+        add_blob, add_schema, add_schema_and_documents, test_runner, TestNode,
+    };
+
+    const CREATE_DOCUMENT_QUERY: &str = r#"
+        mutation TestCreateDocument(
+            $schemaId: String!,
+            $fields: OperationFields!,
+            $account: String!
+        ) {
+            createDocument(schemaId: $schemaId, fields: $fields, account: $account)
+        }"#;
+
+    const UPDATE_DOCUMENT_QUERY: &str = r#"
+        mutation TestUpdateDocument($viewId: String!, $fields: OperationFields!) {
+            updateDocument(viewId: $viewId, fields: $fields)
+        }"#;
+
+    const DELETE_DOCUMENT_QUERY: &str = r#"
+        mutation TestDeleteDocument($viewId: String!) {
+            deleteDocument(viewId: $viewId)
+        }"#;
+
+    /// Returns the view id returned by a mutation.
+    fn view_id_from_response(response: Response, mutation: &str) -> DocumentViewId {
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        response
+            .data
+            .into_json()
+            .unwrap()
+            .get(mutation)
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[rstest]
+    fn creates_and_updates_documents_with_service_accounts(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venue",
+                vec![
+                    ("name", FieldType::String),
+                    ("capacity", FieldType::Integer),
+                    ("logo", FieldType::Bytes),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let admin = KeyPair::new();
+            let (tx, _) = broadcast::channel(16);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(CapabilityProvider::new(None, vec![admin.public_key()]))
+                .with_author_keys(AuthorKeys::new(vec![(
+                    "backend".into(),
+                    KeyPair::from_private_key(key_pair.private_key()).unwrap(),
+                )])),
+            )
+            .await;
+
+            // Unknown service accounts are rejected
+            let create_request = |account: &str| {
+                Request::new(CREATE_DOCUMENT_QUERY)
+                    .variables(Variables::from_json(json!({
+                        "schemaId": schema.id().to_string(),
+                        "fields": {
+                            "name": "Panda Cafe",
+                            "capacity": 32,
+                            "logo": "cafe",
+                        },
+                        "account": account,
+                    })))
+                    .data(Authenticated(admin.public_key()))
+            };
+            let response = manager.execute(create_request("frontend")).await;
+            assert!(response.errors[0]
+                .message
+                .contains("Service account 'frontend' not found"));
+
+            // The node signs and publishes the CREATE operation
+            let response = manager.execute(create_request("backend")).await;
+            let view_id = view_id_from_response(response, "createDocument");
+            let operation_id = OperationId::from_str(&view_id.to_string()).unwrap();
+
+            let operation = node
+                .context
+                .store
+                .get_operation(&operation_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(operation.action(), OperationAction::Create);
+            assert_eq!(operation.public_key(), &key_pair.public_key());
+            assert_eq!(
+                operation.fields().unwrap().get("capacity"),
+                Some(&OperationValue::Integer(32))
+            );
+
+            // Field values are checked against the schema
+            let response = manager
+                .execute(
+                    Request::new(UPDATE_DOCUMENT_QUERY)
+                        .variables(Variables::from_json(json!({
+                            "viewId": view_id.to_string(),
+                            "fields": { "capacity": "many" },
+                        })))
+                        .data(Authenticated(admin.public_key())),
+                )
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("Invalid value of field 'capacity'"));
+
+            // Updates are signed with the key pair of the document author by default
+            let response = manager
+                .execute(
+                    Request::new(UPDATE_DOCUMENT_QUERY)
+                        .variables(Variables::from_json(json!({
+                            "viewId": view_id.to_string(),
+                            "fields": { "capacity": 64 },
+                        })))
+                        .data(Authenticated(admin.public_key())),
+                )
+                .await;
+            let update_view_id = view_id_from_response(response, "updateDocument");
+            let operation = node
+                .context
+                .store
+                .get_operation(&OperationId::from_str(&update_view_id.to_string()).unwrap())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(operation.action(), OperationAction::Update);
+            assert_eq!(operation.public_key(), &key_pair.public_key());
+            assert_eq!(operation.previous(), Some(&view_id));
+        });
+    }
+
+    #[rstest]
+    fn deletes_documents_and_collects_exclusive_blobs(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_view_id = add_blob(
+                &mut node,
+                "Hello, World!".as_bytes(),
+                7,
+                "text/plain",
+                &key_pair,
+            )
+            .await;
+            let (_, view_ids) = add_schema_and_documents(
+                &mut node,
+                "img",
+                vec![vec![(
+                    "blob",
+                    blob_view_id.clone().into(),
+                    Some(SchemaId::Blob(1)),
+                )]],
+                &key_pair,
+            )
+            .await;
+            let other_view_id = add_blob(
+                &mut node,
+                "Hello, Panda!".as_bytes(),
+                7,
+                "text/plain",
+                &KeyPair::new(),
+            )
+            .await;
+
+            // The node holds the key pair of the document author
+            let admin = KeyPair::new();
+            let (tx, mut rx) = broadcast::channel(16);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(CapabilityProvider::new(None, vec![admin.public_key()]))
+                .with_author_keys(AuthorKeys::new(vec![(
+                    "backend".into(),
+                    KeyPair::from_private_key(key_pair.private_key()).unwrap(),
+                )])),
+            )
+            .await;
+
+            let request = |view_id: &DocumentViewId| {
+                Request::new(DELETE_DOCUMENT_QUERY)
+                    .variables(Variables::from_value(value!({
+                        "viewId": view_id.to_string(),
+                    })))
+                    .data(Authenticated(admin.public_key()))
+            };
+
+            // Documents of authors the node does not hold a key pair of can't be deleted
+            let response = manager.execute(request(&other_view_id)).await;
+            assert!(response.errors[0]
+                .message
+                .contains("is not held by this node"));
+
+            let response = manager.execute(request(&view_ids[0])).await;
+            let delete_view_id = view_id_from_response(response, "deleteDocument");
+            let operation_id: OperationId = delete_view_id.to_string().parse().unwrap();
+
+            // The DELETE operation was signed by the held key pair and published
+            let operation = node
+                .context
+                .store
+                .get_operation(&operation_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(operation.is_delete());
+            assert_eq!(operation.public_key(), &key_pair.public_key());
+            assert_eq!(operation.previous(), Some(&view_ids[0]));
+            assert_eq!(
+                rx.recv().await,
+                Ok(ServiceMessage::NewOperation(operation_id))
+            );
+
+            // The blob only the deleted document referred to is garbage collected
+            let blob_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+            assert_eq!(
+                rx.recv().await,
+                Ok(ServiceMessage::ScheduleTask(Task::new(
+                    "garbage_collection",
+                    TaskInput::DocumentId(blob_id)
+                )))
+            );
+        });
+    }
+
+    #[rstest]
+    fn requires_admin_when_access_control_is_enabled() {
+        test_runner(|node: TestNode| async move {
+            let admin = KeyPair::new();
+            let capability_schema_id = SchemaId::from_str(
+                "capability_0020c65567ae37efea293e34a9c7d13f8f2bf23dbdc3b5c7b9ab46293111c48fc78b",
+            )
+            .unwrap();
+
+            let (tx, _) = broadcast::channel(16);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(CapabilityProvider::new(
+                    Some(capability_schema_id),
+                    vec![admin.public_key()],
+                )),
+            )
+            .await;
+
+            let request = || {
+                Request::new(CREATE_DOCUMENT_QUERY).variables(Variables::from_json(json!({
+                    "schemaId": "schema_definition_v1",
+                    "fields": {},
+                    "account": "backend",
+                })))
+            };
+
+            let response = manager.execute(request()).await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = manager
+                .execute(request().data(Authenticated(KeyPair::new().public_key())))
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+
+            // Admins pass the check, the service account is not configured though
+            let response = manager
+                .execute(request().data(Authenticated(admin.public_key())))
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("Service account 'backend' not found"));
+        });
+    }
+}
@@ -0,0 +1,329 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::info;
+
+use crate::api::{decode_commits, import};
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::db::SqlStore;
+use crate::graphql::mutations::{check_admin, MutationRoot};
+use crate::graphql::responses::{FailedImport, ImportResult};
+use crate::graphql::scalars::HexBytesScalar;
+use crate::schema::SchemaProvider;
+
+/// GraphQL "importCommits" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct ImportCommits(MutationRoot);
+
+#[MutationFields]
+impl ImportCommits {
+    /// Import raw encoded entries and operations, for example exported from another node.
+    ///
+    /// The data is a sequence of CBOR arrays, each holding the bytes of an encoded entry and its
+    /// encoded operation. Every entry and operation is validated like a published one before it
+    /// gets materialized. The request needs to be authenticated with an auth token of an admin, it
+    /// is refused when no admin public keys are configured on this node.
+    ///
+    /// Returns the number of imported entries and the ones which failed.
+    async fn import_commits(
+        ctx: &Context<'_>,
+        // Sequence of encoded entries and operations.
+        data: HexBytesScalar,
+    ) -> Result<ImportResult> {
+        let store = ctx.data::<SqlStore>()?;
+        let tx = ctx.data::<ServiceSender>()?;
+        let schema_provider = ctx.data::<SchemaProvider>()?;
+
+        let data =
+            hex::decode(String::from(data)).map_err(|_| anyhow!("Invalid hex encoding of data"))?;
+
+        ///////////////////////////////////////
+        // CHECK CAPABILITIES OF THE REQUEST //
+        ///////////////////////////////////////
+
+        let public_key = check_admin(ctx, "import data").await?;
+
+        ///////////////////////////////////////
+        // IMPORT THE ENTRIES AND OPERATIONS //
+        ///////////////////////////////////////
+
+        let commits = decode_commits(&data)?;
+        let report = import(store, schema_provider, commits).await?;
+
+        info!(
+            "Imported {} entries by {}, {} existed already and {} failed",
+            report.imported.len(),
+            public_key,
+            report.existing,
+            report.failed.len()
+        );
+
+        /////////////////////////////////////////
+        // SEND THE OPERATIONS TO MATERIALIZER //
+        /////////////////////////////////////////
+
+        for operation_id in &report.imported {
+            if tx
+                .send(ServiceMessage::NewOperation(operation_id.to_owned()))
+                .is_err()
+            {
+                // Silently fail here as we don't mind if there are no subscribers. We have
+                // tests in other places to check if messages arrive.
+            }
+        }
+
+        Ok(ImportResult {
+            imported: report.imported.len() as u64,
+            existing: report.existing as u64,
+            failed: report
+                .failed
+                .into_iter()
+                .map(|(entry_hash, reason)| FailedImport {
+                    entry_hash: entry_hash.into(),
+                    reason,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Request, Variables};
+    use p2panda_rs::entry::traits::AsEncodedEntry;
+    use p2panda_rs::entry::{EncodedEntry, LogId, SeqNum};
+    use p2panda_rs::identity::KeyPair;
+    use rstest::rstest;
+    use tokio::sync::broadcast;
+
+    use crate::bus::ServiceMessage;
+    use crate::capabilities::{Authenticated, CapabilityProvider};
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::http::HttpServiceContext;
+    use crate::schema::SchemaProvider;
+    use crate::test_utils::{
+        populate_store, populate_store_config, test_runner_with_manager, PopulateStoreConfig,
+        TestNode, TestNodeManager,
+    };
+    use crate::AllowList;
+
+    const IMPORT_COMMITS_QUERY: &str = r#"
+        mutation TestImportCommits($data: String!) {
+            importCommits(data: $data) {
+                imported
+                existing
+                failed {
+                    entryHash
+                    reason
+                }
+            }
+        }"#;
+
+    async fn http_context(
+        node: &TestNode,
+        capability_provider: CapabilityProvider,
+        schema_provider: SchemaProvider,
+    ) -> (HttpServiceContext, broadcast::Receiver<ServiceMessage>) {
+        let (tx, rx) = broadcast::channel(120);
+        let manager = GraphQLSchemaManager::new(
+            GraphQLSharedData::new(node.context.store.clone(), tx, schema_provider)
+                .with_capability_provider(capability_provider),
+        )
+        .await;
+        let context = HttpServiceContext::new(
+            node.context.store.clone(),
+            manager,
+            node.context.blob_store.clone(),
+        );
+
+        (context, rx)
+    }
+
+    /// Returns the entries and operations of a populated node encoded as import data.
+    async fn export_data(node: &TestNode, config: &PopulateStoreConfig) -> Vec<u8> {
+        populate_store(&node.context.store, config).await;
+
+        let mut data = Vec::new();
+        for key_pair in &config.authors {
+            let entries = node
+                .context
+                .store
+                .get_entries_from(
+                    &key_pair.public_key(),
+                    &LogId::default(),
+                    &SeqNum::default(),
+                )
+                .await
+                .unwrap();
+
+            for entry in entries {
+                let commit = (
+                    EncodedEntry::from_bytes(&entry.into_bytes()),
+                    entry.payload().unwrap().to_owned(),
+                );
+                ciborium::ser::into_writer(&commit, &mut data).unwrap();
+            }
+        }
+
+        data
+    }
+
+    #[rstest]
+    fn imports_commits(
+        #[from(populate_store_config)]
+        #[with(3, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let source = manager.create().await;
+            let node = manager.create().await;
+            let data = export_data(&source, &config).await;
+
+            let schema_provider =
+                SchemaProvider::new(vec![config.schema.clone()], AllowList::Wildcard);
+            let admin = KeyPair::new();
+            let (context, mut rx) = http_context(
+                &node,
+                CapabilityProvider::new(None, vec![admin.public_key()]),
+                schema_provider,
+            )
+            .await;
+
+            let request = Request::new(IMPORT_COMMITS_QUERY)
+                .variables(Variables::from_value(value!({
+                    "data": hex::encode(&data),
+                })))
+                .data(Authenticated(admin.public_key()));
+            let response = context.schema.execute(request).await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+            assert_eq!(
+                response.data,
+                value!({
+                    "importCommits": {
+                        "imported": 3,
+                        "existing": 0,
+                        "failed": [],
+                    }
+                })
+            );
+
+            // Imported operations are sent to the materializer
+            for _ in 0..3 {
+                assert!(matches!(
+                    rx.recv().await.unwrap(),
+                    ServiceMessage::NewOperation(_)
+                ));
+            }
+
+            // Importing the same data again reports existing entries
+            let request = Request::new(IMPORT_COMMITS_QUERY)
+                .variables(Variables::from_value(value!({
+                    "data": hex::encode(&data),
+                })))
+                .data(Authenticated(admin.public_key()));
+            let response = context.schema.execute(request).await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "importCommits": {
+                        "imported": 0,
+                        "existing": 3,
+                        "failed": [],
+                    }
+                })
+            );
+
+            // Invalid data is rejected
+            let request = Request::new(IMPORT_COMMITS_QUERY)
+                .variables(Variables::from_value(value!({
+                    "data": "010203",
+                })))
+                .data(Authenticated(admin.public_key()));
+            let response = context.schema.execute(request).await;
+            assert!(response.is_err());
+        });
+    }
+
+    #[rstest]
+    #[case::admin(true, true, None)]
+    #[case::no_admin(true, false, Some("is not permitted"))]
+    #[case::no_auth_token(false, true, Some("requires an auth token"))]
+    fn checks_capabilities(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        #[case] is_authenticated: bool,
+        #[case] is_admin: bool,
+        #[case] expected_error: Option<&'static str>,
+    ) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let source = manager.create().await;
+            let node = manager.create().await;
+            let data = export_data(&source, &config).await;
+
+            let admin = KeyPair::new();
+            let public_key = if is_admin {
+                admin.public_key()
+            } else {
+                KeyPair::new().public_key()
+            };
+
+            let schema_provider =
+                SchemaProvider::new(vec![config.schema.clone()], AllowList::Wildcard);
+            let (context, _rx) = http_context(
+                &node,
+                CapabilityProvider::new(None, vec![admin.public_key()]),
+                schema_provider,
+            )
+            .await;
+
+            let mut request =
+                Request::new(IMPORT_COMMITS_QUERY).variables(Variables::from_value(value!({
+                    "data": hex::encode(&data),
+                })));
+            if is_authenticated {
+                request = request.data(Authenticated(public_key));
+            }
+            let response = context.schema.execute(request).await;
+
+            match expected_error {
+                Some(expected_error) => assert!(
+                    response.errors[0].message.contains(expected_error),
+                    "{:?}",
+                    response.errors
+                ),
+                None => assert!(response.is_ok(), "{:?}", response.errors),
+            }
+        });
+    }
+
+    #[rstest]
+    fn refuses_import_without_admins(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let source = manager.create().await;
+            let node = manager.create().await;
+            let data = export_data(&source, &config).await;
+
+            let schema_provider =
+                SchemaProvider::new(vec![config.schema.clone()], AllowList::Wildcard);
+            let (context, mut rx) =
+                http_context(&node, CapabilityProvider::default(), schema_provider).await;
+
+            let request = Request::new(IMPORT_COMMITS_QUERY)
+                .variables(Variables::from_value(value!({
+                    "data": hex::encode(&data),
+                })))
+                .data(Authenticated(KeyPair::new().public_key()));
+            let response = context.schema.execute(request).await;
+            assert!(response.errors[0]
+                .message
+                .contains("no admin public keys are configured"));
+            assert!(rx.try_recv().is_err());
+        });
+    }
+}
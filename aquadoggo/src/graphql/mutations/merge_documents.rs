@@ -0,0 +1,304 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::{info, warn};
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::storage_provider::traits::DocumentStore;
+
+use crate::capabilities::Authenticated;
+use crate::db::types::StorageDocument;
+use crate::db::SqlStore;
+use crate::graphql::mutations::MutationRoot;
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// Returns true if both documents contain the same field values.
+fn has_identical_fields(document: &StorageDocument, other: &StorageDocument) -> bool {
+    match (document.fields(), other.fields()) {
+        (Some(fields), Some(other_fields)) => {
+            fields.len() == other_fields.len()
+                && fields.iter().all(|(name, value)| {
+                    other_fields
+                        .get(name)
+                        .is_some_and(|other_value| other_value.value() == value.value())
+                })
+        }
+        _ => false,
+    }
+}
+
+/// GraphQL "mergeDocuments" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct MergeDocuments(MutationRoot);
+
+#[MutationFields]
+impl MergeDocuments {
+    /// Merge a duplicate document into a canonical document with identical content.
+    ///
+    /// Both documents need to be of the same schema and created by the same author, for example
+    /// when two devices of one author accidentally published the same document. Afterwards
+    /// queries and relations targeting the merged document resolve to the canonical one. The
+    /// request needs to be authenticated with an auth token of the author of both documents.
+    ///
+    /// Returns the id of the canonical document.
+    async fn merge_documents(
+        ctx: &Context<'_>,
+        // Id of the duplicate document which should be merged.
+        document_id: DocumentIdScalar,
+        // Id of the document the duplicate gets merged into.
+        canonical_id: DocumentIdScalar,
+    ) -> Result<DocumentIdScalar> {
+        let store = ctx.data::<SqlStore>()?;
+
+        let document_id = DocumentId::from(&document_id);
+        let canonical_id = DocumentId::from(&canonical_id);
+
+        /////////////////////////////////////////
+        // CHECK AUTHENTICATION OF THE REQUEST //
+        /////////////////////////////////////////
+
+        let public_key = match ctx.data_opt::<Authenticated>() {
+            Some(authenticated) => authenticated.0,
+            None => return Err(anyhow!("Merging documents requires an auth token").into()),
+        };
+
+        //////////////////////////////////////
+        // CHECK IF DOCUMENTS CAN BE MERGED //
+        //////////////////////////////////////
+
+        let document = store
+            .get_document(&document_id)
+            .await?
+            .ok_or_else(|| anyhow!("Document {} not found", document_id))?;
+        let canonical = store
+            .get_document(&canonical_id)
+            .await?
+            .ok_or_else(|| anyhow!("Document {} not found", canonical_id))?;
+
+        if document.author() != &public_key || canonical.author() != &public_key {
+            warn!(
+                "Rejected merging {} into {} by public key {} which is not the author",
+                document_id, canonical_id, public_key
+            );
+            return Err(anyhow!(
+                "Public key {} is not the author of both documents",
+                public_key
+            )
+            .into());
+        }
+
+        if document.schema_id() != canonical.schema_id() {
+            return Err(anyhow!("Documents need to be of the same schema").into());
+        }
+
+        if !has_identical_fields(&document, &canonical) {
+            return Err(anyhow!("Documents need to contain identical field values").into());
+        }
+
+        ///////////////////////////
+        // REDIRECT THE DOCUMENT //
+        ///////////////////////////
+
+        let canonical_id = store
+            .insert_document_redirect(&document_id, &canonical_id)
+            .await?;
+
+        info!(
+            "Merged document {} into {} by {}",
+            document_id, canonical_id, public_key
+        );
+
+        Ok(DocumentIdScalar::from(&canonical_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::{OperationValue, Relation, RelationList};
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use serde_json::{json, Value as JsonValue};
+
+    use crate::test_utils::{
+        add_document, add_schema, auth_token, http_test_client, test_runner, TestNode,
+    };
+
+    const MERGE_DOCUMENTS_QUERY: &str = r#"
+        mutation TestMergeDocuments($documentId: String!, $canonicalId: String!) {
+            mergeDocuments(documentId: $documentId, canonicalId: $canonicalId)
+        }"#;
+
+    fn merge_request(document_id: &DocumentId, canonical_id: &DocumentId) -> JsonValue {
+        json!({
+            "query": MERGE_DOCUMENTS_QUERY,
+            "variables": {
+                "documentId": document_id.to_string(),
+                "canonicalId": canonical_id.to_string(),
+            }
+        })
+    }
+
+    #[rstest]
+    fn merges_identical_documents(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "cafe",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let menu_schema = add_schema(
+                &mut node,
+                "menu",
+                vec![
+                    ("cafe", FieldType::Relation(schema.id().to_owned())),
+                    (
+                        "other_cafes",
+                        FieldType::RelationList(schema.id().to_owned()),
+                    ),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            // The same author published the same document twice and another one
+            let mut document_ids: Vec<DocumentId> = Vec::new();
+            for name in ["Panda Cafe", "Panda Cafe", "Bamboo Bar"] {
+                let view_id = add_document(
+                    &mut node,
+                    schema.id(),
+                    vec![("name", name.into())],
+                    &key_pair,
+                )
+                .await;
+                document_ids.push(view_id.to_string().parse().unwrap());
+            }
+
+            // Relate to the duplicate document
+            add_document(
+                &mut node,
+                menu_schema.id(),
+                vec![
+                    (
+                        "cafe",
+                        OperationValue::Relation(Relation::new(document_ids[1].clone())),
+                    ),
+                    (
+                        "other_cafes",
+                        OperationValue::RelationList(RelationList::new(vec![
+                            document_ids[1].clone()
+                        ])),
+                    ),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let client = http_test_client(&node).await;
+            let query = |request: JsonValue, key_pair: Option<&KeyPair>| {
+                let client = &client;
+                let authorization =
+                    key_pair.map(|key_pair| format!("Bearer {}", auth_token(&node, key_pair)));
+                async move {
+                    let mut builder = client.post("/graphql").json(&request);
+                    if let Some(authorization) = authorization {
+                        builder = builder.header("Authorization", authorization);
+                    }
+                    builder.send().await.json::<Response>().await
+                }
+            };
+
+            // Documents with different content can't be merged
+            let response = query(
+                merge_request(&document_ids[2], &document_ids[0]),
+                Some(&key_pair),
+            )
+            .await;
+            assert!(response.is_err());
+
+            // Only the authenticated author can merge documents
+            let response = query(merge_request(&document_ids[1], &document_ids[0]), None).await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = query(
+                merge_request(&document_ids[1], &document_ids[0]),
+                Some(&KeyPair::new()),
+            )
+            .await;
+            assert!(response.errors[0]
+                .message
+                .contains("is not the author of both documents"));
+
+            let response = query(
+                merge_request(&document_ids[1], &document_ids[0]),
+                Some(&key_pair),
+            )
+            .await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+            assert_eq!(
+                response.data,
+                value!({ "mergeDocuments": document_ids[0].to_string() })
+            );
+
+            // Queries and relations resolve to the canonical document, duplicates are hidden from
+            // collections
+            let response = query(
+                json!({ "query": format!(
+                r#"{{
+                    duplicate: {cafe}(id: "{duplicate}") {{ meta {{ documentId }} }},
+                    cafes: all_{cafe} {{ totalCount }},
+                    menus: all_{menu} {{
+                        documents {{
+                            fields {{
+                                cafe {{ meta {{ documentId }} }},
+                                other_cafes {{ documents {{ meta {{ documentId }} }} }}
+                            }}
+                        }}
+                    }}
+                }}"#,
+                cafe = schema.id(),
+                menu = menu_schema.id(),
+                duplicate = document_ids[1],
+            ) }),
+                None,
+            )
+            .await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+            assert_eq!(
+                response.data,
+                value!({
+                    "duplicate": { "meta": { "documentId": document_ids[0].to_string() } },
+                    "cafes": { "totalCount": 2 },
+                    "menus": {
+                        "documents": [{
+                            "fields": {
+                                "cafe": { "meta": { "documentId": document_ids[0].to_string() } },
+                                "other_cafes": {
+                                    "documents": [{
+                                        "meta": { "documentId": document_ids[0].to_string() }
+                                    }]
+                                }
+                            }
+                        }]
+                    }
+                })
+            );
+
+            // Documents can't be merged into themselves
+            let response = query(
+                merge_request(&document_ids[0], &document_ids[1]),
+                Some(&key_pair),
+            )
+            .await;
+            assert!(response.is_err());
+        });
+    }
+}
@@ -1,5 +1,27 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod admin;
+mod annotate_document;
+mod approve_document;
+mod author_documents;
+mod import_commits;
+mod merge_documents;
+mod pause_replication;
 mod publish;
+mod publish_batch;
+mod purge_document;
+mod redeem_invite;
+mod schedule_task;
 
+pub(crate) use admin::check_admin;
+pub use annotate_document::AnnotateDocument;
+pub use approve_document::ApproveDocument;
+pub use author_documents::{CreateDocument, DeleteDocument, UpdateDocument};
+pub use import_commits::ImportCommits;
+pub use merge_documents::MergeDocuments;
+pub use pause_replication::{PauseReplication, ResumeReplication};
 pub use publish::{MutationRoot, Publish};
+pub use publish_batch::PublishBatch;
+pub use purge_document::PurgeDocument;
+pub use redeem_invite::RedeemInvite;
+pub use schedule_task::ScheduleTask;
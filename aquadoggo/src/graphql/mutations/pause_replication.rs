@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use libp2p::PeerId;
+use p2panda_rs::schema::SchemaId;
+
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::graphql::mutations::{check_admin, MutationRoot};
+use crate::replication::ReplicationPause;
+
+/// Parse the optional peer id and schema ids arguments into a replication scope.
+fn parse_scope(
+    peer_id: Option<String>,
+    schema_ids: Option<Vec<String>>,
+) -> Result<ReplicationPause> {
+    let peer_id = match peer_id {
+        Some(peer_id) => Some(
+            peer_id
+                .parse::<PeerId>()
+                .map_err(|_| anyhow!("Invalid peer id '{}'", peer_id))?,
+        ),
+        None => None,
+    };
+
+    let schema_ids = schema_ids
+        .unwrap_or_default()
+        .iter()
+        .map(|schema_id| {
+            schema_id
+                .parse::<SchemaId>()
+                .map_err(|_| anyhow!("Invalid schema id '{}'", schema_id))
+        })
+        .collect::<Result<Vec<SchemaId>, _>>()?;
+
+    Ok(ReplicationPause {
+        peer_id,
+        schema_ids,
+    })
+}
+
+/// GraphQL "pauseReplication" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct PauseReplication(MutationRoot);
+
+#[MutationFields]
+impl PauseReplication {
+    /// Pause replication, for example to perform backups or migrations without stopping the node.
+    ///
+    /// While paused no new replication sessions are initiated or accepted and idle sessions get
+    /// closed, running sessions are allowed to finish. Replication can be paused with all peers
+    /// and for all schema ids or limited to one peer and a set of schema ids. The request needs to
+    /// be authenticated with an auth token of an admin, it is refused when no admin public keys
+    /// are configured on this node.
+    ///
+    /// Returns true when the node was informed about the pause.
+    async fn pause_replication(
+        ctx: &Context<'_>,
+        // Id of the peer replication is paused with, all peers when not given.
+        peer_id: Option<String>,
+        // Schema ids replication is paused for, all schema ids when not given.
+        schema_ids: Option<Vec<String>>,
+    ) -> Result<bool> {
+        let tx = ctx.data::<ServiceSender>()?;
+
+        let pause = parse_scope(peer_id, schema_ids)?;
+        check_admin(ctx, "control replication").await?;
+
+        if tx.send(ServiceMessage::PauseReplication(pause)).is_err() {
+            return Err(anyhow!("Replication service is not running").into());
+        }
+
+        Ok(true)
+    }
+}
+
+/// GraphQL "resumeReplication" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct ResumeReplication(MutationRoot);
+
+#[MutationFields]
+impl ResumeReplication {
+    /// Resume replication which was paused before.
+    ///
+    /// All pauses lying within the given peer and schema ids are lifted, without arguments
+    /// replication is resumed completely. The request needs to be authenticated with an auth token
+    /// of an admin, it is refused when no admin public keys are configured on this node.
+    ///
+    /// Returns true when the node was informed about resuming replication.
+    async fn resume_replication(
+        ctx: &Context<'_>,
+        // Id of the peer replication is resumed with, all peers when not given.
+        peer_id: Option<String>,
+        // Schema ids replication is resumed for, all schema ids when not given.
+        schema_ids: Option<Vec<String>>,
+    ) -> Result<bool> {
+        let tx = ctx.data::<ServiceSender>()?;
+
+        let scope = parse_scope(peer_id, schema_ids)?;
+        check_admin(ctx, "control replication").await?;
+
+        if tx.send(ServiceMessage::ResumeReplication(scope)).is_err() {
+            return Err(anyhow!("Replication service is not running").into());
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use async_graphql::{value, Request, Variables};
+    use libp2p::PeerId;
+    use p2panda_rs::identity::KeyPair;
+    use tokio::sync::broadcast;
+
+    use crate::bus::ServiceMessage;
+    use crate::capabilities::{Authenticated, CapabilityProvider};
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::replication::ReplicationPause;
+    use crate::test_utils::{test_runner, TestNode};
+
+    const PAUSE_REPLICATION_QUERY: &str = r#"
+        mutation TestPauseReplication($peerId: String, $schemaIds: [String!]) {
+            pauseReplication(peerId: $peerId, schemaIds: $schemaIds)
+        }"#;
+
+    const RESUME_REPLICATION_QUERY: &str = r#"
+        mutation TestResumeReplication {
+            resumeReplication
+        }"#;
+
+    #[test]
+    fn pauses_and_resumes_replication() {
+        test_runner(|node: TestNode| async move {
+            let admin = KeyPair::new();
+            let peer_id =
+                PeerId::from_str("12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY").unwrap();
+
+            // Only configured admins are allowed to control replication, also when access control
+            // via capability documents is disabled
+            let (tx, mut rx) = broadcast::channel(16);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(CapabilityProvider::new(None, vec![admin.public_key()])),
+            )
+            .await;
+
+            let request = || {
+                Request::new(PAUSE_REPLICATION_QUERY).variables(Variables::from_value(value!({
+                    "peerId": peer_id.to_string(),
+                })))
+            };
+
+            // Requests without admin permission are rejected
+            let response = manager.execute(request()).await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = manager
+                .execute(request().data(Authenticated(KeyPair::new().public_key())))
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+            assert!(rx.try_recv().is_err());
+
+            // Admins can pause and resume replication
+            let response = manager
+                .execute(request().data(Authenticated(admin.public_key())))
+                .await;
+            assert_eq!(
+                response.data,
+                value!({ "pauseReplication": true }),
+                "{:?}",
+                response.errors
+            );
+            assert_eq!(
+                rx.recv().await,
+                Ok(ServiceMessage::PauseReplication(ReplicationPause {
+                    peer_id: Some(peer_id),
+                    schema_ids: vec![],
+                }))
+            );
+
+            let response = manager
+                .execute(
+                    Request::new(RESUME_REPLICATION_QUERY).data(Authenticated(admin.public_key())),
+                )
+                .await;
+            assert_eq!(response.data, value!({ "resumeReplication": true }));
+            assert_eq!(
+                rx.recv().await,
+                Ok(ServiceMessage::ResumeReplication(
+                    ReplicationPause::default()
+                ))
+            );
+
+            // Invalid arguments are rejected
+            let response = manager
+                .execute(
+                    Request::new(PAUSE_REPLICATION_QUERY)
+                        .variables(Variables::from_value(value!({
+                            "schemaIds": ["not_a_schema"],
+                        })))
+                        .data(Authenticated(admin.public_key())),
+                )
+                .await;
+            assert!(response.errors[0].message.contains("Invalid schema id"));
+        });
+    }
+}
@@ -1,19 +1,25 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use anyhow::anyhow;
+use async_graphql::{Error, ErrorExtensions};
 use dynamic_graphql::{Context, Mutation, MutationFields, MutationRoot, Result};
-use log::debug;
+use log::{debug, warn};
 use p2panda_rs::api::publish;
-use p2panda_rs::entry::traits::AsEncodedEntry;
-use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
+use p2panda_rs::entry::{EncodedEntry, Entry};
 use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::plain::PlainOperation;
 use p2panda_rs::operation::traits::Schematic;
 use p2panda_rs::operation::{EncodedOperation, OperationId};
+use p2panda_rs::schema::Schema;
 
 use crate::bus::{ServiceMessage, ServiceSender};
+use crate::capabilities::CapabilityProvider;
 use crate::db::SqlStore;
 use crate::graphql::responses::NextArguments;
 use crate::graphql::scalars::{EncodedEntryScalar, EncodedOperationScalar};
+use crate::graphql::{Begin, IdempotencyCache};
 use crate::schema::SchemaProvider;
 
 /// GraphQL mutation root.
@@ -29,16 +35,20 @@ impl Publish {
     /// Publish an entry using parameters obtained through `nextArgs` query.
     ///
     /// Returns arguments for publishing the next entry in the same log.
+    ///
+    /// Clients can pass an idempotency key to safely retry a request, for example after a
+    /// timeout. The response of the first successful request with the same key and entry is
+    /// returned again instead of failing with an occupied sequence number.
     async fn publish(
         ctx: &Context<'_>,
         // Signed and encoded entry to publish
         entry: EncodedEntryScalar,
         // p2panda operation representing the entry payload.
         operation: EncodedOperationScalar,
+        // Optional key identifying retries of the same request.
+        idempotency_key: Option<String>,
     ) -> Result<NextArguments> {
-        let store = ctx.data::<SqlStore>()?;
-        let tx = ctx.data::<ServiceSender>()?;
-        let schema_provider = ctx.data::<SchemaProvider>()?;
+        let idempotency_cache = ctx.data::<IdempotencyCache>()?;
 
         let encoded_entry: EncodedEntry = entry.into();
         let encoded_operation: EncodedOperation = operation.into();
@@ -48,47 +58,214 @@ impl Publish {
             encoded_entry.hash()
         );
 
-        let operation = decode_operation(&encoded_operation)?;
+        let pending_request = match idempotency_key {
+            Some(key) => {
+                let public_key = decode_entry(&encoded_entry)?.public_key().to_owned();
 
-        let schema = schema_provider
-            .get(operation.schema_id())
-            .await
-            .ok_or_else(|| anyhow!("Schema not found"))?;
+                match idempotency_cache.begin(&public_key, &key, &encoded_entry.hash())? {
+                    Begin::Cached(response) => {
+                        debug!("Return cached response for idempotency key '{}'", key);
+                        return Ok(response);
+                    }
+                    Begin::Pending(pending_request) => Some(pending_request),
+                }
+            }
+            None => None,
+        };
 
-        /////////////////////////////////////
-        // PUBLISH THE ENTRY AND OPERATION //
-        /////////////////////////////////////
+        let response = publish_entry(ctx, &encoded_entry, &encoded_operation).await?;
 
-        let (backlink, skiplink, seq_num, log_id) = publish(
-            store,
-            &schema,
-            &encoded_entry,
-            &operation,
-            &encoded_operation,
-        )
-        .await?;
+        // Remember successful responses. Failed or cancelled requests are forgotten when the
+        // pending request gets dropped, they can be retried with the same key
+        if let Some(pending_request) = pending_request {
+            pending_request.complete(&response);
+        }
+
+        Ok(response)
+    }
+}
+
+/// Validate and store an entry and operation, then inform the materializer about it.
+pub(super) async fn publish_entry(
+    ctx: &Context<'_>,
+    encoded_entry: &EncodedEntry,
+    encoded_operation: &EncodedOperation,
+) -> Result<NextArguments> {
+    let tx = ctx.data::<ServiceSender>()?;
+
+    let validated = validate_entry(ctx, encoded_entry, encoded_operation).await?;
+    let next_args = store_entry(ctx, &validated, encoded_entry, encoded_operation).await?;
 
-        ////////////////////////////////////////
-        // SEND THE OPERATION TO MATERIALIZER //
-        ////////////////////////////////////////
+    ////////////////////////////////////////
+    // SEND THE OPERATION TO MATERIALIZER //
+    ////////////////////////////////////////
 
-        // Send new operation on service communication bus, this will arrive eventually at
-        // the materializer service
+    // Send new operation on service communication bus, this will arrive eventually at
+    // the materializer service
 
-        let operation_id: OperationId = encoded_entry.hash().into();
+    let operation_id: OperationId = encoded_entry.hash().into();
 
-        if tx.send(ServiceMessage::NewOperation(operation_id)).is_err() {
-            // Silently fail here as we don't mind if there are no subscribers. We have
-            // tests in other places to check if messages arrive.
+    if tx.send(ServiceMessage::NewOperation(operation_id)).is_err() {
+        // Silently fail here as we don't mind if there are no subscribers. We have
+        // tests in other places to check if messages arrive.
+    }
+
+    Ok(next_args)
+}
+
+/// Entry and operation which passed all checks before they can get stored.
+pub(super) struct ValidatedEntry {
+    pub entry: Entry,
+    pub operation: PlainOperation,
+    pub schema: Schema,
+}
+
+/// Decode an entry and operation and check if they can get published on this node.
+pub(super) async fn validate_entry(
+    ctx: &Context<'_>,
+    encoded_entry: &EncodedEntry,
+    encoded_operation: &EncodedOperation,
+) -> Result<ValidatedEntry> {
+    let store = ctx.data::<SqlStore>()?;
+    let schema_provider = ctx.data::<SchemaProvider>()?;
+    let capability_provider = ctx.data::<CapabilityProvider>()?;
+
+    let operation = decode_operation(encoded_operation)?;
+
+    let schema = schema_provider
+        .get(operation.schema_id())
+        .await
+        .ok_or_else(|| anyhow!("Schema not found"))?;
+
+    ///////////////////////////////////////
+    // CHECK CAPABILITIES OF THE AUTHOR //
+    ///////////////////////////////////////
+
+    let entry = decode_entry(encoded_entry)?;
+
+    if capability_provider.is_enabled() {
+        let permission = capability_provider.publish_permission(schema.id());
+
+        if !capability_provider
+            .is_permitted(store, entry.public_key(), &permission)
+            .await?
+        {
+            return Err(anyhow!(
+                "Public key {} is not permitted to publish operations for schema {}",
+                entry.public_key(),
+                schema.id()
+            )
+            .into());
         }
+    }
+
+    /////////////////////////////////////
+    // CHECK ENCODING OF THE OPERATION //
+    /////////////////////////////////////
+
+    if !schema_provider.accepts_encoding(&operation, encoded_operation) {
+        warn!(
+            "Rejected operation of {} not encoded in canonical CBOR",
+            entry.public_key()
+        );
+
+        return Err(anyhow!("Operation is not encoded in canonical CBOR").into());
+    }
 
-        Ok(NextArguments {
-            log_id: log_id.into(),
-            seq_num: seq_num.into(),
-            backlink: backlink.map(|hash| hash.into()),
-            skiplink: skiplink.map(|hash| hash.into()),
-        })
+    //////////////////////////////////
+    // REFUSE EXTENDING FORKED LOGS //
+    //////////////////////////////////
+
+    if store
+        .is_log_forked(entry.public_key(), entry.log_id())
+        .await?
+    {
+        return Err(anyhow!(
+            "Log {} of public key {} has forked and can not be extended anymore",
+            entry.log_id().as_u64(),
+            entry.public_key()
+        )
+        .into());
     }
+
+    /////////////////////////////
+    // CHECK FIELD CONSTRAINTS //
+    /////////////////////////////
+
+    if let Err(violation) = schema_provider.check_constraints(&schema, &operation) {
+        return Err(Error::new(violation.to_string())
+            .extend_with(|_, extensions| extensions.set("field", violation.field())));
+    }
+
+    Ok(ValidatedEntry {
+        entry,
+        operation,
+        schema,
+    })
+}
+
+/// Store a validated entry and operation, returns the arguments for publishing the next entry in
+/// the same log.
+///
+/// The materializer is not informed about the new operation.
+pub(super) async fn store_entry(
+    ctx: &Context<'_>,
+    validated: &ValidatedEntry,
+    encoded_entry: &EncodedEntry,
+    encoded_operation: &EncodedOperation,
+) -> Result<NextArguments> {
+    let store = ctx.data::<SqlStore>()?;
+    let tx = ctx.data::<ServiceSender>()?;
+    let ValidatedEntry {
+        entry,
+        operation,
+        schema,
+    } = validated;
+
+    /////////////////////////////////////
+    // PUBLISH THE ENTRY AND OPERATION //
+    /////////////////////////////////////
+
+    let result = publish(store, schema, encoded_entry, operation, encoded_operation).await;
+
+    let (backlink, skiplink, seq_num, log_id) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            // Check if the entry got rejected because it conflicts with the log we already
+            // hold, another device might have published to it with the same key pair
+            let fork = store.detect_log_fork(entry, &encoded_entry.hash()).await?;
+
+            if fork.is_none() {
+                return Err(err.into());
+            }
+
+            warn!(
+                "Detected fork in log {} of {}",
+                entry.log_id().as_u64(),
+                entry.public_key()
+            );
+
+            let _ = tx.send(ServiceMessage::LogForked(
+                entry.public_key().to_owned(),
+                entry.log_id().to_owned(),
+            ));
+
+            return Err(anyhow!(
+                "Entry conflicts with log {} of public key {}, is the key pair used on more than \
+                one device?",
+                entry.log_id().as_u64(),
+                entry.public_key()
+            )
+            .into());
+        }
+    };
+
+    Ok(NextArguments {
+        log_id: log_id.into(),
+        seq_num: seq_num.into(),
+        backlink: backlink.map(|hash| hash.into()),
+        skiplink: skiplink.map(|hash| hash.into()),
+    })
 }
 
 #[cfg(test)]
@@ -104,7 +281,7 @@ mod tests {
     use p2panda_rs::entry::traits::AsEncodedEntry;
     use p2panda_rs::entry::{EncodedEntry, EntryBuilder, LogId, SeqNum};
     use p2panda_rs::hash::Hash;
-    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::identity::{KeyPair, PublicKey};
     use p2panda_rs::operation::encode::encode_operation;
     use p2panda_rs::operation::{
         EncodedOperation, OperationBuilder, OperationValue, PinnedRelationList,
@@ -123,8 +300,11 @@ mod tests {
     use tokio::sync::broadcast;
 
     use crate::bus::ServiceMessage;
-    use crate::graphql::GraphQLSchemaManager;
+    use crate::capabilities::CapabilityProvider;
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
     use crate::http::HttpServiceContext;
+    use crate::schema::FieldConstraint;
+    use crate::test_utils::helpers::non_canonical_operation;
     use crate::test_utils::{
         add_schema, doggo_fields, doggo_schema, http_test_client, populate_and_materialize,
         populate_store_config, test_runner, PopulateStoreConfig, TestNode,
@@ -230,16 +410,16 @@ mod tests {
             populate_and_materialize(&mut node, &config).await;
 
             let (tx, _rx) = broadcast::channel(120);
-            let manager = GraphQLSchemaManager::new(
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
                 node.context.store.clone(),
                 tx,
                 node.context.schema_provider.clone(),
-            )
+            ))
             .await;
             let context = HttpServiceContext::new(
                 node.context.store.clone(),
                 manager,
-                node.context.config.blobs_base_path.to_path_buf(),
+                node.context.blob_store.clone(),
             );
 
             let response = context.schema.execute(publish_request).await;
@@ -258,6 +438,76 @@ mod tests {
         });
     }
 
+    #[rstest]
+    fn retry_with_idempotency_key(
+        #[from(populate_store_config)]
+        #[with(0, 0, vec![], false, test_schema())]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(|mut node: TestNode| async move {
+            // Adds the test_schema to the store and schema provider.
+            populate_and_materialize(&mut node, &config).await;
+
+            let client = http_test_client(&node).await;
+
+            let query = r#"
+                mutation TestPublish($entry: String!, $operation: String!, $key: String) {
+                    publish(entry: $entry, operation: $operation, idempotencyKey: $key) {
+                        logId,
+                        seqNum,
+                        backlink,
+                        skiplink
+                    }
+                }"#;
+
+            let expected_response = json!({
+                "data": {
+                    "publish": {
+                        "logId": "0",
+                        "seqNum": "2",
+                        "backlink": "0020dda3b3977477e4c621ce124903a736e54b139afcb033e99677a6c8470b26514c",
+                        "skiplink": null
+                    }
+                }
+            });
+
+            let publish = |key: &'static str| {
+                client
+                    .post("/graphql")
+                    .json(&json!({
+                        "query": query,
+                        "variables": {
+                            "entry": EncodedEntry::from_bytes(&ENTRY_ENCODED).to_string(),
+                            "operation": EncodedOperation::from_bytes(&OPERATION_ENCODED).to_string(),
+                            "key": key,
+                        }
+                    }))
+                    .send()
+            };
+
+            // Publish the entry and retry the same request, both receive the same response
+            let response = publish("retry-me").await;
+            assert_eq!(
+                response.json::<serde_json::Value>().await,
+                expected_response
+            );
+
+            let response = publish("retry-me").await;
+            assert_eq!(
+                response.json::<serde_json::Value>().await,
+                expected_response
+            );
+
+            // Without the same key the sequence number is occupied
+            let response = publish("other-key").await;
+            let response = response.json::<serde_json::Value>().await;
+            assert_eq!(
+                response["errors"][0]["message"],
+                "Entry's claimed seq num of 1 does not match expected seq num of 2 for given public key and log"
+            );
+        });
+    }
+
     #[rstest]
     fn publish_entry_with_empty_relation_list(
         #[from(populate_store_config)]
@@ -295,16 +545,16 @@ mod tests {
             let entry = encode_entry(&entry).unwrap();
 
             let (tx, _rx) = broadcast::channel(120);
-            let manager = GraphQLSchemaManager::new(
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
                 node.context.store.clone(),
                 tx,
                 node.context.schema_provider.clone(),
-            )
+            ))
             .await;
             let context = HttpServiceContext::new(
                 node.context.store.clone(),
                 manager,
-                node.context.config.blobs_base_path.to_path_buf(),
+                node.context.blob_store.clone(),
             );
 
             let response = context
@@ -316,6 +566,168 @@ mod tests {
         });
     }
 
+    #[rstest]
+    #[case::admin(vec![key_pair(PRIVATE_KEY).public_key()], true)]
+    #[case::no_capability(vec![KeyPair::new().public_key()], false)]
+    fn checks_capabilities(
+        #[from(populate_store_config)]
+        #[with(0, 0, vec![], false, test_schema())]
+        config: PopulateStoreConfig,
+        publish_request: Request,
+        #[case] admin_public_keys: Vec<PublicKey>,
+        #[case] is_permitted: bool,
+    ) {
+        test_runner(move |mut node: TestNode| async move {
+            // Adds the test_schema to the store and schema provider.
+            populate_and_materialize(&mut node, &config).await;
+
+            // Enable access control, no capability documents have been published yet so only
+            // admins are allowed to publish
+            let capability_schema_id = SchemaId::from_str(
+                "capability_0020c65567ae37efea293e34a9c7d13f8f2bf23dbdc3b5c7b9ab46293111c48fc78b",
+            )
+            .unwrap();
+
+            let (tx, _rx) = broadcast::channel(120);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(CapabilityProvider::new(
+                    Some(capability_schema_id),
+                    admin_public_keys,
+                )),
+            )
+            .await;
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                manager,
+                node.context.blob_store.clone(),
+            );
+
+            let response = context.schema.execute(publish_request).await;
+            assert_eq!(response.is_ok(), is_permitted, "{:?}", response.errors);
+        });
+    }
+
+    #[rstest]
+    #[case::valid(64, true)]
+    #[case::too_long(8, false)]
+    fn checks_field_constraints(
+        #[from(populate_store_config)]
+        #[with(0, 0, vec![], false, test_schema())]
+        config: PopulateStoreConfig,
+        publish_request: Request,
+        #[case] max_length: usize,
+        #[case] is_valid: bool,
+    ) {
+        test_runner(move |mut node: TestNode| async move {
+            // Adds the test_schema to the store and schema provider.
+            populate_and_materialize(&mut node, &config).await;
+
+            let mut constraint = FieldConstraint::new(test_schema().id(), "message");
+            constraint.max_length = Some(max_length);
+            let schema_provider = node
+                .context
+                .schema_provider
+                .clone()
+                .with_field_constraints(vec![constraint]);
+
+            let (tx, _rx) = broadcast::channel(120);
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                schema_provider,
+            ))
+            .await;
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                manager,
+                node.context.blob_store.clone(),
+            );
+
+            let response = context.schema.execute(publish_request).await;
+            assert_eq!(response.is_ok(), is_valid, "{:?}", response.errors);
+
+            if !is_valid {
+                let error = &response.errors[0];
+                assert_eq!(
+                    error.message,
+                    "Field 'message' needs to have a length of at most 8"
+                );
+                assert_eq!(
+                    error.extensions.as_ref().unwrap().get("field"),
+                    Some(&value!("message"))
+                );
+            }
+        });
+    }
+
+    #[rstest]
+    #[case::lenient(false, true)]
+    #[case::strict(true, false)]
+    fn checks_canonical_encoding(
+        #[from(populate_store_config)]
+        #[with(0, 0, vec![], false, test_schema())]
+        config: PopulateStoreConfig,
+        #[case] require_canonical_encoding: bool,
+        #[case] is_accepted: bool,
+    ) {
+        test_runner(move |mut node: TestNode| async move {
+            // Adds the test_schema to the store and schema provider.
+            populate_and_materialize(&mut node, &config).await;
+
+            let schema_provider = node
+                .context
+                .schema_provider
+                .clone()
+                .with_canonical_encoding(require_canonical_encoding);
+
+            let (tx, _rx) = broadcast::channel(120);
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                schema_provider,
+            ))
+            .await;
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                manager,
+                node.context.blob_store.clone(),
+            );
+
+            // Sign an entry for a lenient encoding of the operation
+            let encoded_operation =
+                non_canonical_operation(&EncodedOperation::from_bytes(&OPERATION_ENCODED));
+            let entry = encoded_entry(
+                1,
+                0,
+                None,
+                None,
+                encoded_operation.clone(),
+                key_pair(PRIVATE_KEY),
+            );
+
+            let response = context
+                .schema
+                .execute(publish_request(
+                    &entry.to_string(),
+                    &encoded_operation.to_string(),
+                ))
+                .await;
+            assert_eq!(response.is_ok(), is_accepted, "{:?}", response.errors);
+
+            if !is_accepted {
+                assert_eq!(
+                    response.errors[0].message,
+                    "Operation is not encoded in canonical CBOR"
+                );
+            }
+        });
+    }
+
     #[rstest]
     fn sends_message_on_communication_bus(
         #[from(populate_store_config)]
@@ -327,16 +739,16 @@ mod tests {
             // Adds the test_schema to the store and schema provider.
             populate_and_materialize(&mut node, &config).await;
             let (tx, mut rx) = broadcast::channel(120);
-            let manager = GraphQLSchemaManager::new(
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
                 node.context.store.clone(),
                 tx,
                 node.context.schema_provider.clone(),
-            )
+            ))
             .await;
             let context = HttpServiceContext::new(
                 node.context.store.clone(),
                 manager,
-                node.context.config.blobs_base_path.to_path_buf(),
+                node.context.blob_store.clone(),
             );
 
             context.schema.execute(publish_request).await;
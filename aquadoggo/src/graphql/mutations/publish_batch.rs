@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::{debug, warn};
+use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::operation::EncodedOperation;
+
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::db::SqlStore;
+use crate::graphql::mutations::publish::{store_entry, validate_entry};
+use crate::graphql::mutations::MutationRoot;
+use crate::graphql::responses::NextArguments;
+use crate::graphql::scalars::{EncodedEntryScalar, EncodedOperationScalar};
+
+/// Maximum number of entries which can be published in one batch.
+const MAX_BATCH_SIZE: usize = 256;
+
+/// GraphQL "publishBatch" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct PublishBatch(MutationRoot);
+
+#[MutationFields]
+impl PublishBatch {
+    /// Publish entries of one or many documents atomically, either all of them get published or
+    /// none.
+    ///
+    /// Entries are published in the given order, later entries can depend on earlier ones, for
+    /// example a document and the documents relating to it. The operations are only materialized
+    /// when the whole batch got published.
+    ///
+    /// Returns arguments for publishing the next entry in the log of every published entry.
+    async fn publish_batch(
+        ctx: &Context<'_>,
+        // Signed and encoded entries to publish.
+        entries: Vec<EncodedEntryScalar>,
+        // p2panda operations representing the payloads of the entries, in the same order.
+        operations: Vec<EncodedOperationScalar>,
+    ) -> Result<Vec<NextArguments>> {
+        let store = ctx.data::<SqlStore>()?;
+        let tx = ctx.data::<ServiceSender>()?;
+
+        if entries.len() != operations.len() {
+            return Err(anyhow!("Every entry in a batch needs exactly one operation").into());
+        }
+
+        if entries.is_empty() || entries.len() > MAX_BATCH_SIZE {
+            return Err(anyhow!("Batches need between 1 and {} entries", MAX_BATCH_SIZE).into());
+        }
+
+        let commits: Vec<(EncodedEntry, EncodedOperation)> = entries
+            .into_iter()
+            .map(EncodedEntry::from)
+            .zip(operations.into_iter().map(EncodedOperation::from))
+            .collect();
+
+        debug!(
+            "Query to publish batch of {} entries received",
+            commits.len()
+        );
+
+        ////////////////////////////////////
+        // VALIDATE ALL ENTRIES UP FRONT //
+        ////////////////////////////////////
+
+        let mut validated = Vec::with_capacity(commits.len());
+        for (index, (encoded_entry, encoded_operation)) in commits.iter().enumerate() {
+            let entry = validate_entry(ctx, encoded_entry, encoded_operation)
+                .await
+                .map_err(|err| anyhow!("Rejected entry {} of batch: {}", index, err.message))?;
+            validated.push(entry);
+        }
+
+        ///////////////////////////////////////
+        // PUBLISH THE ENTRIES AND OPERATIONS //
+        ///////////////////////////////////////
+
+        // Entries are recorded as pending before they get stored, this allows us to remove them
+        // again when any entry of the batch fails, even after the node stopped in between
+        let batch_id = hex::encode(rand::random::<[u8; 16]>());
+        let mut next_args = Vec::with_capacity(commits.len());
+
+        for (index, ((encoded_entry, encoded_operation), validated)) in
+            commits.iter().zip(validated.iter()).enumerate()
+        {
+            let entry_hash = encoded_entry.hash();
+
+            let result = match store
+                .insert_pending_batch_entry(&batch_id, &validated.entry, &entry_hash)
+                .await
+            {
+                Ok(_) => {
+                    let result =
+                        store_entry(ctx, validated, encoded_entry, encoded_operation).await;
+
+                    // Entries which did not get stored must not be removed during rollback, they
+                    // might have been published before
+                    if result.is_err() {
+                        store
+                            .remove_pending_batch_entry(&batch_id, &entry_hash)
+                            .await?;
+                    }
+
+                    result
+                }
+                Err(err) => Err(err.into()),
+            };
+
+            match result {
+                Ok(args) => next_args.push(args),
+                Err(err) => {
+                    warn!(
+                        "Rolling back batch after entry {} got rejected: {}",
+                        index, err.message
+                    );
+                    store.rollback_batch(&batch_id).await?;
+
+                    return Err(
+                        anyhow!("Rejected entry {} of batch: {}", index, err.message).into(),
+                    );
+                }
+            }
+        }
+
+        store.complete_batch(&batch_id).await?;
+
+        /////////////////////////////////////////
+        // SEND THE OPERATIONS TO MATERIALIZER //
+        /////////////////////////////////////////
+
+        for (encoded_entry, _) in &commits {
+            if tx
+                .send(ServiceMessage::NewOperation(encoded_entry.hash().into()))
+                .is_err()
+            {
+                // Silently fail here as we don't mind if there are no subscribers. We have
+                // tests in other places to check if messages arrive.
+            }
+        }
+
+        Ok(next_args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Request, Variables};
+    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::entry::encode::sign_and_encode_entry;
+    use p2panda_rs::entry::traits::AsEncodedEntry;
+    use p2panda_rs::entry::{EncodedEntry, LogId, SeqNum};
+    use p2panda_rs::hash::Hash;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::encode::encode_operation;
+    use p2panda_rs::operation::{
+        EncodedOperation, OperationAction, OperationBuilder, OperationValue,
+    };
+    use p2panda_rs::schema::{FieldType, Schema};
+    use p2panda_rs::storage_provider::traits::EntryStore;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_hash};
+    use rstest::rstest;
+    use tokio::sync::broadcast;
+
+    use crate::bus::ServiceMessage;
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::http::HttpServiceContext;
+    use crate::test_utils::{add_schema, test_runner, TestNode};
+
+    const PUBLISH_BATCH_QUERY: &str = r#"
+        mutation TestPublishBatch($entries: [String!]!, $operations: [String!]!) {
+            publishBatch(entries: $entries, operations: $operations) {
+                logId,
+                seqNum
+            }
+        }"#;
+
+    /// Signs a CREATE and an UPDATE operation in the first log of the key pair, the UPDATE entry
+    /// links to the given backlink or the CREATE entry.
+    fn create_and_update(
+        schema: &Schema,
+        key_pair: &KeyPair,
+        backlink: Option<Hash>,
+    ) -> Vec<(EncodedEntry, EncodedOperation)> {
+        let create_operation = encode_operation(
+            &OperationBuilder::new(schema.id())
+                .fields(&[("name", OperationValue::String("Pub".into()))])
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        let create_entry = sign_and_encode_entry(
+            &LogId::default(),
+            &SeqNum::default(),
+            None,
+            None,
+            &create_operation,
+            key_pair,
+        )
+        .unwrap();
+
+        let update_operation = encode_operation(
+            &OperationBuilder::new(schema.id())
+                .action(OperationAction::Update)
+                .previous(&DocumentViewId::new(&[create_entry.hash().into()]))
+                .fields(&[("name", OperationValue::String("Bar".into()))])
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+        let update_entry = sign_and_encode_entry(
+            &LogId::default(),
+            &SeqNum::new(2).unwrap(),
+            None,
+            Some(&backlink.unwrap_or_else(|| create_entry.hash())),
+            &update_operation,
+            key_pair,
+        )
+        .unwrap();
+
+        vec![
+            (create_entry, create_operation),
+            (update_entry, update_operation),
+        ]
+    }
+
+    fn publish_batch_request(commits: &[(EncodedEntry, EncodedOperation)]) -> Request {
+        let entries: Vec<String> = commits.iter().map(|(entry, _)| entry.to_string()).collect();
+        let operations: Vec<String> = commits
+            .iter()
+            .map(|(_, operation)| operation.to_string())
+            .collect();
+
+        Request::new(PUBLISH_BATCH_QUERY).variables(Variables::from_value(value!({
+            "entries": entries,
+            "operations": operations,
+        })))
+    }
+
+    #[rstest]
+    fn publishes_batches_atomically(key_pair: KeyPair, #[from(random_hash)] wrong_backlink: Hash) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let (tx, mut rx) = broadcast::channel(120);
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                node.context.schema_provider.clone(),
+            ))
+            .await;
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                manager,
+                node.context.blob_store.clone(),
+            );
+
+            // All entries of a valid batch get published and materialized
+            let commits = create_and_update(&schema, &KeyPair::new(), None);
+            let response = context
+                .schema
+                .execute(publish_batch_request(&commits))
+                .await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+            assert_eq!(
+                response.data,
+                value!({
+                    "publishBatch": [
+                        { "logId": "0", "seqNum": "2" },
+                        { "logId": "0", "seqNum": "3" },
+                    ]
+                })
+            );
+
+            for (entry, _) in &commits {
+                assert!(matches!(
+                    rx.recv().await.unwrap(),
+                    ServiceMessage::NewOperation(operation_id)
+                        if operation_id.as_hash() == &entry.hash()
+                ));
+            }
+
+            // Entries published before a rejected entry are removed again
+            let commits = create_and_update(&schema, &KeyPair::new(), Some(wrong_backlink));
+            let response = context
+                .schema
+                .execute(publish_batch_request(&commits))
+                .await;
+            assert!(response.is_err());
+            assert!(response.errors[0].message.contains("Rejected entry 1"));
+
+            let store = &node.context.store;
+            for (entry, _) in &commits {
+                assert!(store.get_entry(&entry.hash()).await.unwrap().is_none());
+            }
+            assert!(rx.try_recv().is_err());
+
+            // The rejected batch can be published again after fixing it
+            let key_pair = KeyPair::new();
+            let commits = create_and_update(&schema, &key_pair, Some(wrong_backlink));
+            let response = context
+                .schema
+                .execute(publish_batch_request(&commits))
+                .await;
+            assert!(response.is_err());
+
+            let commits = create_and_update(&schema, &key_pair, None);
+            let response = context
+                .schema
+                .execute(publish_batch_request(&commits))
+                .await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+        });
+    }
+
+    #[rstest]
+    fn rejects_invalid_batches(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let (tx, _rx) = broadcast::channel(120);
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                node.context.schema_provider.clone(),
+            ))
+            .await;
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                manager,
+                node.context.blob_store.clone(),
+            );
+
+            // Empty batches
+            let response = context.schema.execute(publish_batch_request(&[])).await;
+            assert!(response.is_err());
+
+            // Entries without operations
+            let commits = create_and_update(&schema, &KeyPair::new(), None);
+            let request =
+                Request::new(PUBLISH_BATCH_QUERY).variables(Variables::from_value(value!({
+                    "entries": [commits[0].0.to_string(), commits[1].0.to_string()],
+                    "operations": [commits[0].1.to_string()],
+                })));
+            let response = context.schema.execute(request).await;
+            assert!(response.is_err());
+        });
+    }
+}
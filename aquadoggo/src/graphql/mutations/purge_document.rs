@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::info;
+use p2panda_rs::document::DocumentId;
+
+use crate::api::purge_document;
+use crate::blobs::BlobStore;
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::capabilities::CapabilityProvider;
+use crate::db::SqlStore;
+use crate::graphql::mutations::{check_admin, MutationRoot};
+use crate::graphql::responses::PurgeResult;
+use crate::graphql::scalars::DocumentIdScalar;
+use crate::materializer::{Task, TaskInput};
+
+/// GraphQL "purgeDocument" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct PurgeDocument(MutationRoot);
+
+#[MutationFields]
+impl PurgeDocument {
+    /// Purge a document with all its entries, operations and materialized views right away,
+    /// instead of waiting for garbage collection.
+    ///
+    /// With `includeBlobs` the files and pieces of purged blobs are removed as well, blobs the
+    /// document relates to are purged when nothing else relates to them anymore. Purging is
+    /// idempotent, documents which do not exist result in an empty report. The request needs to
+    /// be authenticated with an auth token of an admin, purging is refused when no admin public
+    /// keys are configured on this node.
+    ///
+    /// Returns a report of what got removed.
+    async fn purge_document(
+        ctx: &Context<'_>,
+        // Id of the document to purge.
+        id: DocumentIdScalar,
+        // Remove blob files and pieces as well.
+        include_blobs: bool,
+    ) -> Result<PurgeResult> {
+        let store = ctx.data::<SqlStore>()?;
+        let tx = ctx.data::<ServiceSender>()?;
+        let blob_store = ctx
+            .data_opt::<BlobStore>()
+            .ok_or_else(|| anyhow!("Purging documents is not supported by this node"))?;
+
+        let document_id = DocumentId::from(&id);
+
+        ///////////////////////////////////////
+        // CHECK CAPABILITIES OF THE REQUEST //
+        ///////////////////////////////////////
+
+        check_admin(ctx, "purge documents").await?;
+
+        ////////////////////////
+        // PURGE THE DOCUMENT //
+        ////////////////////////
+
+        let report = purge_document(store, blob_store, &document_id, include_blobs).await?;
+
+        // Purged documents might have been capability documents
+        ctx.data::<CapabilityProvider>()?.invalidate();
+        if !report.is_empty() {
+            info!(
+                "Purged document {} with {} operations",
+                document_id,
+                report.removed_operations.len()
+            );
+        }
+
+        // Garbage collect views of related documents which are not pinned anymore
+        for affected_id in &report.affected_documents {
+            let task = Task::new(
+                "garbage_collection",
+                TaskInput::DocumentId(affected_id.to_owned()),
+            );
+            if tx.send(ServiceMessage::ScheduleTask(task)).is_err() {
+                // Silently fail here as we don't mind if there are no subscribers. Views of these
+                // documents get garbage collected with their next update.
+            }
+        }
+
+        Ok(PurgeResult::from(&report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::storage_provider::traits::DocumentStore;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{
+        add_document, add_schema, auth_token, http_test_client, test_runner,
+        test_runner_with_manager, TestNode, TestNodeManager,
+    };
+    use crate::Configuration;
+
+    fn purge_query(document_id: &DocumentId) -> String {
+        format!(
+            r#"mutation {{
+                purgeDocument(id: "{}", includeBlobs: true) {{
+                    removedOperations
+                    removedViews
+                    removedBlobs
+                }}
+            }}"#,
+            document_id
+        )
+    }
+
+    #[rstest]
+    fn purges_document(key_pair: KeyPair) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let mut node = manager
+                .create_with_config(Configuration {
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Pub".into())],
+                &key_pair,
+            )
+            .await;
+            let document_id = DocumentId::new(view_id.graph_tips().first().unwrap());
+
+            let client = http_test_client(&node).await;
+            let query = purge_query(&document_id);
+            let authorization = format!("Bearer {}", auth_token(&node, &admin));
+
+            // Requests of anyone else than an admin are rejected
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": query }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &key_pair)),
+                )
+                .json(&json!({ "query": query }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+
+            let response = client
+                .post("/graphql")
+                .header("Authorization", &authorization)
+                .json(&json!({ "query": query }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "purgeDocument": {
+                        "removedOperations": 1,
+                        "removedViews": [view_id.to_string()],
+                        "removedBlobs": [],
+                    }
+                })
+            );
+            assert!(node
+                .context
+                .store
+                .get_document(&document_id)
+                .await
+                .unwrap()
+                .is_none());
+
+            // Purging the document again is not an error
+            let response = client
+                .post("/graphql")
+                .header("Authorization", &authorization)
+                .json(&json!({ "query": query }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "purgeDocument": {
+                        "removedOperations": 0,
+                        "removedViews": [],
+                        "removedBlobs": [],
+                    }
+                })
+            );
+        });
+    }
+
+    #[rstest]
+    fn refuses_purging_without_admins(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Pub".into())],
+                &key_pair,
+            )
+            .await;
+            let document_id = DocumentId::new(view_id.graph_tips().first().unwrap());
+
+            // Nodes without configured admins don't allow purging, not even with an auth token
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &key_pair)),
+                )
+                .json(&json!({ "query": purge_query(&document_id) }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("no admin public keys are configured"));
+            assert!(node
+                .context
+                .store
+                .get_document(&document_id)
+                .await
+                .unwrap()
+                .is_some());
+        });
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::{info, warn};
+
+use crate::capabilities::Authenticated;
+use crate::db::SqlStore;
+use crate::graphql::mutations::MutationRoot;
+use crate::replication::now;
+
+/// GraphQL "redeemInvite" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct RedeemInvite(MutationRoot);
+
+#[MutationFields]
+impl RedeemInvite {
+    /// Redeem an invite code handed out by the node operator.
+    ///
+    /// The request needs to be authenticated with an auth token, the public key of the token is
+    /// allowed to publish to the schemas of the invite until it expires. Every invite can only be
+    /// redeemed once.
+    ///
+    /// Returns true when the invite was redeemed.
+    async fn redeem_invite(
+        ctx: &Context<'_>,
+        // Invite code handed out by the node operator.
+        code: String,
+    ) -> Result<bool> {
+        let store = ctx.data::<SqlStore>()?;
+
+        let public_key = match ctx.data_opt::<Authenticated>() {
+            Some(authenticated) => &authenticated.0,
+            None => return Err(anyhow!("Redeeming invites requires an auth token").into()),
+        };
+
+        if !store.redeem_invite(&code, public_key, now() as i64).await? {
+            warn!("Rejected invalid or expired invite of {}", public_key);
+            return Err(anyhow!("Invite is invalid, expired or was already redeemed").into());
+        }
+
+        info!("Public key {} redeemed an invite", public_key);
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use async_graphql::{value, Request, Variables};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::SchemaId;
+    use rstest::rstest;
+    use tokio::sync::broadcast;
+
+    use crate::capabilities::{Authenticated, CapabilityProvider, Invite, Permission};
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::test_utils::{test_runner, TestNode};
+
+    const REDEEM_INVITE_QUERY: &str = r#"
+        mutation TestRedeemInvite($code: String!) {
+            redeemInvite(code: $code)
+        }"#;
+
+    #[rstest]
+    fn redeemed_invites_grant_publishing() {
+        test_runner(|node: TestNode| async move {
+            let capability_schema_id = SchemaId::from_str(
+                "capability_0020c65567ae37efea293e34a9c7d13f8f2bf23dbdc3b5c7b9ab46293111c48fc78b",
+            )
+            .unwrap();
+            let capability_provider = CapabilityProvider::new(
+                Some(capability_schema_id),
+                vec![KeyPair::new().public_key()],
+            );
+
+            let (tx, _) = broadcast::channel(16);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(capability_provider.clone()),
+            )
+            .await;
+
+            let invite = Invite::create(
+                &node.context.store,
+                vec![SchemaId::Blob(1)],
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+            let invited = KeyPair::new().public_key();
+
+            let request = || {
+                Request::new(REDEEM_INVITE_QUERY).variables(Variables::from_value(value!({
+                    "code": invite.code.clone(),
+                })))
+            };
+
+            // Requests without auth token are rejected
+            let response = manager.execute(request()).await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            assert!(!capability_provider
+                .is_permitted(
+                    &node.context.store,
+                    &invited,
+                    &Permission::Publish(SchemaId::Blob(1))
+                )
+                .await
+                .unwrap());
+
+            let response = manager
+                .execute(request().data(Authenticated(invited)))
+                .await;
+            assert_eq!(response.data, value!({ "redeemInvite": true }));
+
+            // The invited public key may publish to the schemas of the invite, nothing else
+            for (permission, expected) in [
+                (Permission::Publish(SchemaId::Blob(1)), true),
+                (Permission::Publish(SchemaId::BlobPiece(1)), false),
+                (Permission::Admin, false),
+            ] {
+                assert_eq!(
+                    capability_provider
+                        .is_permitted(&node.context.store, &invited, &permission)
+                        .await
+                        .unwrap(),
+                    expected
+                );
+            }
+
+            // Invites can only be redeemed once
+            let response = manager
+                .execute(request().data(Authenticated(KeyPair::new().public_key())))
+                .await;
+            assert!(response.errors[0].message.contains("already redeemed"));
+        });
+    }
+}
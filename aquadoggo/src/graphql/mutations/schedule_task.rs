@@ -0,0 +1,355 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::anyhow;
+use dynamic_graphql::{Context, Mutation, MutationFields, Result};
+use log::info;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::storage_provider::traits::OperationStore;
+
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::db::SqlStore;
+use crate::graphql::mutations::{check_admin, MutationRoot};
+use crate::graphql::scalars::{DocumentIdScalar, DocumentViewIdScalar};
+use crate::materializer::tasks::is_valid_task;
+use crate::materializer::{Task, TaskInput};
+
+/// GraphQL "scheduleTask" mutation.
+#[derive(Mutation, Default, Debug, Copy, Clone)]
+pub struct ScheduleTask(MutationRoot);
+
+#[MutationFields]
+impl ScheduleTask {
+    /// Manually schedule a materializer task, for example to recover a stuck document.
+    ///
+    /// Either a document id or a document view id needs to be given, depending on the worker. The
+    /// request needs to be authenticated with an auth token of an admin, it is refused when no
+    /// admin public keys are configured on this node.
+    ///
+    /// Returns true when the task was moved into the queue.
+    async fn schedule_task(
+        ctx: &Context<'_>,
+        // Name of the worker processing the task, for example "reduce".
+        worker: String,
+        // Id of the document the task is processing.
+        document_id: Option<DocumentIdScalar>,
+        // Id of the document view the task is processing.
+        view_id: Option<DocumentViewIdScalar>,
+    ) -> Result<bool> {
+        let store = ctx.data::<SqlStore>()?;
+        let tx = ctx.data::<ServiceSender>()?;
+
+        let input = match (document_id, view_id) {
+            (Some(document_id), None) => TaskInput::DocumentId(DocumentId::from(&document_id)),
+            (None, Some(view_id)) => TaskInput::DocumentViewId(DocumentViewId::from(view_id)),
+            _ => return Err(anyhow!("Expected either 'documentId' or 'viewId' argument").into()),
+        };
+
+        let task = Task::new(&worker, input);
+
+        if !is_valid_task(&task) {
+            return Err(anyhow!(
+                "Worker '{}' does not exist or does not accept {}",
+                worker,
+                task.input()
+            )
+            .into());
+        }
+
+        ///////////////////////////////////////
+        // CHECK CAPABILITIES OF THE REQUEST //
+        ///////////////////////////////////////
+
+        let public_key = check_admin(ctx, "schedule tasks").await?;
+
+        ////////////////////////////////////
+        // CHECK IF THE TASK INPUT EXISTS //
+        ////////////////////////////////////
+
+        match task.input() {
+            TaskInput::DocumentId(document_id) => {
+                if store
+                    .get_operations_by_document_id(document_id)
+                    .await?
+                    .is_empty()
+                {
+                    return Err(anyhow!("Document {} not found", document_id).into());
+                }
+            }
+            TaskInput::DocumentViewId(view_id) => {
+                for operation_id in view_id.iter() {
+                    if store.get_operation(operation_id).await?.is_none() {
+                        return Err(anyhow!("Operation {} not found", operation_id).into());
+                    }
+                }
+            }
+        }
+
+        ///////////////////////////////////////
+        // SEND THE TASK TO THE MATERIALIZER //
+        ///////////////////////////////////////
+
+        info!(
+            "Scheduled {} task for {} by {}",
+            worker,
+            task.input(),
+            public_key
+        );
+
+        if tx.send(ServiceMessage::ScheduleTask(task)).is_err() {
+            return Err(anyhow!("Materializer service is not running").into());
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Request, Variables};
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+    use tokio::sync::broadcast;
+
+    use crate::bus::ServiceMessage;
+    use crate::capabilities::{Authenticated, CapabilityProvider};
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::http::HttpServiceContext;
+    use crate::materializer::{Task, TaskInput};
+    use crate::test_utils::{
+        populate_store, populate_store_config, test_runner, PopulateStoreConfig, TestNode,
+    };
+
+    const SCHEDULE_TASK_QUERY: &str = r#"
+        mutation TestScheduleTask($worker: String!, $documentId: String, $viewId: String) {
+            scheduleTask(worker: $worker, documentId: $documentId, viewId: $viewId)
+        }"#;
+
+    async fn http_context(
+        node: &TestNode,
+        capability_provider: CapabilityProvider,
+    ) -> (HttpServiceContext, broadcast::Receiver<ServiceMessage>) {
+        let (tx, rx) = broadcast::channel(120);
+        let manager = GraphQLSchemaManager::new(
+            GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                node.context.schema_provider.clone(),
+            )
+            .with_capability_provider(capability_provider),
+        )
+        .await;
+        let context = HttpServiceContext::new(
+            node.context.store.clone(),
+            manager,
+            node.context.blob_store.clone(),
+        );
+
+        (context, rx)
+    }
+
+    #[rstest]
+    fn schedules_task(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(|node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            let document_id = documents[0].id().to_owned();
+            let view_id = documents[0].view_id().to_owned();
+
+            let admin = KeyPair::new();
+            let (context, mut rx) = http_context(
+                &node,
+                CapabilityProvider::new(None, vec![admin.public_key()]),
+            )
+            .await;
+
+            let request = Request::new(SCHEDULE_TASK_QUERY)
+                .variables(Variables::from_value(value!({
+                    "worker": "reduce",
+                    "documentId": document_id.to_string(),
+                })))
+                .data(Authenticated(admin.public_key()));
+            let response = context.schema.execute(request).await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+            assert_eq!(response.data, value!({ "scheduleTask": true }));
+
+            let request = Request::new(SCHEDULE_TASK_QUERY)
+                .variables(Variables::from_value(value!({
+                    "worker": "dependency",
+                    "viewId": view_id.to_string(),
+                })))
+                .data(Authenticated(admin.public_key()));
+            let response = context.schema.execute(request).await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+
+            assert_eq!(
+                rx.recv().await.unwrap(),
+                ServiceMessage::ScheduleTask(Task::new(
+                    "reduce",
+                    TaskInput::DocumentId(document_id)
+                ))
+            );
+            assert_eq!(
+                rx.recv().await.unwrap(),
+                ServiceMessage::ScheduleTask(Task::new(
+                    "dependency",
+                    TaskInput::DocumentViewId(view_id)
+                ))
+            );
+        });
+    }
+
+    #[rstest]
+    #[case::unknown_worker("sleep", true, false, "Worker 'sleep' does not exist")]
+    #[case::wrong_input("garbage_collection", false, true, "does not accept")]
+    #[case::missing_input("reduce", false, false, "Expected either 'documentId' or 'viewId'")]
+    #[case::both_inputs("reduce", true, true, "Expected either 'documentId' or 'viewId'")]
+    fn rejects_invalid_tasks(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        #[case] worker: &'static str,
+        #[case] with_document_id: bool,
+        #[case] with_view_id: bool,
+        #[case] expected_error: &'static str,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            let admin = KeyPair::new();
+            let (context, mut rx) = http_context(
+                &node,
+                CapabilityProvider::new(None, vec![admin.public_key()]),
+            )
+            .await;
+
+            let mut variables = value!({ "worker": worker });
+            if let async_graphql::Value::Object(map) = &mut variables {
+                if with_document_id {
+                    map.insert(
+                        async_graphql::Name::new("documentId"),
+                        value!(documents[0].id().to_string()),
+                    );
+                }
+                if with_view_id {
+                    map.insert(
+                        async_graphql::Name::new("viewId"),
+                        value!(documents[0].view_id().to_string()),
+                    );
+                }
+            }
+
+            let request = Request::new(SCHEDULE_TASK_QUERY)
+                .variables(Variables::from_value(variables))
+                .data(Authenticated(admin.public_key()));
+            let response = context.schema.execute(request).await;
+            assert!(
+                response.errors[0].message.contains(expected_error),
+                "{:?}",
+                response.errors
+            );
+            assert!(rx.try_recv().is_err());
+        });
+    }
+
+    #[rstest]
+    fn rejects_unknown_documents() {
+        test_runner(|node: TestNode| async move {
+            let admin = KeyPair::new();
+            let (context, _rx) = http_context(
+                &node,
+                CapabilityProvider::new(None, vec![admin.public_key()]),
+            )
+            .await;
+            let document_id = random_document_id();
+
+            let request = Request::new(SCHEDULE_TASK_QUERY)
+                .variables(Variables::from_value(value!({
+                    "worker": "reduce",
+                    "documentId": document_id.to_string(),
+                })))
+                .data(Authenticated(admin.public_key()));
+            let response = context.schema.execute(request).await;
+            assert_eq!(
+                response.errors[0].message,
+                format!("Document {} not found", document_id)
+            );
+        });
+    }
+
+    #[rstest]
+    #[case::admin(true, true, None)]
+    #[case::no_admin(true, false, Some("is not permitted"))]
+    #[case::no_auth_token(false, true, Some("requires an auth token"))]
+    fn checks_capabilities(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        #[case] is_authenticated: bool,
+        #[case] is_admin: bool,
+        #[case] expected_error: Option<&'static str>,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            let document_id = documents[0].id().to_owned();
+
+            let admin = KeyPair::new();
+            let public_key = if is_admin {
+                admin.public_key()
+            } else {
+                KeyPair::new().public_key()
+            };
+            let (context, _rx) = http_context(
+                &node,
+                CapabilityProvider::new(None, vec![admin.public_key()]),
+            )
+            .await;
+
+            let mut request =
+                Request::new(SCHEDULE_TASK_QUERY).variables(Variables::from_value(value!({
+                    "worker": "reduce",
+                    "documentId": document_id.to_string(),
+                })));
+            if is_authenticated {
+                request = request.data(Authenticated(public_key));
+            }
+            let response = context.schema.execute(request).await;
+
+            match expected_error {
+                Some(expected_error) => assert!(
+                    response.errors[0].message.contains(expected_error),
+                    "{:?}",
+                    response.errors
+                ),
+                None => assert!(response.is_ok(), "{:?}", response.errors),
+            }
+        });
+    }
+
+    #[rstest]
+    fn refuses_scheduling_without_admins(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            let (context, mut rx) = http_context(&node, CapabilityProvider::default()).await;
+
+            let request = Request::new(SCHEDULE_TASK_QUERY)
+                .variables(Variables::from_value(value!({
+                    "worker": "reduce",
+                    "documentId": documents[0].id().to_string(),
+                })))
+                .data(Authenticated(KeyPair::new().public_key()));
+            let response = context.schema.execute(request).await;
+            assert!(response.errors[0]
+                .message
+                .contains("no admin public keys are configured"));
+            assert!(rx.try_recv().is_err());
+        });
+    }
+}
@@ -38,7 +38,7 @@ pub fn build_paginated_document_object(schema: &Schema) -> Object {
                     let document = Resolved::downcast(&ctx);
 
                     let cursor = match &document {
-                        Resolved::CollectionDocument(cursor, _) => cursor,
+                        Resolved::CollectionDocument(cursor, _, _) => cursor,
                         _ => panic!("Paginated document expected"),
                     };
 
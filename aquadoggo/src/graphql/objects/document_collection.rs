@@ -26,7 +26,7 @@ pub fn build_document_collection_object(schema: &Schema) -> Object {
                         let collection = Resolved::downcast(&ctx);
 
                         let total_count = match collection {
-                            Resolved::Collection(page_info, _) => page_info.total_count,
+                            Resolved::Collection(page_info, _, _) => page_info.total_count,
                             _ => panic!("Expected document collection"),
                         };
 
@@ -47,7 +47,7 @@ pub fn build_document_collection_object(schema: &Schema) -> Object {
                         let collection = Resolved::downcast(&ctx);
 
                         let end_cursor = match collection {
-                            Resolved::Collection(page_info, _) => page_info.end_cursor,
+                            Resolved::Collection(page_info, _, _) => page_info.end_cursor,
                             _ => panic!("Expected document collection"),
                         };
 
@@ -71,7 +71,7 @@ pub fn build_document_collection_object(schema: &Schema) -> Object {
                         let collection = Resolved::downcast(&ctx);
 
                         let has_next_page = match collection {
-                            Resolved::Collection(page_info, _) => page_info.has_next_page,
+                            Resolved::Collection(page_info, _, _) => page_info.has_next_page,
                             _ => panic!("Expected document collection"),
                         };
 
@@ -92,8 +92,8 @@ pub fn build_document_collection_object(schema: &Schema) -> Object {
                         // Here we just pass up the root query parameters to be used in the fields
                         // resolver
                         let collection = Resolved::downcast(&ctx);
-                        let documents = match collection {
-                            Resolved::Collection(_, documents) => documents,
+                        let (documents, depth) = match collection {
+                            Resolved::Collection(_, documents, depth) => (documents, depth),
                             _ => panic!("Expected document collection"),
                         };
 
@@ -101,7 +101,7 @@ pub fn build_document_collection_object(schema: &Schema) -> Object {
                             .into_iter()
                             .map(|(cursor, document)| {
                                 FieldValue::owned_any(Resolved::CollectionDocument(
-                                    cursor, document,
+                                    cursor, document, depth,
                                 ))
                             })
                             .collect();
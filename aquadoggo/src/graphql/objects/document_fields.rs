@@ -1,15 +1,17 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use async_graphql::dynamic::{Field, FieldFuture, Object};
+use async_graphql::dynamic::{Field, FieldFuture, Object, TypeRef};
 use p2panda_rs::schema::{FieldType, Schema};
 
 use crate::graphql::resolvers::resolve_document_field;
 use crate::graphql::utils::{fields_name, graphql_type, with_collection_arguments};
+use crate::schema::SchemaProvider;
 
 /// Dynamically build GraphQL objects describing the application fields of a p2panda schema.
 ///
-/// Each generated object has a type name with the formatting `<schema_id>Fields`.
-pub fn build_document_fields_object(schema: &Schema) -> Object {
+/// Each generated object has a type name with the formatting `<schema_id>Fields`. Fields holding
+/// decimals are of the `Decimal` type, fields holding dates and times of the `DateTime` type.
+pub fn build_document_fields_object(schema: &Schema, schema_provider: &SchemaProvider) -> Object {
     // Construct the document fields object which will be named `<schema_id>Fields`
     let schema_field_name = fields_name(schema.id());
     let mut document_schema_fields = Object::new(schema_field_name);
@@ -26,14 +28,26 @@ pub fn build_document_fields_object(schema: &Schema) -> Object {
                     schema_id,
                 )
             }
-            _ => Field::new(name, graphql_type(field_type), move |ctx| {
-                FieldFuture::new(async move { resolve_document_field(ctx).await })
-            })
-            .description(format!(
-                "The `{}` field of a {} document.",
-                name,
-                schema.id().name()
-            )),
+            _ => {
+                // Decimals and dates are stored in integer or string fields but exposed as their
+                // own scalars
+                let type_ref = if schema_provider.decimal_scale(schema, name).is_some() {
+                    TypeRef::named("Decimal")
+                } else if schema_provider.is_datetime_field(schema, name) {
+                    TypeRef::named("DateTime")
+                } else {
+                    graphql_type(field_type)
+                };
+
+                Field::new(name, type_ref, move |ctx| {
+                    FieldFuture::new(async move { resolve_document_field(ctx).await })
+                })
+                .description(format!(
+                    "The `{}` field of a {} document.",
+                    name,
+                    schema.id().name()
+                ))
+            }
         };
         document_schema_fields = document_schema_fields.field(field).description(format!(
             "The application fields of a `{}` document.",
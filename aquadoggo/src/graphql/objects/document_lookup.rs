@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, FieldValue, Object, TypeRef};
+use async_graphql::Value;
+use p2panda_rs::schema::Schema;
+
+use crate::graphql::constants;
+use crate::graphql::resolvers::Resolved;
+use crate::graphql::utils::lookup_name;
+
+/// Dynamically build objects describing the result of looking up a document by its id.
+///
+/// Each object contains the requested `id` and the found `document`, which is `null` when it was
+/// not found.
+///
+/// Each generated object has a type name with the formatting `<schema_id>Lookup`.
+pub fn build_document_lookup_object(schema: &Schema) -> Object {
+    Object::new(lookup_name(schema.id()))
+        .field(
+            Field::new(
+                constants::DOCUMENT_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_ID),
+                move |ctx| {
+                    FieldFuture::new(async move {
+                        let document_id = match Resolved::downcast(&ctx) {
+                            Resolved::Lookup(document_id, _) => document_id,
+                            _ => panic!("Expected document lookup"),
+                        };
+
+                        Ok(Some(FieldValue::from(Value::from(document_id.as_str()))))
+                    })
+                },
+            )
+            .description("The requested document id."),
+        )
+        .field(
+            Field::new(
+                constants::DOCUMENT_FIELD,
+                TypeRef::named(schema.id().to_string()),
+                move |ctx| {
+                    FieldFuture::new(async move {
+                        let document = match Resolved::downcast(&ctx) {
+                            Resolved::Lookup(_, document) => document,
+                            _ => panic!("Expected document lookup"),
+                        };
+
+                        // Pass the document up to the children fields
+                        Ok(document
+                            .map(|document| FieldValue::owned_any(Resolved::Document(document, 0))))
+                    })
+                },
+            )
+            .description("The found document, `null` if it does not exist."),
+        )
+        .description(format!(
+            "Result of looking up a `{}` document by its id.",
+            schema.id().name()
+        ))
+}
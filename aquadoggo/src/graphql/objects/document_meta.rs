@@ -2,6 +2,7 @@
 
 use dynamic_graphql::SimpleObject;
 
+use crate::db::stores::DocumentStats as DocumentStatsData;
 use crate::graphql::scalars::{DocumentIdScalar, DocumentViewIdScalar, PublicKeyScalar};
 
 /// Meta fields of a document, contains id and authorship information.
@@ -17,4 +18,27 @@ pub struct DocumentMeta {
 
     /// The public key of the author who first created this document.
     pub owner: PublicKeyScalar,
+
+    /// Statistics about the history of this document, only available when the node maintains
+    /// document statistics.
+    pub stats: Option<DocumentStats>,
+}
+
+/// Statistics about the history of a document, maintained by the node.
+#[derive(SimpleObject)]
+pub struct DocumentStats {
+    /// Number of updates applied to this document.
+    pub updates: u64,
+
+    /// Number of distinct public keys which created, updated or deleted this document.
+    pub authors: u64,
+}
+
+impl From<DocumentStatsData> for DocumentStats {
+    fn from(stats: DocumentStatsData) -> Self {
+        Self {
+            updates: stats.update_count,
+            authors: stats.author_count,
+        }
+    }
 }
@@ -3,9 +3,11 @@
 mod document;
 mod document_collection;
 mod document_fields;
+mod document_lookup;
 mod document_meta;
 
 pub use document::{build_document_object, build_paginated_document_object};
 pub use document_collection::build_document_collection_object;
 pub use document_fields::build_document_fields_object;
-pub use document_meta::DocumentMeta;
+pub use document_lookup::build_document_lookup_object;
+pub use document_meta::{DocumentMeta, DocumentStats};
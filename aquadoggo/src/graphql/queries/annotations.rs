@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, TypeRef};
+use async_graphql::Value;
+use dynamic_graphql::{FieldValue, ScalarValue};
+use p2panda_rs::document::DocumentId;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::resolvers::reveals_document;
+use crate::graphql::responses::Annotation;
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// Add "annotations" query to the root query object.
+pub fn build_annotations_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::ANNOTATIONS_QUERY,
+            TypeRef::named_nn_list_nn(constants::ANNOTATION),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let document_id = ctx.args.try_get(constants::ANNOTATIONS_DOCUMENT_ID_ARG)?;
+                    let document_id =
+                        DocumentIdScalar::from_value(Value::from(document_id.string()?))?;
+
+                    // Annotations of documents the client is not allowed to read are hidden
+                    let document_id = DocumentId::from(&document_id);
+                    if !reveals_document(&ctx, &document_id).await? {
+                        return Ok(Some(FieldValue::list(Vec::<FieldValue>::new())));
+                    }
+
+                    let annotations: Vec<Annotation> = store
+                        .get_document_annotations(&document_id)
+                        .await?
+                        .into_iter()
+                        .map(Annotation::from)
+                        .collect();
+
+                    Ok(Some(FieldValue::list(
+                        annotations.into_iter().map(FieldValue::owned_any),
+                    )))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::ANNOTATIONS_DOCUMENT_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_ID),
+            )
+            .description("Id of the annotated document"),
+        )
+        .description(
+            "Return node-local annotations of a document. Annotations are never replicated to \
+            other nodes. Annotations of documents the client is not allowed to read are not \
+            returned.",
+        ),
+    )
+}
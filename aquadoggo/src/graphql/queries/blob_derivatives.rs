@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, TypeRef};
+use async_graphql::Value;
+use dynamic_graphql::{FieldValue, ScalarValue};
+use p2panda_rs::document::DocumentId;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::responses::BlobDerivative;
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// Add "blobDerivatives" query to the root query object.
+pub fn build_blob_derivatives_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::BLOB_DERIVATIVES_QUERY,
+            TypeRef::named_nn_list_nn(constants::BLOB_DERIVATIVE),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let document_id = ctx.args.try_get(constants::DOCUMENT_ID_ARG)?;
+                    let document_id =
+                        DocumentIdScalar::from_value(Value::from(document_id.string()?))?;
+
+                    let derivatives: Vec<BlobDerivative> = store
+                        .get_blob_derivatives(&DocumentId::from(&document_id))
+                        .await?
+                        .into_iter()
+                        .map(BlobDerivative::from)
+                        .collect();
+
+                    Ok(Some(FieldValue::list(
+                        derivatives.into_iter().map(FieldValue::owned_any),
+                    )))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_ID),
+            )
+            .description("Id of the blob document"),
+        )
+        .description(
+            "Return blobs derived from the latest version of a blob by transcoders of this node, \
+            for example web-friendly versions of audio and video files. Derived blobs are never \
+            replicated to other nodes.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_view_id};
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{add_blob, http_test_client, test_runner, TestNode};
+
+    #[rstest]
+    fn derivatives_of_latest_blob_view(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_view_id =
+                add_blob(&mut node, b"Hello, Panda!", 6, "text/plain", &key_pair).await;
+            let document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            let store = &node.context.store;
+            store
+                .insert_blob_derivative(&document_id, &blob_view_id, "upper", "text/plain", 13)
+                .await
+                .unwrap();
+
+            // Derivatives of other views are not returned
+            store
+                .insert_blob_derivative(
+                    &document_id,
+                    &random_document_view_id(),
+                    "lower",
+                    "text/plain",
+                    13,
+                )
+                .await
+                .unwrap();
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": format!(
+                        r#"{{
+                            blobDerivatives(id: "{}") {{
+                                name
+                                viewId
+                                mimeType
+                                length
+                                path
+                            }}
+                        }}"#,
+                        document_id
+                    )
+                }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(
+                response.data,
+                value!({
+                    "blobDerivatives": [{
+                        "name": "upper",
+                        "viewId": blob_view_id.to_string(),
+                        "mimeType": "text/plain",
+                        "length": 13,
+                        "path": format!("/blobs/{}/derived/upper", document_id),
+                    }]
+                })
+            );
+        })
+    }
+}
@@ -0,0 +1,146 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, TypeRef};
+use async_graphql::Value;
+use dynamic_graphql::{FieldValue, ScalarValue};
+use p2panda_rs::document::DocumentId;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::responses::BlobStatus;
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// Add "blobStatus" query to the root query object.
+pub fn build_blob_status_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::BLOB_STATUS_QUERY,
+            TypeRef::named(constants::BLOB_STATUS),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let document_id = ctx.args.try_get(constants::DOCUMENT_ID_ARG)?;
+                    let document_id =
+                        DocumentIdScalar::from_value(Value::from(document_id.string()?))?;
+
+                    let status = store
+                        .get_blob_status(&DocumentId::from(&document_id))
+                        .await?;
+
+                    Ok(status.map(|status| FieldValue::owned_any(BlobStatus::from(status))))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_ID),
+            )
+            .description("Id of the blob document"),
+        )
+        .description(
+            "Return which pieces of a blob are still missing on this node. Blob documents can be \
+            published before all of their pieces, interrupted uploads can be resumed by only \
+            publishing the missing pieces. Returns null if the blob document is not known.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_view_id};
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{add_document, http_test_client, test_runner, TestNode};
+
+    #[rstest]
+    fn missing_blob_pieces(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_piece_view_id = add_document(
+                &mut node,
+                &SchemaId::BlobPiece(1),
+                vec![("data", "Hello, ".as_bytes().into())],
+                &key_pair,
+            )
+            .await;
+            let missing_piece_view_id = random_document_view_id();
+
+            // Publish a blob of which only the first piece arrived at the node yet
+            let blob_view_id = add_document(
+                &mut node,
+                &SchemaId::Blob(1),
+                vec![
+                    ("length", 13_i64.into()),
+                    ("mime_type", "text/plain".into()),
+                    (
+                        "pieces",
+                        vec![blob_piece_view_id, missing_piece_view_id.clone()].into(),
+                    ),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": format!(
+                        r#"{{
+                            blobStatus(id: "{}") {{
+                                viewId
+                                length
+                                numPieces
+                                missingPieces
+                                isComplete
+                            }}
+                        }}"#,
+                        blob_view_id
+                    )
+                }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(
+                response.data,
+                value!({
+                    "blobStatus": {
+                        "viewId": blob_view_id.to_string(),
+                        "length": 13,
+                        "numPieces": 2,
+                        "missingPieces": [missing_piece_view_id.to_string()],
+                        "isComplete": false,
+                    }
+                })
+            );
+        })
+    }
+
+    #[rstest]
+    fn unknown_blob() {
+        test_runner(|node: TestNode| async move {
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": format!(
+                        r#"{{ blobStatus(id: "{}") {{ isComplete }} }}"#,
+                        random_document_view_id()
+                    )
+                }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(response.data, value!({ "blobStatus": null }));
+        })
+    }
+}
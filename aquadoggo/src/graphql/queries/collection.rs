@@ -29,9 +29,9 @@ pub fn build_collection_query(query: Object, schema: &Schema) -> Object {
                         schema.id()
                     );
 
-                    FieldFuture::new(
-                        async move { resolve_document_collection(ctx, schema, None).await },
-                    )
+                    FieldFuture::new(async move {
+                        resolve_document_collection(ctx, schema, None, 0).await
+                    })
                 },
             ),
             &schema_id,
@@ -48,16 +48,19 @@ pub fn build_collection_query(query: Object, schema: &Schema) -> Object {
 mod tests {
     use async_graphql::{value, Response, Value};
     use p2panda_rs::document::DocumentViewId;
-    use p2panda_rs::operation::{PinnedRelationList, RelationList};
+    use p2panda_rs::operation::{PinnedRelationList, Relation, RelationList};
     use p2panda_rs::schema::{FieldType, Schema, SchemaId};
     use p2panda_rs::test_utils::fixtures::key_pair;
     use p2panda_rs::{identity::KeyPair, operation::OperationValue};
     use rstest::rstest;
     use serde_json::{json, Value as JsonValue};
 
+    use crate::config::Configuration;
+    use crate::context::Context;
+    use crate::schema::{DateTimeField, DecimalField};
     use crate::test_utils::{
         add_document, add_schema, add_schema_and_documents, http_test_client, test_runner,
-        TestClient, TestNode,
+        test_runner_with_manager, update_document, TestClient, TestNode, TestNodeManager,
     };
 
     /// Make a GraphQL collection query for songs stored on the node.
@@ -1009,6 +1012,9 @@ mod tests {
     #[case("(filter: { audio: { notEq: \"aa\" } })", "")]
     #[case("(filter: { audio: { eq: \"E8\" } })", "")]
     #[case("(filter: { audio: { eq: \"\" } })", "")]
+    #[case("(filter: { lyrics: { countGte: 2 } })", "")]
+    #[case("(filter: { lyrics: { countEq: 0 } })", "")]
+    #[case("(filter: { lyrics: { countGt: 1, countLte: 10 } })", "")]
     #[case(
         "(orderDirection: DESC, orderBy: title)",
         "(orderDirection: ASC, orderBy: line)"
@@ -1451,4 +1457,460 @@ mod tests {
             assert_eq!(data["query"]["documents"].as_array().unwrap().len(), 0);
         })
     }
+
+    #[rstest]
+    fn filters_decimal_fields(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "products",
+                vec![("name", FieldType::String), ("price", FieldType::Integer)],
+                &key_pair,
+            )
+            .await;
+
+            // Prices are stored with two fractional digits
+            for (name, price) in [("Tea", 250), ("Cake", 1999), ("Refund", -50)] {
+                add_document(
+                    &mut node,
+                    schema.id(),
+                    vec![("name", name.into()), ("price", price.into())],
+                    &key_pair,
+                )
+                .await;
+            }
+
+            let schema_provider = node
+                .context
+                .schema_provider
+                .clone()
+                .with_decimal_fields(vec![DecimalField::new(schema.id(), "price", 2)]);
+            let node = TestNode {
+                context: Context::new(
+                    node.context.store.clone(),
+                    KeyPair::new(),
+                    node.context.config.clone(),
+                    schema_provider,
+                ),
+            };
+            let client = http_test_client(&node).await;
+
+            let query_prices = |filter: &str| {
+                json!({
+                    "query": format!(
+                        r#"{{
+                            query: all_{type_name}(filter: {{ price: {filter} }}, orderBy: price) {{
+                                documents {{ fields {{ price }} }}
+                            }}
+                        }}"#,
+                        type_name = schema.id(),
+                        filter = filter
+                    )
+                })
+            };
+
+            let response: Response = client
+                .post("/graphql")
+                .json(&query_prices(r#"{ gte: "-0.5", lt: "19.99" }"#))
+                .send()
+                .await
+                .json()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "query": {
+                        "documents": [
+                            { "fields": { "price": "-0.50" } },
+                            { "fields": { "price": "2.50" } },
+                        ]
+                    }
+                }),
+                "{:?}",
+                response.errors
+            );
+
+            let response: Response = client
+                .post("/graphql")
+                .json(&query_prices(r#"{ eq: "19.990" }"#))
+                .send()
+                .await
+                .json()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "query": { "documents": [{ "fields": { "price": "19.99" } }] }
+                }),
+                "{:?}",
+                response.errors
+            );
+
+            // Filter values with more fractional digits than the field are rejected
+            let response: Response = client
+                .post("/graphql")
+                .json(&query_prices(r#"{ eq: "19.999" }"#))
+                .send()
+                .await
+                .json()
+                .await;
+            assert!(response.is_err());
+        })
+    }
+
+    #[rstest]
+    fn filters_datetime_fields(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "events",
+                vec![
+                    ("title", FieldType::String),
+                    ("starts_at", FieldType::Integer),
+                    ("ends_at", FieldType::String),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            // Start times are stored as UNIX timestamps, end times as UTC strings
+            for (title, starts_at, ends_at) in [
+                ("Launch", 1_697_450_400_i64, "2023-10-16T12:00:00Z"),
+                ("Retro", 1_697_536_800_i64, "2023-10-17T11:00:00Z"),
+                ("Kickoff", 1_696_154_400_i64, "2023-10-01T11:00:00Z"),
+            ] {
+                add_document(
+                    &mut node,
+                    schema.id(),
+                    vec![
+                        ("title", title.into()),
+                        ("starts_at", starts_at.into()),
+                        ("ends_at", ends_at.into()),
+                    ],
+                    &key_pair,
+                )
+                .await;
+            }
+
+            let schema_provider = node
+                .context
+                .schema_provider
+                .clone()
+                .with_datetime_fields(vec![
+                    DateTimeField::new(schema.id(), "starts_at"),
+                    DateTimeField::new(schema.id(), "ends_at"),
+                ]);
+            let node = TestNode {
+                context: Context::new(
+                    node.context.store.clone(),
+                    KeyPair::new(),
+                    node.context.config.clone(),
+                    schema_provider,
+                ),
+            };
+            let client = http_test_client(&node).await;
+
+            let query_events = |filter: &str| {
+                json!({
+                    "query": format!(
+                        r#"{{
+                            query: all_{type_name}(filter: {filter}, orderBy: starts_at) {{
+                                documents {{ fields {{ starts_at ends_at }} }}
+                            }}
+                        }}"#,
+                        type_name = schema.id(),
+                        filter = filter
+                    )
+                })
+            };
+
+            // Filter values are converted to UTC
+            let response: Response = client
+                .post("/graphql")
+                .json(&query_events(
+                    r#"{
+                        starts_at: { gte: "2023-10-16T12:00:00+02:00" },
+                        ends_at: { lte: "2023-10-17T13:00:00+02:00" }
+                    }"#,
+                ))
+                .send()
+                .await
+                .json()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "query": {
+                        "documents": [
+                            {
+                                "fields": {
+                                    "starts_at": "2023-10-16T10:00:00Z",
+                                    "ends_at": "2023-10-16T12:00:00Z",
+                                }
+                            },
+                            {
+                                "fields": {
+                                    "starts_at": "2023-10-17T10:00:00Z",
+                                    "ends_at": "2023-10-17T11:00:00Z",
+                                }
+                            },
+                        ]
+                    }
+                }),
+                "{:?}",
+                response.errors
+            );
+
+            // UNIX timestamps are accepted as filter values as well
+            let response: Response = client
+                .post("/graphql")
+                .json(&query_events(r#"{ ends_at: { lt: 1697450400 } }"#))
+                .send()
+                .await
+                .json()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "query": {
+                        "documents": [{
+                            "fields": {
+                                "starts_at": "2023-10-01T10:00:00Z",
+                                "ends_at": "2023-10-01T11:00:00Z",
+                            }
+                        }]
+                    }
+                }),
+                "{:?}",
+                response.errors
+            );
+
+            let response: Response = client
+                .post("/graphql")
+                .json(&query_events(r#"{ starts_at: { eq: "yesterday" } }"#))
+                .send()
+                .await
+                .json()
+                .await;
+            assert!(response.is_err());
+        })
+    }
+
+    #[rstest]
+    fn orders_by_fields_of_related_documents(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let (authors_schema, author_view_ids) = add_schema_and_documents(
+                &mut node,
+                "authors",
+                vec![
+                    vec![("name", "Panda".into(), None)],
+                    vec![("name", "Eve".into(), None)],
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let comments = author_view_ids
+                .iter()
+                .zip(["panda's comment", "eve's comment"])
+                .map(|(view_id, text)| {
+                    vec![
+                        ("text", text.into(), None),
+                        (
+                            "author",
+                            OperationValue::Relation(Relation::new(
+                                view_id.to_string().parse().unwrap(),
+                            )),
+                            Some(authors_schema.id().to_owned()),
+                        ),
+                    ]
+                })
+                .collect();
+            let (schema, _) =
+                add_schema_and_documents(&mut node, "comments", comments, &key_pair).await;
+
+            let client = http_test_client(&node).await;
+            let response: Response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": format!(
+                        r#"{{
+                            query: all_{type_name}(orderBy: author__name) {{
+                                documents {{ fields {{ text }} }}
+                            }}
+                        }}"#,
+                        type_name = schema.id(),
+                    )
+                }))
+                .send()
+                .await
+                .json()
+                .await;
+
+            assert_eq!(
+                response.data,
+                value!({
+                    "query": {
+                        "documents": [
+                            { "fields": { "text": "eve's comment" } },
+                            { "fields": { "text": "panda's comment" } },
+                        ]
+                    }
+                }),
+                "{:?}",
+                response.errors
+            );
+        })
+    }
+
+    #[rstest]
+    fn paginates_over_snapshot(key_pair: KeyPair) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            async fn query_venues(
+                client: &TestClient,
+                schema_id: &SchemaId,
+                args: &str,
+            ) -> Response {
+                client
+                    .post("/graphql")
+                    .json(&json!({
+                        "query": format!(
+                            r#"{{
+                                query: all_{schema_id}({args}) {{
+                                    totalCount
+                                    endCursor
+                                    documents {{ fields {{ name }} }}
+                                }}
+                            }}"#
+                        )
+                    }))
+                    .send()
+                    .await
+                    .json()
+                    .await
+            }
+
+            let config = Configuration {
+                graphql_snapshot_ttl: 300,
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let mut view_ids = Vec::new();
+            for name in ["Pub", "Bar", "Club"] {
+                let view_id = add_document(
+                    &mut node,
+                    schema.id(),
+                    vec![("name", name.into())],
+                    &key_pair,
+                )
+                .await;
+                view_ids.push(view_id);
+            }
+
+            let client = http_test_client(&node).await;
+
+            let response = query_venues(&client, schema.id(), "first: 2, snapshot: true").await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+            let data = response.data.into_json().unwrap();
+            let end_cursor = data["query"]["endCursor"].clone();
+            let mut names: Vec<JsonValue> = data["query"]["documents"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|document| document["fields"]["name"].clone())
+                .collect();
+
+            // Make sure changes don't happen in the same millisecond as the snapshot
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+            // Change the collection while paginating
+            for view_id in &view_ids {
+                update_document(
+                    &mut node,
+                    schema.id(),
+                    vec![("name", "Closed".into())],
+                    view_id,
+                    &key_pair,
+                )
+                .await;
+            }
+            add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Cafe".into())],
+                &key_pair,
+            )
+            .await;
+
+            // The following page shows the collection as it was at the first page
+            let response = query_venues(
+                &client,
+                schema.id(),
+                &format!("first: 2, after: {end_cursor}"),
+            )
+            .await;
+            assert!(response.is_ok(), "{:?}", response.errors);
+            let data = response.data.into_json().unwrap();
+            assert_eq!(data["query"]["totalCount"], json!(3));
+            names.extend(
+                data["query"]["documents"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|document| document["fields"]["name"].clone()),
+            );
+            names.sort_by_key(|name| name.to_string());
+            assert_eq!(names, vec![json!("Bar"), json!("Club"), json!("Pub")]);
+
+            // Without snapshot the current state is shown
+            let response = query_venues(&client, schema.id(), "first: 10").await;
+            let data = response.data.into_json().unwrap();
+            assert_eq!(data["query"]["totalCount"], json!(4));
+        });
+    }
+
+    #[rstest]
+    fn snapshots_disabled(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let client = http_test_client(&node).await;
+            let response: Response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": format!(
+                        r#"{{
+                            query: all_{}(snapshot: true) {{
+                                documents {{ meta {{ documentId }} }}
+                            }}
+                        }}"#,
+                        schema.id()
+                    )
+                }))
+                .send()
+                .await
+                .json()
+                .await;
+
+            assert_eq!(
+                response.errors[0].message,
+                "Snapshots are disabled on this node"
+            );
+        })
+    }
 }
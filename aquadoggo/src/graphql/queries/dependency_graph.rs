@@ -0,0 +1,251 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, TypeRef};
+use async_graphql::Value;
+use dynamic_graphql::{FieldValue, ScalarValue};
+use p2panda_rs::document::DocumentId;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::mutations::check_admin;
+use crate::graphql::responses::DependencyGraph;
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// Add "dependencyGraph" query to the root query object.
+pub fn build_dependency_graph_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::DEPENDENCY_GRAPH_QUERY,
+            TypeRef::named(constants::DEPENDENCY_GRAPH),
+            |ctx| {
+                FieldFuture::new(async move {
+                    check_admin(&ctx, "inspect dependency graphs").await?;
+
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let document_id = ctx.args.try_get(constants::DOCUMENT_ID_ARG)?;
+                    let document_id =
+                        DocumentIdScalar::from_value(Value::from(document_id.string()?))?;
+
+                    let graph = store
+                        .get_dependency_graph(&DocumentId::from(&document_id))
+                        .await?;
+
+                    Ok(graph.map(|graph| FieldValue::owned_any(DependencyGraph::from(graph))))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_ID),
+            )
+            .description("Id of the document"),
+        )
+        .description(
+            "Return the materialized views of a document, which other documents and views they \
+            relate to or are related from and which materializer tasks are pending for them. Meant \
+            for debugging documents which are stuck waiting for their dependencies. Returns null \
+            if the document is not known. Requires an auth token of an admin.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_id};
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::materializer::{Task, TaskInput};
+    use crate::test_utils::{
+        add_document, add_schema, auth_token, http_test_client, test_runner_with_manager, TestNode,
+        TestNodeManager,
+    };
+    use crate::Configuration;
+
+    async fn create_node(manager: &TestNodeManager, admin: &KeyPair) -> TestNode {
+        manager
+            .create_with_config(Configuration {
+                admin_public_keys: vec![admin.public_key()],
+                ..Configuration::default()
+            })
+            .await
+    }
+
+    #[rstest]
+    fn dependency_graph(key_pair: KeyPair) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let mut node = create_node(&manager, &admin).await;
+
+            let venue_schema = add_schema(
+                &mut node,
+                "venue",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let schema = add_schema(
+                &mut node,
+                "event",
+                vec![("venue", FieldType::Relation(venue_schema.id().to_owned()))],
+                &key_pair,
+            )
+            .await;
+            let missing_document_id = random_document_id();
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("venue", missing_document_id.clone().into())],
+                &key_pair,
+            )
+            .await;
+
+            // Materializer keeps waiting for the related document
+            node.context
+                .store
+                .insert_task(&Task::new(
+                    "dependency",
+                    TaskInput::DocumentViewId(view_id.clone()),
+                ))
+                .await
+                .unwrap();
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .json(&json!({
+                    "query": format!(
+                        r#"{{
+                            dependencyGraph(id: "{}") {{
+                                currentViewId
+                                views {{
+                                    viewId
+                                    isCurrent
+                                    relations {{
+                                        field
+                                        target
+                                        isPinned
+                                        isMaterialized
+                                    }}
+                                    referencedBy
+                                }}
+                                referencedBy
+                                pendingTasks {{
+                                    worker
+                                    documentId
+                                    viewId
+                                }}
+                            }}
+                        }}"#,
+                        view_id
+                    )
+                }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(
+                response.data,
+                value!({
+                    "dependencyGraph": {
+                        "currentViewId": view_id.to_string(),
+                        "views": [{
+                            "viewId": view_id.to_string(),
+                            "isCurrent": true,
+                            "relations": [{
+                                "field": "venue",
+                                "target": missing_document_id.to_string(),
+                                "isPinned": false,
+                                "isMaterialized": false,
+                            }],
+                            "referencedBy": [],
+                        }],
+                        "referencedBy": [],
+                        "pendingTasks": [{
+                            "worker": "dependency",
+                            "documentId": null,
+                            "viewId": view_id.to_string(),
+                        }],
+                    }
+                })
+            );
+        })
+    }
+
+    #[rstest]
+    fn unknown_document() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = create_node(&manager, &admin).await;
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .json(&json!({
+                    "query": format!(
+                        r#"{{ dependencyGraph(id: "{}") {{ currentViewId }} }}"#,
+                        random_document_id()
+                    )
+                }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(response.data, value!({ "dependencyGraph": null }));
+        })
+    }
+
+    #[rstest]
+    fn requires_admin() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = create_node(&manager, &admin).await;
+            let query = json!({
+                "query": format!(
+                    r#"{{ dependencyGraph(id: "{}") {{ currentViewId }} }}"#,
+                    random_document_id()
+                )
+            });
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&query)
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &KeyPair::new())),
+                )
+                .json(&query)
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+        })
+    }
+}
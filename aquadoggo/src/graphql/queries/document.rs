@@ -100,7 +100,11 @@ mod test {
     use rstest::rstest;
     use serde_json::json;
 
-    use crate::test_utils::{add_document, add_schema, http_test_client, test_runner, TestNode};
+    use crate::config::Configuration;
+    use crate::test_utils::{
+        add_document, add_schema, http_test_client, test_runner, test_runner_with_manager,
+        TestNode, TestNodeManager,
+    };
 
     #[rstest]
     fn single_query(#[from(random_key_pair)] key_pair: KeyPair) {
@@ -319,4 +323,58 @@ mod test {
             assert_eq!(response.data, expected_data, "{:#?}", response.errors);
         });
     }
+
+    #[rstest]
+    fn document_stats(#[from(random_key_pair)] key_pair: KeyPair) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let config = Configuration {
+                document_stats: true,
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            let schema = add_schema(
+                &mut node,
+                "schema_name",
+                vec![("bool", FieldType::Boolean)],
+                &key_pair,
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("bool", true.into())],
+                &key_pair,
+            )
+            .await;
+
+            let client = http_test_client(&node).await;
+            let query = format!(
+                r#"{{
+                    document: {type_name}(viewId: "{view_id}") {{
+                        meta {{ stats {{ updates authors }} }}
+                    }}
+                }}"#,
+                type_name = schema.id(),
+                view_id = view_id,
+            );
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": query,
+                }))
+                .send()
+                .await;
+
+            let response: Response = response.json().await;
+
+            let expected_data = value!({
+                "document": {
+                    "meta": { "stats": { "updates": 0, "authors": 1 } }
+                }
+            });
+            assert_eq!(response.data, expected_data, "{:#?}", response.errors);
+        });
+    }
 }
@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::str::FromStr;
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, ResolverContext, TypeRef};
+use async_graphql::Error;
+use dynamic_graphql::FieldValue;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::SchemaId;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::resolvers::reveals_document_of_schema;
+use crate::graphql::responses::{DocumentChangeFeed, DocumentChangeRecord};
+
+/// Number of document changes returned when not specified otherwise.
+const DEFAULT_DOCUMENT_CHANGES: u64 = 100;
+
+/// Maximum number of document changes returned by one query.
+const MAX_DOCUMENT_CHANGES: u64 = 1000;
+
+/// Add "documentChanges" query to the root query object.
+pub fn build_document_changes_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::DOCUMENT_CHANGES_QUERY,
+            TypeRef::named_nn(constants::DOCUMENT_CHANGE_FEED),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let (since, first) = parse_arguments(&ctx)?;
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let rows = store.get_document_changes(since, first).await?;
+
+                    // Continue after the last recorded change, also when it was hidden
+                    let next = rows
+                        .last()
+                        .map(|row| row.sequence.to_string())
+                        .unwrap_or_else(|| since.to_string());
+
+                    // Hide changes of documents the client is not allowed to read
+                    let mut changes = Vec::new();
+                    for row in rows {
+                        let schema_id = SchemaId::from_str(&row.schema_id)?;
+                        let document_id = DocumentId::from_str(&row.document_id)?;
+                        if reveals_document_of_schema(&ctx, &schema_id, &document_id).await? {
+                            changes.push(DocumentChangeRecord::from(row));
+                        }
+                    }
+
+                    Ok(Some(FieldValue::owned_any(DocumentChangeFeed {
+                        changes,
+                        next,
+                    })))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_CHANGES_SINCE_ARG,
+                TypeRef::named(TypeRef::STRING),
+            )
+            .description(
+                "Token of the last processed change, starts at the first change if not set.",
+            ),
+        )
+        .argument(
+            InputValue::new(
+                constants::PAGINATION_FIRST_ARG,
+                TypeRef::named(TypeRef::INT),
+            )
+            .description("Maximum number of returned changes")
+            .default_value(DEFAULT_DOCUMENT_CHANGES),
+        )
+        .description(
+            "Return documents which got created, updated or deleted on this node, ordered by the \
+            token of their change. Pass the returned `next` token in the following query to \
+            consume every change exactly once, also across restarts of the node. Changes of \
+            documents the client is not allowed to read are skipped.",
+        ),
+    )
+}
+
+/// Parse and validate the arguments passed to documentChanges.
+fn parse_arguments(ctx: &ResolverContext) -> Result<(u64, u64), Error> {
+    let since = match ctx.args.get(constants::DOCUMENT_CHANGES_SINCE_ARG) {
+        Some(since) if !since.is_null() => {
+            let since = since.string()?;
+            u64::from_str(since)
+                .map_err(|_| Error::new(format!("Invalid change token '{}'", since)))?
+        }
+        _ => 0,
+    };
+
+    let first = match ctx.args.get(constants::PAGINATION_FIRST_ARG) {
+        Some(first) => first.u64()?,
+        None => DEFAULT_DOCUMENT_CHANGES,
+    };
+    if first == 0 || first > MAX_DOCUMENT_CHANGES {
+        return Err(Error::new(format!(
+            "Number of changes needs to be between 1 and {}",
+            MAX_DOCUMENT_CHANGES
+        )));
+    }
+
+    Ok((since, first))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{
+        add_document, add_schema, http_test_client, test_runner, update_document, TestNode,
+    };
+
+    const QUERY: &str = r#"query Changes($since: String) {
+        documentChanges(since: $since, first: 1) {
+            changes {
+                token
+                change
+                documentId
+                viewId
+            }
+            next
+        }
+    }"#;
+
+    #[rstest]
+    fn document_changes(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let since = node
+                .context
+                .store
+                .get_document_changes(0, 100)
+                .await
+                .unwrap()
+                .last()
+                .unwrap()
+                .sequence;
+
+            let create_view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Pub".into()))],
+                &key_pair,
+            )
+            .await;
+            let document_id = DocumentId::new(create_view_id.graph_tips().first().unwrap());
+            let update_view_id = update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Bar".into()))],
+                &create_view_id,
+                &key_pair,
+            )
+            .await;
+
+            let client = http_test_client(&node).await;
+
+            // Changes are consumed one after another by passing on the `next` token
+            let mut since = since.to_string();
+            for (change, view_id) in [("create", create_view_id), ("update", update_view_id)] {
+                let response = client
+                    .post("/graphql")
+                    .json(&json!({ "query": QUERY, "variables": { "since": since } }))
+                    .send()
+                    .await
+                    .json::<Response>()
+                    .await;
+                let next = (since.parse::<u64>().unwrap() + 1).to_string();
+                assert_eq!(
+                    response.data,
+                    value!({
+                        "documentChanges": {
+                            "changes": [{
+                                "token": next.clone(),
+                                "change": change,
+                                "documentId": document_id.to_string(),
+                                "viewId": view_id.to_string(),
+                            }],
+                            "next": next.clone(),
+                        }
+                    }),
+                    "{:#?}",
+                    response.errors
+                );
+                since = next;
+            }
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY, "variables": { "since": since } }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({ "documentChanges": { "changes": [], "next": since } })
+            );
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY, "variables": { "since": "nope" } }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(response.errors[0].message, "Invalid change token 'nope'");
+        });
+    }
+}
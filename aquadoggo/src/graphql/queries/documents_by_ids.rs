@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, ResolverContext, TypeRef};
+use async_graphql::{Error, Value};
+use dynamic_graphql::ScalarValue;
+use log::debug;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::Schema;
+
+use crate::graphql::constants;
+use crate::graphql::resolvers::resolve_documents_by_ids;
+use crate::graphql::scalars::DocumentIdScalar;
+use crate::graphql::utils::lookup_name;
+
+/// Maximum number of documents which can be requested by one query.
+const MAX_DOCUMENT_IDS: usize = 100;
+
+/// Adds a GraphQL query for retrieving many documents selected by their ids to the root query
+/// object.
+///
+/// The query follows the format `byIds_<SCHEMA_ID>(ids: [<DOCUMENT_ID>])`. It returns one lookup
+/// for every requested id in the same order, their document is `null` if it was not found.
+pub fn build_documents_by_ids_query(query: Object, schema: &Schema) -> Object {
+    let schema_id = schema.id().clone();
+    let schema = schema.clone();
+
+    query.field(
+        Field::new(
+            format!("{}{}", constants::QUERY_BY_IDS_PREFIX, schema_id),
+            TypeRef::named_nn_list_nn(lookup_name(&schema_id)),
+            move |ctx| {
+                let schema = schema.clone();
+
+                FieldFuture::new(async move {
+                    let ids = parse_arguments(&ctx)?;
+                    resolve_documents_by_ids(ctx, schema, ids).await
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_IDS_ARG,
+                TypeRef::named_nn_list_nn(constants::DOCUMENT_ID),
+            )
+            .description("Specify the ids of the documents to be retrieved"),
+        )
+        .description(format!(
+            "Query many {} documents by their ids at once.",
+            schema_id.name()
+        )),
+    )
+}
+
+/// Parse and validate the arguments passed into this query.
+fn parse_arguments(ctx: &ResolverContext) -> Result<Vec<DocumentId>, Error> {
+    let mut ids = Vec::new();
+
+    for (name, value) in ctx.field().arguments()?.into_iter() {
+        if let (constants::DOCUMENT_IDS_ARG, Value::List(values)) = (name.as_str(), value) {
+            for value in values {
+                ids.push(DocumentId::from(&DocumentIdScalar::from_value(value)?));
+            }
+        }
+    }
+
+    if ids.len() > MAX_DOCUMENT_IDS {
+        return Err(Error::new(format!(
+            "Can not request more than {} documents at once",
+            MAX_DOCUMENT_IDS
+        )));
+    }
+
+    debug!(
+        "Query to {} received for {} documents",
+        ctx.field().name(),
+        ids.len()
+    );
+
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod test {
+    use async_graphql::{value, Response, Value};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::random_key_pair;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{add_document, add_schema, http_test_client, test_runner, TestNode};
+
+    const UNKNOWN_DOCUMENT_ID: &str =
+        "00208f7492d6eb01360a886dac93da88982029484d8c04a0bd2ac0607101b80a6634";
+
+    #[rstest]
+    fn documents_by_ids(#[from(random_key_pair)] key_pair: KeyPair) {
+        test_runner(move |mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "sensor",
+                vec![("temperature", FieldType::Integer)],
+                &key_pair,
+            )
+            .await;
+
+            let mut document_ids = Vec::new();
+            for temperature in 1..=3 {
+                let view_id = add_document(
+                    &mut node,
+                    schema.id(),
+                    vec![("temperature", (temperature as i64).into())],
+                    &key_pair,
+                )
+                .await;
+                document_ids.push(view_id.to_string());
+            }
+
+            let client = http_test_client(&node).await;
+            let query = format!(
+                r#"{{
+                    documents: byIds_{type_name}(ids: ["{}", "{}", "{}", "{}"]) {{
+                        id
+                        document {{ fields {{ temperature }} }}
+                    }}
+                }}"#,
+                document_ids[2],
+                UNKNOWN_DOCUMENT_ID,
+                document_ids[0],
+                document_ids[2],
+                type_name = schema.id(),
+            );
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": query,
+                }))
+                .send()
+                .await;
+
+            let response: Response = response.json().await;
+
+            // Documents are returned in the requested order, unknown ones as `null`
+            let expected_data = value!({
+                "documents": [
+                    { "id": document_ids[2], "document": { "fields": { "temperature": 3 } } },
+                    { "id": UNKNOWN_DOCUMENT_ID, "document": Value::Null },
+                    { "id": document_ids[0], "document": { "fields": { "temperature": 1 } } },
+                    { "id": document_ids[2], "document": { "fields": { "temperature": 3 } } },
+                ],
+            });
+            assert_eq!(response.data, expected_data, "{:#?}", response.errors);
+        });
+    }
+
+    #[rstest]
+    fn too_many_ids(#[from(random_key_pair)] key_pair: KeyPair) {
+        test_runner(move |mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "sensor",
+                vec![("temperature", FieldType::Integer)],
+                &key_pair,
+            )
+            .await;
+
+            let ids = vec![format!("\"{UNKNOWN_DOCUMENT_ID}\""); 101];
+            let client = http_test_client(&node).await;
+            let query = format!(
+                r#"{{ byIds_{}(ids: [{}]) {{ id }} }}"#,
+                schema.id(),
+                ids.join(", ")
+            );
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": query,
+                }))
+                .send()
+                .await;
+
+            let response: Response = response.json().await;
+            assert_eq!(
+                response.errors[0].message,
+                "Can not request more than 100 documents at once"
+            );
+        });
+    }
+}
@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, TypeRef};
+use async_graphql::Value;
+use dynamic_graphql::{FieldValue, ScalarValue};
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::resolvers::{reveals_document, reveals_document_view};
+use crate::graphql::scalars::{DocumentIdScalar, DocumentViewIdScalar};
+
+/// Add "documentExists" query to the root query object.
+pub fn build_document_exists_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::DOCUMENT_EXISTS_QUERY,
+            TypeRef::named_nn(TypeRef::BOOLEAN),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let document_id = ctx.args.try_get(constants::DOCUMENT_ID_ARG)?;
+                    let document_id =
+                        DocumentIdScalar::from_value(Value::from(document_id.string()?))?;
+
+                    // Documents the client is not allowed to read are reported as missing
+                    let document_id = DocumentId::from(&document_id);
+                    let exists = store.document_exists(&document_id).await?
+                        && reveals_document(&ctx, &document_id).await?;
+
+                    Ok(Some(FieldValue::value(exists)))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_ID),
+            )
+            .description("Id of the document"),
+        )
+        .description(
+            "Return true if this node materialized the document and it was not deleted. Cheaper \
+            than querying the document as none of its fields are loaded, unless it is access \
+            controlled or moderated. Documents the client is not allowed to read are reported \
+            as missing.",
+        ),
+    )
+}
+
+/// Add "viewExists" query to the root query object.
+pub fn build_view_exists_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::VIEW_EXISTS_QUERY,
+            TypeRef::named_nn(TypeRef::BOOLEAN),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let view_id = ctx.args.try_get(constants::DOCUMENT_VIEW_ID_ARG)?;
+                    let view_id = DocumentViewIdScalar::from_value(Value::from(view_id.string()?))?;
+
+                    // Views the client is not allowed to read are reported as missing
+                    let view_id = DocumentViewId::from(view_id);
+                    let exists = store.document_view_exists(&view_id).await?
+                        && reveals_document_view(&ctx, &view_id).await?;
+
+                    Ok(Some(FieldValue::value(exists)))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_VIEW_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_VIEW_ID),
+            )
+            .description("Id of the document view"),
+        )
+        .description(
+            "Return true if this node materialized the document view. Cheaper than querying the \
+            document as none of its fields are loaded, unless it is access controlled or \
+            moderated. Views the client is not allowed to read are reported as missing.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::document::DocumentViewId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::random_document_view_id;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{
+        http_test_client, populate_and_materialize, populate_store_config, test_runner,
+        PopulateStoreConfig, TestNode,
+    };
+
+    #[rstest]
+    fn document_and_view_exist(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        #[from(random_document_view_id)] unknown_view_id: DocumentViewId,
+    ) {
+        test_runner(|mut node: TestNode| async move {
+            let documents = populate_and_materialize(&mut node, &config).await;
+            let client = http_test_client(&node).await;
+
+            let query = format!(
+                r#"{{
+                    document: documentExists(id: "{}")
+                    view: viewExists(viewId: "{}")
+                    unknownDocument: documentExists(id: "{}")
+                    unknownView: viewExists(viewId: "{}")
+                }}"#,
+                documents[0].id(),
+                documents[0].view_id(),
+                unknown_view_id,
+                unknown_view_id,
+            );
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": query }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(
+                response.data,
+                value!({
+                    "document": true,
+                    "view": true,
+                    "unknownDocument": false,
+                    "unknownView": false,
+                }),
+                "{:?}",
+                response.errors
+            );
+        });
+    }
+}
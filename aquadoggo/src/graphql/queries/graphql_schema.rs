@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, Object, TypeRef};
+use dynamic_graphql::FieldValue;
+
+use crate::graphql::constants;
+use crate::graphql::responses::GraphQLSchemaInfo;
+use crate::graphql::GraphQLSdl;
+
+/// Add "graphqlSchema" query to the root query object.
+pub fn build_graphql_schema_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::GRAPHQL_SCHEMA_QUERY,
+            TypeRef::named_nn(constants::GRAPHQL_SCHEMA_INFO),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let (sdl, version) = ctx.data_unchecked::<GraphQLSdl>().current();
+                    Ok(Some(FieldValue::owned_any(GraphQLSchemaInfo {
+                        sdl,
+                        version,
+                    })))
+                })
+            },
+        )
+        .description(
+            "Return the SDL of the GraphQL schema served by this node and its version. The \
+            version increases every time new schemas get materialized, tooling can poll it to \
+            find out when to refresh.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_graphql::Response;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{add_schema, http_test_client, test_runner, TestNode};
+
+    const QUERY: &str = r#"{
+        graphqlSchema {
+            sdl
+            version
+        }
+    }"#;
+
+    #[rstest]
+    fn graphql_schema(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let client = http_test_client(&node).await;
+
+            let query = || async {
+                let response = client
+                    .post("/graphql")
+                    .json(&json!({ "query": QUERY }))
+                    .send()
+                    .await
+                    .json::<Response>()
+                    .await;
+                let data = response.data.into_json().unwrap();
+                (
+                    data["graphqlSchema"]["sdl"].as_str().unwrap().to_string(),
+                    data["graphqlSchema"]["version"].as_u64().unwrap(),
+                )
+            };
+
+            let (sdl, version) = query().await;
+            assert_eq!(version, 1);
+            assert!(sdl.contains("graphqlSchema: GraphQLSchemaInfo!"));
+
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            // Schema changes are collected for a moment before the GraphQL schema is rebuilt
+            let (sdl, version) = loop {
+                let (sdl, version) = query().await;
+                if version > 1 {
+                    break (sdl, version);
+                }
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            };
+            assert_eq!(version, 2);
+            assert!(sdl.contains(&format!("type {} {{", schema.id())));
+        });
+    }
+}
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, TypeRef};
+use async_graphql::Error;
+use dynamic_graphql::{FieldValue, ScalarValue};
+use p2panda_rs::schema::SchemaId;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::mutations::check_admin;
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// Add "heldDocuments" query to the root query object.
+pub fn build_held_documents_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::HELD_DOCUMENTS_QUERY,
+            TypeRef::named_nn_list_nn(constants::DOCUMENT_ID),
+            |ctx| {
+                FieldFuture::new(async move {
+                    check_admin(&ctx, "list held documents").await?;
+
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let schema_id = ctx
+                        .args
+                        .try_get(constants::HELD_DOCUMENTS_SCHEMA_ID_ARG)?
+                        .string()?;
+                    let schema_id: SchemaId = schema_id
+                        .parse()
+                        .map_err(|_| Error::new(format!("Invalid schema id '{schema_id}'")))?;
+
+                    let document_ids = store.get_held_document_ids(&schema_id).await?;
+
+                    Ok(Some(FieldValue::list(document_ids.iter().map(
+                        |document_id| {
+                            FieldValue::value(DocumentIdScalar::from(document_id).to_value())
+                        },
+                    ))))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::HELD_DOCUMENTS_SCHEMA_ID_ARG,
+                TypeRef::named_nn(TypeRef::STRING),
+            )
+            .description("Id of the moderated schema"),
+        )
+        .description(
+            "Return ids of documents of a moderated schema which arrived from other nodes and \
+            wait for approval. Held documents don't show up in queries until an admin approved \
+            them with the `approveDocument` mutation. Requires an auth token of an admin.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::schema_id;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{
+        auth_token, http_test_client, test_runner_with_manager, TestNodeManager,
+    };
+    use crate::Configuration;
+
+    #[rstest]
+    fn requires_admin() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = manager
+                .create_with_config(Configuration {
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+
+            let client = http_test_client(&node).await;
+            let query = json!({
+                "query": format!(r#"{{ heldDocuments(schemaId: "{}") }}"#, schema_id())
+            });
+
+            // Held documents are only listed to admins
+            let response = client
+                .post("/graphql")
+                .json(&query)
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &KeyPair::new())),
+                )
+                .json(&query)
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .json(&query)
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(response.data, value!({ "heldDocuments": [] }));
+        })
+    }
+}
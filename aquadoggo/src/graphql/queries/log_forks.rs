@@ -0,0 +1,185 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, Object, TypeRef};
+use dynamic_graphql::FieldValue;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::mutations::check_admin;
+use crate::graphql::responses::LogFork;
+
+/// Add "logForks" query to the root query object.
+pub fn build_log_forks_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::LOG_FORKS_QUERY,
+            TypeRef::named_nn_list_nn(constants::LOG_FORK),
+            |ctx| {
+                FieldFuture::new(async move {
+                    check_admin(&ctx, "list log forks").await?;
+
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let forks: Vec<LogFork> = store
+                        .get_log_forks()
+                        .await?
+                        .into_iter()
+                        .map(LogFork::from)
+                        .collect();
+
+                    Ok(Some(FieldValue::list(
+                        forks.into_iter().map(FieldValue::owned_any),
+                    )))
+                })
+            },
+        )
+        .description(
+            "Return logs in which conflicting entries of the same author were detected, latest \
+            first. Forked logs can not be extended on this node anymore, the key pair is probably \
+            used on more than one device. Requires an auth token of an admin.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::entry::decode::decode_entry;
+    use p2panda_rs::entry::encode::sign_and_encode_entry;
+    use p2panda_rs::entry::traits::AsEncodedEntry;
+    use p2panda_rs::entry::{LogId, SeqNum};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::EncodedOperation;
+    use p2panda_rs::test_utils::fixtures::encoded_operation;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{
+        auth_token, http_test_client, populate_store, populate_store_config,
+        test_runner_with_manager, PopulateStoreConfig, TestNodeManager,
+    };
+    use crate::Configuration;
+
+    const QUERY: &str = r#"{
+        logForks {
+            publicKey
+            logId
+            seqNum
+            conflictingEntryHash
+        }
+    }"#;
+
+    #[rstest]
+    fn log_forks(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+        encoded_operation: EncodedOperation,
+    ) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = manager
+                .create_with_config(Configuration {
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+            let _ = populate_store(&node.context.store, &config).await;
+            let client = http_test_client(&node).await;
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(response.data, value!({ "logForks": [] }));
+
+            let key_pair = config.authors.first().expect("At least one key pair");
+            let conflicting_entry = sign_and_encode_entry(
+                &LogId::default(),
+                &SeqNum::default(),
+                None,
+                None,
+                &encoded_operation,
+                key_pair,
+            )
+            .unwrap();
+            node.context
+                .store
+                .detect_log_fork(
+                    &decode_entry(&conflicting_entry).unwrap(),
+                    &conflicting_entry.hash(),
+                )
+                .await
+                .unwrap()
+                .expect("Fork detected");
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "logForks": [{
+                        "publicKey": key_pair.public_key().to_string(),
+                        "logId": "0",
+                        "seqNum": "1",
+                        "conflictingEntryHash": conflicting_entry.hash().to_string(),
+                    }]
+                })
+            );
+        });
+    }
+
+    #[rstest]
+    fn requires_admin() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = manager
+                .create_with_config(Configuration {
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &KeyPair::new())),
+                )
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+        })
+    }
+}
@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, Object, TypeRef};
+use dynamic_graphql::FieldValue;
+
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::responses::{MaterializerProgress, PendingTasks};
+
+/// Add "materializerProgress" query to the root query object.
+pub fn build_materializer_progress_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::MATERIALIZER_PROGRESS_QUERY,
+            TypeRef::named_nn(constants::MATERIALIZER_PROGRESS),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let store = ctx.data_unchecked::<SqlStore>();
+
+                    let pending_tasks: Vec<PendingTasks> = store
+                        .get_task_counts()
+                        .await?
+                        .into_iter()
+                        .map(|(worker, count)| PendingTasks { worker, count })
+                        .collect();
+                    let documents_awaiting_reduce = store.count_unindexed_documents().await?;
+
+                    let progress = MaterializerProgress {
+                        is_idle: pending_tasks.is_empty() && documents_awaiting_reduce == 0,
+                        pending_tasks,
+                        documents_awaiting_reduce,
+                    };
+
+                    Ok(Some(FieldValue::owned_any(progress)))
+                })
+            },
+        )
+        .description(
+            "Return progress of the node materializing received operations into documents.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::PublicKey;
+    use p2panda_rs::operation::{Operation, OperationId};
+    use p2panda_rs::storage_provider::traits::OperationStore;
+    use p2panda_rs::test_utils::fixtures::{document_id, operation, operation_id, public_key};
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::materializer::{Task, TaskInput};
+    use crate::test_utils::{http_test_client, test_runner, TestNode};
+
+    const QUERY: &str = r#"{
+        materializerProgress {
+            pendingTasks {
+                worker,
+                count
+            },
+            documentsAwaitingReduce,
+            isIdle
+        }
+    }"#;
+
+    #[rstest]
+    fn idle_materializer() {
+        test_runner(|node: TestNode| async move {
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(
+                response.data,
+                value!({
+                    "materializerProgress": {
+                        "pendingTasks": [],
+                        "documentsAwaitingReduce": 0,
+                        "isIdle": true,
+                    }
+                })
+            );
+        })
+    }
+
+    #[rstest]
+    fn pending_materialization(
+        operation: Operation,
+        operation_id: OperationId,
+        public_key: PublicKey,
+        document_id: DocumentId,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            // Insert an operation which did not get materialized yet
+            node.context
+                .store
+                .insert_operation(&operation_id, &public_key, &operation, &document_id)
+                .await
+                .unwrap();
+            node.context
+                .store
+                .insert_task(&Task::new("reduce", TaskInput::DocumentId(document_id)))
+                .await
+                .unwrap();
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+
+            assert_eq!(
+                response.data,
+                value!({
+                    "materializerProgress": {
+                        "pendingTasks": [{
+                            "worker": "reduce",
+                            "count": 1,
+                        }],
+                        "documentsAwaitingReduce": 1,
+                        "isIdle": false,
+                    }
+                })
+            );
+        })
+    }
+}
@@ -1,9 +1,37 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod annotations;
+mod blob_derivatives;
+mod blob_status;
 mod collection;
+mod dependency_graph;
 mod document;
+mod document_changes;
+mod documents_by_ids;
+mod exists;
+mod graphql_schema;
+mod held_documents;
+mod log_forks;
+mod materializer_progress;
+mod network_metrics;
 mod next_args;
+mod node_info;
+mod search;
 
+pub use annotations::build_annotations_query;
+pub use blob_derivatives::build_blob_derivatives_query;
+pub use blob_status::build_blob_status_query;
 pub use collection::build_collection_query;
+pub use dependency_graph::build_dependency_graph_query;
 pub use document::build_document_query;
+pub use document_changes::build_document_changes_query;
+pub use documents_by_ids::build_documents_by_ids_query;
+pub use exists::{build_document_exists_query, build_view_exists_query};
+pub use graphql_schema::build_graphql_schema_query;
+pub use held_documents::build_held_documents_query;
+pub use log_forks::build_log_forks_query;
+pub use materializer_progress::build_materializer_progress_query;
+pub use network_metrics::build_network_metrics_query;
 pub use next_args::build_next_args_query;
+pub use node_info::{build_node_info_query, HttpIdentity};
+pub use search::build_search_query;
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{Field, FieldFuture, Object, TypeRef};
+use dynamic_graphql::FieldValue;
+
+use crate::graphql::constants;
+use crate::graphql::mutations::check_admin;
+use crate::graphql::responses::NetworkTraffic;
+use crate::network::NetworkMetrics;
+
+/// Add "networkMetrics" query to the root query object.
+pub fn build_network_metrics_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::NETWORK_METRICS_QUERY,
+            TypeRef::named_nn_list_nn(constants::NETWORK_TRAFFIC),
+            |ctx| {
+                FieldFuture::new(async move {
+                    check_admin(&ctx, "inspect network metrics").await?;
+
+                    let network_metrics = ctx.data_unchecked::<NetworkMetrics>();
+
+                    let traffic: Vec<FieldValue> = network_metrics
+                        .snapshot()
+                        .into_iter()
+                        .map(|(class, metrics)| {
+                            FieldValue::owned_any(NetworkTraffic {
+                                transport: class.transport_str().to_string(),
+                                protocol: class.protocol.as_str().to_string(),
+                                inbound_bytes: metrics.inbound_bytes,
+                                outbound_bytes: metrics.outbound_bytes,
+                                open_connections: metrics.open_connections,
+                                total_connections: metrics.total_connections,
+                            })
+                        })
+                        .collect();
+
+                    Ok(Some(FieldValue::list(traffic)))
+                })
+            },
+        )
+        .description(
+            "Return bandwidth and connection metrics of the node per transport and protocol. \
+            Transports and protocols which have not been used yet are omitted. Requires an auth \
+            token of an admin.",
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use libp2p::swarm::ConnectionId;
+    use p2panda_rs::identity::KeyPair;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{
+        auth_token, http_test_client, test_runner_with_manager, TestNodeManager,
+    };
+    use crate::Configuration;
+
+    const QUERY: &str = r#"{
+        networkMetrics {
+            transport
+            protocol
+            inboundBytes
+            outboundBytes
+            openConnections
+            totalConnections
+        }
+    }"#;
+
+    #[rstest]
+    fn network_metrics() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = manager
+                .create_with_config(Configuration {
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+            let client = http_test_client(&node).await;
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(response.data, value!({ "networkMetrics": [] }));
+
+            let address = "/ip4/127.0.0.1/tcp/2022/p2p/12D3KooWLxGKMgUtekXam9JsSjMa3b7M3rYEYUYUywdehHTRrLgU/p2p-circuit"
+                .parse()
+                .unwrap();
+            node.context
+                .network_metrics
+                .on_connection_established(ConnectionId::new_unchecked(1), &address);
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "networkMetrics": [{
+                        "transport": "tcp",
+                        "protocol": "relay",
+                        "inboundBytes": 0,
+                        "outboundBytes": 0,
+                        "openConnections": 1,
+                        "totalConnections": 1,
+                    }]
+                })
+            );
+        });
+    }
+
+    #[rstest]
+    fn requires_admin() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+            let node = manager
+                .create_with_config(Configuration {
+                    admin_public_keys: vec![admin.public_key()],
+                    ..Configuration::default()
+                })
+                .await;
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0]
+                .message
+                .contains("requires an auth token"));
+
+            let response = client
+                .post("/graphql")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &KeyPair::new())),
+                )
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert!(response.errors[0].message.contains("is not permitted"));
+        })
+    }
+}
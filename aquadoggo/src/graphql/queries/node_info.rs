@@ -0,0 +1,257 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Instant;
+
+use async_graphql::dynamic::{Field, FieldFuture, Object, TypeRef};
+use dynamic_graphql::FieldValue;
+
+use crate::graphql::constants;
+use crate::graphql::responses::{NodeInfo, RelayInfo};
+use crate::network::{LocalAddresses, RelayStatus};
+
+/// Identity the HTTP API is served under instead of the peer id of the node.
+#[derive(Clone, Debug)]
+pub struct HttpIdentity(pub String);
+
+/// Add "nodeInfo" query to the root query object.
+pub fn build_node_info_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::NODE_INFO_QUERY,
+            TypeRef::named_nn(constants::NODE_INFO),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let local_addresses = ctx.data_unchecked::<LocalAddresses>();
+                    let statuses = local_addresses.relay_statuses();
+                    let now = Instant::now();
+
+                    // The peer id is not revealed when the HTTP API has its own identity
+                    let (identity, peer_id) = match ctx.data_opt::<HttpIdentity>() {
+                        Some(HttpIdentity(identity)) => (Some(identity.to_owned()), None),
+                        None => {
+                            let peer_id =
+                                local_addresses.peer_id().map(|peer_id| peer_id.to_string());
+                            (peer_id.clone(), peer_id)
+                        }
+                    };
+
+                    let node_info = NodeInfo {
+                        identity,
+                        peer_id,
+                        relay: local_addresses.relay().map(|(peer_id, address)| {
+                            match statuses.iter().find(|status| status.peer_id == peer_id) {
+                                Some(status) => relay_info(status, now),
+                                // We did not learn anything about the relay yet
+                                None => RelayInfo {
+                                    peer_id: peer_id.to_string(),
+                                    address: address.to_string(),
+                                    status: "pending".into(),
+                                    expires_in: None,
+                                    error: None,
+                                    reservation_accepted: false,
+                                },
+                            }
+                        }),
+                        relays: statuses
+                            .iter()
+                            .map(|status| relay_info(status, now))
+                            .collect(),
+                    };
+
+                    Ok(Some(FieldValue::owned_any(node_info)))
+                })
+            },
+        )
+        .description(
+            "Return information about this node, like the relay it currently uses and the state \
+            of its registration there.",
+        ),
+    )
+}
+
+fn relay_info(status: &RelayStatus, now: Instant) -> RelayInfo {
+    RelayInfo {
+        peer_id: status.peer_id.to_string(),
+        address: status.addr.to_string(),
+        status: status.state(now).to_string(),
+        expires_in: status.expires_in(now).map(|duration| duration.as_secs()),
+        error: status.error().map(str::to_string),
+        reservation_accepted: status.reservation_accepted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use async_graphql::{value, Request, Response};
+    use libp2p::{Multiaddr, PeerId};
+    use rstest::rstest;
+    use serde_json::json;
+    use tokio::sync::broadcast;
+
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::network::{LocalAddresses, Registration, RelayStatus, Transport};
+    use crate::test_utils::{http_test_client, test_runner, TestNode};
+
+    const QUERY: &str = r#"{
+        nodeInfo {
+            peerId
+            relay {
+                ...relayInfo
+            }
+            relays {
+                ...relayInfo
+            }
+        }
+    }
+
+    fragment relayInfo on RelayInfo {
+        peerId
+        address
+        status
+        expiresIn
+        error
+        reservationAccepted
+    }"#;
+
+    #[rstest]
+    fn node_info() {
+        test_runner(|node: TestNode| async move {
+            let client = http_test_client(&node).await;
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({ "nodeInfo": { "peerId": null, "relay": null, "relays": [] } })
+            );
+
+            let peer_id = PeerId::random();
+            let relay_peer_id = PeerId::random();
+            let relay_address: Multiaddr = "/ip4/127.0.0.1/udp/2022/quic-v1".parse().unwrap();
+            node.context
+                .local_addresses
+                .set_local_peer(peer_id, Transport::QUIC);
+            node.context
+                .local_addresses
+                .set_relay(Some((relay_peer_id, relay_address.clone())));
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "nodeInfo": {
+                        "peerId": peer_id.to_string(),
+                        "relay": {
+                            "peerId": relay_peer_id.to_string(),
+                            "address": relay_address.to_string(),
+                            "status": "pending",
+                            "expiresIn": null,
+                            "error": null,
+                            "reservationAccepted": false,
+                        },
+                        "relays": [],
+                    }
+                })
+            );
+
+            // Failed registrations are reported with their reason
+            let failed_peer_id = PeerId::random();
+            node.context.local_addresses.set_relay_statuses(vec![
+                RelayStatus {
+                    peer_id: relay_peer_id,
+                    addr: relay_address.clone(),
+                    connected: true,
+                    reservation_accepted: true,
+                    registration: Registration::Active {
+                        renew_at: Instant::now() + Duration::from_secs(60),
+                        expires_at: Instant::now() + Duration::from_secs(120),
+                    },
+                },
+                RelayStatus {
+                    peer_id: failed_peer_id,
+                    addr: relay_address.clone(),
+                    connected: true,
+                    reservation_accepted: false,
+                    registration: Registration::Failed {
+                        reason: "Unavailable".into(),
+                        retry_at: Instant::now(),
+                    },
+                },
+            ]);
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({ "query": QUERY }))
+                .send()
+                .await
+                .json::<Response>()
+                .await;
+            let data = response.data.into_json().unwrap();
+            assert_eq!(data["nodeInfo"]["relay"]["status"], "active");
+            assert!(data["nodeInfo"]["relay"]["expiresIn"].as_u64().unwrap() <= 120);
+            assert_eq!(data["nodeInfo"]["relay"]["reservationAccepted"], true);
+            assert_eq!(
+                data["nodeInfo"]["relays"][1],
+                json!({
+                    "peerId": failed_peer_id.to_string(),
+                    "address": relay_address.to_string(),
+                    "status": "failed",
+                    "expiresIn": null,
+                    "error": "Unavailable",
+                    "reservationAccepted": false,
+                })
+            );
+        });
+    }
+
+    #[rstest]
+    fn http_identity() {
+        test_runner(|node: TestNode| async move {
+            let peer_id = PeerId::random();
+            let local_addresses = LocalAddresses::default();
+            local_addresses.set_local_peer(peer_id, Transport::QUIC);
+
+            let (tx, _rx) = broadcast::channel(120);
+            let manager = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_local_addresses(local_addresses),
+            )
+            .await;
+
+            // The peer id is the identity of the node by default
+            let query = "{ nodeInfo { identity peerId } }";
+            let response = manager.execute(Request::new(query)).await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "nodeInfo": { "identity": peer_id.to_string(), "peerId": peer_id.to_string() }
+                })
+            );
+
+            // The configured HTTP identity hides the peer id
+            let manager = manager.with_http_identity(Some("node.example.org".into()));
+            let response = manager.execute(Request::new(query)).await;
+            assert_eq!(
+                response.data,
+                value!({ "nodeInfo": { "identity": "node.example.org", "peerId": null } })
+            );
+        });
+    }
+}
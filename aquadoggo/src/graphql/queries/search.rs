@@ -0,0 +1,460 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::ops::Range;
+
+use async_graphql::dynamic::{Field, FieldFuture, InputValue, Object, ResolverContext, TypeRef};
+use async_graphql::Error;
+use dynamic_graphql::FieldValue;
+use p2panda_rs::schema::SchemaId;
+use p2panda_rs::storage_provider::traits::DocumentStore;
+
+use crate::capabilities::{Authenticated, CapabilityProvider};
+use crate::db::stores::SearchMatch;
+use crate::db::SqlStore;
+use crate::graphql::constants;
+use crate::graphql::resolvers::sees_held_documents;
+use crate::graphql::responses::{SearchResult, SearchSnippet};
+use crate::schema::SchemaProvider;
+
+/// Number of search results returned when not specified otherwise.
+const DEFAULT_SEARCH_RESULTS: u64 = 25;
+
+/// Maximum number of search results returned by one query.
+const MAX_SEARCH_RESULTS: u64 = 100;
+
+/// Number of characters shown before and after the first occurrence of the text in a snippet.
+const SNIPPET_CONTEXT: usize = 32;
+
+/// Markers wrapping occurrences of the searched text in snippets.
+const HIGHLIGHT_START: &str = "<mark>";
+const HIGHLIGHT_END: &str = "</mark>";
+
+/// Marker indicating that a snippet got truncated.
+const ELLIPSIS: &str = "…";
+
+/// Add "search" query to the root query object.
+pub fn build_search_query(query: Object) -> Object {
+    query.field(
+        Field::new(
+            constants::SEARCH_QUERY,
+            TypeRef::named_nn_list_nn(constants::SEARCH_RESULT),
+            |ctx| {
+                FieldFuture::new(async move {
+                    let (text, schema_ids, first) = parse_arguments(&ctx).await?;
+                    let results = search(&ctx, &text, &schema_ids, first).await?;
+
+                    Ok(Some(FieldValue::list(
+                        results.into_iter().map(FieldValue::owned_any),
+                    )))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::SEARCH_TEXT_ARG,
+                TypeRef::named_nn(TypeRef::STRING),
+            )
+            .description("Text to search for in string fields, case-insensitive."),
+        )
+        .argument(
+            InputValue::new(
+                constants::SEARCH_SCHEMAS_ARG,
+                TypeRef::named_nn_list(TypeRef::STRING),
+            )
+            .description("Schemas to search, defaults to all application schemas of this node."),
+        )
+        .argument(
+            InputValue::new(
+                constants::PAGINATION_FIRST_ARG,
+                TypeRef::named(TypeRef::INT),
+            )
+            .description("Maximum number of returned documents")
+            .default_value(DEFAULT_SEARCH_RESULTS),
+        )
+        .description(
+            "Search documents of many schemas for string fields containing a text. Results are \
+            ranked by the number of matching fields.",
+        ),
+    )
+}
+
+/// Parse and validate the arguments passed to search.
+async fn parse_arguments(ctx: &ResolverContext<'_>) -> Result<(String, Vec<SchemaId>, u64), Error> {
+    let schema_provider = ctx.data_unchecked::<SchemaProvider>();
+
+    let text = ctx.args.try_get(constants::SEARCH_TEXT_ARG)?;
+    let text = text.string()?.trim();
+    if text.is_empty() {
+        return Err(Error::new("Search text can not be empty"));
+    }
+
+    let first = match ctx.args.get(constants::PAGINATION_FIRST_ARG) {
+        Some(first) => first.u64()?,
+        None => DEFAULT_SEARCH_RESULTS,
+    };
+    if first == 0 || first > MAX_SEARCH_RESULTS {
+        return Err(Error::new(format!(
+            "Number of search results needs to be between 1 and {}",
+            MAX_SEARCH_RESULTS
+        )));
+    }
+
+    let schema_ids = match ctx.args.get(constants::SEARCH_SCHEMAS_ARG) {
+        Some(schemas) if !schemas.is_null() => {
+            let mut schema_ids = Vec::new();
+
+            for schema_id in schemas.list()?.iter() {
+                let schema_id: SchemaId = schema_id.string()?.parse()?;
+                if schema_provider.get(&schema_id).await.is_none() {
+                    return Err(Error::new(format!(
+                        "Schema {} is not supported by this node",
+                        schema_id
+                    )));
+                }
+                schema_ids.push(schema_id);
+            }
+
+            schema_ids
+        }
+        _ => schema_provider
+            .all()
+            .await
+            .into_iter()
+            .map(|schema| schema.id().to_owned())
+            .filter(|schema_id| matches!(schema_id, SchemaId::Application(_, _)))
+            .collect(),
+    };
+
+    Ok((text.to_string(), schema_ids, first))
+}
+
+/// Search documents and return the ones the client is allowed to read.
+async fn search(
+    ctx: &ResolverContext<'_>,
+    text: &str,
+    schema_ids: &[SchemaId],
+    first: u64,
+) -> Result<Vec<SearchResult>, Error> {
+    let store = ctx.data_unchecked::<SqlStore>();
+    let schema_provider = ctx.data_unchecked::<SchemaProvider>();
+    let capability_provider = ctx.data_unchecked::<CapabilityProvider>();
+    let reader = ctx.data_opt::<Authenticated>().map(|reader| &reader.0);
+    let sees_held = sees_held_documents(ctx).await?;
+
+    let mut results = Vec::new();
+    let mut offset = 0;
+
+    // Fetch batches of matches until we found enough documents the client can read
+    loop {
+        let matches = store.search(text, schema_ids, first, offset).await?;
+        let is_last_batch = (matches.len() as u64) < first;
+        offset += matches.len() as u64;
+
+        for search_match in matches {
+            if results.len() as u64 == first {
+                return Ok(results);
+            }
+
+            let schema = match schema_provider.get(&search_match.schema_id).await {
+                Some(schema) => schema,
+                None => continue,
+            };
+
+            // Hide access controlled documents the client is not allowed to read
+            if capability_provider.read_acl_field(&schema).is_some() {
+                let document = match store.get_document(&search_match.document_id).await? {
+                    Some(document) => document,
+                    None => continue,
+                };
+
+                if !capability_provider
+                    .can_read(store, reader, &schema, &document)
+                    .await?
+                {
+                    continue;
+                }
+            }
+
+            // Hide documents of moderated schemas which are waiting for approval
+            if !sees_held
+                && schema_provider.is_moderated(&search_match.schema_id)
+                && store.is_document_held(&search_match.document_id).await?
+            {
+                continue;
+            }
+
+            results.push(search_result(search_match, text));
+        }
+
+        if is_last_batch || results.len() as u64 == first {
+            return Ok(results);
+        }
+    }
+}
+
+/// Convert a search match into a result with highlighted snippets of all matching fields.
+fn search_result(search_match: SearchMatch, text: &str) -> SearchResult {
+    SearchResult {
+        schema_id: search_match.schema_id.to_string(),
+        document_id: (&search_match.document_id).into(),
+        view_id: (&search_match.view_id).into(),
+        snippets: search_match
+            .fields
+            .into_iter()
+            .map(|(field, value)| SearchSnippet {
+                field,
+                text: snippet(&value, text),
+            })
+            .collect(),
+    }
+}
+
+/// Returns the byte ranges of all case-insensitive occurrences of the needle in the text.
+fn find_occurrences(text: &str, needle: &str) -> Vec<Range<usize>> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    let mut occurrences = Vec::new();
+
+    if needle.is_empty() {
+        return occurrences;
+    }
+
+    let mut skip_until = 0;
+    'outer: for (start, _) in text.char_indices() {
+        if start < skip_until {
+            continue;
+        }
+
+        let mut remaining = needle.as_slice();
+        for (offset, c) in text[start..].char_indices() {
+            for lower in c.to_lowercase() {
+                match remaining.split_first() {
+                    Some((first, rest)) if *first == lower => remaining = rest,
+                    _ => continue 'outer,
+                }
+            }
+
+            if remaining.is_empty() {
+                let end = start + offset + c.len_utf8();
+                occurrences.push(start..end);
+                skip_until = end;
+                continue 'outer;
+            }
+        }
+    }
+
+    occurrences
+}
+
+/// Returns an excerpt around the first occurrence of the searched text with all occurrences in it
+/// being highlighted.
+fn snippet(value: &str, text: &str) -> String {
+    let occurrences = find_occurrences(value, text);
+    let first = match occurrences.first() {
+        Some(first) => first.clone(),
+        None => return value.to_string(),
+    };
+
+    let window_start = value[..first.start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map(|(index, _)| index)
+        .unwrap_or(0);
+    let window_end = value[first.end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT)
+        .map(|(index, _)| first.end + index)
+        .unwrap_or(value.len());
+
+    let mut snippet = String::new();
+    if window_start > 0 {
+        snippet.push_str(ELLIPSIS);
+    }
+
+    let mut position = window_start;
+    for occurrence in occurrences
+        .into_iter()
+        .filter(|occurrence| occurrence.end <= window_end)
+    {
+        snippet.push_str(&value[position..occurrence.start]);
+        snippet.push_str(HIGHLIGHT_START);
+        snippet.push_str(&value[occurrence.clone()]);
+        snippet.push_str(HIGHLIGHT_END);
+        position = occurrence.end;
+    }
+    snippet.push_str(&value[position..window_end]);
+
+    if window_end < value.len() {
+        snippet.push_str(ELLIPSIS);
+    }
+
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Response};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::test_utils::{add_document, add_schema, http_test_client, test_runner, TestNode};
+
+    use super::{find_occurrences, snippet};
+
+    #[rstest]
+    #[case::single("Hello, Panda!", "panda", vec![7..12])]
+    #[case::many("panda PANDA", "Panda", vec![0..5, 6..11])]
+    #[case::overlapping("aaa", "aa", vec![0..2])]
+    #[case::unicode("Größe größer", "GRÖ", vec![0..4, 8..12])]
+    #[case::none("Hello, Panda!", "bear", vec![])]
+    fn finds_occurrences(
+        #[case] text: &str,
+        #[case] needle: &str,
+        #[case] expected: Vec<std::ops::Range<usize>>,
+    ) {
+        assert_eq!(find_occurrences(text, needle), expected);
+    }
+
+    #[rstest]
+    #[case::short("Hello, Panda!", "Hello, <mark>Panda</mark>!".into())]
+    #[case::truncated(
+        &format!("{}Panda{}", "a".repeat(40), "b".repeat(40)),
+        format!("…{}<mark>Panda</mark>{}…", "a".repeat(32), "b".repeat(32))
+    )]
+    #[case::many("Panda and panda", "<mark>Panda</mark> and <mark>panda</mark>".into())]
+    fn highlights_snippets(#[case] value: &str, #[case] expected: String) {
+        assert_eq!(snippet(value, "panda"), expected);
+    }
+
+    #[rstest]
+    fn search_across_schemas(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let songs = add_schema(
+                &mut node,
+                "songs",
+                vec![("title", FieldType::String), ("year", FieldType::Integer)],
+                &key_pair,
+            )
+            .await;
+
+            let artists = add_schema(
+                &mut node,
+                "artists",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            let song_view_id = add_document(
+                &mut node,
+                songs.id(),
+                vec![("title", "Panda Party".into()), ("year", 2023.into())],
+                &key_pair,
+            )
+            .await;
+
+            let artist_view_id = add_document(
+                &mut node,
+                artists.id(),
+                vec![("name", "The Bears".into())],
+                &key_pair,
+            )
+            .await;
+
+            let client = http_test_client(&node).await;
+            let query = |text: &str, schemas: Option<Vec<String>>| {
+                json!({
+                    "query": r#"query Search($text: String!, $schemas: [String!]) {
+                        search(text: $text, schemas: $schemas) {
+                            schemaId
+                            viewId
+                            snippets {
+                                field
+                                text
+                            }
+                        }
+                    }"#,
+                    "variables": {
+                        "text": text,
+                        "schemas": schemas,
+                    }
+                })
+            };
+
+            // Search all application schemas
+            let response: Response = client
+                .post("/graphql")
+                .json(&query("party", None))
+                .send()
+                .await
+                .json()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "search": [{
+                        "schemaId": songs.id().to_string(),
+                        "viewId": song_view_id.to_string(),
+                        "snippets": [{
+                            "field": "title",
+                            "text": "Panda <mark>Party</mark>",
+                        }],
+                    }]
+                }),
+                "{:?}",
+                response.errors
+            );
+
+            // Search only selected schemas
+            let response: Response = client
+                .post("/graphql")
+                .json(&query("the", Some(vec![artists.id().to_string()])))
+                .send()
+                .await
+                .json()
+                .await;
+            assert_eq!(
+                response.data,
+                value!({
+                    "search": [{
+                        "schemaId": artists.id().to_string(),
+                        "viewId": artist_view_id.to_string(),
+                        "snippets": [{
+                            "field": "name",
+                            "text": "<mark>The</mark> Bears",
+                        }],
+                    }]
+                })
+            );
+
+            // Unknown schemas are rejected
+            let response: Response = client
+                .post("/graphql")
+                .json(&query(
+                    "the",
+                    Some(vec![
+                        "unknown_0020c65567ae37efea293e34a9c7d13f8f2bf23dbdc3b5c7b9ab46293111c48fc78b"
+                            .to_string(),
+                    ]),
+                ))
+                .send()
+                .await
+                .json()
+                .await;
+            assert!(response.is_err());
+
+            // Empty search text is rejected
+            let response: Response = client
+                .post("/graphql")
+                .json(&query("  ", None))
+                .send()
+                .await
+                .json()
+                .await;
+            assert!(response.is_err());
+        });
+    }
+}
@@ -1,35 +1,57 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashMap;
+
 use async_graphql::dynamic::ResolverContext;
 use async_graphql::Error;
+use async_stream::try_stream;
 use dynamic_graphql::FieldValue;
+use futures::Stream;
+use log::warn;
 use p2panda_rs::document::traits::AsDocument;
-use p2panda_rs::operation::OperationValue;
-use p2panda_rs::schema::{FieldType, Schema};
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::{OperationId, OperationValue, Relation};
+use p2panda_rs::schema::{FieldType, Schema, SchemaId};
 use p2panda_rs::storage_provider::traits::DocumentStore;
+use tokio::sync::broadcast::error::RecvError;
 
+use crate::api::DocumentFilter;
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::capabilities::{Authenticated, CapabilityProvider, Permission, ReadScope};
+use crate::db::query::{Field, MetaField};
 use crate::db::stores::{PaginationCursor, PaginationData, RelationList};
 use crate::db::types::StorageDocument;
 use crate::db::SqlStore;
+use crate::graphql::explain::QueryPlans;
+use crate::graphql::loader::DocumentLoader;
 use crate::graphql::objects::DocumentMeta;
 use crate::graphql::scalars::{DocumentIdScalar, DocumentViewIdScalar};
+use crate::graphql::traversal::RelationTraversal;
 use crate::graphql::utils::{get_document_from_params, gql_scalar, parse_collection_arguments};
-use crate::schema::SchemaProvider;
+use crate::schema::{format_datetime_value, format_decimal, SchemaProvider};
 
 /// Document data passed between resolvers.
+///
+/// Documents carry their depth, the number of relations which were followed from the root query
+/// to reach them.
 #[derive(Clone, Debug)]
 pub enum Resolved {
     /// Single document.
-    Document(StorageDocument),
+    Document(StorageDocument, usize),
 
     /// Collection of multiple documents, with pagination data.
     Collection(
         PaginationData<PaginationCursor>,
         Vec<(PaginationCursor, StorageDocument)>,
+        usize,
     ),
 
     /// Single document as part of a collection, with pagination data.
-    CollectionDocument(PaginationCursor, StorageDocument),
+    CollectionDocument(PaginationCursor, StorageDocument, usize),
+
+    /// Result of looking up a document by its id, `None` if it was not found.
+    Lookup(DocumentId, Option<StorageDocument>),
 }
 
 impl Resolved {
@@ -53,7 +75,13 @@ pub async fn resolve_document(
     let store = ctx.data_unchecked::<SqlStore>();
 
     let document = match get_document_from_params(store, &document_id, &document_view_id).await? {
-        Some(document) => Resolved::Document(document),
+        Some(document) => document,
+        None => return Ok(FieldValue::NONE),
+    };
+
+    // Hide documents the client is not allowed to read
+    let document = match readable_document(&ctx, document).await? {
+        Some(document) => Resolved::Document(document, 0),
         None => return Ok(FieldValue::NONE),
     };
 
@@ -61,23 +89,184 @@ pub async fn resolve_document(
     Ok(Some(FieldValue::owned_any(document)))
 }
 
+/// Resolve a stream of documents of a schema, yielding the latest view of a document every time it
+/// was created or updated.
+///
+/// When a document id is given only changes of this document are yielded, starting with its
+/// current view. When a filter is given only changes of documents matching it are yielded.
+/// Changes of documents the client is not allowed to read are skipped.
+pub fn resolve_document_changes<'a>(
+    ctx: ResolverContext<'a>,
+    schema: Schema,
+    document_id: Option<DocumentId>,
+    filter: Option<DocumentFilter>,
+) -> impl Stream<Item = Result<FieldValue<'a>, Error>> + Send + 'a {
+    let store = ctx.data_unchecked::<SqlStore>().clone();
+    let schema_id = schema.id().to_owned();
+
+    // Subscribe before looking up the current view to not miss any changes in between
+    let mut rx = ctx.data_unchecked::<ServiceSender>().subscribe();
+
+    try_stream! {
+        if let Some(document_id) = &document_id {
+            let current = store.get_document(document_id).await?;
+            if let Some(document) = current.filter(|document| document.schema_id() == &schema_id) {
+                if let Some(document) = readable_document(&ctx, document).await? {
+                    yield FieldValue::owned_any(Resolved::Document(document, 0));
+                }
+            }
+        }
+
+        loop {
+            let message = match rx.recv().await {
+                Ok(message) => message,
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Subscription missed {} messages on the bus", count);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            let (changed_document_id, view_id) = match message {
+                ServiceMessage::DocumentUpdated(changed_schema_id, changed_document_id, view_id)
+                    if changed_schema_id == schema_id
+                        && document_id
+                            .as_ref()
+                            .map_or(true, |document_id| document_id == &changed_document_id) =>
+                {
+                    (changed_document_id, view_id)
+                }
+                _ => continue,
+            };
+
+            // Filters are evaluated by the database, like they are for collection queries
+            if let Some(filter) = &filter {
+                let is_match = filter.matches(&store, &schema, &changed_document_id).await?;
+                if !is_match {
+                    continue;
+                }
+            }
+
+            // The view might have been replaced and garbage collected in the meantime, then the
+            // next change is yielded instead
+            let document = match store.get_cached_document_by_view_id(&view_id).await? {
+                Some(document) => document,
+                None => continue,
+            };
+
+            if let Some(document) = readable_document(&ctx, document).await? {
+                yield FieldValue::owned_any(Resolved::Document(document, 0));
+            }
+        }
+    }
+}
+
+/// Resolve many documents of a schema in the order of the given ids.
+///
+/// Every requested id results in a lookup, its document is `null` if it was not found or the
+/// client is not allowed to read it.
+pub async fn resolve_documents_by_ids(
+    ctx: ResolverContext<'_>,
+    schema: Schema,
+    ids: Vec<DocumentId>,
+) -> Result<Option<FieldValue>, Error> {
+    let store = ctx.data_unchecked::<SqlStore>();
+
+    let mut documents = HashMap::new();
+    for document in store.get_documents_by_ids(schema.id(), &ids).await? {
+        // Hide documents the client is not allowed to read
+        if let Some(document) = readable_document(&ctx, document).await? {
+            documents.insert(document.id().to_owned(), document);
+        }
+    }
+
+    let lookups = ids.into_iter().map(|id| {
+        let document = documents.get(&id).cloned();
+        FieldValue::owned_any(Resolved::Lookup(id, document))
+    });
+
+    Ok(Some(FieldValue::list(lookups)))
+}
+
 /// Resolve a collection of documents.
 ///
 /// This collection can be either resolved by schema (root collection), or via a relation list
-/// (nested collection).
+/// (nested collection) at the given depth.
 pub async fn resolve_document_collection(
     ctx: ResolverContext<'_>,
     schema: Schema,
     list: Option<RelationList>,
+    depth: usize,
 ) -> Result<Option<FieldValue>, Error> {
     let store = ctx.data_unchecked::<SqlStore>();
+    let schema_provider = ctx.data_unchecked::<SchemaProvider>();
+
+    let capability_provider = ctx.data_unchecked::<CapabilityProvider>();
 
     // Populate query arguments with values from GraphQL query
-    let query = parse_collection_arguments(&ctx, &schema, &list)?;
+    let mut query = parse_collection_arguments(&ctx, &schema, &list).await?;
+
+    // Only select access controlled documents the client is allowed to read
+    if let Some(field_name) = capability_provider.read_acl_field(&schema) {
+        let reader = ctx.data_opt::<Authenticated>().map(|reader| &reader.0);
+
+        if let ReadScope::Only(acl_document_ids) =
+            capability_provider.read_scope(store, reader).await?
+        {
+            if acl_document_ids.is_empty() {
+                let pagination_data = PaginationData {
+                    total_count: Some(0),
+                    has_next_page: false,
+                    has_previous_page: false,
+                    start_cursor: None,
+                    end_cursor: None,
+                };
+                let collection = Resolved::Collection(pagination_data, Vec::new(), depth);
+                return Ok(Some(FieldValue::owned_any(collection)));
+            }
+
+            let values: Vec<OperationValue> = acl_document_ids
+                .into_iter()
+                .map(|document_id| OperationValue::Relation(Relation::new(document_id)))
+                .collect();
+            query
+                .filter
+                .restrict(&Field::Field(field_name.to_string()), &values);
+        }
+    }
+
+    // Hide documents of moderated schemas which are waiting for approval
+    if schema_provider.is_moderated(schema.id()) && !sees_held_documents(&ctx).await? {
+        let held_document_ids: Vec<OperationValue> = store
+            .get_held_document_ids(schema.id())
+            .await?
+            .into_iter()
+            .map(|document_id| OperationValue::String(document_id.to_string()))
+            .collect();
+
+        if !held_document_ids.is_empty() {
+            query
+                .filter
+                .exclude(&Field::Meta(MetaField::DocumentId), &held_document_ids);
+        }
+    }
 
     // Fetch all queried documents and compose the value to be passed up the query tree
-    let (pagination_data, documents) = store.query(&schema, &query, list.as_ref()).await?;
-    let collection = Resolved::Collection(pagination_data, documents);
+    let (pagination_data, mut documents) = store.query(&schema, &query, list.as_ref()).await?;
+
+    // Explain the query when the request asks for it
+    if let Some(query_plans) = ctx.data_opt::<QueryPlans>() {
+        query_plans.push(store.explain_query(&schema, &query, list.as_ref()).await?);
+    }
+
+    // Documents of relation lists count towards the related documents of this query
+    if list.is_some() {
+        if let Some(traversal) = ctx.data_opt::<RelationTraversal>() {
+            documents.truncate(traversal.take(documents.len()));
+        }
+    }
+
+    let collection = Resolved::Collection(pagination_data, documents, depth);
 
     Ok(Some(FieldValue::owned_any(collection)))
 }
@@ -90,9 +279,22 @@ pub async fn resolve_document_meta(
 
     // Extract the document in the case of a single or paginated request
     let document = match document {
-        Resolved::Document(document) => document,
-        Resolved::CollectionDocument(_, document) => document,
-        Resolved::Collection(_, _) => panic!("Expected list item or single document"),
+        Resolved::Document(document, _) => document,
+        Resolved::CollectionDocument(_, document, _) => document,
+        Resolved::Collection(_, _, _) | Resolved::Lookup(_, _) => {
+            panic!("Expected list item or single document")
+        }
+    };
+
+    // Statistics are only looked up when they were actually requested
+    let stats = if ctx.look_ahead().field("stats").exists() {
+        let store = ctx.data_unchecked::<SqlStore>();
+        store
+            .get_document_stats(document.id())
+            .await?
+            .map(|stats| stats.into())
+    } else {
+        None
     };
 
     // We defined the document meta type and registered it in the GraphQL schema
@@ -100,6 +302,7 @@ pub async fn resolve_document_meta(
         document_id: document.id().into(),
         document_view_id: document.view_id().into(),
         owner: document.author().to_owned().into(),
+        stats,
     };
 
     Ok(Some(FieldValue::owned_any(document_meta)))
@@ -113,14 +316,15 @@ pub async fn resolve_document_meta(
 pub async fn resolve_document_field(
     ctx: ResolverContext<'_>,
 ) -> Result<Option<FieldValue<'_>>, Error> {
-    let store = ctx.data_unchecked::<SqlStore>();
     let schema_provider = ctx.data_unchecked::<SchemaProvider>();
 
     // Parse the bubble up value
-    let document = match Resolved::downcast(&ctx) {
-        Resolved::Document(document) => document,
-        Resolved::CollectionDocument(_, document) => document,
-        Resolved::Collection(_, _) => panic!("Expected list item or single document"),
+    let (document, depth) = match Resolved::downcast(&ctx) {
+        Resolved::Document(document, depth) => (document, depth),
+        Resolved::CollectionDocument(_, document, depth) => (document, depth),
+        Resolved::Collection(_, _, _) | Resolved::Lookup(_, _) => {
+            panic!("Expected list item or single document")
+        }
     };
 
     let schema = schema_provider
@@ -131,28 +335,73 @@ pub async fn resolve_document_field(
     // Determine name of the field to be resolved
     let name = ctx.field().name();
 
-    match document
+    let value = document
         .get(name)
-        .expect("Selected field should be in document")
-    {
+        .expect("Selected field should be in document");
+
+    // Relations are not followed beyond the depth limit of this query, single related documents
+    // additionally need to fit into its budget of related documents
+    let traversal = ctx.data_opt::<RelationTraversal>();
+    let is_relation = matches!(
+        value,
+        OperationValue::Relation(_)
+            | OperationValue::PinnedRelation(_)
+            | OperationValue::RelationList(_)
+            | OperationValue::PinnedRelationList(_)
+    );
+    if let (Some(traversal), true) = (traversal, is_relation) {
+        if !traversal.allows_depth(depth + 1) {
+            return Ok(FieldValue::NONE);
+        }
+
+        let is_single = matches!(
+            value,
+            OperationValue::Relation(_) | OperationValue::PinnedRelation(_)
+        );
+        if is_single && traversal.take(1) == 0 {
+            return Ok(FieldValue::NONE);
+        }
+    }
+
+    match value {
         // Relation fields are expected to resolve to the related document
         OperationValue::Relation(relation) => {
-            let document = match store.get_document(relation.document_id()).await? {
+            let schema_id = match schema.fields().get(name) {
+                Some(FieldType::Relation(schema_id)) => schema_id,
+                _ => panic!("Schema should define relation field"),
+            };
+
+            // Related documents of all documents in a list are looked up together, redirects are
+            // followed when a related document was merged into another one
+            let document = match document_loader(&ctx)
+                .load_document(schema_id, relation.document_id())
+                .await?
+            {
                 Some(document) => document,
                 None => return Ok(FieldValue::NONE),
             };
 
-            let document = Resolved::Document(document);
+            let document = match readable_document(&ctx, document).await? {
+                Some(document) => document,
+                None => return Ok(FieldValue::NONE),
+            };
+
+            let document = Resolved::Document(document, depth + 1);
             Ok(Some(FieldValue::owned_any(document)))
         }
         // Pinned relation behaves the same as relation but passes along a document view id
         OperationValue::PinnedRelation(relation) => {
-            let document = match store.get_document_by_view_id(relation.view_id()).await? {
+            let document = match document_loader(&ctx).load_view(relation.view_id()).await? {
                 Some(document) => document,
                 None => return Ok(FieldValue::NONE),
             };
 
-            let document = Resolved::Document(document);
+            let document = match readable_document(&ctx, document).await? {
+                Some(document) => document,
+                None => return Ok(FieldValue::NONE),
+            };
+
+            let document = Resolved::Document(document, depth + 1);
             Ok(Some(FieldValue::owned_any(document)))
         }
         // Relation lists are handled by collecting and returning a list of all document ids in
@@ -175,7 +424,7 @@ pub async fn resolve_document_field(
             // Select relation field containing list of documents
             let list = RelationList::new_unpinned(document.view_id(), name);
 
-            resolve_document_collection(ctx, schema, Some(list)).await
+            resolve_document_collection(ctx, schema, Some(list), depth + 1).await
         }
         // Pinned relation lists behave the same as relation lists but pass along view ids
         OperationValue::PinnedRelationList(_) => {
@@ -197,9 +446,219 @@ pub async fn resolve_document_field(
             // Select relation field containing list of pinned document views
             let list = RelationList::new_pinned(document.view_id(), name);
 
-            resolve_document_collection(ctx, schema, Some(list)).await
+            resolve_document_collection(ctx, schema, Some(list), depth + 1).await
+        }
+        // All other fields are simply resolved to their scalar value, decimals are formatted with
+        // their fixed number of fractional digits and dates and times in UTC
+        value if schema_provider.is_datetime_field(&schema, name) => {
+            let datetime = format_datetime_value(value)?;
+            Ok(Some(FieldValue::value(datetime)))
+        }
+        value => match (value, schema_provider.decimal_scale(&schema, name)) {
+            (OperationValue::Integer(value), Some(scale)) => {
+                Ok(Some(FieldValue::value(format_decimal(*value, scale))))
+            }
+            _ => Ok(Some(FieldValue::value(gql_scalar(value)))),
+        },
+    }
+}
+
+/// Returns the loader of related documents of this query.
+fn document_loader(ctx: &ResolverContext<'_>) -> DocumentLoader {
+    match ctx.data_opt::<DocumentLoader>() {
+        Some(loader) => loader.clone(),
+        // Queries executed without loader look up every related document on its own
+        None => DocumentLoader::new(ctx.data_unchecked::<SqlStore>().clone()),
+    }
+}
+
+/// Read permissions of a client, decides which documents are revealed to it.
+///
+/// Documents of schemas with read access control are only revealed to readers which were granted
+/// access, documents of moderated schemas which are waiting for approval only to admins.
+#[derive(Clone, Copy)]
+pub struct ReadAccess<'a> {
+    store: &'a SqlStore,
+    schema_provider: &'a SchemaProvider,
+    capability_provider: &'a CapabilityProvider,
+    reader: Option<&'a PublicKey>,
+}
+
+impl<'a> ReadAccess<'a> {
+    /// Returns the read permissions of the given reader, `None` for anonymous clients.
+    pub fn new(
+        store: &'a SqlStore,
+        schema_provider: &'a SchemaProvider,
+        capability_provider: &'a CapabilityProvider,
+        reader: Option<&'a PublicKey>,
+    ) -> Self {
+        Self {
+            store,
+            schema_provider,
+            capability_provider,
+            reader,
         }
-        // All other fields are simply resolved to their scalar value
-        value => Ok(Some(FieldValue::value(gql_scalar(value)))),
     }
+
+    /// Returns the read permissions of the client sending this GraphQL request.
+    pub fn from_context(ctx: &'a ResolverContext<'_>) -> Self {
+        Self::new(
+            ctx.data_unchecked::<SqlStore>(),
+            ctx.data_unchecked::<SchemaProvider>(),
+            ctx.data_unchecked::<CapabilityProvider>(),
+            ctx.data_opt::<Authenticated>().map(|reader| &reader.0),
+        )
+    }
+
+    /// Returns the document if the client is allowed to read it.
+    pub async fn readable_document(
+        &self,
+        document: StorageDocument,
+    ) -> Result<Option<StorageDocument>, Error> {
+        let schema = match self.schema_provider.get(document.schema_id()).await {
+            Some(schema) => schema,
+            None => return Ok(Some(document)),
+        };
+
+        if !self
+            .capability_provider
+            .can_read(self.store, self.reader, &schema, &document)
+            .await?
+        {
+            return Ok(None);
+        }
+
+        // Documents of moderated schemas which are waiting for approval are only shown to admins
+        if self.schema_provider.is_moderated(schema.id())
+            && self.store.is_document_held(document.id()).await?
+            && !self.sees_held_documents().await?
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(document))
+    }
+
+    /// Returns true if the client is allowed to learn about the document with the given id and
+    /// schema, see `reveals_document`.
+    pub async fn reveals_document_of_schema(
+        &self,
+        schema_id: &SchemaId,
+        document_id: &DocumentId,
+    ) -> Result<bool, Error> {
+        if !self.restricts_reading(schema_id).await {
+            return Ok(true);
+        }
+
+        // Deleted documents can not be read anymore, they stay hidden
+        match self.store.get_document(document_id).await? {
+            Some(document) => Ok(self.readable_document(document).await?.is_some()),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns true if documents of this schema are hidden from some clients, either by read
+    /// access control or as they are waiting for approval.
+    pub async fn restricts_reading(&self, schema_id: &SchemaId) -> bool {
+        match self.schema_provider.get(schema_id).await {
+            Some(schema) => {
+                self.capability_provider.read_acl_field(&schema).is_some()
+                    || self.schema_provider.is_moderated(schema_id)
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the client is allowed to see documents waiting for approval.
+    ///
+    /// Only authenticated admins can review held documents, this requires access control to be
+    /// enabled.
+    pub async fn sees_held_documents(&self) -> Result<bool, Error> {
+        if !self.capability_provider.is_enabled() {
+            return Ok(false);
+        }
+
+        match self.reader {
+            Some(reader) => Ok(self
+                .capability_provider
+                .is_permitted(self.store, reader, &Permission::Admin)
+                .await?),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Returns the document if the client is allowed to read it.
+async fn readable_document(
+    ctx: &ResolverContext<'_>,
+    document: StorageDocument,
+) -> Result<Option<StorageDocument>, Error> {
+    ReadAccess::from_context(ctx)
+        .readable_document(document)
+        .await
+}
+
+/// Returns true if the client is allowed to learn about the document with the given id.
+///
+/// Documents of schemas with read access control or moderation are only revealed when their
+/// latest view can be read. Other documents are revealed without loading them, also when they are
+/// not known to this node.
+pub async fn reveals_document(
+    ctx: &ResolverContext<'_>,
+    document_id: &DocumentId,
+) -> Result<bool, Error> {
+    let store = ctx.data_unchecked::<SqlStore>();
+
+    // Document ids are the ids of their CREATE operations
+    let operation_id: OperationId = document_id.as_str().parse()?;
+    match store.get_schema_id_by_operation_id(&operation_id).await? {
+        Some(schema_id) => reveals_document_of_schema(ctx, &schema_id, document_id).await,
+        None => Ok(true),
+    }
+}
+
+/// Returns true if the client is allowed to learn about the document with the given id and
+/// schema, see `reveals_document`.
+pub async fn reveals_document_of_schema(
+    ctx: &ResolverContext<'_>,
+    schema_id: &SchemaId,
+    document_id: &DocumentId,
+) -> Result<bool, Error> {
+    ReadAccess::from_context(ctx)
+        .reveals_document_of_schema(schema_id, document_id)
+        .await
+}
+
+/// Returns true if the client is allowed to learn about the document view with the given id.
+///
+/// Views of schemas with read access control or moderation are only revealed when they can be
+/// read.
+pub async fn reveals_document_view(
+    ctx: &ResolverContext<'_>,
+    view_id: &DocumentViewId,
+) -> Result<bool, Error> {
+    let store = ctx.data_unchecked::<SqlStore>();
+    let read_access = ReadAccess::from_context(ctx);
+
+    // Document view ids consist of ids of operations of the same document
+    let schema_id = match view_id.iter().next() {
+        Some(operation_id) => store.get_schema_id_by_operation_id(operation_id).await?,
+        None => None,
+    };
+
+    match schema_id {
+        Some(schema_id) if read_access.restricts_reading(&schema_id).await => {
+            match store.get_cached_document_by_view_id(view_id).await? {
+                Some(document) => Ok(read_access.readable_document(document).await?.is_some()),
+                None => Ok(false),
+            }
+        }
+        _ => Ok(true),
+    }
+}
+
+/// Returns true if the client is allowed to see documents waiting for approval, see
+/// `ReadAccess::sees_held_documents`.
+pub async fn sees_held_documents(ctx: &ResolverContext<'_>) -> Result<bool, Error> {
+    ReadAccess::from_context(ctx).sees_held_documents().await
 }
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `annotations` query.
+use dynamic_graphql::SimpleObject;
+
+use crate::db::models::AnnotationRow;
+
+/// Node-local key-value pair attached to a document, it is never replicated to other nodes.
+#[derive(SimpleObject)]
+pub struct Annotation {
+    /// Key of the annotation, for example "label" or "moderation".
+    pub key: String,
+
+    /// Value of the annotation.
+    pub value: String,
+}
+
+impl From<AnnotationRow> for Annotation {
+    fn from(row: AnnotationRow) -> Self {
+        Self {
+            key: row.key,
+            value: row.value,
+        }
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `blobDerivatives` query.
+use dynamic_graphql::SimpleObject;
+
+use crate::db::models::BlobDerivativeRow;
+
+/// Blob derived from a blob by a transcoder of this node, for example a web-friendly version of a
+/// video. Derived blobs are never replicated to other nodes.
+#[derive(SimpleObject)]
+pub struct BlobDerivative {
+    /// Name of the transcoder which produced the derived blob.
+    pub name: String,
+
+    /// View id of the original blob the derived blob was transcoded from.
+    #[graphql(name = "viewId")]
+    pub view_id: String,
+
+    /// Mime type of the derived blob.
+    #[graphql(name = "mimeType")]
+    pub mime_type: String,
+
+    /// Length of the derived blob in bytes.
+    pub length: u64,
+
+    /// HTTP path the derived blob is served under.
+    pub path: String,
+}
+
+impl From<BlobDerivativeRow> for BlobDerivative {
+    fn from(row: BlobDerivativeRow) -> Self {
+        Self {
+            path: format!("/blobs/{}/derived/{}", row.document_id, row.name),
+            name: row.name,
+            view_id: row.view_id,
+            mime_type: row.mime_type,
+            length: row.length as u64,
+        }
+    }
+}
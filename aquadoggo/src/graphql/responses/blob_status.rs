@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `blobStatus` query.
+use dynamic_graphql::SimpleObject;
+
+use crate::db::stores::BlobStatus as BlobStatusData;
+use crate::graphql::scalars::DocumentViewIdScalar;
+
+/// Completeness of the pieces of a blob document, used to resume interrupted uploads.
+#[derive(SimpleObject)]
+pub struct BlobStatus {
+    /// Latest view id of the blob document.
+    #[graphql(name = "viewId")]
+    pub view_id: DocumentViewIdScalar,
+
+    /// Claimed length of the blob in bytes.
+    pub length: u64,
+
+    /// Number of pieces the blob consists of.
+    #[graphql(name = "numPieces")]
+    pub num_pieces: u64,
+
+    /// View ids of pieces which were not accepted by this node yet, in the order they appear in
+    /// the blob.
+    #[graphql(name = "missingPieces")]
+    pub missing_pieces: Vec<DocumentViewIdScalar>,
+
+    /// True if all pieces of the blob are available on this node.
+    #[graphql(name = "isComplete")]
+    pub is_complete: bool,
+}
+
+impl From<BlobStatusData> for BlobStatus {
+    fn from(status: BlobStatusData) -> Self {
+        Self {
+            is_complete: status.is_complete(),
+            view_id: DocumentViewIdScalar::from(&status.view_id),
+            length: status.length,
+            num_pieces: status.num_pieces as u64,
+            missing_pieces: status
+                .missing_pieces
+                .iter()
+                .map(DocumentViewIdScalar::from)
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `dependencyGraph` query.
+use dynamic_graphql::SimpleObject;
+
+use crate::db::stores::{
+    DependencyGraph as DependencyGraphData, ViewDependencies as ViewDependenciesData,
+    ViewRelation as ViewRelationData,
+};
+use crate::graphql::scalars::{DocumentIdScalar, DocumentViewIdScalar};
+use crate::materializer::{Task, TaskInput};
+
+/// Relation of a document view to another document or document view.
+#[derive(SimpleObject)]
+pub struct ViewRelation {
+    /// Name of the relation field.
+    pub field: String,
+
+    /// Id of the related document or, for pinned relations, of the related document view.
+    pub target: String,
+
+    /// True if this is a pinned relation pointing at a document view.
+    #[graphql(name = "isPinned")]
+    pub is_pinned: bool,
+
+    /// True if the related document or document view is materialized on this node.
+    #[graphql(name = "isMaterialized")]
+    pub is_materialized: bool,
+}
+
+impl From<ViewRelationData> for ViewRelation {
+    fn from(relation: ViewRelationData) -> Self {
+        Self {
+            field: relation.field,
+            target: relation.target,
+            is_pinned: relation.is_pinned,
+            is_materialized: relation.is_materialized,
+        }
+    }
+}
+
+/// Materialized view of a document and how it is connected to other views.
+#[derive(SimpleObject)]
+pub struct ViewDependencies {
+    /// Id of the document view.
+    #[graphql(name = "viewId")]
+    pub view_id: DocumentViewIdScalar,
+
+    /// True if this is the current view of the document.
+    #[graphql(name = "isCurrent")]
+    pub is_current: bool,
+
+    /// Relations of this view to other documents and views.
+    pub relations: Vec<ViewRelation>,
+
+    /// Views pinning exactly this view.
+    #[graphql(name = "referencedBy")]
+    pub referenced_by: Vec<DocumentViewIdScalar>,
+}
+
+impl From<ViewDependenciesData> for ViewDependencies {
+    fn from(view: ViewDependenciesData) -> Self {
+        Self {
+            view_id: DocumentViewIdScalar::from(&view.view_id),
+            is_current: view.is_current,
+            relations: view.relations.into_iter().map(ViewRelation::from).collect(),
+            referenced_by: view
+                .referenced_by
+                .iter()
+                .map(DocumentViewIdScalar::from)
+                .collect(),
+        }
+    }
+}
+
+/// Pending materializer task.
+#[derive(SimpleObject)]
+pub struct DependencyTask {
+    /// Name of the worker which will process the task, for example "reduce".
+    pub worker: String,
+
+    /// Id of the document processed by the task.
+    #[graphql(name = "documentId")]
+    pub document_id: Option<DocumentIdScalar>,
+
+    /// Id of the document view processed by the task.
+    #[graphql(name = "viewId")]
+    pub view_id: Option<DocumentViewIdScalar>,
+}
+
+impl From<Task<TaskInput>> for DependencyTask {
+    fn from(task: Task<TaskInput>) -> Self {
+        let (document_id, view_id) = match task.input() {
+            TaskInput::DocumentId(document_id) => (Some(DocumentIdScalar::from(document_id)), None),
+            TaskInput::DocumentViewId(view_id) => (None, Some(DocumentViewIdScalar::from(view_id))),
+        };
+
+        Self {
+            worker: task.worker_name().to_string(),
+            document_id,
+            view_id,
+        }
+    }
+}
+
+/// Dependency graph of the materialized views of a document.
+#[derive(SimpleObject)]
+pub struct DependencyGraph {
+    /// Id of the document.
+    #[graphql(name = "documentId")]
+    pub document_id: DocumentIdScalar,
+
+    /// Current view of the document, null if it was not materialized yet.
+    #[graphql(name = "currentViewId")]
+    pub current_view_id: Option<DocumentViewIdScalar>,
+
+    /// All materialized views of the document.
+    pub views: Vec<ViewDependencies>,
+
+    /// Views relating to the document without pinning one of its views.
+    #[graphql(name = "referencedBy")]
+    pub referenced_by: Vec<DocumentViewIdScalar>,
+
+    /// Pending materializer tasks for the document or any of its views.
+    #[graphql(name = "pendingTasks")]
+    pub pending_tasks: Vec<DependencyTask>,
+}
+
+impl From<DependencyGraphData> for DependencyGraph {
+    fn from(graph: DependencyGraphData) -> Self {
+        Self {
+            document_id: DocumentIdScalar::from(&graph.document_id),
+            current_view_id: graph
+                .current_view_id
+                .as_ref()
+                .map(DocumentViewIdScalar::from),
+            views: graph
+                .views
+                .into_iter()
+                .map(ViewDependencies::from)
+                .collect(),
+            referenced_by: graph
+                .referenced_by
+                .iter()
+                .map(DocumentViewIdScalar::from)
+                .collect(),
+            pending_tasks: graph
+                .pending_tasks
+                .into_iter()
+                .map(DependencyTask::from)
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `documentChanges` query.
+use dynamic_graphql::SimpleObject;
+
+use crate::db::models::DocumentChangeRow;
+
+/// Document which got created, updated or deleted on this node.
+#[derive(SimpleObject)]
+pub struct DocumentChangeRecord {
+    /// Token of this change, increasing with every recorded change.
+    pub token: String,
+
+    /// Kind of change, either "create", "update" or "delete".
+    pub change: String,
+
+    /// Id of the schema of the document.
+    pub schema_id: String,
+
+    /// Id of the changed document.
+    pub document_id: String,
+
+    /// Id of the view the document changed to.
+    pub view_id: String,
+
+    /// UNIX timestamp in milliseconds of when the change was recorded.
+    pub changed_at: i64,
+}
+
+impl From<DocumentChangeRow> for DocumentChangeRecord {
+    fn from(row: DocumentChangeRow) -> Self {
+        Self {
+            token: row.sequence.to_string(),
+            change: row.change_type,
+            schema_id: row.schema_id,
+            document_id: row.document_id,
+            view_id: row.document_view_id,
+            changed_at: row.changed_at,
+        }
+    }
+}
+
+/// Page of the feed of document changes.
+#[derive(SimpleObject)]
+pub struct DocumentChangeFeed {
+    /// Changes ordered by their token.
+    pub changes: Vec<DocumentChangeRecord>,
+
+    /// Token to pass as `since` argument to request the following changes.
+    pub next: String,
+}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `graphqlSchema` query.
+use dynamic_graphql::SimpleObject;
+
+/// GraphQL schema currently served by the node.
+#[derive(SimpleObject)]
+pub struct GraphQLSchemaInfo {
+    /// Schema definition language (SDL) representation of the GraphQL schema.
+    pub sdl: String,
+
+    /// Version of the GraphQL schema, increases every time the schema changes.
+    pub version: u64,
+}
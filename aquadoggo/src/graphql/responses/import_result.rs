@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `import_commits` mutation.
+use dynamic_graphql::SimpleObject;
+
+use crate::graphql::scalars::EntryHashScalar;
+
+/// Entry which could not be imported.
+#[derive(SimpleObject)]
+pub struct FailedImport {
+    /// Hash of the entry.
+    #[graphql(name = "entryHash")]
+    pub entry_hash: EntryHashScalar,
+
+    /// Reason why the entry could not be imported.
+    pub reason: String,
+}
+
+/// Result of importing entries and operations.
+#[derive(SimpleObject)]
+pub struct ImportResult {
+    /// Number of entries and operations which have been imported and will be materialized.
+    pub imported: u64,
+
+    /// Number of entries which already existed on the node.
+    pub existing: u64,
+
+    /// Entries which could not be imported.
+    pub failed: Vec<FailedImport>,
+}
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `logForks` query.
+use dynamic_graphql::SimpleObject;
+
+use crate::db::models::LogForkRow;
+
+/// Two conflicting entries of the same author in the same log, the key pair is probably used on
+/// more than one device.
+#[derive(SimpleObject)]
+pub struct LogFork {
+    /// Public key of the author of both entries.
+    pub public_key: String,
+
+    /// Id of the forked log.
+    pub log_id: String,
+
+    /// Sequence number at which the log forked.
+    pub seq_num: String,
+
+    /// Hash of the entry this node holds at this position.
+    pub entry_hash: String,
+
+    /// Hash of the conflicting entry which got rejected.
+    pub conflicting_entry_hash: String,
+
+    /// UNIX timestamp in seconds of when the fork was detected.
+    pub detected_at: i64,
+}
+
+impl From<LogForkRow> for LogFork {
+    fn from(row: LogForkRow) -> Self {
+        Self {
+            public_key: row.public_key,
+            log_id: row.log_id,
+            seq_num: row.seq_num,
+            entry_hash: row.entry_hash,
+            conflicting_entry_hash: row.conflicting_entry_hash,
+            detected_at: row.detected_at,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `materializer_progress` query.
+use dynamic_graphql::SimpleObject;
+
+/// Number of pending tasks of one materializer worker.
+#[derive(SimpleObject)]
+pub struct PendingTasks {
+    /// Name of the worker, for example "reduce" or "dependency".
+    pub worker: String,
+
+    /// Number of tasks waiting to be processed by this worker.
+    pub count: u64,
+}
+
+/// Progress of the node materializing received operations into documents.
+#[derive(SimpleObject)]
+pub struct MaterializerProgress {
+    /// Pending tasks for each worker, workers without any pending tasks are omitted.
+    #[graphql(name = "pendingTasks")]
+    pub pending_tasks: Vec<PendingTasks>,
+
+    /// Number of documents with operations which have not been materialized yet.
+    #[graphql(name = "documentsAwaitingReduce")]
+    pub documents_awaiting_reduce: u64,
+
+    /// True if there is no pending materialization work left.
+    #[graphql(name = "isIdle")]
+    pub is_idle: bool,
+}
@@ -1,5 +1,31 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod annotation;
+mod blob_derivative;
+mod blob_status;
+mod dependency_graph;
+mod document_change;
+mod graphql_schema;
+mod import_result;
+mod log_fork;
+mod materializer_progress;
+mod network_metrics;
 mod next_arguments;
+mod node_info;
+mod purge_result;
+mod search_result;
 
+pub use annotation::Annotation;
+pub use blob_derivative::BlobDerivative;
+pub use blob_status::BlobStatus;
+pub use dependency_graph::{DependencyGraph, DependencyTask, ViewDependencies, ViewRelation};
+pub use document_change::{DocumentChangeFeed, DocumentChangeRecord};
+pub use graphql_schema::GraphQLSchemaInfo;
+pub use import_result::{FailedImport, ImportResult};
+pub use log_fork::LogFork;
+pub use materializer_progress::{MaterializerProgress, PendingTasks};
+pub use network_metrics::NetworkTraffic;
 pub use next_arguments::NextArguments;
+pub use node_info::{NodeInfo, RelayInfo};
+pub use purge_result::PurgeResult;
+pub use search_result::{SearchResult, SearchSnippet};
@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `network_metrics` query.
+use dynamic_graphql::SimpleObject;
+
+/// Bandwidth and connection metrics of one transport and protocol.
+#[derive(SimpleObject)]
+pub struct NetworkTraffic {
+    /// Transport protocol, either "tcp" or "quic".
+    pub transport: String,
+
+    /// Purpose of the traffic, either "replication" for direct connections or "relay" for
+    /// connections via a relay circuit.
+    pub protocol: String,
+
+    /// Total number of bytes received.
+    #[graphql(name = "inboundBytes")]
+    pub inbound_bytes: u64,
+
+    /// Total number of bytes sent.
+    #[graphql(name = "outboundBytes")]
+    pub outbound_bytes: u64,
+
+    /// Number of currently open connections.
+    #[graphql(name = "openConnections")]
+    pub open_connections: u64,
+
+    /// Total number of established connections.
+    #[graphql(name = "totalConnections")]
+    pub total_connections: u64,
+}
@@ -6,7 +6,7 @@ use dynamic_graphql::SimpleObject;
 use crate::graphql::scalars::{EntryHashScalar, LogIdScalar, SeqNumScalar};
 
 /// Arguments required to sign and encode the next entry for a public_key.
-#[derive(SimpleObject)]
+#[derive(SimpleObject, Clone, Debug)]
 pub struct NextArguments {
     /// Log id of the entry.
     #[graphql(name = "logId")]
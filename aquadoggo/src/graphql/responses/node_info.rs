@@ -0,0 +1,54 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `nodeInfo` query.
+use dynamic_graphql::SimpleObject;
+
+/// Relay the node is connected to.
+#[derive(SimpleObject)]
+pub struct RelayInfo {
+    /// Peer id of the relay.
+    #[graphql(name = "peerId")]
+    pub peer_id: String,
+
+    /// Address we're connected to the relay at.
+    pub address: String,
+
+    /// State of our registration at the relay, one of "pending", "active", "expiring", "failed"
+    /// or "disconnected".
+    ///
+    /// Registrations are "expiring" when they are due for renewal but the relay did not renew
+    /// them yet.
+    pub status: String,
+
+    /// Seconds until our registration at the relay expires.
+    #[graphql(name = "expiresIn")]
+    pub expires_in: Option<u64>,
+
+    /// Reason why the last registration or circuit reservation failed.
+    pub error: Option<String>,
+
+    /// Did the relay accept our circuit reservation, without it other nodes can't reach us via
+    /// the relay.
+    #[graphql(name = "reservationAccepted")]
+    pub reservation_accepted: bool,
+}
+
+/// Information about the local node.
+#[derive(SimpleObject)]
+pub struct NodeInfo {
+    /// Identity the HTTP API of the node is served under, the configured HTTP identity or
+    /// otherwise the peer id.
+    pub identity: Option<String>,
+
+    /// Peer id of the node, not available before the network service started or when the node
+    /// is served under its own HTTP identity.
+    #[graphql(name = "peerId")]
+    pub peer_id: Option<String>,
+
+    /// Relay the node is currently using to be reachable by other nodes, picked from all
+    /// configured relays based on their health and load.
+    pub relay: Option<RelayInfo>,
+
+    /// All relays the node is or was connected to.
+    pub relays: Vec<RelayInfo>,
+}
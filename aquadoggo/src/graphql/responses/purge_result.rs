@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `purgeDocument` mutation.
+use dynamic_graphql::SimpleObject;
+
+use crate::api::PurgeReport;
+use crate::graphql::scalars::{DocumentIdScalar, DocumentViewIdScalar};
+
+/// Report of what got removed when purging a document.
+#[derive(SimpleObject)]
+pub struct PurgeResult {
+    /// Number of operations of the document which were removed together with their entries.
+    #[graphql(name = "removedOperations")]
+    pub removed_operations: u64,
+
+    /// Materialized views of the document which were removed.
+    #[graphql(name = "removedViews")]
+    pub removed_views: Vec<DocumentViewIdScalar>,
+
+    /// Blob documents which were purged together with the document.
+    #[graphql(name = "removedBlobs")]
+    pub removed_blobs: Vec<DocumentIdScalar>,
+
+    /// Blob piece documents which were purged as no other blob uses them.
+    #[graphql(name = "removedBlobPieces")]
+    pub removed_blob_pieces: Vec<DocumentIdScalar>,
+
+    /// Blob views of which files were removed from the file system.
+    #[graphql(name = "removedBlobFiles")]
+    pub removed_blob_files: Vec<DocumentViewIdScalar>,
+
+    /// Documents the purged document related to, they get garbage collected next.
+    #[graphql(name = "affectedDocuments")]
+    pub affected_documents: Vec<DocumentIdScalar>,
+}
+
+impl From<&PurgeReport> for PurgeResult {
+    fn from(report: &PurgeReport) -> Self {
+        Self {
+            removed_operations: report.removed_operations.len() as u64,
+            removed_views: report.removed_views.iter().map(Into::into).collect(),
+            removed_blobs: report.removed_blobs.iter().map(Into::into).collect(),
+            removed_blob_pieces: report.removed_blob_pieces.iter().map(Into::into).collect(),
+            removed_blob_files: report.removed_blob_files.iter().map(Into::into).collect(),
+            affected_documents: report.affected_documents.iter().map(Into::into).collect(),
+        }
+    }
+}
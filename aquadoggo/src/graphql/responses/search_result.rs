@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Return type for `search` query.
+use dynamic_graphql::SimpleObject;
+
+use crate::graphql::scalars::{DocumentIdScalar, DocumentViewIdScalar};
+
+/// Excerpt of a document field containing the searched text.
+#[derive(SimpleObject)]
+pub struct SearchSnippet {
+    /// Name of the field.
+    pub field: String,
+
+    /// Excerpt of the field value, all occurrences of the searched text are wrapped in `<mark>`
+    /// and `</mark>`. The value itself is not escaped.
+    pub text: String,
+}
+
+/// Document matching a search across schemas.
+#[derive(SimpleObject)]
+pub struct SearchResult {
+    /// Schema of the document.
+    #[graphql(name = "schemaId")]
+    pub schema_id: String,
+
+    /// Id of the document.
+    #[graphql(name = "documentId")]
+    pub document_id: DocumentIdScalar,
+
+    /// Latest view id of the document.
+    #[graphql(name = "viewId")]
+    pub view_id: DocumentViewIdScalar,
+
+    /// Excerpts of all fields containing the searched text.
+    pub snippets: Vec<SearchSnippet>,
+}
@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Display;
+
+use dynamic_graphql::{Error, Result, Scalar, ScalarValue, Value};
+use serde::Serialize;
+
+use crate::schema::{format_datetime, parse_datetime};
+
+/// Date and time in UTC encoded as ISO 8601 string, for example "2023-10-16T10:00:00Z".
+///
+/// Values can be given as ISO 8601 strings with any UTC offset or as UNIX timestamps in seconds.
+#[derive(Scalar, Clone, Debug, Eq, PartialEq, Serialize)]
+#[graphql(name = "DateTime", validator(validate))]
+pub struct DateTimeScalar(String);
+
+impl ScalarValue for DateTimeScalar {
+    fn from_value(value: Value) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let timestamp = match &value {
+            Value::String(value) => {
+                parse_datetime(value).map_err(|err| Error::new(err.to_string()))
+            }
+            Value::Number(number) => number
+                .as_i64()
+                .ok_or_else(|| Error::new(format!("Expected date and time, found: {value}"))),
+            _ => Err(Error::new(format!(
+                "Expected date and time, found: {value}"
+            ))),
+        }?;
+
+        let formatted = format_datetime(timestamp).map_err(|err| Error::new(err.to_string()))?;
+        Ok(DateTimeScalar(formatted))
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.0.clone())
+    }
+}
+
+impl From<DateTimeScalar> for Value {
+    fn from(value: DateTimeScalar) -> Self {
+        ScalarValue::to_value(&value)
+    }
+}
+
+impl Display for DateTimeScalar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Validation method used internally in `async-graphql` to check scalar values passed into the
+/// public api.
+fn validate(value: &Value) -> bool {
+    DateTimeScalar::from_value(value.to_owned()).is_ok()
+}
@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Display;
+
+use dynamic_graphql::{Error, Result, Scalar, ScalarValue, Value};
+use serde::Serialize;
+
+use crate::schema::{parse_decimal, DecimalError, MAX_DECIMAL_SCALE};
+
+/// Decimal number with a fixed number of fractional digits encoded as a string, for example
+/// "12.34".
+#[derive(Scalar, Clone, Debug, Eq, PartialEq, Serialize)]
+#[graphql(name = "Decimal", validator(validate))]
+pub struct DecimalScalar(String);
+
+impl ScalarValue for DecimalScalar {
+    fn from_value(value: Value) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        match &value {
+            // Precision and range depend on the field, here we only check the format
+            Value::String(value) => match parse_decimal(value, MAX_DECIMAL_SCALE) {
+                Err(DecimalError::Invalid(_)) => Err(Error::new(format!(
+                    "Expected decimal string, found: {value}"
+                ))),
+                _ => Ok(DecimalScalar(value.to_string())),
+            },
+            _ => Err(Error::new(format!(
+                "Expected decimal string, found: {value}"
+            ))),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.0.clone())
+    }
+}
+
+impl From<String> for DecimalScalar {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<DecimalScalar> for Value {
+    fn from(value: DecimalScalar) -> Self {
+        ScalarValue::to_value(&value)
+    }
+}
+
+impl Display for DecimalScalar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Validation method used internally in `async-graphql` to check scalar values passed into the
+/// public api.
+fn validate(value: &Value) -> bool {
+    DecimalScalar::from_value(value.to_owned()).is_ok()
+}
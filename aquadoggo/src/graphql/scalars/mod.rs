@@ -8,6 +8,8 @@
 //! We use a naming convention of appending the item's GraphQL type (e.g. `Scalar`) when a p2panda
 //! item of the exact same name is being wrapped.
 mod cursor_scalar;
+mod datetime_scalar;
+mod decimal_scalar;
 mod document_id_scalar;
 mod document_view_id_scalar;
 mod encoded_entry_scalar;
@@ -15,10 +17,13 @@ mod encoded_operation_scalar;
 mod entry_hash_scalar;
 mod hex_bytes_scalar;
 mod log_id_scalar;
+mod operation_fields_scalar;
 mod public_key_scalar;
 mod seq_num_scalar;
 
 pub use cursor_scalar::CursorScalar;
+pub use datetime_scalar::DateTimeScalar;
+pub use decimal_scalar::DecimalScalar;
 pub use document_id_scalar::DocumentIdScalar;
 pub use document_view_id_scalar::DocumentViewIdScalar;
 pub use encoded_entry_scalar::EncodedEntryScalar;
@@ -26,5 +31,6 @@ pub use encoded_operation_scalar::EncodedOperationScalar;
 pub use entry_hash_scalar::EntryHashScalar;
 pub use hex_bytes_scalar::HexBytesScalar;
 pub use log_id_scalar::LogIdScalar;
+pub use operation_fields_scalar::OperationFieldsScalar;
 pub use public_key_scalar::PublicKeyScalar;
 pub use seq_num_scalar::SeqNumScalar;
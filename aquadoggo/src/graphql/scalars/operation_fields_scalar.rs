@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::Name;
+use dynamic_graphql::{Error, Result, Scalar, ScalarValue, Value};
+
+/// Field values of an operation given as an object of field names and values.
+///
+/// Values are not validated yet, they get converted according to the field types of the schema
+/// the operation is published for.
+#[derive(Scalar, Clone, Debug, PartialEq)]
+#[graphql(name = "OperationFields", validator(validate))]
+pub struct OperationFieldsScalar(Vec<(String, Value)>);
+
+impl OperationFieldsScalar {
+    /// Returns an iterator over all field names and values.
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Value)> {
+        self.0.iter()
+    }
+}
+
+impl ScalarValue for OperationFieldsScalar {
+    fn from_value(value: Value) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        match value {
+            Value::Object(fields) => Ok(OperationFieldsScalar(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect(),
+            )),
+            _ => Err(Error::new(format!(
+                "Expected an object of field names and values, found: {value}"
+            ))),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Object(
+            self.0
+                .iter()
+                .map(|(name, value)| (Name::new(name), value.to_owned()))
+                .collect(),
+        )
+    }
+}
+
+/// Validation method used internally in `async-graphql` to check scalar values passed into the
+/// public api.
+fn validate(value: &Value) -> bool {
+    OperationFieldsScalar::from_value(value.to_owned()).is_ok()
+}
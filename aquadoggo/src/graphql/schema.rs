@@ -2,44 +2,89 @@
 
 //! Dynamically create and manage GraphQL schemas.
 use std::sync::Arc;
+use std::time::Duration;
 
-use async_graphql::dynamic::{Field, FieldFuture, Object, Schema, TypeRef};
-use async_graphql::{Request, Response, Value};
+use async_graphql::dynamic::{Field, FieldFuture, Object, Schema, Subscription, TypeRef};
+use async_graphql::{Data, Executor, Request, Response, Value};
+use async_stream::stream;
+use async_trait::async_trait;
 use dynamic_graphql::internal::Registry;
+use futures::stream::BoxStream;
+use futures::StreamExt;
 use log::{debug, info, warn};
+use p2panda_rs::identity::PublicKey;
 use p2panda_rs::Human;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::broadcast::Receiver;
 use tokio::sync::Mutex;
 
-use crate::bus::ServiceSender;
+use crate::authors::AuthorKeys;
+use crate::blobs::BlobStore;
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::capabilities::CapabilityProvider;
 use crate::db::SqlStore;
+use crate::graphql::errors::mask_internal_errors;
+use crate::graphql::explain::{QueryPlans, QUERY_PLANS_EXTENSION};
+use crate::graphql::idempotency::IdempotencyCache;
 use crate::graphql::input_values::{
-    build_filter_input_object, build_order_enum_value, BooleanFilter, FloatFilter, HexBytesFilter,
-    IntegerFilter, MetaFilterInputObject, OrderDirection, PinnedRelationFilter,
-    PinnedRelationListFilter, RelationFilter, RelationListFilter, StringFilter,
+    build_filter_input_object, build_order_enum_value, BooleanFilter, DateTimeFilter,
+    DecimalFilter, FloatFilter, HexBytesFilter, IntegerFilter, MetaFilterInputObject,
+    OrderDirection, PinnedRelationFilter, PinnedRelationListFilter, RelationFilter,
+    RelationListFilter, StringFilter,
+};
+use crate::graphql::loader::DocumentLoader;
+use crate::graphql::mutations::{
+    AnnotateDocument, ApproveDocument, CreateDocument, DeleteDocument, ImportCommits,
+    MergeDocuments, MutationRoot, PauseReplication, Publish, PublishBatch, PurgeDocument,
+    RedeemInvite, ResumeReplication, ScheduleTask, UpdateDocument,
 };
-use crate::graphql::mutations::{MutationRoot, Publish};
 use crate::graphql::objects::{
-    build_document_collection_object, build_document_fields_object, build_document_object,
-    build_paginated_document_object, DocumentMeta,
+    build_document_collection_object, build_document_fields_object, build_document_lookup_object,
+    build_document_object, build_paginated_document_object, DocumentMeta, DocumentStats,
 };
 use crate::graphql::queries::{
-    build_collection_query, build_document_query, build_next_args_query,
+    build_annotations_query, build_blob_derivatives_query, build_blob_status_query,
+    build_collection_query, build_dependency_graph_query, build_document_changes_query,
+    build_document_exists_query, build_document_query, build_documents_by_ids_query,
+    build_graphql_schema_query, build_held_documents_query, build_log_forks_query,
+    build_materializer_progress_query, build_network_metrics_query, build_next_args_query,
+    build_node_info_query, build_search_query, build_view_exists_query, HttpIdentity,
+};
+use crate::graphql::resolvers::ReadAccess;
+use crate::graphql::responses::{
+    Annotation, BlobDerivative, BlobStatus, DependencyGraph, DependencyTask, DocumentChangeFeed,
+    DocumentChangeRecord, FailedImport, GraphQLSchemaInfo, ImportResult, LogFork,
+    MaterializerProgress, NetworkTraffic, NextArguments, NodeInfo, PendingTasks, PurgeResult,
+    RelayInfo, SearchResult, SearchSnippet, ViewDependencies, ViewRelation,
 };
-use crate::graphql::responses::NextArguments;
 use crate::graphql::scalars::{
-    CursorScalar, DocumentIdScalar, DocumentViewIdScalar, EncodedEntryScalar,
-    EncodedOperationScalar, EntryHashScalar, HexBytesScalar, LogIdScalar, PublicKeyScalar,
-    SeqNumScalar,
+    CursorScalar, DateTimeScalar, DecimalScalar, DocumentIdScalar, DocumentViewIdScalar,
+    EncodedEntryScalar, EncodedOperationScalar, EntryHashScalar, HexBytesScalar, LogIdScalar,
+    OperationFieldsScalar, PublicKeyScalar, SeqNumScalar,
 };
+use crate::graphql::sdl::GraphQLSdl;
+use crate::graphql::subscriptions::{build_collection_subscription, build_document_subscription};
+use crate::graphql::traversal::{RelationLimits, RelationTraversal, RELATION_LIMITS_EXTENSION};
+use crate::network::{LocalAddresses, NetworkMetrics};
 use crate::schema::SchemaProvider;
 
 /// Dynamically generates and returns a new GraphQL API root schema based on the currently
 /// registered p2panda schemas.
 pub async fn build_root_schema(
-    store: SqlStore,
-    tx: ServiceSender,
-    schema_provider: SchemaProvider,
+    shared: GraphQLSharedData,
 ) -> Result<Schema, async_graphql::dynamic::SchemaError> {
+    let GraphQLSharedData {
+        store,
+        tx,
+        schema_provider,
+        capability_provider,
+        idempotency_cache,
+        network_metrics,
+        local_addresses,
+        author_keys,
+        sdl,
+    } = shared;
+
     let all_schema = schema_provider.all().await;
 
     // Using dynamic-graphql we create a registry and add types
@@ -47,12 +92,49 @@ pub async fn build_root_schema(
         // Register mutation operations
         .register::<MutationRoot>()
         .register::<Publish>()
+        .register::<PublishBatch>()
+        .register::<ScheduleTask>()
+        .register::<MergeDocuments>()
+        .register::<ImportCommits>()
+        .register::<AnnotateDocument>()
+        .register::<ApproveDocument>()
+        .register::<RedeemInvite>()
+        .register::<PauseReplication>()
+        .register::<ResumeReplication>()
+        .register::<CreateDocument>()
+        .register::<UpdateDocument>()
+        .register::<DeleteDocument>()
+        .register::<PurgeDocument>()
         // Register responses
         .register::<NextArguments>()
+        .register::<MaterializerProgress>()
+        .register::<PendingTasks>()
+        .register::<NetworkTraffic>()
+        .register::<NodeInfo>()
+        .register::<RelayInfo>()
+        .register::<ImportResult>()
+        .register::<FailedImport>()
+        .register::<PurgeResult>()
+        .register::<SearchResult>()
+        .register::<SearchSnippet>()
+        .register::<Annotation>()
+        .register::<LogFork>()
+        .register::<BlobStatus>()
+        .register::<BlobDerivative>()
+        .register::<DependencyGraph>()
+        .register::<ViewDependencies>()
+        .register::<ViewRelation>()
+        .register::<DependencyTask>()
+        .register::<GraphQLSchemaInfo>()
+        .register::<DocumentChangeRecord>()
+        .register::<DocumentChangeFeed>()
         // Register objects
         .register::<DocumentMeta>()
+        .register::<DocumentStats>()
         // Register input values
         .register::<BooleanFilter>()
+        .register::<DateTimeFilter>()
+        .register::<DecimalFilter>()
         .register::<HexBytesFilter>()
         .register::<FloatFilter>()
         .register::<IntegerFilter>()
@@ -66,16 +148,19 @@ pub async fn build_root_schema(
         // Register scalars
         .register::<HexBytesScalar>()
         .register::<CursorScalar>()
+        .register::<DateTimeScalar>()
+        .register::<DecimalScalar>()
         .register::<DocumentIdScalar>()
         .register::<DocumentViewIdScalar>()
         .register::<EncodedEntryScalar>()
         .register::<EncodedOperationScalar>()
         .register::<EntryHashScalar>()
         .register::<LogIdScalar>()
+        .register::<OperationFieldsScalar>()
         .register::<PublicKeyScalar>()
         .register::<SeqNumScalar>();
 
-    let mut schema_builder = Schema::build("Query", Some("MutationRoot"), None);
+    let mut schema_builder = Schema::build("Query", Some("MutationRoot"), Some("Subscription"));
 
     // Populate it with the registered types. We can now use these in any following dynamically
     // created query object fields.
@@ -84,15 +169,24 @@ pub async fn build_root_schema(
     // Construct the root query object
     let mut root_query = Object::new("Query");
 
+    // Construct the root subscription object
+    let mut root_subscription = Subscription::new("Subscription");
+
+    // Ordering enums list the fields of related schemas as well
+    let related_schema = all_schema.clone();
+
     // Loop through all schema retrieved from the schema store, dynamically create GraphQL objects,
     // input values and a query for the documents they describe
     for schema in all_schema {
         // Construct the fields type object which will be named `<schema_id>Field`
-        let document_fields_object = build_document_fields_object(&schema);
+        let document_fields_object = build_document_fields_object(&schema, &schema_provider);
 
         // Construct the document object which contains "fields" and "meta" fields
         let document_object = build_document_object(&schema);
 
+        // Construct the result of looking up a document of this schema by its id
+        let document_lookup_object = build_document_lookup_object(&schema);
+
         // Construct the paginated response wrapper for this document schema type
         let document_collection_object = build_document_collection_object(&schema);
 
@@ -101,14 +195,15 @@ pub async fn build_root_schema(
         let paginated_document_object = build_paginated_document_object(&schema);
 
         // Construct the filter and ordering input values for this schema
-        let filter_input = build_filter_input_object(&schema);
-        let order_input = build_order_enum_value(&schema);
+        let filter_input = build_filter_input_object(&schema, &schema_provider);
+        let order_input = build_order_enum_value(&schema, &related_schema);
 
         // Register a schema, schema fields and filter type for every schema
         schema_builder = schema_builder
             .register(document_fields_object)
             .register(document_object)
             .register(document_collection_object)
+            .register(document_lookup_object)
             .register(paginated_document_object)
             .register(order_input)
             .register(filter_input);
@@ -118,27 +213,90 @@ pub async fn build_root_schema(
         // parameters, then forwards them up to the children query fields
         root_query = build_document_query(root_query, &schema);
 
+        // Add a query for retrieving many documents of a certain schema by their ids at once
+        root_query = build_documents_by_ids_query(root_query, &schema);
+
         // Add a query for retrieving all documents of a certain schema
         root_query = build_collection_query(root_query, &schema);
+
+        // Add subscriptions to changes of a single document and of all documents of this schema
+        root_subscription = build_document_subscription(root_subscription, &schema);
+        root_subscription = build_collection_subscription(root_subscription, &schema);
     }
 
     // Add next args to the query object
     let root_query = build_next_args_query(root_query);
 
+    // Add cheap existence checks of documents and views to the query object
+    let root_query = build_document_exists_query(root_query);
+    let root_query = build_view_exists_query(root_query);
+
+    // Add materializer progress to the query object
+    let root_query = build_materializer_progress_query(root_query);
+
+    // Add network metrics to the query object
+    let root_query = build_network_metrics_query(root_query);
+
+    // Add information about the local node to the query object
+    let root_query = build_node_info_query(root_query);
+
+    // Add node-local document annotations to the query object
+    let root_query = build_annotations_query(root_query);
+
+    // Add documents waiting for approval to the query object
+    let root_query = build_held_documents_query(root_query);
+
+    // Add detected forks of logs to the query object
+    let root_query = build_log_forks_query(root_query);
+
+    // Add feed of document changes to the query object
+    let root_query = build_document_changes_query(root_query);
+
+    // Add completeness of blob pieces to the query object
+    let root_query = build_blob_status_query(root_query);
+
+    // Add transcoded versions of blobs to the query object
+    let root_query = build_blob_derivatives_query(root_query);
+
+    // Add dependency graph of document views to the query object
+    let root_query = build_dependency_graph_query(root_query);
+
+    // Add search across schemas to the query object
+    let root_query = build_search_query(root_query);
+
+    // Add SDL and version of the GraphQL schema to the query object
+    let root_query = build_graphql_schema_query(root_query);
+
     // Build the GraphQL schema. We can unwrap here since it will only fail if we forgot to
     // register all required types above
     schema_builder
         .register(root_query)
+        .register(root_subscription)
         .data(store)
         .data(schema_provider)
+        .data(capability_provider)
+        .data(idempotency_cache)
+        .data(network_metrics)
+        .data(local_addresses)
+        .data(author_keys)
+        .data(sdl)
         .data(tx)
         .finish()
 }
 
+/// Duration schema changes are collected after a rebuild before the GraphQL schema is built again.
+///
+/// Many schemas arrive at once when syncing with other nodes, this avoids rebuilding the GraphQL
+/// schema for every single one of them.
+const REBUILD_DEBOUNCE_INTERVAL: Duration = Duration::from_millis(200);
+
 /// List of created GraphQL root schemas.
 type GraphQLSchemas = Arc<Mutex<Vec<Schema>>>;
 
 /// Shared types between GraphQL schemas.
+///
+/// Besides the store, the communication bus and the schema provider all services are optional,
+/// they are empty or disabled unless they are set with the `with_` methods.
 #[derive(Clone, Debug)]
 pub struct GraphQLSharedData {
     /// Database interface.
@@ -149,6 +307,72 @@ pub struct GraphQLSharedData {
 
     /// Schema provider giving us access to currently known schemas.
     schema_provider: SchemaProvider,
+
+    /// Capability provider authorising requests.
+    capability_provider: CapabilityProvider,
+
+    /// Cached responses of publish requests with idempotency keys, shared across all schemas.
+    idempotency_cache: IdempotencyCache,
+
+    /// Bandwidth and connection metrics of the network service.
+    network_metrics: NetworkMetrics,
+
+    /// Addresses and relay of the local node, learned by the network service.
+    local_addresses: LocalAddresses,
+
+    /// Key pairs the node signs entries with on behalf of clients.
+    author_keys: AuthorKeys,
+
+    /// SDL of the latest built GraphQL schema.
+    sdl: GraphQLSdl,
+}
+
+impl GraphQLSharedData {
+    /// Returns a new instance of `GraphQLSharedData`.
+    pub fn new(store: SqlStore, tx: ServiceSender, schema_provider: SchemaProvider) -> Self {
+        Self {
+            store,
+            tx,
+            schema_provider,
+            capability_provider: CapabilityProvider::default(),
+            idempotency_cache: IdempotencyCache::default(),
+            network_metrics: NetworkMetrics::default(),
+            local_addresses: LocalAddresses::default(),
+            author_keys: AuthorKeys::default(),
+            sdl: GraphQLSdl::default(),
+        }
+    }
+
+    /// Authorise requests with the given capability provider, by default requests are not
+    /// restricted.
+    pub fn with_capability_provider(mut self, capability_provider: CapabilityProvider) -> Self {
+        self.capability_provider = capability_provider;
+        self
+    }
+
+    /// Cache responses of publish requests with idempotency keys.
+    pub fn with_idempotency_cache(mut self, idempotency_cache: IdempotencyCache) -> Self {
+        self.idempotency_cache = idempotency_cache;
+        self
+    }
+
+    /// Report bandwidth and connection metrics of the network service.
+    pub fn with_network_metrics(mut self, network_metrics: NetworkMetrics) -> Self {
+        self.network_metrics = network_metrics;
+        self
+    }
+
+    /// Report addresses and relay of the local node learned by the network service.
+    pub fn with_local_addresses(mut self, local_addresses: LocalAddresses) -> Self {
+        self.local_addresses = local_addresses;
+        self
+    }
+
+    /// Sign operations on behalf of clients with the key pairs of service accounts.
+    pub fn with_author_keys(mut self, author_keys: AuthorKeys) -> Self {
+        self.author_keys = author_keys;
+        self
+    }
 }
 
 /// Builds new GraphQL schemas dynamically and executes the latest GraphQL schema for incoming
@@ -169,11 +393,23 @@ pub struct GraphQLSchemaManager {
 
     /// Commonly shared types for GraphQL schemas.
     shared: GraphQLSharedData,
+
+    /// Limits on relations followed during a single query.
+    relation_limits: RelationLimits,
+
+    /// Return details of internal errors to clients instead of masking them.
+    error_details: bool,
+
+    /// Identity reported to clients instead of the peer id of the node.
+    http_identity: Option<HttpIdentity>,
+
+    /// Blob store of the node, required to remove blob files when purging documents.
+    blob_store: Option<BlobStore>,
 }
 
 impl GraphQLSchemaManager {
     /// Returns a new instance of `GraphQLSchemaManager`.
-    pub async fn new(store: SqlStore, tx: ServiceSender, schema_provider: SchemaProvider) -> Self {
+    pub async fn new(shared: GraphQLSharedData) -> Self {
         // Initialize a default GraphQL schema. Used as a fallback when a node has no supported schema configured.
         let root_query = Object::new("Query").field(Field::new(
             "hello",
@@ -186,35 +422,86 @@ impl GraphQLSchemaManager {
             .expect("Empty schema should build");
 
         let schemas = Arc::new(Mutex::new(vec![initial_schema]));
-        let shared = GraphQLSharedData {
-            store,
-            tx,
-            schema_provider,
-        };
 
         // Create manager instance and spawn internal watch task
-        let manager = Self { schemas, shared };
-        manager.spawn_schema_added_task().await;
+        let manager = Self {
+            schemas,
+            shared,
+            relation_limits: RelationLimits::default(),
+            error_details: true,
+            http_identity: None,
+            blob_store: None,
+        };
+        manager.spawn_schema_changed_task().await;
+        manager.spawn_capabilities_changed_task();
 
         manager
     }
 
-    /// Subscribes to `SchemaProvider` for newly added schemas.
+    /// Subscribes to the communication bus for changed capability documents.
+    ///
+    /// The capability provider caches the grants of all capability documents, this spawns a task
+    /// invalidating them whenever a document of the capability schema got materialized.
+    fn spawn_capabilities_changed_task(&self) {
+        let capability_provider = self.shared.capability_provider.clone();
+        let schema_id = match capability_provider.schema_id() {
+            Some(schema_id) => schema_id.to_owned(),
+            None => return,
+        };
+
+        let mut rx = self.shared.tx.subscribe();
+
+        tokio::task::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(ServiceMessage::DocumentUpdated(changed_schema_id, _, _))
+                        if changed_schema_id == schema_id =>
+                    {
+                        debug!("Capability document changed, invalidating cached grants");
+                        capability_provider.invalidate();
+                    }
+                    Ok(_) => continue,
+                    // We might have missed a changed capability document, invalidate to be sure
+                    Err(RecvError::Lagged(_)) => capability_provider.invalidate(),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Subscribes to the communication bus for added and updated schemas.
     ///
-    /// This spawns a task which listens to new p2panda schemas to accordingly build a GraphQL
-    /// schema which will be added to the list.
-    async fn spawn_schema_added_task(&self) {
+    /// This spawns a task which listens to changed p2panda schemas to accordingly build a GraphQL
+    /// schema which will be added to the list. Changes arriving shortly after a rebuild are
+    /// collected and handled by one single rebuild.
+    async fn spawn_schema_changed_task(&self) {
         let shared = self.shared.clone();
         let schemas = self.schemas.clone();
 
-        debug!("Subscribing GraphQL manager to schema provider");
-        let mut on_schema_added = shared.schema_provider.on_schema_added();
+        debug!("Subscribing GraphQL manager to schema changes");
+        let mut rx = shared.tx.subscribe();
 
         // Create the new GraphQL based on the current state of known p2panda application schemas
         async fn rebuild(shared: GraphQLSharedData, schemas: GraphQLSchemas) {
-            match build_root_schema(shared.store, shared.tx, shared.schema_provider).await {
-                Ok(schema) => schemas.lock().await.push(schema),
-                Err(err) => warn!("Can't re-build GraphQL schema: {}", err),
+            let schema = match build_root_schema(shared.clone()).await {
+                Ok(schema) => schema,
+                Err(err) => {
+                    warn!("Can't re-build GraphQL schema: {}", err);
+                    return;
+                }
+            };
+
+            let sdl = schema.sdl();
+            schemas.lock().await.push(schema);
+
+            // Inform about changes of the GraphQL API, clients might need to refresh
+            if let Some((version, diff)) = shared.sdl.update(sdl) {
+                info!("GraphQL schema changed to version {}:\n{}", version, diff);
+
+                // Silently fail here as we don't care if the message was received
+                let _ = shared
+                    .tx
+                    .send(ServiceMessage::GraphQLSchemaChanged(version));
             }
         }
 
@@ -222,37 +509,224 @@ impl GraphQLSchemaManager {
         rebuild(shared.clone(), schemas.clone()).await;
         debug!("Finished building initial GraphQL schema");
 
-        // Spawn a task which reacts to newly registered p2panda schemas
+        // Spawn a task which reacts to changed p2panda schemas
         tokio::task::spawn(async move {
-            loop {
-                match on_schema_added.recv().await {
-                    Ok(schema_id) => {
-                        info!(
-                            "Changed schema {}, rebuilding GraphQL API",
-                            schema_id.display()
-                        );
-                        rebuild(shared.clone(), schemas.clone()).await;
-                    }
-                    Err(err) => {
-                        panic!("Failed receiving schema updates: {}", err)
+            while wait_for_schema_change(&mut rx).await {
+                rebuild(shared.clone(), schemas.clone()).await;
+
+                // Rebuild once more if further changes arrived in the meantime
+                loop {
+                    tokio::time::sleep(REBUILD_DEBOUNCE_INTERVAL).await;
+
+                    if !drain_schema_changes(&mut rx) {
+                        break;
                     }
+
+                    info!("Changed multiple schemas, rebuilding GraphQL API");
+                    rebuild(shared.clone(), schemas.clone()).await;
                 }
             }
         });
     }
 
+    /// Limit depth and number of related documents resolved per query, by default relations are
+    /// followed without limits.
+    pub fn with_relation_limits(mut self, relation_limits: RelationLimits) -> Self {
+        self.relation_limits = relation_limits;
+        self
+    }
+
+    /// Return details of internal errors, for example failed SQL queries, to clients. By default
+    /// all details are returned.
+    ///
+    /// When disabled, messages of internal errors are replaced with a reference id and only
+    /// logged on this node.
+    pub fn with_error_details(mut self, error_details: bool) -> Self {
+        self.error_details = error_details;
+        self
+    }
+
+    /// Report the given identity to clients instead of the peer id of the node. By default the
+    /// peer id is reported.
+    pub fn with_http_identity(mut self, http_identity: Option<String>) -> Self {
+        self.http_identity = http_identity.map(HttpIdentity);
+        self
+    }
+
+    /// Give mutations access to the blob store, for example to remove blob files when purging
+    /// documents. By default purging documents is not supported.
+    pub fn with_blob_store(mut self, blob_store: BlobStore) -> Self {
+        self.blob_store = Some(blob_store);
+        self
+    }
+
+    /// Returns the read permissions of the given reader, the same which are applied to GraphQL
+    /// queries.
+    pub fn read_access<'a>(&'a self, reader: Option<&'a PublicKey>) -> ReadAccess<'a> {
+        ReadAccess::new(
+            &self.shared.store,
+            &self.shared.schema_provider,
+            &self.shared.capability_provider,
+            reader,
+        )
+    }
+
     /// Executes an incoming GraphQL query.
     ///
     /// This method makes sure the GraphQL query will be executed by the latest given schema the
     /// manager knows about.
+    ///
+    /// Related documents are looked up in batches by a loader scoped to this query. When
+    /// relations were not resolved because a limit was reached, the response contains the
+    /// partial result and a list of `relationLimits` warnings in its extensions.
+    ///
+    /// In debug builds, requests with an `explain` extension return the SQL and query plans of
+    /// all queried collections in the `queryPlans` extension of the response.
+    ///
+    /// Internal errors are masked unless error details are enabled, see `with_error_details`.
     pub async fn execute(&self, request: impl Into<Request>) -> Response {
-        self.schemas
+        let traversal = RelationTraversal::new(self.relation_limits);
+        let loader = DocumentLoader::new(self.shared.store.clone());
+        let request = request.into();
+
+        let query_plans = if QueryPlans::is_requested(&request) {
+            Some(QueryPlans::default())
+        } else {
+            None
+        };
+
+        let mut request = self
+            .with_node_data(request)
+            .data(traversal.clone())
+            .data(loader);
+        if let Some(query_plans) = &query_plans {
+            request = request.data(query_plans.clone());
+        }
+
+        let mut response = self
+            .schemas
             .lock()
             .await
             .last()
             .expect("No schema given yet")
             .execute(request)
-            .await
+            .await;
+
+        if let Some(warning) = traversal.warning() {
+            response
+                .extensions
+                .insert(RELATION_LIMITS_EXTENSION.to_string(), warning);
+        }
+
+        if let Some(query_plans) = query_plans {
+            response
+                .extensions
+                .insert(QUERY_PLANS_EXTENSION.to_string(), query_plans.to_value());
+        }
+
+        if !self.error_details {
+            mask_internal_errors(&mut response.errors);
+        }
+
+        response
+    }
+
+    /// Attaches optional data about this node to the request.
+    fn with_node_data(&self, mut request: Request) -> Request {
+        if let Some(http_identity) = &self.http_identity {
+            request = request.data(http_identity.clone());
+        }
+        if let Some(blob_store) = &self.blob_store {
+            request = request.data(blob_store.clone());
+        }
+        request
+    }
+}
+
+#[async_trait]
+impl Executor for GraphQLSchemaManager {
+    async fn execute(&self, request: Request) -> Response {
+        GraphQLSchemaManager::execute(self, request).await
+    }
+
+    /// Executes an incoming GraphQL request and returns a stream of responses, this is used for
+    /// subscriptions over WebSocket connections.
+    ///
+    /// Subscriptions keep using the schema which was the latest when they started. Related
+    /// documents are looked up on their own for every response and only the depth limit of
+    /// relations applies, as subscriptions can run for a long time.
+    fn execute_stream(
+        &self,
+        request: Request,
+        session_data: Option<Arc<Data>>,
+    ) -> BoxStream<'static, Response> {
+        let manager = self.clone();
+
+        stream! {
+            let traversal = RelationTraversal::new(RelationLimits {
+                max_documents: 0,
+                ..manager.relation_limits
+            });
+            let request = manager.with_node_data(request).data(traversal);
+
+            let schema = manager
+                .schemas
+                .lock()
+                .await
+                .last()
+                .expect("No schema given yet")
+                .clone();
+
+            let mut responses = Executor::execute_stream(&schema, request, session_data);
+            while let Some(mut response) = responses.next().await {
+                if !manager.error_details {
+                    mask_internal_errors(&mut response.errors);
+                }
+
+                yield response;
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Waits until a schema got added or updated.
+///
+/// Returns `false` when the communication bus got closed.
+async fn wait_for_schema_change(rx: &mut Receiver<ServiceMessage>) -> bool {
+    loop {
+        match rx.recv().await {
+            Ok(ServiceMessage::SchemaAdded(schema_id))
+            | Ok(ServiceMessage::SchemaUpdated(schema_id)) => {
+                info!(
+                    "Changed schema {}, rebuilding GraphQL API",
+                    schema_id.display()
+                );
+                return true;
+            }
+            Ok(_) => continue,
+            // We might have missed a schema change, rebuild to be sure
+            Err(RecvError::Lagged(_)) => return true,
+            Err(RecvError::Closed) => return false,
+        }
+    }
+}
+
+/// Consumes all messages waiting on the communication bus.
+///
+/// Returns `true` if a schema got added or updated.
+fn drain_schema_changes(rx: &mut Receiver<ServiceMessage>) -> bool {
+    let mut changed = false;
+
+    loop {
+        match rx.try_recv() {
+            Ok(ServiceMessage::SchemaAdded(_)) | Ok(ServiceMessage::SchemaUpdated(_)) => {
+                changed = true
+            }
+            Ok(_) => continue,
+            Err(TryRecvError::Lagged(_)) => changed = true,
+            Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return changed,
+        }
     }
 }
 
@@ -274,6 +748,7 @@ mod test {
     use rstest::rstest;
     use serde_json::{json, Value};
 
+    use crate::graphql::utils::{filter_name, order_by_name};
     use crate::test_utils::{add_schema, http_test_client, test_runner, TestNode};
 
     #[rstest]
@@ -359,4 +834,81 @@ mod test {
             );
         });
     }
+
+    #[rstest]
+    fn filter_and_order_capabilities() {
+        test_runner(|mut node: TestNode| async move {
+            let key_pair = key_pair(PRIVATE_KEY);
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let schema = add_schema(
+                &mut node,
+                "events",
+                vec![
+                    ("is_free", FieldType::Boolean),
+                    ("venues", FieldType::RelationList(schema.id().to_owned())),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": format!(
+                        r#"{{
+                        filter: __type(name: "{}") {{
+                            inputFields {{ name, description }}
+                        }},
+                        order: __type(name: "{}") {{
+                            enumValues(includeDeprecated: true) {{
+                                name, description, isDeprecated
+                            }}
+                        }},
+                    }}"#,
+                        filter_name(schema.id()),
+                        order_by_name(schema.id()),
+                    ),
+                }))
+                .send()
+                .await;
+            let response: Response = response.json().await;
+            let data = response.data.into_json().unwrap();
+
+            // Filter fields list the operators they support
+            let filter_fields = data["filter"]["inputFields"].as_array().unwrap();
+            assert_eq!(filter_fields.len(), 2);
+            for field in filter_fields {
+                let expected = match field["name"].as_str().unwrap() {
+                    "is_free" => r#"@filterOperators(operators: ["eq", "notEq"])"#,
+                    "venues" => {
+                        r#"@filterOperators(operators: ["in", "notIn", "countEq", "countGte", "countGt", "countLte", "countLt"])"#
+                    }
+                    name => panic!("Unexpected filter field {name}"),
+                };
+                assert!(field["description"].as_str().unwrap().ends_with(expected));
+            }
+
+            // Lists can't be ordered by
+            let order_values = data["order"]["enumValues"].as_array().unwrap();
+            assert_eq!(order_values.len(), 4);
+            for value in order_values {
+                let is_list = value["name"] == "venues";
+                assert_eq!(value["isDeprecated"], is_list);
+                assert_eq!(
+                    value["description"]
+                        .as_str()
+                        .unwrap()
+                        .ends_with(r#"@orderBy(directions: ["ASC", "DESC"])"#),
+                    !is_list
+                );
+            }
+        });
+    }
 }
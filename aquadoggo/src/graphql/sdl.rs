@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Track changes of the GraphQL schema served by the node.
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::{Arc, RwLock};
+
+/// Keywords starting a type system definition in SDL.
+const DEFINITION_KEYWORDS: [&str; 9] = [
+    "type",
+    "input",
+    "enum",
+    "scalar",
+    "interface",
+    "union",
+    "schema",
+    "directive",
+    "extend",
+];
+
+#[derive(Debug, Default)]
+struct SdlState {
+    sdl: String,
+    version: u64,
+}
+
+/// SDL of the latest built GraphQL schema and a version number which increases with every change.
+///
+/// API gateways or code generators can poll the version to find out when they need to refresh.
+#[derive(Clone, Debug, Default)]
+pub struct GraphQLSdl(Arc<RwLock<SdlState>>);
+
+impl GraphQLSdl {
+    /// Returns the current SDL and its version, the version is 0 before the first schema was
+    /// built.
+    pub fn current(&self) -> (String, u64) {
+        let state = self.0.read().expect("Could not acquire lock on SDL");
+        (state.sdl.clone(), state.version)
+    }
+
+    /// Replaces the SDL with the one of a newly built GraphQL schema.
+    ///
+    /// Returns the new version and a human-readable diff when the SDL changed. The first SDL is
+    /// not reported as a change.
+    pub fn update(&self, sdl: String) -> Option<(u64, String)> {
+        let mut state = self.0.write().expect("Could not acquire lock on SDL");
+
+        if state.version > 0 && state.sdl == sdl {
+            return None;
+        }
+
+        let diff = (state.version > 0).then(|| diff_sdl(&state.sdl, &sdl));
+        state.sdl = sdl;
+        state.version += 1;
+
+        diff.map(|diff| (state.version, diff))
+    }
+}
+
+/// Returns the signature of a definition, for example `type Query`.
+fn signature(lines: &[&str]) -> String {
+    let mut is_description = false;
+
+    for line in lines.iter().map(|line| line.trim()) {
+        // Skip descriptions, they might contain keywords as well
+        if line.starts_with("\"\"\"") {
+            if line.len() < 6 || !line.ends_with("\"\"\"") {
+                is_description = !is_description;
+            }
+            continue;
+        }
+
+        let is_definition = line
+            .split_whitespace()
+            .next()
+            .map_or(false, |word| DEFINITION_KEYWORDS.contains(&word));
+
+        if !is_description && is_definition {
+            return line.trim_end_matches('{').trim().to_string();
+        }
+    }
+
+    // Fall back to the whole definition if we couldn't find out what it is
+    lines.join("\n")
+}
+
+/// Splits an SDL document into its definitions, keyed by their signature.
+fn definitions(sdl: &str) -> BTreeMap<String, Vec<&str>> {
+    let mut definitions = BTreeMap::new();
+
+    for block in sdl.split("\n\n") {
+        let lines: Vec<&str> = block
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+
+        definitions.insert(signature(&lines), lines);
+    }
+
+    definitions
+}
+
+/// Returns a human-readable diff of two SDL documents.
+///
+/// Lines of added and removed definitions are prefixed with `+` and `-`. Changed definitions are
+/// introduced by their signature, followed by their added and removed lines.
+pub fn diff_sdl(old: &str, new: &str) -> String {
+    let old_definitions = definitions(old);
+    let new_definitions = definitions(new);
+
+    let signatures: BTreeSet<&String> = old_definitions
+        .keys()
+        .chain(new_definitions.keys())
+        .collect();
+
+    let mut diff = Vec::new();
+
+    for signature in signatures {
+        match (
+            old_definitions.get(signature),
+            new_definitions.get(signature),
+        ) {
+            (Some(old_lines), None) => {
+                diff.extend(old_lines.iter().map(|line| format!("- {}", line)));
+            }
+            (None, Some(new_lines)) => {
+                diff.extend(new_lines.iter().map(|line| format!("+ {}", line)));
+            }
+            (Some(old_lines), Some(new_lines)) if old_lines != new_lines => {
+                let old_set: HashSet<&str> = old_lines.iter().copied().collect();
+                let new_set: HashSet<&str> = new_lines.iter().copied().collect();
+
+                diff.push(format!("~ {}", signature));
+                diff.extend(
+                    old_lines
+                        .iter()
+                        .filter(|line| !new_set.contains(*line))
+                        .map(|line| format!("- {}", line)),
+                );
+                diff.extend(
+                    new_lines
+                        .iter()
+                        .filter(|line| !old_set.contains(*line))
+                        .map(|line| format!("+ {}", line)),
+                );
+            }
+            _ => (),
+        }
+    }
+
+    diff.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_sdl, GraphQLSdl};
+
+    const OLD_SDL: &str = "type Query {\n\thello: String!\n\tnodeInfo: NodeInfo!\n}\n\n\
+        type NodeInfo {\n\tpeerId: String\n}\n\n\
+        scalar PublicKey\n";
+
+    const NEW_SDL: &str = "type Query {\n\thello: String!\n\tnodeInfo: NodeInfo!\n\
+        \tall_venues: venues!\n}\n\n\
+        type NodeInfo {\n\tpeerId: String\n}\n\n\
+        \"\"\"\nVenues of events\n\"\"\"\ntype venues {\n\tname: String!\n}\n";
+
+    #[test]
+    fn diffs_definitions() {
+        assert_eq!(
+            diff_sdl(OLD_SDL, NEW_SDL),
+            "- scalar PublicKey\n\
+            ~ type Query\n\
+            + \tall_venues: venues!\n\
+            + \"\"\"\n\
+            + Venues of events\n\
+            + \"\"\"\n\
+            + type venues {\n\
+            + \tname: String!\n\
+            + }"
+        );
+        assert_eq!(diff_sdl(NEW_SDL, NEW_SDL), "");
+    }
+
+    #[test]
+    fn counts_changes() {
+        let sdl = GraphQLSdl::default();
+        assert_eq!(sdl.current(), (String::new(), 0));
+
+        // The first schema is not a change
+        assert_eq!(sdl.update(OLD_SDL.to_string()), None);
+        assert_eq!(sdl.current(), (OLD_SDL.to_string(), 1));
+        assert_eq!(sdl.update(OLD_SDL.to_string()), None);
+
+        let (version, diff) = sdl.update(NEW_SDL.to_string()).unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(diff, diff_sdl(OLD_SDL, NEW_SDL));
+        assert_eq!(sdl.current(), (NEW_SDL.to_string(), 2));
+    }
+}
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{
+    InputValue, Subscription, SubscriptionField, SubscriptionFieldFuture, TypeRef,
+};
+use log::debug;
+use p2panda_rs::schema::Schema;
+
+use crate::api::DocumentFilter;
+use crate::db::query::Filter;
+use crate::graphql::constants;
+use crate::graphql::resolvers::resolve_document_changes;
+use crate::graphql::utils::{filter_name, parse_filter_arguments};
+
+/// Adds a GraphQL subscription to all created and updated documents of a schema to the root
+/// subscription object.
+///
+/// The subscription follows the format `all_<SCHEMA_ID>` and yields every document of this schema
+/// when it was created or updated. It accepts the same `filter` and `meta` arguments as the
+/// collection query of this schema, then only matching documents are yielded.
+pub fn build_collection_subscription(subscription: Subscription, schema: &Schema) -> Subscription {
+    let schema_id = schema.id().clone();
+    let subscribed_schema = schema.clone();
+
+    subscription.field(
+        SubscriptionField::new(
+            format!("{}{}", constants::QUERY_ALL_PREFIX, schema_id),
+            TypeRef::named_nn(schema_id.to_string()),
+            move |ctx| {
+                let schema = subscribed_schema.clone();
+                debug!(
+                    "Subscription to {}{} received",
+                    constants::QUERY_ALL_PREFIX,
+                    schema.id()
+                );
+
+                SubscriptionFieldFuture::new(async move {
+                    // Without filter arguments every document of this schema is yielded
+                    let filter = parse_filter_arguments(&ctx, &schema)?;
+                    let filter = if filter == Filter::default() {
+                        None
+                    } else {
+                        Some(DocumentFilter::from(filter))
+                    };
+
+                    Ok(resolve_document_changes(ctx, schema, None, filter))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::FILTER_ARG,
+                TypeRef::named(filter_name(&schema_id)),
+            )
+            .description("Filter the subscription based on field values"),
+        )
+        .argument(
+            InputValue::new(
+                constants::META_FILTER_ARG,
+                TypeRef::named("MetaFilterInputObject"),
+            )
+            .description("Filter the subscription based on meta field values"),
+        )
+        .description(format!(
+            "Subscribe to all `{}` documents, they are sent every time they get created or \
+            updated.",
+            schema.id().name()
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Executor, Request};
+    use futures::{FutureExt, StreamExt};
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use tokio::sync::broadcast;
+
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::test_utils::{add_document, add_schema, test_runner, TestNode};
+
+    #[rstest]
+    fn subscribe_to_filtered_collection(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+
+            // Forward document changes to the bus, like the materializer service does
+            let (tx, _rx) = broadcast::channel(120);
+            node.context.document_events.forward_events(tx.clone());
+
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                node.context.schema_provider.clone(),
+            ))
+            .await;
+
+            let mut stream = manager.execute_stream(
+                Request::new(format!(
+                    r#"subscription {{
+                        venues: all_{}(filter: {{ name: {{ eq: "Pub" }} }}) {{
+                            fields {{ name }}
+                        }}
+                    }}"#,
+                    schema.id(),
+                )),
+                None,
+            );
+
+            // Start the subscription before any document gets created
+            assert!(stream.next().now_or_never().is_none());
+
+            for name in ["Bar", "Pub"] {
+                add_document(
+                    &mut node,
+                    schema.id(),
+                    vec![("name", OperationValue::String(name.into()))],
+                    &key_pair,
+                )
+                .await;
+            }
+
+            // Only documents matching the filter are sent
+            let response = stream.next().await.unwrap();
+            assert_eq!(
+                response.data,
+                value!({ "venues": { "fields": { "name": "Pub" } } }),
+                "{:#?}",
+                response.errors
+            );
+        });
+    }
+}
@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use async_graphql::dynamic::{
+    InputValue, ResolverContext, Subscription, SubscriptionField, SubscriptionFieldFuture, TypeRef,
+};
+use async_graphql::Error;
+use dynamic_graphql::ScalarValue;
+use log::debug;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::Schema;
+
+use crate::graphql::constants;
+use crate::graphql::resolvers::resolve_document_changes;
+use crate::graphql::scalars::DocumentIdScalar;
+
+/// Adds a GraphQL subscription to changes of a single document selected by its id to the root
+/// subscription object.
+///
+/// The subscription follows the format `<SCHEMA_ID>(id: <DOCUMENT_ID>)`. It yields the current
+/// view of the document first and then every new view it was updated to.
+pub fn build_document_subscription(subscription: Subscription, schema: &Schema) -> Subscription {
+    let schema_id = schema.id().clone();
+    let subscribed_schema = schema.clone();
+
+    subscription.field(
+        SubscriptionField::new(
+            schema_id.to_string(),
+            TypeRef::named_nn(schema_id.to_string()),
+            move |ctx| {
+                let schema = subscribed_schema.clone();
+
+                SubscriptionFieldFuture::new(async move {
+                    let document_id = parse_arguments(&ctx)?;
+                    debug!(
+                        "Subscription to {} received for document {}",
+                        schema.id(),
+                        document_id
+                    );
+
+                    Ok(resolve_document_changes(
+                        ctx,
+                        schema,
+                        Some(document_id),
+                        None,
+                    ))
+                })
+            },
+        )
+        .argument(
+            InputValue::new(
+                constants::DOCUMENT_ID_ARG,
+                TypeRef::named_nn(constants::DOCUMENT_ID),
+            )
+            .description("Specify the id of the document to subscribe to"),
+        )
+        .description(format!(
+            "Subscribe to a {} document by id, it is sent every time it gets updated.",
+            schema.name()
+        )),
+    )
+}
+
+/// Parse and validate the arguments passed into this subscription.
+fn parse_arguments(ctx: &ResolverContext) -> Result<DocumentId, Error> {
+    for (name, value) in ctx.field().arguments()?.into_iter() {
+        if name.as_str() == constants::DOCUMENT_ID_ARG {
+            let document_id = DocumentIdScalar::from_value(value)?;
+            return Ok(DocumentId::from(&document_id));
+        }
+    }
+
+    Err(Error::new("Must provide `id` argument"))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::{value, Executor, Request};
+    use futures::StreamExt;
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use tokio::sync::broadcast;
+
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::test_utils::{add_document, add_schema, test_runner, update_document, TestNode};
+
+    #[rstest]
+    fn subscribe_to_document(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Pub".into()))],
+                &key_pair,
+            )
+            .await;
+            let document_id = DocumentId::new(view_id.graph_tips().first().unwrap());
+
+            // Forward document changes to the bus, like the materializer service does
+            let (tx, _rx) = broadcast::channel(120);
+            node.context.document_events.forward_events(tx.clone());
+
+            let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                node.context.schema_provider.clone(),
+            ))
+            .await;
+
+            let mut stream = manager.execute_stream(
+                Request::new(format!(
+                    r#"subscription {{
+                        venue: {}(id: "{}") {{ fields {{ name }} }}
+                    }}"#,
+                    schema.id(),
+                    document_id
+                )),
+                None,
+            );
+
+            // The current view is sent right away
+            let response = stream.next().await.unwrap();
+            assert_eq!(
+                response.data,
+                value!({ "venue": { "fields": { "name": "Pub" } } }),
+                "{:#?}",
+                response.errors
+            );
+
+            update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Bar".into()))],
+                &view_id,
+                &key_pair,
+            )
+            .await;
+
+            let response = stream.next().await.unwrap();
+            assert_eq!(
+                response.data,
+                value!({ "venue": { "fields": { "name": "Bar" } } }),
+                "{:#?}",
+                response.errors
+            );
+        });
+    }
+}
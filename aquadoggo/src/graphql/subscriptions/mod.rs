@@ -0,0 +1,7 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+mod collection;
+mod document;
+
+pub use collection::build_collection_subscription;
+pub use document::build_document_subscription;
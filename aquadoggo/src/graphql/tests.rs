@@ -3,12 +3,17 @@
 //! Integration tests for dynamic graphql schema generation and query resolution.
 use std::convert::TryInto;
 
-use async_graphql::{value, Response};
+use async_graphql::{value, Request, Response, Value};
+use p2panda_rs::identity::KeyPair;
+use p2panda_rs::operation::{OperationValue, Relation};
 use p2panda_rs::test_utils::fixtures::random_key_pair;
 use p2panda_rs::{document::DocumentId, schema::FieldType};
 use rstest::rstest;
 use serde_json::json;
+use tokio::sync::broadcast;
 
+use crate::capabilities::{Authenticated, CapabilityProvider};
+use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData, RelationLimits};
 use crate::test_utils::{add_document, add_schema, http_test_client, test_runner, TestNode};
 
 // Test querying application documents with scalar fields (no relations) by document id and by view
@@ -213,3 +218,404 @@ fn relation_fields() {
         assert_eq!(response.data, expected_data,);
     });
 }
+
+// Test hiding access controlled documents from clients without read permission.
+#[rstest]
+fn read_access_control() {
+    test_runner(|mut node: TestNode| async move {
+        let admin = random_key_pair();
+        let reader = KeyPair::new();
+
+        let capability_schema = add_schema(
+            &mut node,
+            "capability",
+            vec![
+                ("public_key", FieldType::String),
+                ("permission", FieldType::String),
+            ],
+            &admin,
+        )
+        .await;
+        let acl_schema =
+            add_schema(&mut node, "acl", vec![("name", FieldType::String)], &admin).await;
+        let note_schema = add_schema(
+            &mut node,
+            "note",
+            vec![
+                ("title", FieldType::String),
+                ("acl", FieldType::Relation(acl_schema.id().clone())),
+            ],
+            &admin,
+        )
+        .await;
+
+        // Publish two notes, each controlled by their own ACL document
+        let mut note_ids = Vec::new();
+        let mut acl_ids = Vec::new();
+        for title in ["public", "secret"] {
+            let acl_view_id = add_document(
+                &mut node,
+                acl_schema.id(),
+                vec![("name", title.into())],
+                &admin,
+            )
+            .await;
+            let acl_id: DocumentId = acl_view_id.to_string().parse().unwrap();
+
+            let note_view_id = add_document(
+                &mut node,
+                note_schema.id(),
+                vec![
+                    ("title", title.into()),
+                    (
+                        "acl",
+                        OperationValue::Relation(Relation::new(acl_id.clone())),
+                    ),
+                ],
+                &admin,
+            )
+            .await;
+            note_ids.push(note_view_id.to_string());
+            acl_ids.push(acl_id);
+        }
+
+        // Reader is only allowed to read the first note
+        add_document(
+            &mut node,
+            capability_schema.id(),
+            vec![
+                ("public_key", reader.public_key().to_string().into()),
+                ("permission", format!("read:{}", acl_ids[0]).into()),
+            ],
+            &admin,
+        )
+        .await;
+
+        let (tx, _rx) = broadcast::channel(120);
+        let manager = GraphQLSchemaManager::new(
+            GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                node.context.schema_provider.clone(),
+            )
+            .with_capability_provider(
+                CapabilityProvider::new(
+                    Some(capability_schema.id().clone()),
+                    vec![admin.public_key()],
+                )
+                .with_read_acl_field(Some("acl".into())),
+            ),
+        )
+        .await;
+
+        let query = format!(
+            r#"{{
+                collection: all_{type_name} {{
+                    totalCount
+                    documents {{ fields {{ title }} }}
+                }},
+                public: {type_name}(id: "{public_id}") {{ fields {{ title }} }},
+                secret: {type_name}(id: "{secret_id}") {{ fields {{ title }} }},
+            }}"#,
+            type_name = note_schema.id(),
+            public_id = note_ids[0],
+            secret_id = note_ids[1],
+        );
+
+        let query_as = |query: String, key_pair: Option<&KeyPair>| {
+            let mut request = Request::new(query);
+            if let Some(key_pair) = key_pair {
+                request = request.data(Authenticated(key_pair.public_key()));
+            }
+            let manager = manager.clone();
+            async move {
+                let response = manager.execute(request).await;
+                assert!(response.is_ok(), "{:#?}", response.errors);
+                response.data
+            }
+        };
+
+        // Anonymous clients can not read any access controlled documents
+        assert_eq!(
+            query_as(query.clone(), None).await,
+            value!({
+                "collection": { "totalCount": 0, "documents": [] },
+                "public": Value::Null,
+                "secret": Value::Null,
+            })
+        );
+
+        // Reader can only read documents they were granted access to
+        assert_eq!(
+            query_as(query.clone(), Some(&reader)).await,
+            value!({
+                "collection": {
+                    "totalCount": 1,
+                    "documents": [{ "fields": { "title": "public" } }]
+                },
+                "public": { "fields": { "title": "public" } },
+                "secret": Value::Null,
+            })
+        );
+
+        // Admins can read everything
+        let data = query_as(query, Some(&admin)).await.into_json().unwrap();
+        assert_eq!(data["collection"]["totalCount"], 2);
+        assert_eq!(data["secret"]["fields"]["title"], "secret");
+
+        // Existence, annotations and changes of documents do not leak to clients without read
+        // permission either
+        let secret_id: DocumentId = note_ids[1].parse().unwrap();
+        node.context
+            .store
+            .set_document_annotation(&secret_id, "label", "classified")
+            .await
+            .unwrap();
+
+        let lookup_query = format!(
+            r#"{{
+                exists: documentExists(id: "{secret_id}")
+                viewExists: viewExists(viewId: "{secret_id}")
+                annotations(documentId: "{secret_id}") {{ key }}
+                documentChanges(first: 1000) {{ changes {{ documentId }} }}
+            }}"#,
+            secret_id = note_ids[1],
+        );
+
+        let changed_document_ids = |data: &serde_json::Value| -> Vec<String> {
+            data["documentChanges"]["changes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|change| change["documentId"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        for (key_pair, is_revealed) in [(None, false), (Some(&reader), false), (Some(&admin), true)]
+        {
+            let data = query_as(lookup_query.clone(), key_pair)
+                .await
+                .into_json()
+                .unwrap();
+            assert_eq!(data["exists"], is_revealed);
+            assert_eq!(data["viewExists"], is_revealed);
+            assert_eq!(
+                data["annotations"].as_array().unwrap().len(),
+                is_revealed as usize
+            );
+            assert_eq!(
+                changed_document_ids(&data).contains(&note_ids[1]),
+                is_revealed
+            );
+            assert_eq!(
+                changed_document_ids(&data).contains(&note_ids[0]),
+                key_pair.is_some()
+            );
+        }
+    });
+}
+
+// Test returning partial results when a query follows too many relations.
+#[rstest]
+fn relation_limits() {
+    test_runner(|mut node: TestNode| async move {
+        let key_pair = random_key_pair();
+
+        let leaf_schema = add_schema(
+            &mut node,
+            "leaf",
+            vec![("name", FieldType::String)],
+            &key_pair,
+        )
+        .await;
+        let branch_schema = add_schema(
+            &mut node,
+            "branch",
+            vec![
+                ("one", FieldType::Relation(leaf_schema.id().clone())),
+                ("many", FieldType::RelationList(leaf_schema.id().clone())),
+            ],
+            &key_pair,
+        )
+        .await;
+        let root_schema = add_schema(
+            &mut node,
+            "root",
+            vec![("branch", FieldType::Relation(branch_schema.id().clone()))],
+            &key_pair,
+        )
+        .await;
+
+        let mut leaf_ids: Vec<DocumentId> = Vec::new();
+        for name in ["a", "b", "c"] {
+            let view_id = add_document(
+                &mut node,
+                leaf_schema.id(),
+                vec![("name", name.into())],
+                &key_pair,
+            )
+            .await;
+            leaf_ids.push(view_id.to_string().parse().unwrap());
+        }
+
+        let branch_view_id = add_document(
+            &mut node,
+            branch_schema.id(),
+            vec![
+                ("one", leaf_ids[0].clone().into()),
+                ("many", leaf_ids.clone().into()),
+            ],
+            &key_pair,
+        )
+        .await;
+        let branch_id: DocumentId = branch_view_id.to_string().parse().unwrap();
+
+        let root_view_id = add_document(
+            &mut node,
+            root_schema.id(),
+            vec![("branch", branch_id.into())],
+            &key_pair,
+        )
+        .await;
+
+        let execute = |limits: RelationLimits, query: String| {
+            let store = node.context.store.clone();
+            let schema_provider = node.context.schema_provider.clone();
+            async move {
+                let (tx, _rx) = broadcast::channel(120);
+                let manager =
+                    GraphQLSchemaManager::new(GraphQLSharedData::new(store, tx, schema_provider))
+                        .await
+                        .with_relation_limits(limits);
+
+                let response = manager.execute(Request::new(query)).await;
+                assert!(response.is_ok(), "{:#?}", response.errors);
+                response
+            }
+        };
+
+        // Relations deeper than the limit resolve to null
+        let response = execute(
+            RelationLimits {
+                max_depth: 1,
+                max_documents: 0,
+            },
+            format!(
+                r#"{{
+                    result: {type_name}(viewId: "{view_id}") {{
+                        fields {{ branch {{ fields {{ one {{ fields {{ name }} }} }} }} }}
+                    }}
+                }}"#,
+                type_name = root_schema.id(),
+                view_id = root_view_id,
+            ),
+        )
+        .await;
+
+        assert_eq!(
+            response.data,
+            value!({
+                "result": { "fields": { "branch": { "fields": { "one": Value::Null } } } }
+            })
+        );
+        assert_eq!(
+            response.extensions.get("relationLimits"),
+            Some(&value!(["Relations beyond a depth of 1 were not resolved"]))
+        );
+
+        // Related documents beyond the limit are left out
+        let response = execute(
+            RelationLimits {
+                max_depth: 0,
+                max_documents: 2,
+            },
+            format!(
+                r#"{{
+                    result: {type_name}(viewId: "{view_id}") {{
+                        fields {{
+                            one {{ fields {{ name }} }}
+                            many {{ documents {{ fields {{ name }} }} }}
+                        }}
+                    }}
+                }}"#,
+                type_name = branch_schema.id(),
+                view_id = branch_view_id,
+            ),
+        )
+        .await;
+
+        let data = response.data.into_json().unwrap();
+        let fields = &data["result"]["fields"];
+        let resolved = fields["many"]["documents"].as_array().unwrap().len()
+            + usize::from(!fields["one"].is_null());
+        assert_eq!(resolved, 2);
+        assert_eq!(
+            response.extensions.get("relationLimits"),
+            Some(&value!(["Only 2 related documents were resolved"]))
+        );
+    });
+}
+
+// Test returning the SQL and query plans of queried collections when a request asks for them.
+#[rstest]
+fn explain_query_plans() {
+    test_runner(|mut node: TestNode| async move {
+        let key_pair = random_key_pair();
+
+        let schema = add_schema(
+            &mut node,
+            "venue",
+            vec![("name", FieldType::String)],
+            &key_pair,
+        )
+        .await;
+        add_document(
+            &mut node,
+            schema.id(),
+            vec![("name", "Pub".into())],
+            &key_pair,
+        )
+        .await;
+
+        let (tx, _rx) = broadcast::channel(120);
+        let manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+            node.context.store.clone(),
+            tx,
+            node.context.schema_provider.clone(),
+        ))
+        .await;
+
+        let query = format!(
+            r#"{{
+                venues: all_{type_name}(filter: {{ name: {{ eq: "Pub" }} }}) {{
+                    totalCount
+                }}
+            }}"#,
+            type_name = schema.id(),
+        );
+
+        // Untagged requests don't return query plans
+        let response = manager.execute(Request::new(query.clone())).await;
+        assert!(response.is_ok(), "{:#?}", response.errors);
+        assert!(response.extensions.get("queryPlans").is_none());
+
+        let mut request = Request::new(query);
+        request
+            .extensions
+            .insert("explain".to_string(), Value::Boolean(true));
+        let response = manager.execute(request).await;
+        assert!(response.is_ok(), "{:#?}", response.errors);
+
+        let plans = response
+            .extensions
+            .get("queryPlans")
+            .cloned()
+            .unwrap()
+            .into_json()
+            .unwrap();
+        let plans = plans.as_array().unwrap();
+        assert_eq!(plans.len(), 1);
+        assert!(plans[0]["sql"].as_str().unwrap().contains("SELECT"));
+        assert!(!plans[0]["plan"].as_array().unwrap().is_empty());
+    });
+}
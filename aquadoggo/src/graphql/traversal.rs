@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Limits on how far and how wide a single GraphQL query follows relations between documents.
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_graphql::Value;
+
+/// Key of the response extension listing warnings about relations which were not resolved.
+pub const RELATION_LIMITS_EXTENSION: &str = "relationLimits";
+
+/// Limits applied to relations followed during one GraphQL query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RelationLimits {
+    /// Maximum number of relations followed from a document queried at the root, unlimited when
+    /// zero.
+    pub max_depth: usize,
+
+    /// Maximum number of related documents resolved in total, unlimited when zero.
+    pub max_documents: usize,
+}
+
+/// Keeps track of relations followed while executing a single GraphQL query.
+///
+/// When a limit is reached the affected relations resolve to `null` or to a truncated list of
+/// documents, the rest of the query is still executed. The response informs about this with a
+/// warning in its extensions.
+#[derive(Clone, Debug, Default)]
+pub struct RelationTraversal {
+    limits: RelationLimits,
+
+    /// Number of related documents resolved so far.
+    resolved: Arc<AtomicUsize>,
+
+    depth_exceeded: Arc<AtomicBool>,
+
+    documents_exceeded: Arc<AtomicBool>,
+}
+
+impl RelationTraversal {
+    pub fn new(limits: RelationLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    /// Returns true if a relation can be followed to the given depth.
+    pub fn allows_depth(&self, depth: usize) -> bool {
+        if self.limits.max_depth == 0 || depth <= self.limits.max_depth {
+            return true;
+        }
+
+        self.depth_exceeded.store(true, Ordering::Relaxed);
+        false
+    }
+
+    /// Takes up to `count` related documents from the remaining budget and returns the number of
+    /// documents which can be resolved.
+    pub fn take(&self, count: usize) -> usize {
+        if self.limits.max_documents == 0 {
+            return count;
+        }
+
+        let previous = self
+            .resolved
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |resolved| {
+                Some((resolved + count).min(self.limits.max_documents))
+            })
+            .expect("Update closure always returns a value");
+        let taken = self
+            .limits
+            .max_documents
+            .saturating_sub(previous)
+            .min(count);
+
+        if taken < count {
+            self.documents_exceeded.store(true, Ordering::Relaxed);
+        }
+
+        taken
+    }
+
+    /// Returns a warning for the response extensions if any limit was reached.
+    pub fn warning(&self) -> Option<Value> {
+        let mut messages = Vec::new();
+
+        if self.depth_exceeded.load(Ordering::Relaxed) {
+            messages.push(Value::from(format!(
+                "Relations beyond a depth of {} were not resolved",
+                self.limits.max_depth
+            )));
+        }
+
+        if self.documents_exceeded.load(Ordering::Relaxed) {
+            messages.push(Value::from(format!(
+                "Only {} related documents were resolved",
+                self.limits.max_documents
+            )));
+        }
+
+        if messages.is_empty() {
+            None
+        } else {
+            Some(Value::List(messages))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql::Value;
+
+    use super::{RelationLimits, RelationTraversal};
+
+    #[test]
+    fn unlimited_by_default() {
+        let traversal = RelationTraversal::default();
+        assert!(traversal.allows_depth(100));
+        assert_eq!(traversal.take(1000), 1000);
+        assert!(traversal.warning().is_none());
+    }
+
+    #[test]
+    fn limits_depth_and_documents() {
+        let traversal = RelationTraversal::new(RelationLimits {
+            max_depth: 2,
+            max_documents: 5,
+        });
+
+        assert!(traversal.allows_depth(2));
+        assert_eq!(traversal.take(3), 3);
+        assert!(traversal.warning().is_none());
+
+        assert!(!traversal.allows_depth(3));
+        assert_eq!(traversal.take(3), 2);
+        assert_eq!(traversal.take(1), 0);
+
+        assert_eq!(
+            traversal.warning(),
+            Some(Value::List(vec![
+                Value::from("Relations beyond a depth of 2 were not resolved"),
+                Value::from("Only 5 related documents were resolved"),
+            ]))
+        );
+    }
+}
@@ -12,13 +12,15 @@ use p2panda_rs::storage_provider::error::DocumentStorageError;
 use p2panda_rs::storage_provider::traits::DocumentStore;
 
 use crate::db::query::{
-    Direction, Field, Filter, MetaField, Order, Pagination, PaginationField, Select,
+    Direction, Field, Filter, LowerBound, MetaField, Order, Pagination, PaginationField,
+    RelatedField, Select, UpperBound,
 };
 use crate::db::stores::{PaginationCursor, Query, RelationList};
 use crate::db::types::StorageDocument;
 use crate::db::SqlStore;
 use crate::graphql::constants;
 use crate::graphql::scalars::{CursorScalar, DocumentIdScalar, DocumentViewIdScalar};
+use crate::schema::{datetime_value, parse_datetime, parse_decimal, SchemaProvider};
 
 // Type name suffixes.
 const DOCUMENT_FIELDS_SUFFIX: &str = "Fields";
@@ -26,6 +28,16 @@ const FILTER_INPUT_SUFFIX: &str = "Filter";
 const ORDER_BY_SUFFIX: &str = "OrderBy";
 const COLLECTION_ITEM_SUFFIX: &str = "Item";
 const COLLECTION_SUFFIX: &str = "Collection";
+const LOOKUP_SUFFIX: &str = "Lookup";
+
+/// Separates the name of a relation field and the name of a field of the related document in
+/// ordering enum values, for example `author__name`.
+const RELATED_FIELD_SEPARATOR: &str = "__";
+
+/// Formats the ordering enum value of a field of documents related in a relation field.
+pub fn related_order_name(relation_field: &str, related_field: &str) -> String {
+    format!("{relation_field}{RELATED_FIELD_SEPARATOR}{related_field}")
+}
 
 /// Formats the name of a document collection type.
 pub fn collection_name(schema_id: &SchemaId) -> String {
@@ -37,6 +49,11 @@ pub fn collection_item_name(schema_id: &SchemaId) -> String {
     format!("{}{COLLECTION_ITEM_SUFFIX}", schema_id)
 }
 
+/// Formats the name of a document lookup type.
+pub fn lookup_name(schema_id: &SchemaId) -> String {
+    format!("{}{LOOKUP_SUFFIX}", schema_id)
+}
+
 /// Formats the name of a document fields type.
 pub fn fields_name(schema_id: &SchemaId) -> String {
     format!("{}{DOCUMENT_FIELDS_SUFFIX}", schema_id)
@@ -115,7 +132,7 @@ pub fn filter_to_operation_value(
 }
 
 /// Parse all argument values based on expected keys and types.
-pub fn parse_collection_arguments(
+pub async fn parse_collection_arguments(
     ctx: &ResolverContext,
     schema: &Schema,
     list: &Option<RelationList>,
@@ -133,12 +150,30 @@ pub fn parse_collection_arguments(
             constants::PAGINATION_FIRST_ARG => {
                 pagination.first = NonZeroU64::try_from(value.u64()?)?;
             }
+            constants::SNAPSHOT_ARG => {
+                if value.boolean()? {
+                    let store = ctx.data_unchecked::<SqlStore>();
+                    pagination.snapshot = Some(
+                        store
+                            .acquire_snapshot()
+                            .ok_or_else(|| Error::new("Snapshots are disabled on this node"))?,
+                    );
+                }
+            }
             constants::ORDER_BY_ARG => {
                 let order_by = match value.enum_name()? {
                     "OWNER" => Field::Meta(MetaField::Owner),
                     "DOCUMENT_ID" => Field::Meta(MetaField::DocumentId),
                     "DOCUMENT_VIEW_ID" => Field::Meta(MetaField::DocumentViewId),
-                    field_name => Field::new(field_name),
+                    field_name if schema.fields().contains_key(field_name) => {
+                        Field::new(field_name)
+                    }
+                    name => {
+                        let (relation_field, related_field) =
+                            parse_related_order(ctx, schema, name).await?;
+                        order.related_field = Some(related_field);
+                        Field::new(relation_field)
+                    }
                 };
                 order.field = Some(order_by);
             }
@@ -160,12 +195,25 @@ pub fn parse_collection_arguments(
                 let filter_object = value
                     .object()
                     .map_err(|_| Error::new("internal: is not an object"))?;
-                parse_filter(&mut filter, schema, &filter_object)?;
+                let schema_provider = ctx.data_unchecked::<SchemaProvider>();
+                parse_filter(&mut filter, schema, schema_provider, &filter_object)?;
             }
             _ => panic!("Unknown argument key received"),
         }
     }
 
+    // Cursors of snapshots continue paginating over the same snapshot
+    if let Some(snapshot) = pagination.after.as_ref().and_then(|cursor| cursor.snapshot) {
+        let store = ctx.data_unchecked::<SqlStore>();
+        if !store.is_valid_snapshot(snapshot) {
+            return Err(Error::new(
+                "Snapshot of cursor expired, start paginating from the first page again",
+            ));
+        }
+
+        pagination.snapshot = Some(snapshot);
+    }
+
     // Parse selected fields in GraphQL query
     let (pagination_fields, fields) = look_ahead_selected_fields(ctx);
     let select = Select::new(fields.as_slice());
@@ -182,23 +230,102 @@ pub fn parse_collection_arguments(
     Ok(query)
 }
 
+/// Parse only the filter arguments of a collection, in the same way as `parse_collection_arguments`.
+///
+/// Subscriptions to collections accept the same filters as collection queries.
+pub fn parse_filter_arguments(ctx: &ResolverContext, schema: &Schema) -> Result<Filter, Error> {
+    let mut filter = Filter::default();
+
+    if let Some(value) = ctx.args.get(constants::META_FILTER_ARG) {
+        let filter_object = value
+            .object()
+            .map_err(|_| Error::new("internal: is not an object"))?;
+        parse_meta_filter(&mut filter, &filter_object)?;
+    }
+
+    if let Some(value) = ctx.args.get(constants::FILTER_ARG) {
+        let filter_object = value
+            .object()
+            .map_err(|_| Error::new("internal: is not an object"))?;
+        let schema_provider = ctx.data_unchecked::<SchemaProvider>();
+        parse_filter(&mut filter, schema, schema_provider, &filter_object)?;
+    }
+
+    Ok(filter)
+}
+
+/// Parse an ordering enum value referring to a field of documents related in a relation field.
+///
+/// Returns the name of the relation field and the field of the related documents.
+async fn parse_related_order<'a>(
+    ctx: &ResolverContext<'_>,
+    schema: &Schema,
+    name: &'a str,
+) -> Result<(&'a str, RelatedField), Error> {
+    let unknown_field = || Error::new(format!("Can't order by unknown field '{name}'"));
+
+    let (relation_field, related_field) = name
+        .split_once(RELATED_FIELD_SEPARATOR)
+        .ok_or_else(unknown_field)?;
+
+    let related_schema_id = match schema.fields().get(relation_field) {
+        Some(FieldType::Relation(schema_id)) | Some(FieldType::PinnedRelation(schema_id)) => {
+            schema_id
+        }
+        _ => return Err(unknown_field()),
+    };
+
+    let schema_provider = ctx.data_unchecked::<SchemaProvider>();
+    let related_field_type = schema_provider
+        .get(related_schema_id)
+        .await
+        .and_then(|related_schema| related_schema.fields().get(related_field).cloned())
+        .ok_or_else(unknown_field)?;
+
+    Ok((
+        relation_field,
+        RelatedField::new(related_field, &related_field_type),
+    ))
+}
+
 /// Parse a filter object received from the graphql api into an abstract filter type based on the
 /// schema of the documents being queried.
 fn parse_filter(
     filter: &mut Filter,
     schema: &Schema,
+    schema_provider: &SchemaProvider,
     filter_object: &ObjectAccessor,
 ) -> Result<(), Error> {
     for (field, filters) in filter_object.iter() {
         let filter_field = Field::new(field.as_str());
         let filters = filters.object()?;
+        let field_type = schema.fields().get(field.as_str()).unwrap();
+
+        // Decimals are given as strings and compared as scaled integers, dates and times are
+        // given as strings or timestamps and compared in the representation of their field
+        let decimal_scale = schema_provider.decimal_scale(schema, field.as_str());
+        let is_datetime = schema_provider.is_datetime_field(schema, field.as_str());
+        let to_operation_value = |value: &ValueAccessor| -> Result<OperationValue, Error> {
+            if is_datetime {
+                let timestamp = match value.string() {
+                    Ok(value) => parse_datetime(value)?,
+                    Err(_) => value.i64()?,
+                };
+                return Ok(datetime_value(timestamp, field_type)?);
+            }
+
+            match decimal_scale {
+                Some(scale) => Ok(parse_decimal(value.string()?, scale)?.into()),
+                None => filter_to_operation_value(value, field_type),
+            }
+        };
+
         for (name, value) in filters.iter() {
-            let field_type = schema.fields().get(field.as_str()).unwrap();
             match name.as_str() {
                 "in" => {
                     let mut list_items: Vec<OperationValue> = vec![];
                     for value in value.list()?.iter() {
-                        let item = filter_to_operation_value(&value, field_type)?;
+                        let item = to_operation_value(&value)?;
                         list_items.push(item);
                     }
                     filter.add_in(&filter_field, &list_items);
@@ -206,33 +333,33 @@ fn parse_filter(
                 "notIn" => {
                     let mut list_items: Vec<OperationValue> = vec![];
                     for value in value.list()?.iter() {
-                        let item = filter_to_operation_value(&value, field_type)?;
+                        let item = to_operation_value(&value)?;
                         list_items.push(item);
                     }
                     filter.add_not_in(&filter_field, &list_items);
                 }
                 "eq" => {
-                    let value = filter_to_operation_value(&value, field_type)?;
+                    let value = to_operation_value(&value)?;
                     filter.add(&filter_field, &value);
                 }
                 "notEq" => {
-                    let value = filter_to_operation_value(&value, field_type)?;
+                    let value = to_operation_value(&value)?;
                     filter.add_not(&filter_field, &value);
                 }
                 "gt" => {
-                    let value = filter_to_operation_value(&value, field_type)?;
+                    let value = to_operation_value(&value)?;
                     filter.add_gt(&filter_field, &value);
                 }
                 "gte" => {
-                    let value = filter_to_operation_value(&value, field_type)?;
+                    let value = to_operation_value(&value)?;
                     filter.add_gte(&filter_field, &value);
                 }
                 "lt" => {
-                    let value = filter_to_operation_value(&value, field_type)?;
+                    let value = to_operation_value(&value)?;
                     filter.add_lt(&filter_field, &value);
                 }
                 "lte" => {
-                    let value = filter_to_operation_value(&value, field_type)?;
+                    let value = to_operation_value(&value)?;
                     filter.add_lte(&filter_field, &value);
                 }
                 "contains" => {
@@ -241,6 +368,46 @@ fn parse_filter(
                 "notContains" => {
                     filter.add_not_contains(&filter_field, value.string()?);
                 }
+                "countEq" => {
+                    let count = OperationValue::Integer(value.i64()?);
+                    filter.add_count(
+                        &filter_field,
+                        LowerBound::GreaterEqual(count.clone()),
+                        UpperBound::LowerEqual(count),
+                    );
+                }
+                "countGt" => {
+                    let count = OperationValue::Integer(value.i64()?);
+                    filter.add_count(
+                        &filter_field,
+                        LowerBound::Greater(count),
+                        UpperBound::Unbounded,
+                    );
+                }
+                "countGte" => {
+                    let count = OperationValue::Integer(value.i64()?);
+                    filter.add_count(
+                        &filter_field,
+                        LowerBound::GreaterEqual(count),
+                        UpperBound::Unbounded,
+                    );
+                }
+                "countLt" => {
+                    let count = OperationValue::Integer(value.i64()?);
+                    filter.add_count(
+                        &filter_field,
+                        LowerBound::Unbounded,
+                        UpperBound::Lower(count),
+                    );
+                }
+                "countLte" => {
+                    let count = OperationValue::Integer(value.i64()?);
+                    filter.add_count(
+                        &filter_field,
+                        LowerBound::Unbounded,
+                        UpperBound::LowerEqual(count),
+                    );
+                }
                 _ => panic!("Unknown filter type received"),
             }
         }
@@ -301,10 +468,17 @@ pub async fn get_document_from_params(
     match (document_id, document_view_id) {
         (None, Some(document_view_id)) => {
             store
-                .get_document_by_view_id(&DocumentViewId::from(document_view_id.to_owned()))
+                .get_cached_document_by_view_id(&DocumentViewId::from(document_view_id.to_owned()))
+                .await
+        }
+        (Some(document_id), None) => {
+            // Follow redirect when the document was merged into another one
+            let document_id = store
+                .resolve_document_redirect(&DocumentId::from(document_id))
                 .await
+                .map_err(|err| DocumentStorageError::FatalStorageError(err.to_string()))?;
+            store.get_document(&document_id).await
         }
-        (Some(document_id), None) => store.get_document(&DocumentId::from(document_id)).await,
         _ => panic!("Invalid values passed from query field parent"),
     }
 }
@@ -357,6 +531,12 @@ pub fn with_collection_arguments(
             InputValue::new(constants::PAGINATION_AFTER_ARG, TypeRef::named("Cursor"))
                 .description("The item we wish to start paginating from identified by a cursor"),
         )
+        .argument(
+            InputValue::new(constants::SNAPSHOT_ARG, TypeRef::named(TypeRef::BOOLEAN)).description(
+                "Paginate over the collection as it is now, cursors of the results show \
+                following pages at the same point in time even when documents change",
+            ),
+        )
         .description(format!(
             "Get all {} documents with pagination, ordering and filtering.",
             schema_id
@@ -1,40 +1,521 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
-use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use async_graphql::parser::parse_query;
+use async_graphql::parser::types::OperationType;
+use async_graphql::{Data, Executor, Request, ServerError};
+use async_graphql_axum::{
+    GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket, ALL_WEBSOCKET_PROTOCOLS,
+};
+use async_trait::async_trait;
 use axum::body::StreamBody;
-use axum::extract::{Extension, Path};
-use axum::headers::{ETag, IfNoneMatch};
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::{Extension, Path, Query};
+use axum::headers::authorization::Bearer;
+use axum::headers::{Authorization, ETag, IfNoneMatch};
 use axum::http::StatusCode;
 use axum::response::{self, IntoResponse, Response};
-use axum::TypedHeader;
-use http::header;
-use log::warn;
+use axum::{Json, TypedHeader};
+use futures::stream::{self, BoxStream};
+use futures::{Stream, StreamExt};
+use http::header::{self, HeaderName};
+use log::{debug, warn};
+use p2panda_rs::api;
 use p2panda_rs::document::traits::AsDocument;
 use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::identity::PublicKey;
 use p2panda_rs::schema::SchemaId;
 use p2panda_rs::storage_provider::traits::DocumentStore;
 use p2panda_rs::Human;
-use tokio::fs::File;
+use serde::{Deserialize, Serialize};
 use tokio_util::io::ReaderStream;
 
+use crate::blobs::BlobStore;
+use crate::capabilities::{AuthToken, AuthTokenError, Authenticated};
+use crate::cluster::ClusterState;
+use crate::db::models::DocumentChangeRow;
+use crate::graphql::GraphQLSchemaManager;
 use crate::http::context::HttpServiceContext;
+use crate::http::proxy::BlobProxy;
+use crate::replication::now;
+use crate::startup::StartupPhase;
 
-/// Handle GraphQL playground requests at the given path.
-pub async fn handle_graphql_playground(path: &str) -> impl IntoResponse {
-    response::Html(playground_source(GraphQLPlaygroundConfig::new(path)))
+/// Handle GraphQL playground requests at the given path, subscriptions are sent to the given
+/// WebSocket path.
+pub async fn handle_graphql_playground(path: &str, subscription_path: &str) -> impl IntoResponse {
+    response::Html(playground_source(
+        GraphQLPlaygroundConfig::new(path).subscription_endpoint(subscription_path),
+    ))
 }
 
 /// Handle GraphQL requests.
+///
+/// Clients can authenticate with an auth token passed via the "Authorization: Bearer <token>"
+/// header to read access controlled documents.
+///
+/// Mutations are rejected when this node is part of a cluster and not its leader.
 pub async fn handle_graphql_query(
     Extension(context): Extension<HttpServiceContext>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
-    context.schema.execute(req.into_inner()).await.into()
+    let mut request = req.into_inner();
+
+    if !context.cluster.is_leader() && is_mutation(&request) {
+        let error = ServerError::new("Only the leader of the cluster accepts mutations", None);
+        return async_graphql::Response::from_errors(vec![error]).into();
+    }
+
+    if let Some(TypedHeader(Authorization(bearer))) = authorization {
+        match authenticate(&context.identity, bearer.token()) {
+            Ok(authenticated) => request = request.data(authenticated),
+            Err(err) => {
+                let error = ServerError::new(err.to_string(), None);
+                return async_graphql::Response::from_errors(vec![error]).into();
+            }
+        }
+    }
+
+    context.schema.execute(request).await.into()
+}
+
+/// Handle GraphQL requests over WebSocket connections, following the `graphql-ws` or
+/// `graphql-transport-ws` protocol.
+///
+/// Clients subscribe to changes of documents this way. They can authenticate by passing an auth
+/// token in the "Authorization" field of the connection init payload, for example
+/// `{ "Authorization": "Bearer <token>" }`.
+///
+/// Mutations are rejected when this node is part of a cluster and not its leader.
+///
+/// Every connection runs a limited number of subscriptions at the same time and is closed when
+/// it stayed idle without any active subscription for too long.
+pub async fn handle_graphql_subscription(
+    Extension(context): Extension<HttpServiceContext>,
+    protocol: GraphQLProtocol,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let limits = context.websocket_limits;
+    let identity = context.identity.clone();
+    let executor = WebSocketExecutor {
+        schema: context.schema.clone(),
+        cluster: context.cluster.clone(),
+        subscriptions: Arc::new(AtomicUsize::new(0)),
+        max_subscriptions: limits.max_subscriptions,
+    };
+
+    upgrade
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| {
+            let (sink, stream) = socket.split();
+            let stream = match limits.idle_timeout {
+                0 => stream.boxed(),
+                idle_timeout => close_when_idle(
+                    stream,
+                    executor.subscriptions.clone(),
+                    Duration::from_secs(idle_timeout),
+                )
+                .boxed(),
+            };
+
+            GraphQLWebSocket::new_with_pair(sink, stream, executor, protocol)
+                .on_connection_init(move |payload| authenticate_connection(identity, payload))
+                .serve()
+        })
+}
+
+/// Ends the stream of incoming messages of a WebSocket connection when it was idle for the given
+/// duration, which closes the connection.
+///
+/// Connections are idle when no message was received and no subscription is active.
+fn close_when_idle<S>(
+    incoming: S,
+    subscriptions: Arc<AtomicUsize>,
+    idle_timeout: Duration,
+) -> impl Stream<Item = S::Item>
+where
+    S: Stream + Unpin,
+{
+    stream::unfold(incoming, move |mut incoming| {
+        let subscriptions = subscriptions.clone();
+
+        async move {
+            loop {
+                match tokio::time::timeout(idle_timeout, incoming.next()).await {
+                    Ok(Some(message)) => return Some((message, incoming)),
+                    Ok(None) => return None,
+                    Err(_) if subscriptions.load(Ordering::SeqCst) == 0 => {
+                        debug!("Close idle GraphQL WebSocket connection");
+                        return None;
+                    }
+                    Err(_) => continue,
+                }
+            }
+        }
+    })
+}
+
+/// Verify the auth token passed in the connection init payload of a WebSocket connection, if any.
+async fn authenticate_connection(
+    identity: String,
+    payload: serde_json::Value,
+) -> async_graphql::Result<Data> {
+    let mut data = Data::default();
+
+    if let Some(authorization) = payload
+        .get("Authorization")
+        .and_then(|value| value.as_str())
+    {
+        let token = authorization
+            .strip_prefix("Bearer ")
+            .unwrap_or(authorization);
+        data.insert(authenticate(&identity, token)?);
+    }
+
+    Ok(data)
+}
+
+/// Executes GraphQL requests received over WebSocket connections.
+///
+/// Every connection has its own executor, counting the subscriptions of this connection.
+#[derive(Clone)]
+struct WebSocketExecutor {
+    schema: GraphQLSchemaManager,
+    cluster: ClusterState,
+
+    /// Number of currently active subscriptions of this connection.
+    subscriptions: Arc<AtomicUsize>,
+
+    /// Maximum number of active subscriptions, 0 disables the limit.
+    max_subscriptions: usize,
+}
+
+impl WebSocketExecutor {
+    /// Returns an error response if the request is a mutation this node does not accept.
+    fn reject(&self, request: &Request) -> Option<async_graphql::Response> {
+        if !self.cluster.is_leader() && is_mutation(request) {
+            let error = ServerError::new("Only the leader of the cluster accepts mutations", None);
+            return Some(async_graphql::Response::from_errors(vec![error]));
+        }
+
+        None
+    }
+}
+
+#[async_trait]
+impl Executor for WebSocketExecutor {
+    async fn execute(&self, request: Request) -> async_graphql::Response {
+        match self.reject(&request) {
+            Some(response) => response,
+            None => self.schema.execute(request).await,
+        }
+    }
+
+    fn execute_stream(
+        &self,
+        request: Request,
+        session_data: Option<Arc<Data>>,
+    ) -> BoxStream<'static, async_graphql::Response> {
+        if let Some(response) = self.reject(&request) {
+            return stream::once(async { response }).boxed();
+        }
+
+        let guard = SubscriptionGuard::new(self.subscriptions.clone());
+        if self.max_subscriptions > 0 && guard.count() > self.max_subscriptions {
+            let error = ServerError::new(
+                format!(
+                    "Reached limit of {} subscriptions on this connection",
+                    self.max_subscriptions
+                ),
+                None,
+            );
+            let response = async_graphql::Response::from_errors(vec![error]);
+            return stream::once(async { response }).boxed();
+        }
+
+        // Count the subscription as active until its stream gets dropped
+        self.schema
+            .execute_stream(request, session_data)
+            .map(move |response| {
+                let _ = &guard;
+                response
+            })
+            .boxed()
+    }
+}
+
+/// Counts a subscription of a WebSocket connection as active for as long as it exists.
+struct SubscriptionGuard(Arc<AtomicUsize>);
+
+impl SubscriptionGuard {
+    fn new(subscriptions: Arc<AtomicUsize>) -> Self {
+        subscriptions.fetch_add(1, Ordering::SeqCst);
+        Self(subscriptions)
+    }
+
+    /// Returns the number of active subscriptions, including this one.
+    fn count(&self) -> usize {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns true if the operation executed by this request is a mutation.
+///
+/// Invalid queries are not considered here, they fail during execution.
+fn is_mutation(request: &Request) -> bool {
+    let document = match parse_query(&request.query) {
+        Ok(document) => document,
+        Err(_) => return false,
+    };
+
+    document
+        .operations
+        .iter()
+        .filter(|(name, _)| match &request.operation_name {
+            Some(operation_name) => name.map(|name| name.as_str()) == Some(operation_name),
+            None => true,
+        })
+        .any(|(_, operation)| operation.node.ty == OperationType::Mutation)
+}
+
+/// Verify auth token issued for the given identity of this node and return the authenticated
+/// public key.
+fn authenticate(identity: &str, token: &str) -> Result<Authenticated, AuthTokenError> {
+    AuthToken::from_str(token)?.verify(identity, now())
+}
+
+/// Query parameters of requests for the arguments of the next entry.
+#[derive(Debug, Deserialize)]
+pub struct NextArgsParams {
+    /// Public key of the author.
+    public_key: String,
+
+    /// Optional view of an existing document the next entry is about to update.
+    view_id: Option<String>,
+}
+
+/// Arguments required to sign and encode the next entry, with the same fields as the
+/// `nextArgs` GraphQL query.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NextArgsResponse {
+    log_id: String,
+    seq_num: String,
+    backlink: Option<String>,
+    skiplink: Option<String>,
+}
+
+/// Handle requests for the arguments of the next entry of an author, returned as plain JSON.
+///
+/// This serves clients which can't use GraphQL, for example on microcontrollers, with the same
+/// data as the `nextArgs` GraphQL query.
+pub async fn handle_next_args(
+    Extension(context): Extension<HttpServiceContext>,
+    Query(params): Query<NextArgsParams>,
+) -> Result<Json<NextArgsResponse>, ApiHttpError> {
+    let public_key = PublicKey::from_str(&params.public_key)
+        .map_err(|err| ApiHttpError::InvalidFormat(err.into()))?;
+    let view_id = params
+        .view_id
+        .map(|view_id| DocumentViewId::from_str(&view_id))
+        .transpose()
+        .map_err(|err| ApiHttpError::InvalidFormat(err.into()))?;
+
+    let (backlink, skiplink, seq_num, log_id) =
+        api::next_args(&context.store, &public_key, view_id.as_ref())
+            .await
+            .map_err(|err| ApiHttpError::InvalidRequest(err.into()))?;
+
+    Ok(Json(NextArgsResponse {
+        log_id: log_id.as_u64().to_string(),
+        seq_num: seq_num.as_u64().to_string(),
+        backlink: backlink.map(|hash| hash.to_string()),
+        skiplink: skiplink.map(|hash| hash.to_string()),
+    }))
+}
+
+/// Number of document changes returned when not specified otherwise.
+const DEFAULT_DOCUMENT_CHANGES: u64 = 100;
+
+/// Maximum number of document changes returned by one request.
+const MAX_DOCUMENT_CHANGES: u64 = 1000;
+
+/// Query parameters of requests for the feed of document changes.
+#[derive(Debug, Deserialize)]
+pub struct DocumentChangesParams {
+    /// Token of the last change the client processed, the feed starts at the beginning if not
+    /// set.
+    since: Option<String>,
+
+    /// Maximum number of returned changes.
+    limit: Option<u64>,
+}
+
+/// Document which got created, updated or deleted, as part of a `DocumentChangesResponse`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentChangeResponse {
+    token: String,
+    change: String,
+    schema_id: String,
+    document_id: String,
+    view_id: String,
+    changed_at: i64,
+}
+
+impl From<DocumentChangeRow> for DocumentChangeResponse {
+    fn from(row: DocumentChangeRow) -> Self {
+        Self {
+            token: row.sequence.to_string(),
+            change: row.change_type,
+            schema_id: row.schema_id,
+            document_id: row.document_id,
+            view_id: row.document_view_id,
+            changed_at: row.changed_at,
+        }
+    }
+}
+
+/// Page of the feed of document changes, with the token to request the next page with.
+#[derive(Debug, Serialize)]
+pub struct DocumentChangesResponse {
+    changes: Vec<DocumentChangeResponse>,
+    next: String,
+}
+
+/// Handle requests for the feed of document changes, returned as plain JSON.
+///
+/// Changes are ordered by their token. External indexers consume the feed by persisting the
+/// `next` token together with the processed changes and passing it as `since` parameter in the
+/// following request, this way every change is processed exactly once, also across restarts of
+/// the node or the indexer.
+///
+/// Changes of documents the client is not allowed to read are skipped, clients can authenticate
+/// with an auth token in the "Authorization" header, like for GraphQL queries.
+pub async fn handle_document_changes(
+    Extension(context): Extension<HttpServiceContext>,
+    authorization: Option<TypedHeader<Authorization<Bearer>>>,
+    Query(params): Query<DocumentChangesParams>,
+) -> Result<Json<DocumentChangesResponse>, ApiHttpError> {
+    let reader = match authorization {
+        Some(TypedHeader(Authorization(bearer))) => Some(
+            authenticate(&context.identity, bearer.token())
+                .map_err(|err| ApiHttpError::Unauthorized(err.into()))?
+                .0,
+        ),
+        None => None,
+    };
+
+    let since = match params.since {
+        Some(since) => u64::from_str(&since)
+            .map_err(|_| ApiHttpError::InvalidFormat(anyhow!("Invalid change token '{since}'")))?,
+        None => 0,
+    };
+
+    let limit = params.limit.unwrap_or(DEFAULT_DOCUMENT_CHANGES);
+    if limit == 0 || limit > MAX_DOCUMENT_CHANGES {
+        return Err(ApiHttpError::InvalidRequest(anyhow!(
+            "Number of changes needs to be between 1 and {}",
+            MAX_DOCUMENT_CHANGES
+        )));
+    }
+
+    let rows = context
+        .store
+        .get_document_changes(since, limit)
+        .await
+        .map_err(|err| ApiHttpError::InternalError(err.into()))?;
+
+    // Continue after the last recorded change, also when it was hidden
+    let next = rows
+        .last()
+        .map(|row| row.sequence.to_string())
+        .unwrap_or_else(|| since.to_string());
+
+    // Hide changes of documents the client is not allowed to read
+    let read_access = context.schema.read_access(reader.as_ref());
+    let mut changes = Vec::new();
+    for row in rows {
+        let schema_id = SchemaId::from_str(&row.schema_id)
+            .map_err(|err| ApiHttpError::InternalError(err.into()))?;
+        let document_id = DocumentId::from_str(&row.document_id)
+            .map_err(|err| ApiHttpError::InternalError(err.into()))?;
+        if read_access
+            .reveals_document_of_schema(&schema_id, &document_id)
+            .await
+            .map_err(|err| ApiHttpError::InternalError(anyhow!(err.message)))?
+        {
+            changes.push(DocumentChangeResponse::from(row));
+        }
+    }
+
+    Ok(Json(DocumentChangesResponse { changes, next }))
+}
+
+/// Startup phase which completed, as part of a `ReadinessResponse`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedPhase {
+    phase: StartupPhase,
+    duration_ms: u64,
+}
+
+/// Startup progress of the node reported by the readiness probe.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessResponse {
+    ready: bool,
+    phase: StartupPhase,
+    phase_elapsed_ms: u64,
+    completed: Vec<CompletedPhase>,
+}
+
+/// Handle liveness probes, the node is alive as long as it serves HTTP requests.
+pub async fn handle_liveness() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Handle readiness probes.
+///
+/// Responds with "503 Service Unavailable" and the current startup phase until the node started
+/// completely, this allows telling slow startups, for example during long migrations, apart from
+/// hanging ones.
+pub async fn handle_readiness(Extension(context): Extension<HttpServiceContext>) -> Response {
+    let status = context.readiness.status();
+
+    let status_code = if status.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let response = ReadinessResponse {
+        ready: status.is_ready(),
+        phase: status.phase,
+        phase_elapsed_ms: status.phase_started_at.elapsed().as_millis() as u64,
+        completed: status
+            .completed
+            .iter()
+            .map(|(phase, duration)| CompletedPhase {
+                phase: *phase,
+                duration_ms: duration.as_millis() as u64,
+            })
+            .collect(),
+    };
+
+    (status_code, Json(response)).into_response()
 }
 
 /// Handle requests for a blob document served via HTTP.
@@ -52,15 +533,34 @@ pub async fn handle_blob_document(
         .store
         .get_document(&document_id)
         .await
-        .map_err(|err| BlobHttpError::InternalError(err.into()))?
-        .ok_or_else(|| BlobHttpError::NotFound)?;
+        .map_err(|err| BlobHttpError::InternalError(err.into()))?;
 
     // Requested document is not a blob, treat this as a "not found" error
-    if document.schema_id() != &SchemaId::Blob(1) {
-        return Err(BlobHttpError::NotFound);
+    if let Some(document) = &document {
+        if document.schema_id() != &SchemaId::Blob(1) {
+            return Err(BlobHttpError::NotFound);
+        }
+    }
+
+    // Fetch blobs we don't know about or which are not completely materialized yet from peers
+    if let Some(blob_proxy) = &context.blob_proxy {
+        let is_materialized = match &document {
+            Some(document) => context
+                .blob_store
+                .len(document.view_id())
+                .await
+                .map_err(BlobHttpError::InternalError)?
+                .is_some(),
+            None => false,
+        };
+
+        if !is_materialized {
+            return respond_with_proxied_blob(if_none_match, blob_proxy, &document_id).await;
+        }
     }
 
-    respond_with_blob(if_none_match, context.blobs_base_path, document).await
+    let document = document.ok_or(BlobHttpError::NotFound)?;
+    respond_with_blob(if_none_match, &context.blob_store, document).await
 }
 
 /// Handle requests for a blob document view served via HTTP.
@@ -87,7 +587,64 @@ pub async fn handle_blob_view(
         return Err(BlobHttpError::NotFound);
     }
 
-    respond_with_blob(if_none_match, context.blobs_base_path, document).await
+    respond_with_blob(if_none_match, &context.blob_store, document).await
+}
+
+/// Handle requests for a blob derived from the latest version of a blob document, for example a
+/// transcoded video.
+pub async fn handle_derived_blob(
+    TypedHeader(if_none_match): TypedHeader<IfNoneMatch>,
+    Extension(context): Extension<HttpServiceContext>,
+    Path((document_id, name)): Path<(String, String)>,
+) -> Result<Response, BlobHttpError> {
+    let document_id = DocumentId::from_str(&document_id)
+        .map_err(|err| BlobHttpError::InvalidFormat(err.into()))?;
+
+    let derivative = context
+        .store
+        .get_blob_derivatives(&document_id)
+        .await
+        .map_err(|err| BlobHttpError::InternalError(err.into()))?
+        .into_iter()
+        .find(|derivative| derivative.name == name)
+        .ok_or(BlobHttpError::NotFound)?;
+
+    let view_id = DocumentViewId::from_str(&derivative.view_id)
+        .map_err(|err| BlobHttpError::InternalError(err.into()))?;
+
+    let etag_str = format!("\"{}.{}\"", view_id, name);
+    let etag = ETag::from_str(&etag_str).map_err(|err| BlobHttpError::InternalError(err.into()))?;
+    if !if_none_match.precondition_passes(&etag) {
+        return Ok(StatusCode::NOT_MODIFIED.into_response());
+    }
+
+    match context
+        .blob_store
+        .open_derived(&view_id, &name)
+        .await
+        .map_err(BlobHttpError::InternalError)?
+    {
+        Some(reader) => {
+            let headers: [(HeaderName, &str); 3] = [
+                (header::CONTENT_TYPE, &derivative.mime_type),
+                (header::ETAG, &etag_str),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            ];
+
+            let body = StreamBody::new(ReaderStream::new(reader));
+            Ok((headers, body).into_response())
+        }
+        None => {
+            warn!(
+                "Data inconsistency detected: Derived blob '{}' of {} exists in database but not \
+                on file system",
+                name,
+                view_id.display(),
+            );
+
+            Err(BlobHttpError::NotFound)
+        }
+    }
 }
 
 /// Returns HTTP response with the contents, ETag and given MIME type of a blob.
@@ -95,7 +652,7 @@ pub async fn handle_blob_view(
 /// Supports basic caching by handling "IfNoneMatch" headers matching the latest ETag.
 async fn respond_with_blob(
     if_none_match: IfNoneMatch,
-    blobs_base_path: PathBuf,
+    blob_store: &BlobStore,
     document: impl AsDocument,
 ) -> Result<Response, BlobHttpError> {
     let view_id = document.view_id();
@@ -112,36 +669,35 @@ async fn respond_with_blob(
     }
 
     // Get MIME type of blob
-    let mime_type_str = match document.get("mime_type") {
-        Some(p2panda_rs::operation::OperationValue::String(value)) => Ok(value),
-        _ => Err(BlobHttpError::InternalError(anyhow!(
-            "Blob document did not contain a valid 'mime_type' field"
-        ))),
-    }?;
+    let mime_type_str = mime_type(&document)?;
 
     // Get body from read-stream of stored file on file system
-    let mut file_path = blobs_base_path;
-    file_path.push(format!("{view_id}"));
-    match File::open(&file_path).await {
-        Ok(file) => {
-            let headers = [
+    match blob_store
+        .open(view_id)
+        .await
+        .map_err(BlobHttpError::InternalError)?
+    {
+        Some(reader) => {
+            let headers: [(HeaderName, &str); 3] = [
                 // MIME type to allow browsers to correctly handle this specific blob format
                 (header::CONTENT_TYPE, mime_type_str),
                 // ETag to allow browsers handle caching
                 (header::ETAG, &to_etag_str()),
+                // Prevent browsers from guessing another content type than the claimed one
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
             ];
 
-            let stream = ReaderStream::new(file);
+            let stream = ReaderStream::new(reader);
             let body = StreamBody::new(stream);
 
             Ok((headers, body).into_response())
         }
-        Err(_) => {
+        None => {
             warn!(
                 "Data inconsistency detected: Blob document {} exists in database but not on file
                 system at path {}!",
                 view_id.display(),
-                file_path.display()
+                blob_store.path(view_id).display()
             );
 
             Err(BlobHttpError::NotFound)
@@ -149,6 +705,45 @@ async fn respond_with_blob(
     }
 }
 
+/// Responds with a blob fetched from peers, its bytes are streamed to the client while they
+/// arrive.
+async fn respond_with_proxied_blob(
+    if_none_match: IfNoneMatch,
+    blob_proxy: &BlobProxy,
+    document_id: &DocumentId,
+) -> Result<Response, BlobHttpError> {
+    let (document, stream) = blob_proxy
+        .fetch(document_id)
+        .await
+        .map_err(BlobHttpError::InternalError)?
+        .ok_or(BlobHttpError::NotFound)?;
+
+    let etag_str = format!("\"{}\"", document.view_id());
+    let etag = ETag::from_str(&etag_str).map_err(|err| BlobHttpError::InternalError(err.into()))?;
+    if !if_none_match.precondition_passes(&etag) {
+        return Ok(StatusCode::NOT_MODIFIED.into_response());
+    }
+
+    let mime_type_str = mime_type(&document)?;
+    let headers: [(HeaderName, &str); 3] = [
+        (header::CONTENT_TYPE, mime_type_str),
+        (header::ETAG, &etag_str),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+    ];
+
+    Ok((headers, StreamBody::new(stream)).into_response())
+}
+
+/// Returns the MIME type of a blob document.
+fn mime_type(document: &impl AsDocument) -> Result<&str, BlobHttpError> {
+    match document.get("mime_type") {
+        Some(p2panda_rs::operation::OperationValue::String(value)) => Ok(value),
+        _ => Err(BlobHttpError::InternalError(anyhow!(
+            "Blob document did not contain a valid 'mime_type' field"
+        ))),
+    }
+}
+
 #[derive(Debug)]
 pub enum BlobHttpError {
     NotFound,
@@ -176,18 +771,75 @@ impl IntoResponse for BlobHttpError {
     }
 }
 
+/// Error of requests to the JSON API, responded with as JSON object containing an error message.
+#[derive(Debug)]
+pub enum ApiHttpError {
+    InvalidFormat(anyhow::Error),
+    InvalidRequest(anyhow::Error),
+    Unauthorized(anyhow::Error),
+    InternalError(anyhow::Error),
+}
+
+impl IntoResponse for ApiHttpError {
+    fn into_response(self) -> Response {
+        let (status_code, message) = match self {
+            ApiHttpError::InvalidFormat(err) => (
+                StatusCode::BAD_REQUEST,
+                format!("Could not parse identifier: {}", err),
+            ),
+            ApiHttpError::InvalidRequest(err) => (StatusCode::BAD_REQUEST, err.to_string()),
+            ApiHttpError::Unauthorized(err) => (StatusCode::UNAUTHORIZED, err.to_string()),
+            ApiHttpError::InternalError(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Something went wrong: {}", err),
+            ),
+        };
+
+        (status_code, Json(ApiErrorResponse { error: message })).into_response()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ApiErrorResponse {
+    error: String,
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_graphql::{Executor, Request};
+    use futures::stream::{self, StreamExt};
     use http::{header, StatusCode};
+    use p2panda_rs::document::traits::AsDocument;
     use p2panda_rs::document::DocumentId;
     use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::{OperationValue, Relation};
     use p2panda_rs::schema::validate::MAX_BLOB_PIECE_LENGTH;
+    use p2panda_rs::schema::FieldType;
     use p2panda_rs::test_utils::fixtures::key_pair;
     use rstest::rstest;
+    use serde_json::{json, Value};
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::broadcast;
 
+    use crate::capabilities::CapabilityProvider;
+    use crate::cluster::ClusterState;
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
+    use crate::http::{build_server, HttpServiceContext};
     use crate::materializer::tasks::blob_task;
     use crate::materializer::TaskInput;
-    use crate::test_utils::{add_blob, http_test_client, test_runner, update_blob, TestNode};
+    use crate::test_utils::{
+        add_blob, add_document, add_schema, auth_token, http_test_client, populate_and_materialize,
+        populate_store_config, test_runner, test_runner_with_manager, update_blob,
+        PopulateStoreConfig, TestClient, TestNode, TestNodeManager,
+    };
+    use crate::{Configuration, SchemaSettings, SettingValue};
+
+    use super::{close_when_idle, WebSocketExecutor};
 
     #[rstest]
     fn responds_with_blob_in_http_body(key_pair: KeyPair) {
@@ -304,11 +956,62 @@ mod tests {
                 .expect("ContentType to exist in header");
 
             assert_eq!(content_type, "image/svg+xml");
+            assert_eq!(
+                headers.get(header::X_CONTENT_TYPE_OPTIONS).unwrap(),
+                "nosniff"
+            );
             assert_eq!(status_code, StatusCode::OK);
             assert_eq!(body, blob_data);
         })
     }
 
+    #[rstest]
+    fn responds_with_derived_blob(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let blob_data = "Hello, World!".as_bytes();
+            let blob_view_id = add_blob(&mut node, blob_data, 6, "text/plain", &key_pair).await;
+            let document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            let client = http_test_client(&node).await;
+            let response = client
+                .get(&format!("/blobs/{}/derived/upper", document_id))
+                .send()
+                .await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            // Keep a derived blob as a transcoder would
+            let mut writer = node
+                .context
+                .blob_store
+                .create_derived(&blob_view_id, "upper")
+                .await
+                .unwrap();
+            writer.write_all(b"HELLO, WORLD!").await.unwrap();
+            writer.flush().await.unwrap();
+            node.context
+                .store
+                .insert_blob_derivative(&document_id, &blob_view_id, "upper", "text/x-upper", 13)
+                .await
+                .unwrap();
+
+            let response = client
+                .get(&format!("/blobs/{}/derived/upper", document_id))
+                .send()
+                .await;
+            let status_code = response.status();
+            let headers = response.headers();
+            assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), "text/x-upper");
+            assert_eq!(
+                headers.get(header::ETAG).unwrap().to_str().unwrap(),
+                format!("\"{}.upper\"", blob_view_id)
+            );
+            let body = response.text().await;
+
+            assert_eq!(status_code, StatusCode::OK);
+            assert_eq!(body, "HELLO, WORLD!");
+        })
+    }
+
     #[rstest]
     fn handles_etag_and_if_none_match_precondition(key_pair: KeyPair) {
         test_runner(|mut node: TestNode| async move {
@@ -398,4 +1101,341 @@ mod tests {
             assert_eq!(response.status(), expected_status_code);
         })
     }
+
+    #[rstest]
+    #[case::query("{ nodeInfo { id } }", None, false)]
+    #[case::mutation(
+        "mutation { publish(entry: \"\", operation: \"\") { logId } }",
+        None,
+        true
+    )]
+    #[case::selected_query(
+        "query A { nodeInfo { id } } mutation B { publish(entry: \"\", operation: \"\") { logId } }",
+        Some("A"),
+        false
+    )]
+    #[case::selected_mutation(
+        "query A { nodeInfo { id } } mutation B { publish(entry: \"\", operation: \"\") { logId } }",
+        Some("B"),
+        true
+    )]
+    #[case::invalid_query("mutation {", None, false)]
+    fn detects_mutations(
+        #[case] query: &str,
+        #[case] operation_name: Option<&str>,
+        #[case] expected: bool,
+    ) {
+        let mut request = Request::new(query);
+        if let Some(operation_name) = operation_name {
+            request = request.operation_name(operation_name);
+        }
+
+        assert_eq!(super::is_mutation(&request), expected);
+    }
+
+    #[test]
+    fn responds_with_next_args() {
+        test_runner(|node: TestNode| async move {
+            let client = http_test_client(&node).await;
+
+            let response = client
+                .get("/api/v1/next_args?public_key=8b52ae153142288402382fd6d9619e018978e015e6bc372b1b0c7bd40c6a240a")
+                .send()
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let response: Value = response.json().await;
+            assert_eq!(
+                response,
+                json!({
+                    "logId": "0",
+                    "seqNum": "1",
+                    "backlink": null,
+                    "skiplink": null,
+                })
+            );
+
+            let response = client.get("/api/v1/next_args?public_key=nope").send().await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let response: Value = response.json().await;
+            assert!(response["error"].is_string());
+        })
+    }
+
+    #[rstest]
+    fn responds_with_document_changes(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venues",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let client = http_test_client(&node).await;
+
+            let response: Value = client.get("/api/v1/changes").send().await.json().await;
+            let since = response["next"].as_str().unwrap().to_string();
+
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", OperationValue::String("Pub".into()))],
+                &key_pair,
+            )
+            .await;
+
+            let response = client
+                .get(&format!("/api/v1/changes?since={}", since))
+                .send()
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let response: Value = response.json().await;
+            let next = (since.parse::<u64>().unwrap() + 1).to_string();
+            assert_eq!(
+                response["changes"][0],
+                json!({
+                    "token": next,
+                    "change": "create",
+                    "schemaId": schema.id().to_string(),
+                    "documentId": view_id.to_string(),
+                    "viewId": view_id.to_string(),
+                    "changedAt": response["changes"][0]["changedAt"],
+                })
+            );
+            assert_eq!(response["next"], json!(next));
+
+            // Clients without new changes keep their token
+            let response: Value = client
+                .get(&format!("/api/v1/changes?since={}", next))
+                .send()
+                .await
+                .json()
+                .await;
+            assert_eq!(response, json!({ "changes": [], "next": next }));
+
+            let response = client.get("/api/v1/changes?since=nope").send().await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        })
+    }
+
+    #[rstest]
+    fn hides_changes_of_unreadable_documents(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let admin = KeyPair::new();
+
+            // Documents of the populated schema are held for moderation
+            let mut settings = SchemaSettings::default();
+            settings.insert("moderated", SettingValue::Boolean(true));
+            let mut node = manager
+                .create_with_config(Configuration {
+                    schema_settings: HashMap::from([(config.schema.id().to_owned(), settings)]),
+                    ..Configuration::default()
+                })
+                .await;
+
+            let documents = populate_and_materialize(&mut node, &config).await;
+            let held_id = documents[0].id().to_owned();
+            node.context.store.hold_document(&held_id).await.unwrap();
+
+            // Notes are only readable by clients with access to their ACL document
+            let capability_schema = add_schema(
+                &mut node,
+                "capability",
+                vec![
+                    ("public_key", FieldType::String),
+                    ("permission", FieldType::String),
+                ],
+                &admin,
+            )
+            .await;
+            let acl_schema =
+                add_schema(&mut node, "acl", vec![("name", FieldType::String)], &admin).await;
+            let note_schema = add_schema(
+                &mut node,
+                "note",
+                vec![
+                    ("title", FieldType::String),
+                    ("acl", FieldType::Relation(acl_schema.id().clone())),
+                ],
+                &admin,
+            )
+            .await;
+            let acl_view_id = add_document(
+                &mut node,
+                acl_schema.id(),
+                vec![("name", "secret".into())],
+                &admin,
+            )
+            .await;
+            let acl_id: DocumentId = acl_view_id.to_string().parse().unwrap();
+            let note_view_id = add_document(
+                &mut node,
+                note_schema.id(),
+                vec![
+                    ("title", "secret".into()),
+                    (
+                        "acl",
+                        OperationValue::Relation(Relation::new(acl_id.clone())),
+                    ),
+                ],
+                &admin,
+            )
+            .await;
+
+            let (tx, _rx) = broadcast::channel(120);
+            let schema = GraphQLSchemaManager::new(
+                GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                )
+                .with_capability_provider(
+                    CapabilityProvider::new(
+                        Some(capability_schema.id().clone()),
+                        vec![admin.public_key()],
+                    )
+                    .with_read_acl_field(Some("acl".into())),
+                ),
+            )
+            .await;
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                schema,
+                node.context.blob_store.clone(),
+            )
+            .with_identity(node.context.http_identity());
+            let client = TestClient::new(build_server(context));
+
+            let changed_document_ids = |response: Value| -> Vec<String> {
+                response["changes"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|change| change["documentId"].as_str().unwrap().to_string())
+                    .collect()
+            };
+
+            // Anonymous clients don't learn about the note and the held document
+            let response: Value = client
+                .get("/api/v1/changes?limit=1000")
+                .send()
+                .await
+                .json()
+                .await;
+            let document_ids = changed_document_ids(response);
+            assert!(document_ids.contains(&acl_id.to_string()));
+            assert!(!document_ids.contains(&note_view_id.to_string()));
+            assert!(!document_ids.contains(&held_id.to_string()));
+
+            // Admins see the changes of all documents
+            let response: Value = client
+                .get("/api/v1/changes?limit=1000")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", auth_token(&node, &admin)),
+                )
+                .send()
+                .await
+                .json()
+                .await;
+            let document_ids = changed_document_ids(response);
+            assert!(document_ids.contains(&note_view_id.to_string()));
+            assert!(document_ids.contains(&held_id.to_string()));
+
+            // Invalid auth tokens are rejected
+            let response = client
+                .get("/api/v1/changes")
+                .header("Authorization", "Bearer nope")
+                .send()
+                .await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        })
+    }
+
+    #[test]
+    fn followers_reject_mutations() {
+        test_runner(|node: TestNode| async move {
+            node.context.cluster.set_leader(false);
+            let client = http_test_client(&node).await;
+
+            let response = client
+                .post("/graphql")
+                .json(&json!({
+                    "query": "mutation { publish(entry: \"\", operation: \"\") { logId } }",
+                }))
+                .send()
+                .await;
+            let response: Value = response.json().await;
+
+            assert_eq!(
+                response["errors"][0]["message"],
+                "Only the leader of the cluster accepts mutations"
+            );
+        })
+    }
+
+    #[test]
+    fn limits_subscriptions_per_connection() {
+        test_runner(|node: TestNode| async move {
+            let (tx, _) = broadcast::channel(120);
+            let executor = WebSocketExecutor {
+                schema: GraphQLSchemaManager::new(GraphQLSharedData::new(
+                    node.context.store.clone(),
+                    tx,
+                    node.context.schema_provider.clone(),
+                ))
+                .await,
+                cluster: ClusterState::default(),
+                subscriptions: Arc::new(AtomicUsize::new(0)),
+                max_subscriptions: 1,
+            };
+            let request = || Request::new("{ __typename }");
+
+            // The first subscription stays active as long as its stream exists
+            let active = executor.execute_stream(request(), None);
+            let response = executor
+                .execute_stream(request(), None)
+                .next()
+                .await
+                .unwrap();
+            assert_eq!(
+                response.errors[0].message,
+                "Reached limit of 1 subscriptions on this connection"
+            );
+
+            drop(active);
+            let response = executor
+                .execute_stream(request(), None)
+                .next()
+                .await
+                .unwrap();
+            assert!(response.is_ok(), "{:#?}", response.errors);
+            assert_eq!(executor.subscriptions.load(Ordering::SeqCst), 0);
+        })
+    }
+
+    #[test]
+    fn closes_idle_connections() {
+        test_runner(|_node: TestNode| async move {
+            let idle_timeout = Duration::from_millis(50);
+
+            // Connections without active subscriptions get closed
+            let subscriptions = Arc::new(AtomicUsize::new(0));
+            let mut incoming =
+                close_when_idle(stream::pending::<()>(), subscriptions, idle_timeout).boxed();
+            assert_eq!(incoming.next().await, None);
+
+            // Connections with active subscriptions stay open
+            let subscriptions = Arc::new(AtomicUsize::new(1));
+            let mut incoming =
+                close_when_idle(stream::pending::<()>(), subscriptions, idle_timeout).boxed();
+            assert!(tokio::time::timeout(idle_timeout * 4, incoming.next())
+                .await
+                .is_err());
+        })
+    }
 }
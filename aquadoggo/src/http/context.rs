@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::path::PathBuf;
-
+use crate::blobs::BlobStore;
+use crate::cluster::ClusterState;
 use crate::db::SqlStore;
 use crate::graphql::GraphQLSchemaManager;
+use crate::http::limits::{HttpLimits, WebSocketLimits};
+use crate::http::proxy::BlobProxy;
+use crate::startup::Readiness;
 
 #[derive(Clone)]
 pub struct HttpServiceContext {
@@ -13,16 +16,83 @@ pub struct HttpServiceContext {
     /// Dynamic GraphQL schema manager.
     pub schema: GraphQLSchemaManager,
 
-    /// Path of the directory where blobs should be served from.
-    pub blobs_base_path: PathBuf,
+    /// Blob store where blobs should be served from.
+    pub blob_store: BlobStore,
+
+    /// Limits applied to requests of GraphQL routes.
+    pub graphql_limits: HttpLimits,
+
+    /// Limits applied to requests of blob routes.
+    pub blobs_limits: HttpLimits,
+
+    /// Limits applied to GraphQL WebSocket connections.
+    pub websocket_limits: WebSocketLimits,
+
+    /// Role of this node in a cluster, only the leader accepts mutations.
+    pub cluster: ClusterState,
+
+    /// Fetches blobs which are not materialized on this node from peers, if enabled.
+    pub blob_proxy: Option<BlobProxy>,
+
+    /// Startup progress of the node, reported by the readiness probe.
+    pub readiness: Readiness,
+
+    /// Identity of the node auth tokens need to be issued for.
+    pub identity: String,
 }
 
 impl HttpServiceContext {
-    pub fn new(store: SqlStore, schema: GraphQLSchemaManager, blobs_base_path: PathBuf) -> Self {
+    pub fn new(store: SqlStore, schema: GraphQLSchemaManager, blob_store: BlobStore) -> Self {
         Self {
             store,
             schema,
-            blobs_base_path,
+            blob_store,
+            graphql_limits: HttpLimits::default(),
+            blobs_limits: HttpLimits::default(),
+            websocket_limits: WebSocketLimits::default(),
+            cluster: ClusterState::default(),
+            blob_proxy: None,
+            readiness: Readiness::default(),
+            identity: String::new(),
         }
     }
+
+    /// Limit requests of GraphQL and blob routes, by default requests are not limited.
+    pub fn with_limits(mut self, graphql_limits: HttpLimits, blobs_limits: HttpLimits) -> Self {
+        self.graphql_limits = graphql_limits;
+        self.blobs_limits = blobs_limits;
+        self
+    }
+
+    /// Limit subscriptions of GraphQL WebSocket connections, by default connections are not
+    /// limited.
+    pub fn with_websocket_limits(mut self, websocket_limits: WebSocketLimits) -> Self {
+        self.websocket_limits = websocket_limits;
+        self
+    }
+
+    /// Reject mutations while this node is not the leader of its cluster.
+    pub fn with_cluster(mut self, cluster: ClusterState) -> Self {
+        self.cluster = cluster;
+        self
+    }
+
+    /// Serve blobs which are not materialized on this node from peers.
+    pub fn with_blob_proxy(mut self, blob_proxy: BlobProxy) -> Self {
+        self.blob_proxy = Some(blob_proxy);
+        self
+    }
+
+    /// Report the startup progress of the node via the readiness probe, by default the node is
+    /// reported as not ready.
+    pub fn with_readiness(mut self, readiness: Readiness) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    /// Accept auth tokens issued for the given identity of the node, see `AuthToken`.
+    pub fn with_identity(mut self, identity: String) -> Self {
+        self.identity = identity;
+        self
+    }
 }
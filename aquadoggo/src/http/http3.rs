@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::ConnectInfo;
+use axum::Router;
+use bytes::{Buf, BytesMut};
+use h3::server::RequestStream;
+use http::{Request, Response};
+use log::{debug, warn};
+use tower::ServiceExt;
+
+/// ALPN protocol id of HTTP/3.
+pub const HTTP3_ALPN: &[u8] = b"h3";
+
+/// Serve the HTTP API over HTTP/3 via QUIC on the given UDP address.
+///
+/// The TLS configuration needs to support TLS 1.3 and offer the "h3" ALPN protocol id. Every
+/// request is passed on to the same router also serving HTTP/1.1 and HTTP/2 requests.
+pub async fn serve_http3(
+    address: SocketAddr,
+    tls_config: rustls::ServerConfig,
+    router: Router,
+) -> Result<()> {
+    let server_config = quinn::ServerConfig::with_crypto(Arc::new(tls_config));
+    let endpoint = quinn::Endpoint::server(server_config, address)?;
+
+    while let Some(connecting) = endpoint.accept().await {
+        let router = router.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = handle_connection(connecting, router).await {
+                debug!("HTTP/3 connection closed: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Accept all requests of an incoming QUIC connection.
+async fn handle_connection(connecting: quinn::Connecting, router: Router) -> Result<()> {
+    let connection = connecting.await?;
+    let remote_address = connection.remote_address();
+    let mut connection: h3::server::Connection<_, Bytes> =
+        h3::server::Connection::new(h3_quinn::Connection::new(connection)).await?;
+
+    while let Some((request, stream)) = connection.accept().await? {
+        let router = router.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = handle_request(request, stream, remote_address, router).await {
+                warn!("Failed handling HTTP/3 request: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Pass a HTTP/3 request on to the router and stream the response back.
+async fn handle_request<S>(
+    request: Request<()>,
+    mut stream: RequestStream<S, Bytes>,
+    remote_address: SocketAddr,
+    router: Router,
+) -> Result<()>
+where
+    S: h3::quic::BidiStream<Bytes>,
+{
+    let mut body = BytesMut::new();
+    while let Some(mut chunk) = stream.recv_data().await? {
+        body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+
+    let (parts, _) = request.into_parts();
+    let mut request = Request::from_parts(parts, Body::from(body.freeze()));
+
+    // Pass on the address of the client, like for HTTP/1.1 and HTTP/2 requests
+    request.extensions_mut().insert(ConnectInfo(remote_address));
+
+    let response = router.oneshot(request).await?;
+    let (parts, mut body) = response.into_parts();
+    stream
+        .send_response(Response::from_parts(parts, ()))
+        .await?;
+
+    // Stream response body, for example of large blobs, in chunks
+    while let Some(chunk) = body.data().await {
+        stream.send_data(chunk?).await?;
+    }
+
+    stream.finish().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+    use std::sync::Arc;
+
+    use axum::body::Bytes;
+    use axum::routing::get;
+    use axum::Router;
+    use bytes::Buf;
+    use http::{Request, StatusCode};
+    use rustls::{Certificate, PrivateKey, RootCertStore};
+
+    use super::{serve_http3, HTTP3_ALPN};
+
+    #[tokio::test]
+    async fn serves_requests() {
+        let certificate = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let certificate_der = Certificate(certificate.serialize_der().unwrap());
+        let private_key = PrivateKey(certificate.serialize_private_key_der());
+
+        // Prepare server
+        let mut server_tls_config = rustls::ServerConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate_der.clone()], private_key)
+            .unwrap();
+        server_tls_config.alpn_protocols = vec![HTTP3_ALPN.to_vec()];
+
+        let router = Router::new().route("/hello", get(|| async { "Hello, Panda!" }));
+
+        let address = {
+            let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            socket.local_addr().unwrap()
+        };
+        tokio::spawn(serve_http3(address, server_tls_config, router));
+
+        // Prepare client trusting the self-signed certificate
+        let mut roots = RootCertStore::empty();
+        roots.add(&certificate_der).unwrap();
+        let mut client_tls_config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        client_tls_config.alpn_protocols = vec![HTTP3_ALPN.to_vec()];
+
+        let mut endpoint =
+            quinn::Endpoint::client(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).unwrap();
+        endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(client_tls_config)));
+
+        let connection = endpoint
+            .connect(address, "localhost")
+            .unwrap()
+            .await
+            .unwrap();
+        let (mut driver, mut send_request) = h3::client::new(h3_quinn::Connection::new(connection))
+            .await
+            .unwrap();
+        tokio::spawn(async move { futures::future::poll_fn(|cx| driver.poll_close(cx)).await });
+
+        // Send request and receive response via HTTP/3
+        let request = Request::get("https://localhost/hello").body(()).unwrap();
+        let mut stream = send_request.send_request(request).await.unwrap();
+        stream.finish().await.unwrap();
+
+        let response = stream.recv_response().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut body = Vec::new();
+        while let Some(mut chunk) = stream.recv_data().await.unwrap() {
+            body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+        }
+        assert_eq!(Bytes::from(body), "Hello, Panda!");
+    }
+}
@@ -0,0 +1,285 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::body::{Body, HttpBody};
+use axum::extract::{ConnectInfo, State};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use bytes::BytesMut;
+use http::header::CONTENT_LENGTH;
+use http::{Request, StatusCode};
+use log::debug;
+use tokio::sync::Semaphore;
+
+/// Number of tracked IP addresses after which buckets of idle clients get removed.
+const MAX_TRACKED_ADDRESSES: usize = 1024;
+
+/// Limits applied to requests of a group of HTTP routes.
+///
+/// Every limit can be disabled by setting it to 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HttpLimits {
+    /// Maximum number of requests per minute from a single IP address.
+    pub rate_limit: u32,
+
+    /// Maximum number of requests handled at the same time.
+    pub max_concurrent_requests: usize,
+
+    /// Maximum size of request bodies in bytes.
+    pub max_body_size: usize,
+}
+
+/// Limits applied to GraphQL WebSocket connections.
+///
+/// Every limit can be disabled by setting it to 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WebSocketLimits {
+    /// Maximum number of subscriptions a single connection runs at the same time.
+    pub max_subscriptions: usize,
+
+    /// Duration in seconds after which connections without active subscriptions are closed.
+    pub idle_timeout: u64,
+}
+
+/// Requests a client can send right away, refilled continuously over time.
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token bucket rate limiter, keeping one bucket per IP address.
+///
+/// Every client can send up to `rate_limit` requests in a burst, afterwards one request is
+/// granted every `60 / rate_limit` seconds.
+#[derive(Debug)]
+struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    fn new(rate_limit: u32) -> Self {
+        Self {
+            capacity: rate_limit as f64,
+            refill_per_second: rate_limit as f64 / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the number of tokens of the bucket after refilling it up to the given time.
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.refill_per_second).min(self.capacity);
+        bucket.updated_at = now;
+        bucket.tokens
+    }
+
+    /// Takes a token from the bucket of the given IP address, returns false if there was none
+    /// left.
+    fn check(&self, address: IpAddr, now: Instant) -> bool {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .expect("Could not acquire lock on buckets");
+
+        // Forget about clients which did not send requests for a while, their buckets are full
+        // again anyhow
+        if buckets.len() >= MAX_TRACKED_ADDRESSES && !buckets.contains_key(&address) {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.capacity);
+        }
+
+        let bucket = buckets.entry(address).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+
+        if self.refill(bucket, now) < 1.0 {
+            return false;
+        }
+
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+/// Enforces limits on requests of a group of HTTP routes.
+///
+/// Clones share the same state, limits apply across all of them.
+#[derive(Debug, Clone)]
+pub struct RouteLimiter {
+    max_body_size: usize,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    semaphore: Option<Arc<Semaphore>>,
+}
+
+impl RouteLimiter {
+    pub fn new(limits: HttpLimits) -> Self {
+        let rate_limiter = match limits.rate_limit {
+            0 => None,
+            rate_limit => Some(Arc::new(RateLimiter::new(rate_limit))),
+        };
+
+        let semaphore = match limits.max_concurrent_requests {
+            0 => None,
+            max => Some(Arc::new(Semaphore::new(max))),
+        };
+
+        Self {
+            max_body_size: limits.max_body_size,
+            rate_limiter,
+            semaphore,
+        }
+    }
+}
+
+/// Read the request body into memory, rejecting it when it exceeds the given size.
+async fn limit_body(
+    request: Request<Body>,
+    max_body_size: usize,
+) -> Result<Request<Body>, Response> {
+    let (parts, mut body) = request.into_parts();
+
+    // Reject early when the announced body is already too large
+    let content_length = parts
+        .headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.unwrap_or(0) > max_body_size {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+    }
+
+    // Clients might send more than announced or not announce the length at all
+    let mut bytes = BytesMut::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST.into_response())?;
+        if bytes.len() + chunk.len() > max_body_size {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(Request::from_parts(parts, Body::from(bytes.freeze())))
+}
+
+/// Middleware rejecting requests which exceed the limits of the route limiter.
+///
+/// Clients sending too many requests receive a "429 Too Many Requests" response, requests
+/// exceeding the number of concurrently handled requests a "503 Service Unavailable" and requests
+/// with too large bodies a "413 Payload Too Large" response. Rate limits are only applied when
+/// the IP address of the client is known.
+pub async fn limit_requests(
+    State(limiter): State<RouteLimiter>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    if let (Some(rate_limiter), Some(ConnectInfo(address))) = (
+        &limiter.rate_limiter,
+        request.extensions().get::<ConnectInfo<SocketAddr>>(),
+    ) {
+        if !rate_limiter.check(address.ip(), Instant::now()) {
+            debug!("Rate limit exceeded by {}", address.ip());
+            return StatusCode::TOO_MANY_REQUESTS.into_response();
+        }
+    }
+
+    // Hold on to the permit until the response was built
+    let _permit = match &limiter.semaphore {
+        Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+            Ok(permit) => Some(permit),
+            Err(_) => {
+                debug!("Rejected request exceeding maximum of concurrent requests");
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+        },
+        None => None,
+    };
+
+    let request = if limiter.max_body_size > 0 {
+        match limit_body(request, limiter.max_body_size).await {
+            Ok(request) => request,
+            Err(response) => return response,
+        }
+    } else {
+        request
+    };
+
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::{Duration, Instant};
+
+    use axum::extract::ConnectInfo;
+    use axum::middleware::from_fn_with_state;
+    use axum::routing::post;
+    use axum::{Extension, Router};
+    use http::StatusCode;
+
+    use crate::test_utils::TestClient;
+
+    use super::{limit_requests, HttpLimits, RateLimiter, RouteLimiter};
+
+    #[test]
+    fn refills_buckets_over_time() {
+        let rate_limiter = RateLimiter::new(2);
+        let now = Instant::now();
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        // Clients can send a burst of requests
+        assert!(rate_limiter.check(address, now));
+        assert!(rate_limiter.check(address, now));
+        assert!(!rate_limiter.check(address, now));
+
+        // Other clients have their own bucket
+        assert!(rate_limiter.check(other_address, now));
+
+        // One request is granted every 30 seconds
+        assert!(!rate_limiter.check(address, now + Duration::from_secs(20)));
+        assert!(rate_limiter.check(address, now + Duration::from_secs(30)));
+        assert!(!rate_limiter.check(address, now + Duration::from_secs(31)));
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_exceeding_limits() {
+        let limiter = RouteLimiter::new(HttpLimits {
+            rate_limit: 3,
+            max_concurrent_requests: 8,
+            max_body_size: 16,
+        });
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, 2020));
+
+        let router = Router::new()
+            .route("/echo", post(|body: String| async move { body }))
+            .route_layer(from_fn_with_state(limiter, limit_requests))
+            .layer(Extension(ConnectInfo(address)));
+        let client = TestClient::new(router);
+
+        let response = client.post("/echo").body("Hello, Panda!").send().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await, "Hello, Panda!");
+
+        let response = client
+            .post("/echo")
+            .body("Hello, Panda! Hello, Panda!")
+            .send()
+            .await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = client.post("/echo").body("Hello!").send().await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = client.post("/echo").body("Hello!").send().await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -2,7 +2,11 @@
 
 mod api;
 mod context;
+mod http3;
+mod limits;
+mod proxy;
 mod service;
+mod tls;
 
 #[cfg(test)]
 pub use context::HttpServiceContext;
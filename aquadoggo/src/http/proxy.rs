@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use async_stream::try_stream;
+use futures::{Stream, StreamExt};
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::operation::OperationValue;
+use p2panda_rs::schema::SchemaId;
+use p2panda_rs::storage_provider::traits::DocumentStore;
+use tokio::io::AsyncReadExt;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::time::{timeout_at, Instant};
+use tokio_util::io::ReaderStream;
+
+use crate::blobs::BlobStore;
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::db::types::StorageDocument;
+use crate::db::SqlStore;
+
+/// Time we wait for the next part of a proxied blob before giving up.
+const PROXY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Serves blobs which are not materialized on this node by fetching them from connected peers.
+///
+/// The replication service requests the entries of the blob document and its pieces from a peer
+/// and ingests them like replicated ones. Pieces are streamed to the client as soon as they got
+/// materialized, once the whole blob is materialized the rest is read from the blob store.
+#[derive(Clone, Debug)]
+pub struct BlobProxy {
+    store: SqlStore,
+    blob_store: BlobStore,
+    tx: ServiceSender,
+    timeout: Duration,
+}
+
+impl BlobProxy {
+    pub fn new(store: SqlStore, blob_store: BlobStore, tx: ServiceSender) -> Self {
+        Self {
+            store,
+            blob_store,
+            tx,
+            timeout: PROXY_TIMEOUT,
+        }
+    }
+
+    /// Requests a blob from peers and returns the latest view of its blob document with a stream
+    /// over its bytes.
+    ///
+    /// Returns `None` if no peer could provide the blob document in time.
+    pub async fn fetch(
+        &self,
+        document_id: &DocumentId,
+    ) -> Result<Option<(StorageDocument, impl Stream<Item = Result<Vec<u8>>>)>> {
+        // Subscribe before sending the request to not miss any responses
+        let mut rx = self.tx.subscribe();
+        let _ = self
+            .tx
+            .send(ServiceMessage::BlobRequested(document_id.to_owned()));
+
+        let document = loop {
+            if let Some(document) = self.store.get_document(document_id).await? {
+                break document;
+            }
+
+            if !self.wait(&mut rx, document_id).await {
+                return Ok(None);
+            }
+        };
+
+        if document.schema_id() != &SchemaId::Blob(1) {
+            return Ok(None);
+        }
+
+        let length = match document.get("length") {
+            Some(OperationValue::Integer(length)) => *length as u64,
+            _ => bail!("Blob document did not contain a valid 'length' field"),
+        };
+
+        let pieces = match document.get("pieces") {
+            Some(OperationValue::PinnedRelationList(list)) => list.clone(),
+            _ => bail!("Blob document did not contain a valid 'pieces' field"),
+        };
+
+        let proxy = self.clone();
+        let document_id = document_id.to_owned();
+        let view_id = document.view_id().to_owned();
+
+        let stream = try_stream! {
+            let mut sent = 0;
+            let mut is_materialized = false;
+
+            for piece_view_id in pieces.iter() {
+                match proxy.next_piece(&mut rx, &document_id, &view_id, length, piece_view_id).await? {
+                    Some(data) => {
+                        sent += data.len() as u64;
+                        yield data;
+                    }
+                    None => {
+                        is_materialized = true;
+                        break;
+                    }
+                }
+            }
+
+            // Continue with the bytes we didn't send yet from the materialized blob
+            if is_materialized {
+                let mut reader = proxy
+                    .blob_store
+                    .open(&view_id)
+                    .await?
+                    .ok_or_else(|| anyhow!("Materialized blob {} disappeared", view_id))?;
+                tokio::io::copy(&mut (&mut reader).take(sent), &mut tokio::io::sink()).await?;
+
+                let mut chunks = ReaderStream::new(reader);
+                while let Some(chunk) = chunks.next().await {
+                    yield chunk?.to_vec();
+                }
+            }
+        };
+
+        Ok(Some((document, stream)))
+    }
+
+    /// Waits until a piece of a blob got materialized and returns its data.
+    ///
+    /// Returns `None` if the whole blob got materialized in the meantime.
+    async fn next_piece(
+        &self,
+        rx: &mut Receiver<ServiceMessage>,
+        document_id: &DocumentId,
+        view_id: &DocumentViewId,
+        length: u64,
+        piece_view_id: &DocumentViewId,
+    ) -> Result<Option<Vec<u8>>> {
+        loop {
+            if self.blob_store.len(view_id).await? == Some(length) {
+                return Ok(None);
+            }
+
+            if let Some(piece) = self.store.get_document_by_view_id(piece_view_id).await? {
+                return match piece.get("data") {
+                    Some(OperationValue::Bytes(data)) => Ok(Some(data.to_owned())),
+                    _ => Err(anyhow!("Blob piece {} without data", piece_view_id)),
+                };
+            }
+
+            if !self.wait(rx, document_id).await {
+                bail!(
+                    "Piece {} of blob {} did not arrive in time",
+                    piece_view_id,
+                    view_id
+                );
+            }
+        }
+    }
+
+    /// Waits until the next document got materialized on this node.
+    ///
+    /// Returns false if the requested blob is not available from any peer or nothing got
+    /// materialized in time.
+    async fn wait(&self, rx: &mut Receiver<ServiceMessage>, document_id: &DocumentId) -> bool {
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            match timeout_at(deadline, rx.recv()).await {
+                Ok(Ok(ServiceMessage::DocumentUpdated(_, _, _))) => return true,
+                Ok(Ok(ServiceMessage::BlobUnavailable(unavailable_id)))
+                    if &unavailable_id == document_id =>
+                {
+                    return false
+                }
+                Ok(Ok(_)) => continue,
+                // We might have missed an update, check again
+                Ok(Err(RecvError::Lagged(_))) => return true,
+                Ok(Err(RecvError::Closed)) | Err(_) => return false,
+            }
+        }
+    }
+}
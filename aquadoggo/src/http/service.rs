@@ -1,47 +1,120 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::SocketAddr;
+use std::time::Duration;
 
 use anyhow::Result;
 use axum::extract::Extension;
 use axum::http::Method;
+use axum::middleware::from_fn_with_state;
 use axum::routing::get;
 use axum::Router;
-use http::header::CONTENT_TYPE;
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
 use log::{debug, warn};
 use tower_http::cors::{Any, CorsLayer};
 
+use crate::authors::AuthorKeys;
 use crate::bus::ServiceSender;
+use crate::capabilities::CapabilityProvider;
 use crate::context::Context;
-use crate::graphql::GraphQLSchemaManager;
+use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData, IdempotencyCache, RelationLimits};
 use crate::http::api::{
-    handle_blob_document, handle_blob_view, handle_graphql_playground, handle_graphql_query,
+    handle_blob_document, handle_blob_view, handle_derived_blob, handle_document_changes,
+    handle_graphql_playground, handle_graphql_query, handle_graphql_subscription, handle_liveness,
+    handle_next_args, handle_readiness,
 };
 use crate::http::context::HttpServiceContext;
+use crate::http::limits::{limit_requests, HttpLimits, RouteLimiter, WebSocketLimits};
+use crate::http::proxy::BlobProxy;
+use crate::http::tls::serve_tls;
 use crate::info_or_print;
 use crate::manager::{ServiceReadySender, Shutdown};
 
 /// Route to the GraphQL playground
 const GRAPHQL_ROUTE: &str = "/graphql";
 
-/// Build HTTP server with GraphQL API.
+/// Route to GraphQL subscriptions over WebSocket connections
+const GRAPHQL_SUBSCRIPTION_ROUTE: &str = "/graphql/ws";
+
+/// Route to the arguments of the next entry, for clients without GraphQL
+const NEXT_ARGS_ROUTE: &str = "/api/v1/next_args";
+
+/// Route to the feed of document changes, for external indexers
+const DOCUMENT_CHANGES_ROUTE: &str = "/api/v1/changes";
+
+/// Route to the liveness probe
+const LIVENESS_ROUTE: &str = "/health/live";
+
+/// Route to the readiness probe reporting the startup progress
+const READINESS_ROUTE: &str = "/health/ready";
+
+/// Routes served by an HTTP server.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HttpRoutes {
+    /// GraphQL API and blobs.
+    All,
+
+    /// GraphQL API, the arguments of the next entry and the feed of document changes.
+    GraphQL,
+
+    /// Blobs only.
+    Blobs,
+}
+
+/// Build HTTP server with GraphQL API and blobs.
 pub fn build_server(http_context: HttpServiceContext) -> Router {
+    build_router(http_context, HttpRoutes::All)
+}
+
+/// Build HTTP server serving the given routes.
+pub fn build_router(http_context: HttpServiceContext, routes: HttpRoutes) -> Router {
     // Configure CORS middleware
     let cors = CorsLayer::new()
         .allow_methods(vec![Method::GET, Method::POST, Method::OPTIONS])
-        .allow_headers([CONTENT_TYPE])
+        .allow_headers([AUTHORIZATION, CONTENT_TYPE])
         .allow_credentials(false)
         .allow_origin(Any);
 
-    Router::new()
-        // Add GraphQL routes
+    // Add GraphQL routes
+    let graphql_routes = Router::new()
         .route(
             GRAPHQL_ROUTE,
-            get(|| handle_graphql_playground(GRAPHQL_ROUTE)).post(handle_graphql_query),
+            get(|| handle_graphql_playground(GRAPHQL_ROUTE, GRAPHQL_SUBSCRIPTION_ROUTE))
+                .post(handle_graphql_query),
         )
-        // Add blob routes
+        .route(GRAPHQL_SUBSCRIPTION_ROUTE, get(handle_graphql_subscription))
+        .route(NEXT_ARGS_ROUTE, get(handle_next_args))
+        .route(DOCUMENT_CHANGES_ROUTE, get(handle_document_changes))
+        .route_layer(from_fn_with_state(
+            RouteLimiter::new(http_context.graphql_limits),
+            limit_requests,
+        ));
+
+    // Add blob routes
+    let blob_routes = Router::new()
         .route("/blobs/:document_id", get(handle_blob_document))
         .route("/blobs/:document_id/:view_hash", get(handle_blob_view))
+        .route(
+            "/blobs/:document_id/derived/:name",
+            get(handle_derived_blob),
+        )
+        .route_layer(from_fn_with_state(
+            RouteLimiter::new(http_context.blobs_limits),
+            limit_requests,
+        ));
+
+    let router = match routes {
+        HttpRoutes::All => Router::new().merge(graphql_routes).merge(blob_routes),
+        HttpRoutes::GraphQL => graphql_routes,
+        HttpRoutes::Blobs => blob_routes,
+    };
+
+    // Probes are served on every server and never limited
+    let router = router
+        .route(LIVENESS_ROUTE, get(handle_liveness))
+        .route(READINESS_ROUTE, get(handle_readiness));
+
+    router
         // Add middlewares
         .layer(cors)
         // Add shared context
@@ -56,20 +129,78 @@ pub async fn http_service(
     tx_ready: ServiceReadySender,
 ) -> Result<()> {
     let http_port = context.config.http_port;
-    let http_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), http_port);
+    let http_bind_address = context.config.http_bind_address;
+    let http_address = SocketAddr::new(http_bind_address, http_port);
 
-    // Prepare GraphQL manager executing incoming GraphQL queries via HTTP
-    let graphql_schema_manager =
-        GraphQLSchemaManager::new(context.store.clone(), tx, context.schema_provider.clone()).await;
+    // Prepare capability provider authorising incoming requests
+    let capability_provider = CapabilityProvider::new(
+        context.config.capability_schema_id.clone(),
+        context.config.admin_public_keys.clone(),
+    )
+    .with_read_acl_field(context.config.read_acl_field.clone());
 
-    let blobs_base_path = &context.config.blobs_base_path;
+    // Prepare key pairs of service accounts the node signs entries with on behalf of clients
+    let author_keys = AuthorKeys::load(&context.store, &context.config.service_accounts).await?;
+
+    // Prepare GraphQL manager executing incoming GraphQL queries via HTTP
+    let graphql_schema_manager = GraphQLSchemaManager::new(
+        GraphQLSharedData::new(
+            context.store.clone(),
+            tx.clone(),
+            context.schema_provider.clone(),
+        )
+        .with_capability_provider(capability_provider)
+        .with_idempotency_cache(IdempotencyCache::new(Duration::from_secs(
+            context.config.idempotency_window,
+        )))
+        .with_network_metrics(context.network_metrics.clone())
+        .with_local_addresses(context.local_addresses.clone())
+        .with_author_keys(author_keys),
+    )
+    .await
+    .with_relation_limits(RelationLimits {
+        max_depth: context.config.graphql_max_relation_depth,
+        max_documents: context.config.graphql_max_related_documents,
+    })
+    .with_error_details(context.config.graphql_error_details)
+    .with_http_identity(context.config.http_identity.clone())
+    .with_blob_store(context.blob_store.clone());
 
     // Introduce a new context for all HTTP routes
     let http_context = HttpServiceContext::new(
         context.store.clone(),
         graphql_schema_manager,
-        blobs_base_path.to_owned(),
-    );
+        context.blob_store.clone(),
+    )
+    .with_limits(
+        HttpLimits {
+            rate_limit: context.config.graphql_rate_limit,
+            max_concurrent_requests: context.config.graphql_max_concurrent_requests,
+            max_body_size: context.config.graphql_max_body_size,
+        },
+        HttpLimits {
+            rate_limit: context.config.blobs_rate_limit,
+            max_concurrent_requests: context.config.blobs_max_concurrent_requests,
+            max_body_size: context.config.blobs_max_body_size,
+        },
+    )
+    .with_websocket_limits(WebSocketLimits {
+        max_subscriptions: context.config.graphql_ws_max_subscriptions,
+        idle_timeout: context.config.graphql_ws_idle_timeout,
+    })
+    .with_cluster(context.cluster.clone())
+    .with_readiness(context.readiness.clone())
+    .with_identity(context.http_identity());
+
+    let http_context = if context.config.proxy_blobs {
+        http_context.with_blob_proxy(BlobProxy::new(
+            context.store.clone(),
+            context.blob_store.clone(),
+            tx,
+        ))
+    } else {
+        http_context
+    };
 
     // Start HTTP server with given port and re-attempt with random port if it was taken already
     let builder = if let Ok(builder) = axum::Server::try_bind(&http_address) {
@@ -78,10 +209,34 @@ pub async fn http_service(
         info_or_print(&format!(
             "HTTP port {http_port} was already taken, try random port instead .."
         ));
-        axum::Server::try_bind(&SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0))?
+        axum::Server::try_bind(&SocketAddr::new(http_bind_address, 0))?
+    };
+
+    // Blobs are either served together with the GraphQL API or on their own address
+    let blobs_server = match context.config.blobs_http_address {
+        Some(blobs_address) => {
+            let blobs_router = build_router(http_context.clone(), HttpRoutes::Blobs);
+            let blobs_server = axum::Server::try_bind(&blobs_address)?
+                .serve(blobs_router.into_make_service_with_connect_info::<SocketAddr>());
+            info_or_print(&format!(
+                "Serve blobs on http://{}/blobs",
+                blobs_server.local_addr()
+            ));
+            Some(blobs_server)
+        }
+        None => None,
     };
 
-    let builder = builder.serve(build_server(http_context).into_make_service());
+    let router = match blobs_server {
+        Some(_) => build_router(http_context, HttpRoutes::GraphQL),
+        None => build_router(http_context, HttpRoutes::All),
+    };
+    // Pass on the address of clients to rate limit requests per IP address
+    let builder = builder.serve(
+        router
+            .clone()
+            .into_make_service_with_connect_info::<SocketAddr>(),
+    );
 
     let local_address = builder.local_addr();
     info_or_print(&format!(
@@ -89,44 +244,70 @@ pub async fn http_service(
         local_address
     ));
 
-    builder
-        .with_graceful_shutdown(async {
-            debug!("HTTP service is ready");
-            if tx_ready.send(()).is_err() {
-                warn!("No subscriber informed about HTTP service being ready");
-            };
+    let http_server = builder.with_graceful_shutdown(async {
+        debug!("HTTP service is ready");
+        if tx_ready.send(()).is_err() {
+            warn!("No subscriber informed about HTTP service being ready");
+        };
 
-            signal.await.ok();
-        })
-        .await?;
+        signal.await.ok();
+    });
+
+    // The blobs server stops as soon as the HTTP server shut down
+    let blobs_server = async {
+        match blobs_server {
+            Some(blobs_server) => blobs_server.await,
+            None => std::future::pending().await,
+        }
+    };
+
+    if context.config.tls_domains.is_empty() {
+        tokio::select! {
+            result = http_server => result?,
+            result = blobs_server => result?,
+        }
+    } else {
+        // HTTPS and HTTP/3 servers stop as soon as the HTTP server shut down
+        tokio::select! {
+            result = http_server => result?,
+            result = blobs_server => result?,
+            result = serve_tls(&context.config, router) => result?,
+        }
+    }
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use http::StatusCode;
     use serde_json::json;
     use tokio::sync::broadcast;
 
-    use crate::graphql::GraphQLSchemaManager;
+    use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData};
     use crate::http::context::HttpServiceContext;
     use crate::schema::SchemaProvider;
+    use crate::startup::{Readiness, StartupPhase};
     use crate::test_utils::TestClient;
     use crate::test_utils::{test_runner, TestNode};
 
-    use super::build_server;
+    use super::{build_router, build_server, HttpRoutes};
 
     #[test]
     fn graphql_endpoint() {
         test_runner(|node: TestNode| async move {
             let (tx, _) = broadcast::channel(120);
             let schema_provider = SchemaProvider::default();
-            let graphql_schema_manager =
-                GraphQLSchemaManager::new(node.context.store.clone(), tx, schema_provider).await;
+            let graphql_schema_manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                schema_provider,
+            ))
+            .await;
             let context = HttpServiceContext::new(
                 node.context.store.clone(),
                 graphql_schema_manager,
-                node.context.config.blobs_base_path.clone(),
+                node.context.blob_store.clone(),
             );
             let client = TestClient::new(build_server(context));
 
@@ -151,4 +332,74 @@ mod tests {
             );
         })
     }
+
+    #[test]
+    fn separate_blob_routes() {
+        test_runner(|node: TestNode| async move {
+            let (tx, _) = broadcast::channel(120);
+            let graphql_schema_manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                SchemaProvider::default(),
+            ))
+            .await;
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                graphql_schema_manager,
+                node.context.blob_store.clone(),
+            );
+            let query = json!({ "query": "{ __schema { __typename } }" });
+
+            // Servers for blobs only don't serve the GraphQL API
+            let client = TestClient::new(build_router(context.clone(), HttpRoutes::Blobs));
+            let response = client.post("/graphql").json(&query).send().await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+            let client = TestClient::new(build_router(context, HttpRoutes::GraphQL));
+            let response = client.post("/graphql").json(&query).send().await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let response = client.get("/blobs/unknown").send().await;
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        })
+    }
+
+    #[test]
+    fn readiness_probe() {
+        test_runner(|node: TestNode| async move {
+            let (tx, _) = broadcast::channel(120);
+            let graphql_schema_manager = GraphQLSchemaManager::new(GraphQLSharedData::new(
+                node.context.store.clone(),
+                tx,
+                SchemaProvider::default(),
+            ))
+            .await;
+            let readiness = Readiness::new();
+            let context = HttpServiceContext::new(
+                node.context.store.clone(),
+                graphql_schema_manager,
+                node.context.blob_store.clone(),
+            )
+            .with_readiness(readiness.clone());
+            let client = TestClient::new(build_router(context, HttpRoutes::Blobs));
+
+            let response = client.get("/health/live").send().await;
+            assert_eq!(response.status(), StatusCode::OK);
+
+            // The probe reports the current phase until the node is ready
+            readiness.enter(StartupPhase::Network);
+            let response = client.get("/health/ready").send().await;
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            let body = response.json::<serde_json::Value>().await;
+            assert_eq!(body["ready"], json!(false));
+            assert_eq!(body["phase"], json!("network"));
+            assert_eq!(body["completed"][0]["phase"], json!("migrations"));
+
+            readiness.enter(StartupPhase::Ready);
+            let response = client.get("/health/ready").send().await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.json::<serde_json::Value>().await;
+            assert_eq!(body["ready"], json!(true));
+            assert_eq!(body["completed"].as_array().unwrap().len(), 2);
+        })
+    }
 }
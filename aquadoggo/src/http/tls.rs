@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use axum::middleware::map_response;
+use axum::response::Response;
+use axum::Router;
+use futures::StreamExt;
+use http::header::ALT_SVC;
+use http::HeaderValue;
+use log::{info, warn};
+use rustls::ServerConfig;
+use rustls_acme::caches::DirCache;
+use rustls_acme::AcmeConfig;
+
+use crate::config::Configuration;
+use crate::http::http3::{serve_http3, HTTP3_ALPN};
+
+/// ALPN protocol ids of HTTP/2 and HTTP/1.1, offered via TLS over TCP.
+const HTTPS_ALPN: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// Duration in seconds clients should remember that the API is available via HTTP/3.
+const ALT_SVC_MAX_AGE: u64 = 60 * 60 * 24;
+
+/// Returns value of the `Alt-Svc` header advertising HTTP/3 support on the given port.
+fn alt_svc_header(port: u16) -> HeaderValue {
+    HeaderValue::from_str(&format!("h3=\":{}\"; ma={}", port, ALT_SVC_MAX_AGE))
+        .expect("Header value contains only valid characters")
+}
+
+/// Serve the HTTP API via HTTPS and HTTP/3 with certificates provisioned automatically via ACME.
+///
+/// Certificates are requested and renewed in the background, requests are accepted as soon as a
+/// certificate is available. HTTPS responses advertise HTTP/3 via the `Alt-Svc` header, so clients
+/// can switch to it for subsequent requests.
+pub async fn serve_tls(config: &Configuration, router: Router) -> Result<()> {
+    let address = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), config.https_port);
+
+    let mut state = AcmeConfig::new(&config.tls_domains)
+        .contact(
+            config
+                .acme_contacts
+                .iter()
+                .map(|contact| format!("mailto:{}", contact)),
+        )
+        .cache_option(config.acme_cache_path.clone().map(DirCache::new))
+        .directory(&config.acme_directory_url)
+        .state();
+
+    // TLS over TCP for HTTP/1.1 and HTTP/2, ACME challenges are answered on the same port
+    let mut https_config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(state.resolver());
+    https_config.alpn_protocols = HTTPS_ALPN.iter().map(|alpn| alpn.to_vec()).collect();
+    let acceptor = state.axum_acceptor(Arc::new(https_config));
+
+    // QUIC for HTTP/3 requires TLS 1.3
+    let mut http3_config = ServerConfig::builder()
+        .with_safe_default_cipher_suites()
+        .with_safe_default_kx_groups()
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_no_client_auth()
+        .with_cert_resolver(state.resolver());
+    http3_config.alpn_protocols = vec![HTTP3_ALPN.to_vec()];
+
+    let alt_svc = alt_svc_header(config.https_port);
+    let https_router = router
+        .clone()
+        .layer(map_response(move |mut response: Response| {
+            let alt_svc = alt_svc.clone();
+            async move {
+                response.headers_mut().insert(ALT_SVC, alt_svc);
+                response
+            }
+        }));
+
+    let https_server = async {
+        axum_server::bind(address)
+            .acceptor(acceptor)
+            .serve(https_router.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .map_err(|err| anyhow!("HTTPS server failed: {}", err))
+    };
+
+    let http3_server = serve_http3(address, http3_config, router);
+
+    // Drive certificate provisioning and renewal
+    let acme_events = async move {
+        while let Some(event) = state.next().await {
+            match event {
+                Ok(event) => info!("ACME: {:?}", event),
+                Err(err) => warn!("ACME: {:?}", err),
+            }
+        }
+
+        Ok(())
+    };
+
+    info!(
+        "Serve HTTPS and HTTP/3 on port {} for {}",
+        config.https_port,
+        config.tls_domains.join(", ")
+    );
+
+    tokio::try_join!(https_server, http3_server, acme_events)?;
+
+    Ok(())
+}
@@ -13,30 +13,74 @@
 )]
 #![allow(clippy::uninlined_format_args)]
 mod api;
+mod archive;
+mod authors;
+mod bench;
+mod blobs;
 mod bus;
+mod capabilities;
+mod cluster;
+mod codegen;
 mod config;
 mod context;
 mod db;
+mod faults;
 mod graphql;
 mod http;
 mod manager;
 mod materializer;
+mod metrics;
+mod migrate;
 mod network;
 mod node;
 #[cfg(all(test, feature = "proptests"))]
 mod proptests;
+mod replay;
 mod replication;
 mod schema;
-#[cfg(test)]
-mod test_utils;
+mod startup;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 #[cfg(test)]
 mod tests;
+mod vacuum;
+mod webhooks;
 
 use log::{info, log_enabled, Level};
 
-pub use crate::api::{ConfigFile, LockFile, NodeEvent};
+pub use crate::api::{
+    decode_commits, export_document_bundle, read_commits, ConfigFile, DanglingReason,
+    DanglingRelation, DocumentBundle, DocumentFilter, ImportCommit, ImportReport, LockFile,
+    NodeEvent, NodeProfile, PeerProfile, PurgeReport, RelationReport, SchemaRelations,
+};
+pub use crate::authors::ServiceAccount;
+pub use crate::bench::{run_benchmarks, BenchOptions, BenchResult, BenchSetup};
+pub use crate::blobs::{BlobTranscoder, MimeTypeMismatch};
+pub use crate::capabilities::{AuthToken, AuthTokenError, Invite};
+pub use crate::cluster::ClusterState;
+pub use crate::codegen::{generate_code, generate_schema_code, Language, LanguageParsingError};
 pub use crate::config::{AllowList, Configuration};
-pub use crate::network::{NetworkConfiguration, Transport};
+pub use crate::db::{IsolationLevel, MigrationStep, TableSize};
+#[cfg(feature = "fault-injection")]
+pub use crate::faults::{Fault, FaultInjector, FaultPoint};
+pub use crate::materializer::DocumentChange;
+pub use crate::metrics::MetricsTarget;
+pub use crate::migrate::{migrate_database, MigrateAction};
+pub use crate::network::{
+    build_swarm, ConnectionDirection, ConnectionInfo, ConnectionTicket, CustomBehaviour,
+    CustomBehaviourHandle, IpVersion, NetworkConfiguration, NetworkSimulation, P2pandaBehaviour,
+    Transport,
+};
+pub use crate::replay::{replay_document, ReplayOutcome, ReplayStep};
+pub use crate::replication::{Compression, Direction, DirectionPreference, Mode, ModePreference};
+pub use crate::schema::{
+    ConstraintViolation, DateTimeField, DecimalField, FieldConstraint, FromSettingValue,
+    PrefetchProfile, RelationPath, SchemaPin, SchemaSettings, SchemaVersionPolicy, SettingError,
+    SettingValue,
+};
+pub use crate::startup::{Readiness, StartupPhase, StartupStatus};
+pub use crate::vacuum::VacuumReport;
+pub use crate::webhooks::{Webhook, WebhookError};
 pub use node::Node;
 
 /// Init env_logger before the test suite runs to handle logging outputs.
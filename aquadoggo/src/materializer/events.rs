@@ -0,0 +1,129 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Display;
+
+use log::{debug, warn};
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::schema::SchemaId;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tokio::task::JoinHandle;
+
+use crate::bus::{ServiceMessage, ServiceSender};
+
+/// Capacity of the broadcast channel informing about materialized documents.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Kind of change which led to a new latest view of a document.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DocumentChange {
+    /// Document got created.
+    Create,
+
+    /// Document got updated.
+    Update,
+
+    /// Document got deleted, its view is the final one.
+    Delete,
+}
+
+impl DocumentChange {
+    /// Returns the kind of change which led to the current view of the document.
+    pub fn of(document: &impl AsDocument) -> Self {
+        if document.is_deleted() {
+            DocumentChange::Delete
+        } else if document.is_edited() {
+            DocumentChange::Update
+        } else {
+            DocumentChange::Create
+        }
+    }
+}
+
+impl Display for DocumentChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentChange::Create => write!(f, "create"),
+            DocumentChange::Update => write!(f, "update"),
+            DocumentChange::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+/// Document which got materialized into a new latest view.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DocumentUpdated {
+    pub schema_id: SchemaId,
+    pub document_id: DocumentId,
+    pub view_id: DocumentViewId,
+    pub change: DocumentChange,
+}
+
+impl From<DocumentUpdated> for ServiceMessage {
+    fn from(event: DocumentUpdated) -> Self {
+        ServiceMessage::DocumentUpdated(event.schema_id, event.document_id, event.view_id)
+    }
+}
+
+/// Informs subscribers about documents which got created, updated or deleted by materializer
+/// tasks.
+#[derive(Clone, Debug)]
+pub struct DocumentEvents {
+    /// Sender for broadcast channel informing subscribers about materialized documents.
+    tx: Sender<DocumentUpdated>,
+}
+
+impl DocumentEvents {
+    /// Returns a new `DocumentEvents` instance.
+    pub fn new() -> Self {
+        let (tx, _) = channel(CHANNEL_CAPACITY);
+        Self { tx }
+    }
+
+    /// Inform subscribers about a new latest view of a document.
+    pub fn notify(&self, document: &impl AsDocument) {
+        // Sending only fails when there are no subscribers
+        let _ = self.tx.send(DocumentUpdated {
+            schema_id: document.schema_id().to_owned(),
+            document_id: document.id().to_owned(),
+            view_id: document.view_id().to_owned(),
+            change: DocumentChange::of(document),
+        });
+    }
+
+    /// Returns receiver for broadcast channel.
+    pub fn on_document_updated(&self) -> Receiver<DocumentUpdated> {
+        self.tx.subscribe()
+    }
+
+    /// Spawns a task forwarding all document updates as messages onto the communication bus.
+    ///
+    /// Deleted documents are not forwarded, they have no latest view which could be queried.
+    pub fn forward_events(&self, tx: ServiceSender) -> JoinHandle<()> {
+        let mut rx = self.on_document_updated();
+
+        tokio::task::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if event.change == DocumentChange::Delete => continue,
+                    Ok(event) => {
+                        if tx.send(event.into()).is_err() {
+                            debug!("No service has been informed about updated document");
+                        }
+                    }
+                    Err(RecvError::Lagged(count)) => {
+                        warn!("Missed forwarding {} document updates onto the bus", count);
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+}
+
+impl Default for DocumentEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,211 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Stream of materializer task events for external dashboards.
+//!
+//! Every client connecting to the configured UNIX socket receives all task events as
+//! newline-delimited JSON objects, starting with the first event after it connected.
+use anyhow::{anyhow, Result};
+use log::{debug, warn};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::task;
+
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::context::Context;
+use crate::manager::{ServiceReadySender, Shutdown};
+use crate::materializer::worker::TaskEvent;
+use crate::materializer::TaskInput;
+use crate::replication::now_millis;
+
+/// Escapes a string to be used as a JSON string value.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Encodes a task event as a single line JSON object.
+///
+/// Durations are given in microseconds, the timestamp in milliseconds since the UNIX epoch.
+pub fn task_event_to_json(event: &TaskEvent<TaskInput>, timestamp: u128) -> String {
+    let (name, task, duration, error) = match event {
+        TaskEvent::Started(task) => ("started", task, None, None),
+        TaskEvent::Finished(task, duration) => ("finished", task, Some(duration), None),
+        TaskEvent::Failed(task, duration, error) => ("failed", task, Some(duration), Some(error)),
+    };
+
+    let input = match task.input() {
+        TaskInput::DocumentId(document_id) => format!("\"document_id\":\"{document_id}\""),
+        TaskInput::DocumentViewId(view_id) => format!("\"view_id\":\"{view_id}\""),
+    };
+
+    let mut json = format!(
+        "{{\"event\":\"{}\",\"worker\":\"{}\",{},\"timestamp\":{}",
+        name,
+        escape(task.worker_name()),
+        input,
+        timestamp
+    );
+
+    if let Some(duration) = duration {
+        json.push_str(&format!(",\"duration_us\":{}", duration.as_micros()));
+    }
+
+    if let Some(error) = error {
+        json.push_str(&format!(",\"error\":\"{}\"", escape(error)));
+    }
+
+    json.push('}');
+    json
+}
+
+/// Writes all task events arriving on the bus into the stream until the client disconnects.
+async fn stream_task_events<S>(mut stream: S, mut rx: Receiver<ServiceMessage>)
+where
+    S: AsyncWrite + Unpin,
+{
+    loop {
+        match rx.recv().await {
+            Ok(ServiceMessage::TaskEvent(event)) => {
+                let line = format!("{}\n", task_event_to_json(&event, now_millis().into()));
+                if stream.write_all(line.as_bytes()).await.is_err() {
+                    debug!("Client of materializer events disconnected");
+                    return;
+                }
+            }
+            Ok(_) => continue,
+            Err(RecvError::Lagged(count)) => {
+                warn!("Client missed {} materializer events", count);
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// The instrumentation service streams materializer task events as newline-delimited JSON to
+/// all clients connected to a UNIX socket.
+#[cfg(unix)]
+pub async fn instrumentation_service(
+    context: Context,
+    shutdown: Shutdown,
+    tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()> {
+    let path = match &context.config.materializer_events_socket {
+        Some(path) => path.clone(),
+        None => return Err(anyhow!("No materializer events socket configured")),
+    };
+
+    // Remove socket left behind by a previous run
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)?;
+    debug!("Stream materializer events on {}", path.display());
+
+    let handle = task::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    task::spawn(stream_task_events(stream, tx.subscribe()));
+                }
+                Err(err) => {
+                    warn!("Failed accepting client of materializer events: {}", err);
+                    break;
+                }
+            }
+        }
+    });
+
+    if tx_ready.send(()).is_err() {
+        warn!("No subscriber informed about instrumentation service being ready");
+    };
+
+    tokio::select! {
+        _ = handle => (),
+        _ = shutdown => (),
+    }
+
+    let _ = std::fs::remove_file(&path);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::sync::broadcast;
+
+    use crate::bus::ServiceMessage;
+    use crate::materializer::worker::TaskEvent;
+    use crate::materializer::{Task, TaskInput};
+
+    use super::{stream_task_events, task_event_to_json};
+
+    #[rstest]
+    fn encode_task_events(#[from(random_document_id)] document_id: DocumentId) {
+        let task = Task::new("reduce", TaskInput::DocumentId(document_id.clone()));
+
+        assert_eq!(
+            task_event_to_json(&TaskEvent::Started(task.clone()), 1000),
+            format!(
+                "{{\"event\":\"started\",\"worker\":\"reduce\",\"document_id\":\"{document_id}\",\"timestamp\":1000}}"
+            )
+        );
+
+        assert_eq!(
+            task_event_to_json(
+                &TaskEvent::Failed(task, Duration::from_micros(25), "Missing \"schema\"".into()),
+                1000
+            ),
+            format!(
+                "{{\"event\":\"failed\",\"worker\":\"reduce\",\"document_id\":\"{document_id}\",\"timestamp\":1000,\"duration_us\":25,\"error\":\"Missing \\\"schema\\\"\"}}"
+            )
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn stream_newline_delimited_events(#[from(random_document_id)] document_id: DocumentId) {
+        let (tx, rx) = broadcast::channel(16);
+        let (client, server) = tokio::io::duplex(1024);
+        tokio::task::spawn(stream_task_events(server, rx));
+
+        let task = Task::new("reduce", TaskInput::DocumentId(document_id));
+        tx.send(ServiceMessage::SyncComplete).unwrap();
+        tx.send(ServiceMessage::TaskEvent(TaskEvent::Started(task.clone())))
+            .unwrap();
+        tx.send(ServiceMessage::TaskEvent(TaskEvent::Finished(
+            task,
+            Duration::from_millis(2),
+        )))
+        .unwrap();
+
+        let mut lines = BufReader::new(client).lines();
+        let started = lines.next_line().await.unwrap().unwrap();
+        let finished = lines.next_line().await.unwrap().unwrap();
+
+        assert!(started.starts_with("{\"event\":\"started\""));
+        assert!(finished.starts_with("{\"event\":\"finished\""));
+        assert!(finished.ends_with(",\"duration_us\":2000}"));
+    }
+}
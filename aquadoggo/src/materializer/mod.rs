@@ -1,10 +1,15 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod events;
 mod input;
+mod instrumentation;
 mod service;
 pub(crate) mod tasks;
 mod worker;
 
+pub use events::{DocumentChange, DocumentEvents, DocumentUpdated};
 pub use input::TaskInput;
+#[cfg(unix)]
+pub use instrumentation::instrumentation_service;
 pub use service::materializer_service;
-pub use worker::Task;
+pub use worker::{Task, TaskEvent, TaskResult};
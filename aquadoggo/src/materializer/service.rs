@@ -1,18 +1,27 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
 use anyhow::Result;
 use log::{debug, warn};
+use p2panda_rs::operation::OperationId;
 use p2panda_rs::storage_provider::traits::OperationStore;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::task;
+use tokio::time::{sleep_until, Instant};
 
 use crate::bus::{ServiceMessage, ServiceSender};
 use crate::context::Context;
 use crate::manager::{ServiceReadySender, Shutdown};
 use crate::materializer::tasks::{
-    blob_task, dependency_task, garbage_collection_task, reduce_task, schema_task,
+    blob_task, dependency_task, garbage_collection_task, prefetch_task, reduce_task, schema_task,
 };
-use crate::materializer::worker::{Factory, Task, TaskStatus};
+use crate::materializer::worker::{Factory, Task, TaskGroup, TaskStatus};
 use crate::materializer::TaskInput;
+use crate::network::PeerMessage;
+use crate::replication::Message;
 
 /// Capacity of the internal broadcast channels used inside the worker factory.
 ///
@@ -20,6 +29,46 @@ use crate::materializer::TaskInput;
 /// queues the channels can handle at once.
 const CHANNEL_CAPACITY: usize = 512_000;
 
+/// Duration the task queue needs to stay empty before we consider it drained.
+///
+/// Tasks dispatch subsequent tasks right before they complete, waiting a little bit avoids
+/// signalling a drained queue while these are still on their way.
+const DRAINED_QUEUE_DELAY: Duration = Duration::from_millis(250);
+
+/// Returns true if the message contains entries received during replication.
+fn is_replicated_entry(message: &ServiceMessage) -> bool {
+    match message {
+        ServiceMessage::ReceivedMessage(_, PeerMessage::SyncMessage(sync_message)) => matches!(
+            sync_message.message(),
+            Message::Entry(_, _) | Message::Entries(_, _)
+        ),
+        _ => false,
+    }
+}
+
+/// Returns the schema of the document a task is processing.
+///
+/// Tasks are grouped by schema, so documents of all schemas get materialized in turns.
+async fn task_schema(context: Context, input: TaskInput) -> Option<TaskGroup> {
+    // Document ids and view ids consist of ids of operations of the same document
+    let operation_id: OperationId = match &input {
+        TaskInput::DocumentId(document_id) => document_id.as_str().parse().ok()?,
+        TaskInput::DocumentViewId(view_id) => view_id.iter().next()?.to_owned(),
+    };
+
+    match context
+        .store
+        .get_schema_id_by_operation_id(&operation_id)
+        .await
+    {
+        Ok(schema_id) => schema_id.map(|schema_id| schema_id.to_string()),
+        Err(err) => {
+            warn!("Failed looking up schema of task {}: {}", input, err);
+            None
+        }
+    }
+}
+
 /// The materializer service waits for incoming new operations to transform them into actual useful
 /// application- and system data, like document views or schemas.
 ///
@@ -36,12 +85,25 @@ pub async fn materializer_service(
     let pool_size = context.config.worker_pool_size as usize;
     let mut factory = Factory::<TaskInput, Context>::new(context.clone(), CHANNEL_CAPACITY);
 
+    // Process tasks of all schemas in turns
+    let weights = context
+        .config
+        .schema_task_weights
+        .iter()
+        .map(|(schema_id, weight)| (schema_id.to_string(), *weight))
+        .collect();
+    factory.set_grouping(task_schema, weights);
+
+    // Coalesce bursts of duplicate tasks
+    factory.set_dedup_window(Duration::from_millis(context.config.task_dedup_window));
+
     // Register worker functions in factory
     factory.register("reduce", pool_size, reduce_task);
     factory.register("dependency", pool_size, dependency_task);
     factory.register("schema", pool_size, schema_task);
     factory.register("blob", pool_size, blob_task);
     factory.register("garbage_collection", pool_size, garbage_collection_task);
+    factory.register("prefetch", pool_size, prefetch_task);
 
     // Get a listener for error signal from factory
     let on_error = factory.on_error();
@@ -50,29 +112,71 @@ pub async fn materializer_service(
     let mut on_task_status_change = factory.on_task_status_change();
     let store = context.store.clone();
 
+    // Flag indicating that we've received entries from other peers since the task queue was
+    // drained the last time
+    let has_replicated_entries = Arc::new(AtomicBool::new(false));
+
     // Keep track of status changes and persist it in the database. This allows us to pick up
     // uncompleted tasks next time we start the node.
-    let status_handle = task::spawn(async move {
-        loop {
-            match on_task_status_change.recv().await {
-                Ok(TaskStatus::Pending(task)) => {
-                    store
-                        .insert_task(&task)
-                        .await
-                        .expect("Failed inserting pending task into database");
-                }
-                Ok(TaskStatus::Completed(task)) => {
-                    store
-                        .remove_task(&task)
-                        .await
-                        .expect("Failed removing completed task from database");
-                }
-                Err(err) => {
-                    panic!("Failed receiving task status updates: {}", err)
+    //
+    // We also count the pending tasks to inform other services when all replicated data got
+    // materialized.
+    let status_handle = {
+        let has_replicated_entries = has_replicated_entries.clone();
+        let tx = tx.clone();
+
+        task::spawn(async move {
+            let mut pending_tasks: usize = 0;
+            let mut drained_at: Option<Instant> = None;
+
+            loop {
+                let drained = async {
+                    match drained_at {
+                        Some(deadline) => sleep_until(deadline).await,
+                        None => std::future::pending().await,
+                    }
+                };
+
+                tokio::select! {
+                    status = on_task_status_change.recv() => {
+                        match status {
+                            Ok(TaskStatus::Pending(task)) => {
+                                pending_tasks += 1;
+                                store
+                                    .insert_task(&task)
+                                    .await
+                                    .expect("Failed inserting pending task into database");
+                            }
+                            Ok(TaskStatus::Completed(task)) => {
+                                pending_tasks = pending_tasks.saturating_sub(1);
+                                store
+                                    .remove_task(&task)
+                                    .await
+                                    .expect("Failed removing completed task from database");
+                            }
+                            Err(err) => {
+                                panic!("Failed receiving task status updates: {}", err)
+                            }
+                        }
+
+                        drained_at = if pending_tasks == 0 {
+                            Some(Instant::now() + DRAINED_QUEUE_DELAY)
+                        } else {
+                            None
+                        };
+                    }
+                    _ = drained => {
+                        drained_at = None;
+
+                        if has_replicated_entries.swap(false, Ordering::SeqCst) {
+                            debug!("Materialized all replicated data");
+                            let _ = tx.send(ServiceMessage::SyncComplete);
+                        }
+                    }
                 }
             }
-        }
-    });
+        })
+    };
 
     // Reschedule tasks from last time which did not complete
     let tasks = context
@@ -96,7 +200,21 @@ pub async fn materializer_service(
         // Listen to incoming new entries and operations and move them into task queue
         task::spawn(async move {
             loop {
-                if let Ok(ServiceMessage::NewOperation(operation_id)) = rx.recv().await {
+                let message = rx.recv().await;
+
+                if let Ok(message) = &message {
+                    if is_replicated_entry(message) {
+                        has_replicated_entries.store(true, Ordering::SeqCst);
+                    }
+                }
+
+                if let Ok(ServiceMessage::ScheduleTask(task)) = &message {
+                    // Manually scheduled tasks got validated by the sender already, we can move
+                    // them directly into the queue
+                    factory.queue(task.to_owned());
+                }
+
+                if let Ok(ServiceMessage::NewOperation(operation_id)) = message {
                     // Resolve document id of regarding operation
                     let document_id = context
                         .store
@@ -127,11 +245,47 @@ pub async fn materializer_service(
         })
     };
 
+    // Inform other services about tasks being processed, for example to instrument them
+    let task_events_handle = {
+        let mut on_task_event = factory.on_task_event();
+        let tx = tx.clone();
+
+        task::spawn(async move {
+            loop {
+                match on_task_event.recv().await {
+                    Ok(event) => {
+                        let _ = tx.send(ServiceMessage::TaskEvent(event));
+                    }
+                    Err(RecvError::Lagged(count)) => {
+                        warn!("Missed forwarding {} task events onto the bus", count);
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+    };
+
+    // Inform other services about schemas added or updated by schema tasks
+    let schema_events_handle = context.schema_provider.forward_events(tx.clone());
+
+    // Inform other services about documents materialized by reduce tasks
+    let document_events_handle = context.document_events.forward_events(tx.clone());
+
     debug!("Materialiser service is ready");
     if tx_ready.send(()).is_err() {
         warn!("No subscriber informed about materialiser service being ready");
     };
 
+    // Move blobs from the previous flat storage layout, they remain readable in the meantime
+    {
+        let blob_store = context.blob_store.clone();
+        task::spawn(async move {
+            if let Err(err) = blob_store.migrate().await {
+                warn!("Failed migrating blobs to new storage layout: {}", err);
+            }
+        });
+    }
+
     // Re-apply unmaterialized operations as they might have slipped through in an unexpected crash
     // or node shutdown
     let unindexed_operation_ids = context
@@ -148,6 +302,9 @@ pub async fn materializer_service(
     tokio::select! {
         _ = handle => (),
         _ = status_handle => (),
+        _ = task_events_handle => (),
+        _ = schema_events_handle => (),
+        _ = document_events_handle => (),
         _ = shutdown => (),
         _ = on_error => (),
     }
@@ -159,22 +316,30 @@ pub async fn materializer_service(
 mod tests {
     use std::time::Duration;
 
+    use libp2p::swarm::ConnectionId;
+    use libp2p::PeerId;
     use p2panda_rs::document::traits::AsDocument;
     use p2panda_rs::document::DocumentId;
     use p2panda_rs::entry::traits::AsEncodedEntry;
+    use p2panda_rs::entry::EncodedEntry;
     use p2panda_rs::identity::KeyPair;
-    use p2panda_rs::operation::{Operation, OperationId, OperationValue};
+    use p2panda_rs::operation::{EncodedOperation, Operation, OperationId, OperationValue};
     use p2panda_rs::schema::FieldType;
     use p2panda_rs::storage_provider::traits::DocumentStore;
     use p2panda_rs::test_utils::constants::SCHEMA_ID;
-    use p2panda_rs::test_utils::fixtures::{key_pair, operation, operation_fields, schema};
+    use p2panda_rs::test_utils::fixtures::{
+        encoded_entry, encoded_operation, key_pair, operation, operation_fields, schema,
+    };
     use p2panda_rs::test_utils::memory_store::helpers::send_to_store;
     use rstest::rstest;
     use tokio::sync::{broadcast, oneshot};
     use tokio::task;
 
+    use crate::bus::ServiceMessage;
     use crate::context::Context;
     use crate::materializer::{Task, TaskInput};
+    use crate::network::{Peer, PeerMessage};
+    use crate::replication::{Message, SyncMessage};
     use crate::schema::SchemaProvider;
     use crate::test_utils::{
         doggo_fields, doggo_schema, populate_store, populate_store_config, test_runner,
@@ -238,7 +403,7 @@ mod tests {
             }
 
             // Send a message over the bus which kicks in materialization
-            tx.send(crate::bus::ServiceMessage::NewOperation(first_operation_id))
+            tx.send(ServiceMessage::NewOperation(first_operation_id))
                 .unwrap();
 
             // Wait a little bit for work being done ..
@@ -263,6 +428,139 @@ mod tests {
         });
     }
 
+    #[rstest]
+    fn materialize_document_from_scheduled_task(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()], false, schema(vec![("name".to_string(), FieldType::String)], SCHEMA_ID.parse().unwrap(), "A test schema"), vec![("name", OperationValue::String("panda".into()))])]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            // Populate the store with some entries and operations but DON'T materialise any
+            // resulting documents
+            let documents = populate_store(&node.context.store, &config).await;
+            let document_id = documents[0].id();
+
+            // Prepare arguments for service
+            let context = Context::new(
+                node.context.store.clone(),
+                KeyPair::new(),
+                Configuration::default(),
+                SchemaProvider::default(),
+            );
+            let shutdown = task::spawn(async {
+                loop {
+                    // Do this forever .. this means that the shutdown handler will never resolve
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            });
+            let (tx, _) = broadcast::channel(1024);
+            let (tx_ready, rx_ready) = oneshot::channel::<()>();
+
+            // Start materializer service
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                materializer_service(context, shutdown, tx_clone, tx_ready)
+                    .await
+                    .unwrap();
+            });
+
+            if rx_ready.await.is_err() {
+                panic!("Service dropped");
+            }
+
+            // Manually schedule a "reduce" task for the document
+            tx.send(ServiceMessage::ScheduleTask(Task::new(
+                "reduce",
+                TaskInput::DocumentId(document_id.to_owned()),
+            )))
+            .unwrap();
+
+            // Wait a little bit for work being done ..
+            tokio::time::sleep(Duration::from_millis(500)).await;
+
+            // Check database for materialized documents
+            let document = node
+                .context
+                .store
+                .get_document(document_id)
+                .await
+                .unwrap()
+                .expect("We expect that the document is `Some`");
+            assert_eq!(document.id(), document_id);
+        });
+    }
+
+    #[rstest]
+    fn signal_sync_complete(
+        #[from(populate_store_config)]
+        #[with(1, 1, vec![KeyPair::new()], false, schema(vec![("name".to_string(), FieldType::String)], SCHEMA_ID.parse().unwrap(), "A test schema"), vec![("name", OperationValue::String("panda".into()))])]
+        config: PopulateStoreConfig,
+        encoded_entry: EncodedEntry,
+        encoded_operation: EncodedOperation,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            let first_operation_id: OperationId = documents[0].id().to_string().parse().unwrap();
+
+            // Prepare arguments for service
+            let context = Context::new(
+                node.context.store.clone(),
+                KeyPair::new(),
+                Configuration::default(),
+                SchemaProvider::default(),
+            );
+            let shutdown = task::spawn(async {
+                loop {
+                    // Do this forever .. this means that the shutdown handler will never resolve
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            });
+            let (tx, mut rx) = broadcast::channel(1024);
+            let (tx_ready, rx_ready) = oneshot::channel::<()>();
+
+            // Start materializer service
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                materializer_service(context, shutdown, tx_clone, tx_ready)
+                    .await
+                    .unwrap();
+            });
+
+            if rx_ready.await.is_err() {
+                panic!("Service dropped");
+            }
+
+            // Pretend we've received the operation from another peer
+            let peer = Peer::new(PeerId::random(), ConnectionId::new_unchecked(1));
+            tx.send(ServiceMessage::ReceivedMessage(
+                peer,
+                PeerMessage::SyncMessage(SyncMessage::new(
+                    0,
+                    Message::Entry(encoded_entry, Some(encoded_operation)),
+                )),
+            ))
+            .unwrap();
+            tx.send(ServiceMessage::NewOperation(first_operation_id))
+                .unwrap();
+
+            // Wait a little bit for work being done ..
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+
+            // Service informs us about all replicated data being materialized
+            let mut messages = Vec::new();
+            while let Ok(message) = rx.try_recv() {
+                messages.push(message);
+            }
+            assert_eq!(
+                messages
+                    .iter()
+                    .filter(|message| message == &&ServiceMessage::SyncComplete)
+                    .count(),
+                1
+            );
+        });
+    }
+
     #[rstest]
     fn materialize_document_from_last_runtime(
         #[from(populate_store_config)]
@@ -460,10 +758,8 @@ mod tests {
             }
 
             // Send a message over the bus which kicks in materialization
-            tx.send(crate::bus::ServiceMessage::NewOperation(
-                first_operation_id.clone(),
-            ))
-            .unwrap();
+            tx.send(ServiceMessage::NewOperation(first_operation_id.clone()))
+                .unwrap();
 
             // Wait a little bit for work being done ..
             tokio::time::sleep(Duration::from_millis(500)).await;
@@ -490,10 +786,8 @@ mod tests {
             .expect("Publish entry");
 
             // Send a message over the bus which kicks in materialization
-            tx.send(crate::bus::ServiceMessage::NewOperation(
-                entry_encoded.hash().into(),
-            ))
-            .unwrap();
+            tx.send(ServiceMessage::NewOperation(entry_encoded.hash().into()))
+                .unwrap();
 
             // Wait a little bit for work being done ..
             tokio::time::sleep(Duration::from_millis(500)).await;
@@ -562,7 +856,7 @@ mod tests {
                     .expect("Publish entry");
 
             // Send a message over the bus which kicks in materialization
-            tx.send(crate::bus::ServiceMessage::NewOperation(
+            tx.send(ServiceMessage::NewOperation(
                 AsEncodedEntry::hash(&entry_encoded).into(),
             ))
             .unwrap();
@@ -1,14 +1,17 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use futures::{pin_mut, StreamExt};
-use log::{debug, info};
+use log::{debug, info, warn};
 use p2panda_rs::document::traits::AsDocument;
 use p2panda_rs::operation::OperationValue;
 use p2panda_rs::schema::SchemaId;
 use p2panda_rs::storage_provider::traits::DocumentStore;
-use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
 
+use crate::blobs::{
+    essence, is_compatible, sniff_mime_type, MimeTypeMismatch, DETECTED_MIME_TYPE_ANNOTATION,
+};
+use crate::config::AllowList;
 use crate::context::Context;
 use crate::materializer::worker::{TaskError, TaskResult};
 use crate::materializer::TaskInput;
@@ -41,58 +44,85 @@ pub async fn blob_task(context: Context, input: TaskInput) -> TaskResult<TaskInp
                 )));
             }
 
-            // Determine a path for this blob file on the file system
-            let blob_view_path = context
-                .config
-                .blobs_base_path
-                .join(blob_document.view_id().to_string());
+            let blob_store = &context.blob_store;
+            let view_id = blob_document.view_id();
+
+            let expected_blob_length = match blob_document.get("length").unwrap() {
+                OperationValue::Integer(length) => *length as u64,
+                _ => unreachable!(),
+            };
 
             // Check if the blob has already been fully materialized and return early from this task
             // with an error if it has.
-            let is_blob_materialized =
-                match OpenOptions::new().read(true).open(&blob_view_path).await {
-                    Ok(file) => {
-                        let metadata = file
-                            .metadata()
-                            .await
-                            .expect("Can retrieve blob file metadata");
-
-                        let expected_blob_length = match blob_document.get("length").unwrap() {
-                            OperationValue::Integer(length) => length,
-                            _ => unreachable!(),
-                        };
-
-                        metadata.len() == *expected_blob_length as u64
-                    }
-                    Err(_) => false,
-                };
-            if is_blob_materialized {
+            let blob_length = blob_store
+                .len(view_id)
+                .await
+                .map_err(|err| TaskError::Critical(format!("Could not access blob: {}", err)))?;
+            if blob_length == Some(expected_blob_length) {
                 return Err(TaskError::Failure(format!(
-                    "Blob file already exists at {}",
-                    blob_view_path.display()
+                    "Blob {} already exists on file system",
+                    view_id
                 )));
             }
 
             // Get a stream of raw blob data
             let mut blob_stream = context
                 .store
-                .get_blob_by_view_id(blob_document.view_id())
+                .get_blob_by_view_id(view_id)
                 .await
                 // We don't raise a critical error here, as it is possible that this method returns an
                 // error, for example when not all blob pieces are available yet for materialisation
                 .map_err(|err| TaskError::Failure(err.to_string()))?
                 .expect("Blob data exists at this point");
 
-            // Write the blob to the filesystem
-            info!("Creating blob at path {}", blob_view_path.display());
+            let stream = blob_stream.read_all();
+            pin_mut!(stream);
+
+            // Check the content type of the blob with its first piece before materializing it
+            let first_chunk = match stream.next().await {
+                Some(value) => value.map_err(|err| {
+                    TaskError::Failure(format!(
+                        "Blob data is invalid and can not be materialised: {}",
+                        err
+                    ))
+                })?,
+                None => Vec::new(),
+            };
+            check_mime_type(&context, &blob_document, &first_chunk).await?;
 
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(&blob_view_path)
-                .await
-                .map_err(|err| {
+            let stream = futures::stream::once(async { Ok(first_chunk) }).chain(stream);
+            pin_mut!(stream);
+
+            if blob_store.is_packed(expected_blob_length) {
+                // Small blobs are collected in memory and aggregated in a packfile
+                info!("Adding blob {} to packfile", view_id);
+
+                let mut data = Vec::with_capacity(expected_blob_length as usize);
+                while let Some(value) = stream.next().await {
+                    let buf = value.map_err(|err| {
+                        TaskError::Failure(format!(
+                            "Blob data is invalid and can not be materialised: {}",
+                            err
+                        ))
+                    })?;
+                    data.extend_from_slice(&buf);
+                }
+
+                blob_store
+                    .insert_packed(view_id, &data)
+                    .await
+                    .map_err(|err| {
+                        TaskError::Critical(format!(
+                            "Could not add blob {} to packfile: {}",
+                            view_id, err
+                        ))
+                    })?;
+            } else {
+                // Write the blob to the filesystem
+                let blob_view_path = blob_store.path(view_id);
+                info!("Creating blob at path {}", blob_view_path.display());
+
+                let mut file = blob_store.create(view_id).await.map_err(|err| {
                     TaskError::Critical(format!(
                         "Could not create blob file @ {}: {}",
                         blob_view_path.display(),
@@ -100,26 +130,45 @@ pub async fn blob_task(context: Context, input: TaskInput) -> TaskResult<TaskInp
                     ))
                 })?;
 
-            // Read from the stream, chunk by chunk, and write every part to the file. This should put
-            // less pressure on our systems memory and allow writing large blob files
-            let stream = blob_stream.read_all();
-            pin_mut!(stream);
-
-            while let Some(value) = stream.next().await {
-                match value {
-                    Ok(buf) => file.write_all(&buf).await.map_err(|err| {
-                        TaskError::Critical(format!(
-                            "Error occurred when writing to blob file @ {}: {}",
-                            blob_view_path.display(),
+                // Read from the stream, chunk by chunk, and write every part to the file. This
+                // should put less pressure on our systems memory and allow writing large blob files
+                while let Some(value) = stream.next().await {
+                    match value {
+                        Ok(buf) => file.write_all(&buf).await.map_err(|err| {
+                            TaskError::Critical(format!(
+                                "Error occurred when writing to blob file @ {}: {}",
+                                blob_view_path.display(),
+                                err
+                            ))
+                        }),
+                        Err(err) => Err(TaskError::Failure(format!(
+                            "Blob data is invalid and can not be materialised: {}",
                             err
-                        ))
-                    }),
-                    Err(err) => Err(TaskError::Failure(format!(
-                        "Blob data is invalid and can not be materialised: {}",
+                        ))),
+                    }?;
+                }
+
+                file.flush().await.map_err(|err| {
+                    TaskError::Critical(format!(
+                        "Error occurred when writing to blob file @ {}: {}",
+                        blob_view_path.display(),
                         err
-                    ))),
-                }?;
+                    ))
+                })?;
+            }
+
+            // The blob is served from the file system from now on, we don't need to keep the
+            // data of its pieces in the database anymore
+            if context.config.compact_blob_pieces {
+                let compacted_count = context
+                    .store
+                    .compact_blob_pieces(view_id)
+                    .await
+                    .map_err(|err| TaskError::Failure(err.to_string()))?;
+                debug!("Compacted {} pieces of blob {}", compacted_count, view_id);
             }
+
+            transcode_blob(&context, &blob_document).await;
         }
         // If the blob document did not exist yet in the store we fail this task.
         None => {
@@ -130,18 +179,130 @@ pub async fn blob_task(context: Context, input: TaskInput) -> TaskResult<TaskInp
     Ok(None)
 }
 
+/// Runs all configured transcoders accepting the mime type of a materialized blob and records
+/// the derived blobs.
+///
+/// Blobs are served in their original format when transcoding fails, errors are only logged.
+async fn transcode_blob(context: &Context, blob_document: &impl AsDocument) {
+    let view_id = blob_document.view_id();
+    let mime_type = match blob_document.get("mime_type").unwrap() {
+        OperationValue::String(mime_type) => mime_type,
+        _ => unreachable!(),
+    };
+
+    for transcoder in &context.config.blob_transcoders {
+        if !transcoder.accepts(mime_type) {
+            continue;
+        }
+
+        info!("Transcoding blob {} with '{}'", view_id, transcoder.name);
+
+        let result = match transcoder.transcode(&context.blob_store, view_id).await {
+            Ok(length) => context
+                .store
+                .insert_blob_derivative(
+                    blob_document.id(),
+                    view_id,
+                    &transcoder.name,
+                    &transcoder.output_mime_type,
+                    length,
+                )
+                .await
+                .map_err(anyhow::Error::from),
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            warn!(
+                "Could not transcode blob {} with '{}': {}",
+                view_id, transcoder.name, err
+            );
+        }
+    }
+}
+
+/// Checks the mime type claimed by the author of a blob against the allow-list of this node and
+/// the mime type detected from the beginning of the blob.
+async fn check_mime_type(
+    context: &Context,
+    blob_document: &impl AsDocument,
+    data: &[u8],
+) -> Result<(), TaskError> {
+    let view_id = blob_document.view_id();
+    let claimed = match blob_document.get("mime_type").unwrap() {
+        OperationValue::String(mime_type) => mime_type,
+        _ => unreachable!(),
+    };
+
+    if let AllowList::Set(mime_types) = &context.config.allow_blob_mime_types {
+        let claimed = essence(claimed);
+
+        if !mime_types
+            .iter()
+            .any(|mime_type| essence(mime_type) == claimed)
+        {
+            warn!(
+                "Blob {} has mime type {} which is not allowed",
+                view_id, claimed
+            );
+            return Err(TaskError::Failure(format!(
+                "Mime type {} of blob {} is not allowed",
+                claimed, view_id
+            )));
+        }
+    }
+
+    let detected = match sniff_mime_type(data) {
+        Some(detected) if !is_compatible(claimed, detected) => detected,
+        // Remove flags of previous versions of this blob
+        _ => {
+            context
+                .store
+                .delete_document_annotation(blob_document.id(), DETECTED_MIME_TYPE_ANNOTATION)
+                .await
+                .map_err(|err| TaskError::Failure(err.to_string()))?;
+            return Ok(());
+        }
+    };
+
+    warn!(
+        "Blob {} claims mime type {} but looks like {}",
+        view_id, claimed, detected
+    );
+
+    match context.config.blob_mime_type_mismatch {
+        MimeTypeMismatch::Flag => context
+            .store
+            .set_document_annotation(blob_document.id(), DETECTED_MIME_TYPE_ANNOTATION, detected)
+            .await
+            .map_err(|err| TaskError::Failure(err.to_string())),
+        MimeTypeMismatch::Reject => Err(TaskError::Failure(format!(
+            "Mime type {} of blob {} does not match its content",
+            claimed, view_id
+        ))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::document::DocumentId;
     use p2panda_rs::identity::KeyPair;
-    use p2panda_rs::storage_provider::traits::DocumentStore;
+    use p2panda_rs::operation::OperationValue;
+    use p2panda_rs::storage_provider::traits::{DocumentStore, EntryStore};
     use p2panda_rs::test_utils::fixtures::key_pair;
     use rstest::rstest;
+    use tempfile::TempDir;
     use tokio::fs;
+    use tokio::io::AsyncReadExt;
 
+    use crate::blobs::{BlobTranscoder, MimeTypeMismatch, DETECTED_MIME_TYPE_ANNOTATION};
     use crate::materializer::tasks::blob_task;
     use crate::materializer::TaskInput;
-    use crate::test_utils::{add_blob, test_runner, TestNode};
+    use crate::test_utils::{
+        add_blob, test_runner, test_runner_with_manager, TestNode, TestNodeManager,
+    };
+    use crate::{AllowList, Configuration};
 
     #[rstest]
     fn materializes_blob_to_filesystem(key_pair: KeyPair) {
@@ -164,8 +325,7 @@ mod tests {
             assert!(result.unwrap().is_none());
 
             // Construct the expected path to the blob view file
-            let base_path = &node.context.config.blobs_base_path;
-            let blob_path = base_path.join(blob_view_id.to_string());
+            let blob_path = node.context.blob_store.path(&blob_view_id);
 
             // Read from this file
             let retrieved_blob_data = fs::read_to_string(blob_path).await;
@@ -214,8 +374,7 @@ mod tests {
             assert!(result.unwrap().is_none());
 
             // Construct the expected path to the blob view file
-            let base_path = &node.context.config.blobs_base_path;
-            let blob_path = base_path.join(blob_view_id.to_string());
+            let blob_path = node.context.blob_store.path(&blob_view_id);
 
             // Read from this file
             let retrieved_blob_data = fs::read(blob_path).await;
@@ -226,6 +385,132 @@ mod tests {
         })
     }
 
+    #[rstest]
+    fn materializes_small_blob_to_packfile(key_pair: KeyPair) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let temp_dir = TempDir::new().unwrap();
+            let config = Configuration {
+                blobs_base_path: temp_dir.path().to_path_buf(),
+                blobs_pack_threshold: Some(1024),
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            // Publish blob
+            let blob_data = "Hello, World!";
+            let blob_view_id =
+                add_blob(&mut node, blob_data.as_bytes(), 5, "plain/text", &key_pair).await;
+
+            // Run blob task
+            let result = blob_task(
+                node.context.clone(),
+                TaskInput::DocumentViewId(blob_view_id.clone()),
+            )
+            .await;
+            assert!(result.is_ok(), "{:#?}", result);
+
+            // The blob was added to a packfile instead of its own file
+            let blob_store = &node.context.blob_store;
+            assert!(!fs::try_exists(blob_store.path(&blob_view_id))
+                .await
+                .unwrap());
+
+            let mut retrieved_blob_data = String::new();
+            blob_store
+                .open(&blob_view_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .read_to_string(&mut retrieved_blob_data)
+                .await
+                .unwrap();
+            assert_eq!(blob_data, retrieved_blob_data);
+
+            // Run the blob task again, it should return an error as the blob was already
+            // materialized once.
+            let result = blob_task(
+                node.context.clone(),
+                TaskInput::DocumentViewId(blob_view_id.clone()),
+            )
+            .await;
+            assert!(result.is_err(), "{:?}", result);
+        })
+    }
+
+    #[rstest]
+    fn compacts_blob_pieces(key_pair: KeyPair) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let temp_dir = TempDir::new().unwrap();
+            let config = Configuration {
+                blobs_base_path: temp_dir.path().to_path_buf(),
+                compact_blob_pieces: true,
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            // Publish blob
+            let blob_data = "Hello, World!";
+            let blob_view_id =
+                add_blob(&mut node, blob_data.as_bytes(), 5, "plain/text", &key_pair).await;
+
+            // Run blob task
+            let result = blob_task(
+                node.context.clone(),
+                TaskInput::DocumentViewId(blob_view_id.clone()),
+            )
+            .await;
+            assert!(result.is_ok(), "{:#?}", result);
+
+            // The blob is still served from the file system
+            let retrieved_blob_data =
+                fs::read_to_string(node.context.blob_store.path(&blob_view_id))
+                    .await
+                    .unwrap();
+            assert_eq!(blob_data, retrieved_blob_data);
+
+            let blob_document = node
+                .context
+                .store
+                .get_document_by_view_id(&blob_view_id)
+                .await
+                .unwrap()
+                .unwrap();
+            let piece_view_ids = match blob_document.get("pieces").unwrap() {
+                OperationValue::PinnedRelationList(list) => {
+                    list.iter().cloned().collect::<Vec<_>>()
+                }
+                _ => unreachable!(),
+            };
+            assert_eq!(piece_view_ids.len(), 3);
+
+            for piece_view_id in piece_view_ids {
+                // Entries of the pieces are kept without their payloads
+                let operation_id = piece_view_id.graph_tips().first().unwrap();
+                let entry = node
+                    .context
+                    .store
+                    .get_entry(operation_id.as_hash())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert!(entry.payload().is_none());
+
+                // The data of the pieces got removed
+                let piece_document = node
+                    .context
+                    .store
+                    .get_document_by_view_id(&piece_view_id)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(
+                    piece_document.get("data"),
+                    Some(&OperationValue::Bytes(vec![]))
+                );
+            }
+        })
+    }
+
     #[rstest]
     fn rejects_incorrect_schema(key_pair: KeyPair) {
         test_runner(|mut node: TestNode| async move {
@@ -272,11 +557,13 @@ mod tests {
                 add_blob(&mut node, blob_data.as_bytes(), 5, "plain/text", &key_pair).await;
 
             // Construct the expected path to the blob view file
-            let base_path = &node.context.config.blobs_base_path;
-            let blob_path = base_path.join(blob_view_id.to_string());
+            let blob_path = node.context.blob_store.path(&blob_view_id);
 
             // Write some bytes to the expected blob path which are < than the actual blob
             // bytes length. We expect this file to be overwritten when we run the blob task.
+            fs::create_dir_all(blob_path.parent().unwrap())
+                .await
+                .unwrap();
             fs::write(blob_path.clone(), vec![0, 1, 2]).await.unwrap();
 
             // Run blob task
@@ -299,4 +586,146 @@ mod tests {
             assert_eq!(blob_data.len(), retrieved_blob_data.unwrap().len());
         })
     }
+
+    #[rstest]
+    #[case::flag(MimeTypeMismatch::Flag, true)]
+    #[case::reject(MimeTypeMismatch::Reject, false)]
+    fn checks_detected_mime_type(
+        key_pair: KeyPair,
+        #[case] blob_mime_type_mismatch: MimeTypeMismatch,
+        #[case] is_materialized: bool,
+    ) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let temp_dir = TempDir::new().unwrap();
+            let config = Configuration {
+                blobs_base_path: temp_dir.path().to_path_buf(),
+                blob_mime_type_mismatch,
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            // Publish HTML page claiming to be an image
+            let blob_data = "<html><script>alert(1)</script></html>";
+            let blob_view_id =
+                add_blob(&mut node, blob_data.as_bytes(), 10, "image/png", &key_pair).await;
+            let document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            let result = blob_task(
+                node.context.clone(),
+                TaskInput::DocumentViewId(blob_view_id.clone()),
+            )
+            .await;
+            assert_eq!(result.is_ok(), is_materialized, "{:?}", result);
+
+            let blob_length = node.context.blob_store.len(&blob_view_id).await.unwrap();
+            assert_eq!(blob_length.is_some(), is_materialized);
+
+            // Materialized blobs are flagged with their detected mime type
+            let annotations = node
+                .context
+                .store
+                .get_document_annotations(&document_id)
+                .await
+                .unwrap();
+            let is_flagged = annotations.iter().any(|annotation| {
+                annotation.key == DETECTED_MIME_TYPE_ANNOTATION && annotation.value == "text/html"
+            });
+            assert_eq!(is_flagged, is_materialized);
+        })
+    }
+
+    #[rstest]
+    #[case::allowed("image/png; charset=binary", true)]
+    #[case::not_allowed("text/html", false)]
+    fn checks_allowed_mime_types(
+        key_pair: KeyPair,
+        #[case] mime_type: &'static str,
+        #[case] is_materialized: bool,
+    ) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let temp_dir = TempDir::new().unwrap();
+            let config = Configuration {
+                blobs_base_path: temp_dir.path().to_path_buf(),
+                allow_blob_mime_types: AllowList::Set(vec!["image/png".into()]),
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            let blob_data = b"\x89PNG\r\n\x1a\n";
+            let blob_view_id = add_blob(&mut node, blob_data, 10, mime_type, &key_pair).await;
+
+            let result = blob_task(
+                node.context.clone(),
+                TaskInput::DocumentViewId(blob_view_id.clone()),
+            )
+            .await;
+            assert_eq!(result.is_ok(), is_materialized, "{:?}", result);
+        })
+    }
+
+    #[rstest]
+    fn transcodes_blobs(key_pair: KeyPair) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let temp_dir = TempDir::new().unwrap();
+            let transcoder = |name: &str, mime_types: &[&str], command: &str| BlobTranscoder {
+                name: name.into(),
+                mime_types: mime_types
+                    .iter()
+                    .map(|mime_type| mime_type.to_string())
+                    .collect(),
+                output_mime_type: "text/plain".into(),
+                command: vec!["sh".into(), "-c".into(), command.into()],
+                timeout: 10,
+            };
+            let config = Configuration {
+                blobs_base_path: temp_dir.path().to_path_buf(),
+                blob_transcoders: vec![
+                    transcoder("upper", &["text/*"], "tr a-z A-Z < {input} > {output}"),
+                    transcoder("failing", &["text/plain"], "exit 1"),
+                    transcoder("image", &["image/*"], "cp {input} {output}"),
+                ],
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            let blob_view_id = add_blob(
+                &mut node,
+                "Hello, World!".as_bytes(),
+                5,
+                "text/plain",
+                &key_pair,
+            )
+            .await;
+            let document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            // Failing transcoders don't fail the materialization of the blob
+            let result = blob_task(
+                node.context.clone(),
+                TaskInput::DocumentViewId(blob_view_id.clone()),
+            )
+            .await;
+            assert!(result.is_ok(), "{:#?}", result);
+
+            let derivatives = node
+                .context
+                .store
+                .get_blob_derivatives(&document_id)
+                .await
+                .unwrap();
+            assert_eq!(derivatives.len(), 1);
+            assert_eq!(derivatives[0].name, "upper");
+            assert_eq!(derivatives[0].length, 13);
+
+            let mut reader = node
+                .context
+                .blob_store
+                .open_derived(&blob_view_id, "upper")
+                .await
+                .unwrap()
+                .unwrap();
+            let mut data = String::new();
+            reader.read_to_string(&mut data).await.unwrap();
+            assert_eq!(data, "HELLO, WORLD!");
+        })
+    }
 }
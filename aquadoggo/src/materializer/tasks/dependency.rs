@@ -1,5 +1,8 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
 use log::{debug, trace};
 use p2panda_rs::document::traits::AsDocument;
 use p2panda_rs::document::DocumentViewId;
@@ -22,6 +25,12 @@ use crate::materializer::TaskInput;
 /// In order to guarantee all required document views are present we dispatch a reduce task for the
 /// view of each pinned relation found.
 ///
+/// To prevent documents with huge pinned relation lists from monopolizing the worker pool, at most
+/// `dependency_fan_out` reduce tasks are dispatched at once. When more views are missing, the task
+/// requeues itself as a continuation and processes the remaining relations in a later run. The
+/// position of the continuation is kept in `DependencyCursors`, every run continues after the
+/// relations handled by the previous one, so views which never arrive can not block the others.
+///
 /// Expects a _reduce_ task to have completed successfully for the given document view itself and
 /// returns a critical error otherwise.
 pub async fn dependency_task(context: Context, input: TaskInput) -> TaskResult<TaskInput> {
@@ -38,7 +47,7 @@ pub async fn dependency_task(context: Context, input: TaskInput) -> TaskResult<T
 
     let document = context
         .store
-        .get_document_by_view_id(&view_id)
+        .get_cached_document_by_view_id(&view_id)
         .await
         .map_err(|err| TaskError::Critical(err.to_string()))?;
 
@@ -52,10 +61,14 @@ pub async fn dependency_task(context: Context, input: TaskInput) -> TaskResult<T
         // tasks are only dispatched after a successful "reduce" task this is fairly rare, usually
         // a result of a race condition where other tasks changed the state before the "dependency"
         // task got dispatched.
-        None => Err(TaskError::Failure(format!(
-            "Expected document with view {} not found in store",
-            view_id
-        ))),
+        None => {
+            context.dependency_cursors.remove(&view_id);
+
+            Err(TaskError::Failure(format!(
+                "Expected document with view {} not found in store",
+                view_id
+            )))
+        }
     }?;
 
     // We can unwrap the view here as only documents with views (meaning they are not deleted) are
@@ -63,6 +76,7 @@ pub async fn dependency_task(context: Context, input: TaskInput) -> TaskResult<T
     let document_view = document.view().unwrap();
 
     let mut next_tasks = Vec::new();
+    let mut pinned_view_ids = Vec::new();
 
     // First we handle all pinned or unpinned relations defined in this document view. We can think
     // of these as "child" relations.
@@ -89,11 +103,7 @@ pub async fn dependency_task(context: Context, input: TaskInput) -> TaskResult<T
                     pinned_relation.view_id()
                 );
 
-                if let Some(task) =
-                    get_relation_task(&context, pinned_relation.view_id().clone()).await?
-                {
-                    next_tasks.push(task);
-                }
+                pinned_view_ids.push(pinned_relation.view_id().clone());
             }
             OperationValue::PinnedRelationList(pinned_relation_list) => {
                 // same as above...
@@ -106,11 +116,7 @@ pub async fn dependency_task(context: Context, input: TaskInput) -> TaskResult<T
                             document_view_id
                         );
 
-                        if let Some(task) =
-                            get_relation_task(&context, document_view_id.clone()).await?
-                        {
-                            next_tasks.push(task);
-                        }
+                        pinned_view_ids.push(document_view_id.clone());
                     }
                 }
             }
@@ -118,9 +124,65 @@ pub async fn dependency_task(context: Context, input: TaskInput) -> TaskResult<T
         }
     }
 
+    // Dispatch reduce tasks for all pinned views which are not materialised yet, but never more
+    // than the configured fan-out limit at once. Continuations start after the relations which
+    // were handled by the previous run
+    let fan_out = context.config.dependency_fan_out.max(1);
+    let offset = context
+        .dependency_cursors
+        .get(&view_id)
+        .min(pinned_view_ids.len());
+    let mut cursor = offset;
+    let mut has_remainder = false;
+
+    for document_view_id in pinned_view_ids.iter().skip(offset) {
+        if let Some(task) = get_relation_task(&context, document_view_id.clone()).await? {
+            if next_tasks.len() == fan_out {
+                has_remainder = true;
+                break;
+            }
+
+            next_tasks.push(task);
+        }
+
+        cursor += 1;
+    }
+
+    // Requeue this task to process the remaining relations later. Parent relations are handled in
+    // the last run, when all "child" relations got processed
+    if has_remainder {
+        debug!(
+            "Reached fan-out limit of {} tasks, requeue dependency task for {} at relation {}",
+            fan_out, view_id, cursor
+        );
+
+        context.dependency_cursors.set(&view_id, cursor);
+
+        next_tasks.push(Task::new(
+            "dependency",
+            TaskInput::DocumentViewId(document_view.id().clone()),
+        ));
+
+        return Ok(Some(next_tasks));
+    }
+
+    context.dependency_cursors.remove(&view_id);
+
+    // Relations handled by earlier runs of a continuation were not dispatched again, check if
+    // they got materialised in the meantime
+    let mut child_dependencies_met = next_tasks.is_empty();
+    for document_view_id in pinned_view_ids.iter().take(offset) {
+        if !child_dependencies_met {
+            break;
+        }
+
+        child_dependencies_met = get_relation_task(&context, document_view_id.clone())
+            .await?
+            .is_none();
+    }
+
     // Construct additional tasks if the task input matches certain system schemas and all
     // "child" dependencies have been reduced
-    let child_dependencies_met = next_tasks.is_empty();
     if child_dependencies_met {
         match document.schema_id() {
             // Start `schema` task when a schema definition view is completed with
@@ -161,7 +223,7 @@ async fn get_relation_task(
 
     match context
         .store
-        .get_document_by_view_id(&document_view_id)
+        .get_cached_document_by_view_id(&document_view_id)
         .await
         .map_err(|err| TaskError::Critical(err.to_string()))?
     {
@@ -238,8 +300,47 @@ async fn get_inverse_relation_tasks(
     Ok(tasks)
 }
 
+/// Positions of dependency tasks which requeued themselves as continuations.
+///
+/// Maps the document view of a dependency task to the index of the first pinned relation which
+/// was not handled yet. Positions are only kept in memory, after a restart persisted continuations
+/// process all relations again.
+#[derive(Clone, Debug, Default)]
+pub struct DependencyCursors(Arc<Mutex<HashMap<DocumentViewId, usize>>>);
+
+impl DependencyCursors {
+    /// Returns the position of the continuation for the given document view, zero if there is
+    /// none.
+    pub fn get(&self, view_id: &DocumentViewId) -> usize {
+        self.0
+            .lock()
+            .expect("Could not acquire lock on dependency cursors")
+            .get(view_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Stores the position of the continuation for the given document view.
+    pub fn set(&self, view_id: &DocumentViewId, position: usize) {
+        self.0
+            .lock()
+            .expect("Could not acquire lock on dependency cursors")
+            .insert(view_id.clone(), position);
+    }
+
+    /// Removes the position of the continuation for the given document view.
+    pub fn remove(&self, view_id: &DocumentViewId) {
+        self.0
+            .lock()
+            .expect("Could not acquire lock on dependency cursors")
+            .remove(view_id);
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use p2panda_rs::document::traits::AsDocument;
     use p2panda_rs::document::{DocumentId, DocumentViewId};
     use p2panda_rs::entry::decode::decode_entry;
@@ -258,6 +359,8 @@ mod tests {
     use p2panda_rs::WithId;
     use rstest::rstest;
 
+    use crate::config::Configuration;
+    use crate::context::Context;
     use crate::materializer::tasks::reduce_task;
     use crate::materializer::{Task, TaskInput};
     use crate::test_utils::{
@@ -437,6 +540,99 @@ mod tests {
         });
     }
 
+    #[rstest]
+    fn requeues_task_when_reaching_fan_out_limit(
+        #[from(populate_store_config)]
+        #[with(
+            1,
+            1,
+            vec![KeyPair::new()],
+            false,
+            schema_from_fields(vec![
+                ("many_previous_drafts", OperationValue::PinnedRelationList(
+                    PinnedRelationList::new(
+                        [0; 5].iter().map(|_|random_document_view_id()).collect())))
+            ]),
+            vec![
+                ("many_previous_drafts", OperationValue::PinnedRelationList(
+                    PinnedRelationList::new(
+                        [0; 5].iter().map(|_|random_document_view_id()).collect())))
+            ],
+            vec![]
+        )]
+        config: PopulateStoreConfig,
+    ) {
+        test_runner(move |node: TestNode| async move {
+            let documents = populate_store(&node.context.store, &config).await;
+            let document_id = documents[0].id().clone();
+
+            reduce_task(
+                node.context.clone(),
+                TaskInput::DocumentId(document_id.clone()),
+            )
+            .await
+            .unwrap();
+
+            let view_id = node
+                .context
+                .store
+                .get_document(&document_id)
+                .await
+                .unwrap()
+                .unwrap()
+                .view_id()
+                .to_owned();
+
+            // Allow only two reduce tasks per dependency task
+            let context = Context::new(
+                node.context.store.clone(),
+                KeyPair::new(),
+                Configuration {
+                    dependency_fan_out: 2,
+                    ..Configuration::default()
+                },
+                node.context.schema_provider.clone(),
+            );
+
+            // None of the pinned views ever arrive, still every run continues with the next
+            // relations until all of them got dispatched
+            let mut dispatched = HashSet::new();
+            for expected_reduce_tasks in [2, 2, 1] {
+                let next_tasks =
+                    dependency_task(context.clone(), TaskInput::DocumentViewId(view_id.clone()))
+                        .await
+                        .unwrap()
+                        .unwrap();
+
+                let reduce_tasks: Vec<&Task<TaskInput>> = next_tasks
+                    .iter()
+                    .filter(|task| task.worker_name() == "reduce")
+                    .collect();
+                assert_eq!(reduce_tasks.len(), expected_reduce_tasks);
+
+                for task in reduce_tasks {
+                    assert!(dispatched.insert(task.input().clone()));
+                }
+
+                // The task requeues itself while relations are left
+                if expected_reduce_tasks == 2 {
+                    assert_eq!(
+                        next_tasks.last(),
+                        Some(&Task::new(
+                            "dependency",
+                            TaskInput::DocumentViewId(view_id.clone())
+                        ))
+                    );
+                    assert!(context.dependency_cursors.get(&view_id) > 0);
+                }
+            }
+
+            // All relations were handled, the next run starts from the beginning again
+            assert_eq!(dispatched.len(), 5);
+            assert_eq!(context.dependency_cursors.get(&view_id), 0);
+        });
+    }
+
     #[rstest]
     fn no_reduce_task_for_materialised_document_relations(
         key_pair: KeyPair,
@@ -1,7 +1,5 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use tokio::fs::{remove_file, try_exists};
-
 use log::debug;
 use p2panda_rs::document::DocumentViewId;
 use p2panda_rs::operation::traits::AsOperation;
@@ -111,6 +109,66 @@ pub async fn garbage_collection_task(context: Context, input: TaskInput) -> Task
 
             let is_blob = matches!(operation.schema_id(), SchemaId::Blob(1));
 
+            // Evict the least recently read views of documents holding more historic views than
+            // allowed for their schema, even if they are still pinned
+            if let Some(max_views) = context
+                .config
+                .max_document_views
+                .get(&operation.schema_id())
+            {
+                let evicted_view_ids = context
+                    .store
+                    .get_least_recently_used_views(&document_id, *max_views)
+                    .await
+                    .map_err(|err| TaskError::Critical(err.to_string()))?;
+
+                for view_id in evicted_view_ids {
+                    let position = match remaining_views
+                        .iter()
+                        .position(|remaining_view_id| **remaining_view_id == view_id)
+                    {
+                        Some(position) => position,
+                        None => continue,
+                    };
+
+                    let child_relations = context
+                        .store
+                        .get_child_document_ids(&view_id)
+                        .await
+                        .map_err(|err| TaskError::Critical(err.to_string()))?;
+
+                    let view_evicted = context
+                        .store
+                        .evict_document_view(&view_id)
+                        .await
+                        .map_err(|err| TaskError::Critical(err.to_string()))?;
+
+                    if view_evicted {
+                        deleted_views.push(remaining_views.remove(position));
+                        effected_child_documents.extend(child_relations);
+                    }
+                }
+            }
+
+            // Remove the history of documents of which only the latest view is kept. This needs to
+            // happen after all dangling views were deleted as their fields refer to it.
+            if context
+                .config
+                .latest_view_only_schemas
+                .contains(&operation.schema_id())
+            {
+                let pruned_fields = context
+                    .store
+                    .prune_operation_history(&document_id)
+                    .await
+                    .map_err(|err| TaskError::Critical(err.to_string()))?;
+                debug!(
+                    "Pruned {} historic operation fields of document: {}",
+                    pruned_fields,
+                    document_id.display()
+                );
+            }
+
             // If the number of remaining views is equal to one (the current view) and this is a
             // blob document then we should attempt to purge the blob completely from the store
             // and filesystem.
@@ -139,13 +197,20 @@ pub async fn garbage_collection_task(context: Context, input: TaskInput) -> Task
             if is_blob {
                 for view_id in deleted_views {
                     // Delete this blob view from the filesystem also
-                    let blob_view_path = context.config.blobs_base_path.join(view_id.to_string());
-                    if let Ok(true) = try_exists(&blob_view_path).await {
-                        remove_file(blob_view_path)
-                            .await
-                            .map_err(|err| TaskError::Critical(err.to_string()))?;
+                    let is_removed = context
+                        .blob_store
+                        .remove(view_id)
+                        .await
+                        .map_err(|err| TaskError::Critical(err.to_string()))?;
+                    if is_removed {
                         debug!("Deleted blob view from filesystem: {}", view_id);
                     }
+
+                    context
+                        .store
+                        .delete_blob_derivatives(view_id)
+                        .await
+                        .map_err(|err| TaskError::Failure(err.to_string()))?;
                 }
             }
 
@@ -176,16 +241,23 @@ pub async fn garbage_collection_task(context: Context, input: TaskInput) -> Task
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{HashMap, HashSet};
     use std::fs;
+    use std::time::Duration;
 
+    use p2panda_rs::document::traits::AsDocument;
     use p2panda_rs::document::DocumentId;
     use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::traits::AsOperation;
+    use p2panda_rs::operation::OperationValue;
     use p2panda_rs::schema::SchemaId;
-    use p2panda_rs::storage_provider::traits::DocumentStore;
+    use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
     use p2panda_rs::test_utils::fixtures::{key_pair, random_document_view_id};
     use rstest::rstest;
 
-    use crate::materializer::tasks::{blob_task, garbage_collection_task};
+    use crate::config::Configuration;
+    use crate::context::Context;
+    use crate::materializer::tasks::{blob_task, garbage_collection_task, reduce_task};
     use crate::materializer::{Task, TaskInput};
     use crate::test_utils::{
         add_blob, add_schema_and_documents, assert_query, delete_document, test_runner,
@@ -514,11 +586,7 @@ mod tests {
             .await
             .unwrap();
 
-            let blob_view_path = node
-                .context
-                .config
-                .blobs_base_path
-                .join(blob_document_view.to_string());
+            let blob_view_path = node.context.blob_store.path(&blob_document_view);
 
             let result = fs::read(blob_view_path.clone());
             assert!(result.is_ok());
@@ -622,11 +690,7 @@ mod tests {
             .await;
 
             // And it no longer exists on the file system.
-            let blob_view_path = node
-                .context
-                .config
-                .blobs_base_path
-                .join(blob_view_id.to_string());
+            let blob_view_path = node.context.blob_store.path(&blob_view_id);
 
             let result = fs::read(blob_view_path.clone());
             assert!(result.is_err());
@@ -714,11 +778,7 @@ mod tests {
             .await;
 
             // And it no longer exists on the file system.
-            let blob_view_path = node
-                .context
-                .config
-                .blobs_base_path
-                .join(blob_view_id.to_string());
+            let blob_view_path = node.context.blob_store.path(&blob_view_id);
 
             let result = fs::read(blob_view_path.clone());
             assert!(result.is_err());
@@ -806,11 +866,7 @@ mod tests {
             assert!(blob.is_some());
 
             // And it should still be on the file system.
-            let blob_view_path = node
-                .context
-                .config
-                .blobs_base_path
-                .join(blob_view_id.to_string());
+            let blob_view_path = node.context.blob_store.path(&blob_view_id);
 
             let result = fs::read(blob_view_path.clone());
             assert!(result.is_ok());
@@ -909,4 +965,173 @@ mod tests {
             }
         })
     }
+
+    #[rstest]
+    fn prunes_history_of_latest_view_only_documents(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let (schema, document_view_ids) = add_schema_and_documents(
+                &mut node,
+                "sensor",
+                vec![vec![
+                    ("temperature", 20.into(), None),
+                    ("unit", "celsius".into(), None),
+                ]],
+                &key_pair,
+            )
+            .await;
+            let document_id: DocumentId = document_view_ids[0].to_string().parse().unwrap();
+
+            let mut view_id = document_view_ids[0].clone();
+            for temperature in 21..24 {
+                view_id = update_document(
+                    &mut node,
+                    schema.id(),
+                    vec![("temperature", (temperature as i64).into())],
+                    &view_id,
+                    &key_pair,
+                )
+                .await;
+            }
+
+            let context = Context::new(
+                node.context.store.clone(),
+                KeyPair::new(),
+                Configuration {
+                    latest_view_only_schemas: vec![schema.id().to_owned()],
+                    ..Configuration::default()
+                },
+                node.context.schema_provider.clone(),
+            );
+
+            garbage_collection_task(context.clone(), TaskInput::DocumentId(document_id.clone()))
+                .await
+                .unwrap();
+
+            // Only fields of the current view are left, all operations are still there
+            let operations = context
+                .store
+                .get_operations_by_document_id(&document_id)
+                .await
+                .unwrap();
+            let fields: Vec<usize> = operations
+                .iter()
+                .map(|operation| operation.fields().unwrap().len())
+                .collect();
+            assert_eq!(fields, vec![1, 0, 0, 1]);
+
+            // Earlier views are not materialized anymore
+            let next_tasks = reduce_task(
+                context.clone(),
+                TaskInput::DocumentViewId(document_view_ids[0].clone()),
+            )
+            .await
+            .unwrap();
+            assert!(next_tasks.is_none());
+
+            // Documents can still be updated after their history was pruned
+            let view_id = update_document(
+                &mut node,
+                schema.id(),
+                vec![("unit", "kelvin".into())],
+                &view_id,
+                &key_pair,
+            )
+            .await;
+
+            let document = context
+                .store
+                .get_document(&document_id)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(document.view_id(), &view_id);
+            assert_eq!(
+                document.get("temperature").unwrap(),
+                &OperationValue::Integer(23)
+            );
+            assert_eq!(
+                document.get("unit").unwrap(),
+                &OperationValue::String("kelvin".into())
+            );
+        })
+    }
+
+    #[rstest]
+    fn evicts_least_recently_used_views(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let (profile_schema, profile_view_ids) = add_schema_and_documents(
+                &mut node,
+                "profile",
+                vec![vec![("name", "panda".into(), None)]],
+                &key_pair,
+            )
+            .await;
+            let document_id: DocumentId = profile_view_ids[0].to_string().parse().unwrap();
+
+            let mut view_ids = vec![profile_view_ids[0].clone()];
+            for name in ["Panda", "PANDA", "pAnDa"] {
+                let view_id = update_document(
+                    &mut node,
+                    profile_schema.id(),
+                    vec![("name", name.into())],
+                    view_ids.last().unwrap(),
+                    &key_pair,
+                )
+                .await;
+                view_ids.push(view_id);
+            }
+
+            // Pin all historic views of the profile from other documents
+            add_schema_and_documents(
+                &mut node,
+                "post",
+                view_ids[..3]
+                    .iter()
+                    .map(|view_id| {
+                        vec![(
+                            "author",
+                            view_id.clone().into(),
+                            Some(profile_schema.id().to_owned()),
+                        )]
+                    })
+                    .collect(),
+                &key_pair,
+            )
+            .await;
+
+            // Read the first view, it is the most recently used historic view now
+            let store = node
+                .context
+                .store
+                .clone()
+                .with_view_eviction(HashSet::from([profile_schema.id().to_owned()]));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            store.get_document_by_view_id(&view_ids[0]).await.unwrap();
+
+            let context = Context::new(
+                store.clone(),
+                KeyPair::new(),
+                Configuration {
+                    max_document_views: HashMap::from([(profile_schema.id().to_owned(), 1)]),
+                    ..Configuration::default()
+                },
+                node.context.schema_provider.clone(),
+            );
+
+            garbage_collection_task(context.clone(), TaskInput::DocumentId(document_id.clone()))
+                .await
+                .unwrap();
+
+            // Only the most recently used historic and the current view are left
+            let mut remaining_view_ids = context
+                .store
+                .get_all_document_view_ids(&document_id)
+                .await
+                .unwrap();
+            remaining_view_ids.sort();
+            let mut expected_view_ids = vec![view_ids[0].clone(), view_ids[3].clone()];
+            expected_view_ids.sort();
+            assert_eq!(remaining_view_ids, expected_view_ids);
+        })
+    }
 }
@@ -3,11 +3,64 @@
 mod blob;
 mod dependency;
 mod garbage_collection;
+mod prefetch;
 mod reduce;
 mod schema;
 
 pub use blob::blob_task;
-pub use dependency::dependency_task;
+pub use dependency::{dependency_task, DependencyCursors};
 pub use garbage_collection::garbage_collection_task;
+pub use prefetch::prefetch_task;
 pub use reduce::reduce_task;
 pub use schema::schema_task;
+
+use crate::materializer::{Task, TaskInput};
+
+/// Returns true if a worker with the given name is registered in the materializer and accepts the
+/// kind of input of this task.
+///
+/// This is used to validate tasks which were scheduled from outside of the materializer, for
+/// example by an operator trying to recover a document.
+pub fn is_valid_task(task: &Task<TaskInput>) -> bool {
+    matches!(
+        (task.worker_name().as_str(), task.input()),
+        ("reduce", _)
+            | (
+                "dependency" | "schema" | "blob" | "prefetch",
+                TaskInput::DocumentViewId(_)
+            )
+            | ("garbage_collection", TaskInput::DocumentId(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::{DocumentId, DocumentViewId};
+    use p2panda_rs::test_utils::fixtures::{document_id, document_view_id};
+    use rstest::rstest;
+
+    use crate::materializer::{Task, TaskInput};
+
+    use super::is_valid_task;
+
+    #[rstest]
+    fn validates_task_inputs(document_id: DocumentId, document_view_id: DocumentViewId) {
+        let by_id = TaskInput::DocumentId(document_id);
+        let by_view_id = TaskInput::DocumentViewId(document_view_id);
+
+        assert!(is_valid_task(&Task::new("reduce", by_id.clone())));
+        assert!(is_valid_task(&Task::new("reduce", by_view_id.clone())));
+        assert!(is_valid_task(&Task::new("dependency", by_view_id.clone())));
+        assert!(is_valid_task(&Task::new("schema", by_view_id.clone())));
+        assert!(is_valid_task(&Task::new("blob", by_view_id.clone())));
+        assert!(is_valid_task(&Task::new("prefetch", by_view_id.clone())));
+        assert!(is_valid_task(&Task::new(
+            "garbage_collection",
+            by_id.clone()
+        )));
+
+        assert!(!is_valid_task(&Task::new("dependency", by_id.clone())));
+        assert!(!is_valid_task(&Task::new("garbage_collection", by_view_id)));
+        assert!(!is_valid_task(&Task::new("unknown", by_id)));
+    }
+}
@@ -0,0 +1,290 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use log::debug;
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentId, DocumentViewId};
+use p2panda_rs::operation::OperationValue;
+use p2panda_rs::schema::{FieldType, SchemaId};
+
+use crate::context::Context;
+use crate::db::types::StorageDocument;
+use crate::materializer::worker::{TaskError, TaskResult};
+use crate::materializer::TaskInput;
+use crate::schema::prefetched_relations;
+
+/// Maximum number of related documents loaded by a single prefetch task.
+const MAX_PREFETCHED_DOCUMENTS: usize = 1000;
+
+/// A prefetch task loads documents related to a document view into the cache of document views.
+///
+/// The `input` argument must contain only a view id.
+///
+/// This task is dispatched after a document of a schema with a prefetch profile got materialized
+/// into a new latest view. It follows all relation paths configured for the schema, the first
+/// GraphQL query traversing them is answered from the cache afterwards. At most
+/// `MAX_PREFETCHED_DOCUMENTS` are loaded, even when relation lists hold more documents.
+pub async fn prefetch_task(context: Context, input: TaskInput) -> TaskResult<TaskInput> {
+    debug!("Working on {}", input);
+
+    let view_id = match input {
+        TaskInput::DocumentViewId(view_id) => view_id,
+        _ => {
+            return Err(TaskError::Critical(
+                "Missing document view id in task input".into(),
+            ))
+        }
+    };
+
+    let document = context
+        .store
+        .get_cached_document_by_view_id(&view_id)
+        .await
+        .map_err(|err| TaskError::Failure(err.to_string()))?;
+
+    // The document might have been deleted in the meantime, there is nothing to prefetch then
+    let document = match document {
+        Some(document) => document,
+        None => return Ok(None),
+    };
+
+    let mut prefetched = 0;
+
+    for path in prefetched_relations(&context.config.prefetch_profiles, document.schema_id()) {
+        let mut documents = vec![document.clone()];
+
+        for field_name in path.fields() {
+            if documents.is_empty() || prefetched >= MAX_PREFETCHED_DOCUMENTS {
+                break;
+            }
+
+            documents = load_related_documents(
+                &context,
+                &documents,
+                field_name,
+                MAX_PREFETCHED_DOCUMENTS - prefetched,
+            )
+            .await?;
+            prefetched += documents.len();
+        }
+    }
+
+    debug!("Prefetched {} documents related to {}", prefetched, view_id);
+
+    Ok(None)
+}
+
+/// Loads up to `limit` documents the given relation field of the documents points at into the
+/// cache of document views and returns them.
+///
+/// Fields which don't exist or are not relations are ignored, unpinned relations resolve to the
+/// current view of the related document.
+async fn load_related_documents(
+    context: &Context,
+    documents: &[StorageDocument],
+    field_name: &str,
+    limit: usize,
+) -> Result<Vec<StorageDocument>, TaskError> {
+    let mut document_ids: HashMap<SchemaId, Vec<DocumentId>> = HashMap::new();
+    let mut view_ids: Vec<DocumentViewId> = Vec::new();
+    let mut count = 0;
+
+    for document in documents {
+        let schema = match context.schema_provider.get(document.schema_id()).await {
+            Some(schema) => schema,
+            None => continue,
+        };
+
+        let value = match document.get(field_name) {
+            Some(value) => value,
+            None => continue,
+        };
+
+        match (schema.fields().get(field_name), value) {
+            (Some(FieldType::Relation(schema_id)), OperationValue::Relation(relation)) => {
+                document_ids
+                    .entry(schema_id.to_owned())
+                    .or_default()
+                    .push(relation.document_id().to_owned());
+                count += 1;
+            }
+            (Some(FieldType::RelationList(schema_id)), OperationValue::RelationList(list)) => {
+                let ids = document_ids.entry(schema_id.to_owned()).or_default();
+                for document_id in list.iter().take(limit - count) {
+                    ids.push(document_id.to_owned());
+                    count += 1;
+                }
+            }
+            (_, OperationValue::PinnedRelation(relation)) => {
+                view_ids.push(relation.view_id().to_owned());
+                count += 1;
+            }
+            (_, OperationValue::PinnedRelationList(list)) => {
+                for view_id in list.iter().take(limit - count) {
+                    view_ids.push(view_id.to_owned());
+                    count += 1;
+                }
+            }
+            _ => (),
+        }
+
+        if count >= limit {
+            break;
+        }
+    }
+
+    let mut related = context
+        .store
+        .get_cached_documents_by_view_ids(&view_ids)
+        .await
+        .map_err(|err| TaskError::Failure(err.to_string()))?;
+
+    for (schema_id, document_ids) in document_ids {
+        // Follow redirects when related documents were merged into other ones
+        let redirects = context
+            .store
+            .resolve_document_redirects(&document_ids)
+            .await
+            .map_err(|err| TaskError::Failure(err.to_string()))?;
+        let canonical_ids: Vec<DocumentId> = document_ids
+            .iter()
+            .map(|document_id| redirects.get(document_id).unwrap_or(document_id).to_owned())
+            .collect();
+
+        related.extend(
+            context
+                .store
+                .get_cached_documents_by_ids(&schema_id, &canonical_ids)
+                .await
+                .map_err(|err| TaskError::Failure(err.to_string()))?,
+        );
+    }
+
+    Ok(related)
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::traits::AsDocument;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::{OperationValue, PinnedRelation, Relation, RelationList};
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use crate::config::Configuration;
+    use crate::context::Context;
+    use crate::materializer::TaskInput;
+    use crate::schema::PrefetchProfile;
+    use crate::test_utils::{add_document, add_schema, test_runner, TestNode};
+
+    use super::prefetch_task;
+
+    #[rstest]
+    fn prefetches_related_documents(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let owner_schema = add_schema(
+                &mut node,
+                "owner",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let venue_schema = add_schema(
+                &mut node,
+                "venue",
+                vec![
+                    ("name", FieldType::String),
+                    ("owner", FieldType::Relation(owner_schema.id().to_owned())),
+                ],
+                &key_pair,
+            )
+            .await;
+            let event_schema = add_schema(
+                &mut node,
+                "event",
+                vec![
+                    ("title", FieldType::String),
+                    (
+                        "venue",
+                        FieldType::PinnedRelation(venue_schema.id().to_owned()),
+                    ),
+                    (
+                        "hosts",
+                        FieldType::RelationList(owner_schema.id().to_owned()),
+                    ),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let owner_view_id = add_document(
+                &mut node,
+                owner_schema.id(),
+                vec![("name", OperationValue::String("Panda".into()))],
+                &key_pair,
+            )
+            .await;
+            let owner_id = owner_view_id.to_string().parse().unwrap();
+            let host_view_id = add_document(
+                &mut node,
+                owner_schema.id(),
+                vec![("name", OperationValue::String("Penguin".into()))],
+                &key_pair,
+            )
+            .await;
+            let venue_view_id = add_document(
+                &mut node,
+                venue_schema.id(),
+                vec![
+                    ("name", OperationValue::String("Pub".into())),
+                    ("owner", Relation::new(owner_id).into()),
+                ],
+                &key_pair,
+            )
+            .await;
+            let event_view_id = add_document(
+                &mut node,
+                event_schema.id(),
+                vec![
+                    ("title", OperationValue::String("Karaoke".into())),
+                    ("venue", PinnedRelation::new(venue_view_id.clone()).into()),
+                    (
+                        "hosts",
+                        RelationList::new(vec![host_view_id.to_string().parse().unwrap()]).into(),
+                    ),
+                ],
+                &key_pair,
+            )
+            .await;
+
+            let config = Configuration {
+                prefetch_profiles: vec![PrefetchProfile::new(
+                    event_schema.id(),
+                    &["venue.owner".parse().unwrap()],
+                )],
+                ..Configuration::default()
+            };
+            let context = Context::new(
+                node.context.store.clone().with_document_cache(16),
+                KeyPair::new(),
+                config,
+                node.context.schema_provider.clone(),
+            );
+
+            prefetch_task(context.clone(), TaskInput::DocumentViewId(event_view_id))
+                .await
+                .unwrap();
+
+            // Documents along the relation path are cached, others are not
+            let cache = &context.store.document_cache;
+            assert!(cache.get(&venue_view_id).is_some());
+            assert!(cache.get(&owner_view_id).is_some());
+            assert!(cache.get(&host_view_id).is_none());
+
+            let owner = cache.get(&owner_view_id).unwrap();
+            assert_eq!(owner.schema_id(), owner_schema.id());
+        });
+    }
+}
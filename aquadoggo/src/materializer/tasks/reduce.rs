@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashSet;
+
 use log::{debug, info, trace};
 use p2panda_rs::document::traits::AsDocument;
 use p2panda_rs::document::{DocumentBuilder, DocumentId, DocumentViewId};
@@ -9,8 +11,10 @@ use p2panda_rs::storage_provider::traits::{DocumentStore, EntryStore, LogStore,
 use p2panda_rs::{Human, WithId};
 
 use crate::context::Context;
+use crate::db::stores::DocumentStats;
 use crate::materializer::worker::{Task, TaskError, TaskResult};
 use crate::materializer::TaskInput;
+use crate::schema::prefetched_relations;
 
 /// Build a materialised view for a document by reducing the document's operation graph and storing
 /// it to disk.
@@ -130,6 +134,23 @@ async fn reduce_document_view<O: AsOperation + WithId<OperationId> + WithPublicK
         return Ok(None);
     }
 
+    // Only the latest view is kept of documents of some schemas, their history might be pruned
+    // already and does not allow materializing earlier views
+    let is_latest_view_only = operations.first().is_some_and(|operation| {
+        context
+            .config
+            .latest_view_only_schemas
+            .contains(&operation.schema_id())
+    });
+
+    if is_latest_view_only {
+        debug!(
+            "Skip materializing view with id {} of latest-view-only document",
+            document_view_id
+        );
+        return Ok(None);
+    }
+
     // Materialize document view
     let document_builder: DocumentBuilder = operations.into();
     let document = match document_builder.build_to_view_id(document_view_id.to_owned()) {
@@ -175,10 +196,24 @@ async fn reduce_document_view<O: AsOperation + WithId<OperationId> + WithPublicK
         document.view_id()
     );
 
-    Ok(Some(vec![Task::new(
+    let mut tasks = vec![Task::new(
         "dependency",
         TaskInput::DocumentViewId(document.view_id().to_owned()),
-    )]))
+    )];
+
+    // Evict views of documents which hold more views than allowed now
+    if context
+        .config
+        .max_document_views
+        .contains_key(document.schema_id())
+    {
+        tasks.push(Task::new(
+            "garbage_collection",
+            TaskInput::DocumentId(document.id().to_owned()),
+        ));
+    }
+
+    Ok(Some(tasks))
 }
 
 /// Helper method to reduce an operation graph to the latest document view, returning the
@@ -190,6 +225,11 @@ async fn reduce_document<O: AsOperation + WithId<OperationId> + WithPublicKey>(
     context: &Context,
     operations: &Vec<O>,
 ) -> Result<Option<Vec<Task<TaskInput>>>, TaskError> {
+    let stats = context
+        .config
+        .document_stats
+        .then(|| document_stats(operations));
+
     match DocumentBuilder::from(operations).build() {
         Ok((document, operations)) => {
             // Make sure to not materialize and store document view twice
@@ -224,6 +264,14 @@ async fn reduce_document<O: AsOperation + WithId<OperationId> + WithPublicKey>(
                 .await
                 .map_err(|err| TaskError::Critical(err.to_string()))?;
 
+            if let Some(stats) = stats {
+                context
+                    .store
+                    .set_document_stats(document.id(), &stats)
+                    .await
+                    .map_err(|err| TaskError::Critical(err.to_string()))?;
+            }
+
             let mut tasks = vec![];
 
             if document.is_deleted() {
@@ -242,6 +290,8 @@ async fn reduce_document<O: AsOperation + WithId<OperationId> + WithPublicKey>(
                 info!("Created {}", document.display());
             };
 
+            context.document_events.notify(&document);
+
             if document.is_deleted() || document.is_edited() {
                 debug!(
                     "Dispatch garbage collection task for document with id: {}",
@@ -266,6 +316,24 @@ async fn reduce_document<O: AsOperation + WithId<OperationId> + WithPublicKey>(
                 ));
             }
 
+            let is_prefetched =
+                prefetched_relations(&context.config.prefetch_profiles, document.schema_id())
+                    .next()
+                    .is_some();
+
+            if !document.is_deleted() && is_prefetched && context.store.document_cache.is_enabled()
+            {
+                debug!(
+                    "Dispatch prefetch task for view with id: {}",
+                    document.view_id()
+                );
+
+                tasks.push(Task::new(
+                    "prefetch",
+                    TaskInput::DocumentViewId(document.view_id().to_owned()),
+                ));
+            }
+
             Ok(Some(tasks))
         }
         Err(err) => {
@@ -277,6 +345,22 @@ async fn reduce_document<O: AsOperation + WithId<OperationId> + WithPublicKey>(
     }
 }
 
+/// Helper method to count the updates and distinct authors of a document.
+fn document_stats<O: AsOperation + WithPublicKey>(operations: &[O]) -> DocumentStats {
+    let authors: HashSet<String> = operations
+        .iter()
+        .map(|operation| operation.public_key().to_string())
+        .collect();
+
+    DocumentStats {
+        update_count: operations
+            .iter()
+            .filter(|operation| operation.is_update())
+            .count() as u64,
+        author_count: authors.len() as u64,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use p2panda_rs::document::traits::AsDocument;
@@ -286,7 +370,7 @@ mod tests {
     use p2panda_rs::identity::KeyPair;
     use p2panda_rs::operation::traits::AsOperation;
     use p2panda_rs::operation::OperationValue;
-    use p2panda_rs::schema::Schema;
+    use p2panda_rs::schema::{FieldType, Schema};
     use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
     use p2panda_rs::test_utils::constants;
     use p2panda_rs::test_utils::fixtures::{
@@ -296,11 +380,14 @@ mod tests {
     use p2panda_rs::WithId;
     use rstest::rstest;
 
+    use crate::config::Configuration;
+    use crate::db::stores::DocumentStats;
     use crate::materializer::tasks::reduce_task;
     use crate::materializer::TaskInput;
     use crate::test_utils::{
-        doggo_fields, doggo_schema, generate_key_pairs, populate_store, populate_store_config,
-        test_runner, PopulateStoreConfig, TestNode,
+        add_document, add_schema, doggo_fields, doggo_schema, generate_key_pairs, populate_store,
+        populate_store_config, test_runner, test_runner_with_manager, update_document,
+        PopulateStoreConfig, TestNode, TestNodeManager,
     };
 
     #[rstest]
@@ -703,4 +790,68 @@ mod tests {
             assert_eq!(document_view_fields, *expected_document.fields().unwrap());
         })
     }
+
+    #[test]
+    fn maintains_document_stats() {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let key_pairs = generate_key_pairs(2);
+            let config = Configuration {
+                document_stats: true,
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            let schema = add_schema(
+                &mut node,
+                "venue",
+                vec![("name", FieldType::String)],
+                &key_pairs[0],
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Panda Cafe".into())],
+                &key_pairs[0],
+            )
+            .await;
+            let document_id: DocumentId = view_id.to_string().parse().unwrap();
+
+            let stats = node.context.store.get_document_stats(&document_id).await;
+            assert_eq!(
+                stats.unwrap(),
+                Some(DocumentStats {
+                    update_count: 0,
+                    author_count: 1,
+                })
+            );
+
+            // Updates by another author are counted
+            let view_id = update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Panda Bar".into())],
+                &view_id,
+                &key_pairs[0],
+            )
+            .await;
+            update_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Panda Pub".into())],
+                &view_id,
+                &key_pairs[1],
+            )
+            .await;
+
+            let stats = node.context.store.get_document_stats(&document_id).await;
+            assert_eq!(
+                stats.unwrap(),
+                Some(DocumentStats {
+                    update_count: 2,
+                    author_count: 2,
+                })
+            );
+        })
+    }
 }
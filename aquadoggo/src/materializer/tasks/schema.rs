@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use log::debug;
+use p2panda_rs::schema::SchemaId;
 
 use crate::context::Context;
 use crate::materializer::worker::{TaskError, TaskResult};
@@ -29,6 +30,24 @@ pub async fn schema_task(context: Context, input: TaskInput) -> TaskResult<TaskI
     match schema {
         // Schema was assembled successfully and is now passed to schema provider.
         Some(schema) => {
+            // Schemas pinned by name and version in the allow-list are resolved first
+            if let SchemaId::Application(name, _) = schema.id() {
+                if context.schema_provider.is_pinned(name) {
+                    let versions = context
+                        .store
+                        .get_schema_versions(&input_view_id)
+                        .await
+                        .map_err(|err| TaskError::Critical(err.to_string()))?;
+
+                    if let Some(versions) = versions {
+                        context
+                            .schema_provider
+                            .allow_pinned_version(&versions)
+                            .await;
+                    }
+                }
+            }
+
             match context.schema_provider.update(schema.clone()).await {
                 Ok(_) => (),
                 Err(err) => debug!("Schema not supported: {}", err),
@@ -45,6 +64,7 @@ pub async fn schema_task(context: Context, input: TaskInput) -> TaskResult<TaskI
 
 #[cfg(test)]
 mod tests {
+    use p2panda_rs::document::DocumentViewId;
     use p2panda_rs::identity::KeyPair;
     use p2panda_rs::operation::{OperationValue, PinnedRelationList};
     use p2panda_rs::schema::{FieldType, SchemaId, SchemaName};
@@ -53,7 +73,12 @@ mod tests {
 
     use crate::materializer::tasks::dependency_task;
     use crate::materializer::{Task, TaskInput};
-    use crate::test_utils::{add_document, test_runner, TestNode};
+    use crate::schema::SchemaVersionPolicy;
+    use crate::test_utils::{
+        add_document, test_runner, test_runner_with_manager, update_document, TestNode,
+        TestNodeManager,
+    };
+    use crate::{AllowList, Configuration};
 
     use super::schema_task;
 
@@ -196,4 +221,90 @@ mod tests {
             );
         });
     }
+
+    #[rstest]
+    fn resolves_pinned_schemas(key_pair: KeyPair) {
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let config = Configuration {
+                allow_schema_ids: AllowList::Set(vec![
+                    SchemaId::SchemaDefinition(1),
+                    SchemaId::SchemaFieldDefinition(1),
+                ]),
+                allow_schema_pins: vec!["venues@1".parse().unwrap()],
+                schema_version_policy: SchemaVersionPolicy::Compatible,
+                ..Configuration::default()
+            };
+            let mut node = manager.create_with_config(config).await;
+
+            let mut field_view_ids = Vec::new();
+            for field_name in ["title", "address"] {
+                let field_view_id = add_document(
+                    &mut node,
+                    &SchemaId::SchemaFieldDefinition(1),
+                    vec![
+                        ("name", OperationValue::String(field_name.to_string())),
+                        ("type", FieldType::String.into()),
+                    ],
+                    &key_pair,
+                )
+                .await;
+                field_view_ids.push(field_view_id);
+            }
+
+            let fields = |view_ids: &[DocumentViewId]| {
+                OperationValue::PinnedRelationList(PinnedRelationList::new(view_ids.to_vec()))
+            };
+            let schema_id = |view_id: &DocumentViewId| {
+                SchemaId::Application(SchemaName::new("venues").unwrap(), view_id.to_owned())
+            };
+
+            // Pinned first version gets allowed
+            let v1 = add_document(
+                &mut node,
+                &SchemaId::SchemaDefinition(1),
+                vec![
+                    ("name", OperationValue::String("venues".to_string())),
+                    ("description", OperationValue::String("".to_string())),
+                    ("fields", fields(&field_view_ids[..1])),
+                ],
+                &key_pair,
+            )
+            .await;
+            let input = TaskInput::DocumentViewId(v1.clone());
+            assert!(schema_task(node.context.clone(), input).await.is_ok());
+
+            let provider = &node.context.schema_provider;
+            assert!(provider.get(&schema_id(&v1)).await.is_some());
+            assert!(provider
+                .supported_schema_ids()
+                .await
+                .contains(&schema_id(&v1)));
+
+            // Adding a field keeps the schema compatible
+            let v2 = update_document(
+                &mut node,
+                &SchemaId::SchemaDefinition(1),
+                vec![("fields", fields(&field_view_ids))],
+                &v1,
+                &key_pair,
+            )
+            .await;
+            let input = TaskInput::DocumentViewId(v2.clone());
+            assert!(schema_task(node.context.clone(), input).await.is_ok());
+            assert!(provider.get(&schema_id(&v2)).await.is_some());
+
+            // Removing a field of the pinned version does not
+            let v3 = update_document(
+                &mut node,
+                &SchemaId::SchemaDefinition(1),
+                vec![("fields", fields(&field_view_ids[1..]))],
+                &v2,
+                &key_pair,
+            )
+            .await;
+            let input = TaskInput::DocumentViewId(v3.clone());
+            assert!(schema_task(node.context.clone(), input).await.is_ok());
+            assert!(provider.get(&schema_id(&v3)).await.is_none());
+        });
+    }
 }
@@ -81,20 +81,35 @@
 //! same, but the worker function might have access to a database with possibily diverging state
 //! between the tasks.
 //! ```
-use std::collections::HashMap;
+//!
+//! Duplicates of a task which is still waiting in the queue are dropped right away, only
+//! duplicates arriving while a worker processes the task cause it to be re-scheduled. A
+//! deduplication window can be set to coalesce bursts of duplicates further: tasks with an input
+//! which completed less than the window ago wait until the window passed before they are queued.
+//!
+//! Optionally tasks can be assigned to groups, for example by the schema of the document they
+//! process. Every group gets its own queue inside a worker pool and workers take tasks from the
+//! groups in turns, weighted by the configured share of each group. This keeps one group with a
+//! large backlog from starving all others.
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Debug, Display};
 use std::future::Future;
 use std::hash::Hash;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use deadqueue::unlimited::Queue;
 use log::{debug, error, info};
 use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tokio::sync::Semaphore;
 use tokio::task;
 use triggered::{Listener, Trigger};
 
+/// Number of recently completed inputs after which the ones outside of the deduplication window
+/// are forgotten.
+const MAX_COMPLETED_INPUTS: usize = 1024;
+
 /// A task holding a generic input value and the name of the worker which will process it
 /// eventually.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -142,9 +157,33 @@ pub enum TaskStatus<IN> {
     Completed(Task<IN>),
 }
 
+/// Instrumentation events emitted while workers process tasks.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TaskEvent<IN> {
+    /// A worker started processing the task.
+    Started(Task<IN>),
+
+    /// Task succeeded after this duration.
+    Finished(Task<IN>, Duration),
+
+    /// Task failed or got aborted after this duration with an error.
+    Failed(Task<IN>, Duration, String),
+}
+
 /// Workers are identified by simple string values.
 pub type WorkerName = String;
 
+/// State of an input in the task queue of a worker pool.
+enum InputState {
+    /// A task with this input waits in the queue. Duplicates can be dropped as the task will see
+    /// their changes when it gets processed.
+    Queued,
+
+    /// A worker processes a task with this input, the flag defines what happens after it
+    /// completed.
+    InProgress(PostAction),
+}
+
 /// Flags for queue items to define post-completion actions.
 enum PostAction {
     /// Moves the completed task into the queue again.
@@ -169,10 +208,14 @@ where
     /// after it completed. This is useful to account for more events which arrived _while_ the
     /// task was processed. It is enough to only remember one of the potentially many events
     /// arriving in this time, we're "batching" them for the next round.
-    input_index: Arc<Mutex<HashMap<IN, PostAction>>>,
+    input_index: Arc<Mutex<HashMap<IN, InputState>>>,
 
-    /// FIFO queue of all tasks for this worker pool.
-    queue: Arc<Queue<QueueItem<IN>>>,
+    /// Time when tasks with these inputs completed the last time, only kept while the
+    /// deduplication window did not pass yet.
+    completed_at: Arc<Mutex<HashMap<IN, Instant>>>,
+
+    /// Queue of all tasks for this worker pool, FIFO within every task group.
+    queue: Arc<FairQueue<IN>>,
 }
 
 impl<IN> WorkerManager<IN>
@@ -180,14 +223,138 @@ where
     IN: Send + Sync + Clone + Hash + Eq + Display + 'static,
 {
     /// Returns a new worker manager.
-    pub fn new() -> Self {
+    pub fn new(weights: HashMap<TaskGroup, u32>) -> Self {
         Self {
             input_index: Arc::new(Mutex::new(HashMap::new())),
-            queue: Arc::new(Queue::new()),
+            completed_at: Arc::new(Mutex::new(HashMap::new())),
+            queue: Arc::new(FairQueue::new(weights)),
         }
     }
 }
 
+/// Identifier of a group of tasks sharing worker pools fairly with other groups.
+pub type TaskGroup = String;
+
+/// Queue taking items from task groups in weighted round-robin order.
+///
+/// Every group takes as many items in a row as its weight defines (1 by default) before it is the
+/// next group's turn. Items without a group are treated as a group of their own.
+struct FairQueue<IN>
+where
+    IN: Send + Sync + Clone + Display + 'static,
+{
+    /// Items waiting in the queue, grouped by their task group.
+    groups: Mutex<FairQueueGroups<IN>>,
+
+    /// Number of items waiting in the queue.
+    available: Semaphore,
+
+    /// Number of items a group takes in a row.
+    weights: HashMap<TaskGroup, u32>,
+}
+
+struct FairQueueGroups<IN>
+where
+    IN: Send + Sync + Clone + Display + 'static,
+{
+    /// FIFO queues of every group with waiting items.
+    queues: HashMap<Option<TaskGroup>, VecDeque<QueueItem<IN>>>,
+
+    /// Groups with waiting items in the order of their turns.
+    turns: VecDeque<Option<TaskGroup>>,
+
+    /// Number of items the group at the front can still take in its current turn.
+    credit: u32,
+}
+
+impl<IN> FairQueue<IN>
+where
+    IN: Send + Sync + Clone + Display + 'static,
+{
+    fn new(weights: HashMap<TaskGroup, u32>) -> Self {
+        Self {
+            groups: Mutex::new(FairQueueGroups {
+                queues: HashMap::new(),
+                turns: VecDeque::new(),
+                credit: 0,
+            }),
+            available: Semaphore::new(0),
+            weights,
+        }
+    }
+
+    /// Adds an item to the end of the queue of its group.
+    fn push(&self, group: Option<TaskGroup>, item: QueueItem<IN>) {
+        let mut groups = self.groups.lock().expect("Fair queue lock poisoned");
+
+        let queue = groups.queues.entry(group.clone()).or_default();
+        queue.push_back(item);
+
+        // Groups which had no waiting items line up for their next turn
+        if queue.len() == 1 {
+            groups.turns.push_back(group);
+        }
+
+        self.available.add_permits(1);
+    }
+
+    /// Waits until an item is available and takes it from the group whose turn it is.
+    async fn pop(&self) -> QueueItem<IN> {
+        self.available
+            .acquire()
+            .await
+            .expect("Fair queue semaphore closed")
+            .forget();
+
+        let mut groups = self.groups.lock().expect("Fair queue lock poisoned");
+
+        let group = groups
+            .turns
+            .front()
+            .cloned()
+            .expect("Fair queue has waiting items");
+
+        if groups.credit == 0 {
+            groups.credit = group
+                .as_ref()
+                .and_then(|group| self.weights.get(group))
+                .copied()
+                .unwrap_or(1)
+                .max(1);
+        }
+        groups.credit -= 1;
+
+        let queue = groups
+            .queues
+            .get_mut(&group)
+            .expect("Group with turn has a queue");
+        let item = queue
+            .pop_front()
+            .expect("Group with turn has waiting items");
+
+        if queue.is_empty() {
+            // Group has no waiting items anymore, it is the next group's turn
+            groups.queues.remove(&group);
+            groups.turns.pop_front();
+            groups.credit = 0;
+        } else if groups.credit == 0 {
+            // Group used up its turn, line up again
+            groups.turns.rotate_left(1);
+        }
+
+        item
+    }
+
+    /// Returns true if there are no items waiting in the queue.
+    fn is_empty(&self) -> bool {
+        self.groups
+            .lock()
+            .expect("Fair queue lock poisoned")
+            .queues
+            .is_empty()
+    }
+}
+
 /// This trait defines a generic async worker function receiving the task input and shared context
 /// and returning a task result
 ///
@@ -225,6 +392,32 @@ where
     }
 }
 
+/// This trait defines a generic async function assigning a task input to a task group.
+///
+/// Tasks without a group share the worker pool as if they were a group of their own.
+#[async_trait::async_trait]
+pub trait Groupable<IN, D>
+where
+    IN: Send + Sync + Clone + 'static,
+    D: Send + Sync + 'static,
+{
+    async fn group(&self, context: D, input: IN) -> Option<TaskGroup>;
+}
+
+/// Implements our `Groupable` trait for a generic async function.
+#[async_trait::async_trait]
+impl<FN, F, IN, D> Groupable<IN, D> for FN
+where
+    FN: Fn(D, IN) -> F + Sync,
+    F: Future<Output = Option<TaskGroup>> + Send + 'static,
+    IN: Send + Sync + Clone + 'static,
+    D: Send + Sync + 'static,
+{
+    async fn group(&self, context: D, input: IN) -> Option<TaskGroup> {
+        (self)(context, input).await
+    }
+}
+
 /// Every queue consists of items which hold an unique identifier and the task input value.
 #[derive(Debug)]
 pub struct QueueItem<IN>
@@ -280,12 +473,25 @@ where
     /// Map of all registered worker pools.
     managers: HashMap<WorkerName, WorkerManager<IN>>,
 
+    /// Function assigning tasks to groups, all tasks are processed in FIFO order when not set.
+    grouping: Option<Arc<dyn Groupable<IN, D> + Send + Sync>>,
+
+    /// Number of tasks a group takes in a row before it is the next group's turn.
+    weights: HashMap<TaskGroup, u32>,
+
+    /// Minimum time between two runs of tasks with the same input, duplicates arriving in
+    /// between are coalesced into one task.
+    dedup_window: Duration,
+
     /// Broadcast channel to inform worker pools about new tasks.
     tx: Sender<Task<IN>>,
 
     /// Broadcast channel to inform callbacks about pending or completed tasks.
     tx_status: Sender<TaskStatus<IN>>,
 
+    /// Broadcast channel to inform callbacks about tasks being processed by workers.
+    tx_events: Sender<TaskEvent<IN>>,
+
     /// Sender of error signal.
     error_signal: Trigger,
 
@@ -311,18 +517,57 @@ where
     pub fn new(context: D, capacity: usize) -> Self {
         let (tx, _) = channel(capacity);
         let (tx_status, _) = channel(capacity);
+        let (tx_events, _) = channel(capacity);
         let (error_signal, error_handle) = triggered::trigger();
 
         Self {
             context,
             managers: HashMap::new(),
+            grouping: None,
+            weights: HashMap::new(),
+            dedup_window: Duration::ZERO,
             tx,
             tx_status,
+            tx_events,
             error_signal,
             error_handle,
         }
     }
 
+    /// Assigns tasks to groups which share worker pools fairly.
+    ///
+    /// Workers take tasks from the groups in turns, every group takes as many tasks in a row as
+    /// its weight defines. Groups without a configured weight have a weight of 1.
+    ///
+    /// This needs to be set before any worker pool gets registered.
+    pub fn set_grouping<G: Groupable<IN, D> + Send + Sync + 'static>(
+        &mut self,
+        grouping: G,
+        weights: HashMap<TaskGroup, u32>,
+    ) {
+        if !self.managers.is_empty() {
+            panic!("Can not set task grouping after worker pools got registered");
+        }
+
+        self.grouping = Some(Arc::new(grouping));
+        self.weights = weights;
+    }
+
+    /// Coalesces duplicate tasks arriving within the given window after a task with the same
+    /// input completed, by default tasks are queued right away.
+    ///
+    /// Tasks are held back until the window passed since the last run with the same input
+    /// completed, all duplicates arriving in the meantime are processed by this one task.
+    ///
+    /// This needs to be set before any worker pool gets registered.
+    pub fn set_dedup_window(&mut self, window: Duration) {
+        if !self.managers.is_empty() {
+            panic!("Can not set deduplication window after worker pools got registered");
+        }
+
+        self.dedup_window = window;
+    }
+
     /// Registers a new worker pool with a dedicated worker function.
     ///
     /// Choose a worker pool size fitting the work and computational resources you have at hand to
@@ -342,7 +587,7 @@ where
         if self.managers.contains_key(name) {
             panic!("Can not create task manager twice");
         } else {
-            let new_manager = WorkerManager::new();
+            let new_manager = WorkerManager::new(self.weights.clone());
             self.managers.insert(name.into(), new_manager);
         }
 
@@ -382,6 +627,11 @@ where
         self.tx_status.subscribe()
     }
 
+    /// Subscribe to tasks being started, finished or failed by workers.
+    pub fn on_task_event(&self) -> Receiver<TaskEvent<IN>> {
+        self.tx_events.subscribe()
+    }
+
     /// Spawns a task which listens to broadcast channel for incoming new tasks which might be
     /// added to the worker queue.
     fn spawn_dispatcher(&self, name: &str) {
@@ -399,9 +649,15 @@ where
 
         // Increment references to move worker data safely into the async task
         let input_index = manager.input_index.clone();
+        let completed_at = manager.completed_at.clone();
+        let dedup_window = self.dedup_window;
         let name = String::from(name);
         let queue = manager.queue.clone();
 
+        // Create handles to assign tasks to groups
+        let grouping = self.grouping.clone();
+        let context = self.context.clone();
+
         // Create handle for error signal
         let error_signal = self.error_signal.clone();
 
@@ -422,6 +678,22 @@ where
                             continue; // This is not for us ..
                         }
 
+                        // Tasks with inputs which completed recently wait until the
+                        // deduplication window passed
+                        let delay = match completed_at.lock() {
+                            Ok(mut completed_at) => match completed_at.get(&task.1) {
+                                Some(at) if at.elapsed() < dedup_window => {
+                                    Some(dedup_window - at.elapsed())
+                                }
+                                Some(_) => {
+                                    completed_at.remove(&task.1);
+                                    None
+                                }
+                                None => None,
+                            },
+                            Err(_) => None,
+                        };
+
                         // Check if a task with the same input values already exists in queue
                         match input_index.lock() {
                             Ok(mut index) => {
@@ -436,18 +708,52 @@ where
                                         // Generate a unique id for this new task and add it to queue
                                         debug!("Sending materializer {} task with input {} to the task queue.", task.worker_name(), task.input());
                                         let next_id = counter.fetch_add(1, Ordering::Relaxed);
-                                        queue.push(QueueItem::new(next_id, task.1.clone()));
-                                        index.insert(task.1, PostAction::Idle);
+                                        let item = QueueItem::new(next_id, task.1.clone());
+                                        index.insert(task.1, InputState::Queued);
+
+                                        // Looking up the group of a task might take a while,
+                                        // so we do it outside of this loop to not lag behind
+                                        // incoming tasks
+                                        if grouping.is_none() && delay.is_none() {
+                                            queue.push(None, item);
+                                        } else {
+                                            let queue = queue.clone();
+                                            let grouping = grouping.clone();
+                                            let context = context.clone();
+                                            task::spawn(async move {
+                                                let group = match grouping {
+                                                    Some(grouping) => {
+                                                        grouping.group(context, item.input()).await
+                                                    }
+                                                    None => None,
+                                                };
+
+                                                if let Some(delay) = delay {
+                                                    tokio::time::sleep(delay).await;
+                                                }
+
+                                                queue.push(group, item);
+                                            });
+                                        }
+                                    }
+                                    Some(InputState::Queued) => {
+                                        // 2. The same task is still waiting in the queue, it will
+                                        // see the changes of this duplicate when it gets processed
+                                        debug!("Materializer {} task with input {} not sent to queue as it is already waiting in the queue.", task.worker_name(), task.input());
+                                        continue;
                                     }
-                                    Some(PostAction::Idle) => {
-                                        // 2. This is the first duplicate coming in, let's set the
-                                        // requeue flag to indicate that more work needs to be done
-                                        // when the current task completes
+                                    Some(InputState::InProgress(PostAction::Idle)) => {
+                                        // 3. This is the first duplicate coming in while the task
+                                        // is processed, let's set the requeue flag to indicate
+                                        // that more work needs to be done when it completes
                                         debug!("Duplicate materializer {} task already in progress, setting re-queue flag for task with input {} and not adding this task to the queue.", task.worker_name(), task.input());
-                                        index.insert(task.1, PostAction::Requeue);
+                                        index.insert(
+                                            task.1,
+                                            InputState::InProgress(PostAction::Requeue),
+                                        );
                                     }
-                                    Some(PostAction::Requeue) => {
-                                        // 3. We observed already one duplicate task coming in, let's
+                                    Some(InputState::InProgress(PostAction::Requeue)) => {
+                                        // 4. We observed already one duplicate task coming in, let's
                                         // ignore this one
                                         debug!("Materializer {} task with input {} not sent to queue as a task for this document has already been re-queued.", task.worker_name(), task.input());
                                         continue;
@@ -491,14 +797,17 @@ where
             let context = self.context.clone();
             let queue = manager.queue.clone();
             let input_index = manager.input_index.clone();
+            let completed_at = manager.completed_at.clone();
+            let dedup_window = self.dedup_window;
             let tx = self.tx.clone();
             let name = name.to_string();
 
             // Create handle for error signal
             let error_signal = self.error_signal.clone();
 
-            // Create handle to send task status updates
+            // Create handles to send task status updates and events
             let tx_status = self.tx_status.clone();
+            let tx_events = self.tx_events.clone();
 
             task::spawn(async move {
                 // Inform status subscribers that we just completed a task
@@ -514,9 +823,29 @@ where
                     // Wait until there is a new task arriving in the queue
                     let item = queue.pop().await;
 
+                    // Mark the task as in progress, duplicates arriving from now on cause it to
+                    // be re-scheduled after it completed
+                    if let Ok(mut index) = input_index.lock() {
+                        index.insert(item.input(), InputState::InProgress(PostAction::Idle));
+                    }
+
                     // Take this task and do work ..
+                    //
+                    // Sending events only fails when there are no subscribers, we don't mind that
+                    let task = Task::new(&name, item.input());
+                    let _ = tx_events.send(TaskEvent::Started(task.clone()));
+
+                    let started_at = Instant::now();
                     let result = work.call(context.clone(), item.input()).await;
 
+                    let duration = started_at.elapsed();
+                    let _ = tx_events.send(match &result {
+                        Ok(_) => TaskEvent::Finished(task, duration),
+                        Err(TaskError::Critical(err)) | Err(TaskError::Failure(err)) => {
+                            TaskEvent::Failed(task, duration, err.to_owned())
+                        }
+                    });
+
                     // Check the result
                     match result {
                         Ok(Some(list)) => {
@@ -543,12 +872,24 @@ where
                         _ => (), // Task succeeded, but nothing to dispatch
                     };
 
+                    // Remember when this input completed to coalesce duplicates arriving within the
+                    // deduplication window
+                    if !dedup_window.is_zero() {
+                        if let Ok(mut completed_at) = completed_at.lock() {
+                            if completed_at.len() >= MAX_COMPLETED_INPUTS {
+                                completed_at.retain(|_, at| at.elapsed() < dedup_window);
+                            }
+
+                            completed_at.insert(item.input(), Instant::now());
+                        }
+                    }
+
                     // Remove input index from queue and check if we should requeue that task
                     let requeue = match input_index.lock() {
                         Ok(mut index) => match index.remove(&item.input()) {
-                            Some(PostAction::Idle) => false,
-                            Some(PostAction::Requeue) => true,
-                            None => {
+                            Some(InputState::InProgress(PostAction::Idle)) => false,
+                            Some(InputState::InProgress(PostAction::Requeue)) => true,
+                            Some(InputState::Queued) | None => {
                                 error!("Incosistency detected in queue input index");
                                 error_signal.trigger();
                                 false
@@ -586,12 +927,15 @@ mod tests {
     use std::collections::HashMap;
     use std::fmt::Display;
     use std::sync::{Arc, Mutex};
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     use rand::seq::SliceRandom;
     use rand::Rng;
 
-    use super::{Factory, Task, TaskError, TaskResult, TaskStatus};
+    use super::{
+        Factory, FairQueue, QueueItem, Task, TaskError, TaskEvent, TaskGroup, TaskResult,
+        TaskStatus,
+    };
 
     #[tokio::test]
     async fn factory() {
@@ -639,6 +983,126 @@ mod tests {
         assert!(factory.is_empty("second"));
     }
 
+    #[tokio::test]
+    async fn deduplicate_tasks() {
+        type Input = usize;
+        type Data = Arc<Mutex<Vec<(Input, Instant)>>>;
+
+        // Test database which records when tasks completed
+        let database = Arc::new(Mutex::new(Vec::new()));
+
+        let mut factory = Factory::<Input, Data>::new(database.clone(), 1024);
+        factory.set_dedup_window(Duration::from_millis(200));
+
+        async fn work(database: Data, input: Input) -> TaskResult<Input> {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let mut db = database
+                .lock()
+                .map_err(|err| TaskError::Critical(err.to_string()))?;
+            db.push((input, Instant::now()));
+            Ok(None)
+        }
+
+        factory.register("work", 1, work);
+
+        // Duplicates of a task which is still waiting in the queue are dropped
+        factory.queue(Task::new("work", 0));
+        for _ in 0..5 {
+            factory.queue(Task::new("work", 1));
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let inputs: Vec<Input> = database.lock().unwrap().iter().map(|(i, _)| *i).collect();
+        assert_eq!(inputs, vec![0, 1]);
+
+        // Duplicates arriving right after a task completed wait until the window passed and get
+        // coalesced into one task
+        for _ in 0..3 {
+            factory.queue(Task::new("work", 1));
+        }
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let runs = database.lock().unwrap().clone();
+        assert_eq!(runs.len(), 3);
+        assert!(runs[2].1 - runs[1].1 >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn slow_grouping_does_not_block_dispatch() {
+        type Input = usize;
+        type Data = Arc<Mutex<Vec<Input>>>;
+
+        let database = Arc::new(Mutex::new(Vec::new()));
+
+        // Use a tiny channel, the dispatcher would lag behind if it waited for every group
+        let mut factory = Factory::<Input, Data>::new(database.clone(), 4);
+
+        async fn group(_database: Data, input: Input) -> Option<TaskGroup> {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Some((input % 2).to_string())
+        }
+
+        async fn work(database: Data, input: Input) -> TaskResult<Input> {
+            let mut db = database
+                .lock()
+                .map_err(|err| TaskError::Critical(err.to_string()))?;
+            db.push(input);
+            Ok(None)
+        }
+
+        factory.set_grouping(group, HashMap::new());
+        factory.register("work", 4, work);
+        let on_error = factory.on_error();
+
+        for i in 0..32 {
+            factory.queue(Task::new("work", i));
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        // All tasks got processed without the error signal being triggered
+        let result = tokio::time::timeout(Duration::from_millis(300), on_error).await;
+        assert!(result.is_err());
+        assert_eq!(database.lock().unwrap().len(), 32);
+    }
+
+    #[tokio::test]
+    async fn fair_queue() {
+        let queue = FairQueue::new(HashMap::from([("a".to_string(), 2)]));
+
+        for (id, (group, input)) in [
+            (Some("a"), "a1"),
+            (Some("a"), "a2"),
+            (Some("a"), "a3"),
+            (Some("a"), "a4"),
+            (Some("b"), "b1"),
+            (Some("b"), "b2"),
+            (None, "n1"),
+        ]
+        .iter()
+        .enumerate()
+        {
+            queue.push(
+                group.map(String::from),
+                QueueItem::new(id as u64, input.to_string()),
+            );
+        }
+
+        // Groups take turns, "a" takes two items in a row as it has a weight of 2
+        let mut order = Vec::new();
+        while !queue.is_empty() {
+            order.push(queue.pop().await.input());
+        }
+        assert_eq!(order, vec!["a1", "a2", "b1", "n1", "a3", "a4", "b2"]);
+
+        // Groups without waiting items line up again at the end
+        queue.push(Some("b".into()), QueueItem::new(7, "b3".into()));
+        queue.push(Some("a".into()), QueueItem::new(8, "a5".into()));
+        queue.push(Some("b".into()), QueueItem::new(9, "b4".into()));
+        assert_eq!(queue.pop().await.input(), "b3");
+        assert_eq!(queue.pop().await.input(), "a5");
+        assert_eq!(queue.pop().await.input(), "b4");
+    }
+
     #[tokio::test]
     async fn on_task_status_change_subscription() {
         type Input = usize;
@@ -681,6 +1145,43 @@ mod tests {
         assert_eq!(messages.lock().unwrap().len(), 12);
     }
 
+    #[tokio::test]
+    async fn on_task_event_subscription() {
+        type Input = usize;
+        type Data = usize;
+
+        let mut factory = Factory::<Input, Data>::new(1, 1024);
+        let mut on_task_event = factory.on_task_event();
+
+        factory.register("even", 1, |_, input: Input| async move {
+            if input % 2 == 0 {
+                Ok(None)
+            } else {
+                Err(TaskError::Failure("Odd number".into()))
+            }
+        });
+
+        factory.queue(Task::new("even", 2));
+        assert_eq!(
+            on_task_event.recv().await.unwrap(),
+            TaskEvent::Started(Task::new("even", 2))
+        );
+        assert!(matches!(
+            on_task_event.recv().await.unwrap(),
+            TaskEvent::Finished(task, _) if task == Task::new("even", 2)
+        ));
+
+        factory.queue(Task::new("even", 3));
+        assert_eq!(
+            on_task_event.recv().await.unwrap(),
+            TaskEvent::Started(Task::new("even", 3))
+        );
+        assert!(matches!(
+            on_task_event.recv().await.unwrap(),
+            TaskEvent::Failed(task, _, err) if task == Task::new("even", 3) && err == "Odd number"
+        ));
+    }
+
     #[tokio::test]
     async fn jigsaw() {
         // This test solves multiple jigsaw puzzles with our task queue implementation.
@@ -0,0 +1,205 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::identity::PublicKey;
+
+use crate::bus::ServiceMessage;
+use crate::network::NetworkMetrics;
+
+/// Prefix of metric names when pushed to statsd or graphite.
+const METRICS_PREFIX: &str = "aquadoggo";
+
+/// Collects node metrics from messages on the service bus.
+#[derive(Debug, Default, Clone)]
+pub struct Metrics {
+    /// Number of currently connected peers.
+    connected_peers: u64,
+
+    /// Total number of operations which arrived at the node.
+    operations_received: u64,
+
+    /// Total number of replication messages sent to other peers.
+    messages_sent: u64,
+
+    /// Total number of replication messages received from other peers.
+    messages_received: u64,
+
+    /// Total number of failed replication sessions.
+    replication_failures: u64,
+
+    /// Bandwidth and connection metrics of the network service.
+    network: NetworkMetrics,
+}
+
+impl Metrics {
+    /// Returns a new collector including the given network metrics in its snapshots.
+    pub fn new(network: NetworkMetrics) -> Self {
+        Self {
+            network,
+            ..Default::default()
+        }
+    }
+
+    /// Update metrics based on a message from the service bus.
+    pub fn handle_message(&mut self, message: &ServiceMessage) {
+        match message {
+            ServiceMessage::NewOperation(_) => self.operations_received += 1,
+            ServiceMessage::PeerConnected(_, _) => self.connected_peers += 1,
+            ServiceMessage::PeerDisconnected(_, _) => {
+                self.connected_peers = self.connected_peers.saturating_sub(1)
+            }
+            ServiceMessage::SentMessage(_, _) => self.messages_sent += 1,
+            ServiceMessage::ReceivedMessage(_, _) => self.messages_received += 1,
+            ServiceMessage::ReplicationFailed(_) => self.replication_failures += 1,
+            _ => (),
+        }
+    }
+
+    /// Returns the current state of all metrics.
+    ///
+    /// Network metrics are named after transport and protocol, for example
+    /// `network_tcp_relay_inbound_bytes`. Only transports and protocols which have been used are
+    /// included.
+    pub fn snapshot(&self, public_key: PublicKey, timestamp: u64) -> MetricsSnapshot {
+        let mut values: Vec<(String, u64)> = vec![
+            ("connected_peers".into(), self.connected_peers),
+            ("operations_received".into(), self.operations_received),
+            ("replication_messages_sent".into(), self.messages_sent),
+            (
+                "replication_messages_received".into(),
+                self.messages_received,
+            ),
+            ("replication_failures".into(), self.replication_failures),
+        ];
+
+        for (class, metrics) in self.network.snapshot() {
+            let prefix = format!(
+                "network_{}_{}",
+                class.transport_str(),
+                class.protocol.as_str()
+            );
+            values.push((format!("{prefix}_inbound_bytes"), metrics.inbound_bytes));
+            values.push((format!("{prefix}_outbound_bytes"), metrics.outbound_bytes));
+            values.push((
+                format!("{prefix}_open_connections"),
+                metrics.open_connections,
+            ));
+            values.push((
+                format!("{prefix}_total_connections"),
+                metrics.total_connections,
+            ));
+        }
+
+        MetricsSnapshot {
+            public_key,
+            timestamp,
+            values,
+        }
+    }
+}
+
+/// Metric values of a node at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Public key of the node.
+    pub public_key: PublicKey,
+
+    /// UNIX timestamp in seconds of when the snapshot was taken.
+    pub timestamp: u64,
+
+    /// Names and values of all metrics.
+    pub values: Vec<(String, u64)>,
+}
+
+impl MetricsSnapshot {
+    /// Encodes snapshot as JSON object.
+    pub fn to_json(&self) -> String {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(name, value)| format!("\"{name}\":{value}"))
+            .collect();
+
+        format!(
+            "{{\"public_key\":\"{}\",\"timestamp\":{},\"metrics\":{{{}}}}}",
+            self.public_key,
+            self.timestamp,
+            values.join(",")
+        )
+    }
+
+    /// Encodes snapshot as statsd gauges, one per line.
+    pub fn to_statsd(&self) -> String {
+        self.values
+            .iter()
+            .map(|(name, value)| format!("{METRICS_PREFIX}.{name}:{value}|g\n"))
+            .collect()
+    }
+
+    /// Encodes snapshot in the graphite plaintext protocol, one metric per line.
+    pub fn to_graphite(&self) -> String {
+        self.values
+            .iter()
+            .map(|(name, value)| format!("{METRICS_PREFIX}.{name} {value} {}\n", self.timestamp))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::swarm::ConnectionId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_operation_id};
+    use rstest::rstest;
+
+    use crate::bus::ServiceMessage;
+    use crate::network::NetworkMetrics;
+
+    use super::Metrics;
+
+    #[rstest]
+    fn collect_and_encode_metrics(key_pair: KeyPair) {
+        let mut metrics = Metrics::default();
+        metrics.handle_message(&ServiceMessage::NewOperation(random_operation_id()));
+        metrics.handle_message(&ServiceMessage::NewOperation(random_operation_id()));
+        metrics.handle_message(&ServiceMessage::SyncComplete);
+
+        let snapshot = metrics.snapshot(key_pair.public_key(), 1000);
+
+        assert_eq!(
+            snapshot.to_json(),
+            format!(
+                "{{\"public_key\":\"{}\",\"timestamp\":1000,\"metrics\":{{\"connected_peers\":0,\
+                \"operations_received\":2,\"replication_messages_sent\":0,\
+                \"replication_messages_received\":0,\"replication_failures\":0}}}}",
+                key_pair.public_key()
+            )
+        );
+        assert!(snapshot
+            .to_statsd()
+            .starts_with("aquadoggo.connected_peers:0|g\naquadoggo.operations_received:2|g\n"));
+        assert!(snapshot.to_graphite().starts_with(
+            "aquadoggo.connected_peers 0 1000\naquadoggo.operations_received 2 1000\n"
+        ));
+    }
+
+    #[rstest]
+    fn include_network_metrics(key_pair: KeyPair) {
+        let network = NetworkMetrics::default();
+        let metrics = Metrics::new(network.clone());
+
+        let address = "/ip4/127.0.0.1/udp/2022/quic-v1".parse().unwrap();
+        network.on_connection_established(ConnectionId::new_unchecked(1), &address);
+
+        let snapshot = metrics.snapshot(key_pair.public_key(), 1000);
+        assert!(snapshot
+            .values
+            .contains(&("network_quic_replication_open_connections".to_string(), 1)));
+        assert!(snapshot
+            .values
+            .contains(&("network_quic_replication_inbound_bytes".to_string(), 0)));
+        assert!(!snapshot
+            .values
+            .iter()
+            .any(|(name, _)| name.starts_with("network_tcp")));
+    }
+}
@@ -0,0 +1,13 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Periodic push of node metrics to a remote endpoint.
+//!
+//! Nodes running behind a NAT can not be scraped by a metrics collector. Instead they push
+//! snapshots of their metrics to a configured HTTP endpoint, statsd or graphite server.
+mod collector;
+mod service;
+mod target;
+
+pub use collector::{Metrics, MetricsSnapshot};
+pub use service::metrics_service;
+pub use target::MetricsTarget;
@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request};
+use log::{debug, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::bus::ServiceSender;
+use crate::context::Context;
+use crate::manager::{ServiceReadySender, Shutdown};
+use crate::metrics::{Metrics, MetricsSnapshot, MetricsTarget};
+use crate::replication::now;
+
+/// Send a metrics snapshot to the remote target.
+pub async fn push_snapshot(target: &MetricsTarget, snapshot: &MetricsSnapshot) -> Result<()> {
+    match target {
+        MetricsTarget::Http(uri) => {
+            let request = Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(snapshot.to_json()))?;
+
+            let response = Client::new().request(request).await?;
+            if !response.status().is_success() {
+                return Err(anyhow!(
+                    "Endpoint responded with status {}",
+                    response.status()
+                ));
+            }
+        }
+        MetricsTarget::Statsd(address) => {
+            let address = lookup_host(address)
+                .await?
+                .next()
+                .ok_or_else(|| anyhow!("Could not resolve address {}", address))?;
+            let bind_address = if address.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+
+            let socket = UdpSocket::bind(bind_address).await?;
+            socket
+                .send_to(snapshot.to_statsd().as_bytes(), address)
+                .await?;
+        }
+        MetricsTarget::Graphite(address) => {
+            let mut stream = TcpStream::connect(address).await?;
+            stream.write_all(snapshot.to_graphite().as_bytes()).await?;
+            stream.shutdown().await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The metrics service collects node metrics from the service bus and periodically pushes
+/// snapshots of them to a configured remote target.
+pub async fn metrics_service(
+    context: Context,
+    shutdown: Shutdown,
+    tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()> {
+    let target = match &context.config.metrics_push_target {
+        Some(target) => target.clone(),
+        None => return Err(anyhow!("No metrics push target configured")),
+    };
+    let period = Duration::from_secs(context.config.metrics_push_interval.max(1));
+    let public_key = context.key_pair.public_key();
+    let network_metrics = context.network_metrics.clone();
+
+    let mut rx = tx.subscribe();
+
+    let handle = task::spawn(async move {
+        let mut metrics = Metrics::new(network_metrics);
+        let mut interval = interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                message = rx.recv() => match message {
+                    Ok(message) => metrics.handle_message(&message),
+                    Err(RecvError::Lagged(_)) => (),
+                    Err(RecvError::Closed) => break,
+                },
+                _ = interval.tick() => {
+                    let snapshot = metrics.snapshot(public_key, now());
+                    let target = target.clone();
+
+                    // Push in separate task to not block receiving messages from the bus
+                    task::spawn(async move {
+                        match push_snapshot(&target, &snapshot).await {
+                            Ok(()) => debug!("Pushed metrics snapshot to {}", target),
+                            Err(err) => warn!("Failed pushing metrics to {}: {}", target, err),
+                        }
+                    });
+                }
+            }
+        }
+    });
+
+    if tx_ready.send(()).is_err() {
+        warn!("No subscriber informed about metrics service being ready");
+    };
+
+    tokio::select! {
+        _ = handle => (),
+        _ = shutdown => (),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::{TcpListener, UdpSocket};
+
+    use crate::metrics::{Metrics, MetricsTarget};
+
+    use super::push_snapshot;
+
+    #[rstest]
+    #[tokio::test]
+    async fn push_to_statsd_and_graphite(key_pair: KeyPair) {
+        let snapshot = Metrics::default().snapshot(key_pair.public_key(), 1000);
+
+        // Statsd receives gauges via UDP
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = MetricsTarget::Statsd(socket.local_addr().unwrap().to_string());
+        push_snapshot(&target, &snapshot).await.unwrap();
+
+        let mut buf = vec![0; 1024];
+        let len = socket.recv(&mut buf).await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&buf[..len]), snapshot.to_statsd());
+
+        // Graphite receives plaintext lines via TCP
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = MetricsTarget::Graphite(listener.local_addr().unwrap().to_string());
+        let (result, accepted) = tokio::join!(push_snapshot(&target, &snapshot), listener.accept());
+        result.unwrap();
+
+        let mut received = String::new();
+        accepted
+            .unwrap()
+            .0
+            .read_to_string(&mut received)
+            .await
+            .unwrap();
+        assert_eq!(received, snapshot.to_graphite());
+    }
+}
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use http::Uri;
+use thiserror::Error;
+
+/// Default port of statsd servers.
+const STATSD_DEFAULT_PORT: u16 = 8125;
+
+/// Default port of graphite servers receiving the plaintext protocol.
+const GRAPHITE_DEFAULT_PORT: u16 = 2003;
+
+/// Remote endpoint receiving metric snapshots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsTarget {
+    /// HTTP endpoint receiving snapshots as JSON via POST requests, for example
+    /// `http://localhost:9091/metrics`.
+    Http(Uri),
+
+    /// Statsd server receiving gauges via UDP, for example `statsd://localhost:8125`.
+    Statsd(String),
+
+    /// Graphite server receiving metrics via the plaintext protocol over TCP, for example
+    /// `graphite://localhost:2003`.
+    Graphite(String),
+}
+
+impl FromStr for MetricsTarget {
+    type Err = MetricsTargetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let uri = Uri::from_str(s).map_err(|_| MetricsTargetError::InvalidUrl(s.to_string()))?;
+
+        let address = |default_port: u16| {
+            let authority = uri
+                .authority()
+                .ok_or_else(|| MetricsTargetError::InvalidUrl(s.to_string()))?;
+            let port = authority.port_u16().unwrap_or(default_port);
+            Ok(format!("{}:{}", authority.host(), port))
+        };
+
+        match uri.scheme_str() {
+            Some("http") => Ok(MetricsTarget::Http(uri.clone())),
+            Some("statsd") => Ok(MetricsTarget::Statsd(address(STATSD_DEFAULT_PORT)?)),
+            Some("graphite") => Ok(MetricsTarget::Graphite(address(GRAPHITE_DEFAULT_PORT)?)),
+            _ => Err(MetricsTargetError::UnsupportedScheme(s.to_string())),
+        }
+    }
+}
+
+impl Display for MetricsTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetricsTarget::Http(uri) => write!(f, "{}", uri),
+            MetricsTarget::Statsd(address) => write!(f, "statsd://{}", address),
+            MetricsTarget::Graphite(address) => write!(f, "graphite://{}", address),
+        }
+    }
+}
+
+/// Errors returned when parsing metrics targets.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MetricsTargetError {
+    /// Target is not a valid URL.
+    #[error("Invalid metrics target URL '{0}'")]
+    InvalidUrl(String),
+
+    /// Target uses a protocol we can't push metrics to.
+    #[error("Unsupported metrics target '{0}', expected http://, statsd:// or graphite:// URL")]
+    UnsupportedScheme(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use rstest::rstest;
+
+    use super::{MetricsTarget, MetricsTargetError};
+
+    #[rstest]
+    #[case(
+        "http://localhost:9091/metrics",
+        MetricsTarget::Http("http://localhost:9091/metrics".parse().unwrap())
+    )]
+    #[case("statsd://127.0.0.1", MetricsTarget::Statsd("127.0.0.1:8125".into()))]
+    #[case("statsd://127.0.0.1:9125", MetricsTarget::Statsd("127.0.0.1:9125".into()))]
+    #[case("graphite://metrics.local", MetricsTarget::Graphite("metrics.local:2003".into()))]
+    fn parse_targets(#[case] url: &str, #[case] expected: MetricsTarget) {
+        assert_eq!(MetricsTarget::from_str(url), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("https://localhost/metrics")]
+    #[case("ftp://localhost")]
+    #[case("localhost:8125")]
+    fn unsupported_targets(#[case] url: &str) {
+        assert!(matches!(
+            MetricsTarget::from_str(url),
+            Err(MetricsTargetError::UnsupportedScheme(_)) | Err(MetricsTargetError::InvalidUrl(_))
+        ));
+    }
+}
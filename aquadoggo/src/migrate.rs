@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Applying and reverting database migrations without starting the node.
+use anyhow::Result;
+use log::info;
+
+use crate::config::Configuration;
+use crate::db::{
+    connection_pool, create_database, pending_migrations, revert_migrations,
+    run_pending_migrations, MigrationStep,
+};
+
+/// Migrations to run against the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrateAction {
+    /// Apply all pending migrations, as the node does on start.
+    Apply,
+
+    /// Revert the given number of most recently applied migrations.
+    Revert(usize),
+}
+
+/// Apply or revert migrations of the node's database, returns the affected migrations in the
+/// order they were run.
+///
+/// With `dry_run` the database is not changed, the affected migrations are only reported together
+/// with the current number of rows in the tables they touch. This gives an estimate of how long
+/// a migration takes on a large database.
+///
+/// Only the most recent migrations can be reverted, reverting fails without changing the
+/// database if any of the requested migrations has no down migration. Reverting drops the data
+/// of the reverted tables and columns.
+pub async fn migrate_database(
+    config: &Configuration,
+    action: MigrateAction,
+    dry_run: bool,
+) -> Result<Vec<MigrationStep>> {
+    if action == MigrateAction::Apply && !dry_run {
+        create_database(&config.database_url).await?;
+    }
+
+    let pool = connection_pool(&config.database_url, 1, config.database_key.as_deref()).await?;
+
+    let steps = match action {
+        MigrateAction::Apply => {
+            let steps = pending_migrations(&pool).await;
+            if !dry_run && steps.is_ok() {
+                info!("Apply pending database migrations");
+                run_pending_migrations(&pool).await?;
+            }
+            steps
+        }
+        MigrateAction::Revert(count) => revert_migrations(&pool, count, dry_run).await,
+    };
+
+    pool.close().await;
+    steps
+}
@@ -7,12 +7,14 @@ use libp2p::allow_block_list;
 use libp2p::allow_block_list::{AllowedPeers, BlockedPeers};
 use libp2p::identity::Keypair;
 use libp2p::swarm::behaviour::toggle::Toggle;
-use libp2p::swarm::NetworkBehaviour;
+use libp2p::swarm::{dummy, NetworkBehaviour};
 use libp2p::{connection_limits, dcutr, identify, mdns, relay, rendezvous};
 use log::debug;
 
 use crate::network::config::NODE_NAMESPACE;
+use crate::network::custom::CustomBehaviour;
 use crate::network::peers;
+use crate::network::rendezvous_server;
 use crate::network::NetworkConfiguration;
 use crate::AllowList;
 
@@ -39,9 +41,12 @@ const RELAY_MAX_CIRCUIT_DURATION: Duration = Duration::from_secs(60 * 60); // 1
 /// capabilities of each peer for us and upgrades the protocol accordingly. For example two peers
 /// can handle p2panda messages with each others (using the `peers` behaviour) but do not
 /// necessarily need to be able to support the `relay` behaviour.
+///
+/// Applications embedding aquadoggo can add their own behaviour next to the ones of the node, see
+/// `CustomBehaviour`.
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "Event", event_process = false)]
-pub struct P2pandaBehaviour {
+pub struct P2pandaBehaviour<B: NetworkBehaviour = dummy::Behaviour> {
     /// Periodically exchange information between peer on an established connection. This is useful
     /// for learning the external address of the local node from a remote peer.
     pub identify: Toggle<identify::Behaviour>,
@@ -65,7 +70,7 @@ pub struct P2pandaBehaviour {
 
     /// Serve as a rendezvous point for remote peers to register their external addresses and query
     /// the addresses of other peers.
-    pub rendezvous_server: Toggle<rendezvous::server::Behaviour>,
+    pub rendezvous_server: Toggle<rendezvous_server::Behaviour>,
 
     /// Allow two peers behind NAT to communicate directly by utilizing a technique called hole
     /// punching.
@@ -82,6 +87,9 @@ pub struct P2pandaBehaviour {
 
     /// Register peer connections and handle p2panda messaging with them.
     pub peers: peers::Behaviour,
+
+    /// Network behaviour of the application embedding aquadoggo.
+    pub custom: CustomBehaviour<B>,
 }
 
 impl P2pandaBehaviour {
@@ -91,6 +99,24 @@ impl P2pandaBehaviour {
         network_config: &NetworkConfiguration,
         key_pair: &Keypair,
         relay_client: Option<relay::client::Behaviour>,
+    ) -> Result<Self> {
+        Self::with_custom(
+            network_config,
+            key_pair,
+            relay_client,
+            CustomBehaviour::default(),
+        )
+    }
+}
+
+impl<B: NetworkBehaviour> P2pandaBehaviour<B> {
+    /// Generate a new instance of the composed network behaviour according to the network
+    /// configuration, running the given network behaviour of an application next to it.
+    pub fn with_custom(
+        network_config: &NetworkConfiguration,
+        key_pair: &Keypair,
+        relay_client: Option<relay::client::Behaviour>,
+        custom: CustomBehaviour<B>,
     ) -> Result<Self> {
         let peer_id = key_pair.public().to_peer_id();
 
@@ -132,13 +158,11 @@ impl P2pandaBehaviour {
             None
         };
 
-        // Create a rendezvous server behaviour with default configuration if the rendezvous server
-        // flag is set
+        // Create a rendezvous server behaviour with configured TTL bounds and registration quota if
+        // the relay server flag is set
         let rendezvous_server = if network_config.relay_mode {
             debug!("Rendezvous server network behaviour enabled");
-            Some(rendezvous::server::Behaviour::new(
-                rendezvous::server::Config::default(),
-            ))
+            Some(rendezvous_server::Behaviour::new(network_config))
         } else {
             None
         };
@@ -194,7 +218,7 @@ impl P2pandaBehaviour {
         };
 
         // Always create behaviour to manage peer connections and handle p2panda messaging
-        let peers = peers::Behaviour::new();
+        let peers = peers::Behaviour::new(network_config.faults.clone());
 
         Ok(Self {
             identify: identify.into(),
@@ -208,6 +232,7 @@ impl P2pandaBehaviour {
             peers,
             allowed_peers: allowed_peers.into(),
             blocked_peers: blocked_peers.into(),
+            custom,
         })
     }
 }
@@ -221,7 +246,6 @@ pub enum Event {
     #[allow(dead_code)]
     RelayServer(relay::Event),
     RendezvousClient(rendezvous::client::Event),
-    #[allow(dead_code)]
     RendezvousServer(rendezvous::server::Event),
     Dcutr(dcutr::Event),
     Peers(peers::Event),
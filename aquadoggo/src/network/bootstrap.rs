@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use libp2p::multiaddr::Protocol;
+use libp2p::swarm::ConnectionId;
+use libp2p::{Multiaddr, PeerId};
+
+use crate::network::Transport;
+use crate::replication::now;
+
+/// Highest health score a bootstrap peer can reach.
+pub const MAX_SCORE: i64 = 10;
+
+/// Lowest health score a bootstrap peer can reach.
+///
+/// Learned peers reaching this score are forgotten, configured peers stay in the list.
+pub const MIN_SCORE: i64 = -10;
+
+/// Score added after a successful connection attempt.
+const SUCCESS_REWARD: i64 = 1;
+
+/// Score deducted after a failed connection attempt.
+const FAILURE_PENALTY: i64 = 2;
+
+/// Peer we can dial to join the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapPeer {
+    /// Address we dial to reach this peer.
+    pub address: Multiaddr,
+
+    /// Peer id, known after we connected to this peer at least once.
+    pub peer_id: Option<PeerId>,
+
+    /// Health score, increasing with successful and decreasing with failed connection attempts.
+    pub score: i64,
+
+    /// Flag indicating if this peer was configured by the user or learned during runtime.
+    pub configured: bool,
+
+    /// UNIX timestamp in seconds of the last successful connection to this peer.
+    pub last_seen: Option<u64>,
+
+    /// Number of successful connection attempts.
+    pub successes: u64,
+
+    /// Number of failed connection attempts.
+    pub failures: u64,
+
+    /// Counter of the round this peer was last dialed in, used to rotate between peers of the same
+    /// score.
+    last_dialed: u64,
+}
+
+impl BootstrapPeer {
+    /// Returns a new bootstrap peer with neutral health score.
+    pub fn new(address: Multiaddr, peer_id: Option<PeerId>, configured: bool) -> Self {
+        Self {
+            address: without_peer_id(&address),
+            peer_id,
+            score: 0,
+            configured,
+            last_seen: None,
+            successes: 0,
+            failures: 0,
+            last_dialed: 0,
+        }
+    }
+
+    /// Returns true if we did not connect to this peer for longer than the given duration in
+    /// seconds.
+    fn is_expired(&self, expiry: u64, now: u64) -> bool {
+        self.last_seen
+            .is_some_and(|last_seen| now.saturating_sub(last_seen) > expiry)
+    }
+}
+
+/// Keeps track of peers we can dial to reach a target number of connections.
+///
+/// Peers are rotated by their health score: peers which we could reach in the past are dialed
+/// first, peers of the same score are dialed in turns. Failed connection attempts lower the score
+/// of a peer.
+///
+/// Learned peers are forgotten when they reached the lowest score or when a connection attempt
+/// fails and we did not reach them for longer than the expiry duration.
+#[derive(Debug)]
+pub struct BootstrapPeers {
+    /// Number of connections we try to keep up with other peers.
+    target_connections: usize,
+
+    /// Duration in seconds after which learned peers we can not reach anymore are forgotten.
+    expiry: u64,
+
+    /// All known bootstrap peers.
+    peers: Vec<BootstrapPeer>,
+
+    /// Addresses of outgoing connection attempts which did not resolve yet.
+    pending_dials: HashMap<ConnectionId, Multiaddr>,
+
+    /// Counter of dial rounds.
+    round: u64,
+}
+
+impl BootstrapPeers {
+    /// Returns a new, empty set of bootstrap peers.
+    pub fn new(target_connections: usize, expiry: u64) -> Self {
+        Self {
+            target_connections,
+            expiry,
+            peers: Vec::new(),
+            pending_dials: HashMap::new(),
+            round: 0,
+        }
+    }
+
+    /// Adds a peer to the set, updating peer id and score if it is already known.
+    pub fn insert(&mut self, peer: BootstrapPeer) {
+        match self
+            .peers
+            .iter_mut()
+            .find(|known| known.address == peer.address)
+        {
+            Some(known) => {
+                known.peer_id = peer.peer_id.or(known.peer_id);
+                known.score = known.score.max(peer.score);
+                known.configured |= peer.configured;
+                known.last_seen = known.last_seen.max(peer.last_seen);
+                known.successes = known.successes.max(peer.successes);
+                known.failures = known.failures.max(peer.failures);
+            }
+            None => self.peers.push(peer),
+        }
+    }
+
+    /// Returns true if a peer with this address is known.
+    pub fn contains(&self, address: &Multiaddr) -> bool {
+        self.peers.iter().any(|peer| &peer.address == address)
+    }
+
+    /// Returns all known bootstrap peers.
+    #[cfg(test)]
+    pub fn peers(&self) -> &[BootstrapPeer] {
+        &self.peers
+    }
+
+    /// Returns the addresses of peers we should dial next to reach our target number of
+    /// connections.
+    ///
+    /// Peers we are already connected to or which are currently being dialed are skipped.
+    pub fn next_dials(
+        &mut self,
+        connected_peers: &[PeerId],
+        transport: Transport,
+    ) -> Vec<(Multiaddr, Option<PeerId>)> {
+        let missing = self
+            .target_connections
+            .saturating_sub(connected_peers.len() + self.pending_dials.len());
+
+        if missing == 0 {
+            return Vec::new();
+        }
+
+        self.round += 1;
+        let round = self.round;
+
+        let pending_dials: Vec<&Multiaddr> = self.pending_dials.values().collect();
+        let mut candidates: Vec<&mut BootstrapPeer> = self
+            .peers
+            .iter_mut()
+            .filter(|peer| supports_transport(&peer.address, transport))
+            .filter(|peer| !pending_dials.contains(&&peer.address))
+            .filter(|peer| {
+                peer.peer_id
+                    .is_none_or(|peer_id| !connected_peers.contains(&peer_id))
+            })
+            .collect();
+
+        // Dial healthiest peers first, rotate between peers of the same score
+        candidates.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.last_dialed.cmp(&b.last_dialed))
+        });
+
+        candidates
+            .into_iter()
+            .take(missing)
+            .map(|peer| {
+                peer.last_dialed = round;
+                (peer.address.clone(), peer.peer_id)
+            })
+            .collect()
+    }
+
+    /// Remember an outgoing connection attempt to a bootstrap peer.
+    pub fn on_dial(&mut self, connection_id: ConnectionId, address: Multiaddr) {
+        self.pending_dials.insert(connection_id, address);
+    }
+
+    /// Handle an established connection.
+    ///
+    /// Peers we dialed ourselves directly (not via a relay) are learned as new bootstrap peers.
+    /// Returns the updated peer if its health changed.
+    pub fn on_connection_established(
+        &mut self,
+        connection_id: ConnectionId,
+        peer_id: PeerId,
+        dialed_address: Option<&Multiaddr>,
+    ) -> Option<BootstrapPeer> {
+        let address = match self.pending_dials.remove(&connection_id) {
+            Some(address) => address,
+            None => match dialed_address {
+                Some(address) if !is_relayed(address) => without_peer_id(address),
+                _ => return None,
+            },
+        };
+
+        self.insert(BootstrapPeer::new(address.clone(), Some(peer_id), false));
+
+        let peer = self.peers.iter_mut().find(|peer| peer.address == address)?;
+        peer.peer_id = Some(peer_id);
+        peer.score = (peer.score + SUCCESS_REWARD).min(MAX_SCORE);
+        peer.successes += 1;
+        peer.last_seen = Some(now());
+
+        Some(peer.clone())
+    }
+
+    /// Handle a failed outgoing connection attempt.
+    ///
+    /// Returns the updated peer if the attempt was targeting a bootstrap peer. Learned peers are
+    /// removed from the set when they reach the lowest health score or expired.
+    pub fn on_dial_failed(&mut self, connection_id: ConnectionId) -> Option<BootstrapPeer> {
+        let address = self.pending_dials.remove(&connection_id)?;
+        let index = self.peers.iter().position(|peer| peer.address == address)?;
+
+        let peer = &mut self.peers[index];
+        peer.score = (peer.score - FAILURE_PENALTY).max(MIN_SCORE);
+        peer.failures += 1;
+        let peer = peer.clone();
+
+        if !peer.configured && (peer.score == MIN_SCORE || peer.is_expired(self.expiry, now())) {
+            self.peers.remove(index);
+        }
+
+        Some(peer)
+    }
+}
+
+/// Returns true if address can be dialed with the given transport protocol.
+fn supports_transport(address: &Multiaddr, transport: Transport) -> bool {
+    address.iter().any(|protocol| match transport {
+        Transport::QUIC => matches!(protocol, Protocol::QuicV1),
+        Transport::TCP => matches!(protocol, Protocol::Tcp(_)),
+    }) && !is_relayed(address)
+}
+
+/// Returns true if address describes a connection via a relay circuit.
+fn is_relayed(address: &Multiaddr) -> bool {
+    address
+        .iter()
+        .any(|protocol| matches!(protocol, Protocol::P2pCircuit))
+}
+
+/// Removes the peer id suffix of an address.
+fn without_peer_id(address: &Multiaddr) -> Multiaddr {
+    address
+        .iter()
+        .filter(|protocol| !matches!(protocol, Protocol::P2p(_)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::swarm::ConnectionId;
+    use libp2p::{Multiaddr, PeerId};
+
+    use crate::network::Transport;
+    use crate::replication::now;
+
+    use super::{BootstrapPeer, BootstrapPeers, MIN_SCORE};
+
+    const EXPIRY: u64 = 60 * 60;
+
+    fn address(port: u16) -> Multiaddr {
+        format!("/ip4/192.0.2.1/udp/{port}/quic-v1")
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn rotate_by_health_score() {
+        let mut bootstrap = BootstrapPeers::new(1, EXPIRY);
+        bootstrap.insert(BootstrapPeer::new(address(1), None, true));
+        bootstrap.insert(BootstrapPeer::new(address(2), None, true));
+        bootstrap.insert(BootstrapPeer::new(address(3), None, true));
+
+        // Peers of the same score are dialed in turns
+        let mut dialed = Vec::new();
+        for id in 0..3 {
+            let dials = bootstrap.next_dials(&[], Transport::QUIC);
+            assert_eq!(dials.len(), 1);
+            bootstrap.on_dial(ConnectionId::new_unchecked(id), dials[0].0.clone());
+            bootstrap.on_dial_failed(ConnectionId::new_unchecked(id));
+            dialed.push(dials[0].0.clone());
+        }
+        assert_eq!(dialed, vec![address(1), address(2), address(3)]);
+
+        // Peers which were reachable are preferred
+        let dials = bootstrap.next_dials(&[], Transport::QUIC);
+        bootstrap.on_dial(ConnectionId::new_unchecked(3), dials[0].0.clone());
+        let peer_id = PeerId::random();
+        let peer = bootstrap
+            .on_connection_established(ConnectionId::new_unchecked(3), peer_id, None)
+            .unwrap();
+        assert_eq!(peer.peer_id, Some(peer_id));
+
+        // We reached our target number of connections
+        assert!(bootstrap.next_dials(&[peer_id], Transport::QUIC).is_empty());
+
+        // After disconnecting we dial the healthy peer again
+        let dials = bootstrap.next_dials(&[], Transport::QUIC);
+        assert_eq!(dials, vec![(peer.address, Some(peer_id))]);
+    }
+
+    #[test]
+    fn learn_and_forget_peers() {
+        let mut bootstrap = BootstrapPeers::new(4, EXPIRY);
+
+        // Peers we dialed directly are learned, relayed connections are ignored
+        let relayed: Multiaddr = format!("{}/p2p-circuit", address(1)).parse().unwrap();
+        assert!(bootstrap
+            .on_connection_established(
+                ConnectionId::new_unchecked(0),
+                PeerId::random(),
+                Some(&relayed)
+            )
+            .is_none());
+        assert!(bootstrap
+            .on_connection_established(
+                ConnectionId::new_unchecked(1),
+                PeerId::random(),
+                Some(&address(2))
+            )
+            .is_some());
+        assert_eq!(bootstrap.peers().len(), 1);
+
+        // Peers of other transports are not dialed
+        assert!(bootstrap.next_dials(&[], Transport::TCP).is_empty());
+
+        // Learned peers are removed after failing too often
+        let mut id = 2;
+        while !bootstrap.peers().is_empty() {
+            let dials = bootstrap.next_dials(&[], Transport::QUIC);
+            bootstrap.on_dial(ConnectionId::new_unchecked(id), dials[0].0.clone());
+            let peer = bootstrap
+                .on_dial_failed(ConnectionId::new_unchecked(id))
+                .unwrap();
+            id += 1;
+
+            if bootstrap.peers().is_empty() {
+                assert_eq!(peer.score, MIN_SCORE);
+            }
+        }
+    }
+
+    #[test]
+    fn forget_expired_peers() {
+        let mut bootstrap = BootstrapPeers::new(2, EXPIRY);
+
+        // Learned peer we did not reach for a long time
+        let mut expired = BootstrapPeer::new(address(1), Some(PeerId::random()), false);
+        expired.last_seen = Some(1);
+        bootstrap.insert(expired);
+
+        // Configured peers never expire
+        let mut configured = BootstrapPeer::new(address(2), None, true);
+        configured.last_seen = Some(1);
+        bootstrap.insert(configured);
+
+        let dials = bootstrap.next_dials(&[], Transport::QUIC);
+        assert_eq!(dials.len(), 2);
+        for (id, (address, _)) in dials.into_iter().enumerate() {
+            bootstrap.on_dial(ConnectionId::new_unchecked(id), address);
+            let peer = bootstrap
+                .on_dial_failed(ConnectionId::new_unchecked(id))
+                .unwrap();
+            assert_eq!(peer.failures, 1);
+        }
+
+        assert!(!bootstrap.contains(&address(1)));
+        assert!(bootstrap.contains(&address(2)));
+
+        // Successful connections are counted
+        let dials = bootstrap.next_dials(&[], Transport::QUIC);
+        bootstrap.on_dial(ConnectionId::new_unchecked(2), dials[0].0.clone());
+        let peer = bootstrap
+            .on_connection_established(ConnectionId::new_unchecked(2), PeerId::random(), None)
+            .unwrap();
+        assert_eq!(peer.successes, 1);
+        assert!(!peer.is_expired(EXPIRY, now()));
+    }
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
 use std::str::FromStr;
 
 use anyhow::Error;
@@ -8,8 +8,11 @@ use libp2p::connection_limits::ConnectionLimits;
 use libp2p::multiaddr::Protocol;
 use libp2p::pnet::PreSharedKey;
 use libp2p::{Multiaddr, PeerId};
+use p2panda_rs::identity::PublicKey;
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::faults::FaultInjector;
+use crate::network::NetworkSimulation;
 use crate::AllowList;
 
 /// The namespace used by the `identify` network behaviour.
@@ -32,6 +35,12 @@ pub struct NetworkConfiguration {
     /// QUIC or TCP port for node-node communication and data replication.
     pub port: u16,
 
+    /// IP versions the node listens on and dials other nodes with.
+    pub ip_version: IpVersion,
+
+    /// QUIC or TCP port for IPv6 addresses, uses the same port as IPv4 when not set.
+    pub port_v6: Option<u16>,
+
     /// Discover peers on the local network via mDNS (over IPv4 only, using port 5353).
     pub mdns: bool,
 
@@ -43,6 +52,24 @@ pub struct NetworkConfiguration {
     /// least one relay.
     pub direct_node_addresses: Vec<PeerAddress>,
 
+    /// List of bootstrap node addresses.
+    ///
+    /// In contrast to direct node addresses the node does not attempt to stay connected to every
+    /// bootstrap node. Instead it dials them in turns, ordered by how reliably they could be
+    /// reached in the past, until `bootstrap_target_connections` is reached. Peers we connected
+    /// to successfully are remembered in the database and dialed again after a restart.
+    pub bootstrap_peers: Vec<PeerAddress>,
+
+    /// Number of connections the node tries to keep up by dialing bootstrap peers.
+    pub bootstrap_target_connections: usize,
+
+    /// Duration in seconds after which learned bootstrap peers are forgotten when we could not
+    /// connect to them anymore.
+    ///
+    /// Expired peers are removed from the database on startup and as soon as a connection
+    /// attempt to them fails. Configured bootstrap peers never expire.
+    pub bootstrap_peer_expiry: u64,
+
     /// List of peers which are allowed to connect to your node.
     ///
     /// If set then only nodes (identified by their peer id) contained in this list will be able to
@@ -71,12 +98,33 @@ pub struct NetworkConfiguration {
     /// known number of excluded nodes.
     pub block_peer_ids: Vec<PeerId>,
 
+    /// Hex-encoded ed25519 private key of the organization operating this node.
+    ///
+    /// If set, the node proves control of this operator key to every peer it connects to. The
+    /// operator key is independent from the node's own key pair, many nodes of one organization
+    /// can share it.
+    pub operator_private_key: Option<String>,
+
+    /// List of operator public keys of peers we replicate with.
+    ///
+    /// If set then replication sessions are only initiated with and accepted from peers which
+    /// proved control of one of the listed operator keys. When not set we replicate with any
+    /// peer.
+    ///
+    /// Use this list for example to let the nodes of a federation of known organizations
+    /// replicate data only among themselves, without needing to share a pre-shared key.
+    pub allow_operator_keys: AllowList<PublicKey>,
+
     /// List of relay addresses.
     ///
     /// A relay helps discover other nodes on the internet (also known as "rendesvouz" or
     /// "bootstrap" server) and helps establishing direct p2p connections when node is behind a
     /// firewall or NAT (also known as "holepunching").
     ///
+    /// When multiple relays are given the node connects to all of them but registers only at one,
+    /// preferring relays which are reachable and report the least load. When the connection to
+    /// this relay gets lost the node fails over to the next best one.
+    ///
     /// WARNING: This will potentially expose your IP address on the network. Do only connect to
     /// trusted relays or make sure your IP address is hidden via a VPN or proxy if you're
     /// concerned about leaking your IP.
@@ -90,6 +138,28 @@ pub struct NetworkConfiguration {
     /// static IP address through an VPS.
     pub relay_mode: bool,
 
+    /// Name of the network this node participates in.
+    ///
+    /// Nodes register and discover each other at rendezvous points in a namespace derived from
+    /// this name, nodes of different networks do not find each other even when they use the same
+    /// relays. When not set the default "aquadoggo" namespace is used.
+    pub network_name: Option<String>,
+
+    /// Minimum time-to-live in seconds a peer can request for its registration when our node
+    /// serves as a rendezvous point in relay mode.
+    pub rendezvous_min_ttl: u64,
+
+    /// Maximum time-to-live in seconds a peer can request for its registration when our node
+    /// serves as a rendezvous point in relay mode.
+    pub rendezvous_max_ttl: u64,
+
+    /// Maximum number of peers which can hold registrations at our rendezvous point at the same
+    /// time when running in relay mode.
+    ///
+    /// When this quota is reached, requests from peers without a registration are ignored until
+    /// other registrations expire or get removed.
+    pub rendezvous_max_registrations: usize,
+
     /// Notify handler buffer size.
     ///
     /// Defines the buffer size for events sent from a network protocol handler to the connection
@@ -129,6 +199,15 @@ pub struct NetworkConfiguration {
 
     /// Maximum connections per peer (includes outgoing and incoming).
     pub max_connections_per_peer: u32,
+
+    /// Faults injected into the replication codec, only used with the `fault-injection` feature.
+    pub faults: FaultInjector,
+
+    /// Latency, jitter and packet loss simulated on all connections, for testing replication
+    /// under poor connectivity. Disabled by default.
+    ///
+    /// WARNING: Network simulation is only supported when using TCP for the transport layer.
+    pub simulation: NetworkSimulation,
 }
 
 impl Default for NetworkConfiguration {
@@ -137,12 +216,23 @@ impl Default for NetworkConfiguration {
             transport: Transport::QUIC,
             psk: None,
             port: 2022,
+            ip_version: IpVersion::default(),
+            port_v6: None,
             mdns: true,
             direct_node_addresses: Vec::new(),
+            bootstrap_peers: Vec::new(),
+            bootstrap_target_connections: 8,
+            bootstrap_peer_expiry: 60 * 60 * 24 * 30,
             allow_peer_ids: AllowList::<PeerId>::Wildcard,
             block_peer_ids: Vec::new(),
+            operator_private_key: None,
+            allow_operator_keys: AllowList::<PublicKey>::Wildcard,
             relay_addresses: Vec::new(),
             relay_mode: false,
+            network_name: None,
+            rendezvous_min_ttl: 60 * 60 * 2,
+            rendezvous_max_ttl: 60 * 60 * 72,
+            rendezvous_max_registrations: 1024,
             notify_handler_buffer_size: 128,
             per_connection_event_buffer_size: 8,
             dial_concurrency_factor: 8,
@@ -151,6 +241,8 @@ impl Default for NetworkConfiguration {
             max_connections_pending_in: 8,
             max_connections_pending_out: 8,
             max_connections_per_peer: 2,
+            faults: FaultInjector::default(),
+            simulation: NetworkSimulation::default(),
         }
     }
 }
@@ -165,6 +257,19 @@ impl NetworkConfiguration {
             .with_max_established_incoming(Some(self.max_connections_in))
             .with_max_established_per_peer(Some(self.max_connections_per_peer))
     }
+
+    /// Returns the port used for IPv6 addresses.
+    pub fn port_v6(&self) -> u16 {
+        self.port_v6.unwrap_or(self.port)
+    }
+
+    /// Returns the namespace this node registers and discovers peers in at rendezvous points.
+    pub fn rendezvous_namespace(&self) -> String {
+        match &self.network_name {
+            Some(network_name) => format!("{NODE_NAMESPACE}/{network_name}"),
+            None => NODE_NAMESPACE.to_string(),
+        }
+    }
 }
 
 /// Helper struct for handling ambiguous string addresses which may need resolving via
@@ -175,6 +280,9 @@ impl NetworkConfiguration {
 /// When `to_socket` is first called it's successful result is cached internally and this value
 /// is used directly from this point on. This is an optimization which avoids unnecessary DNS
 /// lookups.
+///
+/// Addresses resolving to both IPv4 and IPv6 addresses are dialed via IPv6 when the node uses
+/// both IP versions and the host has a route to the IPv6 address.
 #[derive(Debug, Clone)]
 pub struct PeerAddress {
     addr_str: String,
@@ -189,25 +297,23 @@ impl PeerAddress {
         }
     }
 
-    pub fn socket(&mut self) -> Result<SocketAddr, Error> {
+    pub fn socket(&mut self, ip_version: IpVersion) -> Result<SocketAddr, Error> {
         if let Some(socket_addr) = self.socket_addr {
             return Ok(socket_addr);
         }
 
-        let socket_addr = match self.addr_str.to_socket_addrs() {
-            Ok(mut addrs) => match addrs.next() {
-                Some(addrs) => addrs,
-                None => return Err(anyhow::format_err!("No socket addresses found")),
-            },
-            Err(e) => return Err(e.into()),
+        let socket_addrs: Vec<SocketAddr> = self.addr_str.to_socket_addrs()?.collect();
+        let socket_addr = match ip_version.select(&socket_addrs, has_ipv6_route) {
+            Some(socket_addr) => socket_addr,
+            None => return Err(anyhow::format_err!("No socket addresses found")),
         };
 
         let _ = self.socket_addr.replace(socket_addr);
         Ok(socket_addr)
     }
 
-    pub fn quic_multiaddr(&mut self) -> Result<Multiaddr, Error> {
-        match self.socket() {
+    pub fn quic_multiaddr(&mut self, ip_version: IpVersion) -> Result<Multiaddr, Error> {
+        match self.socket(ip_version) {
             Ok(socket_address) => {
                 let mut multiaddr = match socket_address.ip() {
                     IpAddr::V4(ip) => Multiaddr::from(Protocol::Ip4(ip)),
@@ -221,8 +327,8 @@ impl PeerAddress {
         }
     }
 
-    pub fn tcp_multiaddr(&mut self) -> Result<Multiaddr, Error> {
-        match self.socket() {
+    pub fn tcp_multiaddr(&mut self, ip_version: IpVersion) -> Result<Multiaddr, Error> {
+        match self.socket(ip_version) {
             Ok(socket_address) => {
                 let mut multiaddr = match socket_address.ip() {
                     IpAddr::V4(ip) => Multiaddr::from(Protocol::Ip4(ip)),
@@ -236,6 +342,16 @@ impl PeerAddress {
     }
 }
 
+/// Returns true if the host has a route to the given IPv6 address.
+///
+/// Connecting an UDP socket does not send any packets, it only fails when the address is not
+/// routable, for example on hosts without IPv6 connectivity.
+fn has_ipv6_route(address: &SocketAddr) -> bool {
+    UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))
+        .and_then(|socket| socket.connect(address))
+        .is_ok()
+}
+
 impl From<String> for PeerAddress {
     fn from(value: String) -> Self {
         Self::new(value)
@@ -249,7 +365,7 @@ impl std::fmt::Display for PeerAddress {
 }
 
 /// Enum representing transport protocol types.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
 pub enum Transport {
     #[default]
     /// UDP/QUIC transport protocol
@@ -287,3 +403,128 @@ impl FromStr for Transport {
         }
     }
 }
+
+/// IP versions the node listens on and dials other nodes with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+pub enum IpVersion {
+    /// Only use IPv4 addresses.
+    V4,
+
+    /// Only use IPv6 addresses.
+    V6,
+
+    #[default]
+    /// Listen on IPv4 and IPv6 addresses and prefer IPv6 when dialing other nodes.
+    Dual,
+}
+
+impl IpVersion {
+    /// Returns the unspecified addresses to listen on, the first one is required to succeed.
+    pub fn listen_ips(&self) -> Vec<IpAddr> {
+        match self {
+            IpVersion::V4 => vec![Ipv4Addr::UNSPECIFIED.into()],
+            IpVersion::V6 => vec![Ipv6Addr::UNSPECIFIED.into()],
+            IpVersion::Dual => vec![Ipv4Addr::UNSPECIFIED.into(), Ipv6Addr::UNSPECIFIED.into()],
+        }
+    }
+
+    /// Select the address to dial from resolved socket addresses.
+    ///
+    /// IPv6 addresses are preferred when both IP versions are used and the host has a route to
+    /// them, IPv4 addresses are used otherwise.
+    pub fn select(
+        &self,
+        socket_addrs: &[SocketAddr],
+        is_routable: impl Fn(&SocketAddr) -> bool,
+    ) -> Option<SocketAddr> {
+        let mut v4 = socket_addrs.iter().filter(|addr| addr.is_ipv4());
+        let mut v6 = socket_addrs.iter().filter(|addr| addr.is_ipv6());
+
+        match self {
+            IpVersion::V4 => v4.next(),
+            IpVersion::V6 => v6.next(),
+            IpVersion::Dual => v6.find(|addr| is_routable(addr)).or_else(|| v4.next()),
+        }
+        .copied()
+    }
+}
+
+impl<'de> Deserialize<'de> for IpVersion {
+    fn deserialize<D>(deserializer: D) -> Result<IpVersion, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let str_value = String::deserialize(deserializer)?;
+        let ip_version = str_value
+            .parse()
+            .map_err(|_| serde::de::Error::custom("Could not parse string as ip version"))?;
+
+        Ok(ip_version)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct IpVersionParsingError;
+
+impl FromStr for IpVersion {
+    type Err = IpVersionParsingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "v4" | "ipv4" => Ok(IpVersion::V4),
+            "v6" | "ipv6" => Ok(IpVersion::V6),
+            "dual" => Ok(IpVersion::Dual),
+            _ => Err(IpVersionParsingError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    use rstest::rstest;
+
+    use super::IpVersion;
+
+    #[rstest]
+    #[case(IpVersion::V4, true, Some("10.0.0.1:2022"))]
+    #[case(IpVersion::V6, false, Some("[2001:db8::1]:2022"))]
+    #[case(IpVersion::Dual, true, Some("[2001:db8::1]:2022"))]
+    #[case(IpVersion::Dual, false, Some("10.0.0.1:2022"))]
+    fn select_dialed_address(
+        #[case] ip_version: IpVersion,
+        #[case] is_routable: bool,
+        #[case] expected: Option<&str>,
+    ) {
+        let socket_addrs: Vec<SocketAddr> = ["10.0.0.1:2022", "[2001:db8::1]:2022"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+
+        assert_eq!(
+            ip_version.select(&socket_addrs, |_| is_routable),
+            expected.map(|addr| addr.parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn select_in_v6_only_environments() {
+        let v4_only: Vec<SocketAddr> = vec!["10.0.0.1:2022".parse().unwrap()];
+        let v6_only: Vec<SocketAddr> = vec!["[2001:db8::1]:2022".parse().unwrap()];
+
+        assert_eq!(IpVersion::V6.select(&v4_only, |_| true), None);
+        assert_eq!(IpVersion::V6.select(&v6_only, |_| true), Some(v6_only[0]));
+        assert_eq!(IpVersion::Dual.select(&v6_only, |_| true), Some(v6_only[0]));
+        assert_eq!(IpVersion::V4.select(&v6_only, |_| true), None);
+    }
+
+    #[rstest]
+    #[case("v4", IpVersion::V4)]
+    #[case("IPv6", IpVersion::V6)]
+    #[case("dual", IpVersion::Dual)]
+    fn parse_ip_version(#[case] value: &str, #[case] expected: IpVersion) {
+        assert_eq!(IpVersion::from_str(value), Ok(expected));
+    }
+}
@@ -0,0 +1,261 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt;
+use std::task::{Context, Poll};
+
+use anyhow::{anyhow, Result};
+use libp2p::core::Endpoint;
+use libp2p::swarm::{
+    dummy, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Closure sent from the application to be executed on its network behaviour.
+type Command<B> = Box<dyn FnOnce(&mut B) + Send>;
+
+/// Network behaviour of an application embedding aquadoggo, running next to the node's own
+/// behaviours on the same swarm.
+///
+/// This allows applications to share one swarm and its connections with the node for their own
+/// protocols, for example gossip, instead of running two networking stacks.
+///
+/// As the swarm is driven by the node, the application interacts with its behaviour through the
+/// `CustomBehaviourHandle` returned by `CustomBehaviour::new`.
+pub struct CustomBehaviour<B: NetworkBehaviour> {
+    inner: B,
+    commands: UnboundedReceiver<Command<B>>,
+    events: UnboundedSender<B::ToSwarm>,
+}
+
+impl<B: NetworkBehaviour> CustomBehaviour<B> {
+    /// Wraps the given network behaviour of an application.
+    ///
+    /// Returns the wrapped behaviour to be handed to `P2pandaBehaviour::with_custom` and a handle
+    /// to interact with it after the node started.
+    pub fn new(inner: B) -> (Self, CustomBehaviourHandle<B>) {
+        let (commands_tx, commands_rx) = unbounded_channel();
+        let (events_tx, events_rx) = unbounded_channel();
+
+        let behaviour = Self {
+            inner,
+            commands: commands_rx,
+            events: events_tx,
+        };
+
+        let handle = CustomBehaviourHandle {
+            commands: commands_tx,
+            events: events_rx,
+        };
+
+        (behaviour, handle)
+    }
+}
+
+impl Default for CustomBehaviour<dummy::Behaviour> {
+    fn default() -> Self {
+        let (behaviour, _) = Self::new(dummy::Behaviour);
+        behaviour
+    }
+}
+
+impl<B: NetworkBehaviour> fmt::Debug for CustomBehaviour<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomBehaviour").finish_non_exhaustive()
+    }
+}
+
+impl<B: NetworkBehaviour> NetworkBehaviour for CustomBehaviour<B> {
+    type ConnectionHandler = B::ConnectionHandler;
+
+    // Events are forwarded to the application, the node never sees them
+    type ToSwarm = void::Void;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.inner.on_swarm_event(event);
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        // Execute all commands the application sent in the meantime
+        while let Poll::Ready(Some(command)) = self.commands.poll_recv(cx) {
+            command(&mut self.inner);
+        }
+
+        loop {
+            match self.inner.poll(cx) {
+                Poll::Ready(ToSwarm::GenerateEvent(event)) => {
+                    // Ignore events when the application is not interested in them anymore
+                    let _ = self.events.send(event);
+                }
+                Poll::Ready(action) => {
+                    return Poll::Ready(action.map_out(|_| {
+                        unreachable!("Generated events are forwarded to the application")
+                    }));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Handle to interact with the network behaviour of an application after it got handed over to
+/// the node.
+pub struct CustomBehaviourHandle<B: NetworkBehaviour> {
+    commands: UnboundedSender<Command<B>>,
+    events: UnboundedReceiver<B::ToSwarm>,
+}
+
+impl<B: NetworkBehaviour> CustomBehaviourHandle<B> {
+    /// Execute a closure on the network behaviour, for example to send a message to a peer.
+    ///
+    /// The closure runs the next time the node polls the swarm. Returns an error if the network
+    /// service of the node stopped.
+    pub fn execute<F>(&self, command: F) -> Result<()>
+    where
+        F: FnOnce(&mut B) + Send + 'static,
+    {
+        self.commands
+            .send(Box::new(command))
+            .map_err(|_| anyhow!("Network service stopped"))
+    }
+
+    /// Receive the next event generated by the network behaviour.
+    ///
+    /// Returns `None` if the network service of the node stopped.
+    pub async fn next_event(&mut self) -> Option<B::ToSwarm> {
+        self.events.recv().await
+    }
+}
+
+impl<B: NetworkBehaviour> fmt::Debug for CustomBehaviourHandle<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomBehaviourHandle")
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::identify;
+    use libp2p::swarm::Swarm;
+    use libp2p_swarm_test::SwarmExt;
+
+    use super::CustomBehaviour;
+
+    fn identify_behaviour(key_pair: libp2p::identity::Keypair) -> identify::Behaviour {
+        identify::Behaviour::new(identify::Config::new(
+            "/test/1.0.0".into(),
+            key_pair.public(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn forwards_events_and_commands() {
+        let mut handle = None;
+        let mut swarm_1 = Swarm::new_ephemeral(|key_pair| {
+            let (behaviour, custom_handle) = CustomBehaviour::new(identify_behaviour(key_pair));
+            handle = Some(custom_handle);
+            behaviour
+        });
+        let mut handle = handle.unwrap();
+        let mut swarm_2 = Swarm::new_ephemeral(identify_behaviour);
+
+        swarm_1.listen().with_memory_addr_external().await;
+        swarm_2.connect(&mut swarm_1).await;
+        let peer_id = *swarm_2.local_peer_id();
+
+        // Drive the swarms until the application received the identify info of the other peer
+        let received = async {
+            loop {
+                if let Some(identify::Event::Received { peer_id, .. }) = handle.next_event().await {
+                    return peer_id;
+                }
+            }
+        };
+        let received_peer_id = tokio::select! {
+            peer_id = received => peer_id,
+            _ = swarm_1.loop_on_next() => unreachable!(),
+            _ = swarm_2.loop_on_next() => unreachable!(),
+        };
+        assert_eq!(received_peer_id, peer_id);
+
+        // Commands are executed on the wrapped behaviour while the swarm is polled
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        handle
+            .execute(move |behaviour: &mut identify::Behaviour| {
+                behaviour.push(std::iter::once(peer_id));
+                let _ = tx.send(());
+            })
+            .unwrap();
+
+        tokio::select! {
+            result = rx => assert!(result.is_ok()),
+            _ = swarm_1.loop_on_next() => unreachable!(),
+        };
+    }
+}
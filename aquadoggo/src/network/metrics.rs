@@ -0,0 +1,305 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Bandwidth and connection metrics of the libp2p swarm.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use libp2p::metrics::Registry;
+use libp2p::multiaddr::Protocol;
+use libp2p::swarm::ConnectionId;
+use libp2p::Multiaddr;
+
+use crate::network::Transport;
+
+/// Name of the bandwidth counters registered by libp2p.
+const BANDWIDTH_METRIC: &str = "libp2p_bandwidth_bytes_total";
+
+/// Purpose of the traffic on a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrafficProtocol {
+    /// Direct connection to another peer, used for replication.
+    Replication,
+
+    /// Connection to another peer established via a relay circuit.
+    Relay,
+}
+
+impl TrafficProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrafficProtocol::Replication => "replication",
+            TrafficProtocol::Relay => "relay",
+        }
+    }
+}
+
+/// Transport and protocol a connection was established with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrafficClass {
+    pub transport: Transport,
+    pub protocol: TrafficProtocol,
+}
+
+impl TrafficClass {
+    /// All known classes in the order they are reported.
+    const ALL: [TrafficClass; 4] = [
+        TrafficClass::new(Transport::TCP, TrafficProtocol::Replication),
+        TrafficClass::new(Transport::TCP, TrafficProtocol::Relay),
+        TrafficClass::new(Transport::QUIC, TrafficProtocol::Replication),
+        TrafficClass::new(Transport::QUIC, TrafficProtocol::Relay),
+    ];
+
+    const fn new(transport: Transport, protocol: TrafficProtocol) -> Self {
+        Self {
+            transport,
+            protocol,
+        }
+    }
+
+    /// Derive the class from the remote address of a connection.
+    pub fn from_address(address: &Multiaddr) -> Self {
+        let mut transport = Transport::TCP;
+        let mut protocol = TrafficProtocol::Replication;
+
+        for component in address.iter() {
+            match component {
+                Protocol::Quic | Protocol::QuicV1 => transport = Transport::QUIC,
+                Protocol::P2pCircuit => protocol = TrafficProtocol::Relay,
+                _ => (),
+            }
+        }
+
+        Self::new(transport, protocol)
+    }
+
+    /// Derive the class from a protocol stack label of libp2p, for example "/ip4/udp/quic-v1".
+    fn from_protocol_stack(protocols: &str) -> Self {
+        let mut transport = Transport::TCP;
+        let mut protocol = TrafficProtocol::Replication;
+
+        for tag in protocols.split('/') {
+            match tag {
+                "quic" | "quic-v1" => transport = Transport::QUIC,
+                "p2p-circuit" => protocol = TrafficProtocol::Relay,
+                _ => (),
+            }
+        }
+
+        Self::new(transport, protocol)
+    }
+
+    /// Returns the name of the transport, for example "tcp".
+    pub fn transport_str(&self) -> &'static str {
+        match self.transport {
+            Transport::TCP => "tcp",
+            Transport::QUIC => "quic",
+        }
+    }
+}
+
+/// Traffic and connection statistics of one transport and protocol.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrafficMetrics {
+    /// Total number of bytes received.
+    pub inbound_bytes: u64,
+
+    /// Total number of bytes sent.
+    pub outbound_bytes: u64,
+
+    /// Number of currently open connections.
+    pub open_connections: u64,
+
+    /// Total number of established connections.
+    pub total_connections: u64,
+}
+
+#[derive(Debug, Default)]
+struct ConnectionStats {
+    /// Class of every currently open connection.
+    open: HashMap<ConnectionId, TrafficClass>,
+
+    /// Number of established connections per class.
+    total: HashMap<TrafficClass, u64>,
+}
+
+/// Bandwidth and connection metrics of the node's swarm, broken down by transport and protocol.
+///
+/// Bandwidth is measured by libp2p on the transport layer, the counters are kept in a prometheus
+/// registry which is handed to the swarm builder. Connections are counted from swarm events.
+///
+/// Relayed connections are tunneled through the connection to the relay node, their traffic is
+/// therefore also included in the replication traffic of the relay connection.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkMetrics {
+    registry: Arc<Mutex<Registry>>,
+    connections: Arc<Mutex<ConnectionStats>>,
+}
+
+impl NetworkMetrics {
+    /// Returns the registry the libp2p bandwidth metrics are recorded in.
+    pub fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry
+            .lock()
+            .expect("Network metrics registry lock poisoned")
+    }
+
+    /// Count a newly established connection.
+    pub fn on_connection_established(&self, connection_id: ConnectionId, address: &Multiaddr) {
+        let class = TrafficClass::from_address(address);
+        let mut connections = self
+            .connections
+            .lock()
+            .expect("Network metrics lock poisoned");
+        connections.open.insert(connection_id, class);
+        *connections.total.entry(class).or_default() += 1;
+    }
+
+    /// Remove a closed connection from the open connections.
+    pub fn on_connection_closed(&self, connection_id: ConnectionId) {
+        self.connections
+            .lock()
+            .expect("Network metrics lock poisoned")
+            .open
+            .remove(&connection_id);
+    }
+
+    /// Returns current metrics of all transports and protocols which have been used.
+    pub fn snapshot(&self) -> Vec<(TrafficClass, TrafficMetrics)> {
+        let mut metrics: HashMap<TrafficClass, TrafficMetrics> = HashMap::new();
+
+        for (protocols, is_inbound, bytes) in self.bandwidth() {
+            let entry = metrics
+                .entry(TrafficClass::from_protocol_stack(&protocols))
+                .or_default();
+            if is_inbound {
+                entry.inbound_bytes += bytes;
+            } else {
+                entry.outbound_bytes += bytes;
+            }
+        }
+
+        {
+            let connections = self
+                .connections
+                .lock()
+                .expect("Network metrics lock poisoned");
+            for class in connections.open.values() {
+                metrics.entry(*class).or_default().open_connections += 1;
+            }
+            for (class, total) in &connections.total {
+                metrics.entry(*class).or_default().total_connections = *total;
+            }
+        }
+
+        TrafficClass::ALL
+            .iter()
+            .filter_map(|class| metrics.remove(class).map(|metrics| (*class, metrics)))
+            .collect()
+    }
+
+    /// Returns the protocol stack, direction and byte count of all libp2p bandwidth counters.
+    fn bandwidth(&self) -> Vec<(String, bool, u64)> {
+        let mut encoded = String::new();
+        if prometheus_client::encoding::text::encode(&mut encoded, &self.registry()).is_err() {
+            return Vec::new();
+        }
+
+        encoded.lines().filter_map(parse_bandwidth_line).collect()
+    }
+}
+
+/// Parse a bandwidth counter in the prometheus text format, for example:
+///
+/// `libp2p_bandwidth_bytes_total{protocols="/ip4/tcp",direction="Inbound"} 1024`
+fn parse_bandwidth_line(line: &str) -> Option<(String, bool, u64)> {
+    let labels = line.strip_prefix(BANDWIDTH_METRIC)?.strip_prefix('{')?;
+    let (labels, value) = labels.split_once("} ")?;
+
+    let mut protocols = None;
+    let mut is_inbound = None;
+    for label in labels.split(',') {
+        match label.split_once('=')? {
+            ("protocols", value) => protocols = Some(value.trim_matches('"').to_string()),
+            ("direction", value) => is_inbound = Some(value.trim_matches('"') == "Inbound"),
+            _ => (),
+        }
+    }
+
+    Some((protocols?, is_inbound?, value.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::swarm::ConnectionId;
+    use libp2p::Multiaddr;
+    use rstest::rstest;
+
+    use crate::network::Transport;
+
+    use super::{parse_bandwidth_line, NetworkMetrics, TrafficClass, TrafficProtocol};
+
+    #[rstest]
+    #[case(
+        "/ip4/127.0.0.1/tcp/2022",
+        Transport::TCP,
+        TrafficProtocol::Replication
+    )]
+    #[case(
+        "/ip4/127.0.0.1/udp/2022/quic-v1",
+        Transport::QUIC,
+        TrafficProtocol::Replication
+    )]
+    #[case(
+        "/ip4/127.0.0.1/udp/2022/quic-v1/p2p/12D3KooWLxGKMgUtekXam9JsSjMa3b7M3rYEYUYUywdehHTRrLgU/p2p-circuit",
+        Transport::QUIC,
+        TrafficProtocol::Relay
+    )]
+    fn classify_addresses(
+        #[case] address: &str,
+        #[case] transport: Transport,
+        #[case] protocol: TrafficProtocol,
+    ) {
+        let address: Multiaddr = address.parse().unwrap();
+        let class = TrafficClass::from_address(&address);
+        assert_eq!(class.transport, transport);
+        assert_eq!(class.protocol, protocol);
+    }
+
+    #[test]
+    fn parse_bandwidth_counters() {
+        assert_eq!(
+            parse_bandwidth_line(
+                "libp2p_bandwidth_bytes_total{protocols=\"/ip4/udp/quic-v1\",direction=\"Inbound\"} 1024"
+            ),
+            Some(("/ip4/udp/quic-v1".to_string(), true, 1024))
+        );
+        assert_eq!(
+            parse_bandwidth_line("# TYPE libp2p_bandwidth_bytes counter"),
+            None
+        );
+        assert_eq!(
+            TrafficClass::from_protocol_stack("/ip4/tcp/p2p/p2p-circuit/p2p"),
+            TrafficClass {
+                transport: Transport::TCP,
+                protocol: TrafficProtocol::Relay
+            }
+        );
+    }
+
+    #[test]
+    fn count_connections() {
+        let metrics = NetworkMetrics::default();
+        assert!(metrics.snapshot().is_empty());
+
+        let tcp: Multiaddr = "/ip4/127.0.0.1/tcp/2022".parse().unwrap();
+        metrics.on_connection_established(ConnectionId::new_unchecked(1), &tcp);
+        metrics.on_connection_established(ConnectionId::new_unchecked(2), &tcp);
+        metrics.on_connection_closed(ConnectionId::new_unchecked(1));
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].0.transport, Transport::TCP);
+        assert_eq!(snapshot[0].1.open_connections, 1);
+        assert_eq!(snapshot[0].1.total_connections, 2);
+    }
+}
@@ -1,16 +1,30 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 mod behaviour;
+mod bootstrap;
 mod config;
+mod custom;
 pub mod identity;
+mod metrics;
 mod peers;
 mod relay;
+mod rendezvous_server;
 mod service;
 mod shutdown;
+mod simulation;
 mod swarm;
+mod ticket;
 pub mod utils;
 
-pub use config::{NetworkConfiguration, Transport};
-pub use peers::{Peer, PeerMessage};
-pub use service::network_service;
+pub use behaviour::P2pandaBehaviour;
+pub use bootstrap::BootstrapPeer;
+pub use config::{IpVersion, NetworkConfiguration, Transport};
+pub use custom::{CustomBehaviour, CustomBehaviourHandle};
+pub use metrics::NetworkMetrics;
+pub use peers::{ConnectionDirection, ConnectionInfo, Peer, PeerMessage};
+pub use relay::{Registration, RelayStatus};
+pub use service::{network_service, network_service_with_swarm};
 pub use shutdown::ShutdownHandler;
+pub use simulation::NetworkSimulation;
+pub use swarm::build_swarm;
+pub use ticket::{ConnectionTicket, LocalAddresses};
@@ -3,7 +3,7 @@
 use std::collections::VecDeque;
 use std::task::{Context, Poll};
 
-use libp2p::core::Endpoint;
+use libp2p::core::{ConnectedPoint, Endpoint};
 use libp2p::swarm::derive_prelude::ConnectionEstablished;
 use libp2p::swarm::{
     ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, NotifyHandler,
@@ -11,19 +11,20 @@ use libp2p::swarm::{
 };
 use libp2p::{Multiaddr, PeerId};
 
+use crate::faults::FaultInjector;
 use crate::network::peers::handler::{Handler, HandlerFromBehaviour, HandlerToBehaviour};
-use crate::network::peers::{Peer, PeerMessage};
+use crate::network::peers::{ConnectionInfo, Peer, PeerMessage, Reassembly};
 
 #[derive(Debug)]
 pub enum Event {
     /// Message received on the inbound stream.
     MessageReceived(Peer, PeerMessage),
 
-    /// We established an inbound or outbound connection to a peer for the first time.
-    PeerConnected(Peer),
+    /// We established an inbound or outbound connection to a peer.
+    PeerConnected(Peer, ConnectionInfo),
 
-    /// Peer does not have any inbound or outbound connections left with us.
-    PeerDisconnected(Peer),
+    /// A connection to a peer was closed.
+    PeerDisconnected(Peer, ConnectionInfo),
 }
 
 /// p2panda network behaviour managing peers who can speak the "p2panda" protocol, handling
@@ -80,24 +81,43 @@ pub enum Event {
 pub struct Behaviour {
     events: VecDeque<ToSwarm<Event, HandlerFromBehaviour>>,
     enabled: bool,
+    faults: FaultInjector,
+
+    /// Incomplete transfers of fragmented messages, shared by all connection handlers so they
+    /// can be resumed after reconnecting.
+    reassembly: Reassembly,
 }
 
 impl Behaviour {
-    pub fn new() -> Self {
+    pub fn new(faults: FaultInjector) -> Self {
         Self {
             events: VecDeque::new(),
             enabled: true,
+            faults,
+            reassembly: Reassembly::default(),
         }
     }
 
-    fn on_connection_established(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
+    fn on_connection_established(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        endpoint: &ConnectedPoint,
+    ) {
         let peer = Peer::new(peer_id, connection_id);
-        self.push_event(ToSwarm::GenerateEvent(Event::PeerConnected(peer)));
+        let info = ConnectionInfo::new(peer_id, connection_id, endpoint);
+        self.push_event(ToSwarm::GenerateEvent(Event::PeerConnected(peer, info)));
     }
 
-    fn on_connection_closed(&mut self, peer_id: PeerId, connection_id: ConnectionId) {
+    fn on_connection_closed(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        endpoint: &ConnectedPoint,
+    ) {
         let peer = Peer::new(peer_id, connection_id);
-        self.push_event(ToSwarm::GenerateEvent(Event::PeerDisconnected(peer)));
+        let info = ConnectionInfo::new(peer_id, connection_id, endpoint);
+        self.push_event(ToSwarm::GenerateEvent(Event::PeerDisconnected(peer, info)));
     }
 
     fn on_received_message(
@@ -145,21 +165,29 @@ impl NetworkBehaviour for Behaviour {
     fn handle_established_inbound_connection(
         &mut self,
         _: ConnectionId,
-        _: PeerId,
+        peer_id: PeerId,
         _: &Multiaddr,
         _: &Multiaddr,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new())
+        Ok(Handler::new(
+            self.faults.clone(),
+            peer_id,
+            self.reassembly.clone(),
+        ))
     }
 
     fn handle_established_outbound_connection(
         &mut self,
         _: ConnectionId,
-        _: PeerId,
+        peer_id: PeerId,
         _: &Multiaddr,
         _: Endpoint,
     ) -> Result<THandler<Self>, ConnectionDenied> {
-        Ok(Handler::new())
+        Ok(Handler::new(
+            self.faults.clone(),
+            peer_id,
+            self.reassembly.clone(),
+        ))
     }
 
     fn on_connection_handler_event(
@@ -180,16 +208,18 @@ impl NetworkBehaviour for Behaviour {
             FromSwarm::ConnectionEstablished(ConnectionEstablished {
                 peer_id,
                 connection_id,
+                endpoint,
                 ..
             }) => {
-                self.on_connection_established(peer_id, connection_id);
+                self.on_connection_established(peer_id, connection_id, endpoint);
             }
             FromSwarm::ConnectionClosed(ConnectionClosed {
                 peer_id,
                 connection_id,
+                endpoint,
                 ..
             }) => {
-                self.on_connection_closed(peer_id, connection_id);
+                self.on_connection_closed(peer_id, connection_id, endpoint);
             }
             _ => {}
         }
@@ -215,8 +245,9 @@ mod tests {
     use p2panda_rs::schema::SchemaId;
     use rstest::rstest;
 
-    use crate::network::{Peer, PeerMessage};
-    use crate::replication::{Message, SchemaIdSet, SyncMessage};
+    use crate::faults::FaultInjector;
+    use crate::network::{ConnectionDirection, Peer, PeerMessage};
+    use crate::replication::{Compression, Message, SchemaIdSet, SyncMessage};
     use crate::test_utils::helpers::random_schema_id_set;
 
     use super::{Behaviour as PeersBehaviour, Event};
@@ -224,8 +255,8 @@ mod tests {
     #[tokio::test]
     async fn peers_connect() {
         // Create two swarms
-        let mut swarm_1 = Swarm::new_ephemeral(|_| PeersBehaviour::new());
-        let mut swarm_2 = Swarm::new_ephemeral(|_| PeersBehaviour::new());
+        let mut swarm_1 = Swarm::new_ephemeral(|_| PeersBehaviour::new(FaultInjector::default()));
+        let mut swarm_2 = Swarm::new_ephemeral(|_| PeersBehaviour::new(FaultInjector::default()));
 
         // Listen on swarm_1 and connect from swarm_2, this should establish a bi-directional
         // connection.
@@ -255,7 +286,7 @@ mod tests {
     #[allow(unused_variables)]
     async fn incompatible_network_behaviour() {
         // Create two swarms
-        let mut swarm_1 = Swarm::new_ephemeral(|_| PeersBehaviour::new());
+        let mut swarm_1 = Swarm::new_ephemeral(|_| PeersBehaviour::new(FaultInjector::default()));
         let mut swarm_2 = Swarm::new_ephemeral(|_| dummy::Behaviour);
 
         // Listen on swarm_1 and connect from swarm_2, this should establish a bi-directional connection.
@@ -288,7 +319,7 @@ mod tests {
             Peer::new(swarm_2_peer_id, ConnectionId::new_unchecked(1)),
             PeerMessage::SyncMessage(SyncMessage::new(
                 0,
-                Message::SyncRequest(0.into(), SchemaIdSet::new(&[])),
+                Message::SyncRequest(0.into(), SchemaIdSet::new(&[]), 0.into()),
             )),
         );
 
@@ -311,8 +342,8 @@ mod tests {
     async fn swarm_behaviour_events(#[case] set_1: SchemaIdSet, #[case] set_2: SchemaIdSet) {
         use libp2p::swarm::dial_opts::DialOpts;
 
-        let mut swarm_1 = Swarm::new_ephemeral(|_| PeersBehaviour::new());
-        let mut swarm_2 = Swarm::new_ephemeral(|_| PeersBehaviour::new());
+        let mut swarm_1 = Swarm::new_ephemeral(|_| PeersBehaviour::new(FaultInjector::default()));
+        let mut swarm_2 = Swarm::new_ephemeral(|_| PeersBehaviour::new(FaultInjector::default()));
 
         // Listen on swarm_1 and connect from swarm_2, this should establish a bi-directional
         // connection
@@ -331,19 +362,36 @@ mod tests {
         let swarm_1_peer_id = *swarm_1.local_peer_id();
         let swarm_2_peer_id = *swarm_2.local_peer_id();
 
+        let mut connection_1 = None;
+        let mut connection_2 = None;
+
         // Collect the next 2 behaviour events which occur in either swarms.
         for _ in 0..2 {
             tokio::select! {
-                Event::PeerConnected(peer) = swarm_1.next_behaviour_event() => {
+                Event::PeerConnected(peer, info) = swarm_1.next_behaviour_event() => {
                     events_1.push((peer, None));
+                    connection_1 = Some(info);
+                },
+                Event::PeerConnected(peer, info) = swarm_2.next_behaviour_event() => {
+                    events_2.push((peer, None));
+                    connection_2 = Some(info);
                 },
-                Event::PeerConnected(peer) = swarm_2.next_behaviour_event() => events_2.push((peer, None)),
             }
         }
 
         assert_eq!(events_1.len(), 1);
         assert_eq!(events_2.len(), 1);
 
+        // Swarm_2 dialed swarm_1
+        let connection_1 = connection_1.unwrap();
+        assert_eq!(connection_1.peer_id, swarm_2_peer_id);
+        assert_eq!(connection_1.direction, ConnectionDirection::Inbound);
+        assert!(!connection_1.relayed);
+
+        let connection_2 = connection_2.unwrap();
+        assert_eq!(connection_2.peer_id, swarm_1_peer_id);
+        assert_eq!(connection_2.direction, ConnectionDirection::Outbound);
+
         // The first event should have been a ConnectionEstablished containing the expected peer
         // id
         let (peer_2, message) = events_1[0].clone();
@@ -359,7 +407,7 @@ mod tests {
             peer_2,
             PeerMessage::SyncMessage(SyncMessage::new(
                 0,
-                Message::SyncRequest(0.into(), set_1.clone()),
+                Message::SyncRequest(0.into(), set_1.clone(), 0.into()),
             )),
         );
 
@@ -368,7 +416,7 @@ mod tests {
             peer_1,
             PeerMessage::SyncMessage(SyncMessage::new(
                 1,
-                Message::SyncRequest(0.into(), set_2.clone()),
+                Message::SyncRequest(0.into(), set_2.clone(), 0.into()),
             )),
         );
 
@@ -390,7 +438,7 @@ mod tests {
             message.unwrap(),
             PeerMessage::SyncMessage(SyncMessage::new(
                 1,
-                Message::SyncRequest(0.into(), set_2.clone())
+                Message::SyncRequest(0.into(), set_2.clone(), 0.into())
             ))
         );
 
@@ -399,7 +447,57 @@ mod tests {
         assert_eq!(peer.id(), swarm_1_peer_id);
         assert_eq!(
             message.unwrap(),
-            PeerMessage::SyncMessage(SyncMessage::new(0, Message::SyncRequest(0.into(), set_1)))
+            PeerMessage::SyncMessage(SyncMessage::new(
+                0,
+                Message::SyncRequest(0.into(), set_1, 0.into())
+            ))
         );
     }
+
+    #[tokio::test]
+    async fn fragment_large_messages() {
+        let mut swarm_1 = Swarm::new_ephemeral(|_| PeersBehaviour::new(FaultInjector::default()));
+        let mut swarm_2 = Swarm::new_ephemeral(|_| PeersBehaviour::new(FaultInjector::default()));
+
+        swarm_1.listen().with_memory_addr_external().await;
+        swarm_2.connect(&mut swarm_1).await;
+
+        let mut peer_2 = None;
+        while peer_2.is_none() {
+            tokio::select! {
+                Event::PeerConnected(peer, _) = swarm_1.next_behaviour_event() => {
+                    peer_2 = Some(peer);
+                },
+                _ = swarm_2.next_behaviour_event() => (),
+            }
+        }
+
+        // Large messages are sent in fragments, messages sent after them still arrive in order
+        let large_message = PeerMessage::SyncMessage(SyncMessage::new(
+            0,
+            Message::Entries(Compression::Zstd, vec![7; 2 * 1024 * 1024]),
+        ));
+        let small_message = PeerMessage::SyncMessage(SyncMessage::new(
+            1,
+            Message::SyncRequest(0.into(), random_schema_id_set(), 0.into()),
+        ));
+
+        let peer_2 = peer_2.unwrap();
+        swarm_1
+            .behaviour_mut()
+            .send_message(peer_2, large_message.clone());
+        swarm_1
+            .behaviour_mut()
+            .send_message(peer_2, small_message.clone());
+        tokio::spawn(swarm_1.loop_on_next());
+
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            if let Event::MessageReceived(_, message) = swarm_2.next_behaviour_event().await {
+                received.push(message);
+            }
+        }
+
+        assert_eq!(received, vec![large_message, small_message]);
+    }
 }
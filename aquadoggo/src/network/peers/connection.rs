@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use libp2p::core::ConnectedPoint;
+use libp2p::multiaddr::Protocol;
+use libp2p::swarm::ConnectionId;
+use libp2p::{Multiaddr, PeerId};
+
+use crate::network::Transport;
+
+/// Side which opened a connection or replication session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionDirection {
+    /// Remote peer dialed us or requested the session.
+    Inbound,
+
+    /// We dialed the remote peer or requested the session.
+    Outbound,
+}
+
+/// Details about a connection with another peer.
+///
+/// A peer can hold more than one connection with us at the same time, for example a relayed one
+/// and a direct one after a successful hole punch. They can be told apart by their connection id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// PeerId of the remote peer.
+    pub peer_id: PeerId,
+
+    /// Identifier of this connection, unique within the node.
+    pub connection_id: ConnectionId,
+
+    /// Address of the remote peer.
+    pub address: Multiaddr,
+
+    /// Side which opened the connection.
+    pub direction: ConnectionDirection,
+
+    /// Transport protocol the connection runs on.
+    pub transport: Transport,
+
+    /// True if the connection runs through a relay circuit.
+    pub relayed: bool,
+}
+
+impl ConnectionInfo {
+    /// Returns connection details derived from the endpoint libp2p reports for it.
+    pub fn new(peer_id: PeerId, connection_id: ConnectionId, endpoint: &ConnectedPoint) -> Self {
+        let direction = match endpoint {
+            ConnectedPoint::Dialer { .. } => ConnectionDirection::Outbound,
+            ConnectedPoint::Listener { .. } => ConnectionDirection::Inbound,
+        };
+
+        let address = endpoint.get_remote_address().to_owned();
+
+        let mut transport = Transport::TCP;
+        let mut relayed = false;
+        for protocol in address.iter() {
+            match protocol {
+                Protocol::Quic | Protocol::QuicV1 => transport = Transport::QUIC,
+                Protocol::P2pCircuit => relayed = true,
+                _ => (),
+            }
+        }
+
+        Self {
+            peer_id,
+            connection_id,
+            address,
+            direction,
+            transport,
+            relayed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::core::{ConnectedPoint, Endpoint};
+    use libp2p::swarm::ConnectionId;
+    use libp2p::{Multiaddr, PeerId};
+
+    use crate::network::Transport;
+
+    use super::{ConnectionDirection, ConnectionInfo};
+
+    #[test]
+    fn connection_details() {
+        let peer_id = PeerId::random();
+        let connection_id = ConnectionId::new_unchecked(1);
+
+        let address: Multiaddr = "/ip4/127.0.0.1/udp/2022/quic-v1".parse().unwrap();
+        let info = ConnectionInfo::new(
+            peer_id,
+            connection_id,
+            &ConnectedPoint::Dialer {
+                address: address.clone(),
+                role_override: Endpoint::Dialer,
+            },
+        );
+        assert_eq!(info.address, address);
+        assert_eq!(info.direction, ConnectionDirection::Outbound);
+        assert_eq!(info.transport, Transport::QUIC);
+        assert!(!info.relayed);
+
+        let relayed_address: Multiaddr = format!(
+            "/ip4/127.0.0.1/tcp/2022/p2p/{}/p2p-circuit/p2p/{}",
+            PeerId::random(),
+            peer_id
+        )
+        .parse()
+        .unwrap();
+        let info = ConnectionInfo::new(
+            peer_id,
+            connection_id,
+            &ConnectedPoint::Listener {
+                local_addr: "/ip4/0.0.0.0/tcp/2022".parse().unwrap(),
+                send_back_addr: relayed_address,
+            },
+        );
+        assert_eq!(info.direction, ConnectionDirection::Inbound);
+        assert_eq!(info.transport, Transport::TCP);
+        assert!(info.relayed);
+    }
+}
@@ -0,0 +1,440 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Fragmentation and reassembly of p2panda messages which are too large to be sent at once.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use libp2p::PeerId;
+use serde::ser::SerializeSeq;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::network::peers::PeerMessage;
+use crate::replication::{FRAGMENT_RESUME_TYPE, FRAGMENT_TYPE};
+
+/// Maximum size in bytes of an encoded message before it gets split into fragments.
+pub const MAX_MESSAGE_SIZE: usize = 256 * 1024;
+
+/// Maximum size in bytes of the payload of one fragment.
+const FRAGMENT_SIZE: usize = 128 * 1024;
+
+/// Maximum size in bytes of a fragmented message.
+pub const MAX_TRANSFER_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Maximum size in bytes of all incomplete transfers we keep in memory, the oldest transfers get
+/// dropped first when this is exceeded.
+const MAX_BUFFERED_SIZE: usize = 128 * 1024 * 1024;
+
+/// Duration after which incomplete transfers without any progress get dropped.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Identifier of a transfer, the BLAKE3 hash of the complete encoded message.
+pub type TransferId = [u8; 32];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FragmentError {
+    #[error("Could not encode or decode fragmented message: {0}")]
+    Codec(String),
+
+    #[error("Message of {0} bytes exceeds maximum transfer size")]
+    TooLarge(u64),
+
+    #[error("Fragment at offset {0} does not fit into transfer")]
+    InvalidFragment(u64),
+
+    #[error("Reassembled message does not match its transfer id")]
+    InvalidTransferId,
+
+    #[error("Fragmented messages can not contain fragments")]
+    NestedFragment,
+}
+
+/// Part of an encoded message which is too large to be sent at once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fragment {
+    /// Hash of the complete encoded message.
+    pub transfer_id: TransferId,
+
+    /// Position of this fragment in the encoded message.
+    pub offset: u64,
+
+    /// Size of the complete encoded message.
+    pub total_length: u64,
+
+    /// Bytes of the encoded message starting at the offset.
+    pub bytes: Vec<u8>,
+}
+
+impl Serialize for Fragment {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(5))?;
+        seq.serialize_element(&FRAGMENT_TYPE)?;
+        seq.serialize_element(serde_bytes::Bytes::new(&self.transfer_id))?;
+        seq.serialize_element(&self.offset)?;
+        seq.serialize_element(&self.total_length)?;
+        seq.serialize_element(serde_bytes::Bytes::new(&self.bytes))?;
+        seq.end()
+    }
+}
+
+/// Request to continue a transfer from the given offset, sent by the receiving peer when it
+/// already got parts of the message earlier or when it missed a fragment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FragmentResume {
+    /// Hash of the complete encoded message.
+    pub transfer_id: TransferId,
+
+    /// Position in the encoded message from where the transfer should continue.
+    pub offset: u64,
+}
+
+impl Serialize for FragmentResume {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&FRAGMENT_RESUME_TYPE)?;
+        seq.serialize_element(serde_bytes::Bytes::new(&self.transfer_id))?;
+        seq.serialize_element(&self.offset)?;
+        seq.end()
+    }
+}
+
+/// Encoded message which gets sent in fragments.
+#[derive(Debug)]
+pub struct OutgoingTransfer {
+    transfer_id: TransferId,
+    bytes: Vec<u8>,
+    offset: usize,
+}
+
+impl OutgoingTransfer {
+    /// Returns a transfer when the encoded message exceeds the maximum message size, `None` if it
+    /// can be sent at once.
+    pub fn from_message(message: &PeerMessage) -> Result<Option<Self>, FragmentError> {
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(message, &mut bytes)
+            .map_err(|err| FragmentError::Codec(err.to_string()))?;
+
+        if bytes.len() <= MAX_MESSAGE_SIZE {
+            return Ok(None);
+        }
+
+        if bytes.len() as u64 > MAX_TRANSFER_SIZE {
+            return Err(FragmentError::TooLarge(bytes.len() as u64));
+        }
+
+        Ok(Some(Self {
+            transfer_id: *blake3::hash(&bytes).as_bytes(),
+            bytes,
+            offset: 0,
+        }))
+    }
+
+    pub fn transfer_id(&self) -> &TransferId {
+        &self.transfer_id
+    }
+
+    /// Returns true if not all fragments have been sent yet.
+    pub fn has_fragments(&self) -> bool {
+        self.offset < self.bytes.len()
+    }
+
+    /// Returns the next fragment to send, `None` if all fragments have been sent.
+    pub fn next_fragment(&mut self) -> Option<Fragment> {
+        if !self.has_fragments() {
+            return None;
+        }
+
+        let end = (self.offset + FRAGMENT_SIZE).min(self.bytes.len());
+        let fragment = Fragment {
+            transfer_id: self.transfer_id,
+            offset: self.offset as u64,
+            total_length: self.bytes.len() as u64,
+            bytes: self.bytes[self.offset..end].to_vec(),
+        };
+        self.offset = end;
+
+        Some(fragment)
+    }
+
+    /// Continue sending the transfer from the offset the remote peer asked for.
+    pub fn resume(&mut self, offset: u64) {
+        if offset <= self.bytes.len() as u64 {
+            self.offset = offset as usize;
+        }
+    }
+}
+
+/// Result of receiving a fragment.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Received {
+    /// The fragment completed the transfer, contains the decoded message.
+    Complete(PeerMessage),
+
+    /// More fragments are needed to complete the transfer.
+    Incomplete,
+
+    /// The remote peer should continue the transfer from the given offset.
+    Resume(FragmentResume),
+}
+
+/// Incomplete transfer of a remote peer.
+#[derive(Debug)]
+struct IncomingTransfer {
+    total_length: u64,
+    bytes: Vec<u8>,
+    last_activity: Instant,
+    requested_offset: Option<u64>,
+}
+
+/// Reassembles fragmented messages of all remote peers.
+///
+/// Incomplete transfers are kept across connections. When a peer reconnects and sends the same
+/// message again, we ask it to continue from where the previous connection stopped instead of
+/// receiving the whole message again. Transfers are dropped after some time without progress or
+/// when too many bytes are buffered.
+#[derive(Clone, Debug, Default)]
+pub struct Reassembly {
+    transfers: Arc<Mutex<HashMap<(PeerId, TransferId), IncomingTransfer>>>,
+}
+
+impl Reassembly {
+    /// Add a fragment received from a remote peer to its transfer.
+    pub fn receive(&self, peer_id: PeerId, fragment: Fragment) -> Result<Received, FragmentError> {
+        if fragment.total_length > MAX_TRANSFER_SIZE {
+            return Err(FragmentError::TooLarge(fragment.total_length));
+        }
+
+        let fragment_end = fragment
+            .offset
+            .checked_add(fragment.bytes.len() as u64)
+            .filter(|end| *end <= fragment.total_length)
+            .ok_or(FragmentError::InvalidFragment(fragment.offset))?;
+
+        if fragment.bytes.is_empty() || fragment.bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(FragmentError::InvalidFragment(fragment.offset));
+        }
+
+        let mut transfers = self
+            .transfers
+            .lock()
+            .expect("Could not acquire lock for transfers");
+        transfers.retain(|_, transfer| transfer.last_activity.elapsed() < TRANSFER_TIMEOUT);
+
+        let key = (peer_id, fragment.transfer_id);
+        let transfer = transfers.entry(key).or_insert_with(|| IncomingTransfer {
+            total_length: fragment.total_length,
+            bytes: Vec::new(),
+            last_activity: Instant::now(),
+            requested_offset: None,
+        });
+
+        if transfer.total_length != fragment.total_length {
+            transfers.remove(&key);
+            return Err(FragmentError::InvalidFragment(fragment.offset));
+        }
+
+        transfer.last_activity = Instant::now();
+        let received = transfer.bytes.len() as u64;
+
+        // We either missed a fragment or the peer started sending this message again after we
+        // already received parts of it, ask it once to continue where we are
+        if fragment.offset > received || (fragment.offset == 0 && received > 0) {
+            if transfer.requested_offset == Some(received) {
+                return Ok(Received::Incomplete);
+            }
+
+            transfer.requested_offset = Some(received);
+            return Ok(Received::Resume(FragmentResume {
+                transfer_id: fragment.transfer_id,
+                offset: received,
+            }));
+        }
+
+        // Ignore fragments we already received
+        if fragment_end <= received {
+            return Ok(Received::Incomplete);
+        }
+
+        let skip = (received - fragment.offset) as usize;
+        transfer.bytes.extend_from_slice(&fragment.bytes[skip..]);
+
+        if transfer.bytes.len() as u64 == transfer.total_length {
+            let transfer = transfers.remove(&key).expect("Transfer exists");
+
+            if blake3::hash(&transfer.bytes).as_bytes() != &fragment.transfer_id {
+                return Err(FragmentError::InvalidTransferId);
+            }
+
+            return decode_message(&transfer.bytes).map(Received::Complete);
+        }
+
+        // Drop the transfers with the oldest activity when we buffer too many bytes
+        while transfers
+            .values()
+            .map(|transfer| transfer.bytes.len())
+            .sum::<usize>()
+            > MAX_BUFFERED_SIZE
+        {
+            let oldest = *transfers
+                .iter()
+                .min_by_key(|(_, transfer)| transfer.last_activity)
+                .map(|(key, _)| key)
+                .expect("Transfers are not empty");
+            transfers.remove(&oldest);
+        }
+
+        Ok(Received::Incomplete)
+    }
+}
+
+/// Decode a reassembled message.
+fn decode_message(bytes: &[u8]) -> Result<PeerMessage, FragmentError> {
+    let message: PeerMessage =
+        ciborium::de::from_reader(bytes).map_err(|err| FragmentError::Codec(err.to_string()))?;
+
+    match message {
+        PeerMessage::Fragment(_) | PeerMessage::FragmentResume(_) => {
+            Err(FragmentError::NestedFragment)
+        }
+        message => Ok(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::PeerId;
+    use p2panda_rs::serde::{deserialize_into, serialize_from};
+
+    use crate::network::peers::PeerMessage;
+    use crate::replication::{Compression, Message, SyncMessage};
+
+    use super::{
+        FragmentError, FragmentResume, OutgoingTransfer, Reassembly, Received, FRAGMENT_SIZE,
+        MAX_MESSAGE_SIZE,
+    };
+
+    fn large_message(size: usize) -> PeerMessage {
+        let bytes = (0..size).map(|index| (index % 251) as u8).collect();
+        PeerMessage::SyncMessage(SyncMessage::new(
+            1,
+            Message::Entries(Compression::Zstd, bytes),
+        ))
+    }
+
+    #[test]
+    fn small_messages_are_not_fragmented() {
+        let message = large_message(1024);
+        assert!(OutgoingTransfer::from_message(&message).unwrap().is_none());
+    }
+
+    #[test]
+    fn fragments_and_reassembles() {
+        let message = large_message(MAX_MESSAGE_SIZE * 2);
+        let mut transfer = OutgoingTransfer::from_message(&message).unwrap().unwrap();
+        let reassembly = Reassembly::default();
+        let peer_id = PeerId::random();
+
+        let mut fragments = Vec::new();
+        while let Some(fragment) = transfer.next_fragment() {
+            assert!(fragment.bytes.len() <= FRAGMENT_SIZE);
+
+            // Fragments survive encoding on the wire
+            let encoded = serialize_from(PeerMessage::Fragment(fragment.clone()));
+            assert_eq!(
+                deserialize_into::<PeerMessage>(&encoded).unwrap(),
+                PeerMessage::Fragment(fragment.clone())
+            );
+
+            fragments.push(fragment);
+        }
+        assert!(fragments.len() > 2);
+
+        let last = fragments.pop().unwrap();
+        for fragment in fragments {
+            assert_eq!(
+                reassembly.receive(peer_id, fragment).unwrap(),
+                Received::Incomplete
+            );
+        }
+        assert_eq!(
+            reassembly.receive(peer_id, last).unwrap(),
+            Received::Complete(message)
+        );
+    }
+
+    #[test]
+    fn resumes_transfers() {
+        let message = large_message(MAX_MESSAGE_SIZE * 2);
+        let reassembly = Reassembly::default();
+        let peer_id = PeerId::random();
+
+        // Connection breaks after the first two fragments
+        let mut transfer = OutgoingTransfer::from_message(&message).unwrap().unwrap();
+        for _ in 0..2 {
+            let fragment = transfer.next_fragment().unwrap();
+            reassembly.receive(peer_id, fragment).unwrap();
+        }
+
+        // Peer sends the same message again after reconnecting, we ask it to continue
+        let mut transfer = OutgoingTransfer::from_message(&message).unwrap().unwrap();
+        let first = transfer.next_fragment().unwrap();
+        let second = transfer.next_fragment().unwrap();
+        let resume = FragmentResume {
+            transfer_id: *transfer.transfer_id(),
+            offset: 2 * FRAGMENT_SIZE as u64,
+        };
+        assert_eq!(
+            reassembly.receive(peer_id, first).unwrap(),
+            Received::Resume(resume.clone())
+        );
+
+        // Fragments sent before the peer received our request are ignored
+        assert_eq!(
+            reassembly.receive(peer_id, second).unwrap(),
+            Received::Incomplete
+        );
+
+        // Peer continues from the requested offset
+        transfer.resume(resume.offset);
+        let mut result = Received::Incomplete;
+        while let Some(fragment) = transfer.next_fragment() {
+            result = reassembly.receive(peer_id, fragment).unwrap();
+        }
+        assert_eq!(result, Received::Complete(message));
+    }
+
+    #[test]
+    fn rejects_invalid_fragments() {
+        let message = large_message(MAX_MESSAGE_SIZE * 2);
+        let reassembly = Reassembly::default();
+        let peer_id = PeerId::random();
+
+        let mut transfer = OutgoingTransfer::from_message(&message).unwrap().unwrap();
+        let mut fragment = transfer.next_fragment().unwrap();
+
+        // Fragments exceeding the transfer
+        let mut invalid = fragment.clone();
+        invalid.offset = invalid.total_length;
+        assert!(matches!(
+            reassembly.receive(peer_id, invalid),
+            Err(FragmentError::InvalidFragment(_))
+        ));
+
+        // Reassembled messages which do not match their transfer id
+        fragment.transfer_id = [1; 32];
+        reassembly.receive(peer_id, fragment).unwrap();
+        let mut result = Ok(Received::Incomplete);
+        while let Some(mut fragment) = transfer.next_fragment() {
+            fragment.transfer_id = [1; 32];
+            result = reassembly.receive(peer_id, fragment);
+        }
+        assert_eq!(result, Err(FragmentError::InvalidTransferId));
+    }
+}
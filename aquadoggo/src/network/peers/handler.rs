@@ -11,10 +11,13 @@ use libp2p::swarm::handler::{ConnectionEvent, FullyNegotiatedInbound, FullyNegot
 use libp2p::swarm::{
     ConnectionHandler, ConnectionHandlerEvent, Stream as NegotiatedStream, SubstreamProtocol,
 };
+use libp2p::PeerId;
 use log::warn;
 use thiserror::Error;
 
-use crate::network::peers::{Codec, CodecError, PeerMessage, Protocol};
+use crate::faults::FaultInjector;
+use crate::network::peers::fragment::{OutgoingTransfer, Received};
+use crate::network::peers::{Codec, CodecError, PeerMessage, Protocol, Reassembly};
 
 /// Handler for an incoming or outgoing connection to a remote peer dealing with the p2panda
 /// protocol.
@@ -22,6 +25,11 @@ use crate::network::peers::{Codec, CodecError, PeerMessage, Protocol};
 /// Manages the bi-directional data streams and encodes and decodes p2panda messages on them using
 /// the CBOR format.
 ///
+/// Messages exceeding the maximum message size are split into fragments which are sent one after
+/// another, before any later message. Received fragments are reassembled before the message gets
+/// passed on. Incomplete transfers are kept by the network behaviour, when a peer sends the same
+/// message again after a reconnect we ask it to continue where the previous connection stopped.
+///
 /// Connection handlers can be closed due to critical errors, for example when a replication error
 /// occurred. They also can close after a certain duration of no networking activity (timeout).
 /// Note that this does _not_ close the connection to the peer in general, only the p2panda
@@ -83,7 +91,22 @@ pub struct Handler {
     outbound_substream_establishing: bool,
 
     /// Queue of messages that we want to send to the remote.
-    send_queue: VecDeque<PeerMessage>,
+    send_queue: VecDeque<OutgoingMessage>,
+
+    /// Queue of messages controlling fragmented transfers, sent before any other message.
+    control_queue: VecDeque<PeerMessage>,
+
+    /// Fragmented message we are currently sending or have sent last.
+    ///
+    /// It is kept after all fragments have been sent, in case the remote asks us to send parts of
+    /// it again.
+    current_transfer: Option<OutgoingTransfer>,
+
+    /// Id of the remote peer.
+    peer_id: PeerId,
+
+    /// Incomplete transfers of fragmented messages we received from remote peers.
+    reassembly: Reassembly,
 
     /// Last time we've observed inbound or outbound messaging activity.
     last_io_activity: Instant,
@@ -96,13 +119,17 @@ pub struct Handler {
 }
 
 impl Handler {
-    pub fn new() -> Self {
+    pub fn new(faults: FaultInjector, peer_id: PeerId, reassembly: Reassembly) -> Self {
         Self {
-            listen_protocol: SubstreamProtocol::new(Protocol::new(), ()),
+            listen_protocol: SubstreamProtocol::new(Protocol::new(faults), ()),
             outbound_substream: None,
             inbound_substream: None,
             outbound_substream_establishing: false,
             send_queue: VecDeque::new(),
+            control_queue: VecDeque::new(),
+            current_transfer: None,
+            peer_id,
+            reassembly,
             last_io_activity: Instant::now(),
             critical_error: false,
         }
@@ -128,6 +155,92 @@ impl Handler {
     ) {
         self.inbound_substream = Some(InboundSubstreamState::WaitingInput(protocol));
     }
+
+    /// Queue a message for sending, splitting it into fragments if it is too large.
+    fn queue_message(&mut self, message: PeerMessage) {
+        match OutgoingTransfer::from_message(&message) {
+            Ok(None) => self.send_queue.push_back(OutgoingMessage::Message(message)),
+            Ok(Some(transfer)) => self
+                .send_queue
+                .push_back(OutgoingMessage::Transfer(transfer)),
+            Err(err) => warn!("Dropping outbound message: {err}"),
+        }
+    }
+
+    /// Returns true if there are messages or fragments waiting to be sent.
+    fn has_outbound_messages(&self) -> bool {
+        !self.send_queue.is_empty()
+            || !self.control_queue.is_empty()
+            || self
+                .current_transfer
+                .as_ref()
+                .is_some_and(OutgoingTransfer::has_fragments)
+    }
+
+    /// Returns the next message to send to the remote.
+    ///
+    /// Control messages go first, followed by the fragments of the current transfer and then the
+    /// queued messages in their order.
+    fn next_outbound_message(&mut self) -> Option<PeerMessage> {
+        if let Some(message) = self.control_queue.pop_front() {
+            return Some(message);
+        }
+
+        loop {
+            if let Some(fragment) = self
+                .current_transfer
+                .as_mut()
+                .and_then(OutgoingTransfer::next_fragment)
+            {
+                return Some(PeerMessage::Fragment(fragment));
+            }
+
+            match self.send_queue.pop_front()? {
+                OutgoingMessage::Message(message) => return Some(message),
+                OutgoingMessage::Transfer(transfer) => self.current_transfer = Some(transfer),
+            }
+        }
+    }
+
+    /// Handle a message controlling fragmented transfers, returns a message which should be passed
+    /// on to the behaviour.
+    fn on_inbound_message(&mut self, message: PeerMessage) -> Option<PeerMessage> {
+        match message {
+            PeerMessage::Fragment(fragment) => {
+                match self.reassembly.receive(self.peer_id, fragment) {
+                    Ok(Received::Complete(message)) => return Some(message),
+                    Ok(Received::Incomplete) => (),
+                    Ok(Received::Resume(resume)) => {
+                        self.control_queue
+                            .push_back(PeerMessage::FragmentResume(resume));
+                    }
+                    Err(err) => warn!("Ignoring inbound fragment: {err}"),
+                }
+
+                None
+            }
+            PeerMessage::FragmentResume(resume) => {
+                if let Some(transfer) = self.current_transfer.as_mut() {
+                    if transfer.transfer_id() == &resume.transfer_id {
+                        transfer.resume(resume.offset);
+                    }
+                }
+
+                None
+            }
+            message => Some(message),
+        }
+    }
+}
+
+/// Message waiting to be sent to the remote.
+#[derive(Debug)]
+enum OutgoingMessage {
+    /// Message which can be sent at once.
+    Message(PeerMessage),
+
+    /// Message which gets sent in fragments.
+    Transfer(OutgoingTransfer),
 }
 
 /// An event sent from the network behaviour to the connection handler.
@@ -219,7 +332,7 @@ impl ConnectionHandler for Handler {
     fn on_behaviour_event(&mut self, event: Self::FromBehaviour) {
         match event {
             HandlerFromBehaviour::Message(message) => {
-                self.send_queue.push_back(message);
+                self.queue_message(message);
             }
             HandlerFromBehaviour::CriticalError => {
                 self.critical_error = true;
@@ -233,17 +346,6 @@ impl ConnectionHandler for Handler {
     ) -> Poll<
         ConnectionHandlerEvent<Self::OutboundProtocol, Self::OutboundOpenInfo, Self::ToBehaviour>,
     > {
-        // Determine if we need to create the outbound stream
-        if !self.send_queue.is_empty()
-            && self.outbound_substream.is_none()
-            && !self.outbound_substream_establishing
-        {
-            self.outbound_substream_establishing = true;
-            return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
-                protocol: self.listen_protocol(),
-            });
-        }
-
         // Process inbound stream
         loop {
             match std::mem::replace(
@@ -259,9 +361,12 @@ impl ConnectionHandler for Handler {
                             self.inbound_substream =
                                 Some(InboundSubstreamState::WaitingInput(substream));
 
-                            return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
-                                HandlerToBehaviour::Message(message),
-                            ));
+                            // Fragments are handled here until their message is complete
+                            if let Some(message) = self.on_inbound_message(message) {
+                                return Poll::Ready(ConnectionHandlerEvent::NotifyBehaviour(
+                                    HandlerToBehaviour::Message(message),
+                                ));
+                            }
                         }
                         Poll::Ready(Some(Err(err))) => {
                             warn!("Error decoding inbound message: {err}");
@@ -312,6 +417,18 @@ impl ConnectionHandler for Handler {
             }
         }
 
+        // Determine if we need to create the outbound stream. This happens after processing the
+        // inbound stream as received fragments can make us send control messages
+        if self.has_outbound_messages()
+            && self.outbound_substream.is_none()
+            && !self.outbound_substream_establishing
+        {
+            self.outbound_substream_establishing = true;
+            return Poll::Ready(ConnectionHandlerEvent::OutboundSubstreamRequest {
+                protocol: self.listen_protocol(),
+            });
+        }
+
         // Process outbound stream
         loop {
             match std::mem::replace(
@@ -319,7 +436,7 @@ impl ConnectionHandler for Handler {
                 Some(OutboundSubstreamState::Poisoned),
             ) {
                 Some(OutboundSubstreamState::WaitingOutput(substream)) => {
-                    match self.send_queue.pop_front() {
+                    match self.next_outbound_message() {
                         Some(message) => {
                             self.outbound_substream =
                                 Some(OutboundSubstreamState::PendingSend(substream, message));
@@ -1,5 +1,9 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::convert::TryFrom;
+
+use ed25519_dalek::Signature;
+use p2panda_rs::document::DocumentId;
 use p2panda_rs::entry::{EncodedEntry, LogId, SeqNum};
 use p2panda_rs::identity::PublicKey;
 use p2panda_rs::operation::EncodedOperation;
@@ -8,10 +12,15 @@ use serde::de::Visitor;
 use serde::{Deserialize, Serialize};
 
 use crate::replication::{
-    Announcement, AnnouncementMessage, Message, Mode, SchemaIdSet, SessionId, SyncMessage,
-    ANNOUNCE_TYPE, ENTRY_TYPE, HAVE_TYPE, SYNC_DONE_TYPE, SYNC_REQUEST_TYPE,
+    default_supported_modes, Announcement, AnnouncementMessage, Compression, Direction, LogRanges,
+    Message, Mode, OperatorProof, SchemaIdSet, SessionId, SyncMessage, ANNOUNCE_TYPE,
+    BLOB_REQUEST_TYPE, ENTRIES_TYPE, ENTRY_TYPE, FRAGMENT_RESUME_TYPE, FRAGMENT_TYPE, HAVE_TYPE,
+    OPERATOR_PROOF_TYPE, SYNC_DONE_TYPE, SYNC_REQUEST_TYPE, WANT_TYPE,
 };
 
+use crate::network::peers::fragment::TransferId;
+use crate::network::peers::{Fragment, FragmentResume};
+
 /// p2panda protocol messages which can be sent over the wire.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
 #[serde(untagged)]
@@ -21,6 +30,15 @@ pub enum PeerMessage {
 
     /// Replication status and data exchange.
     SyncMessage(SyncMessage),
+
+    /// Part of a message which is too large to be sent at once.
+    Fragment(Fragment),
+
+    /// Request to continue sending a fragmented message from a given offset.
+    FragmentResume(FragmentResume),
+
+    /// Proof of a peer that it is run by the holder of an operator key.
+    OperatorProof(OperatorProof),
 }
 
 impl<'de> Deserialize<'de> for PeerMessage {
@@ -63,11 +81,21 @@ impl<'de> Deserialize<'de> for PeerMessage {
                             serde::de::Error::custom("invalid target set in announce message")
                         })?;
 
+                        // Peers not supporting compression omit this field
+                        let supported_compressions: Vec<Compression> =
+                            seq.next_element()?.unwrap_or_default();
+
+                        // Peers only supporting the default replication modes omit this field
+                        let supported_modes: Vec<Mode> =
+                            seq.next_element()?.unwrap_or_else(default_supported_modes);
+
                         PeerMessage::Announce(AnnouncementMessage(
                             protocol_version,
                             Announcement {
                                 supported_schema_ids,
                                 timestamp,
+                                supported_compressions,
+                                supported_modes,
                             },
                         ))
                     }
@@ -94,9 +122,12 @@ impl<'de> Deserialize<'de> for PeerMessage {
                             ));
                         }
 
+                        // Peers replicating in both directions omit this field
+                        let direction: Direction = seq.next_element()?.unwrap_or_default();
+
                         PeerMessage::SyncMessage(SyncMessage::new(
                             session_id,
-                            Message::SyncRequest(mode, target_set),
+                            Message::SyncRequest(mode, target_set, direction),
                         ))
                     }
                     ENTRY_TYPE => {
@@ -115,6 +146,30 @@ impl<'de> Deserialize<'de> for PeerMessage {
                             Message::Entry(entry_bytes, operation_bytes),
                         ))
                     }
+                    ENTRIES_TYPE => {
+                        let session_id: SessionId = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing session id in replication message")
+                        })?;
+
+                        let compression: Compression = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing compression in entries message")
+                        })?;
+
+                        if compression == Compression::Unknown {
+                            return Err(serde::de::Error::custom(
+                                "unknown compression in entries message",
+                            ));
+                        }
+
+                        let bytes: serde_bytes::ByteBuf = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing payload in entries message")
+                        })?;
+
+                        PeerMessage::SyncMessage(SyncMessage::new(
+                            session_id,
+                            Message::Entries(compression, bytes.into_vec()),
+                        ))
+                    }
                     SYNC_DONE_TYPE => {
                         let session_id: SessionId = seq.next_element()?.ok_or_else(|| {
                             serde::de::Error::custom("missing session id in replication message")
@@ -144,6 +199,117 @@ impl<'de> Deserialize<'de> for PeerMessage {
                             Message::Have(log_heights),
                         ))
                     }
+                    WANT_TYPE => {
+                        let session_id: SessionId = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing session id in replication message")
+                        })?;
+
+                        let log_ranges: Vec<LogRanges> = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing log ranges in want message")
+                        })?;
+
+                        for (_, ranges) in &log_ranges {
+                            if ranges.iter().any(|(_, from, to)| from > to) {
+                                return Err(serde::de::Error::custom(
+                                    "invalid log range in want message",
+                                ));
+                            }
+                        }
+
+                        PeerMessage::SyncMessage(SyncMessage::new(
+                            session_id,
+                            Message::Want(log_ranges),
+                        ))
+                    }
+                    BLOB_REQUEST_TYPE => {
+                        let session_id: SessionId = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing session id in replication message")
+                        })?;
+
+                        let document_id: DocumentId = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing document id in blob request message")
+                        })?;
+
+                        PeerMessage::SyncMessage(SyncMessage::new(
+                            session_id,
+                            Message::BlobRequest(document_id),
+                        ))
+                    }
+                    FRAGMENT_TYPE => {
+                        let transfer_id: serde_bytes::ByteBuf =
+                            seq.next_element()?.ok_or_else(|| {
+                                serde::de::Error::custom("missing transfer id in fragment message")
+                            })?;
+                        let transfer_id =
+                            TransferId::try_from(transfer_id.as_slice()).map_err(|_| {
+                                serde::de::Error::custom("invalid transfer id in fragment message")
+                            })?;
+
+                        let offset: u64 = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing offset in fragment message")
+                        })?;
+
+                        let total_length: u64 = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing total length in fragment message")
+                        })?;
+
+                        let bytes: serde_bytes::ByteBuf = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing payload in fragment message")
+                        })?;
+
+                        PeerMessage::Fragment(Fragment {
+                            transfer_id,
+                            offset,
+                            total_length,
+                            bytes: bytes.into_vec(),
+                        })
+                    }
+                    FRAGMENT_RESUME_TYPE => {
+                        let transfer_id: serde_bytes::ByteBuf =
+                            seq.next_element()?.ok_or_else(|| {
+                                serde::de::Error::custom(
+                                    "missing transfer id in fragment resume message",
+                                )
+                            })?;
+                        let transfer_id =
+                            TransferId::try_from(transfer_id.as_slice()).map_err(|_| {
+                                serde::de::Error::custom(
+                                    "invalid transfer id in fragment resume message",
+                                )
+                            })?;
+
+                        let offset: u64 = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing offset in fragment resume message")
+                        })?;
+
+                        PeerMessage::FragmentResume(FragmentResume {
+                            transfer_id,
+                            offset,
+                        })
+                    }
+                    OPERATOR_PROOF_TYPE => {
+                        let public_key: PublicKey = seq.next_element()?.ok_or_else(|| {
+                            serde::de::Error::custom("missing public key in operator proof message")
+                        })?;
+
+                        let signature: serde_bytes::ByteBuf =
+                            seq.next_element()?.ok_or_else(|| {
+                                serde::de::Error::custom(
+                                    "missing signature in operator proof message",
+                                )
+                            })?;
+                        let signature =
+                            Signature::try_from(signature.as_slice()).map_err(|_| {
+                                serde::de::Error::custom(
+                                    "invalid signature in operator proof message",
+                                )
+                            })?;
+
+                        PeerMessage::OperatorProof(OperatorProof {
+                            public_key,
+                            signature,
+                        })
+                    }
                     _ => return Err(serde::de::Error::custom("unknown message type")),
                 };
 
@@ -167,14 +333,17 @@ impl<'de> Deserialize<'de> for PeerMessage {
 mod tests {
     use ciborium::cbor;
     use ciborium::value::{Error, Value};
+    use libp2p::PeerId;
+    use p2panda_rs::document::DocumentId;
     use p2panda_rs::entry::{LogId, SeqNum};
-    use p2panda_rs::identity::PublicKey;
-    use p2panda_rs::serde::{deserialize_into, serialize_value};
-    use p2panda_rs::test_utils::fixtures::public_key;
+    use p2panda_rs::identity::{KeyPair, PublicKey};
+    use p2panda_rs::serde::{deserialize_into, serialize_from, serialize_value};
+    use p2panda_rs::test_utils::fixtures::{document_id, key_pair, public_key};
     use rstest::rstest;
 
     use crate::replication::{
-        Announcement, AnnouncementMessage, Message, Mode, SchemaIdSet, SyncMessage,
+        Announcement, AnnouncementMessage, Compression, Direction, Message, Mode, OperatorProof,
+        SchemaIdSet, SyncMessage,
     };
     use crate::test_utils::helpers::random_schema_id_set;
 
@@ -185,6 +354,7 @@ mod tests {
         #[from(random_schema_id_set)] supported_schema_ids: SchemaIdSet,
         #[from(random_schema_id_set)] target_set: SchemaIdSet,
         public_key: PublicKey,
+        document_id: DocumentId,
     ) {
         assert_eq!(
             deserialize_into::<PeerMessage>(&serialize_value(cbor!([
@@ -196,16 +366,76 @@ mod tests {
             .unwrap(),
             PeerMessage::Announce(AnnouncementMessage::new(Announcement {
                 timestamp: 12345678,
+                supported_schema_ids: supported_schema_ids.clone(),
+                supported_compressions: vec![],
+                supported_modes: vec![Mode::LogHeight],
+            }))
+        );
+
+        assert_eq!(
+            deserialize_into::<PeerMessage>(&serialize_value(cbor!([
+                0,
+                1,
+                12345678,
                 supported_schema_ids,
+                [1, 0]
+            ])))
+            .unwrap(),
+            PeerMessage::Announce(AnnouncementMessage::new(Announcement {
+                timestamp: 12345678,
+                supported_schema_ids: supported_schema_ids.clone(),
+                supported_compressions: vec![Compression::Deflate, Compression::Zstd],
+                supported_modes: vec![Mode::LogHeight],
             }))
         );
 
+        assert_eq!(
+            deserialize_into::<PeerMessage>(&serialize_value(cbor!([
+                0,
+                1,
+                12345678,
+                supported_schema_ids,
+                [],
+                [1, 0]
+            ])))
+            .unwrap(),
+            PeerMessage::Announce(AnnouncementMessage::new(Announcement {
+                timestamp: 12345678,
+                supported_schema_ids,
+                supported_compressions: vec![],
+                supported_modes: vec![Mode::SetReconciliation, Mode::LogHeight],
+            }))
+        );
+
+        assert_eq!(
+            deserialize_into::<PeerMessage>(&serialize_value(cbor!([
+                4,
+                12,
+                0,
+                serde_bytes::Bytes::new(&[1, 2, 3])
+            ])))
+            .unwrap(),
+            PeerMessage::SyncMessage(SyncMessage::new(
+                12,
+                Message::Entries(Compression::Zstd, vec![1, 2, 3])
+            ))
+        );
+
         assert_eq!(
             deserialize_into::<PeerMessage>(&serialize_value(cbor!([1, 12, 0, target_set])))
                 .unwrap(),
             PeerMessage::SyncMessage(SyncMessage::new(
                 12,
-                Message::SyncRequest(Mode::LogHeight, target_set.clone())
+                Message::SyncRequest(Mode::LogHeight, target_set.clone(), Direction::Both)
+            ))
+        );
+
+        assert_eq!(
+            deserialize_into::<PeerMessage>(&serialize_value(cbor!([1, 12, 0, target_set, 2])))
+                .unwrap(),
+            PeerMessage::SyncMessage(SyncMessage::new(
+                12,
+                Message::SyncRequest(Mode::LogHeight, target_set.clone(), Direction::Pull)
             ))
         );
 
@@ -236,6 +466,47 @@ mod tests {
                 )])
             ))
         );
+
+        assert_eq!(
+            deserialize_into::<PeerMessage>(&serialize_value(cbor!([
+                11,
+                12,
+                vec![(
+                    serde_bytes::Bytes::new(&public_key.to_bytes()),
+                    vec![(0, 1, 256)]
+                )]
+            ])))
+            .unwrap(),
+            PeerMessage::SyncMessage(SyncMessage::new(
+                12,
+                Message::Want(vec![(
+                    public_key,
+                    vec![(
+                        LogId::default(),
+                        SeqNum::default(),
+                        SeqNum::new(256).unwrap()
+                    )]
+                )])
+            ))
+        );
+
+        assert_eq!(
+            deserialize_into::<PeerMessage>(&serialize_value(cbor!([20, 12, document_id])))
+                .unwrap(),
+            PeerMessage::SyncMessage(SyncMessage::new(12, Message::BlobRequest(document_id)))
+        );
+    }
+
+    #[rstest]
+    fn deserialize_operator_proof(key_pair: KeyPair) {
+        let proof = OperatorProof::new(&key_pair, &PeerId::random(), &PeerId::random());
+        assert_eq!(
+            deserialize_into::<PeerMessage>(&serialize_from(PeerMessage::OperatorProof(
+                proof.clone()
+            )))
+            .unwrap(),
+            PeerMessage::OperatorProof(proof)
+        );
     }
 
     #[rstest]
@@ -246,13 +517,25 @@ mod tests {
     #[should_panic(expected = "missing timestamp in announce message")]
     #[case::announce_missing_timestamp(cbor!([0, 122]))]
     #[should_panic(expected = "too many fields for p2panda message")]
-    #[case::announce_too_many_fields(cbor!([0, 1, 0, ["schema_field_definition_v1"], "too much"]))]
+    #[case::announce_too_many_fields(cbor!([0, 1, 0, ["schema_field_definition_v1"], [0], [0], "too much"]))]
     #[should_panic(expected = "missing session id in replication message")]
     #[case::sync_only_message_type(cbor!([1]))]
     #[should_panic(expected = "empty target set in sync request")]
     #[case::sync_only_message_type(cbor!([1, 0, 0, []]))]
     #[should_panic(expected = "too many fields for p2panda message")]
     #[case::sync_too_many_fields(cbor!([1, 0, 0, ["schema_field_definition_v1"], "too much"]))]
+    #[should_panic(expected = "missing payload in entries message")]
+    #[case::entries_missing_payload(cbor!([4, 0, 0]))]
+    #[should_panic(expected = "unknown compression in entries message")]
+    #[case::entries_unknown_compression(cbor!([4, 0, 12, serde_bytes::Bytes::new(&[1, 2, 3])]))]
+    #[should_panic(expected = "missing log ranges in want message")]
+    #[case::want_missing_ranges(cbor!([11, 0]))]
+    #[should_panic(expected = "missing document id in blob request message")]
+    #[case::blob_request_missing_document_id(cbor!([20, 0]))]
+    #[should_panic(expected = "invalid log range in want message")]
+    #[case::want_invalid_range(cbor!([11, 0, [[serde_bytes::Bytes::new(&[0; 32]), [[0, 8, 4]]]]]))]
+    #[should_panic(expected = "missing signature in operator proof message")]
+    #[case::operator_proof_missing_signature(cbor!([40, serde_bytes::Bytes::new(&[0; 32])]))]
     fn deserialize_invalid_messages(#[case] cbor: Result<Value, Error>) {
         // Check the cbor is valid
         assert!(cbor.is_ok());
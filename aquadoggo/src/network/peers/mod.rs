@@ -1,12 +1,16 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 mod behaviour;
+mod connection;
+mod fragment;
 mod handler;
 mod message;
 mod peer;
 mod protocol;
 
 pub use behaviour::{Behaviour, Event};
+pub use connection::{ConnectionDirection, ConnectionInfo};
+pub use fragment::{Fragment, FragmentResume, Reassembly};
 pub use message::PeerMessage;
 pub use peer::Peer;
 pub use protocol::{Codec, CodecError, Protocol};
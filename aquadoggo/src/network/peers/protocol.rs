@@ -1,26 +1,79 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::io;
 use std::pin::Pin;
 
-use asynchronous_codec::{CborCodec, CborCodecError, Framed};
+use asynchronous_codec::{BytesMut, CborCodec, CborCodecError, Decoder, Encoder, Framed};
 use futures::{future, AsyncRead, AsyncWrite, Future};
 use libp2p::core::UpgradeInfo;
 use libp2p::{InboundUpgrade, OutboundUpgrade};
 
+use crate::faults::{FaultError, FaultInjector, FaultOutcome, FaultPoint};
 use crate::network::peers::PeerMessage;
 
 pub const PROTOCOL_NAME: &str = "/p2p/p2panda/1.0.0";
 
 pub type CodecError = CborCodecError;
 
-pub type Codec = CborCodec<PeerMessage, PeerMessage>;
+impl From<FaultError> for CodecError {
+    fn from(err: FaultError) -> Self {
+        CborCodecError::Io(io::Error::other(err))
+    }
+}
+
+/// CBOR codec for peer messages with injectable faults.
+#[derive(Debug)]
+pub struct Codec {
+    inner: CborCodec<PeerMessage, PeerMessage>,
+    faults: FaultInjector,
+}
+
+impl Codec {
+    pub fn new(faults: FaultInjector) -> Self {
+        Self {
+            inner: CborCodec::new(),
+            faults,
+        }
+    }
+}
+
+impl Encoder for Codec {
+    type Item<'a> = PeerMessage;
+    type Error = CodecError;
+
+    fn encode(&mut self, message: Self::Item<'_>, buf: &mut BytesMut) -> Result<(), Self::Error> {
+        if self.faults.check_blocking(FaultPoint::EncodeMessage)? == FaultOutcome::Drop {
+            return Ok(());
+        }
+
+        self.inner.encode(message, buf)
+    }
+}
+
+impl Decoder for Codec {
+    type Item = PeerMessage;
+    type Error = CodecError;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // Dropped messages are consumed from the buffer, continue with the next one
+        while let Some(message) = self.inner.decode(buf)? {
+            if self.faults.check_blocking(FaultPoint::DecodeMessage)? == FaultOutcome::Continue {
+                return Ok(Some(message));
+            }
+        }
+
+        Ok(None)
+    }
+}
 
 #[derive(Clone, Debug)]
-pub struct Protocol;
+pub struct Protocol {
+    faults: FaultInjector,
+}
 
 impl Protocol {
-    pub fn new() -> Self {
-        Self
+    pub fn new(faults: FaultInjector) -> Self {
+        Self { faults }
     }
 }
 
@@ -42,7 +95,7 @@ where
     type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
 
     fn upgrade_inbound(self, socket: TSocket, _protocol_id: Self::Info) -> Self::Future {
-        Box::pin(future::ok(Framed::new(socket, Codec::new())))
+        Box::pin(future::ok(Framed::new(socket, Codec::new(self.faults))))
     }
 }
 
@@ -55,6 +108,63 @@ where
     type Future = Pin<Box<dyn Future<Output = Result<Self::Output, Self::Error>> + Send>>;
 
     fn upgrade_outbound(self, socket: TSocket, _protocol_id: Self::Info) -> Self::Future {
-        Box::pin(future::ok(Framed::new(socket, Codec::new())))
+        Box::pin(future::ok(Framed::new(socket, Codec::new(self.faults))))
+    }
+}
+
+#[cfg(all(test, feature = "fault-injection"))]
+mod tests {
+    use std::time::Duration;
+
+    use asynchronous_codec::{BytesMut, Decoder, Encoder};
+
+    use crate::faults::{FaultInjector, FaultPoint};
+    use crate::network::peers::PeerMessage;
+    use crate::replication::{Message, SchemaIdSet, SyncMessage};
+    use crate::test_utils::helpers::random_schema_id_set;
+    use crate::test_utils::{delay, drop_next, fail_once};
+
+    use super::Codec;
+
+    fn message(session_id: u64, target_set: &SchemaIdSet) -> PeerMessage {
+        PeerMessage::SyncMessage(SyncMessage::new(
+            session_id,
+            Message::SyncRequest(0.into(), target_set.clone(), 0.into()),
+        ))
+    }
+
+    #[test]
+    fn inject_codec_faults() {
+        let faults = FaultInjector::default();
+        let mut codec = Codec::new(faults.clone());
+        let mut buf = BytesMut::new();
+        let target_set = random_schema_id_set();
+
+        // Dropped messages are not written
+        drop_next(&faults, FaultPoint::EncodeMessage, 1);
+        codec.encode(message(0, &target_set), &mut buf).unwrap();
+        assert!(buf.is_empty());
+
+        fail_once(&faults, FaultPoint::EncodeMessage);
+        assert!(codec.encode(message(0, &target_set), &mut buf).is_err());
+
+        codec.encode(message(1, &target_set), &mut buf).unwrap();
+        codec.encode(message(2, &target_set), &mut buf).unwrap();
+
+        // Dropped messages are skipped when decoding
+        drop_next(&faults, FaultPoint::DecodeMessage, 1);
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(message(2, &target_set))
+        );
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        // Delayed messages arrive eventually
+        delay(&faults, FaultPoint::DecodeMessage, Duration::from_millis(1));
+        codec.encode(message(3, &target_set), &mut buf).unwrap();
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            Some(message(3, &target_set))
+        );
     }
 }
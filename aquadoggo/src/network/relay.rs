@@ -1,12 +1,101 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::core::transport::ListenerId;
 use libp2p::multiaddr::Protocol;
+use libp2p::swarm::NetworkBehaviour;
 use libp2p::{rendezvous, Multiaddr, PeerId, Swarm};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
 
 use crate::network::behaviour::P2pandaBehaviour;
-use crate::network::config::NODE_NAMESPACE;
+
+/// Share of the registration lifetime after which we renew it.
+const RENEWAL_THRESHOLD: f64 = 0.75;
+
+/// Time we wait before retrying a failed registration, doubles with every further failure.
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Longest time we wait before retrying a failed registration.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 10);
+
+/// State of our registration at a relay.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Registration {
+    /// We did not register yet or are waiting for the relay to accept our registration.
+    Pending,
+
+    /// The relay accepted our registration, we renew it after `renew_at`.
+    Active {
+        renew_at: Instant,
+        expires_at: Instant,
+    },
+
+    /// The relay refused our registration or it got lost, we try again after `retry_at`.
+    Failed { reason: String, retry_at: Instant },
+}
+
+/// Snapshot of the state of a relay, used to inform about it outside of the network service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayStatus {
+    /// PeerId of the relay node.
+    pub peer_id: PeerId,
+
+    /// Address we're connected to the relay at.
+    pub addr: Multiaddr,
+
+    /// Are we currently connected to the relay.
+    pub connected: bool,
+
+    /// Was our relay circuit reservation accepted.
+    pub reservation_accepted: bool,
+
+    /// State of our registration at the relay.
+    pub registration: Registration,
+}
+
+impl RelayStatus {
+    /// Returns the state of the registration as a string, one of "pending", "active", "expiring",
+    /// "failed" or "disconnected".
+    ///
+    /// Registrations are "expiring" when they are due for renewal but the relay did not renew
+    /// them yet.
+    pub fn state(&self, now: Instant) -> &'static str {
+        if !self.connected {
+            return "disconnected";
+        }
+
+        match &self.registration {
+            Registration::Pending => "pending",
+            Registration::Active { renew_at, .. } if now >= *renew_at => "expiring",
+            Registration::Active { .. } => "active",
+            Registration::Failed { .. } => "failed",
+        }
+    }
+
+    /// Returns the time left until the registration expires.
+    pub fn expires_in(&self, now: Instant) -> Option<Duration> {
+        match &self.registration {
+            Registration::Active { expires_at, .. } => {
+                Some(expires_at.saturating_duration_since(now))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the reason why the last registration attempt failed.
+    pub fn error(&self) -> Option<&str> {
+        match &self.registration {
+            Registration::Failed { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+}
 
 /// A relay node.
+#[derive(Debug, Clone)]
 pub struct Relay {
     /// PeerId of the relay node.
     pub(crate) peer_id: PeerId,
@@ -31,22 +120,61 @@ pub struct Relay {
 
     /// Was our relay circuit reservation accepted.
     pub(crate) reservation_accepted: bool,
+
+    /// Did we ask the relay for the peers registered in our namespace yet to learn about its
+    /// load.
+    pub(crate) probing: bool,
+
+    /// Listener on the circuit address of this relay, if we're using it.
+    pub(crate) listener: Option<ListenerId>,
+
+    /// Are we currently connected to the relay.
+    pub(crate) connected: bool,
+
+    /// Number of peers registered in our namespace, as reported by the relay.
+    pub(crate) load: Option<usize>,
+
+    /// Number of times the connection to the relay got lost or it refused our registration.
+    pub(crate) failures: usize,
+
+    /// State of our registration, used to renew it before it expires.
+    pub(crate) registration: Registration,
+
+    /// Number of failed registration attempts in a row, used to back off retries.
+    pub(crate) retries: u32,
 }
 
 impl Relay {
-    pub fn new(peer_id: PeerId, addr: Multiaddr) -> Self {
+    pub fn new(peer_id: PeerId, addr: Multiaddr, namespace: String) -> Self {
         Relay {
             peer_id,
             addr,
-            namespace: NODE_NAMESPACE.to_string(),
+            namespace,
             told_addr: false,
             discovering: false,
             registering: false,
             registered: false,
             reservation_accepted: false,
+            probing: false,
+            listener: None,
+            connected: true,
+            load: None,
+            failures: 0,
+            registration: Registration::Pending,
+            retries: 0,
         }
     }
 
+    /// Forget the state of the previous connection after the relay connected again, keeping its
+    /// health and load reports.
+    pub fn reconnect(&mut self) {
+        *self = Self {
+            load: self.load,
+            failures: self.failures,
+            ..Self::new(self.peer_id, self.addr.clone(), self.namespace.clone())
+        };
+    }
+
     /// The circuit address we should listen at for this relay.
     pub fn circuit_addr(&self) -> Multiaddr {
         self.addr
@@ -56,23 +184,115 @@ impl Relay {
     }
 
     /// Start listening on the relay circuit address and register on our discovery namespace.
-    pub fn register(&mut self, swarm: &mut Swarm<P2pandaBehaviour>) -> Result<bool, anyhow::Error> {
+    ///
+    /// After a failed attempt we wait before registering again, the delay grows with every
+    /// further failure.
+    pub fn register<B: NetworkBehaviour>(
+        &mut self,
+        swarm: &mut Swarm<P2pandaBehaviour<B>>,
+    ) -> Result<bool, anyhow::Error> {
         if self.registered || self.registering {
             return Ok(false);
         }
 
-        // Start listening on the circuit relay address.
-        let circuit_address = self.circuit_addr();
-        swarm.listen_on(circuit_address.clone())?;
+        if let Registration::Failed { retry_at, .. } = self.registration {
+            if Instant::now() < retry_at {
+                return Ok(false);
+            }
+        }
+
+        // Start listening on the circuit relay address, unless we still are from a previous
+        // attempt.
+        if self.listener.is_none() {
+            let circuit_address = self.circuit_addr();
+            self.listener = Some(swarm.listen_on(circuit_address)?);
+        }
+
+        self.send_registration(swarm)?;
+
+        Ok(true)
+    }
+
+    /// Renew our registration when it is due, before the relay forgets about us.
+    ///
+    /// Registrations which expired before the relay renewed them are considered failed.
+    pub fn renew<B: NetworkBehaviour>(
+        &mut self,
+        swarm: &mut Swarm<P2pandaBehaviour<B>>,
+        now: Instant,
+    ) -> Result<bool, anyhow::Error> {
+        let (renew_at, expires_at) = match self.registration {
+            Registration::Active {
+                renew_at,
+                expires_at,
+            } => (renew_at, expires_at),
+            _ => return Ok(false),
+        };
+
+        if now >= expires_at {
+            self.on_registration_failed("Registration expired".into(), now);
+            return Ok(false);
+        }
+
+        if now < renew_at || self.registering {
+            return Ok(false);
+        }
+
+        self.send_registration(swarm)?;
+
+        Ok(true)
+    }
+
+    /// Remember that the relay accepted our registration for the given ttl in seconds.
+    pub fn on_registered(&mut self, ttl: u64, now: Instant) {
+        let ttl = Duration::from_secs(ttl);
+
+        self.registering = false;
+        self.registered = true;
+        self.retries = 0;
+        self.registration = Registration::Active {
+            renew_at: now + ttl.mul_f64(RENEWAL_THRESHOLD),
+            expires_at: now + ttl,
+        };
+    }
+
+    /// Remember that our registration or circuit reservation failed and schedule a retry.
+    pub fn on_registration_failed(&mut self, reason: String, now: Instant) {
+        self.registering = false;
+        self.registered = false;
+        self.discovering = false;
+        self.registration = Registration::Failed {
+            reason,
+            retry_at: now + retry_delay(self.retries),
+        };
+        self.retries += 1;
+    }
+
+    /// Returns a snapshot of the state of this relay.
+    pub fn status(&self) -> RelayStatus {
+        RelayStatus {
+            peer_id: self.peer_id,
+            addr: self.addr.clone(),
+            connected: self.connected,
+            reservation_accepted: self.reservation_accepted,
+            registration: self.registration.clone(),
+        }
+    }
 
-        // Register in the `NODE_NAMESPACE` using the rendezvous network behaviour.
+    /// Send a registration request to the relay.
+    fn send_registration<B: NetworkBehaviour>(
+        &mut self,
+        swarm: &mut Swarm<P2pandaBehaviour<B>>,
+    ) -> Result<(), anyhow::Error> {
+        // Register in the namespace of our network using the rendezvous network behaviour.
+        let namespace = rendezvous::Namespace::new(self.namespace.clone())?;
         let result = swarm
             .behaviour_mut()
             .rendezvous_client
             .as_mut()
             .unwrap()
             .register(
-                rendezvous::Namespace::from_static(NODE_NAMESPACE),
+                namespace,
                 self.peer_id,
                 None, // Default ttl is 7200s
             );
@@ -81,11 +301,14 @@ impl Relay {
         self.registering = result.is_ok();
         result?;
 
-        Ok(true)
+        Ok(())
     }
 
     /// Start discovering peers also registered at the same namespace.
-    pub fn discover(&mut self, swarm: &mut Swarm<P2pandaBehaviour>) -> bool {
+    pub fn discover<B: NetworkBehaviour>(
+        &mut self,
+        swarm: &mut Swarm<P2pandaBehaviour<B>>,
+    ) -> bool {
         if self.reservation_accepted && self.registered && !self.discovering {
             self.discovering = true;
 
@@ -96,7 +319,7 @@ impl Relay {
                 .expect("Relay client behaviour exists")
                 .discover(
                     Some(
-                        rendezvous::Namespace::new(NODE_NAMESPACE.to_string())
+                        rendezvous::Namespace::new(self.namespace.clone())
                             .expect("Valid namespace"),
                     ),
                     None,
@@ -109,4 +332,174 @@ impl Relay {
             false
         }
     }
+
+    /// Ask the relay for the peers registered in our namespace to learn about its load.
+    ///
+    /// Unlike `discover` this does not require us to be registered at the relay, which allows
+    /// comparing relays before picking one.
+    pub fn probe<B: NetworkBehaviour>(&mut self, swarm: &mut Swarm<P2pandaBehaviour<B>>) -> bool {
+        if self.probing || self.discovering {
+            return false;
+        }
+
+        self.probing = true;
+
+        let namespace = match rendezvous::Namespace::new(self.namespace.clone()) {
+            Ok(namespace) => namespace,
+            Err(_) => return false,
+        };
+
+        swarm
+            .behaviour_mut()
+            .rendezvous_client
+            .as_mut()
+            .expect("Relay client behaviour exists")
+            .discover(Some(namespace), None, None, self.peer_id);
+
+        true
+    }
+
+    /// Stop using this relay, closing the listener on its circuit address.
+    pub fn release<B: NetworkBehaviour>(&mut self, swarm: &mut Swarm<P2pandaBehaviour<B>>) {
+        if let Some(listener) = self.listener.take() {
+            swarm.remove_listener(listener);
+        }
+
+        self.registering = false;
+        self.registered = false;
+        self.reservation_accepted = false;
+        self.discovering = false;
+
+        // Keep the reason of failed registrations to inform about it
+        if !matches!(self.registration, Registration::Failed { .. }) {
+            self.registration = Registration::Pending;
+        }
+    }
+}
+
+/// Returns the time to wait before the next registration attempt after the given number of
+/// failed attempts in a row.
+fn retry_delay(retries: u32) -> Duration {
+    MIN_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(retries))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Pick the relay we register at from all relays we're connected to.
+///
+/// Relays which failed less often are preferred, followed by relays reporting less load. Relays
+/// which did not report their load yet come last. When multiple relays are equally suitable one
+/// of them is picked at random, this spreads nodes evenly across relays.
+pub fn select_relay(relays: &HashMap<PeerId, Relay>) -> Option<PeerId> {
+    let score = |relay: &Relay| (relay.failures, relay.load.unwrap_or(usize::MAX));
+
+    let best = relays
+        .values()
+        .filter(|relay| relay.connected)
+        .map(score)
+        .min()?;
+
+    let candidates: Vec<PeerId> = relays
+        .values()
+        .filter(|relay| relay.connected && score(relay) == best)
+        .map(|relay| relay.peer_id)
+        .collect();
+
+    candidates.choose(&mut thread_rng()).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use libp2p::{Multiaddr, PeerId};
+
+    use super::{retry_delay, select_relay, Registration, Relay};
+
+    fn relay(load: Option<usize>, failures: usize) -> Relay {
+        let mut relay = Relay::new(
+            PeerId::random(),
+            "/ip4/127.0.0.1/udp/2022/quic-v1"
+                .parse::<Multiaddr>()
+                .unwrap(),
+            "aquadoggo".into(),
+        );
+        relay.load = load;
+        relay.failures = failures;
+        relay
+    }
+
+    #[test]
+    fn selects_healthy_relay_with_least_load() {
+        let mut relays = HashMap::new();
+        assert_eq!(select_relay(&relays), None);
+
+        let busy = relay(Some(20), 0);
+        let idle = relay(Some(2), 0);
+        let failing = relay(Some(0), 2);
+        let unknown = relay(None, 0);
+        for relay in [&busy, &idle, &failing, &unknown] {
+            relays.insert(relay.peer_id, relay.clone());
+        }
+
+        assert_eq!(select_relay(&relays), Some(idle.peer_id));
+
+        // Fail over to the next relay when the connection got lost
+        relays.get_mut(&idle.peer_id).unwrap().connected = false;
+        assert_eq!(select_relay(&relays), Some(busy.peer_id));
+
+        // Relays without load report come last
+        relays.get_mut(&busy.peer_id).unwrap().connected = false;
+        assert_eq!(select_relay(&relays), Some(unknown.peer_id));
+
+        // Failing relays are only used as a last resort
+        relays.get_mut(&unknown.peer_id).unwrap().connected = false;
+        assert_eq!(select_relay(&relays), Some(failing.peer_id));
+    }
+
+    #[test]
+    fn tracks_registration_state() {
+        let mut relay = relay(None, 0);
+        let now = Instant::now();
+        assert_eq!(relay.status().state(now), "pending");
+
+        relay.on_registered(100, now);
+        let status = relay.status();
+        assert_eq!(status.state(now), "active");
+        assert_eq!(status.expires_in(now), Some(Duration::from_secs(100)));
+
+        // Registrations are due for renewal before they expire
+        assert_eq!(status.state(now + Duration::from_secs(80)), "expiring");
+
+        // Every further failure makes us wait longer before we try again
+        relay.on_registration_failed("Unavailable".into(), now);
+        relay.on_registration_failed("Unavailable".into(), now);
+        let status = relay.status();
+        assert_eq!(status.state(now), "failed");
+        assert_eq!(status.error(), Some("Unavailable"));
+        assert_eq!(
+            status.registration,
+            Registration::Failed {
+                reason: "Unavailable".into(),
+                retry_at: now + Duration::from_secs(10),
+            }
+        );
+        assert!(!relay.registered);
+
+        // A successful registration resets the backoff
+        relay.on_registered(100, now);
+        assert_eq!(relay.retries, 0);
+
+        relay.connected = false;
+        assert_eq!(relay.status().state(now), "disconnected");
+    }
+
+    #[test]
+    fn backs_off_retries() {
+        assert_eq!(retry_delay(0), Duration::from_secs(5));
+        assert_eq!(retry_delay(1), Duration::from_secs(10));
+        assert_eq!(retry_delay(10), Duration::from_secs(600));
+        assert_eq!(retry_delay(u32::MAX), Duration::from_secs(600));
+    }
 }
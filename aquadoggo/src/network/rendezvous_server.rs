@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::{HashMap, HashSet};
+use std::task::{Context, Poll};
+
+use libp2p::core::Endpoint;
+use libp2p::rendezvous::server::{Config, Event};
+use libp2p::rendezvous::{server, Namespace};
+use libp2p::swarm::{
+    ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour, THandler, THandlerInEvent,
+    THandlerOutEvent, ToSwarm,
+};
+use libp2p::{Multiaddr, PeerId};
+use log::debug;
+
+use crate::network::NetworkConfiguration;
+
+/// Rendezvous point where peers register their addresses and discover each other.
+///
+/// Wraps the rendezvous server behaviour of libp2p and adds a quota to it: once the maximum
+/// number of peers holding registrations is reached, requests of all other peers are ignored
+/// until registrations expire or get removed. Peers which already hold a registration can still
+/// refresh it or discover other peers.
+pub struct Behaviour {
+    inner: server::Behaviour,
+
+    /// Maximum number of peers which can hold registrations at the same time.
+    max_registrations: usize,
+
+    /// Namespaces each peer currently holds a registration in.
+    registrations: HashMap<PeerId, HashSet<Namespace>>,
+}
+
+impl Behaviour {
+    /// Returns a new rendezvous server behaviour with TTL bounds and quota of the network
+    /// configuration.
+    pub fn new(network_config: &NetworkConfiguration) -> Self {
+        let config = Config::default()
+            .with_min_ttl(network_config.rendezvous_min_ttl)
+            .with_max_ttl(network_config.rendezvous_max_ttl);
+
+        Self {
+            inner: server::Behaviour::new(config),
+            max_registrations: network_config.rendezvous_max_registrations,
+            registrations: HashMap::new(),
+        }
+    }
+
+    /// Returns true if the peer is allowed to send requests to the rendezvous point.
+    fn accepts(&self, peer_id: &PeerId) -> bool {
+        self.registrations.len() < self.max_registrations
+            || self.registrations.contains_key(peer_id)
+    }
+
+    /// Keep track of registrations based on the events of the inner behaviour.
+    fn on_server_event(&mut self, event: &Event) {
+        match event {
+            Event::PeerRegistered { peer, registration } => {
+                self.registrations
+                    .entry(*peer)
+                    .or_default()
+                    .insert(registration.namespace.clone());
+            }
+            Event::PeerUnregistered { peer, namespace } => {
+                self.remove(peer, namespace);
+            }
+            Event::RegistrationExpired(registration) => {
+                self.remove(&registration.record.peer_id(), &registration.namespace);
+            }
+            _ => (),
+        }
+    }
+
+    fn remove(&mut self, peer_id: &PeerId, namespace: &Namespace) {
+        if let Some(namespaces) = self.registrations.get_mut(peer_id) {
+            namespaces.remove(namespace);
+
+            if namespaces.is_empty() {
+                self.registrations.remove(peer_id);
+            }
+        }
+    }
+}
+
+impl NetworkBehaviour for Behaviour {
+    type ConnectionHandler = <server::Behaviour as NetworkBehaviour>::ConnectionHandler;
+
+    type ToSwarm = Event;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.inner
+            .handle_pending_inbound_connection(connection_id, local_addr, remote_addr)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner
+            .handle_established_outbound_connection(connection_id, peer, addr, role_override)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        self.inner.on_swarm_event(event);
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        // Dropping the event closes the request without a response
+        if !self.accepts(&peer_id) {
+            debug!("Ignore rendezvous request from {peer_id}, registration quota is reached");
+            return;
+        }
+
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event);
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        let poll = self.inner.poll(cx);
+
+        if let Poll::Ready(ToSwarm::GenerateEvent(event)) = &poll {
+            self.on_server_event(event);
+        }
+
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::rendezvous::{client, Namespace};
+    use libp2p::swarm::Swarm;
+    use libp2p_swarm_test::SwarmExt;
+
+    use crate::network::NetworkConfiguration;
+
+    use super::Behaviour;
+
+    #[tokio::test]
+    async fn registration_quota() {
+        let network_config = NetworkConfiguration {
+            rendezvous_max_registrations: 1,
+            ..NetworkConfiguration::default()
+        };
+
+        let mut server = Swarm::new_ephemeral(|_| Behaviour::new(&network_config));
+        server.listen().with_memory_addr_external().await;
+        let server_peer_id = *server.local_peer_id();
+
+        let mut client_1 = Swarm::new_ephemeral(client::Behaviour::new);
+        let mut client_2 = Swarm::new_ephemeral(client::Behaviour::new);
+        for client in [&mut client_1, &mut client_2] {
+            client.listen().with_memory_addr_external().await;
+            client.connect(&mut server).await;
+        }
+
+        tokio::spawn(server.loop_on_next());
+
+        let namespace = Namespace::new(network_config.rendezvous_namespace()).unwrap();
+
+        // First peer takes the only available registration slot
+        client_1
+            .behaviour_mut()
+            .register(namespace.clone(), server_peer_id, None)
+            .unwrap();
+        assert!(matches!(
+            client_1.next_behaviour_event().await,
+            client::Event::Registered { .. }
+        ));
+
+        // Requests of other peers are ignored
+        client_2
+            .behaviour_mut()
+            .register(namespace.clone(), server_peer_id, None)
+            .unwrap();
+        assert!(matches!(
+            client_2.next_behaviour_event().await,
+            client::Event::RegisterFailed { .. }
+        ));
+
+        // Registered peers can still refresh their registration
+        client_1
+            .behaviour_mut()
+            .register(namespace, server_peer_id, None)
+            .unwrap();
+        assert!(matches!(
+            client_1.next_behaviour_event().await,
+            client::Event::Registered { .. }
+        ));
+    }
+}
@@ -1,15 +1,16 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::collections::HashMap;
-use std::net::Ipv4Addr;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroU8;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
+use libp2p::core::ConnectedPoint;
 use libp2p::multiaddr::Protocol;
 use libp2p::rendezvous::Registration;
 use libp2p::swarm::dial_opts::DialOpts;
-use libp2p::swarm::SwarmEvent;
+use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
 use libp2p::{dcutr, identify, mdns, relay, rendezvous, Multiaddr, PeerId, Swarm};
 use log::{debug, info, trace, warn};
 use tokio::task;
@@ -19,18 +20,30 @@ use tokio_stream::StreamExt;
 
 use crate::bus::{ServiceMessage, ServiceSender};
 use crate::context::Context;
+use crate::db::SqlStore;
 use crate::manager::{ServiceReadySender, Shutdown};
 use crate::network::behaviour::{Event, P2pandaBehaviour};
-use crate::network::config::Transport;
-use crate::network::relay::Relay;
+use crate::network::bootstrap::{BootstrapPeer, BootstrapPeers};
+use crate::network::config::{IpVersion, Transport};
+use crate::network::custom::CustomBehaviour;
+use crate::network::metrics::NetworkMetrics;
+use crate::network::relay::{select_relay, Relay};
 use crate::network::swarm::{build_quic_swarm, build_tcp_swarm};
+use crate::network::ticket::LocalAddresses;
 use crate::network::utils::{dial_known_peer, is_known_peer_address};
 use crate::network::{identity, peers, utils, ShutdownHandler};
+use crate::replication::now;
 use crate::{info_or_print, NetworkConfiguration};
 
 /// Interval at which we attempt to dial known peers and relays.
 const REDIAL_INTERVAL: Duration = Duration::from_secs(20);
 
+/// Interval at which we check if our relay registration is due for renewal or a retry.
+const RELAY_RENEWAL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of peers from past sessions we load from the database on start up.
+const MAX_STORED_BOOTSTRAP_PEERS: usize = 64;
+
 /// Network service which handles all networking logic for a p2panda node.
 ///
 /// This includes:
@@ -49,58 +62,89 @@ pub async fn network_service(
 ) -> Result<()> {
     let mut network_config = context.config.network.clone();
     let key_pair = identity::to_libp2p_key_pair(&context.key_pair);
-    let local_peer_id = key_pair.public().to_peer_id();
-
-    info_or_print(&format!("Peer id: {local_peer_id}"));
 
     if network_config.psk.is_some() && network_config.transport == Transport::QUIC {
         warn!("Private net not supported for QUIC transport protocol, switching to TCP");
         network_config.transport = Transport::TCP;
     }
 
-    let mut swarm = match network_config.transport {
-        Transport::QUIC => build_quic_swarm(&network_config, key_pair),
-        Transport::TCP => build_tcp_swarm(&network_config, key_pair),
-    }?;
+    if network_config.simulation.is_enabled() && network_config.transport == Transport::QUIC {
+        warn!("Network simulation not supported for QUIC transport protocol, switching to TCP");
+        network_config.transport = Transport::TCP;
+    }
 
-    match network_config.transport {
+    let custom = CustomBehaviour::default();
+    let swarm = match network_config.transport {
         Transport::QUIC => {
-            // Start listening on QUIC address. Pick a random one if the given is taken already.
-            let mut listen_addr_quic = Multiaddr::empty()
-                .with(Protocol::from(Ipv4Addr::UNSPECIFIED))
-                .with(Protocol::Udp(network_config.port))
-                .with(Protocol::QuicV1);
-            if swarm.listen_on(listen_addr_quic.clone()).is_err() {
-                info_or_print(&format!(
-                    "QUIC port {} was already taken, try random port instead ..",
-                    network_config.port
-                ));
-
-                listen_addr_quic = Multiaddr::empty()
-                    .with(Protocol::from(Ipv4Addr::UNSPECIFIED))
-                    .with(Protocol::Udp(0))
-                    .with(Protocol::QuicV1);
-
-                swarm.listen_on(listen_addr_quic.clone())?;
-            }
+            build_quic_swarm(&network_config, key_pair, &context.network_metrics, custom)
         }
         Transport::TCP => {
-            // Start listening on TCP address. Pick a random one if the given is taken already.
-            let mut listen_address_tcp = Multiaddr::empty()
-                .with(Protocol::from(Ipv4Addr::UNSPECIFIED))
-                .with(Protocol::Tcp(network_config.port));
-            if swarm.listen_on(listen_address_tcp.clone()).is_err() {
-                info_or_print(&format!(
-                    "TCP port {} was already taken, try random port instead ..",
-                    network_config.port
-                ));
+            build_tcp_swarm(&network_config, key_pair, &context.network_metrics, custom)
+        }
+    }?;
 
-                listen_address_tcp = Multiaddr::empty()
-                    .with(Protocol::from(Ipv4Addr::UNSPECIFIED))
-                    .with(Protocol::Tcp(0));
+    run_network_service(swarm, network_config, context, shutdown, tx, tx_ready).await
+}
 
-                swarm.listen_on(listen_address_tcp.clone())?;
-            }
+/// Network service running on a swarm which was built by the application embedding the node, see
+/// `build_swarm`.
+pub async fn network_service_with_swarm<B>(
+    swarm: Swarm<P2pandaBehaviour<B>>,
+    context: Context,
+    shutdown: Shutdown,
+    tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()>
+where
+    B: NetworkBehaviour + Send,
+{
+    let mut network_config = context.config.network.clone();
+
+    // The swarm was built with TCP in this case, see `build_swarm`
+    if network_config.psk.is_some() && network_config.transport == Transport::QUIC {
+        network_config.transport = Transport::TCP;
+    }
+
+    run_network_service(swarm, network_config, context, shutdown, tx, tx_ready).await
+}
+
+async fn run_network_service<B>(
+    mut swarm: Swarm<P2pandaBehaviour<B>>,
+    network_config: NetworkConfiguration,
+    context: Context,
+    shutdown: Shutdown,
+    tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()>
+where
+    B: NetworkBehaviour + Send,
+{
+    let local_peer_id = *swarm.local_peer_id();
+
+    info_or_print(&format!("Peer id: {local_peer_id}"));
+
+    context
+        .local_addresses
+        .set_local_peer(local_peer_id, network_config.transport);
+
+    // Start listening on all configured IP families. In dual-stack mode we require the IPv4
+    // socket to be available, IPv6 might not be supported by every environment.
+    for (index, ip) in network_config
+        .ip_version
+        .listen_ips()
+        .into_iter()
+        .enumerate()
+    {
+        let port = match ip {
+            IpAddr::V4(_) => network_config.port,
+            IpAddr::V6(_) => network_config.port_v6(),
+        };
+
+        let result = listen_on(&mut swarm, network_config.transport, ip, port);
+        match result {
+            Ok(_) => (),
+            Err(err) if index > 0 => warn!("Could not listen on {ip}: {err}"),
+            Err(err) => return Err(err),
         }
     }
 
@@ -111,6 +155,7 @@ pub async fn network_service(
         swarm,
         network_config.to_owned(),
         local_peer_id,
+        context.clone(),
         shutdown,
         tx,
         tx_ready,
@@ -118,10 +163,38 @@ pub async fn network_service(
     .await
 }
 
+/// Start listening on given IP address and port with the configured transport protocol. Pick a
+/// random port if the given one is taken already.
+fn listen_on<B: NetworkBehaviour>(
+    swarm: &mut Swarm<P2pandaBehaviour<B>>,
+    transport: Transport,
+    ip: IpAddr,
+    port: u16,
+) -> Result<()> {
+    let listen_address = |port: u16| {
+        let address = Multiaddr::empty().with(Protocol::from(ip));
+        match transport {
+            Transport::QUIC => address.with(Protocol::Udp(port)).with(Protocol::QuicV1),
+            Transport::TCP => address.with(Protocol::Tcp(port)),
+        }
+    };
+
+    if swarm.listen_on(listen_address(port)).is_err() {
+        info_or_print(&format!(
+            "{:?} port {} was already taken, try random port instead ..",
+            transport, port
+        ));
+
+        swarm.listen_on(listen_address(0))?;
+    }
+
+    Ok(())
+}
+
 /// Main loop polling the async swarm event stream and incoming service messages stream.
-struct EventLoop {
+struct EventLoop<B: NetworkBehaviour> {
     /// libp2p swarm.
-    swarm: Swarm<P2pandaBehaviour>,
+    swarm: Swarm<P2pandaBehaviour<B>>,
 
     /// p2panda network configuration.
     network_config: NetworkConfiguration,
@@ -135,9 +208,28 @@ struct EventLoop {
     /// Relays for which we have discovered a PeerId via the identify behaviour.
     relays: HashMap<PeerId, Relay>,
 
+    /// Relay we're registered at, picked from all connected relays based on their health and
+    /// load.
+    active_relay: Option<PeerId>,
+
+    /// Configured and learned peers we dial to reach the target number of connections.
+    bootstrap: BootstrapPeers,
+
+    /// Store to persist learned bootstrap peers.
+    store: SqlStore,
+
+    /// Bandwidth and connection metrics of the swarm.
+    metrics: NetworkMetrics,
+
+    /// Addresses of our node, used for issuing connection tickets.
+    local_addresses: LocalAddresses,
+
     /// Scheduler which triggers known peer redial attempts.
     redial_scheduler: IntervalStream,
 
+    /// Scheduler which triggers renewals and retries of our relay registration.
+    relay_scheduler: IntervalStream,
+
     /// Service message channel sender.
     tx: ServiceSender,
 
@@ -147,18 +239,26 @@ struct EventLoop {
     /// Shutdown handler.
     shutdown_handler: ShutdownHandler,
 
-    /// Did we learn our own port yet.
-    learned_port: bool,
+    /// IP versions we learned our own port for yet.
+    learned_ports: HashSet<IpVersion>,
 
     /// Did we learn our observed address yet.
     learned_observed_addr: bool,
+
+    /// Did we show a connection ticket yet.
+    announced_ticket: bool,
 }
 
-impl EventLoop {
+impl<B> EventLoop<B>
+where
+    B: NetworkBehaviour + Send,
+{
     pub fn new(
-        swarm: Swarm<P2pandaBehaviour>,
+        swarm: Swarm<P2pandaBehaviour<B>>,
         network_config: NetworkConfiguration,
         local_peer_id: PeerId,
+        context: &Context,
+        bootstrap: BootstrapPeers,
         tx: ServiceSender,
         shutdown_handler: ShutdownHandler,
     ) -> Self {
@@ -166,14 +266,38 @@ impl EventLoop {
             swarm,
             network_config,
             redial_scheduler: IntervalStream::new(interval(REDIAL_INTERVAL)),
+            relay_scheduler: IntervalStream::new(interval(RELAY_RENEWAL_INTERVAL)),
             local_peer_id,
             rx: BroadcastStream::new(tx.subscribe()),
             tx,
             known_peers: HashMap::new(),
             relays: HashMap::new(),
+            active_relay: None,
+            bootstrap,
+            store: context.store.clone(),
+            metrics: context.network_metrics.clone(),
+            local_addresses: context.local_addresses.clone(),
             shutdown_handler,
-            learned_port: false,
+            learned_ports: HashSet::new(),
             learned_observed_addr: false,
+            announced_ticket: false,
+        }
+    }
+
+    /// Show the port we're listening on once per IP version.
+    fn show_listen_address(&mut self, address: SocketAddr, protocol: &str) {
+        let (ip_version, unspecified) = match address {
+            SocketAddr::V4(_) => (IpVersion::V4, "0.0.0.0"),
+            SocketAddr::V6(_) => (IpVersion::V6, "[::]"),
+        };
+
+        if self.learned_ports.insert(ip_version) {
+            info_or_print(&format!(
+                "Node is listening on {}:{} ({})",
+                unspecified,
+                address.port(),
+                protocol
+            ));
         }
     }
 
@@ -193,6 +317,22 @@ impl EventLoop {
         self.shutdown_handler.set_done();
     }
 
+    /// Show a ticket for connecting to our node once we know a reachable address.
+    fn announce_ticket(&mut self) {
+        if self.announced_ticket {
+            return;
+        }
+
+        if let Some(ticket) = self.local_addresses.ticket(&self.network_config) {
+            if !ticket.addresses.is_empty() {
+                info_or_print(&format!(
+                    "Share this ticket to connect with other nodes: {ticket}"
+                ));
+                self.announced_ticket = true;
+            }
+        }
+    }
+
     /// Main event loop handling libp2p swarm events and incoming messages from the service bus as
     /// an ongoing async stream.
     pub async fn run(mut self) {
@@ -204,27 +344,21 @@ impl EventLoop {
                     let event = event.expect("Swarm stream to be infinite");
                     match event {
                         SwarmEvent::NewListenAddr { address, .. } => {
-                            if !self.learned_port {
-                                // Show only one QUIC address during the runtime of the node, otherwise
-                                // it might get too spammy
-                                if let Some(address) = utils::to_quic_address(&address) {
-                                    info_or_print(&format!("Node is listening on 0.0.0.0:{} (QUIC)", address.port()));
-                                    self.learned_port = true;
-                                }
-                            }
-
-                            if !self.learned_port {
-                                // Show only one TCP address during the runtime of the node, otherwise
-                                // it might get too spammy
-                                if let Some(address) = utils::to_tcp_address(&address) {
-                                    info_or_print(&format!("Node is listening on 0.0.0.0:{} (TCP)", address.port()));
-                                    self.learned_port = true;
-                                }
+                            self.local_addresses.add_listen_address(address.clone());
+                            self.announce_ticket();
+
+                            // Show only one address per IP version during the runtime of the
+                            // node, otherwise it might get too spammy
+                            if let Some(address) = utils::to_quic_address(&address) {
+                                self.show_listen_address(address, "QUIC");
+                            } else if let Some(address) = utils::to_tcp_address(&address) {
+                                self.show_listen_address(address, "TCP");
                             }
                         }
                         SwarmEvent::Behaviour(Event::Identify(event)) => self.handle_identify_events(&event).await,
                         SwarmEvent::Behaviour(Event::Mdns(event)) => self.handle_mdns_events(&event).await,
                         SwarmEvent::Behaviour(Event::RendezvousClient(event)) => self.handle_rendezvous_client_events(&event).await,
+                        SwarmEvent::Behaviour(Event::RendezvousServer(event)) => self.handle_rendezvous_server_events(&event).await,
                         SwarmEvent::Behaviour(Event::Peers(event)) => self.handle_peers_events(&event).await,
                         SwarmEvent::Behaviour(Event::RelayClient(event)) => self.handle_relay_client_events(&event).await,
                         SwarmEvent::Behaviour(Event::Dcutr(event)) => self.handle_dcutr_events(&event).await,
@@ -245,6 +379,11 @@ impl EventLoop {
                 // The redial_scheduler emits an event every `REDIAL_INTERVAL` seconds.
                 Some(_) = self.redial_scheduler.next() => {
                     self.attempt_dial_known_addresses().await;
+                    self.attempt_dial_bootstrap_peers().await;
+                },
+                // The relay_scheduler emits an event every `RELAY_RENEWAL_INTERVAL` seconds.
+                Some(_) = self.relay_scheduler.next() => {
+                    self.renew_relay_registration();
                 },
                 _ = shutdown_request_received.next() => {
                     self.shutdown().await;
@@ -263,6 +402,7 @@ impl EventLoop {
                 &mut self.known_peers,
                 relay_address,
                 self.network_config.transport,
+                self.network_config.ip_version,
             );
         }
 
@@ -273,10 +413,187 @@ impl EventLoop {
                 &mut self.known_peers,
                 direct_node_address,
                 self.network_config.transport,
+                self.network_config.ip_version,
             );
         }
     }
 
+    /// Dial bootstrap peers in the order of their health score until we reach our target number
+    /// of connections.
+    async fn attempt_dial_bootstrap_peers(&mut self) {
+        let transport = self.network_config.transport;
+        let ip_version = self.network_config.ip_version;
+
+        // Resolve configured bootstrap addresses, this can fail if they are given as domain names
+        // and we're currently offline
+        for address in self.network_config.bootstrap_peers.iter_mut() {
+            let address = match transport {
+                Transport::QUIC => address.quic_multiaddr(ip_version),
+                Transport::TCP => address.tcp_multiaddr(ip_version),
+            };
+
+            match address {
+                Ok(address) => self
+                    .bootstrap
+                    .insert(BootstrapPeer::new(address, None, true)),
+                Err(e) => debug!("Failed to resolve bootstrap multiaddr: {}", e),
+            }
+        }
+
+        let connected_peers: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+
+        for (address, peer_id) in self.bootstrap.next_dials(&connected_peers, transport) {
+            let opts = match peer_id {
+                Some(peer_id) => DialOpts::peer_id(peer_id)
+                    .addresses(vec![address.clone()])
+                    .override_dial_concurrency_factor(NonZeroU8::new(1).expect("Is nonzero u8"))
+                    .build(),
+                None => DialOpts::unknown_peer_id().address(address.clone()).build(),
+            };
+            let connection_id = opts.connection_id();
+
+            match self.swarm.dial(opts) {
+                Ok(_) => {
+                    debug!("Dialed bootstrap peer {}", address);
+                    self.bootstrap.on_dial(connection_id, address);
+                }
+                Err(err) => debug!("Error dialing bootstrap peer {}: {}", address, err),
+            }
+        }
+    }
+
+    /// Persist the health of a bootstrap peer to use it again after a restart.
+    ///
+    /// Learned peers which got forgotten are removed from the database.
+    fn persist_bootstrap_peer(&self, peer: BootstrapPeer) {
+        let store = self.store.clone();
+        let is_known = self.bootstrap.contains(&peer.address);
+
+        // Do not block the event loop while writing to the database
+        task::spawn(async move {
+            let result = if !peer.configured && !is_known {
+                store.remove_bootstrap_peer(&peer.address).await
+            } else {
+                store.insert_bootstrap_peer(&peer).await
+            };
+
+            if let Err(err) = result {
+                warn!("Failed persisting bootstrap peer {}: {}", peer.address, err);
+            }
+        });
+    }
+
+    /// Make sure we're using the most suitable relay.
+    ///
+    /// As long as we did not start registering at the current relay we switch to a better one as
+    /// soon as we learn about it, afterwards we only fail over to another relay when the current
+    /// one becomes unavailable.
+    fn update_active_relay(&mut self) {
+        let is_settled = self
+            .active_relay
+            .and_then(|peer_id| self.relays.get(&peer_id))
+            .is_some_and(|relay| relay.connected && (relay.registering || relay.registered));
+
+        if !is_settled {
+            let next = select_relay(&self.relays);
+            if next != self.active_relay {
+                self.switch_relay(next);
+            }
+        }
+
+        self.register_on_active_relay();
+    }
+
+    /// Stop using the current relay and start using the given one instead.
+    fn switch_relay(&mut self, next: Option<PeerId>) {
+        if let Some(previous) = self.active_relay.take() {
+            if let Some(relay) = self.relays.get_mut(&previous) {
+                relay.release(&mut self.swarm);
+            }
+        }
+
+        self.active_relay = next;
+
+        match next.and_then(|peer_id| self.relays.get(&peer_id)) {
+            Some(relay) => {
+                info!(
+                    "Using relay {} at {} (load: {})",
+                    relay.peer_id,
+                    relay.addr,
+                    relay
+                        .load
+                        .map_or("unknown".to_string(), |load| load.to_string())
+                );
+                self.local_addresses
+                    .set_relay(Some((relay.peer_id, relay.addr.clone())));
+            }
+            None => {
+                warn!("No relay available");
+                self.local_addresses.set_relay(None);
+            }
+        }
+    }
+
+    /// Register at the relay we're using as soon as we know our observed address and told the
+    /// relay about its own.
+    fn register_on_active_relay(&mut self) {
+        if !self.learned_observed_addr {
+            return;
+        }
+
+        let relay = match self
+            .active_relay
+            .and_then(|peer_id| self.relays.get_mut(&peer_id))
+        {
+            Some(relay) => relay,
+            None => return,
+        };
+
+        if !relay.told_addr {
+            return;
+        }
+
+        match relay.register(&mut self.swarm) {
+            Ok(registered) => {
+                if registered {
+                    debug!("Registration request sent to relay {}", relay.peer_id)
+                }
+            }
+            Err(e) => debug!("Error registering on relay: {}", e),
+        };
+    }
+
+    /// Renew our registration at the relay we're using before it expires and retry failed
+    /// registrations.
+    fn renew_relay_registration(&mut self) {
+        if let Some(relay) = self
+            .active_relay
+            .and_then(|peer_id| self.relays.get_mut(&peer_id))
+        {
+            let was_registered = relay.registered;
+
+            match relay.renew(&mut self.swarm, Instant::now()) {
+                Ok(true) => debug!("Renewing registration on relay {}", relay.peer_id),
+                Ok(false) => (),
+                Err(e) => debug!("Error renewing registration on relay: {}", e),
+            }
+
+            if was_registered && !relay.registered {
+                warn!("Registration on relay {} expired", relay.peer_id);
+            }
+        }
+
+        // Retry failed registrations once their backoff passed
+        self.register_on_active_relay();
+        self.update_relay_statuses();
+    }
+
+    /// Inform about the state of all known relays outside of the network service.
+    fn update_relay_statuses(&self) {
+        self.local_addresses
+            .set_relay_statuses(self.relays.values().map(Relay::status).collect());
+    }
+
     /// Send a message on the communication bus to inform other services.
     fn send_service_message(&mut self, message: ServiceMessage) {
         if self.tx.send(message).is_err() {
@@ -302,13 +619,13 @@ impl EventLoop {
 
     async fn handle_peers_events(&mut self, event: &peers::Event) {
         match event {
-            peers::Event::PeerConnected(peer) => {
+            peers::Event::PeerConnected(peer, info) => {
                 // Inform other services about new peer
-                self.send_service_message(ServiceMessage::PeerConnected(*peer));
+                self.send_service_message(ServiceMessage::PeerConnected(*peer, info.clone()));
             }
-            peers::Event::PeerDisconnected(peer) => {
+            peers::Event::PeerDisconnected(peer, info) => {
                 // Inform other services about peer leaving
-                self.send_service_message(ServiceMessage::PeerDisconnected(*peer));
+                self.send_service_message(ServiceMessage::PeerDisconnected(*peer, info.clone()));
             }
             peers::Event::MessageReceived(peer, message) => {
                 // Inform other services about received messages from peer
@@ -324,6 +641,16 @@ impl EventLoop {
                 rendezvous_node,
                 ..
             } => {
+                // The number of registered peers tells us about the load of the relay
+                if let Some(relay) = self.relays.get_mut(rendezvous_node) {
+                    debug!(
+                        "Relay {} reports {} registered peers",
+                        rendezvous_node,
+                        registrations.len()
+                    );
+                    relay.load = Some(registrations.len());
+                }
+
                 for Registration { record, .. } in registrations {
                     let peer_id = record.peer_id();
                     let addresses = record.addresses();
@@ -358,17 +685,40 @@ impl EventLoop {
                         };
                     }
                 }
+
+                self.update_active_relay();
+            }
+            rendezvous::client::Event::RegisterFailed {
+                rendezvous_node,
+                error,
+                ..
+            } => {
+                if let Some(relay) = self.relays.get_mut(rendezvous_node) {
+                    warn!("Registration on relay {rendezvous_node} failed: {error:?}");
+                    relay.failures += 1;
+                    relay.on_registration_failed(format!("{error:?}"), Instant::now());
+
+                    // Fail over to another relay if there is a better one
+                    let next = select_relay(&self.relays);
+                    if self.active_relay == Some(*rendezvous_node) && next != self.active_relay {
+                        self.switch_relay(next);
+                        self.register_on_active_relay();
+                    }
+                }
             }
             rendezvous::client::Event::Registered {
                 namespace,
                 rendezvous_node,
-                ..
+                ttl,
             } => {
                 if let Some(relay) = self.relays.get_mut(rendezvous_node) {
-                    if !relay.registered {
-                        debug!("Registered on rendezvous {rendezvous_node} in namespace \"{namespace}\"");
-                        relay.registered = true;
+                    if relay.registered {
+                        debug!("Renewed registration on rendezvous {rendezvous_node} for {ttl}s");
+                    } else {
+                        debug!("Registered on rendezvous {rendezvous_node} in namespace \"{namespace}\" for {ttl}s");
                     }
+                    relay.on_registered(*ttl, Instant::now());
+
                     if relay.discover(&mut self.swarm) {
                         info!(
                             "Discovering peers in namespace \"{}\" on relay {}",
@@ -379,8 +729,30 @@ impl EventLoop {
             }
             event => trace!("{event:?}"),
         }
+
+        self.update_relay_statuses();
     }
 
+    async fn handle_rendezvous_server_events(&mut self, event: &rendezvous::server::Event) {
+        match event {
+            rendezvous::server::Event::PeerRegistered { peer, registration } => {
+                debug!(
+                    "Peer {peer} registered in namespace \"{}\" for {}s",
+                    registration.namespace, registration.ttl
+                );
+            }
+            rendezvous::server::Event::PeerNotRegistered {
+                peer,
+                namespace,
+                error,
+            } => {
+                debug!(
+                    "Declined registration of peer {peer} in namespace \"{namespace}\": {error:?}"
+                );
+            }
+            event => trace!("{event:?}"),
+        }
+    }
     async fn handle_identify_events(&mut self, event: &identify::Event) {
         match event {
             identify::Event::Received {
@@ -398,6 +770,8 @@ impl EventLoop {
                     .any(|addr| addr == observed_addr)
                 {
                     self.swarm.add_external_address(observed_addr.clone());
+                    self.local_addresses
+                        .add_external_address(observed_addr.clone());
                 }
 
                 // Configuring known static relay and peer addresses is done by providing an ip
@@ -407,21 +781,10 @@ impl EventLoop {
                 // their peer id to our address book. This is then used when dialing the peer
                 // to avoid multiple connections being established to the same peer.
 
-                // Check if the identified peer is one of our configured relay addresses.
-                if let Some(relay) = self.relays.get_mut(peer_id) {
-                    if !self.learned_observed_addr || !relay.told_addr {
-                        return;
-                    }
-
-                    // Attempt to register with the relay.
-                    match relay.register(&mut self.swarm) {
-                        Ok(registered) => {
-                            if registered {
-                                debug!("Registration request sent to relay {}", relay.peer_id)
-                            }
-                        }
-                        Err(e) => debug!("Error registering on relay: {}", e),
-                    };
+                // Check if the identified peer is one of our configured relay addresses and
+                // attempt to register with it if it's the one we're using.
+                if self.relays.contains_key(peer_id) {
+                    self.update_active_relay();
                 }
             }
             identify::Event::Sent { peer_id } => {
@@ -431,20 +794,8 @@ impl EventLoop {
                         relay.told_addr = true;
                     }
 
-                    if !self.learned_observed_addr || !relay.told_addr {
-                        return;
-                    }
-
-                    // Attempt to register with the relay.
-                    debug!("Register on relay {}", relay.peer_id);
-                    match relay.register(&mut self.swarm) {
-                        Ok(registered) => {
-                            if registered {
-                                debug!("Registration request sent to relay {}", relay.peer_id)
-                            }
-                        }
-                        Err(e) => debug!("Error registering on relay: {}", e),
-                    };
+                    // Attempt to register with the relay if it's the one we're using.
+                    self.update_active_relay();
                 }
             }
             event => trace!("{event:?}"),
@@ -487,6 +838,8 @@ impl EventLoop {
                         );
                     };
                 }
+
+                self.update_relay_statuses();
             }
             event => trace!("{event:?}"),
         }
@@ -510,6 +863,7 @@ impl EventLoop {
                 endpoint,
                 num_established,
                 peer_id,
+                connection_id,
                 ..
             } => {
                 debug!(
@@ -518,20 +872,59 @@ impl EventLoop {
                     num_established
                 );
 
+                self.metrics
+                    .on_connection_established(connection_id, endpoint.get_remote_address());
+
+                // Update health of bootstrap peers or learn about new ones we dialed directly
+                let dialed_address = match &endpoint {
+                    ConnectedPoint::Dialer { address, .. } => Some(address),
+                    ConnectedPoint::Listener { .. } => None,
+                };
+                if let Some(peer) =
+                    self.bootstrap
+                        .on_connection_established(connection_id, peer_id, dialed_address)
+                {
+                    self.persist_bootstrap_peer(peer);
+                }
+
                 // Check if the connected peer is one of our relay addresses.
                 if let Some(addr) = is_known_peer_address(
                     &mut self.network_config.relay_addresses,
                     &[endpoint.get_remote_address().to_owned()],
                     self.network_config.transport,
+                    self.network_config.ip_version,
                 ) {
-                    if self.relays.contains_key(&peer_id) {
-                        return;
+                    match self.relays.get_mut(&peer_id) {
+                        Some(relay) if relay.connected => return,
+                        Some(relay) => {
+                            debug!("Relay reconnected {peer_id} {addr}");
+                            relay.reconnect();
+                        }
+                        None => {
+                            debug!("Relay identified {peer_id} {addr}");
+                            self.relays.insert(
+                                peer_id,
+                                Relay::new(
+                                    peer_id,
+                                    addr.clone(),
+                                    self.network_config.rendezvous_namespace(),
+                                ),
+                            );
+                        }
                     }
 
                     // Add the relay to our known peers.
-                    debug!("Relay identified {peer_id} {addr}");
-                    self.known_peers.insert(addr.clone(), peer_id);
-                    self.relays.insert(peer_id, Relay::new(peer_id, addr));
+                    self.known_peers.insert(addr, peer_id);
+
+                    // Ask the relay about its load to compare it with other relays
+                    if let Some(relay) = self.relays.get_mut(&peer_id) {
+                        if relay.probe(&mut self.swarm) {
+                            debug!("Requested load report from relay {peer_id}");
+                        }
+                    }
+
+                    self.update_active_relay();
+                    self.update_relay_statuses();
                 }
 
                 // Check if the connected peer is one of our direct node addresses.
@@ -539,6 +932,7 @@ impl EventLoop {
                     &mut self.network_config.direct_node_addresses,
                     &[endpoint.get_remote_address().to_owned()],
                     self.network_config.transport,
+                    self.network_config.ip_version,
                 ) {
                     // Add the direct node to our known peers.
                     debug!("Direct node identified {peer_id} {addr}");
@@ -549,8 +943,8 @@ impl EventLoop {
                 peer_id,
                 connection_id,
                 endpoint,
+                num_established,
                 cause,
-                ..
             } => {
                 debug!(
                     "Connection closed with peer {}({}) at {}: {}",
@@ -562,29 +956,122 @@ impl EventLoop {
                         .unwrap_or("No cause given".to_string())
                 );
 
+                self.metrics.on_connection_closed(connection_id);
+
                 // Remove this peer address from our known peers.
                 self.known_peers.remove(endpoint.get_remote_address());
+
+                // Fail over to another relay when we lost the connection to the current one
+                if num_established == 0 {
+                    if let Some(relay) = self.relays.get_mut(&peer_id) {
+                        if relay.connected {
+                            warn!("Lost connection to relay {peer_id}");
+                            relay.connected = false;
+                            relay.failures += 1;
+                        }
+                    }
+
+                    if self.active_relay == Some(peer_id) {
+                        self.switch_relay(select_relay(&self.relays));
+                        self.register_on_active_relay();
+                    }
+
+                    self.update_relay_statuses();
+                }
+            }
+            SwarmEvent::ListenerClosed {
+                listener_id,
+                reason: Err(error),
+                ..
+            } => {
+                // The listener on a relay circuit address closes when the relay did not accept
+                // or renew our reservation
+                if let Some(relay) = self
+                    .relays
+                    .values_mut()
+                    .find(|relay| relay.listener == Some(listener_id))
+                {
+                    warn!(
+                        "Circuit reservation on relay {} failed: {}",
+                        relay.peer_id, error
+                    );
+                    relay.listener = None;
+                    relay.reservation_accepted = false;
+                    relay.on_registration_failed(
+                        format!("Circuit reservation failed: {error}"),
+                        Instant::now(),
+                    );
+                    self.update_relay_statuses();
+                }
+            }
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                self.local_addresses.remove_listen_address(&address);
+            }
+            SwarmEvent::OutgoingConnectionError {
+                connection_id,
+                error,
+                ..
+            } => {
+                if let Some(peer) = self.bootstrap.on_dial_failed(connection_id) {
+                    debug!("Failed dialing bootstrap peer {}: {}", peer.address, error);
+                    self.persist_bootstrap_peer(peer);
+                }
             }
             event => trace!("{event:?}"),
         }
     }
 }
 
-pub async fn spawn_event_loop(
-    swarm: Swarm<P2pandaBehaviour>,
+pub async fn spawn_event_loop<B>(
+    swarm: Swarm<P2pandaBehaviour<B>>,
     network_config: NetworkConfiguration,
     local_peer_id: PeerId,
+    context: Context,
     shutdown: Shutdown,
     tx: ServiceSender,
     tx_ready: ServiceReadySender,
-) -> Result<()> {
+) -> Result<()>
+where
+    B: NetworkBehaviour + Send,
+{
     let mut shutdown_handler = ShutdownHandler::new();
 
+    // Remember peers we connected to during the last runtime for faster cold starts, expired
+    // peers are forgotten
+    let last_seen_before = now().saturating_sub(network_config.bootstrap_peer_expiry);
+    match context
+        .store
+        .remove_expired_bootstrap_peers(last_seen_before)
+        .await
+    {
+        Ok(0) => (),
+        Ok(removed) => debug!("Removed {} expired bootstrap peers from database", removed),
+        Err(err) => warn!(
+            "Failed removing expired bootstrap peers from database: {}",
+            err
+        ),
+    }
+
+    let mut bootstrap = BootstrapPeers::new(
+        network_config.bootstrap_target_connections,
+        network_config.bootstrap_peer_expiry,
+    );
+    match context
+        .store
+        .get_bootstrap_peers(MAX_STORED_BOOTSTRAP_PEERS)
+        .await
+    {
+        Ok(peers) => peers.into_iter().for_each(|peer| bootstrap.insert(peer)),
+        Err(err) => warn!("Failed loading bootstrap peers from database: {}", err),
+    }
+
     // Spawn a task to run swarm in event loop
     let event_loop = EventLoop::new(
         swarm,
         network_config,
         local_peer_id,
+        &context,
+        bootstrap,
         tx,
         shutdown_handler.clone(),
     );
@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Simulated latency and packet loss on connections to other nodes, for testing replication
+//! under poor connectivity on a local machine.
+//!
+//! Sockets of the TCP transport are wrapped in a delay line: written data is held back for the
+//! configured latency, varied by the jitter, before it is passed on to the socket. TCP does not
+//! lose data, a lost packet delays all following data until it got retransmitted. Packet loss is
+//! simulated the same way, by adding retransmission timeouts to the delay.
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::ready;
+use rand::Rng;
+use tokio::time::{sleep, Instant, Sleep};
+
+/// Shortest time until lost data gets retransmitted.
+const MIN_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Longest time until lost data gets retransmitted, the timeout doubles with every retry.
+const MAX_RETRANSMISSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Number of bytes held back after which writing to the stream blocks.
+const MAX_BUFFERED_BYTES: usize = 256 * 1024;
+
+/// Network conditions simulated on connections to other nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NetworkSimulation {
+    /// Time after which written data arrives at the other node.
+    pub latency: Duration,
+
+    /// Maximum random deviation from the latency, in both directions.
+    pub jitter: Duration,
+
+    /// Probability between 0.0 and 1.0 (exclusive) of written data getting lost and retransmitted.
+    pub packet_loss: f64,
+}
+
+impl NetworkSimulation {
+    /// Returns true if any network condition is simulated.
+    pub fn is_enabled(&self) -> bool {
+        !self.latency.is_zero() || !self.jitter.is_zero() || self.packet_loss > 0.0
+    }
+
+    /// Returns a random delay for data written now.
+    fn delay<R: Rng>(&self, rng: &mut R) -> Duration {
+        let mut delay = if self.jitter.is_zero() {
+            self.latency
+        } else {
+            (self.latency + rng.gen_range(Duration::ZERO..=self.jitter * 2))
+                .saturating_sub(self.jitter)
+        };
+
+        let mut timeout = MIN_RETRANSMISSION_TIMEOUT.max(self.latency * 2);
+        while self.packet_loss > 0.0 && rng.gen_bool(self.packet_loss) {
+            delay += timeout;
+            timeout = (timeout * 2).min(MAX_RETRANSMISSION_TIMEOUT);
+        }
+
+        delay
+    }
+}
+
+/// Stream holding back written data according to the simulated network conditions.
+///
+/// Data is written in the order it was given, data delayed by a retransmission holds back all
+/// data written after it.
+pub struct SimulatedStream<S> {
+    inner: S,
+
+    simulation: NetworkSimulation,
+
+    /// Data waiting to be written to the inner stream, with the time it is due.
+    queue: VecDeque<(Instant, Vec<u8>)>,
+
+    /// Number of bytes of the first item in the queue which were already written.
+    written: usize,
+
+    /// Number of bytes in the queue.
+    buffered: usize,
+
+    timer: Pin<Box<Sleep>>,
+}
+
+impl<S> SimulatedStream<S> {
+    pub fn new(inner: S, simulation: NetworkSimulation) -> Self {
+        Self {
+            inner,
+            simulation,
+            queue: VecDeque::new(),
+            written: 0,
+            buffered: 0,
+            timer: Box::pin(sleep(Duration::ZERO)),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> SimulatedStream<S> {
+    /// Writes all data which is due to the inner stream.
+    ///
+    /// Returns `Poll::Ready` when the queue is empty.
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while let Some((due, data)) = self.queue.front() {
+            if Instant::now() < *due {
+                self.timer.as_mut().reset(*due);
+                ready!(self.timer.as_mut().poll(cx));
+                continue;
+            }
+
+            let count = ready!(Pin::new(&mut self.inner).poll_write(cx, &data[self.written..]))?;
+            if count == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+
+            self.written += count;
+            if self.written == data.len() {
+                self.buffered -= data.len();
+                self.written = 0;
+                self.queue.pop_front();
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for SimulatedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for SimulatedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if let Poll::Ready(Err(err)) = this.poll_drain(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        // Apply backpressure like a full send buffer would
+        if this.buffered >= MAX_BUFFERED_BYTES {
+            return Poll::Pending;
+        }
+
+        let delay = this.simulation.delay(&mut rand::thread_rng());
+        let due = match this.queue.back() {
+            Some((last, _)) => (*last).max(Instant::now() + delay),
+            None => Instant::now() + delay,
+        };
+
+        this.queue.push_back((due, buf.to_vec()));
+        this.buffered += buf.len();
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::io::{AsyncWriteExt, Cursor};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use tokio::time::Instant;
+
+    use super::{NetworkSimulation, SimulatedStream, MIN_RETRANSMISSION_TIMEOUT};
+
+    #[test]
+    fn random_delays() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let simulation = NetworkSimulation {
+            latency: Duration::from_millis(100),
+            jitter: Duration::from_millis(20),
+            packet_loss: 0.0,
+        };
+        assert!(simulation.is_enabled());
+        for _ in 0..100 {
+            let delay = simulation.delay(&mut rng);
+            assert!(delay >= Duration::from_millis(80));
+            assert!(delay <= Duration::from_millis(120));
+        }
+
+        let simulation = NetworkSimulation {
+            latency: Duration::from_millis(10),
+            jitter: Duration::ZERO,
+            packet_loss: 0.5,
+        };
+        let delays: Vec<Duration> = (0..100).map(|_| simulation.delay(&mut rng)).collect();
+        assert!(delays.contains(&Duration::from_millis(10)));
+        assert!(delays.contains(&(Duration::from_millis(10) + MIN_RETRANSMISSION_TIMEOUT)));
+
+        assert!(!NetworkSimulation::default().is_enabled());
+    }
+
+    #[tokio::test]
+    async fn delay_written_data() {
+        let latency = Duration::from_millis(50);
+        let mut stream = SimulatedStream::new(
+            Cursor::new(Vec::new()),
+            NetworkSimulation {
+                latency,
+                ..Default::default()
+            },
+        );
+
+        let start = Instant::now();
+        stream.write_all(b"Hello, ").await.unwrap();
+        stream.write_all(b"Panda!").await.unwrap();
+
+        // Data is held back until the stream gets flushed after the latency
+        assert!(stream.inner.get_ref().is_empty());
+        stream.flush().await.unwrap();
+        assert!(start.elapsed() >= latency);
+        assert_eq!(stream.inner.get_ref(), b"Hello, Panda!");
+    }
+}
@@ -5,15 +5,63 @@ use either::Either;
 use libp2p::core::upgrade::Version;
 use libp2p::identity::Keypair;
 use libp2p::pnet::PnetConfig;
+use libp2p::swarm::NetworkBehaviour;
 use libp2p::{noise, tcp, yamux, Swarm, SwarmBuilder, Transport};
+use log::warn;
+use p2panda_rs::identity::KeyPair;
 
 use crate::network::behaviour::P2pandaBehaviour;
-use crate::network::NetworkConfiguration;
+use crate::network::custom::CustomBehaviour;
+use crate::network::identity::to_libp2p_key_pair;
+use crate::network::metrics::NetworkMetrics;
+use crate::network::simulation::SimulatedStream;
+use crate::network::{NetworkConfiguration, Transport as TransportProtocol};
 
-pub fn build_tcp_swarm(
+/// Build a swarm with the node's network behaviours and the given network behaviour of an
+/// application embedding aquadoggo, to be handed over to `Node::start_with_swarm`.
+///
+/// The swarm is built with the transport protocol of the network configuration and needs to use
+/// the same key pair as the node. Bandwidth of swarms built this way is not recorded in the
+/// node's network metrics.
+pub fn build_swarm<B>(
+    network_config: &NetworkConfiguration,
+    key_pair: &KeyPair,
+    custom: CustomBehaviour<B>,
+) -> Result<Swarm<P2pandaBehaviour<B>>>
+where
+    B: NetworkBehaviour + Send,
+{
+    let key_pair = to_libp2p_key_pair(key_pair);
+    let metrics = NetworkMetrics::default();
+
+    if network_config.psk.is_some() && network_config.transport == TransportProtocol::QUIC {
+        warn!("Private net not supported for QUIC transport protocol, switching to TCP");
+        return build_tcp_swarm(network_config, key_pair, &metrics, custom);
+    }
+
+    if network_config.simulation.is_enabled() && network_config.transport == TransportProtocol::QUIC
+    {
+        warn!("Network simulation not supported for QUIC transport protocol, switching to TCP");
+        return build_tcp_swarm(network_config, key_pair, &metrics, custom);
+    }
+
+    match network_config.transport {
+        TransportProtocol::QUIC => build_quic_swarm(network_config, key_pair, &metrics, custom),
+        TransportProtocol::TCP => build_tcp_swarm(network_config, key_pair, &metrics, custom),
+    }
+}
+
+pub fn build_tcp_swarm<B>(
     network_config: &NetworkConfiguration,
     key_pair: Keypair,
-) -> Result<Swarm<P2pandaBehaviour>> {
+    metrics: &NetworkMetrics,
+    custom: CustomBehaviour<B>,
+) -> Result<Swarm<P2pandaBehaviour<B>>>
+where
+    B: NetworkBehaviour + Send,
+{
+    let mut registry = metrics.registry();
+
     let swarm = SwarmBuilder::with_existing_identity(key_pair)
         .with_tokio()
         .with_other_transport(|key| {
@@ -21,6 +69,14 @@ pub fn build_tcp_swarm(
             let yamux_config = yamux::Config::default();
 
             let base_transport = tcp::tokio::Transport::new(tcp::Config::default().nodelay(true));
+            let base_transport = if network_config.simulation.is_enabled() {
+                let simulation = network_config.simulation;
+                Either::Left(
+                    base_transport.map(move |socket, _| SimulatedStream::new(socket, simulation)),
+                )
+            } else {
+                Either::Right(base_transport)
+            };
             let maybe_encrypted = match network_config.psk {
                 Some(psk) => Either::Left(
                     base_transport
@@ -37,14 +93,17 @@ pub fn build_tcp_swarm(
     let swarm = if !network_config.relay_mode && !network_config.relay_addresses.is_empty() {
         swarm
             .with_relay_client(noise::Config::new, yamux::Config::default)?
+            .with_bandwidth_metrics(&mut registry)
             .with_behaviour(|key_pair, relay_client| {
-                P2pandaBehaviour::new(network_config, key_pair, Some(relay_client)).unwrap()
+                P2pandaBehaviour::with_custom(network_config, key_pair, Some(relay_client), custom)
+                    .unwrap()
             })?
             .build()
     } else {
         swarm
+            .with_bandwidth_metrics(&mut registry)
             .with_behaviour(|key_pair| {
-                P2pandaBehaviour::new(network_config, key_pair, None).unwrap()
+                P2pandaBehaviour::with_custom(network_config, key_pair, None, custom).unwrap()
             })?
             .build()
     };
@@ -52,10 +111,17 @@ pub fn build_tcp_swarm(
     Ok(swarm)
 }
 
-pub fn build_quic_swarm(
+pub fn build_quic_swarm<B>(
     network_config: &NetworkConfiguration,
     key_pair: Keypair,
-) -> Result<Swarm<P2pandaBehaviour>> {
+    metrics: &NetworkMetrics,
+    custom: CustomBehaviour<B>,
+) -> Result<Swarm<P2pandaBehaviour<B>>>
+where
+    B: NetworkBehaviour + Send,
+{
+    let mut registry = metrics.registry();
+
     let swarm = SwarmBuilder::with_existing_identity(key_pair)
         .with_tokio()
         .with_quic();
@@ -63,14 +129,17 @@ pub fn build_quic_swarm(
     let swarm = if !network_config.relay_mode && !network_config.relay_addresses.is_empty() {
         swarm
             .with_relay_client(noise::Config::new, yamux::Config::default)?
+            .with_bandwidth_metrics(&mut registry)
             .with_behaviour(|key_pair, relay_client| {
-                P2pandaBehaviour::new(network_config, key_pair, Some(relay_client)).unwrap()
+                P2pandaBehaviour::with_custom(network_config, key_pair, Some(relay_client), custom)
+                    .unwrap()
             })?
             .build()
     } else {
         swarm
+            .with_bandwidth_metrics(&mut registry)
             .with_behaviour(|key_pair| {
-                P2pandaBehaviour::new(network_config, key_pair, None).unwrap()
+                P2pandaBehaviour::with_custom(network_config, key_pair, None, custom).unwrap()
             })?
             .build()
     };
@@ -0,0 +1,453 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Compact tickets for connecting two nodes without exchanging addresses manually.
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, bail, Error, Result};
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+
+use crate::network::relay::RelayStatus;
+use crate::network::utils::{is_announceable, to_quic_address, to_tcp_address};
+use crate::network::{NetworkConfiguration, Transport};
+use crate::AllowList;
+
+/// Prefix of encoded connection tickets.
+const TICKET_PREFIX: &str = "doggo";
+
+/// Version of the connection ticket format.
+const TICKET_VERSION: u8 = 1;
+
+/// Everything another node needs to know to connect to this node.
+///
+/// Tickets are encoded as a base58 string with a "doggo" prefix, which can be shared via chat or a
+/// QR code. Joining via a ticket adds its addresses as direct node addresses and its relay hints
+/// as relay addresses to the network configuration of the joining node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionTicket {
+    /// Peer id of the node which issued the ticket.
+    pub peer_id: PeerId,
+
+    /// Transport protocol used by the node.
+    pub transport: Transport,
+
+    /// Addresses the node is reachable at, formatted as "IP:PORT".
+    pub addresses: Vec<String>,
+
+    /// Relays the node is connected to, formatted as "IP:PORT" or "DOMAIN:PORT".
+    pub relay_addresses: Vec<String>,
+
+    /// Name of the network the node participates in.
+    pub network_name: Option<String>,
+
+    /// Fingerprint of the pre-shared key when the node is part of a private network.
+    ///
+    /// The key itself is never contained in a ticket, it needs to be shared separately.
+    pub psk_fingerprint: Option<String>,
+}
+
+/// Wire format of a ticket, encoded as a CBOR array to keep it compact.
+#[derive(Serialize, Deserialize)]
+struct EncodedTicket(
+    u8,
+    #[serde(with = "serde_bytes")] Vec<u8>,
+    u8,
+    Vec<String>,
+    Vec<String>,
+    Option<String>,
+    Option<String>,
+);
+
+impl ConnectionTicket {
+    /// Add the addresses of this ticket to the network configuration, so the node connects to the
+    /// issuer of the ticket.
+    ///
+    /// Fails when the configuration is not compatible with the ticket, for example when it uses
+    /// another transport protocol or pre-shared key.
+    pub fn apply(&self, network: &mut NetworkConfiguration) -> Result<()> {
+        if self.transport != network.transport {
+            bail!(
+                "Ticket of peer {} requires the {:?} transport",
+                self.peer_id,
+                self.transport
+            );
+        }
+
+        let psk_fingerprint = network.psk.map(|psk| psk.fingerprint().to_string());
+        if psk_fingerprint != self.psk_fingerprint {
+            match self.psk_fingerprint {
+                Some(_) => bail!(
+                    "Ticket of peer {} requires a matching pre-shared key",
+                    self.peer_id
+                ),
+                None => bail!("Peer {} is not part of a private network", self.peer_id),
+            }
+        }
+
+        match (&network.network_name, &self.network_name) {
+            (Some(name), Some(ticket_name)) if name != ticket_name => bail!(
+                "Peer {} participates in network '{}'",
+                self.peer_id,
+                ticket_name
+            ),
+            (None, Some(ticket_name)) => network.network_name = Some(ticket_name.to_owned()),
+            _ => (),
+        }
+
+        for address in &self.addresses {
+            if !network
+                .direct_node_addresses
+                .iter()
+                .any(|known| &known.to_string() == address)
+            {
+                network
+                    .direct_node_addresses
+                    .push(address.to_owned().into());
+            }
+        }
+
+        // We can't use relays when we are one
+        if !network.relay_mode {
+            for address in &self.relay_addresses {
+                if !network
+                    .relay_addresses
+                    .iter()
+                    .any(|known| &known.to_string() == address)
+                {
+                    network.relay_addresses.push(address.to_owned().into());
+                }
+            }
+        }
+
+        // Make sure we don't block the peer we want to connect to
+        if let AllowList::Set(peer_ids) = &mut network.allow_peer_ids {
+            if !peer_ids.contains(&self.peer_id) {
+                peer_ids.push(self.peer_id);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for ConnectionTicket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let transport = match self.transport {
+            Transport::QUIC => 0,
+            Transport::TCP => 1,
+        };
+
+        let encoded = EncodedTicket(
+            TICKET_VERSION,
+            self.peer_id.to_bytes(),
+            transport,
+            self.addresses.clone(),
+            self.relay_addresses.clone(),
+            self.network_name.clone(),
+            self.psk_fingerprint.clone(),
+        );
+
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&encoded, &mut bytes).map_err(|_| std::fmt::Error)?;
+
+        write!(f, "{}{}", TICKET_PREFIX, bs58::encode(bytes).into_string())
+    }
+}
+
+impl FromStr for ConnectionTicket {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let bytes = value
+            .trim()
+            .strip_prefix(TICKET_PREFIX)
+            .and_then(|encoded| bs58::decode(encoded).into_vec().ok())
+            .ok_or_else(|| anyhow!("Invalid encoding of connection ticket"))?;
+
+        let EncodedTicket(
+            version,
+            peer_id,
+            transport,
+            addresses,
+            relay_addresses,
+            network_name,
+            psk_fingerprint,
+        ) = ciborium::de::from_reader(bytes.as_slice())
+            .map_err(|_| anyhow!("Invalid encoding of connection ticket"))?;
+
+        if version != TICKET_VERSION {
+            bail!("Unsupported connection ticket version {}", version);
+        }
+
+        let transport = match transport {
+            0 => Transport::QUIC,
+            1 => Transport::TCP,
+            _ => bail!("Unknown transport in connection ticket"),
+        };
+
+        Ok(Self {
+            peer_id: PeerId::from_bytes(&peer_id)
+                .map_err(|_| anyhow!("Invalid peer id in connection ticket"))?,
+            transport,
+            addresses,
+            relay_addresses,
+            network_name,
+            psk_fingerprint,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct LocalAddressesInner {
+    /// Our own peer id.
+    peer_id: Option<PeerId>,
+
+    /// Transport protocol the swarm was started with.
+    transport: Transport,
+
+    /// Addresses the swarm is listening on.
+    listen_addresses: Vec<Multiaddr>,
+
+    /// Addresses other peers observed us at.
+    external_addresses: Vec<Multiaddr>,
+
+    /// Peer id and address of the relay we're currently registered at.
+    relay: Option<(PeerId, Multiaddr)>,
+
+    /// State of all relays we're connected to or were connected to before.
+    relay_statuses: Vec<RelayStatus>,
+}
+
+/// Addresses of the local node, learned by the network service during runtime.
+#[derive(Debug, Clone, Default)]
+pub struct LocalAddresses(Arc<Mutex<LocalAddressesInner>>);
+
+impl LocalAddresses {
+    fn inner(&self) -> std::sync::MutexGuard<'_, LocalAddressesInner> {
+        self.0.lock().expect("Local addresses lock poisoned")
+    }
+
+    /// Set the peer id of the local node and the transport protocol its swarm uses.
+    pub fn set_local_peer(&self, peer_id: PeerId, transport: Transport) {
+        let mut inner = self.inner();
+        inner.peer_id = Some(peer_id);
+        inner.transport = transport;
+    }
+
+    /// Remember an address the swarm started listening on.
+    pub fn add_listen_address(&self, address: Multiaddr) {
+        let mut inner = self.inner();
+        if !inner.listen_addresses.contains(&address) {
+            inner.listen_addresses.push(address);
+        }
+    }
+
+    /// Forget an address the swarm stopped listening on.
+    pub fn remove_listen_address(&self, address: &Multiaddr) {
+        self.inner()
+            .listen_addresses
+            .retain(|listen_address| listen_address != address);
+    }
+
+    /// Remember an address other peers observed us at.
+    pub fn add_external_address(&self, address: Multiaddr) {
+        let mut inner = self.inner();
+        if !inner.external_addresses.contains(&address) {
+            inner.external_addresses.push(address);
+        }
+    }
+
+    /// Remember the relay we're currently using or forget it when `None` is given.
+    pub fn set_relay(&self, relay: Option<(PeerId, Multiaddr)>) {
+        self.inner().relay = relay;
+    }
+
+    /// Remember the state of all known relays.
+    pub fn set_relay_statuses(&self, statuses: Vec<RelayStatus>) {
+        self.inner().relay_statuses = statuses;
+    }
+
+    /// Returns our own peer id or `None` when the network service has not started yet.
+    pub fn peer_id(&self) -> Option<PeerId> {
+        self.inner().peer_id
+    }
+
+    /// Returns peer id and address of the relay we're currently using.
+    pub fn relay(&self) -> Option<(PeerId, Multiaddr)> {
+        self.inner().relay.clone()
+    }
+
+    /// Returns the state of all known relays, including our registrations at them.
+    pub fn relay_statuses(&self) -> Vec<RelayStatus> {
+        self.inner().relay_statuses.clone()
+    }
+
+    /// Returns a ticket for connecting to this node.
+    ///
+    /// Addresses observed by other peers come first, followed by the IPv4 and IPv6 addresses of
+    /// all network interfaces except of loopback and link-local addresses. Returns `None` when the network service has
+    /// not started yet.
+    pub fn ticket(&self, network: &NetworkConfiguration) -> Option<ConnectionTicket> {
+        let inner = self.inner();
+        let peer_id = inner.peer_id?;
+
+        let mut addresses: Vec<String> = Vec::new();
+        for address in inner
+            .external_addresses
+            .iter()
+            .chain(inner.listen_addresses.iter())
+        {
+            let socket_address = match inner.transport {
+                Transport::QUIC => to_quic_address(address),
+                Transport::TCP => to_tcp_address(address),
+            };
+
+            if let Some(socket_address) = socket_address {
+                let address = socket_address.to_string();
+                if is_announceable(&socket_address) && !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+        }
+
+        Some(ConnectionTicket {
+            peer_id,
+            transport: inner.transport,
+            addresses,
+            relay_addresses: network
+                .relay_addresses
+                .iter()
+                .map(|address| address.to_string())
+                .collect(),
+            network_name: network.network_name.clone(),
+            psk_fingerprint: network.psk.map(|psk| psk.fingerprint().to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libp2p::pnet::PreSharedKey;
+    use libp2p::{Multiaddr, PeerId};
+
+    use crate::network::{NetworkConfiguration, Transport};
+    use crate::AllowList;
+
+    use super::{ConnectionTicket, LocalAddresses};
+
+    #[test]
+    fn issue_and_join_via_ticket() {
+        let peer_id = PeerId::random();
+        let addresses = LocalAddresses::default();
+
+        let network = NetworkConfiguration {
+            relay_addresses: vec!["relay.p2panda.org:2022".to_string().into()],
+            network_name: Some("pandas".into()),
+            ..Default::default()
+        };
+
+        // No ticket is issued before the network service started
+        assert!(addresses.ticket(&network).is_none());
+
+        addresses.set_local_peer(peer_id, Transport::QUIC);
+        for address in [
+            "/ip4/127.0.0.1/udp/2022/quic-v1",
+            "/ip4/192.168.1.12/udp/2022/quic-v1",
+        ] {
+            addresses.add_listen_address(address.parse::<Multiaddr>().unwrap());
+        }
+        addresses.add_external_address("/ip4/85.13.5.1/udp/2022/quic-v1".parse().unwrap());
+
+        let ticket = addresses.ticket(&network).unwrap();
+        assert_eq!(
+            ticket.addresses,
+            vec!["85.13.5.1:2022", "192.168.1.12:2022"]
+        );
+
+        // Tickets survive encoding
+        let encoded = ticket.to_string();
+        assert!(encoded.starts_with("doggo"));
+        let decoded: ConnectionTicket = encoded.parse().unwrap();
+        assert_eq!(decoded, ticket);
+        assert!("doggo123".parse::<ConnectionTicket>().is_err());
+
+        // Joining via ticket adds addresses and relay hints
+        let mut joining = NetworkConfiguration {
+            allow_peer_ids: AllowList::Set(vec![]),
+            ..Default::default()
+        };
+        decoded.apply(&mut joining).unwrap();
+        assert_eq!(joining.direct_node_addresses.len(), 2);
+        assert_eq!(
+            joining.relay_addresses[0].to_string(),
+            "relay.p2panda.org:2022"
+        );
+        assert_eq!(joining.network_name, Some("pandas".into()));
+        assert!(
+            matches!(joining.allow_peer_ids, AllowList::Set(ref peer_ids) if peer_ids == &vec![peer_id])
+        );
+
+        // Applying a ticket twice does not add duplicates
+        decoded.apply(&mut joining).unwrap();
+        assert_eq!(joining.direct_node_addresses.len(), 2);
+    }
+
+    #[test]
+    fn announce_ipv6_addresses() {
+        let addresses = LocalAddresses::default();
+        addresses.set_local_peer(PeerId::random(), Transport::TCP);
+
+        for address in [
+            "/ip6/::1/tcp/2022",
+            "/ip6/fe80::1/tcp/2022",
+            "/ip6/2001:db8::12/tcp/2022",
+            "/ip4/192.168.1.12/tcp/2022",
+        ] {
+            addresses.add_listen_address(address.parse::<Multiaddr>().unwrap());
+        }
+
+        // Loopback and link-local addresses are not announced
+        let ticket = addresses.ticket(&NetworkConfiguration::default()).unwrap();
+        assert_eq!(
+            ticket.addresses,
+            vec!["[2001:db8::12]:2022", "192.168.1.12:2022"]
+        );
+    }
+
+    #[test]
+    fn reject_incompatible_configuration() {
+        let psk = PreSharedKey::new([1; 32]);
+        let ticket = ConnectionTicket {
+            peer_id: PeerId::random(),
+            transport: Transport::TCP,
+            addresses: vec!["85.13.5.1:2022".into()],
+            relay_addresses: vec![],
+            network_name: None,
+            psk_fingerprint: Some(psk.fingerprint().to_string()),
+        };
+
+        // Transport needs to match
+        let mut network = NetworkConfiguration {
+            psk: Some(psk),
+            ..Default::default()
+        };
+        assert!(ticket.apply(&mut network).is_err());
+
+        // Pre-shared key needs to match
+        let mut network = NetworkConfiguration {
+            transport: Transport::TCP,
+            psk: Some(PreSharedKey::new([2; 32])),
+            ..Default::default()
+        };
+        assert!(ticket.apply(&mut network).is_err());
+
+        let mut network = NetworkConfiguration {
+            transport: Transport::TCP,
+            psk: Some(psk),
+            ..Default::default()
+        };
+        assert!(ticket.apply(&mut network).is_ok());
+    }
+}
@@ -1,49 +1,56 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroU8;
 
+use libp2p::multiaddr::{self, Protocol};
 use libp2p::swarm::dial_opts::DialOpts;
+use libp2p::swarm::NetworkBehaviour;
 use libp2p::{Multiaddr, PeerId, Swarm};
 use log::debug;
-use regex::Regex;
 
 use crate::network::behaviour::P2pandaBehaviour;
-use crate::network::config::{PeerAddress, Transport};
+use crate::network::config::{IpVersion, PeerAddress, Transport};
+
+/// Returns the IP address and the remaining protocols of a multiaddress.
+fn split_ip(address: &Multiaddr) -> Option<(IpAddr, multiaddr::Iter<'_>)> {
+    let mut protocols = address.iter();
+    let ip = match protocols.next()? {
+        Protocol::Ip4(ip) => IpAddr::V4(ip),
+        Protocol::Ip6(ip) => IpAddr::V6(ip),
+        _ => return None,
+    };
+
+    Some((ip, protocols))
+}
 
 pub fn to_quic_address(address: &Multiaddr) -> Option<SocketAddr> {
-    let hay = address.to_string();
-    let regex = Regex::new(r"/ip4/(\d+.\d+.\d+.\d+)/udp/(\d+)/quic-v1").unwrap();
-    let caps = regex.captures(&hay);
-
-    match caps {
-        None => None,
-        Some(caps) => {
-            let ip_address = caps.get(1).unwrap().as_str();
-            let port = caps.get(2).unwrap().as_str();
-            let socket = format!("{ip_address}:{port}")
-                .parse::<SocketAddr>()
-                .expect("Tried to convert invalid address");
-            Some(socket)
-        }
+    let (ip, mut protocols) = split_ip(address)?;
+
+    match (protocols.next()?, protocols.next()?) {
+        (Protocol::Udp(port), Protocol::QuicV1) => Some(SocketAddr::new(ip, port)),
+        _ => None,
     }
 }
 
 pub fn to_tcp_address(address: &Multiaddr) -> Option<SocketAddr> {
-    let hay = address.to_string();
-    let regex = Regex::new(r"/ip4/(\d+.\d+.\d+.\d+)/tcp/(\d+)").unwrap();
-    let caps = regex.captures(&hay);
-
-    match caps {
-        None => None,
-        Some(caps) => {
-            let ip_address = caps.get(1).unwrap().as_str();
-            let port = caps.get(2).unwrap().as_str();
-            let socket = format!("{ip_address}:{port}")
-                .parse::<SocketAddr>()
-                .expect("Tried to convert invalid address");
-            Some(socket)
+    let (ip, mut protocols) = split_ip(address)?;
+
+    match protocols.next()? {
+        Protocol::Tcp(port) => Some(SocketAddr::new(ip, port)),
+        _ => None,
+    }
+}
+
+/// Returns true if other nodes can reach this address from outside of the host or local link.
+pub fn is_announceable(address: &SocketAddr) -> bool {
+    match address.ip() {
+        IpAddr::V4(ip) => !ip.is_loopback() && !ip.is_unspecified(),
+        // Link-local unicast addresses (fe80::/10) are only valid together with a network
+        // interface of the host
+        IpAddr::V6(ip) => {
+            !ip.is_loopback() && !ip.is_unspecified() && (ip.segments()[0] & 0xffc0) != 0xfe80
         }
     }
 }
@@ -52,11 +59,12 @@ pub fn is_known_peer_address(
     known_addresses: &mut [PeerAddress],
     peer_addresses: &[Multiaddr],
     transport: Transport,
+    ip_version: IpVersion,
 ) -> Option<Multiaddr> {
     for address in known_addresses.iter_mut() {
         let address = match transport {
-            Transport::QUIC => address.quic_multiaddr(),
-            Transport::TCP => address.tcp_multiaddr(),
+            Transport::QUIC => address.quic_multiaddr(ip_version),
+            Transport::TCP => address.tcp_multiaddr(ip_version),
         };
 
         if let Ok(addr) = address {
@@ -68,18 +76,19 @@ pub fn is_known_peer_address(
     None
 }
 
-pub fn dial_known_peer(
-    swarm: &mut Swarm<P2pandaBehaviour>,
+pub fn dial_known_peer<B: NetworkBehaviour>(
+    swarm: &mut Swarm<P2pandaBehaviour<B>>,
     known_peers: &mut HashMap<Multiaddr, PeerId>,
     address: &mut PeerAddress,
     transport: Transport,
+    ip_version: IpVersion,
 ) {
     // Get the peers multiaddr, this can error if the address was provided in the form
     // of a domain name and we are not able to resolve it to a valid address (for example,
     // if we are offline).
     let address = match transport {
-        Transport::QUIC => address.quic_multiaddr(),
-        Transport::TCP => address.tcp_multiaddr(),
+        Transport::QUIC => address.quic_multiaddr(ip_version),
+        Transport::TCP => address.tcp_multiaddr(ip_version),
     };
 
     let address = match address {
@@ -1,22 +1,48 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use std::sync::Mutex;
+use std::time::Duration;
+
 use anyhow::Result;
+use libp2p::swarm::{dummy, NetworkBehaviour};
+use libp2p::Swarm;
+use log::warn;
+use p2panda_rs::document::DocumentId;
 use p2panda_rs::identity::KeyPair;
+use p2panda_rs::schema::SchemaId;
 use tokio::sync::mpsc::Receiver;
 
-use crate::api::{NodeEvent, NodeInterface};
+use crate::api::{
+    DocumentBundle, DocumentFilter, ImportCommit, ImportReport, NodeEvent, NodeInterface,
+    PeerProfile, PurgeReport, RelationReport,
+};
+use crate::archive::archive_service;
 use crate::bus::ServiceMessage;
+use crate::capabilities::Invite;
+use crate::cluster::cluster_service;
 use crate::config::Configuration;
 use crate::context::Context;
 use crate::db::SqlStore;
-use crate::db::{connection_pool, create_database, run_pending_migrations, Pool};
+use crate::db::{
+    connection_pool, create_database, initialize_archive, run_pending_migrations, Pool,
+    TransactionConfig,
+};
 use crate::http::http_service;
 use crate::manager::ServiceManager;
+#[cfg(unix)]
+use crate::materializer::instrumentation_service;
 use crate::materializer::materializer_service;
-use crate::network::network_service;
+use crate::metrics::metrics_service;
+use crate::network::identity::to_libp2p_key_pair;
+use crate::network::{
+    network_service, network_service_with_swarm, ConnectionTicket, P2pandaBehaviour,
+};
 use crate::replication::replication_service;
 use crate::schema::SchemaProvider;
-use crate::LockFile;
+use crate::startup::{Readiness, StartupPhase};
+use crate::vacuum::vacuum_service;
+use crate::webhooks::webhook_service;
+use crate::{LockFile, VacuumReport};
 
 /// Capacity of the internal broadcast channel used to communicate between services.
 const SERVICE_BUS_CAPACITY: usize = 512_000;
@@ -30,7 +56,12 @@ async fn initialize_db(config: &Configuration) -> Result<Pool> {
     create_database(&config.database_url).await?;
 
     // Create connection pool
-    let pool = connection_pool(&config.database_url, config.database_max_connections).await?;
+    let pool = connection_pool(
+        &config.database_url,
+        config.database_max_connections,
+        config.database_key.as_deref(),
+    )
+    .await?;
 
     // Run pending migrations
     run_pending_migrations(&pool).await?;
@@ -38,10 +69,30 @@ async fn initialize_db(config: &Configuration) -> Result<Pool> {
     Ok(pool)
 }
 
+/// Makes sure archive database is created and initialized before returning connection pool.
+async fn initialize_archive_db(url: &str, config: &Configuration) -> Result<Pool> {
+    // Create database when not existing
+    create_database(url).await?;
+
+    // Create connection pool
+    let pool = connection_pool(
+        url,
+        config.database_max_connections,
+        config.database_key.as_deref(),
+    )
+    .await?;
+
+    // Create archive tables when not existing
+    initialize_archive(&pool).await?;
+
+    Ok(pool)
+}
+
 /// Main runtime managing the p2panda node process.
 #[allow(missing_debug_implementations)]
 pub struct Node {
     pool: Pool,
+    archive_pool: Option<Pool>,
     manager: ServiceManager<Context, ServiceMessage>,
     api: NodeInterface,
 }
@@ -50,60 +101,277 @@ impl Node {
     /// Start p2panda node with your configuration. This method can be used to run the node within
     /// other applications.
     pub async fn start(key_pair: KeyPair, config: Configuration) -> Self {
+        Self::start_inner::<dummy::Behaviour>(key_pair, config, None, Readiness::default()).await
+    }
+
+    /// Start p2panda node and report its startup progress to the given readiness handle.
+    ///
+    /// Subscribe to the handle with `Readiness::subscribe` before starting the node to observe
+    /// each startup phase as it completes, for example to show progress during long migrations.
+    pub async fn start_with_readiness(
+        key_pair: KeyPair,
+        config: Configuration,
+        readiness: Readiness,
+    ) -> Self {
+        Self::start_inner::<dummy::Behaviour>(key_pair, config, None, readiness).await
+    }
+
+    /// Start p2panda node on a libp2p swarm built by the application embedding it.
+    ///
+    /// This allows applications to run their own network behaviours, for example gossip, on the
+    /// same swarm and connections as the node instead of running two networking stacks. Use
+    /// `build_swarm` with a `CustomBehaviour` wrapping the application's behaviour to construct
+    /// the swarm and the returned `CustomBehaviourHandle` to interact with it after the node
+    /// started.
+    ///
+    /// Panics if the swarm was not built with the key pair of the node.
+    pub async fn start_with_swarm<B>(
+        key_pair: KeyPair,
+        config: Configuration,
+        swarm: Swarm<P2pandaBehaviour<B>>,
+    ) -> Self
+    where
+        B: NetworkBehaviour + Send,
+    {
+        let peer_id = to_libp2p_key_pair(&key_pair).public().to_peer_id();
+        if swarm.local_peer_id() != &peer_id {
+            panic!("Swarm was not built with the key pair of the node");
+        }
+
+        Self::start_inner(key_pair, config, Some(swarm), Readiness::default()).await
+    }
+
+    async fn start_inner<B>(
+        key_pair: KeyPair,
+        config: Configuration,
+        swarm: Option<Swarm<P2pandaBehaviour<B>>>,
+        readiness: Readiness,
+    ) -> Self
+    where
+        B: NetworkBehaviour + Send,
+    {
+        // Warn about phases taking long, to tell slow startups apart from hanging ones
+        readiness.enter(StartupPhase::Migrations);
+        tokio::task::spawn(readiness.clone().warn_on_stalls());
+
         // Initialize database and get connection pool
         let pool = initialize_db(&config)
             .await
             .expect("Could not initialize database");
 
+        // Initialize optional archive database
+        let archive_pool = match &config.archive_database_url {
+            Some(url) => Some(
+                initialize_archive_db(url, &config)
+                    .await
+                    .expect("Could not initialize archive database"),
+            ),
+            None => None,
+        };
+
         // Prepare storage and schema providers using connection pool
+        readiness.enter(StartupPhase::Store);
         let store = SqlStore::new(pool.clone());
 
+        // Views get materialized by the leader of a cluster and entries get published on any
+        // instance, cached views and logs would go stale
+        let store = match config.cluster_instance {
+            Some(_) => store,
+            None => store
+                .with_document_cache(config.document_view_cache_size)
+                .with_log_cache(config.log_state_cache_size),
+        };
+        let store = store.with_view_eviction(config.max_document_views.keys().cloned().collect());
+        let store = store.with_transactions(TransactionConfig {
+            isolation_level: config.database_isolation_level,
+            max_retries: config.database_max_retries,
+        });
+        let store = match config.graphql_snapshot_ttl {
+            0 => store,
+            ttl => store.with_snapshots(Duration::from_secs(ttl)),
+        };
+        let store = match &archive_pool {
+            Some(archive_pool) => store.with_archive(archive_pool.clone()),
+            None => store,
+        };
+
+        // Remove entries of batches which were not published completely when the node stopped.
+        // Other instances of a cluster might still be publishing their batches
+        if config.cluster_instance.is_none() {
+            let rolled_back = store
+                .rollback_pending_batches()
+                .await
+                .expect("Could not roll back pending batches");
+
+            if rolled_back > 0 {
+                warn!("Rolled back {} incomplete batches", rolled_back);
+            }
+        }
+
         // Initiate the SchemaProvider with all currently known schema from the store.
         //
         // If a list of allowed schema ids is provided then only schema identified in this list
         // will be added to the provider and supported by the node.
+        readiness.enter(StartupPhase::SchemaProvider);
         let application_schema = store.get_all_schema().await.unwrap();
         let schema_provider =
-            SchemaProvider::new(application_schema, config.allow_schema_ids.clone());
+            SchemaProvider::new(application_schema.clone(), config.allow_schema_ids.clone())
+                .with_schema_pins(
+                    config.allow_schema_pins.clone(),
+                    config.schema_version_policy,
+                )
+                .with_field_constraints(config.field_constraints.clone())
+                .with_decimal_fields(config.decimal_fields.clone())
+                .with_datetime_fields(config.datetime_fields.clone())
+                .with_canonical_encoding(config.require_canonical_encoding)
+                .with_schema_settings(config.schema_settings.clone());
+
+        // Schemas pinned by name and version in the allow-list are resolved again, the schema
+        // provider only knows about the allowed schema ids yet
+        for schema in application_schema {
+            if let SchemaId::Application(name, view_id) = schema.id() {
+                if !schema_provider.is_pinned(name) {
+                    continue;
+                }
+
+                let versions = store
+                    .get_schema_versions(view_id)
+                    .await
+                    .expect("Could not resolve pinned schemas");
+
+                if let Some(versions) = versions {
+                    if schema_provider.allow_pinned_version(&versions).await {
+                        let _ = schema_provider.update(schema).await;
+                    }
+                }
+            }
+        }
 
         // Create service manager with shared data between services
-        let context = Context::new(store, key_pair, config, schema_provider);
+        let context =
+            Context::with_readiness(store, key_pair, config, schema_provider, readiness.clone());
         let mut manager =
             ServiceManager::<Context, ServiceMessage>::new(SERVICE_BUS_CAPACITY, context.clone());
 
-        // Start materializer service
-        if manager
-            .add("materializer", materializer_service)
-            .await
-            .is_err()
-        {
-            panic!("Failed starting materialiser service");
-        }
-
-        // Start HTTP server with GraphQL API
+        // Start HTTP server with GraphQL API first, its readiness probe reports the progress of
+        // the remaining phases
+        readiness.enter(StartupPhase::Network);
         if manager.add("http", http_service).await.is_err() {
             panic!("Failed starting HTTP service");
         }
 
-        // Start network service
-        if manager.add("network", network_service).await.is_err() {
-            panic!("Failed starting network service");
+        // Instances of a cluster only run the services writing to the database or talking to
+        // the network while they are the elected leader
+        if context.config.cluster_instance.is_some() {
+            if swarm.is_some() {
+                panic!("Clustered nodes can not run on a custom swarm");
+            }
+
+            readiness.enter(StartupPhase::Services);
+            if manager.add("cluster", cluster_service).await.is_err() {
+                panic!("Failed starting cluster service");
+            }
+        } else {
+            // Start network service, either on the swarm handed in by the application or on our
+            // own
+            let network_result = match swarm {
+                Some(swarm) => {
+                    // The service is only started once, so we can move the swarm into it
+                    let swarm = Mutex::new(Some(swarm));
+                    manager
+                        .add("network", move |context, shutdown, tx, tx_ready| {
+                            let swarm = swarm
+                                .lock()
+                                .expect("Could not acquire lock on swarm")
+                                .take()
+                                .expect("Network service can only be started once");
+                            network_service_with_swarm(swarm, context, shutdown, tx, tx_ready)
+                        })
+                        .await
+                }
+                None => manager.add("network", network_service).await,
+            };
+
+            if network_result.is_err() {
+                panic!("Failed starting network service");
+            }
+
+            readiness.enter(StartupPhase::Services);
+
+            // Start materializer service
+            if manager
+                .add("materializer", materializer_service)
+                .await
+                .is_err()
+            {
+                panic!("Failed starting materialiser service");
+            }
+
+            // Start replication service syncing data with other nodes
+            if manager
+                .add("replication", replication_service)
+                .await
+                .is_err()
+            {
+                panic!("Failed starting replication service");
+            }
+
+            // Start archive service moving inactive documents into archive database
+            if archive_pool.is_some() && manager.add("archive", archive_service).await.is_err() {
+                panic!("Failed starting archive service");
+            }
+
+            // Start vacuum service periodically cleaning up entries and operations of failed
+            // ingests
+            if context.config.vacuum_interval.is_some()
+                && manager.add("vacuum", vacuum_service).await.is_err()
+            {
+                panic!("Failed starting vacuum service");
+            }
+
+            // Start webhook service notifying external services about document changes
+            if !context.config.webhooks.is_empty()
+                && manager.add("webhooks", webhook_service).await.is_err()
+            {
+                panic!("Failed starting webhook service");
+            }
         }
 
-        // Start replication service syncing data with other nodes
-        if manager
-            .add("replication", replication_service)
-            .await
-            .is_err()
+        // Start instrumentation service streaming materializer events to external tools
+        #[cfg(unix)]
+        if context.config.materializer_events_socket.is_some()
+            && manager
+                .add("instrumentation", instrumentation_service)
+                .await
+                .is_err()
+        {
+            panic!("Failed starting instrumentation service");
+        }
+
+        // Start metrics service pushing snapshots to a remote target
+        if context.config.metrics_push_target.is_some()
+            && manager.add("metrics", metrics_service).await.is_err()
         {
-            panic!("Failed starting replication service");
+            panic!("Failed starting metrics service");
         }
 
         // Create a low-level interface which can be exposed so developers can interact with the
         // internal store and service bus
         let api = NodeInterface::new(context, manager.get_sender());
 
-        Self { pool, manager, api }
+        // Publish the profile of this node if it changed since the last start
+        if let Err(err) = api.publish_node_profile().await {
+            warn!("Failed publishing node profile: {}", err);
+        }
+
+        readiness.enter(StartupPhase::Ready);
+
+        Self {
+            pool,
+            archive_pool,
+            manager,
+            api,
+        }
     }
 
     /// This future resolves when at least one system service stopped.
@@ -119,8 +387,12 @@ impl Node {
         // Wait until all tasks are shut down
         self.manager.shutdown().await;
 
-        // Close connection pool
+        // Close connection pools
         self.pool.close().await;
+
+        if let Some(archive_pool) = self.archive_pool {
+            archive_pool.close().await;
+        }
     }
 
     /// Utility method to publish multiple operations and entries in the node database.
@@ -133,9 +405,106 @@ impl Node {
         self.api.migrate(lock_file).await
     }
 
+    /// Validate and publish raw encoded entries and operations in the node database, followed by
+    /// their materialization.
+    ///
+    /// This method is useful for migrating data from other node implementations or restoring
+    /// partial data. Entries and operations can be read from files with `read_commits`.
+    ///
+    /// Returns a report of imported, already existing and failed entries.
+    pub async fn import(&self, commits: Vec<ImportCommit>) -> Result<ImportReport> {
+        self.api.import(commits).await
+    }
+
+    /// Remove or re-link entries and operations which belong to no known document, for example
+    /// after an ingest failed half-way.
+    ///
+    /// Returns a report of removed and re-linked data. Sweeps can also run periodically, see the
+    /// `vacuum_interval` configuration option.
+    pub async fn vacuum(&self) -> Result<VacuumReport> {
+        self.api.vacuum().await
+    }
+
+    /// Report relations pointing at documents which are missing on this node, got deleted or are
+    /// of an unexpected schema, grouped by schema.
+    ///
+    /// Only the latest views of documents which are not deleted are checked. Dangling relations
+    /// are usually caused by partial replication or garbage collection.
+    pub async fn check_relations(&self) -> Result<RelationReport> {
+        self.api.check_relations().await
+    }
+
+    /// Export all entries and operations of a document as a bundle signed by this node.
+    ///
+    /// Third parties can verify the bundle offline with `DocumentBundle::verify` and import it
+    /// into their own node, see `import`.
+    pub async fn export_document(&self, document_id: &DocumentId) -> Result<DocumentBundle> {
+        self.api.export_document(document_id).await
+    }
+
+    /// Purge a document with all its entries, operations and materialized views right away,
+    /// instead of waiting for garbage collection.
+    ///
+    /// With `include_blobs` the files and pieces of purged blobs are removed as well, blobs the
+    /// document relates to are purged when nothing else relates to them anymore. Purging is
+    /// idempotent, documents which do not exist result in an empty report.
+    pub async fn purge_document(
+        &self,
+        document_id: &DocumentId,
+        include_blobs: bool,
+    ) -> Result<PurgeReport> {
+        self.api.purge_document(document_id, include_blobs).await
+    }
+
+    /// Generate an invite granting the public key redeeming it the permission to publish to the
+    /// given schemas until it expires.
+    ///
+    /// The invite code can be redeemed with the `redeemInvite` GraphQL mutation. Invites only have
+    /// an effect when access control via a capability schema is enabled.
+    pub async fn create_invite(
+        &self,
+        schema_ids: Vec<SchemaId>,
+        valid_for: Duration,
+    ) -> Result<Invite> {
+        self.api.create_invite(schema_ids, valid_for).await
+    }
+
+    /// Returns a ticket other nodes can use to connect to this node, see `ConnectionTicket::apply`
+    /// or the `join_tickets` configuration option.
+    ///
+    /// Returns `None` as long as the network service did not start listening yet.
+    pub fn connection_ticket(&self) -> Option<ConnectionTicket> {
+        self.api.connection_ticket()
+    }
+
     /// Subscribe to channel reporting on significant node events which can be interesting for
     /// clients, for example when peers connect or disconnect.
+    ///
+    /// Connection events contain the address, transport and direction of the connection, which
+    /// can be used to show the connectivity of the node or to apply custom connection policies.
     pub async fn subscribe(&self) -> Receiver<NodeEvent> {
         self.api.subscribe().await
     }
+
+    /// Subscribe to channel reporting on new views of documents of the given schema which match
+    /// the filter.
+    ///
+    /// The filter is evaluated by the node against every updated document, only matching
+    /// documents are reported as `NodeEvent::DocumentUpdated`. Returns an error if the schema is
+    /// not supported by this node or the filter does not match the schema.
+    pub async fn subscribe_documents(
+        &self,
+        schema_id: &SchemaId,
+        filter: DocumentFilter,
+    ) -> Result<Receiver<NodeEvent>> {
+        self.api.subscribe_documents(schema_id, filter).await
+    }
+
+    /// Returns the profiles other nodes published to the node profile schema.
+    ///
+    /// Profiles are only known when their documents were replicated to this node. Returns an
+    /// empty list if no node profile schema is configured.
+    pub async fn peer_profiles(&self) -> Result<Vec<PeerProfile>> {
+        self.api.peer_profiles().await
+    }
 }
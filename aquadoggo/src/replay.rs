@@ -0,0 +1,347 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Deterministic replay of the materialization of a single document for debugging.
+//!
+//! The replay runs against a copy of the database, the original store is never modified.
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use log::{debug, info, trace, warn};
+use p2panda_rs::document::traits::AsDocument;
+use p2panda_rs::document::{DocumentBuilder, DocumentId};
+use p2panda_rs::identity::KeyPair;
+use p2panda_rs::operation::traits::AsOperation;
+use p2panda_rs::storage_provider::traits::{DocumentStore, OperationStore};
+use sqlx::query;
+use tempfile::TempDir;
+
+use crate::config::Configuration;
+use crate::context::Context;
+use crate::db::{connection_pool, run_pending_migrations, Pool, SqlStore};
+use crate::materializer::tasks::{dependency_task, reduce_task};
+use crate::materializer::{Task, TaskInput, TaskResult};
+use crate::schema::SchemaProvider;
+
+/// Outcome of a single task during a replay.
+#[derive(Debug)]
+pub enum ReplayOutcome {
+    /// Task completed and dispatched these tasks, formatted as `<worker> <input>`.
+    Completed(Vec<String>),
+
+    /// Task failed with this error.
+    Failed(String),
+
+    /// Task was dispatched before with the same input and is not processed again.
+    ///
+    /// This usually indicates a loop between tasks.
+    Repeated,
+
+    /// Task is not part of the replay, only reduce and dependency tasks are processed.
+    Skipped,
+}
+
+/// Task processed during a replay and its outcome.
+#[derive(Debug)]
+pub struct ReplayStep {
+    /// Name of the worker processing this task.
+    pub worker_name: String,
+
+    /// Input of the task.
+    pub input: String,
+
+    /// Outcome of processing the task.
+    pub outcome: ReplayOutcome,
+}
+
+impl Display for ReplayStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}: ", self.worker_name, self.input)?;
+
+        match &self.outcome {
+            ReplayOutcome::Completed(tasks) if tasks.is_empty() => write!(f, "completed"),
+            ReplayOutcome::Completed(tasks) => {
+                write!(f, "completed, dispatched {}", tasks.join(", "))
+            }
+            ReplayOutcome::Failed(err) => write!(f, "failed with \"{}\"", err),
+            ReplayOutcome::Repeated => write!(f, "dispatched again, possible loop"),
+            ReplayOutcome::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// Re-run reduce and dependency tasks for a document against a copy of the node's database.
+///
+/// The materialized views of the document are removed from the copy before the replay, starting
+/// with a reduce task for the document. All tasks which get dispatched from there are processed in
+/// order, stopping after `max_steps` tasks. Every applied operation and inserted view is logged.
+///
+/// Only SQLite databases are supported. The archive database is not consulted.
+pub async fn replay_document(
+    config: &Configuration,
+    document_id: &DocumentId,
+    max_steps: usize,
+) -> Result<Vec<ReplayStep>> {
+    if !config.database_url.starts_with("sqlite") {
+        bail!("Replay is only supported for SQLite databases");
+    }
+
+    let pool = connection_pool(&config.database_url, 1, config.database_key.as_deref()).await?;
+    let steps = replay(&pool, config, document_id, max_steps).await;
+    pool.close().await;
+
+    steps
+}
+
+async fn replay(
+    source_pool: &Pool,
+    config: &Configuration,
+    document_id: &DocumentId,
+    max_steps: usize,
+) -> Result<Vec<ReplayStep>> {
+    // Copy the database into a temporary file, it gets removed when the directory runs out of
+    // scope
+    let tmp_dir = TempDir::new()?;
+    let database_path = tmp_dir.path().join("replay.sqlite3");
+    info!("Copy database to {}", database_path.display());
+
+    // Use an URI with explicit mode, otherwise SQLite would create the copy of an in-memory
+    // database in memory as well
+    let escaped_path = database_path.display().to_string().replace('\'', "''");
+    query(&format!("VACUUM INTO 'file:{}?mode=rwc'", escaped_path))
+        .execute(source_pool)
+        .await?;
+
+    let pool = connection_pool(&format!("sqlite:{}", database_path.display()), 1, None).await?;
+    run_pending_migrations(&pool).await?;
+
+    let store = SqlStore::new(pool.clone());
+    let schema_provider = SchemaProvider::new(
+        store.get_all_schema().await?,
+        config.allow_schema_ids.clone(),
+    );
+    let context = Context::new(
+        store.clone(),
+        KeyPair::new(),
+        config.clone(),
+        schema_provider,
+    );
+
+    if store
+        .get_operations_by_document_id(document_id)
+        .await?
+        .is_empty()
+    {
+        pool.close().await;
+        bail!("No operations found for document {}", document_id);
+    }
+
+    store.discard_materialized_document(document_id).await?;
+    info!("Removed materialized views of document {}", document_id);
+
+    let mut queue = VecDeque::from([Task::new(
+        "reduce",
+        TaskInput::DocumentId(document_id.clone()),
+    )]);
+    let mut dispatched = HashSet::new();
+    let mut steps = Vec::new();
+
+    while let Some(task) = queue.pop_front() {
+        if steps.len() >= max_steps {
+            warn!("Stop replay after {} steps", max_steps);
+            break;
+        }
+
+        let worker_name = task.worker_name().to_string();
+        let input = task.input().to_owned();
+
+        let outcome = if !dispatched.insert((worker_name.clone(), input.clone())) {
+            warn!("Task {} {} was dispatched again", worker_name, input);
+            ReplayOutcome::Repeated
+        } else {
+            match worker_name.as_str() {
+                "reduce" => {
+                    trace_operations(&store, &input).await?;
+                    let result = reduce_task(context.clone(), input.clone()).await;
+                    trace_views(&store, &input).await?;
+                    outcome(result, &mut queue)
+                }
+                "dependency" => {
+                    let result = dependency_task(context.clone(), input.clone()).await;
+                    outcome(result, &mut queue)
+                }
+                _ => ReplayOutcome::Skipped,
+            }
+        };
+
+        let step = ReplayStep {
+            worker_name,
+            input: input.to_string(),
+            outcome,
+        };
+        info!("{}", step);
+        steps.push(step);
+    }
+
+    pool.close().await;
+
+    Ok(steps)
+}
+
+/// Queue the tasks dispatched by a task and convert its result into an outcome.
+fn outcome(result: TaskResult<TaskInput>, queue: &mut VecDeque<Task<TaskInput>>) -> ReplayOutcome {
+    match result {
+        Ok(tasks) => {
+            let tasks = tasks.unwrap_or_default();
+            let names = tasks
+                .iter()
+                .map(|task| format!("{} {}", task.worker_name(), task.input()))
+                .collect();
+            queue.extend(tasks);
+            ReplayOutcome::Completed(names)
+        }
+        Err(err) => ReplayOutcome::Failed(format!("{:?}", err)),
+    }
+}
+
+/// Log the operations of a document in the order they get applied by the reduce task.
+async fn trace_operations(store: &SqlStore, input: &TaskInput) -> Result<()> {
+    let document_id = match input {
+        TaskInput::DocumentId(document_id) => Some(document_id.to_owned()),
+        TaskInput::DocumentViewId(view_id) => {
+            // Pick one operation from the view to determine the document id
+            let operation_id = view_id.iter().last().expect("View ids are never empty");
+            store.get_document_id_by_operation_id(operation_id).await?
+        }
+    };
+
+    let document_id = match document_id {
+        Some(document_id) => document_id,
+        None => {
+            debug!("No operations found for {}", input);
+            return Ok(());
+        }
+    };
+
+    let operations = store.get_operations_by_document_id(&document_id).await?;
+    let builder = DocumentBuilder::from(&operations);
+    let result = match input {
+        TaskInput::DocumentId(_) => builder.build(),
+        TaskInput::DocumentViewId(view_id) => builder.build_to_view_id(view_id.to_owned()),
+    };
+
+    match result {
+        Ok((_, sorted_operations)) => {
+            for (index, (operation_id, operation, public_key)) in
+                sorted_operations.iter().enumerate()
+            {
+                debug!(
+                    "Apply operation #{} {} ({}) by {}, previous: {}",
+                    index,
+                    operation_id,
+                    operation.action().as_str(),
+                    public_key,
+                    operation
+                        .previous()
+                        .map(|previous| previous.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                );
+                trace!("{:?}", operation.fields());
+            }
+        }
+        Err(err) => debug!("Operations can not be applied: {}", err),
+    }
+
+    Ok(())
+}
+
+/// Log the views of the document which exist in the store after a reduce task.
+async fn trace_views(store: &SqlStore, input: &TaskInput) -> Result<()> {
+    let document = match input {
+        TaskInput::DocumentId(document_id) => store.get_document(document_id).await?,
+        TaskInput::DocumentViewId(view_id) => store.get_document_by_view_id(view_id).await?,
+    };
+
+    match document {
+        Some(document) => debug!(
+            "Stored view {} of document {} (deleted: {})",
+            document.view_id(),
+            document.id(),
+            document.is_deleted()
+        ),
+        None => debug!("No view stored for {}", input),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::storage_provider::traits::DocumentStore;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_id};
+    use rstest::rstest;
+
+    use crate::test_utils::{add_document, add_schema, test_runner, TestNode};
+
+    use super::{replay, ReplayOutcome};
+
+    #[rstest]
+    fn replays_document_materialization(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venue",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Panda Cafe".into())],
+                &key_pair,
+            )
+            .await;
+            let document_id: DocumentId = view_id.to_string().parse().unwrap();
+
+            let steps = replay(
+                &node.context.store.pool,
+                &node.context.config,
+                &document_id,
+                100,
+            )
+            .await
+            .unwrap();
+
+            // Document gets reduced and its dependencies checked
+            assert_eq!(steps[0].worker_name, "reduce");
+            assert!(matches!(steps[0].outcome, ReplayOutcome::Completed(_)));
+            assert_eq!(steps[1].worker_name, "dependency");
+            assert!(steps
+                .iter()
+                .all(|step| !matches!(step.outcome, ReplayOutcome::Failed(_))));
+
+            // The original store stays untouched
+            assert!(node
+                .context
+                .store
+                .get_document(&document_id)
+                .await
+                .unwrap()
+                .is_some());
+
+            // Replaying unknown documents fails
+            assert!(replay(
+                &node.context.store.pool,
+                &node.context.config,
+                &random_document_id(),
+                100
+            )
+            .await
+            .is_err());
+        });
+    }
+}
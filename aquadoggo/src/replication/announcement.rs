@@ -1,18 +1,28 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::ser::SerializeSeq;
 use serde::Serialize;
 
-use crate::replication::{SchemaIdSet, ANNOUNCE_TYPE, REPLICATION_PROTOCOL_VERSION};
+use crate::replication::{
+    Compression, Mode, SchemaIdSet, ANNOUNCE_TYPE, REPLICATION_PROTOCOL_VERSION,
+};
 
 /// U64 timestamp from UNIX epoch until now.
 pub fn now() -> u64 {
+    since_unix_epoch().as_secs()
+}
+
+/// U64 timestamp from UNIX epoch until now in milliseconds.
+pub fn now_millis() -> u64 {
+    since_unix_epoch().as_millis() as u64
+}
+
+fn since_unix_epoch() -> Duration {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("System time invalid, operation system time configured before UNIX epoch")
-        .as_secs()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -23,17 +33,35 @@ pub struct Announcement {
     /// Timestamp of this announcement. Helps to understand if we can override the previous
     /// announcement with a newer one.
     pub timestamp: u64,
+
+    /// List of compression algorithms this peer can use for replication messages, ordered by
+    /// preference.
+    pub supported_compressions: Vec<Compression>,
+
+    /// List of replication modes this peer supports.
+    pub supported_modes: Vec<Mode>,
 }
 
 impl Announcement {
-    pub fn new(supported_schema_ids: SchemaIdSet) -> Self {
+    pub fn new(
+        supported_schema_ids: SchemaIdSet,
+        supported_compressions: Vec<Compression>,
+        supported_modes: Vec<Mode>,
+    ) -> Self {
         Self {
             timestamp: now(),
             supported_schema_ids,
+            supported_compressions,
+            supported_modes,
         }
     }
 }
 
+/// Replication modes of peers which do not announce them.
+pub fn default_supported_modes() -> Vec<Mode> {
+    vec![Mode::LogHeight]
+}
+
 pub type ProtocolVersion = u64;
 
 /// Message which can be used to send announcements over the wire.
@@ -59,11 +87,24 @@ impl Serialize for AnnouncementMessage {
     where
         S: serde::Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(4))?;
+        // Supported compression algorithms and replication modes are optional and only encoded
+        // when they differ from the defaults, this keeps announcements readable for peers which do
+        // not know about them
+        let has_modes = self.1.supported_modes != default_supported_modes();
+        let has_compressions = has_modes || !self.1.supported_compressions.is_empty();
+
+        let len = 4 + usize::from(has_compressions) + usize::from(has_modes);
+        let mut seq = serializer.serialize_seq(Some(len))?;
         seq.serialize_element(&ANNOUNCE_TYPE)?;
         seq.serialize_element(&self.0)?;
         seq.serialize_element(&self.1.timestamp)?;
         seq.serialize_element(&self.1.supported_schema_ids)?;
+        if has_compressions {
+            seq.serialize_element(&self.1.supported_compressions)?;
+        }
+        if has_modes {
+            seq.serialize_element(&self.1.supported_modes)?;
+        }
         seq.end()
     }
 }
@@ -74,17 +115,54 @@ mod tests {
     use p2panda_rs::serde::{serialize_from, serialize_value};
     use rstest::rstest;
 
-    use crate::replication::SchemaIdSet;
+    use crate::replication::{Compression, Mode, SchemaIdSet};
     use crate::test_utils::helpers::random_schema_id_set;
 
-    use super::{Announcement, AnnouncementMessage};
+    use super::{default_supported_modes, Announcement, AnnouncementMessage};
 
     #[rstest]
     fn serialize(#[from(random_schema_id_set)] supported_schema_ids: SchemaIdSet) {
-        let announcement = Announcement::new(supported_schema_ids.clone());
+        let announcement = Announcement::new(
+            supported_schema_ids.clone(),
+            vec![],
+            default_supported_modes(),
+        );
         assert_eq!(
             serialize_from(AnnouncementMessage::new(announcement.clone())),
             serialize_value(cbor!([0, 1, announcement.timestamp, supported_schema_ids]))
         );
+
+        let announcement = Announcement::new(
+            supported_schema_ids.clone(),
+            vec![Compression::Zstd, Compression::Deflate],
+            default_supported_modes(),
+        );
+        assert_eq!(
+            serialize_from(AnnouncementMessage::new(announcement.clone())),
+            serialize_value(cbor!([
+                0,
+                1,
+                announcement.timestamp,
+                supported_schema_ids,
+                [0, 1]
+            ]))
+        );
+
+        let announcement = Announcement::new(
+            supported_schema_ids.clone(),
+            vec![],
+            vec![Mode::SetReconciliation, Mode::LogHeight],
+        );
+        assert_eq!(
+            serialize_from(AnnouncementMessage::new(announcement.clone())),
+            serialize_value(cbor!([
+                0,
+                1,
+                announcement.timestamp,
+                supported_schema_ids,
+                [],
+                [1, 0]
+            ]))
+        );
     }
 }
@@ -0,0 +1,183 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::SchemaId;
+
+use crate::replication::{SchemaIdSet, SessionId};
+
+/// Session id of the first blob request, ids of replication sessions count up from zero and never
+/// reach this range.
+const FIRST_BLOB_SESSION_ID: SessionId = 1 << 63;
+
+/// Returns the schema ids both peers need to support to exchange blobs.
+pub fn blob_schema_ids() -> SchemaIdSet {
+    SchemaIdSet::new(&[SchemaId::Blob(1), SchemaId::BlobPiece(1)])
+}
+
+/// State of a blob requested from a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BlobRequest {
+    /// Requested blob document.
+    document_id: DocumentId,
+
+    /// Number of entries the peer sent us so far.
+    received_entries: usize,
+
+    /// True if any of the received entries could not be ingested.
+    is_failed: bool,
+}
+
+/// Blobs requested from peers which did not respond completely yet.
+///
+/// Clients can request blobs which are not materialized on this node. The node then asks one of
+/// its peers for the entries of the blob document and its pieces, they are sent outside of
+/// replication sessions but under the session id of the request and get ingested like any other
+/// replicated entry.
+#[derive(Debug)]
+pub struct BlobRequests<P> {
+    requests: HashMap<(P, SessionId), BlobRequest>,
+    next_session_id: SessionId,
+}
+
+impl<P> BlobRequests<P>
+where
+    P: Clone + Hash + Eq,
+{
+    pub fn new() -> Self {
+        Self {
+            requests: HashMap::new(),
+            next_session_id: FIRST_BLOB_SESSION_ID,
+        }
+    }
+
+    /// Returns true if this blob is currently requested from any peer.
+    pub fn is_requested(&self, document_id: &DocumentId) -> bool {
+        self.requests
+            .values()
+            .any(|request| &request.document_id == document_id)
+    }
+
+    /// Returns true if messages of this peer with the given session id belong to a blob request.
+    pub fn contains(&self, peer: &P, session_id: SessionId) -> bool {
+        self.requests.contains_key(&(peer.clone(), session_id))
+    }
+
+    /// Register a blob requested from a peer, returns the session id of the request.
+    pub fn insert(&mut self, peer: &P, document_id: &DocumentId) -> SessionId {
+        let session_id = self.next_session_id;
+        self.next_session_id = self
+            .next_session_id
+            .wrapping_add(1)
+            .max(FIRST_BLOB_SESSION_ID);
+
+        self.requests.insert(
+            (peer.clone(), session_id),
+            BlobRequest {
+                document_id: document_id.to_owned(),
+                received_entries: 0,
+                is_failed: false,
+            },
+        );
+
+        session_id
+    }
+
+    /// Count an entry the peer sent in response to a request, `is_ingested` is false if it could
+    /// not be ingested.
+    pub fn on_entry(&mut self, peer: &P, session_id: SessionId, is_ingested: bool) {
+        if let Some(request) = self.requests.get_mut(&(peer.clone(), session_id)) {
+            request.received_entries += 1;
+            request.is_failed |= !is_ingested;
+        }
+    }
+
+    /// Finish a request after the peer sent all entries it had.
+    ///
+    /// Returns the requested blob and true if the peer sent entries which were all ingested.
+    pub fn remove(&mut self, peer: &P, session_id: SessionId) -> Option<(DocumentId, bool)> {
+        self.requests
+            .remove(&(peer.clone(), session_id))
+            .map(|request| {
+                let is_complete = request.received_entries > 0 && !request.is_failed;
+                (request.document_id, is_complete)
+            })
+    }
+
+    /// Drop all requests sent to this peer, returns the requested blobs.
+    pub fn remove_peer(&mut self, peer: &P) -> Vec<DocumentId> {
+        let mut document_ids = Vec::new();
+
+        self.requests.retain(|(requested_peer, _), request| {
+            if requested_peer == peer {
+                document_ids.push(request.document_id.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        document_ids
+    }
+}
+
+impl<P> Default for BlobRequests<P>
+where
+    P: Clone + Hash + Eq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::test_utils::fixtures::random_document_id;
+    use rstest::rstest;
+
+    use super::BlobRequests;
+
+    #[rstest]
+    fn tracks_requests(
+        #[from(random_document_id)] document_id: DocumentId,
+        #[from(random_document_id)] other_document_id: DocumentId,
+    ) {
+        let mut requests = BlobRequests::new();
+
+        let session_id = requests.insert(&"peer_a", &document_id);
+        let other_session_id = requests.insert(&"peer_b", &other_document_id);
+        assert_ne!(session_id, other_session_id);
+        assert!(requests.is_requested(&document_id));
+        assert!(requests.contains(&"peer_a", session_id));
+        assert!(!requests.contains(&"peer_b", session_id));
+
+        // Requests are only complete when the peer sent entries which could all be ingested
+        requests.on_entry(&"peer_a", session_id, true);
+        assert_eq!(
+            requests.remove(&"peer_a", session_id),
+            Some((document_id.clone(), true))
+        );
+        assert!(!requests.is_requested(&document_id));
+
+        let session_id = requests.insert(&"peer_a", &document_id);
+        requests.on_entry(&"peer_a", session_id, true);
+        requests.on_entry(&"peer_a", session_id, false);
+        assert_eq!(
+            requests.remove(&"peer_a", session_id),
+            Some((document_id.clone(), false))
+        );
+
+        let session_id = requests.insert(&"peer_a", &document_id);
+        assert_eq!(
+            requests.remove(&"peer_a", session_id),
+            Some((document_id, false))
+        );
+
+        // Requests get dropped when the connection to the peer closes
+        assert_eq!(requests.remove_peer(&"peer_b"), vec![other_document_id]);
+        assert_eq!(requests.remove(&"peer_b", other_session_id), None);
+    }
+}
@@ -0,0 +1,433 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::{self, Display};
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::operation::EncodedOperation;
+use p2panda_rs::Human;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::replication::errors::CompressionError;
+use crate::replication::Message;
+
+/// Compression algorithms this node supports, ordered by preference.
+pub const SUPPORTED_COMPRESSIONS: [Compression; 2] = [Compression::Zstd, Compression::Deflate];
+
+/// Maximum size in bytes of uncompressed entries and operations inside of one batch.
+///
+/// Entries exceeding this size on their own are sent uncompressed.
+pub const MAX_BATCH_SIZE: usize = 512 * 1024;
+
+/// Maximum size in bytes we accept when decompressing a batch of entries.
+///
+/// This accounts for the CBOR overhead around the batched entries and protects us against
+/// payloads unfolding into unreasonably large buffers.
+const MAX_DECOMPRESSED_SIZE: usize = 2 * MAX_BATCH_SIZE;
+
+/// Compression level used for zstd, the library default offers a good trade-off between speed and
+/// compression ratio.
+const ZSTD_LEVEL: i32 = 0;
+
+/// Entry with its (optional) operation, as transmitted inside of a compressed batch.
+pub type EntryWithOperation = (EncodedEntry, Option<EncodedOperation>);
+
+/// Compression algorithm for entries exchanged during replication.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Compression {
+    /// Zstandard compression.
+    Zstd,
+
+    /// Deflate compression.
+    Deflate,
+
+    /// Algorithm announced by a remote peer which is not known to us.
+    Unknown,
+}
+
+impl Compression {
+    /// Returns the name of this compression algorithm.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Compression::Zstd => "zstd",
+            Compression::Deflate => "deflate",
+            Compression::Unknown => "unknown",
+        }
+    }
+
+    /// Returns the integer representing this compression algorithm in the wire format.
+    ///
+    /// Unknown algorithms announced by remote peers have no representation.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Compression::Zstd => Some(0),
+            Compression::Deflate => Some(1),
+            Compression::Unknown => None,
+        }
+    }
+
+    /// Returns the first compression algorithm of our local preferences which is also supported
+    /// by the remote peer.
+    pub fn negotiate(local: &[Compression], remote: &[Compression]) -> Option<Compression> {
+        local
+            .iter()
+            .find(|compression| {
+                **compression != Compression::Unknown && remote.contains(compression)
+            })
+            .copied()
+    }
+
+    /// Compress bytes with this algorithm.
+    pub fn compress(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::Zstd => zstd::stream::encode_all(bytes, ZSTD_LEVEL)
+                .expect("Compressing into memory buffer should not fail"),
+            Compression::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder
+                    .write_all(bytes)
+                    .expect("Compressing into memory buffer should not fail");
+                encoder
+                    .finish()
+                    .expect("Compressing into memory buffer should not fail")
+            }
+            Compression::Unknown => unreachable!("Can't compress with unknown algorithm"),
+        }
+    }
+
+    /// Decompress bytes with this algorithm, failing if the result exceeds the given maximum
+    /// size.
+    pub fn decompress(&self, bytes: &[u8], max_size: usize) -> Result<Vec<u8>, CompressionError> {
+        let reader: Box<dyn Read> = match self {
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(bytes)?),
+            Compression::Deflate => Box::new(DeflateDecoder::new(bytes)),
+            Compression::Unknown => return Err(CompressionError::UnknownAlgorithm),
+        };
+
+        // Read one byte more than allowed to detect oversized payloads
+        let mut buffer = Vec::new();
+        reader.take(max_size as u64 + 1).read_to_end(&mut buffer)?;
+
+        if buffer.len() > max_size {
+            return Err(CompressionError::TooLarge(max_size));
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl From<u64> for Compression {
+    fn from(value: u64) -> Self {
+        match value {
+            0 => Compression::Zstd,
+            1 => Compression::Deflate,
+            _ => Compression::Unknown,
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = CompressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "zstd" => Ok(Compression::Zstd),
+            "deflate" => Ok(Compression::Deflate),
+            _ => Err(CompressionError::UnknownAlgorithm),
+        }
+    }
+}
+
+impl Serialize for Compression {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.as_u64() {
+            Some(value) => serializer.serialize_u64(value),
+            None => Err(S::Error::custom(
+                "can't serialize unknown compression algorithm",
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Compression {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let compression = u64::deserialize(deserializer)?;
+        Ok(compression.into())
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Human for Compression {
+    fn display(&self) -> String {
+        self.as_str().to_owned()
+    }
+}
+
+fn entry_size(entry_bytes: &EncodedEntry, operation_bytes: &Option<EncodedOperation>) -> usize {
+    let operation_size = operation_bytes.as_ref().map_or(0, |bytes| bytes.size());
+    (entry_bytes.size() + operation_size) as usize
+}
+
+/// Compress all currently batched entries into one message and clear the batch.
+fn flush_batch(
+    compression: Compression,
+    batch: &mut Vec<EntryWithOperation>,
+    batch_size: &mut usize,
+    result: &mut Vec<Message>,
+) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(batch, &mut bytes)
+        .expect("Encoding entries into memory buffer should not fail");
+    result.push(Message::Entries(compression, compression.compress(&bytes)));
+
+    batch.clear();
+    *batch_size = 0;
+}
+
+/// Replace consecutive `Entry` messages with compressed `Entries` batches of at most the given
+/// size in bytes.
+///
+/// The order of all messages is kept intact. Entries which are larger than the given batch size
+/// on their own are sent in a batch by themselves, entries larger than `MAX_BATCH_SIZE` are
+/// passed through uncompressed. Nothing gets compressed with an unknown algorithm.
+pub fn compress_entries(
+    compression: Compression,
+    messages: Vec<Message>,
+    max_batch_size: usize,
+) -> Vec<Message> {
+    if compression == Compression::Unknown {
+        return messages;
+    }
+
+    let max_batch_size = max_batch_size.min(MAX_BATCH_SIZE);
+    let mut result = Vec::new();
+    let mut batch: Vec<EntryWithOperation> = Vec::new();
+    let mut batch_size = 0;
+
+    for message in messages {
+        match message {
+            Message::Entry(entry_bytes, operation_bytes)
+                if entry_size(&entry_bytes, &operation_bytes) <= MAX_BATCH_SIZE =>
+            {
+                let size = entry_size(&entry_bytes, &operation_bytes);
+
+                if batch_size + size > max_batch_size {
+                    flush_batch(compression, &mut batch, &mut batch_size, &mut result);
+                }
+
+                batch_size += size;
+                batch.push((entry_bytes, operation_bytes));
+            }
+            message => {
+                flush_batch(compression, &mut batch, &mut batch_size, &mut result);
+                result.push(message);
+            }
+        }
+    }
+
+    flush_batch(compression, &mut batch, &mut batch_size, &mut result);
+
+    result
+}
+
+/// Decompress and decode a batch of entries received with an `Entries` message.
+pub fn decompress_entries(
+    compression: Compression,
+    bytes: &[u8],
+) -> Result<Vec<EntryWithOperation>, CompressionError> {
+    let bytes = compression.decompress(bytes, MAX_DECOMPRESSED_SIZE)?;
+    ciborium::de::from_reader(bytes.as_slice())
+        .map_err(|err| CompressionError::Decode(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ciborium::cbor;
+    use p2panda_rs::entry::EncodedEntry;
+    use p2panda_rs::operation::EncodedOperation;
+    use p2panda_rs::serde::{deserialize_into, serialize_from, serialize_value};
+    use p2panda_rs::test_utils::fixtures::{encoded_entry, encoded_operation};
+    use rstest::rstest;
+
+    use crate::replication::errors::CompressionError;
+    use crate::replication::Message;
+
+    use super::{
+        compress_entries, decompress_entries, entry_size, Compression, MAX_BATCH_SIZE,
+        SUPPORTED_COMPRESSIONS,
+    };
+
+    #[test]
+    fn u64_representation() {
+        assert_eq!(Compression::Zstd.as_u64(), Some(0));
+        assert_eq!(Compression::Deflate.as_u64(), Some(1));
+        assert_eq!(Compression::Unknown.as_u64(), None);
+    }
+
+    #[test]
+    fn serialize() {
+        let bytes = serialize_from(vec![Compression::Zstd, Compression::Deflate]);
+        assert_eq!(bytes, serialize_value(cbor!([0, 1])));
+
+        // Unknown algorithms fail to serialize instead of panicking
+        let mut bytes = Vec::new();
+        assert!(ciborium::ser::into_writer(&Compression::Unknown, &mut bytes).is_err());
+    }
+
+    #[test]
+    fn deserialize() {
+        let compressions: Vec<Compression> =
+            deserialize_into(&serialize_value(cbor!([1, 0, 12]))).unwrap();
+        assert_eq!(
+            compressions,
+            vec![
+                Compression::Deflate,
+                Compression::Zstd,
+                Compression::Unknown
+            ]
+        );
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Compression::from_str("zstd").unwrap(), Compression::Zstd);
+        assert_eq!(
+            Compression::from_str("deflate").unwrap(),
+            Compression::Deflate
+        );
+        assert!(Compression::from_str("brotli").is_err());
+    }
+
+    #[rstest]
+    #[case(&SUPPORTED_COMPRESSIONS, &SUPPORTED_COMPRESSIONS, Some(Compression::Zstd))]
+    #[case(&SUPPORTED_COMPRESSIONS, &[Compression::Deflate], Some(Compression::Deflate))]
+    #[case(&[Compression::Deflate], &SUPPORTED_COMPRESSIONS, Some(Compression::Deflate))]
+    #[case(&SUPPORTED_COMPRESSIONS, &[Compression::Unknown], None)]
+    #[case(&SUPPORTED_COMPRESSIONS, &[], None)]
+    #[case(&[], &SUPPORTED_COMPRESSIONS, None)]
+    fn negotiate(
+        #[case] local: &[Compression],
+        #[case] remote: &[Compression],
+        #[case] expected: Option<Compression>,
+    ) {
+        assert_eq!(Compression::negotiate(local, remote), expected);
+    }
+
+    #[rstest]
+    #[case(Compression::Zstd)]
+    #[case(Compression::Deflate)]
+    fn compress_and_decompress(#[case] compression: Compression) {
+        let bytes = "Hello, Panda! ".repeat(100).into_bytes();
+
+        let compressed = compression.compress(&bytes);
+        assert!(compressed.len() < bytes.len() / 2);
+        assert_eq!(
+            compression.decompress(&compressed, bytes.len()).unwrap(),
+            bytes
+        );
+
+        // Payloads exceeding the maximum size get rejected
+        assert!(matches!(
+            compression.decompress(&compressed, bytes.len() - 1),
+            Err(CompressionError::TooLarge(_))
+        ));
+    }
+
+    #[rstest]
+    #[case(Compression::Zstd)]
+    #[case(Compression::Deflate)]
+    fn batch_entries(
+        #[case] compression: Compression,
+        encoded_entry: EncodedEntry,
+        encoded_operation: EncodedOperation,
+    ) {
+        let entry = Message::Entry(encoded_entry.clone(), Some(encoded_operation.clone()));
+        let messages = vec![
+            Message::Have(vec![]),
+            entry.clone(),
+            entry.clone(),
+            Message::SyncDone(false),
+        ];
+
+        let compressed = compress_entries(compression, messages, MAX_BATCH_SIZE);
+        assert_eq!(compressed.len(), 3);
+        assert_eq!(compressed[0], Message::Have(vec![]));
+        assert_eq!(compressed[2], Message::SyncDone(false));
+
+        match &compressed[1] {
+            Message::Entries(batch_compression, bytes) => {
+                assert_eq!(batch_compression, &compression);
+                assert_eq!(
+                    decompress_entries(compression, bytes).unwrap(),
+                    vec![
+                        (encoded_entry.clone(), Some(encoded_operation.clone())),
+                        (encoded_entry, Some(encoded_operation))
+                    ]
+                );
+            }
+            _ => panic!("Expected compressed entries"),
+        }
+    }
+
+    #[rstest]
+    fn oversized_entries_stay_uncompressed(encoded_entry: EncodedEntry) {
+        let large_operation = EncodedOperation::from_bytes(&vec![0; MAX_BATCH_SIZE]);
+        let messages = vec![Message::Entry(encoded_entry, Some(large_operation))];
+
+        assert_eq!(
+            compress_entries(Compression::Zstd, messages.clone(), MAX_BATCH_SIZE),
+            messages
+        );
+    }
+
+    #[rstest]
+    fn smaller_batches(encoded_entry: EncodedEntry, encoded_operation: EncodedOperation) {
+        let entry = Message::Entry(encoded_entry.clone(), Some(encoded_operation.clone()));
+        let size = entry_size(&encoded_entry, &Some(encoded_operation));
+
+        // Two entries fit into one batch
+        let compressed = compress_entries(Compression::Zstd, vec![entry.clone(); 4], size * 2);
+        assert_eq!(compressed.len(), 2);
+
+        // Entries exceeding the batch size on their own are still compressed
+        let compressed = compress_entries(Compression::Zstd, vec![entry; 2], size / 2);
+        assert_eq!(compressed.len(), 2);
+        assert!(compressed
+            .iter()
+            .all(|message| matches!(message, Message::Entries(_, _))));
+    }
+
+    #[test]
+    fn reject_invalid_payloads() {
+        assert!(decompress_entries(Compression::Deflate, &[1, 2, 3]).is_err());
+        assert!(decompress_entries(Compression::Unknown, &[1, 2, 3]).is_err());
+
+        let not_entries = Compression::Zstd.compress(&serialize_value(cbor!("panda")));
+        assert!(matches!(
+            decompress_entries(Compression::Zstd, &not_entries),
+            Err(CompressionError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use libp2p::PeerId;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::replication::errors::ReplicationError;
+
+/// Direction in which entries are exchanged during a replication session, seen from the peer
+/// which uses it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Direction {
+    /// Send entries to and receive entries from the remote peer.
+    #[default]
+    Both,
+
+    /// Only send entries to the remote peer, never receive any from it.
+    Push,
+
+    /// Only receive entries from the remote peer, never send any to it.
+    Pull,
+
+    /// Direction requested by a remote peer which is not known to us.
+    Unknown,
+}
+
+impl Direction {
+    /// Returns the name of this replication direction.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Direction::Both => "both",
+            Direction::Push => "push",
+            Direction::Pull => "pull",
+            Direction::Unknown => "unknown",
+        }
+    }
+
+    /// Returns the identifier of this replication direction used on the wire.
+    pub fn as_u64(&self) -> u64 {
+        match self {
+            Direction::Both => 0,
+            Direction::Push => 1,
+            Direction::Pull => 2,
+            Direction::Unknown => unreachable!("Can't create an unknown replication direction"),
+        }
+    }
+
+    /// Returns true if entries are sent to the remote peer.
+    pub fn is_pushing(&self) -> bool {
+        matches!(self, Direction::Both | Direction::Push)
+    }
+
+    /// Returns true if entries are received from the remote peer.
+    pub fn is_pulling(&self) -> bool {
+        matches!(self, Direction::Both | Direction::Pull)
+    }
+
+    /// Returns the direction seen from the remote peer.
+    pub fn reverse(&self) -> Self {
+        match self {
+            Direction::Push => Direction::Pull,
+            Direction::Pull => Direction::Push,
+            direction => *direction,
+        }
+    }
+
+    /// Combine our direction with the one requested by the remote peer.
+    ///
+    /// Entries only flow where both peers allow it. Returns `None` when no entries would be
+    /// exchanged at all.
+    pub fn negotiate(&self, remote: &Direction) -> Option<Direction> {
+        let remote = remote.reverse();
+
+        let is_pushing = self.is_pushing() && remote.is_pushing();
+        let is_pulling = self.is_pulling() && remote.is_pulling();
+
+        match (is_pushing, is_pulling) {
+            (true, true) => Some(Direction::Both),
+            (true, false) => Some(Direction::Push),
+            (false, true) => Some(Direction::Pull),
+            (false, false) => None,
+        }
+    }
+}
+
+impl From<u64> for Direction {
+    fn from(value: u64) -> Self {
+        match value {
+            0 => Direction::Both,
+            1 => Direction::Push,
+            2 => Direction::Pull,
+            _ => Direction::Unknown,
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = ReplicationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "both" => Ok(Direction::Both),
+            "push" => Ok(Direction::Push),
+            "pull" => Ok(Direction::Pull),
+            _ => Err(ReplicationError::UnsupportedDirection),
+        }
+    }
+}
+
+impl Serialize for Direction {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u64(self.as_u64())
+    }
+}
+
+impl<'de> Deserialize<'de> for Direction {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let direction = u64::deserialize(deserializer)?;
+        Ok(direction.into())
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Replication direction used with a remote peer.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DirectionPreference {
+    /// Peer this preference applies to, applies to all peers when not set.
+    pub peer_id: Option<PeerId>,
+
+    /// Direction in which entries are exchanged with the peer.
+    pub direction: Direction,
+}
+
+/// Returns the direction of the first preference matching the peer, otherwise entries are
+/// exchanged in both directions.
+pub fn select_direction(preferences: &[DirectionPreference], peer_id: &PeerId) -> Direction {
+    preferences
+        .iter()
+        .find(|preference| preference.peer_id.is_none_or(|id| &id == peer_id))
+        .map(|preference| preference.direction)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ciborium::cbor;
+    use libp2p::PeerId;
+    use p2panda_rs::serde::{deserialize_into, serialize_from, serialize_value};
+    use rstest::rstest;
+
+    use super::{select_direction, Direction, DirectionPreference};
+
+    #[test]
+    fn serialize() {
+        assert_eq!(serialize_from(Direction::Pull), vec![2]);
+
+        let direction: Direction = deserialize_into(&serialize_value(cbor!(1))).unwrap();
+        assert_eq!(direction, Direction::Push);
+
+        let direction: Direction = deserialize_into(&serialize_value(cbor!(12))).unwrap();
+        assert_eq!(direction, Direction::Unknown);
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Direction::from_str("both").unwrap(), Direction::Both);
+        assert_eq!(Direction::from_str("push").unwrap(), Direction::Push);
+        assert_eq!(Direction::from_str("pull").unwrap(), Direction::Pull);
+        assert!(Direction::from_str("unknown").is_err());
+    }
+
+    #[rstest]
+    #[case(Direction::Both, Direction::Both, Some(Direction::Both))]
+    #[case(Direction::Both, Direction::Push, Some(Direction::Pull))]
+    #[case(Direction::Both, Direction::Pull, Some(Direction::Push))]
+    #[case(Direction::Pull, Direction::Both, Some(Direction::Pull))]
+    #[case(Direction::Pull, Direction::Push, Some(Direction::Pull))]
+    #[case(Direction::Push, Direction::Pull, Some(Direction::Push))]
+    #[case(Direction::Push, Direction::Push, None)]
+    #[case(Direction::Pull, Direction::Pull, None)]
+    #[case(Direction::Both, Direction::Unknown, None)]
+    fn negotiate(
+        #[case] local: Direction,
+        #[case] remote: Direction,
+        #[case] expected: Option<Direction>,
+    ) {
+        assert_eq!(local.negotiate(&remote), expected);
+    }
+
+    #[test]
+    fn select_direction_by_peer() {
+        let peer_id = PeerId::random();
+        let other_peer_id = PeerId::random();
+
+        let preferences = vec![
+            DirectionPreference {
+                peer_id: Some(other_peer_id),
+                direction: Direction::Push,
+            },
+            DirectionPreference {
+                peer_id: None,
+                direction: Direction::Pull,
+            },
+        ];
+
+        assert_eq!(select_direction(&[], &peer_id), Direction::Both);
+        assert_eq!(select_direction(&preferences, &peer_id), Direction::Pull);
+        assert_eq!(
+            select_direction(&preferences, &other_peer_id),
+            Direction::Push
+        );
+    }
+}
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
 use thiserror::Error;
 
 use crate::replication::SchemaIdSet;
@@ -10,9 +11,18 @@ pub enum ReplicationError {
     #[error("Remote peer requested unsupported replication mode")]
     UnsupportedMode,
 
+    #[error("Remote peer requested unsupported replication direction")]
+    UnsupportedDirection,
+
     #[error("Sync request received containing unsupported target set")]
     UnsupportedTargetSet,
 
+    #[error("Sync request received while replication of target set is paused")]
+    Paused,
+
+    #[error("Sync request received from peer without allowed operator key")]
+    UnknownOperator,
+
     #[error("Duplicate session error: {0}")]
     DuplicateSession(#[from] DuplicateSessionRequestError),
 
@@ -30,6 +40,9 @@ pub enum ReplicationError {
 
     #[error("Incoming data could not be ingested: {0}")]
     Validation(#[from] IngestError),
+
+    #[error("Incoming compressed entries could not be read: {0}")]
+    Compression(#[from] CompressionError),
 }
 
 #[derive(Error, Debug)]
@@ -41,6 +54,9 @@ pub enum IngestError {
     #[error("Schema not found")]
     SchemaNotFound,
 
+    #[error(transparent)]
+    ConstraintViolation(#[from] crate::schema::ConstraintViolation),
+
     #[error(transparent)]
     Domain(#[from] p2panda_rs::api::DomainError),
 
@@ -52,6 +68,27 @@ pub enum IngestError {
 
     #[error("Duplicate entry received: {0}")]
     DuplicateEntry(Hash),
+
+    #[error("Entry {0} conflicts with another entry of the same author in this log")]
+    ForkedLog(Hash),
+
+    #[error("Operation of entry {0} by {1} is not encoded in canonical CBOR")]
+    NonCanonicalOperation(Hash, PublicKey),
+}
+
+#[derive(Error, Debug)]
+pub enum CompressionError {
+    #[error("Unknown compression algorithm")]
+    UnknownAlgorithm,
+
+    #[error("Decompressed payload exceeds maximum size of {0} bytes")]
+    TooLarge(usize),
+
+    #[error("Decompressing payload failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Decoding decompressed entries failed: {0}")]
+    Decode(String),
 }
 
 #[derive(Error, Debug)]
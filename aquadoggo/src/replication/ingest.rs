@@ -1,12 +1,14 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use log::trace;
+use log::{trace, warn};
 use p2panda_rs::api::publish;
-use p2panda_rs::entry::traits::AsEncodedEntry;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
 use p2panda_rs::entry::EncodedEntry;
 use p2panda_rs::operation::decode::decode_operation;
-use p2panda_rs::operation::traits::Schematic;
-use p2panda_rs::operation::{EncodedOperation, OperationId};
+use p2panda_rs::operation::traits::{Actionable, Schematic};
+use p2panda_rs::operation::{EncodedOperation, OperationAction, OperationId};
 use p2panda_rs::storage_provider::traits::EntryStore;
 
 use crate::bus::{ServiceMessage, ServiceSender};
@@ -49,6 +51,18 @@ impl SyncIngest {
 
         let plain_operation = decode_operation(encoded_operation)?;
 
+        // Reject lenient encodings of operations if the node requires canonical ones
+        if !self
+            .schema_provider
+            .accepts_encoding(&plain_operation, encoded_operation)
+        {
+            let entry = decode_entry(encoded_entry)?;
+            return Err(IngestError::NonCanonicalOperation(
+                encoded_entry.hash(),
+                entry.public_key().to_owned(),
+            ));
+        }
+
         // If the node has been configured with an allow-list of supported schema ids, check that
         // the sent operation follows one of our supported schema
         if self.schema_provider.is_allow_list_active()
@@ -68,18 +82,61 @@ impl SyncIngest {
             .await
             .ok_or_else(|| IngestError::SchemaNotFound)?;
 
+        // Reject operations violating the field constraints of their schema, otherwise invalid
+        // data could still arrive from other nodes
+        self.schema_provider
+            .check_constraints(&schema, &plain_operation)?;
+
         /////////////////////////////////////
         // PUBLISH THE ENTRY AND OPERATION //
         /////////////////////////////////////
 
-        let _ = publish(
+        if let Err(err) = publish(
             store,
             &schema,
             encoded_entry,
             &plain_operation,
             encoded_operation,
         )
-        .await?;
+        .await
+        {
+            // Check if the entry got rejected because it conflicts with the log we already hold
+            let entry = decode_entry(encoded_entry)?;
+            let fork = store
+                .detect_log_fork(&entry, &encoded_entry.hash())
+                .await
+                .expect("Fatal database error");
+
+            if fork.is_some() {
+                warn!(
+                    "Detected fork in log {} of {}",
+                    entry.log_id().as_u64(),
+                    entry.public_key()
+                );
+
+                let _ = self.tx.send(ServiceMessage::LogForked(
+                    entry.public_key().to_owned(),
+                    entry.log_id().to_owned(),
+                ));
+
+                return Err(IngestError::ForkedLog(encoded_entry.hash()));
+            }
+
+            return Err(err.into());
+        }
+
+        let operation_id: OperationId = encoded_entry.hash().into();
+
+        // Documents of moderated schemas created by other nodes wait for approval, hold them back
+        // before they get materialized
+        if plain_operation.action() == OperationAction::Create
+            && self.schema_provider.is_moderated(schema.id())
+        {
+            store
+                .hold_document(&DocumentId::new(&operation_id))
+                .await
+                .expect("Fatal database error");
+        }
 
         ////////////////////////////////////////
         // SEND THE OPERATION TO MATERIALIZER //
@@ -88,8 +145,6 @@ impl SyncIngest {
         // Send new operation on service communication bus, this will arrive eventually at
         // the materializer service
 
-        let operation_id: OperationId = encoded_entry.hash().into();
-
         if self
             .tx
             .send(ServiceMessage::NewOperation(operation_id))
@@ -105,7 +160,11 @@ impl SyncIngest {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use p2panda_rs::document::DocumentId;
     use p2panda_rs::entry::EncodedEntry;
+    use p2panda_rs::identity::KeyPair;
     use p2panda_rs::operation::EncodedOperation;
     use p2panda_rs::schema::Schema;
     use p2panda_rs::test_utils::fixtures::{encoded_entry, encoded_operation, schema};
@@ -114,8 +173,9 @@ mod tests {
 
     use crate::replication::errors::IngestError;
     use crate::replication::SyncIngest;
+    use crate::test_utils::helpers::non_canonical_operation;
     use crate::test_utils::{test_runner_with_manager, TestNodeManager};
-    use crate::{AllowList, Configuration};
+    use crate::{AllowList, Configuration, FieldConstraint, SchemaSettings, SettingValue};
 
     #[rstest]
     fn reject_duplicate_entries(
@@ -170,4 +230,126 @@ mod tests {
             assert!(result.is_ok());
         });
     }
+
+    #[rstest]
+    fn reject_constraint_violations(
+        schema: Schema,
+        encoded_entry: EncodedEntry,
+        encoded_operation: EncodedOperation,
+    ) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let mut constraint = FieldConstraint::new(schema.id(), "username");
+            constraint.max_length = Some(2);
+            let config = Configuration {
+                field_constraints: vec![constraint],
+                ..Configuration::default()
+            };
+            let node = manager.create_with_config(config).await;
+
+            let _ = node.context.schema_provider.update(schema.clone()).await;
+            let (tx, _rx) = broadcast::channel(8);
+            let ingest = SyncIngest::new(node.context.schema_provider.clone(), tx.clone());
+
+            let result = ingest
+                .handle_entry(&node.context.store, &encoded_entry, &encoded_operation)
+                .await;
+
+            assert!(matches!(result, Err(IngestError::ConstraintViolation(_))));
+        });
+    }
+
+    #[rstest]
+    fn reject_non_canonical_operations(schema: Schema, encoded_operation: EncodedOperation) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let config = Configuration {
+                require_canonical_encoding: true,
+                ..Configuration::default()
+            };
+            let node = manager.create_with_config(config).await;
+
+            let _ = node.context.schema_provider.update(schema).await;
+            let (tx, _rx) = broadcast::channel(8);
+            let ingest = SyncIngest::new(node.context.schema_provider.clone(), tx.clone());
+
+            let encoded_operation = non_canonical_operation(&encoded_operation);
+            let encoded_entry =
+                encoded_entry(1, 0, None, None, encoded_operation.clone(), KeyPair::new());
+
+            let result = ingest
+                .handle_entry(&node.context.store, &encoded_entry, &encoded_operation)
+                .await;
+
+            assert!(matches!(
+                result,
+                Err(IngestError::NonCanonicalOperation(_, _))
+            ));
+        });
+    }
+
+    #[rstest]
+    fn hold_documents_of_moderated_schemas(
+        schema: Schema,
+        encoded_entry: EncodedEntry,
+        encoded_operation: EncodedOperation,
+    ) {
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let mut settings = SchemaSettings::default();
+            settings.insert("moderated", SettingValue::Boolean(true));
+            let config = Configuration {
+                schema_settings: HashMap::from([(schema.id().clone(), settings)]),
+                ..Configuration::default()
+            };
+            let node = manager.create_with_config(config).await;
+
+            let _ = node.context.schema_provider.update(schema).await;
+            let (tx, _rx) = broadcast::channel(8);
+            let ingest = SyncIngest::new(node.context.schema_provider.clone(), tx.clone());
+
+            let result = ingest
+                .handle_entry(&node.context.store, &encoded_entry, &encoded_operation)
+                .await;
+            assert!(result.is_ok());
+
+            let document_id = DocumentId::new(&encoded_entry.hash().into());
+            assert!(node
+                .context
+                .store
+                .is_document_held(&document_id)
+                .await
+                .unwrap());
+        });
+    }
+
+    #[cfg(feature = "fault-injection")]
+    #[rstest]
+    fn retry_after_failed_insert(
+        schema: Schema,
+        encoded_entry: EncodedEntry,
+        encoded_operation: EncodedOperation,
+    ) {
+        use crate::faults::FaultPoint;
+        use crate::test_utils::fail_once;
+
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let node = manager.create().await;
+            let _ = node.context.schema_provider.update(schema).await;
+
+            let (tx, _rx) = broadcast::channel(8);
+            let ingest = SyncIngest::new(node.context.schema_provider.clone(), tx.clone());
+
+            fail_once(node.context.store.faults(), FaultPoint::InsertEntry);
+
+            let result = ingest
+                .handle_entry(&node.context.store, &encoded_entry, &encoded_operation)
+                .await;
+            assert!(matches!(result, Err(IngestError::Domain(_))));
+
+            // Nothing was persisted, the entry can be ingested again
+            let result = ingest
+                .handle_entry(&node.context.store, &encoded_entry, &encoded_operation)
+                .await;
+            assert!(result.is_ok());
+            assert_eq!(node.context.store.faults().hits(FaultPoint::InsertEntry), 2);
+        });
+    }
 }
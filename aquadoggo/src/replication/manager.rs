@@ -1,26 +1,37 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::hash::Hash;
 
 use anyhow::Result;
 use log::{debug, trace, warn};
+use p2panda_rs::api::{DomainError, ValidationError};
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
 use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::Schematic;
 use p2panda_rs::operation::EncodedOperation;
+use p2panda_rs::schema::SchemaId;
 use p2panda_rs::Human;
 
 use crate::db::SqlStore;
 use crate::replication::errors::{DuplicateSessionRequestError, IngestError, ReplicationError};
+use crate::replication::strategies::ReceivedEntry;
 use crate::replication::{
-    Message, Mode, SchemaIdSet, Session, SessionId, SessionState, SyncIngest, SyncMessage,
+    decompress_entries, Compression, Direction, Message, Mode, RangeScheduler, SchemaIdSet,
+    Session, SessionId, SessionState, SyncIngest, SyncMessage,
 };
 
 pub const INITIAL_SESSION_ID: SessionId = 0;
 
-pub const SUPPORTED_MODES: [Mode; 1] = [Mode::LogHeight];
+pub const SUPPORTED_MODES: [Mode; 2] = [Mode::LogHeight, Mode::LogRange];
 
 pub const SUPPORT_LIVE_MODE: bool = false;
 
+/// Maximum number of received entries held back until their schema got materialized.
+const MAX_HELD_ENTRIES: usize = 4096;
+
 fn to_sync_messages(session_id: SessionId, messages: Vec<Message>) -> Vec<SyncMessage> {
     messages
         .into_iter()
@@ -32,6 +43,9 @@ fn to_sync_messages(session_id: SessionId, messages: Vec<Message>) -> Vec<SyncMe
 pub struct SyncResult {
     pub messages: Vec<SyncMessage>,
     pub is_done: bool,
+
+    /// Schemas of received entries which are not materialized on this node yet.
+    pub missing_schema_ids: Vec<SchemaId>,
 }
 
 impl SyncResult {
@@ -39,6 +53,7 @@ impl SyncResult {
         Self {
             is_done,
             messages: to_sync_messages(session_id, messages),
+            missing_schema_ids: Vec::new(),
         }
     }
 }
@@ -49,6 +64,16 @@ pub struct SyncManager<P> {
     ingest: SyncIngest,
     local_peer: P,
     sessions: HashMap<P, Vec<Session>>,
+
+    /// Ranges of logs requested from remote peers in `log-range` sessions.
+    scheduler: RangeScheduler,
+
+    /// Received entries of schemas which are not materialized yet, they are ingested as soon as
+    /// their schema got added.
+    held_entries: HashMap<SchemaId, Vec<ReceivedEntry>>,
+
+    /// Directions in which we exchange entries with remote peers, both directions when not set.
+    directions: HashMap<P, Direction>,
 }
 
 impl<P> SyncManager<P>
@@ -61,15 +86,41 @@ where
             local_peer,
             ingest,
             sessions: HashMap::new(),
+            scheduler: RangeScheduler::default(),
+            held_entries: HashMap::new(),
+            directions: HashMap::new(),
         }
     }
 
+    /// Set the direction in which we exchange entries with a remote peer in future sessions.
+    pub fn set_direction(&mut self, remote_peer: &P, direction: Direction) {
+        self.directions.insert(remote_peer.clone(), direction);
+    }
+
+    /// Remove the direction set for a remote peer, entries are exchanged in both directions
+    /// again.
+    pub fn remove_direction(&mut self, remote_peer: &P) {
+        self.directions.remove(remote_peer);
+    }
+
+    /// Get the direction in which we exchange entries with a remote peer.
+    fn direction(&self, remote_peer: &P) -> Direction {
+        self.directions
+            .get(remote_peer)
+            .copied()
+            .unwrap_or_default()
+    }
+
     /// Removes all sessions related to a remote peer.
     ///
     /// Warning: This might also remove actively running sessions. Do only clear sessions when you
     /// are sure they are a) done or b) the peer closed its connection.
     pub fn remove_sessions(&mut self, remote_peer: &P) {
-        self.sessions.remove(remote_peer);
+        if let Some(sessions) = self.sessions.remove(remote_peer) {
+            for mut session in sessions {
+                session.close();
+            }
+        }
     }
 
     /// Get all sessions related to a remote peer.
@@ -88,15 +139,18 @@ where
         session_id: &SessionId,
         target_set: &SchemaIdSet,
         mode: &Mode,
+        direction: &Direction,
         local: bool,
     ) -> Vec<Message> {
         let mut session = Session::new(
             session_id,
             target_set,
             mode,
+            direction,
             local,
             SUPPORT_LIVE_MODE,
             self.ingest.schema_provider.clone(),
+            self.scheduler.clone(),
         );
         let initial_messages = session.initial_messages(&self.store).await;
 
@@ -116,15 +170,18 @@ where
         session_id: &SessionId,
         target_set: &SchemaIdSet,
         mode: &Mode,
+        direction: &Direction,
         local: bool,
     ) {
         let session = Session::new(
             session_id,
             target_set,
             mode,
+            direction,
             local,
             SUPPORT_LIVE_MODE,
             self.ingest.schema_provider.clone(),
+            self.scheduler.clone(),
         );
 
         if let Some(sessions) = self.sessions.get_mut(remote_peer) {
@@ -143,7 +200,7 @@ where
                 .enumerate()
                 .find(|(_, session)| session.id == *session_id)
             {
-                sessions.remove(index);
+                sessions.remove(index).close();
             } else {
                 debug!(
                     "Tried to remove nonexistent session {} with peer: {}",
@@ -207,12 +264,13 @@ where
 
         // Ignore initial messages when we initiated the session, they will come from the other
         // peer
-        self.insert_session(remote_peer, &session_id, target_set, mode, true)
+        let direction = self.direction(remote_peer);
+        self.insert_session(remote_peer, &session_id, target_set, mode, &direction, true)
             .await;
 
         Ok(vec![SyncMessage::new(
             session_id,
-            Message::SyncRequest(mode.clone(), target_set.clone()),
+            Message::SyncRequest(mode.clone(), target_set.clone(), direction),
         )])
     }
 
@@ -220,6 +278,7 @@ where
         &mut self,
         remote_peer: &P,
         target_set: &SchemaIdSet,
+        direction: &Direction,
         existing_session: &Session,
     ) -> Result<SyncResult, ReplicationError> {
         match existing_session.local {
@@ -270,6 +329,7 @@ where
                     &existing_session.id,
                     target_set,
                     &existing_session.mode(),
+                    direction,
                     false,
                 )
                 .await;
@@ -292,6 +352,7 @@ where
         Ok(SyncResult {
             messages: all_messages,
             is_done: false,
+            missing_schema_ids: Vec::new(),
         })
     }
 
@@ -300,6 +361,7 @@ where
         remote_peer: &P,
         session_id: &SessionId,
         mode: &Mode,
+        direction: &Direction,
         existing_session: &Session,
     ) -> Result<SyncResult, ReplicationError> {
         match existing_session.local {
@@ -345,6 +407,7 @@ where
                     session_id,
                     &existing_session.target_set(),
                     mode,
+                    direction,
                     false,
                 )
                 .await;
@@ -354,6 +417,7 @@ where
         Ok(SyncResult {
             messages: all_messages,
             is_done: false,
+            missing_schema_ids: Vec::new(),
         })
     }
 
@@ -363,9 +427,16 @@ where
         mode: &Mode,
         session_id: &SessionId,
         target_set: &SchemaIdSet,
+        direction: &Direction,
     ) -> Result<SyncResult, ReplicationError> {
         SyncManager::<P>::is_mode_supported(mode)?;
 
+        // Only exchange entries in the directions both peers agree on
+        let direction = self
+            .direction(remote_peer)
+            .negotiate(direction)
+            .ok_or(ReplicationError::UnsupportedDirection)?;
+
         let sessions = self.get_sessions(remote_peer);
 
         // Check if a session with this id already exists for this peer.
@@ -378,7 +449,7 @@ where
         {
             trace!("Handle sync request containing duplicate session id");
             return self
-                .handle_duplicate_session(remote_peer, target_set, existing_session)
+                .handle_duplicate_session(remote_peer, target_set, &direction, existing_session)
                 .await;
         }
 
@@ -389,7 +460,7 @@ where
         {
             trace!("Handle sync request containing duplicate target sets");
             return self
-                .handle_duplicate_target_set(remote_peer, session_id, mode, session)
+                .handle_duplicate_target_set(remote_peer, session_id, mode, &direction, session)
                 .await;
         };
 
@@ -399,7 +470,14 @@ where
         );
 
         let messages = self
-            .insert_and_initialize_session(remote_peer, session_id, target_set, mode, false)
+            .insert_and_initialize_session(
+                remote_peer,
+                session_id,
+                target_set,
+                mode,
+                &direction,
+                false,
+            )
             .await;
 
         Ok(SyncResult::from_messages(*session_id, messages, false))
@@ -460,43 +538,228 @@ where
         entry_bytes: &EncodedEntry,
         operation_bytes: &Option<EncodedOperation>,
     ) -> Result<SyncResult, ReplicationError> {
-        let mut sessions = self.get_sessions(remote_peer);
+        let session = self
+            .sessions
+            .get_mut(remote_peer)
+            .and_then(|sessions| {
+                sessions
+                    .iter_mut()
+                    .find(|session| session.id == *session_id)
+            })
+            .ok_or_else(|| ReplicationError::NoSessionFound(*session_id, remote_peer.display()))?;
 
-        if let Some(session) = sessions
-            .iter_mut()
-            .find(|session| session.id == *session_id)
-        {
-            session.validate_entry(entry_bytes, operation_bytes.as_ref())?;
+        let (entries, messages) = session
+            .handle_entry(&self.store, entry_bytes, operation_bytes.as_ref())
+            .await?;
+        let is_done = session.state == SessionState::Done;
+
+        // Entries of different logs can depend on each other when they're requested in ranges,
+        // these are held back until their dependencies arrived
+        let is_deferrable = session.mode() == Mode::LogRange;
+
+        let missing_schema_ids =
+            self.ingest_entries(entries, is_deferrable)
+                .await
+                .map_err(|err| {
+                    if let ReplicationError::Validation(IngestError::NonCanonicalOperation(
+                        hash,
+                        public_key,
+                    )) = &err
+                    {
+                        warn!(
+                        "Peer {} sent entry {} of {} with operation not encoded in canonical CBOR",
+                        remote_peer.display(),
+                        hash,
+                        public_key.display()
+                    );
+                    }
+
+                    err
+                })?;
+
+        // We're done, clean up after ourselves
+        if is_done {
+            self.remove_session(remote_peer, session_id);
+        }
+
+        Ok(SyncResult {
+            missing_schema_ids,
+            ..SyncResult::from_messages(*session_id, messages, is_done)
+        })
+    }
+
+    /// Hold back a received entry until its schema got materialized.
+    ///
+    /// Returns the schema id when no other entries of this schema are held back yet.
+    fn hold_entry(&mut self, entry: ReceivedEntry) -> Option<SchemaId> {
+        let schema_id = entry
+            .1
+            .as_ref()
+            .and_then(|operation_bytes| decode_operation(operation_bytes).ok())
+            .map(|operation| operation.schema_id().to_owned())?;
+
+        // Entries get dropped when too many are held back already, the remote peer will send
+        // them again in a later session
+        let held_count: usize = self.held_entries.values().map(Vec::len).sum();
+        if held_count >= MAX_HELD_ENTRIES {
+            debug!(
+                "Drop entry of {} as too many entries are held back",
+                schema_id.display()
+            );
+            return None;
+        }
+
+        let is_missing = !self.held_entries.contains_key(&schema_id);
+        self.held_entries
+            .entry(schema_id.clone())
+            .or_default()
+            .push(entry);
+
+        is_missing.then_some(schema_id)
+    }
+
+    /// Ingest all entries which were held back until the given schema got materialized.
+    pub async fn on_schema_added(&mut self, schema_id: &SchemaId) {
+        let entries = match self.held_entries.remove(schema_id) {
+            Some(entries) => entries,
+            None => return,
+        };
+
+        debug!(
+            "Ingest {} held back entries of {}",
+            entries.len(),
+            schema_id.display()
+        );
+
+        if let Err(err) = self.ingest_entries(entries, false).await {
+            warn!(
+                "Failed ingesting held back entries of {}: {}",
+                schema_id.display(),
+                err
+            );
+        }
+    }
+
+    /// Ingest entries in the given order.
+    ///
+    /// Entries pointing at operations we don't know yet are handed back to the range scheduler
+    /// when they're deferrable, together with all following entries of the same log. They are
+    /// tried again after the next entry got ingested.
+    ///
+    /// Entries of schemas which are not materialized yet are held back until the schema got
+    /// added, returns the ids of these schemas when they were missing before.
+    async fn ingest_entries(
+        &mut self,
+        entries: Vec<ReceivedEntry>,
+        is_deferrable: bool,
+    ) -> Result<Vec<SchemaId>, ReplicationError> {
+        let mut queue = VecDeque::from(entries);
+        let mut missing_schema_ids = Vec::new();
+
+        while let Some((entry_bytes, operation_bytes)) = queue.pop_front() {
+            // Entries can arrive without payloads, for example from nodes which compacted their
+            // blob pieces. We can't ingest them without the operation
+            let encoded_operation = match &operation_bytes {
+                Some(encoded_operation) => encoded_operation,
+                None => {
+                    debug!("Skip entry {} without payload", entry_bytes.hash());
+                    continue;
+                }
+            };
 
             match self
                 .ingest
-                .handle_entry(
-                    &self.store,
-                    entry_bytes,
-                    // @TODO: This should handle entries with removed payloads
-                    operation_bytes
-                        .as_ref()
-                        .expect("For now we always expect an operation here"),
-                )
+                .handle_entry(&self.store, &entry_bytes, encoded_operation)
                 .await
             {
-                // When duplicate entries arrive at a node, or a schema is not materialized yet,
-                // we don't want to treat as an error. This is expected behavior which may occur
-                // when concurrent sync sessions are running.
-                Ok(_) | Err(IngestError::DuplicateEntry(_)) | Err(IngestError::SchemaNotFound) => {
-                    Ok(SyncResult {
-                        messages: vec![],
-                        is_done: session.state == SessionState::Done,
-                    })
+                Ok(_) => {
+                    if is_deferrable {
+                        queue.extend(self.scheduler.take_deferred());
+                    }
                 }
-                Err(err) => Err(ReplicationError::Validation(err)),
+                // When duplicate entries arrive at a node we don't want to treat as an error. This
+                // is expected behavior which may occur when concurrent sync sessions are running.
+                Err(IngestError::DuplicateEntry(_)) => (),
+                // The schema definition might still be on its way or is not materialized yet
+                Err(IngestError::SchemaNotFound) => {
+                    if let Some(schema_id) = self.hold_entry((entry_bytes, operation_bytes)) {
+                        missing_schema_ids.push(schema_id);
+                    }
+                }
+                // Forks are recorded during ingest, the peer is not at fault for passing on
+                // entries of an author using the same key pair on more than one device
+                Err(IngestError::ForkedLog(_)) => (),
+                Err(IngestError::Domain(DomainError::ValidationError(
+                    ValidationError::PreviousOperationNotFound(_),
+                ))) if is_deferrable => {
+                    let entry = decode_entry(&entry_bytes).map_err(IngestError::DecodeEntry)?;
+                    let mut deferred = vec![(entry_bytes, operation_bytes)];
+
+                    // Keep the order of entries in the same log
+                    let (same_log, other_logs): (Vec<ReceivedEntry>, Vec<ReceivedEntry>) =
+                        queue.drain(..).partition(|(entry_bytes, _)| {
+                            decode_entry(entry_bytes).is_ok_and(|queued| {
+                                queued.public_key() == entry.public_key()
+                                    && queued.log_id() == entry.log_id()
+                            })
+                        });
+                    deferred.extend(same_log);
+                    queue.extend(other_logs);
+
+                    debug!(
+                        "Defer {} entries of {} on {:?} until their dependencies arrived",
+                        deferred.len(),
+                        entry.public_key().display(),
+                        entry.log_id()
+                    );
+
+                    self.scheduler.defer(
+                        entry.public_key(),
+                        entry.log_id(),
+                        entry.seq_num().as_u64(),
+                        deferred,
+                    );
+                }
+                Err(err) => return Err(ReplicationError::Validation(err)),
+            }
+        }
+
+        Ok(missing_schema_ids)
+    }
+
+    async fn handle_entries(
+        &mut self,
+        remote_peer: &P,
+        session_id: &SessionId,
+        compression: &Compression,
+        bytes: &[u8],
+    ) -> Result<SyncResult, ReplicationError> {
+        let entries = decompress_entries(*compression, bytes)?;
+
+        let mut result = SyncResult {
+            messages: vec![],
+            is_done: false,
+            missing_schema_ids: vec![],
+        };
+
+        for (entry_bytes, operation_bytes) in entries {
+            let entry_result = self
+                .handle_entry(remote_peer, session_id, &entry_bytes, &operation_bytes)
+                .await?;
+
+            result.messages.extend(entry_result.messages);
+            result.is_done = entry_result.is_done;
+            result
+                .missing_schema_ids
+                .extend(entry_result.missing_schema_ids);
+
+            // Session got removed after it finished
+            if result.is_done {
+                break;
             }
-        } else {
-            Err(ReplicationError::NoSessionFound(
-                *session_id,
-                remote_peer.display(),
-            ))
         }
+
+        Ok(result)
     }
 
     pub async fn handle_message(
@@ -505,9 +768,15 @@ where
         sync_message: &SyncMessage,
     ) -> Result<SyncResult, ReplicationError> {
         match sync_message.message() {
-            Message::SyncRequest(mode, target_set) => {
-                self.handle_sync_request(remote_peer, mode, &sync_message.session_id(), target_set)
-                    .await
+            Message::SyncRequest(mode, target_set, direction) => {
+                self.handle_sync_request(
+                    remote_peer,
+                    mode,
+                    &sync_message.session_id(),
+                    target_set,
+                    direction,
+                )
+                .await
             }
             Message::Entry(entry_bytes, operation_bytes) => {
                 self.handle_entry(
@@ -518,6 +787,10 @@ where
                 )
                 .await
             }
+            Message::Entries(compression, bytes) => {
+                self.handle_entries(remote_peer, &sync_message.session_id(), compression, bytes)
+                    .await
+            }
             message => {
                 self.handle_session_message(remote_peer, &sync_message.session_id(), message)
                     .await
@@ -528,14 +801,17 @@ where
 
 #[cfg(test)]
 mod tests {
+    use p2panda_rs::entry::{LogId, SeqNum};
     use p2panda_rs::Human;
     use rstest::rstest;
     use tokio::sync::broadcast;
 
+    use crate::replication::compression::MAX_BATCH_SIZE;
     use crate::replication::errors::{DuplicateSessionRequestError, ReplicationError};
     use crate::replication::message::Message;
     use crate::replication::{
-        Mode, SchemaIdSet, SyncIngest, SyncMessage, HAVE_TYPE, SYNC_DONE_TYPE,
+        compress_entries, Compression, Direction, Mode, SchemaIdSet, SyncIngest, SyncMessage,
+        HAVE_TYPE, SYNC_DONE_TYPE,
     };
     use crate::schema::SchemaProvider;
     use crate::test_utils::helpers::random_schema_id_set;
@@ -613,14 +889,14 @@ mod tests {
 
             let message = SyncMessage::new(
                 0,
-                Message::SyncRequest(Mode::LogHeight, target_set_1.clone()),
+                Message::SyncRequest(Mode::LogHeight, target_set_1.clone(), Direction::Both),
             );
             let result = manager.handle_message(&peer_id_remote, &message).await;
             assert!(result.is_ok());
 
             let message = SyncMessage::new(
                 1,
-                Message::SyncRequest(Mode::LogHeight, target_set_2.clone()),
+                Message::SyncRequest(Mode::LogHeight, target_set_2.clone(), Direction::Both),
             );
             let result = manager.handle_message(&peer_id_remote, &message).await;
             assert!(result.is_ok());
@@ -628,7 +904,7 @@ mod tests {
             // Reject attempt to create session again
             let message = SyncMessage::new(
                 0,
-                Message::SyncRequest(Mode::LogHeight, target_set_3.clone()),
+                Message::SyncRequest(Mode::LogHeight, target_set_3.clone(), Direction::Both),
             );
             let result = manager.handle_message(&peer_id_remote, &message).await;
             assert!(matches!(result,
@@ -638,7 +914,7 @@ mod tests {
             // Reject different session concerning same target set
             let message = SyncMessage::new(
                 2,
-                Message::SyncRequest(Mode::LogHeight, target_set_2.clone()),
+                Message::SyncRequest(Mode::LogHeight, target_set_2.clone(), Direction::Both),
             );
             let result = manager.handle_message(&peer_id_remote, &message).await;
             assert!(matches!(
@@ -1024,7 +1300,7 @@ mod tests {
             );
             let message = SyncMessage::new(
                 INITIAL_SESSION_ID,
-                Message::SyncRequest(Mode::LogHeight, target_set.clone()),
+                Message::SyncRequest(Mode::LogHeight, target_set.clone(), Direction::Both),
             );
             let result = manager.handle_message(&peer_id_remote, &message).await;
             assert!(result.is_ok());
@@ -1033,7 +1309,7 @@ mod tests {
             let mut manager = SyncManager::new(node.context.store.clone(), ingest, peer_id_local);
             let message = SyncMessage::new(
                 INITIAL_SESSION_ID,
-                Message::SyncRequest(Mode::SetReconciliation, target_set.clone()),
+                Message::SyncRequest(Mode::SetReconciliation, target_set.clone(), Direction::Both),
             );
             let result = manager.handle_message(&peer_id_remote, &message).await;
             assert!(result.is_err());
@@ -1107,7 +1383,7 @@ mod tests {
                 messages,
                 vec![SyncMessage::new(
                     0,
-                    Message::SyncRequest(Mode::LogHeight, target_set.clone())
+                    Message::SyncRequest(Mode::LogHeight, target_set.clone(), Direction::Both)
                 )]
             );
 
@@ -1166,4 +1442,269 @@ mod tests {
             }
         })
     }
+
+    #[rstest]
+    fn pull_only_sync(
+        #[from(populate_store_config)]
+        #[with(2, 1, generate_key_pairs(3))]
+        config: PopulateStoreConfig,
+    ) {
+        let peer_id_local: Peer = Peer::new("local");
+        let peer_id_remote: Peer = Peer::new("remote");
+
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let mut node_a = manager.create().await;
+            let node_b = manager.create().await;
+
+            populate_and_materialize(&mut node_a, &config).await;
+
+            let (tx, _rx) = broadcast::channel(8);
+            let target_set = SchemaIdSet::new(&[config.schema.id().to_owned()]);
+
+            let mut manager_a = SyncManager::new(
+                node_a.context.store.clone(),
+                SyncIngest::new(node_a.context.schema_provider.clone(), tx.clone()),
+                peer_id_local.clone(),
+            );
+            manager_a.set_direction(&peer_id_remote, Direction::Pull);
+
+            let mut manager_b = SyncManager::new(
+                node_b.context.store.clone(),
+                SyncIngest::new(node_b.context.schema_provider.clone(), tx),
+                peer_id_remote.clone(),
+            );
+
+            // Local peer announces that it only pulls entries in this session
+            let messages = manager_a
+                .initiate_session(&peer_id_remote, &target_set, &Mode::LogHeight)
+                .await
+                .unwrap();
+            assert_eq!(
+                messages,
+                vec![SyncMessage::new(
+                    0,
+                    Message::SyncRequest(Mode::LogHeight, target_set.clone(), Direction::Pull)
+                )]
+            );
+
+            // Remote peer rejects the session when it also only wants to pull
+            manager_b.set_direction(&peer_id_local, Direction::Pull);
+            let result = manager_b.handle_message(&peer_id_local, &messages[0]).await;
+            assert!(matches!(
+                result,
+                Err(ReplicationError::UnsupportedDirection)
+            ));
+
+            // Remote peer accepts the session when it is allowed to push
+            manager_b.set_direction(&peer_id_local, Direction::Both);
+            let result = manager_b
+                .handle_message(&peer_id_local, &messages[0])
+                .await
+                .unwrap();
+            assert_eq!(
+                manager_b.get_sessions(&peer_id_local)[0].direction,
+                Direction::Push
+            );
+
+            // Local peer holds entries the remote doesn't have but doesn't send them
+            let result_have = manager_a
+                .handle_message(&peer_id_remote, &result.messages[0])
+                .await
+                .unwrap();
+            let message_types: Vec<u64> = result_have
+                .messages
+                .iter()
+                .map(|message| message.message_type())
+                .collect();
+            assert_eq!(message_types, vec![HAVE_TYPE, SYNC_DONE_TYPE]);
+        })
+    }
+
+    #[rstest]
+    fn log_range_sync(
+        #[from(populate_store_config)]
+        #[with(10, 1, generate_key_pairs(2))]
+        config: PopulateStoreConfig,
+        #[values(None, Some(Compression::Zstd))] compression: Option<Compression>,
+    ) {
+        let peer_id_local: Peer = Peer::new("local");
+        let peer_id_remote: Peer = Peer::new("remote");
+
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let mut node_a = manager.create().await;
+            let node_b = manager.create().await;
+
+            populate_and_materialize(&mut node_a, &config).await;
+            let _ = node_b
+                .context
+                .schema_provider
+                .update(config.schema.clone())
+                .await;
+
+            let (tx, _rx) = broadcast::channel(50);
+            let target_set = SchemaIdSet::new(&[config.schema.id().to_owned()]);
+
+            let mut manager_a = SyncManager::new(
+                node_a.context.store.clone(),
+                SyncIngest::new(node_a.context.schema_provider.clone(), tx.clone()),
+                peer_id_remote.clone(),
+            );
+
+            let mut manager_b = SyncManager::new(
+                node_b.context.store.clone(),
+                SyncIngest::new(node_b.context.schema_provider.clone(), tx),
+                peer_id_local.clone(),
+            );
+
+            // Node B requests the data it is missing from node A
+            let mut messages_to_a = manager_b
+                .initiate_session(&peer_id_remote, &target_set, &Mode::LogRange)
+                .await
+                .unwrap();
+            let mut is_done_a = false;
+            let mut is_done_b = false;
+
+            while !messages_to_a.is_empty() {
+                let mut messages_to_b = Vec::new();
+                for message in &messages_to_a {
+                    let result = manager_a
+                        .handle_message(&peer_id_local, message)
+                        .await
+                        .unwrap();
+                    is_done_a = result.is_done;
+                    messages_to_b.extend(result.messages);
+                }
+
+                // Entries sent in compressed batches need to yield the same follow-up messages as
+                // when they were sent one by one
+                if let (Some(compression), Some(message)) = (compression, messages_to_b.first()) {
+                    let session_id = message.session_id();
+                    let messages = messages_to_b
+                        .iter()
+                        .map(|message| message.message().to_owned())
+                        .collect();
+                    messages_to_b = compress_entries(compression, messages, MAX_BATCH_SIZE)
+                        .into_iter()
+                        .map(|message| SyncMessage::new(session_id, message))
+                        .collect();
+                }
+
+                messages_to_a = Vec::new();
+                for message in &messages_to_b {
+                    let result = manager_b
+                        .handle_message(&peer_id_remote, message)
+                        .await
+                        .unwrap();
+                    is_done_b = result.is_done;
+                    messages_to_a.extend(result.messages);
+                }
+            }
+
+            assert!(is_done_a);
+            assert!(is_done_b);
+
+            // Node B received all entries of both logs
+            for key_pair in &config.authors {
+                let entries = node_b
+                    .context
+                    .store
+                    .get_entries_from(
+                        &key_pair.public_key(),
+                        &LogId::default(),
+                        &SeqNum::default(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(entries.len(), 10);
+            }
+        })
+    }
+
+    #[rstest]
+    fn holds_entries_of_missing_schemas(
+        #[from(populate_store_config)]
+        #[with(3, 1, generate_key_pairs(1))]
+        config: PopulateStoreConfig,
+    ) {
+        let peer_id_local: Peer = Peer::new("local");
+        let peer_id_remote: Peer = Peer::new("remote");
+
+        test_runner_with_manager(|manager: TestNodeManager| async move {
+            let mut node_a = manager.create().await;
+            let node_b = manager.create().await;
+
+            // Node B does not know about the schema yet
+            populate_and_materialize(&mut node_a, &config).await;
+
+            let (tx, _rx) = broadcast::channel(50);
+            let target_set = SchemaIdSet::new(&[config.schema.id().to_owned()]);
+
+            let mut manager_a = SyncManager::new(
+                node_a.context.store.clone(),
+                SyncIngest::new(node_a.context.schema_provider.clone(), tx.clone()),
+                peer_id_remote.clone(),
+            );
+
+            let mut manager_b = SyncManager::new(
+                node_b.context.store.clone(),
+                SyncIngest::new(node_b.context.schema_provider.clone(), tx),
+                peer_id_local.clone(),
+            );
+
+            let mut messages_to_a = manager_b
+                .initiate_session(&peer_id_remote, &target_set, &Mode::LogHeight)
+                .await
+                .unwrap();
+            let mut missing_schema_ids = Vec::new();
+
+            while !messages_to_a.is_empty() {
+                let mut messages_to_b = Vec::new();
+                for message in &messages_to_a {
+                    let result = manager_a
+                        .handle_message(&peer_id_local, message)
+                        .await
+                        .unwrap();
+                    messages_to_b.extend(result.messages);
+                }
+
+                messages_to_a = Vec::new();
+                for message in &messages_to_b {
+                    let result = manager_b
+                        .handle_message(&peer_id_remote, message)
+                        .await
+                        .unwrap();
+                    messages_to_a.extend(result.messages);
+                    missing_schema_ids.extend(result.missing_schema_ids);
+                }
+            }
+
+            // Node B reports the missing schema once and holds back the entries
+            assert_eq!(missing_schema_ids, vec![config.schema.id().to_owned()]);
+
+            let public_key = config.authors[0].public_key();
+            let entries = node_b
+                .context
+                .store
+                .get_entries_from(&public_key, &LogId::default(), &SeqNum::default())
+                .await
+                .unwrap();
+            assert!(entries.is_empty());
+
+            // Entries get ingested as soon as the schema got materialized
+            let _ = node_b
+                .context
+                .schema_provider
+                .update(config.schema.clone())
+                .await;
+            manager_b.on_schema_added(config.schema.id()).await;
+
+            let entries = node_b
+                .context
+                .store
+                .get_entries_from(&public_key, &LogId::default(), &SeqNum::default())
+                .await
+                .unwrap();
+            assert_eq!(entries.len(), 3);
+        })
+    }
 }
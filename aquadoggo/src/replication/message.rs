@@ -1,5 +1,6 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use p2panda_rs::document::DocumentId;
 use p2panda_rs::entry::EncodedEntry;
 use p2panda_rs::entry::{LogId, SeqNum};
 use p2panda_rs::identity::PublicKey;
@@ -9,29 +10,41 @@ use serde::ser::SerializeSeq;
 use serde::Serialize;
 
 use crate::replication::{
-    MessageType, Mode, SchemaIdSet, SessionId, ENTRY_TYPE, HAVE_TYPE, SYNC_DONE_TYPE,
-    SYNC_REQUEST_TYPE,
+    Compression, Direction, MessageType, Mode, SchemaIdSet, SessionId, BLOB_REQUEST_TYPE,
+    ENTRIES_TYPE, ENTRY_TYPE, HAVE_TYPE, SYNC_DONE_TYPE, SYNC_REQUEST_TYPE, WANT_TYPE,
 };
 
 pub type LiveMode = bool;
 
 pub type LogHeights = (PublicKey, Vec<(LogId, SeqNum)>);
 
+/// Inclusive ranges of sequence numbers requested from logs of an author.
+pub type LogRanges = (PublicKey, Vec<(LogId, SeqNum, SeqNum)>);
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Message {
-    SyncRequest(Mode, SchemaIdSet),
+    SyncRequest(Mode, SchemaIdSet, Direction),
     Entry(EncodedEntry, Option<EncodedOperation>),
+    Entries(Compression, Vec<u8>),
     SyncDone(LiveMode),
     Have(Vec<LogHeights>),
+    Want(Vec<LogRanges>),
+
+    /// Request for the entries of a blob document and its pieces, answered with `Entry` or
+    /// `Entries` messages and a final `SyncDone` under the same session id.
+    BlobRequest(DocumentId),
 }
 
 impl Message {
     pub fn message_type(&self) -> MessageType {
         match self {
-            Message::SyncRequest(_, _) => SYNC_REQUEST_TYPE,
+            Message::SyncRequest(_, _, _) => SYNC_REQUEST_TYPE,
             Message::Entry(_, _) => ENTRY_TYPE,
+            Message::Entries(_, _) => ENTRIES_TYPE,
             Message::SyncDone(_) => SYNC_DONE_TYPE,
             Message::Have(_) => HAVE_TYPE,
+            Message::Want(_) => WANT_TYPE,
+            Message::BlobRequest(_) => BLOB_REQUEST_TYPE,
         }
     }
 }
@@ -46,6 +59,16 @@ impl Human for Message {
                     .collect();
                 format!("Have({log_heights:?})")
             }
+            Message::Want(log_ranges) => {
+                let log_ranges: Vec<(String, &Vec<_>)> = log_ranges
+                    .iter()
+                    .map(|(public_key, log_ranges)| (public_key.to_string(), log_ranges))
+                    .collect();
+                format!("Want({log_ranges:?})")
+            }
+            Message::Entries(compression, bytes) => {
+                format!("Entries({}, {} bytes)", compression.display(), bytes.len())
+            }
             message => format!("{message:?}"),
         }
     }
@@ -91,10 +114,15 @@ impl Serialize for SyncMessage {
         };
 
         match self.message() {
-            Message::SyncRequest(mode, target_set) => {
-                let mut seq = serialize_header(serializer.serialize_seq(Some(4))?)?;
+            Message::SyncRequest(mode, target_set, direction) => {
+                // Omit the default direction to stay compatible with peers not knowing about it
+                let len = if direction == &Direction::Both { 4 } else { 5 };
+                let mut seq = serialize_header(serializer.serialize_seq(Some(len))?)?;
                 seq.serialize_element(mode)?;
                 seq.serialize_element(target_set)?;
+                if direction != &Direction::Both {
+                    seq.serialize_element(direction)?;
+                }
                 seq.end()
             }
             Message::Entry(entry_bytes, operation_bytes) => {
@@ -103,6 +131,12 @@ impl Serialize for SyncMessage {
                 seq.serialize_element(operation_bytes)?;
                 seq.end()
             }
+            Message::Entries(compression, bytes) => {
+                let mut seq = serialize_header(serializer.serialize_seq(Some(4))?)?;
+                seq.serialize_element(compression)?;
+                seq.serialize_element(serde_bytes::Bytes::new(bytes))?;
+                seq.end()
+            }
             Message::SyncDone(live_mode) => {
                 let mut seq = serialize_header(serializer.serialize_seq(Some(3))?)?;
                 seq.serialize_element(live_mode)?;
@@ -113,6 +147,16 @@ impl Serialize for SyncMessage {
                 seq.serialize_element(log_heights)?;
                 seq.end()
             }
+            Message::Want(log_ranges) => {
+                let mut seq = serialize_header(serializer.serialize_seq(Some(3))?)?;
+                seq.serialize_element(log_ranges)?;
+                seq.end()
+            }
+            Message::BlobRequest(document_id) => {
+                let mut seq = serialize_header(serializer.serialize_seq(Some(3))?)?;
+                seq.serialize_element(document_id)?;
+                seq.end()
+            }
         }
     }
 }
@@ -120,27 +164,40 @@ impl Serialize for SyncMessage {
 #[cfg(test)]
 mod tests {
     use ciborium::cbor;
+    use p2panda_rs::document::DocumentId;
     use p2panda_rs::entry::{LogId, SeqNum};
     use p2panda_rs::identity::PublicKey;
     use p2panda_rs::serde::{serialize_from, serialize_value};
-    use p2panda_rs::test_utils::fixtures::public_key;
+    use p2panda_rs::test_utils::fixtures::{document_id, public_key};
     use rstest::rstest;
 
-    use crate::replication::{Mode, SchemaIdSet};
+    use crate::replication::{Compression, Direction, Mode, SchemaIdSet};
     use crate::test_utils::helpers::random_schema_id_set;
 
     use super::{Message, SyncMessage};
 
     #[rstest]
-    fn serialize(#[from(random_schema_id_set)] target_set: SchemaIdSet, public_key: PublicKey) {
+    fn serialize(
+        #[from(random_schema_id_set)] target_set: SchemaIdSet,
+        public_key: PublicKey,
+        document_id: DocumentId,
+    ) {
         assert_eq!(
             serialize_from(SyncMessage::new(
                 51,
-                Message::SyncRequest(Mode::SetReconciliation, target_set.clone())
+                Message::SyncRequest(Mode::SetReconciliation, target_set.clone(), Direction::Both)
             )),
             serialize_value(cbor!([1, 51, 1, target_set]))
         );
 
+        assert_eq!(
+            serialize_from(SyncMessage::new(
+                51,
+                Message::SyncRequest(Mode::LogHeight, target_set.clone(), Direction::Push)
+            )),
+            serialize_value(cbor!([1, 51, 0, target_set, 1]))
+        );
+
         assert_eq!(
             serialize_from(SyncMessage::new(
                 51,
@@ -160,5 +217,43 @@ mod tests {
                 )]
             ]))
         );
+
+        assert_eq!(
+            serialize_from(SyncMessage::new(
+                51,
+                Message::Want(vec![(
+                    public_key,
+                    vec![(
+                        LogId::default(),
+                        SeqNum::new(3).unwrap(),
+                        SeqNum::new(7).unwrap()
+                    )]
+                )])
+            )),
+            serialize_value(cbor!([
+                11,
+                51,
+                vec![(
+                    serde_bytes::Bytes::new(&public_key.to_bytes()),
+                    vec![(0, 3, 7)]
+                )]
+            ]))
+        );
+
+        assert_eq!(
+            serialize_from(SyncMessage::new(
+                51,
+                Message::Entries(Compression::Deflate, vec![1, 2, 3])
+            )),
+            serialize_value(cbor!([4, 51, 1, serde_bytes::Bytes::new(&[1, 2, 3])]))
+        );
+
+        assert_eq!(
+            serialize_from(SyncMessage::new(
+                51,
+                Message::BlobRequest(document_id.clone())
+            )),
+            serialize_value(cbor!([20, 51, document_id]))
+        );
     }
 }
@@ -1,26 +1,42 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 mod announcement;
+mod blob;
+mod compression;
+mod direction;
 pub mod errors;
 mod ingest;
 mod manager;
 mod message;
 mod mode;
+mod operator;
+mod pacing;
+mod pause;
 mod schema_id_set;
 mod service;
 mod session;
 mod strategies;
 pub mod traits;
 
-pub use announcement::{now, Announcement, AnnouncementMessage};
+pub use announcement::{
+    default_supported_modes, now, now_millis, Announcement, AnnouncementMessage,
+};
+pub use blob::{blob_schema_ids, BlobRequests};
+pub use compression::{compress_entries, decompress_entries, Compression, SUPPORTED_COMPRESSIONS};
+pub use direction::{select_direction, Direction, DirectionPreference};
 pub use ingest::SyncIngest;
-pub use manager::SyncManager;
-pub use message::{LogHeights, Message, SyncMessage};
-pub use mode::Mode;
+pub use manager::{SyncManager, SUPPORTED_MODES};
+pub use message::{LogHeights, LogRanges, Message, SyncMessage};
+pub use mode::{select_modes, Mode, ModePreference};
+pub use operator::OperatorProof;
+pub use pacing::PeerPacing;
+pub use pause::ReplicationPause;
 pub use schema_id_set::SchemaIdSet;
 pub use service::replication_service;
 pub use session::{Session, SessionId, SessionState};
-pub use strategies::{LogHeightStrategy, SetReconciliationStrategy, StrategyResult};
+pub use strategies::{
+    LogHeightStrategy, LogRangeStrategy, RangeScheduler, SetReconciliationStrategy, StrategyResult,
+};
 
 pub type MessageType = u64;
 
@@ -29,7 +45,13 @@ pub const ANNOUNCE_TYPE: MessageType = 0;
 pub const SYNC_REQUEST_TYPE: MessageType = 1;
 pub const ENTRY_TYPE: MessageType = 2;
 pub const SYNC_DONE_TYPE: MessageType = 3;
+pub const ENTRIES_TYPE: MessageType = 4;
 pub const HAVE_TYPE: MessageType = 10;
+pub const WANT_TYPE: MessageType = 11;
+pub const BLOB_REQUEST_TYPE: MessageType = 20;
+pub const FRAGMENT_TYPE: MessageType = 30;
+pub const FRAGMENT_RESUME_TYPE: MessageType = 31;
+pub const OPERATOR_PROOF_TYPE: MessageType = 40;
 
 /// Currently supported p2panda replication protocol version.
 pub const REPLICATION_PROTOCOL_VERSION: u64 = 1;
@@ -1,30 +1,50 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use std::fmt::{self, Display};
+use std::str::FromStr;
 
+use libp2p::PeerId;
+use p2panda_rs::schema::SchemaId;
 use p2panda_rs::Human;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::replication::errors::ReplicationError;
+use crate::replication::SchemaIdSet;
+
+/// Strategy used to determine which entries need to be exchanged during replication.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Mode {
+    /// Compare the heights of all logs of both peers.
     LogHeight,
+
+    /// Reconcile the sets of entries of both peers.
     SetReconciliation,
+
+    /// Compare the heights of all logs of both peers and request missing entries in ranges,
+    /// ranges of the same log can be downloaded from many peers at once.
+    LogRange,
+
+    /// Mode requested by a remote peer which is not known to us.
     Unknown,
 }
 
 impl Mode {
+    /// Returns the name of this replication mode.
     pub fn as_str(&self) -> &str {
         match self {
             Mode::LogHeight => "log-height",
             Mode::SetReconciliation => "set-reconciliation",
+            Mode::LogRange => "log-range",
             Mode::Unknown => "unknown",
         }
     }
 
+    /// Returns the identifier of this replication mode used on the wire.
     pub fn as_u64(&self) -> u64 {
         match self {
             Mode::LogHeight => 0,
             Mode::SetReconciliation => 1,
+            Mode::LogRange => 2,
             Mode::Unknown => unreachable!("Can't create an unknown replication mode"),
         }
     }
@@ -35,11 +55,25 @@ impl From<u64> for Mode {
         match value {
             0 => Mode::LogHeight,
             1 => Mode::SetReconciliation,
+            2 => Mode::LogRange,
             _ => Mode::Unknown,
         }
     }
 }
 
+impl FromStr for Mode {
+    type Err = ReplicationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "log-height" => Ok(Mode::LogHeight),
+            "set-reconciliation" => Ok(Mode::SetReconciliation),
+            "log-range" => Ok(Mode::LogRange),
+            _ => Err(ReplicationError::UnsupportedMode),
+        }
+    }
+}
+
 impl Serialize for Mode {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -71,18 +105,89 @@ impl Human for Mode {
     }
 }
 
+/// Replication mode preferred for a remote peer and / or a set of schema ids.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ModePreference {
+    /// Peer this preference applies to, applies to all peers when not set.
+    pub peer_id: Option<PeerId>,
+
+    /// Schema ids this preference applies to, applies to all schema ids when empty.
+    pub schema_ids: Vec<SchemaId>,
+
+    /// Preferred replication mode.
+    pub mode: Mode,
+}
+
+impl ModePreference {
+    fn matches(&self, peer_id: &PeerId, schema_id: &SchemaId) -> bool {
+        let is_peer_matching = self.peer_id.is_none_or(|id| &id == peer_id);
+        let is_schema_matching = self.schema_ids.is_empty() || self.schema_ids.contains(schema_id);
+        is_peer_matching && is_schema_matching
+    }
+}
+
+/// Split a target set into sets of schema ids which get replicated with the same mode.
+///
+/// The first matching preference determines the mode of a schema id, otherwise the default mode is
+/// used. When a mode is not supported by both peers we fall back to the default mode and finally to
+/// `Mode::LogHeight`, which is supported by every peer.
+pub fn select_modes(
+    preferences: &[ModePreference],
+    default_mode: &Mode,
+    peer_id: &PeerId,
+    local_modes: &[Mode],
+    remote_modes: &[Mode],
+    target_set: &SchemaIdSet,
+) -> Vec<(Mode, SchemaIdSet)> {
+    let is_supported = |mode: &Mode| local_modes.contains(mode) && remote_modes.contains(mode);
+
+    let mut groups: Vec<(Mode, Vec<SchemaId>)> = Vec::new();
+
+    for schema_id in target_set.iter() {
+        let preferred_mode = preferences
+            .iter()
+            .find(|preference| preference.matches(peer_id, schema_id))
+            .map(|preference| &preference.mode);
+
+        let mode = match preferred_mode {
+            Some(mode) if is_supported(mode) => mode.to_owned(),
+            _ if is_supported(default_mode) => default_mode.to_owned(),
+            _ => Mode::LogHeight,
+        };
+
+        match groups
+            .iter_mut()
+            .find(|(group_mode, _)| group_mode == &mode)
+        {
+            Some((_, schema_ids)) => schema_ids.push(schema_id.to_owned()),
+            None => groups.push((mode, vec![schema_id.to_owned()])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(mode, schema_ids)| (mode, SchemaIdSet::new(&schema_ids)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use ciborium::cbor;
+    use std::str::FromStr;
 
+    use ciborium::cbor;
+    use libp2p::PeerId;
+    use p2panda_rs::schema::SchemaId;
     use p2panda_rs::serde::{deserialize_into, serialize_from, serialize_value};
 
-    use super::Mode;
+    use crate::replication::SchemaIdSet;
+
+    use super::{select_modes, Mode, ModePreference};
 
     #[test]
     fn u64_representation() {
         assert_eq!(Mode::LogHeight.as_u64(), 0);
         assert_eq!(Mode::SetReconciliation.as_u64(), 1);
+        assert_eq!(Mode::LogRange.as_u64(), 2);
     }
 
     #[test]
@@ -100,4 +205,93 @@ mod tests {
         let invalid_type = deserialize_into::<Mode>(&serialize_value(cbor!("0")));
         assert!(invalid_type.is_err());
     }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(Mode::from_str("log-height").unwrap(), Mode::LogHeight);
+        assert_eq!(
+            Mode::from_str("set-reconciliation").unwrap(),
+            Mode::SetReconciliation
+        );
+        assert_eq!(Mode::from_str("log-range").unwrap(), Mode::LogRange);
+        assert!(Mode::from_str("unknown").is_err());
+    }
+
+    #[test]
+    fn select_modes_by_preference() {
+        let peer_id = PeerId::random();
+        let other_peer_id = PeerId::random();
+        let schema_id_1 = SchemaId::SchemaDefinition(1);
+        let schema_id_2 = SchemaId::SchemaFieldDefinition(1);
+        let target_set = SchemaIdSet::new(&[schema_id_1.clone(), schema_id_2.clone()]);
+        let all_modes = [Mode::LogHeight, Mode::SetReconciliation];
+
+        let preferences = vec![
+            ModePreference {
+                peer_id: Some(other_peer_id),
+                schema_ids: vec![],
+                mode: Mode::LogHeight,
+            },
+            ModePreference {
+                peer_id: None,
+                schema_ids: vec![schema_id_1.clone()],
+                mode: Mode::SetReconciliation,
+            },
+        ];
+
+        // Without preferences the default mode is used for the whole target set
+        assert_eq!(
+            select_modes(
+                &[],
+                &Mode::LogHeight,
+                &peer_id,
+                &all_modes,
+                &all_modes,
+                &target_set
+            ),
+            vec![(Mode::LogHeight, target_set.clone())]
+        );
+
+        // Target set gets split by preferred modes
+        assert_eq!(
+            select_modes(
+                &preferences,
+                &Mode::LogHeight,
+                &peer_id,
+                &all_modes,
+                &all_modes,
+                &target_set
+            ),
+            vec![
+                (Mode::SetReconciliation, SchemaIdSet::new(&[schema_id_1])),
+                (Mode::LogHeight, SchemaIdSet::new(&[schema_id_2])),
+            ]
+        );
+
+        // Peer specific preferences apply first
+        assert_eq!(
+            select_modes(
+                &preferences,
+                &Mode::SetReconciliation,
+                &other_peer_id,
+                &all_modes,
+                &all_modes,
+                &target_set
+            ),
+            vec![(Mode::LogHeight, target_set.clone())]
+        );
+
+        // Fall back to log-height when remote peer does not support preferred mode
+        assert_eq!(
+            select_modes(
+                &preferences,
+                &Mode::SetReconciliation,
+                &peer_id,
+                &all_modes,
+                &[Mode::LogHeight],
+                &target_set
+            ),
+            vec![(Mode::LogHeight, target_set)]
+        );
+    }
 }
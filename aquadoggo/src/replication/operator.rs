@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use ed25519_dalek::Signature;
+use libp2p::PeerId;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use serde::ser::SerializeSeq;
+use serde::Serialize;
+
+use crate::replication::OPERATOR_PROOF_TYPE;
+
+/// Returns the bytes an operator signs to prove control of its key pair towards a peer.
+///
+/// The signed bytes contain the peer ids of both nodes, proofs can't be replayed towards other
+/// peers or by other nodes.
+fn operator_proof_bytes(sender: &PeerId, receiver: &PeerId) -> Vec<u8> {
+    format!("aquadoggo/operator/{}/{}", sender, receiver).into_bytes()
+}
+
+/// Proof of a node that it is run by the operator holding this public key.
+///
+/// Operator keys are independent from the key pairs identifying nodes on the network, one
+/// organization can run many nodes with the same operator key. Nodes send the proof to every peer
+/// they connect to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperatorProof {
+    /// Public key of the operator.
+    pub public_key: PublicKey,
+
+    /// Signature of the operator over the peer ids of sending and receiving node.
+    pub signature: Signature,
+}
+
+impl OperatorProof {
+    /// Returns a proof of the operator key pair for the connection with a remote peer.
+    pub fn new(key_pair: &KeyPair, local_peer_id: &PeerId, remote_peer_id: &PeerId) -> Self {
+        Self {
+            public_key: key_pair.public_key(),
+            signature: key_pair.sign(&operator_proof_bytes(local_peer_id, remote_peer_id)),
+        }
+    }
+
+    /// Returns true if the remote peer signed this proof with the operator key pair for the
+    /// connection with us.
+    pub fn verify(&self, remote_peer_id: &PeerId, local_peer_id: &PeerId) -> bool {
+        KeyPair::verify(
+            &self.public_key,
+            &operator_proof_bytes(remote_peer_id, local_peer_id),
+            &self.signature,
+        )
+        .is_ok()
+    }
+}
+
+impl Serialize for OperatorProof {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(3))?;
+        seq.serialize_element(&OPERATOR_PROOF_TYPE)?;
+        seq.serialize_element(&self.public_key)?;
+        seq.serialize_element(serde_bytes::Bytes::new(&self.signature.to_bytes()))?;
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ciborium::cbor;
+    use libp2p::PeerId;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::serde::{serialize_from, serialize_value};
+    use p2panda_rs::test_utils::fixtures::key_pair;
+    use rstest::rstest;
+
+    use super::OperatorProof;
+
+    #[rstest]
+    fn verify_proofs(key_pair: KeyPair) {
+        let local_peer_id =
+            PeerId::from_str("12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY").unwrap();
+        let remote_peer_id =
+            PeerId::from_str("12D3KooWCqtLMJQLY3sm9rpDampJ2nPLswPPZto3mrRY7794QATF").unwrap();
+
+        let proof = OperatorProof::new(&key_pair, &remote_peer_id, &local_peer_id);
+        assert!(proof.verify(&remote_peer_id, &local_peer_id));
+
+        // Proofs are only valid for the connection they were made for
+        assert!(!proof.verify(&local_peer_id, &remote_peer_id));
+        assert!(!proof.verify(&PeerId::random(), &local_peer_id));
+
+        let forged = OperatorProof {
+            public_key: KeyPair::new().public_key(),
+            ..proof.clone()
+        };
+        assert!(!forged.verify(&remote_peer_id, &local_peer_id));
+
+        assert_eq!(
+            serialize_from(proof.clone()),
+            serialize_value(cbor!([
+                40,
+                serde_bytes::Bytes::new(&proof.public_key.to_bytes()),
+                serde_bytes::Bytes::new(&proof.signature.to_bytes())
+            ]))
+        );
+    }
+}
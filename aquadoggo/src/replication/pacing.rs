@@ -0,0 +1,259 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::replication::compression::MAX_BATCH_SIZE;
+use crate::replication::{Message, SessionId, SyncMessage};
+
+/// Weight of a new sample in the smoothed round-trip time, as recommended by RFC 6298.
+const RTT_ALPHA: f64 = 0.125;
+
+/// Size in bytes of entry batches sent to peers with short round-trip times.
+pub const MIN_BATCH_SIZE: usize = 32 * 1024;
+
+/// Round-trip time up to which the smallest batches are used.
+const LOW_RTT: Duration = Duration::from_millis(10);
+
+/// Round-trip time from which on the largest batches are used.
+const HIGH_RTT: Duration = Duration::from_millis(250);
+
+/// Number of entry messages in flight per round trip before the window adapted to a peer.
+const INITIAL_WINDOW: usize = 4;
+
+/// Maximum number of entry messages in flight per round trip.
+const MAX_WINDOW: usize = 64;
+
+/// Round trips taking longer than this multiple of the shortest one observed indicate that
+/// messages queue up on the way or that the peer can't keep up with ingesting our entries.
+const QUEUEING_FACTOR: u32 = 2;
+
+/// Paces outgoing entries to a peer based on the measured round-trip time.
+///
+/// Peers far away receive larger batches of entries to keep the connection busy while waiting for
+/// responses, close peers receive smaller ones which they can ingest right away. The number of
+/// entry messages sent per round trip grows by one with every sample which is close to the
+/// shortest round trip observed and is halved when round trips get longer, as slow peers take
+/// longer to respond when they are overwhelmed.
+///
+/// As long as no round trip was measured all messages are sent right away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerPacing {
+    /// Smoothed round-trip time.
+    srtt: Option<Duration>,
+
+    /// Shortest round-trip time observed.
+    min_rtt: Option<Duration>,
+
+    /// Number of entry messages sent per round trip.
+    window: usize,
+
+    /// Messages waiting to be sent, in order.
+    queue: VecDeque<SyncMessage>,
+
+    /// Point in time when the next window of entry messages can be sent.
+    next_release: Option<Instant>,
+}
+
+impl PeerPacing {
+    pub fn new() -> Self {
+        Self {
+            srtt: None,
+            min_rtt: None,
+            window: INITIAL_WINDOW,
+            queue: VecDeque::new(),
+            next_release: None,
+        }
+    }
+
+    /// Smoothed round-trip time to this peer, if measured yet.
+    pub fn srtt(&self) -> Option<Duration> {
+        self.srtt
+    }
+
+    /// Number of entry messages sent per round trip.
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Number of messages waiting to be sent.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Adapt pacing to a newly measured round-trip time.
+    pub fn on_rtt_sample(&mut self, sample: Duration) {
+        let srtt = match self.srtt {
+            Some(srtt) => srtt.mul_f64(1.0 - RTT_ALPHA) + sample.mul_f64(RTT_ALPHA),
+            None => sample,
+        };
+        let min_rtt = self.min_rtt.map_or(sample, |min_rtt| min_rtt.min(sample));
+
+        self.window = if sample > min_rtt * QUEUEING_FACTOR {
+            (self.window / 2).max(1)
+        } else {
+            (self.window + 1).min(MAX_WINDOW)
+        };
+
+        self.srtt = Some(srtt);
+        self.min_rtt = Some(min_rtt);
+    }
+
+    /// Maximum size in bytes of entry batches sent to this peer.
+    pub fn batch_size(&self) -> usize {
+        let srtt = match self.srtt {
+            Some(srtt) => srtt,
+            None => return MAX_BATCH_SIZE,
+        };
+
+        if srtt <= LOW_RTT {
+            return MIN_BATCH_SIZE;
+        }
+
+        if srtt >= HIGH_RTT {
+            return MAX_BATCH_SIZE;
+        }
+
+        let ratio = (srtt - LOW_RTT).as_secs_f64() / (HIGH_RTT - LOW_RTT).as_secs_f64();
+        MIN_BATCH_SIZE + ((MAX_BATCH_SIZE - MIN_BATCH_SIZE) as f64 * ratio) as usize
+    }
+
+    /// Queue messages for sending them to this peer.
+    pub fn push(&mut self, messages: Vec<SyncMessage>) {
+        self.queue.extend(messages);
+    }
+
+    /// Returns all queued messages which can be sent at the given point in time.
+    ///
+    /// Messages are released in order, at most one window of entry messages per round trip.
+    pub fn release(&mut self, now: Instant) -> Vec<SyncMessage> {
+        let srtt = match self.srtt {
+            Some(srtt) => srtt,
+            None => return self.queue.drain(..).collect(),
+        };
+
+        let mut available = match self.next_release {
+            Some(next_release) if now < next_release => 0,
+            _ => self.window,
+        };
+
+        let mut released = Vec::new();
+        let mut released_entries = false;
+
+        while let Some(message) = self.queue.front() {
+            if is_entry(message) {
+                if available == 0 {
+                    break;
+                }
+
+                available -= 1;
+                released_entries = true;
+            }
+
+            released.extend(self.queue.pop_front());
+        }
+
+        if released_entries {
+            self.next_release = Some(now + srtt);
+        }
+
+        released
+    }
+
+    /// Drop all queued messages of a session.
+    pub fn discard(&mut self, session_id: SessionId) {
+        self.queue
+            .retain(|message| message.session_id() != session_id);
+    }
+}
+
+impl Default for PeerPacing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_entry(message: &SyncMessage) -> bool {
+    matches!(
+        message.message(),
+        Message::Entry(_, _) | Message::Entries(_, _)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::replication::compression::MAX_BATCH_SIZE;
+    use crate::replication::{Compression, Message, SyncMessage};
+
+    use super::{PeerPacing, INITIAL_WINDOW, MIN_BATCH_SIZE};
+
+    fn entries(session_id: u64, count: usize) -> Vec<SyncMessage> {
+        (0..count)
+            .map(|_| SyncMessage::new(session_id, Message::Entries(Compression::Zstd, vec![])))
+            .collect()
+    }
+
+    #[test]
+    fn adapts_to_round_trip_time() {
+        let mut pacing = PeerPacing::new();
+        assert_eq!(pacing.srtt(), None);
+        assert_eq!(pacing.batch_size(), MAX_BATCH_SIZE);
+
+        // Close peers receive small batches
+        pacing.on_rtt_sample(Duration::from_millis(5));
+        assert_eq!(pacing.srtt(), Some(Duration::from_millis(5)));
+        assert_eq!(pacing.batch_size(), MIN_BATCH_SIZE);
+        assert_eq!(pacing.window(), INITIAL_WINDOW + 1);
+
+        // Longer round trips shrink the window
+        pacing.on_rtt_sample(Duration::from_millis(85));
+        assert_eq!(pacing.srtt(), Some(Duration::from_millis(15)));
+        assert_eq!(pacing.window(), (INITIAL_WINDOW + 1) / 2);
+
+        // Distant peers receive larger batches
+        let mut distant = PeerPacing::new();
+        distant.on_rtt_sample(Duration::from_millis(130));
+        assert!(distant.batch_size() > MIN_BATCH_SIZE);
+        assert!(distant.batch_size() < MAX_BATCH_SIZE);
+
+        let mut distant = PeerPacing::new();
+        distant.on_rtt_sample(Duration::from_millis(300));
+        assert_eq!(distant.batch_size(), MAX_BATCH_SIZE);
+    }
+
+    #[test]
+    fn paces_entries() {
+        let now = Instant::now();
+
+        // Without a measured round-trip time all messages are sent right away
+        let mut pacing = PeerPacing::new();
+        pacing.push(entries(0, 10));
+        assert_eq!(pacing.release(now).len(), 10);
+
+        pacing.on_rtt_sample(Duration::from_millis(100));
+        let window = pacing.window();
+
+        let mut messages = entries(0, window * 2);
+        messages.push(SyncMessage::new(0, Message::SyncDone(false)));
+        pacing.push(messages);
+
+        // One window of entries is sent per round trip
+        assert_eq!(pacing.release(now).len(), window);
+        assert_eq!(pacing.release(now + Duration::from_millis(50)).len(), 0);
+
+        // Messages following the entries are sent with the last window
+        assert_eq!(
+            pacing.release(now + Duration::from_millis(100)).len(),
+            window + 1
+        );
+        assert_eq!(pacing.queued(), 0);
+
+        // Messages of closed sessions are dropped
+        pacing.push(entries(1, window * 2));
+        pacing.push(entries(2, 1));
+        pacing.discard(1);
+        assert_eq!(pacing.queued(), 1);
+    }
+}
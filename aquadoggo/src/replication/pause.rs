@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use libp2p::PeerId;
+use p2panda_rs::schema::SchemaId;
+
+use crate::replication::SchemaIdSet;
+
+/// Scope of replication which is paused, for example during maintenance of the node.
+///
+/// While a pause is active no new replication sessions concerning its peer and schema ids are
+/// initiated or accepted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicationPause {
+    /// Peer replication is paused with, applies to all peers when not set.
+    pub peer_id: Option<PeerId>,
+
+    /// Schema ids replication is paused for, applies to all schema ids when empty.
+    pub schema_ids: Vec<SchemaId>,
+}
+
+impl ReplicationPause {
+    /// Returns true if replicating this schema id with this peer is paused.
+    pub fn matches(&self, peer_id: &PeerId, schema_id: &SchemaId) -> bool {
+        let is_peer_matching = self.peer_id.is_none_or(|id| &id == peer_id);
+        let is_schema_matching = self.schema_ids.is_empty() || self.schema_ids.contains(schema_id);
+        is_peer_matching && is_schema_matching
+    }
+
+    /// Returns true if replicating any schema id of this target set with this peer is paused.
+    pub fn affects(&self, peer_id: &PeerId, target_set: &SchemaIdSet) -> bool {
+        target_set
+            .iter()
+            .any(|schema_id| self.matches(peer_id, schema_id))
+    }
+
+    /// Returns true if the given pause lies fully within this scope, resuming this scope lifts
+    /// it.
+    pub fn covers(&self, other: &ReplicationPause) -> bool {
+        let is_peer_covered = self.peer_id.is_none() || self.peer_id == other.peer_id;
+        let is_schema_covered = self.schema_ids.is_empty()
+            || (!other.schema_ids.is_empty()
+                && other
+                    .schema_ids
+                    .iter()
+                    .all(|schema_id| self.schema_ids.contains(schema_id)));
+        is_peer_covered && is_schema_covered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use libp2p::PeerId;
+    use p2panda_rs::schema::{SchemaId, SchemaName};
+    use p2panda_rs::test_utils::fixtures::random_document_view_id;
+
+    use super::ReplicationPause;
+
+    #[test]
+    fn matches_and_covers_scopes() {
+        let peer_id =
+            PeerId::from_str("12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY").unwrap();
+        let other_peer_id =
+            PeerId::from_str("12D3KooWCqtLMJQLY3sm9rpDampJ2nPLswPPZto3mrRY7794QATF").unwrap();
+        let schema_id =
+            SchemaId::Application(SchemaName::new("venue").unwrap(), random_document_view_id());
+        let other_schema_id =
+            SchemaId::Application(SchemaName::new("event").unwrap(), random_document_view_id());
+
+        let everything = ReplicationPause::default();
+        assert!(everything.matches(&peer_id, &schema_id));
+        assert!(everything.matches(&other_peer_id, &other_schema_id));
+
+        let peer_pause = ReplicationPause {
+            peer_id: Some(peer_id),
+            schema_ids: vec![],
+        };
+        assert!(peer_pause.matches(&peer_id, &other_schema_id));
+        assert!(!peer_pause.matches(&other_peer_id, &schema_id));
+
+        let schema_pause = ReplicationPause {
+            peer_id: Some(peer_id),
+            schema_ids: vec![schema_id.clone()],
+        };
+        assert!(schema_pause.matches(&peer_id, &schema_id));
+        assert!(!schema_pause.matches(&peer_id, &other_schema_id));
+
+        // Resuming everything lifts all pauses, resuming a schema does not lift a pause of a peer
+        assert!(everything.covers(&peer_pause));
+        assert!(everything.covers(&schema_pause));
+        assert!(peer_pause.covers(&schema_pause));
+        assert!(!schema_pause.covers(&peer_pause));
+        assert!(!peer_pause.covers(&everything));
+    }
+}
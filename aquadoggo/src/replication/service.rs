@@ -1,11 +1,14 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use std::collections::HashMap;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use libp2p::PeerId;
 use log::{debug, info, trace, warn};
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::identity::{KeyPair, PublicKey};
+use p2panda_rs::schema::SchemaId;
 use p2panda_rs::Human;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
@@ -19,13 +22,16 @@ use crate::context::Context;
 use crate::db::SqlStore;
 use crate::manager::{ServiceReadySender, Shutdown};
 use crate::network::identity::to_libp2p_peer_id;
-use crate::network::{Peer, PeerMessage};
-use crate::replication::errors::ReplicationError;
+use crate::network::{ConnectionDirection, Peer, PeerMessage};
+use crate::replication::errors::{IngestError, ReplicationError};
 use crate::replication::{
-    now, Announcement, AnnouncementMessage, Message, Mode, SchemaIdSet, Session, SessionId,
-    SyncIngest, SyncManager, SyncMessage,
+    blob_schema_ids, compress_entries, decompress_entries, now, select_direction, select_modes,
+    Announcement, AnnouncementMessage, BlobRequests, Compression, DirectionPreference, Message,
+    Mode, ModePreference, OperatorProof, PeerPacing, ReplicationPause, SchemaIdSet, Session,
+    SessionId, SyncIngest, SyncManager, SyncMessage, SUPPORTED_MODES,
 };
 use crate::schema::SchemaProvider;
+use crate::AllowList;
 
 /// Maximum number of peers we replicate with at one a time.
 const MAX_PEER_SAMPLE: usize = 3;
@@ -36,6 +42,9 @@ const MAX_SESSIONS_PER_PEER: usize = 3;
 /// How often does the scheduler check for initiating replication sessions with peers.
 const UPDATE_INTERVAL: Duration = Duration::from_secs(5);
 
+/// How often do we check if paced replication messages can be sent to peers.
+const PACING_INTERVAL: Duration = Duration::from_millis(20);
+
 pub async fn replication_service(
     context: Context,
     shutdown: Shutdown,
@@ -44,11 +53,24 @@ pub async fn replication_service(
 ) -> Result<()> {
     let _rx = tx.subscribe();
 
+    let operator_key_pair = match &context.config.network.operator_private_key {
+        Some(private_key) => Some(KeyPair::from_private_key_str(private_key)?),
+        None => None,
+    };
+
     let manager = ConnectionManager::new(
         &context.schema_provider,
         &context.store,
         &tx,
         to_libp2p_peer_id(&context.key_pair.public_key()),
+        &context.config.compression,
+        &context.config.replication_mode,
+        &context.config.replication_modes,
+        &context.config.replication_directions,
+    )
+    .with_operator(
+        operator_key_pair,
+        &context.config.network.allow_operator_keys,
     );
     let handle = task::spawn(manager.run());
 
@@ -82,6 +104,12 @@ struct PeerStatus {
 
     /// Number of failed replication sessions.
     failed_count: usize,
+
+    /// Round-trip time to this peer and replication messages waiting to be sent to it.
+    pacing: PeerPacing,
+
+    /// Operator public key this peer proved control of.
+    operator: Option<PublicKey>,
 }
 
 impl PeerStatus {
@@ -92,6 +120,8 @@ impl PeerStatus {
             sent_our_announcement_timestamp: 0,
             successful_count: 0,
             failed_count: 0,
+            pacing: PeerPacing::new(),
+            operator: None,
         }
     }
 }
@@ -115,9 +145,25 @@ struct ConnectionManager {
     /// logic.
     sync_manager: SyncManager<Peer>,
 
+    /// Blobs we requested from peers outside of replication sessions.
+    blob_requests: BlobRequests<Peer>,
+
+    /// Data ingest for entries of requested blobs.
+    ingest: SyncIngest,
+
+    /// SQL database.
+    store: SqlStore,
+
     /// Async stream giving us a regular interval to initiate new replication sessions.
     scheduler: IntervalStream,
 
+    /// Async stream giving us a regular interval to send paced replication messages.
+    pacer: IntervalStream,
+
+    /// Sessions we initiated and haven't received a response for yet, with the time we sent the
+    /// first message. The first response gives us a sample of the round-trip time to the peer.
+    pending_round_trips: HashMap<(Peer, SessionId), Instant>,
+
     /// Receiver for messages from other services, for example the networking layer.
     tx: ServiceSender,
 
@@ -130,32 +176,99 @@ struct ConnectionManager {
     /// Our latest announcement state we want to propagate to all current and future peers. It
     /// contains a list of schema ids we're supporting as a node.
     announcement: Option<Announcement>,
+
+    /// Compression algorithms we offer to peers for replication messages, ordered by preference.
+    supported_compressions: Vec<Compression>,
+
+    /// Replication mode we use when no preference matches.
+    replication_mode: Mode,
+
+    /// Preferred replication modes for certain peers and schema ids.
+    mode_preferences: Vec<ModePreference>,
+
+    /// Directions in which we exchange entries with certain peers.
+    direction_preferences: Vec<DirectionPreference>,
+
+    /// Currently paused replication scopes, no new sessions are initiated or accepted within
+    /// them.
+    pauses: Vec<ReplicationPause>,
+
+    /// Peer id of our node, operator proofs are bound to it.
+    local_peer_id: PeerId,
+
+    /// Operator key pair we prove control of to every connected peer.
+    operator_key_pair: Option<KeyPair>,
+
+    /// Operator keys of peers we replicate with.
+    allow_operator_keys: AllowList<PublicKey>,
 }
 
 impl ConnectionManager {
     /// Returns a new instance of `ConnectionManager`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         schema_provider: &SchemaProvider,
         store: &SqlStore,
         tx: &ServiceSender,
         local_peer_id: PeerId,
+        supported_compressions: &[Compression],
+        replication_mode: &Mode,
+        mode_preferences: &[ModePreference],
+        direction_preferences: &[DirectionPreference],
     ) -> Self {
+        // Modes which are not implemented are never selected for replication sessions
+        for mode in std::iter::once(replication_mode)
+            .chain(mode_preferences.iter().map(|preference| &preference.mode))
+            .filter(|mode| !SUPPORTED_MODES.contains(mode))
+        {
+            warn!(
+                "Replication mode {} is not supported and will never be used",
+                mode.display()
+            );
+        }
+
         let local_peer = Peer::new_local_peer(local_peer_id);
         let ingest = SyncIngest::new(schema_provider.clone(), tx.clone());
-        let sync_manager = SyncManager::new(store.clone(), ingest, local_peer);
+        let sync_manager = SyncManager::new(store.clone(), ingest.clone(), local_peer);
         let scheduler = IntervalStream::new(interval(UPDATE_INTERVAL));
+        let pacer = IntervalStream::new(interval(PACING_INTERVAL));
 
         Self {
             peers: HashMap::new(),
             sync_manager,
+            blob_requests: BlobRequests::new(),
+            ingest,
+            store: store.clone(),
             scheduler,
+            pacer,
+            pending_round_trips: HashMap::new(),
             tx: tx.clone(),
             rx: BroadcastStream::new(tx.subscribe()),
             schema_provider: schema_provider.clone(),
             announcement: None,
+            supported_compressions: supported_compressions.to_vec(),
+            replication_mode: replication_mode.to_owned(),
+            mode_preferences: mode_preferences.to_vec(),
+            direction_preferences: direction_preferences.to_vec(),
+            pauses: Vec::new(),
+            local_peer_id,
+            operator_key_pair: None,
+            allow_operator_keys: AllowList::Wildcard,
         }
     }
 
+    /// Prove control of an operator key pair to peers and only replicate with peers proving
+    /// control of one of the allowed operator keys.
+    pub fn with_operator(
+        mut self,
+        operator_key_pair: Option<KeyPair>,
+        allow_operator_keys: &AllowList<PublicKey>,
+    ) -> Self {
+        self.operator_key_pair = operator_key_pair;
+        self.allow_operator_keys = allow_operator_keys.to_owned();
+        self
+    }
+
     /// Returns set of schema ids we are interested in and support on this node.
     async fn supported_schema_ids(&self) -> SchemaIdSet {
         let supported_schema_ids = self.schema_provider.supported_schema_ids().await;
@@ -172,11 +285,60 @@ impl ConnectionManager {
             }
             None => {
                 self.peers.insert(peer, PeerStatus::new(peer));
+                self.sync_manager.set_direction(
+                    &peer,
+                    select_direction(&self.direction_preferences, &peer.id()),
+                );
+
+                if let Some(key_pair) = &self.operator_key_pair {
+                    let proof = OperatorProof::new(key_pair, &self.local_peer_id, &peer.id());
+                    self.send_service_message(ServiceMessage::SentMessage(
+                        peer,
+                        PeerMessage::OperatorProof(proof),
+                    ));
+                }
+
                 self.on_update().await;
             }
         }
     }
 
+    /// Handle the proof of a peer that it is run by the holder of an operator key.
+    ///
+    /// Peers sending invalid proofs are treated like failed replication sessions.
+    fn on_operator_proof(&mut self, peer: Peer, proof: OperatorProof) {
+        if !proof.verify(&peer.id(), &self.local_peer_id) {
+            warn!(
+                "Received invalid operator proof from peer: {}",
+                peer.display()
+            );
+            self.send_service_message(ServiceMessage::ReplicationFailed(peer));
+            return;
+        }
+
+        if let Some(status) = self.peers.get_mut(&peer) {
+            info!(
+                "Peer {} proved control of operator key {}",
+                peer.display(),
+                proof.public_key.display()
+            );
+            status.operator = Some(proof.public_key);
+        }
+    }
+
+    /// Returns true if we replicate with this peer based on the operator key it proved control
+    /// of.
+    fn is_operator_allowed(&self, peer: &Peer) -> bool {
+        match &self.allow_operator_keys {
+            AllowList::Wildcard => true,
+            AllowList::Set(public_keys) => self
+                .peers
+                .get(peer)
+                .and_then(|status| status.operator.as_ref())
+                .is_some_and(|public_key| public_keys.contains(public_key)),
+        }
+    }
+
     /// Routines which get executed on every scheduler beat and newly established connection.
     async fn on_update(&mut self) {
         // Inform new peers about our supported protocol version and schema ids
@@ -192,6 +354,15 @@ impl ConnectionManager {
 
         // Clear running replication sessions from sync manager
         self.sync_manager.remove_sessions(&peer);
+        self.sync_manager.remove_direction(&peer);
+        self.pending_round_trips
+            .retain(|(pending_peer, _), _| pending_peer != &peer);
+
+        // Blobs requested from this peer won't arrive anymore
+        for document_id in self.blob_requests.remove_peer(&peer) {
+            self.send_service_message(ServiceMessage::BlobUnavailable(document_id));
+        }
+
         self.remove_connection(peer)
     }
 
@@ -241,9 +412,42 @@ impl ConnectionManager {
     async fn on_replication_message(&mut self, peer: Peer, message: SyncMessage) {
         let session_id = message.session_id();
 
+        // Blob requests and their responses are exchanged outside of replication sessions
+        if let Message::BlobRequest(document_id) = message.message() {
+            self.on_blob_request(peer, session_id, document_id).await;
+            return;
+        }
+
+        if self.blob_requests.contains(&peer, session_id) {
+            self.on_blob_response(peer, session_id, message.message())
+                .await;
+            return;
+        }
+
+        // The first response to a session we initiated completes a round trip
+        if let Some(sent_at) = self.pending_round_trips.remove(&(peer, session_id)) {
+            if let Some(status) = self.peers.get_mut(&peer) {
+                status.pacing.on_rtt_sample(sent_at.elapsed());
+            }
+        }
+
         // If this is a SyncRequest message first we check if the contained target set matches our
         // own locally configured one.
-        if let Message::SyncRequest(_, target_set) = message.message() {
+        let requested_target_set = match message.message() {
+            Message::SyncRequest(_, target_set, _) => Some(target_set.clone()),
+            _ => None,
+        };
+
+        if let Some(target_set) = &requested_target_set {
+            // Refuse new sessions from peers which didn't prove control of an allowed operator
+            // key.
+            if !self.is_operator_allowed(&peer) {
+                self.on_replication_error(peer, session_id, ReplicationError::UnknownOperator)
+                    .await;
+
+                return;
+            }
+
             let local_supported_schema_ids = &self
                 .announcement
                 .as_ref()
@@ -262,20 +466,42 @@ impl ConnectionManager {
 
                 return;
             }
+
+            // Refuse new sessions while replicating any of the requested schema ids with this
+            // peer is paused.
+            if self
+                .pauses
+                .iter()
+                .any(|pause| pause.affects(&peer.id(), target_set))
+            {
+                self.on_replication_error(peer, session_id, ReplicationError::Paused)
+                    .await;
+
+                return;
+            }
         }
 
         match self.sync_manager.handle_message(&peer, &message).await {
             Ok(result) => {
-                for message in result.messages {
-                    self.send_service_message(ServiceMessage::SentMessage(
+                self.send_paced(peer, result.messages);
+
+                // Remote peer requested a new session which we accepted
+                if let Some(target_set) = requested_target_set {
+                    self.send_service_message(ServiceMessage::SessionEstablished(
                         peer,
-                        PeerMessage::SyncMessage(message),
+                        ConnectionDirection::Inbound,
+                        target_set,
                     ));
                 }
 
                 if result.is_done {
                     self.on_replication_finished(peer, session_id).await;
                 }
+
+                if !result.missing_schema_ids.is_empty() {
+                    self.request_schema_definitions(peer, &result.missing_schema_ids)
+                        .await;
+                }
             }
             Err(err) => {
                 self.on_replication_error(peer, session_id, err).await;
@@ -283,6 +509,98 @@ impl ConnectionManager {
         }
     }
 
+    /// Queue outgoing replication messages for a peer and send as many of them as its pacing
+    /// allows right away.
+    fn send_paced(&mut self, peer: Peer, messages: Vec<SyncMessage>) {
+        let messages = self.compress_messages(&peer, messages);
+
+        match self.peers.get_mut(&peer) {
+            Some(status) => {
+                status.pacing.push(messages);
+                self.release_paced(peer);
+            }
+            None => {
+                for message in messages {
+                    self.send_service_message(ServiceMessage::SentMessage(
+                        peer,
+                        PeerMessage::SyncMessage(message),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Send all queued replication messages of a peer which are due.
+    fn release_paced(&mut self, peer: Peer) {
+        let messages = match self.peers.get_mut(&peer) {
+            Some(status) => status.pacing.release(Instant::now()),
+            None => return,
+        };
+
+        for message in messages {
+            self.send_service_message(ServiceMessage::SentMessage(
+                peer,
+                PeerMessage::SyncMessage(message),
+            ));
+        }
+    }
+
+    /// Send queued replication messages of all peers which are due.
+    fn on_pacing(&mut self) {
+        let peers: Vec<Peer> = self
+            .peers
+            .iter()
+            .filter(|(_, status)| status.pacing.queued() > 0)
+            .map(|(peer, _)| *peer)
+            .collect();
+
+        for peer in peers {
+            self.release_paced(peer);
+        }
+    }
+
+    /// Batch and compress entries of outgoing replication messages when the remote peer supports
+    /// one of our compression algorithms.
+    ///
+    /// Batches are sized according to the round-trip time to the peer.
+    fn compress_messages(&self, peer: &Peer, messages: Vec<SyncMessage>) -> Vec<SyncMessage> {
+        let (remote_compressions, max_batch_size) = match self.peers.get(peer) {
+            Some(PeerStatus {
+                announcement: Some(announcement),
+                pacing,
+                ..
+            }) => (&announcement.supported_compressions, pacing.batch_size()),
+            _ => return messages,
+        };
+
+        let compression =
+            match Compression::negotiate(&self.supported_compressions, remote_compressions) {
+                Some(compression) => compression,
+                None => return messages,
+            };
+
+        // Messages are grouped by session, batches should never contain entries of different
+        // sessions
+        let mut sessions: Vec<(SessionId, Vec<Message>)> = Vec::new();
+        for message in messages {
+            match sessions.last_mut() {
+                Some((session_id, session_messages)) if *session_id == message.session_id() => {
+                    session_messages.push(message.message().to_owned());
+                }
+                _ => sessions.push((message.session_id(), vec![message.message().to_owned()])),
+            }
+        }
+
+        sessions
+            .into_iter()
+            .flat_map(|(session_id, messages)| {
+                compress_entries(compression, messages, max_batch_size)
+                    .into_iter()
+                    .map(move |message| SyncMessage::new(session_id, message))
+            })
+            .collect()
+    }
+
     /// Handle successful replication sessions.
     async fn on_replication_finished(&mut self, peer: Peer, _session_id: SessionId) {
         debug!("Finished replication with peer {}", peer.display());
@@ -313,6 +631,7 @@ impl ConnectionManager {
         match self.peers.get_mut(&peer) {
             Some(status) => {
                 status.failed_count += 1;
+                status.pacing.discard(session_id);
             }
             None => {
                 panic!("Tried to access unknown peer");
@@ -320,15 +639,271 @@ impl ConnectionManager {
         }
 
         self.sync_manager.remove_session(&peer, &session_id);
+        self.pending_round_trips.remove(&(peer, session_id));
 
         // Inform network service about error, so it can accordingly react
         self.send_service_message(ServiceMessage::ReplicationFailed(peer));
     }
 
+    /// Replicate schema definitions with a peer which sent us documents of schemas we don't know
+    /// yet.
+    ///
+    /// Without this the documents would only be ingested after the schema definitions arrived in
+    /// a regularly scheduled session, until then they are held back by the sync manager.
+    async fn request_schema_definitions(&mut self, peer: Peer, schema_ids: &[SchemaId]) {
+        // Nodes with an allow-list only accept documents of already known schemas
+        if self.schema_provider.is_allow_list_active() {
+            return;
+        }
+
+        let target_set = SchemaIdSet::new(&[
+            SchemaId::SchemaDefinition(1),
+            SchemaId::SchemaFieldDefinition(1),
+        ]);
+
+        if self
+            .pauses
+            .iter()
+            .any(|pause| pause.affects(&peer.id(), &target_set))
+        {
+            return;
+        }
+
+        // Schema definitions are delivered anyhow when we're already replicating them
+        if self
+            .sync_manager
+            .get_sessions(&peer)
+            .iter()
+            .any(|session| session.target_set() == target_set)
+        {
+            return;
+        }
+
+        debug!(
+            "Request schema definitions of {} from peer {}",
+            schema_ids
+                .iter()
+                .map(|schema_id| schema_id.display())
+                .collect::<Vec<String>>()
+                .join(", "),
+            peer.display()
+        );
+
+        self.initiate_replication(&peer, &target_set, &Mode::LogHeight)
+            .await;
+    }
+
+    /// Request a blob which is not materialized on this node from one of the connected peers
+    /// supporting blobs.
+    ///
+    /// Other services get informed with a `BlobUnavailable` message when no peer can provide it.
+    fn on_blob_requested(&mut self, document_id: DocumentId) {
+        if self.blob_requests.is_requested(&document_id) {
+            return;
+        }
+
+        let target_set = blob_schema_ids();
+        let is_supported = self.announcement.as_ref().is_some_and(|announcement| {
+            announcement.supported_schema_ids.is_valid_set(&target_set)
+        });
+
+        let candidates: Vec<Peer> = self
+            .peers
+            .iter()
+            .filter(|(peer, status)| {
+                status.announcement.as_ref().is_some_and(|announcement| {
+                    announcement.supported_schema_ids.is_valid_set(&target_set)
+                }) && self.is_operator_allowed(peer)
+                    && !self
+                        .pauses
+                        .iter()
+                        .any(|pause| pause.affects(&peer.id(), &target_set))
+            })
+            .map(|(peer, _)| *peer)
+            .collect();
+
+        let peer = match candidates.choose(&mut thread_rng()) {
+            Some(peer) if is_supported => *peer,
+            _ => {
+                debug!("No peer available to request blob {} from", document_id);
+                self.send_service_message(ServiceMessage::BlobUnavailable(document_id));
+                return;
+            }
+        };
+
+        debug!(
+            "Request blob {} from peer {}",
+            document_id.display(),
+            peer.display()
+        );
+
+        let session_id = self.blob_requests.insert(&peer, &document_id);
+        self.send_service_message(ServiceMessage::SentMessage(
+            peer,
+            PeerMessage::SyncMessage(SyncMessage::new(
+                session_id,
+                Message::BlobRequest(document_id),
+            )),
+        ));
+    }
+
+    /// Answer a blob request of a peer with the entries of the blob document and its pieces.
+    ///
+    /// The response is empty when we don't have the blob, don't support blobs, replicating them
+    /// with this peer is paused or it didn't prove control of an allowed operator key.
+    async fn on_blob_request(
+        &mut self,
+        peer: Peer,
+        session_id: SessionId,
+        document_id: &DocumentId,
+    ) {
+        let target_set = blob_schema_ids();
+        let is_supported = self.announcement.as_ref().is_some_and(|announcement| {
+            announcement.supported_schema_ids.is_valid_set(&target_set)
+        });
+        let is_paused = self
+            .pauses
+            .iter()
+            .any(|pause| pause.affects(&peer.id(), &target_set))
+            || !self.is_operator_allowed(&peer);
+
+        let mut messages = Vec::new();
+
+        if is_supported && !is_paused {
+            match self.store.get_blob_entries(document_id).await {
+                Ok(entries) => {
+                    messages.extend(entries.into_iter().map(|entry| {
+                        SyncMessage::new(
+                            session_id,
+                            Message::Entry(entry.encoded_entry.clone(), entry.payload().cloned()),
+                        )
+                    }));
+                }
+                Err(err) => {
+                    warn!("Could not collect entries of blob {}: {}", document_id, err);
+                }
+            }
+        }
+
+        debug!(
+            "Respond to request of peer {} for blob {} with {} entries",
+            peer.display(),
+            document_id.display(),
+            messages.len()
+        );
+
+        messages.push(SyncMessage::new(session_id, Message::SyncDone(false)));
+        self.send_paced(peer, messages);
+    }
+
+    /// Ingest entries a peer sent in response to our blob request.
+    async fn on_blob_response(&mut self, peer: Peer, session_id: SessionId, message: &Message) {
+        let entries = match message {
+            Message::Entry(entry_bytes, operation_bytes) => {
+                vec![(entry_bytes.to_owned(), operation_bytes.to_owned())]
+            }
+            Message::Entries(compression, bytes) => match decompress_entries(*compression, bytes) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    warn!("Could not read entries of requested blob: {}", err);
+                    self.blob_requests.on_entry(&peer, session_id, false);
+                    return;
+                }
+            },
+            Message::SyncDone(_) => {
+                if let Some((document_id, is_complete)) =
+                    self.blob_requests.remove(&peer, session_id)
+                {
+                    if !is_complete {
+                        self.send_service_message(ServiceMessage::BlobUnavailable(document_id));
+                    }
+                }
+                return;
+            }
+            message => {
+                warn!(
+                    "Received unexpected message in response to blob request: {}",
+                    message.display()
+                );
+                return;
+            }
+        };
+
+        for (entry_bytes, operation_bytes) in entries {
+            let is_ingested = match &operation_bytes {
+                Some(encoded_operation) => match self
+                    .ingest
+                    .handle_entry(&self.store, &entry_bytes, encoded_operation)
+                    .await
+                {
+                    Ok(_) | Err(IngestError::DuplicateEntry(_)) => true,
+                    Err(err) => {
+                        warn!("Could not ingest entry of requested blob: {}", err);
+                        false
+                    }
+                },
+                // Entries of compacted blob pieces can't be ingested without their operation
+                None => false,
+            };
+
+            self.blob_requests.on_entry(&peer, session_id, is_ingested);
+        }
+    }
+
+    /// Pause replication within the given scope.
+    ///
+    /// Sessions which are still running are allowed to finish, idle sessions within the scope get
+    /// closed right away.
+    fn on_pause(&mut self, pause: ReplicationPause) {
+        info!(
+            "Pause replication with {} for {}",
+            pause
+                .peer_id
+                .map_or("all peers".to_string(), |peer_id| peer_id.to_string()),
+            if pause.schema_ids.is_empty() {
+                "all schema ids".to_string()
+            } else {
+                format!("{} schema ids", pause.schema_ids.len())
+            }
+        );
+
+        let peers: Vec<Peer> = self.peers.keys().copied().collect();
+        for peer in peers {
+            for session in self.sync_manager.get_sessions(&peer) {
+                if session.is_done() && pause.affects(&peer.id(), &session.target_set()) {
+                    debug!(
+                        "Close idle replication session {} with peer {}",
+                        session.id,
+                        peer.display()
+                    );
+                    self.sync_manager.remove_session(&peer, &session.id);
+                }
+            }
+        }
+
+        if !self.pauses.contains(&pause) {
+            self.pauses.push(pause);
+        }
+    }
+
+    /// Lift all pauses of replication lying within the given scope.
+    fn on_resume(&mut self, scope: ReplicationPause) {
+        let paused_count = self.pauses.len();
+        self.pauses.retain(|pause| !scope.covers(pause));
+
+        if self.pauses.len() < paused_count {
+            info!("Resume replication, {} pauses left", self.pauses.len());
+        }
+    }
+
     /// Generates our new announcement state we can then propagate to all known and future peers.
     async fn update_announcement(&mut self) {
         let supported_schema_ids = self.supported_schema_ids().await;
-        self.announcement = Some(Announcement::new(supported_schema_ids));
+        self.announcement = Some(Announcement::new(
+            supported_schema_ids,
+            self.supported_compressions.clone(),
+            SUPPORTED_MODES.to_vec(),
+        ));
     }
 
     /// Determine if we can attempt new replication sessions with the peers we currently know
@@ -348,47 +923,85 @@ impl ConnectionManager {
         }
 
         // Iterate through all currently connected peers
-        let mut attempt_peers: Vec<(Peer, SchemaIdSet)> = dedup_peers
+        let mut attempt_peers: Vec<(Peer, Vec<(Mode, SchemaIdSet)>)> = dedup_peers
             .values()
             .filter_map(|(peer, status)| {
                 let sessions = self.sync_manager.get_sessions(peer);
 
                 // 1. Did we already receive this peers announcement state? If not we can't do
                 //    anything yet and need to wait.
-                let remote_supported_schema_ids: SchemaIdSet =
-                    if let Some(announcement) = status.clone().announcement {
-                        announcement.supported_schema_ids
-                    } else {
-                        return None;
-                    };
-
-                // 2. Calculate intersection of local and remote schema id sets. Do we have any
+                let announcement = status.announcement.as_ref()?;
+
+                // 2. Did this peer prove control of an operator key we replicate with?
+                if !self.is_operator_allowed(peer) {
+                    return None;
+                }
+
+                // 3. Calculate intersection of local and remote schema id sets. Do we have any
                 //    supported schema id's in common?
                 let target_set = SchemaIdSet::from_intersection(
                     local_supported_schema_ids,
-                    &remote_supported_schema_ids,
+                    &announcement.supported_schema_ids,
                 );
+
+                // 4. Leave out all schema ids we paused replicating with this peer.
+                let unpaused_schema_ids: Vec<SchemaId> = target_set
+                    .iter()
+                    .filter(|schema_id| {
+                        !self
+                            .pauses
+                            .iter()
+                            .any(|pause| pause.matches(&peer.id(), schema_id))
+                    })
+                    .cloned()
+                    .collect();
+                let target_set = SchemaIdSet::new(&unpaused_schema_ids);
                 if target_set.is_empty() {
                     return None;
                 }
 
-                // 3. Check if we're running too many sessions with that peer on this connection
+                // 5. Split the target set by the replication modes we prefer for this peer and
+                //    these schema ids, falling back to modes both peers support.
+                let target_sets = select_modes(
+                    &self.mode_preferences,
+                    &self.replication_mode,
+                    &peer.id(),
+                    &SUPPORTED_MODES,
+                    &announcement.supported_modes,
+                    &target_set,
+                );
+
+                // 6. Check if we're running too many sessions with that peer on this connection
                 //    already. This limit is configurable.
                 let active_sessions: Vec<&Session> = sessions
                     .iter()
                     .filter(|session| !session.is_done())
                     .collect();
 
-                // 4. Check if we're already having at least one session concerning the same target
+                // 7. Check if we're already having at least one session concerning the same target
                 //    set. If we would start that session again it would be considered an error.
-                let has_active_target_set_session = active_sessions
-                    .iter()
-                    .any(|session| session.target_set() == target_set);
+                let mut available_sessions =
+                    MAX_SESSIONS_PER_PEER.saturating_sub(active_sessions.len());
+                let target_sets: Vec<(Mode, SchemaIdSet)> = target_sets
+                    .into_iter()
+                    .filter(|(_, target_set)| {
+                        let has_active_target_set_session = active_sessions
+                            .iter()
+                            .any(|session| &session.target_set() == target_set);
+
+                        if available_sessions > 0 && !has_active_target_set_session {
+                            available_sessions -= 1;
+                            true
+                        } else {
+                            false
+                        }
+                    })
+                    .collect();
 
-                if active_sessions.len() < MAX_SESSIONS_PER_PEER && !has_active_target_set_session {
-                    Some((*peer, target_set))
-                } else {
+                if target_sets.is_empty() {
                     None
+                } else {
+                    Some((*peer, target_sets))
                 }
             })
             .collect();
@@ -401,8 +1014,10 @@ impl ConnectionManager {
         attempt_peers.shuffle(&mut thread_rng());
         attempt_peers.truncate(MAX_PEER_SAMPLE);
 
-        for (peer, target_set) in &attempt_peers {
-            self.initiate_replication(peer, target_set).await;
+        for (peer, target_sets) in &attempt_peers {
+            for (mode, target_set) in target_sets {
+                self.initiate_replication(peer, target_set, mode).await;
+            }
         }
     }
 
@@ -430,19 +1045,30 @@ impl ConnectionManager {
     }
 
     /// Initiate a new replication session with remote peer.
-    async fn initiate_replication(&mut self, peer: &Peer, target_set: &SchemaIdSet) {
+    async fn initiate_replication(&mut self, peer: &Peer, target_set: &SchemaIdSet, mode: &Mode) {
         match self
             .sync_manager
-            .initiate_session(peer, target_set, &Mode::LogHeight)
+            .initiate_session(peer, target_set, mode)
             .await
         {
             Ok(messages) => {
+                if let Some(message) = messages.first() {
+                    self.pending_round_trips
+                        .insert((*peer, message.session_id()), Instant::now());
+                }
+
                 for message in messages {
                     self.send_service_message(ServiceMessage::SentMessage(
                         *peer,
                         PeerMessage::SyncMessage(message),
                     ));
                 }
+
+                self.send_service_message(ServiceMessage::SessionEstablished(
+                    *peer,
+                    ConnectionDirection::Outbound,
+                    target_set.clone(),
+                ));
             }
             Err(err) => {
                 warn!("Replication error: {}", err)
@@ -453,12 +1079,32 @@ impl ConnectionManager {
     /// Handles incoming messages from other services via the bus.
     async fn handle_service_message(&mut self, message: ServiceMessage) {
         match message {
-            ServiceMessage::PeerConnected(peer) => {
+            ServiceMessage::PeerConnected(peer, _) => {
                 self.on_connection_established(peer).await;
             }
-            ServiceMessage::PeerDisconnected(peer) => {
+            ServiceMessage::PeerDisconnected(peer, _) => {
                 self.on_connection_closed(peer).await;
             }
+            ServiceMessage::SchemaAdded(schema_id) => {
+                // Target set got updated
+                self.update_announcement().await;
+
+                // Documents of this schema might have arrived before it was materialized
+                self.sync_manager.on_schema_added(&schema_id).await;
+            }
+            ServiceMessage::SchemaUpdated(_) => {
+                // Target set got updated
+                self.update_announcement().await;
+            }
+            ServiceMessage::PauseReplication(pause) => {
+                self.on_pause(pause);
+            }
+            ServiceMessage::ResumeReplication(scope) => {
+                self.on_resume(scope);
+            }
+            ServiceMessage::BlobRequested(document_id) => {
+                self.on_blob_requested(document_id);
+            }
             ServiceMessage::ReceivedMessage(peer, message) => match message {
                 PeerMessage::SyncMessage(message) => {
                     self.on_replication_message(peer, message).await;
@@ -466,6 +1112,11 @@ impl ConnectionManager {
                 PeerMessage::Announce(message) => {
                     self.on_announcement_message(peer, message).await;
                 }
+                PeerMessage::OperatorProof(proof) => {
+                    self.on_operator_proof(peer, proof);
+                }
+                // Fragments get reassembled by the connection handlers and never arrive here
+                PeerMessage::Fragment(_) | PeerMessage::FragmentResume(_) => (),
             },
             _ => (), // Ignore all other messages
         }
@@ -484,9 +1135,6 @@ impl ConnectionManager {
         // Generate our own first announcement
         self.update_announcement().await;
 
-        // Subscribe to updates when our target set got changed
-        let mut schema_provider_rx = self.schema_provider.on_schema_added();
-
         loop {
             tokio::select! {
                 // Service message arrived
@@ -501,15 +1149,15 @@ impl ConnectionManager {
                     },
                 },
 
-                // Target set got updated
-                Ok(_) = schema_provider_rx.recv() => {
-                    self.update_announcement().await;
-                },
-
                 // Announcement & replication schedule is due
                 Some(_) = self.scheduler.next() => {
                     self.on_update().await;
                 }
+
+                // Paced replication messages might be due
+                Some(_) = self.pacer.next() => {
+                    self.on_pacing();
+                }
             }
         }
     }
@@ -519,22 +1167,28 @@ impl ConnectionManager {
 mod tests {
     use std::str::FromStr;
 
+    use libp2p::core::{ConnectedPoint, Endpoint};
     use libp2p::swarm::ConnectionId;
-    use libp2p::PeerId;
-    use p2panda_rs::document::DocumentViewId;
+    use libp2p::{Multiaddr, PeerId};
+    use p2panda_rs::document::{DocumentId, DocumentViewId};
+    use p2panda_rs::identity::KeyPair;
     use p2panda_rs::schema::{SchemaId, SchemaName};
-    use p2panda_rs::test_utils::fixtures::random_document_view_id;
+    use p2panda_rs::storage_provider::traits::EntryStore;
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_view_id};
     use rstest::rstest;
     use tokio::sync::broadcast;
 
     use crate::bus::ServiceMessage;
-    use crate::network::{Peer, PeerMessage};
+    use crate::network::{ConnectionDirection, ConnectionInfo, Peer, PeerMessage};
     use crate::replication::service::PeerStatus;
     use crate::replication::{
-        Announcement, AnnouncementMessage, Message, Mode, SchemaIdSet, SyncMessage,
+        Announcement, AnnouncementMessage, Direction, Message, Mode, OperatorProof,
+        ReplicationPause, SchemaIdSet, SyncMessage, SUPPORTED_COMPRESSIONS, SUPPORTED_MODES,
     };
     use crate::schema::SchemaProvider;
-    use crate::test_utils::{test_runner, TestNode};
+    use crate::test_utils::{
+        add_blob, test_runner, test_runner_with_manager, TestNode, TestNodeManager,
+    };
     use crate::AllowList;
 
     use super::ConnectionManager;
@@ -554,6 +1208,10 @@ mod tests {
                 &node.context.store,
                 &tx,
                 local_peer_id,
+                &SUPPORTED_COMPRESSIONS,
+                &Mode::LogHeight,
+                &[],
+                &[],
             );
 
             let supported_schema_ids = manager.supported_schema_ids().await;
@@ -561,9 +1219,20 @@ mod tests {
 
             // Inform connection manager about new peer
             let remote_peer = Peer::new(remote_peer_id, ConnectionId::new_unchecked(1));
+            let connection_info = ConnectionInfo::new(
+                remote_peer_id,
+                ConnectionId::new_unchecked(1),
+                &ConnectedPoint::Dialer {
+                    address: Multiaddr::empty(),
+                    role_override: Endpoint::Dialer,
+                },
+            );
 
             manager
-                .handle_service_message(ServiceMessage::PeerConnected(remote_peer))
+                .handle_service_message(ServiceMessage::PeerConnected(
+                    remote_peer,
+                    connection_info.clone(),
+                ))
                 .await;
 
             let status = manager
@@ -582,14 +1251,20 @@ mod tests {
                 Ok(ServiceMessage::SentMessage(
                     remote_peer,
                     PeerMessage::Announce(AnnouncementMessage::new(Announcement::new(
-                        supported_schema_ids.clone()
+                        supported_schema_ids.clone(),
+                        SUPPORTED_COMPRESSIONS.to_vec(),
+                        SUPPORTED_MODES.to_vec(),
                     )))
                 ))
             );
 
             // Peer informs us about its target set
             assert_eq!(status.announcement, None);
-            let announcement = Announcement::new(supported_schema_ids.clone());
+            let announcement = Announcement::new(
+                supported_schema_ids.clone(),
+                vec![],
+                SUPPORTED_MODES.to_vec(),
+            );
             manager
                 .handle_service_message(ServiceMessage::ReceivedMessage(
                     remote_peer,
@@ -605,7 +1280,10 @@ mod tests {
 
             // Inform manager about peer disconnected
             manager
-                .handle_service_message(ServiceMessage::PeerDisconnected(remote_peer))
+                .handle_service_message(ServiceMessage::PeerDisconnected(
+                    remote_peer,
+                    connection_info,
+                ))
                 .await;
 
             // Manager cleans up internal state
@@ -626,8 +1304,16 @@ mod tests {
             let (tx, mut rx) = broadcast::channel::<ServiceMessage>(10);
 
             let schema_provider = SchemaProvider::new(vec![], AllowList::Set(vec![]));
-            let mut manager =
-                ConnectionManager::new(&schema_provider, &node.context.store, &tx, local_peer_id);
+            let mut manager = ConnectionManager::new(
+                &schema_provider,
+                &node.context.store,
+                &tx,
+                local_peer_id,
+                &SUPPORTED_COMPRESSIONS,
+                &Mode::LogHeight,
+                &[],
+                &[],
+            );
             manager.update_announcement().await;
 
             let remote_peer = Peer::new(remote_peer_id, ConnectionId::new_unchecked(1));
@@ -646,7 +1332,11 @@ mod tests {
                     remote_peer,
                     PeerMessage::SyncMessage(SyncMessage::new(
                         0,
-                        Message::SyncRequest(Mode::LogHeight, unsupported_target_set),
+                        Message::SyncRequest(
+                            Mode::LogHeight,
+                            unsupported_target_set,
+                            Direction::Both,
+                        ),
                     )),
                 ))
                 .await;
@@ -661,4 +1351,386 @@ mod tests {
             assert_eq!(manager.sync_manager.get_sessions(&remote_peer).len(), 0);
         });
     }
+
+    #[test]
+    fn paused_replication() {
+        let local_peer_id =
+            PeerId::from_str("12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY").unwrap();
+        let remote_peer_id =
+            PeerId::from_str("12D3KooWCqtLMJQLY3sm9rpDampJ2nPLswPPZto3mrRY7794QATF").unwrap();
+
+        test_runner(move |node: TestNode| async move {
+            let (tx, mut rx) = broadcast::channel::<ServiceMessage>(10);
+
+            let mut manager = ConnectionManager::new(
+                &node.context.schema_provider,
+                &node.context.store,
+                &tx,
+                local_peer_id,
+                &SUPPORTED_COMPRESSIONS,
+                &Mode::LogHeight,
+                &[],
+                &[],
+            );
+            let supported_schema_ids = manager.supported_schema_ids().await;
+            manager.update_announcement().await;
+
+            let remote_peer = Peer::new(remote_peer_id, ConnectionId::new_unchecked(1));
+            let mut status = PeerStatus::new(remote_peer);
+            status.announcement = Some(Announcement::new(
+                supported_schema_ids.clone(),
+                vec![],
+                SUPPORTED_MODES.to_vec(),
+            ));
+            manager.peers.insert(remote_peer, status);
+
+            let pause = ReplicationPause {
+                peer_id: Some(remote_peer_id),
+                schema_ids: vec![],
+            };
+            manager
+                .handle_service_message(ServiceMessage::PauseReplication(pause))
+                .await;
+
+            // No sessions get initiated while replication with this peer is paused
+            manager.update_sessions().await;
+            assert_eq!(rx.len(), 0);
+            assert_eq!(manager.sync_manager.get_sessions(&remote_peer).len(), 0);
+
+            // Sessions requested by the paused peer get refused
+            manager
+                .handle_service_message(ServiceMessage::ReceivedMessage(
+                    remote_peer,
+                    PeerMessage::SyncMessage(SyncMessage::new(
+                        0,
+                        Message::SyncRequest(
+                            Mode::LogHeight,
+                            supported_schema_ids.clone(),
+                            Direction::Both,
+                        ),
+                    )),
+                ))
+                .await;
+            assert_eq!(
+                rx.recv().await,
+                Ok(ServiceMessage::ReplicationFailed(remote_peer))
+            );
+            assert_eq!(manager.sync_manager.get_sessions(&remote_peer).len(), 0);
+
+            // Replication continues after resuming
+            manager
+                .handle_service_message(ServiceMessage::ResumeReplication(
+                    ReplicationPause::default(),
+                ))
+                .await;
+            assert!(manager.pauses.is_empty());
+
+            manager.update_sessions().await;
+            assert_eq!(manager.sync_manager.get_sessions(&remote_peer).len(), 1);
+
+            // Other services get informed about the session we requested
+            assert!(matches!(
+                rx.recv().await,
+                Ok(ServiceMessage::SentMessage(_, PeerMessage::SyncMessage(_)))
+            ));
+            assert!(matches!(
+                rx.recv().await,
+                Ok(ServiceMessage::SessionEstablished(
+                    peer,
+                    ConnectionDirection::Outbound,
+                    _
+                )) if peer == remote_peer
+            ));
+        });
+    }
+
+    #[test]
+    fn allowed_operator_keys() {
+        let local_peer_id =
+            PeerId::from_str("12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY").unwrap();
+        let remote_peer_id =
+            PeerId::from_str("12D3KooWCqtLMJQLY3sm9rpDampJ2nPLswPPZto3mrRY7794QATF").unwrap();
+
+        test_runner(move |node: TestNode| async move {
+            let (tx, mut rx) = broadcast::channel::<ServiceMessage>(10);
+
+            let local_operator = KeyPair::new();
+            let remote_operator = KeyPair::new();
+
+            let mut manager = ConnectionManager::new(
+                &node.context.schema_provider,
+                &node.context.store,
+                &tx,
+                local_peer_id,
+                &SUPPORTED_COMPRESSIONS,
+                &Mode::LogHeight,
+                &[],
+                &[],
+            )
+            .with_operator(
+                Some(local_operator),
+                &AllowList::Set(vec![remote_operator.public_key()]),
+            );
+            let supported_schema_ids = manager.supported_schema_ids().await;
+            manager.update_announcement().await;
+
+            // We prove control of our operator key to new peers
+            let remote_peer = Peer::new(remote_peer_id, ConnectionId::new_unchecked(1));
+            manager.on_connection_established(remote_peer).await;
+            match rx.recv().await {
+                Ok(ServiceMessage::SentMessage(peer, PeerMessage::OperatorProof(proof))) => {
+                    assert_eq!(peer, remote_peer);
+                    assert!(proof.verify(&local_peer_id, &remote_peer_id));
+                }
+                message => panic!("Unexpected message {:?}", message),
+            }
+            assert!(matches!(
+                rx.recv().await,
+                Ok(ServiceMessage::SentMessage(_, PeerMessage::Announce(_)))
+            ));
+
+            manager.peers.get_mut(&remote_peer).unwrap().announcement = Some(Announcement::new(
+                supported_schema_ids.clone(),
+                vec![],
+                SUPPORTED_MODES.to_vec(),
+            ));
+
+            // No sessions get initiated with peers which didn't prove control of an allowed
+            // operator key
+            manager.update_sessions().await;
+            assert_eq!(rx.len(), 0);
+
+            // Sessions requested by them get refused
+            let sync_request = PeerMessage::SyncMessage(SyncMessage::new(
+                0,
+                Message::SyncRequest(
+                    Mode::LogHeight,
+                    supported_schema_ids.clone(),
+                    Direction::Both,
+                ),
+            ));
+            manager
+                .handle_service_message(ServiceMessage::ReceivedMessage(
+                    remote_peer,
+                    sync_request.clone(),
+                ))
+                .await;
+            assert_eq!(
+                rx.recv().await,
+                Ok(ServiceMessage::ReplicationFailed(remote_peer))
+            );
+
+            // Proofs made for other connections are invalid
+            manager
+                .handle_service_message(ServiceMessage::ReceivedMessage(
+                    remote_peer,
+                    PeerMessage::OperatorProof(OperatorProof::new(
+                        &remote_operator,
+                        &PeerId::random(),
+                        &local_peer_id,
+                    )),
+                ))
+                .await;
+            assert_eq!(
+                rx.recv().await,
+                Ok(ServiceMessage::ReplicationFailed(remote_peer))
+            );
+
+            // Sessions get accepted after the peer proved control of an allowed operator key
+            manager
+                .handle_service_message(ServiceMessage::ReceivedMessage(
+                    remote_peer,
+                    PeerMessage::OperatorProof(OperatorProof::new(
+                        &remote_operator,
+                        &remote_peer_id,
+                        &local_peer_id,
+                    )),
+                ))
+                .await;
+            manager
+                .handle_service_message(ServiceMessage::ReceivedMessage(remote_peer, sync_request))
+                .await;
+            assert_eq!(manager.sync_manager.get_sessions(&remote_peer).len(), 1);
+        });
+    }
+
+    #[test]
+    fn measures_round_trip_time() {
+        let local_peer_id =
+            PeerId::from_str("12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY").unwrap();
+        let remote_peer_id =
+            PeerId::from_str("12D3KooWCqtLMJQLY3sm9rpDampJ2nPLswPPZto3mrRY7794QATF").unwrap();
+
+        test_runner(move |node: TestNode| async move {
+            let (tx, _rx) = broadcast::channel::<ServiceMessage>(10);
+
+            let mut manager = ConnectionManager::new(
+                &node.context.schema_provider,
+                &node.context.store,
+                &tx,
+                local_peer_id,
+                &SUPPORTED_COMPRESSIONS,
+                &Mode::LogHeight,
+                &[],
+                &[],
+            );
+            let supported_schema_ids = manager.supported_schema_ids().await;
+            manager.update_announcement().await;
+
+            let remote_peer = Peer::new(remote_peer_id, ConnectionId::new_unchecked(1));
+            let mut status = PeerStatus::new(remote_peer);
+            status.announcement = Some(Announcement::new(
+                supported_schema_ids.clone(),
+                vec![],
+                SUPPORTED_MODES.to_vec(),
+            ));
+            manager.peers.insert(remote_peer, status);
+
+            // Round trip starts with the session we initiate
+            manager.update_sessions().await;
+            assert_eq!(manager.pending_round_trips.len(), 1);
+            let (_, session_id) = *manager.pending_round_trips.keys().next().unwrap();
+
+            // .. and completes with the first response of the remote peer
+            manager
+                .handle_service_message(ServiceMessage::ReceivedMessage(
+                    remote_peer,
+                    PeerMessage::SyncMessage(SyncMessage::new(session_id, Message::Have(vec![]))),
+                ))
+                .await;
+            assert!(manager.pending_round_trips.is_empty());
+            assert!(manager.peers[&remote_peer].pacing.srtt().is_some());
+        });
+    }
+
+    #[rstest]
+    fn requests_blobs_from_peers(key_pair: KeyPair) {
+        let peer_id_a =
+            PeerId::from_str("12D3KooWD3JAiSNrVGxjC7vJCcjwS8egbtJV9kzrstxLRKiwb9UY").unwrap();
+        let peer_id_b =
+            PeerId::from_str("12D3KooWCqtLMJQLY3sm9rpDampJ2nPLswPPZto3mrRY7794QATF").unwrap();
+
+        test_runner_with_manager(move |manager: TestNodeManager| async move {
+            let mut node_a = manager.create().await;
+            let node_b = manager.create().await;
+
+            let blob_view_id = add_blob(
+                &mut node_a,
+                "Hello, World!".as_bytes(),
+                6,
+                "text/plain",
+                &key_pair,
+            )
+            .await;
+            let document_id: DocumentId = blob_view_id.to_string().parse().unwrap();
+
+            // Node A has the blob, node B requests it
+            let (tx_a, mut rx_a) = broadcast::channel::<ServiceMessage>(32);
+            let (tx_b, mut rx_b) = broadcast::channel::<ServiceMessage>(32);
+
+            let mut managers = Vec::new();
+            for (node, tx, local_peer_id) in
+                [(&node_a, &tx_a, peer_id_a), (&node_b, &tx_b, peer_id_b)]
+            {
+                let mut manager = ConnectionManager::new(
+                    &node.context.schema_provider,
+                    &node.context.store,
+                    tx,
+                    local_peer_id,
+                    &SUPPORTED_COMPRESSIONS,
+                    &Mode::LogHeight,
+                    &[],
+                    &[],
+                );
+                manager.update_announcement().await;
+                managers.push(manager);
+            }
+            let mut manager_b = managers.pop().unwrap();
+            let mut manager_a = managers.pop().unwrap();
+
+            let peer_a = Peer::new(peer_id_a, ConnectionId::new_unchecked(1));
+            let peer_b = Peer::new(peer_id_b, ConnectionId::new_unchecked(1));
+            for (manager, remote_peer) in [(&mut manager_a, peer_b), (&mut manager_b, peer_a)] {
+                let mut status = PeerStatus::new(remote_peer);
+                status.announcement = Some(Announcement::new(
+                    manager.supported_schema_ids().await,
+                    vec![],
+                    SUPPORTED_MODES.to_vec(),
+                ));
+                manager.peers.insert(remote_peer, status);
+            }
+
+            manager_b
+                .handle_service_message(ServiceMessage::BlobRequested(document_id.clone()))
+                .await;
+            let request = match rx_b.recv().await {
+                Ok(ServiceMessage::SentMessage(peer, message)) if peer == peer_a => message,
+                message => panic!("Unexpected message {:?}", message),
+            };
+
+            // Node A responds with the entries of the blob document and its pieces
+            manager_a
+                .handle_service_message(ServiceMessage::ReceivedMessage(peer_b, request))
+                .await;
+
+            let mut responses = Vec::new();
+            while let Ok(ServiceMessage::SentMessage(peer, message)) = rx_a.try_recv() {
+                assert_eq!(peer, peer_b);
+                responses.push(message);
+            }
+            assert_eq!(responses.len(), 5);
+
+            // Node B ingests them like replicated entries
+            for response in responses {
+                manager_b
+                    .handle_service_message(ServiceMessage::ReceivedMessage(peer_a, response))
+                    .await;
+            }
+
+            let mut new_operations = 0;
+            while let Ok(message) = rx_b.try_recv() {
+                assert_ne!(
+                    message,
+                    ServiceMessage::BlobUnavailable(document_id.clone())
+                );
+                if let ServiceMessage::NewOperation(_) = message {
+                    new_operations += 1;
+                }
+            }
+            assert_eq!(new_operations, 4);
+            assert!(!manager_b.blob_requests.is_requested(&document_id));
+            assert!(node_b
+                .context
+                .store
+                .get_entry(&blob_view_id.to_string().parse().unwrap())
+                .await
+                .unwrap()
+                .is_some());
+
+            // Blobs nobody has are unavailable
+            let unknown_document_id: DocumentId =
+                random_document_view_id().to_string().parse().unwrap();
+            manager_b
+                .handle_service_message(ServiceMessage::BlobRequested(unknown_document_id.clone()))
+                .await;
+            let request = match rx_b.recv().await {
+                Ok(ServiceMessage::SentMessage(_, message)) => message,
+                message => panic!("Unexpected message {:?}", message),
+            };
+            manager_a
+                .handle_service_message(ServiceMessage::ReceivedMessage(peer_b, request))
+                .await;
+            let response = match rx_a.recv().await {
+                Ok(ServiceMessage::SentMessage(_, message)) => message,
+                message => panic!("Unexpected message {:?}", message),
+            };
+            manager_b
+                .handle_service_message(ServiceMessage::ReceivedMessage(peer_a, response))
+                .await;
+            assert_eq!(
+                rx_b.recv().await,
+                Ok(ServiceMessage::BlobUnavailable(unknown_document_id))
+            );
+        });
+    }
 }
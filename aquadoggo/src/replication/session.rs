@@ -7,9 +7,11 @@ use p2panda_rs::operation::EncodedOperation;
 
 use crate::db::SqlStore;
 use crate::replication::errors::ReplicationError;
+use crate::replication::strategies::ReceivedEntry;
 use crate::replication::traits::Strategy;
 use crate::replication::{
-    LogHeightStrategy, Message, Mode, SchemaIdSet, SetReconciliationStrategy, StrategyResult,
+    Direction, LogHeightStrategy, LogRangeStrategy, Message, Mode, RangeScheduler, SchemaIdSet,
+    SetReconciliationStrategy, StrategyResult,
 };
 use crate::schema::SchemaProvider;
 
@@ -36,6 +38,9 @@ pub struct Session {
     /// Replication strategy handler.
     pub strategy: Box<dyn Strategy>,
 
+    /// Direction in which entries are exchanged, seen from our side.
+    pub direction: Direction,
+
     /// True if we're done locally with this replication session.
     pub is_local_done: bool,
 
@@ -50,16 +55,25 @@ pub struct Session {
 }
 
 impl Session {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: &SessionId,
         target_set: &SchemaIdSet,
         mode: &Mode,
+        direction: &Direction,
         local: bool,
         live_mode: bool,
         schema_provider: SchemaProvider,
+        scheduler: RangeScheduler,
     ) -> Self {
         let strategy: Box<dyn Strategy> = match mode {
             Mode::LogHeight => Box::new(LogHeightStrategy::new(target_set, schema_provider)),
+            Mode::LogRange => Box::new(LogRangeStrategy::new(
+                target_set,
+                schema_provider,
+                scheduler,
+                direction,
+            )),
             Mode::SetReconciliation => Box::new(SetReconciliationStrategy::new()),
             Mode::Unknown => panic!("Unknown replication mode"),
         };
@@ -69,6 +83,7 @@ impl Session {
             state: SessionState::Pending,
             local,
             strategy,
+            direction: *direction,
             is_local_done: false,
             is_remote_done: false,
             is_remote_live_mode: false,
@@ -104,7 +119,15 @@ impl Session {
     }
 
     /// Send `SyncDone` message last as soon as the done flag flipped.
+    ///
+    /// Entries are never sent to the remote peer when we're not pushing in this session.
     fn flippy_flaggy(&mut self, result: &mut StrategyResult) {
+        if !self.direction.is_pushing() {
+            result
+                .messages
+                .retain(|message| !matches!(message, Message::Entry(..) | Message::Entries(..)));
+        }
+
         if result.is_local_done && !self.is_local_done {
             result
                 .messages
@@ -143,6 +166,53 @@ impl Session {
         Ok(())
     }
 
+    /// Handle incoming entry and return the entries which are ready to be ingested, together with
+    /// response messages.
+    ///
+    /// Entries are dropped when we're not pulling in this session. The remote peer only learns
+    /// about this when it initiated the session, otherwise it might still send us entries.
+    pub async fn handle_entry(
+        &mut self,
+        store: &SqlStore,
+        entry_bytes: &EncodedEntry,
+        operation_bytes: Option<&EncodedOperation>,
+    ) -> Result<(Vec<ReceivedEntry>, Vec<Message>), ReplicationError> {
+        self.validate_entry(entry_bytes, operation_bytes)?;
+
+        if !self.direction.is_pulling() {
+            self.update_state();
+            return Ok((vec![], vec![]));
+        }
+
+        let (entries, mut result) = self
+            .strategy
+            .handle_entry(store, entry_bytes, operation_bytes)
+            .await?;
+        self.flippy_flaggy(&mut result);
+        self.update_state();
+
+        Ok((entries, result.messages))
+    }
+
+    /// Clean up after this session, this needs to be called before it gets removed.
+    pub fn close(&mut self) {
+        self.strategy.close();
+    }
+
+    fn update_state(&mut self) {
+        // As soon as we've received any message from the remote peer we can consider the session
+        // to be "established"
+        if self.state == SessionState::Pending {
+            self.state = SessionState::Established;
+        }
+
+        // If local and remote peer decided they're done, we can consider this whole session to be
+        // "done"
+        if self.is_local_done && self.is_remote_done {
+            self.state = SessionState::Done;
+        }
+    }
+
     pub async fn handle_message(
         &mut self,
         store: &SqlStore,
@@ -161,17 +231,7 @@ impl Session {
             }
         };
 
-        // As soon as we've received any message from the remote peer we can consider the session
-        // to be "established"
-        if self.state == SessionState::Pending {
-            self.state = SessionState::Established;
-        }
-
-        // If local and remote peer decided they're done, we can consider this whole session to be
-        // "done"
-        if self.is_local_done && self.is_remote_done {
-            self.state = SessionState::Done;
-        }
+        self.update_state();
 
         Ok(result)
     }
@@ -183,7 +243,7 @@ mod tests {
     use rstest::rstest;
 
     use crate::replication::manager::INITIAL_SESSION_ID;
-    use crate::replication::{Message, Mode, SchemaIdSet, SessionState};
+    use crate::replication::{Direction, Message, Mode, RangeScheduler, SchemaIdSet, SessionState};
     use crate::test_utils::helpers::random_schema_id_set;
     use crate::test_utils::{
         populate_and_materialize, populate_store, populate_store_config, test_runner,
@@ -199,9 +259,11 @@ mod tests {
                 &INITIAL_SESSION_ID,
                 &target_set,
                 &Mode::LogHeight,
+                &Direction::Both,
                 true,
                 false,
                 node.context.schema_provider.clone(),
+                RangeScheduler::default(),
             );
             assert!(!session.is_local_done);
             assert!(!session.is_local_live_mode);
@@ -237,9 +299,11 @@ mod tests {
                 &INITIAL_SESSION_ID,
                 &target_set,
                 &Mode::LogHeight,
+                &Direction::Both,
                 true,
                 false,
                 schema_provider.clone(),
+                RangeScheduler::default(),
             );
 
             let response_messages = session
@@ -258,9 +322,11 @@ mod tests {
                 &INITIAL_SESSION_ID,
                 &target_set,
                 &Mode::LogHeight,
+                &Direction::Both,
                 true,
                 false,
                 schema_provider.clone(),
+                RangeScheduler::default(),
             );
 
             let node_b: TestNode = manager.create().await;
@@ -24,6 +24,13 @@ use crate::schema::SchemaProvider;
 
 type SortedIndex = i32;
 
+/// Maximum size in bytes of entries and operations loaded from the database with one query.
+///
+/// Pages are bounded by size instead of the number of entries, logs of small operations are read
+/// with few queries while single queries never load more than this of large ones, like blob
+/// pieces.
+const MAX_PAGE_SIZE: usize = 512 * 1024;
+
 fn has_blob_relation(schema: &Schema) -> bool {
     for (_, field_type) in schema.fields().iter() {
         match field_type {
@@ -43,33 +50,51 @@ fn has_blob_relation(schema: &Schema) -> bool {
 
 /// Retrieve entries from the store, group the result by document id and then sub-order them by
 /// their sorted index.
+///
+/// Logs are read in pages of at most `max_page_size` bytes.
 async fn retrieve_entries(
     store: &SqlStore,
     remote_needs: &[LogHeights],
+    max_page_size: usize,
 ) -> Vec<(StorageEntry, DocumentId, SortedIndex)> {
     let mut entries = Vec::new();
 
     for (public_key, log_heights) in remote_needs {
         for (log_id, seq_num) in log_heights {
-            // Get the entries the remote needs for each log.
-            let log_entries = store
-                .get_entries_from(public_key, log_id, seq_num)
-                .await
-                .expect("Fatal database error");
+            let mut seq_num = *seq_num;
 
-            for entry in log_entries {
-                // Get the entry as well as we need some additional information in order to
-                // send the entries in the correct order.
-                let operation = store
-                    .get_operation(&entry.hash().into())
+            // Get the entries the remote needs for each log, page by page instead of loading whole
+            // logs with one query.
+            loop {
+                let page = store
+                    .get_paginated_log_entries(public_key, log_id, &seq_num, max_page_size)
                     .await
-                    .expect("Fatal database error")
-                    .expect("Operation should be in store");
+                    .expect("Fatal database error");
+
+                let mut last_seq_num = match page.last() {
+                    Some(entry) => *entry.seq_num(),
+                    None => break,
+                };
+
+                for entry in page {
+                    // Get the entry as well as we need some additional information in order to
+                    // send the entries in the correct order.
+                    let operation = store
+                        .get_operation(&entry.hash().into())
+                        .await
+                        .expect("Fatal database error")
+                        .expect("Operation should be in store");
 
-                // We only send entries if their operation has been materialized.
-                if let Some(sorted_index) = operation.sorted_index {
-                    entries.push((entry, operation.document_id, sorted_index));
+                    // We only send entries if their operation has been materialized.
+                    if let Some(sorted_index) = operation.sorted_index {
+                        entries.push((entry, operation.document_id, sorted_index));
+                    }
                 }
+
+                seq_num = match last_seq_num.next() {
+                    Some(next) => next,
+                    None => break,
+                };
             }
         }
     }
@@ -113,7 +138,7 @@ impl LogHeightStrategy {
     ///
     /// For example, a target set including the schema id `[img_0020, blob_v1]` would look at all
     /// `img_0020` documents and only include blobs which they relate to.
-    async fn included_document_ids(&self, store: &SqlStore) -> Vec<DocumentId> {
+    pub(super) async fn included_document_ids(&self, store: &SqlStore) -> Vec<DocumentId> {
         let wants_blobs = self.target_set().contains(&SchemaId::Blob(1));
         let wants_blob_pieces = self.target_set().contains(&SchemaId::BlobPiece(1));
         let mut all_target_documents = vec![];
@@ -181,7 +206,7 @@ impl LogHeightStrategy {
 
     // Calculate the heights of all logs which contain contributions to documents in the current
     // `SchemaIdSet`.
-    async fn local_log_heights(
+    pub(super) async fn local_log_heights(
         &self,
         store: &SqlStore,
         included_documents: &[DocumentId],
@@ -216,7 +241,7 @@ impl LogHeightStrategy {
             &remote_log_heights.iter().cloned().collect(),
         );
 
-        let entries = retrieve_entries(store, &remote_needs).await;
+        let entries = retrieve_entries(store, &remote_needs, MAX_PAGE_SIZE).await;
 
         // Compose the actual messages.
         entries
@@ -347,7 +372,9 @@ mod tests {
     use crate::materializer::tasks::reduce_task;
     use crate::materializer::TaskInput;
     use crate::replication::ingest::SyncIngest;
-    use crate::replication::strategies::log_height::{retrieve_entries, SortedIndex};
+    use crate::replication::strategies::log_height::{
+        retrieve_entries, SortedIndex, MAX_PAGE_SIZE,
+    };
     use crate::replication::{LogHeightStrategy, LogHeights, Message, SchemaIdSet};
     use crate::test_utils::{
         add_blob, add_schema_and_documents, generate_key_pairs, populate_and_materialize,
@@ -361,17 +388,19 @@ mod tests {
         remote_needs: &[LogHeights],
         expected_entries: &Vec<(DocumentId, SortedIndex)>,
     ) {
-        // Retrieve the entries.
-        let entries = retrieve_entries(&node.context.store, remote_needs).await;
+        // Retrieve the entries, reading logs in large pages and in pages of single entries.
+        for max_page_size in [MAX_PAGE_SIZE, 0] {
+            let entries = retrieve_entries(&node.context.store, remote_needs, max_page_size).await;
 
-        // Map the returned value into a more easily testable form (we assume the entries are
-        // correct, here we are testing the entry retrieval logic mainly)
-        let entries: Vec<(DocumentId, SortedIndex)> = entries
-            .into_iter()
-            .map(|(_, document_id, sorted_index)| (document_id, sorted_index))
-            .collect();
+            // Map the returned value into a more easily testable form (we assume the entries are
+            // correct, here we are testing the entry retrieval logic mainly)
+            let entries: Vec<(DocumentId, SortedIndex)> = entries
+                .into_iter()
+                .map(|(_, document_id, sorted_index)| (document_id, sorted_index))
+                .collect();
 
-        assert_eq!(&entries, expected_entries, "{remote_needs:#?}");
+            assert_eq!(&entries, expected_entries, "{remote_needs:#?}");
+        }
     }
 
     // Helper for updating a document.
@@ -0,0 +1,323 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use log::trace;
+use p2panda_rs::entry::decode::decode_entry;
+use p2panda_rs::entry::traits::AsEntry;
+use p2panda_rs::entry::validate::validate_payload;
+use p2panda_rs::entry::{EncodedEntry, LogId, SeqNum};
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::EncodedOperation;
+use p2panda_rs::storage_provider::traits::EntryStore;
+use p2panda_rs::Human;
+
+use crate::db::SqlStore;
+use crate::replication::errors::ReplicationError;
+use crate::replication::strategies::{RangeOwner, RangeScheduler, ReceivedEntry};
+use crate::replication::traits::Strategy;
+use crate::replication::{
+    Direction, LogHeightStrategy, LogHeights, LogRanges, Message, Mode, SchemaIdSet, StrategyResult,
+};
+use crate::schema::SchemaProvider;
+
+/// Maximum number of ranges we're waiting for from one peer at the same time.
+const MAX_PENDING_RANGES: usize = 4;
+
+/// Replication strategy requesting ranges of logs from the remote peer.
+///
+/// Both peers exchange the heights of their logs first, like in the `log-height` strategy. Missing
+/// entries are then requested in ranges via `Want` messages. Ranges are handed out by a scheduler
+/// shared between all sessions, this allows downloading different parts of the same log from many
+/// peers at once when they all hold it.
+///
+/// When we're not pushing in this session we announce no logs, so the remote never requests any
+/// ranges from us. When we're not pulling we ignore the logs announced by the remote and never
+/// request any ranges from it.
+#[derive(Clone, Debug)]
+pub struct LogRangeStrategy {
+    /// Used for calculating the logs included in the target set.
+    log_height: LogHeightStrategy,
+
+    scheduler: RangeScheduler,
+    owner: RangeOwner,
+
+    /// Direction in which entries are exchanged, seen from our side.
+    direction: Direction,
+
+    /// Heights of the logs we've announced to the remote, we only serve entries up to here.
+    local_log_heights: HashMap<(PublicKey, LogId), u64>,
+
+    /// Logs where the remote holds more entries than we do, with our and their height.
+    remote_log_heights: Vec<(PublicKey, LogId, u64, u64)>,
+
+    received_remote_have: bool,
+    sent_have: bool,
+}
+
+impl LogRangeStrategy {
+    pub fn new(
+        target_set: &SchemaIdSet,
+        schema_provider: SchemaProvider,
+        scheduler: RangeScheduler,
+        direction: &Direction,
+    ) -> Self {
+        let owner = scheduler.register();
+
+        Self {
+            log_height: LogHeightStrategy::new(target_set, schema_provider),
+            scheduler,
+            owner,
+            direction: *direction,
+            local_log_heights: HashMap::new(),
+            remote_log_heights: Vec::new(),
+            received_remote_have: false,
+            sent_have: false,
+        }
+    }
+
+    /// Claim new ranges from the scheduler until we're waiting for the maximum number of ranges
+    /// again.
+    ///
+    /// We're done as soon as there's nothing left to wait for.
+    fn want_ranges(&mut self) -> StrategyResult {
+        let pending = self.scheduler.pending(self.owner);
+        let mut budget = MAX_PENDING_RANGES.saturating_sub(pending);
+        let mut log_ranges: Vec<LogRanges> = Vec::new();
+
+        for (public_key, log_id, local_height, remote_height) in &self.remote_log_heights {
+            if budget == 0 {
+                break;
+            }
+
+            let ranges = self.scheduler.claim(
+                self.owner,
+                public_key,
+                log_id,
+                *local_height,
+                *remote_height,
+                budget,
+            );
+            budget -= ranges.len();
+
+            if ranges.is_empty() {
+                continue;
+            }
+
+            let ranges: Vec<(LogId, SeqNum, SeqNum)> = ranges
+                .into_iter()
+                .map(|(from, to)| {
+                    (
+                        *log_id,
+                        SeqNum::new(from).expect("Ranges start at sequence number 1"),
+                        SeqNum::new(to).expect("Ranges start at sequence number 1"),
+                    )
+                })
+                .collect();
+
+            match log_ranges.iter_mut().find(|(key, _)| key == public_key) {
+                Some((_, author_ranges)) => author_ranges.extend(ranges),
+                None => log_ranges.push((*public_key, ranges)),
+            }
+        }
+
+        let is_local_done = log_ranges.is_empty() && pending == 0;
+        let messages = if log_ranges.is_empty() {
+            vec![]
+        } else {
+            vec![Message::Want(log_ranges)]
+        };
+
+        StrategyResult {
+            messages,
+            is_local_done,
+        }
+    }
+
+    /// Remember which logs the remote can serve to us.
+    async fn handle_have(&mut self, store: &SqlStore, remote_log_heights: &[LogHeights]) {
+        for (public_key, log_heights) in remote_log_heights {
+            for (log_id, seq_num) in log_heights {
+                let local_height = store
+                    .get_latest_entry(public_key, log_id)
+                    .await
+                    .expect("Fatal database error")
+                    .map(|entry| entry.seq_num().as_u64())
+                    .unwrap_or(0);
+
+                if seq_num.as_u64() > local_height {
+                    self.remote_log_heights.push((
+                        *public_key,
+                        *log_id,
+                        local_height,
+                        seq_num.as_u64(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Prepare entry responses for ranges requested by the remote.
+    ///
+    /// Only entries of logs we've announced before are sent.
+    async fn entry_responses(&self, store: &SqlStore, log_ranges: &[LogRanges]) -> Vec<Message> {
+        let mut messages = Vec::new();
+
+        for (public_key, ranges) in log_ranges {
+            for (log_id, from, to) in ranges {
+                let height = match self.local_log_heights.get(&(*public_key, *log_id)) {
+                    Some(height) => *height,
+                    None => continue,
+                };
+
+                let entries = store
+                    .get_entries_from(public_key, log_id, from)
+                    .await
+                    .expect("Fatal database error");
+
+                for entry in entries {
+                    let seq_num = entry.seq_num().as_u64();
+                    if seq_num > to.as_u64() || seq_num > height {
+                        break;
+                    }
+
+                    trace!(
+                        "Prepare message containing entry at {:?} on {:?} for {}",
+                        entry.seq_num(),
+                        entry.log_id(),
+                        entry.public_key().display()
+                    );
+
+                    messages.push(Message::Entry(
+                        entry.clone().encoded_entry,
+                        entry.payload().cloned(),
+                    ));
+                }
+            }
+        }
+
+        messages
+    }
+}
+
+#[async_trait]
+impl Strategy for LogRangeStrategy {
+    fn mode(&self) -> Mode {
+        Mode::LogRange
+    }
+
+    fn target_set(&self) -> SchemaIdSet {
+        self.log_height.target_set()
+    }
+
+    async fn initial_messages(&mut self, store: &SqlStore) -> StrategyResult {
+        let log_heights = if self.direction.is_pushing() {
+            let included_document_ids = self.log_height.included_document_ids(store).await;
+            self.log_height
+                .local_log_heights(store, &included_document_ids)
+                .await
+        } else {
+            HashMap::new()
+        };
+
+        for (public_key, heights) in &log_heights {
+            for (log_id, seq_num) in heights {
+                self.local_log_heights
+                    .insert((*public_key, *log_id), seq_num.as_u64());
+            }
+        }
+
+        self.sent_have = true;
+
+        // We're only done when we know what the remote has
+        StrategyResult {
+            is_local_done: false,
+            messages: vec![Message::Have(log_heights.into_iter().collect())],
+        }
+    }
+
+    async fn handle_message(
+        &mut self,
+        store: &SqlStore,
+        message: &Message,
+    ) -> Result<StrategyResult, ReplicationError> {
+        let mut result = StrategyResult {
+            is_local_done: false,
+            messages: vec![],
+        };
+
+        // Send our Have message to remote if we haven't done it yet
+        if !self.sent_have {
+            result.merge(self.initial_messages(store).await);
+        }
+
+        match message {
+            Message::Have(remote_log_heights) => {
+                if self.received_remote_have {
+                    return Err(ReplicationError::StrategyFailed(
+                        "Received Have from remote message twice".into(),
+                    ));
+                }
+
+                self.received_remote_have = true;
+                if self.direction.is_pulling() {
+                    self.handle_have(store, remote_log_heights).await;
+                }
+                result.merge(self.want_ranges());
+            }
+            Message::Want(log_ranges) => {
+                let response = self.entry_responses(store, log_ranges).await;
+                result.messages.extend(response);
+            }
+            _ => {
+                return Err(ReplicationError::StrategyFailed(
+                    "Received unknown message type".into(),
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+
+    async fn handle_entry(
+        &mut self,
+        _store: &SqlStore,
+        entry_bytes: &EncodedEntry,
+        operation_bytes: Option<&EncodedOperation>,
+    ) -> Result<(Vec<ReceivedEntry>, StrategyResult), ReplicationError> {
+        // Check if the entry is signed by the author and carries the operation it claims
+        let entry = decode_entry(entry_bytes)
+            .map_err(|_| ReplicationError::StrategyFailed("Could not decode entry".into()))?;
+
+        if let Some(operation_bytes) = operation_bytes {
+            validate_payload(&entry, operation_bytes).map_err(|_| {
+                ReplicationError::StrategyFailed("Operation does not match entry".into())
+            })?;
+        }
+
+        let ready = self.scheduler.receive(
+            self.owner,
+            entry.public_key(),
+            entry.log_id(),
+            entry.seq_num().as_u64(),
+            (entry_bytes.clone(), operation_bytes.cloned()),
+        )?;
+
+        // Request the next ranges as soon as one is complete
+        let result = if self.scheduler.pending(self.owner) < MAX_PENDING_RANGES {
+            self.want_ranges()
+        } else {
+            StrategyResult {
+                messages: vec![],
+                is_local_done: false,
+            }
+        };
+
+        Ok((ready, result))
+    }
+
+    fn close(&mut self) {
+        self.scheduler.release(self.owner);
+    }
+}
@@ -2,10 +2,14 @@
 
 mod diff;
 mod log_height;
+mod log_range;
+mod range_scheduler;
 mod set_reconciliation;
 
 pub use diff::diff_log_heights;
 pub use log_height::LogHeightStrategy;
+pub use log_range::LogRangeStrategy;
+pub use range_scheduler::{RangeOwner, RangeScheduler, ReceivedEntry};
 pub use set_reconciliation::SetReconciliationStrategy;
 
 use crate::replication::Message;
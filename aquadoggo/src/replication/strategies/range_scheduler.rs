@@ -0,0 +1,374 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use p2panda_rs::entry::{EncodedEntry, LogId};
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::EncodedOperation;
+
+use crate::replication::errors::ReplicationError;
+
+/// Maximum number of entries of one range requested from a peer.
+pub const RANGE_SIZE: u64 = 256;
+
+/// Identifier of a replication session claiming ranges.
+pub type RangeOwner = u64;
+
+/// Entry and operation received from a remote peer.
+pub type ReceivedEntry = (EncodedEntry, Option<EncodedOperation>);
+
+/// Range of a log requested from one peer.
+#[derive(Debug)]
+struct Claim {
+    owner: RangeOwner,
+
+    /// Sequence number of the next entry we expect from this peer.
+    next: u64,
+
+    /// Last sequence number of this range, inclusive.
+    to: u64,
+}
+
+/// Download state of one log.
+#[derive(Debug)]
+struct LogDownload {
+    /// Highest sequence number of the entries handed out for ingestion, all entries up to here
+    /// are either in the store or on their way to it.
+    ingested: u64,
+
+    /// First sequence number which was not requested from any peer yet.
+    next_unclaimed: u64,
+
+    /// Ranges of peers which stopped replicating before sending all entries.
+    released: Vec<(u64, u64)>,
+
+    /// Ranges currently requested from peers.
+    claims: Vec<Claim>,
+
+    /// Entries received ahead of the ingested height, waiting for the missing entries before
+    /// them.
+    buffer: BTreeMap<u64, ReceivedEntry>,
+}
+
+impl LogDownload {
+    fn new(local_height: u64) -> Self {
+        Self {
+            ingested: local_height,
+            next_unclaimed: local_height + 1,
+            released: Vec::new(),
+            claims: Vec::new(),
+            buffer: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Downloads {
+    next_owner: RangeOwner,
+    owners: HashSet<RangeOwner>,
+    logs: HashMap<(PublicKey, LogId), LogDownload>,
+}
+
+/// Splits the entries of logs into ranges which get requested from different peers at the same
+/// time and reassembles them in order.
+///
+/// Every replication session in `log-range` mode registers itself as an owner and claims ranges
+/// of the logs its remote peer holds. Ranges are handed out in order, a peer only gets ranges it
+/// is able to serve. Entries are only accepted when they continue a range claimed by the session
+/// and get handed out for ingestion as soon as all entries before them arrived.
+#[derive(Clone, Debug, Default)]
+pub struct RangeScheduler {
+    inner: Arc<Mutex<Downloads>>,
+}
+
+impl RangeScheduler {
+    /// Returns a new owner for claiming ranges.
+    pub fn register(&self) -> RangeOwner {
+        let mut downloads = self.inner.lock().expect("Range scheduler lock poisoned");
+        let owner = downloads.next_owner;
+        downloads.next_owner += 1;
+        downloads.owners.insert(owner);
+        owner
+    }
+
+    /// Claims at most `max` ranges of a log for the owner.
+    ///
+    /// The remote peer of the owner holds all entries up to `remote_height`, we hold all entries
+    /// up to `local_height`. Ranges released by other owners are handed out first.
+    pub fn claim(
+        &self,
+        owner: RangeOwner,
+        public_key: &PublicKey,
+        log_id: &LogId,
+        local_height: u64,
+        remote_height: u64,
+        max: usize,
+    ) -> Vec<(u64, u64)> {
+        let mut downloads = self.inner.lock().expect("Range scheduler lock poisoned");
+        let download = downloads
+            .logs
+            .entry((*public_key, *log_id))
+            .or_insert_with(|| LogDownload::new(local_height));
+
+        // Entries might have arrived through other sessions in the meantime
+        if local_height > download.ingested {
+            download.ingested = local_height;
+            download.next_unclaimed = download.next_unclaimed.max(local_height + 1);
+        }
+
+        let mut ranges = Vec::new();
+
+        while ranges.len() < max {
+            match download
+                .released
+                .iter()
+                .position(|(_, to)| *to <= remote_height)
+            {
+                Some(index) => ranges.push(download.released.remove(index)),
+                None => break,
+            }
+        }
+
+        while ranges.len() < max && download.next_unclaimed <= remote_height {
+            let from = download.next_unclaimed;
+            let to = (from + RANGE_SIZE - 1).min(remote_height);
+            download.next_unclaimed = to + 1;
+            ranges.push((from, to));
+        }
+
+        for (from, to) in &ranges {
+            download.claims.push(Claim {
+                owner,
+                next: *from,
+                to: *to,
+            });
+        }
+
+        ranges
+    }
+
+    /// Returns the number of ranges the owner is still waiting for.
+    pub fn pending(&self, owner: RangeOwner) -> usize {
+        let downloads = self.inner.lock().expect("Range scheduler lock poisoned");
+        downloads
+            .logs
+            .values()
+            .flat_map(|download| download.claims.iter())
+            .filter(|claim| claim.owner == owner)
+            .count()
+    }
+
+    /// Accepts an entry received by the owner and returns all entries which can be ingested now,
+    /// in the order of their sequence numbers.
+    ///
+    /// Fails if the entry does not continue one of the ranges claimed by the owner.
+    // @TODO: Make error type smaller in size
+    #[allow(clippy::result_large_err)]
+    pub fn receive(
+        &self,
+        owner: RangeOwner,
+        public_key: &PublicKey,
+        log_id: &LogId,
+        seq_num: u64,
+        entry: ReceivedEntry,
+    ) -> Result<Vec<ReceivedEntry>, ReplicationError> {
+        let mut downloads = self.inner.lock().expect("Range scheduler lock poisoned");
+
+        let download = downloads
+            .logs
+            .get_mut(&(*public_key, *log_id))
+            .ok_or_else(|| {
+                ReplicationError::StrategyFailed("Received entry of unrequested log".into())
+            })?;
+
+        let claim = download
+            .claims
+            .iter_mut()
+            .find(|claim| claim.owner == owner && claim.next == seq_num)
+            .ok_or_else(|| {
+                ReplicationError::StrategyFailed(format!(
+                    "Received entry with unrequested sequence number {}",
+                    seq_num
+                ))
+            })?;
+
+        claim.next += 1;
+        download.claims.retain(|claim| claim.next <= claim.to);
+
+        if seq_num > download.ingested {
+            download.buffer.insert(seq_num, entry);
+        }
+
+        let mut ready = Vec::new();
+        while let Some(entry) = download.buffer.remove(&(download.ingested + 1)) {
+            download.ingested += 1;
+            ready.push(entry);
+        }
+
+        Ok(ready)
+    }
+
+    /// Hands entries back which could not be ingested yet, starting with the given sequence
+    /// number.
+    ///
+    /// They are returned again by `take_deferred` in the next rounds.
+    pub fn defer(
+        &self,
+        public_key: &PublicKey,
+        log_id: &LogId,
+        seq_num: u64,
+        entries: Vec<ReceivedEntry>,
+    ) {
+        let mut downloads = self.inner.lock().expect("Range scheduler lock poisoned");
+
+        if let Some(download) = downloads.logs.get_mut(&(*public_key, *log_id)) {
+            download.ingested = download.ingested.min(seq_num - 1);
+            for (index, entry) in entries.into_iter().enumerate() {
+                download.buffer.insert(seq_num + index as u64, entry);
+            }
+        }
+    }
+
+    /// Returns deferred entries of all logs, in the order of their sequence numbers.
+    pub fn take_deferred(&self) -> Vec<ReceivedEntry> {
+        let mut downloads = self.inner.lock().expect("Range scheduler lock poisoned");
+        let mut ready = Vec::new();
+
+        for download in downloads.logs.values_mut() {
+            while let Some(entry) = download.buffer.remove(&(download.ingested + 1)) {
+                download.ingested += 1;
+                ready.push(entry);
+            }
+        }
+
+        ready
+    }
+
+    /// Releases all ranges the owner did not finish yet, so other owners can claim them.
+    pub fn release(&self, owner: RangeOwner) {
+        let mut downloads = self.inner.lock().expect("Range scheduler lock poisoned");
+        downloads.owners.remove(&owner);
+
+        for download in downloads.logs.values_mut() {
+            let (released, claims): (Vec<Claim>, Vec<Claim>) = download
+                .claims
+                .drain(..)
+                .partition(|claim| claim.owner == owner);
+            download.claims = claims;
+            download
+                .released
+                .extend(released.into_iter().map(|claim| (claim.next, claim.to)));
+        }
+
+        // Forget about all downloads when no session is running anymore, the next sessions start
+        // again from the log heights in the store
+        if downloads.owners.is_empty() {
+            downloads.logs.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use p2panda_rs::entry::traits::AsEncodedEntry;
+    use p2panda_rs::entry::{EncodedEntry, LogId};
+    use p2panda_rs::identity::PublicKey;
+    use p2panda_rs::test_utils::fixtures::public_key;
+    use rstest::rstest;
+
+    use super::{RangeScheduler, ReceivedEntry, RANGE_SIZE};
+
+    fn entry(seq_num: u64) -> ReceivedEntry {
+        (EncodedEntry::from_bytes(&seq_num.to_be_bytes()), None)
+    }
+
+    fn seq_nums(entries: &[ReceivedEntry]) -> Vec<u64> {
+        entries
+            .iter()
+            .map(|(entry, _)| u64::from_be_bytes(entry.into_bytes().try_into().unwrap()))
+            .collect()
+    }
+
+    #[rstest]
+    fn split_and_reassemble_ranges(public_key: PublicKey) {
+        let scheduler = RangeScheduler::default();
+        let log_id = LogId::default();
+        let peer_a = scheduler.register();
+        let peer_b = scheduler.register();
+
+        // Both peers hold a log which is longer than two ranges, we hold the first two entries
+        let remote_height = 2 + RANGE_SIZE * 2;
+        let ranges_a = scheduler.claim(peer_a, &public_key, &log_id, 2, remote_height, 1);
+        let ranges_b = scheduler.claim(peer_b, &public_key, &log_id, 2, remote_height, 4);
+        assert_eq!(ranges_a, vec![(3, 2 + RANGE_SIZE)]);
+        assert_eq!(ranges_b, vec![(3 + RANGE_SIZE, remote_height)]);
+        assert_eq!(scheduler.pending(peer_a), 1);
+
+        // Entries of the second range are held back until the first range arrived
+        let first_b = 3 + RANGE_SIZE;
+        let ready = scheduler
+            .receive(peer_b, &public_key, &log_id, first_b, entry(first_b))
+            .unwrap();
+        assert!(ready.is_empty());
+
+        // Entries need to continue a claimed range
+        assert!(scheduler
+            .receive(peer_a, &public_key, &log_id, 4, entry(4))
+            .is_err());
+        assert!(scheduler
+            .receive(peer_b, &public_key, &log_id, 3, entry(3))
+            .is_err());
+
+        let ready = scheduler
+            .receive(peer_a, &public_key, &log_id, 3, entry(3))
+            .unwrap();
+        assert_eq!(seq_nums(&ready), vec![3]);
+
+        let mut ready = Vec::new();
+        for seq_num in 4..=(2 + RANGE_SIZE) {
+            ready.extend(
+                scheduler
+                    .receive(peer_a, &public_key, &log_id, seq_num, entry(seq_num))
+                    .unwrap(),
+            );
+        }
+        assert_eq!(scheduler.pending(peer_a), 0);
+
+        // Last entry of the first range releases the buffered entry of the second range
+        assert_eq!(ready.len() as u64, RANGE_SIZE);
+        assert_eq!(seq_nums(&ready).last(), Some(&first_b));
+    }
+
+    #[rstest]
+    fn release_unfinished_ranges(public_key: PublicKey) {
+        let scheduler = RangeScheduler::default();
+        let log_id = LogId::default();
+        let peer_a = scheduler.register();
+        let peer_b = scheduler.register();
+
+        let ranges = scheduler.claim(peer_a, &public_key, &log_id, 0, 10, 4);
+        assert_eq!(ranges, vec![(1, 10)]);
+        scheduler
+            .receive(peer_a, &public_key, &log_id, 1, entry(1))
+            .unwrap();
+
+        // Nothing left to claim while the first peer is downloading
+        assert!(scheduler
+            .claim(peer_b, &public_key, &log_id, 0, 10, 4)
+            .is_empty());
+
+        // Other peers take over the rest of the range when a peer stops
+        scheduler.release(peer_a);
+        assert_eq!(
+            scheduler.claim(peer_b, &public_key, &log_id, 0, 10, 4),
+            vec![(2, 10)]
+        );
+        let ready = scheduler
+            .receive(peer_b, &public_key, &log_id, 2, entry(2))
+            .unwrap();
+        assert_eq!(seq_nums(&ready), vec![2]);
+    }
+}
@@ -1,9 +1,12 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use async_trait::async_trait;
+use p2panda_rs::entry::EncodedEntry;
+use p2panda_rs::operation::EncodedOperation;
 
 use crate::db::SqlStore;
 use crate::replication::errors::ReplicationError;
+use crate::replication::strategies::ReceivedEntry;
 use crate::replication::{Message, Mode, SchemaIdSet, StrategyResult};
 
 #[async_trait]
@@ -23,6 +26,30 @@ pub trait Strategy: std::fmt::Debug + StrategyClone + Sync + Send {
         store: &SqlStore,
         message: &Message,
     ) -> Result<StrategyResult, ReplicationError>;
+
+    /// Handle incoming entry and return the entries which are ready to be ingested.
+    ///
+    /// Strategies requesting entries out of order can hold them back here until all entries
+    /// before them arrived.
+    async fn handle_entry(
+        &mut self,
+        _store: &SqlStore,
+        entry_bytes: &EncodedEntry,
+        operation_bytes: Option<&EncodedOperation>,
+    ) -> Result<(Vec<ReceivedEntry>, StrategyResult), ReplicationError> {
+        let result = StrategyResult {
+            messages: vec![],
+            is_local_done: false,
+        };
+
+        Ok((
+            vec![(entry_bytes.clone(), operation_bytes.cloned())],
+            result,
+        ))
+    }
+
+    /// Clean up after the session was closed.
+    fn close(&mut self) {}
 }
 
 // This is a little trick so we can clone trait objects.
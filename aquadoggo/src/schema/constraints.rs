@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::operation::traits::AsOperation;
+use p2panda_rs::operation::OperationValue;
+use p2panda_rs::schema::SchemaId;
+use regex::Regex;
+use thiserror::Error;
+
+/// Validation constraints for a field of an application schema.
+///
+/// Numeric bounds apply to `int` and `float` fields, length bounds to the number of characters of
+/// `str` fields, the number of bytes of `bytes` fields and the number of items of relation lists.
+/// Patterns apply to `str` fields and match anywhere in the value unless they are anchored with
+/// `^` and `$`.
+///
+/// Constraints are checked for every operation published to the node or received via
+/// replication, operations violating them are rejected.
+#[derive(Debug, Clone)]
+pub struct FieldConstraint {
+    /// Schema of the constrained field.
+    pub schema_id: SchemaId,
+
+    /// Name of the constrained field.
+    pub field: String,
+
+    /// Smallest allowed number.
+    pub min: Option<f64>,
+
+    /// Largest allowed number.
+    pub max: Option<f64>,
+
+    /// Smallest allowed length.
+    pub min_length: Option<usize>,
+
+    /// Largest allowed length.
+    pub max_length: Option<usize>,
+
+    /// Regular expression strings need to match.
+    pub pattern: Option<Regex>,
+}
+
+impl FieldConstraint {
+    /// Returns a constraint for the given field without any bounds.
+    pub fn new(schema_id: &SchemaId, field: &str) -> Self {
+        Self {
+            schema_id: schema_id.to_owned(),
+            field: field.to_owned(),
+            min: None,
+            max: None,
+            min_length: None,
+            max_length: None,
+            pattern: None,
+        }
+    }
+
+    /// Check if the given value of the constrained field satisfies this constraint.
+    pub fn check(&self, value: &OperationValue) -> Result<(), ConstraintViolation> {
+        let number = match value {
+            OperationValue::Integer(value) => Some(*value as f64),
+            OperationValue::Float(value) => Some(*value),
+            _ => None,
+        };
+
+        if let Some(number) = number {
+            if let Some(min) = self.min {
+                if number < min {
+                    return Err(ConstraintViolation::TooSmall(self.field.clone(), min));
+                }
+            }
+
+            if let Some(max) = self.max {
+                if number > max {
+                    return Err(ConstraintViolation::TooLarge(self.field.clone(), max));
+                }
+            }
+        }
+
+        let length = match value {
+            OperationValue::String(value) => Some(value.chars().count()),
+            OperationValue::Bytes(value) => Some(value.len()),
+            OperationValue::RelationList(list) => Some(list.iter().count()),
+            OperationValue::PinnedRelationList(list) => Some(list.iter().count()),
+            _ => None,
+        };
+
+        if let Some(length) = length {
+            if let Some(min_length) = self.min_length {
+                if length < min_length {
+                    return Err(ConstraintViolation::TooShort(
+                        self.field.clone(),
+                        min_length,
+                    ));
+                }
+            }
+
+            if let Some(max_length) = self.max_length {
+                if length > max_length {
+                    return Err(ConstraintViolation::TooLong(self.field.clone(), max_length));
+                }
+            }
+        }
+
+        if let (OperationValue::String(value), Some(pattern)) = (value, &self.pattern) {
+            if !pattern.is_match(value) {
+                return Err(ConstraintViolation::PatternMismatch(
+                    self.field.clone(),
+                    pattern.to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check all fields of an operation against the constraints of its schema.
+pub fn check_constraints<O: AsOperation>(
+    constraints: &[FieldConstraint],
+    operation: &O,
+) -> Result<(), ConstraintViolation> {
+    let fields = match operation.fields() {
+        Some(fields) => fields,
+        None => return Ok(()),
+    };
+
+    for constraint in constraints
+        .iter()
+        .filter(|constraint| &constraint.schema_id == operation.schema_id())
+    {
+        if let Some(value) = fields.get(&constraint.field) {
+            constraint.check(value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Error returned when a field value violates a constraint of its schema.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConstraintViolation {
+    #[error("Field '{0}' needs to be at least {1}")]
+    TooSmall(String, f64),
+
+    #[error("Field '{0}' needs to be at most {1}")]
+    TooLarge(String, f64),
+
+    #[error("Field '{0}' needs to have a length of at least {1}")]
+    TooShort(String, usize),
+
+    #[error("Field '{0}' needs to have a length of at most {1}")]
+    TooLong(String, usize),
+
+    #[error("Field '{0}' needs to match pattern '{1}'")]
+    PatternMismatch(String, String),
+
+    #[error("Field '{0}' needs to hold a date and time between the years 0000 and 9999")]
+    InvalidDateTime(String),
+}
+
+impl ConstraintViolation {
+    /// Returns the name of the field violating its constraint.
+    pub fn field(&self) -> &str {
+        match self {
+            ConstraintViolation::TooSmall(field, _)
+            | ConstraintViolation::TooLarge(field, _)
+            | ConstraintViolation::TooShort(field, _)
+            | ConstraintViolation::TooLong(field, _)
+            | ConstraintViolation::PatternMismatch(field, _)
+            | ConstraintViolation::InvalidDateTime(field) => field,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::operation::{OperationBuilder, OperationValue};
+    use p2panda_rs::schema::{SchemaId, SchemaName};
+    use p2panda_rs::test_utils::fixtures::{random_document_view_id, schema_id};
+    use regex::Regex;
+    use rstest::rstest;
+
+    use super::{check_constraints, ConstraintViolation, FieldConstraint};
+
+    #[rstest]
+    fn checks_values(schema_id: SchemaId) {
+        let mut constraint = FieldConstraint::new(&schema_id, "age");
+        constraint.min = Some(0.0);
+        constraint.max = Some(150.0);
+
+        assert!(constraint.check(&OperationValue::Integer(42)).is_ok());
+        assert!(constraint.check(&OperationValue::Float(150.0)).is_ok());
+        assert_eq!(
+            constraint.check(&OperationValue::Integer(-1)),
+            Err(ConstraintViolation::TooSmall("age".into(), 0.0))
+        );
+        assert_eq!(
+            constraint.check(&OperationValue::Float(150.5)),
+            Err(ConstraintViolation::TooLarge("age".into(), 150.0))
+        );
+
+        let mut constraint = FieldConstraint::new(&schema_id, "username");
+        constraint.min_length = Some(2);
+        constraint.max_length = Some(4);
+        constraint.pattern = Some(Regex::new("^[a-z]+$").unwrap());
+
+        assert!(constraint
+            .check(&OperationValue::String("äbc".into()))
+            .is_err());
+        assert!(constraint
+            .check(&OperationValue::String("abcd".into()))
+            .is_ok());
+        assert_eq!(
+            constraint.check(&OperationValue::String("a".into())),
+            Err(ConstraintViolation::TooShort("username".into(), 2))
+        );
+        assert_eq!(
+            constraint.check(&OperationValue::String("abcde".into())),
+            Err(ConstraintViolation::TooLong("username".into(), 4))
+        );
+        assert_eq!(
+            constraint.check(&OperationValue::String("AB".into())),
+            Err(ConstraintViolation::PatternMismatch(
+                "username".into(),
+                "^[a-z]+$".into()
+            ))
+        );
+
+        // Constraints not applying to the type of a value are ignored
+        assert!(constraint.check(&OperationValue::Boolean(true)).is_ok());
+    }
+
+    #[rstest]
+    fn checks_operations(schema_id: SchemaId) {
+        let mut constraint = FieldConstraint::new(&schema_id, "age");
+        constraint.max = Some(150.0);
+        let constraints = vec![constraint];
+
+        let operation = OperationBuilder::new(&schema_id)
+            .fields(&[("age", 200_i64.into())])
+            .build()
+            .unwrap();
+        assert_eq!(
+            check_constraints(&constraints, &operation),
+            Err(ConstraintViolation::TooLarge("age".into(), 150.0))
+        );
+
+        let operation = OperationBuilder::new(&schema_id)
+            .fields(&[("age", 20_i64.into())])
+            .build()
+            .unwrap();
+        assert!(check_constraints(&constraints, &operation).is_ok());
+
+        // Operations of other schemas are not affected
+        let other_schema_id =
+            SchemaId::Application(SchemaName::new("venue").unwrap(), random_document_view_id());
+        let operation = OperationBuilder::new(&other_schema_id)
+            .fields(&[("age", 200_i64.into())])
+            .build()
+            .unwrap();
+        assert!(check_constraints(&constraints, &operation).is_ok());
+    }
+}
@@ -0,0 +1,350 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::operation::traits::AsOperation;
+use p2panda_rs::operation::OperationValue;
+use p2panda_rs::schema::{FieldType, SchemaId};
+use thiserror::Error;
+
+use crate::schema::constraints::ConstraintViolation;
+
+/// UNIX timestamp of "0000-01-01T00:00:00Z", the earliest supported date and time.
+const MIN_TIMESTAMP: i64 = -62_167_219_200;
+
+/// UNIX timestamp of "9999-12-31T23:59:59Z", the latest supported date and time.
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Date and time stored in an `int` or `str` field of an application schema.
+///
+/// `int` fields hold UNIX timestamps in seconds, `str` fields hold UTC dates and times formatted
+/// as "YYYY-MM-DDTHH:MM:SSZ". Both representations order chronologically, this keeps range
+/// filters and ordering correct. The GraphQL API exposes these fields as `DateTime` strings and
+/// accepts ISO 8601 strings with any UTC offset or UNIX timestamps as values.
+///
+/// Operations holding other values in these fields are rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateTimeField {
+    /// Schema of the date and time field.
+    pub schema_id: SchemaId,
+
+    /// Name of the date and time field.
+    pub field: String,
+}
+
+impl DateTimeField {
+    /// Returns a date and time field.
+    pub fn new(schema_id: &SchemaId, field: &str) -> Self {
+        Self {
+            schema_id: schema_id.to_owned(),
+            field: field.to_owned(),
+        }
+    }
+}
+
+/// Returns true if the given field holds dates and times.
+pub fn is_datetime_field(
+    datetime_fields: &[DateTimeField],
+    schema_id: &SchemaId,
+    field: &str,
+) -> bool {
+    datetime_fields
+        .iter()
+        .any(|datetime| &datetime.schema_id == schema_id && datetime.field == field)
+}
+
+/// Number of days since 1970-01-01 of the given date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Date in the proleptic Gregorian calendar of the given number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Format a UNIX timestamp as UTC date and time, for example "2023-10-16T10:00:00Z".
+pub fn format_datetime(timestamp: i64) -> Result<String, DateTimeError> {
+    if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&timestamp) {
+        return Err(DateTimeError::OutOfRange(timestamp.to_string()));
+    }
+
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+    let seconds = timestamp.rem_euclid(SECONDS_PER_DAY);
+
+    Ok(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    ))
+}
+
+/// Parse an ISO 8601 date and time with UTC offset into a UNIX timestamp.
+///
+/// Values need to be given in the RFC 3339 profile of ISO 8601, for example
+/// "2023-10-16T12:00:00+02:00". Fractional seconds are rejected instead of rounded.
+pub fn parse_datetime(value: &str) -> Result<i64, DateTimeError> {
+    let invalid = || DateTimeError::Invalid(value.to_owned());
+
+    if !value.is_ascii() || value.len() < 20 {
+        return Err(invalid());
+    }
+
+    let number = |start: usize, end: usize| -> Result<i64, DateTimeError> {
+        let part = &value[start..end];
+        if !part.chars().all(|char| char.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        part.parse().map_err(|_| invalid())
+    };
+
+    let separators = value.as_bytes();
+    if separators[4] != b'-'
+        || separators[7] != b'-'
+        || !matches!(separators[10], b'T' | b't' | b' ')
+        || separators[13] != b':'
+        || separators[16] != b':'
+    {
+        return Err(invalid());
+    }
+
+    let (year, month, day) = (number(0, 4)?, number(5, 7)?, number(8, 10)?);
+    let (hour, minute, second) = (number(11, 13)?, number(14, 16)?, number(17, 19)?);
+
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(invalid());
+    }
+
+    let mut offset = &value[19..];
+
+    // Fractional seconds are only accepted when they don't add any precision
+    if let Some(fraction) = offset.strip_prefix('.') {
+        let digits = fraction
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        if digits == 0 {
+            return Err(invalid());
+        }
+        if fraction[..digits].chars().any(|char| char != '0') {
+            return Err(DateTimeError::TooPrecise(value.to_owned()));
+        }
+        offset = &fraction[digits..];
+    }
+
+    let offset_seconds = match offset {
+        "Z" | "z" => 0,
+        _ if offset.len() == 6 && &offset[3..4] == ":" => {
+            let sign = match &offset[..1] {
+                "+" => 1,
+                "-" => -1,
+                _ => return Err(invalid()),
+            };
+            let start = value.len() - offset.len();
+            let (hours, minutes) = (number(start + 1, start + 3)?, number(start + 4, start + 6)?);
+            if hours > 23 || minutes > 59 {
+                return Err(invalid());
+            }
+            sign * (hours * 3600 + minutes * 60)
+        }
+        _ => return Err(invalid()),
+    };
+
+    let timestamp =
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second
+            - offset_seconds;
+
+    if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&timestamp) {
+        return Err(DateTimeError::OutOfRange(value.to_owned()));
+    }
+
+    Ok(timestamp)
+}
+
+/// Convert a UNIX timestamp into the value stored in a date and time field of the given type.
+pub fn datetime_value(
+    timestamp: i64,
+    field_type: &FieldType,
+) -> Result<OperationValue, DateTimeError> {
+    let formatted = format_datetime(timestamp)?;
+
+    match field_type {
+        FieldType::String => Ok(OperationValue::String(formatted)),
+        _ => Ok(OperationValue::Integer(timestamp)),
+    }
+}
+
+/// Format the value of a date and time field as UTC date and time.
+pub fn format_datetime_value(value: &OperationValue) -> Result<String, DateTimeError> {
+    match value {
+        OperationValue::Integer(timestamp) => format_datetime(*timestamp),
+        OperationValue::String(value) => format_datetime(parse_datetime(value)?),
+        _ => Err(DateTimeError::Invalid(format!("{value:?}"))),
+    }
+}
+
+/// Check if all date and time fields of an operation hold valid values.
+///
+/// `str` fields need to be formatted as UTC date and time with "YYYY-MM-DDTHH:MM:SSZ", otherwise
+/// their lexicographic order would not be chronological.
+pub fn check_datetimes<O: AsOperation>(
+    datetime_fields: &[DateTimeField],
+    operation: &O,
+) -> Result<(), ConstraintViolation> {
+    let fields = match operation.fields() {
+        Some(fields) => fields,
+        None => return Ok(()),
+    };
+
+    for datetime in datetime_fields
+        .iter()
+        .filter(|datetime| &datetime.schema_id == operation.schema_id())
+    {
+        let is_valid = match fields.get(&datetime.field) {
+            Some(OperationValue::Integer(timestamp)) => format_datetime(*timestamp).is_ok(),
+            Some(OperationValue::String(value)) => {
+                parse_datetime(value).and_then(format_datetime).as_ref() == Ok(value)
+            }
+            _ => true,
+        };
+
+        if !is_valid {
+            return Err(ConstraintViolation::InvalidDateTime(datetime.field.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Error returned when a value can not be converted into a date and time.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DateTimeError {
+    #[error("'{0}' is not an ISO 8601 date and time with UTC offset")]
+    Invalid(String),
+
+    #[error("'{0}' has fractional seconds")]
+    TooPrecise(String),
+
+    #[error("'{0}' is not between the years 0000 and 9999")]
+    OutOfRange(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::operation::{OperationBuilder, OperationValue};
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::fixtures::schema_id;
+    use rstest::rstest;
+
+    use crate::schema::ConstraintViolation;
+
+    use super::{check_datetimes, format_datetime, parse_datetime, DateTimeError, DateTimeField};
+
+    #[rstest]
+    #[case(0, "1970-01-01T00:00:00Z")]
+    #[case(1_697_450_400, "2023-10-16T10:00:00Z")]
+    #[case(951_782_400, "2000-02-29T00:00:00Z")]
+    #[case(-1, "1969-12-31T23:59:59Z")]
+    #[case(-62_167_219_200, "0000-01-01T00:00:00Z")]
+    #[case(253_402_300_799, "9999-12-31T23:59:59Z")]
+    fn formats_datetimes(#[case] timestamp: i64, #[case] expected: &str) {
+        assert_eq!(format_datetime(timestamp), Ok(expected.to_string()));
+        assert_eq!(parse_datetime(expected), Ok(timestamp));
+    }
+
+    #[rstest]
+    #[case("2023-10-16T12:00:00+02:00", Ok(1_697_450_400))]
+    #[case("2023-10-16 05:30:00-04:30", Ok(1_697_450_400))]
+    #[case("2023-10-16t10:00:00.000z", Ok(1_697_450_400))]
+    #[case(
+        "2023-10-16T10:00:00.5Z",
+        Err(DateTimeError::TooPrecise("2023-10-16T10:00:00.5Z".into()))
+    )]
+    #[case("2023-10-16T10:00:00", Err(DateTimeError::Invalid("2023-10-16T10:00:00".into())))]
+    #[case("2023-02-29T10:00:00Z", Err(DateTimeError::Invalid("2023-02-29T10:00:00Z".into())))]
+    #[case("2023-10-16T24:00:00Z", Err(DateTimeError::Invalid("2023-10-16T24:00:00Z".into())))]
+    #[case("2023-10-16T10:00:00.Z", Err(DateTimeError::Invalid("2023-10-16T10:00:00.Z".into())))]
+    #[case("16.10.2023 10:00:00 Z", Err(DateTimeError::Invalid("16.10.2023 10:00:00 Z".into())))]
+    #[case(
+        "0000-01-01T00:00:00+01:00",
+        Err(DateTimeError::OutOfRange("0000-01-01T00:00:00+01:00".into()))
+    )]
+    fn parses_datetimes(#[case] value: &str, #[case] expected: Result<i64, DateTimeError>) {
+        assert_eq!(parse_datetime(value), expected);
+    }
+
+    #[rstest]
+    fn checks_datetime_fields(schema_id: SchemaId) {
+        let datetime_fields = vec![
+            DateTimeField::new(&schema_id, "created"),
+            DateTimeField::new(&schema_id, "due"),
+        ];
+
+        let operation = |created: OperationValue, due: &str| {
+            OperationBuilder::new(&schema_id)
+                .fields(&[("created", created), ("due", due.into())])
+                .build()
+                .unwrap()
+        };
+
+        assert!(check_datetimes(
+            &datetime_fields,
+            &operation(1_697_450_400_i64.into(), "2023-10-16T10:00:00Z")
+        )
+        .is_ok());
+
+        // Timestamps need to be within the supported range
+        assert_eq!(
+            check_datetimes(
+                &datetime_fields,
+                &operation(i64::MAX.into(), "2023-10-16T10:00:00Z")
+            ),
+            Err(ConstraintViolation::InvalidDateTime("created".into()))
+        );
+
+        // Strings need to be formatted in UTC, otherwise they would not order chronologically
+        assert_eq!(
+            check_datetimes(
+                &datetime_fields,
+                &operation(0_i64.into(), "2023-10-16T12:00:00+02:00")
+            ),
+            Err(ConstraintViolation::InvalidDateTime("due".into()))
+        );
+    }
+}
@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::schema::SchemaId;
+use thiserror::Error;
+
+/// Largest supported number of fractional digits, larger scales leave no room for the integer
+/// part in a 64-bit integer.
+pub const MAX_DECIMAL_SCALE: u32 = 18;
+
+/// Decimal number with a fixed number of fractional digits, stored in an `int` field of an
+/// application schema.
+///
+/// Values are stored as integers scaled by `10^scale`, with a scale of 2 the decimal `12.34` is
+/// stored as `1234`. This keeps equality and range filters exact, unlike with `float` fields.
+/// The GraphQL API exposes these fields as `Decimal` strings and converts filter values
+/// accordingly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecimalField {
+    /// Schema of the decimal field.
+    pub schema_id: SchemaId,
+
+    /// Name of the decimal field.
+    pub field: String,
+
+    /// Number of fractional digits.
+    pub scale: u32,
+}
+
+impl DecimalField {
+    /// Returns a decimal field with the given number of fractional digits.
+    pub fn new(schema_id: &SchemaId, field: &str, scale: u32) -> Self {
+        Self {
+            schema_id: schema_id.to_owned(),
+            field: field.to_owned(),
+            scale,
+        }
+    }
+}
+
+/// Returns the scale of the given field if it is a decimal field.
+pub fn decimal_scale(
+    decimal_fields: &[DecimalField],
+    schema_id: &SchemaId,
+    field: &str,
+) -> Option<u32> {
+    decimal_fields
+        .iter()
+        .find(|decimal| &decimal.schema_id == schema_id && decimal.field == field)
+        .map(|decimal| decimal.scale)
+}
+
+/// Format a scaled integer as a decimal string with exactly `scale` fractional digits.
+pub fn format_decimal(value: i64, scale: u32) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let sign = if value < 0 { "-" } else { "" };
+
+    if scale == 0 {
+        return format!("{sign}{digits}");
+    }
+
+    let digits = format!("{:0>width$}", digits, width = scale as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - scale as usize);
+    format!("{sign}{integer}.{fraction}")
+}
+
+/// Parse a decimal string into an integer scaled by `10^scale`.
+///
+/// Values with more fractional digits than the scale are rejected instead of rounded.
+pub fn parse_decimal(value: &str, scale: u32) -> Result<i64, DecimalError> {
+    let invalid = || DecimalError::Invalid(value.to_owned());
+
+    let (is_negative, unsigned) = match value.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+
+    let (integer, fraction) = match unsigned.split_once('.') {
+        Some((integer, fraction)) => (integer, fraction),
+        None => (unsigned, ""),
+    };
+
+    let is_digits = |part: &str| part.chars().all(|char| char.is_ascii_digit());
+    if (integer.is_empty() && fraction.is_empty()) || !is_digits(integer) || !is_digits(fraction) {
+        return Err(invalid());
+    }
+
+    // Trailing zeros don't add any precision
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > scale as usize {
+        return Err(DecimalError::TooPrecise(value.to_owned(), scale));
+    }
+
+    let digits = format!("{}{:0<width$}", integer, fraction, width = scale as usize);
+
+    let scaled = digits
+        .parse::<i128>()
+        .ok()
+        .and_then(|scaled| i64::try_from(if is_negative { -scaled } else { scaled }).ok())
+        .ok_or_else(|| DecimalError::OutOfRange(value.to_owned()))?;
+
+    Ok(scaled)
+}
+
+/// Error returned when a value can not be converted into a decimal.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DecimalError {
+    #[error("'{0}' is not a decimal number")]
+    Invalid(String),
+
+    #[error("'{0}' has more than {1} fractional digits")]
+    TooPrecise(String, u32),
+
+    #[error("'{0}' is out of range")]
+    OutOfRange(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::{format_decimal, parse_decimal, DecimalError};
+
+    #[rstest]
+    #[case(1234, 2, "12.34")]
+    #[case(-5, 2, "-0.05")]
+    #[case(0, 3, "0.000")]
+    #[case(42, 0, "42")]
+    #[case(i64::MIN, 2, "-92233720368547758.08")]
+    fn formats_decimals(#[case] value: i64, #[case] scale: u32, #[case] expected: &str) {
+        assert_eq!(format_decimal(value, scale), expected);
+        assert_eq!(parse_decimal(expected, scale), Ok(value));
+    }
+
+    #[rstest]
+    #[case("12.3", 2, Ok(1230))]
+    #[case("12", 2, Ok(1200))]
+    #[case("+.5", 1, Ok(5))]
+    #[case("-1.500", 1, Ok(-15))]
+    #[case("1.005", 2, Err(DecimalError::TooPrecise("1.005".into(), 2)))]
+    #[case("1e3", 2, Err(DecimalError::Invalid("1e3".into())))]
+    #[case("-", 2, Err(DecimalError::Invalid("-".into())))]
+    #[case("1.2.3", 2, Err(DecimalError::Invalid("1.2.3".into())))]
+    #[case(
+        "92233720368547758.08",
+        2,
+        Err(DecimalError::OutOfRange("92233720368547758.08".into()))
+    )]
+    fn parses_decimals(
+        #[case] value: &str,
+        #[case] scale: u32,
+        #[case] expected: Result<i64, DecimalError>,
+    ) {
+        assert_eq!(parse_decimal(value, scale), expected);
+    }
+}
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use p2panda_rs::operation::encode::encode_plain_operation;
+use p2panda_rs::operation::plain::PlainOperation;
+use p2panda_rs::operation::EncodedOperation;
+
+/// Returns true if the operation is encoded in canonical CBOR.
+///
+/// An operation is canonical if encoding its decoded values again results in exactly the same
+/// bytes. Entries don't need this check, their bamboo encoding only allows one representation.
+pub fn is_canonical_operation(
+    plain_operation: &PlainOperation,
+    encoded_operation: &EncodedOperation,
+) -> bool {
+    match encode_plain_operation(plain_operation) {
+        Ok(encoded) => encoded.into_bytes() == encoded_operation.into_bytes(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::operation::decode::decode_operation;
+    use p2panda_rs::operation::EncodedOperation;
+    use p2panda_rs::test_utils::fixtures::encoded_operation;
+    use rstest::rstest;
+
+    use crate::test_utils::helpers::non_canonical_operation;
+
+    use super::is_canonical_operation;
+
+    #[rstest]
+    fn detect_non_canonical_encoding(encoded_operation: EncodedOperation) {
+        let plain_operation = decode_operation(&encoded_operation).unwrap();
+        assert!(is_canonical_operation(&plain_operation, &encoded_operation));
+
+        // Lenient encoding decodes to the same operation
+        let lenient = non_canonical_operation(&encoded_operation);
+        let plain_operation = decode_operation(&lenient).unwrap();
+        assert!(!is_canonical_operation(&plain_operation, &lenient));
+    }
+}
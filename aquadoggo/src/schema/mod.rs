@@ -1,5 +1,21 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod constraints;
+mod datetime;
+mod decimal;
+mod encoding;
+mod pins;
+mod prefetch;
 mod schema_provider;
+mod settings;
 
+pub use constraints::{ConstraintViolation, FieldConstraint};
+pub use datetime::{
+    datetime_value, format_datetime, format_datetime_value, parse_datetime, DateTimeError,
+    DateTimeField,
+};
+pub use decimal::{format_decimal, parse_decimal, DecimalError, DecimalField, MAX_DECIMAL_SCALE};
+pub use pins::{is_pinned_version, SchemaPin, SchemaVersionPolicy};
+pub use prefetch::{prefetched_relations, PrefetchProfile, RelationPath};
 pub use schema_provider::SchemaProvider;
+pub use settings::{FromSettingValue, SchemaSettings, SettingError, SettingValue};
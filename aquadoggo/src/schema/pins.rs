@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use p2panda_rs::schema::system::SchemaView;
+use p2panda_rs::schema::SchemaName;
+
+/// Separator between schema name and version in schema pins.
+const VERSION_SEPARATOR: char = '@';
+
+/// Application schema allowed by its name and version instead of its schema id.
+///
+/// Every operation of a schema definition document creates a new version of the schema, starting
+/// with version 1 for the first one. Pins are written as "<name>@<version>", for example
+/// "venues@2" for the schema named "venues" after its definition got updated once.
+///
+/// Pins get resolved to schema ids when the schema definitions arrive on the node. Schema names
+/// are not unique, the pin matches the given version of every schema definition carrying this
+/// name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaPin {
+    /// Name of the pinned schema.
+    pub name: SchemaName,
+
+    /// Pinned version of the schema, starting with 1.
+    pub version: u64,
+}
+
+impl SchemaPin {
+    /// Returns true if the given string is written like a schema pin.
+    pub fn is_pin(value: &str) -> bool {
+        value.contains(VERSION_SEPARATOR)
+    }
+}
+
+impl FromStr for SchemaPin {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, version) = s
+            .split_once(VERSION_SEPARATOR)
+            .ok_or_else(|| anyhow!("Schema pin '{s}' is missing a version"))?;
+
+        let name = SchemaName::new(name).map_err(|_| anyhow!("Invalid schema name '{name}'"))?;
+        let version: u64 = version
+            .parse()
+            .map_err(|_| anyhow!("Invalid schema version '{version}'"))?;
+
+        if version == 0 {
+            bail!("Schema versions start with 1");
+        }
+
+        Ok(Self { name, version })
+    }
+}
+
+impl Display for SchemaPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.name, VERSION_SEPARATOR, self.version)
+    }
+}
+
+/// Versions of pinned schemas which get allowed automatically besides the pinned one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaVersionPolicy {
+    /// Only the pinned version is allowed.
+    #[default]
+    Exact,
+
+    /// Later versions are allowed as long as they keep all fields of the pinned version
+    /// unchanged, for example when fields got added.
+    Compatible,
+
+    /// All later versions are allowed.
+    Latest,
+}
+
+impl SchemaVersionPolicy {
+    /// Returns the name of this policy.
+    pub fn as_str(&self) -> &str {
+        match self {
+            SchemaVersionPolicy::Exact => "exact",
+            SchemaVersionPolicy::Compatible => "compatible",
+            SchemaVersionPolicy::Latest => "latest",
+        }
+    }
+}
+
+impl FromStr for SchemaVersionPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(SchemaVersionPolicy::Exact),
+            "compatible" => Ok(SchemaVersionPolicy::Compatible),
+            "latest" => Ok(SchemaVersionPolicy::Latest),
+            _ => Err(anyhow!("Unknown schema version policy '{s}'")),
+        }
+    }
+}
+
+impl Display for SchemaVersionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Returns true if the last of the given versions of a schema definition is allowed by one of the
+/// pins.
+///
+/// Versions need to be ordered, starting with the first version of the schema definition.
+/// Versions are compatible when they pin the same schema field definitions as the pinned version,
+/// fields can only be added.
+pub fn is_pinned_version(
+    pins: &[SchemaPin],
+    policy: SchemaVersionPolicy,
+    versions: &[SchemaView],
+) -> bool {
+    let latest = match versions.last() {
+        Some(latest) => latest,
+        None => return false,
+    };
+    let version = versions.len() as u64;
+
+    pins.iter()
+        .filter(|pin| pin.version <= version && pin.name == *latest.name())
+        .any(|pin| {
+            let pinned = &versions[pin.version as usize - 1];
+            if pinned.name() != latest.name() {
+                return false;
+            }
+
+            match policy {
+                _ if pin.version == version => true,
+                SchemaVersionPolicy::Exact => false,
+                SchemaVersionPolicy::Compatible => pinned.fields().iter().all(|field| {
+                    latest
+                        .fields()
+                        .iter()
+                        .any(|latest_field| latest_field == field)
+                }),
+                SchemaVersionPolicy::Latest => true,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use p2panda_rs::document::{
+        DocumentView, DocumentViewFields, DocumentViewId, DocumentViewValue,
+    };
+    use p2panda_rs::operation::{OperationValue, PinnedRelationList};
+    use p2panda_rs::schema::system::SchemaView;
+    use p2panda_rs::test_utils::fixtures::{random_document_view_id, random_operation_id};
+    use rstest::rstest;
+
+    use super::{is_pinned_version, SchemaPin, SchemaVersionPolicy};
+
+    fn schema_view(name: &str, fields: &[DocumentViewId]) -> SchemaView {
+        let operation_id = random_operation_id();
+        let mut view_fields = DocumentViewFields::new();
+        view_fields.insert(
+            "name",
+            DocumentViewValue::new(&operation_id, &OperationValue::String(name.to_string())),
+        );
+        view_fields.insert(
+            "description",
+            DocumentViewValue::new(&operation_id, &OperationValue::String("".to_string())),
+        );
+        view_fields.insert(
+            "fields",
+            DocumentViewValue::new(
+                &operation_id,
+                &OperationValue::PinnedRelationList(PinnedRelationList::new(fields.to_vec())),
+            ),
+        );
+
+        let view = DocumentView::new(&DocumentViewId::new(&[operation_id]), &view_fields);
+        SchemaView::try_from(view).unwrap()
+    }
+
+    #[test]
+    fn parse_pins() {
+        let pin: SchemaPin = "venues@2".parse().unwrap();
+        assert_eq!(pin.name.to_string(), "venues");
+        assert_eq!(pin.version, 2);
+        assert_eq!(pin.to_string(), "venues@2");
+
+        assert!(SchemaPin::is_pin("venues@2"));
+        assert!("venues@0".parse::<SchemaPin>().is_err());
+        assert!("venues@latest".parse::<SchemaPin>().is_err());
+        assert!("@1".parse::<SchemaPin>().is_err());
+    }
+
+    #[rstest]
+    fn pinned_versions(
+        #[from(random_document_view_id)] title: DocumentViewId,
+        #[from(random_document_view_id)] address: DocumentViewId,
+        #[from(random_document_view_id)] new_title: DocumentViewId,
+    ) {
+        let v1 = schema_view("venues", &[title.clone()]);
+        let v2 = schema_view("venues", &[title.clone(), address.clone()]);
+        let v3 = schema_view("venues", &[new_title, address]);
+
+        let pins = vec!["venues@1".parse::<SchemaPin>().unwrap()];
+
+        // Pinned version is always allowed
+        for policy in [
+            SchemaVersionPolicy::Exact,
+            SchemaVersionPolicy::Compatible,
+            SchemaVersionPolicy::Latest,
+        ] {
+            assert!(is_pinned_version(&pins, policy, &[v1.clone()]));
+        }
+
+        // Added fields are compatible, changed ones are not
+        let history = [v1.clone(), v2.clone()];
+        assert!(!is_pinned_version(
+            &pins,
+            SchemaVersionPolicy::Exact,
+            &history
+        ));
+        assert!(is_pinned_version(
+            &pins,
+            SchemaVersionPolicy::Compatible,
+            &history
+        ));
+
+        let history = [v1.clone(), v2, v3];
+        assert!(!is_pinned_version(
+            &pins,
+            SchemaVersionPolicy::Compatible,
+            &history
+        ));
+        assert!(is_pinned_version(
+            &pins,
+            SchemaVersionPolicy::Latest,
+            &history
+        ));
+
+        // Other schemas are not affected
+        let other = schema_view("events", &[title]);
+        assert!(!is_pinned_version(
+            &pins,
+            SchemaVersionPolicy::Latest,
+            &[v1, other]
+        ));
+    }
+}
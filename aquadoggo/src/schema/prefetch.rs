@@ -0,0 +1,109 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use anyhow::bail;
+use p2panda_rs::schema::SchemaId;
+
+/// Separator between the field names of a relation path.
+const PATH_SEPARATOR: char = '.';
+
+/// Relation fields followed one after another, starting at a document of the profile's schema.
+///
+/// Paths are written as field names separated by dots, for example "venue.owner" follows the
+/// "venue" relation of a document and then the "owner" relation of the venue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationPath(Vec<String>);
+
+impl RelationPath {
+    /// Returns the names of the followed relation fields, in order.
+    pub fn fields(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl FromStr for RelationPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<String> = s.split(PATH_SEPARATOR).map(str::to_string).collect();
+
+        if fields.iter().any(|field| field.is_empty()) {
+            bail!("Relation path '{s}' contains an empty field name");
+        }
+
+        Ok(Self(fields))
+    }
+}
+
+impl Display for RelationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join(&PATH_SEPARATOR.to_string()))
+    }
+}
+
+/// Relations of an application schema which get prefetched whenever one of its documents
+/// changes.
+///
+/// Related documents are loaded into the in-memory cache of document views, the first client
+/// query traversing these relations after a document got synced is answered without reading them
+/// from the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrefetchProfile {
+    /// Schema of the documents whose relations get prefetched.
+    pub schema_id: SchemaId,
+
+    /// Relation paths which get followed.
+    pub relations: Vec<RelationPath>,
+}
+
+impl PrefetchProfile {
+    /// Returns a profile prefetching the given relation paths of a schema.
+    pub fn new(schema_id: &SchemaId, relations: &[RelationPath]) -> Self {
+        Self {
+            schema_id: schema_id.to_owned(),
+            relations: relations.to_vec(),
+        }
+    }
+}
+
+/// Returns the relation paths which get prefetched for documents of the given schema.
+pub fn prefetched_relations<'a>(
+    profiles: &'a [PrefetchProfile],
+    schema_id: &'a SchemaId,
+) -> impl Iterator<Item = &'a RelationPath> {
+    profiles
+        .iter()
+        .filter(move |profile| &profile.schema_id == schema_id)
+        .flat_map(|profile| profile.relations.iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::fixtures::schema_id;
+    use rstest::rstest;
+
+    use super::{prefetched_relations, PrefetchProfile, RelationPath};
+
+    #[rstest]
+    fn parse_relation_paths(schema_id: SchemaId) {
+        let path: RelationPath = "venue.owner".parse().unwrap();
+        assert_eq!(path.fields(), ["venue", "owner"]);
+        assert_eq!(path.to_string(), "venue.owner");
+
+        assert!("".parse::<RelationPath>().is_err());
+        assert!("venue..owner".parse::<RelationPath>().is_err());
+
+        let profiles = vec![PrefetchProfile::new(&schema_id, &[path.clone()])];
+        assert_eq!(
+            prefetched_relations(&profiles, &schema_id).collect::<Vec<_>>(),
+            vec![&path]
+        );
+        assert_eq!(
+            prefetched_relations(&profiles, &SchemaId::SchemaDefinition(1)).count(),
+            0
+        );
+    }
+}
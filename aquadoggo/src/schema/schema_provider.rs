@@ -4,13 +4,45 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::{bail, Result};
-use log::{debug, info, trace};
-use p2panda_rs::schema::{Schema, SchemaId, SYSTEM_SCHEMAS};
+use log::{debug, info, trace, warn};
+use p2panda_rs::operation::plain::PlainOperation;
+use p2panda_rs::operation::validate::validate_operation;
+use p2panda_rs::operation::EncodedOperation;
+use p2panda_rs::schema::system::SchemaView;
+use p2panda_rs::schema::{FieldType, Schema, SchemaId, SchemaName, SYSTEM_SCHEMAS};
 use p2panda_rs::Human;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
+use crate::bus::{ServiceMessage, ServiceSender};
 use crate::config::AllowList;
+use crate::schema::constraints::{check_constraints, ConstraintViolation, FieldConstraint};
+use crate::schema::datetime::{check_datetimes, is_datetime_field, DateTimeField};
+use crate::schema::decimal::{decimal_scale, DecimalField};
+use crate::schema::encoding::is_canonical_operation;
+use crate::schema::pins::{is_pinned_version, SchemaPin, SchemaVersionPolicy};
+use crate::schema::settings::{FromSettingValue, SchemaSettings, SettingError};
+
+/// Change of a schema known to the schema provider.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SchemaEvent {
+    /// Schema was not known before and got added.
+    Added(SchemaId),
+
+    /// Known schema got replaced.
+    Updated(SchemaId),
+}
+
+impl From<SchemaEvent> for ServiceMessage {
+    fn from(event: SchemaEvent) -> Self {
+        match event {
+            SchemaEvent::Added(schema_id) => ServiceMessage::SchemaAdded(schema_id),
+            SchemaEvent::Updated(schema_id) => ServiceMessage::SchemaUpdated(schema_id),
+        }
+    }
+}
 
 /// Provides fast access to system and application schemas.
 ///
@@ -24,8 +56,33 @@ pub struct SchemaProvider {
     /// on this node, if not set _all_ schema ids are accepted (wildcard).
     allow_schema_ids: AllowList<SchemaId>,
 
-    /// Sender for broadcast channel informing subscribers about updated schemas.
-    tx: Sender<SchemaId>,
+    /// Schemas allowed by their name and version besides the allowed schema ids.
+    schema_pins: Arc<Vec<SchemaPin>>,
+
+    /// Later versions of pinned schemas which get allowed as well.
+    version_policy: SchemaVersionPolicy,
+
+    /// Schema ids the pins got resolved to so far.
+    pinned_schema_ids: Arc<Mutex<Vec<SchemaId>>>,
+
+    /// Validation constraints for fields of application schemas.
+    field_constraints: Arc<Vec<FieldConstraint>>,
+
+    /// `int` fields of application schemas holding decimals with a fixed number of fractional
+    /// digits.
+    decimal_fields: Arc<Vec<DecimalField>>,
+
+    /// `int` and `str` fields of application schemas holding dates and times.
+    datetime_fields: Arc<Vec<DateTimeField>>,
+
+    /// Reject operations which are not encoded in canonical CBOR.
+    canonical_encoding: bool,
+
+    /// Node settings attached to application schemas.
+    schema_settings: Arc<HashMap<SchemaId, SchemaSettings>>,
+
+    /// Sender for broadcast channel informing subscribers about added and updated schemas.
+    tx: Sender<SchemaEvent>,
 }
 
 impl SchemaProvider {
@@ -60,15 +117,207 @@ impl SchemaProvider {
         Self {
             schemas: Arc::new(Mutex::new(index)),
             allow_schema_ids,
+            schema_pins: Arc::new(Vec::new()),
+            version_policy: SchemaVersionPolicy::default(),
+            pinned_schema_ids: Arc::new(Mutex::new(Vec::new())),
+            field_constraints: Arc::new(Vec::new()),
+            decimal_fields: Arc::new(Vec::new()),
+            datetime_fields: Arc::new(Vec::new()),
+            canonical_encoding: false,
+            schema_settings: Arc::new(HashMap::new()),
             tx,
         }
     }
 
+    /// Allow schemas by their name and version, besides the allowed schema ids.
+    ///
+    /// Pins are resolved to schema ids with `allow_pinned_version` when their schema definitions
+    /// arrive. Later versions of pinned schemas get allowed as well according to the given policy.
+    pub fn with_schema_pins(
+        mut self,
+        schema_pins: Vec<SchemaPin>,
+        version_policy: SchemaVersionPolicy,
+    ) -> Self {
+        self.schema_pins = Arc::new(schema_pins);
+        self.version_policy = version_policy;
+        self
+    }
+
+    /// Returns true if schemas with the given name are pinned in the allow-list.
+    pub fn is_pinned(&self, name: &SchemaName) -> bool {
+        self.is_allow_list_active() && self.schema_pins.iter().any(|pin| &pin.name == name)
+    }
+
+    /// Allow the last of the given versions of a schema definition when one of the pins resolves
+    /// to it.
+    ///
+    /// Versions need to be ordered, starting with the first version of the schema definition.
+    /// Returns true if the schema is allowed now.
+    pub async fn allow_pinned_version(&self, versions: &[SchemaView]) -> bool {
+        if !self.is_allow_list_active()
+            || !is_pinned_version(&self.schema_pins, self.version_policy, versions)
+        {
+            return false;
+        }
+
+        // We checked above that there is at least one version
+        let latest = versions.last().unwrap();
+        let schema_id = SchemaId::Application(latest.name().to_owned(), latest.id().to_owned());
+
+        let mut pinned_schema_ids = self.pinned_schema_ids.lock().await;
+        if !pinned_schema_ids.contains(&schema_id) {
+            info!(
+                "Allow {} (version {}) pinned in allow-list",
+                schema_id.display(),
+                versions.len()
+            );
+            pinned_schema_ids.push(schema_id);
+        }
+
+        true
+    }
+
+    /// Enforce the given validation constraints on fields of application schemas.
+    pub fn with_field_constraints(mut self, field_constraints: Vec<FieldConstraint>) -> Self {
+        self.field_constraints = Arc::new(field_constraints);
+        self
+    }
+
+    /// Treat the given `int` fields of application schemas as decimals.
+    pub fn with_decimal_fields(mut self, decimal_fields: Vec<DecimalField>) -> Self {
+        self.decimal_fields = Arc::new(decimal_fields);
+        self
+    }
+
+    /// Treat the given `int` and `str` fields of application schemas as dates and times.
+    pub fn with_datetime_fields(mut self, datetime_fields: Vec<DateTimeField>) -> Self {
+        self.datetime_fields = Arc::new(datetime_fields);
+        self
+    }
+
+    /// Only accept operations which are encoded in canonical CBOR.
+    pub fn with_canonical_encoding(mut self, canonical_encoding: bool) -> Self {
+        self.canonical_encoding = canonical_encoding;
+        self
+    }
+
+    /// Attach the given node settings to application schemas.
+    pub fn with_schema_settings(
+        mut self,
+        schema_settings: HashMap<SchemaId, SchemaSettings>,
+    ) -> Self {
+        self.schema_settings = Arc::new(schema_settings);
+        self
+    }
+
+    /// Returns the value of a node setting of the given schema, `None` if it is not set.
+    ///
+    /// An error is returned if the setting holds a value of another type than requested.
+    pub fn setting<T: FromSettingValue>(
+        &self,
+        schema_id: &SchemaId,
+        key: &str,
+    ) -> Result<Option<T>, SettingError> {
+        match self.schema_settings.get(schema_id) {
+            Some(settings) => settings.get(key),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns true if documents of this schema created by other nodes need to be approved before
+    /// they show up in queries.
+    ///
+    /// This is enabled with the "moderated" node setting of a schema.
+    pub fn is_moderated(&self, schema_id: &SchemaId) -> bool {
+        matches!(self.setting::<bool>(schema_id, "moderated"), Ok(Some(true)))
+    }
+
+    /// Returns the number of fractional digits if the given field holds decimals.
+    ///
+    /// Only `int` fields can hold decimals, other fields are never treated as such.
+    pub fn decimal_scale(&self, schema: &Schema, field: &str) -> Option<u32> {
+        match schema.fields().get(field) {
+            Some(FieldType::Integer) => decimal_scale(&self.decimal_fields, schema.id(), field),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the given field holds dates and times.
+    ///
+    /// Only `int` and `str` fields can hold dates and times, other fields are never treated as
+    /// such.
+    pub fn is_datetime_field(&self, schema: &Schema, field: &str) -> bool {
+        match schema.fields().get(field) {
+            Some(FieldType::Integer) | Some(FieldType::String) => {
+                is_datetime_field(&self.datetime_fields, schema.id(), field)
+            }
+            _ => false,
+        }
+    }
+
+    /// Check if the fields of an operation satisfy all validation constraints of its schema.
+    ///
+    /// Fields holding dates and times need to contain valid values as well.
+    pub fn check_constraints(
+        &self,
+        schema: &Schema,
+        operation: &PlainOperation,
+    ) -> Result<(), ConstraintViolation> {
+        if self.field_constraints.is_empty() && self.datetime_fields.is_empty() {
+            return Ok(());
+        }
+
+        // Operations not matching their schema get rejected when publishing them, we don't need
+        // to report this here
+        match validate_operation(operation, schema) {
+            Ok(operation) => {
+                check_constraints(&self.field_constraints, &operation)?;
+                check_datetimes(&self.datetime_fields, &operation)
+            }
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// Check if the encoding of an operation is accepted by this node.
+    ///
+    /// Every encoding is accepted unless canonical encoding is required.
+    pub fn accepts_encoding(
+        &self,
+        operation: &PlainOperation,
+        encoded_operation: &EncodedOperation,
+    ) -> bool {
+        !self.canonical_encoding || is_canonical_operation(operation, encoded_operation)
+    }
+
     /// Returns receiver for broadcast channel.
-    pub fn on_schema_added(&self) -> Receiver<SchemaId> {
+    pub fn on_schema_changed(&self) -> Receiver<SchemaEvent> {
         self.tx.subscribe()
     }
 
+    /// Spawns a task forwarding all schema changes as messages onto the communication bus.
+    ///
+    /// Services react to these explicit `SchemaAdded` and `SchemaUpdated` messages instead of
+    /// subscribing to the schema provider themselves.
+    pub fn forward_events(&self, tx: ServiceSender) -> JoinHandle<()> {
+        let mut rx = self.on_schema_changed();
+
+        tokio::task::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        if tx.send(event.into()).is_err() {
+                            debug!("No service has been informed about changed schema");
+                        }
+                    }
+                    Err(RecvError::Lagged(count)) => {
+                        warn!("Missed forwarding {} schema changes onto the bus", count);
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+
     /// Retrieve a schema that may be a system or application schema by its schema id.
     pub async fn get(&self, schema_id: &SchemaId) -> Option<Schema> {
         self.schemas.lock().await.get(schema_id).cloned()
@@ -85,7 +334,9 @@ impl SchemaProvider {
     /// `false` if it was inserted.
     pub async fn update(&self, schema: Schema) -> Result<bool> {
         if let AllowList::Set(allow_schema_ids) = &self.allow_schema_ids {
-            if !allow_schema_ids.contains(schema.id()) {
+            if !allow_schema_ids.contains(schema.id())
+                && !self.pinned_schema_ids.lock().await.contains(schema.id())
+            {
                 bail!("Attempted to add unsupported schema to schema provider");
             }
         };
@@ -105,7 +356,13 @@ impl SchemaProvider {
             .is_some();
 
         // Inform subscribers about new schema
-        if self.tx.send(schema.id().to_owned()).is_err() {
+        let event = if is_update {
+            SchemaEvent::Updated(schema.id().to_owned())
+        } else {
+            SchemaEvent::Added(schema.id().to_owned())
+        };
+
+        if self.tx.send(event).is_err() {
             debug!("No subscriber has been informed about inserted / updated schema");
         }
 
@@ -115,10 +372,15 @@ impl SchemaProvider {
     /// Returns a list of all supported schema ids.
     ///
     /// If no allow-list was set it returns the list of all currently known schema ids. If an
-    /// allo-wlist was set it directly returns the list itself.
+    /// allo-wlist was set it directly returns the list itself, together with the schema ids pins
+    /// got resolved to.
     pub async fn supported_schema_ids(&self) -> Vec<SchemaId> {
         match &self.allow_schema_ids {
-            AllowList::Set(schema_ids) => schema_ids.clone(),
+            AllowList::Set(schema_ids) => {
+                let mut schema_ids = schema_ids.clone();
+                schema_ids.extend(self.pinned_schema_ids.lock().await.iter().cloned());
+                schema_ids
+            }
             AllowList::Wildcard => self
                 .all()
                 .await
@@ -143,12 +405,17 @@ impl Default for SchemaProvider {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
+
     use p2panda_rs::schema::{FieldType, Schema, SchemaId, SchemaName};
     use p2panda_rs::test_utils::fixtures::random_document_view_id;
+    use tokio::sync::broadcast;
 
+    use crate::bus::ServiceMessage;
+    use crate::schema::{SchemaSettings, SettingValue};
     use crate::AllowList;
 
-    use super::SchemaProvider;
+    use super::{SchemaEvent, SchemaProvider};
 
     #[tokio::test]
     async fn get_all_schemas() {
@@ -222,4 +489,65 @@ mod test {
 
         assert!(provider.get(&new_schema_id).await.is_none());
     }
+
+    #[tokio::test]
+    async fn forward_schema_events() {
+        let provider = SchemaProvider::default();
+        let (tx, mut rx) = broadcast::channel(16);
+        let mut on_schema_changed = provider.on_schema_changed();
+        provider.forward_events(tx);
+
+        let new_schema_id = SchemaId::Application(
+            SchemaName::new("test_schema").unwrap(),
+            random_document_view_id(),
+        );
+        let new_schema = Schema::new(
+            &new_schema_id,
+            "description",
+            &[("test_field", FieldType::String)],
+        )
+        .unwrap();
+        provider.update(new_schema.clone()).await.unwrap();
+
+        // Schema which already exists in its current state is not announced again
+        provider.update(new_schema).await.unwrap();
+
+        assert_eq!(
+            on_schema_changed.recv().await.unwrap(),
+            SchemaEvent::Added(new_schema_id.clone())
+        );
+        assert!(on_schema_changed.try_recv().is_err());
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            ServiceMessage::SchemaAdded(new_schema_id)
+        );
+    }
+
+    #[test]
+    fn read_schema_settings() {
+        let schema_id = SchemaId::Application(
+            SchemaName::new("test_schema").unwrap(),
+            random_document_view_id(),
+        );
+        let mut settings = SchemaSettings::default();
+        settings.insert("retention", SettingValue::Integer(3600));
+        settings.insert("moderated", SettingValue::Boolean(true));
+
+        let provider = SchemaProvider::default()
+            .with_schema_settings(HashMap::from([(schema_id.clone(), settings)]));
+
+        assert_eq!(
+            provider.setting::<u64>(&schema_id, "retention"),
+            Ok(Some(3600))
+        );
+        assert!(provider.setting::<bool>(&schema_id, "retention").is_err());
+        assert_eq!(provider.setting::<u64>(&schema_id, "quota"), Ok(None));
+        assert_eq!(
+            provider.setting::<u64>(&SchemaId::SchemaDefinition(1), "retention"),
+            Ok(None)
+        );
+
+        assert!(provider.is_moderated(&schema_id));
+        assert!(!provider.is_moderated(&SchemaId::SchemaDefinition(1)));
+    }
 }
@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryInto;
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Value of a per-schema node setting as given in the config file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SettingValue {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<SettingValue>),
+    Table(BTreeMap<String, SettingValue>),
+}
+
+impl SettingValue {
+    /// Returns the name of the value type as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            SettingValue::Boolean(_) => "boolean",
+            SettingValue::Integer(_) => "integer",
+            SettingValue::Float(_) => "float",
+            SettingValue::String(_) => "string",
+            SettingValue::List(_) => "list",
+            SettingValue::Table(_) => "table",
+        }
+    }
+}
+
+impl Display for SettingValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingValue::Boolean(value) => write!(f, "{value}"),
+            SettingValue::Integer(value) => write!(f, "{value}"),
+            SettingValue::Float(value) => write!(f, "{value}"),
+            SettingValue::String(value) => write!(f, "\"{value}\""),
+            SettingValue::List(_) | SettingValue::Table(_) => write!(f, "{}", self.type_name()),
+        }
+    }
+}
+
+/// Error returned when a setting does not hold a value of the requested type.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("Setting '{key}' expects {expected} value, found {found}")]
+pub struct SettingError {
+    /// Key of the setting.
+    pub key: String,
+
+    /// Description of the requested type, for example "a positive integer".
+    pub expected: &'static str,
+
+    /// The value given for this setting.
+    pub found: String,
+}
+
+/// Types which per-schema settings can be read as.
+pub trait FromSettingValue: Sized {
+    /// Description of the type as used in error messages, for example "a positive integer".
+    const EXPECTED: &'static str;
+
+    /// Converts the setting value, returns `None` if it does not hold a value of this type.
+    fn from_setting_value(value: &SettingValue) -> Option<Self>;
+}
+
+impl FromSettingValue for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        match value {
+            SettingValue::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromSettingValue for i64 {
+    const EXPECTED: &'static str = "an integer";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        match value {
+            SettingValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromSettingValue for u32 {
+    const EXPECTED: &'static str = "a positive integer";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        i64::from_setting_value(value).and_then(|value| value.try_into().ok())
+    }
+}
+
+impl FromSettingValue for u64 {
+    const EXPECTED: &'static str = "a positive integer";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        i64::from_setting_value(value).and_then(|value| value.try_into().ok())
+    }
+}
+
+impl FromSettingValue for usize {
+    const EXPECTED: &'static str = "a positive integer";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        i64::from_setting_value(value).and_then(|value| value.try_into().ok())
+    }
+}
+
+impl FromSettingValue for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        match value {
+            SettingValue::Float(value) => Some(*value),
+            SettingValue::Integer(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FromSettingValue for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        match value {
+            SettingValue::String(value) => Some(value.to_owned()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromSettingValue> FromSettingValue for Vec<T> {
+    const EXPECTED: &'static str = "a list";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        match value {
+            SettingValue::List(values) => values.iter().map(T::from_setting_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl FromSettingValue for SettingValue {
+    const EXPECTED: &'static str = "any";
+
+    fn from_setting_value(value: &SettingValue) -> Option<Self> {
+        Some(value.to_owned())
+    }
+}
+
+/// Node settings attached to a single schema, for example limits or hooks of subsystems which
+/// only apply to documents of this schema.
+///
+/// Settings are arbitrary key-value pairs, every subsystem reads the keys it knows about with the
+/// type it expects.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SchemaSettings(HashMap<String, SettingValue>);
+
+impl SchemaSettings {
+    /// Returns settings containing the given values.
+    pub fn new(values: HashMap<String, SettingValue>) -> Self {
+        Self(values)
+    }
+
+    /// Sets the value of a setting, replacing the previous one.
+    pub fn insert(&mut self, key: &str, value: SettingValue) {
+        self.0.insert(key.to_owned(), value);
+    }
+
+    /// Returns the value of a setting in the requested type, `None` if it is not set.
+    ///
+    /// An error is returned if the setting holds a value of another type.
+    pub fn get<T: FromSettingValue>(&self, key: &str) -> Result<Option<T>, SettingError> {
+        match self.0.get(key) {
+            Some(value) => T::from_setting_value(value)
+                .map(Some)
+                .ok_or_else(|| SettingError {
+                    key: key.to_owned(),
+                    expected: T::EXPECTED,
+                    found: value.to_string(),
+                }),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the keys of all settings.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.0.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{SchemaSettings, SettingError, SettingValue};
+
+    #[test]
+    fn typed_settings() {
+        let settings = SchemaSettings::new(HashMap::from([
+            ("retention".to_string(), SettingValue::Integer(3600)),
+            ("quota".to_string(), SettingValue::Integer(-1)),
+            ("ratio".to_string(), SettingValue::Integer(2)),
+            (
+                "webhooks".to_string(),
+                SettingValue::List(vec![SettingValue::String("http://localhost".into())]),
+            ),
+        ]));
+
+        assert_eq!(settings.get::<u64>("retention"), Ok(Some(3600)));
+        assert_eq!(settings.get::<f64>("ratio"), Ok(Some(2.0)));
+        assert_eq!(
+            settings.get::<Vec<String>>("webhooks"),
+            Ok(Some(vec!["http://localhost".to_string()]))
+        );
+        assert_eq!(settings.get::<bool>("unknown"), Ok(None));
+
+        assert_eq!(
+            settings.get::<usize>("quota"),
+            Err(SettingError {
+                key: "quota".into(),
+                expected: "a positive integer",
+                found: "-1".into(),
+            })
+        );
+        assert_eq!(
+            settings.get::<String>("retention").unwrap_err().to_string(),
+            "Setting 'retention' expects a string value, found 3600"
+        );
+    }
+}
@@ -0,0 +1,236 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Observable phases of starting a node, reported via logs, node events and readiness probes.
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use serde::Serialize;
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::sync::watch;
+
+use crate::api::NodeEvent;
+
+/// Interval after which a startup phase which did not complete yet gets logged again.
+const STALL_WARNING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Phase of starting a node, phases run one after another in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupPhase {
+    /// Creating the databases and running pending migrations, this can take long after upgrades.
+    Migrations,
+
+    /// Preparing the store and rolling back batches which were not published completely.
+    Store,
+
+    /// Loading all known schemas into the schema provider and resolving pinned schemas.
+    SchemaProvider,
+
+    /// Binding the HTTP server and the network listeners.
+    Network,
+
+    /// Spawning all remaining services.
+    Services,
+
+    /// The node started and is ready to serve requests.
+    Ready,
+}
+
+impl fmt::Display for StartupPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StartupPhase::Migrations => "migrations",
+            StartupPhase::Store => "store",
+            StartupPhase::SchemaProvider => "schema provider",
+            StartupPhase::Network => "network",
+            StartupPhase::Services => "services",
+            StartupPhase::Ready => "ready",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// Progress of starting a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupStatus {
+    /// Phase the node is currently in.
+    pub phase: StartupPhase,
+
+    /// Time when the current phase began.
+    pub phase_started_at: Instant,
+
+    /// Completed phases and how long they took, in the order they ran.
+    pub completed: Vec<(StartupPhase, Duration)>,
+}
+
+impl StartupStatus {
+    /// Returns true if the node started completely.
+    pub fn is_ready(&self) -> bool {
+        self.phase == StartupPhase::Ready
+    }
+}
+
+/// Shared handle reporting the startup progress of a node.
+///
+/// Every phase change is logged, phases which take long are logged repeatedly so slow startups
+/// can be told apart from hanging ones. Applications can observe the phases as node events with
+/// `subscribe`, the HTTP service exposes them via its readiness probe.
+#[derive(Debug, Clone)]
+pub struct Readiness {
+    tx: Arc<watch::Sender<StartupStatus>>,
+}
+
+impl Readiness {
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(StartupStatus {
+            phase: StartupPhase::Migrations,
+            phase_started_at: Instant::now(),
+            completed: Vec::new(),
+        });
+
+        Self { tx: Arc::new(tx) }
+    }
+
+    /// Move on to the next startup phase, completing the current one.
+    ///
+    /// Entering the current phase again only restarts its clock, this is used when the handle was
+    /// created a while before the node started.
+    pub fn enter(&self, phase: StartupPhase) {
+        self.tx.send_modify(|status| {
+            if status.phase != phase {
+                let duration = status.phase_started_at.elapsed();
+                info!(
+                    "Completed startup phase {} in {}ms",
+                    status.phase,
+                    duration.as_millis()
+                );
+                status.completed.push((status.phase, duration));
+                status.phase = phase;
+            }
+
+            status.phase_started_at = Instant::now();
+        });
+
+        if phase == StartupPhase::Ready {
+            info!("Node is ready");
+        } else {
+            info!("Entering startup phase {}", phase);
+        }
+    }
+
+    /// Returns the current startup progress.
+    pub fn status(&self) -> StartupStatus {
+        self.tx.borrow().clone()
+    }
+
+    /// Returns true if the node started completely.
+    pub fn is_ready(&self) -> bool {
+        self.tx.borrow().is_ready()
+    }
+
+    /// Subscribe to completed startup phases.
+    ///
+    /// Phases which completed before subscribing are reported as well. The channel closes after
+    /// the last phase completed and the node is ready.
+    pub fn subscribe(&self) -> Receiver<NodeEvent> {
+        let mut rx = self.tx.subscribe();
+        let (events_tx, events_rx) = mpsc::channel::<NodeEvent>(16);
+
+        tokio::task::spawn(async move {
+            let mut reported = 0;
+
+            loop {
+                let (completed, is_ready) = {
+                    let status = rx.borrow_and_update();
+                    (status.completed[reported..].to_vec(), status.is_ready())
+                };
+
+                for (phase, duration) in completed {
+                    reported += 1;
+
+                    if events_tx
+                        .send(NodeEvent::StartupPhaseCompleted(phase, duration))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+
+                if is_ready || rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        events_rx
+    }
+
+    /// Log a warning whenever a startup phase is still running after a while, until the node is
+    /// ready.
+    pub async fn warn_on_stalls(self) {
+        let mut rx = self.tx.subscribe();
+
+        while !self.is_ready() {
+            if tokio::time::timeout(STALL_WARNING_INTERVAL, rx.changed())
+                .await
+                .is_err()
+            {
+                let status = self.status();
+                warn!(
+                    "Startup phase {} is still running after {}s",
+                    status.phase,
+                    status.phase_started_at.elapsed().as_secs()
+                );
+            }
+        }
+    }
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::NodeEvent;
+
+    use super::{Readiness, StartupPhase};
+
+    #[tokio::test]
+    async fn reports_completed_phases() {
+        let readiness = Readiness::new();
+        readiness.enter(StartupPhase::Migrations);
+        readiness.enter(StartupPhase::Store);
+        assert!(!readiness.is_ready());
+
+        // Phases completed before subscribing get reported as well
+        let mut rx = readiness.subscribe();
+        readiness.enter(StartupPhase::SchemaProvider);
+        readiness.enter(StartupPhase::Ready);
+        assert!(readiness.is_ready());
+
+        let mut phases = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                NodeEvent::StartupPhaseCompleted(phase, _) => phases.push(phase),
+                _ => panic!("Unexpected node event"),
+            }
+        }
+
+        assert_eq!(
+            phases,
+            vec![
+                StartupPhase::Migrations,
+                StartupPhase::Store,
+                StartupPhase::SchemaProvider
+            ]
+        );
+        assert_eq!(readiness.status().completed.len(), 3);
+    }
+}
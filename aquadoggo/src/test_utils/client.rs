@@ -9,12 +9,16 @@ use axum::BoxError;
 use http::header::{HeaderName, HeaderValue};
 use http::{HeaderMap, Request, StatusCode};
 use hyper::{Body, Server};
+use p2panda_rs::identity::KeyPair;
 use tokio::sync::broadcast;
 use tower::make::Shared;
 use tower_service::Service;
 
-use crate::graphql::GraphQLSchemaManager;
+use crate::authors::AuthorKeys;
+use crate::capabilities::{AuthToken, CapabilityProvider};
+use crate::graphql::{GraphQLSchemaManager, GraphQLSharedData, RelationLimits};
 use crate::http::{build_server, HttpServiceContext};
+use crate::replication::now;
 use crate::test_utils::TestNode;
 
 /// HTTP client for testing request and responses.
@@ -70,22 +74,53 @@ impl TestClient {
 pub async fn http_test_client(node: &TestNode) -> TestClient {
     let (tx, _) = broadcast::channel(120);
 
+    // Inform GraphQL schema manager about schemas added to the node during tests
+    node.context.schema_provider.forward_events(tx.clone());
+
+    let author_keys = AuthorKeys::load(&node.context.store, &node.context.config.service_accounts)
+        .await
+        .expect("Load service accounts");
     let manager = GraphQLSchemaManager::new(
-        node.context.store.clone(),
-        tx,
-        node.context.schema_provider.clone(),
+        GraphQLSharedData::new(
+            node.context.store.clone(),
+            tx,
+            node.context.schema_provider.clone(),
+        )
+        .with_capability_provider(
+            CapabilityProvider::new(
+                node.context.config.capability_schema_id.clone(),
+                node.context.config.admin_public_keys.clone(),
+            )
+            .with_read_acl_field(node.context.config.read_acl_field.clone()),
+        )
+        .with_network_metrics(node.context.network_metrics.clone())
+        .with_local_addresses(node.context.local_addresses.clone())
+        .with_author_keys(author_keys),
     )
-    .await;
+    .await
+    .with_relation_limits(RelationLimits {
+        max_depth: node.context.config.graphql_max_relation_depth,
+        max_documents: node.context.config.graphql_max_related_documents,
+    })
+    .with_http_identity(node.context.config.http_identity.clone())
+    .with_blob_store(node.context.blob_store.clone());
 
     let http_context = HttpServiceContext::new(
         node.context.store.clone(),
         manager,
-        node.context.config.blobs_base_path.to_path_buf(),
-    );
+        node.context.blob_store.clone(),
+    )
+    .with_cluster(node.context.cluster.clone())
+    .with_identity(node.context.http_identity());
 
     TestClient::new(build_server(http_context))
 }
 
+/// Returns an auth token of the given key pair, issued for the HTTP API of the node.
+pub fn auth_token(node: &TestNode, key_pair: &KeyPair) -> AuthToken {
+    AuthToken::new(key_pair, &node.context.http_identity(), now())
+}
+
 pub(crate) struct RequestBuilder {
     builder: reqwest::RequestBuilder,
 }
@@ -97,7 +132,6 @@ impl RequestBuilder {
         }
     }
 
-    #[allow(dead_code)]
     pub(crate) fn body(mut self, body: impl Into<reqwest::Body>) -> Self {
         self.builder = self.builder.body(body);
         self
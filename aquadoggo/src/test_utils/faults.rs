@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Helpers to inject faults into a store or the replication codec of a node.
+use std::time::Duration;
+
+use crate::faults::{Fault, FaultInjector, FaultPoint};
+
+/// Fail the next time the given point is reached.
+pub fn fail_once(faults: &FaultInjector, point: FaultPoint) {
+    faults.inject(point, Fault::Error, Some(1));
+}
+
+/// Silently skip the next `times` operations at the given point, for example to simulate a
+/// missing skiplink with `FaultPoint::GetEntryAtSeqNum`.
+pub fn drop_next(faults: &FaultInjector, point: FaultPoint, times: usize) {
+    faults.inject(point, Fault::Drop, Some(times));
+}
+
+/// Delay every operation at the given point, for example to simulate lock contention.
+pub fn delay(faults: &FaultInjector, point: FaultPoint, duration: Duration) {
+    faults.inject(point, Fault::Delay(duration), None);
+}
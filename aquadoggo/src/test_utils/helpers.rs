@@ -3,7 +3,7 @@
 use p2panda_rs::hash::Hash;
 use p2panda_rs::identity::KeyPair;
 use p2panda_rs::operation::{
-    OperationValue, PinnedRelation, PinnedRelationList, Relation, RelationList,
+    EncodedOperation, OperationValue, PinnedRelation, PinnedRelationList, Relation, RelationList,
 };
 use p2panda_rs::schema::{Schema, SchemaId, SchemaName};
 use p2panda_rs::test_utils::constants;
@@ -114,6 +114,44 @@ pub fn random_schema_id_set() -> SchemaIdSet {
     SchemaIdSet::new(&[system_schema_id, schema_id_1, schema_id_2])
 }
 
+/// Generate the given number of random key pairs.
 pub fn generate_key_pairs(num: u64) -> Vec<KeyPair> {
     (0..num).map(|_| KeyPair::new()).collect()
 }
+
+/// Returns a key pair derived from a seed, the same seed always gives the same key pair.
+///
+/// Signatures are deterministic, entries of the same operations signed with such a key pair have
+/// the same hashes across test runs.
+pub fn key_pair_from_seed(seed: u64) -> KeyPair {
+    let mut private_key = [0; 32];
+    private_key[..8].copy_from_slice(&seed.to_be_bytes());
+    KeyPair::from_private_key_str(&hex::encode(private_key)).expect("Invalid private key")
+}
+
+/// Encodes the version of an operation with two bytes instead of one.
+///
+/// The result decodes to the same operation but is not canonical CBOR anymore.
+pub fn non_canonical_operation(encoded_operation: &EncodedOperation) -> EncodedOperation {
+    let mut bytes = encoded_operation.into_bytes();
+    assert_eq!(&bytes[..2], &[0x84, 0x01]);
+    bytes.splice(1..2, [0x18, 0x01]);
+    EncodedOperation::from_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::key_pair_from_seed;
+
+    #[test]
+    fn key_pairs_from_seed() {
+        assert_eq!(
+            key_pair_from_seed(1).public_key(),
+            key_pair_from_seed(1).public_key()
+        );
+        assert_ne!(
+            key_pair_from_seed(1).public_key(),
+            key_pair_from_seed(2).public_key()
+        );
+    }
+}
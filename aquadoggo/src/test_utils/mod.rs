@@ -1,19 +1,38 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+//! Helpers to write tests against a node with a real database.
+//!
+//! Other crates can use them in their integration tests by enabling the `test-utils` feature.
+//! Documents published with key pairs from [`key_pair_from_seed`] are the same in every test run.
+// Not all helpers are part of the public API, some are only used by the tests of this crate
+#![cfg_attr(not(test), allow(dead_code))]
+
+#[cfg(test)]
 mod client;
 mod config;
 mod db;
-pub mod helpers;
+#[cfg(all(test, feature = "fault-injection"))]
+mod faults;
+pub(crate) mod helpers;
 mod node;
 mod runner;
 
-pub use client::{http_test_client, TestClient};
-pub use config::TestConfiguration;
-pub use db::{initialize_db, initialize_sqlite_db};
-pub use helpers::{doggo_fields, doggo_schema, generate_key_pairs, schema_from_fields};
+#[cfg(test)]
+pub use client::{auth_token, http_test_client, TestClient};
+pub(crate) use config::TestConfiguration;
+pub(crate) use db::{initialize_db, initialize_sqlite_db};
+#[cfg(all(test, feature = "fault-injection"))]
+pub use faults::{delay, drop_next, fail_once};
+#[cfg(test)]
+pub use helpers::schema_from_fields;
+pub use helpers::{doggo_fields, doggo_schema, generate_key_pairs, key_pair_from_seed};
+#[cfg(test)]
+pub use node::{
+    add_blob, add_schema_and_documents, assert_query, delete_document, populate_store,
+    populate_store_config, update_blob,
+};
 pub use node::{
-    add_blob, add_document, add_schema, add_schema_and_documents, assert_query, delete_document,
-    populate_and_materialize, populate_store, populate_store_config, update_blob, update_document,
-    PopulateStoreConfig, TestNode,
+    add_document, add_schema, populate_and_materialize, update_document, PopulateStoreConfig,
+    TestNode,
 };
 pub use runner::{test_runner, test_runner_with_manager, TestNodeManager};
@@ -24,8 +24,10 @@ use crate::materializer::tasks::{dependency_task, reduce_task, schema_task};
 use crate::materializer::TaskInput;
 use crate::test_utils::{doggo_fields, doggo_schema};
 
-/// Test node which contains a context with an [`SqlStore`].
+/// Test node which contains a context with an `SqlStore`.
+#[derive(Debug)]
 pub struct TestNode {
+    /// Context of the node with its store, configuration and schema provider.
     pub context: Context<SqlStore>,
 }
 
@@ -1,6 +1,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures::Future;
 use p2panda_rs::identity::KeyPair;
@@ -48,27 +49,43 @@ where
 }
 
 /// A manager which can create many databases and retain a handle on their connection pools.
-#[derive(Default)]
+#[derive(Debug, Default)]
 pub struct TestNodeManager {
     pools: Arc<Mutex<Vec<Pool>>>,
 }
 
 impl TestNodeManager {
+    /// Returns a manager without any databases.
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Create a node with its own in-memory SQLite database and default configuration.
     pub async fn create(&self) -> TestNode {
         self.create_with_config(Configuration::default()).await
     }
 
+    /// Create a node with its own in-memory SQLite database and the given configuration.
     pub async fn create_with_config(&self, config: Configuration) -> TestNode {
         let (_config, pool) = initialize_sqlite_db().await;
 
         // Initialise test store using pool.
-        let store = SqlStore::new(pool.clone());
-
-        let schema_provider = SchemaProvider::new(vec![], config.allow_schema_ids.clone());
+        let store = match config.graphql_snapshot_ttl {
+            0 => SqlStore::new(pool.clone()),
+            ttl => SqlStore::new(pool.clone()).with_snapshots(Duration::from_secs(ttl)),
+        };
+        let store = store.with_view_eviction(config.max_document_views.keys().cloned().collect());
+
+        let schema_provider = SchemaProvider::new(vec![], config.allow_schema_ids.clone())
+            .with_schema_pins(
+                config.allow_schema_pins.clone(),
+                config.schema_version_policy,
+            )
+            .with_field_constraints(config.field_constraints.clone())
+            .with_decimal_fields(config.decimal_fields.clone())
+            .with_datetime_fields(config.datetime_fields.clone())
+            .with_canonical_encoding(config.require_canonical_encoding)
+            .with_schema_settings(config.schema_settings.clone());
 
         // Construct the actual test node
         let test_node = TestNode {
@@ -175,6 +175,38 @@ async fn e2e() {
     aquadoggo.shutdown().await;
 }
 
+#[tokio::test]
+async fn ephemeral_nodes() {
+    // Ephemeral nodes keep all data in memory and listen on random ports, this allows running
+    // many of them next to each other, for example in integration tests of applications.
+    let config_1 = Configuration::ephemeral();
+    let config_2 = Configuration::ephemeral();
+    assert_ne!(config_1.http_port, config_2.http_port);
+    assert_ne!(config_1.database_url, config_2.database_url);
+
+    let node_1 = Node::start(KeyPair::new(), config_1.clone()).await;
+    let node_2 = Node::start(KeyPair::new(), config_2.clone()).await;
+
+    let client = Client::new();
+    for config in [&config_1, &config_2] {
+        let response = client
+            .post(format!("http://127.0.0.1:{}/graphql", config.http_port))
+            .json(&json!({
+                "query": "{ __typename }",
+            }))
+            .send()
+            .await
+            .expect("Send query to node")
+            .json::<GqlResponse>()
+            .await
+            .expect("Parse GraphQL response");
+        assert!(response.is_ok());
+    }
+
+    node_1.shutdown().await;
+    node_2.shutdown().await;
+}
+
 /// Publish an entry and its operation to a node.
 async fn publish(client: &Client, key_pair: &KeyPair, operation: &Operation) -> DocumentViewId {
     // Publishing operations.
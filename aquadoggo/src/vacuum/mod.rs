@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Consistency sweep removing or re-linking data which is not connected to any known document.
+//!
+//! Entries and operations are inserted one after another, an ingest failing half-way can leave an
+//! operation without its entry or an entry without its operation behind. Such data is invisible
+//! to the materializer and can not be replicated correctly.
+mod service;
+mod sweep;
+
+pub use service::vacuum_service;
+pub use sweep::{vacuum, VacuumReport};
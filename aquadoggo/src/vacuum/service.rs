@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::Result;
+use log::warn;
+use tokio::task;
+use tokio::time::interval;
+
+use crate::bus::{ServiceMessage, ServiceSender};
+use crate::context::Context;
+use crate::manager::{ServiceReadySender, Shutdown};
+use crate::vacuum::vacuum;
+
+/// The vacuum service periodically removes or re-links entries and operations which belong to no
+/// known document.
+pub async fn vacuum_service(
+    context: Context,
+    shutdown: Shutdown,
+    tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()> {
+    // The service is only started when an interval is configured
+    let vacuum_interval = Duration::from_secs(
+        context
+            .config
+            .vacuum_interval
+            .expect("Vacuum interval is configured")
+            .max(1),
+    );
+
+    let handle = task::spawn(async move {
+        let mut interval = interval(vacuum_interval);
+
+        loop {
+            interval.tick().await;
+
+            match vacuum(&context.store, &context.schema_provider).await {
+                Ok(report) => {
+                    // Inform materializer about restored operations
+                    for operation_id in report.relinked_operations {
+                        if tx.send(ServiceMessage::NewOperation(operation_id)).is_err() {
+                            // Silently fail here as we don't mind if there are no subscribers
+                        }
+                    }
+                }
+                Err(err) => warn!("Failed vacuuming store: {err}"),
+            }
+        }
+    });
+
+    if tx_ready.send(()).is_err() {
+        warn!("No subscriber informed about vacuum service being ready");
+    };
+
+    tokio::select! {
+        _ = handle => (),
+        _ = shutdown => (),
+    }
+
+    Ok(())
+}
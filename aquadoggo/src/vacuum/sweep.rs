@@ -0,0 +1,289 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::Result;
+use log::{debug, info};
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::entry::traits::{AsEncodedEntry, AsEntry};
+use p2panda_rs::entry::LogId;
+use p2panda_rs::hash::Hash;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::operation::decode::decode_operation;
+use p2panda_rs::operation::traits::{AsOperation, Schematic};
+use p2panda_rs::operation::validate::validate_operation;
+use p2panda_rs::operation::OperationId;
+use p2panda_rs::storage_provider::traits::{LogStore, OperationStore};
+
+use crate::db::types::StorageEntry;
+use crate::db::SqlStore;
+use crate::replication::now;
+use crate::schema::SchemaProvider;
+
+/// Duration in seconds for which newly inserted entries and operations are left alone.
+///
+/// Entries, operations and logs are not inserted in one transaction during ingest. Rows which
+/// look orphaned might still belong to an ingest in progress, touching them would let it fail.
+const GRACE_PERIOD: u64 = 60 * 10;
+
+/// Report of what the consistency sweep cleaned up.
+#[derive(Debug, Default)]
+pub struct VacuumReport {
+    /// Operations which were removed as the entry signing them is missing.
+    pub removed_operations: Vec<OperationId>,
+
+    /// Entries which were removed as their operation is missing and can not be restored.
+    pub removed_entries: Vec<Hash>,
+
+    /// Operations which were restored from the payload of their entry.
+    pub relinked_operations: Vec<OperationId>,
+
+    /// Logs which were registered again as their entries and operations are present.
+    pub relinked_logs: Vec<(PublicKey, LogId)>,
+
+    /// Entries without operation which were kept as their schema is not known to the node.
+    pub skipped_entries: Vec<Hash>,
+}
+
+impl VacuumReport {
+    /// Returns true if nothing was cleaned up.
+    pub fn is_empty(&self) -> bool {
+        self.removed_operations.is_empty()
+            && self.removed_entries.is_empty()
+            && self.relinked_operations.is_empty()
+            && self.relinked_logs.is_empty()
+    }
+}
+
+/// Outcome of restoring the operation of an entry.
+enum Relink {
+    Restored(OperationId),
+    UnknownSchema,
+    Invalid,
+}
+
+/// Identify entries and operations which belong to no known document and remove or re-link them.
+///
+/// Operations without their entry are removed as they can not be verified or replicated anymore.
+/// Entries without their operation are re-linked by restoring the operation from their payload
+/// and removed if that is not possible. Logs of restored entries and operations get registered
+/// again when they went missing.
+///
+/// Only entries and operations which were inserted before the grace period are considered.
+///
+/// Restored operations still need to be materialized, callers should inform the materializer
+/// about the returned `relinked_operations`.
+pub async fn vacuum(store: &SqlStore, schema_provider: &SchemaProvider) -> Result<VacuumReport> {
+    let mut report = VacuumReport::default();
+    let inserted_before = now().saturating_sub(GRACE_PERIOD);
+
+    for operation_id in store.get_operations_without_entry(inserted_before).await? {
+        debug!("Remove operation {} without entry", operation_id);
+        store.remove_operation(&operation_id).await?;
+        report.removed_operations.push(operation_id);
+    }
+
+    for (public_key, log_id, schema_id, document_id) in
+        store.get_unregistered_logs(inserted_before).await?
+    {
+        debug!("Register log {} of {} again", log_id.as_u64(), public_key);
+        store
+            .insert_log(&log_id, &public_key, &schema_id, &document_id)
+            .await?;
+        report.relinked_logs.push((public_key, log_id));
+    }
+
+    for entry in store.get_entries_without_operation(inserted_before).await? {
+        match relink_operation(store, schema_provider, &entry).await? {
+            Relink::Restored(operation_id) => {
+                debug!("Restored operation {} from its entry", operation_id);
+                report.relinked_operations.push(operation_id);
+            }
+            Relink::UnknownSchema => report.skipped_entries.push(entry.hash()),
+            Relink::Invalid => {
+                debug!("Remove entry {} without operation", entry.hash());
+                store.remove_entry(&entry.hash()).await?;
+                report.removed_entries.push(entry.hash());
+            }
+        }
+    }
+
+    if !report.is_empty() {
+        info!(
+            "Vacuumed store: removed {} operations and {} entries, re-linked {} operations and {} \
+            logs",
+            report.removed_operations.len(),
+            report.removed_entries.len(),
+            report.relinked_operations.len(),
+            report.relinked_logs.len()
+        );
+    }
+
+    Ok(report)
+}
+
+/// Restore the operation of an entry from its payload.
+async fn relink_operation(
+    store: &SqlStore,
+    schema_provider: &SchemaProvider,
+    entry: &StorageEntry,
+) -> Result<Relink> {
+    let plain_operation = match entry.payload().map(decode_operation) {
+        Some(Ok(plain_operation)) => plain_operation,
+        _ => return Ok(Relink::Invalid),
+    };
+
+    let schema = match schema_provider.get(plain_operation.schema_id()).await {
+        Some(schema) => schema,
+        None => return Ok(Relink::UnknownSchema),
+    };
+
+    let operation = match validate_operation(&plain_operation, &schema) {
+        Ok(operation) => operation,
+        Err(_) => return Ok(Relink::Invalid),
+    };
+
+    let operation_id: OperationId = entry.hash().into();
+
+    // CREATE operations start a new document, all others continue the document of their log
+    let document_id = if operation.is_create() {
+        DocumentId::new(&operation_id)
+    } else {
+        match store
+            .get_document_id_by_log(entry.public_key(), entry.log_id())
+            .await?
+        {
+            Some(document_id) => document_id,
+            None => return Ok(Relink::Invalid),
+        }
+    };
+
+    store
+        .insert_log(
+            entry.log_id(),
+            entry.public_key(),
+            schema.id(),
+            &document_id,
+        )
+        .await?;
+    store
+        .insert_operation(&operation_id, entry.public_key(), &operation, &document_id)
+        .await?;
+
+    Ok(Relink::Restored(operation_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::DocumentId;
+    use p2panda_rs::entry::traits::AsEntry;
+    use p2panda_rs::identity::KeyPair;
+    use p2panda_rs::operation::{OperationBuilder, OperationId};
+    use p2panda_rs::schema::FieldType;
+    use p2panda_rs::storage_provider::traits::{EntryStore, OperationStore};
+    use p2panda_rs::test_utils::fixtures::{key_pair, random_document_id, random_operation_id};
+    use rstest::rstest;
+    use sqlx::query;
+
+    use crate::test_utils::{add_document, add_schema, test_runner, TestNode};
+
+    use super::vacuum;
+
+    #[rstest]
+    fn removes_and_relinks_orphans(key_pair: KeyPair) {
+        test_runner(|mut node: TestNode| async move {
+            let schema = add_schema(
+                &mut node,
+                "venue",
+                vec![("name", FieldType::String)],
+                &key_pair,
+            )
+            .await;
+            let view_id = add_document(
+                &mut node,
+                schema.id(),
+                vec![("name", "Panda Cafe".into())],
+                &key_pair,
+            )
+            .await;
+            let operation_id: OperationId = view_id.to_string().parse().unwrap();
+            let document_id: DocumentId = view_id.to_string().parse().unwrap();
+            let store = &node.context.store;
+
+            // Nothing to clean up in a consistent store
+            let report = vacuum(store, &node.context.schema_provider).await.unwrap();
+            assert!(report.is_empty());
+
+            // Operation of an ingest which failed before the entry got inserted
+            let orphaned_operation_id = random_operation_id();
+            let operation = OperationBuilder::new(schema.id())
+                .fields(&[("name", "Lost Cafe".into())])
+                .build()
+                .unwrap();
+            store
+                .insert_operation(
+                    &orphaned_operation_id,
+                    &key_pair.public_key(),
+                    &operation,
+                    &random_document_id(),
+                )
+                .await
+                .unwrap();
+
+            // Entry and log which lost their operation and registration
+            let entry = store
+                .get_entry(&operation_id.as_hash().to_owned())
+                .await
+                .unwrap()
+                .unwrap();
+            store.remove_operation(&operation_id).await.unwrap();
+            query("DELETE FROM logs WHERE public_key = $1 AND log_id = $2")
+                .bind(entry.public_key().to_string())
+                .bind(entry.log_id().as_u64().to_string())
+                .execute(&store.pool)
+                .await
+                .unwrap();
+
+            // Recently inserted rows might belong to an ingest in progress and are left alone
+            let report = vacuum(store, &node.context.schema_provider).await.unwrap();
+            assert!(report.is_empty());
+
+            for table in ["entries", "operations_v1"] {
+                query(&format!("UPDATE {table} SET inserted_at = 0"))
+                    .execute(&store.pool)
+                    .await
+                    .unwrap();
+            }
+
+            let report = vacuum(store, &node.context.schema_provider).await.unwrap();
+            assert_eq!(
+                report.removed_operations,
+                vec![orphaned_operation_id.clone()]
+            );
+            assert_eq!(report.relinked_operations, vec![operation_id.clone()]);
+            assert!(report.removed_entries.is_empty());
+
+            assert!(store
+                .get_operation(&orphaned_operation_id)
+                .await
+                .unwrap()
+                .is_none());
+            assert_eq!(
+                store
+                    .get_document_id_by_operation_id(&operation_id)
+                    .await
+                    .unwrap(),
+                Some(document_id.clone())
+            );
+            assert_eq!(
+                store
+                    .get_document_id_by_log(entry.public_key(), entry.log_id())
+                    .await
+                    .unwrap(),
+                Some(document_id)
+            );
+
+            // Store is consistent again
+            let report = vacuum(store, &node.context.schema_provider).await.unwrap();
+            assert!(report.is_empty());
+        })
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Notifications about created, updated and deleted documents sent to external services.
+//!
+//! Instead of polling the GraphQL API, external services can register HTTP endpoints for
+//! documents of a schema. Every time the materializer stores a new latest view of such a document
+//! the endpoint receives a JSON payload about the change via a POST request.
+mod service;
+mod webhook;
+
+pub use service::webhook_service;
+pub use webhook::{Webhook, WebhookError};
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use hyper::header::CONTENT_TYPE;
+use hyper::{Body, Client, Method, Request};
+use log::{debug, warn};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::task;
+
+use crate::bus::ServiceSender;
+use crate::context::Context;
+use crate::manager::{ServiceReadySender, Shutdown};
+use crate::replication::now;
+use crate::webhooks::webhook::payload;
+use crate::webhooks::Webhook;
+
+/// Maximum number of notifications waiting to be delivered to one endpoint, further notifications
+/// are dropped.
+const QUEUE_CAPACITY: usize = 1024;
+
+/// Number of attempts to deliver a notification before it is dropped.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled with every further attempt.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Header containing the signature of the payload when the webhook has a secret.
+const SIGNATURE_HEADER: &str = "X-Aquadoggo-Signature";
+
+/// Send a notification to the endpoint of the webhook.
+pub async fn deliver(webhook: &Webhook, payload: &str) -> Result<()> {
+    let mut builder = Request::builder()
+        .method(Method::POST)
+        .uri(&webhook.url)
+        .header(CONTENT_TYPE, "application/json");
+
+    if let Some(signature) = webhook.signature(payload) {
+        builder = builder.header(SIGNATURE_HEADER, signature);
+    }
+
+    let request = builder.body(Body::from(payload.to_owned()))?;
+
+    let response = Client::new().request(request).await?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Endpoint responded with status {}",
+            response.status()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Deliver notifications to the endpoint of the webhook one after another, retrying failed
+/// deliveries a few times.
+async fn deliver_queue(webhook: Webhook, mut rx: mpsc::Receiver<String>) {
+    while let Some(payload) = rx.recv().await {
+        let mut delay = RETRY_DELAY;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            match deliver(&webhook, &payload).await {
+                Ok(()) => {
+                    debug!("Delivered webhook notification to {}", webhook.url);
+                    break;
+                }
+                Err(err) if attempt < MAX_ATTEMPTS => {
+                    debug!(
+                        "Failed delivering webhook notification to {}, retry in {}s: {}",
+                        webhook.url,
+                        delay.as_secs(),
+                        err
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(err) => {
+                    warn!(
+                        "Dropped webhook notification for {} after {} attempts: {}",
+                        webhook.url, MAX_ATTEMPTS, err
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The webhook service notifies configured HTTP endpoints whenever the materializer created,
+/// updated or deleted a document of their schema.
+pub async fn webhook_service(
+    context: Context,
+    shutdown: Shutdown,
+    _tx: ServiceSender,
+    tx_ready: ServiceReadySender,
+) -> Result<()> {
+    if context.config.webhooks.is_empty() {
+        return Err(anyhow!("No webhooks configured"));
+    }
+
+    let public_key = context.key_pair.public_key();
+
+    // Every endpoint has its own queue, this keeps notifications in order and slow endpoints from
+    // delaying others
+    let queues: Vec<(Webhook, mpsc::Sender<String>)> = context
+        .config
+        .webhooks
+        .iter()
+        .map(|webhook| {
+            let (queue_tx, queue_rx) = mpsc::channel(QUEUE_CAPACITY);
+            task::spawn(deliver_queue(webhook.clone(), queue_rx));
+            (webhook.clone(), queue_tx)
+        })
+        .collect();
+
+    let mut rx = context.document_events.on_document_updated();
+
+    let handle = task::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    for (webhook, queue) in &queues {
+                        if !webhook.matches(&event) {
+                            continue;
+                        }
+
+                        if queue.try_send(payload(&event, &public_key, now())).is_err() {
+                            warn!(
+                                "Dropped webhook notification for {}, too many pending deliveries",
+                                webhook.url
+                            );
+                        }
+                    }
+                }
+                Err(RecvError::Lagged(count)) => {
+                    warn!("Missed {} document changes for webhooks", count);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+
+    if tx_ready.send(()).is_err() {
+        warn!("No subscriber informed about webhook service being ready");
+    };
+
+    tokio::select! {
+        _ = handle => (),
+        _ = shutdown => (),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::extract::State;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::Router;
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::fixtures::schema_id;
+    use rstest::rstest;
+    use tokio::sync::mpsc;
+
+    use crate::webhooks::Webhook;
+
+    use super::{deliver, SIGNATURE_HEADER};
+
+    #[rstest]
+    #[tokio::test]
+    async fn delivers_signed_payloads(schema_id: SchemaId) {
+        let (tx, mut rx) = mpsc::channel::<(Option<String>, String)>(1);
+
+        let router = Router::new().route(
+            "/hooks",
+            post(
+                |State(tx): State<mpsc::Sender<(Option<String>, String)>>,
+                 headers: HeaderMap,
+                 body: String| async move {
+                    let signature = headers
+                        .get(SIGNATURE_HEADER)
+                        .map(|value| value.to_str().unwrap().to_string());
+                    tx.send((signature, body)).await.unwrap();
+                },
+            ),
+        );
+        let server = axum::Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+            .serve(router.with_state(tx).into_make_service());
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        let webhook = Webhook::new(&format!("http://{}/hooks", address), &schema_id)
+            .unwrap()
+            .with_secret("panda");
+        deliver(&webhook, "{\"change\":\"create\"}").await.unwrap();
+
+        let (signature, body) = rx.recv().await.unwrap();
+        assert_eq!(body, "{\"change\":\"create\"}");
+        assert_eq!(signature, webhook.signature(&body));
+
+        // Endpoints responding with an error fail the delivery
+        let webhook = Webhook::new(&format!("http://{}/unknown", address), &schema_id).unwrap();
+        assert!(deliver(&webhook, "{}").await.is_err());
+    }
+}
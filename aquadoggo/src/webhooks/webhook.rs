@@ -0,0 +1,164 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::str::FromStr;
+
+use http::Uri;
+use p2panda_rs::identity::PublicKey;
+use p2panda_rs::schema::SchemaId;
+use thiserror::Error;
+
+use crate::materializer::{DocumentChange, DocumentUpdated};
+
+/// Context used to derive the key signing payloads from the secret of a webhook.
+const SIGNATURE_KEY_CONTEXT: &str = "aquadoggo 2024-01-01 webhook signature";
+
+/// HTTP endpoint notified about changes of documents of a schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    /// Endpoint receiving the notifications via POST requests, for example
+    /// `http://localhost:8080/hooks`.
+    pub url: Uri,
+
+    /// Schema of the documents the endpoint gets notified about.
+    pub schema_id: SchemaId,
+
+    /// Changes the endpoint gets notified about.
+    pub changes: Vec<DocumentChange>,
+
+    /// Secret to sign payloads with, the signature is sent in the `X-Aquadoggo-Signature` header.
+    pub secret: Option<String>,
+}
+
+impl Webhook {
+    /// Returns a webhook notifying the endpoint about all changes of documents of this schema.
+    ///
+    /// Only plain `http://` endpoints are supported.
+    pub fn new(url: &str, schema_id: &SchemaId) -> Result<Self, WebhookError> {
+        let uri = Uri::from_str(url).map_err(|_| WebhookError::InvalidUrl(url.to_string()))?;
+
+        if uri.scheme_str() != Some("http") {
+            return Err(WebhookError::UnsupportedScheme(url.to_string()));
+        }
+
+        Ok(Self {
+            url: uri,
+            schema_id: schema_id.to_owned(),
+            changes: vec![
+                DocumentChange::Create,
+                DocumentChange::Update,
+                DocumentChange::Delete,
+            ],
+            secret: None,
+        })
+    }
+
+    /// Only notify the endpoint about the given changes.
+    pub fn with_changes(mut self, changes: &[DocumentChange]) -> Self {
+        self.changes = changes.to_vec();
+        self
+    }
+
+    /// Sign payloads with the given secret.
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    /// Returns true if the endpoint wants to be notified about this document change.
+    pub(crate) fn matches(&self, event: &DocumentUpdated) -> bool {
+        self.schema_id == event.schema_id && self.changes.contains(&event.change)
+    }
+
+    /// Returns the signature of the payload as a hex-encoded keyed BLAKE3 hash, if a secret is
+    /// set.
+    ///
+    /// Endpoints can verify payloads by deriving the key from the secret with the context
+    /// "aquadoggo 2024-01-01 webhook signature" and hashing the payload with it.
+    pub(crate) fn signature(&self, payload: &str) -> Option<String> {
+        self.secret.as_ref().map(|secret| {
+            let key = blake3::derive_key(SIGNATURE_KEY_CONTEXT, secret.as_bytes());
+            blake3::keyed_hash(&key, payload.as_bytes())
+                .to_hex()
+                .to_string()
+        })
+    }
+}
+
+/// Encodes the notification about a document change as JSON.
+pub(crate) fn payload(event: &DocumentUpdated, public_key: &PublicKey, timestamp: u64) -> String {
+    format!(
+        "{{\"change\":\"{}\",\"schema_id\":\"{}\",\"document_id\":\"{}\",\"view_id\":\"{}\",\"public_key\":\"{}\",\"timestamp\":{}}}",
+        event.change, event.schema_id, event.document_id, event.view_id, public_key, timestamp
+    )
+}
+
+/// Errors returned when creating webhooks.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum WebhookError {
+    /// Endpoint is not a valid URL.
+    #[error("Invalid webhook URL '{0}'")]
+    InvalidUrl(String),
+
+    /// Endpoint uses a protocol we can't send notifications to.
+    #[error("Unsupported webhook URL '{0}', expected http:// URL")]
+    UnsupportedScheme(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use p2panda_rs::document::{DocumentId, DocumentViewId};
+    use p2panda_rs::schema::SchemaId;
+    use p2panda_rs::test_utils::fixtures::{
+        random_document_id, random_document_view_id, schema_id,
+    };
+    use rstest::rstest;
+
+    use crate::materializer::{DocumentChange, DocumentUpdated};
+
+    use super::{Webhook, WebhookError};
+
+    #[rstest]
+    fn matches_changes(
+        schema_id: SchemaId,
+        #[from(random_document_id)] document_id: DocumentId,
+        #[from(random_document_view_id)] view_id: DocumentViewId,
+    ) {
+        let webhook = Webhook::new("http://localhost:8080/hooks", &schema_id)
+            .unwrap()
+            .with_changes(&[DocumentChange::Delete]);
+
+        let mut event = DocumentUpdated {
+            schema_id,
+            document_id,
+            view_id,
+            change: DocumentChange::Update,
+        };
+        assert!(!webhook.matches(&event));
+
+        event.change = DocumentChange::Delete;
+        assert!(webhook.matches(&event));
+
+        event.schema_id = SchemaId::SchemaDefinition(1);
+        assert!(!webhook.matches(&event));
+    }
+
+    #[rstest]
+    fn invalid_urls(schema_id: SchemaId) {
+        assert!(matches!(
+            Webhook::new("https://localhost/hooks", &schema_id),
+            Err(WebhookError::UnsupportedScheme(_))
+        ));
+        assert!(Webhook::new("not a url", &schema_id).is_err());
+    }
+
+    #[rstest]
+    fn signs_payloads(schema_id: SchemaId) {
+        let webhook = Webhook::new("http://localhost:8080/hooks", &schema_id).unwrap();
+        assert_eq!(webhook.signature("{}"), None);
+
+        let webhook = webhook.with_secret("panda");
+        let signature = webhook.signature("{}").unwrap();
+        assert_eq!(signature.len(), 64);
+        assert_ne!(webhook.signature("{ }").unwrap(), signature);
+    }
+}
@@ -2,14 +2,17 @@
 
 use std::path::PathBuf;
 
-use anyhow::{bail, Result};
-use aquadoggo::{AllowList, ConfigFile, Configuration};
-use clap::{crate_version, Parser};
+use anyhow::{bail, Context, Result};
+use aquadoggo::{AllowList, ConfigFile, Configuration, Language};
+use clap::{crate_version, Parser, Subcommand};
 use colored::Colorize;
 use directories::ProjectDirs;
 use figment::providers::{Env, Format, Serialized, Toml};
 use figment::Figment;
+use keyring::Entry;
 use libp2p::PeerId;
+use p2panda_rs::document::DocumentId;
+use p2panda_rs::schema::SchemaId;
 use serde::{Serialize, Serializer};
 
 use crate::utils::absolute_path;
@@ -18,6 +21,10 @@ const WILDCARD: &str = "*";
 
 const CONFIG_FILE_NAME: &str = "config.toml";
 
+const KEYRING_SERVICE: &str = "aquadoggo";
+
+const KEYRING_DATABASE_KEY: &str = "database_key";
+
 type ConfigFilePath = Option<PathBuf>;
 
 /// Get configuration from 1. .toml file, 2. environment variables and 3. command line arguments
@@ -25,11 +32,13 @@ type ConfigFilePath = Option<PathBuf>;
 /// ones).
 ///
 /// Returns a partly unchecked configuration object which results from all of these sources. It
-/// still needs to be converted for aquadoggo as it might still contain invalid values.
-pub fn load_config() -> Result<(ConfigFilePath, ConfigFile)> {
+/// still needs to be converted for aquadoggo as it might still contain invalid values. Next to it
+/// the optional subcommand is returned when one was given.
+pub fn load_config() -> Result<(ConfigFilePath, ConfigFile, Option<Command>)> {
     // Parse command line arguments and CONFIG environment variable first to get optional config
     // file path
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    let command = cli.command.take();
 
     // Determine if a config file path was provided or if we should look for it in common locations
     let config_file_path: ConfigFilePath = match &cli.config {
@@ -48,12 +57,20 @@ pub fn load_config() -> Result<(ConfigFilePath, ConfigFile)> {
         figment = figment.merge(Toml::file(path));
     }
 
-    let config = figment
+    let mut config: ConfigFile = figment
         .merge(Env::raw())
         .merge(Serialized::defaults(cli))
         .extract()?;
 
-    Ok((config_file_path, config))
+    // Look up database key in the keyring of the operating system when requested
+    if config.database_key_keyring && config.database_key.is_none() {
+        let key = Entry::new(KEYRING_SERVICE, KEYRING_DATABASE_KEY)
+            .and_then(|entry| entry.get_password())
+            .context("Could not read database key from keyring")?;
+        config.database_key = Some(key);
+    }
+
+    Ok((config_file_path, config, command))
 }
 
 /// Configuration derived from command line arguments.
@@ -212,6 +229,14 @@ struct Cli {
     #[serde(skip_serializing_if = "Option::is_none")]
     block_peer_ids: Option<Vec<PeerId>>,
 
+    /// List of connection tickets of nodes we want to connect to.
+    ///
+    /// Nodes show a ticket when they start. It contains their peer id, addresses, relays and
+    /// network name, which get added to the direct node and relay addresses of this node.
+    #[arg(short = 'j', long, value_name = "TICKET", num_args = 0..)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    join_tickets: Option<Vec<String>>,
+
     /// List of relay addresses.
     ///
     /// A relay helps discover other nodes on the internet (also known as "rendesvouz" or
@@ -251,6 +276,116 @@ struct Cli {
     #[arg(short = 'l', long, value_name = "LEVEL")]
     #[serde(skip_serializing_if = "Option::is_none")]
     log_level: Option<String>,
+
+    #[command(subcommand)]
+    #[serde(skip_serializing)]
+    command: Option<Command>,
+}
+
+/// Commands which can be run instead of starting the node.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Replay the materialization of a document for debugging.
+    ///
+    /// Reduce and dependency tasks are re-run for the document against a copy of the database,
+    /// logging every applied operation and inserted document view. The database itself is not
+    /// changed. Only SQLite databases are supported.
+    Replay {
+        /// Id of the document to replay.
+        #[arg(long, value_name = "DOCUMENT_ID")]
+        document: DocumentId,
+
+        /// Maximum number of tasks to process before stopping the replay.
+        #[arg(long, value_name = "NUM", default_value_t = 1000)]
+        max_steps: usize,
+    },
+
+    /// Generate typed client code for the documents of a schema published to the node.
+    ///
+    /// For Rust this is a module with a struct holding the document fields, GraphQL queries to
+    /// fetch documents and builders for CREATE, UPDATE and DELETE operations based on `p2panda-rs`.
+    Codegen {
+        /// Id of the schema to generate code for.
+        #[arg(long, value_name = "SCHEMA_ID")]
+        schema: SchemaId,
+
+        /// Language of the generated code, currently only "rust" is supported.
+        #[arg(long, value_name = "LANG", default_value = "rust")]
+        lang: Language,
+
+        /// Path of the file the code is written to, printed when not given.
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// Import raw encoded entries and operations, for example exported from another node.
+    ///
+    /// The path points either at a file holding a sequence of CBOR arrays with the bytes of an
+    /// entry and its operation or a signed document bundle, or at a directory with pairs of files holding the raw bytes, for
+    /// example "0001.entry" and "0001.operation". All data is validated and materialized, the node
+    /// keeps running afterwards.
+    Import {
+        /// Path to the file or directory to import.
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Export a document with all its entries and operations as a signed bundle.
+    ///
+    /// The bundle is signed with the node's private key, third parties can verify it offline and
+    /// import it into their own node with the "import" command.
+    Export {
+        /// Id of the document to export.
+        #[arg(long, value_name = "DOCUMENT_ID")]
+        document: DocumentId,
+
+        /// Path of the file the bundle is written to.
+        #[arg(long, value_name = "PATH")]
+        output: PathBuf,
+    },
+
+    /// Apply pending database migrations or revert the most recently applied ones.
+    ///
+    /// Use this to roll back an upgrade: revert the migrations with the new version of aquadoggo,
+    /// then start the previous version. Only the most recent migrations can be reverted and their
+    /// data gets lost. With "--dry-run" the migrations are only listed together with the number
+    /// of rows in the tables they touch, the database is not changed.
+    Migrate {
+        /// Only list the migrations which would be applied or reverted.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Revert this number of most recently applied migrations instead of applying pending ones.
+        #[arg(long, value_name = "NUM")]
+        revert: Option<usize>,
+    },
+
+    /// Measure publish, replication ingest and reduce throughput with synthetic data.
+    ///
+    /// Creates a synthetic schema and documents authored by temporary key pairs. All data is kept
+    /// in the database, only run this against a dedicated one! Uses a temporary SQLite database
+    /// when no database is given.
+    #[command(hide = true)]
+    Bench {
+        /// URL / connection string to the database to benchmark against, for example PostgreSQL.
+        #[arg(long, value_name = "CONNECTION_STRING")]
+        database: Option<String>,
+
+        /// Number of documents to create for every benchmark.
+        #[arg(long, value_name = "NUM", default_value_t = 1000)]
+        documents: usize,
+
+        /// Number of updates to publish for every document.
+        #[arg(long, value_name = "NUM", default_value_t = 10)]
+        updates: usize,
+    },
+
+    /// Run the node as a Windows service, reporting to the service control manager.
+    ///
+    /// The service needs to be registered with the name "aquadoggo" and should point at a config
+    /// file, for example with `sc.exe create aquadoggo binPath= "<PATH>\aquadoggo.exe service -c
+    /// <PATH>\config.toml"`. Only supported on Windows.
+    Service,
 }
 
 /// Clap converts wildcard symbols from command line arguments (for example --supported-schema-ids
@@ -348,13 +483,16 @@ pub fn print_config(
 
     let allow_schema_ids: String = match &config.allow_schema_ids {
         AllowList::Set(schema_ids) => {
-            if schema_ids.is_empty() {
+            if schema_ids.is_empty() && config.allow_schema_pins.is_empty() {
                 "none (disable replication)".into()
             } else {
                 String::from("\n")
                     + &schema_ids
                         .iter()
                         .map(|id| format!("• {id}"))
+                        .chain(config.allow_schema_pins.iter().map(|pin| {
+                            format!("• {pin} ({} versions)", config.schema_version_policy)
+                        }))
                         .collect::<Vec<String>>()
                         .join("\n")
             }
@@ -366,6 +504,8 @@ pub fn print_config(
         || config.database_url.contains("mode=memory")
     {
         "memory (data is not persisted)".into()
+    } else if config.database_url.contains("sqlite:") && config.database_key.is_some() {
+        format!("SQLite (encrypted): {}", config.database_url)
     } else if config.database_url.contains("sqlite:") {
         format!("SQLite: {}", config.database_url)
     } else {
@@ -2,23 +2,29 @@
 
 mod config;
 mod key_pair;
+mod supervisor;
 mod utils;
 
 use std::convert::TryInto;
 use std::str::FromStr;
 
 use anyhow::Context;
-use aquadoggo::{AllowList, Configuration, Node, Transport};
+use aquadoggo::{
+    export_document_bundle, generate_schema_code, migrate_database, read_commits, replay_document,
+    run_benchmarks, AllowList, BenchOptions, Configuration, MigrateAction, Node, Transport,
+};
 use env_logger::WriteStyle;
 use log::{warn, LevelFilter};
 
-use crate::config::{load_config, print_config};
+use crate::config::{load_config, print_config, Command};
 use crate::key_pair::{generate_ephemeral_key_pair, generate_or_load_key_pair};
+use crate::supervisor::Supervisor;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Load configuration from command line arguments, environment variables and .toml file
-    let (config_file_path, config) = load_config().context("Could not load configuration")?;
+    let (config_file_path, config, command) =
+        load_config().context("Could not load configuration")?;
 
     // Remember if user did not set a blobs directory path, which means that it will default to a
     // temporary one
@@ -30,6 +36,10 @@ async fn main() -> anyhow::Result<()> {
         Ok(log_level) => builder.filter(Some("aquadoggo"), log_level),
         Err(_) => builder.parse_filters(&config.log_level),
     };
+    // Always show the step-by-step tracing when replaying a document
+    if let Some(Command::Replay { .. }) = command {
+        builder.filter(Some("aquadoggo::replay"), LevelFilter::Trace);
+    }
     builder.write_style(WriteStyle::Always).init();
 
     // Convert to `aquadoggo` configuration format and check for invalid inputs
@@ -38,6 +48,96 @@ async fn main() -> anyhow::Result<()> {
         .try_into()
         .context("Could not load configuration")?;
 
+    if let Some(Command::Replay {
+        document,
+        max_steps,
+    }) = command
+    {
+        let steps = replay_document(&node_config, &document, max_steps)
+            .await
+            .context("Could not replay document")?;
+
+        for (index, step) in steps.iter().enumerate() {
+            println!("{:>4}. {}", index + 1, step);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(Command::Codegen {
+        schema,
+        lang,
+        output,
+    }) = &command
+    {
+        let code = generate_schema_code(&node_config, schema, *lang)
+            .await
+            .context("Could not generate code")?;
+
+        match output {
+            Some(output) => {
+                std::fs::write(output, code)
+                    .with_context(|| format!("Could not write code to '{}'", output.display()))?;
+                println!(
+                    "Generated {} code for schema {} in '{}'",
+                    lang,
+                    schema,
+                    output.display()
+                );
+            }
+            None => print!("{}", code),
+        }
+
+        return Ok(());
+    }
+
+    if let Some(Command::Migrate { dry_run, revert }) = command {
+        let action = match revert {
+            Some(count) => MigrateAction::Revert(count),
+            None => MigrateAction::Apply,
+        };
+
+        let steps = migrate_database(&node_config, action, dry_run)
+            .await
+            .context("Could not migrate database")?;
+
+        let verb = match (action, dry_run) {
+            (MigrateAction::Apply, true) => "Pending",
+            (MigrateAction::Apply, false) => "Applied",
+            (MigrateAction::Revert(_), true) => "To be reverted",
+            (MigrateAction::Revert(_), false) => "Reverted",
+        };
+        println!("{} migrations: {}", verb, steps.len());
+        for step in steps {
+            println!("  {}", step);
+        }
+
+        return Ok(());
+    }
+
+    if let Some(Command::Bench {
+        database,
+        documents,
+        updates,
+    }) = command
+    {
+        let options = BenchOptions {
+            documents,
+            updates,
+            ..BenchOptions::default()
+        };
+
+        let results = run_benchmarks(database.as_deref(), options)
+            .await
+            .context("Could not run benchmarks")?;
+
+        for result in results {
+            println!("{}", result);
+        }
+
+        return Ok(());
+    }
+
     // Generate a new key pair, either just for this session or persisted. Folders are
     // automatically created when we picked a path
     let (key_pair_path, key_pair) = match &config.private_key {
@@ -49,6 +149,35 @@ async fn main() -> anyhow::Result<()> {
         None => (None, generate_ephemeral_key_pair()),
     };
 
+    if let Some(Command::Export { document, output }) = command {
+        if key_pair_path.is_none() {
+            warn!("No private key configured, the bundle is signed with a temporary key");
+        }
+
+        let bundle = export_document_bundle(&node_config, &key_pair, &document)
+            .await
+            .context("Could not export document")?;
+        std::fs::write(&output, bundle.to_bytes())
+            .with_context(|| format!("Could not write bundle to '{}'", output.display()))?;
+
+        println!(
+            "Exported {} entries of document {} to '{}'",
+            bundle.commits.len(),
+            document,
+            output.display()
+        );
+
+        return Ok(());
+    }
+
+    // Report the state of the node to service managers supervising it, like systemd
+    let mut supervisor = match command {
+        Some(Command::Service) => {
+            Supervisor::windows_service().context("Could not run as Windows service")?
+        }
+        _ => Supervisor::from_env(),
+    };
+
     // Show configuration info to the user
     println!(
         "{}",
@@ -56,17 +185,49 @@ async fn main() -> anyhow::Result<()> {
     );
     show_warnings(&node_config, is_temporary_blobs_path);
 
+    // Read data to import before starting the node to fail early
+    let import_commits = match command {
+        Some(Command::Import { path }) => {
+            Some(read_commits(&path).context("Could not read data to import")?)
+        }
+        _ => None,
+    };
+
     // Start p2panda node in async runtime
     let node = Node::start(key_pair, node_config).await;
 
-    // Run this until [CTRL] + [C] got pressed or something went wrong
+    // Import data, it gets materialized while the node is running
+    if let Some(commits) = import_commits {
+        let report = node
+            .import(commits)
+            .await
+            .context("Could not import data")?;
+
+        for (entry_hash, reason) in &report.failed {
+            warn!("Could not import entry {}: {}", entry_hash, reason);
+        }
+
+        println!(
+            "Imported {} entries, {} existed already and {} failed",
+            report.imported.len(),
+            report.existing,
+            report.failed.len()
+        );
+    }
+
+    supervisor.ready();
+
+    // Run this until [CTRL] + [C] got pressed, the service manager stopped us or something went
+    // wrong
     tokio::select! {
-        _ = tokio::signal::ctrl_c() => (),
+        _ = supervisor.stop_signal() => (),
         _ = node.on_exit() => (),
     }
 
     // Wait until all tasks are gracefully shut down and exit
+    supervisor.stopping();
     node.shutdown().await;
+    supervisor.stopped();
 
     Ok(())
 }
@@ -82,7 +243,10 @@ fn show_warnings(config: &Configuration, is_temporary_blobs_path: bool) {
 
     match &config.allow_schema_ids {
         AllowList::Set(values) => {
-            if values.is_empty() && !config.network.relay_mode {
+            if values.is_empty()
+                && config.allow_schema_pins.is_empty()
+                && !config.network.relay_mode
+            {
                 warn!(
                     "Your node was set to not allow any schema ids which is only useful in
                     combination with enabling relay mode. With this setting you will not be able to
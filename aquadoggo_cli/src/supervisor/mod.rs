@@ -0,0 +1,160 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Integration with service managers supervising the node, like systemd or the Windows service
+//! control manager.
+#[cfg(target_os = "linux")]
+mod systemd;
+#[cfg(windows)]
+mod windows;
+
+use anyhow::Result;
+#[cfg(unix)]
+use log::warn;
+
+#[cfg(target_os = "linux")]
+use crate::supervisor::systemd::SystemdNotifier;
+#[cfg(windows)]
+use crate::supervisor::windows::WindowsService;
+
+/// Informs service managers about the state of the node and listens to their stop signals.
+///
+/// On Linux systemd is notified when the node was started by a service with `Type=notify`, the
+/// watchdog is pinged when `WatchdogSec` is set. On Windows the service control manager is
+/// informed when the node runs as a Windows service. Without any service manager this only
+/// listens to termination signals.
+pub struct Supervisor {
+    #[cfg(target_os = "linux")]
+    systemd: Option<SystemdNotifier>,
+
+    #[cfg(windows)]
+    windows: Option<WindowsService>,
+}
+
+impl Supervisor {
+    /// Returns a supervisor notifying systemd if the node was started by it.
+    pub fn from_env() -> Self {
+        Self {
+            #[cfg(target_os = "linux")]
+            systemd: SystemdNotifier::from_env(),
+
+            #[cfg(windows)]
+            windows: None,
+        }
+    }
+
+    /// Returns a supervisor reporting to the Windows service control manager.
+    ///
+    /// Fails when the program was not started by the service control manager or when not running
+    /// on Windows.
+    #[cfg(windows)]
+    pub fn windows_service() -> Result<Self> {
+        Ok(Self {
+            windows: Some(WindowsService::start()?),
+        })
+    }
+
+    /// Returns a supervisor reporting to the Windows service control manager.
+    ///
+    /// Fails when the program was not started by the service control manager or when not running
+    /// on Windows.
+    #[cfg(not(windows))]
+    pub fn windows_service() -> Result<Self> {
+        anyhow::bail!("Running as a Windows service is only supported on Windows")
+    }
+
+    /// Inform the service manager that the node started and is ready to handle requests.
+    pub fn ready(&self) {
+        #[cfg(target_os = "linux")]
+        if let Some(systemd) = &self.systemd {
+            if let Err(err) = systemd.notify("READY=1") {
+                warn!("Could not notify systemd about readiness: {}", err);
+            }
+
+            // Keep pinging the watchdog as long as the node is alive
+            if let Some(interval) = SystemdNotifier::watchdog_interval() {
+                let systemd = systemd.clone();
+                tokio::task::spawn(async move {
+                    let mut interval = tokio::time::interval(interval);
+                    loop {
+                        interval.tick().await;
+                        if let Err(err) = systemd.notify("WATCHDOG=1") {
+                            warn!("Could not ping systemd watchdog: {}", err);
+                        }
+                    }
+                });
+            }
+        }
+
+        #[cfg(windows)]
+        if let Some(windows) = &self.windows {
+            windows.running();
+        }
+    }
+
+    /// Inform the service manager that the node is shutting down.
+    pub fn stopping(&self) {
+        #[cfg(target_os = "linux")]
+        if let Some(systemd) = &self.systemd {
+            if let Err(err) = systemd.notify("STOPPING=1") {
+                warn!("Could not notify systemd about stopping: {}", err);
+            }
+        }
+
+        #[cfg(windows)]
+        if let Some(windows) = &self.windows {
+            windows.stopping();
+        }
+    }
+
+    /// Inform the service manager that the node shut down, the process exits right after.
+    pub fn stopped(self) {
+        #[cfg(windows)]
+        if let Some(windows) = self.windows {
+            windows.stopped();
+        }
+    }
+
+    /// Waits until the node was asked to stop, either by pressing [CTRL] + [C], by a termination
+    /// signal or by the service manager.
+    pub async fn stop_signal(&mut self) {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => (),
+            _ = terminate_signal() => (),
+            _ = self.service_stop_signal() => (),
+        }
+    }
+
+    #[cfg(windows)]
+    async fn service_stop_signal(&mut self) {
+        match &mut self.windows {
+            Some(windows) => windows.stop_requested().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    #[cfg(not(windows))]
+    async fn service_stop_signal(&mut self) {
+        std::future::pending().await
+    }
+}
+
+/// Waits for a SIGTERM signal, this is how most service managers ask processes to stop.
+#[cfg(unix)]
+async fn terminate_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut signal) => {
+            signal.recv().await;
+        }
+        Err(err) => {
+            warn!("Could not listen to termination signals: {}", err);
+            std::future::pending().await
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate_signal() {
+    std::future::pending().await
+}
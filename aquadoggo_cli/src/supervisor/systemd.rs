@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::env;
+use std::io;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::time::Duration;
+
+/// Environment variable systemd passes the path of its notification socket in.
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+
+/// Environment variable systemd passes the watchdog timeout in microseconds in.
+const WATCHDOG_USEC_ENV: &str = "WATCHDOG_USEC";
+
+/// Environment variable systemd passes the process id the watchdog is meant for in.
+const WATCHDOG_PID_ENV: &str = "WATCHDOG_PID";
+
+/// Sends state changes to systemd, following the `sd_notify` protocol.
+#[derive(Clone, Debug)]
+pub struct SystemdNotifier {
+    socket_path: String,
+}
+
+impl SystemdNotifier {
+    /// Returns a notifier when systemd expects notifications from this process.
+    pub fn from_env() -> Option<Self> {
+        env::var(NOTIFY_SOCKET_ENV)
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(|socket_path| Self { socket_path })
+    }
+
+    /// Returns the interval the watchdog needs to be pinged in or `None` if it is not enabled.
+    ///
+    /// We ping twice per watchdog timeout to not miss it.
+    pub fn watchdog_interval() -> Option<Duration> {
+        // The watchdog might be meant for another process
+        if let Ok(pid) = env::var(WATCHDOG_PID_ENV) {
+            if pid.parse::<u32>().ok() != Some(std::process::id()) {
+                return None;
+            }
+        }
+
+        let timeout = env::var(WATCHDOG_USEC_ENV).ok()?.parse::<u64>().ok()?;
+        if timeout == 0 {
+            return None;
+        }
+
+        Some(Duration::from_micros(timeout) / 2)
+    }
+
+    /// Sends a state, for example "READY=1", to systemd.
+    pub fn notify(&self, state: &str) -> io::Result<()> {
+        let socket = UnixDatagram::unbound()?;
+
+        // Paths starting with "@" refer to sockets in the abstract namespace
+        match self.socket_path.strip_prefix('@') {
+            Some(name) => {
+                let address = SocketAddr::from_abstract_name(name)?;
+                socket.send_to_addr(state.as_bytes(), &address)?;
+            }
+            None => {
+                socket.send_to(state.as_bytes(), &self.socket_path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixDatagram;
+
+    use tempfile::TempDir;
+
+    use super::SystemdNotifier;
+
+    #[test]
+    fn sends_notifications() {
+        let dir = TempDir::new().unwrap();
+        let socket_path = dir.path().join("notify.sock");
+        let socket = UnixDatagram::bind(&socket_path).unwrap();
+
+        let notifier = SystemdNotifier {
+            socket_path: socket_path.to_str().unwrap().to_string(),
+        };
+        notifier.notify("READY=1").unwrap();
+
+        let mut buffer = [0; 16];
+        let size = socket.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..size], b"READY=1");
+    }
+}
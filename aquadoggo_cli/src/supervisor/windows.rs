@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::ffi::OsString;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use log::{error, warn};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{
+    self, ServiceControlHandlerResult, ServiceStatusHandle,
+};
+use windows_service::{define_windows_service, service_dispatcher};
+
+/// Name of the Windows service, needs to match the name it was registered with.
+const SERVICE_NAME: &str = "aquadoggo";
+
+/// Time the service control manager gives us to connect to it.
+const DISPATCHER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time we expect pending state changes to take at most, for example shutting down the node.
+const WAIT_HINT: Duration = Duration::from_secs(30);
+
+/// Handed over from the service thread once the service got registered.
+struct Registration {
+    status_handle: ServiceStatusHandle,
+    stop_rx: UnboundedReceiver<()>,
+    stopped_tx: mpsc::Sender<()>,
+}
+
+/// Channel to hand over the registration from the service thread.
+static REGISTRATION_TX: Mutex<Option<mpsc::Sender<Result<Registration>>>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Entry point called by the service dispatcher in its own thread.
+///
+/// The node itself keeps running in the async runtime of the main thread, here we only register
+/// the control handler and block until the node shut down.
+fn service_main(_arguments: Vec<OsString>) {
+    let registration_tx = match REGISTRATION_TX.lock().unwrap().take() {
+        Some(registration_tx) => registration_tx,
+        None => return,
+    };
+
+    let (stop_tx, stop_rx) = unbounded_channel();
+    let (stopped_tx, stopped_rx) = mpsc::channel();
+
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                let _ = stop_tx.send(());
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        });
+
+    let registration = status_handle
+        .map(|status_handle| Registration {
+            status_handle,
+            stop_rx,
+            stopped_tx,
+        })
+        .context("Could not register service control handler");
+    let is_registered = registration.is_ok();
+
+    if registration_tx.send(registration).is_err() || !is_registered {
+        return;
+    }
+
+    // Keep the service alive until the node shut down
+    let _ = stopped_rx.recv();
+}
+
+/// Node running as a Windows service, reporting its state to the service control manager.
+pub struct WindowsService {
+    status_handle: ServiceStatusHandle,
+    stop_rx: UnboundedReceiver<()>,
+    stopped_tx: mpsc::Sender<()>,
+}
+
+impl WindowsService {
+    /// Connects to the service control manager and reports that the node is starting.
+    pub fn start() -> Result<Self> {
+        let (registration_tx, registration_rx) = mpsc::channel();
+        REGISTRATION_TX.lock().unwrap().replace(registration_tx);
+
+        // The dispatcher blocks until the service stopped, the node runs in the main thread
+        thread::spawn(|| {
+            if let Err(err) = service_dispatcher::start(SERVICE_NAME, ffi_service_main) {
+                error!("Could not start Windows service dispatcher: {}", err);
+            }
+        });
+
+        let registration = registration_rx
+            .recv_timeout(DISPATCHER_TIMEOUT)
+            .map_err(|_| anyhow!("Not started by the Windows service control manager"))??;
+
+        let service = Self {
+            status_handle: registration.status_handle,
+            stop_rx: registration.stop_rx,
+            stopped_tx: registration.stopped_tx,
+        };
+        service.set_state(ServiceState::StartPending);
+
+        Ok(service)
+    }
+
+    fn set_state(&self, state: ServiceState) {
+        let (controls_accepted, wait_hint) = match state {
+            ServiceState::Running => (
+                ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+                Duration::default(),
+            ),
+            ServiceState::Stopped => (ServiceControlAccept::empty(), Duration::default()),
+            _ => (ServiceControlAccept::empty(), WAIT_HINT),
+        };
+
+        let status = ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint,
+            process_id: None,
+        };
+
+        if let Err(err) = self.status_handle.set_service_status(status) {
+            warn!("Could not report state to service control manager: {}", err);
+        }
+    }
+
+    /// Report that the node is running.
+    pub fn running(&self) {
+        self.set_state(ServiceState::Running);
+    }
+
+    /// Report that the node is shutting down.
+    pub fn stopping(&self) {
+        self.set_state(ServiceState::StopPending);
+    }
+
+    /// Report that the node shut down and end the service.
+    pub fn stopped(self) {
+        self.set_state(ServiceState::Stopped);
+        let _ = self.stopped_tx.send(());
+    }
+
+    /// Waits until the service control manager asks the node to stop.
+    pub async fn stop_requested(&mut self) {
+        if self.stop_rx.recv().await.is_none() {
+            std::future::pending().await
+        }
+    }
+}